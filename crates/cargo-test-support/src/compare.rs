@@ -109,6 +109,7 @@ fn substitute_macros(input: &str) -> String {
         ("[RUNNING]", "     Running"),
         ("[COMPILING]", "   Compiling"),
         ("[CHECKING]", "    Checking"),
+        ("[CHECKED]", "     Checked"),
         ("[COMPLETED]", "   Completed"),
         ("[CREATED]", "     Created"),
         ("[FINISHED]", "    Finished"),
@@ -134,6 +135,11 @@ fn substitute_macros(input: &str) -> String {
         ("[SUMMARY]", "     Summary"),
         ("[FIXED]", "       Fixed"),
         ("[FIXING]", "      Fixing"),
+        ("[WOULD_FIX]", "   Would fix"),
+        ("[READINESS]", "   Readiness"),
+        ("[BACKED_UP]", "   Backed up"),
+        ("[RESTORED]", "    Restored"),
+        ("[SAVED]", "       Saved"),
         ("[EXE]", env::consts::EXE_SUFFIX),
         ("[IGNORED]", "     Ignored"),
         ("[INSTALLED]", "   Installed"),
@@ -143,7 +149,24 @@ fn substitute_macros(input: &str) -> String {
         ("[LOGOUT]", "      Logout"),
         ("[YANK]", "        Yank"),
         ("[OWNER]", "       Owner"),
+        ("[OVERRIDING]", "  Overriding"),
         ("[MIGRATING]", "   Migrating"),
+        ("[ENABLING]", "    Enabling"),
+        ("[COVERAGE]", "    Coverage"),
+        ("[TESTING]", "     Testing"),
+        ("[SKIPPING]", "    Skipping"),
+        ("[CACHED]", "      Cached"),
+        ("[RETRYING]", "    Retrying"),
+        ("[RETRIED]", "     Retried"),
+        ("[RELEASING]", "   Releasing"),
+        ("[COMMITTING]", "  Committing"),
+        ("[TAGGING]", "     Tagging"),
+        ("[RELEASED]", "    Released"),
+        ("[BUMPING]", "     Bumping"),
+        ("[BUNDLING]", "    Bundling"),
+        ("[ASSEMBLING]", "  Assembling"),
+        ("[VALIDATED]", "   Validated"),
+        ("[MERGING]", "     Merging"),
     ];
     let mut result = input.to_owned();
     for &(pat, subst) in &macros {
@@ -622,6 +622,9 @@ impl Package {
             json["features2"] = serde_json::json!(f2);
             json["v"] = serde_json::json!(2);
         }
+        if let Some(rust_version) = &self.rust_version {
+            json["rust_version"] = serde_json::json!(rust_version);
+        }
         if let Some(v) = self.v {
             json["v"] = serde_json::json!(v);
         }
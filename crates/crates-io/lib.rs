@@ -34,6 +34,16 @@ pub struct Crate {
     pub name: String,
     pub description: Option<String>,
     pub max_version: String,
+    pub downloads: Option<u64>,
+    pub recent_downloads: Option<u64>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+    pub homepage: Option<String>,
+    pub documentation: Option<String>,
+    pub repository: Option<String>,
+    /// The license of the latest version, if the registry returns it.
+    #[serde(default)]
+    pub license: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -56,6 +66,8 @@ pub struct NewCrate {
     pub badges: BTreeMap<String, BTreeMap<String, String>>,
     pub links: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub rust_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub v: Option<u32>,
 }
 
@@ -335,12 +347,24 @@ impl Registry {
     }
 
     pub fn search(&mut self, query: &str, limit: u32) -> Result<(Vec<Crate>, u32)> {
+        self.search_with(query, limit, &[])
+    }
+
+    /// Searches like `search`, with the additional query parameters
+    /// `params`, like `("category", "parsing")` or `("sort", "downloads")`.
+    pub fn search_with(
+        &mut self,
+        query: &str,
+        limit: u32,
+        params: &[(&str, &str)],
+    ) -> Result<(Vec<Crate>, u32)> {
         let formatted_query = percent_encode(query.as_bytes(), NON_ALPHANUMERIC);
-        let body = self.req(
-            &format!("/crates?q={}&per_page={}", formatted_query, limit),
-            None,
-            Auth::Unauthorized,
-        )?;
+        let mut path = format!("/crates?q={}&per_page={}", formatted_query, limit);
+        for (key, value) in params {
+            let value = percent_encode(value.as_bytes(), NON_ALPHANUMERIC);
+            path.push_str(&format!("&{}={}", key, value));
+        }
+        let body = self.req(&path, None, Auth::Unauthorized)?;
 
         let crates = serde_json::from_str::<Crates>(&body)?;
         Ok((crates.crates, crates.meta.total))
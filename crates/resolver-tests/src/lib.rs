@@ -10,7 +10,7 @@ use std::rc::Rc;
 use std::time::Instant;
 
 use cargo::core::dependency::DepKind;
-use cargo::core::resolver::{self, ResolveOpts, VersionPreferences};
+use cargo::core::resolver::{self, MinimalVersions, ResolveOpts, VersionPreferences};
 use cargo::core::source::{GitReference, SourceId};
 use cargo::core::Resolve;
 use cargo::core::{Dependency, PackageId, Registry, Summary};
@@ -178,14 +178,21 @@ pub fn resolve_with_config_raw(
     )
     .unwrap();
     let opts = ResolveOpts::everything();
+    let minimal_versions = MinimalVersions {
+        all: config.cli_unstable().minimal_versions,
+        ..MinimalVersions::default()
+    };
     let start = Instant::now();
     let resolve = resolver::resolve(
         &[(summary, opts)],
         &[],
         &mut registry,
         &VersionPreferences::default(),
+        &minimal_versions,
         Some(config),
         true,
+        &BTreeMap::new(),
+        None,
     );
 
     // The largest test in our suite takes less then 30 sec.
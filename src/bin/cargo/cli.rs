@@ -102,7 +102,12 @@ Run with 'cargo -Z [FLAG] [SUBCOMMAND]'",
     }
 
     let is_verbose = expanded_args.occurrences_of("verbose") > 0;
-    if expanded_args.is_present("version") {
+    // `is_present` is also true for the `version` subcommand, which is only
+    // executed as such for its own subcommands, like `cargo version bump`.
+    let has_version_subcommand = expanded_args
+        .subcommand_matches("version")
+        .map_or(false, |args| args.subcommand_name().is_some());
+    if expanded_args.is_present("version") && !has_version_subcommand {
         let version = get_version_string(is_verbose);
         drop_print!(config, "{}", version);
         return Ok(());
@@ -342,11 +347,40 @@ fn execute_subcommand(
     subcommand_args: &ArgMatches<'_>,
 ) -> CliResult {
     if let Some(exec) = commands::builtin_exec(cmd) {
-        return exec(config, subcommand_args);
+        exec(config, subcommand_args)?;
+        cargo::ops::auto_gc(config);
+        return Ok(());
     }
 
     let mut ext_args: Vec<&str> = vec![cmd];
     ext_args.extend(subcommand_args.values_of("").unwrap_or_default());
+    // `cargo add`, `cargo asm`, `cargo expand`, `cargo patch` and `cargo
+    // release` are only built in with their `-Z` flag, they are otherwise
+    // left to the `cargo-add`, `cargo-asm`, `cargo-expand`, `cargo-patch` and
+    // `cargo-release` subcommands that many already have installed.
+    match cmd {
+        "add" if config.cli_unstable().add => {
+            let args = commands::add::cli().get_matches_from_safe(ext_args)?;
+            return commands::add::exec(config, &args);
+        }
+        "asm" if config.cli_unstable().asm => {
+            let args = commands::asm::cli().get_matches_from_safe(ext_args)?;
+            return commands::asm::exec(config, &args);
+        }
+        "expand" if config.cli_unstable().expand => {
+            let args = commands::expand::cli().get_matches_from_safe(ext_args)?;
+            return commands::expand::exec(config, &args);
+        }
+        "patch" if config.cli_unstable().patch => {
+            let args = commands::patch::cli().get_matches_from_safe(ext_args)?;
+            return commands::patch::exec(config, &args);
+        }
+        "release" if config.cli_unstable().release => {
+            let args = commands::release::cli().get_matches_from_safe(ext_args)?;
+            return commands::release::exec(config, &args);
+        }
+        _ => {}
+    }
     super::execute_external_subcommand(config, cmd, &ext_args)
 }
 
@@ -383,7 +417,7 @@ impl GlobalArgs {
     }
 }
 
-fn cli() -> App {
+pub fn cli() -> App {
     let is_rustup = std::env::var_os("RUSTUP_HOME").is_some();
     let usage = if is_rustup {
         "cargo [+toolchain] [OPTIONS] [SUBCOMMAND]"
@@ -446,7 +480,23 @@ See 'cargo help <command>' for more information on a specific command.\n",
         )
         .arg(opt("frozen", "Require Cargo.lock and cache are up to date").global(true))
         .arg(opt("locked", "Require Cargo.lock is up to date").global(true))
+        .arg(
+            opt(
+                "lockfile",
+                "Use another lock file of the workspace, like `Cargo.minimal.lock` (unstable)",
+            )
+            .value_name("NAME")
+            .global(true),
+        )
         .arg(opt("offline", "Run without accessing the network").global(true))
+        .arg(
+            multi_opt(
+                "override",
+                "NAME=PATH",
+                "Replace a dependency by a local package for this invocation only (unstable)",
+            )
+            .global(true),
+        )
         .arg(
             multi_opt(
                 "config",
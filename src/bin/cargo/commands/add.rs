@@ -0,0 +1,71 @@
+use crate::command_prelude::*;
+
+use cargo::core::dependency::DepKind;
+use cargo::ops::{self, AddOptions};
+
+pub fn cli() -> App {
+    subcommand("add")
+        .about("Add dependencies to a Cargo.toml manifest file")
+        .arg(
+            Arg::with_name("crate")
+                .empty_values(false)
+                .multiple(true)
+                .required(true)
+                .help("The dependencies to add, like `serde` or `serde@1.0`"),
+        )
+        .arg(opt("quiet", "No output printed to stdout").short("q"))
+        .arg(opt("path", "Filesystem path to the local crate to add").value_name("PATH"))
+        .arg(opt("registry", "Registry to use").value_name("REGISTRY"))
+        .arg(opt("dev", "Add as a development dependency").conflicts_with("build"))
+        .arg(opt("build", "Add as a build dependency"))
+        .arg(multi_opt(
+            "features",
+            "FEATURES",
+            "Space or comma separated list of features of the dependency to activate",
+        ))
+        .arg(opt("optional", "Mark the dependency as optional"))
+        .arg(opt(
+            "no-default-features",
+            "Disable the default features of the dependency",
+        ))
+        .arg(opt(
+            "analyze",
+            "Print the packages, duplicates, licenses and build time the dependencies bring in",
+        ))
+        .arg_dry_run("Don't actually write the manifest")
+        .arg_package("Package to add the dependencies to")
+        .arg_manifest_path()
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    let ws = args.workspace(config)?;
+    let kind = if args.is_present("dev") {
+        DepKind::Development
+    } else if args.is_present("build") {
+        DepKind::Build
+    } else {
+        DepKind::Normal
+    };
+    let features = values(args, "features")
+        .iter()
+        .flat_map(|s| s.split_whitespace())
+        .flat_map(|s| s.split(','))
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .collect();
+    let opts = AddOptions {
+        config,
+        package: args.value_of("package").map(|s| s.to_string()),
+        crates: values(args, "crate"),
+        path: args.value_of_path("path", config),
+        registry: args.registry(config)?,
+        kind,
+        features,
+        optional: args.is_present("optional"),
+        no_default_features: args.is_present("no-default-features"),
+        analyze: args.is_present("analyze"),
+        dry_run: args.is_present("dry-run"),
+    };
+    ops::add(&ws, &opts)?;
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use crate::command_prelude::*;
+
+use cargo::ops::{self, AsmEmit, AsmOptions};
+
+pub fn cli() -> App {
+    subcommand("asm")
+        .about("Print the assembly, LLVM-IR or MIR of a target of a package")
+        .arg(Arg::with_name("function").help(
+            "Only print this function, by its name or the end of its path, like `add` or `math::add`",
+        ))
+        .arg(opt("llvm-ir", "Print the LLVM-IR instead of the assembly").conflicts_with("mir"))
+        .arg(opt("mir", "Print the MIR instead of the assembly"))
+        .arg_package("Package to inspect")
+        .arg_jobs()
+        .arg_targets_all(
+            "Inspect only this package's library",
+            "Inspect only the specified binary",
+            "Inspect all binaries",
+            "Inspect only the specified example",
+            "Inspect all examples",
+            "Inspect only the specified test target",
+            "Inspect all tests",
+            "Inspect only the specified bench target",
+            "Inspect all benches",
+            "Inspect all targets",
+        )
+        .arg_release("Inspect artifacts in release mode, with optimizations")
+        .arg_profile("Inspect artifacts with the specified profile")
+        .arg_features()
+        .arg_target_triple("Inspect for the target triple")
+        .arg_target_dir()
+        .arg_manifest_path()
+        .arg_ignore_rust_version()
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    let ws = args.workspace(config)?;
+    let mut compile_opts = args.compile_options_for_single_package(
+        config,
+        CompileMode::Build,
+        Some(&ws),
+        ProfileChecking::Custom,
+    )?;
+    let emit = if args.is_present("llvm-ir") {
+        AsmEmit::LlvmIr
+    } else if args.is_present("mir") {
+        AsmEmit::Mir
+    } else {
+        AsmEmit::Asm
+    };
+    let opts = AsmOptions {
+        emit,
+        function: args.value_of("function").map(|s| s.to_string()),
+    };
+    ops::asm(&ws, &mut compile_opts, &opts)?;
+    Ok(())
+}
@@ -0,0 +1,102 @@
+use crate::command_prelude::*;
+use cargo::ops::{self, CertifyOptions};
+
+pub fn cli() -> App {
+    subcommand("audits")
+        .about("Manage the audits of the dependencies in `supply-chain/audits.toml`")
+        .setting(clap::AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            subcommand("init")
+                .about("Exempt every dependency which isn't audited yet")
+                .arg(opt("quiet", "No output printed to stdout").short("q"))
+                .arg_manifest_path(),
+        )
+        .subcommand(
+            subcommand("certify")
+                .about("Record the audit of a version of a crate")
+                .arg(Arg::with_name("crate").required(true))
+                .arg(Arg::with_name("version").required(true))
+                .arg(
+                    opt(
+                        "delta",
+                        "Only the changes since the audited version FROM were reviewed",
+                    )
+                    .value_name("FROM"),
+                )
+                .arg(opt("who", "Who reviewed the crate").value_name("WHO"))
+                .arg(opt("notes", "Notes about the review").value_name("NOTES"))
+                .arg(opt("quiet", "No output printed to stdout").short("q"))
+                .arg_manifest_path(),
+        )
+        .subcommand(
+            subcommand("exempt")
+                .about("Record that a version of a crate doesn't need an audit")
+                .arg(Arg::with_name("crate").required(true))
+                .arg(Arg::with_name("version").required(true))
+                .arg(opt("reason", "Why the version is exempted").value_name("REASON"))
+                .arg(opt("quiet", "No output printed to stdout").short("q"))
+                .arg_manifest_path(),
+        )
+        .subcommand(
+            subcommand("import")
+                .about("Import the audits of another team")
+                .arg(Arg::with_name("name").required(true))
+                .arg(Arg::with_name("url").required(true))
+                .arg(opt("quiet", "No output printed to stdout").short("q"))
+                .arg_manifest_path(),
+        )
+        .subcommand(
+            subcommand("fetch")
+                .about("Fetch the imported audits into `supply-chain/imports.lock`")
+                .arg(opt("quiet", "No output printed to stdout").short("q"))
+                .arg_manifest_path(),
+        )
+        .subcommand(
+            subcommand("check")
+                .about("Print whether each dependency is audited")
+                .arg(opt("quiet", "No output printed to stdout").short("q"))
+                .arg_manifest_path(),
+        )
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    if !config.cli_unstable().audits {
+        return Err(anyhow::format_err!(
+            "the `cargo audits` command is unstable, pass `-Z audits` to enable it"
+        )
+        .into());
+    }
+    let (cmd, args) = match args.subcommand() {
+        (cmd, Some(args)) => (cmd, args),
+        (cmd, None) => panic!("unexpected command `{}`", cmd),
+    };
+    let ws = args.workspace(config)?;
+    match cmd {
+        "init" => ops::audits_init(&ws)?,
+        "certify" => ops::audits_certify(
+            &ws,
+            &CertifyOptions {
+                name: args.value_of("crate").unwrap().to_string(),
+                version: args.value_of("version").unwrap().to_string(),
+                delta: args.value_of("delta").map(String::from),
+                who: args.value_of("who").map(String::from),
+                notes: args.value_of("notes").map(String::from),
+            },
+        )?,
+        "exempt" => ops::audits_exempt(
+            &ws,
+            args.value_of("crate").unwrap(),
+            args.value_of("version").unwrap(),
+            args.value_of("reason").map(String::from),
+        )?,
+        "import" => ops::audits_import(
+            &ws,
+            args.value_of("name").unwrap(),
+            args.value_of("url").unwrap(),
+        )?,
+        "fetch" => ops::audits_fetch(&ws)?,
+        "check" => ops::audits_check(&ws)?,
+        cmd => panic!("unexpected command `{}`", cmd),
+    }
+    Ok(())
+}
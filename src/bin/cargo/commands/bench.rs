@@ -37,11 +37,14 @@ pub fn cli() -> App {
         .arg_jobs()
         .arg_profile("Build artifacts with the specified profile")
         .arg_features()
+        .arg_auto_features()
         .arg_target_triple("Build for the target triple")
+        .arg_auto_install_targets()
         .arg_target_dir()
         .arg_manifest_path()
         .arg_ignore_rust_version()
         .arg_message_format()
+        .arg_warnings()
         .arg(opt(
             "no-fail-fast",
             "Run all benchmarks regardless of failure",
@@ -65,6 +68,9 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
     let ops = TestOptions {
         no_run: args.is_present("no-run"),
         no_fail_fast: args.is_present("no-fail-fast"),
+        affected: false,
+        cache_results: false,
+        test_logs: false,
         compile_opts,
     };
 
@@ -1,5 +1,6 @@
 use crate::command_prelude::*;
 
+use cargo::core::compiler::universal;
 use cargo::ops;
 
 pub fn cli() -> App {
@@ -29,7 +30,9 @@ pub fn cli() -> App {
         .arg_release("Build artifacts in release mode, with optimizations")
         .arg_profile("Build artifacts with the specified profile")
         .arg_features()
+        .arg_auto_features()
         .arg_target_triple("Build for the target triple")
+        .arg_auto_install_targets()
         .arg_target_dir()
         .arg(
             opt(
@@ -38,9 +41,19 @@ pub fn cli() -> App {
             )
             .value_name("PATH"),
         )
+        .arg(opt(
+            "emit-pc",
+            "Write pkg-config files for cdylib and staticlib libraries (unstable)",
+        ))
+        .arg(opt(
+            "lipo",
+            "Merge the artifacts of all targets into universal binaries (unstable)",
+        ))
         .arg_manifest_path()
+        .arg_minimal_versions()
         .arg_ignore_rust_version()
         .arg_message_format()
+        .arg_warnings()
         .arg_build_plan()
         .arg_unit_graph()
         .arg_future_incompat_report()
@@ -48,7 +61,7 @@ pub fn cli() -> App {
 }
 
 pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
-    let ws = args.workspace(config)?;
+    let ws = args.compile_workspace(config)?;
     let mut compile_opts = args.compile_options(
         config,
         CompileMode::Build,
@@ -67,6 +80,25 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
             .cli_unstable()
             .fail_if_stable_opt("--out-dir", 6790)?;
     }
+    if args.is_present("emit-pc") {
+        if !config.cli_unstable().pkg_config {
+            return Err(CliError::new(
+                anyhow::format_err!("Usage of `--emit-pc` requires `-Z pkg-config`"),
+                101,
+            ));
+        }
+        compile_opts.build_config.emit_pc = true;
+    }
+    if args.is_present("lipo") {
+        if !config.cli_unstable().universal_binaries {
+            return Err(CliError::new(
+                anyhow::format_err!("Usage of `--lipo` requires `-Z universal-binaries`"),
+                101,
+            ));
+        }
+        compile_opts.build_config.lipo = true;
+        universal::validate(&compile_opts.build_config)?;
+    }
     ops::compile(&ws, &compile_opts)?;
     Ok(())
 }
@@ -0,0 +1,96 @@
+use crate::command_prelude::*;
+use cargo::ops::{self, CachePruneOptions};
+
+pub fn cli() -> App {
+    subcommand("cache")
+        .about("Inspect and clean up the caches of the Cargo home")
+        .setting(clap::AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            subcommand("info")
+                .about("Print the size of the caches by registry and git repository")
+                .arg(opt("json", "Print the sizes as JSON")),
+        )
+        .subcommand(
+            subcommand("prune")
+                .about("Remove the downloaded crates and git checkouts which are old or unused")
+                .arg(
+                    opt(
+                        "max-age",
+                        "Remove what was downloaded longer ago than DURATION, like `30d`",
+                    )
+                    .value_name("DURATION"),
+                )
+                .arg(
+                    opt(
+                        "unused-for",
+                        "Remove what wasn't used by a build for longer than DURATION",
+                    )
+                    .value_name("DURATION"),
+                )
+                .arg(
+                    opt(
+                        "max-size",
+                        "Remove the least recently used entries until the rest fits in SIZE, like `5GB`",
+                    )
+                    .value_name("SIZE"),
+                )
+                .arg(opt(
+                    "dry-run",
+                    "Print what would be removed without removing it",
+                ))
+                .arg(opt("json", "Print what was removed as JSON")),
+        )
+        .subcommand(
+            subcommand("gc")
+                .about("Run the cleanup configured in `[gc.auto]` now")
+                .arg(opt("dry-run", "Print what would be removed without removing it"))
+                .arg(opt("json", "Print what was removed as JSON")),
+        )
+        .subcommand(
+            subcommand("verify")
+                .about("Check the cached `.crate` files against the checksums of `Cargo.lock`")
+                .arg(opt("json", "Print the result of each crate as JSON"))
+                .arg_manifest_path(),
+        )
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    if !config.cli_unstable().cache {
+        return Err(anyhow::format_err!(
+            "the `cargo cache` command is unstable, pass `-Z cache` to enable it"
+        )
+        .into());
+    }
+    let (cmd, args) = match args.subcommand() {
+        (cmd, Some(args)) => (cmd, args),
+        (cmd, None) => panic!("unexpected command `{}`", cmd),
+    };
+    let json = args.is_present("json");
+    match cmd {
+        "info" => ops::cache_info(config, json)?,
+        "prune" => {
+            let duration = |name| {
+                args.value_of(name)
+                    .map(ops::parse_cache_duration)
+                    .transpose()
+            };
+            ops::cache_prune(
+                config,
+                &CachePruneOptions {
+                    max_age: duration("max-age")?,
+                    unused_for: duration("unused-for")?,
+                    max_size: args
+                        .value_of("max-size")
+                        .map(ops::parse_cache_size)
+                        .transpose()?,
+                    dry_run: args.is_present("dry-run"),
+                    json,
+                },
+            )?
+        }
+        "gc" => ops::cache_gc(config, args.is_present("dry-run"), json)?,
+        "verify" => ops::cache_verify(&args.workspace(config)?, json)?,
+        cmd => panic!("unexpected command `{}`", cmd),
+    }
+    Ok(())
+}
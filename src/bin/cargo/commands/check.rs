@@ -1,6 +1,6 @@
 use crate::command_prelude::*;
 
-use cargo::ops;
+use cargo::ops::{self, CompileFilter, FilterRule, LibRule};
 
 pub fn cli() -> App {
     subcommand("check")
@@ -29,24 +29,71 @@ pub fn cli() -> App {
         .arg_release("Check artifacts in release mode, with optimizations")
         .arg_profile("Check artifacts with the specified profile")
         .arg_features()
+        .arg_auto_features()
         .arg_target_triple("Check for the target triple")
+        .arg_auto_install_targets()
         .arg_target_dir()
         .arg_manifest_path()
+        .arg_minimal_versions()
         .arg_ignore_rust_version()
         .arg_message_format()
+        .arg_warnings()
         .arg_unit_graph()
         .arg_future_incompat_report()
+        .arg(opt(
+            "edition-readiness",
+            "Report how ready the packages are for their next edition, without changing them (unstable)",
+        ))
         .after_help("Run `cargo help check` for more detailed information.\n")
 }
 
 pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
-    let ws = args.workspace(config)?;
+    let ws = args.compile_workspace(config)?;
     // This is a legacy behavior that causes `cargo check` to pass `--test`.
     let test = matches!(args.value_of("profile"), Some("test"));
     let mode = CompileMode::Check { test };
-    let compile_opts =
+    let mut compile_opts =
         args.compile_options(config, mode, Some(&ws), ProfileChecking::LegacyTestOnly)?;
 
+    if args.is_present("edition-readiness") {
+        if !config.cli_unstable().edition_readiness {
+            return Err(anyhow::format_err!(
+                "Usage of `--edition-readiness` requires `-Z edition-readiness`"
+            )
+            .into());
+        }
+        // Like `cargo fix`, all of the targets are checked by default.
+        if let CompileFilter::Default { .. } = compile_opts.filter {
+            compile_opts.filter = CompileFilter::Only {
+                all_targets: true,
+                lib: LibRule::Default,
+                bins: FilterRule::All,
+                examples: FilterRule::All,
+                benches: FilterRule::All,
+                tests: FilterRule::All,
+                fuzz: FilterRule::none(),
+            }
+        }
+        ops::edition_readiness(
+            &ws,
+            &mut ops::FixOptions {
+                edition: true,
+                idioms: false,
+                compile_opts,
+                allow_dirty: true,
+                allow_no_vcs: true,
+                allow_staged: true,
+                broken_code: false,
+                dry_run: true,
+                json: false,
+                diff: false,
+                lints: Vec::new(),
+                vcs_snapshot: false,
+            },
+        )?;
+        return Ok(());
+    }
+
     ops::compile(&ws, &compile_opts)?;
     Ok(())
 }
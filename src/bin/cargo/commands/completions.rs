@@ -0,0 +1,118 @@
+use crate::command_prelude::*;
+
+use cargo::drop_print;
+use cargo::drop_println;
+use cargo::ops::{self, CompletionCommand, CompletionOption, CompletionQuery, CompletionShell};
+use clap::ArgSettings;
+
+pub fn cli() -> App {
+    subcommand("completions")
+        .about("Generate the completion script of a shell")
+        .arg(
+            Arg::with_name("shell")
+                .value_name("SHELL")
+                .help("The shell to generate the script of: bash, fish or zsh")
+                .required_unless("query"),
+        )
+        .arg(
+            opt("query", "List the values completed for KIND")
+                .value_name("KIND")
+                .hidden(true),
+        )
+        .arg(
+            multi_opt("package", "SPEC", "Package to list the values of")
+                .short("p")
+                .hidden(true),
+        )
+        .arg_manifest_path()
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    if !config.cli_unstable().completions {
+        return Err(anyhow::format_err!(
+            "the `cargo completions` command is unstable, pass `-Z completions` to enable it"
+        )
+        .into());
+    }
+    if let Some(query) = args.value_of("query") {
+        let query: CompletionQuery = query.parse()?;
+        let ws = args.workspace(config)?;
+        for value in ops::completion_candidates(&ws, query, &values(args, "package"))? {
+            drop_println!(config, "{}", value);
+        }
+        return Ok(());
+    }
+
+    let shell: CompletionShell = args.value_of("shell").unwrap().parse()?;
+    let app = crate::cli::cli();
+    let help = CompletionOption {
+        short: Some('h'),
+        long: Some("help".to_string()),
+        help: Some("Prints help information".to_string()),
+        takes_value: false,
+    };
+    let global: Vec<_> = options(&app)
+        .into_iter()
+        .filter(|(_, is_global)| *is_global)
+        .map(|(option, _)| option)
+        .chain(Some(help.clone()))
+        .collect();
+    let mut commands: Vec<_> = app
+        .p
+        .subcommands
+        .iter()
+        .map(|cmd| CompletionCommand {
+            name: cmd.p.meta.name.clone(),
+            about: cmd.p.meta.about.map(|s| s.to_string()),
+            options: options(cmd)
+                .into_iter()
+                .map(|(option, _)| option)
+                .chain(global.iter().cloned())
+                .collect(),
+            subcommands: cmd
+                .p
+                .subcommands
+                .iter()
+                .map(|sub| sub.p.meta.name.clone())
+                .collect(),
+        })
+        .collect();
+    for (alias, name, about) in crate::BUILTIN_ALIASES.iter() {
+        if let Some(cmd) = commands.iter().find(|cmd| cmd.name == *name) {
+            let alias = CompletionCommand {
+                name: alias.to_string(),
+                about: Some(about.to_string()),
+                ..cmd.clone()
+            };
+            commands.push(alias);
+        }
+    }
+    commands.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let top: Vec<_> = options(&app)
+        .into_iter()
+        .map(|(option, _)| option)
+        .chain(Some(help))
+        .collect();
+    drop_print!(config, "{}", ops::completion_script(shell, &top, &commands));
+    Ok(())
+}
+
+/// The visible options of `app`, and whether they are global.
+fn options(app: &App) -> Vec<(CompletionOption, bool)> {
+    let flags = app.p.flags.iter().map(|f| (&f.b, &f.s, false));
+    let opts = app.p.opts.iter().map(|o| (&o.b, &o.s, true));
+    flags
+        .chain(opts)
+        .filter(|(b, _, _)| !b.settings.is_set(ArgSettings::Hidden))
+        .map(|(b, s, takes_value)| {
+            let option = CompletionOption {
+                short: s.short,
+                long: s.long.map(|l| l.to_string()),
+                help: b.help.map(|h| h.to_string()),
+                takes_value,
+            };
+            (option, b.settings.is_set(ArgSettings::Global))
+        })
+        .collect()
+}
@@ -24,6 +24,10 @@ pub fn cli() -> App {
                         .default_value("yes"),
                 ),
         )
+        .subcommand(
+            subcommand("show-proxy")
+                .about("Display the proxy used for each registry, and where it is configured"),
+        )
 }
 
 pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
@@ -40,6 +44,9 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
             };
             cargo_config::get(config, &opts)?;
         }
+        ("show-proxy", Some(_)) => {
+            cargo_config::show_proxy(config)?;
+        }
         (cmd, _) => {
             panic!("unexpected command `{}`", cmd)
         }
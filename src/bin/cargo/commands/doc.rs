@@ -1,6 +1,6 @@
 use crate::command_prelude::*;
 
-use cargo::ops::{self, DocOptions};
+use cargo::ops::{self, CompileFilter, DocOptions, FilterRule, LibRule};
 
 pub fn cli() -> App {
     subcommand("doc")
@@ -18,6 +18,14 @@ pub fn cli() -> App {
             "Exclude packages from the build",
         )
         .arg(opt("no-deps", "Don't build documentation for dependencies"))
+        .arg(opt(
+            "offline-first",
+            "Document a dependency into the shared documentation directory (unstable)",
+        ))
+        .arg(opt(
+            "report-coverage",
+            "Report the documentation coverage of the libraries (unstable)",
+        ))
         .arg(opt("document-private-items", "Document private items"))
         .arg_jobs()
         .arg_targets_lib_bin_example(
@@ -31,9 +39,11 @@ pub fn cli() -> App {
         .arg_profile("Build artifacts with the specified profile")
         .arg_features()
         .arg_target_triple("Build for the target triple")
+        .arg_auto_install_targets()
         .arg_target_dir()
         .arg_manifest_path()
         .arg_message_format()
+        .arg_warnings()
         .arg_ignore_rust_version()
         .arg_unit_graph()
         .after_help("Run `cargo help doc` for more detailed information.\n")
@@ -41,6 +51,20 @@ pub fn cli() -> App {
 
 pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
     let ws = args.workspace(config)?;
+    let offline_first = args.is_present("offline-first");
+    if offline_first && !config.cli_unstable().doc_offline_first {
+        return Err(CliError::new(
+            anyhow::format_err!("Usage of `--offline-first` requires `-Z doc-offline-first`"),
+            101,
+        ));
+    }
+    let report_coverage = args.is_present("report-coverage");
+    if report_coverage && !config.cli_unstable().doc_coverage {
+        return Err(CliError::new(
+            anyhow::format_err!("Usage of `--report-coverage` requires `-Z doc-coverage`"),
+            101,
+        ));
+    }
     let mode = CompileMode::Doc {
         deps: !args.is_present("no-deps"),
     };
@@ -51,7 +75,26 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
     let doc_opts = DocOptions {
         open_result: args.is_present("open"),
         compile_opts,
+        offline_first,
     };
     ops::doc(&ws, &doc_opts)?;
+
+    if report_coverage {
+        let mut compile_opts = args.compile_options(
+            config,
+            CompileMode::Doctest,
+            Some(&ws),
+            ProfileChecking::Custom,
+        )?;
+        compile_opts.rustdoc_document_private_items = args.is_present("document-private-items");
+        compile_opts.filter = CompileFilter::new(
+            LibRule::True,
+            FilterRule::none(),
+            FilterRule::none(),
+            FilterRule::none(),
+            FilterRule::none(),
+        );
+        ops::report_doc_coverage(&ws, &compile_opts)?;
+    }
     Ok(())
 }
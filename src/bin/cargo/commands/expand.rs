@@ -0,0 +1,41 @@
+use crate::command_prelude::*;
+
+use cargo::ops;
+
+pub fn cli() -> App {
+    subcommand("expand")
+        .about("Print the code of a target of a package after expanding its macros")
+        .arg_package("Package to expand")
+        .arg_jobs()
+        .arg_targets_all(
+            "Expand only this package's library",
+            "Expand only the specified binary",
+            "Expand all binaries",
+            "Expand only the specified example",
+            "Expand all examples",
+            "Expand only the specified test target",
+            "Expand all tests",
+            "Expand only the specified bench target",
+            "Expand all benches",
+            "Expand all targets",
+        )
+        .arg_release("Expand artifacts in release mode, with optimizations")
+        .arg_profile("Expand artifacts with the specified profile")
+        .arg_features()
+        .arg_target_triple("Expand for the target triple")
+        .arg_target_dir()
+        .arg_manifest_path()
+        .arg_ignore_rust_version()
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    let ws = args.workspace(config)?;
+    let mut compile_opts = args.compile_options_for_single_package(
+        config,
+        CompileMode::Check { test: false },
+        Some(&ws),
+        ProfileChecking::Custom,
+    )?;
+    ops::expand(&ws, &mut compile_opts)?;
+    Ok(())
+}
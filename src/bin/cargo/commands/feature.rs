@@ -0,0 +1,52 @@
+use crate::command_prelude::*;
+use cargo::ops::{self, ExplainFeatureOptions};
+
+pub fn cli() -> App {
+    subcommand("feature")
+        .about("Inspect the features of packages in the dependency graph")
+        .setting(clap::AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            subcommand("explain")
+                .about("Explain why a feature of a package is activated")
+                .arg(
+                    Arg::with_name("feature")
+                        .value_name("SPEC/FEATURE")
+                        .required(true)
+                        .help("The package and feature to explain, e.g. `serde/std`"),
+                )
+                .arg(opt("quiet", "No output printed to stdout").short("q"))
+                .arg_package_spec_no_all(
+                    "Package to resolve features for",
+                    "Resolve features for all packages in the workspace",
+                    "Exclude specific workspace members",
+                )
+                .arg_features()
+                .arg_target_triple("Resolve features for the target triple")
+                .arg_manifest_path(),
+        )
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    if !config.cli_unstable().feature_explain {
+        return Err(anyhow::format_err!(
+            "the `cargo feature` command is unstable, pass `-Z feature-explain` to enable it"
+        )
+        .into());
+    }
+    match args.subcommand() {
+        ("explain", Some(args)) => {
+            let ws = args.workspace(config)?;
+            let opts = ExplainFeatureOptions {
+                feature: args.value_of("feature").unwrap().to_string(),
+                cli_features: args.cli_features()?,
+                packages: args.packages_from_flags()?,
+                targets: args.targets(),
+            };
+            ops::explain_feature(&ws, &opts)?;
+        }
+        (cmd, _) => {
+            panic!("unexpected command `{}`", cmd)
+        }
+    }
+    Ok(())
+}
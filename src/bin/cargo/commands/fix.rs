@@ -61,12 +61,54 @@ pub fn cli() -> App {
                 .long("allow-staged")
                 .help("Fix code even if the working directory has staged changes"),
         )
+        .arg(opt(
+            "dry-run",
+            "Preview the fixes without changing the files (unstable)",
+        ))
+        .arg(opt(
+            "json",
+            "Print the fixes checked with --dry-run as JSON messages (unstable)",
+        ))
+        .arg(multi_opt(
+            "apply-suggestions",
+            "LINTS",
+            "Only apply the suggestions of these lints, even if they are allowed (unstable)",
+        ))
+        .arg(opt(
+            "diff",
+            "Print the fixes as a unified diff instead of changing the files (unstable)",
+        ))
+        .arg(opt(
+            "undo",
+            "Give the files changed by the last `cargo fix` their original code back (unstable)",
+        ))
+        .arg(opt(
+            "vcs-snapshot",
+            "Save the git working tree in the stash before fixing (unstable)",
+        ))
         .arg_ignore_rust_version()
         .after_help("Run `cargo help fix` for more detailed information.\n")
 }
 
 pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
     let ws = args.workspace(config)?;
+
+    let undo = args.is_present("undo");
+    let vcs_snapshot = args.is_present("vcs-snapshot");
+    if (undo || vcs_snapshot) && !config.cli_unstable().fix_backups {
+        return Err(CliError::new(
+            anyhow::format_err!(
+                "Usage of `--{}` requires `-Z fix-backups`",
+                if undo { "undo" } else { "vcs-snapshot" }
+            ),
+            101,
+        ));
+    }
+    if undo {
+        ops::undo_fix(&ws)?;
+        return Ok(());
+    }
+
     // This is a legacy behavior that causes `cargo fix` to pass `--test`.
     let test = matches!(args.value_of("profile"), Some("test"));
     let mode = CompileMode::Check { test };
@@ -84,9 +126,52 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
             examples: FilterRule::All,
             benches: FilterRule::All,
             tests: FilterRule::All,
+            fuzz: FilterRule::none(),
         }
     }
 
+    let dry_run = args.is_present("dry-run");
+    let json = args.is_present("json");
+    if (dry_run || json) && !config.cli_unstable().edition_migration {
+        return Err(CliError::new(
+            anyhow::format_err!(
+                "Usage of `--{}` requires `-Z edition-migration`",
+                if dry_run { "dry-run" } else { "json" }
+            ),
+            101,
+        ));
+    }
+    if json && !dry_run {
+        return Err(CliError::new(
+            anyhow::format_err!("`--json` can only be used with `--dry-run`"),
+            101,
+        ));
+    }
+
+    let lints: Vec<String> = args
+        ._values_of("apply-suggestions")
+        .iter()
+        .flat_map(|lints| lints.split(','))
+        .map(|lint| lint.trim().to_string())
+        .filter(|lint| !lint.is_empty())
+        .collect();
+    let diff = args.is_present("diff");
+    if (args.is_present("apply-suggestions") || diff) && !config.cli_unstable().apply_suggestions {
+        return Err(CliError::new(
+            anyhow::format_err!(
+                "Usage of `--{}` requires `-Z apply-suggestions`",
+                if diff { "diff" } else { "apply-suggestions" }
+            ),
+            101,
+        ));
+    }
+    if diff && json {
+        return Err(CliError::new(
+            anyhow::format_err!("Can't mix --diff with --json"),
+            101,
+        ));
+    }
+
     ops::fix(
         &ws,
         &mut ops::FixOptions {
@@ -97,6 +182,11 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
             allow_no_vcs: args.is_present("allow-no-vcs"),
             allow_staged: args.is_present("allow-staged"),
             broken_code: args.is_present("broken-code"),
+            dry_run: dry_run || diff,
+            json,
+            diff,
+            lints,
+            vcs_snapshot,
         },
     )?;
     Ok(())
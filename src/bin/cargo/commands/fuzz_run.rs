@@ -0,0 +1,74 @@
+use crate::command_prelude::*;
+use cargo::core::compiler::{CompileKind, CompileTarget};
+use cargo::ops::{self, CompileFilter, FilterRule, FuzzRunOptions};
+use cargo_util::ProcessError;
+
+pub fn cli() -> App {
+    subcommand("fuzz-run")
+        .setting(AppSettings::TrailingVarArg)
+        .about("Build and run a fuzz target of the local package")
+        .arg(opt("quiet", "No output printed to stdout").short("q"))
+        .arg(
+            Arg::with_name("TARGET")
+                .required(true)
+                .help("Name of the fuzz target to run"),
+        )
+        .arg(
+            Arg::with_name("args")
+                .multiple(true)
+                .help("Arguments for libFuzzer"),
+        )
+        .arg(
+            opt(
+                "corpus",
+                "Directory of the corpus [default: fuzz/corpus/<TARGET>]",
+            )
+            .value_name("DIR"),
+        )
+        .arg_package("Package with the fuzz target to run")
+        .arg_jobs()
+        .arg_profile("Build artifacts with the specified profile [default: fuzz]")
+        .arg_features()
+        .arg_target_triple("Build for the target triple [default: the host]")
+        .arg_target_dir()
+        .arg_manifest_path()
+        .arg_message_format()
+        .arg_ignore_rust_version()
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    let ws = args.workspace(config)?;
+
+    let mut compile_opts = args.compile_options(
+        config,
+        CompileMode::Build,
+        Some(&ws),
+        ProfileChecking::Custom,
+    )?;
+    compile_opts.build_config.requested_profile =
+        args.get_profile_name(config, "fuzz", ProfileChecking::Custom)?;
+    compile_opts.build_config.fuzz = true;
+    // Build for an explicit target even if it is the host, so that build
+    // scripts and proc-macros are kept apart from the instrumented code.
+    if compile_opts.build_config.requested_kinds == [CompileKind::Host] {
+        let host = config.load_global_rustc(Some(&ws))?.host;
+        compile_opts.build_config.requested_kinds =
+            vec![CompileKind::Target(CompileTarget::new(&host)?)];
+    }
+    let target = args.value_of("TARGET").unwrap().to_string();
+    compile_opts.filter = CompileFilter::new_fuzz(FilterRule::Just(vec![target.clone()]));
+
+    let opts = FuzzRunOptions {
+        target,
+        corpus: args.value_of_path("corpus", config),
+        args: values_os(args, "args"),
+    };
+    ops::fuzz_run(&ws, &compile_opts, &opts).map_err(|err| {
+        // Forward the exit code of libFuzzer, which tells crashes apart from
+        // other failures.
+        match err.downcast_ref::<ProcessError>().and_then(|e| e.code) {
+            Some(exit_code) => CliError::new(err, exit_code),
+            None => CliError::new(err, 101),
+        }
+    })
+}
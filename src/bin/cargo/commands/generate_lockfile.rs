@@ -7,6 +7,7 @@ pub fn cli() -> App {
         .about("Generate the lockfile for a package")
         .arg(opt("quiet", "No output printed to stdout").short("q"))
         .arg_manifest_path()
+        .arg_minimal_versions()
         .after_help("Run `cargo help generate-lockfile` for more detailed information.\n")
 }
 
@@ -0,0 +1,31 @@
+use crate::command_prelude::*;
+
+use cargo::ops;
+use std::path::PathBuf;
+
+pub fn cli() -> App {
+    subcommand("git-bundle")
+        .about("Export the locked git dependencies as git bundles, for offline builds")
+        .arg(opt("quiet", "No output printed to stdout").short("q"))
+        .arg_manifest_path()
+        .arg(Arg::with_name("path").help("Where to write the bundles (`git-bundles` by default)"))
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    if !config.cli_unstable().git_bundle {
+        return Err(anyhow::format_err!(
+            "the `cargo git-bundle` command is unstable, pass `-Z git-bundle` to enable it"
+        )
+        .into());
+    }
+    // Like `cargo vendor`, the bundles are made from the original git
+    // repositories, not from the sources replacing them.
+    config.values_mut()?.remove("source");
+    let ws = args.workspace(config)?;
+    let path = args
+        .value_of_os("path")
+        .map(|val| PathBuf::from(val.to_os_string()))
+        .unwrap_or_else(|| PathBuf::from("git-bundles"));
+    ops::git_bundle(&ws, &path)?;
+    Ok(())
+}
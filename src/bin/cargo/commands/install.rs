@@ -57,8 +57,31 @@ pub fn cli() -> App {
             "Install all examples",
         )
         .arg_target_triple("Build for the target triple")
+        .arg_auto_install_targets()
         .arg_target_dir()
         .arg(opt("root", "Directory to install packages into").value_name("DIR"))
+        .arg(
+            opt(
+                "prefix",
+                "Directory the packages are installed into once staged (unstable)",
+            )
+            .value_name("DIR")
+            .conflicts_with("root"),
+        )
+        .arg(
+            opt(
+                "destdir",
+                "Stage the installation root into this directory (unstable)",
+            )
+            .value_name("DIR"),
+        )
+        .arg(
+            opt(
+                "install-manifest",
+                "Write the installed files to this file (unstable)",
+            )
+            .value_name("PATH"),
+        )
         .arg(
             opt("index", "Registry index to install from")
                 .value_name("INDEX")
@@ -115,7 +138,19 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
     };
 
     let version = args.value_of("version");
-    let root = args.value_of("root");
+    let root = args.value_of("root").or_else(|| args.value_of("prefix"));
+    for flag in ["prefix", "destdir", "install-manifest"] {
+        if args.is_present(flag) && !config.cli_unstable().staged_install {
+            return Err(CliError::new(
+                anyhow::format_err!("Usage of `--{}` requires `-Z staged-install`", flag),
+                101,
+            ));
+        }
+    }
+    let staging = ops::InstallStaging {
+        destdir: args.value_of_path("destdir", config),
+        install_manifest: args.value_of_path("install-manifest", config),
+    };
 
     // We only provide workspace information for local crate installation from
     // one of the following sources:
@@ -150,6 +185,7 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
             &compile_opts,
             args.is_present("force"),
             args.is_present("no-track"),
+            &staging,
         )?;
     }
     Ok(())
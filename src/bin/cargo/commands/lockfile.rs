@@ -0,0 +1,74 @@
+use crate::command_prelude::*;
+
+use cargo::ops;
+
+pub fn cli() -> App {
+    subcommand("lockfile")
+        .about("Manage the format and the merges of `Cargo.lock`")
+        .setting(clap::AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            subcommand("migrate")
+                .about("Rewrite `Cargo.lock` in another version of its format")
+                .arg(
+                    opt("to", "The version to write, 1, 2, 3 or 5 (default: 5)")
+                        .value_name("VERSION"),
+                )
+                .arg(opt("quiet", "No output printed to stdout").short("q"))
+                .arg_manifest_path(),
+        )
+        .subcommand(
+            subcommand("merge-driver")
+                .about("Merge two versions of `Cargo.lock`, as the merge driver of git")
+                .arg(
+                    Arg::with_name("base")
+                        .required(true)
+                        .help("The common ancestor, `%O`"),
+                )
+                .arg(
+                    Arg::with_name("ours")
+                        .required(true)
+                        .help("Our version, `%A`, which is overwritten with the merge"),
+                )
+                .arg(
+                    Arg::with_name("theirs")
+                        .required(true)
+                        .help("Their version, `%B`"),
+                )
+                .arg_manifest_path(),
+        )
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    let (cmd, args) = match args.subcommand() {
+        (cmd, Some(args)) => (cmd, args),
+        (cmd, None) => panic!("unexpected command `{}`", cmd),
+    };
+    if cmd == "merge-driver" {
+        if !config.cli_unstable().lockfile_merge {
+            return Err(anyhow::format_err!(
+                "the `cargo lockfile merge-driver` command is unstable, \
+                 pass `-Z lockfile-merge` to enable it"
+            )
+            .into());
+        }
+    } else if !config.cli_unstable().lockfile_v5 {
+        return Err(anyhow::format_err!(
+            "the `cargo lockfile` command is unstable, pass `-Z lockfile-v5` to enable it"
+        )
+        .into());
+    }
+    let ws = args.workspace(config)?;
+    match cmd {
+        "merge-driver" => ops::merge_lockfiles(
+            &ws,
+            &config.cwd().join(args.value_of("ours").unwrap()),
+            &config.cwd().join(args.value_of("theirs").unwrap()),
+        )?,
+        "migrate" => {
+            let version = ops::parse_lockfile_version(args.value_of("to").unwrap_or("5"))?;
+            ops::migrate_lockfile(&ws, version)?
+        }
+        cmd => panic!("unexpected command `{}`", cmd),
+    }
+    Ok(())
+}
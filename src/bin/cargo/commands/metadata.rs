@@ -20,6 +20,10 @@ pub fn cli() -> App {
             "Output information only about the workspace members \
              and don't fetch dependencies",
         ))
+        .arg(opt(
+            "watch",
+            "Keep running, and print the changes to the metadata when the manifests change (unstable)",
+        ))
         .arg_manifest_path()
         .arg(
             opt("format-version", "Format version")
@@ -30,6 +34,13 @@ pub fn cli() -> App {
 }
 
 pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    let watch = args.is_present("watch");
+    if watch && !config.cli_unstable().metadata_watch {
+        return Err(CliError::new(
+            anyhow::format_err!("Usage of `--watch` requires `-Z metadata-watch`"),
+            101,
+        ));
+    }
     let ws = args.workspace(config)?;
 
     let version = match args.value_of("format-version") {
@@ -50,6 +61,11 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
         version,
     };
 
+    if watch {
+        ops::watch_metadata(config, ws.root_manifest(), &options)?;
+        return Ok(());
+    }
+
     let result = ops::output_metadata(&ws, &options)?;
     config.shell().print_json(&result)?;
     Ok(())
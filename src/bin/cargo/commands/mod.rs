@@ -2,19 +2,26 @@ use crate::command_prelude::*;
 
 pub fn builtin() -> Vec<App> {
     vec![
+        audits::cli(),
         bench::cli(),
         build::cli(),
+        cache::cli(),
         check::cli(),
         clean::cli(),
+        completions::cli(),
         config::cli(),
         doc::cli(),
+        feature::cli(),
         fetch::cli(),
         fix::cli(),
+        fuzz_run::cli(),
         generate_lockfile::cli(),
+        git_bundle::cli(),
         git_checkout::cli(),
         init::cli(),
         install::cli(),
         locate_project::cli(),
+        lockfile::cli(),
         login::cli(),
         logout::cli(),
         metadata::cli(),
@@ -22,6 +29,7 @@ pub fn builtin() -> Vec<App> {
         owner::cli(),
         package::cli(),
         pkgid::cli(),
+        plugin::cli(),
         publish::cli(),
         read_manifest::cli(),
         report::cli(),
@@ -29,10 +37,12 @@ pub fn builtin() -> Vec<App> {
         rustc::cli(),
         rustdoc::cli(),
         search::cli(),
+        serve_api::cli(),
         test::cli(),
         tree::cli(),
         uninstall::cli(),
         update::cli(),
+        validate::cli(),
         vendor::cli(),
         verify_project::cli(),
         version::cli(),
@@ -42,19 +52,26 @@ pub fn builtin() -> Vec<App> {
 
 pub fn builtin_exec(cmd: &str) -> Option<fn(&mut Config, &ArgMatches<'_>) -> CliResult> {
     let f = match cmd {
+        "audits" => audits::exec,
         "bench" => bench::exec,
         "build" => build::exec,
+        "cache" => cache::exec,
         "check" => check::exec,
         "clean" => clean::exec,
+        "completions" => completions::exec,
         "config" => config::exec,
         "doc" => doc::exec,
+        "feature" => feature::exec,
         "fetch" => fetch::exec,
         "fix" => fix::exec,
+        "fuzz-run" => fuzz_run::exec,
         "generate-lockfile" => generate_lockfile::exec,
+        "git-bundle" => git_bundle::exec,
         "git-checkout" => git_checkout::exec,
         "init" => init::exec,
         "install" => install::exec,
         "locate-project" => locate_project::exec,
+        "lockfile" => lockfile::exec,
         "login" => login::exec,
         "logout" => logout::exec,
         "metadata" => metadata::exec,
@@ -62,6 +79,7 @@ pub fn builtin_exec(cmd: &str) -> Option<fn(&mut Config, &ArgMatches<'_>) -> Cli
         "owner" => owner::exec,
         "package" => package::exec,
         "pkgid" => pkgid::exec,
+        "plugin" => plugin::exec,
         "publish" => publish::exec,
         "read-manifest" => read_manifest::exec,
         "report" => report::exec,
@@ -69,10 +87,12 @@ pub fn builtin_exec(cmd: &str) -> Option<fn(&mut Config, &ArgMatches<'_>) -> Cli
         "rustc" => rustc::exec,
         "rustdoc" => rustdoc::exec,
         "search" => search::exec,
+        "serve-api" => serve_api::exec,
         "test" => test::exec,
         "tree" => tree::exec,
         "uninstall" => uninstall::exec,
         "update" => update::exec,
+        "validate" => validate::exec,
         "vendor" => vendor::exec,
         "verify-project" => verify_project::exec,
         "version" => version::exec,
@@ -82,38 +102,53 @@ pub fn builtin_exec(cmd: &str) -> Option<fn(&mut Config, &ArgMatches<'_>) -> Cli
     Some(f)
 }
 
+pub mod add;
+pub mod asm;
+pub mod audits;
 pub mod bench;
 pub mod build;
+pub mod cache;
 pub mod check;
 pub mod clean;
+pub mod completions;
 pub mod config;
 pub mod doc;
+pub mod expand;
+pub mod feature;
 pub mod fetch;
 pub mod fix;
+pub mod fuzz_run;
 pub mod generate_lockfile;
+pub mod git_bundle;
 pub mod git_checkout;
 pub mod help;
 pub mod init;
 pub mod install;
 pub mod locate_project;
+pub mod lockfile;
 pub mod login;
 pub mod logout;
 pub mod metadata;
 pub mod new;
 pub mod owner;
 pub mod package;
+pub mod patch;
 pub mod pkgid;
+pub mod plugin;
 pub mod publish;
 pub mod read_manifest;
+pub mod release;
 pub mod report;
 pub mod run;
 pub mod rustc;
 pub mod rustdoc;
 pub mod search;
+pub mod serve_api;
 pub mod test;
 pub mod tree;
 pub mod uninstall;
 pub mod update;
+pub mod validate;
 pub mod vendor;
 pub mod verify_project;
 pub mod version;
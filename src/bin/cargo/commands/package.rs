@@ -1,6 +1,9 @@
 use crate::command_prelude::*;
 
-use cargo::ops::{self, PackageOpts};
+use cargo::ops::{self, BinaryArchiveFormat, BinaryPackageOpts, PackageOpts};
+use cargo::ops::{CompileFilter, FilterRule, LibRule};
+use cargo::util::vcs_state::VcsCommand;
+use std::path::PathBuf;
 
 pub fn cli() -> App {
     subcommand("package")
@@ -25,6 +28,25 @@ pub fn cli() -> App {
             "allow-dirty",
             "Allow dirty working directories to be packaged",
         ))
+        .arg(opt(
+            "binary",
+            "Package the built binaries into a distributable archive",
+        ))
+        .arg(
+            multi_opt(
+                "include",
+                "PATH",
+                "Add a file or directory of the package to the binary archive",
+            )
+            .requires("binary"),
+        )
+        .arg(
+            opt("archive-format", "Format of the binary archive")
+                .value_name("FORMAT")
+                .possible_values(&["tar.gz", "zip"])
+                .requires("binary"),
+        )
+        .arg_profile("Build the binaries with the specified profile")
         .arg_target_triple("Build for the target triple")
         .arg_target_dir()
         .arg_features()
@@ -40,6 +62,49 @@ pub fn cli() -> App {
 
 pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
     let ws = args.workspace(config)?;
+
+    if args.is_present("binary") {
+        if !config.cli_unstable().binary_package {
+            return Err(CliError::new(
+                anyhow::format_err!("Usage of `--binary` requires `-Z binary-package`"),
+                101,
+            ));
+        }
+        let mut compile_opts = args.compile_options(
+            config,
+            CompileMode::Build,
+            Some(&ws),
+            ProfileChecking::Custom,
+        )?;
+        compile_opts.build_config.requested_profile =
+            args.get_profile_name(config, "release", ProfileChecking::Custom)?;
+        compile_opts.filter = CompileFilter::new(
+            LibRule::False,
+            FilterRule::All,
+            FilterRule::none(),
+            FilterRule::none(),
+            FilterRule::none(),
+        );
+        let format = args
+            .value_of("archive-format")
+            .map(BinaryArchiveFormat::parse)
+            .transpose()?;
+        let include = args
+            .values_of("include")
+            .unwrap_or_default()
+            .map(PathBuf::from)
+            .collect();
+        ops::package_binary(
+            &ws,
+            &BinaryPackageOpts {
+                compile_opts,
+                format,
+                include,
+            },
+        )?;
+        return Ok(());
+    }
+
     let specs = args.packages_from_flags()?;
 
     ops::package(
@@ -50,6 +115,7 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
             list: args.is_present("list"),
             check_metadata: !args.is_present("no-metadata"),
             allow_dirty: args.is_present("allow-dirty"),
+            vcs_command: VcsCommand::Package,
             to_package: specs,
             targets: args.targets(),
             jobs: args.jobs()?,
@@ -0,0 +1,82 @@
+use crate::command_prelude::*;
+
+use cargo::ops::{self, PatchAddOptions};
+
+pub fn cli() -> App {
+    subcommand("patch")
+        .about("Add `[patch]` entries, and explain why they are or aren't applied")
+        .setting(clap::AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            subcommand("add")
+                .about("Patch a dependency of the workspace with a local or git fork")
+                .arg(
+                    Arg::with_name("crate")
+                        .empty_values(false)
+                        .required(true)
+                        .help("The package to patch"),
+                )
+                .arg(opt("quiet", "No output printed to stdout").short("q"))
+                .arg(
+                    opt("path", "Filesystem path to the fork of the package")
+                        .value_name("PATH")
+                        .conflicts_with("git")
+                        .required_unless("git"),
+                )
+                .arg(opt("git", "Git URL of the fork of the package").value_name("URL"))
+                .arg(
+                    opt("branch", "Branch of the git fork")
+                        .value_name("BRANCH")
+                        .requires("git")
+                        .conflicts_with_all(&["tag", "rev"]),
+                )
+                .arg(
+                    opt("tag", "Tag of the git fork")
+                        .value_name("TAG")
+                        .requires("git")
+                        .conflicts_with("rev"),
+                )
+                .arg(
+                    opt("rev", "Commit of the git fork")
+                        .value_name("SHA")
+                        .requires("git"),
+                )
+                .arg(opt("registry", "Registry of the patched package").value_name("REGISTRY"))
+                .arg_dry_run("Don't actually write the manifest and the lock file")
+                .arg_manifest_path(),
+        )
+        .subcommand(
+            subcommand("explain")
+                .about("Explain why the patches of the workspace are or aren't applied")
+                .arg(Arg::with_name("crate").help("Only explain the patches of this package"))
+                .arg(opt("quiet", "No output printed to stdout").short("q"))
+                .arg_manifest_path(),
+        )
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    match args.subcommand() {
+        ("add", Some(args)) => {
+            let ws = args.workspace(config)?;
+            let git_ref = ["branch", "tag", "rev"]
+                .iter()
+                .find_map(|kind| args.value_of(kind).map(|r| (*kind, r.to_string())));
+            let opts = PatchAddOptions {
+                krate: args.value_of("crate").unwrap().to_string(),
+                path: args.value_of_path("path", config),
+                git: args.value_of("git").map(|s| s.to_string()),
+                git_ref,
+                registry: args.registry(config)?,
+                dry_run: args.is_present("dry-run"),
+            };
+            ops::patch_add(&ws, &opts)?;
+        }
+        ("explain", Some(args)) => {
+            let ws = args.workspace(config)?;
+            ops::patch_explain(&ws, args.value_of("crate"))?;
+        }
+        (cmd, _) => {
+            panic!("unexpected command `{}`", cmd)
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use crate::command_prelude::*;
+
+use cargo::core::SourceId;
+use cargo::ops::{self, PluginInstallOptions};
+
+pub fn cli() -> App {
+    subcommand("plugin")
+        .about("Install, list and update Cargo plugins")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            subcommand("install")
+                .about("Install plugins from a registry")
+                .arg(opt("quiet", "No output printed to stdout").short("q"))
+                .arg(
+                    Arg::with_name("crate")
+                        .empty_values(false)
+                        .multiple(true)
+                        .required(true),
+                )
+                .arg(opt("version", "Specify a version to install").value_name("VERSION"))
+                .arg(multi_opt(
+                    "allow",
+                    "PERMISSION",
+                    "Grant PERMISSION to the plugins without asking",
+                ))
+                .arg_jobs()
+                .arg(opt("force", "Force overwriting existing crates or binaries").short("f"))
+                .arg(opt("registry", "Registry to use").value_name("REGISTRY")),
+        )
+        .subcommand(subcommand("list").about("List the installed plugins"))
+        .subcommand(
+            subcommand("update")
+                .about("Update the installed plugins to their latest compatible version")
+                .arg(opt("quiet", "No output printed to stdout").short("q"))
+                .arg(Arg::with_name("crate").empty_values(false).multiple(true))
+                .arg(multi_opt(
+                    "allow",
+                    "PERMISSION",
+                    "Grant PERMISSION to the plugins without asking",
+                ))
+                .arg_jobs(),
+        )
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    if !config.cli_unstable().plugin {
+        return Err(anyhow::format_err!(
+            "the `cargo plugin` command is unstable, pass `-Z plugin` to enable it"
+        )
+        .into());
+    }
+    // Like `cargo install`, don't use the configuration of the current
+    // directory.
+    config.reload_rooted_at(config.home().clone().into_path_unlocked())?;
+    match args.subcommand() {
+        ("install", Some(args)) => install(config, args, false),
+        ("list", Some(_)) => {
+            ops::plugin_list(config)?;
+            Ok(())
+        }
+        ("update", Some(args)) => install(config, args, true),
+        (cmd, _) => panic!("unexpected command `{}`", cmd),
+    }
+}
+
+fn install(config: &Config, args: &ArgMatches<'_>, update: bool) -> CliResult {
+    let source_id = match args.registry(config)? {
+        Some(registry) => SourceId::alt_registry(config, &registry)?,
+        None => SourceId::crates_io(config)?,
+    };
+    let mut compile_opts =
+        args.compile_options(config, CompileMode::Build, None, ProfileChecking::Custom)?;
+    compile_opts.build_config.requested_profile =
+        args.get_profile_name(config, "release", ProfileChecking::Custom)?;
+    let opts = PluginInstallOptions {
+        krates: values(args, "crate"),
+        vers: args.value_of("version").map(|s| s.to_string()),
+        source_id,
+        allow: values(args, "allow"),
+        force: args.is_present("force"),
+        compile_opts: &compile_opts,
+    };
+    if update {
+        ops::plugin_update(config, &opts)?;
+    } else {
+        ops::plugin_install(config, &opts)?;
+    }
+    Ok(())
+}
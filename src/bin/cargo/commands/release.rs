@@ -0,0 +1,64 @@
+use crate::command_prelude::*;
+
+use cargo::ops::{self, ReleaseOptions, VersionBump};
+
+pub fn cli() -> App {
+    subcommand("release")
+        .about("Bump the versions of workspace members, tag and publish them")
+        .arg(
+            Arg::with_name("bump")
+                .help(
+                    "Version bump: `major`, `minor`, `patch` or a version, \
+                     inferred from the conventional commits of each package if omitted",
+                )
+                .conflicts_with("resume"),
+        )
+        .arg(opt("quiet", "No output printed to stdout").short("q"))
+        .arg_package_spec_no_all(
+            "Package(s) to release",
+            "Release all packages in the workspace",
+            "Exclude packages from the release",
+        )
+        .arg(opt("resume", "Continue the release that is in progress"))
+        .arg(opt(
+            "no-changelog",
+            "Don't add the release to the CHANGELOG.md of the packages",
+        ))
+        .arg(opt("no-tag", "Don't tag the release commit"))
+        .arg(opt("no-publish", "Don't publish the released packages"))
+        .arg(opt(
+            "no-verify",
+            "Don't verify the contents of the packages by building them",
+        ))
+        .arg(opt(
+            "allow-dirty",
+            "Allow releasing with uncommitted changes in the working directory",
+        ))
+        .arg(opt("token", "Token to use when uploading").value_name("TOKEN"))
+        .arg(opt("registry", "Registry to publish to").value_name("REGISTRY"))
+        .arg_dry_run("Print the versions that would be released without changing anything")
+        .arg_manifest_path()
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    config.load_credentials()?;
+
+    let ws = args.workspace(config)?;
+    let bump = args.value_of("bump").map(VersionBump::parse).transpose()?;
+    let opts = ReleaseOptions {
+        config,
+        to_release: args.packages_from_flags()?,
+        bump,
+        dry_run: args.is_present("dry-run"),
+        resume: args.is_present("resume"),
+        changelog: !args.is_present("no-changelog"),
+        tag: !args.is_present("no-tag"),
+        publish: !args.is_present("no-publish"),
+        allow_dirty: args.is_present("allow-dirty"),
+        verify: !args.is_present("no-verify"),
+        registry: args.registry(config)?,
+        token: args.value_of("token").map(|s| s.to_string()),
+    };
+    ops::release(&ws, &opts)?;
+    Ok(())
+}
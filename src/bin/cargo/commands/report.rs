@@ -2,12 +2,79 @@ use crate::command_prelude::*;
 use anyhow::anyhow;
 use cargo::core::compiler::future_incompat::{OnDiskReports, REPORT_PREAMBLE};
 use cargo::drop_println;
+use cargo::ops::{
+    self, ReportBloatOptions, ReportBuildStatsOptions, ReportCheckCfgOptions,
+    ReportLicensesOptions, ReportLinkGraphOptions, ReportPathsOptions,
+};
 
 pub fn cli() -> App {
     subcommand("report")
         .about("Generate and display various kinds of reports")
         .after_help("Run `cargo help report` for more detailed information.\n")
         .setting(clap::AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            subcommand("bloat")
+                .about("Breaks down the code size of the built executables and shared libraries")
+                .arg(Arg::with_name("artifact").multiple(true).help(
+                    "Names of the artifacts to analyze, like `foo` or `libfoo.so`, all by default",
+                ))
+                .arg(
+                    opt(
+                        "top",
+                        "Number of the largest crates and functions to print (default 20)",
+                    )
+                    .value_name("N"),
+                )
+                .arg(opt(
+                    "json",
+                    "Print the reports as JSON, one line per artifact",
+                ))
+                .arg(
+                    opt(
+                        "baseline",
+                        "Compare with the reports printed with `--json` in FILE",
+                    )
+                    .value_name("FILE"),
+                )
+                .arg(
+                    opt(
+                        "max-growth",
+                        "Fail if the code of an artifact grew by more than PERCENT",
+                    )
+                    .value_name("PERCENT"),
+                )
+                .arg_manifest_path()
+                .arg_target_triple("Analyze the artifacts of the target triple")
+                .arg_target_dir()
+                .arg_release("Analyze the artifacts of the release profile")
+                .arg_profile("Analyze the artifacts of the specified profile"),
+        )
+        .subcommand(
+            subcommand("build-stats")
+                .about("Prints the trends of the builds recorded with `-Z build-stats`")
+                .arg(
+                    opt("top", "Number of the slowest units to print (default 10)").value_name("N"),
+                )
+                .arg(
+                    opt("builds", "Number of the last builds to print (default 10)")
+                        .value_name("N"),
+                )
+                .arg_manifest_path()
+                .arg_target_dir(),
+        )
+        .subcommand(
+            subcommand("check-cfg")
+                .about("Checks the packages and prints the cfgs each of them expects")
+                .arg_package_spec(
+                    "Package(s) to check",
+                    "Check all packages in the workspace",
+                    "Exclude packages from the check",
+                )
+                .arg_features()
+                .arg_target_triple("Check for the target triple")
+                .arg_target_dir()
+                .arg_manifest_path(),
+        )
         .subcommand(
             subcommand("future-incompatibilities")
                 .alias("future-incompat")
@@ -21,6 +88,34 @@ pub fn cli() -> App {
                 )
                 .arg_package("Package to display a report for"),
         )
+        .subcommand(
+            subcommand("licenses")
+                .about("Lists the licenses of the dependencies and bundles their license texts")
+                .arg(opt(
+                    "bundle",
+                    "Write the license texts to `target/licenses/THIRD-PARTY-NOTICES`",
+                ))
+                .arg_manifest_path()
+                .arg_target_dir(),
+        )
+        .subcommand(
+            subcommand("link-graph")
+                .about("Prints the crates and native libraries linked into each artifact")
+                .arg_manifest_path()
+                .arg_target_triple("Print the link graph of the target triple")
+                .arg_target_dir()
+                .arg_release("Print the link graph of the release profile")
+                .arg_profile("Print the link graph of the specified profile"),
+        )
+        .subcommand(
+            subcommand("paths")
+                .about("Checks the built artifacts for absolute paths of this machine")
+                .arg_manifest_path()
+                .arg_target_triple("Check the artifacts of the target triple")
+                .arg_target_dir()
+                .arg_release("Check the artifacts of the release profile")
+                .arg_profile("Check the artifacts of the specified profile"),
+        )
 }
 
 pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
@@ -28,11 +123,67 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
         return Err(anyhow!("`cargo report` can only be used on the nightly channel").into());
     }
     match args.subcommand() {
+        ("bloat", Some(args)) => report_bloat(config, args),
+        ("build-stats", Some(args)) => report_build_stats(config, args),
+        ("check-cfg", Some(args)) => report_check_cfg(config, args),
         ("future-incompatibilities", Some(args)) => report_future_incompatibilies(config, args),
+        ("licenses", Some(args)) => report_licenses(config, args),
+        ("link-graph", Some(args)) => report_link_graph(config, args),
+        ("paths", Some(args)) => report_paths(config, args),
         (cmd, _) => panic!("unexpected command `{}`", cmd),
     }
 }
 
+fn report_bloat(config: &Config, args: &ArgMatches<'_>) -> CliResult {
+    let ws = args.workspace(config)?;
+    let max_growth = match args.value_of("max-growth") {
+        Some(max) => Some(max.trim_end_matches('%').parse::<f64>().map_err(|_| {
+            anyhow!(
+                "`--max-growth` must be a percentage, like `5`, found `{}`",
+                max
+            )
+        })?),
+        None => None,
+    };
+    let opts = ReportBloatOptions {
+        config,
+        targets: args.targets(),
+        requested_profile: args.get_profile_name(config, "dev", ProfileChecking::Custom)?,
+        artifacts: args
+            .values_of("artifact")
+            .unwrap_or_default()
+            .map(String::from)
+            .collect(),
+        top: args.value_of_u32("top")?.unwrap_or(20) as usize,
+        json: args.is_present("json"),
+        baseline: args.value_of_path("baseline", config),
+        max_growth,
+    };
+    ops::report_bloat(&ws, &opts)?;
+    Ok(())
+}
+
+fn report_build_stats(config: &Config, args: &ArgMatches<'_>) -> CliResult {
+    let ws = args.workspace(config)?;
+    let opts = ReportBuildStatsOptions {
+        config,
+        top: args.value_of_u32("top")?.unwrap_or(10) as usize,
+        builds: args.value_of_u32("builds")?.unwrap_or(10) as usize,
+    };
+    ops::report_build_stats(&ws, &opts)?;
+    Ok(())
+}
+
+fn report_check_cfg(config: &Config, args: &ArgMatches<'_>) -> CliResult {
+    let ws = args.workspace(config)?;
+    let mode = CompileMode::Check { test: false };
+    let opts = ReportCheckCfgOptions {
+        compile_opts: args.compile_options(config, mode, Some(&ws), ProfileChecking::Custom)?,
+    };
+    ops::report_check_cfg(&ws, &opts)?;
+    Ok(())
+}
+
 fn report_future_incompatibilies(config: &Config, args: &ArgMatches<'_>) -> CliResult {
     let ws = args.workspace(config)?;
     let reports = OnDiskReports::load(&ws)?;
@@ -45,3 +196,35 @@ fn report_future_incompatibilies(config: &Config, args: &ArgMatches<'_>) -> CliR
     drop(config.shell().print_ansi_stdout(report.as_bytes()));
     Ok(())
 }
+
+fn report_licenses(config: &Config, args: &ArgMatches<'_>) -> CliResult {
+    let ws = args.workspace(config)?;
+    let opts = ReportLicensesOptions {
+        config,
+        bundle: args.is_present("bundle"),
+    };
+    ops::report_licenses(&ws, &opts)?;
+    Ok(())
+}
+
+fn report_link_graph(config: &Config, args: &ArgMatches<'_>) -> CliResult {
+    let ws = args.workspace(config)?;
+    let opts = ReportLinkGraphOptions {
+        config,
+        targets: args.targets(),
+        requested_profile: args.get_profile_name(config, "dev", ProfileChecking::Custom)?,
+    };
+    ops::report_link_graph(&ws, &opts)?;
+    Ok(())
+}
+
+fn report_paths(config: &Config, args: &ArgMatches<'_>) -> CliResult {
+    let ws = args.workspace(config)?;
+    let opts = ReportPathsOptions {
+        config,
+        targets: args.targets(),
+        requested_profile: args.get_profile_name(config, "dev", ProfileChecking::Custom)?,
+    };
+    ops::report_paths(&ws, &opts)?;
+    Ok(())
+}
@@ -21,10 +21,13 @@ pub fn cli() -> App {
         .arg_release("Build artifacts in release mode, with optimizations")
         .arg_profile("Build artifacts with the specified profile")
         .arg_features()
+        .arg_auto_features()
         .arg_target_triple("Build for the target triple")
+        .arg_auto_install_targets()
         .arg_target_dir()
         .arg_manifest_path()
         .arg_message_format()
+        .arg_warnings()
         .arg_unit_graph()
         .arg_ignore_rust_version()
         .after_help("Run `cargo help run` for more detailed information.\n")
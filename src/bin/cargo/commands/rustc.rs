@@ -3,6 +3,7 @@ use cargo::ops;
 use cargo::util::interning::InternedString;
 
 const PRINT_ARG_NAME: &str = "print";
+const CRATE_TYPE_ARG_NAME: &str = "crate-type";
 
 pub fn cli() -> App {
     subcommand("rustc")
@@ -27,7 +28,9 @@ pub fn cli() -> App {
         .arg_release("Build artifacts in release mode, with optimizations")
         .arg_profile("Build artifacts with the specified profile")
         .arg_features()
+        .arg_auto_features()
         .arg_target_triple("Target triple which compiles will be for")
+        .arg_auto_install_targets()
         .arg(
             opt(
                 PRINT_ARG_NAME,
@@ -35,9 +38,15 @@ pub fn cli() -> App {
             )
             .value_name("INFO"),
         )
+        .arg(multi_opt(
+            CRATE_TYPE_ARG_NAME,
+            "CRATE-TYPE",
+            "Comma separated list of types of crates for the compiler to emit (unstable)",
+        ))
         .arg_target_dir()
         .arg_manifest_path()
         .arg_message_format()
+        .arg_warnings()
         .arg_unit_graph()
         .arg_ignore_rust_version()
         .arg_future_incompat_report()
@@ -70,6 +79,22 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
     } else {
         Some(target_args)
     };
+    let crate_types = values(args, CRATE_TYPE_ARG_NAME);
+    compile_opts.target_rustc_crate_types = if crate_types.is_empty() {
+        None
+    } else {
+        config
+            .cli_unstable()
+            .fail_if_stable_opt(CRATE_TYPE_ARG_NAME, 10083)?;
+        Some(
+            crate_types
+                .iter()
+                .flat_map(|types| types.split(','))
+                .map(|t| t.trim().to_owned())
+                .filter(|t| !t.is_empty())
+                .collect(),
+        )
+    };
     if let Some(opt_value) = args.value_of(PRINT_ARG_NAME) {
         config
             .cli_unstable()
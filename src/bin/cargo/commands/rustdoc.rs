@@ -30,9 +30,11 @@ pub fn cli() -> App {
         .arg_profile("Build artifacts with the specified profile")
         .arg_features()
         .arg_target_triple("Build for the target triple")
+        .arg_auto_install_targets()
         .arg_target_dir()
         .arg_manifest_path()
         .arg_message_format()
+        .arg_warnings()
         .arg_unit_graph()
         .arg_ignore_rust_version()
         .after_help("Run `cargo help rustdoc` for more detailed information.\n")
@@ -55,6 +57,7 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
     let doc_opts = DocOptions {
         open_result: args.is_present("open"),
         compile_opts,
+        offline_first: false,
     };
     ops::doc(&ws, &doc_opts)?;
     Ok(())
@@ -2,7 +2,8 @@ use crate::command_prelude::*;
 
 use std::cmp::min;
 
-use cargo::ops;
+use cargo::ops::{self, SearchOptions};
+use cargo::CargoResult;
 
 pub fn cli() -> App {
     subcommand("search")
@@ -18,16 +19,91 @@ pub fn cli() -> App {
             .value_name("LIMIT"),
         )
         .arg(opt("registry", "Registry to use").value_name("REGISTRY"))
+        .arg(opt("category", "Only show crates in CATEGORY (unstable)").value_name("CATEGORY"))
+        .arg(opt("keyword", "Only show crates with KEYWORD (unstable)").value_name("KEYWORD"))
+        .arg(
+            opt(
+                "license",
+                "Only show crates available under LICENSE (unstable)",
+            )
+            .value_name("LICENSE"),
+        )
+        .arg(
+            opt(
+                "rust-version",
+                "Only show crates supporting Rust VERSION (unstable)",
+            )
+            .value_name("VERSION"),
+        )
+        .arg(
+            opt("sort", "Sort the results (unstable)")
+                .value_name("ORDER")
+                .possible_values(&[
+                    "relevance",
+                    "alpha",
+                    "downloads",
+                    "recent-downloads",
+                    "recent-updates",
+                    "new",
+                ]),
+        )
+        .arg(opt("json", "Print the results as JSON (unstable)"))
         .after_help("Run `cargo help search` for more detailed information.\n")
 }
 
 pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    let unstable = [
+        "category",
+        "keyword",
+        "license",
+        "rust-version",
+        "sort",
+        "json",
+    ];
+    if let Some(flag) = unstable.iter().find(|flag| args.is_present(flag)) {
+        if !config.cli_unstable().search_filters {
+            return Err(CliError::new(
+                anyhow::format_err!("Usage of `--{}` requires `-Z search-filters`", flag),
+                101,
+            ));
+        }
+    }
+
     let registry = args.registry(config)?;
     let index = args.index(config)?;
     let limit = args.value_of_u32("limit")?;
     let limit = min(100, limit.unwrap_or(10));
     let query: Vec<&str> = args.values_of("query").unwrap_or_default().collect();
     let query: String = query.join("+");
-    ops::search(&query, config, index, limit, registry)?;
+    let rust_version = match args.value_of("rust-version") {
+        Some(version) => Some(parse_rust_version(version)?),
+        None => None,
+    };
+    let opts = SearchOptions {
+        index,
+        registry,
+        limit,
+        category: args.value_of("category").map(|s| s.to_string()),
+        keyword: args.value_of("keyword").map(|s| s.to_string()),
+        license: args.value_of("license").map(|s| s.to_string()),
+        rust_version,
+        sort: args.value_of("sort").map(|s| s.parse()).transpose()?,
+        json: args.is_present("json"),
+    };
+    ops::search(&query, config, &opts)?;
     Ok(())
 }
+
+/// Parses a Rust version like `1.56` or `1.56.1`.
+fn parse_rust_version(version: &str) -> CargoResult<semver::Version> {
+    let full = match version.matches('.').count() {
+        1 => format!("{}.0", version),
+        _ => version.to_string(),
+    };
+    semver::Version::parse(&full).map_err(|_| {
+        anyhow::format_err!(
+            "invalid value `{}` for `--rust-version`, expected a version like `1.56`",
+            version
+        )
+    })
+}
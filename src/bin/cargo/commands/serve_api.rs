@@ -0,0 +1,21 @@
+use crate::command_prelude::*;
+
+use cargo::ops;
+
+pub fn cli() -> App {
+    subcommand("serve-api")
+        .about("Serve the operations of Cargo on the workspace over JSON-RPC on stdin and stdout")
+        .arg_manifest_path()
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    if !config.cli_unstable().serve_api {
+        return Err(anyhow::format_err!(
+            "the `cargo serve-api` command is unstable, pass `-Z serve-api` to enable it"
+        )
+        .into());
+    }
+    let root = args.root_manifest(config)?;
+    ops::serve_api(config, &root)?;
+    Ok(())
+}
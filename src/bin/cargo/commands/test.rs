@@ -1,5 +1,7 @@
 use crate::command_prelude::*;
 use anyhow::Error;
+use cargo::core::compiler::{CompileKind, CompileTarget};
+use cargo::core::profiles::Sanitizer;
 use cargo::ops::{self, CompileFilter, FilterRule, LibRule};
 
 pub fn cli() -> App {
@@ -40,6 +42,37 @@ pub fn cli() -> App {
         .arg(opt("doc", "Test only this library's documentation"))
         .arg(opt("no-run", "Compile, but don't run tests"))
         .arg(opt("no-fail-fast", "Run all tests regardless of failure"))
+        .arg(opt(
+            "affected",
+            "Only run the tests whose inputs changed since they last passed (unstable)",
+        ))
+        .arg(opt(
+            "no-cache",
+            "Run the tests even if they passed with the same build before (unstable)",
+        ))
+        .arg(opt(
+            "no-capture-streaming",
+            "Write the output of each test binary to a log file in `target/test-logs` (unstable)",
+        ))
+        .arg(opt(
+            "coverage",
+            "Collect code coverage of the tests into `target/coverage` (unstable)",
+        ))
+        .arg(
+            opt(
+                "sanitizer",
+                "Build and run the tests with the given sanitizer (unstable)",
+            )
+            .value_name("NAME"),
+        )
+        .arg(opt(
+            "miri",
+            "Run the tests in the Miri interpreter to detect undefined behavior (unstable)",
+        ))
+        .arg(opt(
+            "feature-matrix",
+            "Run the tests once for each combination of features (unstable)",
+        ))
         .arg_package_spec(
             "Package to run tests for",
             "Test all packages in the workspace",
@@ -49,18 +82,22 @@ pub fn cli() -> App {
         .arg_release("Build artifacts in release mode, with optimizations")
         .arg_profile("Build artifacts with the specified profile")
         .arg_features()
+        .arg_auto_features()
         .arg_target_triple("Build for the target triple")
+        .arg_auto_install_targets()
         .arg_target_dir()
         .arg_manifest_path()
+        .arg_minimal_versions()
         .arg_ignore_rust_version()
         .arg_message_format()
+        .arg_warnings()
         .arg_unit_graph()
         .arg_future_incompat_report()
         .after_help("Run `cargo help test` for more detailed information.\n")
 }
 
 pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
-    let ws = args.workspace(config)?;
+    let ws = args.compile_workspace(config)?;
 
     let mut compile_opts = args.compile_options(
         config,
@@ -114,12 +151,135 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
         }
     }
 
+    let affected = args.is_present("affected");
+    if affected && !config.cli_unstable().test_affected {
+        return Err(CliError::new(
+            anyhow::format_err!("Usage of `--affected` requires `-Z test-affected`"),
+            101,
+        ));
+    }
+
+    let no_cache = args.is_present("no-cache");
+    if no_cache && !config.cli_unstable().test_cache {
+        return Err(CliError::new(
+            anyhow::format_err!("Usage of `--no-cache` requires `-Z test-cache`"),
+            101,
+        ));
+    }
+
+    let test_logs = args.is_present("no-capture-streaming");
+    if test_logs && !config.cli_unstable().test_logs {
+        return Err(CliError::new(
+            anyhow::format_err!("Usage of `--no-capture-streaming` requires `-Z test-logs`"),
+            101,
+        ));
+    }
+
+    let coverage = args.is_present("coverage");
+    if coverage {
+        if !config.cli_unstable().test_coverage {
+            return Err(CliError::new(
+                anyhow::format_err!("Usage of `--coverage` requires `-Z test-coverage`"),
+                101,
+            ));
+        }
+        if doc {
+            return Err(CliError::new(
+                anyhow::format_err!("Can't collect coverage of doc tests with --coverage"),
+                101,
+            ));
+        }
+        if affected {
+            return Err(CliError::new(
+                anyhow::format_err!("Can't mix --coverage with --affected"),
+                101,
+            ));
+        }
+        compile_opts.build_config.coverage = true;
+    }
+
+    if let Some(sanitizer) = args.value_of("sanitizer") {
+        if !config.cli_unstable().sanitizers {
+            return Err(CliError::new(
+                anyhow::format_err!("Usage of `--sanitizer` requires `-Z sanitizers`"),
+                101,
+            ));
+        }
+        let sanitizer: Sanitizer = sanitizer.parse()?;
+        // The standard library can only be rebuilt for an explicit target.
+        if sanitizer.requires_build_std()
+            && compile_opts.build_config.requested_kinds == [CompileKind::Host]
+        {
+            let host = config.load_global_rustc(Some(&ws))?.host;
+            compile_opts.build_config.requested_kinds =
+                vec![CompileKind::Target(CompileTarget::new(&host)?)];
+        }
+        compile_opts.build_config.sanitizer = Some(sanitizer);
+    }
+
+    let miri = args.is_present("miri");
+    if miri {
+        if !config.cli_unstable().test_miri {
+            return Err(CliError::new(
+                anyhow::format_err!("Usage of `--miri` requires `-Z test-miri`"),
+                101,
+            ));
+        }
+        if doc {
+            return Err(CliError::new(
+                anyhow::format_err!("Can't run doc tests with --miri"),
+                101,
+            ));
+        }
+        if coverage || compile_opts.build_config.sanitizer.is_some() {
+            return Err(CliError::new(
+                anyhow::format_err!("Can't mix --miri with --coverage or --sanitizer"),
+                101,
+            ));
+        }
+        // Build scripts and proc-macros are compiled by rustc and run
+        // natively, so they must be kept apart from what Miri interprets.
+        if compile_opts.build_config.requested_kinds == [CompileKind::Host] {
+            let host = config.load_global_rustc(Some(&ws))?.host;
+            compile_opts.build_config.requested_kinds =
+                vec![CompileKind::Target(CompileTarget::new(&host)?)];
+        }
+        compile_opts.build_config.miri = true;
+    }
+
     let ops = ops::TestOptions {
         no_run,
         no_fail_fast: args.is_present("no-fail-fast"),
+        affected,
+        // Every test needs to run to get complete coverage data.
+        cache_results: config.cli_unstable().test_cache && !no_cache && !coverage,
+        test_logs,
         compile_opts,
     };
 
+    if args.is_present("feature-matrix") {
+        if !config.cli_unstable().feature_matrix {
+            return Err(CliError::new(
+                anyhow::format_err!("Usage of `--feature-matrix` requires `-Z feature-matrix`"),
+                101,
+            ));
+        }
+        if args.is_present("features")
+            || args.is_present("all-features")
+            || args.is_present("no-default-features")
+        {
+            return Err(CliError::new(
+                anyhow::format_err!(
+                    "--feature-matrix cannot be used together with \
+                     --features, --all-features or --no-default-features"
+                ),
+                101,
+            ));
+        }
+        ops::run_feature_matrix(&ws, ops, &test_args)?;
+        return Ok(());
+    }
+
     let err = ops::run_tests(&ws, &ops, &test_args)?;
     match err {
         None => Ok(()),
@@ -21,11 +21,25 @@ pub fn cli() -> App {
             )
             .value_name("PRECISE"),
         )
+        .arg(
+            opt(
+                "merge",
+                "Resolve the merge conflicts of the lock file against the manifests",
+            )
+            .conflicts_with_all(&["package", "workspace", "aggressive", "precise"]),
+        )
         .arg_manifest_path()
+        .arg_minimal_versions()
         .after_help("Run `cargo help update` for more detailed information.\n")
 }
 
 pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    if args.is_present("merge") && !config.cli_unstable().lockfile_merge {
+        return Err(anyhow::format_err!(
+            "the `--merge` flag is unstable, pass `-Z lockfile-merge` to enable it"
+        )
+        .into());
+    }
     let ws = args.workspace(config)?;
 
     if args.is_present_with_zero_values("package") {
@@ -38,6 +52,7 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
         to_update: values(args, "package"),
         dry_run: args.is_present("dry-run"),
         workspace: args.is_present("workspace"),
+        merge: args.is_present("merge"),
         config,
     };
     ops::update_lockfile(&ws, &update_opts)?;
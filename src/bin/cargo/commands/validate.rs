@@ -0,0 +1,62 @@
+use crate::command_prelude::*;
+
+use cargo::ops::{self, Severity, ValidateOptions};
+
+pub fn cli() -> App {
+    subcommand("validate")
+        .about("Check the manifests, lock file and publish-readiness of the workspace")
+        .arg(opt("quiet", "No output printed to stdout").short("q"))
+        .arg(multi_opt("allow", "CHECK", "Ignore the findings of CHECK"))
+        .arg(multi_opt(
+            "warn",
+            "CHECK",
+            "Report the findings of CHECK as warnings",
+        ))
+        .arg(multi_opt(
+            "deny",
+            "CHECK",
+            "Report the findings of CHECK as errors",
+        ))
+        .arg(
+            opt(
+                "cargo-version",
+                "Check the manifests against the schema of this version of Cargo",
+            )
+            .value_name("VERSION"),
+        )
+        .arg(
+            opt("message-format", "Output representation")
+                .value_name("FMT")
+                .possible_values(&["human", "json"])
+                .default_value("human"),
+        )
+        .arg_manifest_path()
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    if !config.cli_unstable().validate {
+        return Err(anyhow::format_err!(
+            "the `cargo validate` command is unstable, pass `-Z validate` to enable it"
+        )
+        .into());
+    }
+    let ws = args.workspace(config)?;
+    // When a check is given to several flags, the strictest one wins.
+    let mut severities = Vec::new();
+    for (flag, severity) in [
+        ("allow", Severity::Allow),
+        ("warn", Severity::Warn),
+        ("deny", Severity::Deny),
+    ] {
+        for check in values(args, flag) {
+            severities.push((check, severity));
+        }
+    }
+    let opts = ValidateOptions {
+        severities,
+        json: args.value_of("message-format") == Some("json"),
+        cargo_version: args.value_of("cargo-version").map(|s| s.to_string()),
+    };
+    ops::validate(&ws, &opts)?;
+    Ok(())
+}
@@ -1,16 +1,67 @@
 use crate::cli;
 use crate::command_prelude::*;
 
+use cargo::ops::{self, BumpVersionOptions, VersionBump};
+
 pub fn cli() -> App {
     subcommand("version")
         .about("Show version information")
         .arg(opt("quiet", "No output printed to stdout").short("q"))
+        .subcommand(
+            subcommand("bump")
+                .about(
+                    "Bump the version of a package, and the requirements of \
+                     the workspace members on it",
+                )
+                .arg(opt("quiet", "No output printed to stdout").short("q"))
+                .arg_package("Package to bump")
+                .arg(opt("major", "Bump the major version"))
+                .arg(opt("minor", "Bump the minor version"))
+                .arg(opt("patch", "Bump the patch version"))
+                .arg(opt("to", "Set the version to VERSION").value_name("VERSION"))
+                .group(
+                    clap::ArgGroup::with_name("level")
+                        .args(&["major", "minor", "patch", "to"])
+                        .required(true),
+                )
+                .arg_dry_run("Print the changes to the manifests as a diff instead of writing them")
+                .arg_manifest_path(),
+        )
         .after_help("Run `cargo help version` for more detailed information.\n")
 }
 
 pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    if let ("bump", Some(args)) = args.subcommand() {
+        return bump(config, args);
+    }
     let verbose = args.occurrences_of("verbose") > 0;
     let version = cli::get_version_string(verbose);
     cargo::drop_print!(config, "{}", version);
     Ok(())
 }
+
+fn bump(config: &Config, args: &ArgMatches<'_>) -> CliResult {
+    if !config.cli_unstable().version_bump {
+        return Err(anyhow::format_err!(
+            "the `cargo version bump` command is unstable, pass `-Z version-bump` to enable it"
+        )
+        .into());
+    }
+    let ws = args.workspace(config)?;
+    let bump = if args.is_present("major") {
+        VersionBump::Major
+    } else if args.is_present("minor") {
+        VersionBump::Minor
+    } else if args.is_present("patch") {
+        VersionBump::Patch
+    } else {
+        VersionBump::parse(args.value_of("to").unwrap())?
+    };
+    let opts = BumpVersionOptions {
+        package: args.value_of("package").map(|s| s.to_string()),
+        bump,
+        dry_run: args.is_present("dry-run"),
+    };
+    ops::bump_version(&ws, &opts)?;
+    Ok(())
+}
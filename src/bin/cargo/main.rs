@@ -20,9 +20,13 @@ use crate::command_prelude::*;
 
 fn main() {
     #[cfg(feature = "pretty-env-logger")]
-    pretty_env_logger::init_custom_env("CARGO_LOG");
+    let logger = pretty_env_logger::formatted_builder()
+        .parse_env("CARGO_LOG")
+        .build();
     #[cfg(not(feature = "pretty-env-logger"))]
-    env_logger::init_from_env("CARGO_LOG");
+    let logger = env_logger::Builder::from_env("CARGO_LOG").build();
+    let level = logger.filter();
+    util::log_file::init(Box::new(logger), level);
 
     let mut config = match Config::default() {
         Ok(cfg) => cfg,
@@ -164,11 +168,15 @@ fn execute_external_subcommand(config: &Config, cmd: &str, args: &[&str]) -> Cli
     };
 
     let cargo_exe = config.cargo_exe()?;
-    let err = match ProcessBuilder::new(&command)
-        .env(cargo::CARGO_ENV, cargo_exe)
-        .args(args)
-        .exec_replace()
-    {
+    let mut process = ProcessBuilder::new(&command);
+    process.env(cargo::CARGO_ENV, cargo_exe).args(args);
+    cargo::ops::restrict_plugin(config, cmd, &command, &mut process);
+    let result = if config.cli_unstable().subcommand_context {
+        cargo::ops::exec_with_context(config, &mut process)
+    } else {
+        process.exec_replace()
+    };
+    let err = match result {
         Ok(()) => return Ok(()),
         Err(e) => e,
     };
@@ -0,0 +1,363 @@
+//! A stable API for tools that use Cargo as a library.
+//!
+//! The rest of this crate is the implementation of Cargo, and changes in
+//! incompatible ways in every release. The items of this module are the
+//! exception: they cover what most tools need (loading a workspace, querying
+//! its metadata, resolving its dependencies, and reading and writing
+//! `Cargo.lock`), and are kept compatible across the releases of the crate,
+//! even though its version number changes with each of them.
+//!
+//! The guarantee is that code written against this module keeps compiling
+//! and behaving the same way with later releases:
+//!
+//! * Items are not removed, and their signatures don't change. New items,
+//!   methods and fields may be added.
+//! * The types wrap the internal ones in private fields, so that those can
+//!   change freely. Structs with public fields are `#[non_exhaustive]`, and
+//!   are created with `Default`. Enums are `#[non_exhaustive]` too.
+//! * The metadata is the JSON document of `cargo metadata --format-version 1`,
+//!   which has the same compatibility guarantee.
+//! * [`API_VERSION`] is only increased if the guarantee ever has to be
+//!   broken, so that tools can check it.
+//!
+//! Nothing outside of this module is covered by the guarantee, including the
+//! internal types some of these items are built from.
+//!
+//! ```no_run
+//! use cargo::api::{Context, Workspace};
+//!
+//! # fn main() -> cargo::CargoResult<()> {
+//! let ctx = Context::new()?;
+//! let ws = Workspace::load(&ctx, "Cargo.toml".as_ref())?;
+//! let resolve = ws.resolve()?;
+//! for member in ws.members() {
+//!     for dep in resolve.dependencies(member.id()) {
+//!         println!("{} depends on {}", member.name(), dep);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use semver::Version;
+use serde_json::Value;
+
+use crate::core::dependency::DepKind;
+use crate::core::resolver::CliFeatures;
+use crate::core::{self, PackageIdSpec, Shell};
+use crate::ops;
+use crate::util::{CargoResult, Config};
+
+/// The version of the compatibility guarantee of this module.
+pub const API_VERSION: u32 = 1;
+
+/// The configuration that workspaces are loaded with, from the environment,
+/// the configuration files and `CARGO_HOME`.
+pub struct Context {
+    config: Config,
+}
+
+impl Context {
+    /// The configuration of the current directory, like Cargo uses it when
+    /// it is run there.
+    pub fn new() -> CargoResult<Context> {
+        Ok(Context {
+            config: Config::default()?,
+        })
+    }
+
+    /// The configuration of the directory `cwd`, with `cargo_home` as
+    /// `CARGO_HOME`. Nothing is printed while workspaces are loaded.
+    pub fn with_paths(cwd: &Path, cargo_home: &Path) -> Context {
+        Context {
+            config: Config::new(
+                Shell::from_write(Box::new(std::io::sink())),
+                cwd.to_path_buf(),
+                cargo_home.to_path_buf(),
+            ),
+        }
+    }
+}
+
+/// A loaded workspace, or a package on its own.
+pub struct Workspace<'ctx> {
+    ws: core::Workspace<'ctx>,
+}
+
+impl<'ctx> Workspace<'ctx> {
+    /// Loads the workspace of the manifest at `manifest_path`, which must be
+    /// an absolute path or relative to the current directory.
+    pub fn load(ctx: &'ctx Context, manifest_path: &Path) -> CargoResult<Workspace<'ctx>> {
+        let manifest_path = ctx.config.cwd().join(manifest_path);
+        Ok(Workspace {
+            ws: core::Workspace::new(&manifest_path, &ctx.config)?,
+        })
+    }
+
+    /// The directory of the root manifest.
+    pub fn root(&self) -> &Path {
+        self.ws.root()
+    }
+
+    /// The path of the root manifest.
+    pub fn root_manifest(&self) -> &Path {
+        self.ws.root_manifest()
+    }
+
+    /// The directory the workspace is built into.
+    pub fn target_dir(&self) -> PathBuf {
+        self.ws.target_dir().into_path_unlocked()
+    }
+
+    /// The members of the workspace.
+    pub fn members(&self) -> Vec<Package> {
+        self.ws.members().cloned().map(Package).collect()
+    }
+
+    /// The metadata of the workspace, as printed by
+    /// `cargo metadata --format-version 1`.
+    pub fn metadata(&self, opts: &MetadataOptions) -> CargoResult<Value> {
+        let opts = ops::OutputMetadataOptions {
+            cli_features: CliFeatures::from_command_line(
+                &opts.features,
+                opts.all_features,
+                !opts.no_default_features,
+            )?,
+            no_deps: opts.no_deps,
+            version: 1,
+            filter_platforms: opts.filter_platforms.clone(),
+        };
+        Ok(serde_json::to_value(ops::output_metadata(
+            &self.ws, &opts,
+        )?)?)
+    }
+
+    /// Resolves the dependencies of the workspace, and updates `Cargo.lock`
+    /// like a build does.
+    pub fn resolve(&self) -> CargoResult<Resolve> {
+        let (_, resolve) = ops::resolve_ws(&self.ws)?;
+        Ok(Resolve { resolve })
+    }
+
+    /// Reads `Cargo.lock`, if there is one.
+    pub fn read_lockfile(&self) -> CargoResult<Option<Resolve>> {
+        let resolve = ops::load_pkg_lockfile(&self.ws)?;
+        Ok(resolve.map(|resolve| Resolve { resolve }))
+    }
+
+    /// Writes `resolve` to `Cargo.lock`, if it changed.
+    pub fn write_lockfile(&self, resolve: &mut Resolve) -> CargoResult<()> {
+        ops::write_pkg_lockfile(&self.ws, &mut resolve.resolve)
+    }
+}
+
+/// The options of [`Workspace::metadata`], like those of `cargo metadata`.
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct MetadataOptions {
+    /// Only includes the members of the workspace, without resolving the
+    /// dependencies.
+    pub no_deps: bool,
+    /// The features to enable.
+    pub features: Vec<String>,
+    pub all_features: bool,
+    pub no_default_features: bool,
+    /// Only includes the dependencies of these target triples.
+    pub filter_platforms: Vec<String>,
+}
+
+/// The identity of a package: its name, version and source.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PackageId(core::PackageId);
+
+impl PackageId {
+    pub fn name(&self) -> &str {
+        self.0.name().as_str()
+    }
+
+    pub fn version(&self) -> &Version {
+        self.0.version()
+    }
+
+    /// The URL of the source, like
+    /// `registry+https://github.com/rust-lang/crates.io-index`.
+    pub fn source(&self) -> String {
+        self.0.source_id().as_url().to_string()
+    }
+
+    /// Whether the package is in a local directory.
+    pub fn is_path(&self) -> bool {
+        self.0.source_id().is_path()
+    }
+
+    /// Whether the package id specification `spec`, like `foo` or
+    /// `foo:1.0.0`, selects this package.
+    pub fn matches(&self, spec: &str) -> CargoResult<bool> {
+        Ok(PackageIdSpec::parse(spec)?.matches(self.0))
+    }
+}
+
+/// Displayed like the ids of `cargo metadata`: `foo 0.1.0 (source)`.
+impl fmt::Display for PackageId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} ({})", self.name(), self.version(), self.source())
+    }
+}
+
+impl fmt::Debug for PackageId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PackageId({})", self)
+    }
+}
+
+/// A package of the workspace.
+#[derive(Clone)]
+pub struct Package(core::Package);
+
+impl Package {
+    pub fn id(&self) -> PackageId {
+        PackageId(self.0.package_id())
+    }
+
+    pub fn name(&self) -> &str {
+        self.0.name().as_str()
+    }
+
+    pub fn version(&self) -> &Version {
+        self.0.version()
+    }
+
+    pub fn manifest_path(&self) -> &Path {
+        self.0.manifest_path()
+    }
+
+    /// The directory of the manifest.
+    pub fn root(&self) -> &Path {
+        self.0.root()
+    }
+
+    pub fn edition(&self) -> String {
+        self.0.manifest().edition().to_string()
+    }
+
+    pub fn rust_version(&self) -> Option<&str> {
+        self.0.rust_version()
+    }
+
+    /// The features of the package, and what each of them enables.
+    pub fn features(&self) -> BTreeMap<String, Vec<String>> {
+        self.0
+            .summary()
+            .features()
+            .iter()
+            .map(|(name, values)| {
+                let values = values.iter().map(|v| v.to_string()).collect();
+                (name.to_string(), values)
+            })
+            .collect()
+    }
+
+    pub fn dependencies(&self) -> Vec<Dependency> {
+        self.0
+            .dependencies()
+            .iter()
+            .cloned()
+            .map(Dependency)
+            .collect()
+    }
+}
+
+/// A dependency declared in a manifest.
+#[derive(Clone)]
+pub struct Dependency(core::Dependency);
+
+impl Dependency {
+    /// The name of the dependency in the manifest, which differs from
+    /// [`Dependency::package_name`] when it is renamed.
+    pub fn name(&self) -> &str {
+        self.0.name_in_toml().as_str()
+    }
+
+    pub fn package_name(&self) -> &str {
+        self.0.package_name().as_str()
+    }
+
+    pub fn version_req(&self) -> String {
+        self.0.version_req().to_string()
+    }
+
+    pub fn kind(&self) -> DependencyKind {
+        match self.0.kind() {
+            DepKind::Normal => DependencyKind::Normal,
+            DepKind::Development => DependencyKind::Development,
+            DepKind::Build => DependencyKind::Build,
+        }
+    }
+
+    pub fn is_optional(&self) -> bool {
+        self.0.is_optional()
+    }
+
+    /// The `cfg` or target triple the dependency is only used for.
+    pub fn target(&self) -> Option<String> {
+        self.0.platform().map(|p| p.to_string())
+    }
+
+    /// The URL of the source, like [`PackageId::source`].
+    pub fn source(&self) -> String {
+        self.0.source_id().as_url().to_string()
+    }
+}
+
+/// The table of the manifest a dependency is declared in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DependencyKind {
+    Normal,
+    Development,
+    Build,
+}
+
+/// The resolved dependency graph of a workspace, as written to `Cargo.lock`.
+pub struct Resolve {
+    resolve: core::Resolve,
+}
+
+impl Resolve {
+    /// The packages of the graph, sorted.
+    pub fn packages(&self) -> Vec<PackageId> {
+        let mut ids: Vec<_> = self.resolve.iter().map(PackageId).collect();
+        ids.sort();
+        ids
+    }
+
+    /// The packages `id` depends on, sorted.
+    pub fn dependencies(&self, id: PackageId) -> Vec<PackageId> {
+        let mut deps: Vec<_> = self
+            .resolve
+            .deps(id.0)
+            .map(|(dep, _)| PackageId(dep))
+            .collect();
+        deps.sort();
+        deps
+    }
+
+    /// The features of `id` enabled by the resolver, sorted.
+    pub fn features(&self, id: PackageId) -> Vec<String> {
+        self.resolve
+            .features(id.0)
+            .iter()
+            .map(|f| f.to_string())
+            .collect()
+    }
+
+    /// The package selected by the package id specification `spec`, like
+    /// `foo` or `foo:1.0.0`. It is an error if it selects none or several.
+    pub fn query(&self, spec: &str) -> CargoResult<PackageId> {
+        Ok(PackageId(self.resolve.query(spec)?))
+    }
+}
@@ -0,0 +1,222 @@
+//! The audit store of a workspace, where a team records the versions of its
+//! dependencies it reviewed.
+//!
+//! The store is the `supply-chain` directory at the root of the workspace:
+//!
+//! * `audits.toml` has the audits of the team, the exemptions of the
+//!   versions which don't need one, and the URLs of the audit feeds of other
+//!   teams to import,
+//! * `imports.lock` has the audits of the imported feeds, as fetched by
+//!   `cargo audits fetch`, so that checking the dependencies doesn't need the
+//!   network.
+//!
+//! A version is audited by a full audit of it, or by a chain of delta audits
+//! from a version with a full audit. The feeds are files in the format of
+//! `audits.toml`, of which only the audits are imported.
+
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::path::PathBuf;
+
+use anyhow::{bail, Context as _};
+use cargo_util::paths;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+use crate::core::{PackageId, Workspace};
+use crate::util::errors::CargoResult;
+
+/// The file of the audits of the team, relative to the workspace root.
+pub const AUDITS_FILE: &str = "supply-chain/audits.toml";
+/// The file of the imported audits, relative to the workspace root.
+pub const IMPORTS_FILE: &str = "supply-chain/imports.lock";
+
+/// The contents of `audits.toml`, and of the imported feeds.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct AuditsFile {
+    /// The URLs of the feeds to import, by name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub imports: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub audits: Vec<Audit>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exemptions: Vec<Exemption>,
+}
+
+/// The review of a version of a crate, or of the changes between two
+/// versions.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Audit {
+    pub name: String,
+    /// The version reviewed in full.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// The versions whose changes were reviewed, as `<from> -> <to>`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delta: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub who: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+/// A version which doesn't need an audit.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Exemption {
+    pub name: String,
+    pub version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// The contents of `imports.lock`, the audits of each imported feed.
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ImportsLock {
+    #[serde(default)]
+    pub imports: BTreeMap<String, ImportedAudits>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ImportedAudits {
+    /// The URL the audits were fetched from.
+    pub url: String,
+    #[serde(default)]
+    pub audits: Vec<Audit>,
+}
+
+/// Whether a version of a dependency is audited.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuditStatus {
+    /// Audited, by the team or by the imported feed with the given name.
+    Audited(Option<String>),
+    Exempted,
+    /// Not audited, with the versions of the crate which are.
+    Unaudited(Vec<Version>),
+}
+
+/// The audits of a workspace, with the imported ones.
+pub struct AuditStore {
+    root: PathBuf,
+    pub audits: AuditsFile,
+    pub imports: ImportsLock,
+}
+
+impl AuditStore {
+    /// Loads the store of `ws`, which is empty if it doesn't exist yet.
+    pub fn load(ws: &Workspace<'_>) -> CargoResult<AuditStore> {
+        let root = ws.root().to_path_buf();
+        let audits: AuditsFile = load_toml(&root.join(AUDITS_FILE))?;
+        for audit in &audits.audits {
+            audit_versions(audit)?;
+        }
+        let imports = load_toml(&root.join(IMPORTS_FILE))?;
+        Ok(AuditStore {
+            root,
+            audits,
+            imports,
+        })
+    }
+
+    /// Writes `audits.toml` and `imports.lock`.
+    pub fn save(&self) -> CargoResult<()> {
+        let audits = self.root.join(AUDITS_FILE);
+        paths::create_dir_all(audits.parent().unwrap())?;
+        paths::write(&audits, toml::to_string(&self.audits)?)?;
+        let imports = self.root.join(IMPORTS_FILE);
+        if !self.imports.imports.is_empty() || imports.exists() {
+            paths::write(&imports, toml::to_string(&self.imports)?)?;
+        }
+        Ok(())
+    }
+
+    /// Whether the version `id` of a crate is audited.
+    pub fn status(&self, id: PackageId) -> AuditStatus {
+        let name = id.name().as_str();
+        let version = id.version();
+        let own = self.audits.audits.iter().map(|audit| (None, audit));
+        let imported = self.imports.imports.iter().flat_map(|(import, imported)| {
+            imported
+                .audits
+                .iter()
+                .map(move |audit| (Some(import.as_str()), audit))
+        });
+
+        // The audited versions, each with who audited it first.
+        let mut audited: BTreeMap<Version, Option<&str>> = BTreeMap::new();
+        let mut deltas = Vec::new();
+        for (by, audit) in own.chain(imported).filter(|(_, audit)| audit.name == name) {
+            match audit_versions(audit) {
+                Ok((None, to)) => {
+                    audited.entry(to).or_insert(by);
+                }
+                Ok((Some(from), to)) => deltas.push((by, from, to)),
+                // Imported audits are not validated when they are fetched.
+                Err(_) => {}
+            }
+        }
+        let mut queue: VecDeque<Version> = audited.keys().cloned().collect();
+        let mut seen: HashSet<Version> = queue.iter().cloned().collect();
+        while let Some(from) = queue.pop_front() {
+            for (by, _, to) in deltas.iter().filter(|(_, f, _)| *f == from) {
+                let by = audited[&from].or(*by);
+                if seen.insert(to.clone()) {
+                    audited.insert(to.clone(), by);
+                    queue.push_back(to.clone());
+                }
+            }
+        }
+
+        if let Some(by) = audited.get(version) {
+            return AuditStatus::Audited(by.map(str::to_string));
+        }
+        if self
+            .audits
+            .exemptions
+            .iter()
+            .any(|e| e.name == name && Version::parse(&e.version).ok().as_ref() == Some(version))
+        {
+            return AuditStatus::Exempted;
+        }
+        AuditStatus::Unaudited(audited.into_keys().collect())
+    }
+}
+
+/// The versions of an audit: the version it starts from, for a delta audit,
+/// and the version it audits.
+fn audit_versions(audit: &Audit) -> CargoResult<(Option<Version>, Version)> {
+    let parse = |version: &str| {
+        Version::parse(version.trim()).with_context(|| {
+            format!(
+                "invalid version `{}` in the audit of `{}`",
+                version, audit.name
+            )
+        })
+    };
+    match (&audit.version, &audit.delta) {
+        (Some(version), None) => Ok((None, parse(version)?)),
+        (None, Some(delta)) => match delta.split_once("->") {
+            Some((from, to)) => Ok((Some(parse(from)?), parse(to)?)),
+            None => bail!(
+                "invalid delta `{}` in the audit of `{}`, expected `<from> -> <to>`",
+                delta,
+                audit.name
+            ),
+        },
+        _ => bail!(
+            "the audit of `{}` must have either a `version` or a `delta`",
+            audit.name
+        ),
+    }
+}
+
+fn load_toml<T: Default + for<'de> Deserialize<'de>>(path: &std::path::Path) -> CargoResult<T> {
+    if !path.exists() {
+        return Ok(T::default());
+    }
+    let contents = paths::read(path)?;
+    toml::from_str(&contents).with_context(|| format!("failed to parse `{}`", path.display()))
+}
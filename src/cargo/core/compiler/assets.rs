@@ -0,0 +1,156 @@
+//! The `[package.assets]` of the packages, prepared before compiling them.
+//!
+//! Each asset is a file, or the files matching a pattern, checked to exist
+//! and optionally compressed into `target/assets`. Cargo writes a Rust file
+//! with a constant for each asset, which the package includes with
+//! `include!(env!("CARGO_ASSETS"))`:
+//!
+//! ```rust,ignore
+//! pub const LOGO: &[u8] = include_bytes!("/path/to/foo/assets/logo.png");
+//! pub const SHADERS: &[(&str, &[u8])] = &[
+//!     ("blur.wgsl", include_bytes!("/path/to/foo/shaders/blur.wgsl")),
+//! ];
+//! ```
+//!
+//! The path of each asset is also in `CARGO_ASSET_<NAME>`, for packages that
+//! rather use the files themselves. The files and the generated file are only
+//! written when their contents change, and rustc reports the included files
+//! as inputs of the crate, so changing an asset rebuilds it.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{bail, format_err, Context as _};
+use cargo_util::paths;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use super::BuildContext;
+use crate::core::manifest::{Asset, AssetCompression};
+use crate::core::Package;
+use crate::util::{short_hash, CargoResult};
+
+/// The variable with the path of the generated Rust file.
+pub const ASSETS: &str = "CARGO_ASSETS";
+
+/// Prepares the assets of `pkg`, and adds the variables pointing at them to
+/// `env`.
+pub fn add_env(
+    bcx: &BuildContext<'_, '_>,
+    pkg: &Package,
+    env: &mut BTreeMap<String, String>,
+) -> CargoResult<()> {
+    let out_dir = bcx
+        .ws
+        .target_dir()
+        .join("assets")
+        .join(format!("{}-{}", pkg.name(), short_hash(&pkg.package_id())))
+        .into_path_unlocked();
+    let mut source = String::from("// Generated by Cargo from `[package.assets]`.\n");
+    for (name, asset) in pkg.manifest().assets() {
+        let const_name = name.replace('-', "_").to_uppercase();
+        let (dir, files) = prepare(&out_dir, name, asset)
+            .with_context(|| format!("failed to prepare asset `{}` of package `{}`", name, pkg))?;
+        if asset.is_glob {
+            writeln!(source, "pub const {}: &[(&str, &[u8])] = &[", const_name)?;
+            for (rel, path) in &files {
+                writeln!(
+                    source,
+                    "    ({:?}, include_bytes!({:?})),",
+                    rel,
+                    path_str(path)?
+                )?;
+            }
+            writeln!(source, "];")?;
+            env.insert(format!("CARGO_ASSET_{}", const_name), path_str(&dir)?);
+        } else {
+            let path = &files[0].1;
+            writeln!(
+                source,
+                "pub const {}: &[u8] = include_bytes!({:?});",
+                const_name,
+                path_str(path)?
+            )?;
+            env.insert(format!("CARGO_ASSET_{}", const_name), path_str(path)?);
+        }
+    }
+    let generated = out_dir.join("assets.rs");
+    paths::create_dir_all(&out_dir)?;
+    paths::write_if_changed(&generated, source)?;
+    env.insert(ASSETS.to_string(), path_str(&generated)?);
+    Ok(())
+}
+
+/// Finds the files of `asset`, compressing them into `out_dir` if asked to.
+///
+/// Returns the directory the files of a pattern are in, and the files, with
+/// their paths relative to it.
+fn prepare(
+    out_dir: &Path,
+    name: &str,
+    asset: &Asset,
+) -> CargoResult<(PathBuf, Vec<(String, PathBuf)>)> {
+    let (base, sources) = if asset.is_glob {
+        let pattern = path_str(&asset.path)?;
+        let base: PathBuf = asset
+            .path
+            .components()
+            .take_while(|c| match c {
+                Component::Normal(c) => !c
+                    .to_string_lossy()
+                    .contains(|c| matches!(c, '*' | '?' | '[')),
+                _ => true,
+            })
+            .collect();
+        let mut sources = Vec::new();
+        for path in glob::glob(&pattern)? {
+            let path = path?;
+            if path.is_file() {
+                let rel = path.strip_prefix(&base).unwrap_or(&path);
+                let rel = rel
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                sources.push((rel, path));
+            }
+        }
+        if sources.is_empty() {
+            bail!("`{}` matched no files", pattern);
+        }
+        sources.sort();
+        (base, sources)
+    } else {
+        if !asset.path.is_file() {
+            bail!("`{}` is not a file", asset.path.display());
+        }
+        let file_name = asset.path.file_name().unwrap().to_string_lossy();
+        let base = asset.path.parent().unwrap().to_path_buf();
+        (base, vec![(file_name.into_owned(), asset.path.clone())])
+    };
+
+    match asset.compress {
+        None => Ok((base, sources)),
+        Some(AssetCompression::Gzip) => {
+            let dir = out_dir.join(name);
+            let mut files = Vec::new();
+            for (rel, source) in sources {
+                let dst = dir.join(format!("{}.gz", rel));
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+                encoder.write_all(&paths::read_bytes(&source)?)?;
+                paths::create_dir_all(dst.parent().unwrap())?;
+                paths::write_if_changed(&dst, encoder.finish()?)?;
+                files.push((rel, dst));
+            }
+            Ok((dir, files))
+        }
+    }
+}
+
+fn path_str(path: &Path) -> CargoResult<String> {
+    path.to_str()
+        .map(|s| s.to_string())
+        .ok_or_else(|| format_err!("path `{}` is not valid UTF-8", path.display()))
+}
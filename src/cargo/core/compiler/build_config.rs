@@ -1,4 +1,6 @@
 use crate::core::compiler::CompileKind;
+use crate::core::profiles::Sanitizer;
+use crate::util::diagnostic_server::FixSummary;
 use crate::util::interning::InternedString;
 use crate::util::{CargoResult, Config, RustfixDiagnosticServer};
 use anyhow::bail;
@@ -6,6 +8,8 @@ use cargo_util::ProcessBuilder;
 use serde::ser;
 use std::cell::RefCell;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 /// Configuration information for a rustc build.
 #[derive(Debug)]
@@ -31,6 +35,9 @@ pub struct BuildConfig {
     /// A thread used by `cargo fix` to receive messages on a socket regarding
     /// the success/failure of applying fixes.
     pub rustfix_diagnostic_server: RefCell<Option<RustfixDiagnosticServer>>,
+    /// The results of `cargo fix` collected from the messages of the server,
+    /// if it prints a summary of them.
+    pub fix_summary: RefCell<Option<FixSummary>>,
     /// The directory to copy final artifacts to. Note that even if `out_dir` is
     /// set, a copy of artifacts still could be found a `target/(debug\release)`
     /// as usual.
@@ -39,6 +46,34 @@ pub struct BuildConfig {
     pub export_dir: Option<PathBuf>,
     /// `true` to output a future incompatibility report at the end of the build
     pub future_incompat_report: bool,
+    /// `true` to write the pkg-config files of the `cdylib` and `staticlib`
+    /// libraries.
+    pub emit_pc: bool,
+    /// `true` to merge the artifacts built for each Apple target into
+    /// universal binaries.
+    pub lipo: bool,
+    /// `true` to instrument the local packages for source-based code coverage.
+    pub coverage: bool,
+    /// `true` to instrument the code built for the target with the coverage
+    /// feedback needed by libFuzzer.
+    pub fuzz: bool,
+    /// Overrides the `sanitizer` setting of the requested profile.
+    pub sanitizer: Option<Sanitizer>,
+    /// `true` to compile the code built for the target with Miri, so that the
+    /// tests can be interpreted by it.
+    pub miri: bool,
+    /// `true` to install the standard library of the requested targets with
+    /// the toolchain provider when it is missing.
+    pub auto_install_targets: bool,
+    /// How the warnings of the compiler are reported, with `--warnings`.
+    pub warnings: WarningHandling,
+    /// `true` to deny the warnings of the requested packages, with
+    /// `--deny-warnings`.
+    pub deny_warnings: bool,
+    /// Set by `cargo serve-api` when a client cancels the build. No more
+    /// jobs are started once it is set, and the build fails when the running
+    /// ones are finished.
+    pub cancelled: Option<Arc<AtomicBool>>,
 }
 
 impl BuildConfig {
@@ -84,8 +119,19 @@ impl BuildConfig {
             unit_graph: false,
             primary_unit_rustc: None,
             rustfix_diagnostic_server: RefCell::new(None),
+            fix_summary: RefCell::new(None),
             export_dir: None,
             future_incompat_report: false,
+            emit_pc: false,
+            lipo: false,
+            coverage: false,
+            fuzz: false,
+            sanitizer: None,
+            miri: false,
+            auto_install_targets: false,
+            warnings: WarningHandling::Full,
+            deny_warnings: false,
+            cancelled: None,
         })
     }
 
@@ -95,6 +141,13 @@ impl BuildConfig {
         matches!(self.message_format, MessageFormat::Json { .. })
     }
 
+    /// Whether the build was cancelled by the client of `cargo serve-api`.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled
+            .as_ref()
+            .map_or(false, |cancelled| cancelled.load(Ordering::SeqCst))
+    }
+
     pub fn test(&self) -> bool {
         self.mode == CompileMode::Test || self.mode == CompileMode::Bench
     }
@@ -124,6 +177,34 @@ pub enum MessageFormat {
     Short,
 }
 
+/// How the warnings rendered by Cargo are reported, with `--warnings`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WarningHandling {
+    /// Every warning is printed, followed by the count of each unit.
+    Full,
+    /// The warnings are only counted, and summarized by lint and package
+    /// once the build is finished.
+    Summary,
+    /// Every warning is printed, and the build fails if there are any.
+    Deny,
+}
+
+impl std::str::FromStr for WarningHandling {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> CargoResult<WarningHandling> {
+        match s {
+            "full" => Ok(WarningHandling::Full),
+            "summary" => Ok(WarningHandling::Summary),
+            "deny" => Ok(WarningHandling::Deny),
+            _ => bail!(
+                "invalid `--warnings` value `{}`, expected `full`, `summary` or `deny`",
+                s
+            ),
+        }
+    }
+}
+
 /// The general "mode" for what to do.
 /// This is used for two purposes. The commands themselves pass this in to
 /// `compile_ws` to tell it the general execution strategy. This influences
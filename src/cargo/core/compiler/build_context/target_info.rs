@@ -1,5 +1,5 @@
 use crate::core::compiler::{
-    BuildOutput, CompileKind, CompileMode, CompileTarget, Context, CrateType,
+    wasm_component, BuildOutput, CompileKind, CompileMode, CompileTarget, Context, CrateType,
 };
 use crate::core::{Dependency, Target, TargetKind, Workspace};
 use crate::util::config::{Config, StringList, TargetConfig};
@@ -118,6 +118,35 @@ impl FileType {
         format!("{}{}{}", self.prefix, name, self.suffix)
     }
 
+    /// The filename for this FileType when it is uplifted under the name
+    /// given by the `package.artifact-name` template, whose placeholders
+    /// other than `{name}` are already filled in.
+    ///
+    /// Returns `None` if the template doesn't apply to this file: it only
+    /// applies to binaries, `cdylib`s and components, and to their debug
+    /// information files unless those are found by their original name, like
+    /// `.pdb` files.
+    pub fn artifact_filename(&self, target: &Target, template: &str) -> Option<String> {
+        if !matches!(
+            self.crate_type,
+            Some(CrateType::Bin) | Some(CrateType::Cdylib)
+        ) || (self.flavor != FileFlavor::Normal && self.should_replace_hyphens)
+        {
+            return None;
+        }
+        let name = match target.binary_filename() {
+            Some(name) => name,
+            None if self.should_replace_hyphens => target.crate_name(),
+            None => target.name().to_string(),
+        };
+        Some(format!(
+            "{}{}{}",
+            self.prefix,
+            template.replace("{name}", &name),
+            self.suffix
+        ))
+    }
+
     /// Creates a new instance representing a `.rmeta` file.
     pub fn new_rmeta() -> FileType {
         // Note that even binaries use the `lib` prefix.
@@ -282,10 +311,10 @@ impl TargetInfo {
         flavor: FileFlavor,
         target_triple: &str,
     ) -> CargoResult<Option<Vec<FileType>>> {
-        let crate_type = if *crate_type == CrateType::Lib {
-            CrateType::Rlib
-        } else {
-            crate_type.clone()
+        let crate_type = match crate_type {
+            CrateType::Lib => CrateType::Rlib,
+            CrateType::Component => return self.component_file_types(target_triple),
+            _ => crate_type.clone(),
         };
 
         let mut crate_types = self.crate_types.borrow_mut();
@@ -416,6 +445,34 @@ impl TargetInfo {
         Ok(Some(ret))
     }
 
+    /// The files of the `component` crate type: the component itself, and
+    /// the core module linked by rustc as a `cdylib`, which it wraps.
+    fn component_file_types(&self, target_triple: &str) -> CargoResult<Option<Vec<FileType>>> {
+        if !target_triple.starts_with("wasm32-") {
+            return Ok(None);
+        }
+        let core =
+            match self.file_types(&CrateType::Cdylib, FileFlavor::Auxiliary, target_triple)? {
+                Some(types) => types,
+                None => return Ok(None),
+            };
+        let component = FileType {
+            suffix: wasm_component::COMPONENT_SUFFIX.to_string(),
+            prefix: core[0].prefix.clone(),
+            flavor: FileFlavor::Normal,
+            crate_type: Some(CrateType::Component),
+            should_replace_hyphens: true,
+        };
+        Ok(Some(
+            std::iter::once(component)
+                .chain(core.into_iter().map(|ft| FileType {
+                    crate_type: Some(CrateType::Component),
+                    ..ft
+                }))
+                .collect(),
+        ))
+    }
+
     fn discover_crate_type(&self, crate_type: &CrateType) -> CargoResult<Option<(String, String)>> {
         let mut process = self.crate_type_process.clone();
 
@@ -477,6 +534,16 @@ impl TargetInfo {
             };
             let file_types = self.file_types(crate_type, flavor, target_triple)?;
             match file_types {
+                Some(types) if *crate_type == CrateType::Component => {
+                    // The core module of a component is also the output of
+                    // the `cdylib` crate type, if there is one.
+                    let has_cdylib = crate_types.contains(&CrateType::Cdylib);
+                    result.extend(
+                        types
+                            .into_iter()
+                            .filter(|ft| ft.flavor == FileFlavor::Normal || !has_cdylib),
+                    );
+                }
                 Some(types) => {
                     result.extend(types);
                 }
@@ -601,8 +668,13 @@ fn env_args(
     // RUSTFLAGS.
     if requested_kinds != [CompileKind::Host] && kind.is_host() {
         // This is probably a build script or plugin and we're
-        // compiling with --target. In this scenario there are
-        // no rustflags we can apply.
+        // compiling with --target. In this scenario only the flags of the
+        // `[host]` table apply.
+        if name == "RUSTFLAGS" && !config.target_applies_to_host()? {
+            if let Some(rustflags) = config.host_cfg_triple(host_triple)?.rustflags {
+                return Ok(rustflags.val.as_slice().to_vec());
+            }
+        }
         return Ok(Vec::new());
     }
 
@@ -704,7 +776,11 @@ impl<'cfg> RustcTargetData<'cfg> {
         let rustc = config.load_global_rustc(Some(ws))?;
         let mut target_config = HashMap::new();
         let mut target_info = HashMap::new();
-        let target_applies_to_host = config.target_applies_to_host()?;
+        // Without `--target`, the build scripts and proc-macros are built
+        // with the same kind as everything else, so the `[host]` table only
+        // applies to cross builds.
+        let target_applies_to_host =
+            config.target_applies_to_host()? || requested_kinds.iter().any(CompileKind::is_host);
         let host_info = TargetInfo::new(config, requested_kinds, &rustc, CompileKind::Host)?;
         let host_config = if target_applies_to_host {
             config.target_cfg_triple(&rustc.host)?
@@ -0,0 +1,103 @@
+//! The variables describing the build, set for packages with
+//! `package.build-info`.
+//!
+//! * `CARGO_GIT_SHA` is the commit the package was built from, and
+//!   `CARGO_GIT_DIRTY` is `true` if its tracked files had uncommitted
+//!   changes. They are only set if the package is in a git repository, or
+//!   comes from a git source.
+//! * `CARGO_BUILD_TIMESTAMP` is the time of the build in seconds since the
+//!   Unix epoch. With `timestamp = "commit"`, it is the time of the commit
+//!   instead, so that rebuilding the same commit gives the same result. The
+//!   `SOURCE_DATE_EPOCH` environment variable overrides both.
+//!
+//! The variables are tracked like the `[env]` table: a new commit rebuilds
+//! the package. The time of the build is the exception, as it would rebuild
+//! the package every time, so it is the time of the last build that did
+//! something.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::SystemTime;
+
+use anyhow::{bail, Context as _};
+
+use crate::core::manifest::{BuildInfo, BuildTimestamp};
+use crate::core::Package;
+use crate::util::{CargoResult, Config};
+
+pub const GIT_SHA: &str = "CARGO_GIT_SHA";
+pub const GIT_DIRTY: &str = "CARGO_GIT_DIRTY";
+pub const BUILD_TIMESTAMP: &str = "CARGO_BUILD_TIMESTAMP";
+
+/// Adds the variables of the `build_info` of `pkg` to `env`.
+pub fn add_env(
+    config: &Config,
+    pkg: &Package,
+    build_info: BuildInfo,
+    env: &mut BTreeMap<String, String>,
+) -> CargoResult<()> {
+    let source_id = pkg.package_id().source_id();
+    let mut commit_time = None;
+    if source_id.is_git() {
+        if let Some(precise) = source_id.precise() {
+            env.insert(GIT_SHA.to_string(), precise.to_string());
+            env.insert(GIT_DIRTY.to_string(), "false".to_string());
+        }
+    } else if source_id.is_path() {
+        if let Some(repo) = discover(pkg.root()) {
+            let head = repo
+                .head()
+                .and_then(|head| head.peel_to_commit())
+                .with_context(|| {
+                    format!("failed to find the commit of `{}` for `build-info`", pkg)
+                })?;
+            let mut opts = git2::StatusOptions::new();
+            opts.include_untracked(false)
+                .include_ignored(false)
+                .exclude_submodules(true);
+            let dirty = !repo.statuses(Some(&mut opts))?.is_empty();
+            env.insert(GIT_SHA.to_string(), head.id().to_string());
+            env.insert(GIT_DIRTY.to_string(), dirty.to_string());
+            commit_time = Some(head.time().seconds());
+        }
+    }
+
+    let timestamp = match config.env().get("SOURCE_DATE_EPOCH") {
+        Some(epoch) => epoch.parse::<i64>().with_context(|| {
+            format!(
+                "`SOURCE_DATE_EPOCH` is not a number of seconds: `{}`",
+                epoch
+            )
+        })?,
+        None => match build_info.timestamp {
+            BuildTimestamp::Build => SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs() as i64),
+            BuildTimestamp::Commit => match commit_time {
+                Some(time) => time,
+                None => bail!(
+                    "`package.build-info.timestamp` of `{}` is `commit`, but it isn't \
+                     in a git repository, set `SOURCE_DATE_EPOCH` to build it",
+                    pkg
+                ),
+            },
+        },
+    };
+    env.insert(BUILD_TIMESTAMP.to_string(), timestamp.to_string());
+    Ok(())
+}
+
+/// Whether the value of the `CARGO_BUILD_TIMESTAMP` of `build_info` should be
+/// left out of the fingerprints, as it changes with every build.
+pub fn is_volatile_timestamp(config: &Config, build_info: BuildInfo) -> bool {
+    build_info.timestamp == BuildTimestamp::Build && !config.env().contains_key("SOURCE_DATE_EPOCH")
+}
+
+fn discover(path: &Path) -> Option<git2::Repository> {
+    let repo = git2::Repository::discover(path).ok()?;
+    // A bare repository or one without commits has nothing to describe.
+    if repo.is_bare() || repo.head().is_err() {
+        return None;
+    }
+    Some(repo)
+}
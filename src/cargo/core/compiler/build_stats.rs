@@ -0,0 +1,275 @@
+//! Statistics of past builds, recorded with `-Z build-stats`.
+//!
+//! After each build, Cargo appends a line of JSON to
+//! `target/.stats/builds.jsonl` with how long each unit took, whether it was
+//! fresh, the size of its outputs, and the peak memory used by the processes
+//! Cargo ran:
+//!
+//! ```json
+//! {
+//!     "time": 1700000000,
+//!     "profile": "dev",
+//!     "success": true,
+//!     "duration": 12.5,
+//!     "peak_memory": 524288000,
+//!     "units": [
+//!         {"package": "bar 0.1.0 (...)", "target": "lib", "mode": "build", "kind": "x86_64-unknown-linux-gnu", "duration": 3.2, "fresh": false, "size": 123456}
+//!     ]
+//! }
+//! ```
+//!
+//! Only the last `MAX_BUILDS` builds are kept. The peak memory is the
+//! largest resident set of the processes run by Cargo, as reported by the
+//! operating system, and is only known on Unix.
+//!
+//! The durations of the units compiled in earlier builds are the costs
+//! given to the job queue, so that the units on the longest chains of slow
+//! units are started first. `cargo report build-stats` prints the trends and
+//! the slowest units.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use cargo_util::paths;
+use serde::{Deserialize, Serialize};
+
+use super::job_queue::JobId;
+use super::{BuildContext, CompileMode, Context, Unit};
+use crate::core::PackageId;
+use crate::util::CargoResult;
+
+/// The path of the statistics file in the target directory.
+pub const FILE_NAME: &str = ".stats/builds.jsonl";
+
+/// The number of builds kept in the statistics file.
+pub const MAX_BUILDS: usize = 100;
+
+/// The cost of units without statistics when there are none at all. It is the
+/// placeholder cost of the job queue, in no particular unit.
+const DEFAULT_COST: usize = 100;
+
+#[derive(Serialize, Deserialize)]
+pub struct BuildRecord {
+    /// When the build finished, in seconds since the Unix epoch.
+    pub time: u64,
+    pub profile: String,
+    pub success: bool,
+    /// The duration of the build, in seconds.
+    pub duration: f64,
+    /// The peak resident set of the processes run by Cargo, in bytes, if
+    /// any was run.
+    pub peak_memory: Option<u64>,
+    pub units: Vec<UnitRecord>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UnitRecord {
+    pub package: PackageId,
+    /// The target of the unit, like `lib` or `bin "foo"`.
+    pub target: String,
+    pub mode: String,
+    /// The target triple the unit is built for.
+    pub kind: String,
+    /// The time it took to build the unit, in seconds.
+    pub duration: f64,
+    /// Whether the unit was up to date, and not built again.
+    pub fresh: bool,
+    /// The total size of the outputs of the unit, in bytes.
+    pub size: Option<u64>,
+}
+
+impl UnitRecord {
+    /// What identifies the same unit across builds.
+    pub fn key(&self) -> (PackageId, &str, &str, &str) {
+        (self.package, &self.target, &self.mode, &self.kind)
+    }
+}
+
+/// Records the statistics of a build, when `-Z build-stats` is enabled.
+pub struct BuildStats {
+    enabled: bool,
+    start: Instant,
+    /// The units being built, with when they started.
+    active: HashMap<JobId, (Unit, Instant, bool)>,
+    units: Vec<UnitRecord>,
+    /// The durations of the units compiled in earlier builds, in
+    /// milliseconds.
+    costs: HashMap<(PackageId, String, String, String), usize>,
+    /// The cost of the units compiled for the first time.
+    default_cost: usize,
+}
+
+impl BuildStats {
+    pub fn new(bcx: &BuildContext<'_, '_>) -> BuildStats {
+        let enabled = bcx.config.cli_unstable().build_stats;
+        let mut costs = HashMap::new();
+        if enabled {
+            let file = stats_file(bcx.ws.target_dir().as_path_unlocked());
+            // The statistics are only a hint, so a missing or broken file
+            // only means that there is nothing to learn from.
+            for build in load(&file).unwrap_or_default() {
+                for unit in build.units.iter().filter(|unit| !unit.fresh) {
+                    let cost = ((unit.duration * 1000.0) as usize).max(1);
+                    costs.insert(
+                        (
+                            unit.package,
+                            unit.target.clone(),
+                            unit.mode.clone(),
+                            unit.kind.clone(),
+                        ),
+                        cost,
+                    );
+                }
+            }
+        }
+        let mut known: Vec<usize> = costs.values().cloned().collect();
+        known.sort_unstable();
+        BuildStats {
+            enabled,
+            start: Instant::now(),
+            active: HashMap::new(),
+            units: Vec::new(),
+            costs,
+            default_cost: known.get(known.len() / 2).cloned().unwrap_or(DEFAULT_COST),
+        }
+    }
+
+    /// The expected cost of building `unit`, from how long it took in
+    /// earlier builds.
+    pub fn cost(&self, bcx: &BuildContext<'_, '_>, unit: &Unit) -> usize {
+        let key = (
+            unit.pkg.package_id(),
+            unit.target.description_named(),
+            mode_name(unit.mode).to_string(),
+            bcx.target_data.short_name(&unit.kind).to_string(),
+        );
+        self.costs.get(&key).cloned().unwrap_or(self.default_cost)
+    }
+
+    pub fn unit_start(&mut self, id: JobId, unit: &Unit, fresh: bool) {
+        if self.enabled {
+            self.active
+                .insert(id, (unit.clone(), Instant::now(), fresh));
+        }
+    }
+
+    pub fn unit_finished(&mut self, id: JobId, cx: &Context<'_, '_>) -> CargoResult<()> {
+        let (unit, start, fresh) = match self.active.remove(&id) {
+            Some(active) => active,
+            None => return Ok(()),
+        };
+        let duration = start.elapsed().as_secs_f64();
+        let size = if unit.mode.is_run_custom_build() {
+            None
+        } else {
+            Some(
+                cx.outputs(&unit)?
+                    .iter()
+                    .filter_map(|output| output.path.metadata().ok())
+                    .map(|metadata| metadata.len())
+                    .sum(),
+            )
+        };
+        self.units.push(UnitRecord {
+            package: unit.pkg.package_id(),
+            target: unit.target.description_named(),
+            mode: mode_name(unit.mode).to_string(),
+            kind: cx.bcx.target_data.short_name(&unit.kind).to_string(),
+            duration,
+            fresh,
+            size,
+        });
+        Ok(())
+    }
+
+    /// Appends the statistics of the build to the statistics file.
+    pub fn finished(&mut self, bcx: &BuildContext<'_, '_>, success: bool) -> CargoResult<()> {
+        if !self.enabled || bcx.build_config.build_plan {
+            return Ok(());
+        }
+        let mut units = std::mem::take(&mut self.units);
+        units.sort_by(|a, b| a.key().cmp(&b.key()));
+        let record = BuildRecord {
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            profile: bcx.build_config.requested_profile.to_string(),
+            success,
+            duration: self.start.elapsed().as_secs_f64(),
+            peak_memory: peak_memory(),
+            units,
+        };
+
+        let file = stats_file(bcx.ws.target_dir().as_path_unlocked());
+        let mut lines: Vec<String> = if file.exists() {
+            paths::read(&file)?.lines().map(|l| l.to_string()).collect()
+        } else {
+            Vec::new()
+        };
+        lines.push(serde_json::to_string(&record)?);
+        let skip = lines.len().saturating_sub(MAX_BUILDS);
+        let mut contents = lines[skip..].join("\n");
+        contents.push('\n');
+        paths::create_dir_all(file.parent().unwrap())?;
+        paths::write(&file, contents)
+    }
+}
+
+/// The statistics file of the target directory `target_dir`.
+pub fn stats_file(target_dir: &Path) -> PathBuf {
+    target_dir.join(FILE_NAME)
+}
+
+/// Loads the builds recorded in `file`, oldest first.
+pub fn load(file: &Path) -> CargoResult<Vec<BuildRecord>> {
+    let reader = BufReader::new(paths::open(file)?);
+    let mut builds = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            builds.push(serde_json::from_str(&line)?);
+        }
+    }
+    Ok(builds)
+}
+
+fn mode_name(mode: CompileMode) -> &'static str {
+    match mode {
+        CompileMode::Test => "test",
+        CompileMode::Build => "build",
+        CompileMode::Check { .. } => "check",
+        CompileMode::Bench => "bench",
+        CompileMode::Doc { .. } => "doc",
+        CompileMode::Doctest => "doctest",
+        CompileMode::Docscrape => "docscrape",
+        CompileMode::RunCustomBuild => "run-custom-build",
+    }
+}
+
+/// The peak resident set of the child processes of Cargo, in bytes.
+#[cfg(unix)]
+fn peak_memory() -> Option<u64> {
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getrusage(libc::RUSAGE_CHILDREN, &mut usage) } != 0 {
+        return None;
+    }
+    let max_rss = usage.ru_maxrss as u64;
+    if max_rss == 0 {
+        // No process was run, like when everything was fresh.
+        return None;
+    }
+    // Linux and the BSDs report kilobytes, macOS reports bytes.
+    if cfg!(target_os = "macos") {
+        Some(max_rss)
+    } else {
+        Some(max_rss * 1024)
+    }
+}
+
+#[cfg(not(unix))]
+fn peak_memory() -> Option<u64> {
+    None
+}
@@ -0,0 +1,194 @@
+//! The `--check-cfg` arguments passed to rustc.
+//!
+//! The cfgs a package expects are declared by:
+//!
+//! * its features,
+//! * `package.expected-cfgs`, the cfgs `[config-flags]` can set for it,
+//! * the `check-cfg` list of `[lints.rust.unexpected_cfgs]`, for the other
+//!   custom cfgs of the package,
+//! * the `cargo:rustc-check-cfg` instructions of its build script, for the
+//!   cfgs it sets with `cargo:rustc-cfg`.
+//!
+//! rustc checks every cfg once any is declared, so the cfgs of a package are
+//! only checked once it declares one of its own, or for every package with
+//! `-Z check-cfg`. All of the declarations above are then passed together.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use cargo_platform::Cfg;
+
+use super::{BuildContext, Unit};
+use crate::util::Config;
+
+/// Where an expected cfg is declared.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Origin {
+    Features,
+    ExpectedCfgs,
+    Lints,
+    BuildScript,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Origin::Features => "features",
+            Origin::ExpectedCfgs => "package.expected-cfgs",
+            Origin::Lints => "lints.rust.unexpected_cfgs",
+            Origin::BuildScript => "build script",
+        })
+    }
+}
+
+/// A `cfg(...)` specification passed to `--check-cfg`.
+#[derive(Clone, Debug)]
+pub struct ExpectedCfg {
+    pub spec: String,
+    pub origin: Origin,
+}
+
+/// The cfgs a package expects, as collected by a build.
+#[derive(Clone, Debug, Default)]
+pub struct ExpectedCfgs {
+    /// Whether rustc checked the cfgs of the package.
+    pub checked: bool,
+    pub cfgs: Vec<ExpectedCfg>,
+}
+
+/// Whether the cfgs of `unit` are checked before its build script runs.
+pub fn is_checked(config: &Config, unit: &Unit) -> bool {
+    if unit.is_std || unit.target.is_custom_build() {
+        return false;
+    }
+    config.cli_unstable().check_cfg
+        || unit.pkg.manifest().expected_cfgs().is_some()
+        || lint_check_cfgs(unit).is_some()
+}
+
+/// The arguments of `unit` for the cfgs it expects and the level of the
+/// `unexpected_cfgs` lint.
+pub fn args(bcx: &BuildContext<'_, '_>, unit: &Unit) -> Vec<String> {
+    let mut args = Vec::new();
+    if unit.is_std || unit.target.is_custom_build() {
+        return args;
+    }
+    if let Some(level) = lint_level(unit) {
+        args.push(format!("--{}", level));
+        args.push("unexpected_cfgs".to_string());
+    }
+    if is_checked(bcx.config, unit) {
+        args.extend(to_args(&declared(unit)));
+    }
+    args
+}
+
+/// The arguments `unit` still needs when its build script declares cfgs,
+/// which are the declarations of its manifest if its cfgs aren't checked
+/// already.
+pub fn unchecked_args(bcx: &BuildContext<'_, '_>, unit: &Unit) -> Vec<String> {
+    if unit.is_std || unit.target.is_custom_build() || is_checked(bcx.config, unit) {
+        Vec::new()
+    } else {
+        to_args(&declared(unit))
+    }
+}
+
+/// The arguments for the cfgs declared by a build script with
+/// `cargo:rustc-check-cfg`, after the [`unchecked_args`] of the unit.
+pub fn build_script_args(check_cfgs: &[String], unchecked: &[String]) -> Vec<String> {
+    if check_cfgs.is_empty() {
+        return Vec::new();
+    }
+    let mut args = unchecked.to_vec();
+    for spec in check_cfgs {
+        args.push("--check-cfg".to_string());
+        args.push(spec.clone());
+    }
+    args
+}
+
+/// The cfgs `unit` expects, including the ones declared by its build script.
+pub fn expected(config: &Config, unit: &Unit, check_cfgs: &[String]) -> ExpectedCfgs {
+    let mut cfgs = declared(unit);
+    cfgs.extend(check_cfgs.iter().map(|spec| ExpectedCfg {
+        spec: spec.clone(),
+        origin: Origin::BuildScript,
+    }));
+    ExpectedCfgs {
+        checked: is_checked(config, unit) || !check_cfgs.is_empty(),
+        cfgs,
+    }
+}
+
+/// The cfgs the manifest of the package of `unit` declares.
+fn declared(unit: &Unit) -> Vec<ExpectedCfg> {
+    let features: Vec<String> = unit
+        .pkg
+        .summary()
+        .features()
+        .keys()
+        .map(|feature| format!("\"{}\"", feature))
+        .collect();
+    let mut cfgs = vec![ExpectedCfg {
+        spec: format!("cfg(feature, values({}))", features.join(", ")),
+        origin: Origin::Features,
+    }];
+
+    // The values of each name, where `None` is the name without a value.
+    let mut names: BTreeMap<&str, Vec<Option<&str>>> = BTreeMap::new();
+    for cfg in unit.pkg.manifest().expected_cfgs().unwrap_or_default() {
+        match cfg {
+            Cfg::Name(name) => names.entry(name).or_default().push(None),
+            Cfg::KeyPair(key, value) => names.entry(key).or_default().push(Some(value)),
+        }
+    }
+    for (name, values) in names {
+        let values: Vec<String> = values
+            .iter()
+            .map(|value| match value {
+                Some(value) => format!("\"{}\"", value),
+                None => "none()".to_string(),
+            })
+            .collect();
+        let spec = if values == ["none()"] {
+            format!("cfg({})", name)
+        } else {
+            format!("cfg({}, values({}))", name, values.join(", "))
+        };
+        cfgs.push(ExpectedCfg {
+            spec,
+            origin: Origin::ExpectedCfgs,
+        });
+    }
+
+    cfgs.extend(
+        lint_check_cfgs(unit)
+            .unwrap_or_default()
+            .iter()
+            .map(|spec| ExpectedCfg {
+                spec: spec.clone(),
+                origin: Origin::Lints,
+            }),
+    );
+    cfgs
+}
+
+fn to_args(cfgs: &[ExpectedCfg]) -> Vec<String> {
+    let mut args = Vec::new();
+    for cfg in cfgs {
+        args.push("--check-cfg".to_string());
+        args.push(cfg.spec.clone());
+    }
+    args
+}
+
+fn lint_check_cfgs(unit: &Unit) -> Option<&[String]> {
+    let lint = unit.pkg.manifest().original().lints()?.unexpected_cfgs()?;
+    lint.check_cfg.as_deref()
+}
+
+fn lint_level(unit: &Unit) -> Option<&str> {
+    let lint = unit.pkg.manifest().original().lints()?.unexpected_cfgs()?;
+    lint.level.as_deref()
+}
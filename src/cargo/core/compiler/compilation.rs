@@ -1,14 +1,19 @@
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::env;
 use std::ffi::{OsStr, OsString};
 use std::path::PathBuf;
 
+use anyhow::Context as _;
 use cargo_platform::CfgExpr;
 use cargo_util::{paths, ProcessBuilder};
 
 use super::BuildContext;
-use crate::core::compiler::{CompileKind, Metadata, Unit};
-use crate::core::Package;
+use crate::core::compiler::check_cfg::ExpectedCfgs;
+use crate::core::compiler::remote_runner::RemoteRunner;
+use crate::core::compiler::wasm_runner::WasmRunner;
+use crate::core::compiler::{assets, build_info};
+use crate::core::compiler::{miri, CompileKind, Metadata, TestResultCache, Unit};
+use crate::core::{Package, PackageId};
 use crate::util::{config, CargoResult, Config};
 
 /// Structure with enough information to run `rustdoc --test`.
@@ -45,6 +50,9 @@ pub struct Compilation<'cfg> {
     /// An array of all tests created during this compilation.
     pub tests: Vec<UnitOutput>,
 
+    /// Where the results of running each of the `tests` are cached.
+    pub test_results: HashMap<Unit, TestResultCache>,
+
     /// An array of all binaries created.
     pub binaries: Vec<UnitOutput>,
 
@@ -82,6 +90,13 @@ pub struct Compilation<'cfg> {
     /// `RunCustomBuild` unit that generated these env vars.
     pub extra_env: HashMap<Metadata, Vec<(String, String)>>,
 
+    /// The variables of the `[env]` tables of the packages, with their values
+    /// read once for the whole build.
+    package_env: HashMap<PackageId, BTreeMap<String, String>>,
+
+    /// The cfgs each package expects, for `cargo report check-cfg`.
+    pub expected_cfgs: BTreeMap<PackageId, ExpectedCfgs>,
+
     /// Libraries to test with rustdoc.
     pub to_doc_test: Vec<Doctest>,
 
@@ -99,6 +114,15 @@ pub struct Compilation<'cfg> {
     primary_rustc_process: Option<ProcessBuilder>,
 
     target_runners: HashMap<CompileKind, Option<(PathBuf, Vec<String>)>>,
+
+    /// Runtimes for the WebAssembly targets without a configured runner.
+    wasm_runners: HashMap<CompileKind, WasmRunner>,
+
+    /// Runners of the targets whose `runner-protocol` is `"remote"`.
+    remote_runners: HashMap<CompileKind, RemoteRunner>,
+
+    /// The `miri` executable, if the tests are to be run in Miri.
+    pub miri: Option<PathBuf>,
 }
 
 impl<'cfg> Compilation<'cfg> {
@@ -116,6 +140,15 @@ impl<'cfg> Compilation<'cfg> {
             }
         }
 
+        let mut target_runners = bcx
+            .build_config
+            .requested_kinds
+            .iter()
+            .chain(Some(&CompileKind::Host))
+            .map(|kind| Ok((*kind, target_runner(bcx, *kind)?)))
+            .collect::<CargoResult<HashMap<_, _>>>()?;
+        let remote_runners = remote_runners(bcx, &mut target_runners)?;
+
         Ok(Compilation {
             // TODO: deprecated; remove.
             native_dirs: BTreeSet::new(),
@@ -137,26 +170,39 @@ impl<'cfg> Compilation<'cfg> {
                 })
                 .collect(),
             tests: Vec::new(),
+            test_results: HashMap::new(),
             binaries: Vec::new(),
             cdylibs: Vec::new(),
             root_crate_names: Vec::new(),
             extra_env: HashMap::new(),
+            package_env: package_env(bcx)?,
+            expected_cfgs: BTreeMap::new(),
             to_doc_test: Vec::new(),
             config: bcx.config,
             host: bcx.host_triple().to_string(),
             rustc_process: rustc,
             rustc_workspace_wrapper_process,
             primary_rustc_process,
-            target_runners: bcx
-                .build_config
-                .requested_kinds
-                .iter()
-                .chain(Some(&CompileKind::Host))
-                .map(|kind| Ok((*kind, target_runner(bcx, *kind)?)))
-                .collect::<CargoResult<HashMap<_, _>>>()?,
+            wasm_runners: wasm_runners(bcx, &target_runners, &remote_runners)?,
+            target_runners,
+            remote_runners,
+            miri: if bcx.build_config.miri {
+                let host_info = bcx.target_data.info(CompileKind::Host);
+                Some(miri::find_miri(
+                    &host_info.sysroot_host_libdir.with_file_name("bin"),
+                )?)
+            } else {
+                None
+            },
         })
     }
 
+    /// Returns the directory with the tools shipped with the host toolchain,
+    /// such as the ones of the `llvm-tools-preview` component.
+    pub fn sysroot_host_bindir(&self) -> PathBuf {
+        self.sysroot_host_libdir.with_file_name("bin")
+    }
+
     /// Returns a [`ProcessBuilder`] for running `rustc`.
     ///
     /// `is_primary` is true if this is a "primary package", which means it
@@ -170,7 +216,9 @@ impl<'cfg> Compilation<'cfg> {
         is_primary: bool,
         is_workspace: bool,
     ) -> CargoResult<ProcessBuilder> {
-        let rustc = if is_primary && self.primary_rustc_process.is_some() {
+        let rustc = if let (Some(miri), false) = (&self.miri, unit.kind.is_host()) {
+            miri::rustc_process(miri)
+        } else if is_primary && self.primary_rustc_process.is_some() {
             self.primary_rustc_process.clone().unwrap()
         } else if is_workspace {
             self.rustc_workspace_wrapper_process.clone()
@@ -194,7 +242,7 @@ impl<'cfg> Compilation<'cfg> {
         unit.target.edition().cmd_edition_arg(&mut p);
 
         for crate_type in unit.target.rustc_crate_types() {
-            p.arg("--crate-type").arg(crate_type.as_str());
+            p.arg("--crate-type").arg(crate_type.rustc_crate_type());
         }
 
         Ok(p)
@@ -224,6 +272,18 @@ impl<'cfg> Compilation<'cfg> {
         self.target_runners.get(&kind).and_then(|x| x.as_ref())
     }
 
+    /// Gets the WebAssembly runtime used to run the executables of the given
+    /// kind, when `-Z wasm-runner` found one.
+    pub fn wasm_runner(&self, kind: CompileKind) -> Option<&WasmRunner> {
+        self.wasm_runners.get(&kind)
+    }
+
+    /// Gets the remote runner the executables of the given kind are run with,
+    /// in place of [`Compilation::target_process`] running them.
+    pub fn remote_runner(&self, kind: CompileKind) -> Option<&RemoteRunner> {
+        self.remote_runners.get(&kind)
+    }
+
     /// Returns a [`ProcessBuilder`] appropriate for running a process for the
     /// target platform. This is typically used for `cargo run` and `cargo
     /// test`.
@@ -238,6 +298,10 @@ impl<'cfg> Compilation<'cfg> {
         pkg: &Package,
         script_meta: Option<Metadata>,
     ) -> CargoResult<ProcessBuilder> {
+        if let Some(runner) = self.wasm_runner(kind) {
+            let native = self.fill_env(ProcessBuilder::new(&cmd), pkg, script_meta, kind, false)?;
+            return Ok(runner.process(cmd.as_ref(), &native));
+        }
         let builder = if let Some((runner, args)) = self.target_runner(kind) {
             let mut builder = ProcessBuilder::new(runner);
             builder.args(args);
@@ -249,6 +313,11 @@ impl<'cfg> Compilation<'cfg> {
         self.fill_env(builder, pkg, script_meta, kind, false)
     }
 
+    /// The variables of the `[env]` tables that apply to compiling `pkg`.
+    pub fn package_env(&self, pkg: PackageId) -> Option<&BTreeMap<String, String>> {
+        self.package_env.get(&pkg)
+    }
+
     /// Prepares a new process with an appropriate environment to run against
     /// the artifacts produced by the build process.
     ///
@@ -345,6 +414,18 @@ impl<'cfg> Compilation<'cfg> {
             .env("CARGO_PKG_AUTHORS", &pkg.authors().join(":"))
             .cwd(pkg.root());
 
+        // Apply the `[env]` of the manifests, which takes precedence over the
+        // config and the environment.
+        if is_rustc_tool {
+            if let Some(env) = self.package_env.get(&pkg.package_id()) {
+                for (key, value) in env {
+                    if !cmd.get_envs().contains_key(key) {
+                        cmd.env(key, value);
+                    }
+                }
+            }
+        }
+
         // Apply any environment variables from the config
         for (key, value) in self.config.env_config()?.iter() {
             // never override a value that has already been set by cargo
@@ -376,6 +457,68 @@ fn fill_rustc_tool_env(mut cmd: ProcessBuilder, unit: &Unit) -> ProcessBuilder {
     cmd
 }
 
+/// Looks for a WebAssembly runtime for every requested kind which has no
+/// configured runner.
+fn wasm_runners(
+    bcx: &BuildContext<'_, '_>,
+    target_runners: &HashMap<CompileKind, Option<(PathBuf, Vec<String>)>>,
+    remote_runners: &HashMap<CompileKind, RemoteRunner>,
+) -> CargoResult<HashMap<CompileKind, WasmRunner>> {
+    let mut runners = HashMap::new();
+    if !bcx.config.cli_unstable().wasm_runner {
+        return Ok(runners);
+    }
+    for (kind, runner) in target_runners {
+        if runner.is_some() || remote_runners.contains_key(kind) {
+            continue;
+        }
+        let target = bcx.target_data.short_name(kind);
+        let cfg = bcx.target_data.info(*kind).cfg();
+        if let Some(runner) = WasmRunner::discover(bcx.config, target, cfg)? {
+            runners.insert(*kind, runner);
+        }
+    }
+    Ok(runners)
+}
+
+/// Takes the runners of the targets whose `runner-protocol` is `"remote"` out
+/// of `target_runners`, as they aren't run in front of each program.
+fn remote_runners(
+    bcx: &BuildContext<'_, '_>,
+    target_runners: &mut HashMap<CompileKind, Option<(PathBuf, Vec<String>)>>,
+) -> CargoResult<HashMap<CompileKind, RemoteRunner>> {
+    let mut runners = HashMap::new();
+    for (kind, runner) in target_runners.iter_mut() {
+        let target = bcx.target_data.short_name(kind);
+        let key = format!("target.{}.runner-protocol", target);
+        match bcx.config.get::<Option<String>>(&key)?.as_deref() {
+            None | Some("exec") => continue,
+            Some("remote") => {}
+            Some(other) => anyhow::bail!(
+                "unknown runner protocol `{}`, expected `exec` or `remote`, set in `{}`",
+                other,
+                key
+            ),
+        }
+        if !bcx.config.cli_unstable().remote_runner {
+            anyhow::bail!("`{}` requires `-Z remote-runner`", key);
+        }
+        let (path, args) = match runner.take() {
+            Some(runner) => runner,
+            None => anyhow::bail!(
+                "`{}` is `remote`, but no `target.{}.runner` is configured",
+                key,
+                target
+            ),
+        };
+        let jobs = bcx
+            .config
+            .get::<Option<u32>>(&format!("target.{}.runner-jobs", target))?;
+        runners.insert(*kind, RemoteRunner::new(path, args, jobs));
+    }
+    Ok(runners)
+}
+
 fn target_runner(
     bcx: &BuildContext<'_, '_>,
     kind: CompileKind,
@@ -417,3 +560,42 @@ fn target_runner(
         )
     }))
 }
+
+/// Reads the variables of the `[env]` tables of the packages being built. The
+/// `[env]` of the workspace root applies to all the members, below their own.
+/// The variables of `package.build-info` and `[package.assets]` are added to
+/// them.
+fn package_env(
+    bcx: &BuildContext<'_, '_>,
+) -> CargoResult<HashMap<PackageId, BTreeMap<String, String>>> {
+    let mut result = HashMap::new();
+    for unit in bcx.unit_graph.keys() {
+        let pkg = &unit.pkg;
+        if result.contains_key(&pkg.package_id()) {
+            continue;
+        }
+        let root_env = if bcx.ws.is_member(pkg) {
+            Some(bcx.ws.root_env())
+        } else {
+            None
+        };
+        let mut env = BTreeMap::new();
+        for (key, value) in root_env.into_iter().flatten().chain(pkg.manifest().env()) {
+            let value = value.resolve().with_context(|| {
+                format!(
+                    "failed to read `[env]` variable `{}` of package `{}`",
+                    key, pkg
+                )
+            })?;
+            env.insert(key.clone(), value);
+        }
+        if let Some(build_info) = pkg.manifest().build_info() {
+            build_info::add_env(bcx.config, pkg, build_info, &mut env)?;
+        }
+        if !pkg.manifest().assets().is_empty() {
+            assets::add_env(bcx, pkg, &mut env)?;
+        }
+        result.insert(pkg.package_id(), env);
+    }
+    Ok(result)
+}
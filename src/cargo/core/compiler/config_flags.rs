@@ -0,0 +1,108 @@
+//! The cfgs set for packages with `[config-flags]`.
+//!
+//! The root manifest of the workspace can set cfgs for any package of the
+//! dependency graph, by name, instead of passing `--cfg` to every crate with
+//! `RUSTFLAGS`:
+//!
+//! ```toml
+//! [config-flags]
+//! tokio = ["tokio_unstable"]
+//! mylib = ['backend = "simd"']
+//! ```
+//!
+//! A package declares the cfgs it can be configured with in
+//! `package.expected-cfgs`. The cfgs set for it must be among them, so that a
+//! typo is an error instead of being silently ignored, and rustc is passed
+//! `--check-cfg` for them, so that the `#[cfg]`s of the package are checked
+//! too.
+
+use std::collections::HashSet;
+
+use anyhow::bail;
+use cargo_platform::Cfg;
+
+use super::unit_graph::UnitGraph;
+use super::Unit;
+use crate::core::{Resolve, Workspace};
+use crate::util::errors::CargoResult;
+use crate::util::lev_distance::closest_msg;
+
+/// Checks the cfgs of `[config-flags]` against the packages they are set for.
+pub fn validate(ws: &Workspace<'_>, resolve: &Resolve, unit_graph: &UnitGraph) -> CargoResult<()> {
+    let flags = ws.root_config_flags();
+    if flags.is_empty() {
+        return Ok(());
+    }
+    for name in flags.keys() {
+        if !resolve.iter().any(|id| id.name() == *name) {
+            ws.config().shell().warn(format!(
+                "`[config-flags]` sets cfgs for `{}`, which is not in the dependency graph",
+                name
+            ))?;
+        }
+    }
+
+    let mut checked = HashSet::new();
+    let mut units: Vec<_> = unit_graph.keys().filter(|unit| !unit.is_std).collect();
+    units.sort_unstable();
+    for unit in units {
+        let pkg = &unit.pkg;
+        let cfgs = match flags.get(&pkg.name()) {
+            Some(cfgs) => cfgs,
+            None => continue,
+        };
+        if !checked.insert(pkg.package_id()) {
+            continue;
+        }
+        let expected = match pkg.manifest().expected_cfgs() {
+            Some(expected) => expected,
+            None => {
+                ws.config().shell().warn(format!(
+                    "package `{}` doesn't declare `package.expected-cfgs`, \
+                     the cfgs set for it in `[config-flags]` are not checked",
+                    pkg.package_id()
+                ))?;
+                continue;
+            }
+        };
+        for cfg in cfgs {
+            if !expected.contains(cfg) {
+                let names: Vec<String> = expected.iter().map(|cfg| cfg.to_string()).collect();
+                let cfg = cfg.to_string();
+                bail!(
+                    "package `{}` doesn't expect the cfg `{}` set for it in `[config-flags]`, \
+                     the cfgs it expects are: {}{}",
+                    pkg.package_id(),
+                    cfg,
+                    if names.is_empty() {
+                        "none".to_string()
+                    } else {
+                        names.join(", ")
+                    },
+                    closest_msg(&cfg, names.iter(), |name| name.as_str())
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The `--cfg` arguments of `unit` for the cfgs set for its package.
+pub fn cfg_args(ws: &Workspace<'_>, unit: &Unit) -> Vec<String> {
+    if unit.is_std || unit.target.is_custom_build() {
+        return Vec::new();
+    }
+    let cfgs = match ws.root_config_flags().get(&unit.pkg.name()) {
+        Some(cfgs) => cfgs,
+        None => return Vec::new(),
+    };
+    let mut args = Vec::new();
+    for cfg in cfgs {
+        args.push("--cfg".to_string());
+        args.push(match cfg {
+            Cfg::Name(name) => name.clone(),
+            Cfg::KeyPair(key, value) => format!("{}=\"{}\"", key, value),
+        });
+    }
+    args
+}
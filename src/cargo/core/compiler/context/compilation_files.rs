@@ -9,7 +9,10 @@ use lazycell::LazyCell;
 use log::info;
 
 use super::{BuildContext, CompileKind, Context, FileFlavor, Layout};
-use crate::core::compiler::{CompileMode, CompileTarget, CrateType, FileType, Unit};
+use crate::core::compiler::{
+    separate_debuginfo, CompileMode, CompileTarget, CrateType, FileType, Unit,
+};
+use crate::core::profiles::Strip;
 use crate::core::{Target, TargetKind, Workspace};
 use crate::util::{self, CargoResult, StableHasher};
 
@@ -197,7 +200,7 @@ impl<'a, 'cfg: 'a> CompilationFiles<'a, 'cfg> {
             self.layout(unit.kind).doc().to_path_buf()
         } else if unit.mode.is_doc_test() {
             panic!("doc tests do not have an out dir");
-        } else if unit.target.is_custom_build() {
+        } else if unit.target.is_build_program() {
             self.build_script_dir(unit)
         } else if unit.target.is_example() {
             self.layout(unit.kind).examples().to_path_buf()
@@ -277,10 +280,11 @@ impl<'a, 'cfg: 'a> CompilationFiles<'a, 'cfg> {
         self.fingerprint_file_path(unit, "output-")
     }
 
-    /// Returns the directory where a compiled build script is stored.
+    /// Returns the directory where a compiled build script or codegen step is
+    /// stored.
     /// `/path/to/target/{debug,release}/build/PKG-HASH`
     pub fn build_script_dir(&self, unit: &Unit) -> PathBuf {
-        assert!(unit.target.is_custom_build());
+        assert!(unit.target.is_build_program());
         assert!(!unit.mode.is_run_custom_build());
         assert!(self.metas.contains_key(unit));
         let dir = self.pkg_dir(unit);
@@ -288,10 +292,10 @@ impl<'a, 'cfg: 'a> CompilationFiles<'a, 'cfg> {
     }
 
     /// Returns the directory where information about running a build script
-    /// is stored.
+    /// or codegen step is stored.
     /// `/path/to/target/{debug,release}/build/PKG-HASH`
     pub fn build_script_run_dir(&self, unit: &Unit) -> PathBuf {
-        assert!(unit.target.is_custom_build());
+        assert!(unit.target.is_build_program());
         assert!(unit.mode.is_run_custom_build());
         let dir = self.pkg_dir(unit);
         self.layout(unit.kind).build().join(dir)
@@ -349,7 +353,13 @@ impl<'a, 'cfg: 'a> CompilationFiles<'a, 'cfg> {
     ///
     /// Returns `None` if the unit shouldn't be uplifted (for example, a
     /// dependent rlib).
-    fn uplift_to(&self, unit: &Unit, file_type: &FileType, from_path: &Path) -> Option<PathBuf> {
+    fn uplift_to(
+        &self,
+        unit: &Unit,
+        file_type: &FileType,
+        from_path: &Path,
+        triple: &str,
+    ) -> Option<PathBuf> {
         // Tests, check, doc, etc. should not be uplifted.
         if unit.mode != CompileMode::Build || file_type.flavor == FileFlavor::Rmeta {
             return None;
@@ -365,18 +375,19 @@ impl<'a, 'cfg: 'a> CompilationFiles<'a, 'cfg> {
         //   historically how Cargo has operated. This is primarily useful to
         //   give the user access to staticlibs and cdylibs.
         if !unit.target.is_bin()
-            && !unit.target.is_custom_build()
+            && !unit.target.is_build_program()
             && file_type.crate_type != Some(CrateType::Dylib)
             && !self.roots.contains(unit)
         {
             return None;
         }
 
-        let filename = file_type.uplift_filename(&unit.target);
+        let filename = artifact_filename(unit, file_type, triple)
+            .unwrap_or_else(|| file_type.uplift_filename(&unit.target));
         let uplift_path = if unit.target.is_example() {
             // Examples live in their own little world.
             self.layout(unit.kind).examples().join(filename)
-        } else if unit.target.is_custom_build() {
+        } else if unit.target.is_build_program() {
             self.build_script_dir(unit).join(filename)
         } else {
             self.layout(unit.kind).dest().join(filename)
@@ -483,8 +494,9 @@ impl<'a, 'cfg: 'a> CompilationFiles<'a, 'cfg> {
 
             // If, the `different_binary_name` feature is enabled, the name of the hardlink will
             // be the name of the binary provided by the user in `Cargo.toml`.
-            let hardlink = self.uplift_to(unit, &file_type, &path);
-            let export_path = if unit.target.is_custom_build() {
+            let hardlink = self.uplift_to(unit, &file_type, &path, triple);
+            // With `--lipo`, the universal binaries are exported instead.
+            let export_path = if unit.target.is_build_program() || bcx.build_config.lipo {
                 None
             } else {
                 self.export_dir.as_ref().and_then(|export_dir| {
@@ -493,17 +505,44 @@ impl<'a, 'cfg: 'a> CompilationFiles<'a, 'cfg> {
                         .map(|hardlink| export_dir.join(hardlink.file_name().unwrap()))
                 })
             };
+            let separate_debuginfo = unit.profile.strip == Strip::Separate
+                && file_type.flavor == FileFlavor::Normal
+                && file_type
+                    .crate_type
+                    .as_ref()
+                    .map_or(false, |ct| separate_debuginfo::uses_objcopy(triple, ct));
+            let debug_output = separate_debuginfo.then(|| OutputFile {
+                path: separate_debuginfo::debug_path(&path),
+                hardlink: hardlink.as_deref().map(separate_debuginfo::debug_path),
+                export_path: export_path.as_deref().map(separate_debuginfo::debug_path),
+                flavor: FileFlavor::DebugInfo,
+            });
             outputs.push(OutputFile {
                 path,
                 hardlink,
                 export_path,
                 flavor: file_type.flavor,
             });
+            outputs.extend(debug_output);
         }
         Ok(outputs)
     }
 }
 
+/// Returns the uplifted filename given by the `package.artifact-name`
+/// template, if the package has one which applies to `file_type`.
+fn artifact_filename(unit: &Unit, file_type: &FileType, triple: &str) -> Option<String> {
+    let template = unit.pkg.manifest().original().artifact_name()?;
+    if unit.target.is_build_program() || unit.target.is_example() {
+        return None;
+    }
+    let template = template
+        .replace("{version}", &unit.pkg.version().to_string())
+        .replace("{target}", triple)
+        .replace("{profile}", &unit.profile.name);
+    file_type.artifact_filename(&unit.target, &template)
+}
+
 fn metadata_of<'a>(
     unit: &Unit,
     cx: &Context<'_, '_>,
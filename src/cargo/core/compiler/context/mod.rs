@@ -13,9 +13,9 @@ use jobserver::Client;
 
 use super::build_plan::BuildPlan;
 use super::custom_build::{self, BuildDeps, BuildScriptOutputs, BuildScripts};
-use super::fingerprint::Fingerprint;
+use super::fingerprint::{Fingerprint, TestResultCache};
 use super::job_queue::JobQueue;
-use super::layout::Layout;
+use super::layout::{self, Layout};
 use super::lto::Lto;
 use super::unit_graph::UnitDep;
 use super::{
@@ -196,7 +196,10 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
                     self.compilation
                         .tests
                         .push(self.unit_output(unit, &output.path));
-                } else if unit.target.is_executable() {
+                    self.compilation
+                        .test_results
+                        .insert(unit.clone(), TestResultCache::new(&self, unit));
+                } else if unit.target.is_executable() || unit.target.is_fuzz() {
                     self.compilation
                         .binaries
                         .push(self.unit_output(unit, bindst));
@@ -213,7 +216,7 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
             // environment variables.
             if unit.target.is_lib() {
                 for dep in &self.bcx.unit_graph[unit] {
-                    if dep.unit.mode.is_run_custom_build() {
+                    if dep.unit.mode.is_run_custom_build() && dep.unit.target.is_custom_build() {
                         let out_dir = self
                             .files()
                             .build_script_out_dir(&dep.unit)
@@ -239,6 +242,11 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
                     args.push("--cfg".into());
                     args.push(format!("feature=\"{}\"", feature).into());
                 }
+                args.extend(
+                    compiler::config_flags::cfg_args(self.bcx.ws, unit)
+                        .into_iter()
+                        .map(Into::into),
+                );
                 let script_meta = self.find_build_script_metadata(unit);
                 if let Some(meta) = script_meta {
                     if let Some(output) = self.build_script_outputs.lock().unwrap().get(meta) {
@@ -278,6 +286,26 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
             super::output_depinfo(&mut self, unit)?;
         }
 
+        // Collect the cfgs each package expects.
+        let outputs = self.build_script_outputs.lock().unwrap();
+        for unit in self.bcx.unit_graph.keys() {
+            if unit.is_std || unit.target.is_custom_build() {
+                continue;
+            }
+            let check_cfgs = self
+                .find_build_script_metadata(unit)
+                .and_then(|meta| outputs.get(meta))
+                .map(|output| output.check_cfgs.as_slice())
+                .unwrap_or_default();
+            self.compilation
+                .expected_cfgs
+                .entry(unit.pkg.package_id())
+                .or_insert_with(|| {
+                    compiler::check_cfg::expected(self.bcx.config, unit, check_cfgs)
+                });
+        }
+        drop(outputs);
+
         for (script_meta, output) in self.build_script_outputs.lock().unwrap().iter() {
             self.compilation
                 .extra_env
@@ -289,6 +317,18 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
                 self.compilation.native_dirs.insert(dir.clone());
             }
         }
+
+        if !build_plan {
+            if self.bcx.config.cli_unstable().link_graph {
+                super::link_graph::record(&mut self)?;
+            }
+            if self.bcx.config.cli_unstable().doc_index && self.bcx.build_config.mode.is_doc() {
+                super::doc_index::record(&mut self)?;
+            }
+            super::headers::generate(&mut self)?;
+            super::universal::merge(&mut self)?;
+            super::package_hook::run(&mut self)?;
+        }
         Ok(self.compilation)
     }
 
@@ -299,7 +339,7 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
                 continue;
             }
 
-            let is_binary = unit.target.is_executable();
+            let is_binary = unit.target.is_executable() || unit.target.is_fuzz();
             let is_test = unit.mode.is_any_test() && !unit.mode.is_check();
 
             if is_binary || is_test {
@@ -311,11 +351,13 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
 
     pub fn prepare_units(&mut self) -> CargoResult<()> {
         let dest = self.bcx.profiles.get_dir_name();
-        let host_layout = Layout::new(self.bcx.ws, None, &dest)?;
+        let namespace = layout::rustc_namespace(self.bcx.config, self.bcx.rustc());
+        let namespace = namespace.as_deref();
+        let host_layout = Layout::new(self.bcx.ws, None, &dest, namespace)?;
         let mut targets = HashMap::new();
         for kind in self.bcx.all_kinds.iter() {
             if let CompileKind::Target(target) = *kind {
-                let layout = Layout::new(self.bcx.ws, Some(target), &dest)?;
+                let layout = Layout::new(self.bcx.ws, Some(target), &dest, namespace)?;
                 targets.insert(target, layout);
             }
         }
@@ -378,7 +420,8 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         &self.bcx.unit_graph[unit]
     }
 
-    /// Returns the RunCustomBuild Unit associated with the given Unit.
+    /// Returns the RunCustomBuild Unit of the build script associated with the
+    /// given Unit.
     ///
     /// If the package does not have a build script, this returns None.
     pub fn find_build_script_unit(&self, unit: &Unit) -> Option<Unit> {
@@ -389,6 +432,7 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
             .iter()
             .find(|unit_dep| {
                 unit_dep.unit.mode.is_run_custom_build()
+                    && unit_dep.unit.target.is_custom_build()
                     && unit_dep.unit.pkg.package_id() == unit.pkg.package_id()
             })
             .map(|unit_dep| unit_dep.unit.clone())
@@ -555,6 +599,22 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
                 }
                 if let Some(hardlink) = output.hardlink.as_ref() {
                     if let Some(other_unit) = output_collisions.insert(hardlink.clone(), unit) {
+                        // The names given by `package.artifact-name` must be
+                        // unique, since nothing else tells the files apart.
+                        if let Some(template) = [unit, other_unit]
+                            .iter()
+                            .find_map(|u| u.pkg.manifest().original().artifact_name())
+                        {
+                            bail!(
+                                "artifact name collision\n\
+                                 {}\
+                                 The `package.artifact-name` template `{}` must give each \
+                                 artifact a unique name, consider adding the `{{name}}`, \
+                                 `{{version}}`, `{{target}}` or `{{profile}}` placeholders.",
+                                describe_collision(unit, other_unit, hardlink),
+                                template
+                            );
+                        }
                         report_collision(unit, other_unit, hardlink, suggestion)?;
                     }
                 }
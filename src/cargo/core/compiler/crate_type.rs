@@ -9,6 +9,9 @@ pub enum CrateType {
     Cdylib,
     Staticlib,
     ProcMacro,
+    /// A WebAssembly component, built by rustc as a `cdylib` and wrapped
+    /// into a component by Cargo.
+    Component,
     Other(String),
 }
 
@@ -22,13 +25,24 @@ impl CrateType {
             CrateType::Cdylib => "cdylib",
             CrateType::Staticlib => "staticlib",
             CrateType::ProcMacro => "proc-macro",
+            CrateType::Component => "component",
             CrateType::Other(s) => s,
         }
     }
 
+    /// The crate type passed to rustc with `--crate-type`.
+    pub fn rustc_crate_type(&self) -> &str {
+        match self {
+            CrateType::Component => "cdylib",
+            _ => self.as_str(),
+        }
+    }
+
     pub fn can_lto(&self) -> bool {
         match self {
-            CrateType::Bin | CrateType::Staticlib | CrateType::Cdylib => true,
+            CrateType::Bin | CrateType::Staticlib | CrateType::Cdylib | CrateType::Component => {
+                true
+            }
             CrateType::Lib
             | CrateType::Rlib
             | CrateType::Dylib
@@ -40,15 +54,19 @@ impl CrateType {
     pub fn is_linkable(&self) -> bool {
         match self {
             CrateType::Lib | CrateType::Rlib | CrateType::Dylib | CrateType::ProcMacro => true,
-            CrateType::Bin | CrateType::Cdylib | CrateType::Staticlib | CrateType::Other(..) => {
-                false
-            }
+            CrateType::Bin
+            | CrateType::Cdylib
+            | CrateType::Staticlib
+            | CrateType::Component
+            | CrateType::Other(..) => false,
         }
     }
 
     pub fn is_dynamic(&self) -> bool {
         match self {
-            CrateType::Dylib | CrateType::Cdylib | CrateType::ProcMacro => true,
+            CrateType::Dylib | CrateType::Cdylib | CrateType::ProcMacro | CrateType::Component => {
+                true
+            }
             CrateType::Lib
             | CrateType::Rlib
             | CrateType::Bin
@@ -84,6 +102,7 @@ impl<'a> From<&'a String> for CrateType {
             "cdylib" => CrateType::Cdylib,
             "staticlib" => CrateType::Staticlib,
             "procmacro" => CrateType::ProcMacro,
+            "component" => CrateType::Component,
             _ => CrateType::Other(s.clone()),
         }
     }
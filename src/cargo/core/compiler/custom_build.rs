@@ -1,11 +1,11 @@
 use super::job::{Freshness, Job, Work};
-use super::{fingerprint, Context, LinkType, Unit};
+use super::{fingerprint, BuildContext, Context, LinkType, Unit};
 use crate::core::compiler::context::Metadata;
 use crate::core::compiler::job_queue::JobState;
 use crate::core::{profiles::ProfileRoot, PackageId, Target};
 use crate::util::errors::CargoResult;
 use crate::util::machine_message::{self, Message};
-use crate::util::{internal, profile};
+use crate::util::{internal, profile, symbols};
 use anyhow::{bail, Context as _};
 use cargo_platform::Cfg;
 use cargo_util::paths;
@@ -28,6 +28,9 @@ pub struct BuildOutput {
     pub linker_args: Vec<(LinkType, String)>,
     /// Various `--cfg` flags to pass to the compiler.
     pub cfgs: Vec<String>,
+    /// The `cfg(...)` specifications of the cfgs the package expects, to
+    /// pass with `--check-cfg`.
+    pub check_cfgs: Vec<String>,
     /// Additional environment variables to run the compiler with.
     pub env: Vec<(String, String)>,
     /// Metadata to pass to the immediate dependencies.
@@ -106,6 +109,17 @@ pub struct BuildDeps {
     pub rerun_if_env_changed: Vec<String>,
 }
 
+/// The variable with the output directory of running `target` when compiling
+/// the package: `OUT_DIR` for the build script, and `CARGO_CODEGEN_DIR_<NAME>`
+/// for a codegen step.
+pub fn out_dir_env(target: &Target) -> String {
+    if target.is_codegen() {
+        format!("CARGO_CODEGEN_DIR_{}", super::envify(target.name()))
+    } else {
+        "OUT_DIR".to_string()
+    }
+}
+
 /// Prepares a `Work` that executes the target as a custom build script.
 pub fn prepare(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Job> {
     let _p = profile::start(format!(
@@ -159,7 +173,7 @@ fn build_work(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Job> {
     let dependencies = cx.unit_deps(unit);
     let build_script_unit = dependencies
         .iter()
-        .find(|d| !d.unit.mode.is_run_custom_build() && d.unit.target.is_custom_build())
+        .find(|d| !d.unit.mode.is_run_custom_build() && d.unit.target == unit.target)
         .map(|d| &d.unit)
         .expect("running a script not depending on an actual script");
     let script_dir = cx.files().build_script_dir(build_script_unit);
@@ -169,7 +183,9 @@ fn build_work(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Job> {
     let invocation_name = unit.buildkey();
 
     if let Some(deps) = unit.pkg.manifest().metabuild() {
-        prepare_metabuild(cx, build_script_unit, deps)?;
+        if unit.target.is_custom_build() {
+            prepare_metabuild(cx, build_script_unit, deps)?;
+        }
     }
 
     // Building the command to execute
@@ -292,6 +308,11 @@ fn build_work(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Job> {
         .collect::<Vec<_>>();
     let library_name = unit.pkg.library().map(|t| t.crate_name());
     let pkg_descr = unit.pkg.to_string();
+    let command_descr = if unit.target.is_codegen() {
+        format!("codegen `{}` for `{}`", unit.target.name(), pkg_descr)
+    } else {
+        format!("custom build command for `{}`", pkg_descr)
+    };
     let build_script_outputs = Arc::clone(&cx.build_script_outputs);
     let id = unit.pkg.package_id();
     let output_file = script_run_dir.join("output");
@@ -316,6 +337,11 @@ fn build_work(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Job> {
     paths::create_dir_all(&script_out_dir)?;
 
     let nightly_features_allowed = cx.bcx.config.nightly_features_allowed;
+    let drop_native_libs = loses_links(cx.bcx, unit);
+    let links = unit.pkg.manifest().links().map(|links| links.to_string());
+    let links_sharers = links_sharers(cx, unit);
+    let links_sharers_fresh = links_sharers.clone();
+    let links_fresh = links.clone();
     let targets: Vec<Target> = unit.pkg.targets().to_vec();
     // Need a separate copy for the fresh closure.
     let targets_fresh = targets.clone();
@@ -393,7 +419,7 @@ fn build_work(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Job> {
                 },
                 true,
             )
-            .with_context(|| format!("failed to run custom build command for `{}`", pkg_descr));
+            .with_context(|| format!("failed to run {}", command_descr));
 
         if let Err(error) = output {
             insert_warnings_in_build_outputs(
@@ -420,7 +446,7 @@ fn build_work(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Job> {
         paths::set_file_time_no_err(output_file, timestamp);
         paths::write(&err_file, &output.stderr)?;
         paths::write(&root_output_file, paths::path2bytes(&script_out_dir)?)?;
-        let parsed_output = BuildOutput::parse(
+        let mut parsed_output = BuildOutput::parse(
             &output.stdout,
             library_name,
             &pkg_descr,
@@ -429,14 +455,24 @@ fn build_work(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Job> {
             nightly_features_allowed,
             &targets,
         )?;
+        if drop_native_libs {
+            parsed_output.drop_native_libs();
+        }
 
         if json_messages {
             emit_build_output(state, &parsed_output, script_out_dir.as_path(), id)?;
         }
-        build_script_outputs
-            .lock()
-            .unwrap()
-            .insert(id, metadata_hash, parsed_output);
+        let mut build_script_outputs = build_script_outputs.lock().unwrap();
+        if let Some(links) = &links {
+            check_shared_links(
+                &build_script_outputs,
+                id,
+                &parsed_output,
+                links,
+                &links_sharers,
+            )?;
+        }
+        build_script_outputs.insert(id, metadata_hash, parsed_output);
         Ok(())
     });
 
@@ -445,7 +481,7 @@ fn build_work(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Job> {
     // above.
     let fresh = Work::new(move |state| {
         let (id, library_name, pkg_descr, build_script_outputs, output_file, script_out_dir) = all;
-        let output = match prev_output {
+        let mut output = match prev_output {
             Some(output) => output,
             None => BuildOutput::parse_file(
                 &output_file,
@@ -457,15 +493,25 @@ fn build_work(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Job> {
                 &targets_fresh,
             )?,
         };
+        if drop_native_libs {
+            output.drop_native_libs();
+        }
 
         if json_messages {
             emit_build_output(state, &output, script_out_dir.as_path(), id)?;
         }
 
-        build_script_outputs
-            .lock()
-            .unwrap()
-            .insert(id, metadata_hash, output);
+        let mut build_script_outputs = build_script_outputs.lock().unwrap();
+        if let Some(links) = &links_fresh {
+            check_shared_links(
+                &build_script_outputs,
+                id,
+                &output,
+                links,
+                &links_sharers_fresh,
+            )?;
+        }
+        build_script_outputs.insert(id, metadata_hash, output);
         Ok(())
     });
 
@@ -498,7 +544,170 @@ fn insert_warnings_in_build_outputs(
         .insert(id, metadata_hash, build_output_with_only_warnings);
 }
 
+/// Whether the build script `unit` shares its `links` value with the winner
+/// of its `[workspace.links]` entry, so that its native libraries aren't
+/// linked.
+pub fn loses_links(bcx: &BuildContext<'_, '_>, unit: &Unit) -> bool {
+    let links = match unit.pkg.manifest().links() {
+        Some(links) if unit.target.is_custom_build() => links,
+        _ => return false,
+    };
+    let winner = match bcx.ws.links_policies().get(links) {
+        Some(policy) if policy.allows(unit.pkg.name()) => match policy.winner {
+            Some(winner) => winner,
+            None => return false,
+        },
+        _ => return false,
+    };
+    unit.pkg.name() != winner
+        && bcx
+            .unit_graph
+            .keys()
+            .any(|u| u.pkg.name() == winner && u.pkg.manifest().links() == Some(links))
+}
+
+/// The other build scripts sharing the `links` value of the build script
+/// `unit` when a `[workspace.links]` entry without a winner allows it, so
+/// that the native libraries of all of them are linked.
+fn links_sharers(cx: &Context<'_, '_>, unit: &Unit) -> Vec<(PackageId, Metadata)> {
+    let links = match unit.pkg.manifest().links() {
+        Some(links) => links,
+        None => return Vec::new(),
+    };
+    match cx.bcx.ws.links_policies().get(links) {
+        Some(policy) if policy.winner.is_none() && policy.allows(unit.pkg.name()) => {}
+        _ => return Vec::new(),
+    }
+    cx.bcx
+        .unit_graph
+        .keys()
+        .filter(|u| {
+            u.mode.is_run_custom_build()
+                && u.kind == unit.kind
+                && u.pkg.package_id() != unit.pkg.package_id()
+                && u.pkg.manifest().links() == Some(links)
+        })
+        .map(|u| (u.pkg.package_id(), cx.get_run_build_script_metadata(u)))
+        .collect()
+}
+
+/// Checks that the native libraries of the build script of `id` don't define
+/// any of the symbols of the native libraries of the build scripts sharing
+/// its `links` value which already ran, as all of them are linked together.
+/// The last build script to run checks its libraries against all the others.
+fn check_shared_links(
+    outputs: &BuildScriptOutputs,
+    id: PackageId,
+    output: &BuildOutput,
+    links: &str,
+    sharers: &[(PackageId, Metadata)],
+) -> CargoResult<()> {
+    let mut symbols = None;
+    for &(other_id, other_metadata) in sharers {
+        let other = match outputs.get(other_metadata) {
+            Some(other) => other,
+            None => continue,
+        };
+        if symbols.is_none() {
+            symbols = Some(native_symbols(id, output, links)?);
+        }
+        let other_symbols = native_symbols(other_id, other, links)?;
+        let common: Vec<_> = symbols
+            .as_ref()
+            .unwrap()
+            .intersection(&other_symbols)
+            .map(|symbol| format!("`{}`", symbol))
+            .collect();
+        if common.is_empty() {
+            continue;
+        }
+        let shown = common.len().min(5);
+        let more = match common.len() - shown {
+            0 => String::new(),
+            n => format!(" and {} more", n),
+        };
+        bail!(
+            "the native libraries of `{}` and `{}` both define {}{}\n\
+             they share `links = \"{}\"`, so all of them are linked; set \
+             `workspace.links.{}.winner` to link the libraries of only one package",
+            other_id,
+            id,
+            common[..shown].join(", "),
+            more,
+            links,
+            links,
+        );
+    }
+    Ok(())
+}
+
+/// The symbols defined by the native libraries a build script links.
+fn native_symbols(
+    id: PackageId,
+    output: &BuildOutput,
+    links: &str,
+) -> CargoResult<BTreeSet<String>> {
+    let mut symbols = BTreeSet::new();
+    for lib in &output.library_links {
+        let lib_symbols = find_native_lib(output, lib).and_then(|path| {
+            let data = paths::read_bytes(&path)?;
+            symbols::defined_symbols(&data)
+                .with_context(|| format!("failed to read the symbols of `{}`", path.display()))
+        });
+        let lib_symbols = lib_symbols.with_context(|| {
+            format!(
+                "failed to determine the symbols of native library `{}` of `{}`, \
+                 which shares `links = \"{}\"` with other packages\n\
+                 set `workspace.links.{}.winner` to link the libraries of only one package",
+                lib, id, links, links
+            )
+        })?;
+        symbols.extend(lib_symbols);
+    }
+    Ok(symbols)
+}
+
+/// Finds the file of the native library `lib`, a `rustc-link-lib` value, in
+/// the search paths printed by the build script, the way the linker does.
+fn find_native_lib(output: &BuildOutput, lib: &str) -> CargoResult<PathBuf> {
+    let (kind, name) = match lib.split_once('=') {
+        Some((kind, name)) => (kind.split(':').next().unwrap(), name),
+        None => ("", lib),
+    };
+    let name = name.split(':').next().unwrap();
+    let extensions: &[&str] = match kind {
+        "static" => &["a"],
+        "" | "dylib" => &["so", "dylib", "a"],
+        _ => bail!("`{}` libraries are not supported", kind),
+    };
+    let dirs = output.library_paths.iter().filter_map(|path| {
+        let path = path.to_str()?;
+        match path.split_once('=') {
+            Some(("native" | "dependency" | "crate" | "all", dir)) => Some(Path::new(dir)),
+            Some(_) => None,
+            None => Some(Path::new(path)),
+        }
+    });
+    for dir in dirs {
+        for extension in extensions {
+            let path = dir.join(format!("lib{}.{}", name, extension));
+            if path.is_file() {
+                return Ok(path);
+            }
+        }
+    }
+    bail!("it is not in any of the search paths printed by the build script")
+}
+
 impl BuildOutput {
+    /// Removes the native libraries, search paths and linker arguments from
+    /// the output, for a package that lost its `links` value to another.
+    fn drop_native_libs(&mut self) {
+        self.library_paths.clear();
+        self.library_links.clear();
+        self.linker_args.clear();
+    }
+
     pub fn parse_file(
         path: &Path,
         library_name: Option<String>,
@@ -537,6 +746,7 @@ impl BuildOutput {
         let mut library_links = Vec::new();
         let mut linker_args = Vec::new();
         let mut cfgs = Vec::new();
+        let mut check_cfgs = Vec::new();
         let mut env = Vec::new();
         let mut metadata = Vec::new();
         let mut rerun_if_changed = Vec::new();
@@ -647,6 +857,8 @@ impl BuildOutput {
                     linker_args.push((LinkType::All, value));
                 }
                 "rustc-cfg" => cfgs.push(value.to_string()),
+                // The `cargo::` form of newer versions of Cargo is accepted too.
+                "rustc-check-cfg" | ":rustc-check-cfg" => check_cfgs.push(value.to_string()),
                 "rustc-env" => {
                     let (key, val) = BuildOutput::parse_rustc_env(&value, &whence)?;
                     // Build scripts aren't allowed to set RUSTC_BOOTSTRAP.
@@ -706,6 +918,7 @@ impl BuildOutput {
             library_links,
             linker_args,
             cfgs,
+            check_cfgs,
             env,
             metadata,
             rerun_if_changed,
@@ -853,7 +1066,7 @@ pub fn build_map(cx: &mut Context<'_, '_>) -> CargoResult<()> {
         }
 
         // If there is a build script override, pre-fill the build output.
-        if unit.mode.is_run_custom_build() {
+        if unit.mode.is_run_custom_build() && unit.target.is_custom_build() {
             if let Some(links) = unit.pkg.manifest().links() {
                 if let Some(output) = cx.bcx.target_data.script_override(links, unit.kind) {
                     let metadata = cx.get_run_build_script_metadata(unit);
@@ -869,7 +1082,7 @@ pub fn build_map(cx: &mut Context<'_, '_>) -> CargoResult<()> {
         let mut ret = BuildScripts::default();
 
         // If a package has a build script, add itself as something to inspect for linking.
-        if !unit.target.is_custom_build() && unit.pkg.has_custom_build() {
+        if !unit.target.is_build_program() && unit.pkg.has_custom_build() {
             let script_meta = cx
                 .find_build_script_metadata(unit)
                 .expect("has_custom_build should have RunCustomBuild");
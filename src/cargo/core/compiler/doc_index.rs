@@ -0,0 +1,150 @@
+//! The index of the documented crates, recorded with `-Z doc-index`.
+//!
+//! All the crates documented in a workspace share the same `doc` directory,
+//! but each `cargo doc` only knows about the crates it documented itself,
+//! like the members selected with `-p`. After documenting, Cargo merges the
+//! crates it documented into `doc-index.json` in that directory:
+//!
+//! ```json
+//! {
+//!     "crates": [
+//!         {
+//!             "package": "foo 0.1.0 (path+file:///.../foo)",
+//!             "name": "foo",
+//!             "kind": "lib",
+//!             "member": true,
+//!             "description": "The foo crate"
+//!         }
+//!     ]
+//! }
+//! ```
+//!
+//! The crates of earlier invocations are kept, unless they were documented
+//! again or their docs were removed. From the index, Cargo writes
+//! `index.html`, a landing page linking to the docs of the workspace members
+//! and then of the dependencies.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use cargo_util::paths;
+use serde::{Deserialize, Serialize};
+
+use super::{Context, Unit};
+use crate::core::PackageId;
+use crate::util::CargoResult;
+
+/// The name of the file with the index, in the `doc` directory.
+pub const FILE_NAME: &str = "doc-index.json";
+
+/// The name of the landing page, in the `doc` directory.
+pub const LANDING_PAGE: &str = "index.html";
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct DocIndex {
+    /// The documented crates, members first, then sorted by name.
+    pub crates: Vec<DocumentedCrate>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct DocumentedCrate {
+    pub package: PackageId,
+    /// The name of the crate, which is the directory of its docs.
+    pub name: String,
+    pub kind: String,
+    /// Whether the package is a member of the workspace.
+    pub member: bool,
+    pub description: Option<String>,
+}
+
+/// Records the crates documented by the build in the index of their `doc`
+/// directory, and writes the landing page.
+pub fn record(cx: &mut Context<'_, '_>) -> CargoResult<()> {
+    let bcx = cx.bcx;
+    let mut documented: BTreeMap<PathBuf, Vec<DocumentedCrate>> = BTreeMap::new();
+    for unit in bcx.unit_graph.keys().filter(|unit| unit.mode.is_doc()) {
+        documented
+            .entry(cx.files().out_dir(unit))
+            .or_default()
+            .push(documented_crate(cx, unit));
+    }
+
+    for (doc_dir, crates) in documented {
+        let mut index = DocIndex { crates };
+        let names: HashSet<String> = index.crates.iter().map(|c| c.name.clone()).collect();
+        if let Ok(previous) = load(&doc_dir) {
+            index.crates.extend(previous.crates.into_iter().filter(|c| {
+                !names.contains(&c.name) && doc_dir.join(&c.name).join("index.html").exists()
+            }));
+        }
+        index
+            .crates
+            .sort_by(|a, b| (!a.member, &a.name).cmp(&(!b.member, &b.name)));
+        paths::write(doc_dir.join(FILE_NAME), serde_json::to_vec(&index)?)?;
+        paths::write(doc_dir.join(LANDING_PAGE), landing_page(&index))?;
+    }
+    Ok(())
+}
+
+/// Loads the index recorded in the `doc` directory `doc_dir`.
+pub fn load(doc_dir: &Path) -> CargoResult<DocIndex> {
+    Ok(serde_json::from_slice(&paths::read_bytes(
+        &doc_dir.join(FILE_NAME),
+    )?)?)
+}
+
+fn documented_crate(cx: &Context<'_, '_>, unit: &Unit) -> DocumentedCrate {
+    DocumentedCrate {
+        package: unit.pkg.package_id(),
+        name: unit.target.crate_name(),
+        kind: unit.target.kind().description().to_string(),
+        member: cx.bcx.ws.is_member(&unit.pkg),
+        description: unit.pkg.manifest().metadata().description.clone(),
+    }
+}
+
+/// The HTML of the landing page of `index`.
+fn landing_page(index: &DocIndex) -> String {
+    let mut html = String::from(
+        "<!DOCTYPE html>\n\
+         <html lang=\"en\">\n\
+         <head>\n\
+         <meta charset=\"utf-8\">\n\
+         <title>Documentation</title>\n\
+         </head>\n\
+         <body>\n",
+    );
+    let (members, deps): (Vec<_>, Vec<_>) = index.crates.iter().partition(|c| c.member);
+    for (title, crates) in [("Workspace crates", members), ("Dependencies", deps)] {
+        if crates.is_empty() {
+            continue;
+        }
+        let _ = writeln!(html, "<h2>{}</h2>\n<ul>", title);
+        for c in crates {
+            let _ = write!(
+                html,
+                "<li><a href=\"{name}/index.html\">{name}</a> {version}",
+                name = c.name,
+                version = c.package.version(),
+            );
+            if c.kind != "lib" {
+                let _ = write!(html, " ({})", c.kind);
+            }
+            if let Some(description) = &c.description {
+                let _ = write!(html, " &mdash; {}", escape(description.trim()));
+            }
+            html.push_str("</li>\n");
+        }
+        html.push_str("</ul>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -119,6 +119,10 @@
 //! applicable). Build script `invoked.timestamp` files are in the build
 //! output directory.
 //!
+//! With `-Z test-cache`, the directory of a test Unit may also contain a
+//! `passed-` file, recording that the test binary passed. See
+//! [`TestResultCache`] for details.
+//!
 //! ## Fingerprint calculation
 //!
 //! After the list of Units has been calculated, the Units are added to the
@@ -313,8 +317,10 @@
 //! <https://github.com/rust-lang/cargo/issues?q=is%3Aissue+is%3Aopen+label%3AA-rebuild-detection>
 
 use std::collections::hash_map::{Entry, HashMap};
+use std::collections::BTreeMap;
 use std::convert::TryInto;
 use std::env;
+use std::ffi::OsStr;
 use std::hash::{self, Hash, Hasher};
 use std::path::{Path, PathBuf};
 use std::str;
@@ -337,8 +343,14 @@ use crate::util::interning::InternedString;
 use crate::util::{internal, path_args, profile, StableHasher};
 use crate::CARGO_ENV;
 
-use super::custom_build::BuildDeps;
+use super::build_info;
+use super::custom_build::{self, BuildDeps};
 use super::job::{Job, Work};
+use super::pkg_config::PkgConfig;
+use super::signing::Signer;
+use super::staticlib_bundle;
+use super::windows_resources::ResourceStep;
+use super::{check_cfg, config_flags};
 use super::{BuildContext, Context, FileFlavor, Unit};
 
 /// Determines if a `unit` is up-to-date, and if not prepares necessary work to
@@ -556,6 +568,10 @@ pub struct Fingerprint {
     /// fingerprints output files are regenerated and look newer than this one.
     #[serde(skip)]
     outputs: Vec<PathBuf>,
+    /// The variables of the `[env]` tables set for this unit, which rustc
+    /// sees in place of those of our own process.
+    #[serde(skip)]
+    manifest_env: BTreeMap<String, String>,
 }
 
 /// Indication of the status on the filesystem for a particular unit.
@@ -714,6 +730,7 @@ impl LocalFingerprint {
         pkg_root: &Path,
         target_root: &Path,
         cargo_exe: &Path,
+        manifest_env: &BTreeMap<String, String>,
     ) -> CargoResult<Option<StaleItem>> {
         match self {
             // We need to parse `dep_info`, learn about the crate's dependencies.
@@ -729,6 +746,11 @@ impl LocalFingerprint {
                     None => return Ok(Some(StaleItem::MissingFile(dep_info))),
                 };
                 for (key, previous) in info.env.iter() {
+                    // The time of the build only matters if something else
+                    // changed, see `build_info`.
+                    if key == build_info::BUILD_TIMESTAMP && !manifest_env.contains_key(key) {
+                        continue;
+                    }
                     let current = if key == CARGO_ENV {
                         Some(
                             cargo_exe
@@ -741,6 +763,8 @@ impl LocalFingerprint {
                                 })?
                                 .to_string(),
                         )
+                    } else if let Some(value) = manifest_env.get(key) {
+                        Some(value.clone())
                     } else {
                         env::var(key).ok()
                     };
@@ -799,6 +823,7 @@ impl Fingerprint {
             compile_kind: 0,
             fs_status: FsStatus::Stale,
             outputs: Vec::new(),
+            manifest_env: BTreeMap::new(),
         }
     }
 
@@ -1085,9 +1110,13 @@ impl Fingerprint {
         // files for this package itself. If we do find something log a helpful
         // message and bail out so we stay stale.
         for local in self.local.get_mut().unwrap().iter() {
-            if let Some(item) =
-                local.find_stale_item(mtime_cache, pkg_root, target_root, cargo_exe)?
-            {
+            if let Some(item) = local.find_stale_item(
+                mtime_cache,
+                pkg_root,
+                target_root,
+                cargo_exe,
+                &self.manifest_env,
+            )? {
                 item.log();
                 return Ok(());
             }
@@ -1314,6 +1343,8 @@ fn calculate_normal(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Finger
         unit.mode,
         cx.bcx.extra_args_for(unit),
         cx.lto[unit],
+        config_flags::cfg_args(cx.bcx.ws, unit),
+        check_cfg::args(cx.bcx, unit),
     ));
     // Include metadata since it is exposed as environment variables.
     let m = unit.pkg.manifest().metadata();
@@ -1330,6 +1361,36 @@ fn calculate_normal(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Finger
     if let Some(allow_features) = &cx.bcx.config.cli_unstable().allow_features {
         allow_features.hash(&mut config);
     }
+    // Bundled staticlibs are linked with different `-l` flags, and rustc
+    // prints the native libraries of the pkg-config file of a staticlib.
+    if staticlib_bundle::is_bundled(cx, unit) {
+        "staticlib-bundle".hash(&mut config);
+    }
+    if PkgConfig::new(cx, unit).map_or(false, |pc| pc.print_arg().is_some()) {
+        "native-static-libs".hash(&mut config);
+    }
+    // The resource script embeds strings of the manifest in the artifact.
+    if let Some(step) = ResourceStep::new(cx, unit)? {
+        step.contents().hash(&mut config);
+    }
+    if let Some(signer) = Signer::new(cx, unit)? {
+        signer.hash(&mut config);
+    }
+    if super::denies_warnings(cx.bcx, unit) {
+        "deny-warnings".hash(&mut config);
+    }
+    // The `[env]` of the manifests, with the contents of the files it reads.
+    let mut manifest_env = cx
+        .compilation
+        .package_env(unit.pkg.package_id())
+        .cloned()
+        .unwrap_or_default();
+    if let Some(build_info) = unit.pkg.manifest().build_info() {
+        if build_info::is_volatile_timestamp(cx.bcx.config, build_info) {
+            manifest_env.remove(build_info::BUILD_TIMESTAMP);
+        }
+    }
+    manifest_env.hash(&mut config);
     let compile_kind = unit.kind.fingerprint_hash();
     Ok(Fingerprint {
         rustc: util::hash_u64(&cx.bcx.rustc().verbose_version),
@@ -1348,6 +1409,7 @@ fn calculate_normal(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Finger
         rustflags: extra_flags,
         fs_status: FsStatus::Stale,
         outputs,
+        manifest_env,
     })
 }
 
@@ -1397,6 +1459,9 @@ fn calculate_run_custom_build(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoRes
         rustc: util::hash_u64(&cx.bcx.rustc().verbose_version),
         deps,
         outputs: if overridden { Vec::new() } else { vec![output] },
+        // Losing the `links` value to another package changes what the
+        // dependents link.
+        config: u64::from(custom_build::loses_links(cx.bcx, unit)),
 
         // Most of the other info is blank here as we don't really include it
         // in the execution of the build script, but... this may be a latent
@@ -1606,6 +1671,79 @@ pub fn dep_info_loc(cx: &mut Context<'_, '_>, unit: &Unit) -> PathBuf {
     cx.files().fingerprint_file_path(unit, "dep-")
 }
 
+/// Records which test binaries passed, so they don't need to run again.
+///
+/// A test binary passed "for a fingerprint" if it was run from the exact build
+/// described by the fingerprint file of its Unit, with the same command line,
+/// working directory and environment, including the variables inherited from
+/// Cargo's own environment. The fingerprint file is only written when the Unit is
+/// rebuilt, so its contents and mtime together identify a build: the hash
+/// alone isn't enough, as it doesn't change when the sources of a path
+/// package are modified.
+#[derive(Clone, Debug)]
+pub struct TestResultCache {
+    /// The fingerprint hash file of the test Unit.
+    fingerprint: PathBuf,
+    /// Where the key of the last passing run is stored.
+    passed: PathBuf,
+}
+
+impl TestResultCache {
+    pub fn new(cx: &Context<'_, '_>, unit: &Unit) -> TestResultCache {
+        TestResultCache {
+            fingerprint: cx.files().fingerprint_file_path(unit, ""),
+            passed: cx.files().fingerprint_file_path(unit, "passed-"),
+        }
+    }
+
+    /// Returns `true` if running `cmd` is known to pass.
+    pub fn is_fresh(&self, cmd: &ProcessBuilder) -> bool {
+        match (self.key(cmd), paths::read(&self.passed)) {
+            (Some(key), Ok(passed)) => key == passed,
+            _ => false,
+        }
+    }
+
+    /// Records that running `cmd` passed.
+    pub fn record_pass(&self, cmd: &ProcessBuilder) -> CargoResult<()> {
+        match self.key(cmd) {
+            Some(key) => paths::write(&self.passed, key),
+            None => {
+                self.record_fail();
+                Ok(())
+            }
+        }
+    }
+
+    /// Forgets any previous passing run.
+    pub fn record_fail(&self) {
+        drop(paths::remove_file(&self.passed));
+    }
+
+    fn key(&self, cmd: &ProcessBuilder) -> Option<String> {
+        let hash = paths::read(&self.fingerprint).ok()?;
+        let mtime = paths::mtime(&self.fingerprint).ok()?;
+        // The environment the test runs with: Cargo's own, with the variables
+        // of `cmd` set or removed.
+        let mut envs: BTreeMap<_, _> = env::vars_os().collect();
+        for (key, value) in cmd.get_envs() {
+            match value {
+                Some(value) => envs.insert(key.into(), value.clone()),
+                None => envs.remove(OsStr::new(key)),
+            };
+        }
+        Some(util::to_hex(util::hash_u64((
+            hash,
+            mtime.unix_seconds(),
+            mtime.nanoseconds(),
+            cmd.get_program(),
+            cmd.get_args(),
+            cmd.get_cwd(),
+            envs,
+        ))))
+    }
+}
+
 /// Returns an absolute path that target directory.
 /// All paths are rewritten to be relative to this.
 fn target_root(cx: &Context<'_, '_>) -> PathBuf {
@@ -1804,6 +1942,8 @@ enum DepInfoPathType {
 ///
 /// The serialized Cargo format will contain a list of files, all of which are
 /// relative if they're under `root`. or absolute if they're elsewhere.
+/// `extra_files` are the inputs read by Cargo itself for the unit, like its
+/// Windows resources, which are tracked alongside the ones of rustc.
 pub fn translate_dep_info(
     rustc_dep_info: &Path,
     cargo_dep_info: &Path,
@@ -1812,6 +1952,7 @@ pub fn translate_dep_info(
     target_root: &Path,
     rustc_cmd: &ProcessBuilder,
     allow_package: bool,
+    extra_files: &[PathBuf],
 ) -> CargoResult<()> {
     let depinfo = parse_rustc_dep_info(rustc_dep_info)?;
 
@@ -1852,7 +1993,7 @@ pub fn translate_dep_info(
         .env
         .retain(|(key, _)| !rustc_cmd.get_envs().contains_key(key) || key == CARGO_ENV);
 
-    for file in depinfo.files {
+    for file in depinfo.files.iter().chain(extra_files) {
         // The path may be absolute or relative, canonical or not. Make sure
         // it is canonicalized so we are comparing the same kinds of paths.
         let abs_file = rustc_cwd.join(file);
@@ -0,0 +1,122 @@
+//! C headers of `cdylib` and `staticlib` libraries, generated with the
+//! `[headers]` table of the `c-headers` feature.
+//!
+//! Once a library with the `cdylib` or `staticlib` crate type is linked, the
+//! generator of its package, `cbindgen` by default, is run in the package
+//! root to write the header into `target/<profile>/include`. A generator
+//! gets the path to write to in `CARGO_HEADER_OUTPUT`, along with the
+//! `CARGO_MANIFEST_DIR`, `CARGO_PKG_NAME` and `CARGO_CRATE_NAME` of the
+//! library; `cbindgen` also gets `--crate` and `--output`.
+//!
+//! The header is generated again when the library was rebuilt since, or when
+//! the command generating it changes. It is copied to `--out-dir` like the
+//! library.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context as _;
+use cargo_util::{paths, ProcessBuilder};
+
+use super::{CompileMode, Context, CrateType, FileFlavor, Unit};
+use crate::util::CargoResult;
+
+/// The generator run when `headers.generator` isn't set.
+const DEFAULT_GENERATOR: &str = "cbindgen";
+
+/// Generates the headers of the libraries built as `cdylib` or `staticlib`.
+pub fn generate(cx: &mut Context<'_, '_>) -> CargoResult<()> {
+    let bcx = cx.bcx;
+    for unit in bcx.roots.iter().filter(|unit| has_header(unit)) {
+        let headers = unit.pkg.manifest().original().headers().unwrap();
+        let crate_name = unit.target.crate_name();
+        let name = headers
+            .name
+            .clone()
+            .unwrap_or_else(|| format!("{}.h", crate_name));
+        let include = cx.files().layout(unit.kind).dest().join("include");
+        let output = include.join(&name);
+
+        let generator = headers.generator.as_deref().unwrap_or(DEFAULT_GENERATOR);
+        let mut cmd = ProcessBuilder::new(generator);
+        if headers.generator.is_none() {
+            cmd.arg("--crate")
+                .arg(unit.pkg.name().as_str())
+                .arg("--output")
+                .arg(&output);
+        }
+        cmd.args(headers.args.as_deref().unwrap_or_default())
+            .cwd(unit.pkg.root())
+            .env("CARGO_HEADER_OUTPUT", &output)
+            .env("CARGO_MANIFEST_DIR", unit.pkg.root())
+            .env("CARGO_PKG_NAME", unit.pkg.name().as_str())
+            .env("CARGO_CRATE_NAME", &crate_name);
+
+        let stamp = cx.files().fingerprint_file_path(unit, "c-header-");
+        let artifacts: Vec<PathBuf> = cx
+            .outputs(unit)?
+            .iter()
+            .filter(|o| o.flavor == FileFlavor::Normal)
+            .map(|o| o.path.clone())
+            .collect();
+        if is_fresh(&output, &stamp, &cmd.to_string(), &artifacts) {
+            log::debug!("header `{}` is fresh", output.display());
+        } else {
+            paths::create_dir_all(&include)?;
+            bcx.config
+                .shell()
+                .status("Generating", format!("{} for {}", name, unit.pkg))?;
+            bcx.config
+                .shell()
+                .verbose(|shell| shell.status("Running", &cmd))?;
+            cmd.exec_with_output().with_context(|| {
+                format!(
+                    "failed to generate the header `{}` of `{}`",
+                    name,
+                    unit.pkg.name()
+                )
+            })?;
+            if !output.exists() {
+                anyhow::bail!(
+                    "the header generator `{}` of `{}` didn't write `{}`",
+                    generator,
+                    unit.pkg.name(),
+                    output.display()
+                );
+            }
+            paths::write(&stamp, cmd.to_string())?;
+        }
+
+        if let Some(export_dir) = cx.files().export_dir() {
+            paths::create_dir_all(&export_dir)?;
+            paths::link_or_copy(&output, export_dir.join(&name))?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether `unit` builds a library with a header to generate.
+fn has_header(unit: &Unit) -> bool {
+    unit.mode == CompileMode::Build
+        && unit.target.is_lib()
+        && unit
+            .target
+            .rustc_crate_types()
+            .iter()
+            .any(|ct| matches!(ct, CrateType::Cdylib | CrateType::Staticlib))
+        && unit.pkg.manifest().original().headers().is_some()
+}
+
+/// Whether `output` was generated with `cmd` after the `artifacts` of the
+/// library were last linked.
+fn is_fresh(output: &Path, stamp: &Path, cmd: &str, artifacts: &[PathBuf]) -> bool {
+    if paths::read(stamp).ok().as_deref() != Some(cmd) {
+        return false;
+    }
+    let generated = match paths::mtime(output) {
+        Ok(mtime) => mtime,
+        Err(_) => return false,
+    };
+    artifacts
+        .iter()
+        .all(|artifact| paths::mtime(artifact).map_or(false, |linked| linked <= generated))
+}
@@ -64,18 +64,20 @@ use jobserver::{Acquired, Client, HelperThread};
 use log::{debug, info, trace};
 use semver::Version;
 
+use super::build_stats::BuildStats;
 use super::context::OutputFile;
 use super::job::{
     Freshness::{self, Dirty, Fresh},
     Job,
 };
 use super::timings::Timings;
-use super::{BuildContext, BuildPlan, CompileMode, Context, Unit};
+use super::warning_summary::WarningSummary;
+use super::{BuildContext, BuildPlan, CompileMode, Context, Unit, WarningHandling};
 use crate::core::compiler::future_incompat::{
     self, FutureBreakageItem, FutureIncompatReportPackage,
 };
 use crate::core::resolver::ResolveBehavior;
-use crate::core::{PackageId, Shell, TargetKind};
+use crate::core::{Dependency, Package, PackageId, Shell, TargetKind};
 use crate::util::diagnostic_server::{self, DiagnosticPrinter};
 use crate::util::machine_message::{self, Message as _};
 use crate::util::CargoResult;
@@ -90,6 +92,7 @@ pub struct JobQueue<'cfg> {
     queue: DependencyQueue<Unit, Artifact, Job>,
     counts: HashMap<PackageId, usize>,
     timings: Timings<'cfg>,
+    stats: BuildStats,
 }
 
 /// This structure is backed by the `DependencyQueue` type and manages the
@@ -134,6 +137,8 @@ struct DrainState<'cfg> {
     /// the number that were suppressed because they were duplicates of a
     /// previous warning.
     warning_count: HashMap<JobId, (usize, usize)>,
+    /// The warnings of the whole build, for `--warnings`.
+    warning_summary: WarningSummary,
     active: HashMap<JobId, Unit>,
     compiled: HashSet<PackageId>,
     documented: HashSet<PackageId>,
@@ -141,6 +146,7 @@ struct DrainState<'cfg> {
     progress: Progress<'cfg>,
     next_id: u32,
     timings: Timings<'cfg>,
+    stats: BuildStats,
 
     /// Tokens that are currently owned by this Cargo, and may be "associated"
     /// with a rustc process. They may also be unused, though if so will be
@@ -220,13 +226,16 @@ pub struct JobState<'a, 'cfg> {
 struct DiagDedupe<'cfg> {
     seen: RefCell<HashSet<u64>>,
     config: &'cfg Config,
+    /// Whether warnings are only counted, for `--warnings=summary`.
+    hide_warnings: bool,
 }
 
 impl<'cfg> DiagDedupe<'cfg> {
-    fn new(config: &'cfg Config) -> Self {
+    fn new(config: &'cfg Config, hide_warnings: bool) -> Self {
         DiagDedupe {
             seen: RefCell::new(HashSet::new()),
             config,
+            hide_warnings,
         }
     }
 
@@ -234,11 +243,14 @@ impl<'cfg> DiagDedupe<'cfg> {
     ///
     /// Returns `true` if the message was emitted, or `false` if it was
     /// suppressed for being a duplicate.
-    fn emit_diag(&self, diag: &str) -> CargoResult<bool> {
+    fn emit_diag(&self, level: &str, diag: &str) -> CargoResult<bool> {
         let h = util::hash_u64(diag);
         if !self.seen.borrow_mut().insert(h) {
             return Ok(false);
         }
+        if self.hide_warnings && level == "warning" {
+            return Ok(true);
+        }
         let mut shell = self.config.shell();
         shell.print_ansi_stderr(diag.as_bytes())?;
         shell.err().write_all(b"\n")?;
@@ -275,10 +287,12 @@ enum Message {
         id: JobId,
         level: String,
         diag: String,
+        lint: Option<String>,
     },
     WarningCount {
         id: JobId,
         emitted: bool,
+        lint: Option<String>,
     },
     FixDiagnostic(diagnostic_server::Message),
     Token(io::Result<Acquired>),
@@ -327,13 +341,14 @@ impl<'a, 'cfg> JobState<'a, 'cfg> {
         Ok(())
     }
 
-    pub fn emit_diag(&self, level: String, diag: String) -> CargoResult<()> {
+    pub fn emit_diag(&self, level: String, diag: String, lint: Option<String>) -> CargoResult<()> {
         if let Some(dedupe) = self.output {
-            let emitted = dedupe.emit_diag(&diag)?;
+            let emitted = dedupe.emit_diag(&level, &diag)?;
             if level == "warning" {
                 self.messages.push(Message::WarningCount {
                     id: self.id,
                     emitted,
+                    lint,
                 });
             }
         } else {
@@ -341,6 +356,7 @@ impl<'a, 'cfg> JobState<'a, 'cfg> {
                 id: self.id,
                 level,
                 diag,
+                lint,
             });
         }
         Ok(())
@@ -385,6 +401,7 @@ impl<'cfg> JobQueue<'cfg> {
             queue: DependencyQueue::new(),
             counts: HashMap::new(),
             timings: Timings::new(bcx, &bcx.roots),
+            stats: BuildStats::new(bcx),
         }
     }
 
@@ -450,11 +467,11 @@ impl<'cfg> JobQueue<'cfg> {
             }
         }
 
-        // For now we use a fixed placeholder value for the cost of each unit, but
-        // in the future this could be used to allow users to provide hints about
-        // relative expected costs of units, or this could be automatically set in
-        // a smarter way using timing data from a previous compilation.
-        self.queue.queue(unit.clone(), job, queue_deps, 100);
+        // The cost of a unit is a fixed placeholder value, unless
+        // `-Z build-stats` recorded how long it took in a previous
+        // compilation.
+        let cost = self.stats.cost(cx.bcx, unit);
+        self.queue.queue(unit.clone(), job, queue_deps, cost);
         *self.counts.entry(unit.pkg.package_id()).or_insert(0) += 1;
         Ok(())
     }
@@ -477,8 +494,12 @@ impl<'cfg> JobQueue<'cfg> {
             // typical messages. If you change this, please update the test
             // caching_large_output, too.
             messages: Arc::new(Queue::new(100)),
-            diag_dedupe: DiagDedupe::new(cx.bcx.config),
+            diag_dedupe: DiagDedupe::new(
+                cx.bcx.config,
+                cx.bcx.build_config.warnings == WarningHandling::Summary,
+            ),
             warning_count: HashMap::new(),
+            warning_summary: WarningSummary::default(),
             active: HashMap::new(),
             compiled: HashSet::new(),
             documented: HashSet::new(),
@@ -486,6 +507,7 @@ impl<'cfg> JobQueue<'cfg> {
             progress,
             next_id: 0,
             timings: self.timings,
+            stats: self.stats,
             tokens: Vec::new(),
             rustc_tokens: HashMap::new(),
             to_send_clients: BTreeMap::new(),
@@ -632,17 +654,25 @@ impl<'cfg> DrainState<'cfg> {
                 shell.print_ansi_stderr(err.as_bytes())?;
                 shell.err().write_all(b"\n")?;
             }
-            Message::Diagnostic { id, level, diag } => {
-                let emitted = self.diag_dedupe.emit_diag(&diag)?;
+            Message::Diagnostic {
+                id,
+                level,
+                diag,
+                lint,
+            } => {
+                let emitted = self.diag_dedupe.emit_diag(&level, &diag)?;
                 if level == "warning" {
-                    self.bump_warning_count(id, emitted);
+                    self.bump_warning_count(id, emitted, lint.as_deref());
                 }
             }
-            Message::WarningCount { id, emitted } => {
-                self.bump_warning_count(id, emitted);
+            Message::WarningCount { id, emitted, lint } => {
+                self.bump_warning_count(id, emitted, lint.as_deref());
             }
             Message::FixDiagnostic(msg) => {
                 self.print.print(&msg)?;
+                if let Some(summary) = cx.bcx.build_config.fix_summary.borrow_mut().as_mut() {
+                    summary.record(&msg);
+                }
             }
             Message::Finish(id, artifact, result) => {
                 let unit = match artifact {
@@ -681,6 +711,7 @@ impl<'cfg> DrainState<'cfg> {
                         let msg = "The following warnings were emitted during compilation:";
                         self.emit_warnings(Some(msg), &unit, cx)?;
                         self.back_compat_notice(cx, &unit)?;
+                        self.minimal_versions_notice(cx, &unit)?;
                         return Err(e);
                     }
                 }
@@ -791,6 +822,17 @@ impl<'cfg> DrainState<'cfg> {
         // must be handled in such a way that the loop is still allowed to
         // drain event messages.
         loop {
+            if error.is_none() && cx.bcx.build_config.is_cancelled() {
+                if !self.active.is_empty() {
+                    drop(
+                        cx.bcx
+                            .config
+                            .shell()
+                            .warn("build cancelled, waiting for other jobs to finish..."),
+                    );
+                }
+                error = Some(format_err!("the build was cancelled"));
+            }
             if error.is_none() {
                 if let Err(e) = self.spawn_work_if_possible(cx, jobserver_helper, scope) {
                     self.handle_error(&mut cx.bcx.config.shell(), &mut error, e);
@@ -820,6 +862,27 @@ impl<'cfg> DrainState<'cfg> {
         }
         self.progress.clear();
 
+        match cx.bcx.build_config.warnings {
+            WarningHandling::Full => {}
+            WarningHandling::Summary => {
+                // It doesn't really matter if this fails.
+                drop(self.warning_summary.print(cx.bcx.config));
+            }
+            WarningHandling::Deny => {
+                let total = self.warning_summary.total();
+                if total > 0 && error.is_none() {
+                    error = Some(format_err!(
+                        "{} denied by `--warnings=deny`",
+                        if total == 1 {
+                            "1 warning was".to_string()
+                        } else {
+                            format!("{} warnings were", total)
+                        }
+                    ));
+                }
+            }
+        }
+
         let profile_name = cx.bcx.build_config.requested_profile;
         // NOTE: this may be a bit inaccurate, since this may not display the
         // profile for what was actually built. Profile overrides can change
@@ -846,6 +909,13 @@ impl<'cfg> DrainState<'cfg> {
                 return Some(e);
             }
         }
+        if let Err(e) = self.stats.finished(cx.bcx, error.is_none()) {
+            if error.is_some() {
+                crate::display_error(&e, &mut cx.bcx.config.shell());
+            } else {
+                return Some(e);
+            }
+        }
         if cx.bcx.build_config.emit_json() {
             let mut shell = cx.bcx.config.shell();
             let msg = machine_message::BuildFinished {
@@ -937,23 +1007,28 @@ impl<'cfg> DrainState<'cfg> {
             CompileMode::RunCustomBuild => format!("{}(build)", pkg_name),
             CompileMode::Test | CompileMode::Check { test: true } => match unit.target.kind() {
                 TargetKind::Lib(_) => format!("{}(test)", target_name),
-                TargetKind::CustomBuild => panic!("cannot test build script"),
+                TargetKind::CustomBuild | TargetKind::Codegen => {
+                    panic!("cannot test build script")
+                }
                 TargetKind::Bin => format!("{}(bin test)", target_name),
                 TargetKind::Test => format!("{}(test)", target_name),
                 TargetKind::Bench => format!("{}(bench)", target_name),
                 TargetKind::ExampleBin | TargetKind::ExampleLib(_) => {
                     format!("{}(example test)", target_name)
                 }
+                TargetKind::Fuzz => format!("{}(fuzz test)", target_name),
             },
             _ => match unit.target.kind() {
                 TargetKind::Lib(_) => pkg_name.to_string(),
                 TargetKind::CustomBuild => format!("{}(build.rs)", pkg_name),
+                TargetKind::Codegen => format!("{}(codegen)", target_name),
                 TargetKind::Bin => format!("{}(bin)", target_name),
                 TargetKind::Test => format!("{}(test)", target_name),
                 TargetKind::Bench => format!("{}(bench)", target_name),
                 TargetKind::ExampleBin | TargetKind::ExampleLib(_) => {
                     format!("{}(example)", target_name)
                 }
+                TargetKind::Fuzz => format!("{}(fuzz)", target_name),
             },
         }
     }
@@ -973,6 +1048,7 @@ impl<'cfg> DrainState<'cfg> {
         let messages = self.messages.clone();
         let fresh = job.freshness();
         let rmeta_required = cx.rmeta_required(unit);
+        self.stats.unit_start(id, unit, fresh == Freshness::Fresh);
 
         let doit = move |state: JobState<'_, '_>| {
             let mut sender = FinishOnDrop {
@@ -1082,12 +1158,14 @@ impl<'cfg> DrainState<'cfg> {
         Ok(())
     }
 
-    fn bump_warning_count(&mut self, id: JobId, emitted: bool) {
+    fn bump_warning_count(&mut self, id: JobId, emitted: bool, lint: Option<&str>) {
         let cnts = self.warning_count.entry(id).or_default();
         cnts.0 += 1;
         if !emitted {
             cnts.1 += 1;
         }
+        let package = self.active[&id].pkg.name();
+        self.warning_summary.record(package, lint, emitted);
     }
 
     /// Displays a final report of the warnings emitted by a particular job.
@@ -1096,6 +1174,9 @@ impl<'cfg> DrainState<'cfg> {
             Some(count) => count,
             None => return,
         };
+        if self.diag_dedupe.hide_warnings {
+            return;
+        }
         let unit = &self.active[&id];
         let mut message = format!("`{}` ({}", unit.pkg.name(), unit.target.description_named());
         if unit.mode.is_rustc_test() && !(unit.target.is_test() || unit.target.is_bench()) {
@@ -1132,7 +1213,10 @@ impl<'cfg> DrainState<'cfg> {
         }
         let unlocked = self.queue.finish(unit, &artifact);
         match artifact {
-            Artifact::All => self.timings.unit_finished(id, unlocked),
+            Artifact::All => {
+                self.timings.unit_finished(id, unlocked);
+                self.stats.unit_finished(id, cx)?;
+            }
             Artifact::Metadata => self.timings.unit_rmeta_finished(id, unlocked),
         }
         Ok(())
@@ -1215,4 +1299,66 @@ feature resolver. Try updating to diesel 1.4.8 to fix this error.
         )?;
         Ok(())
     }
+
+    /// Reports the version requirements resolved to their lower bounds with
+    /// `--minimal-versions` that may be why `unit` doesn't build: the
+    /// requirements on its package, and those of its package on the
+    /// dependencies it builds with.
+    fn minimal_versions_notice(&self, cx: &Context<'_, '_>, unit: &Unit) -> CargoResult<()> {
+        let ws = cx.bcx.ws;
+        let minimal_versions = ws.minimal_versions();
+        if !minimal_versions.is_enabled() {
+            return Ok(());
+        }
+        // The requirement of `parent` that `pkg` was resolved to the lower
+        // bound of, if any.
+        let lower_bound = |parent: &Package, pkg: &Package| -> Option<Dependency> {
+            parent
+                .dependencies()
+                .iter()
+                .find(|dep| {
+                    dep.package_name() == pkg.name()
+                        && dep.matches_id(pkg.package_id())
+                        && minimal_versions.applies_to(dep, ws.is_member(parent))
+                })
+                .cloned()
+        };
+        let mut bounds = BTreeMap::new();
+        for (parent, deps) in cx.bcx.unit_graph.iter() {
+            for dep in deps {
+                if dep.unit.pkg == unit.pkg || parent.pkg == unit.pkg {
+                    if let Some(req) = lower_bound(&parent.pkg, &dep.unit.pkg) {
+                        bounds.insert(
+                            (parent.pkg.package_id(), dep.unit.pkg.package_id()),
+                            req.version_req().to_string(),
+                        );
+                    }
+                }
+            }
+        }
+        if bounds.is_empty() {
+            return Ok(());
+        }
+        let mut msg = String::from(
+            "the build may have failed on the lower bounds of these requirements, \
+             to which the dependencies were resolved:\n",
+        );
+        for ((parent, pkg), req) in &bounds {
+            writeln!(
+                msg,
+                "  `{} = \"{}\"` of {}, resolved to v{}",
+                pkg.name(),
+                req,
+                parent,
+                pkg.version()
+            )
+            .unwrap();
+        }
+        msg.push_str(
+            "Raise the lower bounds, or exempt the dependencies with the `exempt` list \
+             of `[workspace.minimal-versions]`.",
+        );
+        cx.bcx.config.shell().note(msg)?;
+        Ok(())
+    }
 }
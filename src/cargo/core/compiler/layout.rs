@@ -97,10 +97,16 @@
 //!
 //! When cross-compiling, the layout is the same, except it appears in
 //! `target/$TRIPLE`.
+//!
+//! With `-Z rustc-namespace`, the `.fingerprint`, `deps`, `incremental` and
+//! `build` directories are instead in `debug/.rustc/$NAMESPACE/`, where the
+//! namespace is named after the version of rustc, so that the intermediate
+//! artifacts of several toolchains coexist. The final artifacts are still
+//! linked into `debug/`.
 
 use crate::core::compiler::CompileTarget;
 use crate::core::Workspace;
-use crate::util::{CargoResult, FileLock};
+use crate::util::{self, CargoResult, Config, FileLock, Rustc};
 use cargo_util::paths;
 use std::path::{Path, PathBuf};
 
@@ -138,11 +144,13 @@ impl Layout {
     /// This function will block if the directory is already locked.
     ///
     /// `dest` should be the final artifact directory name. Currently either
-    /// "debug" or "release".
+    /// "debug" or "release". `namespace` is the directory of the intermediate
+    /// artifacts under `$dest/.rustc`, see `rustc_namespace`.
     pub fn new(
         ws: &Workspace<'_>,
         target: Option<CompileTarget>,
         dest: &str,
+        namespace: Option<&str>,
     ) -> CargoResult<Layout> {
         let mut root = ws.target_dir();
         if let Some(target) = target {
@@ -164,12 +172,16 @@ impl Layout {
         let lock = dest.open_rw(".cargo-lock", ws.config(), "build directory")?;
         let root = root.into_path_unlocked();
         let dest = dest.into_path_unlocked();
+        let intermediate = match namespace {
+            Some(namespace) => dest.join(".rustc").join(namespace),
+            None => dest.clone(),
+        };
 
         Ok(Layout {
-            deps: dest.join("deps"),
-            build: dest.join("build"),
-            incremental: dest.join("incremental"),
-            fingerprint: dest.join(".fingerprint"),
+            deps: intermediate.join("deps"),
+            build: intermediate.join("build"),
+            incremental: intermediate.join("incremental"),
+            fingerprint: intermediate.join(".fingerprint"),
             examples: dest.join("examples"),
             doc: root.join("doc"),
             tmp: root.join("tmp"),
@@ -228,3 +240,32 @@ impl Layout {
         Ok(&self.tmp)
     }
 }
+
+/// Returns the name of the directory of the intermediate artifacts built by
+/// `rustc` with `-Z rustc-namespace`, or `None` without it.
+///
+/// Like the metadata hash of units, the nightlies of one channel share a
+/// namespace unless `-Z separate-nightlies` is passed, so that updating
+/// nightly doesn't leave stale artifacts behind.
+pub fn rustc_namespace(config: &Config, rustc: &Rustc) -> Option<String> {
+    if !config.cli_unstable().rustc_namespace {
+        return None;
+    }
+    let vers = &rustc.version;
+    if vers.pre.is_empty() || config.cli_unstable().separate_nightlies {
+        Some(format!(
+            "{}.{}.{}-{}",
+            vers.major,
+            vers.minor,
+            vers.patch,
+            util::short_hash(&rustc.verbose_version)
+        ))
+    } else {
+        let channel = vers.pre.split('.').next().unwrap_or_default();
+        Some(format!(
+            "{}-{}",
+            channel,
+            util::short_hash(&(channel, &rustc.host))
+        ))
+    }
+}
@@ -0,0 +1,206 @@
+//! The link graph of the final artifacts, recorded with `-Z link-graph`.
+//!
+//! After a build, Cargo writes `link-graph.json` in the output directory of
+//! each target, with the crates and native libraries that went into each
+//! linked artifact:
+//!
+//! ```json
+//! {
+//!     "target": "x86_64-unknown-linux-gnu",
+//!     "profile": "dev",
+//!     "artifacts": [
+//!         {
+//!             "package": "foo 0.1.0 (path+file:///.../foo)",
+//!             "name": "foo",
+//!             "kind": "bin",
+//!             "test": false,
+//!             "path": "/.../target/debug/foo",
+//!             "crates": [
+//!                 {"package": "bar 0.1.0 (...)", "name": "bar", "path": "/.../libbar-1234.rlib"}
+//!             ],
+//!             "native_libs": [
+//!                 {"package": "libz-sys 1.1.3 (...)", "links": "z", "libs": ["static=z"], "search_paths": ["native=/.../out/lib"]}
+//!             ]
+//!         }
+//!     ]
+//! }
+//! ```
+//!
+//! The native libraries are in the order of the `-L` flags passed to rustc,
+//! which is the order in which the linker finds them. Artifacts of earlier
+//! builds are kept, unless they were built again or removed.
+//! `cargo report link-graph` prints the recorded graphs.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+
+use cargo_util::paths;
+use serde::{Deserialize, Serialize};
+
+use super::{Context, FileFlavor, Unit};
+use crate::core::PackageId;
+use crate::util::{internal, CargoResult};
+
+/// The name of the file with the link graph, in the output directory of each
+/// target.
+pub const FILE_NAME: &str = "link-graph.json";
+
+#[derive(Serialize, Deserialize)]
+pub struct LinkGraph {
+    pub target: String,
+    pub profile: String,
+    pub artifacts: Vec<LinkedArtifact>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct LinkedArtifact {
+    pub package: PackageId,
+    pub name: String,
+    pub kind: String,
+    pub test: bool,
+    pub path: PathBuf,
+    /// The Rust crates linked into the artifact, sorted by package.
+    pub crates: Vec<LinkedCrate>,
+    /// The native libraries of the build scripts, in link order.
+    pub native_libs: Vec<NativeLibs>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LinkedCrate {
+    pub package: PackageId,
+    pub name: String,
+    pub path: PathBuf,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NativeLibs {
+    pub package: PackageId,
+    /// The `links` key of the package, if any.
+    pub links: Option<String>,
+    /// The libraries the build script asked to link, like `static=z`.
+    pub libs: Vec<String>,
+    pub search_paths: Vec<PathBuf>,
+}
+
+/// Records the link graph of the linked artifacts of the build.
+pub fn record(cx: &mut Context<'_, '_>) -> CargoResult<()> {
+    let bcx = cx.bcx;
+    let mut graphs: BTreeMap<PathBuf, LinkGraph> = BTreeMap::new();
+    for unit in bcx.roots.iter().filter(|unit| is_linked(unit)) {
+        let artifact = linked_artifact(cx, unit)?;
+        let file = cx.compilation.root_output[&unit.kind].join(FILE_NAME);
+        graphs
+            .entry(file)
+            .or_insert_with(|| LinkGraph {
+                target: bcx.target_data.short_name(&unit.kind).to_string(),
+                profile: bcx.build_config.requested_profile.to_string(),
+                artifacts: Vec::new(),
+            })
+            .artifacts
+            .extend(artifact);
+    }
+
+    for (file, mut graph) in graphs {
+        if let Ok(previous) = load(&file) {
+            let built: HashSet<PathBuf> = graph.artifacts.iter().map(|a| a.path.clone()).collect();
+            graph.artifacts.extend(
+                previous
+                    .artifacts
+                    .into_iter()
+                    .filter(|a| !built.contains(&a.path) && a.path.exists()),
+            );
+        }
+        graph.artifacts.sort_by(|a, b| {
+            (&a.package, &a.name, &a.kind, a.test).cmp(&(&b.package, &b.name, &b.kind, b.test))
+        });
+        paths::write(&file, serde_json::to_vec(&graph)?)?;
+    }
+    Ok(())
+}
+
+/// Loads the link graph recorded in `file`.
+pub fn load(file: &Path) -> CargoResult<LinkGraph> {
+    Ok(serde_json::from_slice(&paths::read_bytes(file)?)?)
+}
+
+/// Whether `unit` is linked into an executable or a library loaded by
+/// something else than Rust.
+fn is_linked(unit: &Unit) -> bool {
+    if unit.mode.is_check() || unit.mode.is_doc() || unit.mode.is_doc_test() {
+        return false;
+    }
+    unit.mode.is_any_test()
+        || unit
+            .target
+            .rustc_crate_types()
+            .iter()
+            .any(|ct| ct.requires_upstream_objects())
+}
+
+fn linked_artifact(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Option<LinkedArtifact>> {
+    let path = match cx
+        .outputs(unit)?
+        .iter()
+        .find(|output| output.flavor == FileFlavor::Normal)
+    {
+        Some(output) if unit.mode.is_any_test() => output.path.clone(),
+        Some(output) => output.bin_dst().clone(),
+        None => return Ok(None),
+    };
+
+    let mut crates = BTreeSet::new();
+    let mut seen = HashSet::new();
+    let mut stack = vec![unit.clone()];
+    while let Some(parent) = stack.pop() {
+        for dep in cx.unit_deps(&parent).to_vec() {
+            let linked = dep.unit.target.is_linkable()
+                && !dep.unit.target.proc_macro()
+                && !dep.unit.mode.is_run_custom_build();
+            if !linked || !seen.insert(dep.unit.clone()) {
+                continue;
+            }
+            if let Some(output) = cx
+                .outputs(&dep.unit)?
+                .iter()
+                .find(|output| output.flavor == FileFlavor::Linkable)
+            {
+                crates.insert(LinkedCrate {
+                    package: dep.unit.pkg.package_id(),
+                    name: dep.unit.target.crate_name(),
+                    path: output.path.clone(),
+                });
+            }
+            stack.push(dep.unit);
+        }
+    }
+
+    let mut native_libs = Vec::new();
+    if let Some(build_scripts) = cx.build_scripts.get(unit) {
+        let outputs = cx.build_script_outputs.lock().unwrap();
+        for (id, metadata) in &build_scripts.to_link {
+            let output = outputs.get(*metadata).ok_or_else(|| {
+                internal(format!(
+                    "couldn't find build script output for {}/{}",
+                    id, metadata
+                ))
+            })?;
+            let pkg = cx.bcx.packages.get_one(*id)?;
+            native_libs.push(NativeLibs {
+                package: *id,
+                links: pkg.manifest().links().map(|links| links.to_string()),
+                libs: output.library_links.clone(),
+                search_paths: output.library_paths.clone(),
+            });
+        }
+    }
+
+    Ok(Some(LinkedArtifact {
+        package: unit.pkg.package_id(),
+        name: unit.target.name().to_string(),
+        kind: unit.target.kind().description().to_string(),
+        test: unit.mode.is_any_test(),
+        path,
+        crates: crates.into_iter().collect(),
+        native_libs,
+    }))
+}
@@ -1,11 +1,20 @@
 use super::unit_graph::UnitGraph;
 use crate::core::resolver::errors::describe_path;
-use crate::core::{PackageId, Resolve};
+use crate::core::{PackageId, Resolve, Workspace};
 use crate::util::errors::CargoResult;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 /// Validate `links` field does not conflict between packages.
-pub fn validate_links(resolve: &Resolve, unit_graph: &UnitGraph) -> CargoResult<()> {
+///
+/// Packages allowed to share a `links` value by `[workspace.links]` don't
+/// conflict, but a warning reports them on every build. Without a winner, the
+/// symbols of their native libraries are checked once their build scripts
+/// ran, see `custom_build::check_shared_links`.
+pub fn validate_links(
+    ws: &Workspace<'_>,
+    resolve: &Resolve,
+    unit_graph: &UnitGraph,
+) -> CargoResult<()> {
     // NOTE: This is the *old* links validator. Links are usually validated in
     // the resolver. However, the `links` field was added to the index in
     // early 2018 (see https://github.com/rust-lang/cargo/pull/4978). However,
@@ -16,6 +25,7 @@ pub fn validate_links(resolve: &Resolve, unit_graph: &UnitGraph) -> CargoResult<
     // the index.
     let mut validated: HashSet<PackageId> = HashSet::new();
     let mut links: HashMap<String, PackageId> = HashMap::new();
+    let mut shared: BTreeMap<&str, Vec<PackageId>> = BTreeMap::new();
     let mut units: Vec<_> = unit_graph.keys().collect();
     // Sort primarily to make testing easier.
     units.sort_unstable();
@@ -28,11 +38,19 @@ pub fn validate_links(resolve: &Resolve, unit_graph: &UnitGraph) -> CargoResult<
             None => continue,
         };
         if let Some(&prev) = links.get(lib) {
+            let pkg = unit.pkg.package_id();
+            let allowed = ws.links_policies().get(lib).map_or(false, |policy| {
+                policy.allows(prev.name()) && policy.allows(pkg.name())
+            });
+            if allowed {
+                let packages = shared.entry(lib).or_insert_with(|| vec![prev]);
+                packages.push(pkg);
+                continue;
+            }
             let prev_path = resolve
                 .path_to_top(&prev)
                 .into_iter()
                 .map(|(p, d)| (p, d.and_then(|d| d.iter().next())));
-            let pkg = unit.pkg.package_id();
             let path = resolve
                 .path_to_top(&pkg)
                 .into_iter()
@@ -53,5 +71,26 @@ pub fn validate_links(resolve: &Resolve, unit_graph: &UnitGraph) -> CargoResult<
         }
         links.insert(lib.to_string(), unit.pkg.package_id());
     }
+
+    for (lib, packages) in shared {
+        let names: Vec<String> = packages.iter().map(|id| format!("`{}`", id)).collect();
+        let linked = match ws.links_policies()[lib].winner {
+            Some(winner) => format!(
+                "only the native libraries of `{}` are linked, the build scripts \
+                 of the others can't link anything",
+                winner
+            ),
+            None => "the native libraries of all of them are linked, \
+                     once checked not to define the same symbols"
+                .to_string(),
+        };
+        ws.config().shell().warn(format!(
+            "packages {} link to native library `{}`, as allowed by `workspace.links.{}`\n{}",
+            names.join(", "),
+            lib,
+            lib,
+            linked
+        ))?;
+    }
     Ok(())
 }
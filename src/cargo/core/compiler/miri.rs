@@ -0,0 +1,105 @@
+//! Support for `cargo test --miri`, which runs the tests in the Miri
+//! interpreter to check them for undefined behavior.
+//!
+//! Everything built for the target is compiled by Miri acting as rustc
+//! (`MIRI_BE_RUSTC=target`), which keeps the MIR of all functions in the
+//! crate metadata. Build scripts and proc-macros are compiled by rustc and run
+//! natively as usual, which is why `--miri` always builds for an explicit
+//! target. The standard library comes from the sysroot in `MIRI_SYSROOT` if
+//! it is set, otherwise it is built along with the tests like `-Zbuild-std`
+//! does, and shared by all of them.
+//!
+//! Miri interprets a crate from its sources, so a test executable is of no
+//! use to it. Instead, the compiler invocation of each test is saved next to
+//! the executable, and running the test replays that invocation with Miri.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::path::{Path, PathBuf};
+
+use crate::util::errors::CargoResult;
+use anyhow::{bail, Context as _};
+use cargo_util::{paths, ProcessBuilder};
+use serde::{Deserialize, Serialize};
+
+/// The environment variable that makes Miri behave like rustc.
+const MIRI_BE_RUSTC: &str = "MIRI_BE_RUSTC";
+
+/// How a test was compiled, which is all Miri needs to interpret it.
+#[derive(Serialize, Deserialize)]
+struct MiriInvocation {
+    args: Vec<String>,
+    env: BTreeMap<String, Option<String>>,
+}
+
+/// Finds the `miri` executable, either from the `MIRI` environment variable
+/// or next to `rustc` in the sysroot.
+pub fn find_miri(sysroot_bindir: &Path) -> CargoResult<PathBuf> {
+    if let Some(path) = env::var_os("MIRI") {
+        return Ok(PathBuf::from(path));
+    }
+    let path = sysroot_bindir.join(format!("miri{}", env::consts::EXE_SUFFIX));
+    if !path.exists() {
+        bail!(
+            "`cargo test --miri` requires `miri`, which was not found in `{}`\n\
+             Install it with `rustup component add miri`, \
+             or set the `MIRI` environment variable to its path.",
+            sysroot_bindir.display()
+        );
+    }
+    Ok(path)
+}
+
+/// Returns a process running Miri as rustc, to compile code for the target.
+pub fn rustc_process(miri: &Path) -> ProcessBuilder {
+    let mut p = ProcessBuilder::new(miri);
+    p.env(MIRI_BE_RUSTC, "target");
+    p
+}
+
+/// Where the invocation of the test executable `exe` is saved.
+fn invocation_path(exe: &Path) -> PathBuf {
+    let mut path = exe.as_os_str().to_os_string();
+    path.push(".miri.json");
+    PathBuf::from(path)
+}
+
+/// Saves the command which compiled the test executable `exe`.
+pub fn save_invocation(rustc: &ProcessBuilder, exe: &Path) -> CargoResult<()> {
+    let invocation = MiriInvocation {
+        args: rustc
+            .get_args()
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect(),
+        env: rustc
+            .get_envs()
+            .iter()
+            .filter(|(key, _)| key.as_str() != MIRI_BE_RUSTC)
+            .map(|(key, value)| {
+                let value = value.as_ref().map(|v| v.to_string_lossy().into_owned());
+                (key.clone(), value)
+            })
+            .collect(),
+    };
+    paths::write(invocation_path(exe), serde_json::to_vec(&invocation)?)
+}
+
+/// Turns `cmd`, which would run the test executable `exe`, into a command
+/// interpreting the test with Miri. The arguments for the test are to be
+/// added afterwards.
+pub fn interpret(cmd: &mut ProcessBuilder, miri: &Path, exe: &Path) -> CargoResult<()> {
+    let path = invocation_path(exe);
+    let invocation: MiriInvocation = serde_json::from_str(&paths::read(&path)?)
+        .with_context(|| format!("invalid Miri invocation in `{}`", path.display()))?;
+    cmd.program(miri);
+    cmd.args_replace(&invocation.args);
+    cmd.arg("--");
+    for (key, value) in &invocation.env {
+        match value {
+            Some(value) => cmd.env(key, value),
+            None => cmd.env_remove(key),
+        };
+    }
+    Ok(())
+}
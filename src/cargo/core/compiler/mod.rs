@@ -1,25 +1,46 @@
+mod assets;
 mod build_config;
 mod build_context;
+mod build_info;
 mod build_plan;
+pub mod build_stats;
+pub mod check_cfg;
 mod compilation;
 mod compile_kind;
+mod config_flags;
 mod context;
 mod crate_type;
 mod custom_build;
+pub mod doc_index;
 mod fingerprint;
 pub mod future_incompat;
+pub mod headers;
 mod job;
 mod job_queue;
 mod layout;
+pub mod link_graph;
 mod links;
 mod lto;
+pub mod miri;
 mod output_depinfo;
+pub mod package_hook;
+pub mod pkg_config;
+pub mod remote_runner;
 pub mod rustdoc;
+pub mod separate_debuginfo;
+pub mod signing;
 pub mod standard_lib;
+pub mod staticlib_bundle;
 mod timings;
+pub mod toolchain_file;
 mod unit;
 pub mod unit_dependencies;
 pub mod unit_graph;
+pub mod universal;
+mod warning_summary;
+pub mod wasm_component;
+pub mod wasm_runner;
+pub mod windows_resources;
 
 use std::collections::HashSet;
 use std::env;
@@ -33,7 +54,7 @@ use anyhow::{Context as _, Error};
 use lazycell::LazyCell;
 use log::debug;
 
-pub use self::build_config::{BuildConfig, CompileMode, MessageFormat};
+pub use self::build_config::{BuildConfig, CompileMode, MessageFormat, WarningHandling};
 pub use self::build_context::{
     BuildContext, FileFlavor, FileType, RustDocFingerprint, RustcTargetData, TargetInfo,
 };
@@ -43,17 +64,19 @@ pub use self::compile_kind::{CompileKind, CompileTarget};
 pub use self::context::{Context, Metadata};
 pub use self::crate_type::CrateType;
 pub use self::custom_build::{BuildOutput, BuildScriptOutputs, BuildScripts};
+pub use self::fingerprint::{parse_rustc_dep_info, RustcDepInfo, TestResultCache};
 pub use self::job::Freshness;
 use self::job::{Job, Work};
 use self::job_queue::{JobQueue, JobState};
-pub(crate) use self::layout::Layout;
+pub(crate) use self::layout::{rustc_namespace, Layout};
 pub use self::lto::Lto;
 use self::output_depinfo::output_depinfo;
+use self::pkg_config::PkgConfig;
 use self::unit_graph::UnitDep;
 use crate::core::compiler::future_incompat::FutureIncompatReport;
 pub use crate::core::compiler::unit::{Unit, UnitInterner};
 use crate::core::manifest::TargetSourcePath;
-use crate::core::profiles::{PanicStrategy, Profile, Strip};
+use crate::core::profiles::{PanicStrategy, Profile, Strip, TrimPaths};
 use crate::core::{Feature, PackageId, Target};
 use crate::util::errors::{CargoResult, VerboseError};
 use crate::util::interning::InternedString;
@@ -213,6 +236,9 @@ fn rustc(cx: &mut Context<'_, '_>, unit: &Unit, exec: &Arc<dyn Executor>) -> Car
     let buildkey = unit.buildkey();
 
     add_cap_lints(cx.bcx, unit, &mut rustc);
+    if denies_warnings(cx.bcx, unit) {
+        rustc.arg("-D").arg("warnings");
+    }
 
     let outputs = cx.outputs(unit)?;
     let root = cx.files().out_dir(unit);
@@ -226,6 +252,13 @@ fn rustc(cx: &mut Context<'_, '_>, unit: &Unit, exec: &Arc<dyn Executor>) -> Car
     // If we are a binary and the package also contains a library, then we
     // don't pass the `-l` flags.
     let pass_l_flag = unit.target.is_lib() || !unit.pkg.targets().iter().any(|t| t.is_lib());
+    // A bundled staticlib merges the static libraries of build scripts, and
+    // the pkg-config file of a staticlib has the native libraries left to
+    // link with.
+    let bundle = staticlib_bundle::is_bundled(cx, unit);
+    if let Some(print_arg) = PkgConfig::new(cx, unit).and_then(|pc| pc.print_arg()) {
+        rustc.arg("--print").arg(print_arg);
+    }
 
     let dep_info_name = if cx.files().use_extra_filename(unit) {
         format!(
@@ -260,7 +293,44 @@ fn rustc(cx: &mut Context<'_, '_>, unit: &Unit, exec: &Arc<dyn Executor>) -> Car
         .to_path_buf();
     let fingerprint_dir = cx.files().fingerprint_dir(unit);
     let script_metadata = cx.find_build_script_metadata(unit);
+    let unchecked_cfg_args = check_cfg::unchecked_args(cx.bcx, unit);
     let is_local = unit.is_local();
+    let miri_test =
+        if cx.bcx.build_config.miri && unit.mode == CompileMode::Test && !unit.kind.is_host() {
+            outputs
+                .iter()
+                .find(|output| output.flavor == FileFlavor::Normal)
+                .map(|output| output.path.clone())
+        } else {
+            None
+        };
+    let component_step = wasm_component::ComponentStep::new(cx, unit)?;
+    let resource_step = windows_resources::ResourceStep::new(cx, unit)?;
+    if let Some(step) = &resource_step {
+        rustc.arg("-C").arg(step.link_arg());
+    }
+    let signer = signing::Signer::new(cx, unit)?;
+    let signed: Vec<_> = outputs
+        .iter()
+        .filter(|output| signer.is_some() && output.flavor == FileFlavor::Normal)
+        .map(|output| output.path.clone())
+        .collect();
+    // The files read by Cargo itself to build the unit.
+    let mut inputs = Vec::new();
+    if let Some(step) = &resource_step {
+        inputs.extend_from_slice(step.inputs());
+    }
+    if let Some(signer) = &signer {
+        inputs.extend(signer.inputs());
+    }
+    let separate_debuginfo: Vec<_> = outputs
+        .iter()
+        .filter(|output| {
+            output.flavor == FileFlavor::DebugInfo
+                && output.path.extension() == Some(OsStr::new(separate_debuginfo::DEBUG_EXTENSION))
+        })
+        .map(|output| (output.path.with_extension(""), output.path.clone()))
+        .collect();
 
     return Ok(Work::new(move |state| {
         // Only at runtime have we discovered what the extra -L and -l
@@ -278,12 +348,19 @@ fn rustc(cx: &mut Context<'_, '_>, unit: &Unit, exec: &Arc<dyn Executor>) -> Car
                     &script_outputs,
                     &build_scripts,
                     pass_l_flag,
+                    bundle,
                     &target,
                     current_id,
                 )?;
                 add_plugin_deps(&mut rustc, &script_outputs, &build_scripts, &root_output)?;
             }
             add_custom_env(&mut rustc, &script_outputs, script_metadata);
+            if let Some(output) = script_metadata.and_then(|meta| script_outputs.get(meta)) {
+                rustc.args(&check_cfg::build_script_args(
+                    &output.check_cfgs,
+                    &unchecked_cfg_args,
+                ));
+            }
         }
 
         for output in outputs.iter() {
@@ -316,6 +393,9 @@ fn rustc(cx: &mut Context<'_, '_>, unit: &Unit, exec: &Arc<dyn Executor>) -> Car
         if build_plan {
             state.build_plan(buildkey, rustc.clone(), outputs.clone());
         } else {
+            if let Some(step) = &resource_step {
+                step.run()?;
+            }
             exec.exec(
                 &rustc,
                 package_id,
@@ -350,6 +430,23 @@ fn rustc(cx: &mut Context<'_, '_>, unit: &Unit, exec: &Arc<dyn Executor>) -> Car
             })?;
             // Exec should never return with success *and* generate an error.
             debug_assert_eq!(output_options.errors_seen, 0);
+            if let Some(exe) = &miri_test {
+                miri::save_invocation(&rustc, exe)?;
+            }
+            if let Some(step) = &component_step {
+                step.run()?;
+            }
+            for (binary, debug) in &separate_debuginfo {
+                separate_debuginfo::separate(binary, debug)?;
+            }
+            if let Some(signer) = &signer {
+                for path in &signed {
+                    let cmd = signer.command(path);
+                    state.running(&cmd);
+                    cmd.exec_with_output()
+                        .with_context(|| format!("failed to sign `{}`", path.display()))?;
+                }
+            }
         }
 
         if rustc_dep_info_loc.exists() {
@@ -362,6 +459,7 @@ fn rustc(cx: &mut Context<'_, '_>, unit: &Unit, exec: &Arc<dyn Executor>) -> Car
                 &rustc,
                 // Do not track source files in the fingerprint for registry dependencies.
                 is_local,
+                &inputs,
             )
             .with_context(|| {
                 internal(format!(
@@ -384,6 +482,7 @@ fn rustc(cx: &mut Context<'_, '_>, unit: &Unit, exec: &Arc<dyn Executor>) -> Car
         build_script_outputs: &BuildScriptOutputs,
         build_scripts: &BuildScripts,
         pass_l_flag: bool,
+        bundle: bool,
         target: &Target,
         current_id: PackageId,
     ) -> CargoResult<()> {
@@ -404,7 +503,16 @@ fn rustc(cx: &mut Context<'_, '_>, unit: &Unit, exec: &Arc<dyn Executor>) -> Car
                 }
                 if pass_l_flag {
                     for name in output.library_links.iter() {
-                        rustc.arg("-l").arg(name);
+                        match staticlib_bundle::bundled_link(name) {
+                            Some(bundled) if bundle => rustc.arg("-l").arg(bundled),
+                            _ => rustc.arg("-l").arg(name),
+                        };
+                    }
+                }
+            } else if bundle {
+                for name in output.library_links.iter() {
+                    if let Some(bundled) = staticlib_bundle::bundled_link(name) {
+                        rustc.arg("-l").arg(bundled);
                     }
                 }
             }
@@ -453,6 +561,7 @@ fn link_targets(cx: &mut Context<'_, '_>, unit: &Unit, fresh: bool) -> CargoResu
     let features = unit.features.iter().map(|s| s.to_string()).collect();
     let json_messages = bcx.build_config.emit_json();
     let executable = cx.get_executable(unit)?;
+    let pkg_config = PkgConfig::new(cx, unit);
     let mut target = Target::clone(&unit.target);
     if let TargetSourcePath::Metabuild = target.src_path() {
         // Give it something to serialize.
@@ -490,6 +599,10 @@ fn link_targets(cx: &mut Context<'_, '_>, unit: &Unit, fresh: bool) -> CargoResu
             }
         }
 
+        if let Some(pkg_config) = &pkg_config {
+            pkg_config.write()?;
+        }
+
         if json_messages {
             let art_profile = machine_message::ArtifactProfile {
                 opt_level: profile.opt_level.as_str(),
@@ -624,6 +737,9 @@ fn rustdoc(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Work> {
     rustdoc.arg("--crate-name").arg(&crate_name);
     add_path_args(bcx.ws, unit, &mut rustdoc);
     add_cap_lints(bcx, unit, &mut rustdoc);
+    if denies_warnings(bcx, unit) {
+        rustdoc.arg("-D").arg("warnings");
+    }
 
     if let CompileKind::Target(target) = unit.kind {
         rustdoc.arg("--target").arg(target.rustc_target());
@@ -640,6 +756,8 @@ fn rustdoc(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Work> {
     for feat in &unit.features {
         rustdoc.arg("--cfg").arg(&format!("feature=\"{}\"", feat));
     }
+    rustdoc.args(&config_flags::cfg_args(bcx.ws, unit));
+    rustdoc.args(&check_cfg::args(bcx, unit));
 
     add_error_format_and_color(cx, &mut rustdoc, false);
     add_allow_features(cx, &mut rustdoc);
@@ -705,12 +823,17 @@ fn rustdoc(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Work> {
     let target = Target::clone(&unit.target);
     let mut output_options = OutputOptions::new(cx, unit);
     let script_metadata = cx.find_build_script_metadata(unit);
+    let unchecked_cfg_args = check_cfg::unchecked_args(cx.bcx, unit);
     Ok(Work::new(move |state| {
         if let Some(script_metadata) = script_metadata {
             if let Some(output) = build_script_outputs.lock().unwrap().get(script_metadata) {
                 for cfg in output.cfgs.iter() {
                     rustdoc.arg("--cfg").arg(cfg);
                 }
+                rustdoc.args(&check_cfg::build_script_args(
+                    &output.check_cfgs,
+                    &unchecked_cfg_args,
+                ));
                 for &(ref name, ref value) in output.env.iter() {
                     rustdoc.env(name, value);
                 }
@@ -773,6 +896,25 @@ fn add_cap_lints(bcx: &BuildContext<'_, '_>, unit: &Unit, cmd: &mut ProcessBuild
     }
 }
 
+/// Whether the warnings of `unit` are denied, because it is a workspace
+/// member and the root manifest sets `lints.workspace-deny-warnings`, or its
+/// package was requested with `--deny-warnings`.
+///
+/// Unlike `RUSTFLAGS=-Dwarnings`, this leaves the dependencies, and their
+/// fingerprints, alone. The warnings of the packages which aren't local
+/// couldn't be denied anyway, as their lints are capped.
+fn denies_warnings(bcx: &BuildContext<'_, '_>, unit: &Unit) -> bool {
+    if !unit.is_local() {
+        return false;
+    }
+    (bcx.ws.root_deny_warnings() && bcx.ws.is_member(&unit.pkg))
+        || (bcx.build_config.deny_warnings
+            && bcx
+                .roots
+                .iter()
+                .any(|root| root.pkg.package_id() == unit.pkg.package_id()))
+}
+
 /// Forward -Zallow-features if it is set for cargo.
 fn add_allow_features(cx: &Context<'_, '_>, cmd: &mut ProcessBuilder) {
     if let Some(allow) = &cx.bcx.config.cli_unstable().allow_features {
@@ -827,6 +969,44 @@ fn add_error_format_and_color(cx: &Context<'_, '_>, cmd: &mut ProcessBuilder, pi
     }
 }
 
+/// Adds the flags making the paths of the source files relative in the outputs
+/// selected by `trim_paths`.
+///
+/// The sources of workspace members are relative to the workspace root, and
+/// those of other packages to a `<name>-<version>` directory. When debuginfo
+/// is split, the paths to the split files are relative to the workspace root
+/// too, or to `target` if the target directory is outside of it.
+fn trim_paths_args(
+    bcx: &BuildContext<'_, '_>,
+    cmd: &mut ProcessBuilder,
+    unit: &Unit,
+    trim_paths: TrimPaths,
+    split_debuginfo: bool,
+) {
+    cmd.arg(format!("--remap-path-scope={}", trim_paths));
+    let ws_root = bcx.ws.root();
+    let mut remap = |from: &Path, to: &str| {
+        let mut arg = OsString::from("--remap-path-prefix=");
+        arg.push(from);
+        arg.push("=");
+        arg.push(to);
+        cmd.arg(arg);
+    };
+    // When several prefixes match, the last one wins.
+    remap(ws_root, "");
+    let target_dir = bcx.ws.target_dir().into_path_unlocked();
+    if split_debuginfo && trim_paths.object && !target_dir.starts_with(ws_root) {
+        remap(&target_dir, "target");
+    }
+    let pkg_root = unit.pkg.root();
+    if !pkg_root.starts_with(ws_root) {
+        remap(
+            pkg_root,
+            &format!("{}-{}", unit.pkg.name(), unit.pkg.version()),
+        );
+    }
+}
+
 fn build_base_args(
     cx: &mut Context<'_, '_>,
     cmd: &mut ProcessBuilder,
@@ -848,6 +1028,8 @@ fn build_base_args(
         ref panic,
         incremental,
         strip,
+        sanitizer,
+        trim_paths,
         ..
     } = unit.profile;
     let test = unit.mode.is_any_test();
@@ -863,7 +1045,7 @@ fn build_base_args(
 
     if !test {
         for crate_type in crate_types.iter() {
-            cmd.arg("--crate-type").arg(crate_type.as_str());
+            cmd.arg("--crate-type").arg(crate_type.rustc_crate_type());
         }
     }
 
@@ -878,7 +1060,7 @@ fn build_base_args(
         cmd.arg("--emit=dep-info,link");
     }
 
-    let prefer_dynamic = (unit.target.for_host() && !unit.target.is_custom_build())
+    let prefer_dynamic = (unit.target.for_host() && !unit.target.is_build_program())
         || (crate_types.contains(&CrateType::Dylib) && !cx.is_primary_package(unit));
     if prefer_dynamic {
         cmd.arg("-C").arg("prefer-dynamic");
@@ -960,6 +1142,8 @@ fn build_base_args(
     for feat in &unit.features {
         cmd.arg("--cfg").arg(&format!("feature=\"{}\"", feat));
     }
+    cmd.args(&config_flags::cfg_args(bcx.ws, unit));
+    cmd.args(&check_cfg::args(bcx, unit));
 
     let meta = cx.files().metadata(unit);
     cmd.arg("-C").arg(&format!("metadata={}", meta));
@@ -996,8 +1180,26 @@ fn build_base_args(
         opt(cmd, "-C", "incremental=", Some(dir));
     }
 
-    if strip != Strip::None {
-        cmd.arg("-Z").arg(format!("strip={}", strip));
+    match strip {
+        Strip::None => {}
+        Strip::Named(_) => {
+            cmd.arg("-Z").arg(format!("strip={}", strip));
+        }
+        Strip::Separate => {
+            // Only rustc can strip after `dsymutil`, the other targets are
+            // handled in `separate_debuginfo`.
+            if bcx.target_data.short_name(&unit.kind).contains("-apple-") {
+                cmd.arg("-Z").arg("strip=debuginfo");
+            }
+        }
+    }
+
+    if let Some(sanitizer) = sanitizer {
+        cmd.arg("-Z").arg(format!("sanitizer={}", sanitizer));
+    }
+
+    if !trim_paths.is_none() {
+        trim_paths_args(bcx, cmd, unit, trim_paths, split_debuginfo.is_some());
     }
 
     if unit.is_std {
@@ -1108,7 +1310,10 @@ fn build_deps_args(
 
     for dep in deps {
         if dep.unit.mode.is_run_custom_build() {
-            cmd.env("OUT_DIR", &cx.files().build_script_out_dir(&dep.unit));
+            cmd.env(
+                &custom_build::out_dir_env(&dep.unit.target),
+                &cx.files().build_script_out_dir(&dep.unit),
+            );
         }
     }
 
@@ -1351,6 +1556,11 @@ fn on_stderr_line_inner(
                 rendered: String,
                 message: String,
                 level: String,
+                code: Option<DiagnosticCode>,
+            }
+            #[derive(serde::Deserialize)]
+            struct DiagnosticCode {
+                code: String,
             }
             if let Ok(mut msg) = serde_json::from_str::<CompilerMessage>(compiler_message.get()) {
                 if msg.message.starts_with("aborting due to")
@@ -1360,6 +1570,11 @@ fn on_stderr_line_inner(
                     // Skip this line; we'll print our own summary at the end.
                     return Ok(true);
                 }
+                if msg.message.starts_with("native artifacts to link against") {
+                    // Asked for by the pkg-config file of a staticlib, which
+                    // has them instead.
+                    return Ok(true);
+                }
                 // state.stderr will add a newline
                 if msg.rendered.ends_with('\n') {
                     msg.rendered.pop();
@@ -1375,7 +1590,7 @@ fn on_stderr_line_inner(
                 };
                 if options.show_diagnostics {
                     count_diagnostic(&msg.level, options);
-                    state.emit_diag(msg.level, rendered)?;
+                    state.emit_diag(msg.level, rendered, msg.code.map(|c| c.code))?;
                 }
                 return Ok(true);
             }
@@ -0,0 +1,152 @@
+//! Packaging hooks, run after linking with `-Z package-hooks`.
+//!
+//! Some targets can't run a bare executable: an Android app has to be
+//! assembled into an APK, an iOS one bundled and codesigned. The
+//! `target.<triple>.package-hook` program does that, once everything for the
+//! target is built, with the set of artifacts described in the JSON file
+//! whose path is in its `CARGO_ARTIFACTS` environment variable:
+//!
+//! ```json
+//! {
+//!     "target": "aarch64-linux-android",
+//!     "profile": "dev",
+//!     "artifacts": [
+//!         {"package": "foo", "name": "foo", "kind": "bin", "test": false, "path": "/.../foo"}
+//!     ]
+//! }
+//! ```
+//!
+//! The hook answers on stdout, one JSON object per line, with the packages it
+//! created, like `{"artifact":"/.../foo","package":"/.../foo.apk"}`. `cargo
+//! run` and `cargo test` then hand the package to the target's runner instead
+//! of the artifact.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context as _};
+use cargo_util::{paths, ProcessBuilder};
+use serde::{Deserialize, Serialize};
+
+use super::{Context, UnitOutput};
+use crate::util::{config, CargoResult};
+
+#[derive(Serialize)]
+struct Artifacts<'a> {
+    target: &'a str,
+    profile: &'a str,
+    artifacts: Vec<Artifact<'a>>,
+}
+
+#[derive(Serialize)]
+struct Artifact<'a> {
+    package: &'a str,
+    name: &'a str,
+    kind: &'a str,
+    test: bool,
+    path: &'a Path,
+}
+
+#[derive(Deserialize)]
+struct Packaged {
+    artifact: PathBuf,
+    package: PathBuf,
+}
+
+/// Runs the packaging hooks of the requested targets, and replaces the
+/// artifacts they packaged with their packages in the compilation.
+pub fn run(cx: &mut Context<'_, '_>) -> CargoResult<()> {
+    let bcx = cx.bcx;
+    for kind in &bcx.build_config.requested_kinds {
+        let target = bcx.target_data.short_name(kind);
+        let key = format!("target.{}.package-hook", target);
+        let hook = match bcx.config.get::<Option<config::PathAndArgs>>(&key)? {
+            Some(hook) => hook,
+            None => continue,
+        };
+        if !bcx.config.cli_unstable().package_hooks {
+            bail!("`{}` requires `-Z package-hooks`", key);
+        }
+
+        let compilation = &mut cx.compilation;
+        let outputs: Vec<&UnitOutput> = compilation
+            .tests
+            .iter()
+            .chain(&compilation.binaries)
+            .chain(&compilation.cdylibs)
+            .filter(|output| output.unit.kind == *kind && !output.unit.mode.is_check())
+            .collect();
+        if outputs.is_empty() {
+            continue;
+        }
+        let artifacts = Artifacts {
+            target,
+            profile: &bcx.build_config.requested_profile,
+            artifacts: outputs
+                .iter()
+                .map(|output| Artifact {
+                    package: output.unit.pkg.name().as_str(),
+                    name: output.unit.target.name(),
+                    kind: output.unit.target.kind().description(),
+                    test: output.unit.mode.is_any_test(),
+                    path: &output.path,
+                })
+                .collect(),
+        };
+        let file = compilation.root_output[kind].join("package-hook.json");
+        paths::write(&file, serde_json::to_vec(&artifacts)?)?;
+
+        let mut names: Vec<_> = outputs.iter().map(|o| o.unit.target.name()).collect();
+        names.dedup();
+        bcx.config
+            .shell()
+            .status("Packaging", format!("{} for {}", names.join(", "), target))?;
+        let mut cmd = ProcessBuilder::new(hook.path.resolve_program(bcx.config));
+        cmd.args(&hook.args)
+            .env("CARGO_ARTIFACTS", &file)
+            .cwd(bcx.ws.root());
+        bcx.config
+            .shell()
+            .verbose(|shell| shell.status("Running", &cmd))?;
+        let mut messages = Vec::new();
+        cmd.exec_with_streaming(
+            &mut |line| {
+                messages.push(line.to_string());
+                Ok(())
+            },
+            &mut |line| {
+                writeln!(bcx.config.shell().err(), "{}", line)?;
+                Ok(())
+            },
+            false,
+        )
+        .with_context(|| format!("package hook for `{}` failed, set in `{}`", target, key))?;
+
+        for line in messages.iter().filter(|line| !line.trim().is_empty()) {
+            let packaged: Packaged = serde_json::from_str(line).with_context(|| {
+                format!(
+                    "invalid message from the package hook for `{}`: `{}`",
+                    target, line
+                )
+            })?;
+            let mut found = false;
+            for output in compilation
+                .tests
+                .iter_mut()
+                .chain(&mut compilation.binaries)
+                .chain(&mut compilation.cdylibs)
+                .filter(|output| output.unit.kind == *kind && output.path == packaged.artifact)
+            {
+                output.path = packaged.package.clone();
+                found = true;
+            }
+            if !found {
+                bail!(
+                    "the package hook for `{}` packaged `{}`, which is not one of its artifacts",
+                    target,
+                    packaged.artifact.display()
+                );
+            }
+        }
+    }
+    Ok(())
+}
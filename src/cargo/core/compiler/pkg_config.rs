@@ -0,0 +1,237 @@
+//! pkg-config files of `cdylib` and `staticlib` libraries, written with
+//! `cargo build --emit-pc` or for the bundled staticlibs of
+//! `-Z staticlib-bundle`.
+//!
+//! Once a library is linked, its file is written next to it, in
+//! `target/<profile>/pkgconfig/<name>.pc`, so that C build systems can find
+//! it through `PKG_CONFIG_PATH`. The `[package.pkg-config]` table of the
+//! `pkg-config` feature sets its name, its description and extra fields; the
+//! include directory of the `c-headers` feature is added to `Cflags` when the
+//! package has a `[headers]` table.
+//!
+//! A `staticlib` needs the native libraries of its dependencies to be linked
+//! as well. They are printed by rustc with `--print native-static-libs`, and
+//! are added to `Libs` with the search paths of the build scripts linking
+//! them, or to `Libs.private` when the library is also a `cdylib`.
+
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use cargo_util::paths;
+
+use super::staticlib_bundle::{self, bundled_link};
+use super::{BuildScriptOutputs, BuildScripts, CompileMode, Context, CrateType, Unit};
+use crate::util::toml::TomlPkgConfig;
+use crate::util::CargoResult;
+
+/// What is needed to write the pkg-config file of a library once it is
+/// linked.
+pub struct PkgConfig {
+    /// The pkg-config file written next to the library.
+    pc_file: PathBuf,
+    /// Where rustc writes the native libraries to link with, for a
+    /// `staticlib`.
+    native_static_libs: Option<PathBuf>,
+    /// Whether the library is also a `cdylib`, which links its native
+    /// libraries itself.
+    is_cdylib: bool,
+    /// Whether `-Z staticlib-bundle` merged the static libraries of build
+    /// scripts linked with `-bundle` into the `staticlib`.
+    bundled: bool,
+    crate_name: String,
+    name: String,
+    description: String,
+    version: String,
+    has_headers: bool,
+    fields: TomlPkgConfig,
+    build_scripts: Option<Arc<BuildScripts>>,
+    build_script_outputs: Arc<Mutex<BuildScriptOutputs>>,
+}
+
+impl PkgConfig {
+    /// Returns the pkg-config file of `unit` if it links a `cdylib` or a
+    /// `staticlib` with `--emit-pc`, or a bundled `staticlib`.
+    pub fn new(cx: &Context<'_, '_>, unit: &Unit) -> Option<PkgConfig> {
+        if unit.mode != CompileMode::Build || !unit.target.is_lib() {
+            return None;
+        }
+        let crate_types = unit.target.rustc_crate_types();
+        let is_staticlib = crate_types.contains(&CrateType::Staticlib);
+        let is_cdylib = crate_types.contains(&CrateType::Cdylib);
+        let bundled = staticlib_bundle::is_bundled(cx, unit);
+        if !bundled && !(cx.bcx.build_config.emit_pc && (is_staticlib || is_cdylib)) {
+            return None;
+        }
+
+        let crate_name = unit.target.crate_name();
+        let fields = unit
+            .pkg
+            .manifest()
+            .original()
+            .pkg_config()
+            .cloned()
+            .unwrap_or_default();
+        let name = fields.name.clone().unwrap_or_else(|| crate_name.clone());
+        let description = fields
+            .description
+            .as_deref()
+            .or_else(|| unit.pkg.manifest().metadata().description.as_deref())
+            .map(|d| d.trim().replace('\n', " "))
+            .unwrap_or_else(|| unit.pkg.name().to_string());
+        Some(PkgConfig {
+            pc_file: cx
+                .files()
+                .layout(unit.kind)
+                .dest()
+                .join("pkgconfig")
+                .join(format!("{}.pc", name)),
+            native_static_libs: if is_staticlib {
+                Some(
+                    cx.files()
+                        .fingerprint_file_path(unit, "native-static-libs-"),
+                )
+            } else {
+                None
+            },
+            is_cdylib,
+            bundled,
+            crate_name,
+            name,
+            description,
+            version: unit.pkg.version().to_string(),
+            has_headers: unit.pkg.manifest().original().headers().is_some(),
+            fields,
+            build_scripts: cx.build_scripts.get(unit).cloned(),
+            build_script_outputs: Arc::clone(&cx.build_script_outputs),
+        })
+    }
+
+    /// The argument of `--print` making rustc write the native libraries of
+    /// a `staticlib`.
+    pub fn print_arg(&self) -> Option<OsString> {
+        let path = self.native_static_libs.as_ref()?;
+        let mut arg = OsString::from("native-static-libs=");
+        arg.push(path);
+        Some(arg)
+    }
+
+    /// Writes the pkg-config file of the library.
+    pub fn write(&self) -> CargoResult<()> {
+        let mut libs = vec!["-L${libdir}".to_string(), format!("-l{}", self.crate_name)];
+        libs.extend(self.fields.libs.iter().flatten().cloned());
+        let mut libs_private = self
+            .fields
+            .libs_private
+            .iter()
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>();
+        if let Some(native_static_libs) = &self.native_static_libs {
+            let native = self.native_libs(native_static_libs)?;
+            if self.is_cdylib {
+                libs_private.extend(native);
+            } else {
+                libs.extend(native);
+            }
+        }
+
+        let mut cflags = Vec::new();
+        if self.has_headers {
+            cflags.push("-I${includedir}".to_string());
+        }
+        cflags.extend(self.fields.cflags.iter().flatten().cloned());
+
+        let mut pc = String::from(
+            "prefix=${pcfiledir}/..\n\
+             libdir=${prefix}\n\
+             includedir=${prefix}/include\n\n",
+        );
+        pc.push_str(&format!("Name: {}\n", self.name));
+        pc.push_str(&format!("Description: {}\n", self.description));
+        pc.push_str(&format!("Version: {}\n", self.version));
+        for (key, values) in [
+            (
+                "Requires",
+                self.fields.requires.as_deref().unwrap_or_default(),
+            ),
+            (
+                "Requires.private",
+                self.fields.requires_private.as_deref().unwrap_or_default(),
+            ),
+            ("Libs", &libs),
+            ("Libs.private", &libs_private),
+            ("Cflags", &cflags),
+        ] {
+            if !values.is_empty() {
+                pc.push_str(&format!("{}: {}\n", key, values.join(" ")));
+            }
+        }
+
+        paths::create_dir_all(self.pc_file.parent().unwrap())?;
+        paths::write_if_changed(&self.pc_file, pc)
+    }
+
+    /// The flags printed by rustc to link the native libraries of a
+    /// `staticlib`, without the libraries merged into it, after the search
+    /// paths of the build scripts linking the others.
+    fn native_libs(&self, native_static_libs: &Path) -> CargoResult<Vec<String>> {
+        let printed = paths::read(native_static_libs)?;
+
+        let mut merged = HashSet::new();
+        let mut flags = Vec::new();
+        if let Some(build_scripts) = &self.build_scripts {
+            let outputs = self.build_script_outputs.lock().unwrap();
+            for (_, metadata) in build_scripts.to_link.iter() {
+                let output = match outputs.get(*metadata) {
+                    Some(output) => output,
+                    None => continue,
+                };
+                let mut links_remaining = false;
+                for link in output.library_links.iter() {
+                    if self.bundled && bundled_link(link).is_some() {
+                        merged.insert(format!("-l{}", link_name(link)));
+                    } else {
+                        links_remaining = true;
+                    }
+                }
+                if !links_remaining {
+                    continue;
+                }
+                for path in output.library_paths.iter() {
+                    if let Some(path) = native_search_path(path) {
+                        let flag = format!("-L{}", path.display());
+                        if !flags.contains(&flag) {
+                            flags.push(flag);
+                        }
+                    }
+                }
+            }
+        }
+        flags.extend(
+            printed
+                .split_whitespace()
+                .filter(|flag| !merged.contains(*flag))
+                .map(str::to_string),
+        );
+        Ok(flags)
+    }
+}
+
+/// The name of the library linked by `link`, without its kind and rename.
+fn link_name(link: &str) -> &str {
+    let name = link.split_once('=').map_or(link, |(_, name)| name);
+    name.split(':').next().unwrap()
+}
+
+/// The path of a `-L` argument of a build script searched for native
+/// libraries.
+fn native_search_path(path: &Path) -> Option<PathBuf> {
+    let s = path.to_str()?;
+    match s.split_once('=') {
+        Some(("native" | "all", path)) => Some(PathBuf::from(path)),
+        Some(("crate" | "dependency" | "framework", _)) => None,
+        _ => Some(path.to_path_buf()),
+    }
+}
@@ -0,0 +1,327 @@
+//! Runners that execute programs on another machine, with `-Z remote-runner`.
+//!
+//! When `target.<triple>.runner-protocol` is `"remote"`, the `runner` of the
+//! target is not run once per program. Instead it is started once, as a
+//! long-lived agent that knows how to reach the device or emulator (over
+//! `ssh`, `adb`, in `qemu`, ...), and Cargo asks it to run each program.
+//!
+//! Cargo talks to the agent over its stdin and stdout, one JSON object per
+//! line, each with a `type` and an `id` tying the responses to a request:
+//!
+//! * The agent first sends `{"type":"hello","version":1}`.
+//! * `{"type":"upload","id":1,"path":"/host/path/to/exe"}` asks it to copy
+//!   a program to the device. It answers with
+//!   `{"type":"uploaded","id":1,"path":"/remote/path"}`.
+//! * `{"type":"run","id":2,"program":"/remote/path","args":[..],"env":{..}}`
+//!   asks it to run an uploaded program. It answers with a
+//!   `{"type":"stdout","id":2,"line":".."}` or `{"type":"stderr",..}` for each
+//!   line of output, then with `{"type":"exit","id":2,"code":0}`.
+//! * Any request can be answered with `{"type":"error","id":..,"message":".."}`.
+//!
+//! Each program is only uploaded once. `target.<triple>.runner-jobs` limits
+//! how many tests run at the same time on the device, by setting
+//! `RUST_TEST_THREADS` for them. The agent is stopped by closing its stdin.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, format_err, Context as _};
+use cargo_util::{ProcessBuilder, ProcessError};
+use serde::{Deserialize, Serialize};
+
+use super::wasm_runner::forwarded_env;
+use crate::ops::test_logs::TestLog;
+use crate::util::toml::TomlDuration;
+use crate::util::{CargoResult, Config};
+
+/// The version of the protocol spoken with the agent.
+const PROTOCOL_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+enum Request<'a> {
+    Upload {
+        id: u64,
+        path: &'a Path,
+    },
+    Run {
+        id: u64,
+        program: &'a str,
+        args: Vec<String>,
+        env: BTreeMap<String, String>,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+enum Response {
+    Hello { version: u32 },
+    Uploaded { id: u64, path: String },
+    Stdout { id: u64, line: String },
+    Stderr { id: u64, line: String },
+    Exit { id: u64, code: i32 },
+    Error { id: u64, message: String },
+}
+
+/// The runner of a target configured with `runner-protocol = "remote"`.
+pub struct RemoteRunner {
+    path: PathBuf,
+    args: Vec<String>,
+    jobs: Option<u32>,
+    agent: RefCell<Option<Agent>>,
+}
+
+/// A running agent.
+struct Agent {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    responses: Receiver<String>,
+    /// The remote paths of the programs uploaded so far.
+    uploads: HashMap<PathBuf, String>,
+    next_id: u64,
+}
+
+impl RemoteRunner {
+    pub fn new(path: PathBuf, args: Vec<String>, jobs: Option<u32>) -> RemoteRunner {
+        RemoteRunner {
+            path,
+            args,
+            jobs,
+            agent: RefCell::new(None),
+        }
+    }
+
+    /// Runs `cmd` on the device, like [`ProcessBuilder::exec`] runs it
+    /// locally. The program is killed, by stopping the agent, if it is still
+    /// running after `timeout`. Its output is routed into `log` if given.
+    pub fn exec(
+        &self,
+        config: &Config,
+        cmd: &ProcessBuilder,
+        timeout: Option<Duration>,
+        mut log: Option<&mut TestLog>,
+    ) -> CargoResult<()> {
+        let mut agent = self.agent.borrow_mut();
+        if agent.is_none() {
+            *agent = Some(self.start()?);
+        }
+        let result =
+            agent
+                .as_mut()
+                .unwrap()
+                .run(config, cmd, self.jobs, timeout, log.as_deref_mut());
+        if let Some(log) = log {
+            log.finish(config)?;
+        }
+        match result {
+            Ok(Some(0)) => Ok(()),
+            Ok(Some(code)) => Err(ProcessError::new_raw(
+                &format!("process didn't exit successfully: {}", cmd),
+                Some(code),
+                &format!("exit status: {}", code),
+                None,
+                None,
+            )
+            .into()),
+            Ok(None) => {
+                // The program can't be stopped on its own, so the agent goes
+                // with it and is started again for the next program.
+                *agent = None;
+                Err(ProcessError::new_raw(
+                    &format!("process didn't exit successfully: {}", cmd),
+                    None,
+                    &format!("timed out after {}", TomlDuration(timeout.unwrap())),
+                    None,
+                    None,
+                )
+                .into())
+            }
+            Err(e) => {
+                *agent = None;
+                Err(e.context(format!("remote runner `{}` failed", self.path.display())))
+            }
+        }
+    }
+
+    fn start(&self) -> CargoResult<Agent> {
+        let mut child = Command::new(&self.path)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("failed to start remote runner `{}`", self.path.display()))?;
+        let stdout = child.stdout.take().unwrap();
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                match line {
+                    Ok(line) => {
+                        if tx.send(line).is_err() {
+                            break;
+                        }
+                    }
+                    Err(_) => break,
+                }
+            }
+        });
+        let mut agent = Agent {
+            stdin: child.stdin.take(),
+            child,
+            responses: rx,
+            uploads: HashMap::new(),
+            next_id: 1,
+        };
+        match agent.recv(None) {
+            Ok(Some(Response::Hello { version })) if version == PROTOCOL_VERSION => Ok(agent),
+            Ok(Some(Response::Hello { version })) => bail!(
+                "remote runner `{}` speaks version {} of the protocol, expected version {}",
+                self.path.display(),
+                version,
+                PROTOCOL_VERSION
+            ),
+            Ok(_) => bail!(
+                "remote runner `{}` did not greet with a `hello` message",
+                self.path.display()
+            ),
+            Err(e) => Err(e.context(format!(
+                "failed to start remote runner `{}`",
+                self.path.display()
+            ))),
+        }
+    }
+}
+
+impl Agent {
+    /// Runs `cmd`, returning its exit code, or `None` if it timed out.
+    fn run(
+        &mut self,
+        config: &Config,
+        cmd: &ProcessBuilder,
+        jobs: Option<u32>,
+        timeout: Option<Duration>,
+        mut log: Option<&mut TestLog>,
+    ) -> CargoResult<Option<i32>> {
+        let program = self.upload(Path::new(cmd.get_program()))?;
+        let mut env: BTreeMap<_, _> = forwarded_env(cmd).into_iter().collect();
+        if let Some(jobs) = jobs {
+            env.entry("RUST_TEST_THREADS".to_string())
+                .or_insert_with(|| jobs.to_string());
+        }
+        let id = self.send(|id| Request::Run {
+            id,
+            program: &program,
+            args: cmd
+                .get_args()
+                .iter()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect(),
+            env,
+        })?;
+
+        let start = Instant::now();
+        loop {
+            let wait = match timeout {
+                Some(timeout) => match timeout.checked_sub(start.elapsed()) {
+                    Some(wait) => Some(wait),
+                    None => return Ok(None),
+                },
+                None => None,
+            };
+            let response = match self.recv(wait)? {
+                Some(response) => response,
+                None => return Ok(None),
+            };
+            match response {
+                Response::Stdout { id: i, line } if i == id => match log.as_deref_mut() {
+                    Some(log) => log.write_line(config, &line)?,
+                    None => writeln!(config.shell().out(), "{}", line)?,
+                },
+                Response::Stderr { id: i, line } if i == id => match log.as_deref_mut() {
+                    Some(log) => log.write_line(config, &line)?,
+                    None => writeln!(config.shell().err(), "{}", line)?,
+                },
+                Response::Exit { id: i, code } if i == id => return Ok(Some(code)),
+                Response::Error { id: i, message } if i == id => bail!("{}", message),
+                _ => bail!("unexpected response to request {}", id),
+            }
+        }
+    }
+
+    /// Uploads the program at `path`, unless it already was, and returns its
+    /// path on the device.
+    fn upload(&mut self, path: &Path) -> CargoResult<String> {
+        if let Some(remote) = self.uploads.get(path) {
+            return Ok(remote.clone());
+        }
+        let id = self.send(|id| Request::Upload { id, path })?;
+        let remote = match self.recv(None)? {
+            Some(Response::Uploaded {
+                id: i,
+                path: remote,
+            }) if i == id => remote,
+            Some(Response::Error { id: i, message }) if i == id => {
+                return Err(format_err!("{}", message)
+                    .context(format!("failed to upload `{}`", path.display())));
+            }
+            _ => bail!("unexpected response to request {}", id),
+        };
+        self.uploads.insert(path.to_path_buf(), remote.clone());
+        Ok(remote)
+    }
+
+    /// Sends the request built by `request` with a new id, and returns the id.
+    fn send<'a>(&mut self, request: impl FnOnce(u64) -> Request<'a>) -> CargoResult<u64> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let mut line = serde_json::to_string(&request(id))?;
+        line.push('\n');
+        let stdin = self.stdin.as_mut().unwrap();
+        stdin
+            .write_all(line.as_bytes())
+            .and_then(|()| stdin.flush())
+            .context("failed to send a request")?;
+        Ok(id)
+    }
+
+    /// Waits up to `wait`, or indefinitely, for the next response. Returns
+    /// `None` if there was none in time.
+    fn recv(&mut self, wait: Option<Duration>) -> CargoResult<Option<Response>> {
+        let line = match wait {
+            Some(wait) => match self.responses.recv_timeout(wait) {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) => return Ok(None),
+                Err(RecvTimeoutError::Disconnected) => bail!("the runner exited unexpectedly"),
+            },
+            None => match self.responses.recv() {
+                Ok(line) => line,
+                Err(_) => bail!("the runner exited unexpectedly"),
+            },
+        };
+        let response = serde_json::from_str(&line)
+            .with_context(|| format!("invalid message from the runner: `{}`", line))?;
+        Ok(Some(response))
+    }
+}
+
+impl Drop for Agent {
+    fn drop(&mut self) {
+        // Closing its stdin asks the agent to stop, which it may not do if it
+        // is still busy with a program that timed out.
+        drop(self.stdin.take());
+        let start = Instant::now();
+        while let Ok(None) = self.child.try_wait() {
+            if start.elapsed() > Duration::from_secs(1) {
+                drop(self.child.kill());
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        drop(self.child.wait());
+    }
+}
@@ -0,0 +1,66 @@
+//! Support for `strip = "separate"`, which produces a stripped binary and
+//! its debug information as a separate file in the same build.
+//!
+//! How the debug information is separated depends on the target:
+//!
+//! * On Apple targets, rustc packs it into a `.dSYM` with `dsymutil`
+//!   (`-C split-debuginfo=packed`) before stripping the binary.
+//! * On MSVC targets, the linker always writes it to a `.pdb`, and the
+//!   binary only refers to that file.
+//! * Elsewhere, Cargo copies it with `objcopy --only-keep-debug` to a
+//!   `.debug` file after rustc has linked the binary, then strips the binary
+//!   with `objcopy --strip-debug`. The `OBJCOPY` environment variable
+//!   overrides the `objcopy` executable.
+//!
+//! The separate file is uplifted along with the binary, and is reported in
+//! the `filenames` of the `compiler-artifact` message.
+
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use crate::core::compiler::CrateType;
+use crate::util::errors::CargoResult;
+use anyhow::Context as _;
+use cargo_util::ProcessBuilder;
+
+/// The extension of the debug information file written by `objcopy`.
+pub const DEBUG_EXTENSION: &str = "debug";
+
+/// Returns whether Cargo separates the debug information of `crate_type`
+/// itself with `objcopy` for the target `triple`, instead of rustc or the
+/// linker doing it.
+pub fn uses_objcopy(triple: &str, crate_type: &CrateType) -> bool {
+    matches!(
+        crate_type,
+        CrateType::Bin | CrateType::Dylib | CrateType::Cdylib | CrateType::ProcMacro
+    ) && !triple.contains("-apple-")
+        && !triple.ends_with("-msvc")
+        && !triple.starts_with("wasm")
+}
+
+/// Returns the path of the debug information file of the binary in `path`.
+pub fn debug_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap().to_os_string();
+    name.push(".");
+    name.push(DEBUG_EXTENSION);
+    path.with_file_name(name)
+}
+
+/// Moves the debug information of the binary in `binary` to `debug`.
+pub fn separate(binary: &Path, debug: &Path) -> CargoResult<()> {
+    let objcopy = env::var_os("OBJCOPY").unwrap_or_else(|| OsString::from("objcopy"));
+    let mut keep_debug = ProcessBuilder::new(&objcopy);
+    keep_debug.arg("--only-keep-debug").arg(binary).arg(debug);
+    let mut strip_debug = ProcessBuilder::new(&objcopy);
+    strip_debug.arg("--strip-debug").arg(binary);
+    for cmd in [keep_debug, strip_debug] {
+        cmd.exec_with_output().with_context(|| {
+            format!(
+                "failed to separate the debug information of `{}`",
+                binary.display()
+            )
+        })?;
+    }
+    Ok(())
+}
@@ -0,0 +1,209 @@
+//! Code signing of the binaries and `cdylib`s built for Apple and Windows
+//! targets, after they are linked.
+//!
+//! The `signing` setting of the profile, and the `[target.<triple>.signing]`
+//! config table, whose keys take precedence, describe how an artifact is
+//! signed:
+//!
+//! * Apple targets are signed with `codesign`. A secure timestamp is only
+//!   requested with `timestamp-server` or `notarize`, which also enables the
+//!   hardened runtime required by the notarization service of Apple.
+//! * Windows targets are signed with `signtool`, with a certificate of the
+//!   store or a `.pfx` file.
+//!
+//! The `CODESIGN` and `SIGNTOOL` environment variables override these tools.
+//! The entitlements and `.pfx` files are tracked in the dep-info of the unit,
+//! and the settings in its fingerprint, so changing them signs the artifacts
+//! again.
+
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use anyhow::bail;
+use cargo_platform::Cfg;
+use cargo_util::ProcessBuilder;
+use serde::Deserialize;
+
+use super::{CompileMode, Context, CrateType, Unit};
+use crate::util::config::ConfigRelativePath;
+use crate::util::CargoResult;
+
+/// The `[target.<triple>.signing]` config table.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+struct SigningConfig {
+    identity: Option<String>,
+    entitlements: Option<ConfigRelativePath>,
+    timestamp_server: Option<String>,
+    notarize: Option<bool>,
+}
+
+#[derive(Hash, Clone, Copy, PartialEq)]
+enum Platform {
+    Apple,
+    Windows,
+}
+
+/// How the artifacts of a unit are signed.
+#[derive(Hash)]
+pub struct Signer {
+    platform: Platform,
+    identity: String,
+    /// A `.pfx` file holding the certificate, for Windows.
+    certificate_file: Option<PathBuf>,
+    entitlements: Option<PathBuf>,
+    timestamp_server: Option<String>,
+    notarize: bool,
+}
+
+impl Signer {
+    /// Returns the signer of `unit`, if it is a binary or a `cdylib` built
+    /// for an Apple or Windows target with a signing identity.
+    pub fn new(cx: &Context<'_, '_>, unit: &Unit) -> CargoResult<Option<Signer>> {
+        let crate_types = unit.target.rustc_crate_types();
+        if unit.mode != CompileMode::Build
+            || !(unit.target.is_bin()
+                || crate_types.contains(&CrateType::Cdylib)
+                || crate_types.contains(&CrateType::Dylib))
+        {
+            return Ok(None);
+        }
+        let cfg = cx.bcx.target_data.cfg(unit.kind);
+        let is = |key: &str, value: &str| {
+            cfg.iter()
+                .any(|c| matches!(c, Cfg::KeyPair(k, v) if k == key && v == value))
+        };
+        let platform = if is("target_vendor", "apple") {
+            Platform::Apple
+        } else if is("target_os", "windows") {
+            Platform::Windows
+        } else {
+            return Ok(None);
+        };
+
+        let config = cx.bcx.config;
+        let triple = cx.bcx.target_data.short_name(&unit.kind);
+        let key = format!("target.{}.signing", triple);
+        let target = config.get::<Option<SigningConfig>>(&key)?;
+        if target.is_some() && !config.cli_unstable().code_signing {
+            bail!("`{}` requires `-Z code-signing`", key);
+        }
+        let profile = unit.profile.signing;
+        if target.is_none() && profile.is_none() {
+            return Ok(None);
+        }
+        let target = target.unwrap_or_default();
+        let profile = profile.unwrap_or_default();
+        let ws_root = cx.bcx.ws.root();
+
+        let identity = match target
+            .identity
+            .or_else(|| profile.identity.map(|s| s.to_string()))
+        {
+            Some(identity) => identity,
+            None => bail!(
+                "no signing identity for `{}`, set `identity` in `profile.{}.signing` or `{}`",
+                triple,
+                unit.profile.name,
+                key
+            ),
+        };
+        let notarize = target.notarize.unwrap_or(profile.notarize);
+        if notarize && platform == Platform::Apple && identity == "-" {
+            bail!(
+                "the ad-hoc signing identity `-` of `{}` cannot be notarized, \
+                 a Developer ID certificate is required",
+                triple
+            );
+        }
+        let certificate_file = if platform == Platform::Windows
+            && (identity.ends_with(".pfx") || identity.ends_with(".p12"))
+        {
+            Some(ws_root.join(&identity))
+        } else {
+            None
+        };
+        let entitlements = match target.entitlements {
+            Some(path) => Some(path.resolve_path(config)),
+            None => profile.entitlements.map(|path| ws_root.join(path.as_str())),
+        };
+        Ok(Some(Signer {
+            platform,
+            identity,
+            certificate_file,
+            entitlements,
+            timestamp_server: target
+                .timestamp_server
+                .or_else(|| profile.timestamp_server.map(|s| s.to_string())),
+            notarize,
+        }))
+    }
+
+    /// The files read to sign the artifacts.
+    pub fn inputs(&self) -> Vec<PathBuf> {
+        self.certificate_file
+            .iter()
+            .chain(&self.entitlements)
+            .cloned()
+            .collect()
+    }
+
+    /// The command which signs the artifact at `path`.
+    pub fn command(&self, path: &Path) -> ProcessBuilder {
+        match self.platform {
+            Platform::Apple => {
+                let codesign =
+                    env::var_os("CODESIGN").unwrap_or_else(|| OsString::from("codesign"));
+                let mut cmd = ProcessBuilder::new(codesign);
+                cmd.arg("--force").arg("--sign").arg(&self.identity);
+                if let Some(entitlements) = &self.entitlements {
+                    cmd.arg("--entitlements").arg(entitlements);
+                }
+                if self.notarize {
+                    cmd.arg("--options").arg("runtime");
+                }
+                match &self.timestamp_server {
+                    Some(url) => {
+                        cmd.arg(format!("--timestamp={}", url));
+                    }
+                    None if self.notarize => {
+                        cmd.arg("--timestamp");
+                    }
+                    // Signing with a certificate requests a timestamp by
+                    // default, which needs the network.
+                    None if self.identity != "-" => {
+                        cmd.arg("--timestamp=none");
+                    }
+                    None => {}
+                }
+                cmd.arg(path);
+                cmd
+            }
+            Platform::Windows => {
+                let signtool =
+                    env::var_os("SIGNTOOL").unwrap_or_else(|| OsString::from("signtool"));
+                let mut cmd = ProcessBuilder::new(signtool);
+                cmd.arg("sign").arg("/fd").arg("SHA256");
+                match &self.certificate_file {
+                    Some(file) => {
+                        cmd.arg("/f").arg(file);
+                    }
+                    None if self.identity.len() == 40
+                        && self.identity.chars().all(|c| c.is_ascii_hexdigit()) =>
+                    {
+                        cmd.arg("/sha1").arg(&self.identity);
+                    }
+                    None => {
+                        cmd.arg("/n").arg(&self.identity);
+                    }
+                }
+                if let Some(url) = &self.timestamp_server {
+                    cmd.arg("/tr").arg(url).arg("/td").arg("SHA256");
+                }
+                cmd.arg(path);
+                cmd
+            }
+        }
+    }
+}
@@ -8,7 +8,7 @@ use crate::core::resolver::HasDevUnits;
 use crate::core::{Dependency, PackageId, PackageSet, Resolve, SourceId, Workspace};
 use crate::ops::{self, Packages};
 use crate::util::errors::CargoResult;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::env;
 use std::path::PathBuf;
 
@@ -65,11 +65,21 @@ pub fn resolve_std<'cfg>(
         &Some(members),
         /*default_members*/ &None,
         /*exclude*/ &None,
+        /*groups*/ &None,
+        /*links_policies*/ BTreeMap::new(),
+        /*changelog*/ None,
+        /*policy*/ None,
+        /*minimal_versions_exempt*/ BTreeSet::new(),
+        /*allow_prerelease*/ BTreeSet::new(),
         /*custom_metadata*/ &None,
     ));
     let virtual_manifest = crate::core::VirtualManifest::new(
         /*replace*/ Vec::new(),
         patch,
+        /*feature_overrides*/ BTreeMap::new(),
+        /*config_flags*/ BTreeMap::new(),
+        /*env*/ BTreeMap::new(),
+        /*deny_warnings*/ false,
         ws_config,
         /*profiles*/ None,
         crate::core::Features::default(),
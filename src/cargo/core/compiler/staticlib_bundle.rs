@@ -0,0 +1,40 @@
+//! Packaging of `staticlib` libraries for C projects with
+//! `-Z staticlib-bundle`.
+//!
+//! A `staticlib` already contains its Rust dependencies and the static
+//! native libraries which build scripts link with the default `+bundle`
+//! modifier. With this flag, the static libraries that build scripts link
+//! with `-bundle` are merged into the archive as well, by passing them to
+//! rustc with `+bundle` when it links the `staticlib`.
+//!
+//! The remaining native libraries, which the C linker still has to be told
+//! about, are written to the pkg-config file of the library, see the
+//! `pkg_config` module.
+
+use super::{CompileMode, Context, CrateType, Unit};
+
+/// Whether `unit` links a `staticlib` and `-Z staticlib-bundle` is enabled.
+pub fn is_bundled(cx: &Context<'_, '_>, unit: &Unit) -> bool {
+    cx.bcx.config.cli_unstable().staticlib_bundle
+        && unit.mode == CompileMode::Build
+        && unit.target.is_lib()
+        && unit
+            .target
+            .rustc_crate_types()
+            .contains(&CrateType::Staticlib)
+}
+
+/// Returns `link`, a `-l` argument of a build script, with the `+bundle`
+/// modifier if it links a static library with `-bundle`.
+pub fn bundled_link(link: &str) -> Option<String> {
+    let (kind, name) = link.split_once('=')?;
+    let (kind, modifiers) = kind.split_once(':')?;
+    if kind != "static" || !modifiers.split(',').any(|m| m == "-bundle") {
+        return None;
+    }
+    let modifiers: Vec<_> = modifiers
+        .split(',')
+        .map(|m| if m == "-bundle" { "+bundle" } else { m })
+        .collect();
+    Some(format!("{}:{}={}", kind, modifiers.join(","), name))
+}
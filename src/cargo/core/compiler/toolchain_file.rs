@@ -0,0 +1,334 @@
+//! Support for `rust-toolchain.toml`, the file which pins the toolchain of a
+//! project for rustup.
+//!
+//! With `-Z toolchain-file`, Cargo reads the file itself and checks the
+//! running `rustc` against it before building: its channel, and whether the
+//! components and targets listed in the file are installed in its sysroot.
+//! Missing components and targets are installed with the provider in
+//! `toolchain.provider` if `toolchain.auto-install` is set, otherwise the
+//! mismatches are reported together instead of failing deep in the build
+//! with a missing `std`.
+//!
+//! With `-Z auto-install-targets`, the standard library of every target
+//! passed with `--target` is also checked, whether or not there is a
+//! toolchain file.
+
+use crate::core::compiler::{BuildConfig, CompileKind, RustcTargetData};
+use crate::util::config::{add_to_toolchain, ProviderAction, ToolchainConfig};
+use crate::util::{CargoResult, Config};
+use anyhow::{bail, Context as _};
+use cargo_util::paths;
+use serde::Deserialize;
+use std::env;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+/// The names of the toolchain file, in the order rustup looks for them.
+const FILE_NAMES: &[&str] = &["rust-toolchain", "rust-toolchain.toml"];
+
+/// A parsed `rust-toolchain.toml` file.
+#[derive(Debug)]
+pub struct ToolchainFile {
+    /// The path of the file.
+    pub path: PathBuf,
+    /// The toolchain channel, like `stable`, `nightly-2021-09-01` or `1.56.0`.
+    pub channel: Option<String>,
+    /// The components which must be installed, like `clippy`.
+    pub components: Vec<String>,
+    /// The targets whose standard library must be installed.
+    pub targets: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct TomlToolchainFile {
+    toolchain: TomlToolchain,
+}
+
+#[derive(Deserialize)]
+struct TomlToolchain {
+    channel: Option<String>,
+    #[serde(default)]
+    components: Vec<String>,
+    #[serde(default)]
+    targets: Vec<String>,
+}
+
+/// Finds the toolchain file of the current directory, searching its
+/// ancestors like rustup does.
+pub fn find(config: &Config) -> CargoResult<Option<ToolchainFile>> {
+    for dir in config.cwd().ancestors() {
+        for name in FILE_NAMES {
+            let path = dir.join(name);
+            if path.is_file() {
+                return parse(&path).map(Some);
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn parse(path: &Path) -> CargoResult<ToolchainFile> {
+    let contents = paths::read(path)?;
+    let trimmed = contents.trim();
+    // The legacy `rust-toolchain` file may only contain the channel.
+    if !trimmed.contains('\n') && !trimmed.contains('=') && !trimmed.starts_with('[') {
+        return Ok(ToolchainFile {
+            path: path.to_path_buf(),
+            channel: Some(trimmed.to_string()),
+            components: Vec::new(),
+            targets: Vec::new(),
+        });
+    }
+    let file: TomlToolchainFile = toml::from_str(&contents)
+        .with_context(|| format!("failed to parse toolchain file `{}`", path.display()))?;
+    Ok(ToolchainFile {
+        path: path.to_path_buf(),
+        channel: file.toolchain.channel,
+        components: file.toolchain.components,
+        targets: file.toolchain.targets,
+    })
+}
+
+/// Checks the running `rustc` against the toolchain file, if any.
+///
+/// Mismatches are warnings, except for a missing target which is built for,
+/// since that build can't succeed.
+pub fn check(
+    config: &Config,
+    target_data: &RustcTargetData<'_>,
+    requested_kinds: &[CompileKind],
+) -> CargoResult<()> {
+    if !config.cli_unstable().toolchain_file {
+        return Ok(());
+    }
+    let file = match find(config)? {
+        Some(file) => file,
+        None => return Ok(()),
+    };
+    let rustc = &target_data.rustc;
+    let sysroot = &target_data.info(CompileKind::Host).sysroot;
+    let toolchain: ToolchainConfig = config
+        .get::<Option<ToolchainConfig>>("toolchain")?
+        .unwrap_or_default();
+    let auto_install = toolchain.auto_install == Some(true);
+    let version = file
+        .channel
+        .clone()
+        .unwrap_or_else(|| rustc.version.to_string());
+
+    let mut problems = Vec::new();
+    let mut fatal = false;
+    if let Some(channel) = &file.channel {
+        if channel_matches(channel, &rustc.version) == Some(false) {
+            problems.push(format!(
+                "channel `{}` is required, but rustc is {}",
+                channel, rustc.version
+            ));
+        }
+    }
+    for component in &file.components {
+        if component_installed(sysroot, &rustc.host, component) != Some(false) {
+            continue;
+        }
+        if auto_install {
+            add_to_toolchain(
+                config,
+                ProviderAction::AddComponent(component),
+                &version,
+                sysroot,
+            )?;
+            if component_installed(sysroot, &rustc.host, component) != Some(false) {
+                continue;
+            }
+        }
+        problems.push(format!("component `{}` is not installed", component));
+    }
+    for target in &file.targets {
+        if target_installed(sysroot, target) {
+            continue;
+        }
+        if auto_install {
+            add_to_toolchain(config, ProviderAction::AddTarget(target), &version, sysroot)?;
+            if target_installed(sysroot, target) {
+                continue;
+            }
+        }
+        if requested_kinds.iter().any(|kind| match kind {
+            CompileKind::Host => *target == rustc.host.as_str(),
+            CompileKind::Target(t) => t.short_name() == target,
+        }) {
+            fatal = true;
+        }
+        problems.push(format!("target `{}` is not installed", target));
+    }
+    if problems.is_empty() {
+        return Ok(());
+    }
+
+    let mut msg = format!("the toolchain does not match `{}`:\n", file.path.display());
+    for problem in &problems {
+        let _ = writeln!(msg, "  - {}", problem);
+    }
+    let _ = write!(
+        msg,
+        "rustc `{}` has its sysroot in `{}`",
+        rustc.path.display(),
+        sysroot.display()
+    );
+    if !auto_install {
+        msg.push_str(
+            "\n\nSet `toolchain.auto-install` and `toolchain.provider` in the Cargo config \
+             to install the missing targets and components automatically.",
+        );
+    }
+    if fatal {
+        bail!(msg);
+    }
+    config.shell().warn(msg)
+}
+
+/// Checks that the standard library of each requested target is installed,
+/// and installs it with the toolchain provider with `--auto-install-targets`
+/// or `toolchain.auto-install`.
+pub fn check_requested_targets(
+    config: &Config,
+    target_data: &RustcTargetData<'_>,
+    build_config: &BuildConfig,
+) -> CargoResult<()> {
+    if !config.cli_unstable().auto_install_targets || config.cli_unstable().build_std.is_some() {
+        return Ok(());
+    }
+    let toolchain: ToolchainConfig = config
+        .get::<Option<ToolchainConfig>>("toolchain")?
+        .unwrap_or_default();
+    let auto_install = build_config.auto_install_targets || toolchain.auto_install == Some(true);
+    let rustc = &target_data.rustc;
+    for kind in &build_config.requested_kinds {
+        let target = match kind {
+            CompileKind::Host => continue,
+            CompileKind::Target(target) => target,
+        };
+        let info = target_data.info(*kind);
+        if info.sysroot_target_libdir.is_dir() {
+            continue;
+        }
+        let name = target.short_name();
+        // The standard library of a custom target can only be built.
+        let is_custom = target.rustc_target().ends_with(".json");
+        if auto_install && !is_custom {
+            add_to_toolchain(
+                config,
+                ProviderAction::AddTarget(name),
+                &rustc.version.to_string(),
+                &info.sysroot,
+            )?;
+            if info.sysroot_target_libdir.is_dir() {
+                continue;
+            }
+            bail!(
+                "the toolchain provider did not install the standard library for target `{}` \
+                 into `{}`",
+                name,
+                info.sysroot_target_libdir.display()
+            );
+        }
+
+        let mut msg = format!(
+            "the standard library for target `{}` is not installed\n\n\
+             rustc `{}` looks for it in `{}`.",
+            name,
+            rustc.path.display(),
+            info.sysroot_target_libdir.display()
+        );
+        if !is_custom {
+            if toolchain.provider.is_some() {
+                msg.push_str(
+                    "\nPass `--auto-install-targets` to install it with `toolchain.provider`.",
+                );
+            } else if let Ok(rustup_toolchain) = env::var("RUSTUP_TOOLCHAIN") {
+                let _ = write!(
+                    msg,
+                    "\nTo install it, run `rustup target add {} --toolchain {}`.",
+                    name, rustup_toolchain
+                );
+            } else {
+                msg.push_str(
+                    "\nConfigure `toolchain.provider` and pass `--auto-install-targets` \
+                     to install it.",
+                );
+            }
+        }
+        msg.push_str("\nTo build the standard library from source instead, pass `-Z build-std`.");
+        bail!(msg);
+    }
+    Ok(())
+}
+
+/// Returns whether the version of rustc is one of `channel`, or `None` if
+/// the channel isn't known, like the name of a custom toolchain.
+fn channel_matches(channel: &str, version: &semver::Version) -> Option<bool> {
+    let pre = version.pre.as_str();
+    let channel_of = |name: &str| match name {
+        "stable" => Some(pre.is_empty()),
+        "beta" => Some(pre.starts_with("beta")),
+        "nightly" => Some(pre.starts_with("nightly") || pre.starts_with("dev")),
+        _ => None,
+    };
+    if let Some(matches) = channel_of(channel) {
+        return Some(matches);
+    }
+    // Dated channels, like `nightly-2021-09-01`.
+    if let Some((name, _date)) = channel.split_once('-') {
+        if channel_of(name).is_some() {
+            return channel_of(name);
+        }
+    }
+    // Versions, like `1.56` or `1.56.0`.
+    let parts: Vec<_> = channel.split('.').map(|p| p.parse::<u64>().ok()).collect();
+    match parts.as_slice() {
+        [Some(major), Some(minor)] => {
+            Some(version.major == *major && version.minor == *minor && pre.is_empty())
+        }
+        [Some(major), Some(minor), Some(patch)] => Some(
+            version.major == *major
+                && version.minor == *minor
+                && version.patch == *patch
+                && pre.is_empty(),
+        ),
+        _ => None,
+    }
+}
+
+/// Returns whether `component` is installed in the sysroot, or `None` if
+/// Cargo doesn't know how to tell.
+fn component_installed(sysroot: &Path, host: &str, component: &str) -> Option<bool> {
+    let component = component.strip_suffix("-preview").unwrap_or(component);
+    let path = match component {
+        "rustfmt" => tool_path(sysroot, "rustfmt"),
+        "clippy" => tool_path(sysroot, "clippy-driver"),
+        "rust-analyzer" => tool_path(sysroot, "rust-analyzer"),
+        "miri" => tool_path(sysroot, "miri"),
+        "rust-src" => sysroot.join("lib/rustlib/src/rust"),
+        "rust-docs" => sysroot.join("share/doc/rust/html"),
+        "llvm-tools" => sysroot.join("lib/rustlib").join(host).join("bin"),
+        _ => return None,
+    };
+    Some(path.exists())
+}
+
+/// Returns whether the standard library of `target` is installed in the
+/// sysroot.
+pub fn target_installed(sysroot: &Path, target: &str) -> bool {
+    sysroot
+        .join("lib/rustlib")
+        .join(target)
+        .join("lib")
+        .is_dir()
+}
+
+fn tool_path(sysroot: &Path, tool: &str) -> PathBuf {
+    sysroot
+        .join("bin")
+        .join(tool)
+        .with_extension(std::env::consts::EXE_EXTENSION)
+}
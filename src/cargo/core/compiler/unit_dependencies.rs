@@ -100,7 +100,8 @@ pub fn build_unit_dependencies<'a, 'cfg>(
     let std_unit_deps = calc_deps_of_std(&mut state, std_roots)?;
 
     deps_of_roots(roots, &mut state)?;
-    super::links::validate_links(state.resolve(), &state.unit_dependencies)?;
+    super::links::validate_links(ws, state.resolve(), &state.unit_dependencies)?;
+    super::config_flags::validate(ws, state.resolve(), &state.unit_dependencies)?;
     // Hopefully there aren't any links conflicts with the standard library?
 
     if let Some(std_unit_deps) = std_unit_deps {
@@ -188,7 +189,7 @@ fn deps_of_roots(roots: &[Unit], mut state: &mut State<'_, '_>) -> CargoResult<(
             } else {
                 UnitFor::new_test(state.config)
             }
-        } else if unit.target.is_custom_build() {
+        } else if unit.target.is_build_program() {
             // This normally doesn't happen, except `clean` aggressively
             // generates all units.
             UnitFor::new_host(false)
@@ -244,10 +245,10 @@ fn compute_deps(
 
     let id = unit.pkg.package_id();
     let filtered_deps = state.deps(unit, unit_for, &|dep| {
-        // If this target is a build command, then we only want build
-        // dependencies, otherwise we want everything *other than* build
-        // dependencies.
-        if unit.target.is_custom_build() != dep.is_build() {
+        // If this target is a build command or a codegen step, then we only
+        // want build dependencies, otherwise we want everything *other than*
+        // build dependencies.
+        if unit.target.is_build_program() != dep.is_build() {
             return false;
         }
 
@@ -256,6 +257,7 @@ fn compute_deps(
         if !dep.is_transitive()
             && !unit.target.is_test()
             && !unit.target.is_example()
+            && !unit.target.is_fuzz()
             && !unit.mode.is_doc_scrape()
             && !unit.mode.is_any_test()
         {
@@ -311,10 +313,10 @@ fn compute_deps(
     }
     state.dev_dependency_edges.extend(dev_deps);
 
-    // If this target is a build script, then what we've collected so far is
-    // all we need. If this isn't a build script, then it depends on the
-    // build script if there is one.
-    if unit.target.is_custom_build() {
+    // If this target is a build script or a codegen step, then what we've
+    // collected so far is all we need. Otherwise it depends on running the
+    // build script and the codegen steps if there are any.
+    if unit.target.is_build_program() {
         return Ok(ret);
     }
     ret.extend(dep_build_script(unit, unit_for, state)?);
@@ -382,10 +384,11 @@ fn compute_deps_custom_build(
     state: &mut State<'_, '_>,
 ) -> CargoResult<Vec<UnitDep>> {
     if let Some(links) = unit.pkg.manifest().links() {
-        if state
-            .target_data
-            .script_override(links, unit.kind)
-            .is_some()
+        if unit.target.is_custom_build()
+            && state
+                .target_data
+                .script_override(links, unit.kind)
+                .is_some()
         {
             // Overridden build scripts don't have any dependencies.
             return Ok(Vec::new());
@@ -518,22 +521,22 @@ fn maybe_lib(
         .transpose()
 }
 
-/// If a build script is scheduled to be run for the package specified by
-/// `unit`, this function will return the unit to run that build script.
+/// Returns the units to run the build script and the codegen steps of the
+/// package specified by `unit`, which have to run before it is compiled.
 ///
 /// Overriding a build script simply means that the running of the build
 /// script itself doesn't have any dependencies, so even in that case a unit
-/// of work is still returned. `None` is only returned if the package has no
-/// build script.
+/// of work is still returned. The result is only empty if the package has no
+/// build script and no codegen steps.
 fn dep_build_script(
     unit: &Unit,
     unit_for: UnitFor,
     state: &State<'_, '_>,
-) -> CargoResult<Option<UnitDep>> {
+) -> CargoResult<Vec<UnitDep>> {
     unit.pkg
         .targets()
         .iter()
-        .find(|t| t.is_custom_build())
+        .filter(|t| t.is_build_program())
         .map(|t| {
             // The profile stored in the Unit is the profile for the thing
             // the custom build script is running for.
@@ -575,7 +578,7 @@ fn dep_build_script(
                 profile,
             )
         })
-        .transpose()
+        .collect()
 }
 
 /// Choose the correct mode for dependencies.
@@ -744,7 +747,10 @@ fn connect_run_custom_build_deps(state: &mut State<'_, '_>) {
                 .filter_map(|other| {
                     state.unit_dependencies[&other.unit]
                         .iter()
-                        .find(|other_dep| other_dep.unit.mode == CompileMode::RunCustomBuild)
+                        .find(|other_dep| {
+                            other_dep.unit.mode == CompileMode::RunCustomBuild
+                                && other_dep.unit.target.is_custom_build()
+                        })
                         .cloned()
                 })
                 .collect::<HashSet<_>>();
@@ -0,0 +1,180 @@
+//! Universal binaries for Apple targets with `cargo build --lipo`.
+//!
+//! A universal (or "fat") binary holds the code of several architectures,
+//! and runs natively on each of them. With `--lipo`, each `--target` is built
+//! as usual, and then the binaries, `cdylib`s and `staticlib`s built for all
+//! of them are merged with `lipo -create` into
+//! `target/universal-apple-darwin/<profile>`. The `LIPO` environment variable
+//! overrides the `lipo` executable.
+//!
+//! The merged files replace the ones of the single targets in `--out-dir`,
+//! and are reported with a `universal-artifact` JSON message.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context as _};
+use cargo_util::{paths, ProcessBuilder};
+
+use super::{BuildConfig, CompileKind, CompileMode, Context, FileFlavor};
+use crate::core::{PackageId, Target};
+use crate::util::machine_message::{self, Message};
+use crate::util::CargoResult;
+
+/// Checks that `--lipo` builds for several Apple targets of the same
+/// platform.
+pub fn validate(build_config: &BuildConfig) -> CargoResult<()> {
+    if !build_config.lipo {
+        return Ok(());
+    }
+    let triples: Vec<_> = build_config
+        .requested_kinds
+        .iter()
+        .filter_map(|kind| match kind {
+            CompileKind::Host => None,
+            CompileKind::Target(target) => Some(target.short_name()),
+        })
+        .collect();
+    if triples.len() < 2 || triples.len() != build_config.requested_kinds.len() {
+        bail!("`--lipo` requires at least two `--target` flags");
+    }
+    let first = platform(triples[0]);
+    for triple in &triples {
+        if !triple.contains("-apple-") {
+            bail!(
+                "`--lipo` only supports Apple targets, but `{}` is not one",
+                triple
+            );
+        }
+        if platform(triple) != first {
+            bail!(
+                "`--lipo` requires targets of the same platform, \
+                 but `{}` and `{}` are for different platforms",
+                triples[0],
+                triple
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Returns the platform of an Apple target, like `apple-darwin`.
+fn platform(triple: &str) -> &str {
+    triple
+        .split_once('-')
+        .map_or(triple, |(_arch, platform)| platform)
+}
+
+/// The files of one artifact, built for each target.
+struct Merge<'a> {
+    package_id: PackageId,
+    target: &'a Target,
+    sources: Vec<(String, PathBuf)>,
+}
+
+/// Merges the artifacts built for each target into universal ones.
+pub fn merge(cx: &mut Context<'_, '_>) -> CargoResult<()> {
+    let bcx = cx.bcx;
+    if !bcx.build_config.lipo {
+        return Ok(());
+    }
+    let first = bcx.build_config.requested_kinds[0];
+    let universal_dir = bcx
+        .ws
+        .target_dir()
+        .join(format!(
+            "universal-{}",
+            platform(bcx.target_data.short_name(&first))
+        ))
+        .into_path_unlocked();
+
+    // The artifacts by their path relative to the output directory of their
+    // target, like `debug/foo`.
+    let mut merges: BTreeMap<PathBuf, Merge<'_>> = BTreeMap::new();
+    for unit in &bcx.roots {
+        if unit.mode != CompileMode::Build {
+            continue;
+        }
+        let dest = cx.files().layout(unit.kind).dest().to_path_buf();
+        let triple = bcx.target_data.short_name(&unit.kind).to_string();
+        for output in cx.outputs(unit)?.iter() {
+            if output.flavor != FileFlavor::Normal {
+                continue;
+            }
+            let path = output.bin_dst();
+            let relative = match path.strip_prefix(dest.parent().unwrap()) {
+                Ok(relative) => relative.to_path_buf(),
+                Err(_) => continue,
+            };
+            merges
+                .entry(relative)
+                .or_insert_with(|| Merge {
+                    package_id: unit.pkg.package_id(),
+                    target: &unit.target,
+                    sources: Vec::new(),
+                })
+                .sources
+                .push((triple.clone(), path.clone()));
+        }
+    }
+
+    let lipo = env::var_os("LIPO").unwrap_or_else(|| OsString::from("lipo"));
+    for (relative, merge) in &merges {
+        if merge.sources.len() != bcx.build_config.requested_kinds.len() {
+            continue;
+        }
+        let dst = universal_dir.join(relative);
+        let triples: Vec<_> = merge.sources.iter().map(|(t, _)| t.as_str()).collect();
+        if !is_fresh(&dst, merge)? {
+            bcx.config.shell().status(
+                "Merging",
+                format!("{} for {}", relative.display(), triples.join(", ")),
+            )?;
+            paths::create_dir_all(dst.parent().unwrap())?;
+            let mut cmd = ProcessBuilder::new(&lipo);
+            cmd.arg("-create").arg("-output").arg(&dst);
+            for (_, source) in &merge.sources {
+                cmd.arg(source);
+            }
+            cmd.exec_with_output().with_context(|| {
+                format!("failed to create the universal binary `{}`", dst.display())
+            })?;
+        }
+        let mut filenames = vec![dst.clone()];
+        if let Some(export_dir) = &bcx.build_config.export_dir {
+            paths::create_dir_all(export_dir)?;
+            let exported = export_dir.join(dst.file_name().unwrap());
+            paths::link_or_copy(&dst, &exported)?;
+            filenames.push(exported);
+        }
+        if bcx.build_config.emit_json() {
+            let msg = machine_message::UniversalArtifact {
+                package_id: merge.package_id,
+                target: merge.target,
+                targets: triples,
+                filenames,
+                sources: merge.sources.iter().map(|(_, s)| s.as_path()).collect(),
+            }
+            .to_json_string();
+            crate::drop_println!(bcx.config, "{}", msg);
+        }
+    }
+    Ok(())
+}
+
+/// Returns whether the universal file `dst` is newer than the files it
+/// merges.
+fn is_fresh(dst: &Path, merge: &Merge<'_>) -> CargoResult<bool> {
+    let dst_mtime = match paths::mtime(dst) {
+        Ok(mtime) => mtime,
+        Err(_) => return Ok(false),
+    };
+    for (_, source) in &merge.sources {
+        if paths::mtime(source)? > dst_mtime {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
@@ -0,0 +1,94 @@
+//! The summary of the warnings of a build, for `--warnings=summary`.
+//!
+//! Every warning rendered by Cargo is recorded with its lint and package.
+//! Once the build is finished, the warnings are summarized by lint, with the
+//! number of warnings of each package, instead of printing each of them.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+use crate::util::interning::InternedString;
+use crate::util::{CargoResult, Config};
+
+/// The name under which the warnings without a lint are counted.
+const OTHER: &str = "other";
+
+#[derive(Default)]
+pub struct WarningSummary {
+    /// The number of warnings by lint, then by package.
+    lints: BTreeMap<String, BTreeMap<InternedString, usize>>,
+    /// The number of warnings suppressed for being duplicates.
+    duplicates: usize,
+}
+
+impl WarningSummary {
+    /// Records a warning of `package`, which was a duplicate of a previous
+    /// one if it wasn't `emitted`.
+    pub fn record(&mut self, package: InternedString, lint: Option<&str>, emitted: bool) {
+        if !emitted {
+            self.duplicates += 1;
+            return;
+        }
+        *self
+            .lints
+            .entry(lint.unwrap_or(OTHER).to_string())
+            .or_default()
+            .entry(package)
+            .or_default() += 1;
+    }
+
+    /// The number of warnings, without the duplicates.
+    pub fn total(&self) -> usize {
+        self.lints
+            .values()
+            .flat_map(|packages| packages.values())
+            .sum()
+    }
+
+    /// Prints the summary, the most frequent lints first.
+    pub fn print(&self, config: &Config) -> CargoResult<()> {
+        let total = self.total();
+        if total == 0 {
+            return Ok(());
+        }
+        let mut packages: Vec<_> = self
+            .lints
+            .values()
+            .flat_map(|packages| packages.keys())
+            .collect();
+        packages.sort();
+        packages.dedup();
+        let mut message = format!(
+            "{} in {}",
+            plural(total, "warning"),
+            plural(packages.len(), "package")
+        );
+        if self.duplicates > 0 {
+            write!(message, " ({})", plural(self.duplicates, "duplicate"))?;
+        }
+        let mut lints: Vec<_> = self
+            .lints
+            .iter()
+            .map(|(lint, packages)| (packages.values().sum::<usize>(), lint, packages))
+            .collect();
+        lints.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(b.1)));
+        for (count, lint, packages) in lints {
+            let packages: Vec<_> = packages
+                .iter()
+                .map(|(package, count)| format!("`{}`: {}", package, count))
+                .collect();
+            write!(message, "\n  {}: {} ({})", lint, count, packages.join(", "))?;
+        }
+        let mut shell = config.shell();
+        shell.warn(message)?;
+        shell.note("pass `--warnings=full` to see each warning")
+    }
+}
+
+fn plural(n: usize, noun: &str) -> String {
+    if n == 1 {
+        format!("1 {}", noun)
+    } else {
+        format!("{} {}s", n, noun)
+    }
+}
@@ -0,0 +1,150 @@
+//! Support for the `component` crate type, which builds a library into a
+//! [WebAssembly component](https://component-model.bytecodealliance.org/).
+//!
+//! rustc links the library as a `cdylib`, the core module. After linking,
+//! Cargo wraps the core module into a component with `wasm-tools`, as
+//! described by the `[package.component]` table:
+//!
+//! * With `wit`, the WIT world is first embedded into the core module with
+//!   `wasm-tools component embed`, for the libraries which don't embed it
+//!   themselves with `wit-bindgen`.
+//! * `wasm-tools component new` then creates the component, with the
+//!   `adapters` implementing the imports of the core module, like the WASI
+//!   preview 1 adapter.
+//!
+//! The component is the primary artifact of the library, the core module is
+//! kept next to it. The `WASM_TOOLS` environment variable overrides the
+//! `wasm-tools` executable.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context as _;
+use cargo_util::{paths, ProcessBuilder};
+
+use super::{CompileMode, Context, CrateType, FileFlavor, Unit};
+use crate::util::CargoResult;
+
+/// The suffix of the component file, the core module is `<name>.wasm`.
+pub const COMPONENT_SUFFIX: &str = ".component.wasm";
+
+/// The post-link step which wraps the core module of a library into a
+/// component.
+pub struct ComponentStep {
+    /// The core module linked by rustc.
+    core: PathBuf,
+    /// Where the component is written.
+    component: PathBuf,
+    /// The WIT file or directory to embed into the core module.
+    wit: Option<PathBuf>,
+    /// The world in `wit` to embed.
+    world: Option<String>,
+    /// The adapter modules by the name of the module they implement.
+    adapters: BTreeMap<String, PathBuf>,
+}
+
+impl ComponentStep {
+    /// Returns the step for `unit`, if it builds a component.
+    pub fn new(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Option<ComponentStep>> {
+        if unit.mode != CompileMode::Build
+            || !unit
+                .target
+                .rustc_crate_types()
+                .contains(&CrateType::Component)
+        {
+            return Ok(None);
+        }
+        // There is no component for the targets which don't support it.
+        let component = match cx.outputs(unit)?.iter().find(|output| {
+            output.flavor == FileFlavor::Normal
+                && output.path.to_string_lossy().ends_with(COMPONENT_SUFFIX)
+        }) {
+            Some(output) => output.path.clone(),
+            None => return Ok(None),
+        };
+        // The core module is named like a `cdylib`.
+        let name = component.file_name().unwrap().to_string_lossy();
+        let core = component.with_file_name(format!(
+            "{}.wasm",
+            name.strip_suffix(COMPONENT_SUFFIX).unwrap()
+        ));
+
+        let root = unit.pkg.root();
+        let toml = unit
+            .pkg
+            .manifest()
+            .original()
+            .component()
+            .cloned()
+            .unwrap_or_default();
+        Ok(Some(ComponentStep {
+            core,
+            component,
+            wit: toml.wit.map(|wit| root.join(wit)),
+            world: toml.world,
+            adapters: toml
+                .adapters
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(name, path)| (name, root.join(path)))
+                .collect(),
+        }))
+    }
+
+    /// Wraps the core module into the component.
+    pub fn run(&self) -> CargoResult<()> {
+        let wasm_tools = env::var_os("WASM_TOOLS").unwrap_or_else(|| OsString::from("wasm-tools"));
+        let mut module = self.core.clone();
+        if let Some(wit) = &self.wit {
+            let embedded = with_suffix(&self.component, ".embed");
+            let mut embed = ProcessBuilder::new(&wasm_tools);
+            embed
+                .arg("component")
+                .arg("embed")
+                .arg(wit)
+                .arg(&self.core)
+                .arg("-o")
+                .arg(&embedded);
+            if let Some(world) = &self.world {
+                embed.arg("--world").arg(world);
+            }
+            self.exec(&embed)?;
+            module = embedded;
+        }
+
+        let mut new = ProcessBuilder::new(&wasm_tools);
+        new.arg("component")
+            .arg("new")
+            .arg(&module)
+            .arg("-o")
+            .arg(&self.component);
+        for (name, path) in &self.adapters {
+            let mut adapt = OsString::from(format!("{}=", name));
+            adapt.push(path);
+            new.arg("--adapt").arg(adapt);
+        }
+        self.exec(&new)?;
+        if module != self.core {
+            paths::remove_file(&module)?;
+        }
+        Ok(())
+    }
+
+    fn exec(&self, cmd: &ProcessBuilder) -> CargoResult<()> {
+        cmd.exec_with_output().with_context(|| {
+            format!(
+                "failed to create the WebAssembly component `{}`",
+                self.component.display()
+            )
+        })?;
+        Ok(())
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
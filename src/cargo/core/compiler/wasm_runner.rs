@@ -0,0 +1,264 @@
+//! Built-in runners for WebAssembly targets.
+//!
+//! With `-Z wasm-runner`, `cargo run`, `cargo test` and `cargo bench` run
+//! the executables built for a WebAssembly target with a runtime found in
+//! `PATH`, unless a `runner` is configured for the target:
+//!
+//! * `wasmtime` or `wasmer` for WASI targets, like `wasm32-wasip1`.
+//! * `node` for Emscripten targets, whose executables are JavaScript files.
+//!
+//! `target.<triple>.wasm-runtime` picks one of them instead.
+//!
+//! WASI programs only see what the runtime grants them, so the working
+//! directory is made accessible to them, and the environment variables Cargo
+//! sets for the program are passed on, along with the `RUST_*` ones.
+
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::bail;
+use cargo_platform::Cfg;
+use cargo_util::{paths, ProcessBuilder, ProcessError};
+
+use crate::util::{CargoResult, Config};
+
+/// The WebAssembly runtimes Cargo knows how to run programs with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WasmRuntime {
+    Wasmtime,
+    Wasmer,
+    Node,
+}
+
+impl WasmRuntime {
+    /// The runtimes able to run the executables of a target with the given
+    /// `cfg` values, in order of preference.
+    fn candidates(cfg: &[Cfg]) -> &'static [WasmRuntime] {
+        let is = |key: &str, value: &str| {
+            cfg.iter()
+                .any(|c| matches!(c, Cfg::KeyPair(k, v) if k == key && v == value))
+        };
+        if !is("target_family", "wasm") {
+            &[]
+        } else if is("target_os", "wasi") {
+            &[WasmRuntime::Wasmtime, WasmRuntime::Wasmer]
+        } else if is("target_os", "emscripten") {
+            &[WasmRuntime::Node]
+        } else {
+            &[]
+        }
+    }
+
+    fn parse(s: &str) -> CargoResult<WasmRuntime> {
+        match s {
+            "wasmtime" => Ok(WasmRuntime::Wasmtime),
+            "wasmer" => Ok(WasmRuntime::Wasmer),
+            "node" => Ok(WasmRuntime::Node),
+            _ => bail!(
+                "unknown WebAssembly runtime `{}`, expected one of `wasmtime`, `wasmer` or `node`",
+                s
+            ),
+        }
+    }
+
+    /// The exit code with which the runtime reports that the program trapped,
+    /// if it has a distinct one.
+    fn trap_exit_code(self) -> Option<i32> {
+        match self {
+            // wasmtime mimics the exit status of `abort()`.
+            WasmRuntime::Wasmtime if cfg!(windows) => Some(3),
+            WasmRuntime::Wasmtime => Some(134),
+            WasmRuntime::Wasmer | WasmRuntime::Node => None,
+        }
+    }
+}
+
+impl fmt::Display for WasmRuntime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WasmRuntime::Wasmtime => "wasmtime",
+            WasmRuntime::Wasmer => "wasmer",
+            WasmRuntime::Node => "node",
+        }
+        .fmt(f)
+    }
+}
+
+/// A WebAssembly runtime to run the executables of a target with.
+#[derive(Clone, Debug)]
+pub struct WasmRunner {
+    runtime: WasmRuntime,
+    path: PathBuf,
+}
+
+impl WasmRunner {
+    /// Looks for a runtime able to run the executables of `target`.
+    ///
+    /// Returns `None` if `target` is not a WebAssembly target, or if none of
+    /// its runtimes is installed.
+    pub fn discover(config: &Config, target: &str, cfg: &[Cfg]) -> CargoResult<Option<WasmRunner>> {
+        let candidates = WasmRuntime::candidates(cfg);
+        if candidates.is_empty() {
+            return Ok(None);
+        }
+        let key = format!("target.{}.wasm-runtime", target);
+        if let Some(runtime) = config.get::<Option<String>>(&key)? {
+            let runtime = WasmRuntime::parse(&runtime)?;
+            if !candidates.contains(&runtime) {
+                bail!(
+                    "`{}` can't run the executables of the target `{}`, set in `{}`",
+                    runtime,
+                    target,
+                    key
+                );
+            }
+            return match paths::resolve_executable(Path::new(&runtime.to_string())) {
+                Ok(path) => Ok(Some(WasmRunner { runtime, path })),
+                Err(_) => bail!("`{}` was not found in PATH, set in `{}`", runtime, key),
+            };
+        }
+        Ok(candidates.iter().find_map(|&runtime| {
+            paths::resolve_executable(Path::new(&runtime.to_string()))
+                .ok()
+                .map(|path| WasmRunner { runtime, path })
+        }))
+    }
+
+    /// Returns a process running the program `module` with the runtime.
+    ///
+    /// `env` is the process Cargo would have run the program with natively,
+    /// from which the environment and working directory are taken.
+    pub fn process(&self, module: &OsStr, env: &ProcessBuilder) -> ProcessBuilder {
+        let mut cmd = env.clone();
+        cmd.program(&self.path);
+        cmd.args_replace(&self.args(module, &forwarded_env(env)));
+        cmd
+    }
+
+    /// The arguments to run `module` with `vars` set in its environment.
+    fn args(&self, module: &OsStr, vars: &[(String, String)]) -> Vec<OsString> {
+        let mut args = Vec::new();
+        match self.runtime {
+            WasmRuntime::Wasmtime | WasmRuntime::Wasmer => {
+                args.push("run".into());
+                args.push("--dir=.".into());
+                for (key, value) in vars {
+                    args.push("--env".into());
+                    args.push(format!("{}={}", key, value).into());
+                }
+                args.push(module.to_os_string());
+                if self.runtime == WasmRuntime::Wasmer {
+                    args.push("--".into());
+                }
+            }
+            // Node already hands its environment to the program.
+            WasmRuntime::Node => args.push(module.to_os_string()),
+        }
+        args
+    }
+
+    /// Reports a trap, which is how a panic ends on WebAssembly, with the exit
+    /// code of a panicking Rust program.
+    pub fn translate_error(&self, mut err: ProcessError) -> ProcessError {
+        if err.code.is_some() && err.code == self.runtime.trap_exit_code() {
+            err.code = Some(101);
+        }
+        err
+    }
+}
+
+/// The environment variables passed on to a program that doesn't run on the
+/// host, and so doesn't inherit the environment of Cargo.
+pub(super) fn forwarded_env(cmd: &ProcessBuilder) -> Vec<(String, String)> {
+    let mut vars: Vec<_> = cmd
+        .get_envs()
+        .iter()
+        .filter(|(key, _)| key.as_str() != paths::dylib_path_envvar())
+        .filter_map(|(key, value)| {
+            let value = value.as_ref()?.to_str()?;
+            Some((key.clone(), value.to_string()))
+        })
+        .collect();
+    for (key, value) in env::vars() {
+        if key.starts_with("RUST_") && !cmd.get_envs().contains_key(&key) {
+            vars.push((key, value));
+        }
+    }
+    vars.sort();
+    vars
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{WasmRunner, WasmRuntime};
+    use cargo_platform::Cfg;
+    use cargo_util::ProcessError;
+    use std::ffi::OsStr;
+
+    fn cfg(s: &[&str]) -> Vec<Cfg> {
+        s.iter().map(|c| c.parse().unwrap()).collect()
+    }
+
+    fn args(runtime: WasmRuntime) -> Vec<String> {
+        let runner = WasmRunner {
+            runtime,
+            path: runtime.to_string().into(),
+        };
+        let vars = [("CARGO_PKG_NAME".to_string(), "foo".to_string())];
+        runner
+            .args(OsStr::new("foo.wasm"), &vars)
+            .iter()
+            .map(|a| a.to_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn candidates() {
+        let wasi = cfg(&[r#"target_family="wasm""#, r#"target_os="wasi""#]);
+        assert_eq!(
+            WasmRuntime::candidates(&wasi),
+            [WasmRuntime::Wasmtime, WasmRuntime::Wasmer]
+        );
+        let emscripten = cfg(&[r#"target_family="wasm""#, r#"target_os="emscripten""#]);
+        assert_eq!(WasmRuntime::candidates(&emscripten), [WasmRuntime::Node]);
+        let unknown = cfg(&[r#"target_family="wasm""#, r#"target_os="unknown""#]);
+        assert!(WasmRuntime::candidates(&unknown).is_empty());
+        let linux = cfg(&[r#"target_family="unix""#, r#"target_os="linux""#]);
+        assert!(WasmRuntime::candidates(&linux).is_empty());
+    }
+
+    #[test]
+    fn runtime_args() {
+        assert_eq!(
+            args(WasmRuntime::Wasmtime),
+            ["run", "--dir=.", "--env", "CARGO_PKG_NAME=foo", "foo.wasm"]
+        );
+        assert_eq!(
+            args(WasmRuntime::Wasmer),
+            [
+                "run",
+                "--dir=.",
+                "--env",
+                "CARGO_PKG_NAME=foo",
+                "foo.wasm",
+                "--"
+            ]
+        );
+        assert_eq!(args(WasmRuntime::Node), ["foo.wasm"]);
+    }
+
+    #[test]
+    fn traps_exit_like_panics() {
+        let runner = WasmRunner {
+            runtime: WasmRuntime::Wasmtime,
+            path: "wasmtime".into(),
+        };
+        let trap = WasmRuntime::Wasmtime.trap_exit_code();
+        let err = ProcessError::new_raw("failed", trap, "trapped", None, None);
+        assert_eq!(runner.translate_error(err).code, Some(101));
+        let err = ProcessError::new_raw("failed", Some(1), "exit status: 1", None, None);
+        assert_eq!(runner.translate_error(err).code, Some(1));
+    }
+}
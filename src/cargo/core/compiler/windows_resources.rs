@@ -0,0 +1,228 @@
+//! Embedding of Windows resources, as described by `[package.windows]`.
+//!
+//! For the binaries and `cdylib`s built for a Windows target, Cargo writes a
+//! resource script with a version resource derived from the package, and the
+//! icon and application manifest of the table. Before rustc links the
+//! artifact, the script is compiled with the resource compiler of the target,
+//! and the compiled resources are passed to the linker:
+//!
+//! * MSVC targets use `rc.exe`, which writes a `.res` file.
+//! * Other targets use `windres` of MinGW-w64, which writes a COFF object.
+//!   When cross-compiling, it is prefixed with the triple of MinGW-w64, like
+//!   `x86_64-w64-mingw32-windres`.
+//!
+//! The `RC` environment variable overrides the resource compiler. The icon
+//! and the manifest are tracked in the dep-info of the unit, so changing
+//! them rebuilds it.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+use cargo_platform::Cfg;
+use cargo_util::{paths, ProcessBuilder};
+
+use super::{CompileMode, Context, CrateType, FileFlavor, Unit};
+use crate::util::CargoResult;
+
+/// `RT_MANIFEST`, the type of the application manifest resource.
+const RT_MANIFEST: u32 = 24;
+
+/// The step which compiles the resources of a unit before it is linked.
+pub struct ResourceStep {
+    /// The resource script written by Cargo.
+    script: PathBuf,
+    /// The contents of the script.
+    contents: String,
+    /// The compiled resources, passed to the linker.
+    compiled: PathBuf,
+    /// The resource compiler, and whether it is `rc.exe`.
+    compiler: OsString,
+    msvc: bool,
+    /// The files included by the script.
+    inputs: Vec<PathBuf>,
+}
+
+impl ResourceStep {
+    /// Returns the step for `unit`, if it is a binary or a `cdylib` of a
+    /// package with a `[package.windows]` table, built for Windows.
+    pub fn new(cx: &Context<'_, '_>, unit: &Unit) -> CargoResult<Option<ResourceStep>> {
+        let windows = match unit.pkg.manifest().original().windows() {
+            Some(windows) => windows,
+            None => return Ok(None),
+        };
+        let is_dll = unit.target.rustc_crate_types().contains(&CrateType::Cdylib);
+        if unit.mode != CompileMode::Build || !(unit.target.is_bin() || is_dll) {
+            return Ok(None);
+        }
+        let cfg = cx.bcx.target_data.cfg(unit.kind);
+        let is = |key: &str, value: &str| {
+            cfg.iter()
+                .any(|c| matches!(c, Cfg::KeyPair(k, v) if k == key && v == value))
+        };
+        if !is("target_os", "windows") {
+            return Ok(None);
+        }
+        let msvc = is("target_env", "msvc");
+
+        let root = unit.pkg.root();
+        let mut inputs = Vec::new();
+        let mut contents = String::from("#pragma code_page(65001)\n");
+        if let Some(icon) = &windows.icon {
+            let icon = root.join(icon);
+            contents.push_str(&format!("1 ICON {}\n", quote(&icon.to_string_lossy())));
+            inputs.push(icon);
+        }
+        if let Some(manifest) = &windows.manifest {
+            let manifest = root.join(manifest);
+            // `ISOLATIONAWARE_MANIFEST_RESOURCE_ID` for libraries,
+            // `CREATEPROCESS_MANIFEST_RESOURCE_ID` for executables.
+            let id = if is_dll { 2 } else { 1 };
+            contents.push_str(&format!(
+                "{} {} {}\n",
+                id,
+                RT_MANIFEST,
+                quote(&manifest.to_string_lossy())
+            ));
+            inputs.push(manifest);
+        }
+
+        let filename = cx
+            .outputs(unit)?
+            .iter()
+            .find(|output| output.flavor == FileFlavor::Normal)
+            .map(|output| {
+                output
+                    .bin_dst()
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .unwrap_or_default();
+        let pkg = unit.pkg.manifest();
+        let version = pkg.version();
+        let mut strings = BTreeMap::new();
+        strings.insert(
+            "FileDescription".to_string(),
+            pkg.metadata()
+                .description
+                .clone()
+                .unwrap_or_else(|| unit.pkg.name().to_string()),
+        );
+        strings.insert("FileVersion".to_string(), version.to_string());
+        strings.insert("InternalName".to_string(), unit.target.name().to_string());
+        strings.insert("OriginalFilename".to_string(), filename);
+        strings.insert("ProductName".to_string(), unit.pkg.name().to_string());
+        strings.insert("ProductVersion".to_string(), version.to_string());
+        if let Some(version_info) = &windows.version_info {
+            strings.extend(version_info.clone());
+        }
+        let numbers = [version.major, version.minor, version.patch]
+            .iter()
+            .map(|n| (*n).min(u64::from(u16::MAX)).to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        contents.push_str(&format!(
+            "1 VERSIONINFO\n\
+             FILEVERSION {numbers},0\n\
+             PRODUCTVERSION {numbers},0\n\
+             FILEOS 0x40004\n\
+             FILETYPE {}\n\
+             BEGIN\n\
+             \x20 BLOCK \"StringFileInfo\"\n\
+             \x20 BEGIN\n\
+             \x20   BLOCK \"040904B0\"\n\
+             \x20   BEGIN\n",
+            if is_dll { "0x2" } else { "0x1" },
+            numbers = numbers
+        ));
+        for (name, value) in &strings {
+            contents.push_str(&format!("      VALUE \"{}\", {}\n", name, quote(value)));
+        }
+        contents.push_str(
+            "    END\n\
+             \x20 END\n\
+             \x20 BLOCK \"VarFileInfo\"\n\
+             \x20 BEGIN\n\
+             \x20   VALUE \"Translation\", 0x409, 1200\n\
+             \x20 END\n\
+             END\n",
+        );
+
+        let stem = if cx.files().use_extra_filename(unit) {
+            format!("{}-{}", unit.target.crate_name(), cx.files().metadata(unit))
+        } else {
+            unit.target.crate_name()
+        };
+        let out_dir = cx.files().out_dir(unit);
+        let compiler = env::var_os("RC").unwrap_or_else(|| {
+            if msvc {
+                OsString::from("rc")
+            } else if cfg!(windows) {
+                OsString::from("windres")
+            } else {
+                let arch = cx.bcx.target_data.short_name(&unit.kind);
+                let arch = arch.split('-').next().unwrap();
+                OsString::from(format!("{}-w64-mingw32-windres", arch))
+            }
+        });
+        Ok(Some(ResourceStep {
+            script: out_dir.join(format!("{}.rc", stem)),
+            contents,
+            compiled: out_dir.join(format!("{}.{}", stem, if msvc { "res" } else { "res.o" })),
+            compiler,
+            msvc,
+            inputs,
+        }))
+    }
+
+    /// The argument which passes the compiled resources to the linker.
+    pub fn link_arg(&self) -> OsString {
+        let mut arg = OsString::from("link-arg=");
+        arg.push(&self.compiled);
+        arg
+    }
+
+    /// The files included by the resource script.
+    pub fn inputs(&self) -> &[PathBuf] {
+        &self.inputs
+    }
+
+    /// The contents of the resource script, tracked by the fingerprint.
+    pub fn contents(&self) -> &str {
+        &self.contents
+    }
+
+    /// Writes and compiles the resource script.
+    pub fn run(&self) -> CargoResult<()> {
+        paths::write(&self.script, &self.contents)?;
+        let mut cmd = ProcessBuilder::new(&self.compiler);
+        if self.msvc {
+            cmd.arg("/nologo")
+                .arg("/fo")
+                .arg(&self.compiled)
+                .arg(&self.script);
+        } else {
+            cmd.arg("--input")
+                .arg(&self.script)
+                .arg("--output-format=coff")
+                .arg("--output")
+                .arg(&self.compiled);
+        }
+        cmd.exec_with_output().with_context(|| {
+            format!(
+                "failed to compile the Windows resources `{}`",
+                self.script.display()
+            )
+        })?;
+        Ok(())
+    }
+}
+
+/// Quotes a string of a resource script, where quotes are doubled.
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\"\""))
+}
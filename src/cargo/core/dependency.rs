@@ -397,6 +397,13 @@ impl Dependency {
                 || (self.inner.req.matches(id.version()) && self.inner.source_id == id.source_id()))
     }
 
+    /// Lets the version requirement match the pre-release versions whose
+    /// release matches it, for `workspace.allow-prerelease`.
+    pub fn allow_prerelease(mut self) -> Dependency {
+        Rc::make_mut(&mut self.inner).req.allow_prerelease();
+        self
+    }
+
     pub fn map_source(mut self, to_replace: SourceId, replace_with: SourceId) -> Dependency {
         if self.source_id() == to_replace {
             self.set_source_id(replace_with);
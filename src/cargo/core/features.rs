@@ -409,6 +409,93 @@ features! {
 
     // Allow specifying different binary name apart from the crate name
     (unstable, different_binary_name, "", "reference/unstable.html#different-binary-name"),
+
+    // Allow naming groups of workspace members with `[workspace.groups]`.
+    (unstable, workspace_groups, "", "reference/unstable.html#workspace-groups"),
+
+    // Allow tuning `cargo test --feature-matrix` with a `[feature-matrix]` table.
+    (unstable, feature_matrix, "", "reference/unstable.html#feature-matrix"),
+
+    // Allow describing features and their conflicts in `[features.metadata]`.
+    (unstable, feature_metadata, "", "reference/unstable.html#feature-metadata"),
+
+    // Allow forcing the features of dependencies with `[feature-overrides]`.
+    (unstable, feature_overrides, "", "reference/unstable.html#feature-overrides"),
+
+    // Allow `timeout` and `retries` in profiles for running test binaries.
+    (unstable, test_timeouts, "", "reference/unstable.html#test-timeouts"),
+
+    // Allow `[[fuzz]]` targets and the `cargo fuzz-run` command.
+    (unstable, fuzz_targets, "", "reference/unstable.html#fuzz-targets"),
+
+    // Allow `sanitizer` in profiles.
+    (unstable, sanitizers, "", "reference/unstable.html#sanitizers"),
+
+    // Allow `[profile.*.package."prefix-*"]` name patterns.
+    (unstable, profile_package_globs, "", "reference/unstable.html#profile-package-globs"),
+
+    // Allow setting environment variables for compilation in `[env]`.
+    (unstable, manifest_env, "", "reference/unstable.html#manifest-env"),
+
+    // Allow `package.build-info` to describe the build to the compiler.
+    (unstable, build_info, "", "reference/unstable.html#build-info"),
+
+    // Allow `[package.assets]` to compile files into the package.
+    (unstable, assets, "", "reference/unstable.html#assets"),
+
+    // Allow `[[codegen]]` steps that generate code before compiling the package.
+    (unstable, codegen, "", "reference/unstable.html#codegen"),
+
+    // Allow `[workspace.links]` to let packages share a `links` value.
+    (unstable, links_policy, "", "reference/unstable.html#links-policy"),
+
+    // Allow `package.autofix` to keep `cargo fix` from changing a package.
+    (unstable, autofix, "", "reference/unstable.html#apply-suggestions"),
+
+    // Allow `[workspace.changelog]` to check and assemble changelog fragments.
+    (unstable, changelog_fragments, "", "reference/unstable.html#changelog-fragments"),
+
+    // Allow `[workspace.policy]` to check the dependency graph.
+    (unstable, workspace_policy, "", "reference/unstable.html#workspace-policy"),
+
+    // Allow `package.strict` to turn the warnings of a manifest into errors.
+    (unstable, strict_manifest, "", "reference/unstable.html#strict-manifest"),
+
+    // Allow `manifest-version` to declare the version of the manifest schema.
+    (unstable, manifest_version, "", "reference/unstable.html#manifest-version"),
+
+    // Allow `[lints]` to require documentation coverage.
+    (unstable, doc_coverage, "", "reference/unstable.html#doc-coverage"),
+
+    // Allow `[headers]` to generate a C header for `cdylib` and `staticlib` libraries.
+    (unstable, c_headers, "", "reference/unstable.html#c-headers"),
+
+    // Allow describing the pkg-config file of the library in `[package.pkg-config]`.
+    (unstable, pkg_config, "", "reference/unstable.html#pkg-config"),
+
+    // Allow `[config-flags]` to set cfgs for packages, and `package.expected-cfgs`.
+    (unstable, config_flags, "", "reference/unstable.html#config-flags"),
+
+    // Allow `[lints.rust.unexpected_cfgs]` to declare the custom cfgs of a package.
+    (unstable, check_cfg, "", "reference/unstable.html#check-cfg"),
+
+    // Allow naming the uplifted artifacts with `package.artifact-name`.
+    (unstable, artifact_name, "", "reference/unstable.html#artifact-name"),
+
+    // Allow the `component` crate type and the `[package.component]` table.
+    (unstable, wasm_component, "", "reference/unstable.html#wasm-component"),
+
+    // Allow the `[package.windows]` table of resources embedded into Windows binaries.
+    (unstable, windows_resources, "", "reference/unstable.html#windows-resources"),
+
+    // Allow `signing` in profiles.
+    (unstable, code_signing, "", "reference/unstable.html#code-signing"),
+
+    // Allow `lints.workspace-deny-warnings` to deny the warnings of the workspace members.
+    (unstable, deny_warnings, "", "reference/unstable.html#deny-warnings"),
+
+    // Allow `workspace.allow-prerelease` to match pre-release versions of the listed packages.
+    (unstable, allow_prerelease, "", "reference/unstable.html#allow-prerelease"),
 }
 
 pub struct Feature {
@@ -626,33 +713,101 @@ unstable_cli_options!(
 
     // All other unstable features.
     // Please keep this list lexiographically ordered.
+    add: bool = ("Enable the `cargo add` command"),
     advanced_env: bool = (HIDDEN),
+    asm: bool = ("Enable the `cargo asm` command"),
+    audits: bool = ("Enable the `cargo audits` command to manage the audit store"),
+    apply_suggestions: bool = ("Allow applying the suggestions of chosen lints with `cargo fix --apply-suggestions`"),
+    auto_features: bool = ("Allow enabling the required features of selected targets with `--auto-features`"),
+    auto_install_targets: bool = ("Detect the targets whose standard library isn't installed, and install them with `--auto-install-targets`"),
     avoid_dev_deps: bool = ("Avoid installing dev-dependencies if possible"),
     binary_dep_depinfo: bool = ("Track changes to dependency artifacts"),
+    binary_package: bool = ("Allow packaging the built binaries into distributable archives with `cargo package --binary`"),
+    build_stats: bool = ("Record the statistics of builds in `target/.stats` and schedule units by how long they took"),
     #[serde(deserialize_with = "deserialize_build_std")]
     build_std: Option<Vec<String>>  = ("Enable Cargo to compile the standard library itself as part of a crate graph compilation"),
     build_std_features: Option<Vec<String>>  = ("Configure features enabled for the standard library itself when building the standard library"),
+    cache: bool = ("Enable the `cargo cache` command to inspect and clean up the caches of the Cargo home"),
+    changed_since: bool = ("Allow selecting workspace members changed since a git revision with `--changed-since`"),
+    check_cfg: bool = ("Pass `--check-cfg` for the cfgs every package expects"),
+    client_certs: bool = ("Allow configuring the client certificate of each registry for mutual TLS"),
+    code_signing: bool = ("Allow the `[target.<triple>.signing]` config table to code sign binaries"),
     config_include: bool = ("Enable the `include` key in config files"),
     credential_process: bool = ("Add a config setting to fetch registry authentication tokens by calling an external process"),
+    deny_warnings: bool = ("Allow denying the warnings of the requested packages with `--deny-warnings`"),
+    delta_downloads: bool = ("Download the new versions of registry crates as patches of the cached versions"),
+    direct_minimal_versions: bool = ("Resolve minimal versions of the direct dependencies of the workspace members"),
+    doc_coverage: bool = ("Allow reporting the documentation coverage with `cargo doc --report-coverage`"),
+    doc_index: bool = ("Record the crates documented in the doc directory and generate a landing page"),
+    doc_offline_first: bool = ("Allow documenting dependencies into a shared directory with `cargo doc --offline-first`"),
     doctest_in_workspace: bool = ("Compile doctests with paths relative to the workspace root"),
     doctest_xcompile: bool = ("Compile and run doctests for non-host target using runner config"),
+    downloads: bool = ("Allow limiting the concurrency and rate of downloads, and print download messages with `--message-format json`"),
     dual_proc_macros: bool = ("Build proc-macros for both the host and the target"),
+    edition_migration: bool = ("Summarize `cargo fix` for the whole workspace and allow previewing it with `--dry-run`"),
+    edition_readiness: bool = ("Allow reporting the readiness of the packages for their next edition with `cargo check --edition-readiness`"),
+    expand: bool = ("Enable the `cargo expand` command"),
     future_incompat_report: bool = ("Enable creation of a future-incompat report for all dependencies"),
+    feature_explain: bool = ("Enable the `cargo feature explain` command"),
+    feature_matrix: bool = ("Allow testing combinations of features with `cargo test --feature-matrix`"),
     features: Option<Vec<String>>  = (HIDDEN),
+    fix_backups: bool = ("Back up the files changed by `cargo fix`, to undo the fixes with `cargo fix --undo`"),
+    gc: bool = ("Clean up the caches of the Cargo home after commands with the policy of `[gc.auto]`"),
+    git_bundle: bool = ("Enable the `cargo git-bundle` command"),
+    http_transport: bool = ("Allow configuring the HTTP version, the proxy authentication and the connection sharing of the HTTP requests"),
     jobserver_per_rustc: bool = (HIDDEN),
+    link_graph: bool = ("Record the crates and native libraries linked into each artifact"),
+    lockfile_merge: bool = ("Enable `cargo update --merge` and the `Cargo.lock` merge driver"),
+    lockfile_v5: bool = ("Write new and updated lock files in version 5, with the provenance of the packages"),
+    metadata_watch: bool = ("Allow printing the changes to the metadata with `cargo metadata --watch`"),
     minimal_versions: bool = ("Resolve minimal dependency versions instead of maximum"),
     mtime_on_use: bool = ("Configure Cargo to update the mtime of used files"),
     multitarget: bool = ("Allow passing multiple `--target` flags to the cargo subcommand selected"),
+    named_lockfiles: bool = ("Allow selecting another lock file of the workspace with `--lockfile`"),
     namespaced_features: bool = ("Allow features with `dep:` prefix"),
+    net_ssh: bool = ("Allow configuring the SSH keys of the git sources, and verify the SSH host keys"),
+    network_log: bool = ("Allow recording and replaying the network requests with `net.record` and `net.replay`"),
     no_index_update: bool = ("Do not update the registry index even if the cache is outdated"),
     panic_abort_tests: bool = ("Enable support to run tests with -Cpanic=abort"),
-    host_config: bool = ("Enable the [host] section in the .cargo/config.toml file"),
-    target_applies_to_host: bool = ("Enable the `target-applies-to-host` key in the .cargo/config.toml file"),
+    patch: bool = ("Enable the `cargo patch` command"),
+    pkg_config: bool = ("Allow writing pkg-config files for libraries with `cargo build --emit-pc`"),
+    proxy_config: bool = ("Detect the proxy of the system, and allow overriding the proxy of each registry"),
+    registry_dedup: bool = ("Store the extracted sources of registry crates once per file contents, with hard links"),
+    release: bool = ("Enable the `cargo release` command"),
+    rustc_namespace: bool = ("Keep the intermediate artifacts of each rustc version in their own directory"),
     rustdoc_map: bool = ("Allow passing external documentation mappings to rustdoc"),
     separate_nightlies: bool = (HIDDEN),
+    target_expr: bool = ("Allow selecting targets with an expression passed to `--targets`"),
+    temporary_overrides: bool = ("Allow replacing dependencies by local packages with `--override` and `Cargo.override.toml`"),
+    test_affected: bool = ("Allow running only the tests whose inputs changed with `cargo test --affected`"),
+    test_cache: bool = ("Skip running tests that already passed with the same build"),
+    test_coverage: bool = ("Allow collecting code coverage with `cargo test --coverage`"),
+    sanitizers: bool = ("Allow building and testing with `--sanitizer`"),
+    test_miri: bool = ("Allow running the tests in Miri with `cargo test --miri`"),
+    wasm_runner: bool = ("Run WebAssembly executables with an installed runtime"),
+    remote_runner: bool = ("Run executables on another machine through a long-lived runner"),
+    plugin: bool = ("Enable the `cargo plugin` command"),
+    completions: bool = ("Enable the `cargo completions` command"),
+    package_hooks: bool = ("Run a packaging program for a target after building it"),
+    test_logs: bool = ("Allow writing test output to log files with `cargo test --no-capture-streaming`"),
+    search_filters: bool = ("Allow filtering, sorting and printing as JSON with `cargo search`"),
+    serve_api: bool = ("Enable the `cargo serve-api` command"),
+    source_modes: bool = ("Allow replacing sources per profile or named mode with `replace-when`, with a lock file per mode"),
+    staged_install: bool = ("Allow staging the installed binaries into a directory with `cargo install --destdir`"),
+    staticlib_bundle: bool = ("Bundle the native libraries of build scripts into staticlibs and write their pkg-config files"),
+    subcommand_context: bool = ("Answer the queries of external subcommands for the workspace context"),
+    strict_manifest: bool = ("Turn the warnings of the manifests of path packages into errors"),
+    sumdb: bool = ("Record and verify the checksums of registry crates in a checksum database"),
     terminal_width: Option<Option<usize>>  = ("Provide a terminal width to rustc for error truncation"),
     timings: Option<Vec<String>>  = ("Display concurrency information"),
+    toolchain_config: bool = ("Select and install the rustc toolchain with the `[toolchain]` config table"),
+    toolchain_file: bool = ("Check the toolchain against `rust-toolchain.toml` before building"),
+    universal_binaries: bool = ("Allow merging the artifacts of Apple targets into universal binaries with `cargo build --lipo`"),
     unstable_options: bool = ("Allow the usage of unstable options"),
+    validate: bool = ("Enable the `cargo validate` command"),
+    vcs_policy: bool = ("Configure what commands do with uncommitted changes with `[vcs.allow-dirty]`"),
+    version_bump: bool = ("Enable the `cargo version bump` command"),
+    warnings: bool = ("Allow summarizing or denying the warnings of a build with `--warnings`"),
     weak_dep_features: bool = ("Allow `dep_name?/feature` feature syntax"),
     // TODO(wcrichto): move scrape example configuration into Cargo.toml before stabilization
     // See: https://github.com/rust-lang/cargo/pull/9525#discussion_r728470927
@@ -701,6 +856,12 @@ const STABILIZED_CONFIGURABLE_ENV: &str = "The [env] section is now always enabl
 
 const STABILIZED_PATCH_IN_CONFIG: &str = "The patch-in-config feature is now always enabled.";
 
+const STABILIZED_HOST_CONFIG: &str = "The [host] section and the \
+    `target-applies-to-host` key are now always enabled, and the [target] section \
+    no longer applies to build scripts and proc-macros when cross compiling.\n\
+    See https://doc.rust-lang.org/nightly/cargo/reference/config.html#host \
+    for more information.";
+
 const STABILIZED_NAMED_PROFILES: &str = "The named-profiles feature is now always enabled.\n\
     See https://doc.rust-lang.org/nightly/cargo/reference/profiles.html#custom-profiles \
     for more information";
@@ -830,8 +991,12 @@ impl CliUnstable {
             "unstable-options" => self.unstable_options = parse_empty(k, v)?,
             "no-index-update" => self.no_index_update = parse_empty(k, v)?,
             "avoid-dev-deps" => self.avoid_dev_deps = parse_empty(k, v)?,
+            "auto-features" => self.auto_features = parse_empty(k, v)?,
+            "auto-install-targets" => self.auto_install_targets = parse_empty(k, v)?,
             "minimal-versions" => self.minimal_versions = parse_empty(k, v)?,
             "advanced-env" => self.advanced_env = parse_empty(k, v)?,
+            "apply-suggestions" => self.apply_suggestions = parse_empty(k, v)?,
+            "asm" => self.asm = parse_empty(k, v)?,
             "config-include" => self.config_include = parse_empty(k, v)?,
             "dual-proc-macros" => self.dual_proc_macros = parse_empty(k, v)?,
             // can also be set in .cargo/config or with and ENV
@@ -842,13 +1007,71 @@ impl CliUnstable {
                 self.build_std = Some(crate::core::compiler::standard_lib::parse_unstable_flag(v))
             }
             "build-std-features" => self.build_std_features = Some(parse_features(v)),
+            "changed-since" => self.changed_since = parse_empty(k, v)?,
+            "edition-migration" => self.edition_migration = parse_empty(k, v)?,
+            "edition-readiness" => self.edition_readiness = parse_empty(k, v)?,
+            "expand" => self.expand = parse_empty(k, v)?,
+            "feature-explain" => self.feature_explain = parse_empty(k, v)?,
+            "feature-matrix" => self.feature_matrix = parse_empty(k, v)?,
+            "fix-backups" => self.fix_backups = parse_empty(k, v)?,
+            "test-affected" => self.test_affected = parse_empty(k, v)?,
+            "test-cache" => self.test_cache = parse_empty(k, v)?,
+            "test-coverage" => self.test_coverage = parse_empty(k, v)?,
+            "toolchain-config" => self.toolchain_config = parse_empty(k, v)?,
+            "toolchain-file" => self.toolchain_file = parse_empty(k, v)?,
+            "sanitizers" => self.sanitizers = parse_empty(k, v)?,
+            "test-miri" => self.test_miri = parse_empty(k, v)?,
+            "wasm-runner" => self.wasm_runner = parse_empty(k, v)?,
+            "remote-runner" => self.remote_runner = parse_empty(k, v)?,
+            "package-hooks" => self.package_hooks = parse_empty(k, v)?,
+            "test-logs" => self.test_logs = parse_empty(k, v)?,
             "timings" => self.timings = Some(parse_timings(v)),
             "doctest-xcompile" => self.doctest_xcompile = parse_empty(k, v)?,
             "doctest-in-workspace" => self.doctest_in_workspace = parse_empty(k, v)?,
             "panic-abort-tests" => self.panic_abort_tests = parse_empty(k, v)?,
+            "release" => self.release = parse_empty(k, v)?,
             "jobserver-per-rustc" => self.jobserver_per_rustc = parse_empty(k, v)?,
-            "host-config" => self.host_config = parse_empty(k, v)?,
-            "target-applies-to-host" => self.target_applies_to_host = parse_empty(k, v)?,
+            "link-graph" => self.link_graph = parse_empty(k, v)?,
+            "target-expr" => self.target_expr = parse_empty(k, v)?,
+            "source-modes" => self.source_modes = parse_empty(k, v)?,
+            "temporary-overrides" => self.temporary_overrides = parse_empty(k, v)?,
+            "vcs-policy" => self.vcs_policy = parse_empty(k, v)?,
+            "version-bump" => self.version_bump = parse_empty(k, v)?,
+            "sumdb" => self.sumdb = parse_empty(k, v)?,
+            "validate" => self.validate = parse_empty(k, v)?,
+            "strict-manifest" => self.strict_manifest = parse_empty(k, v)?,
+            "metadata-watch" => self.metadata_watch = parse_empty(k, v)?,
+            "serve-api" => self.serve_api = parse_empty(k, v)?,
+            "plugin" => self.plugin = parse_empty(k, v)?,
+            "subcommand-context" => self.subcommand_context = parse_empty(k, v)?,
+            "completions" => self.completions = parse_empty(k, v)?,
+            "search-filters" => self.search_filters = parse_empty(k, v)?,
+            "doc-offline-first" => self.doc_offline_first = parse_empty(k, v)?,
+            "doc-index" => self.doc_index = parse_empty(k, v)?,
+            "doc-coverage" => self.doc_coverage = parse_empty(k, v)?,
+            "staticlib-bundle" => self.staticlib_bundle = parse_empty(k, v)?,
+            "pkg-config" => self.pkg_config = parse_empty(k, v)?,
+            "proxy-config" => self.proxy_config = parse_empty(k, v)?,
+            "staged-install" => self.staged_install = parse_empty(k, v)?,
+            "binary-package" => self.binary_package = parse_empty(k, v)?,
+            "build-stats" => self.build_stats = parse_empty(k, v)?,
+            "add" => self.add = parse_empty(k, v)?,
+            "patch" => self.patch = parse_empty(k, v)?,
+            "check-cfg" => self.check_cfg = parse_empty(k, v)?,
+            "code-signing" => self.code_signing = parse_empty(k, v)?,
+            "deny-warnings" => self.deny_warnings = parse_empty(k, v)?,
+            "audits" => self.audits = parse_empty(k, v)?,
+            "delta-downloads" => self.delta_downloads = parse_empty(k, v)?,
+            "downloads" => self.downloads = parse_empty(k, v)?,
+            "registry-dedup" => self.registry_dedup = parse_empty(k, v)?,
+            "cache" => self.cache = parse_empty(k, v)?,
+            "gc" => self.gc = parse_empty(k, v)?,
+            "git-bundle" => self.git_bundle = parse_empty(k, v)?,
+            "http-transport" => self.http_transport = parse_empty(k, v)?,
+            "lockfile-v5" => self.lockfile_v5 = parse_empty(k, v)?,
+            "lockfile-merge" => self.lockfile_merge = parse_empty(k, v)?,
+            "direct-minimal-versions" => self.direct_minimal_versions = parse_empty(k, v)?,
+            "named-lockfiles" => self.named_lockfiles = parse_empty(k, v)?,
             "features" => {
                 // For now this is still allowed (there are still some
                 // unstable options like "compare"). This should be removed at
@@ -870,10 +1093,16 @@ impl CliUnstable {
             "separate-nightlies" => self.separate_nightlies = parse_empty(k, v)?,
             "multitarget" => self.multitarget = parse_empty(k, v)?,
             "rustdoc-map" => self.rustdoc_map = parse_empty(k, v)?,
+            "rustc-namespace" => self.rustc_namespace = parse_empty(k, v)?,
+            "universal-binaries" => self.universal_binaries = parse_empty(k, v)?,
             "terminal-width" => self.terminal_width = Some(parse_usize_opt(v)?),
             "namespaced-features" => self.namespaced_features = parse_empty(k, v)?,
+            "network-log" => self.network_log = parse_empty(k, v)?,
+            "net-ssh" => self.net_ssh = parse_empty(k, v)?,
             "weak-dep-features" => self.weak_dep_features = parse_empty(k, v)?,
+            "warnings" => self.warnings = parse_empty(k, v)?,
             "credential-process" => self.credential_process = parse_empty(k, v)?,
+            "client-certs" => self.client_certs = parse_empty(k, v)?,
             "rustdoc-scrape-examples" => self.rustdoc_scrape_examples = v.map(|s| s.to_string()),
             "skip-rustdoc-fingerprint" => self.skip_rustdoc_fingerprint = parse_empty(k, v)?,
             "compile-progress" => stabilized_warn(k, "1.30", STABILIZED_COMPILE_PROGRESS),
@@ -886,6 +1115,8 @@ impl CliUnstable {
             "extra-link-arg" => stabilized_warn(k, "1.56", STABILIZED_EXTRA_LINK_ARG),
             "configurable-env" => stabilized_warn(k, "1.56", STABILIZED_CONFIGURABLE_ENV),
             "patch-in-config" => stabilized_warn(k, "1.56", STABILIZED_PATCH_IN_CONFIG),
+            "host-config" => stabilized_warn(k, "1.59", STABILIZED_HOST_CONFIG),
+            "target-applies-to-host" => stabilized_warn(k, "1.59", STABILIZED_HOST_CONFIG),
             "future-incompat-report" => self.future_incompat_report = parse_empty(k, v)?,
             _ => bail!("unknown `-Z` flag specified: {}", k),
         }
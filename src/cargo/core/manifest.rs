@@ -6,6 +6,7 @@ use std::rc::Rc;
 use std::sync::Arc;
 
 use anyhow::Context as _;
+use cargo_platform::Cfg;
 use semver::Version;
 use serde::ser;
 use serde::Serialize;
@@ -44,6 +45,10 @@ pub struct Manifest {
     publish: Option<Vec<String>>,
     replace: Vec<(PackageIdSpec, Dependency)>,
     patch: HashMap<Url, Vec<Dependency>>,
+    feature_overrides: BTreeMap<InternedString, FeatureOverride>,
+    config_flags: BTreeMap<InternedString, Vec<Cfg>>,
+    env: BTreeMap<String, EnvValue>,
+    deny_warnings: bool,
     workspace: WorkspaceConfig,
     original: Rc<TomlManifest>,
     unstable_features: Features,
@@ -52,6 +57,10 @@ pub struct Manifest {
     im_a_teapot: Option<bool>,
     default_run: Option<String>,
     metabuild: Option<Vec<String>>,
+    build_info: Option<BuildInfo>,
+    assets: BTreeMap<String, Asset>,
+    autofix: bool,
+    expected_cfgs: Option<Vec<Cfg>>,
     resolve_behavior: Option<ResolveBehavior>,
 }
 
@@ -71,6 +80,10 @@ pub struct Warnings(Vec<DelayedWarning>);
 pub struct VirtualManifest {
     replace: Vec<(PackageIdSpec, Dependency)>,
     patch: HashMap<Url, Vec<Dependency>>,
+    feature_overrides: BTreeMap<InternedString, FeatureOverride>,
+    config_flags: BTreeMap<InternedString, Vec<Cfg>>,
+    env: BTreeMap<String, EnvValue>,
+    deny_warnings: bool,
     workspace: WorkspaceConfig,
     profiles: Option<TomlProfiles>,
     warnings: Warnings,
@@ -78,6 +91,109 @@ pub struct VirtualManifest {
     resolve_behavior: Option<ResolveBehavior>,
 }
 
+/// A variable of the `[env]` table, set when compiling the package.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum EnvValue {
+    /// A fixed value.
+    Value(String),
+    /// The contents of a file, without trailing whitespace.
+    File(PathBuf),
+}
+
+impl EnvValue {
+    pub fn resolve(&self) -> CargoResult<String> {
+        match self {
+            EnvValue::Value(value) => Ok(value.clone()),
+            EnvValue::File(path) => Ok(cargo_util::paths::read(path)?.trim_end().to_string()),
+        }
+    }
+}
+
+/// The `package.build-info` setting, which makes Cargo describe the build to
+/// the compiler in the `CARGO_GIT_*` and `CARGO_BUILD_TIMESTAMP` variables.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BuildInfo {
+    pub timestamp: BuildTimestamp,
+}
+
+/// Where `CARGO_BUILD_TIMESTAMP` comes from, unless `SOURCE_DATE_EPOCH` is set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BuildTimestamp {
+    /// The time of the build, which doesn't cause rebuilds by itself.
+    Build,
+    /// The time of the commit, for reproducible builds.
+    Commit,
+}
+
+/// An entry of the `[package.assets]` table, a file or a set of files
+/// compiled into the package.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Asset {
+    /// The path of the file, or a pattern matching the files if `is_glob`.
+    pub path: PathBuf,
+    pub is_glob: bool,
+    pub compress: Option<AssetCompression>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AssetCompression {
+    Gzip,
+}
+
+/// An entry of the `[feature-overrides]` table, which changes the features
+/// that every dependency on a package requests.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FeatureOverride {
+    /// Forces `default-features` on every dependency on the package.
+    pub default_features: Option<bool>,
+    /// Features requested by every dependency on the package.
+    pub enable: Vec<InternedString>,
+    /// Features which are never enabled, not even by other features.
+    pub disable: Vec<InternedString>,
+}
+
+impl FeatureOverride {
+    /// Rewrites the features requested by a dependency on the overridden
+    /// package.
+    pub fn apply(&self, mut dep: Dependency) -> Dependency {
+        if let Some(default_features) = self.default_features {
+            dep.set_default_features(default_features);
+        }
+        let features: Vec<InternedString> = dep
+            .features()
+            .iter()
+            .filter(|f| !self.disable.contains(f) && !self.enable.contains(f))
+            .chain(&self.enable)
+            .cloned()
+            .collect();
+        dep.set_features(features);
+        dep
+    }
+
+    /// Describes what the override changes, like "default features disabled,
+    /// feature `fs` disabled".
+    pub fn describe(&self) -> String {
+        let mut changes = Vec::new();
+        match self.default_features {
+            Some(true) => changes.push("default features enabled".to_string()),
+            Some(false) => changes.push("default features disabled".to_string()),
+            None => {}
+        }
+        for (what, features) in [("enabled", &self.enable), ("disabled", &self.disable)] {
+            if !features.is_empty() {
+                let names: Vec<_> = features.iter().map(|f| format!("`{}`", f)).collect();
+                changes.push(format!(
+                    "feature{} {} {}",
+                    if names.len() == 1 { "" } else { "s" },
+                    names.join(", "),
+                    what
+                ));
+            }
+        }
+        changes.join(", ")
+    }
+}
+
 /// General metadata about a package which is just blindly uploaded to the
 /// registry.
 ///
@@ -111,6 +227,8 @@ pub enum TargetKind {
     ExampleLib(Vec<CrateType>),
     ExampleBin,
     CustomBuild,
+    Fuzz,
+    Codegen,
 }
 
 impl ser::Serialize for TargetKind {
@@ -126,6 +244,8 @@ impl ser::Serialize for TargetKind {
             Test => ["test"].serialize(s),
             CustomBuild => ["custom-build"].serialize(s),
             Bench => ["bench"].serialize(s),
+            Fuzz => ["fuzz"].serialize(s),
+            Codegen => ["codegen"].serialize(s),
         }
     }
 }
@@ -140,6 +260,8 @@ impl fmt::Debug for TargetKind {
             Test => "test".fmt(f),
             CustomBuild => "custom-build".fmt(f),
             Bench => "bench".fmt(f),
+            Fuzz => "fuzz".fmt(f),
+            Codegen => "codegen".fmt(f),
         }
     }
 }
@@ -153,6 +275,8 @@ impl TargetKind {
             TargetKind::ExampleBin | TargetKind::ExampleLib(..) => "example",
             TargetKind::Bench => "bench",
             TargetKind::CustomBuild => "build-script",
+            TargetKind::Fuzz => "fuzz",
+            TargetKind::Codegen => "codegen",
         }
     }
 
@@ -178,6 +302,8 @@ impl TargetKind {
             | TargetKind::Bench
             | TargetKind::Test
             | TargetKind::ExampleBin
+            | TargetKind::Fuzz
+            | TargetKind::Codegen
             | TargetKind::Bin => vec![CrateType::Bin],
         }
     }
@@ -383,6 +509,10 @@ impl Manifest {
         publish: Option<Vec<String>>,
         replace: Vec<(PackageIdSpec, Dependency)>,
         patch: HashMap<Url, Vec<Dependency>>,
+        feature_overrides: BTreeMap<InternedString, FeatureOverride>,
+        config_flags: BTreeMap<InternedString, Vec<Cfg>>,
+        env: BTreeMap<String, EnvValue>,
+        deny_warnings: bool,
         workspace: WorkspaceConfig,
         unstable_features: Features,
         edition: Edition,
@@ -391,6 +521,10 @@ impl Manifest {
         default_run: Option<String>,
         original: Rc<TomlManifest>,
         metabuild: Option<Vec<String>>,
+        build_info: Option<BuildInfo>,
+        assets: BTreeMap<String, Asset>,
+        autofix: bool,
+        expected_cfgs: Option<Vec<Cfg>>,
         resolve_behavior: Option<ResolveBehavior>,
     ) -> Manifest {
         Manifest {
@@ -408,6 +542,10 @@ impl Manifest {
             publish,
             replace,
             patch,
+            feature_overrides,
+            config_flags,
+            env,
+            deny_warnings,
             workspace,
             unstable_features,
             edition,
@@ -416,6 +554,10 @@ impl Manifest {
             im_a_teapot,
             default_run,
             metabuild,
+            build_info,
+            assets,
+            autofix,
+            expected_cfgs,
             resolve_behavior,
         }
     }
@@ -481,6 +623,21 @@ impl Manifest {
     pub fn patch(&self) -> &HashMap<Url, Vec<Dependency>> {
         &self.patch
     }
+    pub fn feature_overrides(&self) -> &BTreeMap<InternedString, FeatureOverride> {
+        &self.feature_overrides
+    }
+    /// The cfgs set for packages in `[config-flags]`, by package name.
+    pub fn config_flags(&self) -> &BTreeMap<InternedString, Vec<Cfg>> {
+        &self.config_flags
+    }
+    pub fn env(&self) -> &BTreeMap<String, EnvValue> {
+        &self.env
+    }
+    /// Whether `lints.workspace-deny-warnings` denies the warnings of the
+    /// workspace members.
+    pub fn deny_warnings(&self) -> bool {
+        self.deny_warnings
+    }
     pub fn links(&self) -> Option<&str> {
         self.links.as_deref()
     }
@@ -559,6 +716,24 @@ impl Manifest {
         self.metabuild.as_ref()
     }
 
+    pub fn build_info(&self) -> Option<BuildInfo> {
+        self.build_info
+    }
+
+    pub fn assets(&self) -> &BTreeMap<String, Asset> {
+        &self.assets
+    }
+
+    /// The cfgs the package can be configured with, declared in
+    /// `package.expected-cfgs`.
+    pub fn expected_cfgs(&self) -> Option<&[Cfg]> {
+        self.expected_cfgs.as_deref()
+    }
+    /// Whether `cargo fix` may change the files of the package.
+    pub fn autofix(&self) -> bool {
+        self.autofix
+    }
+
     pub fn metabuild_path(&self, target_dir: Filesystem) -> PathBuf {
         let hash = short_hash(&self.package_id());
         target_dir
@@ -572,6 +747,10 @@ impl VirtualManifest {
     pub fn new(
         replace: Vec<(PackageIdSpec, Dependency)>,
         patch: HashMap<Url, Vec<Dependency>>,
+        feature_overrides: BTreeMap<InternedString, FeatureOverride>,
+        config_flags: BTreeMap<InternedString, Vec<Cfg>>,
+        env: BTreeMap<String, EnvValue>,
+        deny_warnings: bool,
         workspace: WorkspaceConfig,
         profiles: Option<TomlProfiles>,
         features: Features,
@@ -580,6 +759,10 @@ impl VirtualManifest {
         VirtualManifest {
             replace,
             patch,
+            feature_overrides,
+            config_flags,
+            env,
+            deny_warnings,
             workspace,
             profiles,
             warnings: Warnings::new(),
@@ -596,6 +779,25 @@ impl VirtualManifest {
         &self.patch
     }
 
+    pub fn feature_overrides(&self) -> &BTreeMap<InternedString, FeatureOverride> {
+        &self.feature_overrides
+    }
+
+    /// The cfgs set for packages in `[config-flags]`, by package name.
+    pub fn config_flags(&self) -> &BTreeMap<InternedString, Vec<Cfg>> {
+        &self.config_flags
+    }
+
+    pub fn env(&self) -> &BTreeMap<String, EnvValue> {
+        &self.env
+    }
+
+    /// Whether `lints.workspace-deny-warnings` denies the warnings of the
+    /// workspace members.
+    pub fn deny_warnings(&self) -> bool {
+        self.deny_warnings
+    }
+
     pub fn workspace_config(&self) -> &WorkspaceConfig {
         &self.workspace
     }
@@ -757,6 +959,34 @@ impl Target {
         target
     }
 
+    pub fn fuzz_target(
+        name: &str,
+        src_path: PathBuf,
+        required_features: Option<Vec<String>>,
+        edition: Edition,
+    ) -> Target {
+        let mut target = Target::with_path(src_path, edition);
+        target
+            .set_kind(TargetKind::Fuzz)
+            .set_name(name)
+            .set_required_features(required_features)
+            .set_tested(false)
+            .set_benched(false);
+        target
+    }
+
+    /// Builds a `Target` corresponding to a `[[codegen]]` step.
+    pub fn codegen_target(name: &str, src_path: PathBuf, edition: Edition) -> Target {
+        let mut target = Target::with_path(src_path, edition);
+        target
+            .set_kind(TargetKind::Codegen)
+            .set_name(name)
+            .set_for_host(true)
+            .set_benched(false)
+            .set_tested(false);
+        target
+    }
+
     pub fn name(&self) -> &str {
         &self.inner.name
     }
@@ -823,7 +1053,9 @@ impl Target {
 
     pub fn is_cdylib(&self) -> bool {
         match self.kind() {
-            TargetKind::Lib(libs) => libs.iter().any(|l| *l == CrateType::Cdylib),
+            TargetKind::Lib(libs) => libs
+                .iter()
+                .any(|l| matches!(l, CrateType::Cdylib | CrateType::Component)),
             _ => false,
         }
     }
@@ -871,6 +1103,18 @@ impl Target {
     pub fn is_custom_build(&self) -> bool {
         *self.kind() == TargetKind::CustomBuild
     }
+    pub fn is_fuzz(&self) -> bool {
+        *self.kind() == TargetKind::Fuzz
+    }
+    pub fn is_codegen(&self) -> bool {
+        *self.kind() == TargetKind::Codegen
+    }
+
+    /// Returns `true` if it is compiled for the host and run while building
+    /// the package, like the build script and the codegen steps.
+    pub fn is_build_program(&self) -> bool {
+        self.is_custom_build() || self.is_codegen()
+    }
 
     /// Returns the arguments suitable for `--crate-type` to pass to rustc.
     pub fn rustc_crate_types(&self) -> Vec<CrateType> {
@@ -938,6 +1182,8 @@ impl Target {
                 format!("example \"{}\"", self.name())
             }
             TargetKind::CustomBuild => "build script".to_string(),
+            TargetKind::Fuzz => format!("fuzz target \"{}\"", self.name()),
+            TargetKind::Codegen => format!("codegen \"{}\"", self.name()),
         }
     }
 }
@@ -953,6 +1199,8 @@ impl fmt::Display for Target {
                 write!(f, "Target(example: {})", self.name())
             }
             TargetKind::CustomBuild => write!(f, "Target(script)"),
+            TargetKind::Fuzz => write!(f, "Target(fuzz: {})", self.name()),
+            TargetKind::Codegen => write!(f, "Target(codegen: {})", self.name()),
         }
     }
 }
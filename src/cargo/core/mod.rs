@@ -1,17 +1,23 @@
 pub use self::dependency::Dependency;
 pub use self::features::{CliUnstable, Edition, Feature, Features};
-pub use self::manifest::{EitherManifest, VirtualManifest};
+pub use self::manifest::{EitherManifest, EnvValue, FeatureOverride, VirtualManifest};
 pub use self::manifest::{Manifest, Target, TargetKind};
 pub use self::package::{Package, PackageSet};
 pub use self::package_id::PackageId;
 pub use self::package_id_spec::PackageIdSpec;
+pub use self::policy::{
+    check_license_expr, AllowedSources, DeniedPackage, RequireAudits, WorkspacePolicy,
+};
 pub use self::registry::Registry;
 pub use self::resolver::{Resolve, ResolveVersion};
 pub use self::shell::{Shell, Verbosity};
 pub use self::source::{GitReference, Source, SourceId, SourceMap};
 pub use self::summary::{FeatureMap, FeatureValue, Summary};
-pub use self::workspace::{MaybePackage, Workspace, WorkspaceConfig, WorkspaceRootConfig};
+pub use self::workspace::{
+    ChangelogConfig, LinksPolicy, MaybePackage, Workspace, WorkspaceConfig, WorkspaceRootConfig,
+};
 
+pub mod audits;
 pub mod compiler;
 pub mod dependency;
 pub mod features;
@@ -19,6 +25,7 @@ pub mod manifest;
 pub mod package;
 pub mod package_id;
 mod package_id_spec;
+mod policy;
 pub mod profiles;
 pub mod registry;
 pub mod resolver;
@@ -1,6 +1,6 @@
 use std::cell::{Cell, Ref, RefCell, RefMut};
 use std::cmp::Ordering;
-use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::fmt;
 use std::hash;
 use std::mem;
@@ -8,7 +8,7 @@ use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::time::{Duration, Instant};
 
-use anyhow::Context;
+use anyhow::{bail, Context};
 use bytesize::ByteSize;
 use curl::easy::{Easy, HttpVersion};
 use curl::multi::{EasyHandle, Multi};
@@ -16,6 +16,7 @@ use lazycell::LazyCell;
 use log::{debug, warn};
 use semver::Version;
 use serde::Serialize;
+use url::Url;
 
 use crate::core::compiler::{CompileKind, RustcTargetData};
 use crate::core::dependency::DepKind;
@@ -28,7 +29,9 @@ use crate::ops;
 use crate::util::config::PackageCacheLock;
 use crate::util::errors::{CargoResult, HttpNot200};
 use crate::util::interning::InternedString;
+use crate::util::machine_message::{self, Message};
 use crate::util::network::Retry;
+use crate::util::toml::TomlFeatureMetadata;
 use crate::util::{self, internal, Config, Progress, ProgressStyle};
 
 pub const MANIFEST_PREAMBLE: &str = "\
@@ -87,6 +90,8 @@ pub struct SerializedPackage {
     dependencies: Vec<Dependency>,
     targets: Vec<Target>,
     features: BTreeMap<InternedString, Vec<InternedString>>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    feature_metadata: BTreeMap<InternedString, TomlFeatureMetadata>,
     manifest_path: PathBuf,
     metadata: Option<toml::Value>,
     publish: Option<Vec<String>>,
@@ -255,6 +260,12 @@ impl Package {
             dependencies: summary.dependencies().to_vec(),
             targets,
             features,
+            feature_metadata: self
+                .manifest()
+                .original()
+                .feature_metadata()
+                .cloned()
+                .unwrap_or_default(),
             manifest_path: self.manifest_path().to_path_buf(),
             metadata: self.manifest().custom_metadata().cloned(),
             authors: manmeta.authors.clone(),
@@ -326,8 +337,14 @@ pub struct Downloads<'a, 'cfg> {
     /// finished.
     pending: HashMap<usize, (Download<'cfg>, EasyHandle)>,
     /// Set of packages currently being downloaded. This should stay in sync
-    /// with `pending`.
+    /// with `pending` and `queued`.
     pending_ids: HashSet<PackageId>,
+    /// Downloads which are waiting to start, because
+    /// `net.max-concurrent-downloads` are running already, or to be retried
+    /// after a delay.
+    queued: VecDeque<(Download<'cfg>, Easy)>,
+    /// The maximum number of downloads running at once.
+    max_concurrent: Option<usize>,
     /// The final result of each download. A pair `(token, result)`. This is a
     /// temporary holding area, needed because curl can report multiple
     /// downloads at once, but the main loop (`wait`) is written to only
@@ -343,6 +360,8 @@ pub struct Downloads<'a, 'cfg> {
     downloaded_bytes: u64,
     /// Size (in bytes) and package name of the largest downloaded package.
     largest: (u64, String),
+    /// The maximum rate of each download, in bytes per second.
+    max_rate: Option<u64>,
     /// Time when downloading started.
     start: Instant,
     /// Indicates *all* downloads were successful.
@@ -402,6 +421,13 @@ struct Download<'cfg> {
 
     /// Logic used to track retrying this download if it's a spurious failure.
     retry: Retry<'cfg>,
+    /// The number of attempts which failed.
+    failed_attempts: u32,
+    /// The number of bytes of `data` received by the attempts which failed,
+    /// which the current attempt resumes from.
+    resumed_from: u64,
+    /// When the download may be retried, while it's queued.
+    retry_at: Option<Instant>,
 }
 
 impl<'cfg> PackageSet<'cfg> {
@@ -452,12 +478,16 @@ impl<'cfg> PackageSet<'cfg> {
     pub fn enable_download<'a>(&'a self) -> CargoResult<Downloads<'a, 'cfg>> {
         assert!(!self.downloading.replace(true));
         let timeout = ops::HttpTimeout::new(self.config)?;
+        let (max_concurrent, max_rate) = download_limits(self.config)?;
         Ok(Downloads {
             start: Instant::now(),
             set: self,
             next: 0,
             pending: HashMap::new(),
             pending_ids: HashSet::new(),
+            queued: VecDeque::new(),
+            max_concurrent,
+            max_rate,
             results: Vec::new(),
             progress: RefCell::new(Some(Progress::with_style(
                 "Downloading",
@@ -715,12 +745,25 @@ impl<'a, 'cfg> Downloads<'a, 'cfg> {
         // happen during `wait_for_download`
         let token = self.next;
         self.next += 1;
-        debug!("downloading {} as {}", id, token);
+        debug!("downloading {} as {} from {}", id, token, url);
         assert!(self.pending_ids.insert(id));
 
-        let (mut handle, _timeout) = ops::http_handle_and_timeout(self.set.config)?;
+        // A replayed download reads the recorded response instead.
+        let mut handle = match self.set.config.network_log()? {
+            Some(log) if log.is_replay() => {
+                let path = log.replay_http(&url)?;
+                let mut handle = Easy::new();
+                handle.url(Url::from_file_path(&path).unwrap().as_str())?;
+                handle
+            }
+            _ => {
+                let (mut handle, _timeout) = ops::http_handle_and_timeout(self.set.config)?;
+                handle.url(&url)?;
+                ops::configure_registry_handle(self.set.config, &mut handle, id.source_id())?;
+                handle
+            }
+        };
         handle.get(true)?;
-        handle.url(&url)?;
         handle.follow_location(true)?; // follow redirects
 
         // Enable HTTP/2 to be used as it'll allow true multiplexing which makes
@@ -735,10 +778,15 @@ impl<'a, 'cfg> Downloads<'a, 'cfg> {
         // HTTP/2 but newer ones will. All that to basically say we ignore
         // errors here on OSX, but consider this a fatal error to not activate
         // HTTP/2 on all other platforms.
-        if self.set.multiplexing {
-            try_old_curl!(handle.http_version(HttpVersion::V2), "HTTP2");
-        } else {
-            handle.http_version(HttpVersion::V11)?;
+        //
+        // The version of `http.version` was already set with the other
+        // settings of the handle.
+        if self.set.config.http_config()?.version.is_none() {
+            if self.set.multiplexing {
+                try_old_curl!(handle.http_version(HttpVersion::V2), "HTTP2");
+            } else {
+                handle.http_version(HttpVersion::V11)?;
+            }
         }
 
         // This is an option to `libcurl` which indicates that if there's a
@@ -750,6 +798,10 @@ impl<'a, 'cfg> Downloads<'a, 'cfg> {
         // reduces the number of connections done to a more manageable state.
         try_old_curl!(handle.pipewait(true), "pipewait");
 
+        if let Some(rate) = self.max_rate {
+            handle.max_recv_speed(rate)?;
+        }
+
         handle.write_function(move |buf| {
             debug!("{} - {} bytes of data", token, buf.len());
             tls::with(|downloads| {
@@ -796,8 +848,12 @@ impl<'a, 'cfg> Downloads<'a, 'cfg> {
             start: Instant::now(),
             timed_out: Cell::new(None),
             retry: Retry::new(self.set.config)?,
+            failed_attempts: 0,
+            resumed_from: 0,
+            retry_at: None,
         };
-        self.enqueue(dl, handle)?;
+        self.queued.push_back((dl, handle));
+        self.start_queued()?;
         self.tick(WhyTick::DownloadStarted)?;
 
         Ok(None)
@@ -805,7 +861,7 @@ impl<'a, 'cfg> Downloads<'a, 'cfg> {
 
     /// Returns the number of crates that are still downloading.
     pub fn remaining(&self) -> usize {
-        self.pending.len()
+        self.pending.len() + self.queued.len()
     }
 
     /// Blocks the current thread waiting for a package to finish downloading.
@@ -818,7 +874,10 @@ impl<'a, 'cfg> Downloads<'a, 'cfg> {
     /// This function will panic if there are no remaining downloads.
     pub fn wait(&mut self) -> CargoResult<&'a Package> {
         let (dl, data) = loop {
-            assert_eq!(self.pending.len(), self.pending_ids.len());
+            assert_eq!(
+                self.pending.len() + self.queued.len(),
+                self.pending_ids.len()
+            );
             let (token, result) = self.wait_for_curl()?;
             debug!("{} finished with {:?}", token, result);
 
@@ -826,60 +885,99 @@ impl<'a, 'cfg> Downloads<'a, 'cfg> {
                 .pending
                 .remove(&token)
                 .expect("got a token for a non-in-progress transfer");
-            let data = mem::take(&mut *dl.data.borrow_mut());
             let mut handle = self.set.multi.remove(handle)?;
             self.pending_ids.remove(&dl.id);
 
+            // A server which doesn't support ranges can't resume a download,
+            // so it starts over, which isn't a retry.
+            if let Err(e) = &result {
+                if dl.resumed_from > 0 && e.is_range_error() {
+                    debug!("{} can't be resumed, starting over", dl.id);
+                    dl.data.borrow_mut().clear();
+                    dl.resumed_from = 0;
+                    handle.resume_from(0)?;
+                    self.pending_ids.insert(dl.id);
+                    self.queued.push_front((dl, handle));
+                    continue;
+                }
+            }
+
             // Check if this was a spurious error. If it was a spurious error
             // then we want to re-enqueue our request for another attempt and
             // then we wait for another request to finish.
-            let ret = {
-                let timed_out = &dl.timed_out;
-                let url = &dl.url;
-                dl.retry
-                    .r#try(|| {
-                        if let Err(e) = result {
-                            // If this error is "aborted by callback" then that's
-                            // probably because our progress callback aborted due to
-                            // a timeout. We'll find out by looking at the
-                            // `timed_out` field, looking for a descriptive message.
-                            // If one is found we switch the error code (to ensure
-                            // it's flagged as spurious) and then attach our extra
-                            // information to the error.
-                            if !e.is_aborted_by_callback() {
-                                return Err(e.into());
-                            }
-
-                            return Err(match timed_out.replace(None) {
-                                Some(msg) => {
-                                    let code = curl_sys::CURLE_OPERATION_TIMEDOUT;
-                                    let mut err = curl::Error::new(code);
-                                    err.set_extra(msg);
-                                    err
-                                }
-                                None => e,
-                            }
-                            .into());
-                        }
+            let result = (|| -> CargoResult<()> {
+                if let Err(e) = result {
+                    // If this error is "aborted by callback" then that's
+                    // probably because our progress callback aborted due to
+                    // a timeout. We'll find out by looking at the
+                    // `timed_out` field, looking for a descriptive message.
+                    // If one is found we switch the error code (to ensure
+                    // it's flagged as spurious) and then attach our extra
+                    // information to the error.
+                    if !e.is_aborted_by_callback() {
+                        return Err(e.into());
+                    }
 
-                        let code = handle.response_code()?;
-                        if code != 200 && code != 0 {
-                            let url = handle.effective_url()?.unwrap_or(url);
-                            return Err(HttpNot200 {
-                                code,
-                                url: url.to_string(),
-                            }
-                            .into());
+                    return Err(match dl.timed_out.replace(None) {
+                        Some(msg) => {
+                            let code = curl_sys::CURLE_OPERATION_TIMEDOUT;
+                            let mut err = curl::Error::new(code);
+                            err.set_extra(msg);
+                            err
                         }
-                        Ok(())
-                    })
-                    .with_context(|| format!("failed to download from `{}`", dl.url))?
-            };
+                        None => e,
+                    }
+                    .into());
+                }
+
+                let code = handle.response_code()?;
+                let resumed = code == 206 && dl.resumed_from > 0;
+                if code != 200 && code != 0 && !resumed {
+                    let url = handle.effective_url()?.unwrap_or(&dl.url);
+                    return Err(HttpNot200 {
+                        code,
+                        url: url.to_string(),
+                    }
+                    .into());
+                }
+                Ok(())
+            })();
+            if let Some(log) = self.set.config.network_log()? {
+                let status = handle.response_code().ok();
+                log.record_http(&dl.url, status, &result, &dl.data.borrow(), dl.start)?;
+            }
+            // The data received before a transfer error is kept to resume
+            // from, unlike the body of an HTTP error.
+            let resumable = matches!(&result, Err(e) if e.is::<curl::Error>());
+            let error = result.as_ref().err().map(|e| e.root_cause().to_string());
+            let ret = dl
+                .retry
+                .r#try(|| result)
+                .with_context(|| format!("failed to download from `{}`", dl.url))?;
             match ret {
-                Some(()) => break (dl, data),
+                Some(()) => {
+                    let data = mem::take(&mut *dl.data.borrow_mut());
+                    break (dl, data);
+                }
                 None => {
+                    dl.failed_attempts += 1;
+                    if !resumable {
+                        dl.data.borrow_mut().clear();
+                    }
+                    dl.resumed_from = dl.data.borrow().len() as u64;
+                    handle.resume_from(dl.resumed_from)?;
+                    let delay = retry_delay(dl.failed_attempts);
+                    dl.retry_at = Some(Instant::now() + delay);
+                    self.message(&machine_message::DownloadRetry {
+                        package_id: dl.id,
+                        url: &dl.url,
+                        attempt: dl.failed_attempts,
+                        error: error.unwrap_or_default(),
+                        retry_in_ms: delay.as_millis() as u64,
+                        resume_from: dl.resumed_from,
+                    })?;
                     self.pending_ids.insert(dl.id);
-                    self.enqueue(dl, handle)?
+                    self.queued.push_back((dl, handle));
                 }
             }
         };
@@ -893,6 +991,15 @@ impl<'a, 'cfg> Downloads<'a, 'cfg> {
             .shell()
             .status("Downloaded", &dl.descriptor)?;
 
+        self.message(&machine_message::DownloadFinished {
+            package_id: dl.id,
+            url: &dl.url,
+            bytes: data.len() as u64,
+            resumed_bytes: dl.resumed_from,
+            attempts: dl.failed_attempts + 1,
+            duration_ms: dl.start.elapsed().as_millis() as u64,
+        })?;
+
         self.downloads_finished += 1;
         self.downloaded_bytes += dl.total.get();
         if dl.total.get() > self.largest.0 {
@@ -948,6 +1055,43 @@ impl<'a, 'cfg> Downloads<'a, 'cfg> {
         Ok(())
     }
 
+    /// Starts the queued downloads which are ready, as long as fewer than
+    /// `net.max-concurrent-downloads` are running.
+    fn start_queued(&mut self) -> CargoResult<()> {
+        let now = Instant::now();
+        let mut i = 0;
+        while i < self.queued.len() {
+            if let Some(max) = self.max_concurrent {
+                if self.pending.len() >= max {
+                    break;
+                }
+            }
+            if self.queued[i].0.retry_at.map_or(false, |at| at > now) {
+                i += 1;
+                continue;
+            }
+            let (mut dl, handle) = self.queued.remove(i).unwrap();
+            dl.retry_at = None;
+            self.enqueue(dl, handle)?;
+        }
+        Ok(())
+    }
+
+    /// When the next queued download to be retried may start.
+    fn next_retry(&self) -> Option<Instant> {
+        self.queued.iter().filter_map(|(dl, _)| dl.retry_at).min()
+    }
+
+    /// Prints a JSON message about the downloads, with
+    /// `--message-format json`.
+    fn message(&self, msg: &impl Message) -> CargoResult<()> {
+        if self.set.config.download_messages() {
+            self.progress.borrow_mut().as_mut().unwrap().clear();
+            crate::drop_println!(self.set.config, "{}", msg.to_json_string());
+        }
+        Ok(())
+    }
+
     /// Block, waiting for curl. Returns a token and a `Result` for that token
     /// (`Ok` means the download successfully finished).
     fn wait_for_curl(&mut self) -> CargoResult<(usize, Result<(), curl::Error>)> {
@@ -967,6 +1111,13 @@ impl<'a, 'cfg> Downloads<'a, 'cfg> {
         // actually block waiting for I/O to happen, which we achieve with the
         // `wait` method on `multi`.
         loop {
+            self.start_queued()?;
+            if self.pending.is_empty() {
+                // Only downloads waiting to be retried are left.
+                let next = self.next_retry().expect("no download to wait for");
+                std::thread::sleep(next.saturating_duration_since(Instant::now()));
+                continue;
+            }
             let n = tls::set(self, || {
                 self.set
                     .multi
@@ -990,11 +1141,14 @@ impl<'a, 'cfg> Downloads<'a, 'cfg> {
                 break Ok(pair);
             }
             assert!(!self.pending.is_empty());
-            let timeout = self
+            let mut timeout = self
                 .set
                 .multi
                 .get_timeout()?
                 .unwrap_or_else(|| Duration::new(5, 0));
+            if let Some(next) = self.next_retry() {
+                timeout = timeout.min(next.saturating_duration_since(Instant::now()));
+            }
             self.set
                 .multi
                 .wait(&mut [], timeout)
@@ -1098,6 +1252,44 @@ impl<'a, 'cfg> Downloads<'a, 'cfg> {
     }
 }
 
+/// The number of downloads running at once with `net.max-download-rate`,
+/// unless `net.max-concurrent-downloads` is set.
+const RATE_LIMITED_DOWNLOADS: usize = 8;
+
+/// The `net.max-concurrent-downloads`, and the rate of each download, in
+/// bytes per second, which share the `net.max-download-rate`.
+fn download_limits(config: &Config) -> CargoResult<(Option<usize>, Option<u64>)> {
+    if !config.cli_unstable().downloads {
+        return Ok((None, None));
+    }
+    let net = config.net_config()?;
+    let rate = match &net.max_download_rate {
+        Some(rate) => match rate.parse::<ByteSize>() {
+            Ok(parsed) if parsed.0 > 0 => Some(parsed.0),
+            _ => bail!(
+                "`net.max-download-rate` must be a number of bytes per second like `2MB`, \
+                 found `{}`",
+                rate
+            ),
+        },
+        None => None,
+    };
+    let max_concurrent = match net.max_concurrent_downloads {
+        Some(0) => bail!("`net.max-concurrent-downloads` must be at least 1"),
+        Some(max) => Some(max as usize),
+        None => rate.map(|_| RATE_LIMITED_DOWNLOADS),
+    };
+    let rate = rate.map(|rate| (rate / max_concurrent.unwrap() as u64).max(1));
+    Ok((max_concurrent, rate))
+}
+
+/// How long to wait before retrying a download after `failed_attempts`,
+/// doubling from half a second up to ten seconds.
+fn retry_delay(failed_attempts: u32) -> Duration {
+    let delay = Duration::from_millis(500) * 2u32.saturating_pow(failed_attempts - 1);
+    delay.min(Duration::from_secs(10))
+}
+
 #[derive(Copy, Clone)]
 enum WhyTick<'a> {
     DownloadStarted,
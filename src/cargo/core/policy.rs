@@ -0,0 +1,592 @@
+//! The rules of `[workspace.policy]`, which are checked against the resolved
+//! dependency graph of a workspace.
+//!
+//! The rules cover the licenses of the dependencies, denied packages, the
+//! number of versions of a package in the graph, the registries and git
+//! repositories the dependencies come from, the dependencies trusted to run
+//! a build script, and the audits of the dependencies in the audit store, see
+//! `core::audits`. The members of the workspace are
+//! not checked, only their dependencies. All of the violations are reported
+//! together, each with the dependency path that introduced it.
+//!
+//! The `allowed-sources` rule is the exception, as it is enforced by the
+//! resolver with `AllowedSources`, so that nothing is fetched from a source
+//! that isn't allowed.
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt::Write as _;
+
+use anyhow::bail;
+use semver::VersionReq;
+
+use crate::core::audits::{AuditStatus, AuditStore, AUDITS_FILE};
+use crate::core::{Package, PackageId, PackageSet, Resolve, SourceId, Workspace};
+use crate::sources::CRATES_IO_REGISTRY;
+use crate::util::interning::InternedString;
+use crate::util::{CargoResult, Config};
+
+/// The rules of the `[workspace.policy]` table.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorkspacePolicy {
+    /// The SPDX license identifiers the license expressions of the
+    /// dependencies must be satisfied with. Any license is allowed without
+    /// them.
+    pub allowed_licenses: Option<Vec<String>>,
+    /// The packages whose licenses are not checked.
+    pub license_exceptions: Vec<InternedString>,
+    /// The packages which may not be dependencies.
+    pub deny: Vec<DeniedPackage>,
+    /// The number of versions of a package allowed in the graph.
+    pub max_duplicate_versions: Option<usize>,
+    /// The packages not limited by `max_duplicate_versions`.
+    pub allow_duplicates: Vec<InternedString>,
+    /// The names of the registries the dependencies may come from, or any
+    /// registry without them.
+    pub allowed_registries: Option<Vec<String>>,
+    /// The prefixes of the URLs of the git repositories the dependencies may
+    /// come from, or any repository without them.
+    pub allowed_git: Option<Vec<String>>,
+    /// The names of the registries, and the prefixes of the URLs of the
+    /// other sources, the dependencies may come from, enforced by the
+    /// resolver.
+    pub allowed_sources: Option<Vec<String>>,
+    /// Whether the build scripts of the dependencies not in
+    /// `allow_build_scripts` are denied.
+    pub deny_build_scripts_by_default: bool,
+    /// The dependencies whose build scripts may run.
+    pub allow_build_scripts: Vec<DeniedPackage>,
+    /// The dependencies whose build scripts may not run, even if they are
+    /// allowed.
+    pub deny_build_scripts: Vec<DeniedPackage>,
+    /// Whether the dependencies must be audited in the audit store.
+    pub require_audits: Option<RequireAudits>,
+}
+
+/// What happens to a dependency which isn't audited, from the
+/// `require-audits` rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequireAudits {
+    Warn,
+    Deny,
+}
+
+/// The sources the dependencies may come from, from the `allowed-sources`
+/// rule, resolved against the registries of the configuration.
+#[derive(Debug, Clone)]
+pub struct AllowedSources {
+    registries: Vec<SourceId>,
+    url_prefixes: Vec<String>,
+}
+
+/// A package which may not be a dependency, from the `deny` list, or an
+/// entry of the build script lists.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeniedPackage {
+    pub name: InternedString,
+    /// The versions denied, or all of them.
+    pub version: Option<VersionReq>,
+    /// Why the package is denied, shown in the report, or the notes of the
+    /// review of an allowed build script.
+    pub reason: Option<String>,
+}
+
+/// A violation of the policy.
+struct Violation {
+    /// What is wrong.
+    problem: String,
+    /// The packages whose dependency paths are shown.
+    packages: Vec<PackageId>,
+    /// How to solve it.
+    help: String,
+}
+
+impl DeniedPackage {
+    fn matches(&self, id: PackageId) -> bool {
+        self.name == id.name()
+            && self
+                .version
+                .as_ref()
+                .map_or(true, |req| req.matches(id.version()))
+    }
+}
+
+impl AllowedSources {
+    /// Whether the packages of `source` may be dependencies. The path
+    /// dependencies are always allowed, as they are local.
+    pub fn allows(&self, source: SourceId) -> bool {
+        if source.is_path() {
+            return true;
+        }
+        if source.is_registry()
+            && self
+                .registries
+                .iter()
+                .any(|r| r.canonical_url() == source.canonical_url())
+        {
+            return true;
+        }
+        let url = source.url().as_str();
+        self.url_prefixes
+            .iter()
+            .any(|prefix| url_has_prefix(url, prefix))
+    }
+}
+
+impl WorkspacePolicy {
+    /// The sources of the `allowed-sources` rule, if it is given. Its entries
+    /// with a `://` are URL prefixes, and the others are registry names.
+    pub fn allowed_sources(&self, config: &Config) -> CargoResult<Option<AllowedSources>> {
+        let entries = match &self.allowed_sources {
+            Some(entries) => entries,
+            None => return Ok(None),
+        };
+        let mut allowed = AllowedSources {
+            registries: Vec::new(),
+            url_prefixes: Vec::new(),
+        };
+        for entry in entries {
+            if entry.contains("://") {
+                allowed.url_prefixes.push(entry.clone());
+            } else {
+                allowed.registries.push(registry_source(config, entry)?);
+            }
+        }
+        Ok(Some(allowed))
+    }
+
+    /// Checks the dependencies of the members of `ws` in `resolve`. The
+    /// licenses are checked for the packages of `pkg_set` that are
+    /// downloaded, which are the ones that may be built.
+    pub fn check(
+        &self,
+        ws: &Workspace<'_>,
+        resolve: &Resolve,
+        pkg_set: &PackageSet<'_>,
+    ) -> CargoResult<()> {
+        let config = ws.config();
+        let members: HashSet<PackageId> = ws.members().map(|p| p.package_id()).collect();
+        let mut ids: Vec<PackageId> = resolve.iter().filter(|id| !members.contains(id)).collect();
+        ids.sort();
+        let packages: HashMap<PackageId, _> = pkg_set
+            .packages()
+            .map(|pkg| (pkg.package_id(), pkg))
+            .collect();
+        let registries = match &self.allowed_registries {
+            Some(names) => Some(
+                names
+                    .iter()
+                    .map(|name| registry_source(config, name))
+                    .collect::<CargoResult<Vec<_>>>()?,
+            ),
+            None => None,
+        };
+
+        let mut violations = Vec::new();
+        for &id in &ids {
+            for denied in self.deny.iter().filter(|denied| denied.matches(id)) {
+                violations.push(Violation {
+                    problem: match &denied.reason {
+                        Some(reason) => format!("{} is denied: {}", id, reason),
+                        None => format!("{} is denied", id),
+                    },
+                    packages: vec![id],
+                    help: format!("remove the dependencies on `{}`", id.name()),
+                });
+            }
+
+            let source = id.source_id();
+            if let (true, Some(registries)) = (source.is_registry(), &registries) {
+                if !registries
+                    .iter()
+                    .any(|r| r.canonical_url() == source.canonical_url())
+                {
+                    violations.push(Violation {
+                        problem: format!(
+                            "{} comes from the registry `{}`, which is not in `allowed-registries`",
+                            id,
+                            source.display_registry_name()
+                        ),
+                        packages: vec![id],
+                        help: "depend on it from an allowed registry, \
+                               or add its registry to `allowed-registries`"
+                            .to_string(),
+                    });
+                }
+            }
+            if let (true, Some(prefixes)) = (source.is_git(), &self.allowed_git) {
+                let url = source.url().as_str();
+                if !prefixes.iter().any(|prefix| url_has_prefix(url, prefix)) {
+                    violations.push(Violation {
+                        problem: format!(
+                            "{} comes from the git repository `{}`, which is not in `allowed-git`",
+                            id, url
+                        ),
+                        packages: vec![id],
+                        help: "depend on it from an allowed source, \
+                               or add its repository to `allowed-git`"
+                            .to_string(),
+                    });
+                }
+            }
+
+            if let Some(violation) = packages
+                .get(&id)
+                .and_then(|pkg| self.check_build_script(pkg))
+            {
+                violations.push(violation);
+            }
+
+            let allowed = match &self.allowed_licenses {
+                Some(allowed) if !self.license_exceptions.contains(&id.name()) => allowed,
+                _ => continue,
+            };
+            let pkg = match packages.get(&id) {
+                Some(pkg) => pkg,
+                None => continue,
+            };
+            let help = format!(
+                "allow one of its licenses in `allowed-licenses`, \
+                 or add `{}` to `license-exceptions` once its license is reviewed",
+                id.name()
+            );
+            let problem = match &pkg.manifest().metadata().license {
+                Some(license) => match license_allowed(license, allowed) {
+                    Ok(true) => continue,
+                    Ok(false) => format!(
+                        "{} has the license `{}`, which is not allowed by `allowed-licenses`",
+                        id, license
+                    ),
+                    Err(e) => format!(
+                        "{} has the license `{}`, which is not a valid SPDX expression: {}",
+                        id, license, e
+                    ),
+                },
+                None => format!("{} has no license expression", id),
+            };
+            violations.push(Violation {
+                problem,
+                packages: vec![id],
+                help,
+            });
+        }
+
+        if let Some(max) = self.max_duplicate_versions {
+            let mut versions: BTreeMap<InternedString, Vec<PackageId>> = BTreeMap::new();
+            for &id in &ids {
+                versions.entry(id.name()).or_default().push(id);
+            }
+            for (name, ids) in versions {
+                if ids.len() <= max || self.allow_duplicates.contains(&name) {
+                    continue;
+                }
+                violations.push(Violation {
+                    problem: format!(
+                        "`{}` is resolved to {} versions ({}), \
+                         more than the {} allowed by `max-duplicate-versions`",
+                        name,
+                        ids.len(),
+                        ids.iter()
+                            .map(|id| id.version().to_string())
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                        max
+                    ),
+                    packages: ids,
+                    help: format!(
+                        "update the dependencies so they agree on a version, \
+                         or add `{}` to `allow-duplicates`",
+                        name
+                    ),
+                });
+            }
+        }
+
+        if let Some(require) = self.require_audits {
+            let store = AuditStore::load(ws)?;
+            for &id in ids.iter().filter(|id| !id.source_id().is_path()) {
+                let audited = match store.status(id) {
+                    AuditStatus::Unaudited(audited) => audited,
+                    _ => continue,
+                };
+                let previous = audited.iter().rev().find(|v| *v < id.version());
+                let (problem, help) = match previous {
+                    Some(previous) => (
+                        format!(
+                            "{} is not audited in `{}`, version {} is",
+                            id, AUDITS_FILE, previous
+                        ),
+                        format!(
+                            "review the changes since {1}, then record the audit with \
+                             `cargo audits certify {0} {2} --delta {1}`, \
+                             or exempt it with `cargo audits exempt`",
+                            id.name(),
+                            previous,
+                            id.version()
+                        ),
+                    ),
+                    None => (
+                        format!("{} is not audited in `{}`", id, AUDITS_FILE),
+                        format!(
+                            "review it, then record the audit with \
+                             `cargo audits certify {} {}`, or exempt it with `cargo audits exempt`",
+                            id.name(),
+                            id.version()
+                        ),
+                    ),
+                };
+                match require {
+                    RequireAudits::Warn => config
+                        .shell()
+                        .warn(format!("{}\nhelp: {}", problem, help))?,
+                    RequireAudits::Deny => violations.push(Violation {
+                        problem,
+                        packages: vec![id],
+                        help,
+                    }),
+                }
+            }
+        }
+
+        if violations.is_empty() {
+            return Ok(());
+        }
+        let paths = dependency_paths(resolve, &members);
+        let mut report = format!(
+            "{} violation{} of the workspace policy in `{}`:\n",
+            violations.len(),
+            if violations.len() == 1 { "" } else { "s" },
+            ws.root_manifest().display()
+        );
+        for violation in violations {
+            write!(report, "\n  {}\n", violation.problem).unwrap();
+            for id in violation.packages {
+                let mut path = vec![id];
+                while let Some(&parent) = paths.get(path.last().unwrap()) {
+                    path.push(parent);
+                }
+                let path: Vec<_> = path.iter().rev().map(|id| id.to_string()).collect();
+                write!(report, "    dependency path: {}\n", path.join(" -> ")).unwrap();
+            }
+            write!(report, "    help: {}\n", violation.help).unwrap();
+        }
+        bail!("{}", report.trim_end())
+    }
+
+    /// Checks that the build script of `pkg`, if it has one, may run.
+    fn check_build_script(&self, pkg: &Package) -> Option<Violation> {
+        let id = pkg.package_id();
+        let script = pkg.targets().iter().find(|t| t.is_custom_build())?;
+        if let Some(denied) = self.deny_build_scripts.iter().find(|d| d.matches(id)) {
+            return Some(Violation {
+                problem: match &denied.reason {
+                    Some(reason) => format!(
+                        "{} has a build script, which is denied by `deny-build-scripts`: {}",
+                        id, reason
+                    ),
+                    None => format!(
+                        "{} has a build script, which is denied by `deny-build-scripts`",
+                        id
+                    ),
+                },
+                packages: vec![id],
+                help: format!("remove the dependencies on `{}`", id.name()),
+            });
+        }
+        if !self.deny_build_scripts_by_default
+            || self.allow_build_scripts.iter().any(|a| a.matches(id))
+        {
+            return None;
+        }
+        let path = match script.src_path().path() {
+            Some(path) => format!("`{}`", path.display()),
+            None => "the metabuild dependencies".to_string(),
+        };
+        Some(Violation {
+            problem: format!(
+                "{} has a build script, which is not in `allow-build-scripts`",
+                id
+            ),
+            packages: vec![id],
+            help: format!(
+                "review its build script at {}, then add \
+                 `{{ name = \"{}\", version = \"={}\" }}` to `allow-build-scripts`",
+                path,
+                id.name(),
+                id.version()
+            ),
+        })
+    }
+}
+
+/// The source of the registry named `name`.
+fn registry_source(config: &Config, name: &str) -> CargoResult<SourceId> {
+    if name == CRATES_IO_REGISTRY {
+        SourceId::crates_io(config)
+    } else {
+        SourceId::alt_registry(config, name)
+    }
+}
+
+/// The dependent of each package on one of its shortest dependency paths
+/// from the members `members`.
+fn dependency_paths(
+    resolve: &Resolve,
+    members: &HashSet<PackageId>,
+) -> HashMap<PackageId, PackageId> {
+    let mut dependents = HashMap::new();
+    let mut seen: HashSet<PackageId> = members.clone();
+    let mut members: Vec<_> = members.iter().cloned().collect();
+    members.sort();
+    let mut queue: VecDeque<PackageId> = members.into_iter().collect();
+    while let Some(id) = queue.pop_front() {
+        let mut deps: Vec<PackageId> = resolve.deps(id).map(|(dep, _)| dep).collect();
+        deps.sort();
+        for dep in deps {
+            if seen.insert(dep) {
+                dependents.insert(dep, id);
+                queue.push_back(dep);
+            }
+        }
+    }
+    dependents
+}
+
+/// Checks that `expr` is a well-formed SPDX license expression, with the
+/// syntax understood by `allowed-licenses`.
+pub fn check_license_expr(expr: &str) -> CargoResult<()> {
+    license_allowed(expr, &[]).map(drop)
+}
+
+/// Whether the SPDX license expression `expr` is satisfied with the licenses
+/// `allowed`. An alternative is satisfied if all of its licenses are allowed,
+/// and a license with an exception is allowed if either the license or the
+/// license with the exception, like `Apache-2.0 WITH LLVM-exception`, is.
+/// The deprecated `/` separator is read as `OR`.
+fn license_allowed(expr: &str, allowed: &[String]) -> CargoResult<bool> {
+    let expr = expr
+        .replace('(', " ( ")
+        .replace(')', " ) ")
+        .replace('/', " OR ");
+    let tokens: Vec<&str> = expr.split_whitespace().collect();
+    let mut pos = 0;
+    let result = any_of(&tokens, &mut pos, allowed)?;
+    match tokens.get(pos) {
+        Some(token) => bail!("unexpected `{}`", token),
+        None => Ok(result),
+    }
+}
+
+fn any_of(tokens: &[&str], pos: &mut usize, allowed: &[String]) -> CargoResult<bool> {
+    let mut result = all_of(tokens, pos, allowed)?;
+    while tokens.get(*pos) == Some(&"OR") {
+        *pos += 1;
+        let alternative = all_of(tokens, pos, allowed)?;
+        result = result || alternative;
+    }
+    Ok(result)
+}
+
+fn all_of(tokens: &[&str], pos: &mut usize, allowed: &[String]) -> CargoResult<bool> {
+    let mut result = license(tokens, pos, allowed)?;
+    while tokens.get(*pos) == Some(&"AND") {
+        *pos += 1;
+        let other = license(tokens, pos, allowed)?;
+        result = result && other;
+    }
+    Ok(result)
+}
+
+fn license(tokens: &[&str], pos: &mut usize, allowed: &[String]) -> CargoResult<bool> {
+    let token = match tokens.get(*pos) {
+        Some(token) => *token,
+        None => bail!("expected a license at the end of the expression"),
+    };
+    *pos += 1;
+    if token == "(" {
+        let result = any_of(tokens, pos, allowed)?;
+        if tokens.get(*pos) != Some(&")") {
+            bail!("expected `)`");
+        }
+        *pos += 1;
+        return Ok(result);
+    }
+    if ["AND", "OR", "WITH", ")"].contains(&token) {
+        bail!("expected a license, found `{}`", token);
+    }
+    if tokens.get(*pos) != Some(&"WITH") {
+        return Ok(allowed.iter().any(|a| a == token));
+    }
+    *pos += 1;
+    let exception = match tokens.get(*pos) {
+        Some(exception) => *exception,
+        None => bail!("expected an exception after `WITH`"),
+    };
+    *pos += 1;
+    let with_exception = format!("{} WITH {}", token, exception);
+    Ok(allowed.iter().any(|a| *a == token || *a == with_exception))
+}
+
+/// Whether `url` is `prefix`, or a URL under it. The prefix only matches
+/// whole path segments, so that `https://github.com/org` doesn't match
+/// `https://github.com/org-fork/repo`.
+fn url_has_prefix(url: &str, prefix: &str) -> bool {
+    match url.strip_prefix(prefix) {
+        Some(rest) => {
+            prefix.ends_with('/')
+                || rest.is_empty()
+                || rest == ".git"
+                || rest.starts_with(|c| matches!(c, '/' | '?' | '#'))
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{license_allowed, url_has_prefix};
+
+    #[test]
+    fn license_expressions() {
+        let allowed = vec![
+            "MIT".to_string(),
+            "Apache-2.0".to_string(),
+            "GPL-2.0-only WITH Classpath-exception-2.0".to_string(),
+        ];
+        let check = |expr| license_allowed(expr, &allowed).unwrap();
+        assert!(check("MIT"));
+        assert!(check("MIT OR Apache-2.0"));
+        assert!(check("MIT/Apache-2.0"));
+        assert!(check("GPL-3.0-only OR MIT"));
+        assert!(!check("GPL-3.0-only"));
+        assert!(!check("MIT AND GPL-3.0-only"));
+        assert!(check("(MIT OR GPL-3.0-only) AND Apache-2.0"));
+        assert!(check("Apache-2.0 WITH LLVM-exception"));
+        assert!(check("GPL-2.0-only WITH Classpath-exception-2.0"));
+        assert!(!check("GPL-2.0-only"));
+        assert!(license_allowed("MIT OR", &allowed).is_err());
+        assert!(license_allowed("(MIT", &allowed).is_err());
+        assert!(license_allowed("MIT Apache-2.0", &allowed).is_err());
+    }
+
+    #[test]
+    fn url_prefixes() {
+        let org = "https://github.com/org";
+        assert!(url_has_prefix("https://github.com/org", org));
+        assert!(url_has_prefix("https://github.com/org/repo", org));
+        assert!(url_has_prefix("https://github.com/org.git", org));
+        assert!(!url_has_prefix("https://github.com/org-fork/repo", org));
+        assert!(!url_has_prefix("https://github.com/organization", org));
+        assert!(url_has_prefix(
+            "https://github.com/org/repo",
+            "https://github.com/org/"
+        ));
+        assert!(url_has_prefix(
+            "https://github.com/org/repo",
+            "https://github.com"
+        ));
+        assert!(!url_has_prefix(
+            "https://github.com.evil.com/org",
+            "https://github.com"
+        ));
+    }
+}
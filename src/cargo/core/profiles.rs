@@ -2,11 +2,15 @@ use crate::core::compiler::{CompileKind, CompileMode, Unit};
 use crate::core::resolver::features::FeaturesFor;
 use crate::core::{Feature, PackageId, PackageIdSpec, Resolve, Shell, Target, Workspace};
 use crate::util::interning::InternedString;
-use crate::util::toml::{ProfilePackageSpec, StringOrBool, TomlProfile, TomlProfiles, U32OrBool};
+use crate::util::toml::{
+    ProfilePackageSpec, StringOrBool, TomlProfile, TomlProfiles, TomlSigning, TomlTrimPaths,
+    U32OrBool,
+};
 use crate::util::{closest_msg, config, CargoResult, Config};
 use anyhow::{bail, Context as _};
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::hash::Hash;
+use std::time::Duration;
 use std::{cmp, env, fmt, hash};
 
 /// Collection of all profiles.
@@ -31,6 +35,9 @@ pub struct Profiles {
     requested_profile: InternedString,
     /// The host target for rustc being used by this `Profiles`.
     rustc_host: InternedString,
+    /// The sanitizer applied to all target units, either from the requested
+    /// profile or from `--sanitizer`.
+    sanitizer: Option<Sanitizer>,
 }
 
 impl Profiles {
@@ -52,6 +59,7 @@ impl Profiles {
                 original_profiles: profiles.clone(),
                 requested_profile,
                 rustc_host,
+                sanitizer: None,
             };
 
             profile_makers.by_name.insert(
@@ -95,6 +103,7 @@ impl Profiles {
                 .dir_names
                 .insert(InternedString::new("doc"), InternedString::new("debug"));
 
+            profile_makers.sanitizer = profile_makers.base_profile().sanitizer;
             return Ok(profile_makers);
         }
 
@@ -106,13 +115,14 @@ impl Profiles {
             original_profiles: profiles.clone(),
             requested_profile,
             rustc_host,
+            sanitizer: None,
         };
 
         Self::add_root_profiles(&mut profile_makers, &profiles);
 
         // Merge with predefined profiles.
         use std::collections::btree_map::Entry;
-        for (predef_name, mut predef_prof) in Self::predefined_profiles().into_iter() {
+        for (predef_name, mut predef_prof) in Self::predefined_profiles(ws).into_iter() {
             match profiles.entry(InternedString::new(predef_name)) {
                 Entry::Vacant(vac) => {
                     vac.insert(predef_prof);
@@ -133,6 +143,7 @@ impl Profiles {
         // This simplifies the API (no need for CargoResult), and enforces
         // assumptions about how config profiles are loaded.
         profile_makers.get_profile_maker(requested_profile)?;
+        profile_makers.sanitizer = profile_makers.base_profile().sanitizer;
         Ok(profile_makers)
     }
 
@@ -164,8 +175,8 @@ impl Profiles {
 
     /// Returns the built-in profiles (not including dev/release, which are
     /// "root" profiles).
-    fn predefined_profiles() -> Vec<(&'static str, TomlProfile)> {
-        vec![
+    fn predefined_profiles(ws: &Workspace<'_>) -> Vec<(&'static str, TomlProfile)> {
+        let mut profiles = vec![
             (
                 "bench",
                 TomlProfile {
@@ -187,7 +198,21 @@ impl Profiles {
                     ..TomlProfile::default()
                 },
             ),
-        ]
+        ];
+        if ws.unstable_features().is_enabled(Feature::fuzz_targets()) {
+            // Used by `cargo fuzz-run`. Fuzzing needs optimized code, but the
+            // assertions are what turns many bugs into crashes.
+            profiles.push((
+                "fuzz",
+                TomlProfile {
+                    inherits: Some(InternedString::new("release")),
+                    debug_assertions: Some(true),
+                    overflow_checks: Some(true),
+                    ..TomlProfile::default()
+                },
+            ));
+        }
+        profiles
     }
 
     /// Creates a `ProfileMaker`, and inserts it into `self.by_name`.
@@ -347,6 +372,21 @@ impl Profiles {
             }
         }
 
+        // `strip = "separate"` needs debuginfo to separate. On macOS it is
+        // packed into a `.dSYM` by rustc before the binary is stripped.
+        if profile.strip == Strip::Separate {
+            if profile.debuginfo.unwrap_or(0) == 0 {
+                profile.debuginfo = Some(2);
+            }
+            let target = match &kind {
+                CompileKind::Host => self.rustc_host.as_str(),
+                CompileKind::Target(target) => target.short_name(),
+            };
+            if target.contains("-apple-") {
+                profile.split_debuginfo = Some(InternedString::new("packed"));
+            }
+        }
+
         // Default macOS debug information to being stored in the "unpacked"
         // split-debuginfo format. At the time of this writing that's the only
         // platform which has a stable `-Csplit-debuginfo` option for rustc,
@@ -378,6 +418,15 @@ impl Profiles {
         if !is_local {
             profile.incremental = false;
         }
+
+        // The whole target graph must agree on the sanitizer, but build
+        // scripts and proc-macros run inside cargo and rustc and must not be
+        // instrumented.
+        profile.sanitizer = if unit_for.is_for_host() {
+            None
+        } else {
+            self.sanitizer
+        };
         profile.name = profile_name;
         profile
     }
@@ -414,11 +463,31 @@ impl Profiles {
     }
 
     /// Gets the directory name for a profile, like `debug` or `release`.
+    ///
+    /// Sanitized builds get their own directory, like `debug-asan`, so that
+    /// they don't clobber the uninstrumented artifacts.
     pub fn get_dir_name(&self) -> InternedString {
-        *self
+        let dir_name = *self
             .dir_names
             .get(&self.requested_profile)
-            .unwrap_or(&self.requested_profile)
+            .unwrap_or(&self.requested_profile);
+        match self.sanitizer {
+            Some(sanitizer) => {
+                InternedString::new(&format!("{}-{}", dir_name, sanitizer.short_name()))
+            }
+            None => dir_name,
+        }
+    }
+
+    /// Overrides the sanitizer of the requested profile, used for
+    /// `--sanitizer`.
+    pub fn set_sanitizer(&mut self, sanitizer: Sanitizer) {
+        self.sanitizer = Some(sanitizer);
+    }
+
+    /// The sanitizer applied to target units, if any.
+    pub fn sanitizer(&self) -> Option<Sanitizer> {
+        self.sanitizer
     }
 
     /// Used to check for overrides for non-existing packages.
@@ -468,6 +537,8 @@ impl Profiles {
 /// The precedence of profiles are (first one wins):
 /// - Profiles in `.cargo/config` files (using same order as below).
 /// - [profile.dev.package.name] -- a named package.
+/// - [profile.dev.package."prefix-*"] -- the packages whose name starts with
+///   `prefix-`, the longest matching prefix winning.
 /// - [profile.dev.package."*"] -- this cannot apply to workspace members.
 /// - [profile.dev.build-override] -- this can only apply to `build.rs` scripts
 ///   and their dependencies.
@@ -552,10 +623,23 @@ fn merge_toml_overrides(
             }
         }
         if let Some(pkg_id) = pkg_id {
+            // The pattern with the longest prefix is the most specific.
+            let glob = overrides
+                .iter()
+                .filter_map(|(key, glob_profile)| match *key {
+                    ProfilePackageSpec::Glob(prefix) if pkg_id.name().starts_with(&*prefix) => {
+                        Some((prefix.len(), glob_profile))
+                    }
+                    _ => None,
+                })
+                .max_by_key(|(len, _)| *len);
+            if let Some((_, glob_profile)) = glob {
+                merge_profile(profile, glob_profile);
+            }
             let mut matches = overrides
                 .iter()
                 .filter_map(|(key, spec_profile)| match *key {
-                    ProfilePackageSpec::All => None,
+                    ProfilePackageSpec::All | ProfilePackageSpec::Glob(_) => None,
                     ProfilePackageSpec::Spec(ref s) => {
                         if s.matches(pkg_id) {
                             Some(spec_profile)
@@ -630,8 +714,27 @@ fn merge_profile(profile: &mut Profile, toml: &TomlProfile) {
         Some(StringOrBool::Bool(true)) => Strip::Named(InternedString::new("symbols")),
         None | Some(StringOrBool::Bool(false)) => Strip::None,
         Some(StringOrBool::String(ref n)) if is_off(n.as_str()) => Strip::None,
+        Some(StringOrBool::String(ref n)) if n == "separate" => Strip::Separate,
         Some(StringOrBool::String(ref n)) => Strip::Named(InternedString::new(n)),
     };
+    if let Some(timeout) = toml.timeout {
+        profile.test_timeout = Some(timeout.0);
+    }
+    if let Some(retries) = toml.retries {
+        profile.test_retries = retries;
+    }
+    if let Some(sanitizer) = &toml.sanitizer {
+        // This should be validated in TomlProfile::validate
+        profile.sanitizer = Some(sanitizer.parse().unwrap());
+    }
+    if let Some(trim_paths) = &toml.trim_paths {
+        // This should be validated in TomlProfile::validate and
+        // TomlProfile::validate_override
+        profile.trim_paths = TrimPaths::from_toml(trim_paths).unwrap();
+    }
+    if let Some(signing) = &toml.signing {
+        profile.signing = Some(Signing::from_toml(signing));
+    }
 }
 
 /// The root profile (dev/release).
@@ -666,6 +769,19 @@ pub struct Profile {
     pub incremental: bool,
     pub panic: PanicStrategy,
     pub strip: Strip,
+    /// How long a test binary may run before it is killed.
+    #[serde(skip)]
+    pub test_timeout: Option<Duration>,
+    /// How many times a failing test binary is run again.
+    #[serde(skip)]
+    pub test_retries: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sanitizer: Option<Sanitizer>,
+    #[serde(skip_serializing_if = "TrimPaths::is_none")]
+    pub trim_paths: TrimPaths,
+    /// How the binaries and `cdylib`s are code signed after linking.
+    #[serde(skip)]
+    pub signing: Option<Signing>,
 }
 
 impl Default for Profile {
@@ -685,6 +801,11 @@ impl Default for Profile {
             incremental: false,
             panic: PanicStrategy::Unwind,
             strip: Strip::None,
+            test_timeout: None,
+            test_retries: 0,
+            sanitizer: None,
+            trim_paths: TrimPaths::none(),
+            signing: None,
         }
     }
 }
@@ -712,6 +833,11 @@ compact_debug! {
                 incremental
                 panic
                 strip
+                test_timeout
+                test_retries
+                sanitizer
+                trim_paths
+                signing
             )]
         }
     }
@@ -756,6 +882,10 @@ impl Profile {
             name: InternedString::new("release"),
             root: ProfileRoot::Release,
             opt_level: InternedString::new("3"),
+            trim_paths: TrimPaths {
+                object: true,
+                ..TrimPaths::none()
+            },
             ..Profile::default()
         }
     }
@@ -783,23 +913,31 @@ impl Profile {
         }
     }
 
-    /// Compares all fields except `name`, which doesn't affect compilation.
+    /// Compares all fields except `name`, `test_timeout`, `test_retries` and
+    /// `signing`, which don't affect compilation. The signing of a unit is
+    /// tracked by its fingerprint.
     /// This is necessary for `Unit` deduplication for things like "test" and
     /// "dev" which are essentially the same.
     fn comparable(&self) -> impl Hash + Eq {
         (
-            self.opt_level,
-            self.lto,
-            self.codegen_backend,
-            self.codegen_units,
-            self.debuginfo,
-            self.split_debuginfo,
-            self.debug_assertions,
-            self.overflow_checks,
-            self.rpath,
-            self.incremental,
-            self.panic,
-            self.strip,
+            (
+                self.opt_level,
+                self.lto,
+                self.codegen_backend,
+                self.codegen_units,
+                self.debuginfo,
+                self.split_debuginfo,
+            ),
+            (
+                self.debug_assertions,
+                self.overflow_checks,
+                self.rpath,
+                self.incremental,
+                self.panic,
+                self.strip,
+                self.sanitizer,
+                self.trim_paths,
+            ),
         )
     }
 }
@@ -857,6 +995,8 @@ pub enum Strip {
     None,
     /// Named Strip settings
     Named(InternedString),
+    /// Move the debuginfo into a separate file next to the binary
+    Separate,
 }
 
 impl fmt::Display for Strip {
@@ -864,11 +1004,185 @@ impl fmt::Display for Strip {
         match *self {
             Strip::None => "none",
             Strip::Named(s) => s.as_str(),
+            Strip::Separate => "separate",
+        }
+        .fmt(f)
+    }
+}
+
+/// The `signing` setting, see `TomlSigning`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, PartialOrd, Ord, Default)]
+pub struct Signing {
+    pub identity: Option<InternedString>,
+    pub entitlements: Option<InternedString>,
+    pub timestamp_server: Option<InternedString>,
+    pub notarize: bool,
+}
+
+impl Signing {
+    fn from_toml(toml: &TomlSigning) -> Signing {
+        let intern = |s: &Option<String>| s.as_deref().map(InternedString::new);
+        Signing {
+            identity: intern(&toml.identity),
+            entitlements: intern(&toml.entitlements),
+            timestamp_server: intern(&toml.timestamp_server),
+            notarize: toml.notarize.unwrap_or(false),
+        }
+    }
+}
+
+/// The `trim-paths` setting: the kinds of output in which the paths of the
+/// source files are made relative, so that they don't reveal where the build
+/// happened.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, PartialOrd, Ord)]
+pub struct TrimPaths {
+    /// Paths expanded by macros, like `file!()` and panic locations.
+    pub macros: bool,
+    /// Paths in the compiler's diagnostics.
+    pub diagnostics: bool,
+    /// Paths in the compiled artifacts, including their debuginfo.
+    pub object: bool,
+}
+
+impl TrimPaths {
+    pub fn none() -> TrimPaths {
+        TrimPaths {
+            macros: false,
+            diagnostics: false,
+            object: false,
+        }
+    }
+
+    pub fn all() -> TrimPaths {
+        TrimPaths {
+            macros: true,
+            diagnostics: true,
+            object: true,
+        }
+    }
+
+    pub fn is_none(&self) -> bool {
+        *self == TrimPaths::none()
+    }
+
+    pub fn from_toml(toml: &TomlTrimPaths) -> CargoResult<TrimPaths> {
+        let values = match toml {
+            TomlTrimPaths::Bool(true) => return Ok(TrimPaths::all()),
+            TomlTrimPaths::Bool(false) => return Ok(TrimPaths::none()),
+            TomlTrimPaths::String(value) => std::slice::from_ref(value),
+            TomlTrimPaths::Values(values) => values.as_slice(),
+        };
+        let mut trim_paths = TrimPaths::none();
+        for value in values {
+            match value.as_str() {
+                "none" => {}
+                "all" => trim_paths = TrimPaths::all(),
+                "macro" => trim_paths.macros = true,
+                "diagnostics" => trim_paths.diagnostics = true,
+                "object" => trim_paths.object = true,
+                _ => bail!(
+                    "unknown `trim-paths` value `{}`, expected one of \
+                     `none`, `all`, `macro`, `diagnostics` or `object`",
+                    value
+                ),
+            }
+        }
+        Ok(trim_paths)
+    }
+}
+
+/// Formats as the `--remap-path-scope` of rustc.
+impl fmt::Display for TrimPaths {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if *self == TrimPaths::all() {
+            return "all".fmt(f);
+        }
+        if self.is_none() {
+            return "none".fmt(f);
+        }
+        let scopes: Vec<_> = [
+            (self.macros, "macro"),
+            (self.diagnostics, "diagnostics"),
+            (self.object, "object"),
+        ]
+        .iter()
+        .filter(|(enabled, _)| *enabled)
+        .map(|(_, scope)| *scope)
+        .collect();
+        scopes.join(",").fmt(f)
+    }
+}
+
+impl serde::ser::Serialize for TrimPaths {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        self.to_string().serialize(s)
+    }
+}
+
+/// The `sanitizer` setting.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, PartialOrd, Ord, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sanitizer {
+    Address,
+    Thread,
+    Memory,
+    Leak,
+}
+
+impl Sanitizer {
+    /// The abbreviated name, used for the artifact directory and matching
+    /// the name of the runtime library shipped in the sysroot.
+    pub fn short_name(self) -> &'static str {
+        match self {
+            Sanitizer::Address => "asan",
+            Sanitizer::Thread => "tsan",
+            Sanitizer::Memory => "msan",
+            Sanitizer::Leak => "lsan",
+        }
+    }
+
+    /// Whether the standard library must be rebuilt with the sanitizer.
+    ///
+    /// MemorySanitizer reports false positives for any uninstrumented code,
+    /// and ThreadSanitizer misses synchronization done in an uninstrumented
+    /// std.
+    pub fn requires_build_std(self) -> bool {
+        matches!(self, Sanitizer::Memory | Sanitizer::Thread)
+    }
+}
+
+impl fmt::Display for Sanitizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Sanitizer::Address => "address",
+            Sanitizer::Thread => "thread",
+            Sanitizer::Memory => "memory",
+            Sanitizer::Leak => "leak",
         }
         .fmt(f)
     }
 }
 
+impl std::str::FromStr for Sanitizer {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> CargoResult<Sanitizer> {
+        match s {
+            "address" | "asan" => Ok(Sanitizer::Address),
+            "thread" | "tsan" => Ok(Sanitizer::Thread),
+            "memory" | "msan" => Ok(Sanitizer::Memory),
+            "leak" | "lsan" => Ok(Sanitizer::Leak),
+            _ => bail!(
+                "unknown sanitizer `{}`, expected one of `address`, `thread`, `memory` or `leak`",
+                s
+            ),
+        }
+    }
+}
+
 /// Flags used in creating `Unit`s to indicate the purpose for the target, and
 /// to ensure the target's dependencies have the correct settings.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
@@ -1034,7 +1348,7 @@ impl UnitFor {
         // point: the build script itself does *not* use host features, that's
         // why the parent is checked here, and not the dependency).
         let host_features =
-            self.host_features || parent.target.is_custom_build() || dep_target.proc_macro();
+            self.host_features || parent.target.is_build_program() || dep_target.proc_macro();
         // Build scripts and proc macros, and all of their dependencies are
         // AlwaysUnwind.
         let panic_setting = if dep_for_host {
@@ -1202,7 +1516,7 @@ fn validate_packages_unique(
         let matches: Vec<&PackageIdSpec> = overrides
             .keys()
             .filter_map(|key| match *key {
-                ProfilePackageSpec::All => None,
+                ProfilePackageSpec::All | ProfilePackageSpec::Glob(_) => None,
                 ProfilePackageSpec::Spec(ref spec) => {
                     if spec.matches(pkg_id) {
                         Some(spec)
@@ -1251,6 +1565,20 @@ fn validate_packages_unmatched(
         None => return Ok(()),
     };
 
+    for key in overrides.keys() {
+        if let ProfilePackageSpec::Glob(prefix) = *key {
+            if !resolve
+                .iter()
+                .any(|pkg_id| pkg_id.name().starts_with(&*prefix))
+            {
+                shell.warn(format!(
+                    "profile package pattern `{}*` in profile `{}` did not match any packages",
+                    prefix, name
+                ))?;
+            }
+        }
+    }
+
     // Verify every override matches at least one package.
     let missing_specs = overrides.keys().filter_map(|key| {
         if let ProfilePackageSpec::Spec(ref spec) = *key {
@@ -1,7 +1,8 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
-use crate::core::PackageSet;
+use crate::core::resolver::Metadata;
 use crate::core::{Dependency, PackageId, Source, SourceId, SourceMap, Summary};
+use crate::core::{FeatureOverride, FeatureValue, PackageSet};
 use crate::sources::config::SourceConfigMap;
 use crate::util::errors::CargoResult;
 use crate::util::interning::InternedString;
@@ -77,6 +78,12 @@ pub struct PackageRegistry<'cfg> {
     patches: HashMap<CanonicalUrl, Vec<Summary>>,
     patches_locked: bool,
     patches_available: HashMap<CanonicalUrl, Vec<PackageId>>,
+
+    /// The `[feature-overrides]` of the workspace, keyed by package name.
+    feature_overrides: BTreeMap<InternedString, FeatureOverride>,
+    /// The packages of `workspace.allow-prerelease`, whose dependencies also
+    /// match their pre-release versions.
+    allow_prerelease: BTreeSet<InternedString>,
 }
 
 /// A map of all "locked packages" which is filled in when parsing a lock file
@@ -135,6 +142,8 @@ impl<'cfg> PackageRegistry<'cfg> {
             patches: HashMap::new(),
             patches_locked: false,
             patches_available: HashMap::new(),
+            feature_overrides: BTreeMap::new(),
+            allow_prerelease: BTreeSet::new(),
         })
     }
 
@@ -227,6 +236,24 @@ impl<'cfg> PackageRegistry<'cfg> {
         sub_vec.push((id, deps));
     }
 
+    /// Locks the git sources replacing others in the source mode to the
+    /// revisions recorded in the `[metadata]` of the previous lock file,
+    /// except those replacing one of the `unlocked` sources.
+    pub fn lock_replacement_revisions(
+        &mut self,
+        metadata: &Metadata,
+        unlocked: &HashSet<SourceId>,
+    ) -> CargoResult<()> {
+        self.source_config.lock_revisions(metadata, unlocked)
+    }
+
+    /// Records in `metadata` the revisions the git sources replacing others in
+    /// the source mode were updated to.
+    pub fn record_replacement_revisions(&self, metadata: &mut Metadata) {
+        self.source_config
+            .record_revisions(metadata, self.sources.sources().map(|s| &**s));
+    }
+
     /// Insert a `[patch]` section into this registry.
     ///
     /// This method will insert a `[patch]` section for the `url` specified,
@@ -395,6 +422,24 @@ impl<'cfg> PackageRegistry<'cfg> {
         Ok(unlock_patches)
     }
 
+    /// Sets the `[feature-overrides]` to apply to every summary as it is
+    /// locked.
+    ///
+    /// This must be called before `lock_patches`.
+    pub fn override_features(&mut self, overrides: BTreeMap<InternedString, FeatureOverride>) {
+        assert!(!self.patches_locked);
+        self.feature_overrides = overrides;
+    }
+
+    /// Sets the packages of `workspace.allow-prerelease`, whose dependencies
+    /// also match their pre-release versions as every summary is locked.
+    ///
+    /// This must be called before `lock_patches`.
+    pub fn allow_prerelease(&mut self, names: BTreeSet<InternedString>) {
+        assert!(!self.patches_locked);
+        self.allow_prerelease = names;
+    }
+
     /// Lock all patch summaries added via `patch`, making them available to
     /// resolution via `query`.
     ///
@@ -407,7 +452,9 @@ impl<'cfg> PackageRegistry<'cfg> {
         for summaries in self.patches.values_mut() {
             for summary in summaries {
                 debug!("locking patch {:?}", summary);
-                *summary = lock(&self.locked, &self.patches_available, summary.clone());
+                let unlocked = allow_prerelease(&self.allow_prerelease, summary.clone());
+                let locked = lock(&self.locked, &self.patches_available, unlocked);
+                *summary = override_features(&self.feature_overrides, locked);
             }
         }
         self.patches_locked = true;
@@ -470,7 +517,9 @@ impl<'cfg> PackageRegistry<'cfg> {
     /// through.
     pub fn lock(&self, summary: Summary) -> Summary {
         assert!(self.patches_locked);
-        lock(&self.locked, &self.patches_available, summary)
+        let summary = allow_prerelease(&self.allow_prerelease, summary);
+        let summary = lock(&self.locked, &self.patches_available, summary);
+        override_features(&self.feature_overrides, summary)
     }
 
     fn warn_bad_override(
@@ -614,6 +663,8 @@ impl<'cfg> Registry for PackageRegistry<'cfg> {
                         // already selected, then we skip this `summary`.
                         let locked = &self.locked;
                         let all_patches = &self.patches_available;
+                        let feature_overrides = &self.feature_overrides;
+                        let prerelease = &self.allow_prerelease;
                         let callback = &mut |summary: Summary| {
                             for patch in patches.iter() {
                                 let patch = patch.package_id().version();
@@ -621,7 +672,9 @@ impl<'cfg> Registry for PackageRegistry<'cfg> {
                                     return;
                                 }
                             }
-                            f(lock(locked, all_patches, summary))
+                            let summary = allow_prerelease(prerelease, summary);
+                            let summary = lock(locked, all_patches, summary);
+                            f(override_features(feature_overrides, summary))
                         };
                         return if fuzzy {
                             source.fuzzy_query(dep, callback)
@@ -788,6 +841,73 @@ fn lock(
     })
 }
 
+/// Applies the `[feature-overrides]` of the workspace to a summary.
+///
+/// Dependencies on an overridden package get their features rewritten. A
+/// disabled feature is additionally removed from the overridden package's own
+/// features and from `dep/feature` values that point at it, so that it can't
+/// be turned back on indirectly.
+fn override_features(
+    overrides: &BTreeMap<InternedString, FeatureOverride>,
+    summary: Summary,
+) -> Summary {
+    if overrides.is_empty() {
+        return summary;
+    }
+    let summary = match overrides.get(&summary.name()) {
+        Some(o) if !o.disable.is_empty() => summary.retain_feature_values(|fv| match fv {
+            FeatureValue::Feature(f) => !o.disable.contains(f),
+            _ => true,
+        }),
+        _ => summary,
+    };
+    let overridden_deps: HashMap<InternedString, &FeatureOverride> = summary
+        .dependencies()
+        .iter()
+        .filter_map(|dep| {
+            overrides
+                .get(&dep.package_name())
+                .map(|o| (dep.name_in_toml(), o))
+        })
+        .collect();
+    let summary = if overridden_deps.is_empty() {
+        summary
+    } else {
+        summary.retain_feature_values(|fv| match fv {
+            FeatureValue::DepFeature {
+                dep_name,
+                dep_feature,
+                ..
+            } => overridden_deps
+                .get(dep_name)
+                .map_or(true, |o| !o.disable.contains(dep_feature)),
+            _ => true,
+        })
+    };
+    summary.map_dependencies(|dep| match overrides.get(&dep.package_name()) {
+        Some(o) => o.apply(dep),
+        None => dep,
+    })
+}
+
+/// Lets the dependencies of a summary on the packages of
+/// `workspace.allow-prerelease` match their pre-release versions.
+///
+/// This is done before locking the summary, so that the pre-release versions
+/// of the lock file still match.
+fn allow_prerelease(names: &BTreeSet<InternedString>, summary: Summary) -> Summary {
+    if names.is_empty() {
+        return summary;
+    }
+    summary.map_dependencies(|dep| {
+        if names.contains(&dep.package_name()) {
+            dep.allow_prerelease()
+        } else {
+            dep
+        }
+    })
+}
+
 /// This is a helper for selecting the summary, or generating a helpful error message.
 fn summary_for_patch(
     orig_patch: &Dependency,
@@ -2,13 +2,14 @@ use super::dep_cache::RegistryQueryer;
 use super::errors::ActivateResult;
 use super::types::{ConflictMap, ConflictReason, FeaturesSet, ResolveOpts};
 use super::RequestedFeatures;
-use crate::core::{Dependency, PackageId, SourceId, Summary};
+use crate::core::{AllowedSources, Dependency, LinksPolicy, PackageId, SourceId, Summary};
 use crate::util::interning::InternedString;
 use crate::util::Graph;
 use anyhow::format_err;
 use log::debug;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::num::NonZeroU64;
+use std::rc::Rc;
 
 pub use super::encode::Metadata;
 pub use super::encode::{EncodableDependency, EncodablePackageId, EncodableResolve};
@@ -26,6 +27,10 @@ pub struct Context {
     pub resolve_features: im_rc::HashMap<PackageId, FeaturesSet>,
     /// get the package that will be linking to a native library by its links attribute
     pub links: im_rc::HashMap<InternedString, PackageId>,
+    /// the links attributes that the packages of `[workspace.links]` may share
+    pub links_policies: Rc<BTreeMap<InternedString, LinksPolicy>>,
+    /// the sources the dependencies may come from, if they are restricted
+    pub allowed_sources: Option<Rc<AllowedSources>>,
     /// for each package the list of names it can see,
     /// then for each name the exact version that name represents and whether the name is public.
     pub public_dependency: Option<PublicDependency>,
@@ -77,12 +82,32 @@ impl PackageId {
     }
 }
 
+/// Whether `a` and `b` can't both declare `links = link`, because
+/// `[workspace.links]` doesn't allow them to share it.
+fn links_conflict(
+    policies: &BTreeMap<InternedString, LinksPolicy>,
+    link: InternedString,
+    a: PackageId,
+    b: PackageId,
+) -> bool {
+    a != b
+        && !policies.get(&link).map_or(false, |policy| {
+            policy.allows(a.name()) && policy.allows(b.name())
+        })
+}
+
 impl Context {
-    pub fn new(check_public_visible_dependencies: bool) -> Context {
+    pub fn new(
+        check_public_visible_dependencies: bool,
+        links_policies: &BTreeMap<InternedString, LinksPolicy>,
+        allowed_sources: Option<&AllowedSources>,
+    ) -> Context {
         Context {
             age: 0,
             resolve_features: im_rc::HashMap::new(),
             links: im_rc::HashMap::new(),
+            links_policies: Rc::new(links_policies.clone()),
+            allowed_sources: allowed_sources.map(|a| Rc::new(a.clone())),
             public_dependency: if check_public_visible_dependencies {
                 Some(PublicDependency::new())
             } else {
@@ -93,6 +118,11 @@ impl Context {
         }
     }
 
+    /// Whether `a` and `b` can't both declare `links = link`.
+    pub fn links_conflict(&self, link: InternedString, a: PackageId, b: PackageId) -> bool {
+        links_conflict(&self.links_policies, link, a, b)
+    }
+
     /// Activate this summary by inserting it into our list of known activations.
     ///
     /// The `parent` passed in here is the parent summary/dependency edge which
@@ -118,7 +148,9 @@ impl Context {
             }
             im_rc::hashmap::Entry::Vacant(v) => {
                 if let Some(link) = summary.links() {
-                    if self.links.insert(link, id).is_some() {
+                    let prev = self.links.insert(link, id);
+                    let policies = &self.links_policies;
+                    if prev.map_or(false, |prev| links_conflict(policies, link, prev, id)) {
                         return Err(format_err!(
                             "Attempting to resolve a dependency with more than \
                              one crate with links={}.\nThis will not build as \
@@ -11,12 +11,14 @@
 
 use crate::core::resolver::context::Context;
 use crate::core::resolver::errors::describe_path_in_context;
-use crate::core::resolver::types::{ConflictReason, DepInfo, FeaturesSet};
+use crate::core::resolver::types::{ConflictReason, DepInfo, FeaturesSet, MinimalVersions};
 use crate::core::resolver::{
     ActivateError, ActivateResult, CliFeatures, RequestedFeatures, ResolveOpts, VersionOrdering,
     VersionPreferences,
 };
-use crate::core::{Dependency, FeatureValue, PackageId, PackageIdSpec, Registry, Summary};
+use crate::core::{
+    Dependency, FeatureValue, PackageId, PackageIdSpec, Registry, SourceId, Summary,
+};
 use crate::util::errors::CargoResult;
 use crate::util::interning::InternedString;
 
@@ -29,12 +31,13 @@ pub struct RegistryQueryer<'a> {
     pub registry: &'a mut (dyn Registry + 'a),
     replacements: &'a [(PackageIdSpec, Dependency)],
     version_prefs: &'a VersionPreferences,
-    /// If set the list of dependency candidates will be sorted by minimal
-    /// versions first. That allows `cargo update -Z minimal-versions` which will
+    /// The dependencies whose candidates will be sorted by minimal versions
+    /// first. That allows `cargo update --minimal-versions` which will
     /// specify minimum dependency versions to be used.
-    minimal_versions: bool,
-    /// a cache of `Candidate`s that fulfil a `Dependency`
-    registry_cache: HashMap<Dependency, Rc<Vec<Summary>>>,
+    minimal_versions: &'a MinimalVersions,
+    /// a cache of `Candidate`s that fulfil a `Dependency`, sorted by minimal
+    /// versions first or not
+    registry_cache: HashMap<(Dependency, bool), Rc<Vec<Summary>>>,
     /// a cache of `Dependency`s that are required for a `Summary`
     summary_cache: HashMap<
         (Option<PackageId>, Summary, ResolveOpts),
@@ -49,7 +52,7 @@ impl<'a> RegistryQueryer<'a> {
         registry: &'a mut dyn Registry,
         replacements: &'a [(PackageIdSpec, Dependency)],
         version_prefs: &'a VersionPreferences,
-        minimal_versions: bool,
+        minimal_versions: &'a MinimalVersions,
     ) -> Self {
         RegistryQueryer {
             registry,
@@ -77,7 +80,21 @@ impl<'a> RegistryQueryer<'a> {
     /// applied by performing a second query for what the override should
     /// return.
     pub fn query(&mut self, dep: &Dependency) -> CargoResult<Rc<Vec<Summary>>> {
-        if let Some(out) = self.registry_cache.get(dep).cloned() {
+        self.query_sorted(dep, self.minimal_versions.applies_to(dep, false))
+    }
+
+    /// Like `query`, with the candidates sorted by minimal versions first if
+    /// `minimal_versions` is set.
+    fn query_sorted(
+        &mut self,
+        dep: &Dependency,
+        minimal_versions: bool,
+    ) -> CargoResult<Rc<Vec<Summary>>> {
+        if let Some(out) = self
+            .registry_cache
+            .get(&(dep.clone(), minimal_versions))
+            .cloned()
+        {
             return Ok(out);
         }
 
@@ -168,7 +185,7 @@ impl<'a> RegistryQueryer<'a> {
         // the "best candidates" first. VersionPreferences implements this notion.
         self.version_prefs.sort_summaries(
             &mut ret,
-            if self.minimal_versions {
+            if minimal_versions {
                 VersionOrdering::MinimumVersionsFirst
             } else {
                 VersionOrdering::MaximumVersionsFirst
@@ -177,7 +194,8 @@ impl<'a> RegistryQueryer<'a> {
 
         let out = Rc::new(ret);
 
-        self.registry_cache.insert(dep.clone(), out.clone());
+        self.registry_cache
+            .insert((dep.clone(), minimal_versions), out.clone());
 
         Ok(out)
     }
@@ -207,18 +225,27 @@ impl<'a> RegistryQueryer<'a> {
         // for our own dependencies.
         let (used_features, deps) = resolve_features(parent, candidate, opts)?;
 
+        // The summaries without a parent are the members of the workspace.
+        let direct = parent.is_none();
+
         // Next, transform all dependencies into a list of possible candidates
         // which can satisfy that dependency.
         let mut deps = deps
             .into_iter()
             .map(|(dep, features)| {
-                let candidates = self.query(&dep).with_context(|| {
+                check_allowed_source(cx, candidate, &dep, dep.source_id())?;
+                let minimal_versions = self.minimal_versions.applies_to(&dep, direct);
+                let candidates = self.query_sorted(&dep, minimal_versions).with_context(|| {
                     format!(
                         "failed to get `{}` as a dependency of {}",
                         dep.package_name(),
                         describe_path_in_context(cx, &candidate.package_id()),
                     )
                 })?;
+                // A `[patch]` may bring in a package from another source.
+                for summary in candidates.iter() {
+                    check_allowed_source(cx, candidate, &dep, summary.source_id())?;
+                }
                 Ok((dep, candidates, features))
             })
             .collect::<CargoResult<Vec<DepInfo>>>()?;
@@ -242,6 +269,35 @@ impl<'a> RegistryQueryer<'a> {
 
 /// Returns the features we ended up using and
 /// all dependencies and the features we want from each of them.
+/// Checks that the dependency `dep` of `parent` may come from `source`, if the
+/// sources are restricted by `allowed-sources`.
+fn check_allowed_source(
+    cx: &Context,
+    parent: &Summary,
+    dep: &Dependency,
+    source: SourceId,
+) -> CargoResult<()> {
+    match &cx.allowed_sources {
+        Some(allowed) if !allowed.allows(source) => {}
+        _ => return Ok(()),
+    }
+    let source = if source.is_registry() {
+        format!("the registry `{}`", source.display_registry_name())
+    } else if source.is_git() {
+        format!("the git repository `{}`", source.url())
+    } else {
+        format!("`{}`", source.url())
+    };
+    anyhow::bail!(
+        "dependency `{}` comes from {}, which is not one of the `allowed-sources` \
+         of the workspace policy\n\
+         required by {}",
+        dep.package_name(),
+        source,
+        describe_path_in_context(cx, &parent.package_id())
+    )
+}
+
 pub fn resolve_features<'b>(
     parent: Option<PackageId>,
     s: &'b Summary,
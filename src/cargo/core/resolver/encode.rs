@@ -57,6 +57,24 @@
 //! Listed from most recent to oldest, these are some of the changes we've made
 //! to `Cargo.lock`'s serialization format:
 //!
+//! * Version 5 records the `resolver` of the workspace at the top of the
+//!   file, under `version`, and the provenance of each registry package in
+//!   its `mirror` and `signature` keys, right after `checksum`: the name of
+//!   the `[source]` replacing the registry, and how `-Z sumdb` verified the
+//!   checksum. The provenance is recorded when the package is locked and kept
+//!   as is while it stays locked, and the keys are left out when there's
+//!   nothing to record, so that the lock file doesn't change with the
+//!   configuration of whoever updates it, or with later builds. It is
+//!   written with `-Z lockfile-v5`, and `cargo lockfile migrate` converts
+//!   between the versions. Version 4 is skipped, it is the encoding of git
+//!   URLs of upstream Cargo.
+//!
+//!   The order of the file is the same as in the previous versions: packages
+//!   sorted by name, version and source, with the keys of each one in a fixed
+//!   order and their `dependencies` sorted, then `[[patch.unused]]` and
+//!   `[metadata]`. A version bump then only changes the lines of the package
+//!   and the dependency edges which need its version to be unambiguous.
+//!
 //! * A `version` marker is now at the top of the lock file which is a way for
 //!   super-old Cargos (at least since this was implemented) to give a formal
 //!   error if they see a lock file from a super-future Cargo. Additionally as
@@ -111,7 +129,7 @@
 //!   special fashion to make sure we have strict control over the on-disk
 //!   format.
 
-use super::{Resolve, ResolveVersion};
+use super::{Provenance, Resolve, ResolveVersion};
 use crate::core::{Dependency, GitReference, Package, PackageId, SourceId, Workspace};
 use crate::util::errors::CargoResult;
 use crate::util::interning::InternedString;
@@ -129,6 +147,8 @@ use std::str::FromStr;
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EncodableResolve {
     version: Option<u32>,
+    /// The `resolver` of the workspace, written by `ops::lockfile` in `V5`.
+    resolver: Option<String>,
     package: Option<Vec<EncodableDependency>>,
     /// `root` is optional to allow backward compatibility.
     root: Option<EncodableDependency>,
@@ -156,9 +176,11 @@ impl EncodableResolve {
     pub fn into_resolve(self, original: &str, ws: &Workspace<'_>) -> CargoResult<Resolve> {
         let path_deps = build_path_deps(ws)?;
         let mut checksums = HashMap::new();
+        let mut provenance = HashMap::new();
 
         let mut version = match self.version {
             Some(3) => ResolveVersion::V3,
+            Some(5) => ResolveVersion::V5,
             Some(n) => bail!(
                 "lock file version `{}` was found, but this version of Cargo \
                  does not understand this lock file, perhaps Cargo needs \
@@ -212,6 +234,16 @@ impl EncodableResolve {
                     checksums.insert(id, Some(cksum.clone()));
                 }
 
+                if version >= ResolveVersion::V5 {
+                    provenance.insert(
+                        id,
+                        Provenance {
+                            mirror: pkg.mirror.clone(),
+                            signature: pkg.signature.clone(),
+                        },
+                    );
+                }
+
                 assert!(live_pkgs.insert(enc_id, (id, pkg)).is_none())
             }
             live_pkgs
@@ -389,7 +421,7 @@ impl EncodableResolve {
             version = ResolveVersion::V2;
         }
 
-        Ok(Resolve::new(
+        let mut resolve = Resolve::new(
             g,
             replacements,
             HashMap::new(),
@@ -398,7 +430,9 @@ impl EncodableResolve {
             unused_patches,
             version,
             HashMap::new(),
-        ))
+        );
+        *resolve.provenance_mut() = provenance;
+        Ok(resolve)
     }
 }
 
@@ -482,6 +516,8 @@ pub struct EncodableDependency {
     version: String,
     source: Option<SourceId>,
     checksum: Option<String>,
+    mirror: Option<String>,
+    signature: Option<String>,
     dependencies: Option<Vec<EncodablePackageId>>,
     replace: Option<EncodablePackageId>,
 }
@@ -596,6 +632,8 @@ impl<'a> ser::Serialize for Resolve {
                     name: id.name().to_string(),
                     version: id.version().to_string(),
                     source: encode_source(id.source_id()),
+                    mirror: None,
+                    signature: None,
                     dependencies: None,
                     replace: None,
                     checksum: if self.version() >= ResolveVersion::V2 {
@@ -607,11 +645,13 @@ impl<'a> ser::Serialize for Resolve {
                 .collect(),
         };
         EncodableResolve {
+            resolver: None,
             package: Some(encodable),
             root: None,
             metadata,
             patch,
             version: match self.version() {
+                ResolveVersion::V5 => Some(5),
                 ResolveVersion::V3 => Some(3),
                 ResolveVersion::V2 | ResolveVersion::V1 => None,
             },
@@ -664,10 +704,16 @@ fn encodable_resolve_node(
         }
     };
 
+    let provenance = match resolve.provenance().get(&id) {
+        Some(provenance) if resolve.version() >= ResolveVersion::V5 => provenance.clone(),
+        _ => Provenance::default(),
+    };
     EncodableDependency {
         name: id.name().to_string(),
         version: id.version().to_string(),
         source: encode_source(id.source_id()),
+        mirror: provenance.mirror,
+        signature: provenance.signature,
         dependencies: deps,
         replace,
         checksum: if resolve.version() >= ResolveVersion::V2 {
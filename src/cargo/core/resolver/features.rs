@@ -195,6 +195,11 @@ impl FeatureOpts {
             },
         }
     }
+
+    /// Whether build dependencies and proc-macros get their own set of features.
+    pub fn decouples_host_deps(&self) -> bool {
+        self.decouple_host_deps
+    }
 }
 
 /// Features flags requested for a package.
@@ -379,6 +384,41 @@ impl ResolvedFeatures {
 /// Key is `(pkg_id, for_host)`. Value is a set of features or dependencies removed.
 pub type DiffMap = BTreeMap<(PackageId, bool), BTreeSet<InternedString>>;
 
+/// One reason why a feature of a package is activated, as reported by
+/// `cargo feature explain`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FeatureActivation {
+    /// Requested on the command line for a workspace member. This includes
+    /// the `default` feature, unless `--no-default-features` was passed.
+    CommandLine,
+    /// Listed by another activated feature of the same package.
+    Feature(InternedString),
+    /// Requested by the declaration of a dependency on the package. `default`
+    /// is `true` if this is the implicit `default` feature of a dependency
+    /// which doesn't disable `default-features`.
+    Dependency {
+        parent: PackageId,
+        parent_for_host: bool,
+        default: bool,
+    },
+    /// Enabled by a `dep_name/feat_name` value (or `dep_name?/feat_name` if
+    /// `weak`) in an activated feature of a package depending on it.
+    DepFeature {
+        parent: PackageId,
+        parent_for_host: bool,
+        parent_feature: InternedString,
+        weak: bool,
+    },
+}
+
+/// The reasons why a feature is activated for one variant of a package.
+pub struct FeatureExplanation {
+    /// Whether this is the variant for build dependencies and proc-macros.
+    /// This is always `false` if host and target features are unified.
+    pub for_host: bool,
+    pub activations: Vec<FeatureActivation>,
+}
+
 pub struct FeatureResolver<'a, 'cfg> {
     ws: &'a Workspace<'cfg>,
     target_data: &'a RustcTargetData<'cfg>,
@@ -466,6 +506,145 @@ impl<'a, 'cfg> FeatureResolver<'a, 'cfg> {
         })
     }
 
+    /// Runs the resolution algorithm, then reports why `feature` of `pkg_id`
+    /// was activated.
+    ///
+    /// The result has one entry per variant of the package (target or host)
+    /// the feature is activated for.
+    pub fn explain(
+        ws: &Workspace<'cfg>,
+        target_data: &RustcTargetData<'cfg>,
+        resolve: &Resolve,
+        package_set: &'a PackageSet<'cfg>,
+        cli_features: &CliFeatures,
+        specs: &[PackageIdSpec],
+        requested_targets: &[CompileKind],
+        mut opts: FeatureOpts,
+        pkg_id: PackageId,
+        feature: InternedString,
+    ) -> CargoResult<Vec<FeatureExplanation>> {
+        // The legacy mode doesn't track anything. Without decoupling, the new
+        // resolver activates the same features, so use it unconditionally.
+        opts.new_resolver = true;
+        let track_for_host = opts.decouple_host_deps || opts.ignore_inactive_targets;
+        let mut r = FeatureResolver {
+            ws,
+            target_data,
+            requested_targets,
+            resolve,
+            package_set,
+            opts,
+            activated_features: HashMap::new(),
+            activated_dependencies: HashMap::new(),
+            processed_deps: HashSet::new(),
+            track_for_host,
+            deferred_weak_dependencies: HashMap::new(),
+        };
+        r.do_resolve(specs, cli_features)?;
+
+        let member_features = ws.members_with_features(specs, cli_features)?;
+        let mut explanations = Vec::new();
+        for for_host in [false, true] {
+            if for_host && !r.opts.decouple_host_deps {
+                continue;
+            }
+            let enabled = match r.activated_features.get(&(pkg_id, for_host)) {
+                Some(enabled) if enabled.contains(&feature) => enabled,
+                _ => continue,
+            };
+            let mut activations = BTreeSet::new();
+
+            for (member, cli_features) in &member_features {
+                if member.package_id() == pkg_id
+                    && (!for_host || r.is_proc_macro(pkg_id))
+                    && r.fvs_from_requested(pkg_id, cli_features)
+                        .contains(&FeatureValue::Feature(feature))
+                {
+                    activations.insert(FeatureActivation::CommandLine);
+                }
+            }
+
+            let feature_map = resolve.summary(pkg_id).features();
+            for other in enabled {
+                let enables = feature_map.get(other).map_or(false, |fvs| {
+                    fvs.iter().any(|fv| match fv {
+                        FeatureValue::Feature(f) => *f == feature,
+                        // `dep_name/feat_name` also enables the feature of
+                        // the optional dependency.
+                        FeatureValue::DepFeature {
+                            dep_name,
+                            weak: false,
+                            ..
+                        } => *dep_name == feature,
+                        _ => false,
+                    })
+                });
+                if enables {
+                    activations.insert(FeatureActivation::Feature(*other));
+                }
+            }
+
+            for (&(parent, parent_for_host), parent_features) in &r.activated_features {
+                for (dep_id, deps) in r.deps(parent, parent_for_host) {
+                    if dep_id != pkg_id {
+                        continue;
+                    }
+                    for (dep, dep_for_host) in deps {
+                        if (r.opts.decouple_host_deps && dep_for_host) != for_host {
+                            continue;
+                        }
+                        let active = !dep.is_optional()
+                            || r.activated_dependencies
+                                .get(&(parent, parent_for_host))
+                                .map_or(false, |deps| deps.contains(&dep.name_in_toml()));
+                        if active {
+                            let requested = dep
+                                .features()
+                                .iter()
+                                .any(|f| FeatureValue::new(*f) == FeatureValue::Feature(feature));
+                            let default = feature == "default" && dep.uses_default_features();
+                            if requested || default {
+                                activations.insert(FeatureActivation::Dependency {
+                                    parent,
+                                    parent_for_host,
+                                    default: default && !requested,
+                                });
+                            }
+                        }
+                        let parent_map = resolve.summary(parent).features();
+                        for parent_feature in parent_features {
+                            for fv in parent_map.get(parent_feature).into_iter().flatten() {
+                                if let FeatureValue::DepFeature {
+                                    dep_name,
+                                    dep_feature,
+                                    weak,
+                                } = fv
+                                {
+                                    if *dep_name == dep.name_in_toml()
+                                        && *dep_feature == feature
+                                        && (active || !*weak)
+                                    {
+                                        activations.insert(FeatureActivation::DepFeature {
+                                            parent,
+                                            parent_for_host,
+                                            parent_feature: *parent_feature,
+                                            weak: *weak,
+                                        });
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            explanations.push(FeatureExplanation {
+                for_host,
+                activations: activations.into_iter().collect(),
+            });
+        }
+        Ok(explanations)
+    }
+
     /// Performs the process of resolving all features for the resolve graph.
     fn do_resolve(
         &mut self,
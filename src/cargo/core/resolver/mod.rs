@@ -55,9 +55,10 @@ use std::time::{Duration, Instant};
 use log::{debug, trace};
 
 use crate::core::PackageIdSpec;
-use crate::core::{Dependency, PackageId, Registry, Summary};
+use crate::core::{AllowedSources, Dependency, LinksPolicy, PackageId, Registry, Summary};
 use crate::util::config::Config;
 use crate::util::errors::CargoResult;
+use crate::util::interning::InternedString;
 use crate::util::profile;
 
 use self::context::Context;
@@ -70,8 +71,8 @@ pub use self::encode::Metadata;
 pub use self::encode::{EncodableDependency, EncodablePackageId, EncodableResolve};
 pub use self::errors::{ActivateError, ActivateResult, ResolveError};
 pub use self::features::{CliFeatures, ForceAllTargets, HasDevUnits};
-pub use self::resolve::{Resolve, ResolveVersion};
-pub use self::types::{ResolveBehavior, ResolveOpts};
+pub use self::resolve::{Provenance, Resolve, ResolveVersion};
+pub use self::types::{MinimalVersions, ResolveBehavior, ResolveOpts};
 pub use self::version_prefs::{VersionOrdering, VersionPreferences};
 
 mod conflict_cache;
@@ -106,6 +107,9 @@ mod version_prefs;
 /// * `version_prefs` - this represents a preference for some versions over others,
 ///   based on the lock file or other reasons such as `[patch]`es.
 ///
+/// * `minimal_versions` - the dependencies to resolve to their minimal
+///   versions instead of their maximal versions.
+///
 /// * `config` - a location to print warnings and such, or `None` if no warnings
 ///   should be printed
 ///
@@ -119,20 +123,27 @@ mod version_prefs;
 ///
 ///     When we have a decision for how to implement is without breaking existing functionality
 ///     this flag can be removed.
+///
+/// * `allowed_sources` - the sources the dependencies may come from, from the
+///   `allowed-sources` of `[workspace.policy]`. A dependency from any other
+///   source is an error, reported with the path that introduced it.
 pub fn resolve(
     summaries: &[(Summary, ResolveOpts)],
     replacements: &[(PackageIdSpec, Dependency)],
     registry: &mut dyn Registry,
     version_prefs: &VersionPreferences,
+    minimal_versions: &MinimalVersions,
     config: Option<&Config>,
     check_public_visible_dependencies: bool,
+    links_policies: &BTreeMap<InternedString, LinksPolicy>,
+    allowed_sources: Option<&AllowedSources>,
 ) -> CargoResult<Resolve> {
-    let cx = Context::new(check_public_visible_dependencies);
+    let cx = Context::new(
+        check_public_visible_dependencies,
+        links_policies,
+        allowed_sources,
+    );
     let _p = profile::start("resolving");
-    let minimal_versions = match config {
-        Some(config) => config.cli_unstable().minimal_versions,
-        None => false,
-    };
     let mut registry =
         RegistryQueryer::new(registry, replacements, version_prefs, minimal_versions);
     let cx = activate_deps_loop(cx, &mut registry, summaries, config)?;
@@ -743,7 +754,7 @@ impl RemainingCandidates {
             // linked to by a different package then we've gotta skip this.
             if let Some(link) = b.links() {
                 if let Some(&a) = cx.links.get(&link) {
-                    if a != b_id {
+                    if cx.links_conflict(link, a, b_id) {
                         conflicting_prev_active
                             .entry(a)
                             .or_insert_with(|| ConflictReason::Links(link));
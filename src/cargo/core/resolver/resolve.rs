@@ -1,3 +1,4 @@
+use super::context::ActivationsKey;
 use super::encode::Metadata;
 use crate::core::dependency::DepKind;
 use crate::core::{Dependency, PackageId, PackageIdSpec, Summary, Target};
@@ -48,6 +49,9 @@ pub struct Resolve {
     /// `cargo::core::resolver::encode` for more.
     version: ResolveVersion,
     summaries: HashMap<PackageId, Summary>,
+    /// Where the registry packages were locked from, recorded in `V5` lock
+    /// files.
+    provenance: HashMap<PackageId, Provenance>,
 }
 
 /// A version to indicate how a `Cargo.lock` should be serialized. Currently
@@ -70,6 +74,23 @@ pub enum ResolveVersion {
     /// `branch = "master"` are no longer encoded the same way as those without
     /// branch specifiers.
     V3,
+    /// A format that records the `resolver` of the workspace at the top of
+    /// the file, and the provenance of each registry package: the source
+    /// replacing its registry, and how its checksum was verified. Version 4
+    /// is skipped, it is the encoding of git URLs of upstream Cargo.
+    V5,
+}
+
+/// Where a registry package of a `V5` lock file was locked from. It is
+/// recorded once and then kept while the package stays locked, so that it
+/// doesn't change with the configuration of whoever updates the lock file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Provenance {
+    /// The name of the `[source]` replacing the registry, like a mirror.
+    pub mirror: Option<String>,
+    /// How `-Z sumdb` verified the checksum: `transparency-log` or
+    /// `checksum-db`, or `None` if it wasn't.
+    pub signature: Option<String>,
 }
 
 impl Resolve {
@@ -112,6 +133,7 @@ impl Resolve {
             public_dependencies,
             version,
             summaries,
+            provenance: HashMap::new(),
         }
     }
 
@@ -224,9 +246,93 @@ unable to verify that `{0}` is the same as when the lockfile was generated
         // Preserve the lockfile encoding where possible to avoid lockfile churn
         self.version = previous.version;
 
+        // As well as the provenance of the packages which are still locked.
+        for (id, provenance) in previous.provenance.iter() {
+            if self.graph.contains(id) {
+                self.provenance.insert(*id, provenance.clone());
+            }
+        }
+
         Ok(())
     }
 
+    /// Combines two resolves of the same workspace, like the two sides of
+    /// the merge conflicts of a `Cargo.lock`, into a previous resolve to
+    /// re-resolve with.
+    ///
+    /// Where both lock semver compatible versions of a package, the higher
+    /// one is kept and the dependencies on the other point at it instead. The
+    /// resolver then keeps it wherever the version requirements allow, and
+    /// re-resolves the dependencies it no longer matches. Git packages locked
+    /// at different revisions keep the revision of `self`.
+    pub fn union(&self, other: &Resolve) -> Resolve {
+        let mut kept: HashMap<ActivationsKey, PackageId> = HashMap::new();
+        for id in self.iter().chain(other.iter()) {
+            let winner = kept.entry(id.as_activations_key()).or_insert(id);
+            if id.version() > winner.version() {
+                *winner = id;
+            }
+        }
+        let pick = |id: PackageId| kept.get(&id.as_activations_key()).cloned().unwrap_or(id);
+
+        let mut graph: Graph<PackageId, HashSet<Dependency>> = Graph::new();
+        for resolve in &[self, other] {
+            for id in resolve.iter() {
+                if pick(id) != id {
+                    continue;
+                }
+                graph.add(id);
+                for (dep, deps) in resolve.graph.edges(&id) {
+                    graph.link(id, pick(*dep)).extend(deps.iter().cloned());
+                }
+            }
+        }
+
+        let mut replacements = HashMap::new();
+        let mut checksums = HashMap::new();
+        let mut metadata = self.metadata.clone();
+        let mut unused_patches = self.unused_patches.clone();
+        let mut provenance = HashMap::new();
+        for resolve in &[self, other] {
+            for (id, replacement) in resolve.replacements.iter() {
+                replacements
+                    .entry(pick(*id))
+                    .or_insert_with(|| pick(*replacement));
+            }
+            for (id, checksum) in resolve.checksums.iter() {
+                if graph.contains(id) {
+                    checksums.entry(*id).or_insert_with(|| checksum.clone());
+                }
+            }
+            for (id, p) in resolve.provenance.iter() {
+                if graph.contains(id) {
+                    provenance.entry(*id).or_insert_with(|| p.clone());
+                }
+            }
+        }
+        for (key, value) in other.metadata.iter() {
+            metadata.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+        for id in other.unused_patches.iter() {
+            if !unused_patches.contains(id) {
+                unused_patches.push(*id);
+            }
+        }
+
+        let mut union = Resolve::new(
+            graph,
+            replacements,
+            HashMap::new(),
+            checksums,
+            metadata,
+            unused_patches,
+            self.version.max(other.version),
+            HashMap::new(),
+        );
+        union.provenance = provenance;
+        union
+    }
+
     pub fn contains<Q: ?Sized>(&self, k: &Q) -> bool
     where
         PackageId: Borrow<Q>,
@@ -300,6 +406,10 @@ unable to verify that `{0}` is the same as when the lockfile was generated
         &self.metadata
     }
 
+    pub fn metadata_mut(&mut self) -> &mut Metadata {
+        &mut self.metadata
+    }
+
     pub fn extern_crate_name(
         &self,
         from: PackageId,
@@ -362,6 +472,14 @@ unable to verify that `{0}` is the same as when the lockfile was generated
         self.version = version;
     }
 
+    pub fn provenance(&self) -> &HashMap<PackageId, Provenance> {
+        &self.provenance
+    }
+
+    pub fn provenance_mut(&mut self) -> &mut HashMap<PackageId, Provenance> {
+        &mut self.provenance
+    }
+
     pub fn summary(&self, pkg_id: PackageId) -> &Summary {
         &self.summaries[&pkg_id]
     }
@@ -381,7 +499,7 @@ impl PartialEq for Resolve {
             checksums metadata unused_patches public_dependencies summaries
             |
             // fields to ignore
-            version
+            version provenance
         }
     }
 }
@@ -127,6 +127,34 @@ impl ResolveBehavior {
     }
 }
 
+/// Which dependencies are resolved to their minimal versions instead of
+/// their maximal versions, with `--minimal-versions` or
+/// `--direct-minimal-versions`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MinimalVersions {
+    /// Resolves all dependencies to their minimal versions.
+    pub all: bool,
+    /// Resolves the direct dependencies of the workspace members to their
+    /// minimal versions.
+    pub direct: bool,
+    /// The packages resolved to their maximal versions anyway, from the
+    /// `exempt` list of `[workspace.minimal-versions]`.
+    pub exempt: BTreeSet<InternedString>,
+}
+
+impl MinimalVersions {
+    /// Whether any dependency is resolved to its minimal version.
+    pub fn is_enabled(&self) -> bool {
+        self.all || self.direct
+    }
+
+    /// Whether `dep` is resolved to its minimal version, where `direct` is
+    /// whether it is a dependency of a workspace member.
+    pub fn applies_to(&self, dep: &Dependency, direct: bool) -> bool {
+        (self.all || (self.direct && direct)) && !self.exempt.contains(&dep.package_name())
+    }
+}
+
 /// Options for how the resolve should work.
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub struct ResolveOpts {
@@ -93,6 +93,12 @@ pub trait Source {
         false
     }
 
+    /// The revision this source is locked to once updated, like the commit of
+    /// a git source, or `None` if it doesn't have one.
+    fn locked_revision(&self) -> Option<String> {
+        None
+    }
+
     /// Add a number of crates that should be whitelisted for showing up during
     /// queries, even if they are yanked. Currently only applies to registry
     /// sources.
@@ -171,6 +177,10 @@ impl<'a, T: Source + ?Sized + 'a> Source for Box<T> {
         (**self).is_replaced()
     }
 
+    fn locked_revision(&self) -> Option<String> {
+        (**self).locked_revision()
+    }
+
     fn add_to_yanked_whitelist(&mut self, pkgs: &[PackageId]) {
         (**self).add_to_yanked_whitelist(pkgs);
     }
@@ -233,6 +243,10 @@ impl<'a, T: Source + ?Sized + 'a> Source for &'a mut T {
         (**self).is_replaced()
     }
 
+    fn locked_revision(&self) -> Option<String> {
+        (**self).locked_revision()
+    }
+
     fn add_to_yanked_whitelist(&mut self, pkgs: &[PackageId]) {
         (**self).add_to_yanked_whitelist(pkgs);
     }
@@ -27,6 +27,7 @@ struct Inner {
     has_overlapping_features: Option<InternedString>,
     checksum: Option<String>,
     links: Option<InternedString>,
+    rust_version: Option<InternedString>,
 }
 
 impl Summary {
@@ -62,6 +63,7 @@ impl Summary {
                 features: Rc::new(feature_map),
                 checksum: None,
                 links: links.map(|l| l.into()),
+                rust_version: None,
                 has_namespaced_features,
                 has_overlapping_features,
             }),
@@ -133,6 +135,11 @@ impl Summary {
     pub fn links(&self) -> Option<InternedString> {
         self.inner.links
     }
+    /// The `rust-version` of the package, if it has one and it is known. The
+    /// entries of the registry index only have it since it is published.
+    pub fn rust_version(&self) -> Option<InternedString> {
+        self.inner.rust_version
+    }
 
     pub fn override_id(mut self, id: PackageId) -> Summary {
         Rc::make_mut(&mut self.inner).package_id = id;
@@ -143,6 +150,10 @@ impl Summary {
         Rc::make_mut(&mut self.inner).checksum = Some(cksum);
     }
 
+    pub fn set_rust_version(&mut self, rust_version: Option<InternedString>) {
+        Rc::make_mut(&mut self.inner).rust_version = rust_version;
+    }
+
     pub fn map_dependencies<F>(mut self, f: F) -> Summary
     where
         F: FnMut(Dependency) -> Dependency,
@@ -154,6 +165,21 @@ impl Summary {
         self
     }
 
+    /// Removes every value for which `f` returns `false` from the features
+    /// of this summary.
+    pub fn retain_feature_values<F>(mut self, mut f: F) -> Summary
+    where
+        F: FnMut(&FeatureValue) -> bool,
+    {
+        {
+            let features = Rc::make_mut(&mut Rc::make_mut(&mut self.inner).features);
+            for values in features.values_mut() {
+                values.retain(|fv| f(fv));
+            }
+        }
+        self
+    }
+
     pub fn map_source(self, to_replace: SourceId, replace_with: SourceId) -> Summary {
         let me = if self.package_id().source_id() == to_replace {
             let new_id = self.package_id().with_source_id(replace_with);
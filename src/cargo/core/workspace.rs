@@ -5,6 +5,7 @@ use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
 use anyhow::{bail, Context as _};
+use cargo_platform::Cfg;
 use glob::glob;
 use itertools::Itertools;
 use log::debug;
@@ -13,14 +14,15 @@ use url::Url;
 use crate::core::features::Features;
 use crate::core::registry::PackageRegistry;
 use crate::core::resolver::features::CliFeatures;
-use crate::core::resolver::ResolveBehavior;
-use crate::core::{Dependency, Edition, FeatureValue, PackageId, PackageIdSpec};
-use crate::core::{EitherManifest, Package, SourceId, VirtualManifest};
+use crate::core::resolver::{MinimalVersions, ResolveBehavior};
+use crate::core::{Dependency, Edition, FeatureValue, PackageId, PackageIdSpec, WorkspacePolicy};
+use crate::core::{EitherManifest, EnvValue, FeatureOverride, Package, SourceId, VirtualManifest};
 use crate::ops;
-use crate::sources::{PathSource, CRATES_IO_INDEX, CRATES_IO_REGISTRY};
+use crate::sources::{PathSource, SourceConfigMap, CRATES_IO_INDEX, CRATES_IO_REGISTRY};
 use crate::util::errors::{CargoResult, ManifestError};
 use crate::util::interning::InternedString;
 use crate::util::lev_distance;
+use crate::util::restricted_names::is_glob_pattern;
 use crate::util::toml::{read_manifest, TomlDependency, TomlProfiles};
 use crate::util::{config::ConfigRelativePath, Config, Filesystem, IntoUrl};
 use cargo_util::paths;
@@ -71,6 +73,10 @@ pub struct Workspace<'cfg> {
     // or only the root package for non-virtual workspaces.
     default_members: Vec<PathBuf>,
 
+    // Named subsets of `members` declared in `[workspace.groups]`, selectable
+    // with the `--group` flag. Each entry lists member manifest paths.
+    groups: BTreeMap<String, Vec<PathBuf>>,
+
     // `true` if this is a temporary workspace created for the purposes of the
     // `cargo install` or `cargo package` commands.
     is_ephemeral: bool,
@@ -89,11 +95,40 @@ pub struct Workspace<'cfg> {
     // file. This is set for `cargo install` without `--locked`.
     ignore_lock: bool,
 
+    // The file name of the lock file in the root of the workspace, which is
+    // `Cargo.lock` unless another one is selected with `--lockfile`.
+    lockfile_name: String,
+
     /// The resolver behavior specified with the `resolver` field.
     resolve_behavior: ResolveBehavior,
 
     /// Workspace-level custom metadata
     custom_metadata: Option<toml::Value>,
+
+    /// The `links` values that several packages may declare, from the
+    /// `[workspace.links]` table.
+    links_policies: BTreeMap<InternedString, LinksPolicy>,
+
+    /// How the changelogs of the members are checked and assembled, from the
+    /// `[workspace.changelog]` table.
+    changelog: Option<ChangelogConfig>,
+
+    /// The rules the dependencies are checked against, from the
+    /// `[workspace.policy]` table.
+    policy: Option<WorkspacePolicy>,
+
+    /// The dependencies resolved to their minimal versions, from
+    /// `--minimal-versions` or `--direct-minimal-versions`, and the packages
+    /// exempted from the `[workspace.minimal-versions]` table.
+    minimal_versions: MinimalVersions,
+
+    /// The packages whose pre-release versions match the requirements of
+    /// their dependents, from `workspace.allow-prerelease`.
+    allow_prerelease: BTreeSet<InternedString>,
+
+    /// The packages replaced by local packages for this invocation only, from
+    /// `--override` and `Cargo.override.toml`, keyed by package name.
+    dependency_overrides: BTreeMap<InternedString, PathBuf>,
 }
 
 // Separate structure for tracking loaded packages (to avoid loading anything
@@ -132,9 +167,47 @@ pub struct WorkspaceRootConfig {
     members: Option<Vec<String>>,
     default_members: Option<Vec<String>>,
     exclude: Vec<String>,
+    groups: BTreeMap<String, Vec<String>>,
+    links_policies: BTreeMap<InternedString, LinksPolicy>,
+    changelog: Option<ChangelogConfig>,
+    policy: Option<WorkspacePolicy>,
+    minimal_versions_exempt: BTreeSet<InternedString>,
+    allow_prerelease: BTreeSet<InternedString>,
     custom_metadata: Option<toml::Value>,
 }
 
+/// How the packages sharing a `links` value are handled, from an entry of the
+/// `[workspace.links]` table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinksPolicy {
+    /// The names of the packages allowed to declare the `links` value.
+    pub packages: Vec<InternedString>,
+    /// The package whose native libraries are linked. Without one, the
+    /// native libraries of all of the packages are linked.
+    pub winner: Option<InternedString>,
+}
+
+/// How the changelogs of the members are checked and assembled, from the
+/// `[workspace.changelog]` table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangelogConfig {
+    /// The directory of the changelog fragments, relative to the root of each
+    /// member.
+    pub fragments: PathBuf,
+    /// The changelog, relative to the root of each member.
+    pub file: PathBuf,
+    /// Whether packaging fails when the changelog has no entry for the
+    /// version, rather than warning about it.
+    pub require_entry: bool,
+}
+
+impl LinksPolicy {
+    /// Whether the package named `name` may share the `links` value.
+    pub fn allows(&self, name: InternedString) -> bool {
+        self.packages.contains(&name)
+    }
+}
+
 impl<'cfg> Workspace<'cfg> {
     /// Creates a new workspace given the target manifest pointed to by
     /// `manifest_path`.
@@ -155,9 +228,20 @@ impl<'cfg> Workspace<'cfg> {
             ws.root_manifest = ws.find_root(manifest_path)?;
         }
 
-        ws.custom_metadata = ws
-            .load_workspace_config()?
-            .and_then(|cfg| cfg.custom_metadata);
+        if let Some(cfg) = ws.load_workspace_config()? {
+            ws.custom_metadata = cfg.custom_metadata;
+            ws.links_policies = cfg.links_policies;
+            ws.changelog = cfg.changelog;
+            ws.policy = cfg.policy;
+            ws.minimal_versions.exempt = cfg.minimal_versions_exempt;
+            ws.allow_prerelease = cfg.allow_prerelease;
+        }
+        ws.minimal_versions.all = config.cli_unstable().minimal_versions;
+        ws.minimal_versions.direct = config.cli_unstable().direct_minimal_versions;
+        ws.load_override_file()?;
+        if let Some(name) = SourceConfigMap::mode_lockfile_name(config)? {
+            ws.set_lockfile_name(&name)?;
+        }
         ws.find_members()?;
         ws.set_resolve_behavior();
         ws.validate()?;
@@ -177,12 +261,20 @@ impl<'cfg> Workspace<'cfg> {
             members: Vec::new(),
             member_ids: HashSet::new(),
             default_members: Vec::new(),
+            groups: BTreeMap::new(),
             is_ephemeral: false,
             require_optional_deps: true,
             loaded_packages: RefCell::new(HashMap::new()),
             ignore_lock: false,
+            lockfile_name: String::from("Cargo.lock"),
             resolve_behavior: ResolveBehavior::V1,
             custom_metadata: None,
+            links_policies: BTreeMap::new(),
+            changelog: None,
+            policy: None,
+            minimal_versions: MinimalVersions::default(),
+            allow_prerelease: BTreeSet::new(),
+            dependency_overrides: BTreeMap::new(),
         }
     }
 
@@ -452,6 +544,42 @@ impl<'cfg> Workspace<'cfg> {
         Ok(combined)
     }
 
+    /// Returns the `[env]` of the root manifest, which applies to all the
+    /// members.
+    pub fn root_env(&self) -> &BTreeMap<String, EnvValue> {
+        match self.root_maybe() {
+            MaybePackage::Package(p) => p.manifest().env(),
+            MaybePackage::Virtual(vm) => vm.env(),
+        }
+    }
+
+    /// Returns the `[feature-overrides]` of the root manifest, keyed by
+    /// package name.
+    pub fn root_feature_overrides(&self) -> &BTreeMap<InternedString, FeatureOverride> {
+        match self.root_maybe() {
+            MaybePackage::Package(p) => p.manifest().feature_overrides(),
+            MaybePackage::Virtual(vm) => vm.feature_overrides(),
+        }
+    }
+
+    /// Returns the `[config-flags]` of the root manifest, the cfgs set for
+    /// packages by name.
+    pub fn root_config_flags(&self) -> &BTreeMap<InternedString, Vec<Cfg>> {
+        match self.root_maybe() {
+            MaybePackage::Package(p) => p.manifest().config_flags(),
+            MaybePackage::Virtual(vm) => vm.config_flags(),
+        }
+    }
+
+    /// Returns whether the root manifest denies the warnings of the members
+    /// with `lints.workspace-deny-warnings`.
+    pub fn root_deny_warnings(&self) -> bool {
+        match self.root_maybe() {
+            MaybePackage::Package(p) => p.manifest().deny_warnings(),
+            MaybePackage::Virtual(vm) => vm.deny_warnings(),
+        }
+    }
+
     /// Returns an iterator over all packages in this workspace
     pub fn members(&self) -> impl Iterator<Item = &Package> {
         let packages = &self.packages;
@@ -514,6 +642,38 @@ impl<'cfg> Workspace<'cfg> {
         })
     }
 
+    /// Returns an iterator over the packages in the workspace group `name`.
+    ///
+    /// Groups are declared in the `[workspace.groups]` table of the root
+    /// manifest. An error is returned if no such group exists.
+    pub fn group_members(&self, name: &str) -> CargoResult<impl Iterator<Item = &Package>> {
+        let paths = match self.groups.get(name) {
+            Some(paths) => paths,
+            None => {
+                let suggestion =
+                    lev_distance::closest_msg(name, self.groups.keys(), |key| key.as_str());
+                bail!(
+                    "workspace group `{}` not found in workspace `{}`{}",
+                    name,
+                    self.root().display(),
+                    suggestion
+                )
+            }
+        };
+        let packages = &self.packages;
+        Ok(paths
+            .iter()
+            .filter_map(move |path| match packages.get(path) {
+                &MaybePackage::Package(ref p) => Some(p),
+                _ => None,
+            }))
+    }
+
+    /// Returns the names of all groups declared in the workspace.
+    pub fn group_names(&self) -> impl Iterator<Item = &str> {
+        self.groups.keys().map(|name| name.as_str())
+    }
+
     /// Returns true if the package is a member of the workspace.
     pub fn is_member(&self, pkg: &Package) -> bool {
         self.member_ids.contains(&pkg.package_id())
@@ -544,10 +704,131 @@ impl<'cfg> Workspace<'cfg> {
         self
     }
 
+    /// The dependencies resolved to their minimal versions instead of their
+    /// maximal versions.
+    pub fn minimal_versions(&self) -> &MinimalVersions {
+        &self.minimal_versions
+    }
+
+    /// Resolves all dependencies to their minimal versions if `all` is set,
+    /// or the direct dependencies of the members if `direct` is set, for
+    /// `--minimal-versions` and `--direct-minimal-versions`.
+    pub fn set_minimal_versions(&mut self, all: bool, direct: bool) -> &mut Workspace<'cfg> {
+        self.minimal_versions.all |= all;
+        self.minimal_versions.direct |= direct;
+        self
+    }
+
+    /// The packages whose pre-release versions match the requirements of
+    /// their dependents, from `workspace.allow-prerelease`.
+    pub fn allow_prerelease(&self) -> &BTreeSet<InternedString> {
+        &self.allow_prerelease
+    }
+
+    /// The file name of the lock file of the workspace, like `Cargo.lock` or
+    /// `Cargo.minimal.lock`.
+    pub fn lockfile_name(&self) -> &str {
+        &self.lockfile_name
+    }
+
+    /// The path of the lock file of the workspace.
+    pub fn lockfile_path(&self) -> PathBuf {
+        self.root().join(&self.lockfile_name)
+    }
+
+    /// Selects another lock file in the root of the workspace, for
+    /// `--lockfile`. The name must be a file name of the form `Cargo.*.lock`,
+    /// or `Cargo.lock`.
+    pub fn set_lockfile_name(&mut self, name: &str) -> CargoResult<&mut Workspace<'cfg>> {
+        let valid = name == "Cargo.lock"
+            || (name.starts_with("Cargo.")
+                && name.ends_with(".lock")
+                && name.len() > "Cargo..lock".len()
+                && !name.contains(|c| c == '/' || c == '\\'));
+        if !valid {
+            bail!(
+                "invalid lock file name `{}`, expected a file name like `Cargo.minimal.lock`",
+                name
+            );
+        }
+        self.lockfile_name = name.to_string();
+        Ok(self)
+    }
+
+    /// The packages replaced by local packages for this invocation only,
+    /// keyed by package name. They are resolved like the `paths` overrides,
+    /// and are never written to the lock file.
+    pub fn dependency_overrides(&self) -> &BTreeMap<InternedString, PathBuf> {
+        &self.dependency_overrides
+    }
+
+    /// Replaces the package `name` by the local package at `path`, for
+    /// `--override name=path`.
+    pub fn add_dependency_override(&mut self, name: &str, path: PathBuf) -> &mut Workspace<'cfg> {
+        self.dependency_overrides.insert(name.into(), path);
+        self
+    }
+
+    /// Loads the overrides of the `Cargo.override.toml` file next to the root
+    /// manifest, which is meant to be left untracked:
+    ///
+    /// ```toml
+    /// [override]
+    /// bar = { path = "../bar-fork" }
+    /// ```
+    fn load_override_file(&mut self) -> CargoResult<()> {
+        #[derive(serde::Deserialize)]
+        struct OverrideFile {
+            #[serde(rename = "override", default)]
+            overrides: BTreeMap<String, OverrideEntry>,
+        }
+        #[derive(serde::Deserialize)]
+        struct OverrideEntry {
+            path: PathBuf,
+        }
+
+        let path = self.root().join("Cargo.override.toml");
+        if !path.exists() {
+            return Ok(());
+        }
+        if !self.config.cli_unstable().temporary_overrides {
+            return self.config.shell().warn(format!(
+                "ignoring `{}`, which requires `-Z temporary-overrides`",
+                path.display()
+            ));
+        }
+        let contents = paths::read(&path)?;
+        let file: OverrideFile = toml::from_str(&contents)
+            .with_context(|| format!("failed to parse `{}`", path.display()))?;
+        for (name, entry) in file.overrides {
+            let override_path = self.root().join(entry.path);
+            self.add_dependency_override(&name, override_path);
+        }
+        Ok(())
+    }
+
     pub fn custom_metadata(&self) -> Option<&toml::Value> {
         self.custom_metadata.as_ref()
     }
 
+    /// The `links` values that several packages may declare, and how their
+    /// native libraries are linked.
+    pub fn links_policies(&self) -> &BTreeMap<InternedString, LinksPolicy> {
+        &self.links_policies
+    }
+
+    /// How the changelogs of the members are checked and assembled, if the
+    /// workspace has changelog fragments.
+    pub fn changelog(&self) -> Option<&ChangelogConfig> {
+        self.changelog.as_ref()
+    }
+
+    /// The rules the dependencies are checked against, if the workspace has
+    /// a policy.
+    pub fn policy(&self) -> Option<&WorkspacePolicy> {
+        self.policy.as_ref()
+    }
+
     pub fn load_workspace_config(&mut self) -> CargoResult<Option<WorkspaceRootConfig>> {
         // If we didn't find a root, it must mean there is no [workspace] section, and thus no
         // metadata.
@@ -718,7 +999,48 @@ impl<'cfg> Workspace<'cfg> {
             self.default_members.push(self.current_manifest.clone())
         }
 
-        self.find_path_deps(&root_manifest_path, &root_manifest_path, false)
+        self.find_path_deps(&root_manifest_path, &root_manifest_path, false)?;
+        self.find_groups(&workspace_config)
+    }
+
+    /// Expands the `[workspace.groups]` table into lists of member manifest
+    /// paths.
+    ///
+    /// Each entry of a group is a path or glob relative to the workspace
+    /// root. Entries starting with `!` remove the members they match from the
+    /// entries listed before them, so that a broad glob can be narrowed down.
+    /// Glob entries silently skip paths which are not workspace members, but
+    /// a plain path must always point at a member.
+    fn find_groups(&mut self, workspace_config: &WorkspaceRootConfig) -> CargoResult<()> {
+        for (name, entries) in &workspace_config.groups {
+            let mut group: Vec<PathBuf> = Vec::new();
+            for entry in entries {
+                let (exclude, pattern) = match entry.strip_prefix('!') {
+                    Some(pattern) => (true, pattern),
+                    None => (false, entry.as_str()),
+                };
+                let paths = workspace_config.members_paths(&[pattern.to_string()])?;
+                for path in paths {
+                    let manifest_path = paths::normalize_path(&path).join("Cargo.toml");
+                    if exclude {
+                        group.retain(|p| *p != manifest_path);
+                    } else if self.members.contains(&manifest_path) {
+                        if !group.contains(&manifest_path) {
+                            group.push(manifest_path);
+                        }
+                    } else if !is_glob_pattern(pattern) {
+                        bail!(
+                            "package `{}` is listed in workspace group `{}` \
+                             but is not a member.",
+                            path.display(),
+                            name
+                        )
+                    }
+                }
+            }
+            self.groups.insert(name.clone(), group);
+        }
+        Ok(())
     }
 
     fn find_path_deps(
@@ -991,6 +1313,15 @@ impl<'cfg> Workspace<'cfg> {
                 if !manifest.patch().is_empty() {
                     emit_warning("patch")?;
                 }
+                if !manifest.feature_overrides().is_empty() {
+                    emit_warning("feature-overrides")?;
+                }
+                if !manifest.config_flags().is_empty() {
+                    emit_warning("config-flags")?;
+                }
+                if manifest.deny_warnings() {
+                    emit_warning("lints.workspace-deny-warnings")?;
+                }
                 if let Some(behavior) = manifest.resolve_behavior() {
                     if behavior != self.resolve_behavior {
                         // Only warn if they don't match.
@@ -1566,6 +1897,12 @@ impl WorkspaceRootConfig {
         members: &Option<Vec<String>>,
         default_members: &Option<Vec<String>>,
         exclude: &Option<Vec<String>>,
+        groups: &Option<BTreeMap<String, Vec<String>>>,
+        links_policies: BTreeMap<InternedString, LinksPolicy>,
+        changelog: Option<ChangelogConfig>,
+        policy: Option<WorkspacePolicy>,
+        minimal_versions_exempt: BTreeSet<InternedString>,
+        allow_prerelease: BTreeSet<InternedString>,
         custom_metadata: &Option<toml::Value>,
     ) -> WorkspaceRootConfig {
         WorkspaceRootConfig {
@@ -1573,6 +1910,12 @@ impl WorkspaceRootConfig {
             members: members.clone(),
             default_members: default_members.clone(),
             exclude: exclude.clone().unwrap_or_default(),
+            groups: groups.clone().unwrap_or_default(),
+            links_policies,
+            changelog,
+            policy,
+            minimal_versions_exempt,
+            allow_prerelease,
             custom_metadata: custom_metadata.clone(),
         }
     }
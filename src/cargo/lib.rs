@@ -22,6 +22,7 @@ pub const CARGO_ENV: &str = "CARGO";
 #[macro_use]
 mod macros;
 
+pub mod api;
 pub mod core;
 pub mod ops;
 pub mod sources;
@@ -0,0 +1,519 @@
+//! `cargo add`, with `-Z add`.
+//!
+//! The dependencies are added to the manifest as text, so that its formatting
+//! and comments are kept: a `name = "requirement"` line, or a line with an
+//! inline table, is appended to the `[dependencies]` table of the package,
+//! which is created if needed. Without a requirement, a registry dependency
+//! requires the latest version of the package in the registry.
+//!
+//! With `--analyze`, the workspace is resolved before and after adding the
+//! dependencies, and what the new dependencies weigh is printed: the packages
+//! they pull in and how many of them are new to the workspace, the second
+//! versions of packages they introduce, their licenses, and how long the new
+//! packages took to build in the earlier builds recorded with
+//! `-Z build-stats`. Nothing is added with `--dry-run`.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{bail, Context as _};
+use cargo_util::paths;
+use semver::Version;
+
+use super::version_bump::{split_key_value, table_header};
+use crate::core::compiler::build_stats;
+use crate::core::dependency::DepKind;
+use crate::core::registry::PackageRegistry;
+use crate::core::resolver::{CliFeatures, HasDevUnits, Resolve};
+use crate::core::{Dependency, Package, PackageId, Source, SourceId, Workspace};
+use crate::drop_println;
+use crate::ops::{self, Packages};
+use crate::sources::SourceConfigMap;
+use crate::util::errors::CargoResult;
+use crate::util::Config;
+
+pub struct AddOptions<'a> {
+    pub config: &'a Config,
+    /// The package to add the dependencies to, or `None` for the current
+    /// package.
+    pub package: Option<String>,
+    /// The dependencies to add, like `serde` or `serde@1.0`.
+    pub crates: Vec<String>,
+    /// The path of the package to depend on, relative to the current
+    /// directory.
+    pub path: Option<PathBuf>,
+    /// The registry to depend on, or `None` for crates.io.
+    pub registry: Option<String>,
+    pub kind: DepKind,
+    pub features: Vec<String>,
+    pub optional: bool,
+    pub no_default_features: bool,
+    /// Prints what the dependencies weigh before adding them.
+    pub analyze: bool,
+    /// Doesn't write the manifest.
+    pub dry_run: bool,
+}
+
+/// A dependency to add, with the value of its key in the manifest.
+struct NewDependency {
+    name: String,
+    /// What is printed about the dependency, like `serde v1.0.136`.
+    description: String,
+    value: String,
+}
+
+/// Adds dependencies to a member of the workspace.
+pub fn add(ws: &Workspace<'_>, opts: &AddOptions<'_>) -> CargoResult<()> {
+    let config = opts.config;
+    let pkg = match &opts.package {
+        Some(spec) => {
+            let mut pkgs = Packages::Packages(vec![spec.clone()]).get_packages(ws)?;
+            if pkgs.len() > 1 {
+                bail!("`{}` matches more than one package to add to", spec);
+            }
+            pkgs.pop().unwrap()
+        }
+        None => ws.current()?,
+    };
+    if opts.path.is_some() && opts.crates.len() > 1 {
+        bail!("`--path` can only be used when adding a single dependency");
+    }
+    let table = match opts.kind {
+        DepKind::Normal => "dependencies",
+        DepKind::Development => "dev-dependencies",
+        DepKind::Build => "build-dependencies",
+    };
+
+    let mut new_deps = Vec::new();
+    for krate in &opts.crates {
+        new_deps.push(new_dependency(pkg, krate, opts)?);
+    }
+    let manifest_path = pkg.manifest_path();
+    let original = paths::read(manifest_path)?;
+    let mut manifest = original.clone();
+    for dep in &new_deps {
+        manifest = add_to_table(&manifest, &[table], &dep.name, &dep.value)
+            .with_context(|| format!("failed to add `{}` to `{}`", dep.name, pkg.name()))?;
+        config.shell().status(
+            "Adding",
+            format!("{} to {} of {}", dep.description, table, pkg.name()),
+        )?;
+    }
+
+    let before = if opts.analyze {
+        Some(resolve(ws)?)
+    } else {
+        None
+    };
+    paths::write(manifest_path, &manifest)?;
+    let result = (|| -> CargoResult<()> {
+        let new_ws = Workspace::new(ws.root_manifest(), config)?;
+        if let Some((before, _)) = &before {
+            let (after, registry) = resolve(&new_ws)?;
+            analyze(
+                &new_ws,
+                pkg.package_id(),
+                &new_deps,
+                opts.kind,
+                before,
+                &after,
+                registry,
+            )?;
+        }
+        Ok(())
+    })();
+    if result.is_err() || opts.dry_run {
+        paths::write(manifest_path, &original)?;
+    }
+    result?;
+    if opts.dry_run {
+        config
+            .shell()
+            .warn("not writing the manifest because of `--dry-run`")?;
+    }
+    Ok(())
+}
+
+/// The dependency to add for the argument `krate`, like `serde@1.0`.
+fn new_dependency(pkg: &Package, krate: &str, opts: &AddOptions<'_>) -> CargoResult<NewDependency> {
+    let config = opts.config;
+    let (name, req) = match krate.split_once('@') {
+        Some((name, req)) => (name, Some(req)),
+        None => (krate, None),
+    };
+    if name.is_empty() || req == Some("") {
+        bail!(
+            "invalid dependency `{}`, expected `name` or `name@version`",
+            krate
+        );
+    }
+
+    let mut fields = Vec::new();
+    let description;
+    if let Some(path) = &opts.path {
+        let path = paths::normalize_path(&config.cwd().join(path));
+        let package_root = pkg.root();
+        let relative = relative_path(&path, package_root);
+        description = format!("{} ({})", name, path.display());
+        if let Some(req) = req {
+            fields.push(("version", quote(req)));
+        }
+        fields.push((
+            "path",
+            quote(&relative.to_string_lossy().replace('\\', "/")),
+        ));
+    } else {
+        let source_id = match &opts.registry {
+            Some(registry) => SourceId::alt_registry(config, registry)?,
+            None => SourceId::crates_io(config)?,
+        };
+        let req = match req {
+            Some(req) => {
+                Dependency::parse(name, Some(req), source_id)?;
+                req.to_string()
+            }
+            None => latest_version(config, name, source_id)?.to_string(),
+        };
+        description = format!("{} v{}", name, req.trim_start_matches('='));
+        fields.push(("version", quote(&req)));
+        if let Some(registry) = &opts.registry {
+            fields.push(("registry", quote(registry)));
+        }
+    }
+    if !opts.features.is_empty() {
+        let features: Vec<String> = opts.features.iter().map(|f| quote(f)).collect();
+        fields.push(("features", format!("[{}]", features.join(", "))));
+    }
+    if opts.optional {
+        fields.push(("optional", "true".to_string()));
+    }
+    if opts.no_default_features {
+        fields.push(("default-features", "false".to_string()));
+    }
+
+    let value = match fields.as_slice() {
+        [("version", req)] => req.clone(),
+        _ => {
+            let fields: Vec<String> = fields
+                .iter()
+                .map(|(key, value)| format!("{} = {}", key, value))
+                .collect();
+            format!("{{ {} }}", fields.join(", "))
+        }
+    };
+    Ok(NewDependency {
+        name: name.to_string(),
+        description,
+        value,
+    })
+}
+
+/// The latest version of the package `name` in the registry `source_id`,
+/// which is a pre-release only if all of them are.
+fn latest_version(config: &Config, name: &str, source_id: SourceId) -> CargoResult<Version> {
+    let dep = Dependency::parse(name, None, source_id)?;
+    let map = SourceConfigMap::new(config)?;
+    let mut source = map.load(source_id, &HashSet::new())?;
+    let _lock = config.acquire_package_cache_lock()?;
+    source.update()?;
+    let summaries = source.query_vec(&dep)?;
+    let versions = summaries.iter().map(|s| s.version());
+    let latest = versions
+        .clone()
+        .filter(|v| v.pre.is_empty())
+        .max()
+        .or_else(|| versions.max());
+    match latest {
+        Some(version) => Ok(version.clone()),
+        None => bail!("could not find `{}` in {}", name, source_id),
+    }
+}
+
+/// Adds `key = value` to the table with the key path `table` of `manifest`,
+/// creating the table at the end of the manifest if it doesn't exist.
+pub(super) fn add_to_table(
+    manifest: &str,
+    table: &[&str],
+    key: &str,
+    value: &str,
+) -> CargoResult<String> {
+    let new_line = format!("{} = {}\n", key, value);
+    let header_text = table
+        .iter()
+        .map(|k| bare_or_quoted_key(k))
+        .collect::<Vec<_>>()
+        .join(".");
+    let is_table = |header: &[String]| header.iter().map(String::as_str).eq(table.iter().cloned());
+    let mut lines: Vec<&str> = manifest.split_inclusive('\n').collect();
+    let mut current: Option<Vec<String>> = None;
+    // Where to insert the line: after the last key of the table.
+    let mut insert_at = None;
+    for (i, line) in lines.iter().enumerate() {
+        if let Some(header) = table_header(line) {
+            if header.len() == table.len() + 1
+                && is_table(&header[..table.len()])
+                && header[table.len()] == key
+            {
+                bail!("`{}` is already in `[{}]`", key, header_text);
+            }
+            current = Some(header);
+            continue;
+        }
+        if !matches!(&current, Some(header) if is_table(header)) {
+            continue;
+        }
+        if insert_at.is_none() {
+            // The table is empty so far.
+            insert_at = Some(i);
+        }
+        if let Some((k, _)) = split_key_value(line) {
+            if k == key || k.starts_with(&format!("{}.", key)) {
+                bail!("`{}` is already in `[{}]`", key, header_text);
+            }
+            insert_at = Some(i + 1);
+        }
+    }
+    let header_found = manifest
+        .split_inclusive('\n')
+        .filter_map(table_header)
+        .any(|header| is_table(&header));
+
+    if !header_found {
+        let mut out = manifest.to_string();
+        if !out.is_empty() && !out.ends_with('\n') {
+            out.push('\n');
+        }
+        if !out.is_empty() && !out.ends_with("\n\n") {
+            out.push('\n');
+        }
+        out.push_str(&format!("[{}]\n", header_text));
+        out.push_str(&new_line);
+        return Ok(out);
+    }
+    match insert_at {
+        Some(i) => {
+            // A table on the last line of a manifest without a final newline.
+            if i > 0 && !lines[i - 1].ends_with('\n') {
+                let mut out = lines.concat();
+                out.push('\n');
+                out.push_str(&new_line);
+                return Ok(out);
+            }
+            lines.insert(i, &new_line);
+            Ok(lines.concat())
+        }
+        None => {
+            // The header is the last line.
+            let mut out = manifest.to_string();
+            if !out.ends_with('\n') {
+                out.push('\n');
+            }
+            out.push_str(&new_line);
+            Ok(out)
+        }
+    }
+}
+
+/// `key` as a bare key of TOML if it can be one, or else quoted.
+fn bare_or_quoted_key(key: &str) -> String {
+    let bare = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if bare {
+        key.to_string()
+    } else {
+        quote(key)
+    }
+}
+
+/// Resolves the workspace from its lock file, without writing it.
+fn resolve<'cfg>(ws: &Workspace<'cfg>) -> CargoResult<(Resolve, PackageRegistry<'cfg>)> {
+    let mut registry = PackageRegistry::new(ws.config())?;
+    let previous = ops::load_pkg_lockfile(ws)?;
+    let resolve = ops::resolve_with_previous(
+        &mut registry,
+        ws,
+        &CliFeatures::new_all(true),
+        HasDevUnits::Yes,
+        previous.as_ref(),
+        None,
+        &[],
+        true,
+    )?;
+    Ok((resolve, registry))
+}
+
+/// Prints what the dependencies `new_deps` of `member` weigh.
+fn analyze<'cfg>(
+    ws: &Workspace<'cfg>,
+    member: PackageId,
+    new_deps: &[NewDependency],
+    kind: DepKind,
+    before: &Resolve,
+    after: &Resolve,
+    registry: PackageRegistry<'cfg>,
+) -> CargoResult<()> {
+    let config = ws.config();
+    let known: HashSet<PackageId> = before.iter().collect();
+    let pkg_set = ops::get_resolved_packages(after, registry)?;
+    let build_times = build_times(ws);
+
+    for new_dep in new_deps {
+        let dep_id = after
+            .deps(member)
+            .find(|(_, deps)| {
+                deps.iter()
+                    .any(|dep| dep.name_in_toml() == new_dep.name.as_str() && dep.kind() == kind)
+            })
+            .map(|(id, _)| id);
+        let dep_id = match dep_id {
+            Some(id) => id,
+            None => bail!("`{}` was not resolved", new_dep.name),
+        };
+
+        let mut closure = vec![dep_id];
+        let mut seen: HashSet<PackageId> = closure.iter().cloned().collect();
+        let mut i = 0;
+        while i < closure.len() {
+            for (id, _) in after.deps(closure[i]) {
+                if id != member && seen.insert(id) {
+                    closure.push(id);
+                }
+            }
+            i += 1;
+        }
+        closure.sort();
+        let new: Vec<PackageId> = closure
+            .iter()
+            .filter(|id| !known.contains(id))
+            .cloned()
+            .collect();
+
+        drop_println!(
+            config,
+            "{} v{}: {} packages, {} new to the workspace",
+            dep_id.name(),
+            dep_id.version(),
+            closure.len(),
+            new.len()
+        );
+        if !new.is_empty() {
+            let ids: Vec<String> = new
+                .iter()
+                .map(|id| format!("{} v{}", id.name(), id.version()))
+                .collect();
+            drop_println!(config, "  New packages: {}", ids.join(", "));
+        }
+
+        let mut duplicates = Vec::new();
+        for id in &new {
+            let mut versions: Vec<_> = after.iter().filter(|o| o.name() == id.name()).collect();
+            if versions.len() > 1 {
+                versions.sort();
+                let versions: Vec<String> = versions
+                    .iter()
+                    .map(|v| format!("v{}", v.version()))
+                    .collect();
+                duplicates.push(format!("{} {}", id.name(), versions.join(" and ")));
+            }
+        }
+        duplicates.dedup();
+        if !duplicates.is_empty() {
+            drop_println!(config, "  Duplicate versions: {}", duplicates.join(", "));
+        }
+
+        let mut licenses: BTreeMap<String, usize> = BTreeMap::new();
+        for pkg in pkg_set.get_many(closure.iter().cloned())? {
+            let metadata = pkg.manifest().metadata();
+            let license = match (&metadata.license, &metadata.license_file) {
+                (Some(license), _) => license.clone(),
+                (None, Some(file)) => format!("see {}", file),
+                (None, None) => "no license".to_string(),
+            };
+            *licenses.entry(license).or_default() += 1;
+        }
+        let license = pkg_set
+            .get_one(dep_id)?
+            .manifest()
+            .metadata()
+            .license
+            .clone();
+        drop_println!(
+            config,
+            "  License: {}",
+            license.as_deref().unwrap_or("none declared")
+        );
+        if closure.len() > 1 {
+            let licenses: Vec<String> = licenses
+                .iter()
+                .map(|(license, count)| format!("{} ({})", license, count))
+                .collect();
+            drop_println!(config, "  All licenses: {}", licenses.join(", "));
+        }
+
+        let measured: Vec<f64> = new
+            .iter()
+            .filter_map(|id| build_times.get(id).cloned())
+            .collect();
+        if new.is_empty() {
+            drop_println!(config, "  Build time: none, every package is already built");
+        } else if measured.is_empty() {
+            drop_println!(
+                config,
+                "  Build time: unknown, none of the new packages was built with `-Z build-stats`"
+            );
+        } else {
+            drop_println!(
+                config,
+                "  Build time: about {:.1}s, measured for {} of {} new packages",
+                measured.iter().sum::<f64>(),
+                measured.len(),
+                new.len()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// How long each package took to build in the last build which compiled it,
+/// from the statistics recorded with `-Z build-stats`.
+fn build_times(ws: &Workspace<'_>) -> HashMap<PackageId, f64> {
+    let file = build_stats::stats_file(ws.target_dir().as_path_unlocked());
+    // The statistics are only a hint, like for the job queue.
+    let builds = build_stats::load(&file).unwrap_or_default();
+    let mut units = HashMap::new();
+    for unit in builds.iter().flat_map(|build| &build.units) {
+        if !unit.fresh {
+            units.insert(unit.key(), unit.duration);
+        }
+    }
+    let mut times = HashMap::new();
+    for ((package, ..), duration) in units {
+        *times.entry(package).or_default() += duration;
+    }
+    times
+}
+
+/// `path` relative to the directory `base`, both absolute.
+pub(super) fn relative_path(path: &Path, base: &Path) -> PathBuf {
+    let path: Vec<Component<'_>> = path.components().collect();
+    let base: Vec<Component<'_>> = base.components().collect();
+    let common = path.iter().zip(&base).take_while(|(a, b)| a == b).count();
+    if common == 0 {
+        return path.iter().collect();
+    }
+    let mut relative = PathBuf::new();
+    for _ in common..base.len() {
+        relative.push("..");
+    }
+    relative.extend(&path[common..]);
+    if relative.as_os_str().is_empty() {
+        relative.push(".");
+    }
+    relative
+}
+
+pub(super) fn quote(s: &str) -> String {
+    toml::Value::String(s.to_string()).to_string()
+}
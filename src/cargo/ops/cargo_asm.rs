@@ -0,0 +1,264 @@
+//! Implementation of `cargo asm`, which prints the assembly, LLVM-IR or MIR
+//! of a target, or of a single function of it.
+//!
+//! The target is built like with `cargo build`, except that rustc also emits
+//! the requested output with a single codegen unit, so that all of the
+//! functions are in one file. The flags are part of the fingerprint of the
+//! target, and the file is kept in `target/asm`, so that it is only emitted
+//! again when the target is rebuilt.
+
+use std::sync::Arc;
+
+use anyhow::bail;
+use cargo_util::paths;
+
+use crate::core::compiler::{Context, DefaultExecutor, Executor, UnitInterner};
+use crate::core::Workspace;
+use crate::drop_print;
+use crate::ops::{self, CompileOptions};
+use crate::util::{short_hash, CargoResult};
+
+/// What `cargo asm` prints.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AsmEmit {
+    Asm,
+    LlvmIr,
+    Mir,
+}
+
+impl AsmEmit {
+    /// The name of the output for `--emit`.
+    fn emit(self) -> &'static str {
+        match self {
+            AsmEmit::Asm => "asm",
+            AsmEmit::LlvmIr => "llvm-ir",
+            AsmEmit::Mir => "mir",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            AsmEmit::Asm => "s",
+            AsmEmit::LlvmIr => "ll",
+            AsmEmit::Mir => "mir",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            AsmEmit::Asm => "assembly",
+            AsmEmit::LlvmIr => "LLVM-IR",
+            AsmEmit::Mir => "MIR",
+        }
+    }
+}
+
+pub struct AsmOptions {
+    pub emit: AsmEmit,
+    /// The function to print, by its name or the end of its path, or none
+    /// for the whole output.
+    pub function: Option<String>,
+}
+
+/// Builds the single target selected by `options`, and prints its output for
+/// `opts.emit`.
+pub fn asm(ws: &Workspace<'_>, options: &mut CompileOptions, opts: &AsmOptions) -> CargoResult<()> {
+    let config = ws.config();
+    ws.emit_warnings()?;
+    let exec: Arc<dyn Executor> = Arc::new(DefaultExecutor);
+    let dir = ws.target_dir().join("asm").into_path_unlocked();
+
+    // A fresh target whose output was removed is built once more.
+    let mut output = None;
+    for force_rebuild in [false, true] {
+        options.build_config.force_rebuild |= force_rebuild;
+        let interner = UnitInterner::new();
+        let mut bcx = ops::create_bcx(ws, options, &interner)?;
+        let unit = match bcx.roots.as_slice() {
+            [unit] => unit.clone(),
+            [] => bail!("no target selected for `cargo asm`"),
+            _ => bail!(
+                "`cargo asm` prints the {} of a single target, \
+                 select one with `--lib`, `--bin`, `--example`, `--test` or `--bench`",
+                opts.emit.description()
+            ),
+        };
+        let key = short_hash(&(
+            unit.pkg.package_id(),
+            &unit.target,
+            &unit.profile,
+            unit.kind,
+            unit.mode,
+        ));
+        let path = dir.join(format!(
+            "{}-{}.{}",
+            unit.target.crate_name(),
+            key,
+            opts.emit.extension()
+        ));
+        paths::create_dir_all(&dir)?;
+        bcx.extra_compiler_args
+            .entry(unit.clone())
+            .or_default()
+            .extend([
+                format!("--emit={}={}", opts.emit.emit(), path.display()),
+                "-Ccodegen-units=1".to_string(),
+            ]);
+        Context::new(&bcx)?.compile(&exec)?;
+        if path.exists() {
+            output = Some((unit, path));
+            break;
+        }
+    }
+    let (unit, path) = match output {
+        Some(output) => output,
+        None => bail!("rustc did not emit the {}", opts.emit.description()),
+    };
+
+    let text = paths::read(&path)?;
+    let function = match &opts.function {
+        Some(function) => function,
+        None => {
+            drop_print!(config, "{}", text);
+            return Ok(());
+        }
+    };
+    let blocks = find_function(opts.emit, &text, function);
+    if blocks.is_empty() {
+        bail!(
+            "no function matching `{}` in the {} of {} `{}`\n\
+             Functions that are inlined, generic or unused have no code of their own, \
+             mark the function `#[inline(never)]` and make it public to keep it.",
+            function,
+            opts.emit.description(),
+            unit.target.kind().description(),
+            unit.target.name()
+        );
+    }
+    for block in blocks {
+        drop_print!(config, "{}", block);
+    }
+    Ok(())
+}
+
+/// The blocks of `text` with the code of the functions matching `function`.
+fn find_function(emit: AsmEmit, text: &str, function: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut current: Option<String> = None;
+    for line in text.lines() {
+        if let Some(block) = current.as_mut() {
+            block.push_str(line);
+            block.push('\n');
+            if is_end(emit, line) {
+                blocks.extend(current.take());
+            }
+        } else if let Some(name) = function_name(emit, line) {
+            if matches_path(&name, function) {
+                current = Some(format!("{}\n", line));
+            }
+        }
+    }
+    blocks.extend(current);
+    blocks
+}
+
+/// The name of the function starting at `line`, if any.
+fn function_name(emit: AsmEmit, line: &str) -> Option<String> {
+    match emit {
+        AsmEmit::Asm => {
+            // A label at the start of the line, with the mangled symbol.
+            let label = line.strip_suffix(':')?;
+            if label.starts_with('.') || label.contains(char::is_whitespace) {
+                return None;
+            }
+            Some(demangle(label.trim_matches('"')))
+        }
+        AsmEmit::LlvmIr => {
+            let rest = line.strip_prefix("define ")?;
+            let symbol = &rest[rest.find('@')? + 1..];
+            let symbol = &symbol[..symbol.find('(')?];
+            Some(demangle(symbol.trim_matches('"')))
+        }
+        AsmEmit::Mir => {
+            let rest = line.strip_prefix("fn ")?;
+            Some(rest[..rest.find('(')?].to_string())
+        }
+    }
+}
+
+fn is_end(emit: AsmEmit, line: &str) -> bool {
+    match emit {
+        AsmEmit::Asm => {
+            let line = line.trim();
+            line.starts_with(".Lfunc_end")
+                || line.starts_with("Lfunc_end")
+                || line == ".cfi_endproc"
+        }
+        AsmEmit::LlvmIr | AsmEmit::Mir => line == "}",
+    }
+}
+
+/// Whether the path `name` is `function`, or ends with it.
+fn matches_path(name: &str, function: &str) -> bool {
+    name == function
+        || name
+            .strip_suffix(function)
+            .map_or(false, |prefix| prefix.ends_with("::"))
+}
+
+/// The path of a symbol mangled with the legacy scheme, like
+/// `_ZN3foo3add17h0123456789abcdefE` for `foo::add`. Other symbols are
+/// returned as they are.
+fn demangle(symbol: &str) -> String {
+    let mut rest = match symbol
+        .strip_prefix("_ZN")
+        .or_else(|| symbol.strip_prefix("__ZN"))
+        .and_then(|rest| rest.strip_suffix('E'))
+    {
+        Some(rest) => rest,
+        None => return symbol.to_string(),
+    };
+    let mut segments = Vec::new();
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(0);
+        let len = match rest[..digits].parse::<usize>() {
+            Ok(len) if digits + len <= rest.len() => len,
+            _ => return symbol.to_string(),
+        };
+        segments.push(&rest[digits..digits + len]);
+        rest = &rest[digits + len..];
+    }
+    // The last segment is the hash of the symbol.
+    if let Some(hash) = segments.last() {
+        if hash.len() == 17 && hash.starts_with('h') {
+            segments.pop();
+        }
+    }
+    // Segments starting with an escape are prefixed with `_`.
+    let mut path = segments
+        .iter()
+        .map(|s| s.strip_prefix("_$").map_or(*s, |_| &s[1..]))
+        .collect::<Vec<_>>()
+        .join("::");
+    for (escape, c) in [
+        ("$SP$", "@"),
+        ("$BP$", "*"),
+        ("$RF$", "&"),
+        ("$LT$", "<"),
+        ("$GT$", ">"),
+        ("$LP$", "("),
+        ("$RP$", ")"),
+        ("$C$", ","),
+        ("$u20$", " "),
+        ("$u27$", "'"),
+        ("$u5b$", "["),
+        ("$u5d$", "]"),
+        ("$u7b$", "{"),
+        ("$u7d$", "}"),
+        ("$u7e$", "~"),
+        ("..", "::"),
+    ] {
+        path = path.replace(escape, c);
+    }
+    path
+}
@@ -0,0 +1,187 @@
+//! Implementation of `cargo audits`, which manages the audit store of a
+//! workspace, see `core::audits`.
+
+use anyhow::{bail, Context as _};
+use semver::Version;
+
+use crate::core::audits::{
+    Audit, AuditStatus, AuditStore, AuditsFile, Exemption, ImportedAudits, AUDITS_FILE,
+};
+use crate::core::{PackageId, Workspace};
+use crate::drop_println;
+use crate::ops;
+use crate::util::errors::CargoResult;
+
+pub struct CertifyOptions {
+    pub name: String,
+    pub version: String,
+    /// The audited version the changes are reviewed from.
+    pub delta: Option<String>,
+    pub who: Option<String>,
+    pub notes: Option<String>,
+}
+
+/// Exempts every dependency of the lock file which isn't audited, so that
+/// only the versions added afterwards need an audit.
+pub fn audits_init(ws: &Workspace<'_>) -> CargoResult<()> {
+    let mut store = AuditStore::load(ws)?;
+    let mut exempted = 0;
+    for id in dependencies(ws)? {
+        if let AuditStatus::Unaudited(_) = store.status(id) {
+            store.audits.exemptions.push(Exemption {
+                name: id.name().to_string(),
+                version: id.version().to_string(),
+                reason: None,
+            });
+            exempted += 1;
+        }
+    }
+    store.save()?;
+    ws.config().shell().status(
+        "Exempted",
+        format!(
+            "{} unaudited dependenc{} in `{}`",
+            exempted,
+            if exempted == 1 { "y" } else { "ies" },
+            AUDITS_FILE
+        ),
+    )
+}
+
+/// Records the audit of a version, which replaces its exemption.
+pub fn audits_certify(ws: &Workspace<'_>, opts: &CertifyOptions) -> CargoResult<()> {
+    let version = parse_version(&opts.version)?;
+    let mut store = AuditStore::load(ws)?;
+    let audit = Audit {
+        name: opts.name.clone(),
+        version: match &opts.delta {
+            Some(_) => None,
+            None => Some(version.to_string()),
+        },
+        delta: match &opts.delta {
+            Some(from) => Some(format!("{} -> {}", parse_version(from)?, version)),
+            None => None,
+        },
+        who: opts.who.clone(),
+        notes: opts.notes.clone(),
+    };
+    if !store.audits.audits.contains(&audit) {
+        store.audits.audits.push(audit);
+    }
+    let exempts = |e: &Exemption| {
+        e.name == opts.name && parse_version(&e.version).ok().as_ref() == Some(&version)
+    };
+    store.audits.exemptions.retain(|e| !exempts(e));
+    store.save()?;
+    ws.config()
+        .shell()
+        .status("Certified", format!("{} v{}", opts.name, version))
+}
+
+/// Records that a version doesn't need an audit.
+pub fn audits_exempt(
+    ws: &Workspace<'_>,
+    name: &str,
+    version: &str,
+    reason: Option<String>,
+) -> CargoResult<()> {
+    let version = parse_version(version)?;
+    let mut store = AuditStore::load(ws)?;
+    let exemption = Exemption {
+        name: name.to_string(),
+        version: version.to_string(),
+        reason,
+    };
+    if !store.audits.exemptions.contains(&exemption) {
+        store.audits.exemptions.push(exemption);
+    }
+    store.save()?;
+    ws.config()
+        .shell()
+        .status("Exempted", format!("{} v{}", name, version))
+}
+
+/// Adds an audit feed to import, and fetches it.
+pub fn audits_import(ws: &Workspace<'_>, name: &str, url: &str) -> CargoResult<()> {
+    let mut store = AuditStore::load(ws)?;
+    store
+        .audits
+        .imports
+        .insert(name.to_string(), url.to_string());
+    store.save()?;
+    audits_fetch(ws)
+}
+
+/// Fetches the audits of the imported feeds into `imports.lock`.
+pub fn audits_fetch(ws: &Workspace<'_>) -> CargoResult<()> {
+    let config = ws.config();
+    let mut store = AuditStore::load(ws)?;
+    store.imports.imports.clear();
+    for (name, url) in &store.audits.imports {
+        config
+            .shell()
+            .status("Fetching", format!("audits of `{}`", name))?;
+        let feed = ops::http_get(config, url)?;
+        let feed: AuditsFile = toml::from_slice(&feed).map_err(|e| {
+            anyhow::format_err!(
+                "failed to parse the audits of `{}` from `{}`: {}",
+                name,
+                url,
+                e
+            )
+        })?;
+        store.imports.imports.insert(
+            name.clone(),
+            ImportedAudits {
+                url: url.clone(),
+                audits: feed.audits,
+            },
+        );
+    }
+    store.save()
+}
+
+/// Prints whether each dependency of the lock file is audited, and fails if
+/// one isn't.
+pub fn audits_check(ws: &Workspace<'_>) -> CargoResult<()> {
+    let config = ws.config();
+    let store = AuditStore::load(ws)?;
+    let mut unaudited = 0;
+    for id in dependencies(ws)? {
+        let status = match store.status(id) {
+            AuditStatus::Audited(None) => "audited".to_string(),
+            AuditStatus::Audited(Some(import)) => format!("audited by `{}`", import),
+            AuditStatus::Exempted => "exempted".to_string(),
+            AuditStatus::Unaudited(_) => {
+                unaudited += 1;
+                "not audited".to_string()
+            }
+        };
+        drop_println!(config, "{}: {}", id, status);
+    }
+    if unaudited > 0 {
+        bail!(
+            "{} dependenc{} not audited in `{}`",
+            unaudited,
+            if unaudited == 1 { "y is" } else { "ies are" },
+            AUDITS_FILE
+        );
+    }
+    Ok(())
+}
+
+/// The dependencies of the workspace which can be audited, without the path
+/// dependencies.
+fn dependencies(ws: &Workspace<'_>) -> CargoResult<Vec<PackageId>> {
+    let (_, resolve) = ops::resolve_ws(ws)?;
+    let mut ids: Vec<PackageId> = resolve
+        .iter()
+        .filter(|id| !id.source_id().is_path())
+        .collect();
+    ids.sort();
+    Ok(ids)
+}
+
+fn parse_version(version: &str) -> CargoResult<Version> {
+    Version::parse(version).with_context(|| format!("invalid version `{}`", version))
+}
@@ -0,0 +1,598 @@
+//! Implementation of `cargo cache`, which inspects and cleans up the caches
+//! of `$CARGO_HOME`.
+//!
+//! The caches are the registry indexes, the downloaded `.crate` files and
+//! their extracted sources under `registry`, and the git databases and their
+//! checkouts under `git`. The last use of an extracted source or a checkout
+//! is the modification time of its `.cargo-ok` file, which Cargo refreshes
+//! when it uses them. A `.crate` file is last used with its extracted source,
+//! and a git database with its checkouts.
+//!
+//! With `-Z gc`, the cleanup configured in `[gc.auto]` also runs after the
+//! commands, at most once per its frequency.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{bail, Context as _};
+use bytesize::ByteSize;
+use cargo_util::{paths, Sha256};
+use serde::{Deserialize, Serialize};
+
+use crate::core::{PackageId, Source, Workspace};
+use crate::drop_println;
+use crate::ops;
+use crate::sources::registry::short_name;
+use crate::sources::SourceConfigMap;
+use crate::util::errors::CargoResult;
+use crate::util::Config;
+
+pub struct CachePruneOptions {
+    /// Removes the entries downloaded or extracted longer ago than this.
+    pub max_age: Option<Duration>,
+    /// Removes the entries which weren't used for longer than this.
+    pub unused_for: Option<Duration>,
+    /// Removes the least recently used entries until the rest fits in this
+    /// many bytes.
+    pub max_size: Option<u64>,
+    pub dry_run: bool,
+    pub json: bool,
+}
+
+#[derive(Serialize)]
+struct CacheInfo {
+    registries: Vec<RegistryInfo>,
+    git: Vec<GitInfo>,
+    total: u64,
+}
+
+#[derive(Serialize)]
+struct RegistryInfo {
+    name: String,
+    index: u64,
+    cache: u64,
+    crates: usize,
+    src: u64,
+    sources: usize,
+}
+
+#[derive(Serialize)]
+struct GitInfo {
+    name: String,
+    db: u64,
+    checkouts: u64,
+    revisions: usize,
+}
+
+/// Prints the size of each registry and git repository in the caches.
+pub fn cache_info(config: &Config, json: bool) -> CargoResult<()> {
+    let index = config.registry_index_path().into_path_unlocked();
+    let cache = config.registry_cache_path().into_path_unlocked();
+    let src = config.registry_source_path().into_path_unlocked();
+    let git = config.git_path().into_path_unlocked();
+
+    let mut names = dir_names(&index);
+    names.extend(dir_names(&cache));
+    names.extend(dir_names(&src));
+    names.sort();
+    names.dedup();
+    let registries: Vec<RegistryInfo> = names
+        .into_iter()
+        .map(|name| RegistryInfo {
+            index: dir_size(&index.join(&name)),
+            cache: dir_size(&cache.join(&name)),
+            crates: dir_names(&cache.join(&name)).len(),
+            src: dir_size(&src.join(&name)),
+            sources: dir_names(&src.join(&name)).len(),
+            name,
+        })
+        .collect();
+
+    let mut names = dir_names(&git.join("db"));
+    names.extend(dir_names(&git.join("checkouts")));
+    names.sort();
+    names.dedup();
+    let git: Vec<GitInfo> = names
+        .into_iter()
+        .map(|name| GitInfo {
+            db: dir_size(&git.join("db").join(&name)),
+            checkouts: dir_size(&git.join("checkouts").join(&name)),
+            revisions: dir_names(&git.join("checkouts").join(&name)).len(),
+            name,
+        })
+        .collect();
+
+    let total = registries
+        .iter()
+        .map(|r| r.index + r.cache + r.src)
+        .chain(git.iter().map(|g| g.db + g.checkouts))
+        .sum();
+    let info = CacheInfo {
+        registries,
+        git,
+        total,
+    };
+    if json {
+        drop_println!(config, "{}", serde_json::to_string(&info)?);
+        return Ok(());
+    }
+    for r in &info.registries {
+        drop_println!(
+            config,
+            "registry `{}`: {}",
+            r.name,
+            ByteSize(r.index + r.cache + r.src)
+        );
+        drop_println!(config, "    index: {}", ByteSize(r.index));
+        drop_println!(
+            config,
+            "    cache: {} in {} crates",
+            ByteSize(r.cache),
+            r.crates
+        );
+        drop_println!(
+            config,
+            "    src: {} in {} crates",
+            ByteSize(r.src),
+            r.sources
+        );
+    }
+    for g in &info.git {
+        drop_println!(config, "git `{}`: {}", g.name, ByteSize(g.db + g.checkouts));
+        drop_println!(config, "    db: {}", ByteSize(g.db));
+        drop_println!(
+            config,
+            "    checkouts: {} in {} revisions",
+            ByteSize(g.checkouts),
+            g.revisions
+        );
+    }
+    drop_println!(config, "total: {}", ByteSize(info.total));
+    Ok(())
+}
+
+/// An entry of the caches which can be removed on its own.
+#[derive(Serialize)]
+struct Entry {
+    kind: &'static str,
+    path: PathBuf,
+    bytes: u64,
+    #[serde(skip)]
+    created: SystemTime,
+    #[serde(skip)]
+    last_used: SystemTime,
+}
+
+#[derive(Serialize)]
+struct PruneResult {
+    removed: Vec<Entry>,
+    bytes: u64,
+    dry_run: bool,
+}
+
+/// Removes the `.crate` files, extracted sources, git databases and
+/// checkouts which are older, or weren't used for longer, than the options
+/// allow, then the least recently used ones until the rest fits in the
+/// maximum size. The registry indexes are kept.
+pub fn cache_prune(config: &Config, opts: &CachePruneOptions) -> CargoResult<()> {
+    if opts.max_age.is_none() && opts.unused_for.is_none() && opts.max_size.is_none() {
+        bail!(
+            "nothing to prune, pass `--max-age`, `--unused-for` or `--max-size` \
+             to choose what to remove"
+        );
+    }
+    let result = prune(config, opts)?;
+    if opts.json {
+        drop_println!(config, "{}", serde_json::to_string(&result)?);
+    } else if opts.dry_run {
+        for entry in &result.removed {
+            drop_println!(config, "{}", entry.path.display());
+        }
+    }
+    let summary = format!(
+        "{} entries, {} total",
+        result.removed.len(),
+        ByteSize(result.bytes)
+    );
+    if opts.dry_run {
+        config.shell().status("Summary", summary)?;
+        config.shell().warn("no files deleted due to --dry-run")?;
+    } else {
+        config.shell().status("Removed", summary)?;
+    }
+    Ok(())
+}
+
+fn prune(config: &Config, opts: &CachePruneOptions) -> CargoResult<PruneResult> {
+    let _lock = config.acquire_package_cache_lock()?;
+    let now = SystemTime::now();
+    let expired = |entry: &Entry| {
+        let older = |time: SystemTime, limit: Option<Duration>| match limit {
+            Some(limit) => now.duration_since(time).map_or(false, |age| age > limit),
+            None => false,
+        };
+        older(entry.created, opts.max_age) || older(entry.last_used, opts.unused_for)
+    };
+    let (mut removed, mut kept): (Vec<Entry>, Vec<Entry>) = entries(config)
+        .into_iter()
+        .partition(|entry| expired(entry));
+    if let Some(max_size) = opts.max_size {
+        kept.sort_by_key(|entry| entry.last_used);
+        let mut size: u64 = kept.iter().map(|entry| entry.bytes).sum();
+        for entry in kept {
+            if size <= max_size {
+                break;
+            }
+            size -= entry.bytes;
+            removed.push(entry);
+        }
+    }
+
+    if !opts.dry_run {
+        for entry in &removed {
+            config
+                .shell()
+                .verbose(|s| s.status("Removing", entry.path.display()))?;
+            if entry.path.is_dir() {
+                paths::remove_dir_all(&entry.path)?;
+            } else {
+                paths::remove_file(&entry.path)?;
+            }
+        }
+    }
+    Ok(PruneResult {
+        bytes: removed.iter().map(|entry| entry.bytes).sum(),
+        removed,
+        dry_run: opts.dry_run,
+    })
+}
+
+/// The `[gc.auto]` table, which configures the cleanups of `-Z gc`.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+struct GcAutoConfig {
+    /// How often the cleanup runs: `always`, `never`, or a duration.
+    frequency: Option<String>,
+    max_unused: Option<String>,
+    max_size: Option<String>,
+}
+
+const DEFAULT_GC_FREQUENCY: &str = "1 day";
+const DEFAULT_GC_MAX_UNUSED: &str = "3 months";
+
+/// The file of the Cargo home whose modification time is the last cleanup.
+const LAST_GC: &str = ".last-gc";
+
+/// The cleanup configured in `[gc.auto]`, and how often it runs, or `None`
+/// if it never runs automatically.
+fn gc_policy(config: &Config) -> CargoResult<(Option<Duration>, CachePruneOptions)> {
+    let gc = config
+        .get::<Option<GcAutoConfig>>("gc.auto")?
+        .unwrap_or_default();
+    let duration = |key: &str, value: &str| {
+        humantime::parse_duration(value).with_context(|| {
+            format!(
+                "`gc.auto.{}` must be a duration like `30 days`, found `{}`",
+                key, value
+            )
+        })
+    };
+    let frequency = match gc.frequency.as_deref().unwrap_or(DEFAULT_GC_FREQUENCY) {
+        "never" => None,
+        "always" => Some(Duration::from_secs(0)),
+        frequency => Some(duration("frequency", frequency)?),
+    };
+    let max_unused = gc.max_unused.as_deref().unwrap_or(DEFAULT_GC_MAX_UNUSED);
+    let max_size = match gc.max_size {
+        Some(size) => Some(
+            size.parse::<ByteSize>()
+                .map_err(|_| {
+                    anyhow::format_err!(
+                        "`gc.auto.max-size` must be a number of bytes like `5GB`, found `{}`",
+                        size
+                    )
+                })?
+                .0,
+        ),
+        None => None,
+    };
+    let opts = CachePruneOptions {
+        max_age: None,
+        unused_for: Some(duration("max-unused", max_unused)?),
+        max_size,
+        dry_run: false,
+        json: false,
+    };
+    Ok((frequency, opts))
+}
+
+/// Runs the cleanup configured in `[gc.auto]` now, whatever its frequency.
+pub fn cache_gc(config: &Config, dry_run: bool, json: bool) -> CargoResult<()> {
+    let (_, opts) = gc_policy(config)?;
+    cache_prune(
+        config,
+        &CachePruneOptions {
+            dry_run,
+            json,
+            ..opts
+        },
+    )?;
+    if !dry_run {
+        paths::write(&config.home().as_path_unlocked().join(LAST_GC), "")?;
+    }
+    Ok(())
+}
+
+/// Runs the cleanup configured in `[gc.auto]` after a command, with
+/// `-Z gc`, if it didn't run for its frequency. It is skipped offline, when
+/// what it removes couldn't be downloaded again, and its failures are only
+/// warnings.
+pub fn auto_gc(config: &Config) {
+    if !config.cli_unstable().gc || config.offline() {
+        return;
+    }
+    if let Err(e) = try_auto_gc(config) {
+        crate::display_warning_with_error(
+            "failed to clean up the caches of the Cargo home",
+            &e,
+            &mut config.shell(),
+        );
+    }
+}
+
+fn try_auto_gc(config: &Config) -> CargoResult<()> {
+    let home = config.home().as_path_unlocked();
+    if !home.exists() {
+        return Ok(());
+    }
+    let (frequency, opts) = gc_policy(config)?;
+    let frequency = match frequency {
+        Some(frequency) => frequency,
+        None => return Ok(()),
+    };
+    let last_gc = home.join(LAST_GC);
+    if let Ok(last) = fs::metadata(&last_gc).and_then(|m| m.modified()) {
+        // A last cleanup in the future is treated as recent.
+        if SystemTime::now()
+            .duration_since(last)
+            .map_or(true, |since| since < frequency)
+        {
+            return Ok(());
+        }
+    }
+    let result = prune(config, &opts)?;
+    paths::write(&last_gc, "")?;
+    if !result.removed.is_empty() {
+        config.shell().verbose(|s| {
+            s.status(
+                "Cleaned",
+                format!(
+                    "{} entries, {} total from the Cargo home",
+                    result.removed.len(),
+                    ByteSize(result.bytes)
+                ),
+            )
+        })?;
+    }
+    Ok(())
+}
+
+/// The entries of the caches which can be pruned.
+fn entries(config: &Config) -> Vec<Entry> {
+    let cache = config.registry_cache_path().into_path_unlocked();
+    let src = config.registry_source_path().into_path_unlocked();
+    let git = config.git_path().into_path_unlocked();
+    let mut entries = Vec::new();
+
+    for registry in dir_names(&cache) {
+        for name in dir_names(&cache.join(&registry)) {
+            let path = cache.join(&registry).join(&name);
+            let created = mtime(&path);
+            let extracted = src
+                .join(&registry)
+                .join(name.trim_end_matches(".crate"))
+                .join(".cargo-ok");
+            let last_used = fs::metadata(&extracted)
+                .and_then(|m| m.modified())
+                .unwrap_or(created);
+            entries.push(Entry {
+                kind: "crate",
+                bytes: dir_size(&path),
+                path,
+                created,
+                last_used,
+            });
+        }
+    }
+    for registry in dir_names(&src) {
+        for name in dir_names(&src.join(&registry)) {
+            entries.push(checkout_entry("src", src.join(&registry).join(name)));
+        }
+    }
+    for name in dir_names(&git.join("checkouts")) {
+        for rev in dir_names(&git.join("checkouts").join(&name)) {
+            entries.push(checkout_entry(
+                "checkout",
+                git.join("checkouts").join(&name).join(rev),
+            ));
+        }
+    }
+    for name in dir_names(&git.join("db")) {
+        let path = git.join("db").join(&name);
+        let created = mtime(&path);
+        // A database is used whenever one of its checkouts is.
+        let last_used = dir_names(&git.join("checkouts").join(&name))
+            .into_iter()
+            .map(|rev| last_used(&git.join("checkouts").join(&name).join(rev)))
+            .max()
+            .unwrap_or(created);
+        entries.push(Entry {
+            kind: "db",
+            bytes: dir_size(&path),
+            path,
+            created,
+            last_used,
+        });
+    }
+    entries
+}
+
+/// An extracted source, or a git checkout, which are marked with `.cargo-ok`.
+fn checkout_entry(kind: &'static str, path: PathBuf) -> Entry {
+    Entry {
+        kind,
+        bytes: dir_size(&path),
+        created: mtime(&path),
+        last_used: last_used(&path),
+        path,
+    }
+}
+
+fn last_used(dir: &Path) -> SystemTime {
+    fs::metadata(dir.join(".cargo-ok"))
+        .and_then(|m| m.modified())
+        .unwrap_or_else(|_| mtime(dir))
+}
+
+#[derive(Serialize)]
+struct VerifiedCrate {
+    package_id: PackageId,
+    path: PathBuf,
+    status: &'static str,
+}
+
+/// Checks the cached `.crate` files of the dependencies in the lock file
+/// against its checksums.
+pub fn cache_verify(ws: &Workspace<'_>, json: bool) -> CargoResult<()> {
+    let config = ws.config();
+    let resolve = match ops::load_pkg_lockfile(ws)? {
+        Some(resolve) => resolve,
+        None => bail!("no `Cargo.lock` found, generate one with `cargo generate-lockfile`"),
+    };
+    let cache = config.registry_cache_path().into_path_unlocked();
+    let mut ids: Vec<(PackageId, &String)> = resolve
+        .checksums()
+        .iter()
+        .filter(|(id, _)| id.source_id().is_registry())
+        .filter_map(|(id, checksum)| Some((*id, checksum.as_ref()?)))
+        .collect();
+    ids.sort();
+
+    // The `.crate` files are cached under the registry replacing the one of
+    // the lock file, if any.
+    let sources = SourceConfigMap::new(config)?;
+    let mut crates = Vec::new();
+    for (id, checksum) in ids {
+        let source_id = sources
+            .load(id.source_id(), &HashSet::new())?
+            .replaced_source_id();
+        let path =
+            cache
+                .join(short_name(source_id))
+                .join(format!("{}-{}.crate", id.name(), id.version()));
+        let status = if !path.exists() {
+            "missing"
+        } else {
+            let actual = Sha256::new()
+                .update_path(&path)
+                .with_context(|| format!("failed to verify `{}`", path.display()))?
+                .finish_hex();
+            if actual == *checksum {
+                "ok"
+            } else {
+                "mismatch"
+            }
+        };
+        crates.push(VerifiedCrate {
+            package_id: id,
+            path,
+            status,
+        });
+    }
+
+    if json {
+        #[derive(Serialize)]
+        struct Verified<'a> {
+            crates: &'a [VerifiedCrate],
+        }
+        drop_println!(
+            config,
+            "{}",
+            serde_json::to_string(&Verified { crates: &crates })?
+        );
+    }
+    let count = |status| crates.iter().filter(|c| c.status == status).count();
+    let mismatched: Vec<_> = crates.iter().filter(|c| c.status == "mismatch").collect();
+    if !mismatched.is_empty() {
+        let list: Vec<String> = mismatched
+            .iter()
+            .map(|c| format!("  {} at `{}`", c.package_id, c.path.display()))
+            .collect();
+        bail!(
+            "the cached `.crate` files of {} packages don't match the checksums of `Cargo.lock`:\n{}\n\
+             remove them to download them again",
+            mismatched.len(),
+            list.join("\n")
+        );
+    }
+    config.shell().status(
+        "Verified",
+        format!(
+            "{} cached crates, {} not downloaded",
+            count("ok"),
+            count("missing")
+        ),
+    )?;
+    Ok(())
+}
+
+/// Parses a duration like `30d`, `12h` or `2w`.
+pub fn parse_cache_duration(s: &str) -> CargoResult<Duration> {
+    humantime::parse_duration(s).with_context(|| {
+        format!(
+            "invalid duration `{}`, expected a number with a unit like `30d`, `12h` or `2w`",
+            s
+        )
+    })
+}
+
+/// Parses a size like `500MB` or `5GB`.
+pub fn parse_cache_size(s: &str) -> CargoResult<u64> {
+    s.parse::<ByteSize>().map(|size| size.0).map_err(|_| {
+        anyhow::format_err!(
+            "invalid size `{}`, expected a number of bytes like `500MB` or `5GB`",
+            s
+        )
+    })
+}
+
+/// The names of the entries of `dir`, or none if it can't be read.
+fn dir_names(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| !name.starts_with('.'))
+        .collect();
+    names.sort();
+    names
+}
+
+/// The total size of the files under `path`.
+fn dir_size(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+fn mtime(path: &Path) -> SystemTime {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+}
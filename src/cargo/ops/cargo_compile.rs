@@ -24,12 +24,13 @@
 
 use std::collections::{BTreeSet, HashMap, HashSet};
 use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use crate::core::compiler::unit_dependencies::build_unit_dependencies;
 use crate::core::compiler::unit_graph::{self, UnitDep, UnitGraph};
-use crate::core::compiler::{standard_lib, TargetInfo};
-use crate::core::compiler::{BuildConfig, BuildContext, Compilation, Context};
+use crate::core::compiler::{standard_lib, toolchain_file, TargetInfo};
+use crate::core::compiler::{BuildConfig, BuildContext, Compilation, Context, CrateType};
 use crate::core::compiler::{CompileKind, CompileMode, CompileTarget, RustcTargetData, Unit};
 use crate::core::compiler::{DefaultExecutor, Executor, UnitInterner};
 use crate::core::profiles::{Profiles, UnitFor};
@@ -40,6 +41,7 @@ use crate::core::{PackageId, PackageIdSpec, SourceId, TargetKind, Workspace};
 use crate::drop_println;
 use crate::ops;
 use crate::ops::resolve::WorkspaceResolve;
+use crate::ops::TargetExpr;
 use crate::util::config::Config;
 use crate::util::interning::InternedString;
 use crate::util::restricted_names::is_glob_pattern;
@@ -71,6 +73,9 @@ pub struct CompileOptions {
     /// The specified target will be compiled with all the available arguments,
     /// note that this only accounts for the *final* invocation of rustc
     pub target_rustc_args: Option<Vec<String>>,
+    /// Crate types to be passed to rustc (single target only), replacing the
+    /// ones of the manifest.
+    pub target_rustc_crate_types: Option<Vec<String>>,
     /// Extra arguments passed to all selected targets for rustdoc.
     pub local_rustdoc_args: Option<Vec<String>>,
     /// Whether the `--document-private-items` flags was specified and should
@@ -79,6 +84,12 @@ pub struct CompileOptions {
     /// Whether the build process should check the minimum Rust version
     /// defined in the cargo metadata for a crate.
     pub honor_rust_version: bool,
+    /// Expression passed with `--targets` narrowing down the targets selected
+    /// by `filter`.
+    pub target_expr: Option<TargetExpr>,
+    /// Whether the `required-features` of explicitly selected targets should
+    /// be enabled automatically instead of producing an error.
+    pub auto_features: bool,
 }
 
 impl<'a> CompileOptions {
@@ -92,9 +103,12 @@ impl<'a> CompileOptions {
             },
             target_rustdoc_args: None,
             target_rustc_args: None,
+            target_rustc_crate_types: None,
             local_rustdoc_args: None,
             rustdoc_document_private_items: false,
             honor_rust_version: true,
+            target_expr: None,
+            auto_features: false,
         })
     }
 }
@@ -105,10 +119,29 @@ pub enum Packages {
     All,
     OptOut(Vec<String>),
     Packages(Vec<String>),
+    /// Members of the named `[workspace.groups]`.
+    Groups(Vec<String>),
+    /// Members with files changed since the given git revision, along with
+    /// the members which depend on them.
+    ChangedSince(String),
 }
 
 impl Packages {
-    pub fn from_flags(all: bool, exclude: Vec<String>, package: Vec<String>) -> CargoResult<Self> {
+    pub fn from_flags(
+        all: bool,
+        exclude: Vec<String>,
+        package: Vec<String>,
+        group: Vec<String>,
+    ) -> CargoResult<Self> {
+        if !group.is_empty() {
+            if all || !package.is_empty() {
+                anyhow::bail!("--group cannot be used together with --workspace or --package")
+            }
+            if !exclude.is_empty() {
+                anyhow::bail!("--exclude can only be used together with --workspace")
+            }
+            return Ok(Packages::Groups(group));
+        }
         Ok(match (all, exclude.len(), package.len()) {
             (false, 0, 0) => Packages::Default,
             (false, 0, _) => Packages::Packages(package),
@@ -166,6 +199,25 @@ impl Packages {
                 .map(Package::package_id)
                 .map(PackageIdSpec::from_package_id)
                 .collect(),
+            Packages::Groups(_) => self
+                .get_packages(ws)?
+                .into_iter()
+                .map(Package::package_id)
+                .map(PackageIdSpec::from_package_id)
+                .collect(),
+            Packages::ChangedSince(rev) => {
+                let specs: Vec<_> = changed_members(ws, rev)?
+                    .into_iter()
+                    .map(Package::package_id)
+                    .map(PackageIdSpec::from_package_id)
+                    .collect();
+                if specs.is_empty() {
+                    ws.config()
+                        .shell()
+                        .note(format!("no workspace members changed since `{}`", rev))?;
+                }
+                return Ok(specs);
+            }
         };
         if specs.is_empty() {
             if ws.is_virtual() {
@@ -209,6 +261,18 @@ impl Packages {
                 emit_pattern_not_found(ws, patterns, false)?;
                 packages
             }
+            Packages::Groups(groups) => {
+                let mut packages: Vec<&Package> = Vec::new();
+                for group in groups {
+                    for pkg in ws.group_members(group)? {
+                        if !packages.iter().any(|p| p.package_id() == pkg.package_id()) {
+                            packages.push(pkg);
+                        }
+                    }
+                }
+                packages
+            }
+            Packages::ChangedSince(rev) => changed_members(ws, rev)?,
         };
         Ok(packages)
     }
@@ -221,6 +285,8 @@ impl Packages {
             Packages::All => ws.members().count() > 1,
             Packages::Packages(_) => true,
             Packages::OptOut(_) => true,
+            Packages::Groups(_) => true,
+            Packages::ChangedSince(_) => true,
         }
     }
 }
@@ -254,6 +320,9 @@ pub enum CompileFilter {
         examples: FilterRule,
         tests: FilterRule,
         benches: FilterRule,
+        /// Fuzz targets are never selected by `--all-targets`, as they need
+        /// to be built with `cargo fuzz-run`.
+        fuzz: FilterRule,
     },
 }
 
@@ -320,6 +389,67 @@ pub fn print<'a>(
     Ok(())
 }
 
+/// Replaces the crate types of the single root unit with the ones passed
+/// with `cargo rustc --crate-type`.
+///
+/// The target kind is part of the metadata hash of a unit, so the artifacts
+/// and fingerprints are kept apart from those of the crate types of the
+/// manifest.
+fn override_rustc_crate_types(
+    units: &mut [Unit],
+    args: &[String],
+    interner: &UnitInterner,
+) -> CargoResult<()> {
+    if units.len() != 1 {
+        anyhow::bail!(
+            "crate types to rustc can only be passed to one \
+             target, consider filtering\nthe package by passing, \
+             e.g., `--lib` or `--example` to specify a single target"
+        );
+    }
+
+    let unit = &units[0];
+    let override_unit = |f: fn(Vec<CrateType>) -> TargetKind| {
+        let crate_types = args.iter().map(|s| s.into()).collect();
+        let mut target = unit.target.clone();
+        target.set_kind(f(crate_types));
+        interner.intern(
+            &unit.pkg,
+            &target,
+            unit.profile.clone(),
+            unit.kind,
+            unit.mode,
+            unit.features.clone(),
+            unit.is_std,
+            unit.dep_hash,
+        )
+    };
+    units[0] = match unit.target.kind() {
+        TargetKind::Lib(_) => override_unit(TargetKind::Lib),
+        TargetKind::ExampleLib(_) => override_unit(TargetKind::ExampleLib),
+        _ => {
+            anyhow::bail!(
+                "crate types can only be specified for libraries and example libraries.\n\
+                 Binaries, tests, and benchmarks are always the `bin` crate type"
+            );
+        }
+    };
+
+    Ok(())
+}
+
+/// The flags for the coverage instrumentation libFuzzer relies on, matching
+/// what `cargo fuzz` passes.
+const FUZZ_FLAGS: &[&str] = &[
+    "-Cpasses=sancov-module",
+    "-Cllvm-args=-sanitizer-coverage-level=4",
+    "-Cllvm-args=-sanitizer-coverage-inline-8bit-counters",
+    "-Cllvm-args=-sanitizer-coverage-pc-table",
+    "-Cllvm-args=-sanitizer-coverage-trace-compares",
+    "--cfg",
+    "fuzzing",
+];
+
 pub fn create_bcx<'a, 'cfg>(
     ws: &'a Workspace<'cfg>,
     options: &'a CompileOptions,
@@ -332,9 +462,12 @@ pub fn create_bcx<'a, 'cfg>(
         ref filter,
         ref target_rustdoc_args,
         ref target_rustc_args,
+        ref target_rustc_crate_types,
         ref local_rustdoc_args,
         rustdoc_document_private_items,
         honor_rust_version,
+        ref target_expr,
+        auto_features,
     } = *options;
     let config = ws.config();
 
@@ -360,8 +493,21 @@ pub fn create_bcx<'a, 'cfg>(
         }
     }
     config.validate_term_config()?;
+    if config.cli_unstable().downloads {
+        config.set_download_messages(build_config.emit_json());
+    }
 
     let target_data = RustcTargetData::new(ws, &build_config.requested_kinds)?;
+    toolchain_file::check(config, &target_data, &build_config.requested_kinds)?;
+    toolchain_file::check_requested_targets(config, &target_data, build_config)?;
+
+    let auto_cli_features;
+    let cli_features = if auto_features {
+        auto_cli_features = add_required_features(ws, spec, filter, cli_features)?;
+        &auto_cli_features
+    } else {
+        cli_features
+    };
 
     let all_packages = &Packages::All;
     let rustdoc_scrape_examples = &config.cli_unstable().rustdoc_scrape_examples;
@@ -372,7 +518,14 @@ pub fn create_bcx<'a, 'cfg>(
         spec
     };
 
-    let resolve_specs = full_specs.to_package_id_specs(ws)?;
+    let mut resolve_specs = full_specs.to_package_id_specs(ws)?;
+    // `--changed-since` may select no packages at all. The resolver still
+    // needs something to resolve, so use the default members, but build none
+    // of them.
+    let build_specs = resolve_specs.clone();
+    if resolve_specs.is_empty() {
+        resolve_specs = Packages::Default.to_package_id_specs(ws)?;
+    }
     let has_dev_units = if filter.need_dev_deps(build_config.mode) || need_reverse_dependencies {
         HasDevUnits::Yes
     } else {
@@ -394,7 +547,57 @@ pub fn create_bcx<'a, 'cfg>(
         resolved_features,
     } = resolve;
 
-    let std_resolve_features = if let Some(crates) = &config.cli_unstable().build_std {
+    let mut profiles = Profiles::new(ws, build_config.requested_profile)?;
+    if let Some(sanitizer) = build_config.sanitizer {
+        profiles.set_sanitizer(sanitizer);
+    }
+    let mut build_std = config.cli_unstable().build_std.clone();
+    if let Some(sanitizer) = profiles.sanitizer() {
+        for kind in &build_config.requested_kinds {
+            let target = match kind {
+                CompileKind::Host => target_data.rustc.host.as_str(),
+                CompileKind::Target(target) => target.short_name(),
+            };
+            let runtime = format!(".{}.", sanitizer.short_name());
+            let libdir = &target_data.info(*kind).sysroot_target_libdir;
+            let supported = std::fs::read_dir(libdir)
+                .map(|entries| {
+                    entries.filter_map(|e| e.ok()).any(|e| {
+                        let name = e.file_name();
+                        let name = name.to_string_lossy();
+                        name.starts_with("librustc-") && name.contains(&runtime)
+                    })
+                })
+                .unwrap_or(false);
+            if !supported {
+                bail!(
+                    "the `{}` sanitizer is not supported for the target `{}`\n\
+                     No sanitizer runtime was found in `{}`.",
+                    sanitizer,
+                    target,
+                    libdir.display()
+                );
+            }
+        }
+        if sanitizer.requires_build_std() && build_std.is_none() {
+            if build_config.requested_kinds[0].is_host() {
+                bail!(
+                    "the `{}` sanitizer requires rebuilding the standard library, \
+                     which requires --target",
+                    sanitizer
+                );
+            }
+            build_std = Some(standard_lib::parse_unstable_flag(None));
+        }
+    }
+    let miri_sysroot = std::env::var_os("MIRI_SYSROOT");
+    if build_config.miri && miri_sysroot.is_none() && build_std.is_none() {
+        // Miri needs the MIR of the standard library as well, which the one
+        // shipped with rustc doesn't carry.
+        build_std = Some(standard_lib::parse_unstable_flag(None));
+    }
+
+    let std_resolve_features = if let Some(crates) = &build_std {
         if build_config.build_plan {
             config
                 .shell()
@@ -420,7 +623,7 @@ pub fn create_bcx<'a, 'cfg>(
     let specs = if need_reverse_dependencies {
         spec.to_package_id_specs(ws)?
     } else {
-        resolve_specs.clone()
+        build_specs
     };
     let to_build_ids = resolve.specs_to_ids(&specs)?;
     // Now get the `Package` for each `PackageId`. This may trigger a download
@@ -461,7 +664,6 @@ pub fn create_bcx<'a, 'cfg>(
         );
     }
 
-    let profiles = Profiles::new(ws, build_config.requested_profile)?;
     profiles.validate_packages(
         ws.profiles(),
         &mut config.shell(),
@@ -490,6 +692,7 @@ pub fn create_bcx<'a, 'cfg>(
         ws,
         &to_builds,
         filter,
+        target_expr.as_ref(),
         &build_config.requested_kinds,
         explicit_host_kind,
         build_config.mode,
@@ -501,6 +704,10 @@ pub fn create_bcx<'a, 'cfg>(
         interner,
     )?;
 
+    if let Some(args) = target_rustc_crate_types {
+        override_rustc_crate_types(&mut units, args, interner)?;
+    }
+
     let mut scrape_units = match rustdoc_scrape_examples {
         Some(arg) => {
             let filter = match arg.as_str() {
@@ -526,6 +733,7 @@ pub fn create_bcx<'a, 'cfg>(
                 ws,
                 &to_builds,
                 &filter,
+                None,
                 &build_config.requested_kinds,
                 explicit_host_kind,
                 mode,
@@ -540,7 +748,7 @@ pub fn create_bcx<'a, 'cfg>(
         None => Vec::new(),
     };
 
-    let std_roots = if let Some(crates) = &config.cli_unstable().build_std {
+    let std_roots = if let Some(crates) = &build_std {
         // Only build libtest if it looks like it is needed.
         let mut crates = crates.clone();
         if !crates.iter().any(|c| c == "test")
@@ -644,6 +852,55 @@ pub fn create_bcx<'a, 'cfg>(
         }
     }
 
+    if build_config.coverage {
+        // Build scripts and proc-macros run at build time, so they are never
+        // part of the coverage of the tests.
+        for unit in unit_graph.keys() {
+            if unit.is_local()
+                && !unit.mode.is_run_custom_build()
+                && !unit.mode.is_doc()
+                && !unit.mode.is_doc_test()
+                && !unit.target.is_build_program()
+                && !unit.target.proc_macro()
+            {
+                extra_compiler_args
+                    .entry(unit.clone())
+                    .or_default()
+                    .extend(["-C".to_string(), "instrument-coverage".to_string()]);
+            }
+        }
+    }
+
+    if build_config.fuzz {
+        // Everything that ends up in the fuzz targets is instrumented, but
+        // nothing that runs on the host during the build, which is why
+        // `cargo fuzz-run` always builds for an explicit target.
+        for unit in unit_graph.keys() {
+            if !unit.kind.is_host() {
+                extra_compiler_args
+                    .entry(unit.clone())
+                    .or_default()
+                    .extend(FUZZ_FLAGS.iter().map(|flag| flag.to_string()));
+            }
+        }
+    }
+
+    if build_config.miri {
+        for unit in unit_graph.keys() {
+            if !unit.kind.is_host() {
+                let args = extra_compiler_args.entry(unit.clone()).or_default();
+                // Miri sets `cfg(miri)` on its own, but passing it explicitly
+                // also keeps these artifacts from being mistaken for the ones
+                // compiled by rustc.
+                args.extend(["--cfg".to_string(), "miri".to_string()]);
+                if let Some(sysroot) = &miri_sysroot {
+                    args.push("--sysroot".to_string());
+                    args.push(sysroot.to_string_lossy().into_owned());
+                }
+            }
+        }
+    }
+
     if honor_rust_version {
         // Remove any pre-release identifiers for easier comparison
         let current_version = &target_data.rustc.version;
@@ -782,6 +1039,7 @@ impl CompileFilter {
                 examples: rule_exms,
                 benches: rule_bens,
                 tests: rule_tsts,
+                fuzz: FilterRule::none(),
             }
         } else {
             CompileFilter::Default {
@@ -798,6 +1056,20 @@ impl CompileFilter {
             examples: FilterRule::All,
             benches: FilterRule::All,
             tests: FilterRule::All,
+            fuzz: FilterRule::none(),
+        }
+    }
+
+    /// Constructs a filter selecting only the given fuzz targets.
+    pub fn new_fuzz(rule_fuzz: FilterRule) -> CompileFilter {
+        CompileFilter::Only {
+            all_targets: false,
+            lib: LibRule::False,
+            bins: FilterRule::none(),
+            examples: FilterRule::none(),
+            benches: FilterRule::none(),
+            tests: FilterRule::none(),
+            fuzz: rule_fuzz,
         }
     }
 
@@ -814,8 +1086,14 @@ impl CompileFilter {
                     ref examples,
                     ref tests,
                     ref benches,
+                    ref fuzz,
                     ..
-                } => examples.is_specific() || tests.is_specific() || benches.is_specific(),
+                } => {
+                    examples.is_specific()
+                        || tests.is_specific()
+                        || benches.is_specific()
+                        || fuzz.is_specific()
+                }
             },
             CompileMode::RunCustomBuild => panic!("Invalid mode"),
         }
@@ -832,12 +1110,14 @@ impl CompileFilter {
                 ref examples,
                 ref tests,
                 ref benches,
+                ref fuzz,
                 ..
             } => {
                 let rule = match *target.kind() {
                     TargetKind::Bin => bins,
                     TargetKind::Test => tests,
                     TargetKind::Bench => benches,
+                    TargetKind::Fuzz => fuzz,
                     TargetKind::ExampleBin | TargetKind::ExampleLib(..) => examples,
                     TargetKind::Lib(..) => {
                         return match *lib {
@@ -846,7 +1126,7 @@ impl CompileFilter {
                             LibRule::False => false,
                         };
                     }
-                    TargetKind::CustomBuild => return false,
+                    TargetKind::CustomBuild | TargetKind::Codegen => return false,
                 };
                 rule.matches(target)
             }
@@ -878,12 +1158,14 @@ impl CompileFilter {
                 examples,
                 tests,
                 benches,
+                fuzz,
                 ..
             } => {
                 bins.contains_glob_patterns()
                     || examples.contains_glob_patterns()
                     || tests.contains_glob_patterns()
                     || benches.contains_glob_patterns()
+                    || fuzz.contains_glob_patterns()
             }
         }
     }
@@ -911,6 +1193,7 @@ fn generate_targets(
     ws: &Workspace<'_>,
     packages: &[&Package],
     filter: &CompileFilter,
+    target_expr: Option<&TargetExpr>,
     requested_kinds: &[CompileKind],
     explicit_host_kind: CompileKind,
     mode: CompileMode,
@@ -951,8 +1234,9 @@ fn generate_targets(
             } else {
                 UnitFor::new_normal()
             };
-            // Custom build units are added in `build_unit_dependencies`.
-            assert!(!target.is_custom_build());
+            // Custom build and codegen units are added in
+            // `build_unit_dependencies`.
+            assert!(!target.is_build_program());
             let target_mode = match target_mode {
                 CompileMode::Test => {
                     if target.is_example() && !filter.is_specific() && !target.tested() {
@@ -1067,6 +1351,7 @@ fn generate_targets(
             ref examples,
             ref tests,
             ref benches,
+            ref fuzz,
         } => {
             if *lib != LibRule::False {
                 let mut libs = Vec::new();
@@ -1146,6 +1431,23 @@ fn generate_targets(
                 bench_filter,
                 bench_mode,
             )?);
+            proposals.extend(list_rule_targets(
+                packages,
+                fuzz,
+                "fuzz",
+                Target::is_fuzz,
+                mode,
+            )?);
+        }
+    }
+
+    if let Some(expr) = target_expr {
+        proposals.retain(|p| expr.matches(p.pkg, p.target));
+        if proposals.is_empty() {
+            config.shell().warn(format!(
+                "target expression `{}` did not match any targets",
+                expr
+            ))?;
         }
     }
 
@@ -1204,7 +1506,9 @@ fn generate_targets(
         // else, silently skip target.
     }
     let mut units: Vec<_> = units.into_iter().collect();
-    unmatched_target_filters(&units, filter, &mut ws.config().shell())?;
+    if target_expr.is_none() {
+        unmatched_target_filters(&units, filter, &mut ws.config().shell())?;
+    }
 
     // Keep the roots in a consistent order, which helps with checking test output.
     units.sort_unstable();
@@ -1227,6 +1531,7 @@ fn unmatched_target_filters(
         ref examples,
         ref tests,
         ref benches,
+        ref fuzz,
     } = *filter
     {
         if units.is_empty() {
@@ -1247,6 +1552,7 @@ fn unmatched_target_filters(
                 append(tests, " `tests`,");
                 append(examples, " `examples`,");
                 append(benches, " `benches`,");
+                append(fuzz, " `fuzz`,");
                 filters.pop();
             }
 
@@ -1674,6 +1980,270 @@ fn opt_patterns_and_names(
     Ok((opt_patterns, opt_names))
 }
 
+/// Returns a copy of `cli_features` extended with the `required-features` of
+/// the targets explicitly selected by `filter` which would otherwise not be
+/// enabled.
+///
+/// A required feature is considered enabled if it is requested on the
+/// command-line, or reachable from the requested features (including
+/// `default`) through the package's `[features]` table. A status line is
+/// printed for every target which needed features enabled.
+fn add_required_features(
+    ws: &Workspace<'_>,
+    spec: &Packages,
+    filter: &CompileFilter,
+    cli_features: &CliFeatures,
+) -> CargoResult<CliFeatures> {
+    let mut features = (*cli_features.features).clone();
+    let (bins, examples, tests, benches, fuzz) = match filter {
+        CompileFilter::Only {
+            bins,
+            examples,
+            tests,
+            benches,
+            fuzz,
+            ..
+        } if !cli_features.all_features => (bins, examples, tests, benches, fuzz),
+        _ => return Ok(cli_features.clone()),
+    };
+    let packages = spec.get_packages(ws)?;
+    let single_package = packages.len() == 1;
+    for pkg in &packages {
+        let enabled = enabled_package_features(pkg, cli_features, single_package);
+        for target in pkg.targets() {
+            let rule = match target.kind() {
+                TargetKind::Bin => bins,
+                TargetKind::ExampleBin | TargetKind::ExampleLib(..) => examples,
+                TargetKind::Test => tests,
+                TargetKind::Bench => benches,
+                TargetKind::Fuzz => fuzz,
+                _ => continue,
+            };
+            let explicitly_selected = match rule {
+                FilterRule::All => false,
+                FilterRule::Just(names) => names
+                    .iter()
+                    .any(|name| build_glob(name).map_or(false, |pat| pat.matches(target.name()))),
+            };
+            let required = match target.required_features() {
+                Some(rf) if explicitly_selected => rf,
+                _ => continue,
+            };
+            let (missing, unavailable): (Vec<&String>, Vec<&String>) = required
+                .iter()
+                .filter(|f| !enabled.contains(f.as_str()))
+                .partition(|f| {
+                    single_package || !f.contains('/') || dep_feature_applies(ws, &packages, pkg, f)
+                });
+            for f in &unavailable {
+                ws.config().shell().warn(format!(
+                    "feature `{}` required by {} of package `{}` can't be enabled for `{}` \
+                     alone while several packages are selected, select only `{}` with `-p`",
+                    f,
+                    target.description_named(),
+                    pkg.name(),
+                    pkg.name(),
+                    pkg.name()
+                ))?;
+            }
+            if missing.is_empty() {
+                continue;
+            }
+            for f in &missing {
+                let value = if single_package || f.contains('/') {
+                    f.to_string()
+                } else {
+                    format!("{}/{}", pkg.name(), f)
+                };
+                features.insert(FeatureValue::new(InternedString::new(&value)));
+            }
+            let names: Vec<String> = missing.iter().map(|f| format!("`{}`", f)).collect();
+            ws.config().shell().status(
+                "Enabling",
+                format!(
+                    "features {} required by {} of package `{}`",
+                    names.join(", "),
+                    target.description_named(),
+                    pkg.name()
+                ),
+            )?;
+        }
+    }
+    Ok(CliFeatures {
+        features: std::rc::Rc::new(features),
+        all_features: cli_features.all_features,
+        uses_default_features: cli_features.uses_default_features,
+    })
+}
+
+/// Whether the required feature `f` of `pkg`, a `dep/feat` value, can be
+/// passed on the command line while several packages are selected. It then
+/// applies to every selected package depending on `dep`, which unifies the
+/// features of `dep` anyway, but it must not enable an optional dependency of
+/// another package.
+fn dep_feature_applies(ws: &Workspace<'_>, packages: &[&Package], pkg: &Package, f: &str) -> bool {
+    if !ws.allows_new_cli_feature_behavior() {
+        // Only the package in the current directory gets such values.
+        return false;
+    }
+    let (dep_name, weak) = match FeatureValue::new(InternedString::new(f)) {
+        FeatureValue::DepFeature { dep_name, weak, .. } => (dep_name, weak),
+        _ => return false,
+    };
+    let declares = |p: &Package, only_optional: bool| {
+        p.dependencies()
+            .iter()
+            .any(|d| d.name_in_toml() == dep_name && (!only_optional || d.is_optional()))
+    };
+    declares(pkg, false)
+        && (weak
+            || packages
+                .iter()
+                .all(|p| p.package_id() == pkg.package_id() || !declares(p, true)))
+}
+
+/// Computes the names of the features of `pkg` that `cli_features` would
+/// enable, following the package's own `[features]` table.
+fn enabled_package_features(
+    pkg: &Package,
+    cli_features: &CliFeatures,
+    single_package: bool,
+) -> HashSet<InternedString> {
+    let feature_map = pkg.summary().features();
+    let mut pending = Vec::new();
+    for value in cli_features.features.iter() {
+        match value {
+            FeatureValue::Feature(f) if single_package => pending.push(*f),
+            FeatureValue::DepFeature {
+                dep_name,
+                dep_feature,
+                ..
+            } if *dep_name == pkg.name() => pending.push(*dep_feature),
+            _ => {}
+        }
+    }
+    if cli_features.uses_default_features && feature_map.contains_key("default") {
+        pending.push(InternedString::new("default"));
+    }
+    let mut enabled = HashSet::new();
+    while let Some(f) = pending.pop() {
+        if !enabled.insert(f) {
+            continue;
+        }
+        if let Some(values) = feature_map.get(&f) {
+            for value in values {
+                match value {
+                    FeatureValue::Feature(f) => pending.push(*f),
+                    FeatureValue::DepFeature { .. } => {
+                        enabled.insert(InternedString::new(&value.to_string()));
+                    }
+                    FeatureValue::Dep { .. } => {}
+                }
+            }
+        }
+    }
+    enabled
+}
+
+/// Finds the workspace members affected by the files changed since the git
+/// revision `rev`.
+///
+/// A file belongs to the member with the innermost package root containing
+/// it. Changes to the files of the whole workspace, see `is_workspace_file`,
+/// affect every member. Changes in the working directory and the index are
+/// included, as well as untracked files. Members which depend on an affected
+/// member through a `path` dependency are affected as well, transitively.
+///
+/// > This function should be used only in package selection processes such like
+/// `Packages::to_package_id_specs` and `Packages::get_packages`.
+fn changed_members<'ws>(ws: &'ws Workspace<'_>, rev: &str) -> CargoResult<Vec<&'ws Package>> {
+    let repo = git2::Repository::discover(ws.root()).with_context(|| {
+        format!(
+            "`--changed-since` requires the workspace `{}` to be in a git repository",
+            ws.root().display()
+        )
+    })?;
+    let workdir = match repo.workdir() {
+        Some(workdir) => workdir.to_path_buf(),
+        None => anyhow::bail!("`--changed-since` does not support bare git repositories"),
+    };
+    let tree = repo
+        .revparse_single(rev)
+        .and_then(|obj| obj.peel_to_tree())
+        .with_context(|| format!("failed to find git revision `{}`", rev))?;
+    let mut opts = git2::DiffOptions::new();
+    opts.include_untracked(true).recurse_untracked_dirs(true);
+    let diff = repo.diff_tree_to_workdir_with_index(Some(&tree), Some(&mut opts))?;
+
+    let changed_files: Vec<PathBuf> = diff
+        .deltas()
+        .flat_map(|delta| vec![delta.old_file().path(), delta.new_file().path()])
+        .flatten()
+        .map(|path| workdir.join(path))
+        .collect();
+
+    let members: Vec<&Package> = ws.members().collect();
+    if changed_files.iter().any(|file| is_workspace_file(ws, file)) {
+        return Ok(members);
+    }
+    let mut affected = HashSet::new();
+    for file in &changed_files {
+        let owner = members
+            .iter()
+            .filter(|pkg| file.starts_with(pkg.root()))
+            .max_by_key(|pkg| pkg.root().components().count());
+        if let Some(pkg) = owner {
+            affected.insert(pkg.package_id());
+        }
+    }
+
+    // Propagate to reverse dependencies until nothing changes.
+    loop {
+        let mut changed = false;
+        for pkg in &members {
+            if affected.contains(&pkg.package_id()) {
+                continue;
+            }
+            let depends_on_affected = pkg.dependencies().iter().any(|dep| {
+                members
+                    .iter()
+                    .any(|m| affected.contains(&m.package_id()) && dep.matches_id(m.package_id()))
+            });
+            if depends_on_affected {
+                affected.insert(pkg.package_id());
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    Ok(members
+        .into_iter()
+        .filter(|pkg| affected.contains(&pkg.package_id()))
+        .collect())
+}
+
+/// Whether `file` applies to the whole workspace: the root manifest, with the
+/// `[workspace]`, `[patch]` and `[profile]` tables, the lock file, or a
+/// `.cargo/config` file Cargo reads for the workspace.
+fn is_workspace_file(ws: &Workspace<'_>, file: &Path) -> bool {
+    if file == ws.root_manifest() || file == ws.lockfile_path() {
+        return true;
+    }
+    let is_config = matches!(
+        file.file_name().and_then(|name| name.to_str()),
+        Some("config" | "config.toml")
+    );
+    match file.parent() {
+        Some(dir) if is_config && dir.file_name() == Some(".cargo".as_ref()) => {
+            dir.parent().map_or(false, |dir| ws.root().starts_with(dir))
+        }
+        _ => false,
+    }
+}
+
 /// Removes duplicate CompileMode::Doc units that would cause problems with
 /// filename collisions.
 ///
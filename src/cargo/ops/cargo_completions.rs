@@ -0,0 +1,467 @@
+//! Implementation of `cargo completions`, which generates the completion
+//! scripts of shells.
+//!
+//! The scripts complete the commands and their options statically, from the
+//! command line interface they are generated from. The values of the options
+//! that select packages, targets and features are completed dynamically by
+//! running `cargo completions --query`, which only loads the manifests of
+//! the workspace, without resolving its dependencies.
+
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+use std::str::FromStr;
+
+use anyhow::bail;
+
+use crate::core::{Package, Target, Workspace};
+use crate::util::errors::CargoResult;
+
+/// A shell `cargo completions` generates a script for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompletionShell {
+    Bash,
+    Fish,
+    Zsh,
+}
+
+impl FromStr for CompletionShell {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> CargoResult<CompletionShell> {
+        match s {
+            "bash" => Ok(CompletionShell::Bash),
+            "fish" => Ok(CompletionShell::Fish),
+            "zsh" => Ok(CompletionShell::Zsh),
+            other => bail!(
+                "unsupported shell `{}`, expected one of: bash, fish, zsh",
+                other
+            ),
+        }
+    }
+}
+
+/// The values `cargo completions --query` lists.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompletionQuery {
+    Packages,
+    Bins,
+    Examples,
+    Tests,
+    Benches,
+    Features,
+}
+
+impl CompletionQuery {
+    /// The query that completes the value of the option `--long`, if any.
+    fn for_option(long: &str) -> Option<CompletionQuery> {
+        match long {
+            "package" | "exclude" => Some(CompletionQuery::Packages),
+            "bin" => Some(CompletionQuery::Bins),
+            "example" => Some(CompletionQuery::Examples),
+            "test" => Some(CompletionQuery::Tests),
+            "bench" => Some(CompletionQuery::Benches),
+            "features" => Some(CompletionQuery::Features),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            CompletionQuery::Packages => "packages",
+            CompletionQuery::Bins => "bins",
+            CompletionQuery::Examples => "examples",
+            CompletionQuery::Tests => "tests",
+            CompletionQuery::Benches => "benches",
+            CompletionQuery::Features => "features",
+        }
+    }
+
+    fn matches(self, target: &Target) -> bool {
+        match self {
+            CompletionQuery::Bins => target.is_bin(),
+            CompletionQuery::Examples => target.is_example(),
+            CompletionQuery::Tests => target.is_test(),
+            CompletionQuery::Benches => target.is_bench(),
+            CompletionQuery::Packages | CompletionQuery::Features => false,
+        }
+    }
+}
+
+impl FromStr for CompletionQuery {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> CargoResult<CompletionQuery> {
+        match s {
+            "packages" => Ok(CompletionQuery::Packages),
+            "bins" => Ok(CompletionQuery::Bins),
+            "examples" => Ok(CompletionQuery::Examples),
+            "tests" => Ok(CompletionQuery::Tests),
+            "benches" => Ok(CompletionQuery::Benches),
+            "features" => Ok(CompletionQuery::Features),
+            other => bail!(
+                "unknown completion query `{}`, expected one of: \
+                 packages, bins, examples, tests, benches, features",
+                other
+            ),
+        }
+    }
+}
+
+/// An option of a command, as completed by the scripts.
+#[derive(Clone, Debug)]
+pub struct CompletionOption {
+    pub short: Option<char>,
+    pub long: Option<String>,
+    pub help: Option<String>,
+    pub takes_value: bool,
+}
+
+impl CompletionOption {
+    fn words(&self) -> Vec<String> {
+        let short = self.short.map(|s| format!("-{}", s));
+        let long = self.long.as_ref().map(|l| format!("--{}", l));
+        short.into_iter().chain(long).collect()
+    }
+
+    fn query(&self) -> Option<CompletionQuery> {
+        if !self.takes_value {
+            return None;
+        }
+        self.long.as_deref().and_then(CompletionQuery::for_option)
+    }
+
+    /// The first line of the help, for the shells that show it.
+    fn summary(&self) -> Option<&str> {
+        self.help.as_deref().and_then(|h| h.lines().next())
+    }
+}
+
+/// A command of Cargo, as completed by the scripts.
+#[derive(Clone, Debug)]
+pub struct CompletionCommand {
+    pub name: String,
+    pub about: Option<String>,
+    pub options: Vec<CompletionOption>,
+    /// The names of the subcommands of the command, like `get` of
+    /// `cargo config`.
+    pub subcommands: Vec<String>,
+}
+
+impl CompletionCommand {
+    /// The dynamically completed options of the command, grouped by query.
+    fn queries(&self) -> Vec<(CompletionQuery, Vec<String>)> {
+        let mut queries: Vec<(CompletionQuery, Vec<String>)> = Vec::new();
+        for option in &self.options {
+            if let Some(query) = option.query() {
+                match queries.iter_mut().find(|(q, _)| *q == query) {
+                    Some((_, words)) => words.extend(option.words()),
+                    None => queries.push((query, option.words())),
+                }
+            }
+        }
+        queries
+    }
+
+    fn words(&self) -> Vec<String> {
+        let options = self.options.iter().flat_map(|o| o.words());
+        self.subcommands.iter().cloned().chain(options).collect()
+    }
+}
+
+/// Generates the completion script of `shell` for the top-level `options`
+/// and the `commands` of Cargo.
+pub fn completion_script(
+    shell: CompletionShell,
+    options: &[CompletionOption],
+    commands: &[CompletionCommand],
+) -> String {
+    let top = CompletionCommand {
+        name: String::new(),
+        about: None,
+        options: options.to_vec(),
+        subcommands: commands.iter().map(|c| c.name.clone()).collect(),
+    };
+    match shell {
+        CompletionShell::Bash => bash_script(&top, commands),
+        CompletionShell::Fish => fish_script(&top, commands),
+        CompletionShell::Zsh => zsh_script(&top, commands),
+    }
+}
+
+/// The values completed for `query`, in the packages named `packages`, or
+/// in the default members of the workspace if there are none. Packages are
+/// always the members of the workspace.
+pub fn completion_candidates(
+    ws: &Workspace<'_>,
+    query: CompletionQuery,
+    packages: &[String],
+) -> CargoResult<Vec<String>> {
+    let selected: Vec<&Package> = if packages.is_empty() {
+        ws.default_members().collect()
+    } else {
+        ws.members()
+            .filter(|p| packages.iter().any(|name| p.name() == name.as_str()))
+            .collect()
+    };
+    let candidates: BTreeSet<String> = match query {
+        CompletionQuery::Packages => ws.members().map(|p| p.name().to_string()).collect(),
+        CompletionQuery::Features => {
+            let qualify = selected.len() > 1;
+            selected
+                .iter()
+                .flat_map(|p| {
+                    p.summary().features().keys().map(move |feature| {
+                        if qualify {
+                            format!("{}/{}", p.name(), feature)
+                        } else {
+                            feature.to_string()
+                        }
+                    })
+                })
+                .collect()
+        }
+        _ => selected
+            .iter()
+            .flat_map(|p| p.targets())
+            .filter(|t| query.matches(t))
+            .map(|t| t.name().to_string())
+            .collect(),
+    };
+    Ok(candidates.into_iter().collect())
+}
+
+/// The command that lists the values of a query, with the package and
+/// manifest path options given on the command line being completed.
+const QUERY_COMMAND: &str = "cargo -Z completions completions --query";
+
+fn bash_script(top: &CompletionCommand, commands: &[CompletionCommand]) -> String {
+    let mut s = String::new();
+    s.push_str(
+        "\
+# bash completion for cargo, generated by `cargo completions bash`.
+
+_cargo_query()
+{
+\tlocal i args=()
+\tfor (( i=1; i<${#words[@]}-1; i++ )); do
+\t\tcase \"${words[i]}\" in
+\t\t\t-p|--package|--manifest-path) args+=(\"${words[i]}\" \"${words[i+1]}\") ;;
+\t\tesac
+\tdone
+",
+    );
+    writeln!(s, "\t{} \"$1\" \"${{args[@]}}\" 2>/dev/null", QUERY_COMMAND).unwrap();
+    s.push_str(
+        "\
+}
+
+_cargo_complete()
+{
+\tif [[ $1 == features && $cur == *,* ]]; then
+\t\tCOMPREPLY=( $( compgen -P \"${cur%,*},\" -W \"$(_cargo_query features)\" -- \"${cur##*,}\" ) )
+\telse
+\t\tCOMPREPLY=( $( compgen -W \"$(_cargo_query \"$1\")\" -- \"$cur\" ) )
+\tfi
+}
+
+_cargo()
+{
+\tlocal cur prev words cword
+\t_get_comp_words_by_ref cur prev words cword
+\tCOMPREPLY=()
+
+\tlocal i cmd
+\tfor (( i=1; i<cword; i++ )); do
+\t\tif [[ ! \"${words[i]}\" =~ ^[+-] ]]; then
+\t\t\tcmd=\"${words[i]}\"
+\t\t\tbreak
+\t\tfi
+\tdone
+
+\tif [[ $prev == --manifest-path ]]; then
+\t\t_filedir toml
+\t\treturn 0
+\tfi
+
+\tlocal opts
+\tcase \"$cmd\" in
+",
+    );
+    for command in std::iter::once(top).chain(commands) {
+        writeln!(s, "\t\t\"{}\")", command.name).unwrap();
+        let queries = command.queries();
+        if !queries.is_empty() {
+            s.push_str("\t\t\tcase \"$prev\" in\n");
+            for (query, words) in queries {
+                writeln!(
+                    s,
+                    "\t\t\t\t{}) _cargo_complete {}; return 0 ;;",
+                    words.join("|"),
+                    query.name()
+                )
+                .unwrap();
+            }
+            s.push_str("\t\t\tesac\n");
+        }
+        writeln!(s, "\t\t\topts=\"{}\"", command.words().join(" ")).unwrap();
+        s.push_str("\t\t\t;;\n");
+    }
+    s.push_str(
+        "\
+\tesac
+
+\tCOMPREPLY=( $( compgen -W \"$opts\" -- \"$cur\" ) )
+\treturn 0
+} &&
+complete -F _cargo cargo
+",
+    );
+    s
+}
+
+fn zsh_script(top: &CompletionCommand, commands: &[CompletionCommand]) -> String {
+    let mut s = String::new();
+    s.push_str(
+        "\
+#compdef cargo
+# zsh completion for cargo, generated by `cargo completions zsh`.
+
+_cargo_query() {
+  local i
+  local -a args
+  for (( i = 2; i < CURRENT; i++ )); do
+    case $words[i] in
+      (-p|--package|--manifest-path) args+=($words[i] $words[i+1]) ;;
+    esac
+  done
+",
+    );
+    writeln!(s, "  {} $1 $args 2>/dev/null", QUERY_COMMAND).unwrap();
+    s.push_str(
+        "\
+}
+
+_cargo_complete() {
+  local -a values
+  values=(${(f)\"$(_cargo_query $1)\"})
+  compadd -a values
+}
+
+_cargo() {
+  local i cmd prev=$words[CURRENT-1]
+  for (( i = 2; i < CURRENT; i++ )); do
+    if [[ $words[i] != [-+]* ]]; then
+      cmd=$words[i]
+      break
+    fi
+  done
+
+  if [[ $prev == --manifest-path ]]; then
+    _files -g '*.toml'
+    return
+  fi
+
+  case $cmd in
+",
+    );
+    for command in std::iter::once(top).chain(commands) {
+        writeln!(s, "    ('{}')", command.name).unwrap();
+        let queries = command.queries();
+        if !queries.is_empty() {
+            s.push_str("      case $prev in\n");
+            for (query, words) in queries {
+                writeln!(
+                    s,
+                    "        ({}) _cargo_complete {}; return ;;",
+                    words.join("|"),
+                    query.name()
+                )
+                .unwrap();
+            }
+            s.push_str("      esac\n");
+        }
+        writeln!(s, "      compadd -- {}", command.words().join(" ")).unwrap();
+        s.push_str("      ;;\n");
+    }
+    s.push_str(
+        "\
+  esac
+}
+
+_cargo \"$@\"
+",
+    );
+    s
+}
+
+fn fish_script(top: &CompletionCommand, commands: &[CompletionCommand]) -> String {
+    let mut s = String::new();
+    s.push_str(
+        "\
+# fish completion for cargo, generated by `cargo completions fish`.
+
+function __cargo_query
+    set -l tokens (commandline -opc)
+    set -l args
+    for i in (seq 2 (count $tokens))
+        switch $tokens[$i]
+            case -p --package --manifest-path
+                set -l next (math $i + 1)
+                if set -q tokens[$next]
+                    set -a args $tokens[$i] $tokens[$next]
+                end
+        end
+    end
+",
+    );
+    writeln!(s, "    {} $argv[1] $args 2>/dev/null", QUERY_COMMAND).unwrap();
+    s.push_str("end\n\ncomplete -c cargo -f\n");
+    for command in commands {
+        write!(
+            s,
+            "complete -c cargo -n __fish_use_subcommand -a {}",
+            command.name
+        )
+        .unwrap();
+        if let Some(about) = &command.about {
+            write!(s, " -d {}", fish_quote(about)).unwrap();
+        }
+        s.push('\n');
+    }
+    for command in std::iter::once(top).chain(commands) {
+        let condition = if command.name.is_empty() {
+            "__fish_use_subcommand".to_string()
+        } else {
+            let condition = fish_quote(&format!("__fish_seen_subcommand_from {}", command.name));
+            for subcommand in &command.subcommands {
+                writeln!(s, "complete -c cargo -n {} -a {}", condition, subcommand).unwrap();
+            }
+            condition
+        };
+        for option in &command.options {
+            write!(s, "complete -c cargo -n {}", condition).unwrap();
+            if let Some(short) = option.short {
+                write!(s, " -s {}", short).unwrap();
+            }
+            if let Some(long) = &option.long {
+                write!(s, " -l {}", long).unwrap();
+            }
+            match option.query() {
+                Some(query) => write!(s, " -x -a '(__cargo_query {})'", query.name()).unwrap(),
+                None if option.long.as_deref() == Some("manifest-path") => s.push_str(" -r -F"),
+                None if option.takes_value => s.push_str(" -x"),
+                None => {}
+            }
+            if let Some(help) = option.summary() {
+                write!(s, " -d {}", fish_quote(help)).unwrap();
+            }
+            s.push('\n');
+        }
+    }
+    s
+}
+
+/// Quotes `s` as a single-quoted fish string.
+fn fish_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"))
+}
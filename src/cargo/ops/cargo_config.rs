@@ -1,5 +1,7 @@
 //! Implementation of `cargo config` subcommand.
 
+use crate::ops;
+use crate::sources::CRATES_IO_REGISTRY;
 use crate::util::config::{Config, ConfigKey, ConfigValue as CV, Definition};
 use crate::util::errors::CargoResult;
 use crate::{drop_eprintln, drop_println};
@@ -88,6 +90,41 @@ pub fn get(config: &Config, opts: &GetOptions<'_>) -> CargoResult<()> {
     Ok(())
 }
 
+/// Prints the proxy of the requests to each registry, and where it's
+/// configured, for `cargo config show-proxy`.
+pub fn show_proxy(config: &Config) -> CargoResult<()> {
+    let mut names = Vec::new();
+    if let Some(CV::Table(registries, _)) = config.values()?.get("registries") {
+        names.extend(registries.keys().cloned());
+        names.sort();
+    }
+    names.insert(0, CRATES_IO_REGISTRY.to_string());
+    for name in &names {
+        match ops::resolve_proxy(config, Some(name))? {
+            Some((proxy, origin)) if proxy.is_empty() => {
+                drop_println!(config, "{}: no proxy ({})", name, origin)
+            }
+            Some((proxy, origin)) => drop_println!(config, "{}: {} ({})", name, proxy, origin),
+            None => drop_println!(config, "{}: no proxy", name),
+        }
+    }
+    if let Some(system) = config.system_proxy()? {
+        let proxy = system.proxy.as_deref().unwrap_or("none");
+        drop_println!(config, "system proxy: {} ({})", proxy, system.source);
+        if !system.no_proxy.is_empty() {
+            drop_println!(
+                config,
+                "system proxy exceptions: {}",
+                system.no_proxy.join(", ")
+            );
+        }
+        if let Some(url) = &system.pac_url {
+            drop_println!(config, "system proxy auto-config: {} (not evaluated)", url);
+        }
+    }
+    Ok(())
+}
+
 /// Checks for environment variables that might be used.
 fn maybe_env<'config>(
     config: &'config Config,
@@ -195,24 +232,24 @@ fn print_json(config: &Config, key: &ConfigKey, cv: &CV, include_key: bool) {
         root_table
     };
     drop_println!(config, "{}", serde_json::to_string(&json_value).unwrap());
+}
 
-    // Helper for recursively converting a CV to JSON.
-    fn cv_to_json(cv: &CV) -> serde_json::Value {
-        match cv {
-            CV::Boolean(val, _def) => json!(val),
-            CV::Integer(val, _def) => json!(val),
-            CV::String(val, _def) => json!(val),
-            CV::List(vals, _def) => {
-                let jvals: Vec<_> = vals.iter().map(|(val, _def)| json!(val)).collect();
-                json!(jvals)
-            }
-            CV::Table(map, _def) => {
-                let mut table = json!({});
-                for (key, val) in map {
-                    table[key] = cv_to_json(val);
-                }
-                table
+/// Converts a config value to JSON, recursively.
+pub(crate) fn cv_to_json(cv: &CV) -> serde_json::Value {
+    match cv {
+        CV::Boolean(val, _def) => json!(val),
+        CV::Integer(val, _def) => json!(val),
+        CV::String(val, _def) => json!(val),
+        CV::List(vals, _def) => {
+            let jvals: Vec<_> = vals.iter().map(|(val, _def)| json!(val)).collect();
+            json!(jvals)
+        }
+        CV::Table(map, _def) => {
+            let mut table = json!({});
+            for (key, val) in map {
+                table[key] = cv_to_json(val);
             }
+            table
         }
     }
 }
@@ -1,7 +1,12 @@
-use crate::core::{Shell, Workspace};
+use crate::core::compiler::{doc_index, Compilation};
+use crate::core::{PackageId, Shell, Workspace};
 use crate::ops;
 use crate::util::config::PathAndArgs;
-use crate::util::CargoResult;
+use crate::util::{CargoResult, Filesystem};
+use anyhow::bail;
+use cargo_util::paths;
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
 use std::path::Path;
 use std::path::PathBuf;
 use std::process::Command;
@@ -13,36 +18,132 @@ pub struct DocOptions {
     pub open_result: bool,
     /// Options to pass through to the compiler
     pub compile_opts: ops::CompileOptions,
+    /// Whether to document a dependency into the documentation directory
+    /// shared by all workspaces, reusing the docs already there, and opening
+    /// docs.rs if it can't be documented.
+    pub offline_first: bool,
 }
 
 /// Main method for `cargo doc`.
 pub fn doc(ws: &Workspace<'_>, options: &DocOptions) -> CargoResult<()> {
+    if options.offline_first {
+        if let Some(pkg_id) = selected_dependency(ws, options)? {
+            return doc_dependency(ws, options, pkg_id);
+        }
+    }
+
     let compilation = ops::compile(ws, &options.compile_opts)?;
 
     if options.open_result {
-        let name = &compilation.root_crate_names[0];
-        let kind = options.compile_opts.build_config.single_requested_kind()?;
-        let path = compilation.root_output[&kind]
-            .with_file_name("doc")
-            .join(&name)
-            .join("index.html");
+        let path = index_path(ws, &compilation, options)?;
         if path.exists() {
-            let config_browser = {
-                let cfg: Option<PathAndArgs> = ws.config().get("doc.browser")?;
-                cfg.map(|path_args| (path_args.path.resolve_program(ws.config()), path_args.args))
-            };
-
-            let mut shell = ws.config().shell();
-            shell.status("Opening", path.display())?;
-            open_docs(&path, &mut shell, config_browser)?;
+            open(ws, path.as_os_str())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The package selected with `-p`, unless it is a member of the workspace.
+fn selected_dependency(ws: &Workspace<'_>, options: &DocOptions) -> CargoResult<Option<PackageId>> {
+    let specs = options.compile_opts.spec.to_package_id_specs(ws)?;
+    let spec = match specs.as_slice() {
+        [spec] => spec,
+        _ => bail!("`--offline-first` requires selecting a single package with `-p`"),
+    };
+    if ws.members().any(|member| spec.matches(member.package_id())) {
+        return Ok(None);
+    }
+    let (_, resolve) = ops::resolve_ws(ws)?;
+    Ok(Some(spec.query(resolve.iter())?))
+}
+
+/// The file recording which version of each crate the shared documentation
+/// directory has the docs of, by crate name.
+const DOCUMENTED_PACKAGES: &str = ".documented-packages.json";
+
+/// Documents the dependency `pkg_id` into the documentation directory in
+/// `CARGO_HOME` shared by all workspaces, and opens it. The docs are reused
+/// if they were already built for the same version, and docs.rs is opened
+/// instead if documenting a crates.io package fails, like when offline.
+fn doc_dependency(ws: &Workspace<'_>, options: &DocOptions, pkg_id: PackageId) -> CargoResult<()> {
+    let config = ws.config();
+    let shared = config.home().join("doc-cache");
+    let doc_dir = shared.as_path_unlocked().join("doc");
+    let record_path = doc_dir.join(DOCUMENTED_PACKAGES);
+    let mut documented: BTreeMap<String, String> = match paths::read(&record_path) {
+        Ok(contents) => serde_json::from_str(&contents).unwrap_or_default(),
+        Err(_) => BTreeMap::new(),
+    };
+
+    let crate_name = pkg_id.name().replace('-', "_");
+    let path = doc_dir.join(&crate_name).join("index.html");
+    if documented.get(&crate_name) == Some(&pkg_id.to_string()) && path.exists() {
+        config.shell().status("Fresh", pkg_id)?;
+        if options.open_result {
+            open(ws, path.as_os_str())?;
         }
+        return Ok(());
     }
 
+    let mut shared_ws = Workspace::new(ws.root_manifest(), config)?;
+    shared_ws.set_target_dir(Filesystem::new(shared.into_path_unlocked()));
+    let compilation = match ops::compile(&shared_ws, &options.compile_opts) {
+        Ok(compilation) => compilation,
+        Err(e) if pkg_id.source_id().is_default_registry() => {
+            crate::display_warning_with_error(
+                &format!("failed to document `{}`, opening docs.rs instead", pkg_id),
+                &e,
+                &mut config.shell(),
+            );
+            let url = format!("https://docs.rs/{}/{}", pkg_id.name(), pkg_id.version());
+            return open(ws, OsStr::new(&url));
+        }
+        Err(e) => return Err(e),
+    };
+
+    let path = index_path(ws, &compilation, options)?;
+    if let Some(name) = compilation.root_crate_names.first() {
+        documented.insert(name.clone(), pkg_id.to_string());
+        paths::write(&record_path, serde_json::to_string(&documented)?)?;
+    }
+    if options.open_result && path.exists() {
+        open(ws, path.as_os_str())?;
+    }
     Ok(())
 }
 
+/// The path of the main page of the docs of the first root crate, or of the
+/// landing page of the `doc` directory when several crates were selected with
+/// `-Z doc-index`.
+fn index_path(
+    ws: &Workspace<'_>,
+    compilation: &Compilation<'_>,
+    options: &DocOptions,
+) -> CargoResult<PathBuf> {
+    let kind = options.compile_opts.build_config.single_requested_kind()?;
+    let doc_dir = compilation.root_output[&kind].with_file_name("doc");
+    if ws.config().cli_unstable().doc_index && compilation.root_crate_names.len() > 1 {
+        return Ok(doc_dir.join(doc_index::LANDING_PAGE));
+    }
+    let name = &compilation.root_crate_names[0];
+    Ok(doc_dir.join(&name).join("index.html"))
+}
+
+/// Opens `target`, a path or an URL, with the configured browser.
+fn open(ws: &Workspace<'_>, target: &OsStr) -> CargoResult<()> {
+    let config_browser = {
+        let cfg: Option<PathAndArgs> = ws.config().get("doc.browser")?;
+        cfg.map(|path_args| (path_args.path.resolve_program(ws.config()), path_args.args))
+    };
+
+    let mut shell = ws.config().shell();
+    shell.status("Opening", Path::new(target).display())?;
+    open_docs(target, &mut shell, config_browser)
+}
+
 fn open_docs(
-    path: &Path,
+    path: &OsStr,
     shell: &mut Shell,
     config_browser: Option<(PathBuf, Vec<String>)>,
 ) -> CargoResult<()> {
@@ -0,0 +1,163 @@
+//! Implementation of `cargo expand`, which prints the code of a target after
+//! its macros are expanded.
+//!
+//! The target is checked like with `cargo check`, with the dependencies,
+//! features and flags of the unit graph, except that rustc is asked to print
+//! the expansion with `-Zunpretty=expanded`. The expansion is kept in
+//! `target/expand` with the dep-info of the invocation, so that the target is
+//! only expanded again when the invocation or one of the files it read
+//! changes.
+
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use cargo_util::{paths, ProcessBuilder};
+use filetime::FileTime;
+
+use crate::core::compiler::{
+    parse_rustc_dep_info, CompileMode, DefaultExecutor, Executor, RustcDepInfo,
+};
+use crate::core::{PackageId, Target, Workspace};
+use crate::drop_print;
+use crate::ops::{self, CompileOptions};
+use crate::util::{internal, short_hash, CargoResult};
+
+/// The flag asking rustc for the expansion, which also marks the invocation
+/// of the expanded target.
+const UNPRETTY: &str = "-Zunpretty=expanded";
+
+/// Expands the single target selected by `options`, and prints the result.
+pub fn expand(ws: &Workspace<'_>, options: &mut CompileOptions) -> CargoResult<()> {
+    let config = ws.config();
+    options.target_rustc_args = Some(vec![UNPRETTY.to_string()]);
+    // The executor decides whether the expansion is fresh.
+    options.build_config.force_rebuild = true;
+
+    let executor = Arc::new(ExpandExecutor {
+        dir: ws.target_dir().join("expand").into_path_unlocked(),
+        expansion: Mutex::new(None),
+    });
+    let exec: Arc<dyn Executor> = executor.clone();
+    ops::compile_with_exec(ws, options, &exec)?;
+
+    let expansion = executor
+        .expansion
+        .lock()
+        .unwrap()
+        .take()
+        .ok_or_else(|| internal("the expanded target was not compiled"))?;
+    drop_print!(config, "{}", paths::read(&expansion)?);
+    Ok(())
+}
+
+struct ExpandExecutor {
+    /// The directory keeping the expansions, `target/expand`.
+    dir: PathBuf,
+    /// The file with the expansion, once the target is expanded.
+    expansion: Mutex<Option<PathBuf>>,
+}
+
+impl Executor for ExpandExecutor {
+    fn exec(
+        &self,
+        cmd: &ProcessBuilder,
+        id: PackageId,
+        target: &Target,
+        mode: CompileMode,
+        on_stdout_line: &mut dyn FnMut(&str) -> CargoResult<()>,
+        on_stderr_line: &mut dyn FnMut(&str) -> CargoResult<()>,
+    ) -> CargoResult<()> {
+        if !cmd.get_args().iter().any(|arg| arg == UNPRETTY) {
+            return DefaultExecutor.exec(cmd, id, target, mode, on_stdout_line, on_stderr_line);
+        }
+
+        let mut cmd = cmd.clone();
+        // `-Z unpretty` is unstable, but the expansion is only printed.
+        cmd.env("RUSTC_BOOTSTRAP", "1");
+        let key = short_hash(&(
+            cmd.get_program(),
+            cmd.get_args(),
+            cmd.get_envs(),
+            cmd.get_cwd(),
+        ));
+        let expansion = self.dir.join(format!("{}-{}.rs", target.crate_name(), key));
+        let dep_info = expansion.with_extension("d");
+
+        if !is_fresh(&cmd, &expansion, &dep_info) {
+            let start = FileTime::now();
+            let mut output = String::new();
+            cmd.exec_with_streaming(
+                &mut |line| {
+                    output.push_str(line);
+                    output.push('\n');
+                    Ok(())
+                },
+                on_stderr_line,
+                false,
+            )?;
+            paths::create_dir_all(&self.dir)?;
+            match rustc_dep_info(&cmd) {
+                Some(rustc_dep_info) if rustc_dep_info.exists() => {
+                    paths::copy(&rustc_dep_info, &dep_info)?;
+                }
+                _ => {
+                    // Without the dep-info the expansion can't be reused.
+                    if dep_info.exists() {
+                        paths::remove_file(&dep_info)?;
+                    }
+                }
+            }
+            paths::write(&expansion, output)?;
+            // Files changed while expanding make the expansion stale.
+            paths::set_file_time_no_err(&expansion, start);
+        }
+        *self.expansion.lock().unwrap() = Some(expansion);
+        Ok(())
+    }
+}
+
+/// Whether `expansion` is still the expansion of `cmd`, because none of the
+/// files and environment variables in its `dep_info` changed.
+fn is_fresh(cmd: &ProcessBuilder, expansion: &Path, dep_info: &Path) -> bool {
+    let mtime = match paths::mtime(expansion) {
+        Ok(mtime) => mtime,
+        Err(_) => return false,
+    };
+    let RustcDepInfo { files, env } = match parse_rustc_dep_info(dep_info) {
+        Ok(info) => info,
+        Err(_) => return false,
+    };
+    let files_fresh = files.iter().all(|file| {
+        let file = match cmd.get_cwd() {
+            Some(cwd) => cwd.join(file),
+            None => file.clone(),
+        };
+        matches!(paths::mtime(&file), Ok(file_mtime) if file_mtime <= mtime)
+    });
+    files_fresh
+        && env.iter().all(|(key, value)| {
+            cmd.get_env(key).as_deref().and_then(OsStr::to_str) == value.as_deref()
+        })
+}
+
+/// The dep-info file rustc writes for `cmd`, from the flags Cargo passes it.
+fn rustc_dep_info(cmd: &ProcessBuilder) -> Option<PathBuf> {
+    let args = cmd.get_args();
+    let value = |flag: &str| {
+        let index = args.iter().position(|arg| arg == flag)?;
+        args.get(index + 1)?.to_str()
+    };
+    let out_dir = value("--out-dir")?;
+    let crate_name = value("--crate-name")?;
+    let extra_filename = args
+        .iter()
+        .filter_map(|arg| arg.to_str()?.strip_prefix("extra-filename="))
+        .next()
+        .unwrap_or("");
+    let path = Path::new(out_dir).join(format!("{}{}.d", crate_name, extra_filename));
+    Some(match cmd.get_cwd() {
+        Some(cwd) => cwd.join(path),
+        None => path,
+    })
+}
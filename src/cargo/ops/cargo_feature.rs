@@ -0,0 +1,159 @@
+//! Implementation of `cargo feature explain`.
+
+use crate::core::compiler::{CompileKind, RustcTargetData};
+use crate::core::resolver::features::{
+    CliFeatures, FeatureActivation, FeatureOpts, FeatureResolver, ForceAllTargets, HasDevUnits,
+};
+use crate::core::{PackageId, PackageIdSpec, Workspace};
+use crate::drop_println;
+use crate::ops::{self, Packages};
+use crate::util::interning::InternedString;
+use crate::util::CargoResult;
+use anyhow::bail;
+
+pub struct ExplainFeatureOptions {
+    /// The feature to explain, as `pkgid-spec/feature`.
+    pub feature: String,
+    pub cli_features: CliFeatures,
+    /// The workspace members to resolve features for.
+    pub packages: Packages,
+    /// The platforms to resolve features for.
+    pub targets: Vec<String>,
+}
+
+/// Prints why a feature of a package is activated.
+pub fn explain_feature(ws: &Workspace<'_>, opts: &ExplainFeatureOptions) -> CargoResult<()> {
+    let config = ws.config();
+    let (spec, feature) = match opts.feature.rsplit_once('/') {
+        Some((spec, feature)) if !spec.is_empty() && !feature.is_empty() => {
+            (PackageIdSpec::parse(spec)?, InternedString::new(feature))
+        }
+        _ => bail!(
+            "invalid feature `{}`, expected the form `<package>/<feature>`",
+            opts.feature
+        ),
+    };
+
+    let requested_kinds = CompileKind::from_requested_targets(config, &opts.targets)?;
+    let target_data = RustcTargetData::new(ws, &requested_kinds)?;
+    let specs = opts.packages.to_package_id_specs(ws)?;
+    let ws_resolve = ops::resolve_ws_with_opts(
+        ws,
+        &target_data,
+        &requested_kinds,
+        &opts.cli_features,
+        &specs,
+        HasDevUnits::Yes,
+        ForceAllTargets::No,
+    )?;
+    let resolve = &ws_resolve.targeted_resolve;
+    let pkg_id = spec.query(resolve.iter())?;
+    if !resolve.summary(pkg_id).features().contains_key(&feature) {
+        bail!(
+            "package `{}` does not have the feature `{}`",
+            pkg_id,
+            feature
+        );
+    }
+
+    let feature_opts = FeatureOpts::new(ws, HasDevUnits::Yes, ForceAllTargets::No)?;
+    let decoupled = feature_opts.decouples_host_deps();
+    let explanations = FeatureResolver::explain(
+        ws,
+        &target_data,
+        resolve,
+        &ws_resolve.pkg_set,
+        &opts.cli_features,
+        &specs,
+        &requested_kinds,
+        feature_opts,
+        pkg_id,
+        feature,
+    )?;
+
+    if explanations.is_empty() {
+        drop_println!(
+            config,
+            "feature `{}` of `{}` is not activated",
+            feature,
+            pkg_id
+        );
+        return Ok(());
+    }
+    for (i, explanation) in explanations.iter().enumerate() {
+        if i > 0 {
+            drop_println!(config);
+        }
+        let variant = match (decoupled, explanation.for_host) {
+            (false, _) => "",
+            (true, false) => " for the target",
+            (true, true) => " for the host",
+        };
+        drop_println!(
+            config,
+            "feature `{}` of `{}` is activated{} by:",
+            feature,
+            pkg_id,
+            variant
+        );
+        for activation in &explanation.activations {
+            drop_println!(config, "  - {}", describe(activation, pkg_id, decoupled));
+        }
+    }
+    if !decoupled {
+        drop_println!(
+            config,
+            "note: features are unified between host and target dependencies, \
+             see `resolver = \"2\"` to keep them separate"
+        );
+    }
+    Ok(())
+}
+
+fn describe(activation: &FeatureActivation, pkg_id: PackageId, decoupled: bool) -> String {
+    let parent_name = |parent: &PackageId, for_host: bool| {
+        if decoupled && for_host {
+            format!("`{}` (for the host)", parent)
+        } else {
+            format!("`{}`", parent)
+        }
+    };
+    match activation {
+        FeatureActivation::CommandLine => "the command line".to_string(),
+        FeatureActivation::Feature(other) => {
+            format!("feature `{}` of `{}`", other, pkg_id)
+        }
+        FeatureActivation::Dependency {
+            parent,
+            parent_for_host,
+            default,
+        } => {
+            if *default {
+                format!(
+                    "the default features of the dependency in {}",
+                    parent_name(parent, *parent_for_host)
+                )
+            } else {
+                format!(
+                    "the dependency declaration in {}",
+                    parent_name(parent, *parent_for_host)
+                )
+            }
+        }
+        FeatureActivation::DepFeature {
+            parent,
+            parent_for_host,
+            parent_feature,
+            weak,
+        } => format!(
+            "feature `{}` of {}{}",
+            parent_feature,
+            parent_name(parent, *parent_for_host),
+            if *weak {
+                " (weak dependency feature)"
+            } else {
+                ""
+            }
+        ),
+    }
+}
@@ -0,0 +1,96 @@
+//! Implementation of `cargo fuzz-run`.
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use crate::core::compiler::UnitOutput;
+use crate::core::{Feature, Workspace};
+use crate::ops;
+use crate::util::CargoResult;
+use anyhow::bail;
+use cargo_util::paths;
+
+pub struct FuzzRunOptions {
+    /// The name of the fuzz target to run.
+    pub target: String,
+    /// The corpus directory, `fuzz/corpus/<target>` of the package by
+    /// default.
+    pub corpus: Option<PathBuf>,
+    /// The arguments passed on to libFuzzer.
+    pub args: Vec<OsString>,
+}
+
+/// Builds a fuzz target and runs it on its corpus.
+pub fn fuzz_run(
+    ws: &Workspace<'_>,
+    options: &ops::CompileOptions,
+    fuzz_options: &FuzzRunOptions,
+) -> CargoResult<()> {
+    let config = ws.config();
+    if !ws.unstable_features().is_enabled(Feature::fuzz_targets()) {
+        bail!(
+            "`cargo fuzz-run` requires the `fuzz-targets` feature\n\
+             Consider adding `cargo-features = [\"fuzz-targets\"]` to the top of {}",
+            ws.root_manifest().display()
+        );
+    }
+    if options.filter.contains_glob_patterns() {
+        bail!("`cargo fuzz-run` does not support glob patterns on target selection")
+    }
+    options.build_config.single_requested_kind()?;
+
+    // Find the package up front, to report ambiguous names before building.
+    let packages = options.spec.get_packages(ws)?;
+    let candidates: Vec<_> = packages
+        .iter()
+        .filter(|pkg| {
+            pkg.targets()
+                .iter()
+                .any(|t| t.is_fuzz() && t.name() == fuzz_options.target)
+        })
+        .collect();
+    if candidates.len() > 1 {
+        let mut names: Vec<_> = candidates.iter().map(|pkg| pkg.name()).collect();
+        names.sort();
+        bail!(
+            "fuzz target `{}` is defined in multiple packages: {}\n\
+             Use the `-p` option to select one.",
+            fuzz_options.target,
+            names.join(", ")
+        );
+    }
+
+    let compile = ops::compile(ws, options)?;
+    assert_eq!(compile.binaries.len(), 1);
+    let UnitOutput {
+        unit,
+        path,
+        script_meta,
+    } = &compile.binaries[0];
+
+    let fuzz_dir = unit.pkg.root().join("fuzz");
+    let corpus = match &fuzz_options.corpus {
+        Some(corpus) => corpus.clone(),
+        None => fuzz_dir.join("corpus").join(unit.target.name()),
+    };
+    let artifacts = fuzz_dir.join("artifacts").join(unit.target.name());
+    paths::create_dir_all(&corpus)?;
+    paths::create_dir_all(&artifacts)?;
+
+    let cwd = config.cwd();
+    let relative = |path: &Path| path.strip_prefix(cwd).unwrap_or(path).to_path_buf();
+    let mut artifact_prefix = OsString::from("-artifact_prefix=");
+    // libFuzzer prepends the prefix as is, so it has to end with a separator.
+    artifact_prefix.push(relative(&artifacts).join(""));
+
+    let mut process = compile.target_process(path, unit.kind, &unit.pkg, *script_meta)?;
+    process
+        .arg(artifact_prefix)
+        .args(&fuzz_options.args)
+        .arg(relative(&corpus))
+        .cwd(cwd);
+
+    config.shell().status("Running", process.to_string())?;
+
+    process.exec_replace()
+}
@@ -17,6 +17,8 @@ pub struct UpdateOptions<'a> {
     pub aggressive: bool,
     pub dry_run: bool,
     pub workspace: bool,
+    /// Resolve the merge conflicts of `Cargo.lock` instead of updating.
+    pub merge: bool,
 }
 
 pub fn generate_lockfile(ws: &Workspace<'_>) -> CargoResult<()> {
@@ -40,6 +42,10 @@ pub fn update_lockfile(ws: &Workspace<'_>, opts: &UpdateOptions<'_>) -> CargoRes
         anyhow::bail!("cannot specify both aggressive and precise simultaneously")
     }
 
+    if opts.merge && (!opts.to_update.is_empty() || opts.workspace) {
+        anyhow::bail!("cannot specify packages to update when resolving merge conflicts")
+    }
+
     if ws.members().count() == 0 {
         anyhow::bail!("you can't generate a lockfile for an empty workspace.")
     }
@@ -48,26 +54,36 @@ pub fn update_lockfile(ws: &Workspace<'_>, opts: &UpdateOptions<'_>) -> CargoRes
     // that we're synchronized against other Cargos.
     let _lock = ws.config().acquire_package_cache_lock()?;
 
-    let previous_resolve = match ops::load_pkg_lockfile(ws)? {
-        Some(resolve) => resolve,
-        None => {
-            match opts.precise {
-                None => return generate_lockfile(ws),
-
-                // Precise option specified, so calculate a previous_resolve required
-                // by precise package update later.
-                Some(_) => {
-                    let mut registry = PackageRegistry::new(opts.config)?;
-                    ops::resolve_with_previous(
-                        &mut registry,
-                        ws,
-                        &CliFeatures::new_all(true),
-                        HasDevUnits::Yes,
-                        None,
-                        None,
-                        &[],
-                        true,
-                    )?
+    // With `--merge` the changes are reported against our side of the
+    // conflicts, and the previous resolve is the union of both sides, which is
+    // re-resolved against the merged manifests.
+    let mut union = None;
+    let previous_resolve = if opts.merge {
+        let (ours, theirs) = ops::load_conflicted_lockfile(ws)?;
+        union = Some(ours.union(&theirs));
+        ours
+    } else {
+        match ops::load_pkg_lockfile(ws)? {
+            Some(resolve) => resolve,
+            None => {
+                match opts.precise {
+                    None => return generate_lockfile(ws),
+
+                    // Precise option specified, so calculate a previous_resolve required
+                    // by precise package update later.
+                    Some(_) => {
+                        let mut registry = PackageRegistry::new(opts.config)?;
+                        ops::resolve_with_previous(
+                            &mut registry,
+                            ws,
+                            &CliFeatures::new_all(true),
+                            HasDevUnits::Yes,
+                            None,
+                            None,
+                            &[],
+                            true,
+                        )?
+                    }
                 }
             }
         }
@@ -75,7 +91,9 @@ pub fn update_lockfile(ws: &Workspace<'_>, opts: &UpdateOptions<'_>) -> CargoRes
     let mut registry = PackageRegistry::new(opts.config)?;
     let mut to_avoid = HashSet::new();
 
-    if opts.to_update.is_empty() {
+    if opts.merge {
+        // Everything stays locked wherever the manifests allow it.
+    } else if opts.to_update.is_empty() {
         if !opts.workspace {
             to_avoid.extend(previous_resolve.iter());
             to_avoid.extend(previous_resolve.unused_patches());
@@ -121,7 +139,7 @@ pub fn update_lockfile(ws: &Workspace<'_>, opts: &UpdateOptions<'_>) -> CargoRes
         ws,
         &CliFeatures::new_all(true),
         HasDevUnits::Yes,
-        Some(&previous_resolve),
+        Some(union.as_ref().unwrap_or(&previous_resolve)),
         Some(&to_avoid),
         &[],
         true,
@@ -0,0 +1,203 @@
+//! Implementation of `cargo git-bundle`, which exports the locked git
+//! dependencies of a workspace as git bundles, along with the source
+//! replacement config fetching them from the bundles, for the builds
+//! without network access.
+//!
+//! A bundle is made for each git repository, from its database in the Cargo
+//! home. The bundle has the references that Cargo fetches for the `branch`,
+//! `tag` or `rev` of each dependency, pointing at the locked revisions. As
+//! libgit2 can't read bundles, they are made and fetched with the `git` CLI.
+
+use crate::core::shell::Verbosity;
+use crate::core::{GitReference, SourceId, Workspace};
+use crate::ops;
+use crate::sources::GitSource;
+use crate::util::CargoResult;
+use anyhow::Context as _;
+use cargo_util::{paths, ProcessBuilder};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use url::Url;
+
+#[derive(Serialize)]
+struct BundleConfig {
+    source: BTreeMap<String, BundleSource>,
+}
+
+#[derive(Serialize)]
+struct BundleSource {
+    git: String,
+    branch: Option<String>,
+    tag: Option<String>,
+    rev: Option<String>,
+    #[serde(rename = "replace-with")]
+    replace_with: Option<String>,
+}
+
+impl BundleSource {
+    fn new(url: String, reference: &GitReference, replace_with: Option<String>) -> BundleSource {
+        let mut source = BundleSource {
+            git: url,
+            branch: None,
+            tag: None,
+            rev: None,
+            replace_with,
+        };
+        match reference {
+            GitReference::Branch(b) => source.branch = Some(b.clone()),
+            GitReference::Tag(t) => source.tag = Some(t.clone()),
+            GitReference::Rev(r) => source.rev = Some(r.clone()),
+            GitReference::DefaultBranch => {}
+        }
+        source
+    }
+}
+
+pub fn git_bundle(ws: &Workspace<'_>, destination: &Path) -> CargoResult<()> {
+    let config = ws.config();
+    let (packages, resolve) = ops::resolve_ws(ws).with_context(|| "failed to load pkg lockfile")?;
+    let git_ids: Vec<_> = resolve
+        .iter()
+        .filter(|id| id.source_id().is_git())
+        .collect();
+    // Downloading the packages fetches the locked revisions into the git
+    // databases.
+    packages
+        .get_many(git_ids.iter().cloned())
+        .with_context(|| "failed to download packages")?;
+
+    // The sources of each git database, by the name of the database.
+    let mut repos: BTreeMap<String, (PathBuf, BTreeSet<SourceId>)> = BTreeMap::new();
+    for id in git_ids {
+        let db_path = GitSource::new(id.source_id(), config)?.db_path();
+        let name = db_path.file_name().unwrap().to_string_lossy().into_owned();
+        repos
+            .entry(name)
+            .or_insert_with(|| (db_path, BTreeSet::new()))
+            .1
+            .insert(id.source_id());
+    }
+
+    paths::create_dir_all(destination)?;
+    let destination = destination.canonicalize()?;
+    for entry in destination.read_dir()? {
+        let path = entry?.path();
+        let stale = path.extension().map_or(false, |ext| ext == "bundle")
+            && !repos.contains_key(&*path.file_stem().unwrap().to_string_lossy());
+        if stale {
+            paths::remove_file(&path)?;
+        }
+    }
+
+    let _lock = config.acquire_package_cache_lock()?;
+    let mut bundle_config = BTreeMap::new();
+    for (name, (db_path, source_ids)) in repos {
+        let bundle = destination.join(format!("{}.bundle", name));
+        let url = source_ids.iter().next().unwrap().url();
+        config
+            .shell()
+            .status("Bundling", format!("{} to {}", url, bundle.display()))?;
+        write_bundle(&db_path, &source_ids, &bundle)
+            .with_context(|| format!("failed to bundle the git repository `{}`", url))?;
+
+        let bundle_url = Url::from_file_path(&bundle).unwrap().to_string();
+        for source_id in source_ids {
+            let reference = source_id.git_reference().unwrap();
+            let (orig_name, replace_name) = match reference.pretty_ref() {
+                Some(pretty) => (
+                    format!("{}?{}", source_id.url(), pretty),
+                    format!("git-bundle-{}?{}", name, pretty),
+                ),
+                None => (source_id.url().to_string(), format!("git-bundle-{}", name)),
+            };
+            bundle_config.insert(
+                orig_name,
+                BundleSource::new(
+                    source_id.url().to_string(),
+                    reference,
+                    Some(replace_name.clone()),
+                ),
+            );
+            bundle_config.insert(
+                replace_name,
+                BundleSource::new(bundle_url.clone(), reference, None),
+            );
+        }
+    }
+
+    if config.shell().verbosity() != Verbosity::Quiet {
+        if bundle_config.is_empty() {
+            config
+                .shell()
+                .warn("the workspace has no git dependencies to bundle")?;
+        } else {
+            crate::drop_eprint!(
+                config,
+                "To use the git bundles, add this to your .cargo/config.toml for this project:\n\n"
+            );
+            let bundle_config = BundleConfig {
+                source: bundle_config,
+            };
+            crate::drop_print!(config, "{}", &toml::to_string(&bundle_config).unwrap());
+        }
+    }
+    Ok(())
+}
+
+/// Writes the bundle of the locked revisions of `source_ids` from the git
+/// database `db_path`.
+///
+/// The references are made in a temporary repository borrowing the objects
+/// of the database, which is left untouched.
+fn write_bundle(db_path: &Path, source_ids: &BTreeSet<SourceId>, bundle: &Path) -> CargoResult<()> {
+    let tmp = tempfile::Builder::new()
+        .prefix("cargo-git-bundle")
+        .tempdir()?;
+    git2::Repository::init_bare(tmp.path())?;
+    paths::write(
+        &tmp.path().join("objects/info/alternates"),
+        format!("{}\n", db_path.join("objects").display()),
+    )?;
+    let repo = git2::Repository::open_bare(tmp.path())?;
+
+    let mut refs = Vec::new();
+    for source_id in source_ids {
+        let rev = git2::Oid::from_str(source_id.precise().unwrap())?;
+        // A branch for the revisions Cargo fetches with all the branches and
+        // `HEAD`.
+        let locked = format!("refs/heads/cargo-locked-{}", rev);
+        let name = match source_id.git_reference().unwrap() {
+            GitReference::Branch(b) => format!("refs/heads/{}", b),
+            GitReference::Tag(t) => format!("refs/tags/{}", t),
+            GitReference::Rev(r) if r.starts_with("refs/") => r.clone(),
+            GitReference::Rev(_) => locked,
+            GitReference::DefaultBranch => {
+                repo.reference(&locked, rev, true, "cargo git-bundle")?;
+                repo.set_head(&locked)?;
+                refs.push(locked);
+                "HEAD".to_string()
+            }
+        };
+        if name != "HEAD" {
+            repo.reference(&name, rev, true, "cargo git-bundle")?;
+        }
+        refs.push(name);
+    }
+    refs.sort();
+    refs.dedup();
+
+    let mut cmd = ProcessBuilder::new("git");
+    cmd.arg("bundle")
+        .arg("create")
+        .arg(bundle)
+        .args(&refs)
+        .env_remove("GIT_DIR")
+        .env_remove("GIT_WORK_TREE")
+        .env_remove("GIT_INDEX_FILE")
+        .env_remove("GIT_OBJECT_DIRECTORY")
+        .env_remove("GIT_ALTERNATE_OBJECT_DIRECTORIES")
+        .cwd(tmp.path());
+    cmd.exec_with_output()?;
+    Ok(())
+}
@@ -1,5 +1,5 @@
 use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use std::sync::Arc;
 use std::{env, fs};
 
@@ -34,6 +34,18 @@ impl Drop for Transaction {
     }
 }
 
+/// The staged install of `cargo install --destdir`, which lets distribution
+/// packagers install binaries into a staging directory instead of the
+/// system, and list them.
+#[derive(Default)]
+pub struct InstallStaging {
+    /// The directory the installation root is staged into.
+    pub destdir: Option<PathBuf>,
+    /// A file to write the installed files to, one per line, as they will be
+    /// once the staging directory is installed.
+    pub install_manifest: Option<PathBuf>,
+}
+
 struct InstallablePackage<'cfg, 'a> {
     config: &'cfg Config,
     opts: &'a ops::CompileOptions,
@@ -272,7 +284,8 @@ impl<'cfg, 'a> InstallablePackage<'cfg, 'a> {
         Ok(duplicates)
     }
 
-    fn install_one(mut self) -> CargoResult<()> {
+    /// Installs the package, returning the paths of the installed binaries.
+    fn install_one(mut self) -> CargoResult<Vec<PathBuf>> {
         self.config.shell().status("Installing", &self.pkg)?;
 
         let dst = self.root.join("bin").into_path_unlocked();
@@ -309,18 +322,32 @@ impl<'cfg, 'a> InstallablePackage<'cfg, 'a> {
                 self.ws.target_dir().display()
             )
         })?;
-        let mut binaries: Vec<(&str, &Path)> = compile
+        let names = compile
             .binaries
             .iter()
-            .map(|UnitOutput { path, .. }| {
+            .map(|UnitOutput { unit, path, .. }| {
+                // `package.artifact-name` only names the artifacts in the
+                // target directory, installed binaries keep their usual names.
+                if unit.pkg.manifest().original().artifact_name().is_some() {
+                    let name = unit
+                        .target
+                        .binary_filename()
+                        .unwrap_or_else(|| unit.target.name().to_string());
+                    return Ok(format!("{}{}", name, env::consts::EXE_SUFFIX));
+                }
                 let name = path.file_name().unwrap();
                 if let Some(s) = name.to_str() {
-                    Ok((s, path.as_ref()))
+                    Ok(s.to_string())
                 } else {
                     bail!("Binary `{:?}` name can't be serialized into string", name)
                 }
             })
-            .collect::<CargoResult<_>>()?;
+            .collect::<CargoResult<Vec<_>>>()?;
+        let mut binaries: Vec<(&str, &Path)> = names
+            .iter()
+            .map(String::as_str)
+            .zip(compile.binaries.iter().map(|output| output.path.as_ref()))
+            .collect();
         if binaries.is_empty() {
             bail!("no binaries are available for install using the selected features");
         }
@@ -446,6 +473,7 @@ impl<'cfg, 'a> InstallablePackage<'cfg, 'a> {
             }
         }
 
+        let installed_files = successful_bins.iter().map(|bin| dst.join(bin)).collect();
         if duplicates.is_empty() {
             self.config.shell().status(
                 "Installed",
@@ -455,7 +483,7 @@ impl<'cfg, 'a> InstallablePackage<'cfg, 'a> {
                     executables(successful_bins.iter())
                 ),
             )?;
-            Ok(())
+            Ok(installed_files)
         } else {
             if !to_install.is_empty() {
                 self.config.shell().status(
@@ -481,7 +509,7 @@ impl<'cfg, 'a> InstallablePackage<'cfg, 'a> {
                     ),
                 )?;
             }
-            Ok(())
+            Ok(installed_files)
         }
     }
 
@@ -526,15 +554,30 @@ pub fn install(
     opts: &ops::CompileOptions,
     force: bool,
     no_track: bool,
+    staging: &InstallStaging,
 ) -> CargoResult<()> {
-    let root = resolve_root(root, config)?;
+    let prefix = resolve_root(root, config)?;
+    let destdir = match &staging.destdir {
+        Some(destdir) => Some(destdir.clone()),
+        None if config.cli_unstable().staged_install => {
+            config.get_path("install.destdir")?.map(|v| v.val)
+        }
+        None => None,
+    };
+    // A staged install isn't tracked, the tracking files would be packaged
+    // along with the binaries.
+    let (root, no_track) = match &destdir {
+        Some(destdir) => (staged_root(destdir, &prefix)?, true),
+        None => (prefix.clone(), no_track),
+    };
     let dst = root.join("bin").into_path_unlocked();
     let map = SourceConfigMap::new(config)?;
+    let mut installed_files = Vec::new();
 
     let (installed_anything, scheduled_error) = if krates.len() <= 1 {
         let installable_pkg = InstallablePackage::new(
             config,
-            root,
+            root.clone(),
             map,
             krates.into_iter().next(),
             source_id,
@@ -546,7 +589,7 @@ pub fn install(
             true,
         )?;
         if let Some(installable_pkg) = installable_pkg {
-            installable_pkg.install_one()?;
+            installed_files = installable_pkg.install_one()?;
         }
         (true, false)
     } else {
@@ -601,7 +644,8 @@ pub fn install(
 
         for (krate, result) in install_results {
             match result {
-                Ok(()) => {
+                Ok(files) => {
+                    installed_files.extend(files);
                     succeeded.push(krate);
                 }
                 Err(e) => {
@@ -628,7 +672,11 @@ pub fn install(
         (!succeeded.is_empty(), !failed.is_empty())
     };
 
-    if installed_anything {
+    if let Some(install_manifest) = &staging.install_manifest {
+        write_install_manifest(install_manifest, &installed_files, &root, &prefix)?;
+    }
+
+    if installed_anything && destdir.is_none() {
         // Print a warning that if this directory isn't in PATH that they won't be
         // able to run these commands.
         let path = env::var_os("PATH").unwrap_or_default();
@@ -650,6 +698,54 @@ pub fn install(
     Ok(())
 }
 
+/// The directory `prefix` is staged into under `destdir`.
+fn staged_root(destdir: &Path, prefix: &Filesystem) -> CargoResult<Filesystem> {
+    let prefix = prefix.as_path_unlocked();
+    if !prefix.is_absolute() {
+        bail!(
+            "the installation root `{}` must be an absolute path to be staged into `{}`",
+            prefix.display(),
+            destdir.display()
+        );
+    }
+    let relative: PathBuf = prefix
+        .components()
+        .filter(|c| !matches!(c, Component::Prefix(_) | Component::RootDir))
+        .collect();
+    Ok(Filesystem::new(destdir.join(relative)))
+}
+
+/// Writes the files installed into `root` to `path`, as they are once `root`
+/// is installed to `prefix`.
+fn write_install_manifest(
+    path: &Path,
+    installed_files: &[PathBuf],
+    root: &Filesystem,
+    prefix: &Filesystem,
+) -> CargoResult<()> {
+    let root = root.as_path_unlocked();
+    let prefix = prefix.as_path_unlocked();
+    let mut files: Vec<_> = installed_files
+        .iter()
+        .map(|file| match file.strip_prefix(root) {
+            Ok(relative) => prefix.join(relative),
+            Err(_) => file.clone(),
+        })
+        .collect();
+    files.sort();
+    files.dedup();
+    let mut contents = String::new();
+    for file in files {
+        contents.push_str(&file.display().to_string());
+        contents.push('\n');
+    }
+    if let Some(parent) = path.parent() {
+        paths::create_dir_all(parent)?;
+    }
+    paths::write(path, contents)
+        .with_context(|| format!("failed to write the install manifest `{}`", path.display()))
+}
+
 fn is_installed(
     pkg: &Package,
     config: &Config,
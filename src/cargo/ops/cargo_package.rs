@@ -8,11 +8,13 @@ use std::sync::Arc;
 
 use crate::core::compiler::{BuildConfig, CompileMode, DefaultExecutor, Executor};
 use crate::core::resolver::CliFeatures;
-use crate::core::{Feature, Shell, Verbosity, Workspace};
+use crate::core::{ChangelogConfig, Feature, Shell, Verbosity, Workspace};
 use crate::core::{Package, PackageId, PackageSet, Resolve, Source, SourceId};
+use crate::ops::changelog;
 use crate::sources::PathSource;
 use crate::util::errors::CargoResult;
 use crate::util::toml::TomlManifest;
+use crate::util::vcs_state::{self, DirtyPolicy, VcsCommand};
 use crate::util::{self, restricted_names, Config, FileLock};
 use crate::{drop_println, ops};
 use anyhow::Context as _;
@@ -28,6 +30,8 @@ pub struct PackageOpts<'cfg> {
     pub list: bool,
     pub check_metadata: bool,
     pub allow_dirty: bool,
+    /// The command packaging, whose `vcs.allow-dirty` policy applies.
+    pub vcs_command: VcsCommand,
     pub verify: bool,
     pub jobs: Option<u32>,
     pub to_package: ops::Packages,
@@ -61,6 +65,8 @@ enum GeneratedFile {
     Lockfile,
     /// Adds a `.cargo_vcs_info.json` file if in a (clean) git repo.
     VcsInfo(VcsInfo),
+    /// Adds the changelog fragments to a new section of the changelog.
+    Changelog(String),
 }
 
 #[derive(Serialize)]
@@ -73,6 +79,10 @@ struct VcsInfo {
 #[derive(Serialize)]
 struct GitVcsInfo {
     sha1: String,
+    /// Whether the package has uncommitted changes, packaged anyway as the
+    /// `vcs.allow-dirty` policy is `warn`.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    dirty: bool,
 }
 
 pub fn package_one(
@@ -98,14 +108,19 @@ pub fn package_one(
 
     // Check (git) repository state, getting the current commit hash if not
     // dirty.
-    let vcs_info = if !opts.allow_dirty {
-        // This will error if a dirty repo is found.
-        check_repo_state(pkg, &src_files, config)?
+    let policy = DirtyPolicy::for_command(config, opts.vcs_command, opts.allow_dirty)?;
+    let vcs_info = if policy != DirtyPolicy::Allow {
+        // This will error if a dirty repo is found, unless the policy is to
+        // warn about it.
+        check_repo_state(pkg, &src_files, config, policy, opts.vcs_command)?
     } else {
         None
     };
 
-    let ar_files = build_ar_list(ws, pkg, src_files, vcs_info)?;
+    let mut ar_files = build_ar_list(ws, pkg, src_files, vcs_info)?;
+    if let Some(changelog) = ws.changelog() {
+        assemble_changelog(ws, pkg, changelog, &mut ar_files)?;
+    }
 
     if opts.list {
         for ar_file in ar_files {
@@ -175,6 +190,7 @@ pub fn package(ws: &Workspace<'_>, opts: &PackageOpts<'_>) -> CargoResult<Option
                 list: opts.list,
                 check_metadata: opts.check_metadata,
                 allow_dirty: opts.allow_dirty,
+                vcs_command: opts.vcs_command,
                 verify: opts.verify,
                 jobs: opts.jobs,
                 to_package: ops::Packages::Default,
@@ -319,6 +335,68 @@ fn build_ar_list(
     Ok(result)
 }
 
+/// Checks that the changelog of `pkg` has an entry for its version. If it has
+/// none, its changelog fragments are assembled into a new section of the
+/// packaged changelog, in place of the fragments themselves.
+fn assemble_changelog(
+    ws: &Workspace<'_>,
+    pkg: &Package,
+    changelog_config: &ChangelogConfig,
+    ar_files: &mut Vec<ArchiveFile>,
+) -> CargoResult<()> {
+    let path = pkg.root().join(&changelog_config.file);
+    let old = if path.exists() {
+        Some(paths::read(&path)?)
+    } else {
+        None
+    };
+    if let Some(old) = &old {
+        if changelog::has_entry(old, pkg.version()) {
+            return Ok(());
+        }
+    }
+    let fragments = changelog::fragments(&pkg.root().join(&changelog_config.fragments))?;
+    if fragments.is_empty() {
+        let msg = format!(
+            "no changelog entry for {} v{}, add a fragment to `{}` or a section for {} to `{}`",
+            pkg.name(),
+            pkg.version(),
+            changelog_config.fragments.display(),
+            pkg.version(),
+            changelog_config.file.display()
+        );
+        if changelog_config.require_entry {
+            anyhow::bail!(msg);
+        }
+        return ws.config().shell().warn(msg);
+    }
+
+    ws.config().shell().status(
+        "Assembling",
+        format!(
+            "{} changelog fragments into `{}`",
+            fragments.len(),
+            changelog_config.file.display()
+        ),
+    )?;
+    let date = changelog::release_date(ws.config())?;
+    let section = changelog::fragments_section(pkg.version(), &date, &fragments);
+    let contents = changelog::add_section(old.as_deref(), &section);
+    ar_files.retain(|ar_file| match &ar_file.contents {
+        FileContents::OnDisk(disk_path) => {
+            *disk_path != path && fragments.iter().all(|f| f.path != *disk_path)
+        }
+        FileContents::Generated(_) => true,
+    });
+    ar_files.push(ArchiveFile {
+        rel_path: changelog_config.file.clone(),
+        rel_str: changelog_config.file.to_str().unwrap().replace('\\', "/"),
+        contents: FileContents::Generated(GeneratedFile::Changelog(contents)),
+    });
+    ar_files.sort_unstable_by(|a, b| a.rel_path.cmp(&b.rel_path));
+    Ok(())
+}
+
 /// Construct `Cargo.lock` for the package to be published.
 fn build_lock(ws: &Workspace<'_>, orig_pkg: &Package) -> CargoResult<String> {
     let config = ws.config();
@@ -392,43 +470,45 @@ fn check_metadata(pkg: &Package, config: &Config) -> CargoResult<()> {
 
 /// Checks if the package source is in a *git* DVCS repository. If *git*, and
 /// the source is *dirty* (e.g., has uncommitted changes) then `bail!` with an
-/// informative message. Otherwise return the sha1 hash of the current *HEAD*
-/// commit, or `None` if no repo is found.
+/// informative message, or warn about it if that is the `policy`. Otherwise
+/// return the sha1 hash of the current *HEAD* commit, or `None` if no repo is
+/// found.
 fn check_repo_state(
     p: &Package,
     src_files: &[PathBuf],
     config: &Config,
+    policy: DirtyPolicy,
+    command: VcsCommand,
 ) -> CargoResult<Option<VcsInfo>> {
-    if let Ok(repo) = git2::Repository::discover(p.root()) {
-        if let Some(workdir) = repo.workdir() {
-            debug!("found a git repo at {:?}", workdir);
-            let path = p.manifest_path();
-            let path = path.strip_prefix(workdir).unwrap_or(path);
-            if let Ok(status) = repo.status_file(path) {
-                if (status & git2::Status::IGNORED).is_empty() {
-                    debug!(
-                        "found (git) Cargo.toml at {:?} in workdir {:?}",
-                        path, workdir
-                    );
-                    let path_in_vcs = path
-                        .parent()
-                        .and_then(|p| p.to_str())
-                        .unwrap_or("")
-                        .replace("\\", "/");
-                    return Ok(Some(VcsInfo {
-                        git: git(p, src_files, &repo)?,
-                        path_in_vcs,
-                    }));
-                }
+    if let Some(repo) = vcs_state::discover(p.root()) {
+        let workdir = repo.workdir().unwrap();
+        debug!("found a git repo at {:?}", workdir);
+        let path = vcs_state::path_in_repo(&repo, p.manifest_path())
+            .unwrap_or_else(|| p.manifest_path().to_path_buf());
+        if let Ok(status) = repo.status_file(&path) {
+            if (status & git2::Status::IGNORED).is_empty() {
+                debug!(
+                    "found (git) Cargo.toml at {:?} in workdir {:?}",
+                    path, workdir
+                );
+                let path_in_vcs = path
+                    .parent()
+                    .and_then(|p| p.to_str())
+                    .unwrap_or("")
+                    .replace("\\", "/");
+                return Ok(Some(VcsInfo {
+                    git: git(p, src_files, &repo, config, policy, command)?,
+                    path_in_vcs,
+                }));
             }
-            config.shell().verbose(|shell| {
-                shell.warn(format!(
-                    "No (git) Cargo.toml found at `{}` in workdir `{}`",
-                    path.display(),
-                    workdir.display()
-                ))
-            })?;
         }
+        config.shell().verbose(|shell| {
+            shell.warn(format!(
+                "No (git) Cargo.toml found at `{}` in workdir `{}`",
+                path.display(),
+                workdir.display()
+            ))
+        })?;
     } else {
         config.shell().verbose(|shell| {
             shell.warn(format!("No (git) VCS found for `{}`", p.root().display()))
@@ -439,17 +519,28 @@ fn check_repo_state(
     // directory is dirty or not, thus we have to assume that it's clean.
     return Ok(None);
 
-    fn git(p: &Package, src_files: &[PathBuf], repo: &git2::Repository) -> CargoResult<GitVcsInfo> {
+    fn git(
+        p: &Package,
+        src_files: &[PathBuf],
+        repo: &git2::Repository,
+        config: &Config,
+        policy: DirtyPolicy,
+        command: VcsCommand,
+    ) -> CargoResult<GitVcsInfo> {
         // This is a collection of any dirty or untracked files. This covers:
         // - new/modified/deleted/renamed/type change (index or worktree)
         // - untracked files (which are "new" worktree files)
         // - ignored (in case the user has an `include` directive that
         //   conflicts with .gitignore).
-        let mut dirty_files = Vec::new();
-        collect_statuses(repo, &mut dirty_files)?;
-        // Include each submodule so that the error message can provide
-        // specifically *which* files in a submodule are modified.
-        status_submodules(repo, &mut dirty_files)?;
+        // The files of each submodule are included so that the error
+        // message can provide specifically *which* files in a submodule are
+        // modified.
+        let dirty_files: Vec<PathBuf> = vcs_state::changed_files(repo, true)?
+            .into_iter()
+            // It is OK to include Cargo.lock even if it is ignored.
+            .filter(|file| !(file.ignored && file.path.ends_with("Cargo.lock")))
+            .map(|file| file.path)
+            .collect();
 
         // Find the intersection of dirty in git, and the src_files that would
         // be packaged. This is a lazy n^2 check, but seems fine with
@@ -464,70 +555,26 @@ fn check_repo_state(
                     .to_string()
             })
             .collect();
-        if dirty_src_files.is_empty() {
-            let rev_obj = repo.revparse_single("HEAD")?;
-            Ok(GitVcsInfo {
-                sha1: rev_obj.id().to_string(),
-            })
-        } else {
-            anyhow::bail!(
-                "{} files in the working directory contain changes that were \
-                 not yet committed into git:\n\n{}\n\n\
-                 to proceed despite this and include the uncommitted changes, pass the `--allow-dirty` flag",
-                dirty_src_files.len(),
-                dirty_src_files.join("\n")
-            )
-        }
-    }
-
-    // Helper to collect dirty statuses for a single repo.
-    fn collect_statuses(
-        repo: &git2::Repository,
-        dirty_files: &mut Vec<PathBuf>,
-    ) -> CargoResult<()> {
-        let mut status_opts = git2::StatusOptions::new();
-        // Exclude submodules, as they are being handled manually by recursing
-        // into each one so that details about specific files can be
-        // retrieved.
-        status_opts
-            .exclude_submodules(true)
-            .include_ignored(true)
-            .include_untracked(true);
-        let repo_statuses = repo.statuses(Some(&mut status_opts)).with_context(|| {
-            format!(
-                "failed to retrieve git status from repo {}",
-                repo.path().display()
-            )
-        })?;
-        let workdir = repo.workdir().unwrap();
-        let this_dirty = repo_statuses.iter().filter_map(|entry| {
-            let path = entry.path().expect("valid utf-8 path");
-            if path.ends_with("Cargo.lock") && entry.status() == git2::Status::IGNORED {
-                // It is OK to include Cargo.lock even if it is ignored.
-                return None;
-            }
-            // Use an absolute path, so that comparing paths is easier
-            // (particularly with submodules).
-            Some(workdir.join(path))
-        });
-        dirty_files.extend(this_dirty);
-        Ok(())
-    }
-
-    // Helper to collect dirty statuses while recursing into submodules.
-    fn status_submodules(
-        repo: &git2::Repository,
-        dirty_files: &mut Vec<PathBuf>,
-    ) -> CargoResult<()> {
-        for submodule in repo.submodules()? {
-            // Ignore submodules that don't open, they are probably not initialized.
-            // If its files are required, then the verification step should fail.
-            if let Ok(sub_repo) = submodule.open() {
-                status_submodules(&sub_repo, dirty_files)?;
-                collect_statuses(&sub_repo, dirty_files)?;
-            }
+        let dirty = !dirty_src_files.is_empty();
+        if dirty {
+            policy.report(
+                config,
+                command,
+                &format!(
+                    "{} files in the working directory contain changes that were \
+                     not yet committed into git:\n\n{}\n\n",
+                    dirty_src_files.len(),
+                    dirty_src_files.join("\n")
+                ),
+                "to proceed despite this and include the uncommitted changes, \
+                 pass the `--allow-dirty` flag",
+            )?;
         }
-        Ok(())
+        let rev_obj = repo.revparse_single("HEAD")?;
+        Ok(GitVcsInfo {
+            sha1: rev_obj.id().to_string(),
+            dirty,
+        })
     }
 }
 
@@ -581,6 +628,7 @@ fn tar(
                     GeneratedFile::Manifest => pkg.to_registry_toml(ws)?,
                     GeneratedFile::Lockfile => build_lock(ws, pkg)?,
                     GeneratedFile::VcsInfo(ref s) => serde_json::to_string_pretty(s)?,
+                    GeneratedFile::Changelog(ref s) => s.clone(),
                 };
                 header.set_entry_type(EntryType::file());
                 header.set_mode(0o644);
@@ -763,9 +811,12 @@ fn run_verify(
             },
             target_rustdoc_args: None,
             target_rustc_args: rustc_args,
+            target_rustc_crate_types: None,
             local_rustdoc_args: None,
             rustdoc_document_private_items: false,
             honor_rust_version: true,
+            target_expr: None,
+            auto_features: false,
         },
         &exec,
     )?;
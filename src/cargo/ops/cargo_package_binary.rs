@@ -0,0 +1,423 @@
+//! Implementation of `cargo package --binary`, which assembles the built
+//! binaries of a package into an archive to distribute them.
+//!
+//! The binaries are built like with `cargo build --release`. For each
+//! package and target, `target/package/<name>-<version>-<target>.tar.gz`, or
+//! `.zip` for Windows targets, has a `<name>-<version>-<target>` directory
+//! with:
+//!
+//! * the binaries,
+//! * the readme of the package,
+//! * the files and directories given with `--include`, like shell completions
+//!   and man pages,
+//! * a `licenses` directory, with the license files of every package linked
+//!   into the binaries and a `README.txt` listing their licenses.
+//!
+//! The packages linked into a binary are the ones its unit depends on,
+//! except for build scripts and proc-macros, which only run while building.
+//! A `<archive>.sha256` file next to the archive has its checksum.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{bail, Context as _};
+use cargo_util::{paths, Sha256};
+use flate2::write::{DeflateEncoder, GzEncoder};
+use flate2::{Compression, Crc};
+use tar::{Builder, EntryType, Header, HeaderMode};
+
+use crate::core::compiler::{Context, DefaultExecutor, Executor, Unit, UnitInterner};
+use crate::core::{Package, PackageId, Workspace};
+use crate::ops::{self, CompileOptions};
+use crate::util::CargoResult;
+
+/// The format of the archives of `cargo package --binary`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryArchiveFormat {
+    TarGz,
+    Zip,
+}
+
+impl BinaryArchiveFormat {
+    pub fn parse(s: &str) -> CargoResult<BinaryArchiveFormat> {
+        match s {
+            "tar.gz" => Ok(BinaryArchiveFormat::TarGz),
+            "zip" => Ok(BinaryArchiveFormat::Zip),
+            _ => bail!("unknown archive format `{}`, expected `tar.gz` or `zip`", s),
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            BinaryArchiveFormat::TarGz => "tar.gz",
+            BinaryArchiveFormat::Zip => "zip",
+        }
+    }
+}
+
+pub struct BinaryPackageOpts {
+    /// Builds the binaries to package.
+    pub compile_opts: CompileOptions,
+    /// The format of the archives, `zip` for Windows targets and `tar.gz`
+    /// for the others by default.
+    pub format: Option<BinaryArchiveFormat>,
+    /// Files and directories of the packages to add to the archives.
+    pub include: Vec<PathBuf>,
+}
+
+/// A file of an archive.
+enum Entry {
+    OnDisk { path: PathBuf, executable: bool },
+    Generated(String),
+}
+
+/// Builds the binaries selected by `opts` and packages them, returning the
+/// paths of the archives.
+pub fn package_binary(ws: &Workspace<'_>, opts: &BinaryPackageOpts) -> CargoResult<Vec<PathBuf>> {
+    let config = ws.config();
+    let interner = UnitInterner::new();
+    let bcx = ops::create_bcx(ws, &opts.compile_opts, &interner)?;
+
+    // The packages linked into the binaries of each package and target.
+    let mut linked: BTreeMap<(PackageId, String), BTreeSet<PackageId>> = BTreeMap::new();
+    let mut packages: BTreeMap<PackageId, Package> = BTreeMap::new();
+    for root in bcx.roots.iter().filter(|unit| unit.target.is_bin()) {
+        let triple = bcx.target_data.short_name(&root.kind).to_string();
+        let set = linked.entry((root.pkg.package_id(), triple)).or_default();
+        let mut seen = HashSet::new();
+        let mut stack = vec![root];
+        while let Some(unit) = stack.pop() {
+            if !seen.insert(unit) {
+                continue;
+            }
+            set.insert(unit.pkg.package_id());
+            packages.insert(unit.pkg.package_id(), unit.pkg.clone());
+            for dep in &bcx.unit_graph[unit] {
+                if !is_build_only(&dep.unit) {
+                    stack.push(&dep.unit);
+                }
+            }
+        }
+    }
+    if linked.is_empty() {
+        bail!("no binaries are available to package using the selected features");
+    }
+
+    let exec: Arc<dyn Executor> = Arc::new(DefaultExecutor);
+    let compilation = Context::new(&bcx)?.compile(&exec)?;
+    let mut binaries: BTreeMap<(PackageId, String), Vec<PathBuf>> = BTreeMap::new();
+    for output in compilation.binaries.iter() {
+        if !output.unit.target.is_bin() {
+            continue;
+        }
+        let triple = bcx.target_data.short_name(&output.unit.kind).to_string();
+        binaries
+            .entry((output.unit.pkg.package_id(), triple))
+            .or_default()
+            .push(output.path.clone());
+    }
+
+    let dir = ws.target_dir().join("package").into_path_unlocked();
+    paths::create_dir_all(&dir)?;
+    let mut archives = Vec::new();
+    for ((pkg_id, triple), linked) in &linked {
+        let pkg = &packages[pkg_id];
+        let base = format!("{}-{}-{}", pkg.name(), pkg.version(), triple);
+        let format = opts.format.unwrap_or(if triple.contains("windows") {
+            BinaryArchiveFormat::Zip
+        } else {
+            BinaryArchiveFormat::TarGz
+        });
+        config
+            .shell()
+            .status("Packaging", format!("{} ({})", pkg, triple))?;
+
+        let mut entries = BTreeMap::new();
+        for bin in binaries
+            .get(&(*pkg_id, triple.clone()))
+            .into_iter()
+            .flatten()
+        {
+            let name = bin.file_name().unwrap().to_str().unwrap().to_string();
+            entries.insert(
+                name,
+                Entry::OnDisk {
+                    path: bin.clone(),
+                    executable: true,
+                },
+            );
+        }
+        if let Some(readme) = &pkg.manifest().metadata().readme {
+            let path = pkg.root().join(readme);
+            if path.is_file() {
+                entries.insert(file_name(&path), on_disk(path));
+            }
+        }
+        for include in &opts.include {
+            add_included(&mut entries, pkg.root(), include)?;
+        }
+        add_licenses(
+            config,
+            &mut entries,
+            pkg,
+            linked.iter().map(|id| &packages[id]),
+        )?;
+
+        let archive = dir.join(format!("{}.{}", base, format.extension()));
+        let file = File::create(&archive)
+            .with_context(|| format!("failed to create `{}`", archive.display()))?;
+        match format {
+            BinaryArchiveFormat::TarGz => write_tar_gz(&file, &base, &entries),
+            BinaryArchiveFormat::Zip => write_zip(&file, &base, &entries),
+        }
+        .with_context(|| format!("failed to write `{}`", archive.display()))?;
+        drop(file);
+
+        let checksum = Sha256::new().update_path(&archive)?.finish_hex();
+        let archive_name = file_name(&archive);
+        paths::write(
+            dir.join(format!("{}.sha256", archive_name)),
+            format!("{}  {}\n", checksum, archive_name),
+        )?;
+        config
+            .shell()
+            .verbose(|shell| shell.status("Packaged", format!("{} files", entries.len())))?;
+        archives.push(archive);
+    }
+    Ok(archives)
+}
+
+/// Whether `unit` only runs while building, and isn't linked into binaries.
+fn is_build_only(unit: &Unit) -> bool {
+    unit.mode.is_run_custom_build() || unit.target.is_custom_build() || unit.target.proc_macro()
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().unwrap().to_string_lossy().into_owned()
+}
+
+fn on_disk(path: PathBuf) -> Entry {
+    Entry::OnDisk {
+        path,
+        executable: false,
+    }
+}
+
+/// Adds the file or directory `include`, relative to the package root, to
+/// the archive.
+fn add_included(
+    entries: &mut BTreeMap<String, Entry>,
+    root: &Path,
+    include: &Path,
+) -> CargoResult<()> {
+    let path = root.join(include);
+    if path.is_file() {
+        entries.insert(archive_path(include), on_disk(path));
+        return Ok(());
+    }
+    if !path.is_dir() {
+        bail!(
+            "the included path `{}` doesn't exist in `{}`",
+            include.display(),
+            root.display()
+        );
+    }
+    for entry in walkdir::WalkDir::new(&path) {
+        let entry = entry?;
+        if entry.file_type().is_file() {
+            let relative = entry.path().strip_prefix(root).unwrap_or(entry.path());
+            entries.insert(archive_path(relative), on_disk(entry.path().to_path_buf()));
+        }
+    }
+    Ok(())
+}
+
+/// The path of a file in an archive, with `/` separators.
+fn archive_path(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Adds the license files of the `linked` packages and a summary of their
+/// licenses to the `licenses` directory of the archive of `pkg`.
+fn add_licenses<'a>(
+    config: &crate::util::Config,
+    entries: &mut BTreeMap<String, Entry>,
+    pkg: &Package,
+    linked: impl Iterator<Item = &'a Package>,
+) -> CargoResult<()> {
+    let mut summary = format!(
+        "The licenses of the packages linked into the binaries of {} v{}.\n\n",
+        pkg.name(),
+        pkg.version()
+    );
+    for linked in linked {
+        let metadata = linked.manifest().metadata();
+        let dir = format!("licenses/{}-{}", linked.name(), linked.version());
+        let files = license_files(linked)?;
+        let license = match (&metadata.license, files.is_empty()) {
+            (Some(license), _) => license.clone(),
+            (None, false) => "see the license files".to_string(),
+            (None, true) => {
+                config.shell().warn(format!(
+                    "package `{}` linked into the binaries of `{}` has no license information",
+                    linked.package_id(),
+                    pkg.name()
+                ))?;
+                "unknown".to_string()
+            }
+        };
+        summary.push_str(&format!(
+            "{} {}: {}\n",
+            linked.name(),
+            linked.version(),
+            license
+        ));
+        for file in files {
+            entries.insert(format!("{}/{}", dir, file_name(&file)), on_disk(file));
+        }
+    }
+    entries.insert("licenses/README.txt".to_string(), Entry::Generated(summary));
+    Ok(())
+}
+
+/// The license files of a package: its `license-file` and the files in its
+/// root like `LICENSE-MIT` or `COPYING`.
+pub(super) fn license_files(pkg: &Package) -> CargoResult<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(pkg.root())
+        .with_context(|| format!("failed to read `{}`", pkg.root().display()))?
+    {
+        let path = entry?.path();
+        let name = file_name(&path).to_ascii_uppercase();
+        if path.is_file()
+            && ["LICENSE", "LICENCE", "COPYING", "NOTICE"]
+                .iter()
+                .any(|prefix| name.starts_with(prefix))
+        {
+            files.push(path);
+        }
+    }
+    files.sort();
+    if let Some(license_file) = &pkg.manifest().metadata().license_file {
+        let path = pkg.root().join(license_file);
+        if path.is_file() && !files.contains(&path) {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+fn write_tar_gz(file: &File, base: &str, entries: &BTreeMap<String, Entry>) -> CargoResult<()> {
+    let encoder = GzEncoder::new(file, Compression::best());
+    let mut ar = Builder::new(encoder);
+    for (name, entry) in entries {
+        let path = format!("{}/{}", base, name);
+        let mut header = Header::new_gnu();
+        match entry {
+            Entry::OnDisk { path: disk, .. } => {
+                let mut file = File::open(disk)
+                    .with_context(|| format!("failed to open `{}`", disk.display()))?;
+                let metadata = file.metadata()?;
+                header.set_metadata_in_mode(&metadata, HeaderMode::Deterministic);
+                header.set_cksum();
+                ar.append_data(&mut header, &path, &mut file)?;
+            }
+            Entry::Generated(contents) => {
+                header.set_entry_type(EntryType::file());
+                header.set_mode(0o644);
+                header.set_size(contents.len() as u64);
+                header.set_mtime(1);
+                header.set_cksum();
+                ar.append_data(&mut header, &path, contents.as_bytes())?;
+            }
+        }
+    }
+    ar.into_inner()?.finish()?;
+    Ok(())
+}
+
+/// Writes a zip archive whose files are deflated, with the date of the zip
+/// epoch so that it is reproducible.
+fn write_zip(mut file: &File, base: &str, entries: &BTreeMap<String, Entry>) -> CargoResult<()> {
+    const DOS_DATE: u16 = (1 << 5) | 1;
+    let mut central = Vec::new();
+    let mut offset = 0u32;
+    for (name, entry) in entries {
+        let (contents, mode) = match entry {
+            Entry::OnDisk { path, executable } => (
+                paths::read_bytes(path)?,
+                if *executable { 0o755 } else { 0o644 },
+            ),
+            Entry::Generated(contents) => (contents.clone().into_bytes(), 0o644),
+        };
+        let name = format!("{}/{}", base, name);
+        let mut crc = Crc::new();
+        crc.update(&contents);
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(&contents)?;
+        let compressed = encoder.finish()?;
+        let (crc, size, compressed_size) = (
+            crc.sum(),
+            zip_u32(contents.len())?,
+            zip_u32(compressed.len())?,
+        );
+
+        let mut local = Vec::new();
+        local.extend(&0x04034b50u32.to_le_bytes());
+        local.extend(&20u16.to_le_bytes()); // version needed to extract
+        local.extend(&0x0800u16.to_le_bytes()); // UTF-8 names
+        local.extend(&8u16.to_le_bytes()); // deflate
+        local.extend(&0u16.to_le_bytes());
+        local.extend(&DOS_DATE.to_le_bytes());
+        local.extend(&crc.to_le_bytes());
+        local.extend(&compressed_size.to_le_bytes());
+        local.extend(&size.to_le_bytes());
+        local.extend(&(name.len() as u16).to_le_bytes());
+        local.extend(&0u16.to_le_bytes());
+        local.extend(name.as_bytes());
+        file.write_all(&local)?;
+        file.write_all(&compressed)?;
+
+        central.extend(&0x02014b50u32.to_le_bytes());
+        central.extend(&((3u16 << 8) | 20).to_le_bytes()); // made by Unix
+        central.extend(&20u16.to_le_bytes());
+        central.extend(&0x0800u16.to_le_bytes());
+        central.extend(&8u16.to_le_bytes());
+        central.extend(&0u16.to_le_bytes());
+        central.extend(&DOS_DATE.to_le_bytes());
+        central.extend(&crc.to_le_bytes());
+        central.extend(&compressed_size.to_le_bytes());
+        central.extend(&size.to_le_bytes());
+        central.extend(&(name.len() as u16).to_le_bytes());
+        central.extend(&[0; 8]); // extra, comment, disk, internal attributes
+        central.extend(&((0o100000u32 | mode) << 16).to_le_bytes());
+        central.extend(&offset.to_le_bytes());
+        central.extend(name.as_bytes());
+
+        offset = offset
+            .checked_add(zip_u32(local.len() + compressed.len())?)
+            .context("the archive is too large for zip")?;
+    }
+    let count = u16::try_from(entries.len()).context("too many files for a zip archive")?;
+    let mut end = Vec::new();
+    end.extend(&0x06054b50u32.to_le_bytes());
+    end.extend(&[0; 4]); // disk numbers
+    end.extend(&count.to_le_bytes());
+    end.extend(&count.to_le_bytes());
+    end.extend(&zip_u32(central.len())?.to_le_bytes());
+    end.extend(&offset.to_le_bytes());
+    end.extend(&0u16.to_le_bytes());
+    file.write_all(&central)?;
+    file.write_all(&end)?;
+    Ok(())
+}
+
+fn zip_u32(size: usize) -> CargoResult<u32> {
+    u32::try_from(size).context("the archive is too large for zip")
+}
@@ -0,0 +1,333 @@
+//! `cargo patch`, with `-Z patch`.
+//!
+//! `cargo patch add` writes a `[patch]` entry to the root manifest of the
+//! workspace, as text like `cargo add` does, and resolves the workspace to
+//! check that the patch is actually used: a patch whose version doesn't match
+//! the requirements of the dependents is otherwise silently left unused. The
+//! manifest is restored if the patch isn't applied, and the lock file is
+//! updated if it is.
+//!
+//! `cargo patch explain` resolves the workspace without writing the lock file,
+//! and explains for each patch why it is or isn't applied.
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context as _};
+use cargo_util::paths;
+
+use super::cargo_add::{add_to_table, quote, relative_path};
+use crate::core::registry::PackageRegistry;
+use crate::core::resolver::{CliFeatures, HasDevUnits, Resolve};
+use crate::core::{Dependency, PackageId, SourceId, Summary, Workspace};
+use crate::drop_println;
+use crate::ops;
+use crate::util::errors::CargoResult;
+use crate::util::CanonicalUrl;
+
+pub struct PatchAddOptions {
+    /// The package to patch.
+    pub krate: String,
+    /// The path of the fork, relative to the current directory.
+    pub path: Option<PathBuf>,
+    /// The git URL of the fork.
+    pub git: Option<String>,
+    /// The `branch`, `tag` or `rev` of the git fork, and its value.
+    pub git_ref: Option<(&'static str, String)>,
+    /// The registry of the patched package, or `None` for crates.io.
+    pub registry: Option<String>,
+    /// Doesn't write the manifest and the lock file.
+    pub dry_run: bool,
+}
+
+/// What is known of a patch after resolving the workspace.
+struct PatchReport {
+    /// The package of the patch, if it could be loaded.
+    patch: Option<Summary>,
+    applied: bool,
+    /// The reasons the patch is or isn't applied.
+    reasons: Vec<String>,
+}
+
+/// Adds a `[patch]` entry to the root manifest of the workspace.
+pub fn patch_add(ws: &Workspace<'_>, opts: &PatchAddOptions) -> CargoResult<()> {
+    let config = ws.config();
+    let krate = opts.krate.as_str();
+    let (source_id, key) = match &opts.registry {
+        Some(registry) => (SourceId::alt_registry(config, registry)?, registry.as_str()),
+        None => (SourceId::crates_io(config)?, "crates-io"),
+    };
+
+    let mut fields = Vec::new();
+    let description;
+    if let Some(path) = &opts.path {
+        let path = paths::normalize_path(&config.cwd().join(path));
+        let (pkg, _) =
+            ops::read_package(&path.join("Cargo.toml"), SourceId::for_path(&path)?, config)
+                .with_context(|| format!("failed to read the package at `{}`", path.display()))?;
+        if pkg.name() != krate {
+            bail!(
+                "the package at `{}` is `{}`, not `{}`",
+                path.display(),
+                pkg.name(),
+                krate
+            );
+        }
+        let relative = relative_path(&path, ws.root());
+        fields.push((
+            "path",
+            quote(&relative.to_string_lossy().replace('\\', "/")),
+        ));
+        description = pkg.package_id().to_string();
+    } else {
+        let git = opts.git.as_deref().unwrap();
+        fields.push(("git", quote(git)));
+        if let Some((kind, value)) = &opts.git_ref {
+            fields.push((kind, quote(value)));
+        }
+        description = format!("{} ({})", krate, git);
+    }
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|(key, value)| format!("{} = {}", key, value))
+        .collect();
+    let value = format!("{{ {} }}", fields.join(", "));
+
+    let manifest_path = ws.root_manifest();
+    let original = paths::read(manifest_path)?;
+    let manifest = add_to_table(&original, &["patch", key], krate, &value)
+        .with_context(|| format!("failed to add the patch of `{}`", krate))?;
+    config
+        .shell()
+        .status("Adding", format!("{} to patch.{}", description, key))?;
+
+    // The locked versions of the patched package are unlocked, so that a
+    // patch with another version is used.
+    let canonical = CanonicalUrl::new(source_id.url())?;
+    let before = resolve(ws, None)?.0;
+    let to_avoid: HashSet<PackageId> = before
+        .iter()
+        .filter(|id| id.name() == krate && *id.source_id().canonical_url() == canonical)
+        .collect();
+    paths::write(manifest_path, &manifest)?;
+    let result = (|| -> CargoResult<()> {
+        let new_ws = Workspace::new(manifest_path, config)?;
+        let (mut after, registry) = resolve(&new_ws, Some(&to_avoid))?;
+        let dep = new_ws
+            .root_patch()?
+            .into_iter()
+            .filter(|(url, _)| CanonicalUrl::new(url).map_or(false, |url| url == canonical))
+            .flat_map(|(_, deps)| deps)
+            .find(|dep| dep.package_name() == krate)
+            .unwrap();
+        let report = explain(&after, &registry, &canonical, &dep);
+        if !report.applied {
+            let mut msg = format!("the patch of `{}` isn't applied", krate);
+            for reason in &report.reasons {
+                msg.push_str("\n  ");
+                msg.push_str(reason);
+            }
+            bail!(msg);
+        }
+        let patch_id = report.patch.unwrap().package_id();
+        let mut unlocked: Vec<_> = to_avoid.iter().collect();
+        unlocked.sort();
+        for id in unlocked {
+            config
+                .shell()
+                .status("Updating", format!("{} -> {}", id, patch_id))?;
+        }
+        if !opts.dry_run {
+            ops::write_pkg_lockfile(&new_ws, &mut after)?;
+        }
+        Ok(())
+    })();
+    if result.is_err() || opts.dry_run {
+        paths::write(manifest_path, &original)?;
+    }
+    result?;
+    if opts.dry_run {
+        config
+            .shell()
+            .warn("not writing the manifest and the lock file because of `--dry-run`")?;
+    }
+    Ok(())
+}
+
+/// Explains why the patches of the workspace, or those of `krate`, are or
+/// aren't applied.
+pub fn patch_explain(ws: &Workspace<'_>, krate: Option<&str>) -> CargoResult<()> {
+    let config = ws.config();
+    let (resolve, registry) = resolve(ws, None)?;
+    let mut patches: Vec<_> = ws.root_patch()?.into_iter().collect();
+    patches.sort_by(|a, b| a.0.cmp(&b.0));
+    let crates_io = SourceId::crates_io(config)?;
+
+    let mut found = false;
+    for (url, deps) in patches {
+        let canonical = CanonicalUrl::new(&url)?;
+        let patched = if canonical == *crates_io.canonical_url() {
+            "crates-io".to_string()
+        } else {
+            format!("`{}`", url)
+        };
+        for dep in deps {
+            if krate.map_or(false, |krate| dep.package_name() != krate) {
+                continue;
+            }
+            found = true;
+            let report = explain(&resolve, &registry, &canonical, &dep);
+            let patch = match &report.patch {
+                Some(summary) => summary.package_id().to_string(),
+                None => format!("{} ({})", dep.package_name(), dep.source_id()),
+            };
+            let status = if report.applied {
+                "applied"
+            } else {
+                "not applied"
+            };
+            drop_println!(
+                config,
+                "{} patches `{}` of {}: {}",
+                patch,
+                dep.package_name(),
+                patched,
+                status
+            );
+            for reason in &report.reasons {
+                drop_println!(config, "  {}", reason);
+            }
+        }
+    }
+    if !found {
+        match krate {
+            Some(krate) => bail!("the workspace has no patch for `{}`", krate),
+            None => config.shell().warn("the workspace has no patches")?,
+        }
+    }
+    Ok(())
+}
+
+/// Resolves the workspace from its lock file, without writing it, unlocking
+/// the packages `to_avoid`.
+fn resolve<'cfg>(
+    ws: &Workspace<'cfg>,
+    to_avoid: Option<&HashSet<PackageId>>,
+) -> CargoResult<(Resolve, PackageRegistry<'cfg>)> {
+    let mut registry = PackageRegistry::new(ws.config())?;
+    let previous = ops::load_pkg_lockfile(ws)?;
+    let resolve = ops::resolve_with_previous(
+        &mut registry,
+        ws,
+        &CliFeatures::new_all(true),
+        HasDevUnits::Yes,
+        previous.as_ref(),
+        to_avoid,
+        &[],
+        true,
+    )?;
+    Ok((resolve, registry))
+}
+
+/// Explains why the patch `dep` of the source `patched` is or isn't applied
+/// in `resolve`.
+fn explain(
+    resolve: &Resolve,
+    registry: &PackageRegistry<'_>,
+    patched: &CanonicalUrl,
+    dep: &Dependency,
+) -> PatchReport {
+    let name = dep.package_name();
+    let patch = registry
+        .patches()
+        .into_iter()
+        .find(|s| s.name() == name && s.source_id() == dep.source_id());
+    let patch = match patch {
+        Some(patch) => patch,
+        None => {
+            return PatchReport {
+                patch: None,
+                applied: false,
+                reasons: vec![format!(
+                    "no package `{}` was found in the patch location",
+                    name
+                )],
+            }
+        }
+    };
+    let patch_id = patch.package_id();
+    // The packages depending on `id`, with the requirement of the dependency.
+    let dependents = |id: PackageId| -> Vec<(PackageId, String)> {
+        let mut dependents: Vec<_> = resolve
+            .iter()
+            .flat_map(|parent| {
+                resolve
+                    .deps(parent)
+                    .filter(move |(dep_id, _)| *dep_id == id)
+                    .flat_map(|(_, deps)| deps.iter())
+                    .map(move |dep| (parent, dep.version_req().to_string()))
+            })
+            .collect();
+        dependents.sort();
+        dependents.dedup();
+        dependents
+    };
+
+    let mut reasons = Vec::new();
+    if resolve.contains(&patch_id) {
+        for (parent, req) in dependents(patch_id) {
+            reasons.push(format!(
+                "used by `{}`, which requires `{} {}`",
+                parent, name, req
+            ));
+        }
+        return PatchReport {
+            patch: Some(patch),
+            applied: true,
+            reasons,
+        };
+    }
+
+    let version = patch_id.version();
+    let originals: Vec<_> = resolve.iter().filter(|id| id.name() == name).collect();
+    for &orig in &originals {
+        if orig.source_id().canonical_url() != patched {
+            continue;
+        }
+        for (parent, req) in dependents(orig) {
+            if semver::VersionReq::parse(&req).map_or(false, |req| !req.matches(version)) {
+                reasons.push(format!(
+                    "`{}` requires `{} {}`, which v{} of the patch doesn't match",
+                    parent, name, req, version
+                ));
+            } else {
+                reasons.push(format!(
+                    "`{}` requires `{} {}`, but the lock file keeps it on {}, \
+                     run `cargo update -p {}` to use the patch",
+                    parent, name, req, orig, name
+                ));
+            }
+        }
+    }
+    if reasons.is_empty() {
+        for &orig in &originals {
+            if orig.source_id().canonical_url() != patched && orig != patch_id {
+                reasons.push(format!(
+                    "the workspace depends on {}, from another source than the patched one",
+                    orig
+                ));
+            }
+        }
+    }
+    if reasons.is_empty() {
+        reasons.push(format!(
+            "no package of the workspace depends on `{}` from the patched source",
+            name
+        ));
+    }
+    PatchReport {
+        patch: Some(patch),
+        applied: false,
+        reasons,
+    }
+}
@@ -0,0 +1,388 @@
+//! `cargo plugin`, with `-Z plugin`.
+//!
+//! Plugins are the external subcommands (`cargo-foo` binaries) of registry
+//! packages that declare themselves as such in their manifest:
+//!
+//! ```toml
+//! [package.metadata.cargo-plugin]
+//! cargo-version = ">=1.56"
+//! permissions = ["credentials"]
+//! ```
+//!
+//! `cargo plugin install` checks the declaration before installing a plugin
+//! with `cargo install`, and records it in `.cargo-plugins.json` in the
+//! install root. The record is what `cargo plugin list` and `update` work
+//! from, and what restricts the plugin when Cargo runs it: unless it was
+//! granted the `credentials` permission, the registry tokens of the
+//! environment are not passed on to it.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::prelude::*;
+use std::io::{self, SeekFrom};
+use std::path::Path;
+
+use anyhow::{bail, format_err, Context as _};
+use cargo_util::{paths, ProcessBuilder};
+use semver::VersionReq;
+use serde::{Deserialize, Serialize};
+
+use crate::core::{Dependency, Package, PackageId, SourceId};
+use crate::drop_println;
+use crate::ops::common_for_install_and_uninstall::{resolve_root, select_dep_pkg};
+use crate::ops::{self, CompileOptions};
+use crate::sources::SourceConfigMap;
+use crate::util::toml::schema;
+use crate::util::{CargoResult, Config, FileLock, Filesystem};
+
+/// The name of the file plugins are recorded in, in the install root.
+const PLUGINS_FILE: &str = ".cargo-plugins.json";
+
+/// The permissions a plugin can ask for, and what they allow it to do.
+const PERMISSIONS: &[(&str, &str)] = &[(
+    "credentials",
+    "receive the registry tokens of the environment",
+)];
+
+pub struct PluginInstallOptions<'a> {
+    /// The packages to install, or to update for `cargo plugin update`,
+    /// where no package means all of them.
+    pub krates: Vec<String>,
+    pub vers: Option<String>,
+    /// The registry of the packages to install.
+    pub source_id: SourceId,
+    /// The permissions granted on the command line.
+    pub allow: Vec<String>,
+    pub force: bool,
+    pub compile_opts: &'a CompileOptions,
+}
+
+/// The installed plugins.
+#[derive(Default, Deserialize, Serialize)]
+struct PluginListing {
+    plugins: BTreeMap<PackageId, PluginInfo>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct PluginInfo {
+    /// The subcommands of the plugin, without the `cargo-` prefix.
+    commands: BTreeSet<String>,
+    /// The versions of Cargo the plugin declared to work with.
+    cargo_version: Option<String>,
+    /// The permissions the plugin was granted.
+    permissions: BTreeSet<String>,
+}
+
+/// The `[package.metadata.cargo-plugin]` table of a manifest.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct PluginManifest {
+    cargo_version: Option<String>,
+    #[serde(default)]
+    permissions: BTreeSet<String>,
+}
+
+pub fn plugin_install(config: &Config, opts: &PluginInstallOptions<'_>) -> CargoResult<()> {
+    let root = resolve_root(None, config)?;
+    let map = SourceConfigMap::new(config)?;
+    for krate in &opts.krates {
+        let vers = match &opts.vers {
+            Some(vers) => vers.clone(),
+            None => "*".to_string(),
+        };
+        let dep = Dependency::parse(krate.as_str(), Some(&vers), opts.source_id)?;
+        let mut source = map.load(opts.source_id, &Default::default())?;
+        let pkg = select_dep_pkg(&mut source, dep, config, true)?;
+        let info = check_plugin(config, &pkg, &opts.allow, &BTreeSet::new())?;
+        install(config, &root, &pkg, info, opts)?;
+    }
+    Ok(())
+}
+
+pub fn plugin_update(config: &Config, opts: &PluginInstallOptions<'_>) -> CargoResult<()> {
+    let root = resolve_root(None, config)?;
+    let listing = load_listing(config, &root)?;
+    for name in &opts.krates {
+        if !listing.plugins.keys().any(|id| id.name() == name.as_str()) {
+            bail!("the plugin `{}` is not installed", name);
+        }
+    }
+    let map = SourceConfigMap::new(config)?;
+    for (id, installed) in &listing.plugins {
+        if !opts.krates.is_empty() && !opts.krates.iter().any(|k| k == id.name().as_str()) {
+            continue;
+        }
+        // The recorded ids are locked to their version, like those of
+        // `Cargo.lock`.
+        let source_id = id.source_id().with_precise(None);
+        let dep = Dependency::parse(id.name(), Some("*"), source_id)?;
+        let mut source = map.load(source_id, &Default::default())?;
+        let pkg = select_dep_pkg(&mut source, dep, config, true)?;
+        if pkg.version() <= id.version() {
+            config
+                .shell()
+                .status("Fresh", format!("{} is up to date", id))?;
+            continue;
+        }
+        let info = match check_plugin(config, &pkg, &opts.allow, &installed.permissions) {
+            Ok(info) => info,
+            Err(e) => {
+                config
+                    .shell()
+                    .warn(format!("not updating {} to v{}: {}", id, pkg.version(), e))?;
+                continue;
+            }
+        };
+        install(config, &root, &pkg, info, opts)?;
+    }
+    Ok(())
+}
+
+pub fn plugin_list(config: &Config) -> CargoResult<()> {
+    let root = resolve_root(None, config)?;
+    let listing = load_listing(config, &root)?;
+    let current = schema::current();
+    for (id, info) in &listing.plugins {
+        let commands: Vec<_> = info
+            .commands
+            .iter()
+            .map(|c| format!("cargo {}", c))
+            .collect();
+        drop_println!(
+            config,
+            "{} v{}: {}",
+            id.name(),
+            id.version(),
+            commands.join(", ")
+        );
+        if !info.permissions.is_empty() {
+            let permissions: Vec<_> = info.permissions.iter().map(|p| p.as_str()).collect();
+            drop_println!(config, "    permissions: {}", permissions.join(", "));
+        }
+        if let Some(req) = &info.cargo_version {
+            if !VersionReq::parse(req).map_or(false, |req| req.matches(&current)) {
+                drop_println!(
+                    config,
+                    "    incompatible: needs Cargo {}, but this is Cargo {}",
+                    req,
+                    current
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Restricts the environment of the external subcommand `cmd` about to be
+/// run as `process`, if it is a recorded plugin.
+pub fn restrict_plugin(config: &Config, cmd: &str, command: &Path, process: &mut ProcessBuilder) {
+    let info = (|| -> CargoResult<Option<PluginInfo>> {
+        let root = resolve_root(None, config)?.into_path_unlocked();
+        if command.parent() != Some(root.join("bin").as_path()) {
+            return Ok(None);
+        }
+        let path = root.join(PLUGINS_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let listing: PluginListing = serde_json::from_str(&paths::read(&path)?)?;
+        Ok(listing
+            .plugins
+            .into_iter()
+            .map(|(_, info)| info)
+            .find(|info| info.commands.contains(cmd)))
+    })();
+    let info = match info {
+        Ok(Some(info)) => info,
+        _ => return,
+    };
+    if !info.permissions.contains("credentials") {
+        for (key, _) in std::env::vars_os() {
+            let key = key.to_string_lossy();
+            let is_token = key == "CARGO_REGISTRY_TOKEN"
+                || (key.starts_with("CARGO_REGISTRIES_") && key.ends_with("_TOKEN"));
+            if is_token {
+                process.env_remove(&*key);
+            }
+        }
+    }
+    let permissions: Vec<_> = info.permissions.iter().map(|p| p.as_str()).collect();
+    process.env("CARGO_PLUGIN_PERMISSIONS", permissions.join(","));
+}
+
+/// Checks that `pkg` is a plugin that works with this Cargo, and that the
+/// permissions it asks for are granted, either on the command line, by the
+/// user when asked, or when it was installed before.
+fn check_plugin(
+    config: &Config,
+    pkg: &Package,
+    allow: &[String],
+    granted: &BTreeSet<String>,
+) -> CargoResult<PluginInfo> {
+    let manifest = pkg
+        .manifest()
+        .custom_metadata()
+        .and_then(|m| m.get("cargo-plugin"))
+        .ok_or_else(|| {
+            format_err!(
+                "`{}` is not a Cargo plugin, its manifest has no \
+                 `[package.metadata.cargo-plugin]` table",
+                pkg.name()
+            )
+        })?;
+    let manifest: PluginManifest = manifest.clone().try_into().with_context(|| {
+        format!(
+            "invalid `[package.metadata.cargo-plugin]` of `{}`",
+            pkg.name()
+        )
+    })?;
+
+    if let Some(req) = &manifest.cargo_version {
+        let current = schema::current();
+        let matches = VersionReq::parse(req)
+            .with_context(|| format!("invalid `cargo-version` of `{}`", pkg.name()))?
+            .matches(&current);
+        if !matches {
+            bail!(
+                "`{} v{}` needs Cargo {}, but this is Cargo {}",
+                pkg.name(),
+                pkg.version(),
+                req,
+                current
+            );
+        }
+    }
+
+    let commands: BTreeSet<_> = pkg
+        .targets()
+        .iter()
+        .filter(|t| t.is_bin())
+        .filter_map(|t| t.name().strip_prefix("cargo-"))
+        .map(|c| c.to_string())
+        .collect();
+    if commands.is_empty() {
+        bail!(
+            "`{}` has no `cargo-*` binaries to run as Cargo subcommands",
+            pkg.name()
+        );
+    }
+
+    for permission in &manifest.permissions {
+        let description = match PERMISSIONS.iter().find(|(p, _)| p == permission) {
+            Some((_, description)) => description,
+            None => bail!(
+                "`{}` asks for the unknown permission `{}`, expected one of: {}",
+                pkg.name(),
+                permission,
+                PERMISSIONS
+                    .iter()
+                    .map(|(p, _)| *p)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        };
+        if granted.contains(permission) || allow.contains(permission) {
+            continue;
+        }
+        let question = format!(
+            "Allow `{}` to {} (permission `{}`)?",
+            pkg.name(),
+            description,
+            permission
+        );
+        if !atty::is(atty::Stream::Stdin) || !ask(config, &question)? {
+            bail!(
+                "`{}` needs the permission `{}` to {}\n\
+                 Pass `--allow {}` to grant it.",
+                pkg.name(),
+                permission,
+                description,
+                permission
+            );
+        }
+    }
+
+    Ok(PluginInfo {
+        commands,
+        cargo_version: manifest.cargo_version,
+        permissions: manifest.permissions,
+    })
+}
+
+/// Asks the user a yes or no question on the terminal.
+fn ask(config: &Config, question: &str) -> CargoResult<bool> {
+    {
+        let mut shell = config.shell();
+        write!(shell.err(), "{} [y/N] ", question)?;
+        shell.err().flush()?;
+    }
+    let mut line = String::new();
+    io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .with_context(|| "failed to read stdin")?;
+    Ok(matches!(line.trim(), "y" | "Y" | "yes"))
+}
+
+fn install(
+    config: &Config,
+    root: &Filesystem,
+    pkg: &Package,
+    info: PluginInfo,
+    opts: &PluginInstallOptions<'_>,
+) -> CargoResult<()> {
+    let vers = format!("={}", pkg.version());
+    ops::install(
+        config,
+        None,
+        vec![pkg.name().as_str()],
+        pkg.package_id().source_id(),
+        false,
+        Some(&vers),
+        opts.compile_opts,
+        opts.force,
+        false,
+        &ops::InstallStaging::default(),
+    )?;
+
+    let lock = root.open_rw(Path::new(PLUGINS_FILE), config, "plugin metadata")?;
+    let mut listing = read_listing(&lock)?;
+    listing.plugins.retain(|id, _| id.name() != pkg.name());
+    listing.plugins.insert(pkg.package_id(), info);
+    let mut file = lock.file();
+    file.seek(SeekFrom::Start(0))?;
+    file.set_len(0)?;
+    file.write_all(serde_json::to_string(&listing)?.as_bytes())?;
+    Ok(())
+}
+
+/// The recorded plugins, without those whose binaries were removed since,
+/// like with `cargo uninstall`.
+fn load_listing(config: &Config, root: &Filesystem) -> CargoResult<PluginListing> {
+    if !root.join(PLUGINS_FILE).into_path_unlocked().exists() {
+        return Ok(PluginListing::default());
+    }
+    let lock = root.open_ro(Path::new(PLUGINS_FILE), config, "plugin metadata")?;
+    let mut listing = read_listing(&lock)?;
+    let bin = root.join("bin").into_path_unlocked();
+    listing.plugins.retain(|_, info| {
+        info.commands.iter().any(|c| {
+            let exe = format!("cargo-{}{}", c, std::env::consts::EXE_SUFFIX);
+            bin.join(exe).exists()
+        })
+    });
+    Ok(listing)
+}
+
+fn read_listing(lock: &FileLock) -> CargoResult<PluginListing> {
+    let mut contents = String::new();
+    lock.file().read_to_string(&mut contents)?;
+    if contents.is_empty() {
+        return Ok(PluginListing::default());
+    }
+    serde_json::from_str(&contents).with_context(|| {
+        format!(
+            "failed to parse plugin metadata at `{}`",
+            lock.path().display()
+        )
+    })
+}
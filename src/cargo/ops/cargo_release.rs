@@ -0,0 +1,576 @@
+//! `cargo release -Z release`: releasing the members of a workspace.
+//!
+//! A release goes through these steps, each of them done for all the
+//! released packages before the next one:
+//!
+//! 1. The new version of each package is planned, from the version bump
+//!    given on the command line, or from the conventional commits that
+//!    touched the package since its last release tag.
+//! 2. The manifests get the new versions, and the members depending on a
+//!    released package require its new version.
+//! 3. A section listing the commits of the release, or assembled from the
+//!    changelog fragments of the package, is added to the `CHANGELOG.md` of
+//!    each package, and `Cargo.lock` is updated.
+//! 4. The changes are committed, and each package is tagged
+//!    `<name>-v<version>`.
+//! 5. The packages are published, dependencies first.
+//!
+//! The plan and the progress of the release are kept in
+//! `target/cargo-release/state.json`, so that a release that failed, like when a
+//! registry refused a package, goes on from where it stopped with
+//! `cargo release --resume`.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context as _};
+use cargo_util::paths;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+use crate::core::resolver::CliFeatures;
+use crate::core::{Package, Workspace};
+use crate::ops::changelog;
+use crate::ops::version_bump::{self, VersionBump};
+use crate::ops::{self, Packages, PublishOpts};
+use crate::util::interning::InternedString;
+use crate::util::vcs_state::{self, DirtyPolicy, VcsCommand};
+use crate::util::{CargoResult, Config};
+
+const CHANGELOG_FILE: &str = "CHANGELOG.md";
+
+pub struct ReleaseOptions<'cfg> {
+    pub config: &'cfg Config,
+    /// The packages to release.
+    pub to_release: Packages,
+    /// The version bump of the packages, or `None` to infer it from the
+    /// conventional commits of each package.
+    pub bump: Option<VersionBump>,
+    /// Only prints what would be released.
+    pub dry_run: bool,
+    /// Continues the release that is in progress.
+    pub resume: bool,
+    pub changelog: bool,
+    pub tag: bool,
+    pub publish: bool,
+    pub allow_dirty: bool,
+    pub verify: bool,
+    pub registry: Option<String>,
+    pub token: Option<String>,
+}
+
+/// The plan and the progress of a release, in
+/// `target/cargo-release/state.json`.
+#[derive(Serialize, Deserialize)]
+struct ReleaseState {
+    /// The released packages, in publishing order.
+    packages: Vec<PackageRelease>,
+    /// Whether the release is committed and tagged.
+    committed: bool,
+    /// The names of the packages published already.
+    published: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PackageRelease {
+    name: String,
+    from: Version,
+    to: Version,
+    /// Whether the package is published to a registry.
+    publish: bool,
+    /// The subjects of the commits since the last release.
+    commits: Vec<String>,
+}
+
+/// How much a commit changes a package, by the conventional commits
+/// specification.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum ChangeLevel {
+    Fix,
+    Feature,
+    Breaking,
+}
+
+pub fn release(ws: &Workspace<'_>, opts: &ReleaseOptions<'_>) -> CargoResult<()> {
+    let config = opts.config;
+    let state_path = ws
+        .target_dir()
+        .join("cargo-release")
+        .join("state.json")
+        .into_path_unlocked();
+    let repo = match vcs_state::discover(ws.root()) {
+        Some(repo) => repo,
+        None => bail!("`cargo release` requires the workspace to be in a git repository"),
+    };
+
+    let mut state = if opts.resume {
+        if !state_path.exists() {
+            bail!("no release to resume in `{}`", state_path.display());
+        }
+        let state: ReleaseState = serde_json::from_slice(&paths::read_bytes(&state_path)?)
+            .with_context(|| {
+                format!(
+                    "failed to read the release state `{}`",
+                    state_path.display()
+                )
+            })?;
+        state
+    } else {
+        if state_path.exists() {
+            bail!(
+                "a release is already in progress, continue it with `cargo release --resume`, \
+                 or remove `{}` to start over",
+                state_path.display()
+            );
+        }
+        check_repo_state(&repo, opts)?;
+        plan(ws, &repo, opts)?
+    };
+
+    if state.packages.is_empty() {
+        config.shell().warn("nothing to release")?;
+        return Ok(());
+    }
+    for release in &state.packages {
+        config.shell().status(
+            "Releasing",
+            format!("{} v{} -> v{}", release.name, release.from, release.to),
+        )?;
+    }
+    let bumps: Vec<_> = state
+        .packages
+        .iter()
+        .map(|r| (r.name.as_str(), &r.to))
+        .collect();
+    let edits = version_bump::edit_manifests(ws, &bumps)?;
+    if opts.dry_run {
+        config.shell().warn("aborting release due to dry run")?;
+        return Ok(());
+    }
+    // Nothing is changed unless it can be committed.
+    let signature = signature(&repo)?;
+    if !opts.resume {
+        save_state(&state_path, &state)?;
+    }
+    for edit in edits {
+        paths::write(&edit.path, edit.new)?;
+    }
+
+    // The workspace as it is with the new versions.
+    let ws = Workspace::new(ws.root_manifest(), config)?;
+    if !state.committed {
+        let mut files: Vec<PathBuf> = ws
+            .members()
+            .map(|pkg| pkg.manifest_path().to_path_buf())
+            .collect();
+        files.push(ws.root_manifest().to_path_buf());
+        if opts.changelog {
+            let date = changelog::release_date(config)?;
+            for release in &state.packages {
+                let pkg = ws
+                    .members()
+                    .find(|pkg| pkg.name() == release.name.as_str())
+                    .unwrap();
+                files.extend(add_changelog_section(&ws, pkg, release, &date)?);
+            }
+        }
+        let lock = ws.root().join("Cargo.lock");
+        if lock.exists() {
+            ops::resolve_ws(&ws)?;
+            files.push(lock);
+        }
+        commit_release(&repo, &signature, &files, &state, opts)?;
+        state.committed = true;
+        save_state(&state_path, &state)?;
+    }
+
+    if opts.publish {
+        for release in &state.packages {
+            if !release.publish || state.published.contains(&release.name) {
+                continue;
+            }
+            let publish_opts = PublishOpts {
+                config,
+                token: opts.token.clone(),
+                index: None,
+                verify: opts.verify,
+                allow_dirty: opts.allow_dirty,
+                jobs: None,
+                to_publish: Packages::Packages(vec![release.name.clone()]),
+                targets: Vec::new(),
+                dry_run: false,
+                registry: opts.registry.clone(),
+                cli_features: CliFeatures::new_all(false),
+            };
+            ops::publish(&ws, &publish_opts).with_context(|| {
+                format!(
+                    "failed to publish {} v{}, once the problem is solved, \
+                     continue the release with `cargo release --resume`",
+                    release.name, release.to
+                )
+            })?;
+            state.published.push(release.name.clone());
+            save_state(&state_path, &state)?;
+        }
+    }
+
+    paths::remove_file(&state_path)?;
+    config
+        .shell()
+        .status("Released", release_list(&state.packages))?;
+    Ok(())
+}
+
+/// Fails if the working tree has uncommitted changes, which would end up in
+/// the release commit, depending on the `vcs.allow-dirty.release` policy.
+fn check_repo_state(repo: &git2::Repository, opts: &ReleaseOptions<'_>) -> CargoResult<()> {
+    let policy = DirtyPolicy::for_command(opts.config, VcsCommand::Release, opts.allow_dirty)?;
+    if policy == DirtyPolicy::Allow {
+        return Ok(());
+    }
+    let workdir = repo.workdir().unwrap();
+    let dirty: Vec<String> = vcs_state::changed_files(repo, false)?
+        .into_iter()
+        .map(|file| {
+            file.path
+                .strip_prefix(workdir)
+                .unwrap_or(&file.path)
+                .display()
+                .to_string()
+        })
+        .collect();
+    if dirty.is_empty() {
+        return Ok(());
+    }
+    policy.report(
+        opts.config,
+        VcsCommand::Release,
+        &format!(
+            "{} files in the working directory contain changes that were \
+             not yet committed into git:\n\n{}\n\n",
+            dirty.len(),
+            dirty.join("\n")
+        ),
+        "to proceed despite this and include the uncommitted changes in the release, \
+         pass the `--allow-dirty` flag",
+    )
+}
+
+/// Plans the new version of each package to release.
+fn plan(
+    ws: &Workspace<'_>,
+    repo: &git2::Repository,
+    opts: &ReleaseOptions<'_>,
+) -> CargoResult<ReleaseState> {
+    // The root of each member in the repository, to find the member a
+    // changed file belongs to.
+    let roots: Vec<(PathBuf, InternedString)> = ws
+        .members()
+        .filter_map(|pkg| Some((vcs_state::path_in_repo(repo, pkg.root())?, pkg.name())))
+        .collect();
+    let mut releases = Vec::new();
+    for pkg in opts.to_release.get_packages(ws)? {
+        let tag = tag_name(&pkg.name(), pkg.version());
+        let messages = package_commits(repo, &roots, pkg.name(), &tag)
+            .with_context(|| format!("failed to read the git history of `{}`", pkg.name()))?;
+        let bump = match &opts.bump {
+            Some(bump) => bump.clone(),
+            None => match conventional_bump(pkg.version(), &messages) {
+                Some(bump) => bump,
+                None => {
+                    opts.config.shell().status(
+                        "Skipping",
+                        format!(
+                            "{} v{}, it has no releasable commits since its last release",
+                            pkg.name(),
+                            pkg.version()
+                        ),
+                    )?;
+                    continue;
+                }
+            },
+        };
+        let to = bump.apply(pkg.version());
+        if to <= *pkg.version() {
+            bail!(
+                "cannot release {} v{}, as it is not newer than v{}",
+                pkg.name(),
+                to,
+                pkg.version()
+            );
+        }
+        releases.push(PackageRelease {
+            name: pkg.name().to_string(),
+            from: pkg.version().clone(),
+            to,
+            publish: pkg.publish().as_ref().map_or(true, |r| !r.is_empty()),
+            commits: messages
+                .iter()
+                .map(|m| m.lines().next().unwrap_or("").to_string())
+                .collect(),
+        });
+    }
+    Ok(ReleaseState {
+        packages: publish_order(ws, releases),
+        committed: false,
+        published: Vec::new(),
+    })
+}
+
+/// The messages of the commits since the release tag `tag` that changed the
+/// files of the package `name`, newest first. A file belongs to the member
+/// with the deepest root containing it.
+fn package_commits(
+    repo: &git2::Repository,
+    roots: &[(PathBuf, InternedString)],
+    name: InternedString,
+    tag: &str,
+) -> CargoResult<Vec<String>> {
+    let owner = |path: &Path| {
+        roots
+            .iter()
+            .filter(|(root, _)| path.starts_with(root))
+            .max_by_key(|(root, _)| root.components().count())
+            .map(|(_, name)| *name)
+    };
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+    revwalk.push_head()?;
+    if let Ok(tag) = repo.revparse_single(&format!("refs/tags/{}", tag)) {
+        revwalk.hide(tag.peel_to_commit()?.id())?;
+    }
+    let mut messages = Vec::new();
+    for oid in revwalk {
+        let commit = repo.find_commit(oid?)?;
+        let parent_tree = match commit.parent_count() {
+            0 => None,
+            _ => Some(commit.parent(0)?.tree()?),
+        };
+        let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+        let touched = diff.deltas().any(|delta| {
+            [delta.old_file().path(), delta.new_file().path()]
+                .iter()
+                .flatten()
+                .any(|path| owner(path) == Some(name))
+        });
+        if touched {
+            messages.push(commit.message().unwrap_or("").to_string());
+        }
+    }
+    Ok(messages)
+}
+
+/// The version bump of the conventional commits with the messages
+/// `messages`: `feat` commits are new features, `fix` and `perf` commits are
+/// fixes, and commits marked with `!` or a `BREAKING CHANGE` footer are
+/// breaking changes. Below 1.0.0, breaking changes bump the minor version,
+/// and new features the patch version.
+fn conventional_bump(version: &Version, messages: &[String]) -> Option<VersionBump> {
+    let level = messages.iter().filter_map(|m| change_level(m)).max()?;
+    Some(match (level, version.major) {
+        (ChangeLevel::Breaking, 0) => VersionBump::Minor,
+        (ChangeLevel::Breaking, _) => VersionBump::Major,
+        (ChangeLevel::Feature, 0) => VersionBump::Patch,
+        (ChangeLevel::Feature, _) => VersionBump::Minor,
+        (ChangeLevel::Fix, _) => VersionBump::Patch,
+    })
+}
+
+fn change_level(message: &str) -> Option<ChangeLevel> {
+    let subject = message.lines().next()?;
+    let (prefix, _) = subject.split_once(':')?;
+    let kind = prefix.trim_end_matches('!');
+    let kind = kind.split('(').next().unwrap().trim();
+    if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+    let breaking = prefix.ends_with('!')
+        || message
+            .lines()
+            .any(|l| l.starts_with("BREAKING CHANGE:") || l.starts_with("BREAKING-CHANGE:"));
+    if breaking {
+        return Some(ChangeLevel::Breaking);
+    }
+    match kind {
+        "feat" => Some(ChangeLevel::Feature),
+        "fix" | "perf" => Some(ChangeLevel::Fix),
+        _ => None,
+    }
+}
+
+/// Sorts `releases` so that each package comes after the released members it
+/// depends on.
+fn publish_order(ws: &Workspace<'_>, releases: Vec<PackageRelease>) -> Vec<PackageRelease> {
+    let deps: HashMap<InternedString, Vec<InternedString>> = ws
+        .members()
+        .map(|pkg| {
+            let deps = pkg
+                .dependencies()
+                .iter()
+                .filter(|dep| dep.source_id().is_path())
+                .map(|dep| dep.package_name())
+                .collect();
+            (pkg.name(), deps)
+        })
+        .collect();
+    let mut ordered = Vec::new();
+    let mut remaining = releases;
+    while !remaining.is_empty() {
+        let names: HashSet<String> = remaining.iter().map(|r| r.name.clone()).collect();
+        let (ready, blocked): (Vec<_>, Vec<_>) = remaining.into_iter().partition(|r| {
+            deps[r.name.as_str()]
+                .iter()
+                .all(|dep| *dep == r.name.as_str() || !names.contains(dep.as_str()))
+        });
+        remaining = blocked;
+        // A cycle through dev-dependencies.
+        if ready.is_empty() {
+            break;
+        }
+        ordered.extend(ready);
+    }
+    ordered.extend(remaining);
+    ordered
+}
+
+/// Adds the section of `release` to the changelog of the package `pkg`,
+/// above the sections of the previous releases. With changelog fragments,
+/// the section has the changes of the fragments, which are removed. Returns
+/// the paths changed.
+fn add_changelog_section(
+    ws: &Workspace<'_>,
+    pkg: &Package,
+    release: &PackageRelease,
+    date: &str,
+) -> CargoResult<Vec<PathBuf>> {
+    let (path, fragments_dir) = match ws.changelog() {
+        Some(config) => (
+            pkg.root().join(&config.file),
+            Some(pkg.root().join(&config.fragments)),
+        ),
+        None => (pkg.root().join(CHANGELOG_FILE), None),
+    };
+    let old = if path.exists() {
+        Some(paths::read(&path)?)
+    } else {
+        None
+    };
+    let mut changed = vec![path.clone()];
+    changed.extend(fragments_dir.clone());
+    // The section is already there when resuming a release.
+    if old
+        .as_deref()
+        .map_or(false, |old| changelog::has_entry(old, &release.to))
+    {
+        return Ok(changed);
+    }
+    let fragments = match &fragments_dir {
+        Some(dir) => changelog::fragments(dir)?,
+        None => Vec::new(),
+    };
+    let section = if fragments.is_empty() {
+        changelog::section(&release.to, date, &release.commits)
+    } else {
+        changelog::fragments_section(&release.to, date, &fragments)
+    };
+    paths::write(&path, changelog::add_section(old.as_deref(), &section))?;
+    for fragment in &fragments {
+        paths::remove_file(&fragment.path)?;
+    }
+    Ok(changed)
+}
+
+/// Commits the files `files` changed by the release, and tags the commit.
+fn commit_release(
+    repo: &git2::Repository,
+    signature: &git2::Signature<'_>,
+    files: &[PathBuf],
+    state: &ReleaseState,
+    opts: &ReleaseOptions<'_>,
+) -> CargoResult<()> {
+    let config = opts.config;
+    let mut index = repo.index()?;
+    for file in files {
+        let path = match vcs_state::path_in_repo(repo, file) {
+            Some(path) => path,
+            None => bail!("`{}` is not in the git repository", file.display()),
+        };
+        // Only commit a `Cargo.lock` that is tracked already.
+        if path.ends_with("Cargo.lock") && index.get_path(&path, 0).is_none() {
+            continue;
+        }
+        if file.is_file() {
+            index.add_path(&path)?;
+        } else {
+            // Removed files, like the assembled changelog fragments.
+            index.update_all([&path].iter(), None)?;
+        }
+    }
+    index.write()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let head = repo.head()?.peel_to_commit()?;
+    // The release was committed already when resuming after tagging failed.
+    let commit = if tree.id() == head.tree_id() {
+        head
+    } else {
+        let message = format!("chore: release {}", release_list(&state.packages));
+        config.shell().status("Committing", &message)?;
+        let oid = repo.commit(
+            Some("HEAD"),
+            signature,
+            signature,
+            &message,
+            &tree,
+            &[&head],
+        )?;
+        repo.find_commit(oid)?
+    };
+    if !opts.tag {
+        return Ok(());
+    }
+    for release in &state.packages {
+        let tag = tag_name(&release.name, &release.to);
+        if repo.refname_to_id(&format!("refs/tags/{}", tag)).is_ok() {
+            continue;
+        }
+        config.shell().status("Tagging", &tag)?;
+        repo.tag(
+            &tag,
+            commit.as_object(),
+            signature,
+            &format!("Release {} v{}", release.name, release.to),
+            false,
+        )?;
+    }
+    Ok(())
+}
+
+/// The identity of the release commit and tags, from the `user.name` and
+/// `user.email` of the git configuration.
+fn signature(repo: &git2::Repository) -> CargoResult<git2::Signature<'static>> {
+    repo.signature().map_err(|e| {
+        anyhow::format_err!(
+            "`cargo release` commits and tags as the git user, but no identity is configured: {}\n\
+             set it with `git config user.name \"Your Name\"` and \
+             `git config user.email you@example.com`",
+            e.message()
+        )
+    })
+}
+
+fn save_state(path: &Path, state: &ReleaseState) -> CargoResult<()> {
+    paths::create_dir_all(path.parent().unwrap())?;
+    paths::write(path, serde_json::to_vec_pretty(state)?)
+}
+
+fn tag_name(name: &str, version: &Version) -> String {
+    format!("{}-v{}", name, version)
+}
+
+fn release_list(releases: &[PackageRelease]) -> String {
+    releases
+        .iter()
+        .map(|r| format!("{} v{}", r.name, r.to))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
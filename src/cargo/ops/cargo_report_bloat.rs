@@ -0,0 +1,319 @@
+//! Implementation of `cargo report bloat`, which breaks down the code size of
+//! the linked artifacts of a build by crate and by function.
+//!
+//! The artifacts are the executables and shared libraries in the output
+//! directory of the requested profile, like `target/release`. Their functions
+//! are read from their symbol table, see `util::symbols`, and attributed to
+//! crates through their mangled names; the ones that aren't Rust code are
+//! attributed to `[unknown]`.
+//!
+//! Each report is saved in `.bloat/<artifact>.json` in the output directory,
+//! and the next report of the artifact shows how the sizes changed since. A
+//! report printed with `--json` can be passed back with `--baseline`, to
+//! compare against another build, and `--max-growth` fails when the code of
+//! an artifact grew too much.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context as _};
+use cargo_util::paths;
+use serde::{Deserialize, Serialize};
+
+use super::cargo_report_build_stats::format_bytes;
+use super::cargo_report_paths::output_dirs;
+use crate::core::Workspace;
+use crate::drop_println;
+use crate::util::errors::CargoResult;
+use crate::util::interning::InternedString;
+use crate::util::symbols;
+use crate::util::Config;
+
+/// The crate of the functions which aren't Rust code.
+const UNKNOWN_CRATE: &str = "[unknown]";
+
+pub struct ReportBloatOptions<'a> {
+    pub config: &'a Config,
+    /// The target triples whose artifacts are analyzed, or none for the host.
+    pub targets: Vec<String>,
+    /// The profile whose artifacts are analyzed.
+    pub requested_profile: InternedString,
+    /// The names of the artifacts to analyze, like `foo` or `libfoo.so`, or
+    /// none for all of them.
+    pub artifacts: Vec<String>,
+    /// How many crates and functions to print.
+    pub top: usize,
+    /// Print the reports as JSON, one line per artifact.
+    pub json: bool,
+    /// The reports to compare with, printed with `--json` by an earlier
+    /// run, instead of the saved ones.
+    pub baseline: Option<PathBuf>,
+    /// Fail if the code of an artifact grew by more than this percentage.
+    pub max_growth: Option<f64>,
+}
+
+/// The code size of an artifact.
+#[derive(Serialize, Deserialize)]
+struct Bloat {
+    artifact: String,
+    file_size: u64,
+    text_size: u64,
+    /// The change of `text_size` since the report compared with.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    text_change: Option<i64>,
+    /// All of the crates, largest first.
+    crates: Vec<CrateSize>,
+    /// The largest functions.
+    symbols: Vec<SymbolSize>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CrateSize {
+    name: String,
+    size: u64,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    change: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SymbolSize {
+    name: String,
+    #[serde(rename = "crate")]
+    krate: String,
+    size: u64,
+}
+
+/// Prints the size breakdown of the requested artifacts.
+pub fn report_bloat(ws: &Workspace<'_>, opts: &ReportBloatOptions<'_>) -> CargoResult<()> {
+    let config = opts.config;
+    let baseline = match &opts.baseline {
+        Some(path) => Some(load_reports(path)?),
+        None => None,
+    };
+
+    let mut analyzed = 0;
+    let mut too_large = Vec::new();
+    for dir in output_dirs(ws, &opts.targets, opts.requested_profile)? {
+        if !dir.is_dir() {
+            bail!(
+                "no artifacts found in `{}`, build them before analyzing them",
+                dir.display()
+            );
+        }
+        for (path, data) in artifacts(&dir, &opts.artifacts)? {
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            let text = symbols::text_symbols(&data)
+                .with_context(|| format!("failed to read the symbols of `{}`", path.display()))?;
+            let mut bloat = analyze(name.clone(), data.len() as u64, text);
+            bloat.symbols.truncate(opts.top);
+
+            let saved = dir.join(".bloat").join(format!("{}.json", name));
+            let saved_previous = if baseline.is_none() && saved.exists() {
+                load_reports(&saved)?.remove(&name)
+            } else {
+                None
+            };
+            paths::create_dir_all(saved.parent().unwrap())?;
+            paths::write(&saved, serde_json::to_string(&bloat)?)?;
+
+            let previous = match &baseline {
+                Some(baseline) => baseline.get(&name),
+                None => saved_previous.as_ref(),
+            };
+            if let Some(previous) = previous {
+                compare(&mut bloat, previous);
+                if let Some(max_growth) = opts.max_growth {
+                    let growth = growth(previous.text_size, bloat.text_size);
+                    if growth > max_growth {
+                        too_large.push(format!(
+                            "the code of `{}` grew by {:.1}%, from {} to {}",
+                            name,
+                            growth,
+                            format_bytes(previous.text_size),
+                            format_bytes(bloat.text_size)
+                        ));
+                    }
+                }
+            }
+
+            if opts.json {
+                drop_println!(config, "{}", serde_json::to_string(&bloat)?);
+            } else {
+                print(config, &bloat, opts.top);
+            }
+            analyzed += 1;
+        }
+    }
+    if analyzed == 0 {
+        if opts.artifacts.is_empty() {
+            bail!("no executables or shared libraries found, build them before analyzing them");
+        }
+        bail!(
+            "no artifacts named {} found",
+            opts.artifacts
+                .iter()
+                .map(|a| format!("`{}`", a))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    if !too_large.is_empty() {
+        bail!(
+            "{}, more than the {}% allowed by `--max-growth`",
+            too_large.join(", "),
+            opts.max_growth.unwrap()
+        );
+    }
+    Ok(())
+}
+
+/// The executables and shared libraries in `dir`, with their contents,
+/// sorted by name.
+fn artifacts(dir: &Path, names: &[String]) -> CargoResult<Vec<(PathBuf, Vec<u8>)>> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    paths.sort();
+    let mut artifacts = Vec::new();
+    for path in paths {
+        if !path.is_file() {
+            continue;
+        }
+        let file_name = path.file_name().unwrap().to_string_lossy();
+        let stem = path.file_stem().unwrap().to_string_lossy();
+        if !names.is_empty() && !names.iter().any(|n| *n == file_name || *n == stem) {
+            continue;
+        }
+        let data = paths::read_bytes(&path)?;
+        if symbols::is_object(&data) {
+            artifacts.push((path, data));
+        }
+    }
+    Ok(artifacts)
+}
+
+/// Attributes the functions of an artifact to their crates.
+fn analyze(artifact: String, file_size: u64, text: symbols::TextSymbols) -> Bloat {
+    let mut crates: HashMap<String, u64> = HashMap::new();
+    let mut symbols = Vec::new();
+    for symbol in text.symbols {
+        let krate =
+            symbols::symbol_crate(&symbol.name).unwrap_or_else(|| UNKNOWN_CRATE.to_string());
+        *crates.entry(krate.clone()).or_default() += symbol.size;
+        symbols.push(SymbolSize {
+            name: symbols::demangle(&symbol.name),
+            krate,
+            size: symbol.size,
+        });
+    }
+    let mut crates: Vec<CrateSize> = crates
+        .into_iter()
+        .map(|(name, size)| CrateSize {
+            name,
+            size,
+            change: None,
+        })
+        .collect();
+    crates.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+    symbols.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+    Bloat {
+        artifact,
+        file_size,
+        text_size: text.text_size,
+        text_change: None,
+        crates,
+        symbols,
+    }
+}
+
+/// Sets the changes of the sizes of `bloat` since `previous`.
+fn compare(bloat: &mut Bloat, previous: &Bloat) {
+    bloat.text_change = Some(bloat.text_size as i64 - previous.text_size as i64);
+    let sizes: BTreeMap<&str, u64> = previous
+        .crates
+        .iter()
+        .map(|c| (c.name.as_str(), c.size))
+        .collect();
+    for krate in &mut bloat.crates {
+        let previous = sizes.get(krate.name.as_str()).cloned().unwrap_or(0);
+        krate.change = Some(krate.size as i64 - previous as i64);
+    }
+}
+
+/// By how many percents `size` is larger than `previous`.
+fn growth(previous: u64, size: u64) -> f64 {
+    if previous == 0 {
+        return if size == 0 { 0.0 } else { f64::INFINITY };
+    }
+    (size as f64 / previous as f64 - 1.0) * 100.0
+}
+
+/// Loads the reports printed with `--json`, by artifact.
+fn load_reports(path: &Path) -> CargoResult<HashMap<String, Bloat>> {
+    let contents = paths::read(path)?;
+    let mut reports = HashMap::new();
+    for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+        let bloat: Bloat = serde_json::from_str(line)
+            .with_context(|| format!("failed to parse the bloat report `{}`", path.display()))?;
+        reports.insert(bloat.artifact.clone(), bloat);
+    }
+    Ok(reports)
+}
+
+fn print(config: &Config, bloat: &Bloat, top: usize) {
+    let percent = |size: u64| {
+        if bloat.text_size == 0 {
+            0.0
+        } else {
+            size as f64 * 100.0 / bloat.text_size as f64
+        }
+    };
+    drop_println!(
+        config,
+        "{}: {}, .text {}{}",
+        bloat.artifact,
+        format_bytes(bloat.file_size),
+        format_bytes(bloat.text_size),
+        format_change(bloat.text_change)
+    );
+    drop_println!(config, "  Crates:");
+    for krate in bloat.crates.iter().take(top) {
+        drop_println!(
+            config,
+            "  {:>6.1}% {:>10}  {}{}",
+            percent(krate.size),
+            format_bytes(krate.size),
+            krate.name,
+            format_change(krate.change)
+        );
+    }
+    if bloat.crates.len() > top {
+        let rest: u64 = bloat.crates[top..].iter().map(|c| c.size).sum();
+        drop_println!(
+            config,
+            "  {:>6.1}% {:>10}  {} more crates",
+            percent(rest),
+            format_bytes(rest),
+            bloat.crates.len() - top
+        );
+    }
+    drop_println!(config, "  Functions:");
+    for symbol in bloat.symbols.iter().take(top) {
+        drop_println!(
+            config,
+            "  {:>6.1}% {:>10}  {}",
+            percent(symbol.size),
+            format_bytes(symbol.size),
+            symbol.name
+        );
+    }
+}
+
+fn format_change(change: Option<i64>) -> String {
+    match change {
+        Some(0) | None => String::new(),
+        Some(change) if change > 0 => format!(" (+{})", format_bytes(change as u64)),
+        Some(change) => format!(" (-{})", format_bytes(change.unsigned_abs())),
+    }
+}
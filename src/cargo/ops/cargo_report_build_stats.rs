@@ -0,0 +1,152 @@
+//! Implementation of `cargo report build-stats`, which prints the statistics
+//! recorded by builds with `-Z build-stats`: the trend of the last builds and
+//! the units that took the longest to compile.
+
+use std::collections::HashMap;
+
+use anyhow::bail;
+
+use crate::core::compiler::build_stats::{self, UnitRecord};
+use crate::core::Workspace;
+use crate::drop_println;
+use crate::util::errors::CargoResult;
+use crate::util::Config;
+
+pub struct ReportBuildStatsOptions<'a> {
+    pub config: &'a Config,
+    /// How many of the slowest units to print.
+    pub top: usize,
+    /// How many of the last builds to print.
+    pub builds: usize,
+}
+
+/// The compilations of a unit across the recorded builds.
+struct UnitHistory<'a> {
+    /// The last time the unit was compiled.
+    last: &'a UnitRecord,
+    /// The durations of its compilations, oldest first.
+    durations: Vec<f64>,
+}
+
+impl UnitHistory<'_> {
+    fn average(&self) -> f64 {
+        self.durations.iter().sum::<f64>() / self.durations.len() as f64
+    }
+}
+
+/// Prints the last builds, and the units which took the longest to compile
+/// on average.
+pub fn report_build_stats(
+    ws: &Workspace<'_>,
+    opts: &ReportBuildStatsOptions<'_>,
+) -> CargoResult<()> {
+    let config = opts.config;
+    let file = build_stats::stats_file(ws.target_dir().as_path_unlocked());
+    if !file.exists() {
+        bail!(
+            "no build statistics found in `{}`, build with `-Z build-stats` to record them",
+            file.display()
+        );
+    }
+    let builds = build_stats::load(&file)?;
+
+    let shown = builds.len().saturating_sub(opts.builds);
+    drop_println!(
+        config,
+        "Last {} of {} recorded builds:",
+        builds.len() - shown,
+        builds.len()
+    );
+    for (i, build) in builds.iter().enumerate().skip(shown) {
+        let fresh = build.units.iter().filter(|unit| unit.fresh).count();
+        let mut line = format!(
+            "{:>5}  {:<8} {:>8.2}s  {} compiled, {} fresh",
+            i + 1,
+            build.profile,
+            build.duration,
+            build.units.len() - fresh,
+            fresh
+        );
+        if let Some(peak_memory) = build.peak_memory {
+            line.push_str(&format!(", peak memory {}", format_bytes(peak_memory)));
+        }
+        if !build.success {
+            line.push_str(", failed");
+        }
+        drop_println!(config, "{}", line);
+    }
+
+    let mut units: HashMap<_, UnitHistory<'_>> = HashMap::new();
+    for unit in builds.iter().flat_map(|build| &build.units) {
+        if unit.fresh {
+            continue;
+        }
+        let history = units.entry(unit.key()).or_insert_with(|| UnitHistory {
+            last: unit,
+            durations: Vec::new(),
+        });
+        history.last = unit;
+        history.durations.push(unit.duration);
+    }
+    let mut units: Vec<_> = units.into_values().collect();
+    units.sort_by(|a, b| {
+        b.average()
+            .partial_cmp(&a.average())
+            .unwrap()
+            .then_with(|| a.last.key().cmp(&b.last.key()))
+    });
+
+    drop_println!(config, "\nSlowest units, by average compile time:");
+    if units.is_empty() {
+        drop_println!(config, "    no unit was compiled in the recorded builds");
+    }
+    for history in units.iter().take(opts.top) {
+        let unit = history.last;
+        let mut line = format!(
+            "{:>8.2}s  {} {}",
+            history.average(),
+            unit.package,
+            unit.target
+        );
+        if unit.mode != "build" {
+            line.push_str(&format!(" ({})", unit.mode));
+        }
+        line.push_str(&format!(
+            ", last {:.2}s over {} builds",
+            unit.duration,
+            history.durations.len()
+        ));
+        if let Some(trend) = trend(&history.durations) {
+            line.push_str(&format!(" ({:+.0}%)", trend));
+        }
+        if let Some(size) = unit.size {
+            line.push_str(&format!(", {}", format_bytes(size)));
+        }
+        drop_println!(config, "{}", line);
+    }
+    Ok(())
+}
+
+/// How much slower the last compilation was than the earlier ones on
+/// average, in percents.
+fn trend(durations: &[f64]) -> Option<f64> {
+    let (last, earlier) = durations.split_last()?;
+    if earlier.is_empty() {
+        return None;
+    }
+    let average = earlier.iter().sum::<f64>() / earlier.len() as f64;
+    if average <= 0.0 {
+        return None;
+    }
+    Some((last / average - 1.0) * 100.0)
+}
+
+pub(super) fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let bytes = bytes as f64;
+    let i = ((bytes.max(1.0).log2() / 10.0) as usize).min(UNITS.len() - 1);
+    if i == 0 {
+        return format!("{} B", bytes);
+    }
+    format!("{:.1} {}", bytes / 1024_f64.powi(i as i32), UNITS[i])
+}
@@ -0,0 +1,53 @@
+//! Implementation of `cargo report check-cfg`, which checks the workspace and
+//! prints the cfgs each package of the build expects, with where they are
+//! declared, see `core::compiler::check_cfg`.
+
+use crate::core::compiler::CompileMode;
+use crate::core::Workspace;
+use crate::drop_println;
+use crate::ops::{self, CompileOptions};
+use crate::util::errors::CargoResult;
+
+pub struct ReportCheckCfgOptions {
+    /// The options of the `cargo check` the cfgs are collected with.
+    pub compile_opts: CompileOptions,
+}
+
+/// Checks the workspace, so that the build scripts declare their cfgs, then
+/// prints the expected cfgs of every package.
+pub fn report_check_cfg(ws: &Workspace<'_>, opts: &ReportCheckCfgOptions) -> CargoResult<()> {
+    assert!(matches!(
+        opts.compile_opts.build_config.mode,
+        CompileMode::Check { .. }
+    ));
+    let compilation = ops::compile(ws, &opts.compile_opts)?;
+    let config = ws.config();
+    for (id, expected) in &compilation.expected_cfgs {
+        drop_println!(
+            config,
+            "{}{}",
+            id,
+            if expected.checked {
+                ""
+            } else {
+                " (not checked)"
+            }
+        );
+        let width = expected
+            .cfgs
+            .iter()
+            .map(|cfg| cfg.spec.len())
+            .max()
+            .unwrap_or(0);
+        for cfg in &expected.cfgs {
+            drop_println!(
+                config,
+                "  {:<width$}  {}",
+                cfg.spec,
+                cfg.origin,
+                width = width
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,211 @@
+//! Implementation of `cargo report licenses`, which lists the licenses of the
+//! dependencies of a workspace and, with `--bundle`, assembles their license
+//! texts into a third-party notices file to distribute with the workspace's
+//! artifacts.
+//!
+//! The dependencies are the packages of the resolved graph reachable from the
+//! workspace members through normal and build dependencies, without the
+//! members themselves. Their license texts are read from their sources, as
+//! extracted from the downloaded `.crate` files, see `license_files`.
+//!
+//! The bundle is written to `target/licenses`, with:
+//!
+//! * `THIRD-PARTY-NOTICES`, the license expression, repository and license
+//!   texts of every dependency,
+//! * `licenses.json`, a summary of the same information.
+//!
+//! A bundle is only written if every dependency has a valid SPDX license
+//! expression, or a `license-file`, and at least one license file.
+
+use std::collections::{BTreeSet, HashSet};
+use std::path::Path;
+
+use anyhow::bail;
+use cargo_util::paths;
+use serde::Serialize;
+
+use super::cargo_package_binary::license_files;
+use crate::core::dependency::DepKind;
+use crate::core::{check_license_expr, Package, PackageId, Workspace};
+use crate::drop_println;
+use crate::ops;
+use crate::util::errors::CargoResult;
+use crate::util::Config;
+
+pub struct ReportLicensesOptions<'a> {
+    pub config: &'a Config,
+    /// Whether to write the third-party notices to `target/licenses`.
+    pub bundle: bool,
+}
+
+#[derive(Serialize)]
+struct LicenseSummary {
+    packages: Vec<PackageLicense>,
+}
+
+#[derive(Serialize)]
+struct PackageLicense {
+    name: String,
+    version: String,
+    id: PackageId,
+    license: Option<String>,
+    repository: Option<String>,
+    /// The license files, relative to the root of the package.
+    license_files: Vec<String>,
+    #[serde(skip)]
+    texts: Vec<(String, String)>,
+}
+
+/// Lists the licenses of the dependencies of the workspace, or bundles their
+/// license texts.
+pub fn report_licenses(ws: &Workspace<'_>, opts: &ReportLicensesOptions<'_>) -> CargoResult<()> {
+    let config = opts.config;
+    let (pkg_set, resolve) = ops::resolve_ws(ws)?;
+
+    let members: HashSet<PackageId> = ws.members().map(|pkg| pkg.package_id()).collect();
+    let mut seen = BTreeSet::new();
+    let mut stack: Vec<PackageId> = members.iter().cloned().collect();
+    while let Some(id) = stack.pop() {
+        for (dep_id, deps) in resolve.deps(id) {
+            let linked = deps.iter().any(|dep| dep.kind() != DepKind::Development);
+            if linked && !members.contains(&dep_id) && seen.insert(dep_id) {
+                stack.push(dep_id);
+            }
+        }
+    }
+
+    let mut packages = Vec::new();
+    let mut problems = Vec::new();
+    for pkg in pkg_set.get_many(seen)? {
+        let license = package_license(pkg, &mut problems)?;
+        packages.push(license);
+    }
+    packages.sort_by_key(|pkg| pkg.id);
+
+    if !opts.bundle {
+        for pkg in &packages {
+            drop_println!(
+                config,
+                "{} v{}: {} ({})",
+                pkg.name,
+                pkg.version,
+                pkg.license.as_deref().unwrap_or("no license expression"),
+                if pkg.license_files.is_empty() {
+                    "no license files".to_string()
+                } else {
+                    pkg.license_files.join(", ")
+                }
+            );
+        }
+        for problem in problems {
+            config.shell().warn(problem)?;
+        }
+        return Ok(());
+    }
+
+    if !problems.is_empty() {
+        bail!(
+            "the licenses of {} dependencies can't be bundled:\n  {}",
+            problems.len(),
+            problems.join("\n  ")
+        );
+    }
+    let dir = ws.target_dir().join("licenses").into_path_unlocked();
+    paths::create_dir_all(&dir)?;
+    paths::write(dir.join("THIRD-PARTY-NOTICES"), notices(ws, &packages))?;
+    let summary = LicenseSummary { packages };
+    paths::write(
+        dir.join("licenses.json"),
+        serde_json::to_string_pretty(&summary)?,
+    )?;
+    config.shell().status(
+        "Bundled",
+        format!(
+            "the licenses of {} dependencies into `{}`",
+            summary.packages.len(),
+            dir.display()
+        ),
+    )?;
+    Ok(())
+}
+
+/// Reads the license of `pkg`, adding what prevents bundling it to
+/// `problems`.
+fn package_license(pkg: &Package, problems: &mut Vec<String>) -> CargoResult<PackageLicense> {
+    let metadata = pkg.manifest().metadata();
+    let id = pkg.package_id();
+    match &metadata.license {
+        Some(license) => {
+            if let Err(e) = check_license_expr(license) {
+                problems.push(format!(
+                    "{} has the license `{}`, which is not a valid SPDX expression: {}",
+                    id, license, e
+                ));
+            }
+        }
+        None if metadata.license_file.is_none() => {
+            problems.push(format!("{} has no license expression", id));
+        }
+        None => {}
+    }
+
+    let files = license_files(pkg)?;
+    if files.is_empty() {
+        problems.push(format!("{} has no license files", id));
+    }
+    let mut license_files = Vec::new();
+    let mut texts = Vec::new();
+    for file in files {
+        let name = relative_name(pkg, &file);
+        texts.push((name.clone(), paths::read(&file)?));
+        license_files.push(name);
+    }
+    Ok(PackageLicense {
+        name: pkg.name().to_string(),
+        version: pkg.version().to_string(),
+        id,
+        license: metadata.license.clone(),
+        repository: metadata.repository.clone(),
+        license_files,
+        texts,
+    })
+}
+
+/// The path of a license file relative to the root of its package, with `/`
+/// separators.
+fn relative_name(pkg: &Package, file: &Path) -> String {
+    let relative = file.strip_prefix(pkg.root()).unwrap_or(file);
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The contents of the `THIRD-PARTY-NOTICES` file.
+fn notices(ws: &Workspace<'_>, packages: &[PackageLicense]) -> String {
+    let separator = "-".repeat(80);
+    let mut members: Vec<_> = ws.members().map(|pkg| pkg.name().to_string()).collect();
+    members.sort();
+    let mut notices = format!(
+        "THIRD-PARTY SOFTWARE NOTICES\n\n\
+         The licenses of the third-party packages used by {}.\n",
+        members.join(", ")
+    );
+    for pkg in packages {
+        notices.push_str(&format!(
+            "\n{}\n\n{} {}\n",
+            separator, pkg.name, pkg.version
+        ));
+        if let Some(license) = &pkg.license {
+            notices.push_str(&format!("License: {}\n", license));
+        }
+        if let Some(repository) = &pkg.repository {
+            notices.push_str(&format!("Repository: {}\n", repository));
+        }
+        for (name, text) in &pkg.texts {
+            notices.push_str(&format!("\n{}:\n\n{}\n", name, text.trim_end()));
+        }
+    }
+    notices
+}
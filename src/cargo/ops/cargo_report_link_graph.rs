@@ -0,0 +1,38 @@
+//! Implementation of `cargo report link-graph`, which prints the crates and
+//! native libraries linked into each artifact, as recorded by builds with
+//! `-Z link-graph`.
+
+use anyhow::bail;
+
+use super::cargo_report_paths::output_dirs;
+use crate::core::compiler::link_graph;
+use crate::core::Workspace;
+use crate::drop_println;
+use crate::util::errors::CargoResult;
+use crate::util::interning::InternedString;
+use crate::util::Config;
+
+pub struct ReportLinkGraphOptions<'a> {
+    pub config: &'a Config,
+    /// The target triples whose link graphs are printed, or none for the host.
+    pub targets: Vec<String>,
+    /// The profile whose link graphs are printed.
+    pub requested_profile: InternedString,
+}
+
+/// Prints the link graph recorded for each requested target, as one line of
+/// JSON each.
+pub fn report_link_graph(ws: &Workspace<'_>, opts: &ReportLinkGraphOptions<'_>) -> CargoResult<()> {
+    for dir in output_dirs(ws, &opts.targets, opts.requested_profile)? {
+        let file = dir.join(link_graph::FILE_NAME);
+        if !file.exists() {
+            bail!(
+                "no link graph found in `{}`, build with `-Z link-graph` to record it",
+                dir.display()
+            );
+        }
+        let graph = link_graph::load(&file)?;
+        drop_println!(opts.config, "{}", serde_json::to_string(&graph)?);
+    }
+    Ok(())
+}
@@ -0,0 +1,146 @@
+//! Implementation of `cargo report paths`, which checks that the final
+//! artifacts of a build don't contain absolute paths of the machine that
+//! built them, like those that `trim-paths` removes.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::bail;
+
+use crate::core::compiler::CompileKind;
+use crate::core::profiles::Profiles;
+use crate::core::Workspace;
+use crate::drop_println;
+use crate::util::errors::CargoResult;
+use crate::util::interning::InternedString;
+use crate::util::Config;
+
+pub struct ReportPathsOptions<'a> {
+    pub config: &'a Config,
+    /// The target triples whose artifacts are checked, or none for the host.
+    pub targets: Vec<String>,
+    /// The profile whose artifacts are checked.
+    pub requested_profile: InternedString,
+}
+
+/// Scans the artifacts in the output directory of the requested profile for
+/// the home directory, the Cargo home and the workspace root, and fails if
+/// any of them was found.
+pub fn report_paths(ws: &Workspace<'_>, opts: &ReportPathsOptions<'_>) -> CargoResult<()> {
+    let config = opts.config;
+    let dirs = output_dirs(ws, &opts.targets, opts.requested_profile)?;
+
+    let prefixes = prefixes(ws);
+    let mut checked = 0;
+    let mut leaking = 0;
+    for dir in &dirs {
+        if !dir.is_dir() {
+            bail!(
+                "no artifacts found in `{}`, build them before checking them",
+                dir.display()
+            );
+        }
+        let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<_, _>>()?;
+        files.sort();
+        for file in files.iter().filter(|file| is_artifact(file)) {
+            checked += 1;
+            let found = find_paths(&fs::read(file)?, &prefixes);
+            if found.is_empty() {
+                continue;
+            }
+            leaking += 1;
+            let shown = file.strip_prefix(config.cwd()).unwrap_or(file);
+            drop_println!(config, "{}:", shown.display());
+            for path in found {
+                drop_println!(config, "    {}", path);
+            }
+        }
+    }
+    if leaking > 0 {
+        bail!(
+            "found absolute paths in {} of {} artifacts, \
+             see the `trim-paths` profile setting to remove them",
+            leaking,
+            checked
+        );
+    }
+    config.shell().status(
+        "Checked",
+        format!("{} artifacts, found no absolute paths", checked),
+    )?;
+    Ok(())
+}
+
+/// The output directories of the requested profile, for each of the `targets`
+/// or the host.
+pub(super) fn output_dirs(
+    ws: &Workspace<'_>,
+    targets: &[String],
+    requested_profile: InternedString,
+) -> CargoResult<Vec<PathBuf>> {
+    let profiles = Profiles::new(ws, requested_profile)?;
+    let target_dir = ws.target_dir().into_path_unlocked();
+    Ok(CompileKind::from_requested_targets(ws.config(), targets)?
+        .into_iter()
+        .map(|kind| match kind {
+            CompileKind::Host => target_dir.join(profiles.get_dir_name()),
+            CompileKind::Target(target) => target_dir
+                .join(target.short_name())
+                .join(profiles.get_dir_name()),
+        })
+        .collect())
+}
+
+/// The paths that shouldn't be in the artifacts, without the ones inside
+/// another.
+fn prefixes(ws: &Workspace<'_>) -> Vec<String> {
+    let mut prefixes: Vec<PathBuf> = home::home_dir()
+        .into_iter()
+        .chain(Some(ws.config().home().as_path_unlocked().to_path_buf()))
+        .chain(Some(ws.root().to_path_buf()))
+        .collect();
+    prefixes.sort();
+    let mut kept: Vec<PathBuf> = Vec::new();
+    for prefix in prefixes {
+        if !kept.iter().any(|kept| prefix.starts_with(kept)) {
+            kept.push(prefix);
+        }
+    }
+    kept.into_iter()
+        .filter_map(|prefix| prefix.to_str().map(|s| s.to_string()))
+        .collect()
+}
+
+/// Whether `file` is a final artifact, and not some of Cargo's bookkeeping,
+/// like the dep-info files, which are expected to have absolute paths.
+fn is_artifact(file: &Path) -> bool {
+    let name = match file.file_name().and_then(|name| name.to_str()) {
+        Some(name) => name,
+        None => return false,
+    };
+    file.is_file()
+        && !name.starts_with('.')
+        && !matches!(
+            file.extension().and_then(|ext| ext.to_str()),
+            Some("d" | "json")
+        )
+}
+
+/// The printable strings in `bytes` that contain one of the `prefixes`.
+fn find_paths(bytes: &[u8], prefixes: &[String]) -> BTreeSet<String> {
+    let shortest = prefixes
+        .iter()
+        .map(|prefix| prefix.len())
+        .min()
+        .unwrap_or(0);
+    bytes
+        .split(|b| !(b.is_ascii_graphic() || *b == b' '))
+        .filter(|s| s.len() >= shortest)
+        .filter_map(|s| std::str::from_utf8(s).ok())
+        .filter(|s| prefixes.iter().any(|prefix| s.contains(prefix.as_str())))
+        .map(|s| s.to_string())
+        .collect()
+}
@@ -6,6 +6,7 @@ use crate::core::compiler::UnitOutput;
 use crate::core::{TargetKind, Workspace};
 use crate::ops;
 use crate::util::CargoResult;
+use cargo_util::ProcessError;
 
 pub fn run(
     ws: &Workspace<'_>,
@@ -97,5 +98,18 @@ pub fn run(
 
     config.shell().status("Running", process.to_string())?;
 
+    if let Some(runner) = compile.remote_runner(unit.kind) {
+        return runner.exec(config, &process, None, None);
+    }
+    // The exit code of a WebAssembly runtime may need translating, so it
+    // can't replace this process.
+    if let Some(runner) = compile.wasm_runner(unit.kind) {
+        return process
+            .exec()
+            .map_err(|e| match e.downcast::<ProcessError>() {
+                Ok(e) => runner.translate_error(e).into(),
+                Err(e) => e,
+            });
+    }
     process.exec_replace()
 }
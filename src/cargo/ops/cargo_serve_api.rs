@@ -0,0 +1,442 @@
+//! `cargo serve-api`, with `-Z serve-api`.
+//!
+//! Cargo keeps running as a server for IDEs and build tools, which send it
+//! requests instead of running `cargo` and parsing its output for each
+//! operation. The configuration and the workspace stay loaded between the
+//! requests, and the workspace is only loaded again when its manifests or
+//! `Cargo.lock` change.
+//!
+//! The protocol is JSON-RPC 2.0 over stdin and stdout, one message per line.
+//! The methods are:
+//!
+//! * `initialize`, answered with the version of the protocol and of Cargo,
+//!   and the root of the workspace.
+//! * `metadata`, answered with the metadata `cargo metadata` prints. Its
+//!   params are `no_deps`, `features`, `all_features` and
+//!   `no_default_features`.
+//! * `resolve`, answered with the `resolve` of the metadata, and the same
+//!   params except `no_deps`.
+//! * `check` and `build`, which build the workspace and are answered with
+//!   `null` when it succeeded. Their params are `packages`, `workspace`,
+//!   `exclude`, `all_targets`, `release` and the feature params. While they
+//!   run, Cargo sends `build/message` notifications with the JSON messages
+//!   of `--message-format json`, and `log` notifications with the lines of
+//!   its other output, each with the id of the `request`.
+//! * `cancel`, with the `id` of a `check` or `build`. No more jobs are started
+//!   for it, and it fails with the code -32800 once the running ones finish.
+//! * `shutdown`, which stops Cargo once answered. It also stops when its
+//!   stdin is closed.
+//!
+//! Requests are handled one at a time, in the order they are received,
+//! except for `cancel`, which is handled right away.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::SystemTime;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::metadata_watch::{inputs_changed, workspace_inputs};
+use crate::core::compiler::{CompileMode, MessageFormat};
+use crate::core::resolver::CliFeatures;
+use crate::core::{Shell, Workspace};
+use crate::ops::{self, CompileFilter, CompileOptions, OutputMetadataOptions, Packages};
+use crate::util::interning::InternedString;
+use crate::util::{CargoResult, Config};
+
+/// The version of the protocol spoken with the client.
+const PROTOCOL_VERSION: u32 = 1;
+
+// The error codes of JSON-RPC, and of the Language Server Protocol for
+// cancelled requests.
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const REQUEST_FAILED: i64 = -32000;
+const REQUEST_CANCELLED: i64 = -32800;
+
+#[derive(Deserialize)]
+struct Request {
+    jsonrpc: String,
+    /// Missing for notifications, which are not answered.
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct FeatureParams {
+    features: Vec<String>,
+    all_features: bool,
+    no_default_features: bool,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct MetadataParams {
+    no_deps: bool,
+    #[serde(flatten)]
+    features: FeatureParams,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct BuildParams {
+    packages: Vec<String>,
+    workspace: bool,
+    exclude: Vec<String>,
+    all_targets: bool,
+    release: bool,
+    #[serde(flatten)]
+    features: FeatureParams,
+}
+
+#[derive(Deserialize, Default)]
+struct CancelParams {
+    id: Value,
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> RpcError {
+        RpcError {
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// The error of a failed operation, with the causes Cargo would print
+    /// but without the backtrace of `{:?}`.
+    fn failed(e: anyhow::Error) -> RpcError {
+        let mut message = e.to_string();
+        let mut causes = e.chain().skip(1).peekable();
+        if causes.peek().is_some() {
+            message.push_str("\n\nCaused by:");
+        }
+        for cause in causes {
+            for line in cause.to_string().lines() {
+                message.push_str("\n  ");
+                message.push_str(line);
+            }
+        }
+        RpcError::new(REQUEST_FAILED, message)
+    }
+}
+
+/// The cancellation flags of the `check` and `build` requests that were
+/// received and are not answered yet, by the JSON of their id.
+type Builds = Arc<Mutex<HashMap<String, Arc<AtomicBool>>>>;
+
+/// Serves the workspace of `manifest_path` until the client shuts Cargo down
+/// or closes its stdin.
+pub fn serve_api(config: &Config, manifest_path: &Path) -> CargoResult<()> {
+    let builds = Builds::default();
+    let (tx, rx) = mpsc::channel();
+    {
+        let builds = Arc::clone(&builds);
+        thread::spawn(move || read_requests(tx, builds));
+    }
+
+    // The output of the operations is sent to the client as notifications.
+    let request = Rc::new(RefCell::new(Value::Null));
+    let mut shell = Shell::from_write(Box::new(Notifier {
+        request: Rc::clone(&request),
+        line: Vec::new(),
+    }));
+    shell.set_verbosity(config.shell().verbosity());
+    let shell = mem::replace(&mut *config.shell(), shell);
+
+    let mut server = Server {
+        config,
+        manifest_path,
+        ws: None,
+        inputs: Vec::new(),
+    };
+    let result = (|| -> CargoResult<()> {
+        for (req, cancelled) in rx {
+            let id = req.id.clone().unwrap_or(Value::Null);
+            *request.borrow_mut() = id.clone();
+            let result = server.handle(&req, cancelled);
+            if req.id.is_some() {
+                respond(id.clone(), result)?;
+            }
+            builds.lock().unwrap().remove(&id.to_string());
+            if req.method == "shutdown" {
+                break;
+            }
+        }
+        Ok(())
+    })();
+
+    *config.shell() = shell;
+    result
+}
+
+/// Reads the requests from stdin, and passes them on to the server, except
+/// for those that are invalid and `cancel`, which are answered right away.
+fn read_requests(tx: mpsc::Sender<(Request, Option<Arc<AtomicBool>>)>, builds: Builds) {
+    let stdin = io::stdin();
+    for line in stdin.lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let value: Value = match serde_json::from_str(&line) {
+            Ok(value) => value,
+            Err(e) => {
+                let e = RpcError::new(PARSE_ERROR, format!("invalid JSON: {}", e));
+                drop(respond(Value::Null, Err(e)));
+                continue;
+            }
+        };
+        let id = value.get("id").cloned().unwrap_or(Value::Null);
+        let req = match serde_json::from_value::<Request>(value) {
+            Ok(req) if req.jsonrpc == "2.0" => req,
+            Ok(_) => {
+                let e = RpcError::new(INVALID_REQUEST, "`jsonrpc` must be \"2.0\"");
+                drop(respond(id, Err(e)));
+                continue;
+            }
+            Err(e) => {
+                let e = RpcError::new(INVALID_REQUEST, format!("invalid request: {}", e));
+                drop(respond(id, Err(e)));
+                continue;
+            }
+        };
+        match req.method.as_str() {
+            "cancel" => {
+                let result = cancel(&builds, &req.params);
+                if req.id.is_some() {
+                    drop(respond(id, result));
+                }
+            }
+            "check" | "build" => {
+                let cancelled = Arc::new(AtomicBool::new(false));
+                let key = id.to_string();
+                builds.lock().unwrap().insert(key, Arc::clone(&cancelled));
+                if tx.send((req, Some(cancelled))).is_err() {
+                    break;
+                }
+            }
+            _ => {
+                if tx.send((req, None)).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+fn cancel(builds: &Builds, params: &Value) -> Result<Value, RpcError> {
+    let params: CancelParams = parse_params(params)?;
+    match builds.lock().unwrap().get(&params.id.to_string()) {
+        Some(cancelled) => {
+            cancelled.store(true, Ordering::SeqCst);
+            Ok(Value::Null)
+        }
+        None => Err(RpcError::new(
+            INVALID_PARAMS,
+            format!("there is no `check` or `build` with the id {}", params.id),
+        )),
+    }
+}
+
+struct Server<'a, 'cfg> {
+    config: &'cfg Config,
+    manifest_path: &'a Path,
+    /// The workspace, kept loaded between the requests.
+    ws: Option<Workspace<'cfg>>,
+    /// The files the workspace was loaded from, with their modification time.
+    inputs: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+impl<'a, 'cfg> Server<'a, 'cfg> {
+    fn handle(
+        &mut self,
+        req: &Request,
+        cancelled: Option<Arc<AtomicBool>>,
+    ) -> Result<Value, RpcError> {
+        match req.method.as_str() {
+            "initialize" => {
+                let ws = self.workspace().map_err(RpcError::failed)?;
+                Ok(json!({
+                    "protocol_version": PROTOCOL_VERSION,
+                    "cargo_version": crate::version().to_string(),
+                    "workspace_root": ws.root(),
+                }))
+            }
+            "metadata" => {
+                let params: MetadataParams = parse_params(&req.params)?;
+                self.metadata(params.no_deps, &params.features)
+                    .map_err(RpcError::failed)
+            }
+            "resolve" => {
+                let params: FeatureParams = parse_params(&req.params)?;
+                let metadata = self.metadata(false, &params).map_err(RpcError::failed)?;
+                Ok(metadata["resolve"].clone())
+            }
+            "check" | "build" => {
+                let params: BuildParams = parse_params(&req.params)?;
+                let mode = if req.method == "check" {
+                    CompileMode::Check { test: false }
+                } else {
+                    CompileMode::Build
+                };
+                let cancelled = cancelled.unwrap();
+                match self.build(mode, params, &cancelled) {
+                    Ok(()) => Ok(Value::Null),
+                    Err(_) if cancelled.load(Ordering::SeqCst) => {
+                        Err(RpcError::new(REQUEST_CANCELLED, "the build was cancelled"))
+                    }
+                    Err(e) => Err(RpcError::failed(e)),
+                }
+            }
+            "shutdown" => Ok(Value::Null),
+            method => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("unknown method `{}`", method),
+            )),
+        }
+    }
+
+    /// The workspace, loaded again if its inputs changed since the last
+    /// request.
+    fn workspace(&mut self) -> CargoResult<&Workspace<'cfg>> {
+        if self.ws.is_none() || inputs_changed(&self.inputs) {
+            self.ws = None;
+            let ws = Workspace::new(self.manifest_path, self.config)?;
+            self.inputs = workspace_inputs(&ws);
+            self.ws = Some(ws);
+        }
+        Ok(self.ws.as_ref().unwrap())
+    }
+
+    fn metadata(&mut self, no_deps: bool, features: &FeatureParams) -> CargoResult<Value> {
+        let opts = OutputMetadataOptions {
+            cli_features: features.cli_features()?,
+            no_deps,
+            version: 1,
+            filter_platforms: Vec::new(),
+        };
+        let ws = self.workspace()?;
+        Ok(serde_json::to_value(ops::output_metadata(ws, &opts)?)?)
+    }
+
+    fn build(
+        &mut self,
+        mode: CompileMode,
+        params: BuildParams,
+        cancelled: &Arc<AtomicBool>,
+    ) -> CargoResult<()> {
+        let mut opts = CompileOptions::new(self.config, mode)?;
+        opts.spec = Packages::from_flags(
+            params.workspace,
+            params.exclude,
+            params.packages,
+            Vec::new(),
+        )?;
+        opts.cli_features = params.features.cli_features()?;
+        if params.all_targets {
+            opts.filter = CompileFilter::new_all_targets();
+        }
+        if params.release {
+            opts.build_config.requested_profile = InternedString::new("release");
+        }
+        opts.build_config.message_format = MessageFormat::Json {
+            render_diagnostics: false,
+            short: false,
+            ansi: false,
+        };
+        opts.build_config.cancelled = Some(Arc::clone(cancelled));
+        let ws = self.workspace()?;
+        ops::compile(ws, &opts)?;
+        Ok(())
+    }
+}
+
+impl FeatureParams {
+    fn cli_features(&self) -> CargoResult<CliFeatures> {
+        CliFeatures::from_command_line(&self.features, self.all_features, !self.no_default_features)
+    }
+}
+
+fn parse_params<T: for<'de> Deserialize<'de> + Default>(params: &Value) -> Result<T, RpcError> {
+    if params.is_null() {
+        return Ok(T::default());
+    }
+    serde_json::from_value(params.clone())
+        .map_err(|e| RpcError::new(INVALID_PARAMS, format!("invalid params: {}", e)))
+}
+
+/// Sends the answer to the request `id`.
+fn respond(id: Value, result: Result<Value, RpcError>) -> io::Result<()> {
+    let message = match result {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+        Err(e) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": {"code": e.code, "message": e.message},
+        }),
+    };
+    send(&message)
+}
+
+fn send(message: &Value) -> io::Result<()> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    writeln!(out, "{}", message)?;
+    out.flush()
+}
+
+/// The output of Cargo while it serves a request, sent to the client line by
+/// line as notifications.
+struct Notifier {
+    /// The id of the request being served.
+    request: Rc<RefCell<Value>>,
+    line: Vec<u8>,
+}
+
+impl Write for Notifier {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.line.extend_from_slice(buf);
+        while let Some(end) = self.line.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.line.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line[..end]);
+            let request = self.request.borrow().clone();
+            // The JSON messages of the build are sent as they are.
+            let (method, message) = match serde_json::from_str::<Value>(&line) {
+                Ok(message) if message.is_object() => ("build/message", message),
+                _ => ("log", Value::String(line.into_owned())),
+            };
+            send(&json!({
+                "jsonrpc": "2.0",
+                "method": method,
+                "params": {"request": request, "message": message},
+            }))?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
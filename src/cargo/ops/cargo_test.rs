@@ -1,16 +1,32 @@
-use crate::core::compiler::{Compilation, CompileKind, Doctest, UnitOutput};
+use crate::core::compiler::{miri, Compilation, CompileKind, Doctest, Unit, UnitOutput};
 use crate::core::shell::Verbosity;
 use crate::core::{TargetKind, Workspace};
 use crate::ops;
+use crate::ops::test_affected::AffectedTests;
+use crate::ops::test_coverage::Coverage;
+use crate::ops::test_logs::{TestLog, TestLogs};
 use crate::util::errors::CargoResult;
+use crate::util::toml::TomlDuration;
 use crate::util::{add_path_args, CargoTestError, Config, Test};
-use cargo_util::ProcessError;
+use anyhow::Context as _;
+use cargo_util::{ProcessBuilder, ProcessError};
 use std::ffi::OsString;
+use std::path::PathBuf;
+use std::process::{Child, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
 
 pub struct TestOptions {
     pub compile_opts: ops::CompileOptions,
     pub no_run: bool,
     pub no_fail_fast: bool,
+    /// Only run the test binaries whose inputs changed since they last passed.
+    pub affected: bool,
+    /// Skip the test binaries that already passed with the same build.
+    pub cache_results: bool,
+    /// Route the output of each test binary into a log file instead of the
+    /// terminal.
+    pub test_logs: bool,
 }
 
 pub fn run_tests(
@@ -23,14 +39,58 @@ pub fn run_tests(
     if options.no_run {
         return Ok(None);
     }
-    let (test, mut errors) = run_unit_tests(ws.config(), options, test_args, &compilation)?;
-
+    let mut affected = if options.affected {
+        Some(AffectedTests::load(ws))
+    } else {
+        None
+    };
+    let mut logs = if options.test_logs {
+        Some(TestLogs::new(ws)?)
+    } else {
+        None
+    };
+    let coverage = if options.compile_opts.build_config.coverage {
+        Some(Coverage::new(ws)?)
+    } else {
+        None
+    };
+    let (test, mut errors) = run_unit_tests(
+        ws.config(),
+        options,
+        test_args,
+        &compilation,
+        affected.as_mut(),
+        logs.as_mut(),
+        coverage.as_ref(),
+    )?;
+    if let Some(affected) = &affected {
+        affected.save()?;
+    }
     // If we have an error and want to fail fast, then return.
     if !errors.is_empty() && !options.no_fail_fast {
+        if let Some(logs) = &logs {
+            logs.write_index()?;
+        }
         return Ok(Some(CargoTestError::new(test, errors)));
     }
 
-    let (doctest, docerrors) = run_doc_tests(ws, options, test_args, &compilation)?;
+    let (doctest, docerrors) = run_doc_tests(
+        ws,
+        options,
+        test_args,
+        &compilation,
+        logs.as_mut(),
+        coverage.as_ref(),
+    )?;
+    if let Some(logs) = &logs {
+        logs.write_index()?;
+    }
+    if let Some(coverage) = &coverage {
+        // A partial run would give a misleading report.
+        if errors.is_empty() && docerrors.is_empty() {
+            coverage.report(ws, &compilation)?;
+        }
+    }
     let test = if docerrors.is_empty() { test } else { doctest };
     errors.extend(docerrors);
     if errors.is_empty() {
@@ -54,7 +114,8 @@ pub fn run_benches(
     let mut args = args.to_vec();
     args.push("--bench");
 
-    let (test, errors) = run_unit_tests(ws.config(), options, &args, &compilation)?;
+    let (test, errors) =
+        run_unit_tests(ws.config(), options, &args, &compilation, None, None, None)?;
 
     match errors.len() {
         0 => Ok(None),
@@ -68,15 +129,68 @@ fn compile_tests<'a>(ws: &Workspace<'a>, options: &TestOptions) -> CargoResult<C
     Ok(compilation)
 }
 
+/// A test process to run: a test binary, or the doc tests of a library.
+struct TestRun<'a> {
+    unit: &'a Unit,
+    /// The test binary, or rustdoc for doc tests.
+    exe: PathBuf,
+    /// The name of the log of the run, with `--no-capture-streaming`.
+    log_name: String,
+    display: String,
+    cmd: ProcessBuilder,
+    doc: bool,
+    timeout: Option<Duration>,
+    retries: u32,
+}
+
+/// How a `TestRun` went.
+struct TestOutcome {
+    result: CargoResult<()>,
+    attempts: u32,
+    duration: Duration,
+    log: Option<TestLog>,
+}
+
+impl TestRun<'_> {
+    fn print_status(&self, config: &Config) -> CargoResult<()> {
+        if self.doc {
+            config
+                .shell()
+                .status("Doc-tests", self.unit.target.name())?;
+        } else {
+            config
+                .shell()
+                .concise(|shell| shell.status("Running", &self.display))?;
+        }
+        config
+            .shell()
+            .verbose(|shell| shell.status("Running", &self.cmd))
+    }
+
+    fn kind(&self) -> &'static str {
+        if self.doc {
+            "doc-test"
+        } else {
+            self.unit.target.kind().description()
+        }
+    }
+}
+
 /// Runs the unit and integration tests of a package.
 fn run_unit_tests(
     config: &Config,
     options: &TestOptions,
     test_args: &[&str],
     compilation: &Compilation<'_>,
+    mut affected: Option<&mut AffectedTests>,
+    mut logs: Option<&mut TestLogs>,
+    coverage: Option<&Coverage>,
 ) -> CargoResult<(Test, Vec<ProcessError>)> {
     let cwd = config.cwd();
     let mut errors = Vec::new();
+    let mut retried = Vec::new();
+    let mut runs = Vec::new();
+    let mut caches = Vec::new();
 
     for UnitOutput {
         unit,
@@ -84,8 +198,6 @@ fn run_unit_tests(
         script_meta,
     } in compilation.tests.iter()
     {
-        let test = unit.target.name().to_string();
-
         let test_path = unit.target.src_path().path().unwrap();
         let exe_display = if let TargetKind::Test = unit.target.kind() {
             format!(
@@ -103,37 +215,141 @@ fn run_unit_tests(
             )
         };
 
+        if let Some(affected) = &affected {
+            if affected.is_unchanged(path, test_args) {
+                config.shell().status(
+                    "Skipping",
+                    format!("{} (unchanged since it last passed)", exe_display),
+                )?;
+                continue;
+            }
+        }
+
         let mut cmd = compilation.target_process(path, unit.kind, &unit.pkg, *script_meta)?;
+        if let Some(miri) = &compilation.miri {
+            miri::interpret(&mut cmd, miri, path)?;
+        }
         cmd.args(test_args);
         if unit.target.harness() && config.shell().verbosity() == Verbosity::Quiet {
             cmd.arg("--quiet");
         }
-        config
-            .shell()
-            .concise(|shell| shell.status("Running", &exe_display))?;
-        config
-            .shell()
-            .verbose(|shell| shell.status("Running", &cmd))?;
+        if let Some(coverage) = coverage {
+            coverage.configure(&mut cmd);
+        }
+
+        let cache = if options.cache_results {
+            compilation.test_results.get(unit)
+        } else {
+            None
+        };
+        if let Some(cache) = cache {
+            if cache.is_fresh(&cmd) {
+                config
+                    .shell()
+                    .status("Cached", format!("{} (passed previously)", exe_display))?;
+                continue;
+            }
+        }
+        runs.push(TestRun {
+            unit,
+            exe: path.clone(),
+            log_name: path.file_stem().unwrap().to_string_lossy().into_owned(),
+            display: exe_display,
+            cmd,
+            doc: false,
+            timeout: unit.profile.test_timeout,
+            retries: unit.profile.test_retries,
+        });
+        caches.push(cache);
+    }
 
-        let result = cmd.exec();
+    // Test binaries whose output goes to logs can run concurrently, unless a
+    // remote runner has to run them one after another.
+    let concurrent = runs
+        .iter()
+        .all(|run| compilation.remote_runner(run.unit.kind).is_none());
+    let outcomes = match logs.as_deref() {
+        Some(logs) if concurrent => run_logged_tests(
+            config,
+            logs,
+            &runs,
+            options.compile_opts.build_config.jobs,
+            options.no_fail_fast,
+        )?,
+        _ => {
+            let mut outcomes = Vec::new();
+            for run in &runs {
+                run.print_status(config)?;
+                let log = match &logs {
+                    Some(logs) => Some(logs.create(&run.log_name)?),
+                    None => None,
+                };
+                let outcome = run_test(config, compilation, run, log)?;
+                let failed = outcome.result.is_err();
+                outcomes.push(Some(outcome));
+                if failed && !options.no_fail_fast {
+                    break;
+                }
+            }
+            outcomes
+        }
+    };
 
-        match result {
+    for ((run, cache), outcome) in runs.iter().zip(caches).zip(outcomes) {
+        // Not started, as an earlier test binary failed.
+        let outcome = match outcome {
+            Some(outcome) => outcome,
+            None => continue,
+        };
+        if let Some(logs) = logs.as_deref_mut() {
+            record_log(config, logs, run, &outcome)?;
+        }
+        if outcome.attempts > 1 {
+            retried.push((&run.display, outcome.attempts, outcome.result.is_ok()));
+        }
+
+        let unit = run.unit;
+        match outcome.result {
             Err(e) => {
-                let e = e.downcast::<ProcessError>()?;
+                if let Some(affected) = affected.as_deref_mut() {
+                    affected.record_fail(&run.exe);
+                }
+                if let Some(cache) = cache {
+                    cache.record_fail();
+                }
+                let mut e = e.downcast::<ProcessError>()?;
+                if let Some(runner) = compilation.wasm_runner(unit.kind) {
+                    e = runner.translate_error(e);
+                }
                 errors.push((
                     unit.target.kind().clone(),
-                    test.clone(),
+                    unit.target.name().to_string(),
                     unit.pkg.name().to_string(),
                     e,
                 ));
-                if !options.no_fail_fast {
-                    break;
+            }
+            Ok(()) => {
+                if let Some(affected) = affected.as_deref_mut() {
+                    affected.record_pass(&run.exe, test_args);
+                }
+                if let Some(cache) = cache {
+                    cache.record_pass(&run.cmd)?;
                 }
             }
-            Ok(()) => {}
         }
     }
 
+    for (exe_display, attempts, passed) in retried {
+        let outcome = if passed {
+            format!("passed on attempt {}", attempts)
+        } else {
+            format!("failed all {} attempts", attempts)
+        };
+        config
+            .shell()
+            .status("Retried", format!("{}, {}", exe_display, outcome))?;
+    }
+
     if errors.len() == 1 {
         let (kind, name, pkg_name, e) = errors.pop().unwrap();
         Ok((
@@ -152,17 +368,279 @@ fn run_unit_tests(
     }
 }
 
+/// Runs a test process until it passes or runs out of retries, routing its
+/// output into `log` if given.
+fn run_test(
+    config: &Config,
+    compilation: &Compilation<'_>,
+    run: &TestRun<'_>,
+    mut log: Option<TestLog>,
+) -> CargoResult<TestOutcome> {
+    let start = Instant::now();
+    let mut attempts = 0;
+    let result = loop {
+        attempts += 1;
+        if let Some(log) = &mut log {
+            log.start_attempt(attempts)?;
+        }
+        let result = match compilation.remote_runner(run.unit.kind) {
+            Some(runner) => runner.exec(config, &run.cmd, run.timeout, log.as_mut()),
+            None => exec_test(config, &run.cmd, run.timeout, log.as_mut()),
+        };
+        if result.is_ok() || attempts > run.retries {
+            break result;
+        }
+        config.shell().status(
+            "Retrying",
+            format!(
+                "{} (attempt {} of {})",
+                run.display,
+                attempts + 1,
+                run.retries + 1
+            ),
+        )?;
+    };
+    Ok(TestOutcome {
+        result,
+        attempts,
+        duration: start.elapsed(),
+        log,
+    })
+}
+
+/// Runs the test processes `runs` with their output routed into logs, up to
+/// `jobs` of them at a time. The outcomes are in the order of `runs`; without
+/// `no_fail_fast`, the runs not started yet when one fails have none.
+fn run_logged_tests(
+    config: &Config,
+    logs: &TestLogs,
+    runs: &[TestRun<'_>],
+    jobs: u32,
+    no_fail_fast: bool,
+) -> CargoResult<Vec<Option<TestOutcome>>> {
+    struct Running {
+        index: usize,
+        child: Child,
+        log: TestLog,
+        attempts: u32,
+        start: Instant,
+        attempt_start: Instant,
+    }
+
+    let mut outcomes: Vec<Option<TestOutcome>> = runs.iter().map(|_| None).collect();
+    let mut running: Vec<Running> = Vec::new();
+    let mut next = 0;
+    let mut failed = false;
+    loop {
+        while running.len() < jobs as usize && next < runs.len() && (no_fail_fast || !failed) {
+            let run = &runs[next];
+            run.print_status(config)?;
+            let mut log = logs.create(&run.log_name)?;
+            let child = spawn_test(&run.cmd, Some(&mut log))?;
+            running.push(Running {
+                index: next,
+                child,
+                log,
+                attempts: 1,
+                start: Instant::now(),
+                attempt_start: Instant::now(),
+            });
+            next += 1;
+        }
+        if running.is_empty() {
+            break;
+        }
+
+        let mut i = 0;
+        while i < running.len() {
+            let r = &mut running[i];
+            let run = &runs[r.index];
+            r.log.pump(config, Duration::ZERO)?;
+            let status = match r.child.try_wait()? {
+                Some(status) => Some(status),
+                None => match run.timeout {
+                    Some(timeout) if r.attempt_start.elapsed() >= timeout => {
+                        drop(r.child.kill());
+                        r.child.wait()?;
+                        None
+                    }
+                    _ => {
+                        i += 1;
+                        continue;
+                    }
+                },
+            };
+            r.log.finish(config)?;
+            let result = test_result(&run.cmd, status, run.timeout);
+            if result.is_err() && r.attempts <= run.retries {
+                r.attempts += 1;
+                config.shell().status(
+                    "Retrying",
+                    format!(
+                        "{} (attempt {} of {})",
+                        run.display,
+                        r.attempts,
+                        run.retries + 1
+                    ),
+                )?;
+                r.log.start_attempt(r.attempts)?;
+                r.child = spawn_test(&run.cmd, Some(&mut r.log))?;
+                r.attempt_start = Instant::now();
+                i += 1;
+                continue;
+            }
+            let r = running.swap_remove(i);
+            failed |= result.is_err();
+            outcomes[r.index] = Some(TestOutcome {
+                result,
+                attempts: r.attempts,
+                duration: r.start.elapsed(),
+                log: Some(r.log),
+            });
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    Ok(outcomes)
+}
+
+/// Adds a test process that ran to the index of the logs, and points to its
+/// log if it failed.
+fn record_log(
+    config: &Config,
+    logs: &mut TestLogs,
+    run: &TestRun<'_>,
+    outcome: &TestOutcome,
+) -> CargoResult<()> {
+    let log = match &outcome.log {
+        Some(log) => log,
+        None => return Ok(()),
+    };
+    logs.record(
+        run.unit,
+        run.kind(),
+        &run.exe,
+        log,
+        outcome.result.is_ok(),
+        outcome.attempts,
+        outcome.duration,
+    );
+    if outcome.result.is_err() {
+        config.shell().note(format!(
+            "the output of {} is in `{}`",
+            run.display,
+            log.path().display()
+        ))?;
+    }
+    Ok(())
+}
+
+/// Starts a test process, routing its output into `log` if given.
+fn spawn_test(cmd: &ProcessBuilder, log: Option<&mut TestLog>) -> CargoResult<Child> {
+    let mut command = cmd.build_command();
+    if log.is_some() {
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+    }
+    let mut child = command.spawn().with_context(|| {
+        ProcessError::new(&format!("could not execute process {}", cmd), None, None)
+    })?;
+    if let Some(log) = log {
+        log.capture(&mut child);
+    }
+    Ok(child)
+}
+
+/// The result of a test process that exited with `status`, or that was
+/// killed after `timeout` without one.
+fn test_result(
+    cmd: &ProcessBuilder,
+    status: Option<ExitStatus>,
+    timeout: Option<Duration>,
+) -> CargoResult<()> {
+    match status {
+        Some(status) if status.success() => Ok(()),
+        Some(status) => Err(ProcessError::new(
+            &format!("process didn't exit successfully: {}", cmd),
+            Some(status),
+            None,
+        )
+        .into()),
+        None => Err(ProcessError::new_raw(
+            &format!("process didn't exit successfully: {}", cmd),
+            None,
+            &format!("timed out after {}", TomlDuration(timeout.unwrap())),
+            None,
+            None,
+        )
+        .into()),
+    }
+}
+
+/// Runs a test binary, killing it if it is still running after `timeout`,
+/// and routing its output into `log` if given.
+fn exec_test(
+    config: &Config,
+    cmd: &ProcessBuilder,
+    timeout: Option<Duration>,
+    mut log: Option<&mut TestLog>,
+) -> CargoResult<()> {
+    if timeout.is_none() && log.is_none() {
+        return cmd.exec();
+    }
+    let mut child = spawn_test(cmd, log.as_deref_mut())?;
+
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break Some(status);
+        }
+        let elapsed = start.elapsed();
+        let wait = match timeout {
+            Some(timeout) if elapsed >= timeout => {
+                drop(child.kill());
+                child.wait()?;
+                break None;
+            }
+            Some(timeout) => (timeout - elapsed).min(Duration::from_millis(10)),
+            None => Duration::from_millis(10),
+        };
+        let pumped = match log.as_deref_mut() {
+            Some(log) => log.pump(config, wait)?,
+            None => false,
+        };
+        if !pumped {
+            thread::sleep(wait);
+        }
+    };
+    if let Some(log) = log {
+        log.finish(config)?;
+    }
+    test_result(cmd, status, timeout)
+}
+
 fn run_doc_tests(
     ws: &Workspace<'_>,
     options: &TestOptions,
     test_args: &[&str],
     compilation: &Compilation<'_>,
+    logs: Option<&mut TestLogs>,
+    coverage: Option<&Coverage>,
 ) -> CargoResult<(Test, Vec<ProcessError>)> {
     let config = ws.config();
     let mut errors = Vec::new();
     let doctest_xcompile = config.cli_unstable().doctest_xcompile;
     let doctest_in_workspace = config.cli_unstable().doctest_in_workspace;
 
+    if compilation.miri.is_some() {
+        if !compilation.to_doc_test.is_empty() {
+            config
+                .shell()
+                .note("doc tests are not run in Miri, skipping them")?;
+        }
+        return Ok((Test::Doc, errors));
+    }
+
+    let mut runs = Vec::new();
+    let mut uninstrumented = false;
     for doctest_info in &compilation.to_doc_test {
         let Doctest {
             args,
@@ -184,7 +662,6 @@ fn run_doc_tests(
             }
         }
 
-        config.shell().status("Doc-tests", unit.target.name())?;
         let mut p = compilation.rustdoc_process(unit, *script_meta)?;
         p.arg("--crate-name").arg(&unit.target.crate_name());
         p.arg("--test");
@@ -232,6 +709,10 @@ fn run_doc_tests(
             p.arg("-L").arg(native_dep);
         }
 
+        if let Some(sanitizer) = unit.profile.sanitizer {
+            p.arg("-Z").arg(format!("sanitizer={}", sanitizer));
+        }
+
         for arg in test_args {
             p.arg("--test-args").arg(arg);
         }
@@ -246,10 +727,52 @@ fn run_doc_tests(
             p.arg("-Zunstable-options");
         }
 
-        config
-            .shell()
-            .verbose(|shell| shell.status("Running", p.to_string()))?;
-        if let Err(e) = p.exec() {
+        if let Some(coverage) = coverage {
+            uninstrumented |= !coverage.configure_doctests(&mut p, &unit.target.crate_name());
+        }
+
+        runs.push(TestRun {
+            unit,
+            exe: PathBuf::from(p.get_program()),
+            log_name: format!("{}-doctests", unit.target.crate_name()),
+            display: format!("doctests ({})", unit.target.name()),
+            cmd: p,
+            doc: true,
+            timeout: None,
+            retries: 0,
+        });
+    }
+
+    if uninstrumented {
+        config.shell().note(
+            "doc tests are not instrumented for coverage without a nightly toolchain, \
+             so they don't count towards the coverage",
+        )?;
+    }
+
+    // The doc tests go to the logs like the test binaries.
+    if let Some(logs) = logs {
+        let outcomes = run_logged_tests(
+            config,
+            logs,
+            &runs,
+            options.compile_opts.build_config.jobs,
+            options.no_fail_fast,
+        )?;
+        for (run, outcome) in runs.iter().zip(outcomes) {
+            if let Some(outcome) = outcome {
+                record_log(config, logs, run, &outcome)?;
+                if let Err(e) = outcome.result {
+                    errors.push(e.downcast::<ProcessError>()?);
+                }
+            }
+        }
+        return Ok((Test::Doc, errors));
+    }
+
+    for run in &runs {
+        run.print_status(config)?;
+        if let Err(e) = run.cmd.exec() {
             let e = e.downcast::<ProcessError>()?;
             errors.push(e);
             if !options.no_fail_fast {
@@ -0,0 +1,525 @@
+//! Implementation of `cargo validate`.
+//!
+//! Each check looks at one aspect of the workspace and reports findings,
+//! which are errors, warnings or ignored depending on the severity of the
+//! check. The severities can be changed in the `[validate]` table of the
+//! configuration, and with `--allow`, `--warn` and `--deny`.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+
+use anyhow::bail;
+use cargo_util::paths;
+
+use crate::core::registry::PackageRegistry;
+use crate::core::resolver::features::{CliFeatures, HasDevUnits};
+use crate::core::{FeatureValue, MaybePackage, Package, PackageId, Workspace, WorkspaceConfig};
+use crate::ops;
+use crate::util::interning::InternedString;
+use crate::util::machine_message::{self, Message};
+use crate::util::toml::{self, schema};
+use crate::util::CargoResult;
+
+/// How the findings of a check are reported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl Severity {
+    pub fn parse(s: &str) -> CargoResult<Severity> {
+        match s {
+            "allow" => Ok(Severity::Allow),
+            "warn" => Ok(Severity::Warn),
+            "deny" => Ok(Severity::Deny),
+            _ => bail!(
+                "invalid severity `{}`, expected one of: allow, warn, deny",
+                s
+            ),
+        }
+    }
+}
+
+/// The checks, with their default severity.
+const CHECKS: &[(&str, Severity)] = &[
+    // Keys Cargo does not know and deprecated syntax in the manifests.
+    ("manifest", Severity::Warn),
+    // Parts of the manifest schema that the version of Cargo the manifest is
+    // checked against does not support.
+    ("schema", Severity::Deny),
+    // Features that enable each other, or list a value twice.
+    ("features", Severity::Warn),
+    // Targets whose source file does not exist.
+    ("targets", Severity::Deny),
+    // A `Cargo.lock` which does not match the manifests.
+    ("lockfile", Severity::Deny),
+    // Path dependencies that are not members of the workspace.
+    ("workspace", Severity::Warn),
+    // What would keep the members from being published.
+    ("publish", Severity::Warn),
+];
+
+pub struct ValidateOptions {
+    /// The severities of checks given on the command line, which override
+    /// the configuration. They are applied in order.
+    pub severities: Vec<(String, Severity)>,
+    /// Print the findings as JSON messages instead of diagnostics.
+    pub json: bool,
+    /// The version of Cargo to check the manifests against, instead of their
+    /// `manifest-version`.
+    pub cargo_version: Option<String>,
+}
+
+/// A problem found by a check.
+struct Finding {
+    check: &'static str,
+    /// The package with the problem, or `None` for the root manifest of a
+    /// virtual workspace.
+    package_id: Option<PackageId>,
+    manifest_path: PathBuf,
+    message: String,
+}
+
+/// Runs the checks on the members of the workspace, and reports their
+/// findings. Fails if a check with the `deny` severity found something.
+pub fn validate(ws: &Workspace<'_>, opts: &ValidateOptions) -> CargoResult<()> {
+    let config = ws.config();
+    let severities = severities(ws, opts)?;
+
+    let mut findings = Vec::new();
+    check_manifests(ws, &mut findings);
+    check_schema(ws, opts, &mut findings)?;
+    for pkg in ws.members() {
+        check_features(pkg, &mut findings);
+        check_targets(pkg, &mut findings);
+        check_path_dependencies(ws, pkg, &mut findings);
+        check_publish(pkg, &mut findings);
+    }
+    check_lockfile(ws, &mut findings)?;
+    findings.sort_by_key(|f| CHECKS.iter().position(|(check, _)| *check == f.check));
+
+    let mut errors = 0;
+    let mut warnings = 0;
+    for finding in &findings {
+        let severity = severities[finding.check];
+        match severity {
+            Severity::Allow => continue,
+            Severity::Warn => warnings += 1,
+            Severity::Deny => errors += 1,
+        }
+        if opts.json {
+            let msg = machine_message::ValidationFinding {
+                check: finding.check,
+                level: if severity == Severity::Deny {
+                    "error"
+                } else {
+                    "warning"
+                },
+                package_id: finding.package_id,
+                manifest_path: &finding.manifest_path,
+                message: &finding.message,
+            }
+            .to_json_string();
+            writeln!(config.shell().out(), "{}", msg)?;
+            continue;
+        }
+        let location = match finding.package_id {
+            Some(id) => format!("{} v{}", id.name(), id.version()),
+            None => finding.manifest_path.display().to_string(),
+        };
+        let msg = format!("{}: {} [{}]", location, finding.message, finding.check);
+        if severity == Severity::Deny {
+            config.shell().error(msg)?;
+        } else {
+            config.shell().warn(msg)?;
+        }
+    }
+
+    if opts.json {
+        let msg = machine_message::ValidationFinished {
+            success: errors == 0,
+            errors,
+            warnings,
+        }
+        .to_json_string();
+        writeln!(config.shell().out(), "{}", msg)?;
+    }
+    if errors > 0 {
+        bail!(
+            "validation failed due to {} previous {}",
+            errors,
+            if errors == 1 { "error" } else { "errors" }
+        );
+    }
+    let members = ws.members().count();
+    config.shell().status(
+        "Validated",
+        format!(
+            "{} {}, {} {}",
+            members,
+            if members == 1 { "package" } else { "packages" },
+            warnings,
+            if warnings == 1 { "warning" } else { "warnings" }
+        ),
+    )?;
+    Ok(())
+}
+
+/// The severity of each check, from the defaults, the `[validate]` table of
+/// the configuration, and the command line.
+fn severities(
+    ws: &Workspace<'_>,
+    opts: &ValidateOptions,
+) -> CargoResult<BTreeMap<&'static str, Severity>> {
+    let mut severities: BTreeMap<_, _> = CHECKS.iter().cloned().collect();
+    let configured = ws
+        .config()
+        .get::<Option<BTreeMap<String, String>>>("validate")?
+        .unwrap_or_default();
+    let configured = configured
+        .iter()
+        .map(|(check, severity)| Ok((check.clone(), Severity::parse(severity)?)))
+        .collect::<CargoResult<Vec<_>>>()?;
+    for (check, severity) in configured.iter().chain(&opts.severities) {
+        match CHECKS.iter().find(|(c, _)| c == check) {
+            Some((check, _)) => severities.insert(*check, *severity),
+            None => bail!(
+                "unknown check `{}`, expected one of: {}",
+                check,
+                CHECKS
+                    .iter()
+                    .map(|(c, _)| *c)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        };
+    }
+    Ok(severities)
+}
+
+fn check_manifests(ws: &Workspace<'_>, findings: &mut Vec<Finding>) {
+    if let MaybePackage::Virtual(vm) = ws.root_maybe() {
+        for warning in vm.warnings().warnings() {
+            findings.push(Finding {
+                check: "manifest",
+                package_id: None,
+                manifest_path: ws.root_manifest().to_path_buf(),
+                message: warning.message.clone(),
+            });
+        }
+    }
+    for pkg in ws.members() {
+        for warning in pkg.manifest().warnings().warnings() {
+            findings.push(finding(pkg, "manifest", warning.message.clone()));
+        }
+    }
+}
+
+fn check_schema(
+    ws: &Workspace<'_>,
+    opts: &ValidateOptions,
+    findings: &mut Vec<Finding>,
+) -> CargoResult<()> {
+    let cargo_version = match &opts.cargo_version {
+        Some(version) => Some(schema::parse_version(version, "`--cargo-version`")?),
+        None => None,
+    };
+    let mut manifests: Vec<_> = ws
+        .members()
+        .map(|pkg| (Some(pkg.package_id()), pkg.manifest_path()))
+        .collect();
+    if let MaybePackage::Virtual(_) = ws.root_maybe() {
+        manifests.push((None, ws.root_manifest()));
+    }
+    for (package_id, manifest_path) in manifests {
+        let contents = paths::read(manifest_path)?;
+        let toml = toml::parse(&contents, manifest_path, ws.config())?;
+        let manifest_version = schema::check_manifest_version(&toml)?;
+        let mut push = |message: String| {
+            findings.push(Finding {
+                check: "schema",
+                package_id,
+                manifest_path: manifest_path.to_path_buf(),
+                message,
+            })
+        };
+        let (version, against) = match (&cargo_version, manifest_version) {
+            (Some(version), manifest_version) => {
+                if let Some(manifest_version) = manifest_version.filter(|v| v > version) {
+                    push(format!(
+                        "the `manifest-version` {} is newer than Cargo {}",
+                        schema::display(&manifest_version),
+                        schema::display(version)
+                    ));
+                }
+                let against = format!(
+                    "the manifest is checked against Cargo {}",
+                    schema::display(version)
+                );
+                (version.clone(), against)
+            }
+            (None, Some(version)) => {
+                let against = format!("the `manifest-version` is {}", schema::display(&version));
+                (version, against)
+            }
+            (None, None) => continue,
+        };
+        for requirement in schema::requirements(&toml) {
+            if requirement.version > version {
+                push(format!(
+                    "{} needs Cargo {} or newer, but {}",
+                    requirement.what,
+                    schema::display(&requirement.version),
+                    against
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check_features(pkg: &Package, findings: &mut Vec<Finding>) {
+    let features = pkg.summary().features();
+    for (feature, values) in features {
+        let mut seen = HashSet::new();
+        for value in values {
+            if !seen.insert(value) {
+                findings.push(finding(
+                    pkg,
+                    "features",
+                    format!("feature `{}` lists `{}` more than once", feature, value),
+                ));
+            }
+        }
+    }
+
+    // Cycles of a feature to itself are rejected when the package is
+    // resolved, but longer ones are allowed.
+    let enabled = |feature: InternedString| -> BTreeSet<InternedString> {
+        let mut enabled = BTreeSet::new();
+        let mut stack = vec![feature];
+        while let Some(feature) = stack.pop() {
+            for value in features.get(&feature).into_iter().flatten() {
+                if let FeatureValue::Feature(f) = value {
+                    if enabled.insert(*f) {
+                        stack.push(*f);
+                    }
+                }
+            }
+        }
+        enabled
+    };
+    let enabled: BTreeMap<_, _> = features.keys().map(|f| (*f, enabled(*f))).collect();
+    let mut reported = BTreeSet::new();
+    for (feature, reachable) in &enabled {
+        if !reachable.contains(feature) || reported.contains(feature) {
+            continue;
+        }
+        let cycle: Vec<_> = reachable
+            .iter()
+            .filter(|f| enabled[*f].contains(feature))
+            .cloned()
+            .collect();
+        reported.extend(cycle.iter().cloned());
+        findings.push(finding(
+            pkg,
+            "features",
+            format!(
+                "features {} enable each other in a cycle",
+                cycle
+                    .iter()
+                    .map(|f| format!("`{}`", f))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        ));
+    }
+}
+
+fn check_targets(pkg: &Package, findings: &mut Vec<Finding>) {
+    for target in pkg.targets() {
+        let path = match target.src_path().path() {
+            Some(path) => path,
+            None => continue,
+        };
+        if !path.exists() {
+            findings.push(finding(
+                pkg,
+                "targets",
+                format!(
+                    "the source file `{}` of the {} target does not exist",
+                    relative(pkg, path),
+                    target.description_named()
+                ),
+            ));
+        }
+    }
+}
+
+fn check_path_dependencies(ws: &Workspace<'_>, pkg: &Package, findings: &mut Vec<Finding>) {
+    // Without a `[workspace]`, there is no workspace to be a member of.
+    match ws.root_maybe() {
+        MaybePackage::Package(root) => match root.manifest().workspace_config() {
+            WorkspaceConfig::Root(_) => {}
+            WorkspaceConfig::Member { .. } => return,
+        },
+        MaybePackage::Virtual(_) => {}
+    }
+    let mut seen = HashSet::new();
+    for dep in pkg.dependencies() {
+        if !dep.source_id().is_path() || !seen.insert(dep.source_id()) {
+            continue;
+        }
+        let path = match dep.source_id().url().to_file_path() {
+            Ok(path) => path,
+            Err(()) => continue,
+        };
+        if !ws.members().any(|m| m.root() == path) {
+            findings.push(finding(
+                pkg,
+                "workspace",
+                format!(
+                    "the path dependency `{}` at `{}` is not a member of the workspace, \
+                     and does not share its profiles, `[patch]` and `Cargo.lock` \
+                     when it is built on its own",
+                    dep.package_name(),
+                    path.display()
+                ),
+            ));
+        }
+    }
+}
+
+fn check_publish(pkg: &Package, findings: &mut Vec<Finding>) {
+    if pkg.publish() == &Some(Vec::new()) {
+        return;
+    }
+    let md = pkg.manifest().metadata();
+    let mut missing: Vec<&str> = Vec::new();
+    missing.extend(lacking(&[("description", &md.description)]));
+    missing.extend(lacking(&[
+        ("license", &md.license),
+        ("license-file", &md.license_file),
+    ]));
+    missing.extend(lacking(&[
+        ("documentation", &md.documentation),
+        ("homepage", &md.homepage),
+        ("repository", &md.repository),
+    ]));
+    if let Some((last, rest)) = missing.split_last() {
+        let things = if rest.is_empty() {
+            last.to_string()
+        } else {
+            format!("{} or {}", rest.join(", "), last)
+        };
+        findings.push(finding(
+            pkg,
+            "publish",
+            format!("manifest has no {}", things),
+        ));
+    }
+
+    for (field, file) in [("readme", &md.readme), ("license-file", &md.license_file)] {
+        if let Some(file) = file {
+            if !pkg.root().join(file).exists() {
+                findings.push(finding(
+                    pkg,
+                    "publish",
+                    format!("the `{}` file `{}` does not exist", field, file),
+                ));
+            }
+        }
+    }
+
+    for dep in pkg.dependencies() {
+        let which = if dep.source_id().is_path() {
+            "path"
+        } else if dep.source_id().is_git() {
+            "git"
+        } else {
+            if dep.source_id().is_default_registry() && dep.version_req().to_string() == "*" {
+                findings.push(finding(
+                    pkg,
+                    "publish",
+                    format!(
+                        "dependency `{}` has the wildcard version requirement `*`, \
+                         which crates.io does not accept",
+                        dep.package_name()
+                    ),
+                ));
+            }
+            continue;
+        };
+        if !dep.specified_req() && dep.is_transitive() {
+            findings.push(finding(
+                pkg,
+                "publish",
+                format!(
+                    "the {} dependency `{}` does not specify a version, \
+                     which is required to publish",
+                    which,
+                    dep.package_name()
+                ),
+            ));
+        }
+    }
+}
+
+fn check_lockfile(ws: &Workspace<'_>, findings: &mut Vec<Finding>) -> CargoResult<()> {
+    let previous = match ops::load_pkg_lockfile(ws)? {
+        Some(previous) => previous,
+        None => return Ok(()),
+    };
+    let mut registry = PackageRegistry::new(ws.config())?;
+    let mut resolve = ops::resolve_with_previous(
+        &mut registry,
+        ws,
+        &CliFeatures::new_all(true),
+        HasDevUnits::Yes,
+        Some(&previous),
+        None,
+        &[],
+        true,
+    )?;
+    if !ops::is_lockfile_up_to_date(ws, &mut resolve)? {
+        findings.push(Finding {
+            check: "lockfile",
+            package_id: None,
+            manifest_path: ws.root_manifest().to_path_buf(),
+            message: "`Cargo.lock` does not match the manifests, and needs to be updated"
+                .to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// The names of `fields` if none of them is set.
+fn lacking<'a>(fields: &[(&'a str, &Option<String>)]) -> Vec<&'a str> {
+    if fields
+        .iter()
+        .all(|(_, v)| v.as_ref().map_or(true, |s| s.is_empty()))
+    {
+        fields.iter().map(|(name, _)| *name).collect()
+    } else {
+        Vec::new()
+    }
+}
+
+fn finding(pkg: &Package, check: &'static str, message: String) -> Finding {
+    Finding {
+        check,
+        package_id: Some(pkg.package_id()),
+        manifest_path: pkg.manifest_path().to_path_buf(),
+        message,
+    }
+}
+
+/// The path `path` relative to the root of `pkg`, if it is inside of it.
+fn relative(pkg: &Package, path: &Path) -> String {
+    path.strip_prefix(pkg.root())
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
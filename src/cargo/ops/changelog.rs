@@ -0,0 +1,165 @@
+//! Changelogs, and the changelog fragments of `[workspace.changelog]`.
+//!
+//! Each change to a package can be described in a fragment file in its
+//! `changes` directory, named `<name>.<kind>.md`, like `changes/123.fixed.md`.
+//! The kinds are the sections of a release in the "Keep a Changelog" format.
+//! When a package is packaged, the fragments are assembled into a section of
+//! its `CHANGELOG.md` for the version of the package, and `cargo release`
+//! assembles them into the `CHANGELOG.md` in the working tree.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Context as _};
+use cargo_util::paths;
+use semver::Version;
+
+use crate::util::{CargoResult, Config};
+
+/// The kinds of changes, in the order of their sections, with the heading of
+/// their section.
+const KINDS: &[(&str, &str)] = &[
+    ("added", "Added"),
+    ("changed", "Changed"),
+    ("deprecated", "Deprecated"),
+    ("removed", "Removed"),
+    ("fixed", "Fixed"),
+    ("security", "Security"),
+];
+
+/// A changelog fragment.
+pub struct Fragment {
+    pub path: PathBuf,
+    /// The index of the kind of the change in `KINDS`.
+    kind: usize,
+    text: String,
+}
+
+/// The fragments in the directory `dir`, in the order of their sections. The
+/// files without a kind, like `README.md`, are not fragments.
+pub fn fragments(dir: &Path) -> CargoResult<Vec<Fragment>> {
+    let mut fragments = Vec::new();
+    if !dir.is_dir() {
+        return Ok(fragments);
+    }
+    let entries =
+        std::fs::read_dir(dir).with_context(|| format!("failed to read `{}`", dir.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        let name = match path.file_name().and_then(|n| n.to_str()) {
+            Some(name) => name,
+            None => continue,
+        };
+        let kind = match name.strip_suffix(".md").and_then(|n| n.rsplit_once('.')) {
+            Some((_, kind)) => kind,
+            None => continue,
+        };
+        let kind = match KINDS.iter().position(|(k, _)| *k == kind) {
+            Some(kind) => kind,
+            None => bail!(
+                "unknown kind `{}` of the changelog fragment `{}`, expected one of: {}",
+                kind,
+                path.display(),
+                KINDS.iter().map(|(k, _)| *k).collect::<Vec<_>>().join(", ")
+            ),
+        };
+        let text = paths::read(&path)?.trim().to_string();
+        fragments.push(Fragment { path, kind, text });
+    }
+    fragments.sort_by(|a, b| (a.kind, &a.path).cmp(&(b.kind, &b.path)));
+    Ok(fragments)
+}
+
+/// Whether the changelog `changelog` has a section for `version`, like
+/// `## [1.2.0] - 2021-11-02` or `## 1.2.0`.
+pub fn has_entry(changelog: &str, version: &Version) -> bool {
+    let version = version.to_string();
+    changelog.lines().any(|line| {
+        let heading = match line.strip_prefix("## ") {
+            Some(heading) => heading.trim_start_matches(|c| c == '[' || c == 'v'),
+            None => return false,
+        };
+        match heading.strip_prefix(&version) {
+            Some(rest) => !rest.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '.'),
+            None => false,
+        }
+    })
+}
+
+/// The section of the release of `version` on `date`, with the changes of
+/// `fragments`.
+pub fn fragments_section(version: &Version, date: &str, fragments: &[Fragment]) -> String {
+    let mut section = format!("## [{}] - {}\n", version, date);
+    let mut kind = None;
+    for fragment in fragments {
+        if kind != Some(fragment.kind) {
+            kind = Some(fragment.kind);
+            section.push_str(&format!("\n### {}\n\n", KINDS[fragment.kind].1));
+        }
+        section.push_str(&entry(&fragment.text));
+    }
+    section
+}
+
+/// The section of the release of `version` on `date`, listing `changes`.
+pub fn section(version: &Version, date: &str, changes: &[String]) -> String {
+    let mut section = format!("## [{}] - {}\n", version, date);
+    if !changes.is_empty() {
+        section.push('\n');
+    }
+    for change in changes {
+        section.push_str(&entry(change));
+    }
+    section
+}
+
+/// The list item of the change `text`, which may span several lines.
+fn entry(text: &str) -> String {
+    let text = text.trim();
+    let mut entry = String::new();
+    for (i, line) in text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+    {
+        match i {
+            0 if line.starts_with("- ") || line.starts_with("* ") => entry.push_str(line),
+            0 => entry.push_str(&format!("- {}", line)),
+            _ => entry.push_str(&format!("  {}", line)),
+        }
+        entry.push('\n');
+    }
+    entry
+}
+
+/// The changelog `changelog` with `section` above the sections of the
+/// previous releases. A new changelog is started without `changelog`.
+pub fn add_section(changelog: Option<&str>, section: &str) -> String {
+    let changelog = changelog.unwrap_or("# Changelog\n");
+    let previous = if changelog.starts_with("## ") {
+        Some(0)
+    } else {
+        changelog.find("\n## ").map(|i| i + 1)
+    };
+    match previous {
+        Some(i) => format!("{}{}\n{}", &changelog[..i], section, &changelog[i..]),
+        None => format!("{}\n\n{}", changelog.trim_end(), section),
+    }
+}
+
+/// The date of a release, which is today unless `SOURCE_DATE_EPOCH` is set.
+pub fn release_date(config: &Config) -> CargoResult<String> {
+    let time = match config.env().get("SOURCE_DATE_EPOCH") {
+        Some(epoch) => {
+            let secs = epoch.parse::<u64>().with_context(|| {
+                format!(
+                    "`SOURCE_DATE_EPOCH` is not a number of seconds: `{}`",
+                    epoch
+                )
+            })?;
+            UNIX_EPOCH + Duration::from_secs(secs)
+        }
+        None => SystemTime::now(),
+    };
+    Ok(humantime::format_rfc3339_seconds(time).to_string()[..10].to_string())
+}
@@ -0,0 +1,233 @@
+//! Support for `cargo doc --report-coverage`, which reports how much of the
+//! public API of the workspace members is documented.
+//!
+//! The libraries of the selected members are compiled as for their doc
+//! tests, and then rustdoc is run on each of them with `--show-coverage`,
+//! which counts the documented items and the items with examples of every
+//! source file. Cargo adds them up per package and for the workspace, and
+//! checks them against the thresholds of the `[lints]` table of each package.
+//! `--show-coverage` is unstable, so this requires a nightly rustdoc.
+
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+
+use crate::core::compiler::{CompileKind, Doctest};
+use crate::core::shell::Verbosity;
+use crate::core::{Package, PackageId, Workspace};
+use crate::drop_println;
+use crate::ops;
+use crate::util::errors::CargoResult;
+use crate::util::machine_message::{self, Message};
+use crate::util::Config;
+
+/// The counts rustdoc reports for a source file, or their sum.
+#[derive(Deserialize, Serialize, Clone, Copy, Default)]
+pub struct DocCoverage {
+    /// The public items.
+    pub total: u64,
+    /// The public items with documentation.
+    pub with_docs: u64,
+    /// The items which can have examples.
+    pub total_examples: u64,
+    /// The items with examples.
+    pub with_examples: u64,
+}
+
+impl DocCoverage {
+    fn add(&mut self, other: &DocCoverage) {
+        self.total += other.total;
+        self.with_docs += other.with_docs;
+        self.total_examples += other.total_examples;
+        self.with_examples += other.with_examples;
+    }
+
+    /// The percentage of documented items.
+    pub fn docs_percent(&self) -> f64 {
+        percent(self.with_docs, self.total)
+    }
+
+    /// The percentage of items with examples.
+    pub fn examples_percent(&self) -> f64 {
+        percent(self.with_examples, self.total_examples)
+    }
+}
+
+/// An empty crate is completely documented.
+fn percent(part: u64, total: u64) -> f64 {
+    if total == 0 {
+        100.0
+    } else {
+        part as f64 * 100.0 / total as f64
+    }
+}
+
+/// Reports the documentation coverage of the libraries selected by
+/// `options`, which must be in the `Doctest` mode, and fails if a package is
+/// below the coverage required by its `[lints]`.
+pub fn report_doc_coverage(ws: &Workspace<'_>, options: &ops::CompileOptions) -> CargoResult<()> {
+    let config = ws.config();
+    let compilation = ops::compile(ws, options)?;
+
+    let mut packages: BTreeMap<PackageId, (&Package, BTreeMap<PathBuf, DocCoverage>)> =
+        BTreeMap::new();
+    for doctest in &compilation.to_doc_test {
+        let Doctest {
+            unit,
+            args,
+            script_meta,
+            ..
+        } = doctest;
+        if !ws.is_member(&unit.pkg) {
+            continue;
+        }
+        let mut p = compilation.rustdoc_process(unit, *script_meta)?;
+        p.arg("--crate-name").arg(&unit.target.crate_name());
+        p.arg(unit.target.src_path().path().unwrap());
+        for rust_dep in [
+            &compilation.deps_output[&unit.kind],
+            &compilation.deps_output[&CompileKind::Host],
+        ] {
+            let mut arg = OsString::from("dependency=");
+            arg.push(rust_dep);
+            p.arg("-L").arg(arg);
+        }
+        for native_dep in compilation.native_dirs.iter() {
+            p.arg("-L").arg(native_dep);
+        }
+        p.args(args);
+        if options.rustdoc_document_private_items {
+            p.arg("--document-private-items");
+        }
+        p.arg("-Zunstable-options")
+            .arg("--show-coverage")
+            .arg("--output-format")
+            .arg("json");
+        p.cwd(unit.pkg.root());
+
+        config
+            .shell()
+            .verbose(|shell| shell.status("Running", p.to_string()))?;
+        let output = p.exec_with_output()?;
+        let files: BTreeMap<PathBuf, DocCoverage> = serde_json::from_slice(&output.stdout)
+            .with_context(|| {
+                format!(
+                    "failed to parse the documentation coverage of `{}` reported by rustdoc",
+                    unit.target.name()
+                )
+            })?;
+        let root = unit.pkg.root();
+        let entry = packages
+            .entry(unit.pkg.package_id())
+            .or_insert_with(|| (&unit.pkg, BTreeMap::new()));
+        for (file, coverage) in files {
+            let file = file.strip_prefix(root).unwrap_or(&file).to_path_buf();
+            entry.1.entry(file).or_default().add(&coverage);
+        }
+    }
+
+    let mut total = DocCoverage::default();
+    let mut rows = Vec::new();
+    let mut failures = Vec::new();
+    for (pkg_id, (pkg, files)) in &packages {
+        let mut coverage = DocCoverage::default();
+        for file in files.values() {
+            coverage.add(file);
+        }
+        total.add(&coverage);
+        failures.extend(check_thresholds(pkg, &coverage));
+
+        if options.build_config.emit_json() {
+            let msg = machine_message::DocCoverage {
+                package_id: *pkg_id,
+                manifest_path: pkg.manifest_path(),
+                coverage,
+                files,
+            }
+            .to_json_string();
+            drop_println!(config, "{}", msg);
+        } else {
+            rows.push((format!("{} v{}", pkg_id.name(), pkg_id.version()), coverage));
+        }
+    }
+
+    if !options.build_config.emit_json() && config.shell().verbosity() != Verbosity::Quiet {
+        if rows.len() > 1 {
+            rows.push(("total".to_string(), total));
+        }
+        print_table(config, &rows);
+    }
+
+    if !failures.is_empty() {
+        anyhow::bail!(
+            "the documentation coverage is below the thresholds of `[lints]`:\n{}",
+            failures.join("\n")
+        );
+    }
+    Ok(())
+}
+
+/// Checks `coverage` against the `[lints]` of `pkg`, returning the
+/// thresholds that weren't met.
+fn check_thresholds(pkg: &Package, coverage: &DocCoverage) -> Vec<String> {
+    let lints = match pkg.manifest().original().lints() {
+        Some(lints) => lints,
+        None => return Vec::new(),
+    };
+    let mut failures = Vec::new();
+    for (key, required, actual) in [
+        ("doc-coverage", lints.doc_coverage, coverage.docs_percent()),
+        (
+            "doc-example-coverage",
+            lints.doc_example_coverage,
+            coverage.examples_percent(),
+        ),
+    ] {
+        if let Some(required) = required {
+            if actual < required {
+                failures.push(format!(
+                    "  `{}`: {} is {:.1}%, below the required {}%",
+                    pkg.name(),
+                    key,
+                    actual,
+                    required
+                ));
+            }
+        }
+    }
+    failures
+}
+
+fn print_table(config: &Config, rows: &[(String, DocCoverage)]) {
+    let width = rows
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0)
+        .max("package".len());
+    drop_println!(
+        config,
+        "{:<width$}  {:>18}  {:>18}",
+        "package",
+        "documented",
+        "examples",
+        width = width
+    );
+    for (name, coverage) in rows {
+        drop_println!(
+            config,
+            "{:<width$}  {:>18}  {:>18}",
+            name,
+            ratio(coverage.with_docs, coverage.total),
+            ratio(coverage.with_examples, coverage.total_examples),
+            width = width
+        );
+    }
+}
+
+fn ratio(part: u64, total: u64) -> String {
+    format!("{}/{} ({:.1}%)", part, total, percent(part, total))
+}
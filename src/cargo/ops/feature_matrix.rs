@@ -0,0 +1,288 @@
+//! Support for `cargo test --feature-matrix`, which runs the tests of each
+//! selected package once for every meaningful combination of its features.
+//!
+//! Combinations are taken from the subsets of the package's features with at
+//! most `depth` features, 2 by default, tuned by the `[feature-matrix]`
+//! manifest table. The subsets are generated lazily, so a package with many
+//! features doesn't build its whole powerset. A combination is skipped
+//! if it enables the same set of features as one that was already tried, or
+//! if it enables more than one feature of an `exclusive` group or two
+//! features declared as conflicting in `[features.metadata]`. All runs
+//! share the same target directory, so artifacts that don't depend on the
+//! varied features are only built once.
+
+use std::collections::{BTreeSet, HashSet};
+
+use crate::core::resolver::features::CliFeatures;
+use crate::core::{FeatureMap, FeatureValue, Package, Workspace};
+use crate::ops::{self, Packages, TestOptions};
+use crate::util::errors::CargoResult;
+use crate::util::interning::InternedString;
+use anyhow::bail;
+use termcolor::Color::{Green, Red};
+
+/// The maximum number of features varied in a single combination, unless
+/// `[feature-matrix]` sets a `depth`.
+const DEFAULT_DEPTH: usize = 2;
+
+/// Runs the tests of every selected package once per feature combination,
+/// and prints a summary of the results at the end.
+///
+/// The feature flags in `options` are ignored; every combination is built
+/// with `--no-default-features` plus the features of the combination.
+pub fn run_feature_matrix(
+    ws: &Workspace<'_>,
+    mut options: TestOptions,
+    test_args: &[&str],
+) -> CargoResult<()> {
+    let config = ws.config();
+    let packages = options.compile_opts.spec.get_packages(ws)?;
+
+    let mut results = Vec::new();
+    'packages: for pkg in packages {
+        for features in feature_combinations(pkg)? {
+            let label = describe(pkg, &features);
+            config.shell().status("Testing", &label)?;
+
+            let features: Vec<String> = features.iter().map(|f| f.to_string()).collect();
+            options.compile_opts.spec = Packages::Packages(vec![pkg.name().to_string()]);
+            options.compile_opts.cli_features =
+                CliFeatures::from_command_line(&features, false, false)?;
+            let passed = match ops::run_tests(ws, &options, test_args) {
+                Ok(None) => true,
+                Ok(Some(err)) => {
+                    config.shell().error(err.hint(ws, &options.compile_opts))?;
+                    false
+                }
+                Err(e) => {
+                    crate::display_error(&e, &mut config.shell());
+                    false
+                }
+            };
+            results.push((label, passed));
+            if !passed && !options.no_fail_fast {
+                break 'packages;
+            }
+        }
+    }
+
+    let failed = results.iter().filter(|(_, passed)| !passed).count();
+    let mut shell = config.shell();
+    shell.status(
+        "Summary",
+        format!(
+            "feature matrix ran {} combination{}, {} failed",
+            results.len(),
+            if results.len() == 1 { "" } else { "s" },
+            failed
+        ),
+    )?;
+    for (label, passed) in &results {
+        if *passed {
+            shell.status_with_color("ok", label, Green)?;
+        } else {
+            shell.status_with_color("FAILED", label, Red)?;
+        }
+    }
+    drop(shell);
+
+    if failed > 0 {
+        bail!(
+            "{} of {} feature combinations failed",
+            failed,
+            results.len()
+        );
+    }
+    Ok(())
+}
+
+/// Computes the feature combinations to test for `pkg`, smallest first.
+///
+/// Every combination implicitly has the default features disabled.
+pub fn feature_combinations(pkg: &Package) -> CargoResult<Vec<Vec<InternedString>>> {
+    let feature_map = pkg.summary().features();
+    let matrix = pkg
+        .manifest()
+        .original()
+        .feature_matrix()
+        .cloned()
+        .unwrap_or_default();
+    let skip = matrix.skip.unwrap_or_default();
+    let always = matrix.always.unwrap_or_default();
+    let mut exclusive = matrix.exclusive.unwrap_or_default();
+
+    for name in skip.iter().chain(&always).chain(exclusive.iter().flatten()) {
+        if !feature_map.contains_key(name) {
+            bail!(
+                "feature `{}` listed in `[feature-matrix]` is not a feature of package `{}`",
+                name,
+                pkg.name()
+            );
+        }
+    }
+
+    // Conflicts declared in `[features.metadata]` are exclusive pairs.
+    if let Some(metadata) = pkg.manifest().original().feature_metadata() {
+        for (name, meta) in metadata {
+            for conflict in meta.conflicts.iter().flatten() {
+                exclusive.push(vec![*name, *conflict]);
+            }
+        }
+    }
+
+    // Features that only enable the optional dependency of the same name
+    // are still reached through the features that use them.
+    let is_implicit = |name: &InternedString, values: &[FeatureValue]| matches!(values, [FeatureValue::Dep { dep_name }] if dep_name == name);
+    let varied: Vec<InternedString> = feature_map
+        .iter()
+        .filter(|(name, values)| {
+            name.as_str() != "default"
+                && !skip.contains(name)
+                && !always.contains(name)
+                && !is_implicit(name, values)
+        })
+        .map(|(name, _)| *name)
+        .collect();
+    let depth = matrix.depth.unwrap_or(DEFAULT_DEPTH).min(varied.len());
+
+    let mut seen = HashSet::new();
+    let mut combinations = Vec::new();
+    for size in 0..=depth {
+        for subset in Subsets::new(&varied, size) {
+            let mut features = always.clone();
+            features.extend(subset);
+            let enabled = enabled_features(feature_map, &features);
+            let conflicts = exclusive.iter().any(|group| {
+                group
+                    .iter()
+                    .filter(|f| enabled.contains(&FeatureValue::Feature(**f)))
+                    .count()
+                    > 1
+            });
+            if !conflicts && seen.insert(enabled) {
+                combinations.push(features);
+            }
+        }
+    }
+    Ok(combinations)
+}
+
+/// An iterator over every subset of `items` with exactly `size` elements, in
+/// order.
+struct Subsets<'a> {
+    items: &'a [InternedString],
+    /// The indices in `items` of the next subset, or `None` once they are all
+    /// returned.
+    indices: Option<Vec<usize>>,
+}
+
+impl<'a> Subsets<'a> {
+    fn new(items: &'a [InternedString], size: usize) -> Subsets<'a> {
+        Subsets {
+            items,
+            indices: if size <= items.len() {
+                Some((0..size).collect())
+            } else {
+                None
+            },
+        }
+    }
+}
+
+impl Iterator for Subsets<'_> {
+    type Item = Vec<InternedString>;
+
+    fn next(&mut self) -> Option<Vec<InternedString>> {
+        let indices = self.indices.as_mut()?;
+        let subset = indices.iter().map(|&i| self.items[i]).collect();
+        // Advances the last index that can still move right, and resets the
+        // ones after it.
+        let n = self.items.len();
+        let size = indices.len();
+        match (0..size).rev().find(|&i| indices[i] < n - size + i) {
+            Some(i) => {
+                indices[i] += 1;
+                for j in i + 1..size {
+                    indices[j] = indices[j - 1] + 1;
+                }
+            }
+            None => self.indices = None,
+        }
+        Some(subset)
+    }
+}
+
+/// Returns everything transitively enabled by `features` within the package.
+fn enabled_features(
+    feature_map: &FeatureMap,
+    features: &[InternedString],
+) -> BTreeSet<FeatureValue> {
+    let mut enabled = BTreeSet::new();
+    let mut stack: Vec<_> = features.iter().map(|f| FeatureValue::Feature(*f)).collect();
+    while let Some(value) = stack.pop() {
+        if !enabled.insert(value.clone()) {
+            continue;
+        }
+        match value {
+            FeatureValue::Feature(name) => {
+                if let Some(values) = feature_map.get(&name) {
+                    stack.extend(values.iter().cloned());
+                }
+            }
+            FeatureValue::DepFeature {
+                dep_name,
+                weak: false,
+                ..
+            } if feature_map.contains_key(&dep_name) => {
+                stack.push(FeatureValue::Feature(dep_name));
+            }
+            _ => {}
+        }
+    }
+    enabled
+}
+
+fn describe(pkg: &Package, features: &[InternedString]) -> String {
+    if features.is_empty() {
+        format!("{} with no features", pkg.name())
+    } else {
+        let features: Vec<_> = features.iter().map(|f| format!("`{}`", f)).collect();
+        format!("{} with features {}", pkg.name(), features.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Subsets;
+    use crate::util::interning::InternedString;
+
+    #[test]
+    fn subsets_in_order() {
+        let items: Vec<_> = ["a", "b", "c"]
+            .iter()
+            .map(|s| InternedString::new(s))
+            .collect();
+        let names = |size| -> Vec<String> {
+            Subsets::new(&items, size)
+                .map(|s| s.iter().map(|f| f.as_str()).collect())
+                .collect()
+        };
+        assert_eq!(names(0), [""]);
+        assert_eq!(names(1), ["a", "b", "c"]);
+        assert_eq!(names(2), ["ab", "ac", "bc"]);
+        assert_eq!(names(3), ["abc"]);
+        assert!(names(4).is_empty());
+    }
+
+    #[test]
+    fn subsets_are_lazy() {
+        let items: Vec<_> = (0..100)
+            .map(|i| InternedString::new(&i.to_string()))
+            .collect();
+        let mut subsets = Subsets::new(&items, 50);
+        assert_eq!(subsets.next().unwrap(), &items[..50]);
+        let second = subsets.next().unwrap();
+        assert_eq!(second[..49], items[..49]);
+        assert_eq!(second[49], items[50]);
+    }
+}
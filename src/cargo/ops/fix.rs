@@ -38,9 +38,10 @@
 //! - If there are any warnings or errors, rustc will be run one last time to
 //!   show them to the user.
 
-use std::collections::{BTreeSet, HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::env;
 use std::ffi::OsString;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 use std::process::{self, Command, ExitStatus};
 use std::str;
@@ -51,15 +52,21 @@ use log::{debug, trace, warn};
 use rustfix::diagnostics::Diagnostic;
 use rustfix::{self, CodeFix};
 use semver::Version;
+use serde::Deserialize;
 
 use crate::core::compiler::RustcTargetData;
 use crate::core::resolver::features::{DiffMap, FeatureOpts, FeatureResolver};
 use crate::core::resolver::{HasDevUnits, Resolve, ResolveBehavior};
-use crate::core::{Edition, MaybePackage, PackageId, Workspace};
+use crate::core::{Edition, MaybePackage, Package, PackageId, Workspace};
+use crate::ops::fix_backup::{self, Journal};
 use crate::ops::resolve::WorkspaceResolve;
-use crate::ops::{self, CompileOptions};
-use crate::util::diagnostic_server::{Message, RustfixDiagnosticServer};
+use crate::ops::{self, CompileOptions, Packages};
+use crate::util::diagnostic_server::{
+    FixSummary, MacroWarning, Message, PackageFixes, RustfixDiagnosticServer, SuggestedFix,
+};
 use crate::util::errors::CargoResult;
+use crate::util::machine_message::{self, Message as _};
+use crate::util::vcs_state::{self, DirtyPolicy, VcsCommand};
 use crate::util::Config;
 use crate::util::{existing_vcs_repo, LockServer, LockServerClient};
 use crate::{drop_eprint, drop_eprintln};
@@ -68,6 +75,12 @@ const FIX_ENV: &str = "__CARGO_FIX_PLZ";
 const BROKEN_CODE_ENV: &str = "__CARGO_FIX_BROKEN_CODE";
 const EDITION_ENV: &str = "__CARGO_FIX_EDITION";
 const IDIOMS_ENV: &str = "__CARGO_FIX_IDIOMS";
+const DRY_RUN_ENV: &str = "__CARGO_FIX_DRY_RUN";
+const SUMMARY_ENV: &str = "__CARGO_FIX_SUMMARY";
+const DIFF_ENV: &str = "__CARGO_FIX_DIFF";
+const LINTS_ENV: &str = "__CARGO_FIX_LINTS";
+const SKIP_ENV: &str = "__CARGO_FIX_SKIP";
+const BACKUP_ENV: &str = "__CARGO_FIX_BACKUP";
 
 pub struct FixOptions {
     pub edition: bool,
@@ -77,10 +90,48 @@ pub struct FixOptions {
     pub allow_no_vcs: bool,
     pub allow_staged: bool,
     pub broken_code: bool,
+    /// Only preview the fixes, applying them to the code in memory.
+    pub dry_run: bool,
+    /// Print the fixes previewed with `dry_run` as JSON messages.
+    pub json: bool,
+    /// Print the fixes previewed with `dry_run` as a unified diff.
+    pub diff: bool,
+    /// Only apply the suggestions of these lints, which are warned about even
+    /// if they are allowed.
+    pub lints: Vec<String>,
+    /// Save the git working tree in the stash before fixing.
+    pub vcs_snapshot: bool,
 }
 
 pub fn fix(ws: &Workspace<'_>, opts: &mut FixOptions) -> CargoResult<()> {
-    check_version_control(ws.config(), opts)?;
+    let summary = ws.config().cli_unstable().edition_migration;
+    let fix_summary = run_fix(ws, opts, summary)?;
+    if let Some(fix_summary) = fix_summary {
+        let packages = packages_in_order(ws, &fix_summary)?;
+        if summary {
+            print_summary(ws, &packages, opts)?;
+        }
+        if opts.diff {
+            for (_, pkg) in &packages {
+                for (_, patch) in pkg.patches.values() {
+                    write!(ws.config().shell().out(), "{}", patch)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Runs the fixes of `opts`, and returns what was done to each package if
+/// `summary` or `opts.diff` is set.
+fn run_fix(
+    ws: &Workspace<'_>,
+    opts: &mut FixOptions,
+    summary: bool,
+) -> CargoResult<Option<FixSummary>> {
+    if !opts.dry_run {
+        check_version_control(ws.config(), opts)?;
+    }
     if opts.edition {
         check_resolver_change(ws, opts)?;
     }
@@ -103,6 +154,38 @@ pub fn fix(ws: &Workspace<'_>, opts: &mut FixOptions) -> CargoResult<()> {
     if opts.idioms {
         wrapper.env(IDIOMS_ENV, "1");
     }
+    if opts.dry_run {
+        wrapper.env(DRY_RUN_ENV, "1");
+    }
+    if opts.diff {
+        wrapper.env(DIFF_ENV, "1");
+    }
+    if !opts.lints.is_empty() {
+        wrapper.env(LINTS_ENV, opts.lints.join(","));
+    }
+    if summary {
+        wrapper.env(SUMMARY_ENV, "1");
+    }
+    if summary || opts.diff {
+        *opts.compile_opts.build_config.fix_summary.borrow_mut() = Some(FixSummary::default());
+    }
+
+    let mut skipped = Vec::new();
+    for pkg in opts.compile_opts.spec.get_packages(ws)? {
+        if !pkg.manifest().autofix() {
+            ws.config().shell().status(
+                "Skipping",
+                format!(
+                    "fixes of `{}`, which sets `package.autofix = false`",
+                    pkg.name()
+                ),
+            )?;
+            skipped.push(pkg.name().to_string());
+        }
+    }
+    if !skipped.is_empty() {
+        wrapper.env(SKIP_ENV, skipped.join(","));
+    }
 
     *opts
         .compile_opts
@@ -120,6 +203,14 @@ pub fn fix(ws: &Workspace<'_>, opts: &mut FixOptions) -> CargoResult<()> {
         server.configure(&mut wrapper);
     }
 
+    let journal = if ws.config().cli_unstable().fix_backups && !opts.dry_run {
+        let journal = Journal::start(ws, opts.vcs_snapshot)?;
+        wrapper.env(BACKUP_ENV, journal.dir());
+        Some(journal)
+    } else {
+        None
+    };
+
     let rustc = ws.config().load_global_rustc(Some(ws))?;
     wrapper.arg(&rustc.path);
 
@@ -127,7 +218,278 @@ pub fn fix(ws: &Workspace<'_>, opts: &mut FixOptions) -> CargoResult<()> {
     // repeating build until there are no more changes to be applied
     opts.compile_opts.build_config.primary_unit_rustc = Some(wrapper);
 
-    ops::compile(ws, &opts.compile_opts)?;
+    let result = ops::compile(ws, &opts.compile_opts);
+    // The files are backed up even if the build failed after fixing some.
+    if let Some(journal) = journal {
+        journal.finish(ws.config())?;
+    }
+    result?;
+
+    Ok(opts
+        .compile_opts
+        .build_config
+        .fix_summary
+        .borrow_mut()
+        .take())
+}
+
+/// The results of the packages of the workspace in `summary`, in dependency
+/// order.
+fn packages_in_order<'a>(
+    ws: &Workspace<'_>,
+    summary: &'a FixSummary,
+) -> CargoResult<Vec<(PackageId, &'a PackageFixes)>> {
+    let (_, resolve) = ops::resolve_ws(ws)?;
+    let members: HashSet<PackageId> = ws.members().map(|pkg| pkg.package_id()).collect();
+    // The messages name the packages, not the names their dependents give
+    // them, so a renamed dependency is still reported once.
+    Ok(resolve
+        .sort()
+        .into_iter()
+        .filter(|id| members.contains(id))
+        .filter_map(|id| Some((id, summary.packages.get(id.name().as_str())?)))
+        .collect())
+}
+
+/// Prints what `cargo fix` did to each package of the workspace, and the
+/// fixes checked with `--dry-run --json`.
+fn print_summary(
+    ws: &Workspace<'_>,
+    packages: &[(PackageId, &PackageFixes)],
+    opts: &FixOptions,
+) -> CargoResult<()> {
+    let config = ws.config();
+    if packages.is_empty() {
+        return Ok(());
+    }
+
+    let (files, fixes) = packages.iter().fold((0, 0), |(files, fixes), (_, pkg)| {
+        let (pkg_files, pkg_fixes) = pkg.counts();
+        (files + pkg_files, fixes + pkg_fixes)
+    });
+    config.shell().status(
+        "Summary",
+        format!(
+            "{} {} {} to {} {} of {} {}",
+            if opts.dry_run {
+                "would apply"
+            } else {
+                "applied"
+            },
+            fixes,
+            if fixes == 1 { "fix" } else { "fixes" },
+            files,
+            if files == 1 { "file" } else { "files" },
+            packages.len(),
+            if packages.len() == 1 {
+                "package"
+            } else {
+                "packages"
+            },
+        ),
+    )?;
+    for (id, pkg) in packages {
+        let mut notes = Vec::new();
+        let (files, fixes) = pkg.counts();
+        if fixes > 0 {
+            notes.push(format!(
+                "{} {} in {} {}",
+                fixes,
+                if fixes == 1 { "fix" } else { "fixes" },
+                files,
+                if files == 1 { "file" } else { "files" }
+            ));
+        } else if !pkg.failed {
+            notes.push("nothing to fix".to_string());
+        }
+        let macro_warnings: usize = pkg.macro_warnings.values().map(|w| w.len()).sum();
+        if macro_warnings > 0 {
+            notes.push(format!(
+                "{} {} in code generated by macros to fix by hand",
+                macro_warnings,
+                if macro_warnings == 1 {
+                    "warning"
+                } else {
+                    "warnings"
+                }
+            ));
+        }
+        if pkg.failed {
+            notes.push(if opts.broken_code {
+                "the fixes broke the code".to_string()
+            } else {
+                "the fixes broke the code and were backed out".to_string()
+            });
+        }
+        drop_eprintln!(config, "  {}: {}", id.name(), notes.join(", "));
+    }
+
+    if opts.json {
+        for (id, pkg) in packages {
+            for (file, fixes) in &pkg.previews {
+                let msg = machine_message::FixPreview {
+                    package_id: *id,
+                    file,
+                    fixes: fixes.iter().collect(),
+                }
+                .to_json_string();
+                writeln!(config.shell().out(), "{}", msg)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks how ready the selected packages are for their next edition, for
+/// `cargo check --edition-readiness`. The fixes of `cargo fix --edition` are
+/// checked without changing the files, one package at a time so that a
+/// package that doesn't build doesn't keep the others from being reported.
+pub fn edition_readiness(ws: &Workspace<'_>, opts: &mut FixOptions) -> CargoResult<()> {
+    let config = ws.config();
+    let packages: Vec<Package> = opts
+        .compile_opts
+        .spec
+        .get_packages(ws)?
+        .into_iter()
+        .cloned()
+        .collect();
+    let mut reports = Vec::new();
+    for pkg in &packages {
+        let edition = pkg.manifest().edition();
+        let next = edition.saturating_next();
+        if next == edition || (!next.is_stable() && !config.nightly_features_allowed) {
+            reports.push((pkg, edition, None, PackageFixes::default(), None));
+            continue;
+        }
+        opts.compile_opts.spec = Packages::Packages(vec![pkg.name().to_string()]);
+        let (summary, error) = match run_fix(ws, opts, true) {
+            Ok(summary) => (summary, None),
+            Err(e) => {
+                let summary = opts
+                    .compile_opts
+                    .build_config
+                    .fix_summary
+                    .borrow_mut()
+                    .take();
+                (summary, Some(e.to_string()))
+            }
+        };
+        let fixes = summary
+            .and_then(|mut summary| summary.packages.remove(pkg.name().as_str()))
+            .unwrap_or_default();
+        reports.push((pkg, edition, Some(next), fixes, error));
+    }
+
+    let is_ready = |fixes: &PackageFixes, error: &Option<String>| {
+        fixes.counts().1 == 0 && fixes.macro_warnings.is_empty() && error.is_none()
+    };
+    if opts.compile_opts.build_config.emit_json() {
+        for (pkg, edition, next, fixes, error) in &reports {
+            let (files, count) = fixes.counts();
+            let manual = fixes
+                .macro_warnings
+                .iter()
+                .flat_map(|(file, warnings)| {
+                    warnings.iter().map(move |w| machine_message::ManualFix {
+                        file,
+                        line: w.line,
+                        macro_name: &w.macro_name,
+                        message: &w.message,
+                    })
+                })
+                .collect();
+            let msg = machine_message::EditionReadiness {
+                package_id: pkg.package_id(),
+                edition: edition.to_string(),
+                next_edition: next.map(|next| next.to_string()),
+                ready: is_ready(fixes, error),
+                fixes: count,
+                files,
+                manual,
+                error: error.clone(),
+            }
+            .to_json_string();
+            writeln!(config.shell().out(), "{}", msg)?;
+        }
+    } else {
+        let ready = reports
+            .iter()
+            .filter(|(_, _, _, fixes, error)| is_ready(fixes, error))
+            .count();
+        config.shell().status(
+            "Readiness",
+            format!(
+                "{} of {} {} ready for the next edition",
+                ready,
+                reports.len(),
+                if reports.len() == 1 {
+                    "package is"
+                } else {
+                    "packages are"
+                }
+            ),
+        )?;
+        for (pkg, edition, next, fixes, error) in &reports {
+            let next = match next {
+                Some(next) => next,
+                None => {
+                    drop_eprintln!(
+                        config,
+                        "  {} ({}): on the latest edition",
+                        pkg.name(),
+                        edition
+                    );
+                    continue;
+                }
+            };
+            let mut notes = Vec::new();
+            if let Some(error) = error {
+                notes.push(format!("doesn't build: {}", error));
+            }
+            let (files, count) = fixes.counts();
+            if count > 0 {
+                notes.push(format!(
+                    "{} {} in {} {} to apply with `cargo fix --edition`",
+                    count,
+                    if count == 1 { "fix" } else { "fixes" },
+                    files,
+                    if files == 1 { "file" } else { "files" }
+                ));
+            }
+            let macro_warnings: usize = fixes.macro_warnings.values().map(|w| w.len()).sum();
+            if macro_warnings > 0 {
+                notes.push(format!(
+                    "{} {} in code generated by macros to fix by hand",
+                    macro_warnings,
+                    if macro_warnings == 1 {
+                        "warning"
+                    } else {
+                        "warnings"
+                    }
+                ));
+            }
+            if notes.is_empty() {
+                notes.push("ready".to_string());
+            }
+            drop_eprintln!(
+                config,
+                "  {} ({} -> {}): {}",
+                pkg.name(),
+                edition,
+                next,
+                notes.join(", ")
+            );
+        }
+    }
+
+    let broken = reports.iter().filter(|r| r.4.is_some()).count();
+    if broken > 0 {
+        bail!(
+            "{} {} didn't build, see the errors above",
+            broken,
+            if broken == 1 { "package" } else { "packages" }
+        );
+    }
     Ok(())
 }
 
@@ -143,34 +505,24 @@ fn check_version_control(config: &Config, opts: &FixOptions) -> CargoResult<()>
         )
     }
 
-    if opts.allow_dirty && opts.allow_staged {
+    let policy = DirtyPolicy::for_command(config, VcsCommand::Fix, opts.allow_dirty)?;
+    if policy == DirtyPolicy::Allow && opts.allow_staged {
         return Ok(());
     }
 
     let mut dirty_files = Vec::new();
     let mut staged_files = Vec::new();
-    if let Ok(repo) = git2::Repository::discover(config.cwd()) {
-        let mut repo_opts = git2::StatusOptions::new();
-        repo_opts.include_ignored(false);
-        for status in repo.statuses(Some(&mut repo_opts))?.iter() {
-            if let Some(path) = status.path() {
-                match status.status() {
-                    git2::Status::CURRENT => (),
-                    git2::Status::INDEX_NEW
-                    | git2::Status::INDEX_MODIFIED
-                    | git2::Status::INDEX_DELETED
-                    | git2::Status::INDEX_RENAMED
-                    | git2::Status::INDEX_TYPECHANGE => {
-                        if !opts.allow_staged {
-                            staged_files.push(path.to_string())
-                        }
-                    }
-                    _ => {
-                        if !opts.allow_dirty {
-                            dirty_files.push(path.to_string())
-                        }
-                    }
-                };
+    if let Some(repo) = vcs_state::discover(config.cwd()) {
+        let workdir = repo.workdir().unwrap();
+        for file in vcs_state::changed_files(&repo, false)? {
+            let path = file.path.strip_prefix(workdir).unwrap_or(&file.path);
+            let path = path.display().to_string();
+            if file.staged {
+                if !opts.allow_staged {
+                    staged_files.push(path)
+                }
+            } else if policy != DirtyPolicy::Allow {
+                dirty_files.push(path)
             }
         }
     }
@@ -185,12 +537,24 @@ fn check_version_control(config: &Config, opts: &FixOptions) -> CargoResult<()>
         files_list.push_str(&file);
         files_list.push_str(" (dirty)\n");
     }
-    for file in staged_files {
+    for file in &staged_files {
         files_list.push_str("  * ");
-        files_list.push_str(&file);
+        files_list.push_str(file);
         files_list.push_str(" (staged)\n");
     }
 
+    if policy == DirtyPolicy::Warn && staged_files.is_empty() {
+        return policy.report(
+            config,
+            VcsCommand::Fix,
+            &format!(
+                "the working directory of this package has uncommitted changes \
+                 to these files:\n\n{}",
+                files_list
+            ),
+            "",
+        );
+    }
     bail!(
         "the working directory of this package has uncommitted changes, and \
          `cargo fix` can potentially perform destructive changes; if you'd \
@@ -365,7 +729,27 @@ pub fn fix_maybe_exec_rustc(config: &Config) -> CargoResult<bool> {
     // If we didn't actually make any changes then we can immediately execute the
     // new rustc, and otherwise we capture the output to hide it in the scenario
     // that we have to back it all out.
-    if !fixes.files.is_empty() {
+    // With `--dry-run` the fixes were only applied to the code in memory,
+    // which can't be built. The diagnostics of the original code are not
+    // shown again, as they are the ones fixed.
+    if env::var_os(DRY_RUN_ENV).is_some() && !fixes.files.is_empty() {
+        for (path, file) in fixes.files.iter() {
+            let patch = match env::var_os(DIFF_ENV) {
+                Some(_) => Some(unified_diff(path, &file.original_code, &file.fixed_code)?),
+                None => None,
+            };
+            Message::WouldFix {
+                package: package_name(),
+                file: path.clone(),
+                fixes: file.suggested_fixes.clone(),
+                patch,
+            }
+            .post()?;
+        }
+        if !fixes.broken {
+            return Ok(true);
+        }
+    } else if !fixes.files.is_empty() {
         let mut cmd = rustc.build_command();
         args.apply(&mut cmd);
         cmd.arg("--error-format=json");
@@ -375,6 +759,7 @@ pub fn fix_maybe_exec_rustc(config: &Config) -> CargoResult<bool> {
         if output.status.success() {
             for (path, file) in fixes.files.iter() {
                 Message::Fixed {
+                    package: package_name(),
                     file: path.clone(),
                     fixes: file.fixes_applied,
                 }
@@ -421,18 +806,30 @@ pub fn fix_maybe_exec_rustc(config: &Config) -> CargoResult<bool> {
 #[derive(Default)]
 struct FixedCrate {
     files: HashMap<String, FixedFile>,
+    /// The warnings in code generated by macros of the last run of rustc,
+    /// when migrating to the next edition.
+    macro_warnings: BTreeMap<String, Vec<MacroWarning>>,
+    /// Whether the last run of rustc failed, with `--broken-code`.
+    broken: bool,
 }
 
 struct FixedFile {
     errors_applying_fixes: Vec<String>,
+    /// The suggestions left out as they overlap with others.
+    conflicts: Vec<SuggestedFix>,
     fixes_applied: u32,
+    suggested_fixes: Vec<SuggestedFix>,
     original_code: String,
+    /// The code with the fixes, which is only written to the file without
+    /// `--dry-run`.
+    fixed_code: String,
 }
 
 /// Attempts to apply fixes to a single crate.
 ///
 /// This runs `rustc` (possibly multiple times) to gather suggestions from the
-/// compiler and applies them to the files on disk.
+/// compiler and applies them to the files on disk. With `--dry-run`, rustc
+/// runs once and the suggestions are applied to the code in memory.
 fn rustfix_crate(
     lock_addr: &str,
     rustc: &ProcessBuilder,
@@ -440,6 +837,12 @@ fn rustfix_crate(
     args: &FixArgs,
     config: &Config,
 ) -> Result<FixedCrate, Error> {
+    if let Some(skip) = env::var_os(SKIP_ENV) {
+        let name = package_name().unwrap_or_default();
+        if skip.to_string_lossy().split(',').any(|s| s == name) {
+            return Ok(FixedCrate::default());
+        }
+    }
     if !args.can_run_rustfix(config)? {
         // This fix should not be run. Skipping...
         return Ok(FixedCrate::default());
@@ -491,21 +894,27 @@ fn rustfix_crate(
     //   definitely can't make progress, so bail out.
     let mut fixes = FixedCrate::default();
     let mut last_fix_counts = HashMap::new();
-    let iterations = env::var("CARGO_FIX_MAX_RETRIES")
-        .ok()
-        .and_then(|n| n.parse().ok())
-        .unwrap_or(4);
+    let iterations = if env::var_os(DRY_RUN_ENV).is_some() {
+        // The next runs of rustc wouldn't see the fixed code.
+        1
+    } else {
+        env::var("CARGO_FIX_MAX_RETRIES")
+            .ok()
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(4)
+    };
     for _ in 0..iterations {
         last_fix_counts.clear();
         for (path, file) in fixes.files.iter_mut() {
             last_fix_counts.insert(path.clone(), file.fixes_applied);
             // We'll generate new errors below.
             file.errors_applying_fixes.clear();
+            file.conflicts.clear();
         }
         rustfix_and_fix(&mut fixes, rustc, filename, args, config)?;
         let mut progress_yet_to_be_made = false;
         for (path, file) in fixes.files.iter_mut() {
-            if file.errors_applying_fixes.is_empty() {
+            if file.errors_applying_fixes.is_empty() && file.conflicts.is_empty() {
                 continue;
             }
             // If anything was successfully fixed *and* there's at least one
@@ -532,6 +941,28 @@ fn rustfix_crate(
         }
     }
 
+    for (path, file) in fixes.files.iter() {
+        if !file.conflicts.is_empty() {
+            Message::FixConflicts {
+                package: package_name(),
+                file: path.clone(),
+                fixes: file.conflicts.clone(),
+            }
+            .post()?;
+        }
+    }
+
+    if env::var_os(SUMMARY_ENV).is_some() {
+        for (file, warnings) in fixes.macro_warnings.iter() {
+            Message::MacroWarnings {
+                package: package_name(),
+                file: file.clone(),
+                warnings: warnings.clone(),
+            }
+            .post()?;
+        }
+    }
+
     Ok(fixes)
 }
 
@@ -547,8 +978,11 @@ fn rustfix_and_fix(
     config: &Config,
 ) -> Result<(), Error> {
     // If not empty, filter by these lints.
-    // TODO: implement a way to specify this.
-    let only = HashSet::new();
+    let only: HashSet<String> = args
+        .lints
+        .iter()
+        .map(|lint| lint.replace('-', "_"))
+        .collect();
 
     let mut cmd = rustc.build_command();
     cmd.arg("--error-format=json");
@@ -569,6 +1003,7 @@ fn rustfix_and_fix(
     // worse by applying fixes where a bug could cause *more* broken code.
     // Instead, punt upwards which will reexec rustc over the original code,
     // displaying pretty versions of the diagnostics we just read out.
+    fixes.broken = !output.status.success();
     if !output.status.success() && env::var_os(BROKEN_CODE_ENV).is_none() {
         debug!(
             "rustfixing `{:?}` failed, rustc exited with {:?}",
@@ -586,6 +1021,20 @@ fn rustfix_and_fix(
     // indicating fixes that we can apply.
     let stderr = str::from_utf8(&output.stderr).context("failed to parse rustc stderr as UTF-8")?;
 
+    // It's safe since we won't read any content under home dir.
+    let home_path = config.home().as_path_unlocked();
+    if args.prepare_for_edition.is_some() {
+        fixes.macro_warnings.clear();
+        for (file, warning) in stderr
+            .lines()
+            .filter_map(|line| macro_warning(line, &only, fix_mode))
+        {
+            if !Path::new(&file).starts_with(home_path) {
+                fixes.macro_warnings.entry(file).or_default().push(warning);
+            }
+        }
+    }
+
     let suggestions = stderr
         .lines()
         .filter(|x| !x.is_empty())
@@ -598,8 +1047,6 @@ fn rustfix_and_fix(
     // Collect suggestions by file so we can apply them one at a time later.
     let mut file_map = HashMap::new();
     let mut num_suggestion = 0;
-    // It's safe since we won't read any content under home dir.
-    let home_path = config.home().as_path_unlocked();
     for suggestion in suggestions {
         trace!("suggestion");
         // Make sure we've got a file associated with this suggestion and all
@@ -656,6 +1103,12 @@ fn rustfix_and_fix(
         let num_suggestions = suggestions.len();
         debug!("applying {} fixes to {}", num_suggestions, file);
 
+        if !fixes.files.contains_key(&file) {
+            if let Some(dir) = env::var_os(BACKUP_ENV) {
+                fix_backup::save_original(Path::new(&dir), file.as_ref(), &code)?;
+            }
+        }
+
         // If this file doesn't already exist then we just read the original
         // code, so save it. If the file already exists then the original code
         // doesn't need to be updated as we've just read an interim state with
@@ -665,27 +1118,162 @@ fn rustfix_and_fix(
             .entry(file.clone())
             .or_insert_with(|| FixedFile {
                 errors_applying_fixes: Vec::new(),
+                conflicts: Vec::new(),
                 fixes_applied: 0,
+                suggested_fixes: Vec::new(),
                 original_code: code.clone(),
+                fixed_code: String::new(),
             });
         let mut fixed = CodeFix::new(&code);
+        let suggestions = if args.lints.is_empty() {
+            suggestions
+        } else {
+            remove_conflicts(suggestions, &mut fixed_file.conflicts)
+        };
 
         // As mentioned above in `rustfix_crate`, we don't immediately warn
         // about suggestions that fail to apply here, and instead we save them
         // off for later processing.
         for suggestion in suggestions.iter().rev() {
             match fixed.apply(suggestion) {
-                Ok(()) => fixed_file.fixes_applied += 1,
+                Ok(()) => {
+                    fixed_file.fixes_applied += 1;
+                    fixed_file
+                        .suggested_fixes
+                        .extend(suggested_fixes(suggestion));
+                }
                 Err(e) => fixed_file.errors_applying_fixes.push(e.to_string()),
             }
         }
-        let new_code = fixed.finish()?;
-        paths::write(&file, new_code)?;
+        fixed_file.fixed_code = fixed.finish()?;
+        if env::var_os(DRY_RUN_ENV).is_none() {
+            paths::write(&file, &fixed_file.fixed_code)?;
+        }
     }
 
     Ok(())
 }
 
+/// The replacements of `suggestion`, as reported by `--dry-run`.
+fn suggested_fixes(suggestion: &rustfix::Suggestion) -> impl Iterator<Item = SuggestedFix> + '_ {
+    suggestion
+        .solutions
+        .iter()
+        .flat_map(|solution| solution.replacements.iter())
+        .map(move |r| SuggestedFix {
+            message: suggestion.message.clone(),
+            line: r.snippet.line_range.start.line,
+            column: r.snippet.line_range.start.column,
+            original: r.snippet.text.1.clone(),
+            replacement: r.replacement.clone(),
+        })
+}
+
+/// Leaves out the suggestions replacing code that an earlier suggestion
+/// replaces too, as they can't both be applied.
+fn remove_conflicts(
+    suggestions: Vec<rustfix::Suggestion>,
+    conflicts: &mut Vec<SuggestedFix>,
+) -> Vec<rustfix::Suggestion> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    let mut kept: Vec<rustfix::Suggestion> = Vec::new();
+    for suggestion in suggestions {
+        if kept.contains(&suggestion) {
+            continue;
+        }
+        let new: Vec<_> = suggestion
+            .solutions
+            .iter()
+            .flat_map(|solution| solution.replacements.iter())
+            .map(|r| r.snippet.range.clone())
+            .collect();
+        let overlaps = new.iter().any(|a| {
+            ranges
+                .iter()
+                .any(|b| a == b || (a.start < b.end && b.start < a.end))
+        });
+        if overlaps {
+            conflicts.extend(suggested_fixes(&suggestion));
+        } else {
+            ranges.extend(new);
+            kept.push(suggestion);
+        }
+    }
+    kept
+}
+
+/// The unified diff from `original` to `fixed` for `file`, which `git apply`
+/// applies in the directory rustc runs in.
+pub(super) fn unified_diff(file: &str, original: &str, fixed: &str) -> CargoResult<String> {
+    let path = Path::new(file);
+    let mut patch = git2::Patch::from_buffers(
+        original.as_bytes(),
+        Some(path),
+        fixed.as_bytes(),
+        Some(path),
+        None,
+    )?;
+    Ok(String::from_utf8_lossy(&patch.to_buf()?).into_owned())
+}
+
+/// The warning of the diagnostic in `line` if it is in code generated by a
+/// macro and has no suggestion to apply, with the file the macro is invoked
+/// in.
+fn macro_warning(
+    line: &str,
+    only: &HashSet<String>,
+    fix_mode: rustfix::Filter,
+) -> Option<(String, MacroWarning)> {
+    #[derive(Deserialize)]
+    struct MacroDiagnostic {
+        message: String,
+        level: String,
+        spans: Vec<Span>,
+    }
+    #[derive(Deserialize)]
+    struct Span {
+        file_name: String,
+        line_start: usize,
+        is_primary: bool,
+        expansion: Option<Box<Expansion>>,
+    }
+    #[derive(Deserialize)]
+    struct Expansion {
+        span: Span,
+        macro_decl_name: String,
+    }
+
+    let diagnostic = serde_json::from_str::<Diagnostic>(line).ok()?;
+    if rustfix::collect_suggestions(&diagnostic, only, fix_mode).is_some() {
+        return None;
+    }
+    let diagnostic = serde_json::from_str::<MacroDiagnostic>(line).ok()?;
+    if diagnostic.level != "warning" {
+        return None;
+    }
+    // The span where the outermost macro is invoked is in the crate.
+    let mut span = diagnostic.spans.into_iter().find(|span| span.is_primary)?;
+    let mut macro_name = None;
+    while let Some(expansion) = span.expansion.take() {
+        macro_name = Some(expansion.macro_decl_name);
+        span = expansion.span;
+    }
+    Some((
+        span.file_name,
+        MacroWarning {
+            line: span.line_start,
+            macro_name: macro_name?,
+            message: diagnostic.message,
+        },
+    ))
+}
+
+/// The name of the package of the crate being fixed, from the variables
+/// Cargo sets for rustc.
+fn package_name() -> Option<String> {
+    env::var("CARGO_PKG_NAME").ok()
+}
+
 fn exit_with(status: ExitStatus) -> ! {
     #[cfg(unix)]
     {
@@ -746,6 +1334,7 @@ fn log_failed_fix(stderr: &[u8], status: ExitStatus) -> Result<(), Error> {
         Some(exit_status_to_string(status))
     };
     Message::FixFailed {
+        package: package_name(),
         files,
         krate,
         errors,
@@ -775,6 +1364,8 @@ struct FixArgs {
     other: Vec<OsString>,
     /// Path to the `rustc` executable.
     rustc: PathBuf,
+    /// The lints whose suggestions are applied, with `--apply-suggestions`.
+    lints: Vec<String>,
     /// Console output flags (`--error-format`, `--json`, etc.).
     ///
     /// The normal fix procedure always uses `--json`, so it overrides what
@@ -816,6 +1407,9 @@ impl FixArgs {
         }
         let file = file.ok_or_else(|| anyhow::anyhow!("could not find .rs file in rustc args"))?;
         let idioms = env::var(IDIOMS_ENV).is_ok();
+        let lints = env::var(LINTS_ENV)
+            .map(|lints| lints.split(',').map(|s| s.to_string()).collect())
+            .unwrap_or_default();
 
         let prepare_for_edition = env::var(EDITION_ENV).ok().map(|_| {
             enabled_edition
@@ -830,6 +1424,7 @@ impl FixArgs {
             enabled_edition,
             other,
             rustc,
+            lints,
             format_args,
         })
     }
@@ -860,6 +1455,9 @@ impl FixArgs {
                     .arg(format!("rust-{}-compatibility", edition));
             }
         }
+        for lint in &self.lints {
+            cmd.arg("--force-warn").arg(lint);
+        }
     }
 
     /// Validates the edition, and sends a message indicating what is being
@@ -916,6 +1514,7 @@ impl FixArgs {
             .post()
         } else {
             Message::Migrating {
+                package: package_name(),
                 file: self.file.display().to_string(),
                 from_edition,
                 to_edition,
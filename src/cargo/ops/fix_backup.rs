@@ -0,0 +1,309 @@
+//! The backups of `cargo fix -Z fix-backups`, and `cargo fix --undo`.
+//!
+//! Each run of `cargo fix` gets a numbered journal in `target/fix-backups`.
+//! Before a `cargo fix` proxy changes a file for the first time, it saves the
+//! original code of the file in the journal. Once the fixes are done, the
+//! journal is completed with a hash of the code each file was left with, in
+//! `journal.json`:
+//!
+//! ```json
+//! {
+//!     "snapshot": "5d0e4c3...",
+//!     "files": [
+//!         {"path": "/.../foo/src/lib.rs", "original": "...", "fixed": "0123456789abcdef"}
+//!     ]
+//! }
+//! ```
+//!
+//! `cargo fix --undo` gives the files of the last journal their original code
+//! back, as long as they weren't changed since, and removes the journal.
+//!
+//! With `--vcs-snapshot`, the git working tree is also saved before fixing,
+//! as an entry of `git stash list` that `git stash apply` brings back. The
+//! files are left as they are, like with `git stash create`.
+
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context as _};
+use cargo_util::paths;
+use serde::{Deserialize, Serialize};
+
+use crate::core::Workspace;
+use crate::util::{short_hash, CargoResult, Config};
+
+const JOURNAL_FILE: &str = "journal.json";
+const STASH_REF: &str = "refs/stash";
+
+/// The journal of one run of `cargo fix`.
+pub struct Journal {
+    dir: PathBuf,
+    snapshot: Option<git2::Oid>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JournalFile {
+    snapshot: Option<String>,
+    files: Vec<BackupEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BackupEntry {
+    path: PathBuf,
+    original: String,
+    /// The hash of the code `cargo fix` left in the file.
+    #[serde(default)]
+    fixed: String,
+}
+
+impl Journal {
+    /// Starts the journal of a run of `cargo fix`, saving the working tree in
+    /// the git stash first with `vcs_snapshot`.
+    pub fn start(ws: &Workspace<'_>, vcs_snapshot: bool) -> CargoResult<Journal> {
+        let root = backups_dir(ws);
+        let number = journals(&root)?.last().map_or(1, |(n, _)| n + 1);
+        let dir = root.join(number.to_string());
+        paths::create_dir_all(&dir)?;
+        let snapshot = if vcs_snapshot {
+            Some(stash_snapshot(ws, number)?)
+        } else {
+            None
+        };
+        Ok(Journal { dir, snapshot })
+    }
+
+    /// The directory the `cargo fix` proxies save the original code in.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Completes the journal with the files `cargo fix` left changed, or
+    /// removes it if there are none.
+    pub fn finish(self, config: &Config) -> CargoResult<()> {
+        let mut files = Vec::new();
+        let entries = std::fs::read_dir(&self.dir)
+            .with_context(|| format!("failed to read `{}`", self.dir.display()))?;
+        for entry in entries {
+            let entry = entry?.path();
+            if entry.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let mut file: BackupEntry = serde_json::from_slice(&paths::read_bytes(&entry)?)?;
+            paths::remove_file(&entry)?;
+            let code = paths::read(&file.path)?;
+            // Fixes that broke the code were backed out.
+            if code != file.original {
+                file.fixed = short_hash(&code);
+                files.push(file);
+            }
+        }
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        if files.is_empty() && self.snapshot.is_none() {
+            return paths::remove_dir_all(&self.dir);
+        }
+        if let Some(snapshot) = self.snapshot {
+            config.shell().status(
+                "Saved",
+                format!(
+                    "the working tree in the git stash as {}, \
+                     bring it back with `git stash apply`",
+                    snapshot
+                ),
+            )?;
+        }
+        if !files.is_empty() {
+            config.shell().status(
+                "Backed up",
+                format!(
+                    "{} {} in `{}`, undo the fixes with `cargo fix --undo`",
+                    files.len(),
+                    if files.len() == 1 { "file" } else { "files" },
+                    self.dir.display()
+                ),
+            )?;
+        }
+        let journal = JournalFile {
+            snapshot: self.snapshot.map(|oid| oid.to_string()),
+            files,
+        };
+        paths::write(self.dir.join(JOURNAL_FILE), serde_json::to_vec(&journal)?)
+    }
+}
+
+/// Saves the original code of `file` in the journal in `dir`, unless it was
+/// saved already, before a `cargo fix` proxy changes it.
+pub fn save_original(dir: &Path, file: &Path, code: &str) -> CargoResult<()> {
+    let path = paths::normalize_path(&std::env::current_dir()?.join(file));
+    let backup = dir.join(format!("{}.json", short_hash(&path)));
+    let entry = BackupEntry {
+        path,
+        original: code.to_string(),
+        fixed: String::new(),
+    };
+    // Other targets of the package see the code already fixed.
+    match OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&backup)
+    {
+        Ok(mut f) => f
+            .write_all(&serde_json::to_vec(&entry)?)
+            .with_context(|| format!("failed to write `{}`", backup.display())),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Ok(()),
+        Err(e) => {
+            Err(anyhow::Error::from(e).context(format!("failed to create `{}`", backup.display())))
+        }
+    }
+}
+
+/// Gives the files changed by the last run of `cargo fix` their original
+/// code back.
+pub fn undo_fix(ws: &Workspace<'_>) -> CargoResult<()> {
+    let config = ws.config();
+    let root = backups_dir(ws);
+    let dir = match journals(&root)?
+        .into_iter()
+        .map(|(_, dir)| dir)
+        .filter(|dir| dir.join(JOURNAL_FILE).exists())
+        .last()
+    {
+        Some(dir) => dir,
+        None => bail!("no fixes to undo in `{}`", root.display()),
+    };
+    let journal_path = dir.join(JOURNAL_FILE);
+    let journal: JournalFile = serde_json::from_slice(&paths::read_bytes(&journal_path)?)
+        .with_context(|| format!("failed to read the journal `{}`", journal_path.display()))?;
+
+    let changed: Vec<String> = journal
+        .files
+        .iter()
+        .filter(
+            |file| !matches!(paths::read(&file.path), Ok(code) if short_hash(&code) == file.fixed),
+        )
+        .map(|file| format!("  {}", file.path.display()))
+        .collect();
+    if !changed.is_empty() {
+        bail!(
+            "files fixed by `cargo fix` were changed since:\n{}\n\
+             Their original code is in `{}`.",
+            changed.join("\n"),
+            journal_path.display()
+        );
+    }
+
+    for file in &journal.files {
+        paths::write(&file.path, &file.original)?;
+    }
+    if let Some(snapshot) = &journal.snapshot {
+        drop_stash_snapshot(ws, snapshot)?;
+    }
+    paths::remove_dir_all(&dir)?;
+    config.shell().status(
+        "Restored",
+        format!(
+            "{} {} changed by `cargo fix`",
+            journal.files.len(),
+            if journal.files.len() == 1 {
+                "file"
+            } else {
+                "files"
+            }
+        ),
+    )?;
+    Ok(())
+}
+
+fn backups_dir(ws: &Workspace<'_>) -> PathBuf {
+    ws.target_dir().join("fix-backups").into_path_unlocked()
+}
+
+/// The journals in `root`, in the order of the runs. A journal that was
+/// never completed, because `cargo fix` was interrupted, has no
+/// `journal.json`.
+fn journals(root: &Path) -> CargoResult<Vec<(u32, PathBuf)>> {
+    let mut journals = Vec::new();
+    if !root.exists() {
+        return Ok(journals);
+    }
+    let entries =
+        std::fs::read_dir(root).with_context(|| format!("failed to read `{}`", root.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        let number = path.file_name().and_then(|n| n.to_str()?.parse().ok());
+        if let Some(number) = number {
+            journals.push((number, path));
+        }
+    }
+    journals.sort();
+    Ok(journals)
+}
+
+/// Saves the working tree of the repository of the workspace as a stash
+/// entry, without changing it.
+fn stash_snapshot(ws: &Workspace<'_>, number: u32) -> CargoResult<git2::Oid> {
+    let repo = match git2::Repository::discover(ws.root()) {
+        Ok(repo) if !repo.is_bare() => repo,
+        _ => bail!("`--vcs-snapshot` requires the workspace to be in a git repository"),
+    };
+    let head = match repo.head().and_then(|head| head.peel_to_commit()) {
+        Ok(head) => head,
+        Err(_) => bail!("`--vcs-snapshot` requires a commit to stash the working tree on"),
+    };
+    let signature = repo
+        .signature()
+        .or_else(|_| git2::Signature::now("cargo", "cargo@localhost"))?;
+    let branch = repo.head()?.shorthand().unwrap_or("HEAD").to_string();
+    let summary = head.summary().unwrap_or("").to_string();
+
+    // Like `git stash create`: a commit of the index, and a commit of the
+    // tracked files of the working tree with HEAD and the index as parents.
+    let mut index = repo.index()?;
+    let index_tree = repo.find_tree(index.write_tree()?)?;
+    let index_commit = repo.commit(
+        None,
+        &signature,
+        &signature,
+        &format!("index on {}: {} {}", branch, head.id(), summary),
+        &index_tree,
+        &[&head],
+    )?;
+    let index_commit = repo.find_commit(index_commit)?;
+    index.update_all(["*"].iter(), None)?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    // The index of the repository itself is left as it is.
+    index.read(true)?;
+    let message = format!("On {}: cargo fix backup {}", branch, number);
+    let snapshot = repo.commit(
+        None,
+        &signature,
+        &signature,
+        &message,
+        &tree,
+        &[&head, &index_commit],
+    )?;
+    repo.reference_ensure_log(STASH_REF)?;
+    repo.reference(STASH_REF, snapshot, true, &message)?;
+    Ok(snapshot)
+}
+
+/// Removes the stash entry of `snapshot`, if it is still in the stash.
+fn drop_stash_snapshot(ws: &Workspace<'_>, snapshot: &str) -> CargoResult<()> {
+    let mut repo = match git2::Repository::discover(ws.root()) {
+        Ok(repo) => repo,
+        Err(_) => return Ok(()),
+    };
+    let snapshot = git2::Oid::from_str(snapshot)?;
+    let mut stash_index = None;
+    repo.stash_foreach(|index, _, oid| {
+        if *oid == snapshot {
+            stash_index = Some(index);
+        }
+        stash_index.is_none()
+    })?;
+    if let Some(index) = stash_index {
+        repo.stash_drop(index)?;
+    }
+    Ok(())
+}
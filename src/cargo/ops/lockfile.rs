@@ -1,41 +1,168 @@
+use std::collections::HashMap;
 use std::io::prelude::*;
+use std::path::Path;
 
-use crate::core::{resolver, Resolve, ResolveVersion, Workspace};
+use crate::core::resolver::Provenance;
+use crate::core::{resolver, PackageId, Resolve, ResolveVersion, Workspace};
+use crate::sources::registry::{short_name, SumDb};
+use crate::sources::SourceConfigMap;
 use crate::util::errors::CargoResult;
 use crate::util::toml as cargo_toml;
-use crate::util::Filesystem;
+use crate::util::{Config, Filesystem};
 
-use anyhow::Context as _;
+use anyhow::{bail, Context as _};
+use cargo_util::paths;
 
 pub fn load_pkg_lockfile(ws: &Workspace<'_>) -> CargoResult<Option<Resolve>> {
-    if !ws.root().join("Cargo.lock").exists() {
+    if !ws.lockfile_path().exists() {
         return Ok(None);
     }
 
     let root = Filesystem::new(ws.root().to_path_buf());
-    let mut f = root.open_ro("Cargo.lock", ws.config(), "Cargo.lock file")?;
+    let mut f = root.open_ro(ws.lockfile_name(), ws.config(), "Cargo.lock file")?;
 
     let mut s = String::new();
     f.read_to_string(&mut s)
         .with_context(|| format!("failed to read file: {}", f.path().display()))?;
 
-    let resolve = (|| -> CargoResult<Option<Resolve>> {
-        let resolve: toml::Value = cargo_toml::parse(&s, f.path(), ws.config())?;
-        let v: resolver::EncodableResolve = resolve.try_into()?;
-        Ok(Some(v.into_resolve(&s, ws)?))
+    let resolve = (|| -> CargoResult<Resolve> {
+        if split_conflicts(&s)?.is_some() {
+            bail!(
+                "the lock file has merge conflicts, \
+                 resolve them with `cargo update --merge -Z lockfile-merge`"
+            );
+        }
+        parse_lockfile(&s, f.path(), ws)
     })()
     .with_context(|| format!("failed to parse lock file at: {}", f.path().display()))?;
-    Ok(resolve)
+    Ok(Some(resolve))
+}
+
+/// Loads the two sides of the merge conflicts of `Cargo.lock`, ours and
+/// theirs, for `cargo update --merge`. Both are the whole lock file if it
+/// has no conflicts.
+pub fn load_conflicted_lockfile(ws: &Workspace<'_>) -> CargoResult<(Resolve, Resolve)> {
+    let path = ws.lockfile_path();
+    if !path.exists() {
+        bail!(
+            "no `{}` found, generate one with `cargo generate-lockfile`",
+            ws.lockfile_name()
+        );
+    }
+    let s = paths::read(&path)?;
+    (|| -> CargoResult<(Resolve, Resolve)> {
+        Ok(match split_conflicts(&s)? {
+            Some((ours, theirs)) => (
+                parse_lockfile(&ours, &path, ws).context("failed to parse our side")?,
+                parse_lockfile(&theirs, &path, ws).context("failed to parse their side")?,
+            ),
+            None => (
+                parse_lockfile(&s, &path, ws)?,
+                parse_lockfile(&s, &path, ws)?,
+            ),
+        })
+    })()
+    .with_context(|| format!("failed to parse lock file at: {}", path.display()))
+}
+
+/// The git merge driver of `Cargo.lock`, which writes the union of the lock
+/// files `ours` and `theirs` into `ours`, see `Resolve::union`.
+///
+/// The manifests of the merge aren't in the working tree yet while git runs
+/// the driver, so the union isn't re-resolved: the next command re-resolves
+/// whatever the merged manifests no longer match.
+pub fn merge_lockfiles(ws: &Workspace<'_>, ours: &Path, theirs: &Path) -> CargoResult<()> {
+    let load = |path: &Path| -> CargoResult<(String, Resolve)> {
+        let s = paths::read(path)?;
+        let resolve = parse_lockfile(&s, path, ws)
+            .with_context(|| format!("failed to parse lock file at: {}", path.display()))?;
+        Ok((s, resolve))
+    };
+    let (orig, our_resolve) = load(ours)?;
+    let (_, their_resolve) = load(theirs)?;
+    let union = our_resolve.union(&their_resolve);
+    paths::write(ours, serialize_resolve(ws, &union, Some(&orig)))
+}
+
+fn parse_lockfile(s: &str, path: &Path, ws: &Workspace<'_>) -> CargoResult<Resolve> {
+    let resolve: toml::Value = cargo_toml::parse(s, path, ws.config())?;
+    let v: resolver::EncodableResolve = resolve.try_into()?;
+    v.into_resolve(s, ws)
+}
+
+/// Splits a lock file with merge conflicts into our side and their side. The
+/// lines outside of the conflicts belong to both, and the base of `diff3`
+/// conflicts to neither. Returns `None` if there are no conflicts.
+fn split_conflicts(s: &str) -> CargoResult<Option<(String, String)>> {
+    #[derive(PartialEq)]
+    enum Side {
+        Both,
+        Ours,
+        Base,
+        Theirs,
+    }
+
+    let mut side = Side::Both;
+    let mut conflicts = false;
+    let mut ours = String::new();
+    let mut theirs = String::new();
+    for line in s.lines() {
+        match side {
+            Side::Both if line.starts_with("<<<<<<<") => {
+                side = Side::Ours;
+                conflicts = true;
+                continue;
+            }
+            Side::Ours if line.starts_with("|||||||") => {
+                side = Side::Base;
+                continue;
+            }
+            Side::Ours | Side::Base if line.starts_with("=======") => {
+                side = Side::Theirs;
+                continue;
+            }
+            Side::Theirs if line.starts_with(">>>>>>>") => {
+                side = Side::Both;
+                continue;
+            }
+            _ => {}
+        }
+        if side == Side::Both || side == Side::Ours {
+            ours.push_str(line);
+            ours.push('\n');
+        }
+        if side == Side::Both || side == Side::Theirs {
+            theirs.push_str(line);
+            theirs.push('\n');
+        }
+    }
+    if side != Side::Both {
+        bail!("a merge conflict of the lock file is missing its end marker `>>>>>>>`");
+    }
+    Ok(if conflicts {
+        Some((ours, theirs))
+    } else {
+        None
+    })
 }
 
 /// Generate a toml String of Cargo.lock from a Resolve.
 pub fn resolve_to_string(ws: &Workspace<'_>, resolve: &mut Resolve) -> CargoResult<String> {
-    let (_orig, out, _ws_root) = resolve_to_string_orig(ws, resolve);
+    let (_orig, out, _ws_root) = resolve_to_string_orig(ws, resolve)?;
     Ok(out)
 }
 
+/// Whether `Cargo.lock` exists and already records `resolve`.
+pub fn is_lockfile_up_to_date(ws: &Workspace<'_>, resolve: &mut Resolve) -> CargoResult<bool> {
+    let (orig, out, _ws_root) = resolve_to_string_orig(ws, resolve)?;
+    Ok(match orig {
+        Some(orig) => are_equal_lockfiles(&orig, &out, ws),
+        None => false,
+    })
+}
+
 pub fn write_pkg_lockfile(ws: &Workspace<'_>, resolve: &mut Resolve) -> CargoResult<()> {
-    let (orig, mut out, ws_root) = resolve_to_string_orig(ws, resolve);
+    let (orig, mut out, ws_root) = resolve_to_string_orig(ws, resolve)?;
 
     // If the lock file contents haven't changed so don't rewrite it. This is
     // helpful on read-only filesystems.
@@ -45,6 +172,79 @@ pub fn write_pkg_lockfile(ws: &Workspace<'_>, resolve: &mut Resolve) -> CargoRes
         }
     }
 
+    // While we're updating the lock file anyway go ahead and update its
+    // encoding to whatever the latest default is. That way we can slowly roll
+    // out lock file updates as they're otherwise already updated, and changes
+    // which don't touch dependencies won't seemingly spuriously update the lock
+    // file.
+    let latest = latest_version(ws.config());
+    if resolve.version() < latest {
+        resolve.set_version(latest);
+        record_provenance(ws, resolve)?;
+        out = serialize_resolve(ws, resolve, orig.as_deref());
+    }
+
+    write_lockfile(ws, &ws_root, &out)
+}
+
+/// Rewrites `Cargo.lock` in the format `version`, recording the provenance
+/// of its packages anew if it is `V5`.
+pub fn migrate_lockfile(ws: &Workspace<'_>, version: ResolveVersion) -> CargoResult<()> {
+    let mut resolve = match load_pkg_lockfile(ws)? {
+        Some(resolve) => resolve,
+        None => bail!("no `Cargo.lock` found, generate one with `cargo generate-lockfile`"),
+    };
+    let from = resolve.version();
+    resolve.set_version(version);
+    resolve.provenance_mut().clear();
+    let (orig, out, ws_root) = resolve_to_string_orig(ws, &mut resolve)?;
+    if orig.as_deref() != Some(out.as_str()) {
+        write_lockfile(ws, &ws_root, &out)?;
+    }
+    ws.config().shell().status(
+        "Migrated",
+        format!(
+            "`Cargo.lock` from version {} to version {}",
+            version_number(from),
+            version_number(version)
+        ),
+    )?;
+    Ok(())
+}
+
+/// Parses the version of a lock file for `cargo lockfile migrate`.
+pub fn parse_lockfile_version(s: &str) -> CargoResult<ResolveVersion> {
+    Ok(match s {
+        "1" => ResolveVersion::V1,
+        "2" => ResolveVersion::V2,
+        "3" => ResolveVersion::V3,
+        "5" => ResolveVersion::V5,
+        _ => bail!(
+            "lock file version `{}` is not supported, the versions are 1, 2, 3 and 5",
+            s
+        ),
+    })
+}
+
+fn version_number(version: ResolveVersion) -> u32 {
+    match version {
+        ResolveVersion::V1 => 1,
+        ResolveVersion::V2 => 2,
+        ResolveVersion::V3 => 3,
+        ResolveVersion::V5 => 5,
+    }
+}
+
+/// The version of new and updated lock files, `V5` with `-Z lockfile-v5`.
+fn latest_version(config: &Config) -> ResolveVersion {
+    if config.cli_unstable().lockfile_v5 {
+        ResolveVersion::V5
+    } else {
+        ResolveVersion::default()
+    }
+}
+
+fn write_lockfile(ws: &Workspace<'_>, ws_root: &Filesystem, out: &str) -> CargoResult<()> {
     if !ws.config().lock_update_allowed() {
         let flag = if ws.config().network_allowed() {
             "--locked"
@@ -55,51 +255,88 @@ pub fn write_pkg_lockfile(ws: &Workspace<'_>, resolve: &mut Resolve) -> CargoRes
             "the lock file {} needs to be updated but {} was passed to prevent this\n\
              If you want to try to generate the lock file without accessing the network, \
              remove the {} flag and use --offline instead.",
-            ws.root().to_path_buf().join("Cargo.lock").display(),
+            ws.lockfile_path().display(),
             flag,
             flag
         );
     }
 
-    // While we're updating the lock file anyway go ahead and update its
-    // encoding to whatever the latest default is. That way we can slowly roll
-    // out lock file updates as they're otherwise already updated, and changes
-    // which don't touch dependencies won't seemingly spuriously update the lock
-    // file.
-    if resolve.version() < ResolveVersion::default() {
-        resolve.set_version(ResolveVersion::default());
-        out = serialize_resolve(resolve, orig.as_deref());
-    }
-
     // Ok, if that didn't work just write it out
     ws_root
-        .open_rw("Cargo.lock", ws.config(), "Cargo.lock file")
+        .open_rw(ws.lockfile_name(), ws.config(), "Cargo.lock file")
         .and_then(|mut f| {
             f.file().set_len(0)?;
             f.write_all(out.as_bytes())?;
             Ok(())
         })
-        .with_context(|| format!("failed to write {}", ws.root().join("Cargo.lock").display()))?;
+        .with_context(|| format!("failed to write {}", ws.lockfile_path().display()))?;
     Ok(())
 }
 
 fn resolve_to_string_orig(
     ws: &Workspace<'_>,
     resolve: &mut Resolve,
-) -> (Option<String>, String, Filesystem) {
+) -> CargoResult<(Option<String>, String, Filesystem)> {
     // Load the original lock file if it exists.
     let ws_root = Filesystem::new(ws.root().to_path_buf());
-    let orig = ws_root.open_ro("Cargo.lock", ws.config(), "Cargo.lock file");
+    let orig = ws_root.open_ro(ws.lockfile_name(), ws.config(), "Cargo.lock file");
     let orig = orig.and_then(|mut f| {
         let mut s = String::new();
         f.read_to_string(&mut s)?;
         Ok(s)
     });
-    let out = serialize_resolve(resolve, orig.as_deref().ok());
-    (orig.ok(), out, ws_root)
+    record_provenance(ws, resolve)?;
+    let out = serialize_resolve(ws, resolve, orig.as_deref().ok());
+    Ok((orig.ok(), out, ws_root))
 }
 
-fn serialize_resolve(resolve: &Resolve, orig: Option<&str>) -> String {
+/// Records the provenance of the registry packages of a `V5` lock file which
+/// are newly locked: the name of the source replacing their registry, and how
+/// `-Z sumdb` verified their checksum. What is already recorded is kept, and
+/// nothing is added to it later, see `Provenance`.
+///
+/// With `-Z sumdb`, the checksums of the new packages are checked against the
+/// checksum database here, as they would be when downloading them, so that
+/// the signature is recorded by the command writing the lock file rather than
+/// by a later build.
+fn record_provenance(ws: &Workspace<'_>, resolve: &mut Resolve) -> CargoResult<()> {
+    if resolve.version() < ResolveVersion::V5 {
+        return Ok(());
+    }
+    let config = ws.config();
+    let sources = SourceConfigMap::new(config)?;
+    let mut sumdbs = HashMap::new();
+    let mut ids: Vec<(PackageId, String)> = resolve
+        .checksums()
+        .iter()
+        .filter(|(id, _)| !resolve.provenance().contains_key(id))
+        .filter_map(|(id, checksum)| Some((*id, checksum.clone()?)))
+        .collect();
+    ids.sort();
+    for (id, checksum) in ids {
+        let (mirror, used) = match sources.replacement(id.source_id())? {
+            Some((name, used)) => (Some(name.to_string()), used),
+            None => (None, id.source_id()),
+        };
+        let signature = if used.is_remote_registry() {
+            let sumdb = sumdbs
+                .entry(used)
+                .or_insert_with(|| SumDb::new(used, config, &short_name(used)));
+            if config.cli_unstable().sumdb {
+                sumdb.check(id, &checksum)?;
+            }
+            sumdb.verification(id, &checksum)?.map(String::from)
+        } else {
+            None
+        };
+        resolve
+            .provenance_mut()
+            .insert(id, Provenance { mirror, signature });
+    }
+    Ok(())
+}
+
+fn serialize_resolve(ws: &Workspace<'_>, resolve: &Resolve, orig: Option<&str>) -> String {
     let toml = toml::Value::try_from(resolve).unwrap();
 
     let mut out = String::new();
@@ -134,13 +371,32 @@ fn serialize_resolve(resolve: &Resolve, orig: Option<&str>) -> String {
     }
 
     if let Some(version) = toml.get("version") {
-        out.push_str(&format!("version = {}\n\n", version));
+        out.push_str(&format!("version = {}\n", version));
+        if resolve.version() >= ResolveVersion::V5 {
+            let resolver = ws.resolve_behavior().to_manifest();
+            out.push_str(&format!(
+                "resolver = \"{}\"\n",
+                resolver.as_deref().unwrap_or("1")
+            ));
+        }
+        out.push('\n');
     }
 
     let deps = toml["package"].as_array().unwrap();
     for dep in deps {
         let dep = dep.as_table().unwrap();
 
+        // The pre-release versions of `workspace.allow-prerelease` don't match
+        // the requirements of their dependents without it.
+        let name = dep["name"].as_str().unwrap();
+        let version = dep["version"].as_str().unwrap();
+        let is_prerelease = semver::Version::parse(version).map_or(false, |v| !v.pre.is_empty());
+        if is_prerelease && ws.allow_prerelease().contains(name) {
+            out.push_str(&format!(
+                "# {} {} is a pre-release, allowed by `workspace.allow-prerelease`\n",
+                name, version
+            ));
+        }
         out.push_str("[[package]]\n");
         emit_package(dep, &mut out);
     }
@@ -200,6 +456,12 @@ fn emit_package(dep: &toml::value::Table, out: &mut String) {
     if dep.contains_key("checksum") {
         out.push_str(&format!("checksum = {}\n", &dep["checksum"]));
     }
+    if dep.contains_key("mirror") {
+        out.push_str(&format!("mirror = {}\n", &dep["mirror"]));
+    }
+    if dep.contains_key("signature") {
+        out.push_str(&format!("signature = {}\n", &dep["signature"]));
+    }
 
     if let Some(s) = dep.get("dependencies") {
         let slice = s.as_array().unwrap();
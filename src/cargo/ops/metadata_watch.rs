@@ -0,0 +1,289 @@
+//! `cargo metadata --watch`, with `-Z metadata-watch`.
+//!
+//! Instead of printing the metadata once, Cargo keeps running and prints the
+//! changes to it, so that IDEs don't have to run `cargo metadata` again and
+//! parse all of its output each time a manifest changes.
+//!
+//! Cargo talks to the client over its stdin and stdout, one JSON object per
+//! line, each with a `type`:
+//!
+//! * Cargo first sends `{"type":"hello","version":1}`, then the whole
+//!   metadata with `{"type":"metadata","fingerprint":"..","metadata":{..}}`.
+//! * When the manifests of the workspace members or `Cargo.lock` change, it
+//!   sends `{"type":"delta","since":"..","fingerprint":"..",..}` with the
+//!   packages and resolve nodes that were added or changed, the ids of those
+//!   that were removed, and the other fields of the metadata that changed.
+//!   `since` is the fingerprint of the metadata the delta applies to.
+//! * The client can send `{"type":"refresh"}` to have the metadata reloaded
+//!   right away, which is always answered with a delta, even an empty one,
+//!   and `{"type":"full"}` to get the whole metadata again.
+//! * When the metadata can't be loaded, like when a manifest is invalid,
+//!   Cargo sends `{"type":"error","message":".."}` and keeps watching.
+//!
+//! Cargo stops when its stdin is closed.
+
+use std::collections::BTreeMap;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::core::Workspace;
+use crate::ops::{self, OutputMetadataOptions};
+use crate::util::{self, CargoResult, Config};
+
+/// The version of the protocol spoken with the client.
+const PROTOCOL_VERSION: u32 = 1;
+
+/// How often the manifests are checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+enum Message<'a> {
+    Hello {
+        version: u32,
+    },
+    Metadata {
+        fingerprint: &'a str,
+        metadata: &'a Value,
+    },
+    Delta {
+        since: &'a str,
+        fingerprint: &'a str,
+        packages: Vec<&'a Value>,
+        removed_packages: Vec<&'a str>,
+        resolve_nodes: Vec<&'a Value>,
+        removed_resolve_nodes: Vec<&'a str>,
+        /// The other top-level fields of the metadata that changed.
+        fields: Map<String, Value>,
+    },
+    Error {
+        message: String,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+enum Request {
+    Refresh,
+    Full,
+}
+
+/// The metadata last sent to the client.
+struct State {
+    metadata: Value,
+    fingerprint: String,
+    /// The files the metadata is loaded from, with their modification time.
+    inputs: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+/// Prints the metadata of the workspace of `manifest_path`, then its changes
+/// until stdin is closed.
+pub fn watch_metadata(
+    config: &Config,
+    manifest_path: &Path,
+    opts: &OutputMetadataOptions,
+) -> CargoResult<()> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let stdin = std::io::stdin();
+        for line in stdin.lock().lines() {
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    send(
+        config,
+        &Message::Hello {
+            version: PROTOCOL_VERSION,
+        },
+    )?;
+    let mut state = load(config, manifest_path, opts)?;
+    send_full(config, &state)?;
+
+    loop {
+        let request = match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(line) => Some(line?),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        };
+        let request = match request.as_deref().map(str::trim) {
+            None => None,
+            Some("") => continue,
+            Some(line) => match serde_json::from_str(line) {
+                Ok(request) => Some(request),
+                Err(e) => {
+                    send_error(config, format!("invalid request `{}`: {}", line, e))?;
+                    continue;
+                }
+            },
+        };
+        match request {
+            Some(Request::Full) => send_full(config, &state)?,
+            Some(Request::Refresh) => refresh(config, manifest_path, opts, &mut state, true)?,
+            None if inputs_changed(&state.inputs) => {
+                refresh(config, manifest_path, opts, &mut state, false)?
+            }
+            None => {}
+        }
+    }
+}
+
+/// Loads the metadata again, and sends its changes. An unchanged metadata is
+/// only answered with an empty delta when `always` is set.
+fn refresh(
+    config: &Config,
+    manifest_path: &Path,
+    opts: &OutputMetadataOptions,
+    state: &mut State,
+    always: bool,
+) -> CargoResult<()> {
+    let new = match load(config, manifest_path, opts) {
+        Ok(new) => new,
+        Err(e) => {
+            // Don't try again until the inputs change again.
+            state.inputs = stamp(state.inputs.iter().map(|(path, _)| path.clone()));
+            return send_error(config, format!("{:?}", e));
+        }
+    };
+    if always || new.fingerprint != state.fingerprint {
+        send_delta(config, state, &new)?;
+    }
+    *state = new;
+    Ok(())
+}
+
+fn load(config: &Config, manifest_path: &Path, opts: &OutputMetadataOptions) -> CargoResult<State> {
+    let ws = Workspace::new(manifest_path, config)?;
+    let metadata = serde_json::to_value(ops::output_metadata(&ws, opts)?)?;
+    let fingerprint = util::short_hash(&metadata.to_string());
+    Ok(State {
+        metadata,
+        fingerprint,
+        inputs: workspace_inputs(&ws),
+    })
+}
+
+/// The files the workspace `ws` is loaded from, with their modification
+/// time: the manifests of the members and of the root, and `Cargo.lock`.
+pub(super) fn workspace_inputs(ws: &Workspace<'_>) -> Vec<(PathBuf, Option<SystemTime>)> {
+    let mut inputs: Vec<_> = ws
+        .members()
+        .map(|p| p.manifest_path().to_path_buf())
+        .collect();
+    inputs.push(ws.root_manifest().to_path_buf());
+    inputs.push(ws.lockfile_path());
+    inputs.sort();
+    inputs.dedup();
+    stamp(inputs.into_iter())
+}
+
+fn stamp(paths: impl Iterator<Item = PathBuf>) -> Vec<(PathBuf, Option<SystemTime>)> {
+    paths
+        .map(|path| {
+            let mtime = path.metadata().and_then(|m| m.modified()).ok();
+            (path, mtime)
+        })
+        .collect()
+}
+
+/// Whether any of the `inputs` was modified, created or removed since they
+/// were stamped.
+pub(super) fn inputs_changed(inputs: &[(PathBuf, Option<SystemTime>)]) -> bool {
+    inputs
+        .iter()
+        .any(|(path, mtime)| path.metadata().and_then(|m| m.modified()).ok() != *mtime)
+}
+
+fn send_full(config: &Config, state: &State) -> CargoResult<()> {
+    send(
+        config,
+        &Message::Metadata {
+            fingerprint: &state.fingerprint,
+            metadata: &state.metadata,
+        },
+    )
+}
+
+fn send_delta(config: &Config, old: &State, new: &State) -> CargoResult<()> {
+    let (packages, removed_packages) =
+        diff(old.metadata.get("packages"), new.metadata.get("packages"));
+    let old_resolve = old.metadata.get("resolve");
+    let new_resolve = new.metadata.get("resolve");
+    let (resolve_nodes, removed_resolve_nodes) = diff(
+        old_resolve.and_then(|r| r.get("nodes")),
+        new_resolve.and_then(|r| r.get("nodes")),
+    );
+    let mut fields = Map::new();
+    for (key, value) in new.metadata.as_object().into_iter().flatten() {
+        let changed = match key.as_str() {
+            "packages" => false,
+            // Only the changed nodes are sent, unless the resolve appeared,
+            // disappeared or changed its root.
+            "resolve" => {
+                value.is_null() != old_resolve.map_or(true, Value::is_null)
+                    || value.get("root") != old_resolve.and_then(|r| r.get("root"))
+            }
+            _ => old.metadata.get(key) != Some(value),
+        };
+        if changed {
+            fields.insert(key.clone(), value.clone());
+        }
+    }
+    send(
+        config,
+        &Message::Delta {
+            since: &old.fingerprint,
+            fingerprint: &new.fingerprint,
+            packages,
+            removed_packages,
+            resolve_nodes,
+            removed_resolve_nodes,
+            fields,
+        },
+    )
+}
+
+/// The entries of the list `new` that are new or differ from those of `old`
+/// with the same `id`, and the ids of the entries of `old` that are gone.
+fn diff<'a>(old: Option<&'a Value>, new: Option<&'a Value>) -> (Vec<&'a Value>, Vec<&'a str>) {
+    let by_id = |list: Option<&'a Value>| -> BTreeMap<&'a str, &'a Value> {
+        list.and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| Some((entry.get("id")?.as_str()?, entry)))
+            .collect()
+    };
+    let old = by_id(old);
+    let new = by_id(new);
+    let changed = new
+        .iter()
+        .filter(|(id, entry)| old.get(*id) != Some(*entry))
+        .map(|(_, entry)| *entry)
+        .collect();
+    let removed = old
+        .keys()
+        .filter(|id| !new.contains_key(*id))
+        .cloned()
+        .collect();
+    (changed, removed)
+}
+
+fn send_error(config: &Config, message: String) -> CargoResult<()> {
+    send(config, &Message::Error { message })
+}
+
+fn send(config: &Config, message: &Message<'_>) -> CargoResult<()> {
+    let mut shell = config.shell();
+    let out = shell.out();
+    writeln!(out, "{}", serde_json::to_string(message)?)?;
+    out.flush()?;
+    Ok(())
+}
@@ -1,59 +1,135 @@
 use crate::sources::CRATES_IO_DOMAIN;
 
+pub use self::cargo_add::{add, AddOptions};
+pub use self::cargo_asm::{asm, AsmEmit, AsmOptions};
+pub use self::cargo_audits::{
+    audits_certify, audits_check, audits_exempt, audits_fetch, audits_import, audits_init,
+    CertifyOptions,
+};
+pub use self::cargo_cache::{
+    auto_gc, cache_gc, cache_info, cache_prune, cache_verify, parse_cache_duration,
+    parse_cache_size, CachePruneOptions,
+};
 pub use self::cargo_clean::{clean, CleanOptions};
 pub use self::cargo_compile::{
     compile, compile_with_exec, compile_ws, create_bcx, print, resolve_all_features, CompileOptions,
 };
 pub use self::cargo_compile::{CompileFilter, FilterRule, LibRule, Packages};
+pub use self::cargo_completions::{
+    completion_candidates, completion_script, CompletionCommand, CompletionOption, CompletionQuery,
+    CompletionShell,
+};
 pub use self::cargo_doc::{doc, DocOptions};
+pub use self::cargo_expand::expand;
+pub use self::cargo_feature::{explain_feature, ExplainFeatureOptions};
 pub use self::cargo_fetch::{fetch, FetchOptions};
+pub use self::cargo_fuzz::{fuzz_run, FuzzRunOptions};
 pub use self::cargo_generate_lockfile::generate_lockfile;
 pub use self::cargo_generate_lockfile::update_lockfile;
 pub use self::cargo_generate_lockfile::UpdateOptions;
-pub use self::cargo_install::{install, install_list};
+pub use self::cargo_git_bundle::git_bundle;
+pub use self::cargo_install::{install, install_list, InstallStaging};
 pub use self::cargo_new::{init, new, NewOptions, VersionControl};
 pub use self::cargo_output_metadata::{output_metadata, ExportInfo, OutputMetadataOptions};
 pub use self::cargo_package::{package, package_one, PackageOpts};
+pub use self::cargo_package_binary::{package_binary, BinaryArchiveFormat, BinaryPackageOpts};
+pub use self::cargo_patch::{patch_add, patch_explain, PatchAddOptions};
 pub use self::cargo_pkgid::pkgid;
+pub use self::cargo_plugin::{
+    plugin_install, plugin_list, plugin_update, restrict_plugin, PluginInstallOptions,
+};
 pub use self::cargo_read_manifest::{read_package, read_packages};
+pub use self::cargo_release::{release, ReleaseOptions};
+pub use self::cargo_report_bloat::{report_bloat, ReportBloatOptions};
+pub use self::cargo_report_build_stats::{report_build_stats, ReportBuildStatsOptions};
+pub use self::cargo_report_check_cfg::{report_check_cfg, ReportCheckCfgOptions};
+pub use self::cargo_report_licenses::{report_licenses, ReportLicensesOptions};
+pub use self::cargo_report_link_graph::{report_link_graph, ReportLinkGraphOptions};
+pub use self::cargo_report_paths::{report_paths, ReportPathsOptions};
 pub use self::cargo_run::run;
+pub use self::cargo_serve_api::serve_api;
 pub use self::cargo_test::{run_benches, run_tests, TestOptions};
 pub use self::cargo_uninstall::uninstall;
-pub use self::fix::{fix, fix_maybe_exec_rustc, FixOptions};
-pub use self::lockfile::{load_pkg_lockfile, resolve_to_string, write_pkg_lockfile};
+pub use self::cargo_validate::{validate, Severity, ValidateOptions};
+pub use self::doc_coverage::{report_doc_coverage, DocCoverage};
+pub use self::feature_matrix::{feature_combinations, run_feature_matrix};
+pub use self::fix::{edition_readiness, fix, fix_maybe_exec_rustc, FixOptions};
+pub use self::fix_backup::undo_fix;
+pub use self::lockfile::{
+    is_lockfile_up_to_date, load_conflicted_lockfile, load_pkg_lockfile, merge_lockfiles,
+    migrate_lockfile, parse_lockfile_version, resolve_to_string, write_pkg_lockfile,
+};
+pub use self::metadata_watch::watch_metadata;
 pub use self::registry::HttpTimeout;
-pub use self::registry::{configure_http_handle, http_handle, http_handle_and_timeout};
+pub use self::registry::{configure_http_handle, http_get, http_handle, http_handle_and_timeout};
+pub use self::registry::{configure_registry_handle, resolve_proxy};
 pub use self::registry::{modify_owners, yank, OwnersOptions, PublishOpts};
 pub use self::registry::{needs_custom_http_transport, registry_login, registry_logout, search};
 pub use self::registry::{publish, registry_configuration, RegistryConfig};
+pub use self::registry::{SearchOptions, SearchSort};
 pub use self::resolve::{
     add_overrides, get_resolved_packages, resolve_with_previous, resolve_ws, resolve_ws_with_opts,
     WorkspaceResolve,
 };
+pub use self::subcommand_context::{exec_with_context, SUBCOMMAND_CONTEXT_VAR};
+pub use self::target_expr::TargetExpr;
 pub use self::vendor::{vendor, VendorOptions};
+pub use self::version_bump::{bump_version, BumpVersionOptions, VersionBump};
 
+mod cargo_add;
+mod cargo_asm;
+mod cargo_audits;
+mod cargo_cache;
 mod cargo_clean;
 mod cargo_compile;
+mod cargo_completions;
 pub mod cargo_config;
 mod cargo_doc;
+mod cargo_expand;
+mod cargo_feature;
 mod cargo_fetch;
+mod cargo_fuzz;
 mod cargo_generate_lockfile;
+mod cargo_git_bundle;
 mod cargo_install;
 mod cargo_new;
 mod cargo_output_metadata;
 mod cargo_package;
+mod cargo_package_binary;
+mod cargo_patch;
 mod cargo_pkgid;
+mod cargo_plugin;
 mod cargo_read_manifest;
+mod cargo_release;
+mod cargo_report_bloat;
+mod cargo_report_build_stats;
+mod cargo_report_check_cfg;
+mod cargo_report_licenses;
+mod cargo_report_link_graph;
+mod cargo_report_paths;
 mod cargo_run;
+mod cargo_serve_api;
 mod cargo_test;
 mod cargo_uninstall;
+mod cargo_validate;
+mod changelog;
 mod common_for_install_and_uninstall;
+mod doc_coverage;
+mod feature_matrix;
 mod fix;
+mod fix_backup;
 mod lockfile;
+mod metadata_watch;
 mod registry;
 mod resolve;
+mod subcommand_context;
+mod target_expr;
+mod test_affected;
+mod test_coverage;
+pub(crate) mod test_logs;
 pub mod tree;
 mod vendor;
+mod version_bump;
 
 /// Returns true if the dependency is either git or path, false otherwise
 /// Error if a git/path dep is transitive, but has no version (registry source).
@@ -2,7 +2,7 @@ use std::collections::{BTreeMap, HashSet};
 use std::fs::File;
 use std::io::{self, BufRead};
 use std::iter::repeat;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str;
 use std::time::Duration;
 use std::{cmp, env};
@@ -10,7 +10,7 @@ use std::{cmp, env};
 use anyhow::{bail, format_err, Context as _};
 use cargo_util::paths;
 use crates_io::{self, NewCrate, NewCrateDependency, Registry};
-use curl::easy::{Easy, InfoType, SslOpt, SslVersion};
+use curl::easy::{Auth, Easy, HttpVersion, InfoType, SslOpt, SslVersion};
 use log::{log, Level};
 use percent_encoding::{percent_encode, NON_ALPHANUMERIC};
 
@@ -18,13 +18,18 @@ use crate::core::dependency::DepKind;
 use crate::core::manifest::ManifestMetadata;
 use crate::core::resolver::CliFeatures;
 use crate::core::source::Source;
-use crate::core::{Package, SourceId, Workspace};
+use crate::core::{Dependency, Package, SourceId, Workspace};
 use crate::ops;
 use crate::sources::{RegistrySource, SourceConfigMap, CRATES_IO_DOMAIN, CRATES_IO_REGISTRY};
-use crate::util::config::{self, Config, SslVersionConfig, SslVersionConfigRange};
+use crate::util::config::{
+    self, Config, ConfigRelativePath, ConfigValue, OptValue, SslVersionConfig,
+    SslVersionConfigRange,
+};
 use crate::util::errors::CargoResult;
 use crate::util::important_paths::find_root_manifest_for_wd;
+use crate::util::system_proxy::SystemProxy;
 use crate::util::validate_package_name;
+use crate::util::vcs_state::VcsCommand;
 use crate::util::IntoUrl;
 use crate::{drop_print, drop_println, version};
 
@@ -114,6 +119,7 @@ pub fn publish(ws: &Workspace<'_>, opts: &PublishOpts<'_>) -> CargoResult<()> {
             list: false,
             check_metadata: true,
             allow_dirty: opts.allow_dirty,
+            vcs_command: VcsCommand::Publish,
             to_package: ops::Packages::Default,
             targets: opts.targets.clone(),
             jobs: opts.jobs,
@@ -287,6 +293,7 @@ fn transmit(
                 license_file: license_file.clone(),
                 badges: badges.clone(),
                 links: links.clone(),
+                rust_version: pkg.rust_version().map(|v| v.to_string()),
                 v: None,
             },
             tarball,
@@ -488,7 +495,8 @@ fn registry(
     } else {
         None
     };
-    let handle = http_handle(config)?;
+    let mut handle = http_handle(config)?;
+    configure_registry_handle(config, &mut handle, sid)?;
     Ok((Registry::new_handle(api_host, token, handle), reg_cfg, sid))
 }
 
@@ -499,6 +507,39 @@ pub fn http_handle(config: &Config) -> CargoResult<Easy> {
     Ok(handle)
 }
 
+/// Fetches `url` with a GET request, which may also be a `file://` URL.
+///
+/// The request is recorded, or replayed, by the network log.
+pub fn http_get(config: &Config, url: &str) -> CargoResult<Vec<u8>> {
+    match config.network_log()? {
+        Some(log) => log.http_get(url, || http_get_inner(config, url)),
+        None => http_get_inner(config, url),
+    }
+}
+
+fn http_get_inner(config: &Config, url: &str) -> CargoResult<Vec<u8>> {
+    let mut handle = http_handle(config)?;
+    handle.get(true)?;
+    handle.url(url)?;
+    handle.follow_location(true)?;
+    let mut body = Vec::new();
+    {
+        let mut transfer = handle.transfer();
+        transfer.write_function(|buf| {
+            body.extend_from_slice(buf);
+            Ok(buf.len())
+        })?;
+        transfer
+            .perform()
+            .with_context(|| format!("failed to fetch `{}`", url))?;
+    }
+    match handle.response_code()? {
+        // file:// URLs have no response code.
+        0 | 200 => Ok(body),
+        code => bail!("failed to fetch `{}`, got {}", url, code),
+    }
+}
+
 pub fn http_handle_and_timeout(config: &Config) -> CargoResult<(Easy, HttpTimeout)> {
     if config.frozen() {
         bail!(
@@ -528,8 +569,17 @@ pub fn needs_custom_http_transport(config: &Config) -> CargoResult<bool> {
 /// Configure a libcurl http handle with the defaults options for Cargo
 pub fn configure_http_handle(config: &Config, handle: &mut Easy) -> CargoResult<HttpTimeout> {
     let http = config.http_config()?;
-    if let Some(proxy) = http_proxy(config)? {
-        handle.proxy(&proxy)?;
+    let mut proxy = http_proxy(config)?;
+    if proxy.is_none() {
+        if let Some(system) = system_proxy(config)? {
+            if let Some(url) = &system.proxy {
+                handle.noproxy(&system.no_proxy.join(","))?;
+                proxy = Some(url.clone());
+            }
+        }
+    }
+    if let Some(proxy) = &proxy {
+        handle.proxy(proxy)?;
     }
     if let Some(cainfo) = &http.cainfo {
         let cainfo = cainfo.resolve_path(config);
@@ -615,9 +665,95 @@ pub fn configure_http_handle(config: &Config, handle: &mut Easy) -> CargoResult<
         })?;
     }
 
+    configure_http_transport(config, handle, proxy.as_deref())?;
+
     HttpTimeout::new(config)
 }
 
+/// Applies the `http.version`, `http.proxy-auth` and `http.share-connections`
+/// settings of `-Z http-transport` to `handle`.
+fn configure_http_transport(
+    config: &Config,
+    handle: &mut Easy,
+    proxy: Option<&str>,
+) -> CargoResult<()> {
+    let http = config.http_config()?;
+    let settings = [
+        ("http.version", http.version.is_some()),
+        ("http.proxy-auth", http.proxy_auth.is_some()),
+        ("http.share-connections", http.share_connections.is_some()),
+    ];
+    if let Some((key, _)) = settings.iter().find(|(_, set)| *set) {
+        if !config.cli_unstable().http_transport {
+            bail!("`{}` requires `-Z http-transport`", key);
+        }
+    }
+
+    if let Some(version) = http_version(config)? {
+        handle.http_version(version)?;
+    }
+
+    if let Some(methods) = &http.proxy_auth {
+        let mut auth = Auth::new();
+        for method in methods {
+            match method.as_str() {
+                "basic" => auth.basic(true),
+                "digest" => auth.digest(true),
+                "negotiate" => auth.gssnegotiate(true),
+                "ntlm" => auth.ntlm(true),
+                _ => bail!(
+                    "invalid `http.proxy-auth` method `{}`, \
+                     choose from 'basic', 'digest', 'negotiate', 'ntlm'",
+                    method
+                ),
+            };
+        }
+        handle.proxy_auth(&auth)?;
+        // Negotiate and NTLM use the credentials of the current user when the
+        // proxy URL has none, but curl only authenticates with a user name.
+        let current_user = methods.iter().any(|m| m == "negotiate" || m == "ntlm");
+        if current_user && !proxy_has_credentials(proxy) {
+            handle.proxy_username("")?;
+            handle.proxy_password("")?;
+        }
+    }
+
+    if http.share_connections == Some(true) {
+        config.http_share()?.attach(handle)?;
+    }
+    Ok(())
+}
+
+/// The HTTP version of the requests set by `http.version`, if any.
+pub fn http_version(config: &Config) -> CargoResult<Option<HttpVersion>> {
+    let version = match config.http_config()?.version.as_deref() {
+        None => return Ok(None),
+        Some("1.1") => HttpVersion::V11,
+        Some("2") => HttpVersion::V2,
+        Some("3") => {
+            if !curl::Version::get().feature_http3() {
+                bail!("`http.version` is `3`, but the libcurl of Cargo doesn't support HTTP/3");
+            }
+            HttpVersion::V3
+        }
+        Some(version) => bail!(
+            "invalid `http.version` `{}`, choose from '1.1', '2', '3'",
+            version
+        ),
+    };
+    Ok(Some(version))
+}
+
+/// Whether the proxy, `proxy` or the one of the environment, has credentials.
+fn proxy_has_credentials(proxy: Option<&str>) -> bool {
+    let proxy = proxy.map(str::to_string).or_else(|| {
+        ["https_proxy", "HTTPS_PROXY", "http_proxy", "HTTP_PROXY"]
+            .iter()
+            .find_map(|v| env::var(v).ok())
+    });
+    proxy.map_or(false, |proxy| proxy.contains('@'))
+}
+
 #[must_use]
 pub struct HttpTimeout {
     pub dur: Duration,
@@ -668,6 +804,9 @@ fn http_proxy(config: &Config) -> CargoResult<Option<String>> {
     Ok(None)
 }
 
+/// The environment variables of the proxies picked up by libcurl.
+const PROXY_ENV_VARS: [&str; 4] = ["http_proxy", "HTTP_PROXY", "https_proxy", "HTTPS_PROXY"];
+
 /// Determine if an http proxy exists.
 ///
 /// Checks the following for existence, in order:
@@ -678,14 +817,196 @@ fn http_proxy(config: &Config) -> CargoResult<Option<String>> {
 /// * `HTTP_PROXY` env var
 /// * `https_proxy` env var
 /// * `HTTPS_PROXY` env var
+/// * the proxy of the system, with `-Z proxy-config`
 fn http_proxy_exists(config: &Config) -> CargoResult<bool> {
-    if http_proxy(config)?.is_some() {
+    if http_proxy(config)?.is_some() || PROXY_ENV_VARS.iter().any(|v| env::var(v).is_ok()) {
         Ok(true)
     } else {
-        Ok(["http_proxy", "HTTP_PROXY", "https_proxy", "HTTPS_PROXY"]
-            .iter()
-            .any(|v| env::var(v).is_ok()))
+        Ok(system_proxy(config)?.map_or(false, |system| system.proxy.is_some()))
+    }
+}
+
+/// The proxy settings of the system, used when no proxy is configured for
+/// Cargo, git or libcurl.
+fn system_proxy(config: &Config) -> CargoResult<Option<&SystemProxy>> {
+    if PROXY_ENV_VARS.iter().any(|v| env::var(v).is_ok()) {
+        return Ok(None);
     }
+    config.system_proxy()
+}
+
+/// The proxy of the requests to the registry `name`, with `-Z proxy-config`,
+/// overriding the proxy of the other requests. An empty proxy disables the
+/// proxy.
+pub fn registry_proxy(config: &Config, name: &str) -> CargoResult<OptValue<String>> {
+    let key = registry_key(name, "proxy");
+    let proxy = config.get_string(&key)?;
+    if proxy.is_some() && !config.cli_unstable().proxy_config {
+        bail!("`{}` requires `-Z proxy-config`", key);
+    }
+    Ok(proxy)
+}
+
+/// The name of the registry `source_id` in the config, `crates-io` or a key
+/// of `[registries]`, if any.
+fn registry_name(config: &Config, source_id: SourceId) -> CargoResult<Option<String>> {
+    if source_id.is_default_registry() {
+        return Ok(Some(CRATES_IO_REGISTRY.to_string()));
+    }
+    let names: Vec<String> = match config.values()?.get("registries") {
+        Some(ConfigValue::Table(registries, _)) => registries.keys().cloned().collect(),
+        _ => return Ok(None),
+    };
+    for name in names {
+        if config
+            .get_string(&format!("registries.{}.index", name))?
+            .is_none()
+        {
+            continue;
+        }
+        let id = SourceId::alt_registry(config, &name)?;
+        if id.canonical_url() == source_id.canonical_url() {
+            return Ok(Some(name));
+        }
+    }
+    Ok(None)
+}
+
+/// Configures `handle` for the requests to the registry `source_id`, with
+/// the proxy and the client certificate of the registry.
+pub fn configure_registry_handle(
+    config: &Config,
+    handle: &mut Easy,
+    source_id: SourceId,
+) -> CargoResult<()> {
+    let name = match registry_name(config, source_id)? {
+        Some(name) => name,
+        None => return Ok(()),
+    };
+    if let Some(proxy) = registry_proxy(config, &name)? {
+        handle.proxy(&proxy.val)?;
+        // The exceptions of the proxy of the system don't apply to the proxy
+        // of a registry.
+        handle.noproxy("")?;
+    }
+    configure_client_cert(config, handle, &name)
+}
+
+/// The config key of the setting `key` of the registry `name`.
+fn registry_key(name: &str, key: &str) -> String {
+    if name == CRATES_IO_REGISTRY {
+        format!("registry.{}", key)
+    } else {
+        format!("registries.{}.{}", name, key)
+    }
+}
+
+/// Configures `handle` with the client certificate of the registry `name`,
+/// for the registries requiring mutual TLS, with `-Z client-certs`.
+fn configure_client_cert(config: &Config, handle: &mut Easy, name: &str) -> CargoResult<()> {
+    let cert_key = registry_key(name, "client-cert");
+    let key_key = registry_key(name, "client-key");
+    let cert = config.get::<Option<ConfigRelativePath>>(&cert_key)?;
+    let key = config.get::<Option<ConfigRelativePath>>(&key_key)?;
+    if cert.is_none() && key.is_none() {
+        return Ok(());
+    }
+    if !config.cli_unstable().client_certs {
+        let set = if cert.is_some() { cert_key } else { key_key };
+        bail!("`{}` requires `-Z client-certs`", set);
+    }
+    let cert = match cert {
+        Some(cert) => cert,
+        None => bail!("`{}` is set without `{}`", key_key, cert_key),
+    };
+
+    // A certificate of the macOS keychain or of the Windows certificate
+    // store is named, instead of being a file.
+    let raw = cert.raw_value();
+    let cert_path = if let Some(identity) = raw.strip_prefix("keychain:") {
+        handle.ssl_cert(identity)?;
+        None
+    } else if raw.starts_with("CurrentUser\\") || raw.starts_with("LocalMachine\\") {
+        handle.ssl_cert(raw)?;
+        None
+    } else {
+        let path = cert.resolve_path(config);
+        handle.ssl_cert(&path)?;
+        handle.ssl_cert_type(cert_type(&path))?;
+        Some(path)
+    };
+    let key_path = key.map(|key| key.resolve_path(config));
+    if let Some(key) = &key_path {
+        handle.ssl_key(key)?;
+        handle.ssl_key_type(cert_type(key))?;
+    }
+
+    // The passphrase of an encrypted key, or of a PKCS#12 file, is read from
+    // the credential process of the registry.
+    let encrypted = match key_path.as_ref().or(cert_path.as_ref()) {
+        Some(path) if cert_type(path) == "P12" => Some((path, false)),
+        Some(path) => {
+            let contents = paths::read_bytes(path)?;
+            let pem = String::from_utf8_lossy(&contents);
+            pem.contains("ENCRYPTED").then(|| (path, true))
+        }
+        None => None,
+    };
+    if let Some((path, required)) = encrypted {
+        let registry = (name != CRATES_IO_REGISTRY).then(|| name);
+        let process = registry_configuration(config, registry)?.credential_process;
+        if process.is_some() || required {
+            let passphrase = config.client_key_passphrase(name, || {
+                auth::client_key_passphrase(config, process.as_ref(), name, path)
+            })?;
+            handle.key_password(&passphrase)?;
+        }
+    }
+    Ok(())
+}
+
+/// The curl type of the certificate or key `path`, by its extension.
+fn cert_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("p12") | Some("pfx") => "P12",
+        Some("der") => "DER",
+        _ => "PEM",
+    }
+}
+
+/// The proxy of the requests to the registry `registry`, or of the other
+/// requests, and where it's configured, in the order Cargo looks for it.
+/// An empty proxy disables the proxy.
+pub fn resolve_proxy(
+    config: &Config,
+    registry: Option<&str>,
+) -> CargoResult<Option<(String, String)>> {
+    if let Some(name) = registry {
+        if let Some(proxy) = registry_proxy(config, name)? {
+            let origin = format!("`{}` in {}", registry_key(name, "proxy"), proxy.definition);
+            return Ok(Some((proxy.val, origin)));
+        }
+    }
+    if let Some(proxy) = config.get_string("http.proxy")? {
+        let origin = format!("`http.proxy` in {}", proxy.definition);
+        return Ok(Some((proxy.val, origin)));
+    }
+    if let Ok(cfg) = git2::Config::open_default() {
+        if let Ok(proxy) = cfg.get_string("http.proxy") {
+            return Ok(Some((proxy, "git's `http.proxy`".to_string())));
+        }
+    }
+    for var in PROXY_ENV_VARS {
+        if let Ok(proxy) = env::var(var) {
+            return Ok(Some((proxy, format!("environment variable `{}`", var))));
+        }
+    }
+    if let Some(system) = config.system_proxy()? {
+        if let Some(proxy) = &system.proxy {
+            return Ok(Some((proxy.clone(), system.source.to_string())));
+        }
+    }
+    Ok(None)
 }
 
 pub fn registry_login(
@@ -909,13 +1230,96 @@ fn get_source_id(
     }
 }
 
-pub fn search(
-    query: &str,
-    config: &Config,
-    index: Option<String>,
-    limit: u32,
-    reg: Option<String>,
-) -> CargoResult<()> {
+/// The order of the results of `cargo search`, named like the `sort`
+/// parameter of the crates.io API.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchSort {
+    Relevance,
+    Alpha,
+    Downloads,
+    RecentDownloads,
+    RecentUpdates,
+    New,
+}
+
+impl SearchSort {
+    fn as_str(self) -> &'static str {
+        match self {
+            SearchSort::Relevance => "relevance",
+            SearchSort::Alpha => "alpha",
+            SearchSort::Downloads => "downloads",
+            SearchSort::RecentDownloads => "recent-downloads",
+            SearchSort::RecentUpdates => "recent-updates",
+            SearchSort::New => "new",
+        }
+    }
+}
+
+impl str::FromStr for SearchSort {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> CargoResult<SearchSort> {
+        match s {
+            "relevance" => Ok(SearchSort::Relevance),
+            "alpha" => Ok(SearchSort::Alpha),
+            "downloads" => Ok(SearchSort::Downloads),
+            "recent-downloads" => Ok(SearchSort::RecentDownloads),
+            "recent-updates" => Ok(SearchSort::RecentUpdates),
+            "new" => Ok(SearchSort::New),
+            other => bail!(
+                "unknown sort order `{}`, expected one of: relevance, alpha, downloads, \
+                 recent-downloads, recent-updates, new",
+                other
+            ),
+        }
+    }
+}
+
+pub struct SearchOptions {
+    pub index: Option<String>,
+    pub registry: Option<String>,
+    pub limit: u32,
+    /// Only the crates in this category, filtered by the registry.
+    pub category: Option<String>,
+    /// Only the crates with this keyword, filtered by the registry.
+    pub keyword: Option<String>,
+    /// Only the crates whose latest version is available under this license.
+    pub license: Option<String>,
+    /// Only the crates whose latest version supports this version of Rust.
+    pub rust_version: Option<semver::Version>,
+    pub sort: Option<SearchSort>,
+    /// Prints the results as JSON.
+    pub json: bool,
+}
+
+/// A result of `cargo search`, as printed with `--json`.
+#[derive(serde::Serialize)]
+struct SearchResult {
+    name: String,
+    version: String,
+    description: Option<String>,
+    downloads: Option<u64>,
+    recent_downloads: Option<u64>,
+    created_at: Option<String>,
+    updated_at: Option<String>,
+    homepage: Option<String>,
+    documentation: Option<String>,
+    repository: Option<String>,
+    /// From the search results, if the registry returns it.
+    license: Option<String>,
+    /// Only known when filtering by license or Rust version.
+    rust_version: Option<String>,
+}
+
+/// The number of results requested at a time when filtering them, the
+/// maximum of crates.io.
+const SEARCH_PAGE_SIZE: u32 = 100;
+
+/// The number of pages of results filtered at most, so a filter that matches
+/// few crates doesn't go through every crate of the registry.
+const SEARCH_MAX_PAGES: u32 = 10;
+
+pub fn search(query: &str, config: &Config, opts: &SearchOptions) -> CargoResult<()> {
     fn truncate_with_ellipsis(s: &str, max_width: usize) -> String {
         // We should truncate at grapheme-boundary and compute character-widths,
         // yet the dependencies on unicode-segmentation and unicode-width are
@@ -928,24 +1332,121 @@ pub fn search(
         prefix
     }
 
-    let (mut registry, _, source_id) = registry(config, None, index, reg, false, false)?;
-    let (crates, total_crates) = registry.search(query, limit).with_context(|| {
-        format!(
-            "failed to retrieve search results from the registry at {}",
-            registry.host()
-        )
-    })?;
+    let (mut registry, _, source_id) = registry(
+        config,
+        None,
+        opts.index.clone(),
+        opts.registry.clone(),
+        false,
+        false,
+    )?;
+    let mut params = Vec::new();
+    if let Some(category) = &opts.category {
+        params.push(("category", category.as_str()));
+    }
+    if let Some(keyword) = &opts.keyword {
+        params.push(("keyword", keyword.as_str()));
+    }
+    if let Some(sort) = opts.sort {
+        params.push(("sort", sort.as_str()));
+    }
+    let filtered = opts.license.is_some() || opts.rust_version.is_some();
+    let mut results = Vec::new();
+    let mut total_crates;
+    let mut filter = None;
+    let mut page = 1;
+    loop {
+        // The filters are applied before the results are limited, so the
+        // results are requested a page at a time until there are enough.
+        let page_param = page.to_string();
+        let (limit, params) = if filtered {
+            let mut params = params.clone();
+            params.push(("page", page_param.as_str()));
+            (SEARCH_PAGE_SIZE, params)
+        } else {
+            (opts.limit, params.clone())
+        };
+        let (crates, total) = registry
+            .search_with(query, limit, &params)
+            .with_context(|| {
+                format!(
+                    "failed to retrieve search results from the registry at {}",
+                    registry.host()
+                )
+            })?;
+        total_crates = total;
+        let count = crates.len() as u32;
+        let crates: Vec<SearchResult> = crates
+            .into_iter()
+            .map(|krate| SearchResult {
+                name: krate.name,
+                version: krate.max_version,
+                description: krate.description,
+                downloads: krate.downloads,
+                recent_downloads: krate.recent_downloads,
+                created_at: krate.created_at,
+                updated_at: krate.updated_at,
+                homepage: krate.homepage,
+                documentation: krate.documentation,
+                repository: krate.repository,
+                license: krate.license,
+                rust_version: None,
+            })
+            .collect();
+        if !filtered {
+            results = crates;
+            break;
+        }
+        let filter = filter.get_or_insert_with(|| SearchFilter::new(config, source_id));
+        for mut result in crates {
+            if filter.matches(&mut result, opts)? {
+                results.push(result);
+            }
+        }
+        if results.len() >= opts.limit as usize
+            || count < SEARCH_PAGE_SIZE
+            || page * SEARCH_PAGE_SIZE >= total_crates
+            || page >= SEARCH_MAX_PAGES
+        {
+            break;
+        }
+        page += 1;
+    }
+    results.truncate(opts.limit as usize);
+    // Registries that don't know the `sort` parameter return the results in
+    // their own order.
+    match opts.sort {
+        None | Some(SearchSort::Relevance) => {}
+        Some(SearchSort::Alpha) => results.sort_by(|a, b| a.name.cmp(&b.name)),
+        Some(SearchSort::Downloads) => results.sort_by(|a, b| b.downloads.cmp(&a.downloads)),
+        Some(SearchSort::RecentDownloads) => {
+            results.sort_by(|a, b| b.recent_downloads.cmp(&a.recent_downloads))
+        }
+        Some(SearchSort::RecentUpdates) => results.sort_by(|a, b| b.updated_at.cmp(&a.updated_at)),
+        Some(SearchSort::New) => results.sort_by(|a, b| b.created_at.cmp(&a.created_at)),
+    }
+
+    if opts.json {
+        let total = if filtered {
+            results.len() as u32
+        } else {
+            total_crates
+        };
+        let json = serde_json::json!({ "crates": results, "total": total });
+        drop_println!(config, "{}", serde_json::to_string(&json)?);
+        return Ok(());
+    }
 
-    let names = crates
+    let names = results
         .iter()
-        .map(|krate| format!("{} = \"{}\"", krate.name, krate.max_version))
+        .map(|krate| format!("{} = \"{}\"", krate.name, krate.version))
         .collect::<Vec<String>>();
 
     let description_margin = names.iter().map(|s| s.len() + 4).max().unwrap_or_default();
 
     let description_length = cmp::max(80, 128 - description_margin);
 
-    let descriptions = crates.iter().map(|krate| {
+    let descriptions = results.iter().map(|krate| {
         krate
             .description
             .as_ref()
@@ -965,6 +1466,11 @@ pub fn search(
         drop_println!(config, "{}", line);
     }
 
+    // The total of the registry doesn't account for the filters applied here.
+    if filtered {
+        return Ok(());
+    }
+    let limit = opts.limit;
     let search_max_limit = 100;
     if total_crates > limit && limit < search_max_limit {
         drop_println!(
@@ -991,3 +1497,85 @@ pub fn search(
 
     Ok(())
 }
+
+/// Filters the search results by the license and Rust version of their latest
+/// version. The license is the one of the search results, and the Rust
+/// version the one of the index of the registry, so no crate is downloaded.
+struct SearchFilter<'cfg> {
+    config: &'cfg Config,
+    source: RegistrySource<'cfg>,
+    needs_update: bool,
+}
+
+impl<'cfg> SearchFilter<'cfg> {
+    fn new(config: &'cfg Config, source_id: SourceId) -> SearchFilter<'cfg> {
+        SearchFilter {
+            config,
+            source: RegistrySource::remote(source_id, &HashSet::new(), config),
+            needs_update: true,
+        }
+    }
+
+    /// Whether `result` matches the filters of `opts`, in which case its Rust
+    /// version is filled in.
+    fn matches(&mut self, result: &mut SearchResult, opts: &SearchOptions) -> CargoResult<bool> {
+        if let Some(wanted) = &opts.license {
+            if !result
+                .license
+                .as_deref()
+                .map_or(false, |l| license_allows(l, wanted))
+            {
+                return Ok(false);
+            }
+        }
+        let dep = Dependency::parse(
+            result.name.as_str(),
+            Some(&format!("={}", result.version)),
+            self.source.source_id(),
+        )?;
+        let summary = {
+            let _lock = self.config.acquire_package_cache_lock()?;
+            if self.needs_update {
+                self.source.update()?;
+                self.needs_update = false;
+            }
+            self.source.query_vec(&dep)
+        };
+        let summary = match summary.map(|summaries| summaries.into_iter().next()) {
+            Ok(Some(summary)) => summary,
+            Ok(None) => {
+                self.config.shell().warn(format!(
+                    "skipping `{}`, version {} is not in the index",
+                    result.name, result.version
+                ))?;
+                return Ok(false);
+            }
+            Err(e) => {
+                self.config.shell().warn(format!(
+                    "skipping `{}`, its index entry could not be read: {:#}",
+                    result.name, e
+                ))?;
+                return Ok(false);
+            }
+        };
+        let rust_version = summary.rust_version().map(|v| v.to_string());
+        if let (Some(wanted), Some(rust_version)) = (&opts.rust_version, &rust_version) {
+            // Crates that don't declare a `rust-version` are kept, as Cargo
+            // builds them with any version of Rust.
+            match semver::VersionReq::parse(rust_version) {
+                Ok(req) if !req.matches(wanted) => return Ok(false),
+                _ => {}
+            }
+        }
+        result.rust_version = rust_version;
+        Ok(true)
+    }
+}
+
+/// Whether the SPDX license expression `expr`, like `MIT OR Apache-2.0`,
+/// mentions the license `wanted`. The older `MIT/Apache-2.0` syntax is
+/// understood too.
+fn license_allows(expr: &str, wanted: &str) -> bool {
+    expr.split(|c: char| c.is_whitespace() || c == '/' || c == '(' || c == ')')
+        .any(|id| id.eq_ignore_ascii_case(wanted))
+}
@@ -5,13 +5,15 @@ use crate::util::{config, CargoResult, Config};
 use anyhow::{bail, format_err, Context as _};
 use cargo_util::ProcessError;
 use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 
 enum Action {
     Get,
     Store(String),
     Erase,
+    /// Gets the passphrase of the client key at the path.
+    Passphrase(PathBuf),
 }
 
 /// Returns the token to use for the given registry.
@@ -76,6 +78,34 @@ pub(super) fn logout(
     Ok(())
 }
 
+/// Returns the passphrase of the encrypted client key `key` of the given
+/// registry, from its credential process.
+pub(super) fn client_key_passphrase(
+    config: &Config,
+    credential_process: Option<&(PathBuf, Vec<String>)>,
+    registry_name: &str,
+    key: &Path,
+) -> CargoResult<String> {
+    match credential_process {
+        Some(process) => Ok(run_command(
+            config,
+            process,
+            registry_name,
+            "",
+            Action::Passphrase(key.to_path_buf()),
+        )?
+        .unwrap()),
+        None => bail!(
+            "the client key `{}` of registry `{}` is encrypted, and no credential process \
+             is configured to provide its passphrase\n\
+             Set the `credential-process` of the registry, which is run with the \
+             `passphrase` action.",
+            key.display(),
+            registry_name
+        ),
+    }
+}
+
 fn run_command(
     config: &Config,
     process: &(PathBuf, Vec<String>),
@@ -104,12 +134,14 @@ fn run_command(
             Action::Get => {}
             Action::Store(_) => bail!(msg("log in")),
             Action::Erase => bail!(msg("log out")),
+            Action::Passphrase(_) => bail!(msg("unlock the client key")),
         }
     }
     let action_str = match action {
         Action::Get => "get",
         Action::Store(_) => "store",
         Action::Erase => "erase",
+        Action::Passphrase(_) => "passphrase",
     };
     let args: Vec<_> = args
         .iter()
@@ -125,31 +157,40 @@ fn run_command(
         .env("CARGO", config.cargo_exe()?)
         .env("CARGO_REGISTRY_NAME", name)
         .env("CARGO_REGISTRY_API_URL", api_url);
-    match action {
+    match &action {
         Action::Get => {
             cmd.stdout(Stdio::piped());
         }
+        Action::Passphrase(key) => {
+            cmd.stdout(Stdio::piped())
+                .env("CARGO_REGISTRY_CLIENT_KEY", key);
+        }
         Action::Store(_) => {
             cmd.stdin(Stdio::piped());
         }
         Action::Erase => {}
     }
     let mut child = cmd.spawn().with_context(|| {
-        let verb = match action {
-            Action::Get => "fetch",
-            Action::Store(_) => "store",
-            Action::Erase => "erase",
+        let what = match action {
+            Action::Get => "fetch authentication token",
+            Action::Store(_) => "store authentication token",
+            Action::Erase => "erase authentication token",
+            Action::Passphrase(_) => "fetch the client key passphrase",
         };
         format!(
-            "failed to execute `{}` to {} authentication token for registry `{}`",
+            "failed to execute `{}` to {} for registry `{}`",
             exe.display(),
-            verb,
+            what,
             name
         )
     })?;
     let mut token = None;
     match &action {
-        Action::Get => {
+        Action::Get | Action::Passphrase(_) => {
+            let what = match action {
+                Action::Passphrase(_) => "passphrase",
+                _ => "token",
+            };
             let mut buffer = String::new();
             log::debug!("reading into buffer");
             child
@@ -159,7 +200,8 @@ fn run_command(
                 .read_to_string(&mut buffer)
                 .with_context(|| {
                     format!(
-                        "failed to read token from registry credential process `{}`",
+                        "failed to read {} from registry credential process `{}`",
+                        what,
                         exe.display()
                     )
                 })?;
@@ -167,8 +209,9 @@ fn run_command(
                 if buffer.len() > end + 1 {
                     bail!(
                         "credential process `{}` returned more than one line of output; \
-                         expected a single token",
-                        exe.display()
+                         expected a single {}",
+                        exe.display(),
+                        what
                     );
                 }
                 buffer.truncate(end);
@@ -196,6 +239,7 @@ fn run_command(
             Action::Get => "failed to authenticate to registry",
             Action::Store(_) => "failed to store token to registry",
             Action::Erase => "failed to erase token from registry",
+            Action::Passphrase(_) => "failed to unlock the client key of registry",
         };
         return Err(ProcessError::new(
             &format!(
@@ -13,7 +13,8 @@
 use crate::core::compiler::{CompileKind, RustcTargetData};
 use crate::core::registry::{LockedPatchDependency, PackageRegistry};
 use crate::core::resolver::features::{
-    CliFeatures, FeatureOpts, FeatureResolver, ForceAllTargets, RequestedFeatures, ResolvedFeatures,
+    CliFeatures, FeatureOpts, FeatureResolver, FeaturesFor, ForceAllTargets, RequestedFeatures,
+    ResolvedFeatures,
 };
 use crate::core::resolver::{
     self, HasDevUnits, Resolve, ResolveOpts, ResolveVersion, VersionPreferences,
@@ -143,6 +144,10 @@ pub fn resolve_ws_with_opts<'cfg>(
         add_patches,
     )?;
 
+    if let Some(resolve) = &resolve {
+        annotate_dependency_overrides(ws, resolve, &resolved_with_overrides)?;
+    }
+
     let pkg_set = get_resolved_packages(&resolved_with_overrides, registry)?;
 
     let member_ids = ws
@@ -158,6 +163,9 @@ pub fn resolve_ws_with_opts<'cfg>(
         target_data,
         force_all_targets,
     )?;
+    if let Some(policy) = ws.policy() {
+        policy.check(ws, &resolved_with_overrides, &pkg_set)?;
+    }
 
     let feature_opts = FeatureOpts::new(ws, has_dev_units, force_all_targets)?;
     let resolved_features = FeatureResolver::resolve(
@@ -170,6 +178,8 @@ pub fn resolve_ws_with_opts<'cfg>(
         requested_targets,
         feature_opts,
     )?;
+    check_feature_conflicts(&pkg_set, &resolved_features)?;
+    warn_feature_overrides(ws, &resolved_with_overrides)?;
 
     let no_lib_pkgs = pkg_set.no_lib_pkgs(
         &resolved_with_overrides,
@@ -197,6 +207,126 @@ pub fn resolve_ws_with_opts<'cfg>(
     })
 }
 
+/// Prints which packages the temporary overrides of the workspace replace, as
+/// the lock file `resolve` keeps the replaced ones.
+fn annotate_dependency_overrides(
+    ws: &Workspace<'_>,
+    resolve: &Resolve,
+    resolved_with_overrides: &Resolve,
+) -> CargoResult<()> {
+    let config = ws.config();
+    for (name, path) in ws.dependency_overrides() {
+        let source_id = SourceId::for_path(path)?;
+        let id = resolved_with_overrides
+            .iter()
+            .find(|id| id.name() == *name && id.source_id() == source_id);
+        let id = match id {
+            Some(id) => id,
+            None => {
+                config.shell().warn(format!(
+                    "the override of `{}` by `{}` is not used, \
+                     no package of the workspace depends on `{}`",
+                    name,
+                    path.display(),
+                    name
+                ))?;
+                continue;
+            }
+        };
+        let mut replaced: Vec<_> = resolve
+            .iter()
+            .filter(|r| r.name() == *name && *r != id)
+            .map(|r| r.to_string())
+            .collect();
+        replaced.sort();
+        if replaced.is_empty() {
+            replaced.push(name.to_string());
+        }
+        config.shell().status(
+            "Overriding",
+            format!(
+                "{} with {} (override active, not written to {})",
+                replaced.join(", "),
+                id,
+                ws.lockfile_name()
+            ),
+        )?;
+    }
+    Ok(())
+}
+
+/// Checks that no package has two features enabled which its
+/// `[features.metadata]` table declares as conflicting.
+///
+/// This runs right after feature resolution so that invalid combinations are
+/// reported before anything is compiled.
+fn check_feature_conflicts(
+    pkg_set: &PackageSet<'_>,
+    resolved_features: &ResolvedFeatures,
+) -> CargoResult<()> {
+    let mut packages: Vec<_> = pkg_set
+        .packages()
+        .filter(|pkg| pkg.manifest().original().feature_metadata().is_some())
+        .collect();
+    packages.sort_unstable_by_key(|pkg| pkg.package_id());
+    for pkg in packages {
+        let metadata = pkg.manifest().original().feature_metadata().unwrap();
+        for features_for in [FeaturesFor::NormalOrDev, FeaturesFor::HostDep] {
+            let activated = match resolved_features
+                .activated_features_unverified(pkg.package_id(), features_for)
+            {
+                Some(activated) => activated,
+                None => continue,
+            };
+            for (name, meta) in metadata {
+                if !activated.contains(name) {
+                    continue;
+                }
+                if let Some(conflict) = meta
+                    .conflicts
+                    .iter()
+                    .flatten()
+                    .find(|conflict| activated.contains(conflict))
+                {
+                    anyhow::bail!(
+                        "package `{}` has conflicting features `{}` and `{}` enabled\n\
+                         At most one of them may be enabled, as declared in the \
+                         `[features.metadata]` table of `{}`.",
+                        pkg.package_id(),
+                        name,
+                        conflict,
+                        pkg.manifest_path().display()
+                    );
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Warns about every `[feature-overrides]` entry, since they change the
+/// features of packages behind their authors' backs.
+fn warn_feature_overrides(ws: &Workspace<'_>, resolve: &Resolve) -> CargoResult<()> {
+    for (name, o) in ws.root_feature_overrides() {
+        let msg = if resolve.iter().any(|id| id.name() == *name) {
+            format!(
+                "`[feature-overrides]` changes the features of `{}`: {}\n\
+                 This may break packages that depend on `{}`.",
+                name,
+                o.describe(),
+                name
+            )
+        } else {
+            format!(
+                "`[feature-overrides]` entry `{}` does not match any package in the dependency graph",
+                name
+            )
+        };
+        ws.config().shell().warn(msg)?;
+    }
+    Ok(())
+}
+
 fn resolve_with_registry<'cfg>(
     ws: &Workspace<'cfg>,
     registry: &mut PackageRegistry<'cfg>,
@@ -264,6 +394,13 @@ pub fn resolve_with_previous<'cfg>(
         })
         .unwrap_or_default();
 
+    if let Some(previous) = previous {
+        let unlocked = to_avoid
+            .map(|set| set.iter().map(|p| p.source_id()).collect())
+            .unwrap_or_default();
+        registry.lock_replacement_revisions(previous.metadata(), &unlocked)?;
+    }
+
     let pre_patch_keep = |p: &PackageId| {
         !to_avoid_sources.contains(&p.source_id())
             && match to_avoid {
@@ -415,6 +552,8 @@ pub fn resolve_with_previous<'cfg>(
     }
 
     if register_patches {
+        registry.override_features(ws.root_feature_overrides().clone());
+        registry.allow_prerelease(ws.allow_prerelease().clone());
         registry.lock_patches();
     }
 
@@ -456,16 +595,24 @@ pub fn resolve_with_previous<'cfg>(
         None => root_replace.to_vec(),
     };
 
+    let allowed_sources = match ws.policy() {
+        Some(policy) => policy.allowed_sources(ws.config())?,
+        None => None,
+    };
+
     ws.preload(registry);
     let mut resolved = resolver::resolve(
         &summaries,
         &replace,
         registry,
         &version_prefs,
+        ws.minimal_versions(),
         Some(ws.config()),
         ws.unstable_features()
             .require(Feature::public_dependency())
             .is_ok(),
+        ws.links_policies(),
+        allowed_sources.as_ref(),
     )?;
     resolved.register_used_patches(&registry.patches());
     if register_patches {
@@ -489,6 +636,7 @@ pub fn resolve_with_previous<'cfg>(
     if let Some(previous) = previous {
         resolved.merge_from(previous)?;
     }
+    registry.record_replacement_revisions(resolved.metadata_mut());
     Ok(resolved)
 }
 
@@ -499,6 +647,27 @@ pub fn add_overrides<'a>(
     ws: &Workspace<'a>,
 ) -> CargoResult<()> {
     let config = ws.config();
+
+    // The temporary overrides come first, so that they win over the `paths`
+    // overrides.
+    for (name, path) in ws.dependency_overrides() {
+        let id = SourceId::for_path(path)?;
+        let mut source = PathSource::new(path, id, config);
+        let pkg = source
+            .root_package()
+            .with_context(|| format!("failed to load the override of `{}`", name))?;
+        if pkg.name() != *name {
+            anyhow::bail!(
+                "the override of `{}` is the package `{}` at `{}`",
+                name,
+                pkg.name(),
+                path.display()
+            );
+        }
+        source.update()?;
+        registry.add_override(Box::new(source));
+    }
+
     let paths = match config.get_list("paths")? {
         Some(list) => list,
         None => return Ok(()),
@@ -651,6 +820,12 @@ fn register_previous_locks(
         }
         let is_ws_member = ws.is_member(&member);
         for dep in member.dependencies() {
+            let dep = if ws.allow_prerelease().contains(&dep.package_name()) {
+                dep.clone().allow_prerelease()
+            } else {
+                dep.clone()
+            };
+
             // If this dependency didn't match anything special then we may want
             // to poison the source as it may have been added. If this path
             // dependencies is **not** a workspace member, however, and it's an
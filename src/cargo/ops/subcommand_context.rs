@@ -0,0 +1,310 @@
+//! The context of external subcommands, with `-Z subcommand-context`.
+//!
+//! An external subcommand like `cargo-foo` usually has to discover the
+//! configuration and the workspace again, often by running `cargo metadata`,
+//! although the Cargo that runs it has already loaded them. With this, Cargo
+//! doesn't replace itself with the subcommand, but waits for it, and answers
+//! its queries for that context in the meantime.
+//!
+//! Cargo listens on a local TCP port, and passes `<secret>@<address>` to the
+//! subcommand in `CARGO_SUBCOMMAND_CONTEXT`, with a random secret of the
+//! invocation. On a connection, it talks one JSON object per line:
+//!
+//! * The subcommand first sends the secret on its own line. Cargo closes the
+//!   connections that don't, since any user of the host can connect.
+//! * Cargo then sends `{"version":1}`.
+//! * The subcommand sends queries like `{"query":"config"}`, each answered
+//!   with `{"query":"config","value":..}`, or `{"query":"config","error":".."}`
+//!   when it fails.
+//!
+//! The queries are:
+//!
+//! * `config`: the current directory, `CARGO_HOME`, and the configuration
+//!   values of the config files and `--config`. Registry tokens are left out.
+//! * `workspace`: the root, the target directory and the members of the
+//!   workspace.
+//! * `resolve`: the resolved dependency graph of the workspace, like the
+//!   `resolve` of `cargo metadata`.
+//!
+//! `workspace` and `resolve` are for the workspace of the current directory,
+//! unless the query has a `manifest_path`. The answers are computed once,
+//! when first asked for. Cargo serves several connections at once, without
+//! blocking on any of them, and stops when the subcommand exits.
+
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::Context as _;
+use cargo_util::{ProcessBuilder, ProcessError};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::core::resolver::CliFeatures;
+use crate::core::Workspace;
+use crate::ops::cargo_config::cv_to_json;
+use crate::ops::{self, OutputMetadataOptions};
+use crate::util::important_paths::find_root_manifest_for_wd;
+use crate::util::{CargoResult, Config};
+
+/// The environment variable with the secret and the address of the context.
+pub const SUBCOMMAND_CONTEXT_VAR: &str = "CARGO_SUBCOMMAND_CONTEXT";
+
+/// The version of the protocol spoken with the subcommand.
+const PROTOCOL_VERSION: u32 = 1;
+
+#[derive(Deserialize)]
+struct Request {
+    query: String,
+    manifest_path: Option<PathBuf>,
+}
+
+/// The answers computed so far, by query and manifest path.
+struct Context<'cfg> {
+    config: &'cfg Config,
+    answers: HashMap<(String, Option<PathBuf>), Result<Value, String>>,
+}
+
+/// A connection of the subcommand, read and written without blocking so that
+/// a client that stalls doesn't hold up the others or the wait for the
+/// subcommand.
+struct Connection {
+    stream: TcpStream,
+    /// What was received, up to the last complete line.
+    input: Vec<u8>,
+    /// What is left to send.
+    output: Vec<u8>,
+    /// Whether the client presented the secret.
+    authenticated: bool,
+    /// Whether the client closed its side of the connection.
+    eof: bool,
+}
+
+/// Runs the external subcommand `process`, and answers its queries until it
+/// exits.
+pub fn exec_with_context(config: &Config, process: &mut ProcessBuilder) -> CargoResult<()> {
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .with_context(|| "failed to bind TCP listener for the subcommand context")?;
+    listener.set_nonblocking(true)?;
+    let secret = new_secret()?;
+    process.env(
+        SUBCOMMAND_CONTEXT_VAR,
+        format!("{}@{}", secret, listener.local_addr()?),
+    );
+    let mut child = process
+        .build_command()
+        .spawn()
+        .with_context(|| format!("could not execute process {}", process))?;
+
+    let mut context = Context {
+        config,
+        answers: HashMap::new(),
+    };
+    let mut connections = Vec::new();
+    loop {
+        let mut busy = false;
+        loop {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(true)?;
+                    connections.push(Connection {
+                        stream,
+                        input: Vec::new(),
+                        output: Vec::new(),
+                        authenticated: false,
+                        eof: false,
+                    });
+                    busy = true;
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        let mut i = 0;
+        while i < connections.len() {
+            let conn = &mut connections[i];
+            let open = match conn.poll(&mut context, &secret) {
+                Ok(progress) => {
+                    busy |= progress;
+                    !(conn.eof && conn.output.is_empty())
+                }
+                Err(e) => {
+                    log::warn!("failed to serve the subcommand context: {}", e);
+                    false
+                }
+            };
+            if open {
+                i += 1;
+            } else {
+                connections.swap_remove(i);
+            }
+        }
+        if let Some(status) = child.try_wait()? {
+            if status.success() {
+                return Ok(());
+            }
+            let msg = format!("process didn't exit successfully: {}", process);
+            return Err(ProcessError::new(&msg, Some(status), None).into());
+        }
+        if !busy {
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+/// A random secret that the subcommand presents to the context, so that
+/// other users of the host can't query it.
+fn new_secret() -> CargoResult<String> {
+    let mut secret = [0; 16];
+    getrandom::fill(&mut secret)
+        .map_err(|e| anyhow::format_err!("failed to generate a random secret: {}", e))?;
+    Ok(hex::encode(secret))
+}
+
+impl Connection {
+    /// Reads the queries received so far, and sends what it can of the
+    /// answers. Returns whether anything was received or sent.
+    fn poll(&mut self, context: &mut Context<'_>, secret: &str) -> io::Result<bool> {
+        let mut progress = false;
+        let mut buf = [0; 4096];
+        while !self.eof {
+            match self.stream.read(&mut buf) {
+                Ok(0) => self.eof = true,
+                Ok(n) => self.input.extend_from_slice(&buf[..n]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+            progress = true;
+        }
+        while let Some(end) = self.input.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.input.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim();
+            if !self.authenticated {
+                if line != secret {
+                    return Err(io::Error::new(
+                        io::ErrorKind::PermissionDenied,
+                        "the client didn't present the secret of the context",
+                    ));
+                }
+                self.authenticated = true;
+                self.send(json!({ "version": PROTOCOL_VERSION }));
+            } else if !line.is_empty() {
+                let answer = context.query(line);
+                self.send(answer);
+            }
+        }
+        while !self.output.is_empty() {
+            match self.stream.write(&self.output) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => {
+                    self.output.drain(..n);
+                    progress = true;
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(progress)
+    }
+
+    fn send(&mut self, message: Value) {
+        self.output
+            .extend_from_slice(message.to_string().as_bytes());
+        self.output.push(b'\n');
+    }
+}
+
+impl<'cfg> Context<'cfg> {
+    /// Answers the query on `line`.
+    fn query(&mut self, line: &str) -> Value {
+        match serde_json::from_str::<Request>(line) {
+            Ok(req) => {
+                let key = (req.query.clone(), req.manifest_path.clone());
+                if !self.answers.contains_key(&key) {
+                    let answer = self.answer(&req).map_err(|e| format!("{:#}", e));
+                    self.answers.insert(key.clone(), answer);
+                }
+                match &self.answers[&key] {
+                    Ok(value) => json!({"query": req.query, "value": value}),
+                    Err(error) => json!({"query": req.query, "error": error}),
+                }
+            }
+            Err(e) => json!({"error": format!("invalid query `{}`: {}", line, e)}),
+        }
+    }
+
+    fn answer(&self, req: &Request) -> CargoResult<Value> {
+        let config = self.config;
+        match req.query.as_str() {
+            "config" => {
+                let mut values: serde_json::Map<_, _> = config
+                    .values()?
+                    .iter()
+                    .map(|(key, cv)| (key.clone(), cv_to_json(cv)))
+                    .collect();
+                strip_tokens(&mut values);
+                Ok(json!({
+                    "cwd": config.cwd(),
+                    "home": config.home().as_path_unlocked(),
+                    "values": values,
+                }))
+            }
+            "workspace" => {
+                let ws = self.workspace(req)?;
+                let members: Vec<_> = ws.members().map(|p| p.package_id()).collect();
+                let default_members: Vec<_> =
+                    ws.default_members().map(|p| p.package_id()).collect();
+                Ok(json!({
+                    "root": ws.root(),
+                    "root_manifest": ws.root_manifest(),
+                    "target_dir": ws.target_dir().into_path_unlocked(),
+                    "members": members,
+                    "default_members": default_members,
+                }))
+            }
+            "resolve" => {
+                let ws = self.workspace(req)?;
+                let opts = OutputMetadataOptions {
+                    cli_features: CliFeatures::new_all(false),
+                    no_deps: false,
+                    version: 1,
+                    filter_platforms: Vec::new(),
+                };
+                let metadata = serde_json::to_value(ops::output_metadata(&ws, &opts)?)?;
+                Ok(metadata["resolve"].clone())
+            }
+            query => anyhow::bail!(
+                "unknown query `{}`, expected one of: config, workspace, resolve",
+                query
+            ),
+        }
+    }
+
+    fn workspace(&self, req: &Request) -> CargoResult<Workspace<'cfg>> {
+        let manifest_path = match &req.manifest_path {
+            Some(path) => self.config.cwd().join(path),
+            None => find_root_manifest_for_wd(self.config.cwd())?,
+        };
+        Workspace::new(&manifest_path, self.config)
+    }
+}
+
+/// Removes the registry tokens from the config values.
+fn strip_tokens(values: &mut serde_json::Map<String, Value>) {
+    if let Some(Value::Object(registry)) = values.get_mut("registry") {
+        registry.remove("token");
+    }
+    if let Some(Value::Object(registries)) = values.get_mut("registries") {
+        for registry in registries.values_mut() {
+            if let Value::Object(registry) = registry {
+                registry.remove("token");
+            }
+        }
+    }
+}
@@ -0,0 +1,374 @@
+//! Filter expressions for selecting compile targets.
+//!
+//! An expression combines predicates on a target and the package it belongs
+//! to with `&`, `|`, `!` and parentheses, for example:
+//!
+//! ```text
+//! kind(bin) & !name(legacy*)
+//! package(api-*) & (kind(test) | kind(bench))
+//! ```
+//!
+//! The supported predicates are:
+//!
+//! * `kind(K)` where `K` is one of `lib`, `proc-macro`, `bin`, `example`,
+//!   `test` or `bench`.
+//! * `name(GLOB)` matches the name of the target.
+//! * `package(GLOB)` matches the name of the package.
+//! * `required-features(GLOB)` matches targets with a `required-features`
+//!   entry matching the glob.
+//! * `edition(E)` matches targets using the given edition.
+//!
+//! `!` binds tighter than `&`, which binds tighter than `|`.
+
+use std::fmt;
+use std::iter;
+use std::str::{self, FromStr};
+
+use anyhow::{bail, Context as _};
+
+use crate::core::{Edition, Package, Target, TargetKind};
+use crate::util::errors::CargoResult;
+
+/// A parsed target selection expression, as passed to `--targets`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TargetExpr {
+    Not(Box<TargetExpr>),
+    All(Vec<TargetExpr>),
+    Any(Vec<TargetExpr>),
+    Kind(TargetKindPredicate),
+    Name(glob::Pattern),
+    Package(glob::Pattern),
+    RequiredFeatures(glob::Pattern),
+    Edition(Edition),
+}
+
+/// The values accepted by the `kind(..)` predicate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TargetKindPredicate {
+    Lib,
+    ProcMacro,
+    Bin,
+    Example,
+    Test,
+    Bench,
+}
+
+#[derive(Debug, PartialEq)]
+enum Token<'a> {
+    LeftParen,
+    RightParen,
+    And,
+    Or,
+    Not,
+    Word(&'a str),
+}
+
+struct Tokenizer<'a> {
+    s: iter::Peekable<str::CharIndices<'a>>,
+    orig: &'a str,
+}
+
+struct Parser<'a> {
+    t: iter::Peekable<Tokenizer<'a>>,
+}
+
+impl TargetExpr {
+    /// Returns whether `target` of package `pkg` is selected by this
+    /// expression.
+    pub fn matches(&self, pkg: &Package, target: &Target) -> bool {
+        match self {
+            TargetExpr::Not(e) => !e.matches(pkg, target),
+            TargetExpr::All(es) => es.iter().all(|e| e.matches(pkg, target)),
+            TargetExpr::Any(es) => es.iter().any(|e| e.matches(pkg, target)),
+            TargetExpr::Kind(kind) => kind.matches(target),
+            TargetExpr::Name(pat) => pat.matches(target.name()),
+            TargetExpr::Package(pat) => pat.matches(pkg.name().as_str()),
+            TargetExpr::RequiredFeatures(pat) => target
+                .required_features()
+                .map_or(false, |rf| rf.iter().any(|f| pat.matches(f))),
+            TargetExpr::Edition(edition) => target.edition() == *edition,
+        }
+    }
+}
+
+impl TargetKindPredicate {
+    fn matches(self, target: &Target) -> bool {
+        match self {
+            TargetKindPredicate::Lib => target.is_lib(),
+            TargetKindPredicate::ProcMacro => target.proc_macro(),
+            TargetKindPredicate::Bin => target.is_bin(),
+            TargetKindPredicate::Example => target.is_example(),
+            TargetKindPredicate::Test => *target.kind() == TargetKind::Test,
+            TargetKindPredicate::Bench => *target.kind() == TargetKind::Bench,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            TargetKindPredicate::Lib => "lib",
+            TargetKindPredicate::ProcMacro => "proc-macro",
+            TargetKindPredicate::Bin => "bin",
+            TargetKindPredicate::Example => "example",
+            TargetKindPredicate::Test => "test",
+            TargetKindPredicate::Bench => "bench",
+        }
+    }
+}
+
+impl FromStr for TargetKindPredicate {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> CargoResult<TargetKindPredicate> {
+        Ok(match s {
+            "lib" => TargetKindPredicate::Lib,
+            "proc-macro" => TargetKindPredicate::ProcMacro,
+            "bin" => TargetKindPredicate::Bin,
+            "example" => TargetKindPredicate::Example,
+            "test" => TargetKindPredicate::Test,
+            "bench" => TargetKindPredicate::Bench,
+            _ => bail!(
+                "unknown target kind `{}`, expected one of \
+                 `lib`, `proc-macro`, `bin`, `example`, `test` or `bench`",
+                s
+            ),
+        })
+    }
+}
+
+impl FromStr for TargetExpr {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> CargoResult<TargetExpr> {
+        let parse = || {
+            let mut p = Parser {
+                t: Tokenizer {
+                    s: s.char_indices().peekable(),
+                    orig: s,
+                }
+                .peekable(),
+            };
+            let e = p.expr()?;
+            if let Some(token) = p.t.next() {
+                bail!("unexpected {} after the end of the expression", token?);
+            }
+            Ok(e)
+        };
+        parse().with_context(|| format!("failed to parse target expression `{}`", s))
+    }
+}
+
+impl fmt::Display for TargetExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |f: &mut fmt::Formatter<'_>, es: &[TargetExpr], sep: &str| {
+            write!(f, "(")?;
+            for (i, e) in es.iter().enumerate() {
+                if i > 0 {
+                    write!(f, " {} ", sep)?;
+                }
+                write!(f, "{}", e)?;
+            }
+            write!(f, ")")
+        };
+        match self {
+            TargetExpr::Not(e) => write!(f, "!{}", e),
+            TargetExpr::All(es) => join(f, es, "&"),
+            TargetExpr::Any(es) => join(f, es, "|"),
+            TargetExpr::Kind(kind) => write!(f, "kind({})", kind.as_str()),
+            TargetExpr::Name(pat) => write!(f, "name({})", pat),
+            TargetExpr::Package(pat) => write!(f, "package({})", pat),
+            TargetExpr::RequiredFeatures(pat) => write!(f, "required-features({})", pat),
+            TargetExpr::Edition(edition) => write!(f, "edition({})", edition),
+        }
+    }
+}
+
+impl<'a> Parser<'a> {
+    fn expr(&mut self) -> CargoResult<TargetExpr> {
+        let mut es = vec![self.and()?];
+        while self.r#try(&Token::Or)? {
+            es.push(self.and()?);
+        }
+        Ok(if es.len() == 1 {
+            es.pop().unwrap()
+        } else {
+            TargetExpr::Any(es)
+        })
+    }
+
+    fn and(&mut self) -> CargoResult<TargetExpr> {
+        let mut es = vec![self.unary()?];
+        while self.r#try(&Token::And)? {
+            es.push(self.unary()?);
+        }
+        Ok(if es.len() == 1 {
+            es.pop().unwrap()
+        } else {
+            TargetExpr::All(es)
+        })
+    }
+
+    fn unary(&mut self) -> CargoResult<TargetExpr> {
+        match self.t.next().transpose()? {
+            Some(Token::Not) => Ok(TargetExpr::Not(Box::new(self.unary()?))),
+            Some(Token::LeftParen) => {
+                let e = self.expr()?;
+                self.eat(&Token::RightParen)?;
+                Ok(e)
+            }
+            Some(Token::Word(name)) => {
+                self.eat(&Token::LeftParen)?;
+                let arg = match self.t.next().transpose()? {
+                    Some(Token::Word(arg)) => arg,
+                    Some(token) => bail!("expected an argument for `{}`, found {}", name, token),
+                    None => bail!("expected an argument for `{}`, found end of input", name),
+                };
+                self.eat(&Token::RightParen)?;
+                predicate(name, arg)
+            }
+            Some(token) => bail!("expected a predicate, found {}", token),
+            None => bail!("expected a predicate, found end of input"),
+        }
+    }
+
+    fn r#try(&mut self, token: &Token<'a>) -> CargoResult<bool> {
+        match self.t.peek() {
+            Some(Ok(t)) if t == token => {}
+            Some(Err(_)) => return Err(self.t.next().unwrap().unwrap_err()),
+            _ => return Ok(false),
+        }
+        self.t.next();
+        Ok(true)
+    }
+
+    fn eat(&mut self, token: &Token<'a>) -> CargoResult<()> {
+        match self.t.next().transpose()? {
+            Some(ref t) if t == token => Ok(()),
+            Some(t) => bail!("expected {}, found {}", token, t),
+            None => bail!("expected {}, found end of input", token),
+        }
+    }
+}
+
+fn predicate(name: &str, arg: &str) -> CargoResult<TargetExpr> {
+    let pattern = || {
+        glob::Pattern::new(arg).with_context(|| format!("cannot build glob pattern from `{}`", arg))
+    };
+    Ok(match name {
+        "kind" => TargetExpr::Kind(arg.parse()?),
+        "name" => TargetExpr::Name(pattern()?),
+        "package" => TargetExpr::Package(pattern()?),
+        "required-features" => TargetExpr::RequiredFeatures(pattern()?),
+        "edition" => TargetExpr::Edition(arg.parse()?),
+        _ => bail!(
+            "unknown predicate `{}`, expected one of \
+             `kind`, `name`, `package`, `required-features` or `edition`",
+            name
+        ),
+    })
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = CargoResult<Token<'a>>;
+
+    fn next(&mut self) -> Option<CargoResult<Token<'a>>> {
+        loop {
+            match self.s.next() {
+                Some((_, ' ')) | Some((_, '\t')) => {}
+                Some((_, '(')) => return Some(Ok(Token::LeftParen)),
+                Some((_, ')')) => return Some(Ok(Token::RightParen)),
+                Some((_, '&')) => return Some(Ok(Token::And)),
+                Some((_, '|')) => return Some(Ok(Token::Or)),
+                Some((_, '!')) => return Some(Ok(Token::Not)),
+                Some((start, ch)) if is_word_char(ch) => {
+                    while let Some(&(end, ch)) = self.s.peek() {
+                        if !is_word_char(ch) {
+                            return Some(Ok(Token::Word(&self.orig[start..end])));
+                        }
+                        self.s.next();
+                    }
+                    return Some(Ok(Token::Word(&self.orig[start..])));
+                }
+                Some((_, ch)) => {
+                    return Some(Err(anyhow::format_err!("unexpected character `{}`", ch)))
+                }
+                None => return None,
+            }
+        }
+    }
+}
+
+fn is_word_char(ch: char) -> bool {
+    ch.is_alphanumeric() || matches!(ch, '_' | '-' | '*' | '?' | '[' | ']' | '.')
+}
+
+impl fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::LeftParen => write!(f, "`(`"),
+            Token::RightParen => write!(f, "`)`"),
+            Token::And => write!(f, "`&`"),
+            Token::Or => write!(f, "`|`"),
+            Token::Not => write!(f, "`!`"),
+            Token::Word(w) => write!(f, "`{}`", w),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TargetExpr;
+
+    fn roundtrip(s: &str) -> String {
+        s.parse::<TargetExpr>().unwrap().to_string()
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(
+            roundtrip("kind(bin) & !name(legacy*)"),
+            "(kind(bin) & !name(legacy*))"
+        );
+        assert_eq!(
+            roundtrip("kind(bin) | kind(test) & package(api-*)"),
+            "(kind(bin) | (kind(test) & package(api-*)))"
+        );
+        assert_eq!(
+            roundtrip("(kind(bin) | kind(test)) & edition(2018)"),
+            "((kind(bin) | kind(test)) & edition(2018))"
+        );
+        assert_eq!(roundtrip("!!kind(lib)"), "!!kind(lib)");
+    }
+
+    #[test]
+    fn errors() {
+        let err = |s: &str| {
+            let e = s.parse::<TargetExpr>().unwrap_err();
+            format!("{:#}", e)
+        };
+        assert_eq!(
+            err("kind(binary)"),
+            "failed to parse target expression `kind(binary)`: unknown target kind `binary`, \
+             expected one of `lib`, `proc-macro`, `bin`, `example`, `test` or `bench`"
+        );
+        assert_eq!(
+            err("kind(bin) &"),
+            "failed to parse target expression `kind(bin) &`: \
+             expected a predicate, found end of input"
+        );
+        assert_eq!(
+            err("kind(bin) name(x)"),
+            "failed to parse target expression `kind(bin) name(x)`: \
+             unexpected `name` after the end of the expression"
+        );
+        assert_eq!(
+            err("color(red)"),
+            "failed to parse target expression `color(red)`: unknown predicate `color`, \
+             expected one of `kind`, `name`, `package`, `required-features` or `edition`"
+        );
+        assert_eq!(
+            err("kind(bin) && x"),
+            "failed to parse target expression `kind(bin) && x`: \
+             expected a predicate, found `&`"
+        );
+    }
+}
@@ -0,0 +1,114 @@
+//! Support for `cargo test --affected`, which only runs the test binaries
+//! whose inputs changed since they last passed.
+//!
+//! The inputs of a test binary are the binary itself and the source files
+//! listed in the dep-info file rustc writes next to it. Changes to
+//! dependencies are covered by the binary, since it is relinked whenever one
+//! of them is rebuilt. The modification times of the inputs of every binary
+//! that passed are stored in the target directory, along with the arguments
+//! it was run with.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::core::compiler::parse_rustc_dep_info;
+use crate::core::Workspace;
+use crate::util::errors::CargoResult;
+use cargo_util::paths;
+use serde::{Deserialize, Serialize};
+
+const AFFECTED_FILE: &str = ".test-affected.json";
+
+/// The recorded inputs of the test binaries that passed.
+pub struct AffectedTests {
+    /// Where the state is stored.
+    path: PathBuf,
+    /// The workspace root, which relative paths in dep-info files start from.
+    root: PathBuf,
+    /// Maps the path of a test binary to the inputs of its last passing run.
+    passed: BTreeMap<PathBuf, TestInputs>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq)]
+struct TestInputs {
+    args: Vec<String>,
+    /// Modification times, as seconds and nanoseconds since the Unix epoch.
+    files: BTreeMap<PathBuf, (i64, u32)>,
+}
+
+impl AffectedTests {
+    /// Loads the state of previous runs. A missing or unreadable state file
+    /// is treated as if no test had ever passed.
+    pub fn load(ws: &Workspace<'_>) -> AffectedTests {
+        let path = ws.target_dir().as_path_unlocked().join(AFFECTED_FILE);
+        let passed = paths::read(&path)
+            .ok()
+            .and_then(|contents| match serde_json::from_str(&contents) {
+                Ok(passed) => Some(passed),
+                Err(e) => {
+                    log::debug!("ignoring invalid `{}`: {}", path.display(), e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+        AffectedTests {
+            path,
+            root: ws.root().to_path_buf(),
+            passed,
+        }
+    }
+
+    /// Returns `true` if the test binary at `exe` already passed with the
+    /// same arguments and none of its inputs changed since.
+    pub fn is_unchanged(&self, exe: &Path, args: &[&str]) -> bool {
+        match (self.passed.get(exe), self.inputs(exe, args)) {
+            (Some(previous), Some(current)) => *previous == current,
+            _ => false,
+        }
+    }
+
+    /// Records the current inputs of a test binary that passed.
+    pub fn record_pass(&mut self, exe: &Path, args: &[&str]) {
+        match self.inputs(exe, args) {
+            Some(inputs) => {
+                self.passed.insert(exe.to_path_buf(), inputs);
+            }
+            None => {
+                self.passed.remove(exe);
+            }
+        }
+    }
+
+    /// Forgets a test binary, so it is run again next time.
+    pub fn record_fail(&mut self, exe: &Path) {
+        self.passed.remove(exe);
+    }
+
+    pub fn save(&self) -> CargoResult<()> {
+        // Binaries that were removed by `cargo clean` or a rebuild with a
+        // different hash are never run again.
+        let passed: BTreeMap<_, _> = self.passed.iter().filter(|(exe, _)| exe.exists()).collect();
+        paths::create_dir_all(self.path.parent().unwrap())?;
+        paths::write(&self.path, serde_json::to_string(&passed)?)
+    }
+
+    /// Collects the inputs of the test binary at `exe`, or `None` if they
+    /// can't be determined, in which case the binary is always run.
+    fn inputs(&self, exe: &Path, args: &[&str]) -> Option<TestInputs> {
+        let dep_info = parse_rustc_dep_info(&exe.with_extension("d")).ok()?;
+        let mut files = BTreeMap::new();
+        for file in dep_info
+            .files
+            .iter()
+            .map(|f| self.root.join(f))
+            .chain([exe.to_path_buf()])
+        {
+            let mtime = paths::mtime(&file).ok()?;
+            files.insert(file, (mtime.unix_seconds(), mtime.nanoseconds()));
+        }
+        Some(TestInputs {
+            args: args.iter().map(|s| s.to_string()).collect(),
+            files,
+        })
+    }
+}
@@ -0,0 +1,224 @@
+//! Support for `cargo test --coverage`, which collects source-based code
+//! coverage of the local packages while running their tests.
+//!
+//! The local packages are compiled with `-C instrument-coverage`, except for
+//! build scripts and proc-macros, which only run at build time. Every test
+//! binary writes its profile to `target/coverage/profraw`, and once all of
+//! them passed, the profiles are merged with `llvm-profdata` and turned into
+//! an lcov file and an HTML report with `llvm-cov`. Doc tests are
+//! instrumented too with a nightly toolchain, as that requires unstable
+//! rustdoc flags, and their binaries are kept for the report. Otherwise a note
+//! says they don't count towards the coverage.
+
+use std::env;
+use std::path::{Path, PathBuf};
+
+use crate::core::compiler::Compilation;
+use crate::core::Workspace;
+use crate::util::errors::CargoResult;
+use crate::util::Config;
+use anyhow::{bail, Context as _};
+use cargo_util::{paths, ProcessBuilder};
+
+/// The coverage data of one `cargo test --coverage` run.
+pub struct Coverage {
+    /// `target/coverage`.
+    dir: PathBuf,
+    /// Whether rustdoc accepts the unstable flags instrumenting doc tests.
+    instrument_doctests: bool,
+}
+
+impl Coverage {
+    /// Prepares the coverage directory, removing the profiles of previous
+    /// runs.
+    pub fn new(ws: &Workspace<'_>) -> CargoResult<Coverage> {
+        let dir = ws.target_dir().as_path_unlocked().join("coverage");
+        for stale in ["profraw", "doctests"] {
+            if dir.join(stale).exists() {
+                paths::remove_dir_all(dir.join(stale))?;
+            }
+        }
+        paths::create_dir_all(dir.join("profraw"))?;
+        let rustc = ws.config().load_global_rustc(Some(ws))?;
+        let instrument_doctests =
+            rustc.verbose_version.contains("-nightly") || rustc.verbose_version.contains("-dev");
+        Ok(Coverage {
+            dir,
+            instrument_doctests,
+        })
+    }
+
+    /// Makes the test binary run by `cmd` write its profile to the coverage
+    /// directory.
+    pub fn configure(&self, cmd: &mut ProcessBuilder) {
+        // `%p` and `%m` keep the profiles of concurrent processes and of
+        // different binaries apart.
+        cmd.env(
+            "LLVM_PROFILE_FILE",
+            self.dir.join("profraw").join("%p-%m.profraw"),
+        );
+    }
+
+    /// Makes the rustdoc command `cmd` instrument the doc tests of the crate
+    /// `crate_name`, and keep their binaries for the report. Returns `false`
+    /// if the toolchain can't instrument them.
+    pub fn configure_doctests(&self, cmd: &mut ProcessBuilder, crate_name: &str) -> bool {
+        if !self.instrument_doctests {
+            return false;
+        }
+        cmd.arg("-C").arg("instrument-coverage");
+        cmd.arg("-Z").arg("unstable-options");
+        cmd.arg("--persist-doctests")
+            .arg(self.dir.join("doctests").join(crate_name));
+        self.configure(cmd);
+        true
+    }
+
+    /// Merges the profiles and writes the reports.
+    pub fn report(&self, ws: &Workspace<'_>, compilation: &Compilation<'_>) -> CargoResult<()> {
+        let config = ws.config();
+        let bindir = compilation.sysroot_host_bindir();
+        let profdata_tool = find_tool(&bindir, "llvm-profdata", "LLVM_PROFDATA")?;
+        let cov_tool = find_tool(&bindir, "llvm-cov", "LLVM_COV")?;
+
+        let mut profraws = Vec::new();
+        let profraw_dir = self.dir.join("profraw");
+        let entries = std::fs::read_dir(&profraw_dir)
+            .with_context(|| format!("failed to read `{}`", profraw_dir.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().map_or(false, |ext| ext == "profraw") {
+                profraws.push(path);
+            }
+        }
+        if profraws.is_empty() {
+            bail!(
+                "no coverage data was written to `{}`",
+                profraw_dir.display()
+            );
+        }
+        profraws.sort();
+
+        let profdata = self.dir.join("coverage.profdata");
+        ProcessBuilder::new(&profdata_tool)
+            .arg("merge")
+            .arg("-sparse")
+            .args(&profraws)
+            .arg("-o")
+            .arg(&profdata)
+            .exec_with_output()
+            .with_context(|| "failed to merge the coverage data")?;
+
+        let doctests = doctest_binaries(&self.dir.join("doctests"))?;
+        let mut objects: Vec<&Path> = compilation
+            .tests
+            .iter()
+            .chain(&compilation.binaries)
+            .map(|output| output.path.as_path())
+            .chain(doctests.iter().map(|path| path.as_path()))
+            .collect();
+        objects.sort();
+        objects.dedup();
+        let mut report = ProcessBuilder::new(&cov_tool);
+        report
+            .arg(format!("-instr-profile={}", profdata.display()))
+            .arg(format!("-ignore-filename-regex={}", ignored_files(config)));
+        for (i, object) in objects.iter().enumerate() {
+            if i > 0 {
+                report.arg("-object");
+            }
+            report.arg(object);
+        }
+
+        let lcov = self.dir.join("lcov.info");
+        let output = report
+            .clone()
+            .arg("export")
+            .arg("-format=lcov")
+            .exec_with_output()
+            .with_context(|| "failed to export the coverage report")?;
+        paths::write(&lcov, &output.stdout)?;
+
+        let html = self.dir.join("html");
+        report
+            .arg("show")
+            .arg("-format=html")
+            .arg(format!("-output-dir={}", html.display()))
+            .exec_with_output()
+            .with_context(|| "failed to write the HTML coverage report")?;
+
+        let cwd = config.cwd();
+        let display = |path: &Path| path.strip_prefix(cwd).unwrap_or(path).display().to_string();
+        config.shell().status(
+            "Coverage",
+            format!(
+                "report written to `{}` and `{}`",
+                display(&lcov),
+                display(&html.join("index.html"))
+            ),
+        )?;
+        Ok(())
+    }
+}
+
+/// Finds the binaries of the doc tests persisted in `dir`, one directory per
+/// doc test.
+fn doctest_binaries(dir: &Path) -> CargoResult<Vec<PathBuf>> {
+    let binary = format!("rust_out{}", env::consts::EXE_SUFFIX);
+    let mut binaries = Vec::new();
+    if !dir.exists() {
+        return Ok(binaries);
+    }
+    for entry in walkdir::WalkDir::new(dir) {
+        let entry = entry?;
+        if entry.file_type().is_file() && entry.file_name() == binary.as_str() {
+            binaries.push(entry.into_path());
+        }
+    }
+    Ok(binaries)
+}
+
+/// Finds one of the LLVM tools, which can be overridden with the environment
+/// variable `env_var`.
+fn find_tool(bindir: &Path, name: &str, env_var: &str) -> CargoResult<PathBuf> {
+    if let Some(path) = env::var_os(env_var) {
+        return Ok(PathBuf::from(path));
+    }
+    let path = bindir.join(format!("{}{}", name, env::consts::EXE_SUFFIX));
+    if path.exists() {
+        return Ok(path);
+    }
+    bail!(
+        "could not find `{}`, which is needed to generate coverage reports\n\
+         Install it with `rustup component add llvm-tools-preview`, \
+         or set the `{}` environment variable to its path.",
+        name,
+        env_var
+    )
+}
+
+/// Returns a regex matching the source files that are not part of the
+/// report: the standard library and packages from registries or git.
+fn ignored_files(config: &Config) -> String {
+    let home = config
+        .home()
+        .as_path_unlocked()
+        .to_string_lossy()
+        .replace('\\', "/");
+    format!(
+        "^/rustc/|{}|{}",
+        escape_regex(&format!("{}/registry/", home)),
+        escape_regex(&format!("{}/git/", home))
+    )
+}
+
+fn escape_regex(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if "\\.+*?()|[]{}^$".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
@@ -0,0 +1,236 @@
+//! Support for `cargo test --no-capture-streaming`, which routes the output
+//! of each test binary, and of the doc tests of each library, into its own
+//! log file instead of the terminal, so that the test binaries can run
+//! concurrently.
+//!
+//! The logs are written to `target/test-logs/`, along with an `index.json`
+//! describing every test binary that ran. While a binary runs, the lines in
+//! which libtest reports a failure are still printed, so failures show up
+//! live without the noise of passing tests.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Child;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::core::compiler::Unit;
+use crate::core::Workspace;
+use crate::drop_println;
+use crate::util::errors::CargoResult;
+use crate::util::Config;
+use cargo_util::paths;
+use serde::Serialize;
+
+const INDEX_FILE: &str = "index.json";
+
+/// How long the output is still read once the test binary exited. A process
+/// it spawned may keep the pipes open indefinitely.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The logs of the test binaries run by one `cargo test` invocation.
+pub struct TestLogs {
+    dir: PathBuf,
+    entries: Vec<LogEntry>,
+}
+
+#[derive(Serialize)]
+struct LogEntry {
+    package: String,
+    target: String,
+    kind: String,
+    executable: PathBuf,
+    log: PathBuf,
+    success: bool,
+    attempts: u32,
+    duration: f64,
+}
+
+/// The log of a single test binary.
+pub struct TestLog {
+    path: PathBuf,
+    file: File,
+    lines: Option<Receiver<String>>,
+}
+
+impl TestLogs {
+    pub fn new(ws: &Workspace<'_>) -> CargoResult<TestLogs> {
+        let dir = ws.target_dir().as_path_unlocked().join("test-logs");
+        paths::create_dir_all(&dir)?;
+        Ok(TestLogs {
+            dir,
+            entries: Vec::new(),
+        })
+    }
+
+    /// Creates the log `name`, named after the test binary, replacing the log
+    /// of a previous run.
+    pub fn create(&self, name: &str) -> CargoResult<TestLog> {
+        let path = self.dir.join(format!("{}.log", name));
+        let file = File::create(&path)?;
+        Ok(TestLog {
+            path,
+            file,
+            lines: None,
+        })
+    }
+
+    /// Adds a test binary that finished running to the index. `kind` is the
+    /// kind of its target, or `doc-test`, with `exe` being rustdoc.
+    pub fn record(
+        &mut self,
+        unit: &Unit,
+        kind: &str,
+        exe: &Path,
+        log: &TestLog,
+        success: bool,
+        attempts: u32,
+        duration: Duration,
+    ) {
+        self.entries.push(LogEntry {
+            package: unit.pkg.package_id().to_string(),
+            target: unit.target.name().to_string(),
+            kind: kind.to_string(),
+            executable: exe.to_path_buf(),
+            log: log.path.clone(),
+            success,
+            attempts,
+            duration: duration.as_secs_f64(),
+        });
+    }
+
+    pub fn write_index(&self) -> CargoResult<()> {
+        #[derive(Serialize)]
+        struct Index<'a> {
+            tests: &'a [LogEntry],
+        }
+        let index = serde_json::to_string_pretty(&Index {
+            tests: &self.entries,
+        })?;
+        paths::write(self.dir.join(INDEX_FILE), index)
+    }
+}
+
+impl TestLog {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes a line to the log that separates the attempts of a retried
+    /// test binary.
+    pub fn start_attempt(&mut self, attempt: u32) -> CargoResult<()> {
+        if attempt > 1 {
+            writeln!(self.file, "\n--- attempt {} ---\n", attempt)?;
+        }
+        Ok(())
+    }
+
+    /// Starts reading the output of `child`, whose stdout and stderr must be
+    /// piped. Both streams are multiplexed into the log line by line, lossily
+    /// decoded as UTF-8, so that the pipes are drained whatever the output.
+    pub fn capture(&mut self, child: &mut Child) {
+        let (tx, rx) = mpsc::channel();
+        let streams: [Option<Box<dyn Read + Send>>; 2] = [
+            child
+                .stdout
+                .take()
+                .map(|s| Box::new(s) as Box<dyn Read + Send>),
+            child
+                .stderr
+                .take()
+                .map(|s| Box::new(s) as Box<dyn Read + Send>),
+        ];
+        for stream in streams.into_iter().flatten() {
+            let tx = tx.clone();
+            thread::spawn(move || {
+                let mut stream = BufReader::new(stream);
+                let mut buf = Vec::new();
+                loop {
+                    buf.clear();
+                    match stream.read_until(b'\n', &mut buf) {
+                        Ok(0) => break,
+                        Ok(_) => {}
+                        Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                        Err(_) => break,
+                    }
+                    if buf.ends_with(b"\n") {
+                        buf.pop();
+                        if buf.ends_with(b"\r") {
+                            buf.pop();
+                        }
+                    }
+                    let line = String::from_utf8_lossy(&buf).into_owned();
+                    if tx.send(line).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        self.lines = Some(rx);
+    }
+
+    /// Writes the output received so far to the log, waiting up to `wait`
+    /// for more. Returns `false` once all output has been read.
+    pub fn pump(&mut self, config: &Config, wait: Duration) -> CargoResult<bool> {
+        let rx = match &self.lines {
+            Some(rx) => rx,
+            None => return Ok(false),
+        };
+        let mut line = match rx.recv_timeout(wait) {
+            Ok(line) => line,
+            Err(RecvTimeoutError::Timeout) => return Ok(true),
+            Err(RecvTimeoutError::Disconnected) => {
+                self.lines = None;
+                return Ok(false);
+            }
+        };
+        loop {
+            write_line(&mut self.file, config, &line)?;
+            line = match rx.try_recv() {
+                Ok(line) => line,
+                Err(_) => return Ok(true),
+            };
+        }
+    }
+
+    /// Writes a line of output to the log, printing it too if it reports a
+    /// failure.
+    pub fn write_line(&mut self, config: &Config, line: &str) -> CargoResult<()> {
+        write_line(&mut self.file, config, line)
+    }
+
+    /// Writes the remaining output to the log, once the process exited.
+    ///
+    /// The output is read for up to `DRAIN_TIMEOUT`, after which the reader
+    /// threads are left behind, as the pipes may be held open by a process
+    /// the test binary spawned.
+    pub fn finish(&mut self, config: &Config) -> CargoResult<()> {
+        let deadline = Instant::now() + DRAIN_TIMEOUT;
+        loop {
+            let now = Instant::now();
+            if now >= deadline || !self.pump(config, deadline - now)? {
+                break;
+            }
+        }
+        self.lines = None;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+fn write_line(file: &mut File, config: &Config, line: &str) -> CargoResult<()> {
+    writeln!(file, "{}", line)?;
+    if is_failure_line(line) {
+        drop_println!(config, "{}", line);
+    }
+    Ok(())
+}
+
+/// Whether libtest reports a failure in `line`.
+fn is_failure_line(line: &str) -> bool {
+    (line.starts_with("test ") && line.ends_with(" ... FAILED"))
+        || (line.starts_with("thread '") && line.contains(" panicked at "))
+        || line.starts_with("test result: FAILED")
+}
@@ -0,0 +1,548 @@
+//! Bumping the versions of workspace members, for `cargo version bump` and
+//! `cargo release`.
+//!
+//! The manifests are edited as text, so that their formatting and comments
+//! are kept: only the string of `package.version`, and the `version` of the
+//! path dependencies on a bumped package, are replaced. This includes the
+//! dependencies in `[workspace.dependencies]`.
+
+use std::fmt;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context as _};
+use cargo_util::paths;
+use semver::{BuildMetadata, Prerelease, Version};
+
+use crate::core::Workspace;
+use crate::drop_print;
+use crate::ops::{self, Packages};
+use crate::util::CargoResult;
+
+pub struct BumpVersionOptions {
+    /// The package to bump, or `None` for the current package.
+    pub package: Option<String>,
+    pub bump: VersionBump,
+    /// Prints the changes to the manifests as a diff instead of writing them.
+    pub dry_run: bool,
+}
+
+/// Bumps the version of a member of the workspace, and the requirements of
+/// the other members on it.
+pub fn bump_version(ws: &Workspace<'_>, opts: &BumpVersionOptions) -> CargoResult<()> {
+    let config = ws.config();
+    let pkg = match &opts.package {
+        Some(spec) => {
+            let mut pkgs = Packages::Packages(vec![spec.clone()]).get_packages(ws)?;
+            if pkgs.len() > 1 {
+                bail!("`{}` matches more than one package to bump", spec);
+            }
+            pkgs.pop().unwrap()
+        }
+        None => ws.current()?,
+    };
+    let to = opts.bump.apply(pkg.version());
+    if to <= *pkg.version() {
+        bail!(
+            "cannot bump {} v{} to v{}, as it is not newer",
+            pkg.name(),
+            pkg.version(),
+            to
+        );
+    }
+    config.shell().status(
+        "Bumping",
+        format!("{} v{} -> v{}", pkg.name(), pkg.version(), to),
+    )?;
+    let edits = edit_manifests(ws, &[(&pkg.name(), &to)])?;
+
+    if opts.dry_run {
+        for edit in &edits {
+            let path = edit.path.strip_prefix(ws.root()).unwrap_or(&edit.path);
+            let diff =
+                super::fix::unified_diff(&path.to_string_lossy(), &edit.original, &edit.new)?;
+            drop_print!(config, "{}", diff);
+        }
+        config
+            .shell()
+            .warn("aborting version bump due to dry run")?;
+        return Ok(());
+    }
+    for edit in edits {
+        paths::write(&edit.path, edit.new)?;
+    }
+    if ws.lockfile_path().exists() {
+        let mut new_ws = Workspace::new(ws.root_manifest(), config)?;
+        new_ws.set_lockfile_name(ws.lockfile_name())?;
+        ops::resolve_ws(&new_ws)?;
+    }
+    Ok(())
+}
+
+/// A manifest of the workspace edited by a version bump.
+pub struct ManifestEdit {
+    pub path: PathBuf,
+    pub original: String,
+    pub new: String,
+}
+
+/// Edits the manifests of the workspace for the packages of `bumps` to get
+/// their new versions: the `package.version` of each of them, and the
+/// requirements of the path dependencies on them. Returns the manifests that
+/// changed, without writing them.
+pub fn edit_manifests(
+    ws: &Workspace<'_>,
+    bumps: &[(&str, &Version)],
+) -> CargoResult<Vec<ManifestEdit>> {
+    let config = ws.config();
+    let mut manifests: Vec<(PathBuf, Option<&str>)> = ws
+        .members()
+        .map(|pkg| (pkg.manifest_path().to_path_buf(), Some(pkg.name().as_str())))
+        .collect();
+    if !manifests.iter().any(|(path, _)| path == ws.root_manifest()) {
+        manifests.push((ws.root_manifest().to_path_buf(), None));
+    }
+    let mut edits = Vec::new();
+    for (path, name) in manifests {
+        let original = paths::read(&path)?;
+        let mut manifest = original.clone();
+        if let Some((_, version)) = bumps.iter().find(|(bumped, _)| Some(*bumped) == name) {
+            manifest = set_package_version(&manifest, version)
+                .with_context(|| format!("failed to bump `{}`", path.display()))?;
+        }
+        for (bumped, version) in bumps {
+            let (new, changes) = set_dependency_requirements(&manifest, bumped, version);
+            manifest = new;
+            for change in changes {
+                config.shell().status(
+                    "Updating",
+                    format!(
+                        "{}'s requirement on {} from `{}` to `{}`",
+                        name.unwrap_or("workspace"),
+                        bumped,
+                        change.old,
+                        change.new
+                    ),
+                )?;
+            }
+        }
+        if manifest != original {
+            edits.push(ManifestEdit {
+                path,
+                original,
+                new: manifest,
+            });
+        }
+    }
+    Ok(edits)
+}
+
+/// How the version of a package is bumped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VersionBump {
+    Major,
+    Minor,
+    Patch,
+    /// Sets this version.
+    Version(Version),
+}
+
+impl VersionBump {
+    /// Parses `major`, `minor`, `patch` or a version.
+    pub fn parse(s: &str) -> CargoResult<VersionBump> {
+        Ok(match s {
+            "major" => VersionBump::Major,
+            "minor" => VersionBump::Minor,
+            "patch" => VersionBump::Patch,
+            _ => match s.parse() {
+                Ok(version) => VersionBump::Version(version),
+                Err(_) => bail!(
+                    "invalid version bump `{}`, expected `major`, `minor`, `patch` or a version",
+                    s
+                ),
+            },
+        })
+    }
+
+    /// The version `version` is bumped to.
+    pub fn apply(&self, version: &Version) -> Version {
+        let mut new = version.clone();
+        new.pre = Prerelease::EMPTY;
+        new.build = BuildMetadata::EMPTY;
+        match self {
+            VersionBump::Major => {
+                new.major += 1;
+                new.minor = 0;
+                new.patch = 0;
+            }
+            VersionBump::Minor => {
+                new.minor += 1;
+                new.patch = 0;
+            }
+            VersionBump::Patch => {
+                // A pre-release is released as the version it precedes.
+                if version.pre.is_empty() {
+                    new.patch += 1;
+                }
+            }
+            VersionBump::Version(version) => return version.clone(),
+        }
+        new
+    }
+}
+
+impl fmt::Display for VersionBump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VersionBump::Major => f.write_str("major"),
+            VersionBump::Minor => f.write_str("minor"),
+            VersionBump::Patch => f.write_str("patch"),
+            VersionBump::Version(version) => version.fmt(f),
+        }
+    }
+}
+
+/// A change of the requirement of a dependency on a bumped package.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RequirementChange {
+    pub old: String,
+    pub new: String,
+}
+
+/// Sets the `package.version` of the manifest `manifest` to `version`.
+pub fn set_package_version(manifest: &str, version: &Version) -> CargoResult<String> {
+    let mut table = Vec::new();
+    let mut lines: Vec<String> = Vec::new();
+    let mut found = false;
+    for line in manifest.split_inclusive('\n') {
+        if let Some(header) = table_header(line) {
+            table = header;
+        } else if !found && matches!(table.as_slice(), [t] if t == "package" || t == "project") {
+            if let Some(new) = replace_string_value(line, "version", &version.to_string()) {
+                lines.push(new);
+                found = true;
+                continue;
+            }
+        }
+        lines.push(line.to_string());
+    }
+    if !found {
+        bail!("no `package.version` to bump in the manifest");
+    }
+    Ok(lines.concat())
+}
+
+/// Sets the requirement of the path dependencies on the package `name` in
+/// the manifest `manifest` to `version`, keeping their operator and
+/// precision. Returns the new manifest, and the changed requirements.
+pub fn set_dependency_requirements(
+    manifest: &str,
+    name: &str,
+    version: &Version,
+) -> (String, Vec<RequirementChange>) {
+    let mut changes = Vec::new();
+    let mut out = String::new();
+    let mut table = Vec::new();
+    // The key and the lines of the `[dependencies.key]` table being read.
+    let mut dep_table: Option<(String, Vec<&str>)> = None;
+    for line in manifest.split_inclusive('\n') {
+        if let Some(header) = table_header(line) {
+            if let Some((key, lines)) = dep_table.take() {
+                out.push_str(&update_dep_table(&key, &lines, name, version, &mut changes));
+            }
+            if header.len() > 1 && is_dep_table(&header[..header.len() - 1]) {
+                dep_table = Some((header[header.len() - 1].clone(), Vec::new()));
+            }
+            table = header;
+            out.push_str(line);
+        } else if let Some((_, lines)) = dep_table.as_mut() {
+            lines.push(line);
+        } else if is_dep_table(&table) {
+            match update_inline_dep(line, name, version, &mut changes) {
+                Some(new) => out.push_str(&new),
+                None => out.push_str(line),
+            }
+        } else {
+            out.push_str(line);
+        }
+    }
+    if let Some((key, lines)) = dep_table.take() {
+        out.push_str(&update_dep_table(&key, &lines, name, version, &mut changes));
+    }
+    (out, changes)
+}
+
+/// Updates the `[dependencies.key]` table with the lines `lines`, if it is a
+/// path dependency on `name`.
+fn update_dep_table(
+    key: &str,
+    lines: &[&str],
+    name: &str,
+    version: &Version,
+    changes: &mut Vec<RequirementChange>,
+) -> String {
+    let mut package = key.to_string();
+    let mut has_path = false;
+    for line in lines {
+        match split_key_value(line) {
+            Some((k, v)) if k == "package" => package = unquote(v).unwrap_or(package),
+            Some((k, _)) if k == "path" => has_path = true,
+            _ => {}
+        }
+    }
+    lines
+        .iter()
+        .map(|line| match split_key_value(line) {
+            Some((k, _)) if k == "version" && has_path && package == name => {
+                replace_requirement(line, version, changes).unwrap_or_else(|| line.to_string())
+            }
+            _ => line.to_string(),
+        })
+        .collect()
+}
+
+/// Updates the dependency with an inline table on `line`, if it is a path
+/// dependency on `name`.
+fn update_inline_dep(
+    line: &str,
+    name: &str,
+    version: &Version,
+    changes: &mut Vec<RequirementChange>,
+) -> Option<String> {
+    let (key, value) = split_key_value(line)?;
+    let value = value.trim_start();
+    if !value.starts_with('{') || dep_name(&key, value) != name || !has_key(value, "path") {
+        return None;
+    }
+    replace_requirement(line, version, changes)
+}
+
+/// The key path of the table header on `line`, like `["dependencies"]` for
+/// `[dependencies]`, if it is one.
+pub(super) fn table_header(line: &str) -> Option<Vec<String>> {
+    let line = strip_comment(line).trim();
+    let inner = line
+        .strip_prefix("[[")
+        .and_then(|l| l.strip_suffix("]]"))
+        .or_else(|| line.strip_prefix('[').and_then(|l| l.strip_suffix(']')))?;
+    Some(split_key(inner))
+}
+
+/// Whether the table with the key path `table` lists dependencies.
+fn is_dep_table(table: &[String]) -> bool {
+    let deps = match table {
+        [deps] => deps,
+        [t, _, deps] if t == "target" => deps,
+        [w, deps] if w == "workspace" => deps,
+        _ => return false,
+    };
+    matches!(
+        deps.as_str(),
+        "dependencies"
+            | "dev-dependencies"
+            | "dev_dependencies"
+            | "build-dependencies"
+            | "build_dependencies"
+    )
+}
+
+/// Splits a dotted key, like `target.'cfg(unix)'.dependencies`.
+fn split_key(key: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    for c in key.chars() {
+        match (quote, c) {
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '.') => parts.push(std::mem::take(&mut current).trim().to_string()),
+            _ => current.push(c),
+        }
+    }
+    parts.push(current.trim().to_string());
+    parts
+}
+
+/// The key and the value of the `key = value` pair on `line`.
+pub(super) fn split_key_value(line: &str) -> Option<(String, &str)> {
+    let trimmed = line.trim_start();
+    if trimmed.starts_with('#') || trimmed.starts_with('[') {
+        return None;
+    }
+    let eq = trimmed.find('=')?;
+    let key = split_key(&trimmed[..eq]).join(".");
+    Some((key, &trimmed[eq + 1..]))
+}
+
+/// The name of the package of the dependency `key`, with the inline table
+/// `value`.
+fn dep_name(key: &str, value: &str) -> String {
+    inline_value(value, "package")
+        .and_then(|v| unquote(&v))
+        .unwrap_or_else(|| key.to_string())
+}
+
+fn has_key(inline_table: &str, key: &str) -> bool {
+    inline_value(inline_table, key).is_some()
+}
+
+/// The value of `key` in the single-line inline table `inline_table`.
+fn inline_value(inline_table: &str, key: &str) -> Option<String> {
+    let (start, end) = inline_key_span(inline_table, key)?;
+    Some(inline_table[start..end].trim().to_string())
+}
+
+/// The span of the value of `key` in the inline table `inline_table`.
+fn inline_key_span(inline_table: &str, key: &str) -> Option<(usize, usize)> {
+    let mut quote = None;
+    let mut depth = 0;
+    let mut item_start = None;
+    for (i, c) in inline_table.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, '{') | (None, '[') => {
+                depth += 1;
+                if depth == 1 {
+                    item_start = Some(i + 1);
+                }
+            }
+            (None, '}') | (None, ']') => depth -= 1,
+            (None, ',') if depth == 1 => item_start = Some(i + 1),
+            (None, '=') if depth == 1 => {
+                if let Some(start) = item_start.take() {
+                    if inline_table[start..i].trim() == key {
+                        let value_start = i + 1;
+                        let value_end = value_end(inline_table, value_start);
+                        return Some((value_start, value_end));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The end of the value starting at `start` in an inline table.
+fn value_end(inline_table: &str, start: usize) -> usize {
+    let mut quote = None;
+    let mut depth = 0;
+    for (i, c) in inline_table[start..].char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, '{') | (None, '[') => depth += 1,
+            (None, '}') | (None, ']') if depth > 0 => depth -= 1,
+            (None, ',') | (None, '}') if depth == 0 => return start + i,
+            _ => {}
+        }
+    }
+    inline_table.len()
+}
+
+/// Replaces the string value of `key` on the `key = "value"` line `line`.
+fn replace_string_value(line: &str, key: &str, value: &str) -> Option<String> {
+    let (k, v) = split_key_value(line)?;
+    if k != key {
+        return None;
+    }
+    let offset = line.len() - v.len();
+    let (start, end) = string_span(v)?;
+    Some(format!(
+        "{}{}{}",
+        &line[..offset + start],
+        value,
+        &line[offset + end..]
+    ))
+}
+
+/// The span of the contents of the first string in `s`.
+fn string_span(s: &str) -> Option<(usize, usize)> {
+    let start = s.find(|c| c == '"' || c == '\'')?;
+    let quote = s[start..].chars().next()?;
+    let len = s[start + 1..].find(quote)?;
+    Some((start + 1, start + 1 + len))
+}
+
+fn unquote(s: &str) -> Option<String> {
+    let (start, end) = string_span(s)?;
+    Some(s[start..end].to_string())
+}
+
+/// Replaces the `version` requirement on `line`, which is either a
+/// `version = "..."` line or a dependency with an inline table.
+fn replace_requirement(
+    line: &str,
+    version: &Version,
+    changes: &mut Vec<RequirementChange>,
+) -> Option<String> {
+    let (key, value) = split_key_value(line)?;
+    let value_offset = line.len() - value.len();
+    let (start, end) = if key == "version" {
+        string_span(value)?
+    } else {
+        let trimmed = value.trim_start();
+        let trimmed_offset = value.len() - trimmed.len();
+        let (vstart, vend) = inline_key_span(trimmed, "version")?;
+        let (s, e) = string_span(&trimmed[vstart..vend])?;
+        (trimmed_offset + vstart + s, trimmed_offset + vstart + e)
+    };
+    let old = &value[start..end];
+    let new = requirement(old, version);
+    if new != old {
+        changes.push(RequirementChange {
+            old: old.to_string(),
+            new: new.clone(),
+        });
+    }
+    Some(format!(
+        "{}{}{}",
+        &line[..value_offset + start],
+        new,
+        &line[value_offset + end..]
+    ))
+}
+
+/// The requirement `old` changed to require `version`, with the same
+/// operator and precision. Requirements with several comparators are
+/// replaced by a caret requirement.
+fn requirement(old: &str, version: &Version) -> String {
+    let old = old.trim();
+    if old.contains(',') || old.contains('*') || old.contains('<') {
+        return version.to_string();
+    }
+    let op_len = old.find(|c: char| c.is_ascii_digit()).unwrap_or(old.len());
+    let (op, rest) = old.split_at(op_len);
+    let precision = if !version.pre.is_empty() {
+        3
+    } else {
+        rest.split(|c| c == '-' || c == '+')
+            .next()
+            .unwrap_or("")
+            .split('.')
+            .count()
+    };
+    let new = match precision {
+        1 => version.major.to_string(),
+        2 => format!("{}.{}", version.major, version.minor),
+        _ => version.to_string(),
+    };
+    format!("{}{}", op.trim_end(), new)
+}
+
+/// `line` without its comment, if it has one outside of strings.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, '#') => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
@@ -4,6 +4,7 @@
 //! structure usable by Cargo itself. Currently this is primarily used to map
 //! sources to one another via the `replace-with` key in `.cargo/config`.
 
+use crate::core::resolver::Metadata;
 use crate::core::{GitReference, PackageId, Source, SourceId};
 use crate::sources::{ReplacedSource, CRATES_IO_REGISTRY};
 use crate::util::config::{self, ConfigRelativePath, OptValue};
@@ -20,15 +21,24 @@ pub struct SourceConfigMap<'cfg> {
     cfgs: HashMap<String, SourceConfig>,
     /// Mapping of `SourceId` to the source name.
     id2name: HashMap<SourceId, String>,
+    /// Revisions of the git sources replacing others in the current mode, as
+    /// recorded in the mode's lock file.
+    locked_revisions: HashMap<SourceId, String>,
     config: &'cfg Config,
 }
 
+/// Prefix of the `[metadata]` keys of a lock file recording the revision of a
+/// git source replacing another in a source mode, followed by its URL.
+const REVISION_METADATA_PREFIX: &str = "replacing-revision ";
+
 /// Definition of a source in a config file.
 #[derive(Debug, serde::Deserialize)]
 #[serde(rename_all = "kebab-case")]
 struct SourceConfigDef {
     /// Indicates this source should be replaced with another of the given name.
     replace_with: OptValue<String>,
+    /// The sources replacing this one in some source modes, by mode.
+    replace_when: OptValue<HashMap<String, String>>,
     /// A directory source.
     directory: Option<ConfigRelativePath>,
     /// A registry source. Value is a URL.
@@ -64,6 +74,10 @@ struct SourceConfig {
     /// this configuration key was defined (such as the `.cargo/config` path
     /// or the environment variable name).
     replace_with: Option<(String, String)>,
+
+    /// Whether the replacement comes from `replace-when` for the current
+    /// source mode, which has its own lock file.
+    replaced_by_mode: bool,
 }
 
 impl<'cfg> SourceConfigMap<'cfg> {
@@ -71,17 +85,87 @@ impl<'cfg> SourceConfigMap<'cfg> {
         let mut base = SourceConfigMap::empty(config)?;
         let sources: Option<HashMap<String, SourceConfigDef>> = config.get("source")?;
         if let Some(sources) = sources {
+            let mode = config.source_mode()?;
             for (key, value) in sources.into_iter() {
-                base.add_config(key, value)?;
+                base.add_config(key, value, mode.as_deref())?;
             }
         }
         Ok(base)
     }
 
+    /// The name of the lock file of the current source mode, `Cargo.<mode>.lock`,
+    /// if a `replace-when` key replaces a source in that mode.
+    ///
+    /// The replacements of a mode have their own lock file, as the packages of
+    /// the replacing sources may not have the checksums or the versions of the
+    /// replaced ones.
+    pub fn mode_lockfile_name(config: &Config) -> CargoResult<Option<String>> {
+        let mode = match config.source_mode()? {
+            Some(mode) => mode,
+            None => return Ok(None),
+        };
+        let map = SourceConfigMap::new(config)?;
+        if map.cfgs.values().any(|cfg| cfg.replaced_by_mode) {
+            Ok(Some(format!("Cargo.{}.lock", mode)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Locks the git sources replacing others in the current mode to the
+    /// revisions recorded in `metadata`, the `[metadata]` of the previous lock
+    /// file, except those replacing one of the `unlocked` sources.
+    pub fn lock_revisions(
+        &mut self,
+        metadata: &Metadata,
+        unlocked: &HashSet<SourceId>,
+    ) -> CargoResult<()> {
+        let unlocked = unlocked
+            .iter()
+            .filter_map(|id| self.replacement(*id).transpose())
+            .map(|r| r.map(|(_, id)| id))
+            .collect::<CargoResult<HashSet<_>>>()?;
+        for (key, rev) in metadata {
+            let url = match key.strip_prefix(REVISION_METADATA_PREFIX) {
+                Some(url) => url,
+                None => continue,
+            };
+            let id = SourceId::from_url(url)?;
+            if !unlocked.contains(&id) {
+                self.locked_revisions.insert(id, rev.clone());
+            }
+        }
+        Ok(())
+    }
+
+    /// Records in `metadata` the revisions of the git sources replacing
+    /// others in the current mode among `sources`, once they're updated.
+    pub fn record_revisions<'a>(
+        &self,
+        metadata: &mut Metadata,
+        sources: impl Iterator<Item = &'a (dyn Source + 'a)>,
+    ) {
+        metadata.retain(|key, _| !key.starts_with(REVISION_METADATA_PREFIX));
+        for source in sources {
+            let replaced_by_mode = self
+                .id2name
+                .get(&source.source_id())
+                .map_or(false, |name| self.cfgs[name].replaced_by_mode);
+            if !source.is_replaced() || !replaced_by_mode {
+                continue;
+            }
+            if let Some(rev) = source.locked_revision() {
+                let id = source.replaced_source_id().with_precise(None);
+                metadata.insert(format!("{}{}", REVISION_METADATA_PREFIX, id.as_url()), rev);
+            }
+        }
+    }
+
     pub fn empty(config: &'cfg Config) -> CargoResult<SourceConfigMap<'cfg>> {
         let mut base = SourceConfigMap {
             cfgs: HashMap::new(),
             id2name: HashMap::new(),
+            locked_revisions: HashMap::new(),
             config,
         };
         base.add(
@@ -89,6 +173,7 @@ impl<'cfg> SourceConfigMap<'cfg> {
             SourceConfig {
                 id: SourceId::crates_io(config)?,
                 replace_with: None,
+                replaced_by_mode: false,
             },
         )?;
         Ok(base)
@@ -106,47 +191,11 @@ impl<'cfg> SourceConfigMap<'cfg> {
     ) -> CargoResult<Box<dyn Source + 'cfg>> {
         debug!("loading: {}", id);
 
-        let mut name = match self.id2name.get(&id) {
-            Some(name) => name,
+        let (name, new_id) = match self.replacement(id)? {
+            Some(replacement) => replacement,
             None => return id.load(self.config, yanked_whitelist),
         };
-        let mut cfg_loc = "";
-        let orig_name = name;
-        let new_id;
-        loop {
-            let cfg = match self.cfgs.get(name) {
-                Some(cfg) => cfg,
-                None => bail!(
-                    "could not find a configured source with the \
-                     name `{}` when attempting to lookup `{}` \
-                     (configuration in `{}`)",
-                    name,
-                    orig_name,
-                    cfg_loc
-                ),
-            };
-            match &cfg.replace_with {
-                Some((s, c)) => {
-                    name = s;
-                    cfg_loc = c;
-                }
-                None if id == cfg.id => return id.load(self.config, yanked_whitelist),
-                None => {
-                    new_id = cfg.id.with_precise(id.precise().map(|s| s.to_string()));
-                    break;
-                }
-            }
-            debug!("following pointer to {}", name);
-            if name == orig_name {
-                bail!(
-                    "detected a cycle of `replace-with` sources, the source \
-                     `{}` is eventually replaced with itself \
-                     (configuration in `{}`)",
-                    name,
-                    cfg_loc
-                )
-            }
-        }
+        let orig_name = &self.id2name[&id];
 
         let new_src = new_id.load(
             self.config,
@@ -156,7 +205,8 @@ impl<'cfg> SourceConfigMap<'cfg> {
                 .collect(),
         )?;
         let old_src = id.load(self.config, yanked_whitelist)?;
-        if !new_src.supports_checksums() && old_src.supports_checksums() {
+        let replaced_by_mode = self.cfgs[orig_name].replaced_by_mode;
+        if !new_src.supports_checksums() && old_src.supports_checksums() && !replaced_by_mode {
             bail!(
                 "\
 cannot replace `{orig}` with `{name}`, the source `{orig}` supports \
@@ -185,6 +235,59 @@ restore the source replacement configuration to continue the build
         Ok(Box::new(ReplacedSource::new(id, new_id, new_src)))
     }
 
+    /// The name of the source replacing `id` and its `SourceId`, following
+    /// the chain of `replace-with` keys, or `None` if it isn't replaced.
+    pub fn replacement(&self, id: SourceId) -> CargoResult<Option<(&str, SourceId)>> {
+        let mut name = match self.id2name.get(&id) {
+            Some(name) => name,
+            None => return Ok(None),
+        };
+        let mut cfg_loc = "";
+        let orig_name = name;
+        loop {
+            let cfg = match self.cfgs.get(name) {
+                Some(cfg) => cfg,
+                None => bail!(
+                    "could not find a configured source with the \
+                     name `{}` when attempting to lookup `{}` \
+                     (configuration in `{}`)",
+                    name,
+                    orig_name,
+                    cfg_loc
+                ),
+            };
+            match &cfg.replace_with {
+                Some((s, c)) => {
+                    name = s;
+                    cfg_loc = c;
+                }
+                None if id == cfg.id => return Ok(None),
+                None => {
+                    // The precise value of another kind of source, like the
+                    // `locked` of a registry, isn't a revision of a git one,
+                    // which is recorded separately in the mode's lock file.
+                    let precise = if cfg.id.is_git() && !id.is_git() {
+                        self.locked_revisions.get(&cfg.id).cloned()
+                    } else {
+                        id.precise().map(|s| s.to_string())
+                    };
+                    let new_id = cfg.id.with_precise(precise);
+                    return Ok(Some((name, new_id)));
+                }
+            }
+            debug!("following pointer to {}", name);
+            if name == orig_name {
+                bail!(
+                    "detected a cycle of `replace-with` sources, the source \
+                     `{}` is eventually replaced with itself \
+                     (configuration in `{}`)",
+                    name,
+                    cfg_loc
+                )
+            }
+        }
+    }
+
     fn add(&mut self, name: &str, cfg: SourceConfig) -> CargoResult<()> {
         if let Some(old_name) = self.id2name.insert(cfg.id, name.to_string()) {
             // The user is allowed to redefine the built-in crates-io
@@ -203,7 +306,12 @@ restore the source replacement configuration to continue the build
         Ok(())
     }
 
-    fn add_config(&mut self, name: String, def: SourceConfigDef) -> CargoResult<()> {
+    fn add_config(
+        &mut self,
+        name: String,
+        def: SourceConfigDef,
+        mode: Option<&str>,
+    ) -> CargoResult<()> {
         let mut srcs = Vec::new();
         if let Some(registry) = def.registry {
             let url = url(&registry, &format!("source.{}.registry", name))?;
@@ -265,15 +373,30 @@ restore the source replacement configuration to continue the build
         }
         let src = srcs[0];
 
-        let replace_with = def
+        let mut replace_with = def
             .replace_with
             .map(|val| (val.val, val.definition.to_string()));
+        let mut replaced_by_mode = false;
+        if let Some(replace_when) = def.replace_when {
+            if !self.config.cli_unstable().source_modes {
+                bail!(
+                    "`source.{}.replace-when` requires `-Z source-modes` (in {})",
+                    name,
+                    replace_when.definition
+                );
+            }
+            if let Some(replacement) = mode.and_then(|mode| replace_when.val.get(mode)) {
+                replace_with = Some((replacement.clone(), replace_when.definition.to_string()));
+                replaced_by_mode = true;
+            }
+        }
 
         self.add(
             &name,
             SourceConfig {
                 id: src,
                 replace_with,
+                replaced_by_mode,
             },
         )?;
 
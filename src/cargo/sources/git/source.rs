@@ -9,6 +9,7 @@ use crate::util::Config;
 use anyhow::Context;
 use log::trace;
 use std::fmt::{self, Debug, Formatter};
+use std::path::PathBuf;
 use url::Url;
 
 pub struct GitSource<'cfg> {
@@ -50,6 +51,15 @@ impl<'cfg> GitSource<'cfg> {
         self.remote.url()
     }
 
+    /// The path of the database of the repository, in the Cargo home.
+    pub fn db_path(&self) -> PathBuf {
+        self.config
+            .git_path()
+            .into_path_unlocked()
+            .join("db")
+            .join(&self.ident)
+    }
+
     pub fn read_packages(&mut self) -> CargoResult<Vec<Package>> {
         if self.path_source.is_none() {
             self.update()?;
@@ -203,6 +213,10 @@ impl<'cfg> Source for GitSource<'cfg> {
         Ok(self.locked_rev.as_ref().unwrap().to_string())
     }
 
+    fn locked_revision(&self) -> Option<String> {
+        self.locked_rev.map(|rev| rev.to_string())
+    }
+
     fn describe(&self) -> String {
         format!("Git repository {}", self.source_id)
     }
@@ -0,0 +1,482 @@
+//! The SSH settings of the git sources fetched with libgit2, with
+//! `-Z net-ssh`.
+//!
+//! The private key of `net.ssh.key-path`, or of `net.ssh.hosts.<host>.key-path`,
+//! authenticates instead of the keys of ssh-agent, so that a CI container
+//! only needs the key file.
+//!
+//! libgit2 doesn't verify the key of the SSH hosts, so Cargo checks its
+//! SHA256 fingerprint against the keys of `net.ssh.known-hosts`, of
+//! `~/.ssh/known_hosts`, and of the `ssh_known_hosts` file of the Cargo home,
+//! where the keys trusted on first use are added. A host whose known keys
+//! don't include the key received is rejected. An unknown host is trusted
+//! after a prompt, or without one with `net.ssh.accept-new-host-keys`.
+//!
+//! The host names hashed by OpenSSH's `HashKnownHosts` are matched with the
+//! HMAC-SHA1 of the host name. The `@cert-authority` lines aren't supported,
+//! as libssh2 doesn't provide the certificates, but a host they match isn't
+//! trusted on first use either.
+
+use std::cell::RefCell;
+use std::fs::OpenOptions;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context as _};
+use cargo_util::{paths, Sha256};
+use crypto_hash::Algorithm;
+use url::Url;
+
+use crate::util::base64;
+use crate::util::errors::CargoResult;
+use crate::util::Config;
+
+/// The file of the Cargo home with the host keys trusted on first use.
+const CARGO_KNOWN_HOSTS: &str = "ssh_known_hosts";
+
+/// The SSH settings of fetching one URL.
+pub struct SshSettings {
+    /// The host, as named in the `known_hosts` files.
+    host: String,
+    key_path: Option<PathBuf>,
+    known_hosts: RefCell<Vec<KnownHost>>,
+    accept_new: bool,
+}
+
+/// A trusted, or revoked, host key.
+#[derive(Debug, PartialEq)]
+struct KnownHost {
+    /// The comma-separated patterns of the hosts, or the hashed host name,
+    /// `|1|<salt>|<hash>`.
+    patterns: String,
+    /// The SHA256 fingerprint of the key, in base64 without padding.
+    fingerprint: String,
+    revoked: bool,
+    /// Whether the key is of a certificate authority, which signs the host
+    /// keys rather than being one.
+    cert_authority: bool,
+    /// Where the key is listed.
+    source: String,
+}
+
+impl SshSettings {
+    /// The SSH settings of fetching `url`, or `None` without `-Z net-ssh`,
+    /// or if `url` doesn't use SSH.
+    pub fn for_url(config: &Config, url: &str) -> CargoResult<Option<SshSettings>> {
+        let ssh = config.net_config()?.ssh.as_ref();
+        if !config.cli_unstable().net_ssh {
+            if ssh.is_some() {
+                bail!("`net.ssh` requires `-Z net-ssh`");
+            }
+            return Ok(None);
+        }
+        let url = match Url::parse(url) {
+            Ok(url) if matches!(url.scheme(), "ssh" | "git+ssh" | "ssh+git") => url,
+            _ => return Ok(None),
+        };
+        let host_name = url.host_str().unwrap_or_default();
+        let host = match url.port() {
+            Some(port) if port != 22 => format!("[{}]:{}", host_name, port),
+            _ => host_name.to_string(),
+        };
+
+        let mut key_path = None;
+        let mut accept_new = false;
+        let mut known_hosts = Vec::new();
+        if let Some(ssh) = ssh {
+            let host_key = ssh
+                .hosts
+                .get(host_name)
+                .and_then(|host| host.key_path.as_ref())
+                .map(|path| (path, format!("net.ssh.hosts.{}.key-path", host_name)));
+            let key = host_key.or_else(|| {
+                ssh.key_path
+                    .as_ref()
+                    .map(|path| (path, "net.ssh.key-path".to_string()))
+            });
+            if let Some((path, key)) = key {
+                let path = path.resolve_path(config);
+                if !path.is_file() {
+                    bail!(
+                        "the SSH key `{}` of `{}` doesn't exist",
+                        path.display(),
+                        key
+                    );
+                }
+                key_path = Some(path);
+            }
+            accept_new = ssh.accept_new_host_keys.unwrap_or(false);
+            for line in ssh.known_hosts.iter().flatten() {
+                match parse_known_host(line, "`net.ssh.known-hosts`") {
+                    Some(known_host) => known_hosts.push(known_host),
+                    None => bail!(
+                        "invalid `net.ssh.known-hosts` entry `{}`, expected \
+                         `<host> SHA256:<fingerprint>`, or a line of a `known_hosts` \
+                         file",
+                        line
+                    ),
+                }
+            }
+        }
+        if let Some(home) = home::home_dir() {
+            known_hosts.extend(read_known_hosts(&home.join(".ssh/known_hosts")));
+        }
+        known_hosts.extend(read_known_hosts(&cargo_known_hosts(config)));
+
+        Ok(Some(SshSettings {
+            host,
+            key_path,
+            known_hosts: RefCell::new(known_hosts),
+            accept_new,
+        }))
+    }
+
+    /// The private key authenticating to the host, if configured.
+    pub fn key_path(&self) -> Option<&Path> {
+        self.key_path.as_deref()
+    }
+
+    /// Checks the certificate of the host, for the `certificate_check`
+    /// callback of libgit2.
+    pub fn check_host_key(&self, config: &Config, cert: &git2::cert::Cert<'_>) -> CargoResult<()> {
+        let hash = match cert.as_hostkey().and_then(|key| key.hash_sha256()) {
+            Some(hash) => hash,
+            None => bail!(
+                "the SSH host key of `{}` can't be verified, \
+                 as libssh2 doesn't provide its SHA256 fingerprint",
+                self.host
+            ),
+        };
+        let fingerprint = base64::encode_unpadded(hash);
+        let mut known_hosts = self.known_hosts.borrow_mut();
+        let matching: Vec<_> = known_hosts
+            .iter()
+            .filter(|known| host_matches(&known.patterns, &self.host))
+            .collect();
+        if let Some(revoked) = matching.iter().find(|known| {
+            known.revoked && !known.cert_authority && known.fingerprint == fingerprint
+        }) {
+            bail!(
+                "the SSH host key of `{}`, with fingerprint SHA256:{}, is revoked in {}",
+                self.host,
+                fingerprint,
+                revoked.source
+            );
+        }
+        let trusted: Vec<_> = matching
+            .iter()
+            .filter(|known| !known.revoked && !known.cert_authority)
+            .collect();
+        if trusted.iter().any(|known| known.fingerprint == fingerprint) {
+            return Ok(());
+        }
+        if !trusted.is_empty() {
+            let keys: String = trusted
+                .iter()
+                .map(|known| format!("\n  SHA256:{} (in {})", known.fingerprint, known.source))
+                .collect();
+            bail!(
+                "the SSH host key of `{}` doesn't match its known keys\n\
+                 The host sent the key with fingerprint SHA256:{}, but the known keys are:{}\n\
+                 Someone could be intercepting the connection. If the host changed its key, \
+                 remove the old key from the known hosts.",
+                self.host,
+                fingerprint,
+                keys
+            );
+        }
+
+        if let Some(ca) = matching.iter().find(|known| known.cert_authority) {
+            bail!(
+                "the SSH host key of `{}`, with fingerprint SHA256:{}, can't be verified\n\
+                 The host is known through the `@cert-authority` in {}, but the host \
+                 certificates aren't supported. Add the key of the host to \
+                 `net.ssh.known-hosts` after checking its fingerprint.",
+                self.host,
+                fingerprint,
+                ca.source
+            );
+        }
+        if !self.accept_new && !prompt_trust(config, &self.host, &fingerprint)? {
+            bail!(
+                "the SSH host key of `{host}`, with fingerprint SHA256:{fingerprint}, is unknown\n\
+                 To trust it, check the fingerprint, and add `\"{host} SHA256:{fingerprint}\"` \
+                 to `net.ssh.known-hosts`, or connect with `ssh` once to add it to \
+                 `~/.ssh/known_hosts`, or set `net.ssh.accept-new-host-keys` to `true`.",
+                host = self.host,
+                fingerprint = fingerprint,
+            );
+        }
+        let path = cargo_known_hosts(config);
+        paths::create_dir_all(path.parent().unwrap())?;
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| writeln!(file, "{} SHA256:{}", self.host, fingerprint))
+            .with_context(|| format!("failed to add the host key to `{}`", path.display()))?;
+        config.shell().note(format!(
+            "added the SSH host key of `{}`, with fingerprint SHA256:{}, to `{}`",
+            self.host,
+            fingerprint,
+            path.display()
+        ))?;
+        known_hosts.push(KnownHost {
+            patterns: self.host.clone(),
+            fingerprint,
+            revoked: false,
+            cert_authority: false,
+            source: format!("`{}`", path.display()),
+        });
+        Ok(())
+    }
+}
+
+/// The file of the Cargo home with the host keys trusted on first use.
+fn cargo_known_hosts(config: &Config) -> PathBuf {
+    config.home().as_path_unlocked().join(CARGO_KNOWN_HOSTS)
+}
+
+/// Asks whether to trust the unknown key of `host`, if the terminal is
+/// interactive.
+fn prompt_trust(config: &Config, host: &str, fingerprint: &str) -> CargoResult<bool> {
+    if !atty::is(atty::Stream::Stdin) || !atty::is(atty::Stream::Stderr) {
+        return Ok(false);
+    }
+    let mut shell = config.shell();
+    write!(
+        shell.err(),
+        "The authenticity of the host `{}` can't be established.\n\
+         Its SSH host key has the fingerprint SHA256:{}.\n\
+         Trust this key and continue connecting? [y/N] ",
+        host,
+        fingerprint
+    )?;
+    shell.err().flush()?;
+    let mut answer = String::new();
+    io::stdin()
+        .lock()
+        .read_line(&mut answer)
+        .with_context(|| "failed to read stdin")?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// The keys of a `known_hosts` file, ignoring the lines which aren't
+/// supported, like `ssh` does with the lines it can't parse.
+fn read_known_hosts(path: &Path) -> Vec<KnownHost> {
+    let contents = match paths::read(path) {
+        Ok(contents) => contents,
+        Err(_) => return Vec::new(),
+    };
+    let source = format!("`{}`", path.display());
+    contents
+        .lines()
+        .filter_map(|line| parse_known_host(line, &source))
+        .collect()
+}
+
+/// Parses a line of a `known_hosts` file,
+/// `[@revoked|@cert-authority] <hosts> <type> <key>`, or
+/// `<hosts> SHA256:<fingerprint>`.
+fn parse_known_host(line: &str, source: &str) -> Option<KnownHost> {
+    let mut fields = line.split_whitespace().peekable();
+    let (revoked, cert_authority) = match fields.peek()? {
+        &"@revoked" => (true, false),
+        &"@cert-authority" => (false, true),
+        marker if marker.starts_with('@') => return None,
+        _ => (false, false),
+    };
+    if revoked || cert_authority {
+        fields.next();
+    }
+    let patterns = fields.next()?;
+    if patterns.starts_with('#') {
+        return None;
+    }
+    if patterns.starts_with('|') {
+        // Only the SHA1 hashes of OpenSSH exist, with a salt of 20 bytes.
+        let (salt, hash) = patterns.strip_prefix("|1|")?.split_once('|')?;
+        if base64::decode(salt)?.len() != 20 || base64::decode(hash)?.len() != 20 {
+            return None;
+        }
+    }
+    let key = fields.next()?;
+    let fingerprint = match key.strip_prefix("SHA256:") {
+        Some(fingerprint) => {
+            // The fingerprint must be 32 bytes in base64.
+            let fingerprint = fingerprint.trim_end_matches('=');
+            if base64::decode(fingerprint)?.len() != 32 {
+                return None;
+            }
+            fingerprint.to_string()
+        }
+        None => base64::encode_unpadded(
+            &Sha256::new()
+                .update(&base64::decode(fields.next()?)?)
+                .finish(),
+        ),
+    };
+    Some(KnownHost {
+        patterns: patterns.to_string(),
+        fingerprint,
+        revoked,
+        cert_authority,
+        source: source.to_string(),
+    })
+}
+
+/// Whether `host` matches the comma-separated `patterns`, with the `*` and
+/// `?` wildcards, and the negated patterns starting with `!`, or the hashed
+/// host name `|1|<salt>|<hash>`.
+fn host_matches(patterns: &str, host: &str) -> bool {
+    let host = host.to_lowercase();
+    if let Some((salt, hash)) = patterns
+        .strip_prefix("|1|")
+        .and_then(|hashed| hashed.split_once('|'))
+    {
+        return match (base64::decode(salt), base64::decode(hash)) {
+            (Some(salt), Some(hash)) => hmac_sha1(&salt, host.as_bytes()) == hash,
+            _ => false,
+        };
+    }
+    let mut matches = false;
+    for pattern in patterns.to_lowercase().split(',') {
+        match pattern.strip_prefix('!') {
+            Some(pattern) if wildcard_matches(pattern.as_bytes(), host.as_bytes()) => return false,
+            Some(_) => {}
+            None => matches |= wildcard_matches(pattern.as_bytes(), host.as_bytes()),
+        }
+    }
+    matches
+}
+
+fn wildcard_matches(pattern: &[u8], s: &[u8]) -> bool {
+    match (pattern.split_first(), s.split_first()) {
+        (None, None) => true,
+        (Some((b'*', rest)), _) => {
+            wildcard_matches(rest, s) || (!s.is_empty() && wildcard_matches(pattern, &s[1..]))
+        }
+        (Some((b'?', rest)), Some((_, s))) => wildcard_matches(rest, s),
+        (Some((p, rest)), Some((c, s))) => p == c && wildcard_matches(rest, s),
+        _ => false,
+    }
+}
+
+/// The HMAC-SHA1 of `data` with `key`, from RFC 2104, as in the hashed host
+/// names of OpenSSH.
+fn hmac_sha1(key: &[u8], data: &[u8]) -> Vec<u8> {
+    const BLOCK_SIZE: usize = 64;
+    let mut key = if key.len() > BLOCK_SIZE {
+        crypto_hash::digest(Algorithm::SHA1, key)
+    } else {
+        key.to_vec()
+    };
+    key.resize(BLOCK_SIZE, 0);
+    let pad = |byte: u8| key.iter().map(|k| k ^ byte).collect::<Vec<u8>>();
+    let mut inner = pad(0x36);
+    inner.extend_from_slice(data);
+    let mut outer = pad(0x5c);
+    outer.extend(crypto_hash::digest(Algorithm::SHA1, &inner));
+    crypto_hash::digest(Algorithm::SHA1, &outer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_host_lines() {
+        // The fingerprint is the SHA256 of the decoded key.
+        let known = parse_known_host(
+            "github.com,140.82.121.4 ssh-ed25519 \
+             AAAAC3NzaC1lZDI1NTE5AAAAIOMqqnkVzrm0SdG6UOoqKLsabgH5C9okWi0dh2l9GKJl",
+            "test",
+        )
+        .unwrap();
+        assert_eq!(known.patterns, "github.com,140.82.121.4");
+        assert_eq!(
+            known.fingerprint,
+            "+DiY3wvvV6TuJJhbpZisF/zLDA0zPMSvHdkr4UvCOqU"
+        );
+        assert!(!known.revoked);
+
+        let known = parse_known_host(
+            "@revoked *.example SHA256:+DiY3wvvV6TuJJhbpZisF/zLDA0zPMSvHdkr4UvCOqU=",
+            "test",
+        )
+        .unwrap();
+        assert!(known.revoked);
+        assert_eq!(
+            known.fingerprint,
+            "+DiY3wvvV6TuJJhbpZisF/zLDA0zPMSvHdkr4UvCOqU"
+        );
+
+        let known = parse_known_host(
+            "@cert-authority *.example SHA256:+DiY3wvvV6TuJJhbpZisF/zLDA0zPMSvHdkr4UvCOqU",
+            "test",
+        )
+        .unwrap();
+        assert!(known.cert_authority);
+        assert!(!known.revoked);
+
+        for line in [
+            "",
+            "# comment",
+            "|1|c2FsdA==|aGFzaA== ssh-ed25519 AAAA",
+            "|2|c2FsdA==|aGFzaA== ssh-ed25519 AAAA",
+            "@marker *.example ssh-ed25519 AAAA",
+            "example.com SHA256:tooshort",
+            "example.com ssh-ed25519",
+        ] {
+            assert_eq!(parse_known_host(line, "test"), None, "{}", line);
+        }
+    }
+
+    #[test]
+    fn host_patterns() {
+        assert!(host_matches("github.com", "GitHub.com"));
+        assert!(host_matches("gitlab.com,github.com", "github.com"));
+        assert!(host_matches("*.example", "git.example"));
+        assert!(host_matches("git?.example", "git1.example"));
+        assert!(!host_matches("*.example", "example"));
+        assert!(!host_matches("*.example,!secret.example", "secret.example"));
+        assert!(host_matches("[git.example]:2222", "[git.example]:2222"));
+        assert!(!host_matches("git.example", "[git.example]:2222"));
+    }
+
+    #[test]
+    fn hmac_sha1_vectors() {
+        // From RFC 2202.
+        assert_eq!(
+            hex::encode(hmac_sha1(&[0x0b; 20], b"Hi There")),
+            "b617318655057264e28bc0b6fb378c8ef146be00"
+        );
+        assert_eq!(
+            hex::encode(hmac_sha1(
+                &[0xaa; 80],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            )),
+            "aa4ae5e15272d00e95705637ce8a3b55ed402112"
+        );
+    }
+
+    #[test]
+    fn hashed_host_names() {
+        let salt = [7; 20];
+        let hashed = format!(
+            "|1|{}|{}",
+            base64::encode_unpadded(&salt),
+            base64::encode_unpadded(&hmac_sha1(&salt, b"[git.example]:2222"))
+        );
+        let known = parse_known_host(
+            &format!(
+                "{} SHA256:+DiY3wvvV6TuJJhbpZisF/zLDA0zPMSvHdkr4UvCOqU",
+                hashed
+            ),
+            "test",
+        )
+        .unwrap();
+        assert!(host_matches(&known.patterns, "[Git.example]:2222"));
+        assert!(!host_matches(&known.patterns, "git.example"));
+    }
+}
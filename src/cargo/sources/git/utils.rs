@@ -1,9 +1,10 @@
 //! Utilities for handling git repositories, mainly around
 //! authentication/cloning.
 
+use super::ssh::SshSettings;
 use crate::core::GitReference;
 use crate::util::errors::CargoResult;
-use crate::util::{network, Config, IntoUrl, MetricsCounter, Progress};
+use crate::util::{network, network_log, Config, IntoUrl, MetricsCounter, Progress};
 use anyhow::{anyhow, Context as _};
 use cargo_util::{paths, ProcessBuilder};
 use curl::easy::List;
@@ -11,7 +12,9 @@ use git2::{self, ErrorClass, ObjectType};
 use log::{debug, info};
 use serde::ser;
 use serde::Serialize;
+use std::cell::RefCell;
 use std::env;
+use std::ffi::OsStr;
 use std::fmt;
 use std::path::{Path, PathBuf};
 use std::process::Command;
@@ -170,6 +173,8 @@ impl GitDatabase {
                     Err(e) => debug!("failed reset after fetch {:?}", e),
                 }
             } else {
+                // Records the use of the checkout for `cargo cache prune`.
+                paths::set_file_time_no_err(dest.join(".cargo-ok"), filetime::FileTime::now());
                 checkout = Some(co);
             }
         };
@@ -445,7 +450,12 @@ impl<'a> GitCheckout<'a> {
 /// credentials until we give it a reason to not do so. To ensure we don't
 /// just sit here looping forever we keep track of authentications we've
 /// attempted and we don't try the same ones again.
-fn with_authentication<T, F>(url: &str, cfg: &git2::Config, mut f: F) -> CargoResult<T>
+fn with_authentication<T, F>(
+    url: &str,
+    cfg: &git2::Config,
+    ssh_key: Option<&Path>,
+    mut f: F,
+) -> CargoResult<T>
 where
     F: FnMut(&mut git2::Credentials<'_>) -> CargoResult<T>,
 {
@@ -491,8 +501,8 @@ where
 
         // An "SSH_KEY" authentication indicates that we need some sort of SSH
         // authentication. This can currently either come from the ssh-agent
-        // process or from a raw in-memory SSH key. Cargo only supports using
-        // ssh-agent currently.
+        // process or from a raw in-memory SSH key. Cargo supports using
+        // ssh-agent, or the key file of `net.ssh.key-path`.
         //
         // If we get called with this then the only way that should be possible
         // is if a username is specified in the URL itself (e.g., `username` is
@@ -506,7 +516,7 @@ where
             let username = username.unwrap();
             debug_assert!(!ssh_username_requested);
             ssh_agent_attempts.push(username.to_string());
-            return git2::Cred::ssh_key_from_agent(username);
+            return ssh_credential(username, ssh_key);
         }
 
         // Sometimes libgit2 will ask for a username/password in plaintext. This
@@ -572,7 +582,7 @@ where
                     attempts += 1;
                     if attempts == 1 {
                         ssh_agent_attempts.push(s.to_string());
-                        return git2::Cred::ssh_key_from_agent(&s);
+                        return ssh_credential(&s, ssh_key);
                     }
                 }
                 Err(git2::Error::from_str("no authentication available"))
@@ -621,11 +631,19 @@ where
                 .map(|s| format!("`{}`", s))
                 .collect::<Vec<_>>()
                 .join(", ");
-            msg.push_str(&format!(
-                "\n* attempted ssh-agent authentication, but \
-                 no usernames succeeded: {}",
-                names
-            ));
+            match ssh_key {
+                Some(key) => msg.push_str(&format!(
+                    "\n* attempted authentication with the SSH key `{}`, but \
+                     no usernames succeeded: {}",
+                    key.display(),
+                    names
+                )),
+                None => msg.push_str(&format!(
+                    "\n* attempted ssh-agent authentication, but \
+                     no usernames succeeded: {}",
+                    names
+                )),
+            }
         }
         if let Some(failed_cred_helper) = cred_helper_bad {
             if failed_cred_helper {
@@ -674,6 +692,15 @@ where
     Err(err)
 }
 
+/// The SSH credential of `username`, from the key file `ssh_key` or else
+/// from ssh-agent.
+fn ssh_credential(username: &str, ssh_key: Option<&Path>) -> Result<git2::Cred, git2::Error> {
+    match ssh_key {
+        Some(key) => git2::Cred::ssh_key(username, None, key, None),
+        None => git2::Cred::ssh_key_from_agent(username),
+    }
+}
+
 fn reset(repo: &git2::Repository, obj: &git2::Object<'_>, config: &Config) -> CargoResult<()> {
     let mut pb = Progress::new("Checkout", config);
     let mut opts = git2::build::CheckoutBuilder::new();
@@ -693,14 +720,29 @@ pub fn with_fetch_options(
     cb: &mut dyn FnMut(git2::FetchOptions<'_>) -> CargoResult<()>,
 ) -> CargoResult<()> {
     let mut progress = Progress::new("Fetch", config);
+    let ssh = SshSettings::for_url(config, url)?;
+    let ssh_key = ssh.as_ref().and_then(|ssh| ssh.key_path());
+    // libgit2 doesn't verify the SSH host keys, and only reports "user
+    // cancelled hostkey check" when the check fails, so the error of the check
+    // is kept to replace it.
+    let host_key_error = RefCell::new(None);
     network::with_retry(config, || {
-        with_authentication(url, git_config, |f| {
+        with_authentication(url, git_config, ssh_key, |f| {
             let mut last_update = Instant::now();
             let mut rcb = git2::RemoteCallbacks::new();
             // We choose `N=10` here to make a `300ms * 10slots ~= 3000ms`
             // sliding window for tracking the data transfer rate (in bytes/s).
             let mut counter = MetricsCounter::<10>::new(0, last_update);
             rcb.credentials(f);
+            if let Some(ssh) = &ssh {
+                rcb.certificate_check(|cert, _host| match ssh.check_host_key(config, cert) {
+                    Ok(()) => true,
+                    Err(e) => {
+                        *host_key_error.borrow_mut() = Some(e);
+                        false
+                    }
+                });
+            }
             rcb.transfer_progress(|stats| {
                 let indexed_deltas = stats.indexed_deltas();
                 let msg = if indexed_deltas > 0 {
@@ -745,7 +787,11 @@ pub fn with_fetch_options(
             // url
             let mut opts = git2::FetchOptions::new();
             opts.remote_callbacks(rcb);
-            cb(opts)
+            let res = cb(opts);
+            match host_key_error.borrow_mut().take() {
+                Some(e) if res.is_err() => Err(e),
+                _ => res,
+            }
         })?;
         Ok(())
     })
@@ -757,32 +803,15 @@ pub fn fetch(
     reference: &GitReference,
     config: &Config,
 ) -> CargoResult<()> {
-    if config.frozen() {
-        anyhow::bail!(
-            "attempting to update a git repository, but --frozen \
-             was specified"
-        )
-    }
-    if !config.network_allowed() {
-        anyhow::bail!("can't update a git repository in the offline mode")
-    }
-
-    // If we're fetching from GitHub, attempt GitHub's special fast path for
-    // testing if we've already got an up-to-date copy of the repository
-    match github_up_to_date(repo, url, reference, config) {
-        Ok(true) => return Ok(()),
-        Ok(false) => {}
-        Err(e) => debug!("failed to check github {:?}", e),
-    }
-
-    // We reuse repositories quite a lot, so before we go through and update the
-    // repo check to see if it's a little too old and could benefit from a gc.
-    // In theory this shouldn't be too too expensive compared to the network
-    // request we're about to issue.
-    maybe_gc_repo(repo)?;
+    let (refspecs, tags) = refspecs(reference);
+    network_log::fetch_git(config, repo, url, &refspecs, |repo| {
+        fetch_refspecs(repo, url, reference, &refspecs, tags, config)
+    })
+}
 
-    // Translate the reference desired here into an actual list of refspecs
-    // which need to get fetched. Additionally record if we're fetching tags.
+/// Translates the reference desired here into an actual list of refspecs
+/// which need to get fetched, and whether the tags are fetched.
+fn refspecs(reference: &GitReference) -> (Vec<String>, bool) {
     let mut refspecs = Vec::new();
     let mut tags = false;
     // The `+` symbol on the refspec means to allow a forced (fast-forward)
@@ -815,6 +844,40 @@ pub fn fetch(
             }
         }
     }
+    (refspecs, tags)
+}
+
+fn fetch_refspecs(
+    repo: &mut git2::Repository,
+    url: &str,
+    reference: &GitReference,
+    refspecs: &[String],
+    tags: bool,
+    config: &Config,
+) -> CargoResult<()> {
+    if config.frozen() {
+        anyhow::bail!(
+            "attempting to update a git repository, but --frozen \
+             was specified"
+        )
+    }
+    if !config.network_allowed() {
+        anyhow::bail!("can't update a git repository in the offline mode")
+    }
+
+    // If we're fetching from GitHub, attempt GitHub's special fast path for
+    // testing if we've already got an up-to-date copy of the repository
+    match github_up_to_date(repo, url, reference, config) {
+        Ok(true) => return Ok(()),
+        Ok(false) => {}
+        Err(e) => debug!("failed to check github {:?}", e),
+    }
+
+    // We reuse repositories quite a lot, so before we go through and update the
+    // repo check to see if it's a little too old and could benefit from a gc.
+    // In theory this shouldn't be too too expensive compared to the network
+    // request we're about to issue.
+    maybe_gc_repo(repo)?;
 
     // Unfortunately `libgit2` is notably lacking in the realm of authentication
     // when compared to the `git` command line. As a result, allow an escape
@@ -823,7 +886,13 @@ pub fn fetch(
     // flavors of authentication possible while also still giving us all the
     // speed and portability of using `libgit2`.
     if let Some(true) = config.net_config()?.git_fetch_with_cli {
-        return fetch_with_cli(repo, url, &refspecs, tags, config);
+        return fetch_with_cli(repo, url.as_ref(), refspecs, tags, config);
+    }
+
+    // libgit2 can't fetch from a git bundle, like the ones of `cargo
+    // git-bundle`, and `git` only reads a bundle from a path, not a URL.
+    if let Some(path) = bundle_path(url) {
+        return fetch_with_cli(repo, path.as_os_str(), refspecs, tags, config);
     }
 
     debug!("doing a fetch for {}", url);
@@ -847,7 +916,7 @@ pub fn fetch(
             debug!("initiating fetch of {:?} from {}", refspecs, url);
             let res = repo
                 .remote_anonymous(url)?
-                .fetch(&refspecs, Some(&mut opts), None);
+                .fetch(refspecs, Some(&mut opts), None);
             let err = match res {
                 Ok(()) => break,
                 Err(e) => e,
@@ -872,9 +941,20 @@ pub fn fetch(
     })
 }
 
+/// The path of the git bundle `url` points to, if it's a `file://` URL of a
+/// file rather than of a repository.
+fn bundle_path(url: &str) -> Option<PathBuf> {
+    let path = Url::parse(url).ok()?.to_file_path().ok()?;
+    if path.is_file() {
+        Some(path)
+    } else {
+        None
+    }
+}
+
 fn fetch_with_cli(
     repo: &mut git2::Repository,
-    url: &str,
+    url: &OsStr,
     refspecs: &[String],
     tags: bool,
     config: &Config,
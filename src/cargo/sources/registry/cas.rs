@@ -0,0 +1,103 @@
+//! The content-addressed store of `-Z registry-dedup`.
+//!
+//! The sources of registry crates are extracted into a directory per version,
+//! so the versions of a crate with frequent small releases mostly store the
+//! same files again. With `-Z registry-dedup`, each extracted file is kept
+//! once in `$CARGO_HOME/registry/cas`, named by the SHA-256 of its contents,
+//! and the file in the source directory is a hard link to it. Executable
+//! files are kept apart from the others with the same contents, since hard
+//! links share their permissions.
+//!
+//! The files of the store are made read-only when they are added, so the
+//! sources sharing them can't be changed by accident. A file of the store is
+//! hashed again before it is linked to, and replaced if its contents no
+//! longer match its name, so a file changed anyway isn't spread to the
+//! sources extracted afterwards.
+//!
+//! Files that can't be hard-linked, for example because the store is on
+//! another filesystem, are left as they are. A file of the store with a
+//! single link is not used by any source directory anymore.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context as _;
+use cargo_util::{paths, Sha256};
+use log::debug;
+
+use super::PACKAGE_SOURCE_LOCK;
+use crate::util::{CargoResult, Config};
+
+/// Replaces the files of the extracted sources in `dir` with hard links to
+/// the files of the store with the same contents.
+pub fn dedup(config: &Config, dir: &Path) -> CargoResult<()> {
+    let store = config.home().join("registry").join("cas");
+    let store = config.assert_package_cache_locked(&store);
+    for entry in walkdir::WalkDir::new(dir) {
+        let entry = entry.with_context(|| format!("failed to read `{}`", dir.display()))?;
+        // The lock file is written once the sources are extracted.
+        if !entry.file_type().is_file() || entry.file_name() == PACKAGE_SOURCE_LOCK {
+            continue;
+        }
+        let path = entry.path();
+        let (stored, hash) = stored_path(store, path)?;
+        if stored.exists() && Sha256::new().update_path(&stored)?.finish_hex() != hash {
+            debug!(
+                "`{}` was changed, replacing it in the store",
+                stored.display()
+            );
+            paths::remove_file(&stored)?;
+        }
+        if !stored.exists() {
+            paths::create_dir_all(stored.parent().unwrap())?;
+            set_readonly(path)?;
+            if let Err(e) = fs::hard_link(path, &stored) {
+                debug!("failed to add `{}` to the store: {}", path.display(), e);
+            }
+            continue;
+        }
+        // Link to a temporary file first, so the file is still there if
+        // linking fails.
+        let tmp = path.with_file_name(format!(
+            ".{}.cargo-dedup",
+            path.file_name().unwrap().to_string_lossy()
+        ));
+        match fs::hard_link(&stored, &tmp) {
+            Ok(()) => fs::rename(&tmp, path)
+                .with_context(|| format!("failed to replace `{}`", path.display()))?,
+            Err(e) => debug!("failed to link `{}` to the store: {}", path.display(), e),
+        }
+    }
+    Ok(())
+}
+
+/// The path of the file of the store for the file at `path`, and the hash
+/// of its contents.
+fn stored_path(store: &Path, path: &Path) -> CargoResult<(PathBuf, String)> {
+    let hash = Sha256::new().update_path(path)?.finish_hex();
+    let name = if is_executable(path)? {
+        format!("{}-x", hash)
+    } else {
+        hash.clone()
+    };
+    Ok((store.join(&name[..2]).join(name), hash))
+}
+
+/// Makes the file at `path` read-only, along with the hard links to it.
+fn set_readonly(path: &Path) -> CargoResult<()> {
+    let mut permissions = fs::metadata(path)?.permissions();
+    permissions.set_readonly(true);
+    fs::set_permissions(path, permissions)
+        .with_context(|| format!("failed to make `{}` read-only", path.display()))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> CargoResult<bool> {
+    use std::os::unix::fs::PermissionsExt;
+    Ok(fs::metadata(path)?.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_path: &Path) -> CargoResult<bool> {
+    Ok(false)
+}
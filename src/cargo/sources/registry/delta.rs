@@ -0,0 +1,64 @@
+//! The delta downloads of `-Z delta-downloads`.
+//!
+//! A registry can publish patches between the `.crate` files of two versions
+//! of a crate, at the `delta` URL template of its `config.json`. When a
+//! version isn't cached yet but an older version of the crate is, the patch
+//! from the newest of the older versions is downloaded instead of the whole
+//! `.crate` file, and applied to the cached one.
+//!
+//! The patches are zstd patches, as made by
+//! `zstd --patch-from=<from>.crate <to>.crate`, and are applied with the
+//! `zstd` command, so nothing changes if it isn't installed. The result must
+//! have the checksum of the index, like a full download. If the patch is
+//! missing, or can't be applied, the crate is downloaded in full.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use cargo_util::{paths, ProcessBuilder};
+use semver::Version;
+
+use crate::core::PackageId;
+use crate::ops;
+use crate::util::{CargoResult, Config};
+
+/// The marker of the version a patch applies to in the `delta` template.
+pub const FROM_VERSION_TEMPLATE: &str = "{from-version}";
+
+/// The newest cached version of the crate of `pkg` which is older than it,
+/// and the path of its `.crate` file.
+pub fn base(cache_path: &Path, pkg: PackageId) -> Option<(Version, PathBuf)> {
+    let prefix = format!("{}-", pkg.name());
+    fs::read_dir(cache_path)
+        .ok()?
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let name = entry.file_name().into_string().ok()?;
+            let version = name.strip_prefix(&prefix)?.strip_suffix(".crate")?;
+            let version = Version::parse(version).ok()?;
+            let len = entry.metadata().ok()?.len();
+            (version < *pkg.version() && len > 0).then(|| (version, entry.path()))
+        })
+        .max_by(|a, b| a.0.cmp(&b.0))
+}
+
+/// Downloads the patch at `url` and applies it to the `.crate` file `base`,
+/// returning the contents of the new `.crate` file.
+pub fn apply(config: &Config, url: &str, base: &Path) -> CargoResult<Vec<u8>> {
+    let patch = ops::http_get(config, url)?;
+    let tmp = tempfile::Builder::new().prefix("cargo-delta").tempdir()?;
+    let patch_path = tmp.path().join("patch.zst");
+    let output = tmp.path().join("output.crate");
+    paths::write(&patch_path, &patch)?;
+
+    let mut patch_from = std::ffi::OsString::from("--patch-from=");
+    patch_from.push(base);
+    ProcessBuilder::new("zstd")
+        .args(&["-d", "-q", "-f", "--long=31"])
+        .arg(patch_from)
+        .arg(&patch_path)
+        .arg("-o")
+        .arg(&output)
+        .exec_with_output()?;
+    paths::read_bytes(&output)
+}
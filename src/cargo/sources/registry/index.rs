@@ -835,6 +835,7 @@ impl IndexSummary {
             features2,
             yanked,
             links,
+            rust_version,
             v,
         } = serde_json::from_slice(line)?;
         let v = v.unwrap_or(1);
@@ -851,6 +852,7 @@ impl IndexSummary {
         }
         let mut summary = Summary::new(config, pkgid, deps, &features, links)?;
         summary.set_checksum(cksum);
+        summary.set_rust_version(rust_version);
         Ok(IndexSummary {
             summary,
             yanked: yanked.unwrap_or(false),
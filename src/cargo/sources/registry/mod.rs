@@ -166,6 +166,8 @@ use std::io::Write;
 use std::path::{Path, PathBuf};
 
 use anyhow::Context as _;
+use cargo_util::paths;
+use filetime::FileTime;
 use flate2::read::GzDecoder;
 use log::debug;
 use semver::Version;
@@ -216,6 +218,9 @@ pub struct RegistrySource<'cfg> {
     ops: Box<dyn RegistryData + 'cfg>,
     /// Interface for managing the on-disk index.
     index: index::RegistryIndex<'cfg>,
+    /// The checksum database the checksums of the index are checked against
+    /// with `-Z sumdb`.
+    sumdb: Option<sumdb::SumDb<'cfg>>,
     /// A set of packages that should be allowed to be used, even if they are
     /// yanked.
     ///
@@ -251,6 +256,15 @@ pub struct RegistryConfig {
     /// operations like yanks, owner modifications, publish new crates, etc.
     /// If this is None, the registry does not support API commands.
     pub api: Option<String>,
+
+    /// Download endpoint for the patches between the `.crate` files of two
+    /// versions of a crate, used with `-Z delta-downloads`.
+    ///
+    /// The string is a template with the same markers as `dl`, for the new
+    /// version, and `{from-version}`, which will be replaced with the version
+    /// the patch applies to. If this is None, crates are always downloaded
+    /// in full.
+    pub delta: Option<String>,
 }
 
 /// The maximum version of the `v` field in the index this version of cargo
@@ -283,6 +297,11 @@ pub struct RegistryPackage<'a> {
     /// Added early 2018 (see <https://github.com/rust-lang/cargo/pull/4978>),
     /// can be `None` if published before then.
     links: Option<InternedString>,
+    /// The `rust-version` of the package.
+    ///
+    /// Added with `cargo search --rust-version`, can be `None` if published
+    /// before then, or without a `rust-version`.
+    rust_version: Option<InternedString>,
     /// The schema version for this entry.
     ///
     /// If this is None, it defaults to version 1. Entries with unknown
@@ -523,11 +542,17 @@ pub enum MaybeLock {
     Download { url: String, descriptor: String },
 }
 
+mod cas;
+mod delta;
 mod index;
 mod local;
 mod remote;
+mod sumdb;
+
+pub(crate) use self::sumdb::SumDb;
 
-fn short_name(id: SourceId) -> String {
+/// The name of the directories of the registry `id` in the Cargo home.
+pub(crate) fn short_name(id: SourceId) -> String {
     let hash = hex::short_hash(&id);
     let ident = id.url().host_str().unwrap_or("").to_string();
     format!("{}-{}", ident, hash)
@@ -568,6 +593,11 @@ impl<'cfg> RegistrySource<'cfg> {
             source_id,
             updated: false,
             index: index::RegistryIndex::new(source_id, ops.index_path(), config),
+            sumdb: if config.cli_unstable().sumdb {
+                Some(sumdb::SumDb::new(source_id, config, name))
+            } else {
+                None
+            },
             yanked_whitelist: yanked_whitelist.clone(),
             ops,
         }
@@ -595,6 +625,8 @@ impl<'cfg> RegistrySource<'cfg> {
         let unpack_dir = path.parent().unwrap();
         if let Ok(meta) = path.metadata() {
             if meta.len() > 0 {
+                // Records the use of the source for `cargo cache prune`.
+                paths::set_file_time_no_err(&path, FileTime::now());
                 return Ok(unpack_dir.to_path_buf());
             }
         }
@@ -638,6 +670,10 @@ impl<'cfg> RegistrySource<'cfg> {
                 .with_context(|| format!("failed to unpack entry at `{}`", entry_path.display()))?;
         }
 
+        if self.config.cli_unstable().registry_dedup {
+            cas::dedup(self.config, unpack_dir)?;
+        }
+
         // The lock file is created after unpacking so we overwrite a lock file
         // which may have been extracted from the package.
         let mut ok = OpenOptions::new()
@@ -758,6 +794,9 @@ impl<'cfg> Source for RegistrySource<'cfg> {
 
     fn download(&mut self, package: PackageId) -> CargoResult<MaybePackage> {
         let hash = self.index.hash(package, &mut *self.ops)?;
+        if let Some(sumdb) = &mut self.sumdb {
+            sumdb.check(package, hash)?;
+        }
         match self.ops.download(package, hash)? {
             MaybeLock::Ready(file) => self.get_pkg(package, &file).map(MaybePackage::Ready),
             MaybeLock::Download { url, descriptor } => {
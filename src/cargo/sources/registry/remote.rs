@@ -1,5 +1,6 @@
 use crate::core::{GitReference, PackageId, SourceId};
 use crate::sources::git;
+use crate::sources::registry::delta;
 use crate::sources::registry::MaybeLock;
 use crate::sources::registry::{
     RegistryConfig, RegistryData, CHECKSUM_TEMPLATE, CRATE_TEMPLATE, LOWER_PREFIX_TEMPLATE,
@@ -263,6 +264,14 @@ impl<'cfg> RegistryData for RemoteRegistry<'cfg> {
         }
 
         let config = self.config()?.unwrap();
+        if self.config.cli_unstable().delta_downloads {
+            if let Some(template) = &config.delta {
+                if let Some(dst) = self.download_delta(pkg, checksum, template)? {
+                    return Ok(MaybeLock::Ready(dst));
+                }
+            }
+        }
+
         let mut url = config.dl;
         if !url.contains(CRATE_TEMPLATE)
             && !url.contains(VERSION_TEMPLATE)
@@ -272,13 +281,7 @@ impl<'cfg> RegistryData for RemoteRegistry<'cfg> {
         {
             write!(url, "/{}/{}/download", CRATE_TEMPLATE, VERSION_TEMPLATE).unwrap();
         }
-        let prefix = make_dep_path(&*pkg.name(), true);
-        let url = url
-            .replace(CRATE_TEMPLATE, &*pkg.name())
-            .replace(VERSION_TEMPLATE, &pkg.version().to_string())
-            .replace(PREFIX_TEMPLATE, &prefix)
-            .replace(LOWER_PREFIX_TEMPLATE, &prefix.to_lowercase())
-            .replace(CHECKSUM_TEMPLATE, checksum);
+        let url = expand_template(&url, pkg, checksum);
 
         Ok(MaybeLock::Download {
             url,
@@ -331,6 +334,55 @@ impl<'cfg> RegistryData for RemoteRegistry<'cfg> {
     }
 }
 
+impl<'cfg> RemoteRegistry<'cfg> {
+    /// Downloads `pkg` as a patch of a cached older version, see
+    /// `registry::delta`. Returns `None` if it must be downloaded in full.
+    fn download_delta(
+        &mut self,
+        pkg: PackageId,
+        checksum: &str,
+        template: &str,
+    ) -> CargoResult<Option<File>> {
+        let cache_path = self.config.assert_package_cache_locked(&self.cache_path);
+        let (from, base) = match delta::base(cache_path, pkg) {
+            Some(base) => base,
+            None => return Ok(None),
+        };
+        let url = expand_template(template, pkg, checksum)
+            .replace(delta::FROM_VERSION_TEMPLATE, &from.to_string());
+        let data = match delta::apply(self.config, &url, &base) {
+            Ok(data) => data,
+            Err(e) => {
+                debug!("failed to download the delta of `{}`: {:?}", pkg, e);
+                return Ok(None);
+            }
+        };
+        if Sha256::new().update(&data).finish_hex() != checksum {
+            self.config.shell().warn(format!(
+                "the delta of `{}` from v{} does not match its checksum, \
+                 downloading it in full",
+                pkg, from
+            ))?;
+            return Ok(None);
+        }
+        self.config
+            .shell()
+            .status("Downloaded", format!("{} (delta from v{})", pkg, from))?;
+        self.finish_download(pkg, checksum, &data).map(Some)
+    }
+}
+
+/// Replaces the markers of a download URL template for `pkg`.
+fn expand_template(template: &str, pkg: PackageId, checksum: &str) -> String {
+    let prefix = make_dep_path(&*pkg.name(), true);
+    template
+        .replace(CRATE_TEMPLATE, &*pkg.name())
+        .replace(VERSION_TEMPLATE, &pkg.version().to_string())
+        .replace(PREFIX_TEMPLATE, &prefix)
+        .replace(LOWER_PREFIX_TEMPLATE, &prefix.to_lowercase())
+        .replace(CHECKSUM_TEMPLATE, checksum)
+}
+
 impl<'cfg> Drop for RemoteRegistry<'cfg> {
     fn drop(&mut self) {
         // Just be sure to drop this before our other fields
@@ -0,0 +1,537 @@
+//! The checksum database of `-Z sumdb`.
+//!
+//! The checksum of every crate downloaded from a registry is recorded in a
+//! local database the first time it is used, in
+//! `$CARGO_HOME/registry/sumdb/$REG-HASH/sums`, with a line of
+//! `<name> <version> <checksum>` for each crate. Afterwards the checksum in the
+//! index must stay the same, so a tampered index, or a crate republished with
+//! other contents, is detected.
+//!
+//! If the registry has a `sumdb` URL configured, the checksums are also
+//! verified against that transparency log before they are recorded, and their
+//! line ends with ` log`. A checksum recorded before the log was configured is
+//! verified against it the next time it is checked, and recorded again. The log
+//! is a Merkle tree in the format of [RFC 6962], with a leaf of
+//! `<name> <version> <checksum>\n` for each crate, and answers two requests:
+//!
+//! * `<sumdb>/lookup/<name>/<version>` with the JSON object
+//!   `{"checksum", "leaf-index", "tree-head", "inclusion-proof"}`, the
+//!   checksum of the crate and the proof that its leaf is in the tree.
+//! * `<sumdb>/consistency/<old-size>/<new-size>` with the JSON object
+//!   `{"proof"}`, the proof that the tree of `old-size` leaves is a prefix of
+//!   the tree of `new-size` leaves.
+//!
+//! The tree head is a [signed note] of the log, with the text
+//! `<origin>\n<tree size>\n<root hash in base64>\n`, signed with the Ed25519
+//! key of the log. The verifier key of the log is configured with the
+//! `sumdb-key` of the registry, in the format of signed notes,
+//! `<name>+<key hash>+<key>`, and the origin of the tree heads must be the
+//! name of the key. A tree head is only used once its signature is verified,
+//! so a server that isn't the log can't answer for it.
+//!
+//! The latest tree head that was verified is kept in the `tree` file next to
+//! the sums, and every newer tree head must be consistent with it, so the log
+//! can't rewrite its history without being noticed.
+//!
+//! [RFC 6962]: https://datatracker.ietf.org/doc/html/rfc6962
+//! [signed note]: https://pkg.go.dev/golang.org/x/mod/sumdb/note
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context as _};
+use cargo_util::{paths, Sha256};
+use log::debug;
+use serde::Deserialize;
+
+use crate::core::{PackageId, SourceId};
+use crate::ops;
+use crate::util::config::ConfigValue;
+use crate::util::{base64, CargoResult, Config};
+
+type Hash = [u8; 32];
+
+/// The checksum database of a registry.
+pub struct SumDb<'cfg> {
+    source_id: SourceId,
+    config: &'cfg Config,
+    /// The directory of the database, `$CARGO_HOME/registry/sumdb/$REG-HASH`.
+    path: PathBuf,
+    /// The recorded checksums by name and version, once they are loaded.
+    sums: Option<HashMap<(String, String), Sum>>,
+    /// The size and root hash of the latest verified tree head of the log.
+    tree: Option<(u64, Hash)>,
+}
+
+/// A recorded checksum.
+#[derive(Clone)]
+struct Sum {
+    checksum: String,
+    /// Whether the checksum was verified against the transparency log.
+    logged: bool,
+}
+
+/// The transparency log of a registry, from its configuration.
+struct Log {
+    url: String,
+    key: VerifierKey,
+}
+
+/// The key that signs the tree heads of a log, from `sumdb-key`.
+struct VerifierKey {
+    name: String,
+    hash: [u8; 4],
+    key: ed25519_compact::PublicKey,
+}
+
+/// A crate in the transparency log, from a `lookup` request.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct LogEntry {
+    checksum: String,
+    leaf_index: u64,
+    tree_head: String,
+    inclusion_proof: Vec<String>,
+}
+
+/// The proof that a tree is a prefix of another, from a `consistency` request.
+#[derive(Deserialize)]
+struct Consistency {
+    proof: Vec<String>,
+}
+
+impl<'cfg> SumDb<'cfg> {
+    pub fn new(source_id: SourceId, config: &'cfg Config, name: &str) -> SumDb<'cfg> {
+        SumDb {
+            source_id,
+            config,
+            path: config
+                .home()
+                .join("registry")
+                .join("sumdb")
+                .join(name)
+                .into_path_unlocked(),
+            sums: None,
+            tree: None,
+        }
+    }
+
+    /// Checks the checksum `checksum` of `pkg` from the index against the
+    /// database, and records it if it is new.
+    pub fn check(&mut self, pkg: PackageId, checksum: &str) -> CargoResult<()> {
+        let key = (pkg.name().to_string(), pkg.version().to_string());
+        let recorded = self.sums()?.get(&key).cloned();
+        if let Some(recorded) = &recorded {
+            if recorded.checksum != checksum {
+                bail!(
+                    "the checksum of {} changed since it was recorded in the checksum database\n\
+                     recorded: {}\n\
+                     index:    {}\n\
+                     the index may have been tampered with, or the crate republished with other \
+                     contents, the checksum database is in `{}`",
+                    pkg,
+                    recorded.checksum,
+                    checksum,
+                    self.path.display()
+                );
+            }
+        }
+        let log = self.log()?;
+        let logged = match &log {
+            Some(log) if !self.config.offline() => {
+                if recorded.as_ref().map_or(false, |sum| sum.logged) {
+                    return Ok(());
+                }
+                self.verify_in_log(log, pkg, checksum)
+                    .with_context(|| format!("failed to verify {} in the transparency log", pkg))?;
+                true
+            }
+            // It is recorded once it can be verified.
+            Some(log) => {
+                debug!("not verifying {} in `{}` while offline", pkg, log.url);
+                return Ok(());
+            }
+            None if recorded.is_some() => return Ok(()),
+            None => false,
+        };
+        paths::create_dir_all(&self.path)?;
+        let marker = if logged { " log" } else { "" };
+        paths::append(
+            &self.path.join("sums"),
+            format!("{} {} {}{}\n", key.0, key.1, checksum, marker).as_bytes(),
+        )?;
+        self.sums()?.insert(
+            key,
+            Sum {
+                checksum: checksum.to_string(),
+                logged,
+            },
+        );
+        Ok(())
+    }
+
+    /// How the checksum `checksum` of `pkg` was verified, for the provenance
+    /// of lock files: `transparency-log` if it was verified against the log
+    /// of the registry before it was recorded, `checksum-db` if it is only
+    /// recorded, or `None` if it isn't recorded.
+    pub fn verification(
+        &mut self,
+        pkg: PackageId,
+        checksum: &str,
+    ) -> CargoResult<Option<&'static str>> {
+        let key = (pkg.name().to_string(), pkg.version().to_string());
+        Ok(match self.sums()?.get(&key) {
+            Some(sum) if sum.checksum == checksum && sum.logged => Some("transparency-log"),
+            Some(sum) if sum.checksum == checksum => Some("checksum-db"),
+            _ => None,
+        })
+    }
+
+    /// The recorded checksums, loaded on first use.
+    fn sums(&mut self) -> CargoResult<&mut HashMap<(String, String), Sum>> {
+        if self.sums.is_none() {
+            let mut sums = HashMap::new();
+            let path = self.path.join("sums");
+            if path.exists() {
+                for line in paths::read(&path)?.lines() {
+                    let mut parts = line.split(' ');
+                    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+                        (
+                            Some(name),
+                            Some(version),
+                            Some(checksum),
+                            marker @ (None | Some("log")),
+                        ) => {
+                            sums.insert(
+                                (name.to_string(), version.to_string()),
+                                Sum {
+                                    checksum: checksum.to_string(),
+                                    logged: marker.is_some(),
+                                },
+                            );
+                        }
+                        _ => bail!("invalid line `{}` in `{}`", line, path.display()),
+                    }
+                }
+            }
+            let tree_path = self.path.join("tree");
+            if tree_path.exists() {
+                let tree = paths::read(&tree_path)?;
+                self.tree = match tree.trim().split_once(' ') {
+                    Some((size, root)) => Some((
+                        size.parse()
+                            .with_context(|| format!("invalid `{}`", tree_path.display()))?,
+                        parse_hash(root)?,
+                    )),
+                    None => bail!("invalid `{}`", tree_path.display()),
+                };
+            }
+            self.sums = Some(sums);
+        }
+        Ok(self.sums.as_mut().unwrap())
+    }
+
+    /// The transparency log of the registry, from `registry.sumdb` and
+    /// `registry.sumdb-key` for crates.io, and `registries.<name>.sumdb` and
+    /// `registries.<name>.sumdb-key` for the others.
+    fn log(&self) -> CargoResult<Option<Log>> {
+        if self.source_id.is_default_registry() {
+            return self.log_in("registry");
+        }
+        let names: Vec<String> = match self.config.values()?.get("registries") {
+            Some(ConfigValue::Table(registries, _)) => registries.keys().cloned().collect(),
+            _ => return Ok(None),
+        };
+        for name in &names {
+            let table = format!("registries.{}", name);
+            if self
+                .config
+                .get_string(&format!("{}.sumdb", table))?
+                .is_none()
+            {
+                continue;
+            }
+            let id = SourceId::alt_registry(self.config, name)?;
+            if id.canonical_url() == self.source_id.canonical_url() {
+                return self.log_in(&table);
+            }
+        }
+        Ok(None)
+    }
+
+    fn log_in(&self, table: &str) -> CargoResult<Option<Log>> {
+        let url = match self.config.get_string(&format!("{}.sumdb", table))? {
+            Some(url) => url.val,
+            None => return Ok(None),
+        };
+        let key = match self.config.get_string(&format!("{}.sumdb-key", table))? {
+            Some(key) => parse_verifier_key(&key.val)
+                .with_context(|| format!("invalid `{}.sumdb-key` in {}", table, key.definition))?,
+            None => bail!(
+                "the transparency log `{}` has no `{}.sumdb-key`, \
+                 the public key of the log is needed to verify its tree heads",
+                url,
+                table
+            ),
+        };
+        Ok(Some(Log {
+            url: url.trim_end_matches('/').to_string(),
+            key,
+        }))
+    }
+
+    /// Verifies that the log `log` has `checksum` for `pkg`, and that its tree
+    /// is consistent with the latest one verified.
+    fn verify_in_log(&mut self, log: &Log, pkg: PackageId, checksum: &str) -> CargoResult<()> {
+        let url = format!("{}/lookup/{}/{}", log.url, pkg.name(), pkg.version());
+        let entry: LogEntry = serde_json::from_slice(&ops::http_get(self.config, &url)?)
+            .with_context(|| format!("invalid response from `{}`", url))?;
+        if entry.checksum != checksum {
+            bail!(
+                "the checksum of {} in the index differs from the one in the transparency log\n\
+                 log:   {}\n\
+                 index: {}\n\
+                 the index may have been tampered with",
+                pkg,
+                entry.checksum,
+                checksum
+            );
+        }
+        let (size, root) = verify_tree_head(&log.key, &entry.tree_head)
+            .with_context(|| format!("invalid tree head from `{}`", url))?;
+        let leaf = leaf_hash(format!("{} {} {}\n", pkg.name(), pkg.version(), checksum).as_bytes());
+        let proof = parse_hashes(&entry.inclusion_proof)?;
+        if root_from_inclusion_proof(entry.leaf_index, size, leaf, &proof) != Some(root) {
+            bail!("the inclusion proof from `{}` is invalid", url);
+        }
+
+        let new = (size, root);
+        let old = match self.tree {
+            Some(old) if old == new => return Ok(()),
+            Some(old) => old,
+            None => return self.save_tree(new),
+        };
+        let (first, second) = if old.0 <= new.0 {
+            (old, new)
+        } else {
+            (new, old)
+        };
+        let proof = if first.0 == second.0 {
+            Vec::new()
+        } else {
+            let url = format!("{}/consistency/{}/{}", log.url, first.0, second.0);
+            let consistency: Consistency =
+                serde_json::from_slice(&ops::http_get(self.config, &url)?)
+                    .with_context(|| format!("invalid response from `{}`", url))?;
+            parse_hashes(&consistency.proof)?
+        };
+        if !verify_consistency_proof(first.0, second.0, first.1, second.1, &proof) {
+            bail!(
+                "the transparency log `{}` is not consistent with the tree of {} entries \
+                 verified before, its history may have been rewritten",
+                log.url,
+                old.0
+            );
+        }
+        if new.0 > old.0 {
+            self.save_tree(new)?;
+        }
+        Ok(())
+    }
+
+    fn save_tree(&mut self, tree: (u64, Hash)) -> CargoResult<()> {
+        paths::create_dir_all(&self.path)?;
+        paths::write(
+            &self.path.join("tree"),
+            format!("{} {}\n", tree.0, hex::encode(tree.1)),
+        )?;
+        self.tree = Some(tree);
+        Ok(())
+    }
+}
+
+/// Parses a verifier key of signed notes, `<name>+<key hash>+<key>`, where
+/// the key is the base64 of the algorithm, 1 for Ed25519, and the public key,
+/// and the key hash is the first 4 bytes of the SHA-256 of the name, a
+/// newline and the key.
+fn parse_verifier_key(key: &str) -> CargoResult<VerifierKey> {
+    let mut parts = key.split('+');
+    let (name, hash, key) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(name), Some(hash), Some(key), None) if !name.is_empty() => (name, hash, key),
+        _ => bail!("expected `<name>+<key hash>+<key>`, found `{}`", key),
+    };
+    let mut key_hash = [0; 4];
+    hex::decode_to_slice(hash, &mut key_hash)
+        .with_context(|| format!("invalid key hash `{}`", hash))?;
+    let key = match base64::decode(key) {
+        Some(key) if key.len() == 33 && key[0] == 1 => key,
+        _ => bail!(
+            "expected the base64 of an Ed25519 public key, found `{}`",
+            key
+        ),
+    };
+    let expected = Sha256::new()
+        .update(name.as_bytes())
+        .update(b"\n")
+        .update(&key)
+        .finish();
+    if expected[..4] != key_hash {
+        bail!("the key hash `{}` doesn't match the key", hash);
+    }
+    Ok(VerifierKey {
+        name: name.to_string(),
+        hash: key_hash,
+        key: ed25519_compact::PublicKey::from_slice(&key[1..])
+            .map_err(|_| anyhow::format_err!("invalid Ed25519 public key"))?,
+    })
+}
+
+/// Verifies the signature of the signed note `note` with `key`, and returns
+/// the tree size and root hash of the tree head in it.
+fn verify_tree_head(key: &VerifierKey, note: &str) -> CargoResult<(u64, Hash)> {
+    let split = match note.rfind("\n\n") {
+        Some(split) => split,
+        None => bail!("the tree head isn't a signed note"),
+    };
+    let (text, signatures) = (&note[..split + 1], &note[split + 2..]);
+    let signed = signatures.lines().any(|line| {
+        let signature = match line
+            .strip_prefix("\u{2014} ")
+            .and_then(|line| line.split_once(' '))
+        {
+            Some((name, signature)) if name == key.name => signature,
+            _ => return false,
+        };
+        match base64::decode(signature) {
+            Some(signature) if signature.len() == 68 && signature[..4] == key.hash => {
+                ed25519_compact::Signature::from_slice(&signature[4..])
+                    .and_then(|signature| key.key.verify(text, &signature))
+                    .is_ok()
+            }
+            _ => false,
+        }
+    });
+    if !signed {
+        bail!("the tree head isn't signed by the key `{}`", key.name);
+    }
+    let mut lines = text.lines();
+    match (lines.next(), lines.next(), lines.next(), lines.next()) {
+        (Some(origin), Some(size), Some(root), None) if origin == key.name => {
+            let size = size
+                .parse()
+                .with_context(|| format!("invalid tree size `{}`", size))?;
+            match base64::decode(root) {
+                Some(root) if root.len() == 32 => Ok((size, root.try_into().unwrap())),
+                _ => bail!("invalid root hash `{}`", root),
+            }
+        }
+        _ => bail!(
+            "expected a tree head of the log `{}`, found `{}`",
+            key.name,
+            text.trim_end()
+        ),
+    }
+}
+
+fn parse_hash(hash: &str) -> CargoResult<Hash> {
+    let mut result = [0; 32];
+    hex::decode_to_slice(hash, &mut result)
+        .with_context(|| format!("invalid hash `{}` from the transparency log", hash))?;
+    Ok(result)
+}
+
+fn parse_hashes(hashes: &[String]) -> CargoResult<Vec<Hash>> {
+    hashes.iter().map(|hash| parse_hash(hash)).collect()
+}
+
+fn leaf_hash(leaf: &[u8]) -> Hash {
+    Sha256::new().update(&[0]).update(leaf).finish()
+}
+
+fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    Sha256::new()
+        .update(&[1])
+        .update(left)
+        .update(right)
+        .finish()
+}
+
+/// The root hash of the tree of `size` leaves in which `leaf` is at `index`
+/// according to `proof`, from section 2.1.3.2 of RFC 9162.
+fn root_from_inclusion_proof(index: u64, size: u64, leaf: Hash, proof: &[Hash]) -> Option<Hash> {
+    if index >= size {
+        return None;
+    }
+    let (mut f, mut s) = (index, size - 1);
+    let mut root = leaf;
+    for p in proof {
+        if s == 0 {
+            return None;
+        }
+        if f & 1 == 1 || f == s {
+            root = node_hash(p, &root);
+            while f & 1 == 0 && f != 0 {
+                f >>= 1;
+                s >>= 1;
+            }
+        } else {
+            root = node_hash(&root, p);
+        }
+        f >>= 1;
+        s >>= 1;
+    }
+    if s == 0 {
+        Some(root)
+    } else {
+        None
+    }
+}
+
+/// Whether the tree of `first` leaves with the root `first_root` is a prefix
+/// of the tree of `second` leaves with the root `second_root` according to
+/// `proof`, from section 2.1.4.2 of RFC 9162.
+fn verify_consistency_proof(
+    first: u64,
+    second: u64,
+    first_root: Hash,
+    second_root: Hash,
+    proof: &[Hash],
+) -> bool {
+    if first == second {
+        return proof.is_empty() && first_root == second_root;
+    }
+    if first == 0 || first > second {
+        return false;
+    }
+    let mut proof = proof.to_vec();
+    if first.is_power_of_two() {
+        proof.insert(0, first_root);
+    }
+    let (mut f, mut s) = (first - 1, second - 1);
+    while f & 1 == 1 {
+        f >>= 1;
+        s >>= 1;
+    }
+    let (mut fr, mut sr) = match proof.first() {
+        Some(hash) => (*hash, *hash),
+        None => return false,
+    };
+    for c in &proof[1..] {
+        if s == 0 {
+            return false;
+        }
+        if f & 1 == 1 || f == s {
+            fr = node_hash(c, &fr);
+            sr = node_hash(c, &sr);
+            while f & 1 == 0 && f != 0 {
+                f >>= 1;
+                s >>= 1;
+            }
+        } else {
+            sr = node_hash(&sr, c);
+        }
+        f >>= 1;
+        s >>= 1;
+    }
+    s == 0 && fr == first_root && sr == second_root
+}
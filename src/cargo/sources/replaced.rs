@@ -116,6 +116,10 @@ impl<'cfg> Source for ReplacedSource<'cfg> {
         true
     }
 
+    fn locked_revision(&self) -> Option<String> {
+        self.inner.locked_revision()
+    }
+
     fn add_to_yanked_whitelist(&mut self, pkgs: &[PackageId]) {
         let pkgs = pkgs
             .iter()
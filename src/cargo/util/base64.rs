@@ -0,0 +1,64 @@
+//! The standard base64 encoding, for the SSH host key fingerprints and the
+//! signed tree heads of the checksum database.
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encodes `data` in base64, with padding.
+pub fn encode(data: &[u8]) -> String {
+    let mut encoded = encode_unpadded(data);
+    while encoded.len() % 4 != 0 {
+        encoded.push('=');
+    }
+    encoded
+}
+
+/// Encodes `data` in base64, without padding, as in the fingerprints of
+/// OpenSSH.
+pub fn encode_unpadded(data: &[u8]) -> String {
+    let mut encoded = String::new();
+    for chunk in data.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, &byte)| {
+            bits | (byte as u32) << (16 - 8 * i)
+        });
+        for i in 0..=chunk.len() {
+            encoded.push(BASE64[(bits >> (18 - 6 * i) & 63) as usize] as char);
+        }
+    }
+    encoded
+}
+
+/// Decodes base64, with or without padding.
+pub fn decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::new();
+    let mut bits = 0u32;
+    let mut count = 0;
+    for c in encoded.trim_end_matches('=').bytes() {
+        let value = BASE64.iter().position(|&b| b == c)? as u32;
+        bits = bits << 6 | value;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            decoded.push((bits >> count) as u8);
+        }
+    }
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        for data in [&b""[..], b"f", b"fo", b"foo", b"foob", b"fooba", b"foobar"] {
+            assert_eq!(decode(&encode_unpadded(data)).unwrap(), data);
+            assert_eq!(decode(&encode(data)).unwrap(), data);
+        }
+        assert_eq!(encode_unpadded(b"foobar"), "Zm9vYmFy");
+        assert_eq!(encode_unpadded(b"fooba"), "Zm9vYmE");
+        assert_eq!(encode(b"fooba"), "Zm9vYmE=");
+        assert_eq!(encode(b"foob"), "Zm9vYg==");
+        assert_eq!(decode("Zm9vYg==").unwrap(), b"foob");
+        assert_eq!(decode("Zm9v!"), None);
+    }
+}
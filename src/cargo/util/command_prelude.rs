@@ -1,7 +1,7 @@
 use crate::core::compiler::{BuildConfig, MessageFormat};
 use crate::core::resolver::CliFeatures;
 use crate::core::{Edition, Workspace};
-use crate::ops::{CompileFilter, CompileOptions, NewOptions, Packages, VersionControl};
+use crate::ops::{CompileFilter, CompileOptions, NewOptions, Packages, TargetExpr, VersionControl};
 use crate::sources::CRATES_IO_REGISTRY;
 use crate::util::important_paths::find_root_manifest_for_wd;
 use crate::util::interning::InternedString;
@@ -38,6 +38,13 @@ pub trait AppExt: Sized {
     ) -> Self {
         self.arg_package_spec_no_all(package, all, exclude)
             ._arg(opt("all", "Alias for --workspace (deprecated)"))
+            ._arg(
+                opt(
+                    "changed-since",
+                    "Select members with changes since the git revision REV (unstable)",
+                )
+                .value_name("REV"),
+            )
     }
 
     /// Variant of arg_package_spec that does not include the `--all` flag
@@ -52,6 +59,11 @@ pub trait AppExt: Sized {
         self.arg_package_spec_simple(package)
             ._arg(opt("workspace", all))
             ._arg(multi_opt("exclude", "SPEC", exclude))
+            ._arg(multi_opt(
+                "group",
+                "NAME",
+                "Select the members of a workspace group (unstable)",
+            ))
     }
 
     fn arg_package_spec_simple(self, package: &'static str) -> Self {
@@ -93,6 +105,13 @@ pub trait AppExt: Sized {
             ._arg(optional_multi_opt("bench", "NAME", bench))
             ._arg(opt("benches", benches))
             ._arg(opt("all-targets", all))
+            ._arg(
+                opt(
+                    "targets",
+                    "Select targets matching the expression EXPR (unstable)",
+                )
+                .value_name("EXPR"),
+            )
     }
 
     fn arg_targets_lib_bin_example(
@@ -141,6 +160,13 @@ pub trait AppExt: Sized {
         ))
     }
 
+    fn arg_auto_features(self) -> Self {
+        self._arg(opt(
+            "auto-features",
+            "Activate the required features of the selected targets (unstable)",
+        ))
+    }
+
     fn arg_release(self, release: &'static str) -> Self {
         self._arg(opt("release", release))
     }
@@ -157,6 +183,27 @@ pub trait AppExt: Sized {
         self._arg(multi_opt("target", "TRIPLE", target))
     }
 
+    fn arg_auto_install_targets(self) -> Self {
+        self._arg(opt(
+            "auto-install-targets",
+            "Install the standard library of the targets when it is missing (unstable)",
+        ))
+    }
+
+    fn arg_warnings(self) -> Self {
+        self._arg(
+            opt(
+                "warnings",
+                "How warnings are reported: `full`, `summary` or `deny` (unstable)",
+            )
+            .value_name("MODE"),
+        )
+        ._arg(opt(
+            "deny-warnings",
+            "Deny the warnings of the selected packages, but not of their dependencies (unstable)",
+        ))
+    }
+
     fn arg_target_dir(self) -> Self {
         self._arg(
             opt("target-dir", "Directory for all generated artifacts").value_name("DIRECTORY"),
@@ -223,6 +270,20 @@ pub trait AppExt: Sized {
         self._arg(opt("dry-run", dry_run))
     }
 
+    fn arg_minimal_versions(self) -> Self {
+        self._arg(opt(
+            "minimal-versions",
+            "Resolve the dependencies to their minimal versions",
+        ))
+        ._arg(
+            opt(
+                "direct-minimal-versions",
+                "Resolve the direct dependencies to their minimal versions",
+            )
+            .conflicts_with("minimal-versions"),
+        )
+    }
+
     fn arg_ignore_rust_version(self) -> Self {
         self._arg(opt(
             "ignore-rust-version",
@@ -336,10 +397,45 @@ pub trait ArgMatchesExt {
 
     fn workspace<'a>(&self, config: &'a Config) -> CargoResult<Workspace<'a>> {
         let root = self.root_manifest(config)?;
+        if config.cli_unstable().source_modes {
+            let profile = match self._value_of("profile") {
+                _ if self._is_present("release") => "release",
+                Some(profile) => profile,
+                None => "dev",
+            };
+            config.set_source_mode_profile(profile);
+        }
         let mut ws = Workspace::new(&root, config)?;
         if config.cli_unstable().avoid_dev_deps {
             ws.set_require_optional_deps(false);
         }
+        ws.set_minimal_versions(
+            self._is_present("minimal-versions"),
+            self._is_present("direct-minimal-versions"),
+        );
+        if let Some(name) = self._value_of("lockfile") {
+            if !config.cli_unstable().named_lockfiles {
+                bail!("Usage of `--lockfile` requires `-Z named-lockfiles`")
+            }
+            ws.set_lockfile_name(name)?;
+        }
+        for value in self._values_of("override") {
+            if !config.cli_unstable().temporary_overrides {
+                bail!("Usage of `--override` requires `-Z temporary-overrides`")
+            }
+            match value.split_once('=') {
+                Some((name, path)) if !name.is_empty() && !path.is_empty() => {
+                    ws.add_dependency_override(
+                        name,
+                        paths::normalize_path(&config.cwd().join(path)),
+                    );
+                }
+                _ => bail!(
+                    "invalid override `{}`, expected `NAME=PATH`, like `bar=../bar-fork`",
+                    value
+                ),
+            }
+        }
         Ok(ws)
     }
 
@@ -423,6 +519,7 @@ pub trait ArgMatchesExt {
             self._is_present("workspace") || self._is_present("all"),
             self._values_of("exclude"),
             self._values_of("package"),
+            self._values_of("group"),
         )
     }
 
@@ -433,7 +530,19 @@ pub trait ArgMatchesExt {
         workspace: Option<&Workspace<'_>>,
         profile_checking: ProfileChecking,
     ) -> CargoResult<CompileOptions> {
-        let spec = self.packages_from_flags()?;
+        let mut spec = self.packages_from_flags()?;
+        if let Some(rev) = self._value_of("changed-since") {
+            if !config.cli_unstable().changed_since {
+                bail!("Usage of `--changed-since` requires `-Z changed-since`")
+            }
+            if spec != Packages::Default {
+                bail!(
+                    "--changed-since cannot be used together with \
+                     --workspace, --package, --exclude or --group"
+                )
+            }
+            spec = Packages::ChangedSince(rev.to_string());
+        }
         let mut message_format = None;
         let default_json = MessageFormat::Json {
             short: false,
@@ -502,6 +611,7 @@ pub trait ArgMatchesExt {
         build_config.build_plan = self._is_present("build-plan");
         build_config.unit_graph = self._is_present("unit-graph");
         build_config.future_incompat_report = self._is_present("future-incompat-report");
+        build_config.auto_install_targets = self._is_present("auto-install-targets");
         if build_config.build_plan {
             config
                 .cli_unstable()
@@ -524,7 +634,33 @@ pub trait ArgMatchesExt {
             }
         }
 
-        let opts = CompileOptions {
+        if build_config.auto_install_targets && !config.cli_unstable().auto_install_targets {
+            bail!("Usage of `--auto-install-targets` requires `-Z auto-install-targets`")
+        }
+
+        if let Some(warnings) = self._value_of("warnings") {
+            if !config.cli_unstable().warnings {
+                bail!("Usage of `--warnings` requires `-Z warnings`")
+            }
+            build_config.warnings = warnings.parse()?;
+        }
+
+        build_config.deny_warnings = self._is_present("deny-warnings");
+        if build_config.deny_warnings && !config.cli_unstable().deny_warnings {
+            bail!("Usage of `--deny-warnings` requires `-Z deny-warnings`")
+        }
+
+        let target_expr = match self._value_of("targets") {
+            Some(expr) => {
+                if !config.cli_unstable().target_expr {
+                    bail!("Usage of `--targets` requires `-Z target-expr`")
+                }
+                Some(expr.parse::<TargetExpr>()?)
+            }
+            None => None,
+        };
+
+        let mut opts = CompileOptions {
             build_config,
             cli_features: self.cli_features()?,
             spec,
@@ -542,11 +678,29 @@ pub trait ArgMatchesExt {
             ),
             target_rustdoc_args: None,
             target_rustc_args: None,
+            target_rustc_crate_types: None,
             local_rustdoc_args: None,
             rustdoc_document_private_items: false,
             honor_rust_version: !self._is_present("ignore-rust-version"),
+            target_expr,
+            auto_features: false,
         };
 
+        if self._is_present("auto-features") {
+            if !config.cli_unstable().auto_features {
+                bail!("Usage of `--auto-features` requires `-Z auto-features`")
+            }
+            opts.auto_features = true;
+        } else if config.cli_unstable().auto_features {
+            opts.auto_features = config.build_config()?.auto_features == Some(true);
+        }
+
+        // An expression selects among all targets, unless other flags have
+        // already narrowed them down.
+        if opts.target_expr.is_some() && !opts.filter.is_specific() {
+            opts.filter = CompileFilter::new_all_targets();
+        }
+
         if let Some(ws) = workspace {
             self.check_optional_opts(ws, &opts)?;
         } else if self.is_present_with_zero_values("package") {
@@ -678,6 +832,17 @@ about this warning.";
         Ok(())
     }
 
+    /// The workspace of a compilation command, which is resolved in memory
+    /// without `Cargo.lock` with `--minimal-versions` or
+    /// `--direct-minimal-versions`, and leaves it alone.
+    fn compile_workspace<'a>(&self, config: &'a Config) -> CargoResult<Workspace<'a>> {
+        let mut ws = self.workspace(config)?;
+        if self._is_present("minimal-versions") || self._is_present("direct-minimal-versions") {
+            ws.set_ignore_lock(true);
+        }
+        Ok(ws)
+    }
+
     fn is_present_with_zero_values(&self, name: &str) -> bool {
         self._is_present(name) && self._value_of(name).is_none()
     }
@@ -50,7 +50,7 @@
 //! desired type.
 
 use std::borrow::Cow;
-use std::cell::{RefCell, RefMut};
+use std::cell::{Cell, RefCell, RefMut};
 use std::collections::hash_map::Entry::{Occupied, Vacant};
 use std::collections::{HashMap, HashSet};
 use std::env;
@@ -71,6 +71,9 @@ use crate::core::shell::Verbosity;
 use crate::core::{features, CliUnstable, Shell, SourceId, Workspace};
 use crate::ops;
 use crate::util::errors::CargoResult;
+use crate::util::http_share::HttpShare;
+use crate::util::network_log::NetworkLog;
+use crate::util::system_proxy::{self, SystemProxy};
 use crate::util::toml as cargo_toml;
 use crate::util::validate_package_name;
 use crate::util::{FileLock, Filesystem, IntoUrl, IntoUrlWithBase, Rustc};
@@ -96,6 +99,9 @@ pub use path::{ConfigRelativePath, PathAndArgs};
 mod target;
 pub use target::{TargetCfgConfig, TargetConfig};
 
+mod toolchain;
+pub use toolchain::{add_to_toolchain, ProviderAction, ToolchainConfig};
+
 // Helper macro for creating typed access methods.
 macro_rules! get_value_typed {
     ($name:ident, $ty:ty, $variant:ident, $expected:expr) => {
@@ -140,6 +146,8 @@ pub struct Config {
     cargo_exe: LazyCell<PathBuf>,
     /// The location of the rustdoc executable
     rustdoc: LazyCell<PathBuf>,
+    /// The directory of the toolchain selected by `[toolchain]`, if any
+    toolchain: LazyCell<Option<PathBuf>>,
     /// Whether we are printing extra verbose messages
     extra_verbose: bool,
     /// `frozen` is the same as `locked`, but additionally will not access the
@@ -180,11 +188,23 @@ pub struct Config {
     http_config: LazyCell<CargoHttpConfig>,
     future_incompat_config: LazyCell<CargoFutureIncompatConfig>,
     net_config: LazyCell<CargoNetConfig>,
+    network_log: LazyCell<Option<NetworkLog>>,
+    /// The connections shared by the HTTP handles, with `http.share-connections`.
+    /// Declared after `easy`, which is dropped first.
+    http_share: LazyCell<HttpShare>,
+    system_proxy: LazyCell<Option<SystemProxy>>,
+    /// The passphrases of the client keys of the registries, by registry.
+    client_key_passphrases: RefCell<HashMap<String, String>>,
     build_config: LazyCell<CargoBuildConfig>,
     target_cfgs: LazyCell<Vec<(String, TargetCfgConfig)>>,
     doc_extern_map: LazyCell<RustdocExternMap>,
     progress_config: ProgressConfig,
     env_config: LazyCell<EnvConfig>,
+    /// Whether the downloads print JSON messages, with `--message-format json`.
+    download_messages: Cell<bool>,
+    /// The profile selected on the command line, the default source mode of
+    /// `-Z source-modes`.
+    source_mode_profile: RefCell<Option<String>>,
     /// This should be false if:
     /// - this is an artifact of the rustc distribution process for "stable" or for "beta"
     /// - this is an `#[test]` that does not opt in with `enable_nightly_features`
@@ -253,6 +273,7 @@ impl Config {
             cli_config: None,
             cargo_exe: LazyCell::new(),
             rustdoc: LazyCell::new(),
+            toolchain: LazyCell::new(),
             extra_verbose: false,
             frozen: false,
             locked: false,
@@ -278,11 +299,17 @@ impl Config {
             http_config: LazyCell::new(),
             future_incompat_config: LazyCell::new(),
             net_config: LazyCell::new(),
+            network_log: LazyCell::new(),
+            http_share: LazyCell::new(),
+            system_proxy: LazyCell::new(),
+            client_key_passphrases: RefCell::new(HashMap::new()),
             build_config: LazyCell::new(),
             target_cfgs: LazyCell::new(),
             doc_extern_map: LazyCell::new(),
             progress_config: ProgressConfig::default(),
             env_config: LazyCell::new(),
+            download_messages: Cell::new(false),
+            source_mode_profile: RefCell::new(None),
             nightly_features_allowed: matches!(&*features::channel(), "nightly" | "dev"),
         }
     }
@@ -344,10 +371,34 @@ impl Config {
     /// Gets the path to the `rustdoc` executable.
     pub fn rustdoc(&self) -> CargoResult<&Path> {
         self.rustdoc
-            .try_borrow_with(|| Ok(self.get_tool("rustdoc", &self.build_config()?.rustdoc)))
+            .try_borrow_with(|| self.get_toolchain_tool("rustdoc", &self.build_config()?.rustdoc))
             .map(AsRef::as_ref)
     }
 
+    /// Gets the directory of the toolchain selected by the `[toolchain]`
+    /// table, installing it first if needed.
+    pub fn toolchain(&self) -> CargoResult<Option<&Path>> {
+        self.toolchain
+            .try_borrow_with(|| toolchain::load_toolchain(self))
+            .map(|dir| dir.as_deref())
+    }
+
+    /// Like `get_tool`, but falls back to the tool of the toolchain selected
+    /// by the `[toolchain]` table before looking in `PATH`.
+    fn get_toolchain_tool(
+        &self,
+        tool: &str,
+        from_config: &Option<ConfigRelativePath>,
+    ) -> CargoResult<PathBuf> {
+        if let Some(path) = self.maybe_get_tool(tool, from_config) {
+            return Ok(path);
+        }
+        Ok(match self.toolchain()? {
+            Some(dir) => toolchain::tool_path(dir, tool),
+            None => PathBuf::from(tool),
+        })
+    }
+
     /// Gets the path to the `rustc` executable.
     pub fn load_global_rustc(&self, ws: Option<&Workspace<'_>>) -> CargoResult<Rustc> {
         let cache_location = ws.map(|ws| {
@@ -362,7 +413,7 @@ impl Config {
         );
 
         Rustc::new(
-            self.get_tool("rustc", &self.build_config()?.rustc),
+            self.get_toolchain_tool("rustc", &self.build_config()?.rustc)?,
             wrapper,
             rustc_workspace_wrapper,
             &self
@@ -1411,11 +1462,6 @@ impl Config {
 
     /// Looks for a path for `tool` in an environment variable or config path, defaulting to `tool`
     /// as a path.
-    fn get_tool(&self, tool: &str, from_config: &Option<ConfigRelativePath>) -> PathBuf {
-        self.maybe_get_tool(tool, from_config)
-            .unwrap_or_else(|| PathBuf::from(tool))
-    }
-
     pub fn jobserver_from_env(&self) -> Option<&jobserver::Client> {
         self.jobserver.as_ref()
     }
@@ -1448,6 +1494,123 @@ impl Config {
             .try_borrow_with(|| self.get::<CargoNetConfig>("net"))
     }
 
+    /// The network log recording or replaying the requests, with
+    /// `net.record` or `net.replay`.
+    pub fn network_log(&self) -> CargoResult<Option<&NetworkLog>> {
+        self.network_log
+            .try_borrow_with(|| NetworkLog::from_config(self))
+            .map(Option::as_ref)
+    }
+
+    /// The connections shared by the HTTP handles.
+    pub fn http_share(&self) -> CargoResult<&HttpShare> {
+        self.http_share.try_borrow_with(HttpShare::new)
+    }
+
+    /// The proxy settings of the operating system, with `-Z proxy-config`,
+    /// unless `http.system-proxy` is `false`.
+    pub fn system_proxy(&self) -> CargoResult<Option<&SystemProxy>> {
+        self.system_proxy
+            .try_borrow_with(|| {
+                let enabled = self.http_config()?.system_proxy;
+                if !self.cli_unstable().proxy_config {
+                    if enabled.is_some() {
+                        bail!("`http.system-proxy` requires `-Z proxy-config`");
+                    }
+                    return Ok(None);
+                }
+                if enabled == Some(false) {
+                    return Ok(None);
+                }
+                let system = system_proxy::detect();
+                if let Some(SystemProxy {
+                    proxy: None,
+                    pac_url: Some(url),
+                    source,
+                    ..
+                }) = &system
+                {
+                    self.shell().warn(format!(
+                        "the {} use the proxy auto-config file `{}`, which Cargo can't \
+                         evaluate, so no proxy is used\n\
+                         Set `http.proxy` to the proxy to use, or set `http.system-proxy` \
+                         to `false` to disable the detection.",
+                        source, url
+                    ))?;
+                }
+                Ok(system)
+            })
+            .map(Option::as_ref)
+    }
+
+    /// The passphrase of the client key of the registry `name`, read with
+    /// `read` the first time it's needed.
+    pub fn client_key_passphrase(
+        &self,
+        name: &str,
+        read: impl FnOnce() -> CargoResult<String>,
+    ) -> CargoResult<String> {
+        if let Some(passphrase) = self.client_key_passphrases.borrow().get(name) {
+            return Ok(passphrase.clone());
+        }
+        let passphrase = read()?;
+        self.client_key_passphrases
+            .borrow_mut()
+            .insert(name.to_string(), passphrase.clone());
+        Ok(passphrase)
+    }
+
+    /// Whether the downloads print the `download-retry` and
+    /// `download-finished` JSON messages.
+    pub fn download_messages(&self) -> bool {
+        self.download_messages.get()
+    }
+
+    pub fn set_download_messages(&self, on: bool) {
+        self.download_messages.set(on);
+    }
+
+    /// Records the profile selected on the command line, which is the source
+    /// mode unless `build.source-mode` is set.
+    pub fn set_source_mode_profile(&self, profile: &str) {
+        *self.source_mode_profile.borrow_mut() = Some(profile.to_string());
+    }
+
+    /// The source mode selecting the `replace-when` replacements of the
+    /// sources, with `-Z source-modes`: `build.source-mode`, or else the
+    /// profile selected on the command line, or else `dev`.
+    pub fn source_mode(&self) -> CargoResult<Option<String>> {
+        let configured = self.build_config()?.source_mode.as_ref();
+        if !self.cli_unstable().source_modes {
+            if configured.is_some() {
+                bail!("`build.source-mode` requires `-Z source-modes`");
+            }
+            return Ok(None);
+        }
+        let mode = match configured {
+            Some(mode) => {
+                let valid = !mode.is_empty()
+                    && mode
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+                if !valid {
+                    bail!(
+                        "invalid `build.source-mode` `{}`, expected a name made of \
+                         letters, digits, `-` and `_`",
+                        mode
+                    );
+                }
+                mode.clone()
+            }
+            None => self
+                .source_mode_profile
+                .borrow()
+                .clone()
+                .unwrap_or_else(|| "dev".to_string()),
+        };
+        Ok(Some(mode))
+    }
+
     pub fn build_config(&self) -> CargoResult<&CargoBuildConfig> {
         self.build_config
             .try_borrow_with(|| self.get::<CargoBuildConfig>("build"))
@@ -2039,6 +2202,10 @@ pub struct CargoHttpConfig {
     pub debug: Option<bool>,
     pub multiplexing: Option<bool>,
     pub ssl_version: Option<SslVersionConfig>,
+    pub version: Option<String>,
+    pub proxy_auth: Option<Vec<String>>,
+    pub share_connections: Option<bool>,
+    pub system_proxy: Option<bool>,
 }
 
 #[derive(Debug, Default, Deserialize, PartialEq)]
@@ -2104,6 +2271,36 @@ pub struct CargoNetConfig {
     pub retry: Option<u32>,
     pub offline: Option<bool>,
     pub git_fetch_with_cli: Option<bool>,
+    pub max_concurrent_downloads: Option<u32>,
+    pub max_download_rate: Option<String>,
+    /// The directory of the network log recording the requests.
+    pub record: Option<ConfigRelativePath>,
+    /// The directory of the network log serving the requests.
+    pub replay: Option<ConfigRelativePath>,
+    /// The SSH settings of the git sources, with `-Z net-ssh`.
+    pub ssh: Option<CargoSshConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CargoSshConfig {
+    /// The private key authenticating to all the hosts.
+    pub key_path: Option<ConfigRelativePath>,
+    /// The trusted host keys, as lines of a `known_hosts` file, or as a host
+    /// and the fingerprint of its key.
+    pub known_hosts: Option<Vec<String>>,
+    /// Whether the key of an unknown host is trusted without prompting.
+    pub accept_new_host_keys: Option<bool>,
+    /// The settings of each host.
+    #[serde(default)]
+    pub hosts: HashMap<String, SshHostConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SshHostConfig {
+    /// The private key authenticating to this host.
+    pub key_path: Option<ConfigRelativePath>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -2122,6 +2319,9 @@ pub struct CargoBuildConfig {
     pub rustc: Option<ConfigRelativePath>,
     pub rustdoc: Option<ConfigRelativePath>,
     pub out_dir: Option<ConfigRelativePath>,
+    pub auto_features: Option<bool>,
+    /// The source mode of `-Z source-modes`.
+    pub source_mode: Option<String>,
 }
 
 #[derive(Deserialize, Default)]
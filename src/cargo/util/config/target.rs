@@ -66,39 +66,20 @@ pub(super) fn load_target_cfgs(config: &Config) -> CargoResult<Vec<(String, Targ
 
 /// Returns true if the `[target]` table should be applied to host targets.
 pub(super) fn get_target_applies_to_host(config: &Config) -> CargoResult<bool> {
-    if config.cli_unstable().target_applies_to_host {
-        if let Ok(target_applies_to_host) = config.get::<bool>("target-applies-to-host") {
-            Ok(target_applies_to_host)
-        } else {
-            Ok(!config.cli_unstable().host_config)
-        }
-    } else if config.cli_unstable().host_config {
-        anyhow::bail!(
-            "the -Zhost-config flag requires the -Ztarget-applies-to-host flag to be set"
-        );
-    } else {
-        Ok(true)
-    }
+    Ok(config
+        .get::<Option<bool>>("target-applies-to-host")?
+        .unwrap_or(false))
 }
 
 /// Loads a single `[host]` table for the given triple.
 pub(super) fn load_host_triple(config: &Config, triple: &str) -> CargoResult<TargetConfig> {
-    if config.cli_unstable().host_config {
-        let host_triple_prefix = format!("host.{}", triple);
-        let host_triple_key = ConfigKey::from_str(&host_triple_prefix);
-        let host_prefix = match config.get_cv(&host_triple_key)? {
-            Some(_) => host_triple_prefix,
-            None => "host".to_string(),
-        };
-        load_config_table(config, &host_prefix)
-    } else {
-        Ok(TargetConfig {
-            runner: None,
-            rustflags: None,
-            linker: None,
-            links_overrides: BTreeMap::new(),
-        })
-    }
+    let host_triple_prefix = format!("host.{}", triple);
+    let host_triple_key = ConfigKey::from_str(&host_triple_prefix);
+    let host_prefix = match config.get_cv(&host_triple_key)? {
+        Some(_) => host_triple_prefix,
+        None => "host".to_string(),
+    };
+    load_config_table(config, &host_prefix)
 }
 
 /// Loads a single `[target]` table for the given triple.
@@ -139,7 +120,8 @@ fn parse_links_overrides(
         // Skip these keys, it shares the namespace with `TargetConfig`.
         match lib_name.as_str() {
             // `ar` is a historical thing.
-            "ar" | "linker" | "runner" | "rustflags" => continue,
+            "ar" | "linker" | "runner" | "runner-protocol" | "runner-jobs" | "rustflags"
+            | "wasm-runtime" | "package-hook" | "signing" => continue,
             _ => {}
         }
         let mut output = BuildOutput::default();
@@ -190,6 +172,10 @@ fn parse_links_overrides(
                     let list = value.list(key)?;
                     output.cfgs.extend(list.iter().map(|v| v.0.clone()));
                 }
+                "rustc-check-cfg" => {
+                    let list = value.list(key)?;
+                    output.check_cfgs.extend(list.iter().map(|v| v.0.clone()));
+                }
                 "rustc-env" => {
                     for (name, val) in value.table(key)?.0 {
                         let val = val.string(name)?.0;
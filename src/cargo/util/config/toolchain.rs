@@ -0,0 +1,273 @@
+//! Selection of the Rust toolchain with the `[toolchain]` config table.
+//!
+//! A toolchain is a directory with the layout of a rustc sysroot, with the
+//! `rustc` and `rustdoc` executables in its `bin` directory. It is either
+//! given directly with `toolchain.path`, or named by `toolchain.version` and
+//! installed on first use into `$CARGO_HOME/toolchains/<version>` by the
+//! process in `toolchain.provider`.
+
+use super::{Config, ConfigRelativePath, PathAndArgs};
+use crate::core::compiler::toolchain_file;
+use crate::util::CargoResult;
+use anyhow::{bail, Context as _};
+use cargo_util::{paths, ProcessError};
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Config definition of the `[toolchain]` table.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ToolchainConfig {
+    /// The directory of a toolchain to use.
+    pub path: Option<ConfigRelativePath>,
+    /// The version of the toolchain to use, installed by `provider`.
+    pub version: Option<String>,
+    /// The process which installs a toolchain version.
+    pub provider: Option<PathAndArgs>,
+    /// Whether the targets and components required by `rust-toolchain.toml`
+    /// are installed with `provider` when they are missing.
+    pub auto_install: Option<bool>,
+}
+
+/// What the toolchain provider is asked to do.
+#[derive(Clone, Copy)]
+pub enum ProviderAction<'a> {
+    /// Installs a toolchain.
+    Install,
+    /// Adds the standard library of a target to an installed toolchain.
+    AddTarget(&'a str),
+    /// Adds a component, like `clippy`, to an installed toolchain.
+    AddComponent(&'a str),
+}
+
+impl ProviderAction<'_> {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ProviderAction::Install => "install",
+            ProviderAction::AddTarget(_) => "add-target",
+            ProviderAction::AddComponent(_) => "add-component",
+        }
+    }
+
+    fn describe(&self, version: &str) -> String {
+        match self {
+            ProviderAction::Install => format!("install toolchain `{}`", version),
+            ProviderAction::AddTarget(name) => format!("add target `{}`", name),
+            ProviderAction::AddComponent(name) => format!("add component `{}`", name),
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            ProviderAction::Install => "",
+            ProviderAction::AddTarget(name) | ProviderAction::AddComponent(name) => name,
+        }
+    }
+}
+
+/// Returns the directory of the toolchain selected by the `[toolchain]`
+/// table, or `None` if the table selects none.
+pub(super) fn load_toolchain(config: &Config) -> CargoResult<Option<PathBuf>> {
+    if !config.cli_unstable().toolchain_config {
+        return Ok(None);
+    }
+    let toolchain: ToolchainConfig = config
+        .get::<Option<ToolchainConfig>>("toolchain")?
+        .unwrap_or_default();
+    let mut version = toolchain.version.clone();
+    if config.cli_unstable().toolchain_file
+        && version.is_none()
+        && toolchain.path.is_none()
+        && toolchain.provider.is_some()
+    {
+        version = toolchain_file::find(config)?.and_then(|file| file.channel);
+    }
+    let dir = match (&toolchain.path, &version) {
+        (None, None) => return Ok(None),
+        (Some(_), Some(_)) => {
+            bail!("`toolchain.path` and `toolchain.version` cannot both be specified")
+        }
+        (Some(path), None) => {
+            let dir = path.resolve_path(config);
+            if !tool_path(&dir, "rustc").exists() {
+                bail!(
+                    "the toolchain in `{}` (from `toolchain.path` in {}) does not contain `bin/rustc`",
+                    dir.display(),
+                    path.value().definition
+                );
+            }
+            dir
+        }
+        (None, Some(version)) => {
+            let provider = match &toolchain.provider {
+                Some(provider) => provider,
+                None => bail!(
+                    "`toolchain.version` requires a `toolchain.provider` to install \
+                     toolchain `{}`",
+                    version
+                ),
+            };
+            install(config, version, provider)?
+        }
+    };
+    Ok(Some(dir))
+}
+
+/// Returns the path of the executable `tool` in the toolchain in `dir`.
+pub(super) fn tool_path(dir: &Path, tool: &str) -> PathBuf {
+    dir.join("bin")
+        .join(tool)
+        .with_extension(env::consts::EXE_EXTENSION)
+}
+
+/// Installs toolchain `version` with `provider` unless it is already
+/// installed, and returns its directory.
+fn install(config: &Config, version: &str, provider: &PathAndArgs) -> CargoResult<PathBuf> {
+    if version.is_empty()
+        || !version
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '+'))
+    {
+        bail!(
+            "invalid `toolchain.version` `{}`, only alphanumeric characters, \
+             `.`, `-`, `_` and `+` are allowed",
+            version
+        );
+    }
+    let toolchains = config.home().join("toolchains");
+    let dir = toolchains.join(version).into_path_unlocked();
+    if tool_path(&dir, "rustc").exists() {
+        return Ok(dir);
+    }
+    if config.offline() {
+        bail!(
+            "toolchain `{}` is not installed, and can't be installed \
+             when the network is disabled (--offline or --frozen)",
+            version
+        );
+    }
+
+    let _lock = config.acquire_package_cache_lock()?;
+    // Another cargo may have installed it while we waited for the lock.
+    if tool_path(&dir, "rustc").exists() {
+        return Ok(dir);
+    }
+    // The provider installs into a staging directory, which is only moved
+    // into place once it is complete, so an interrupted install is retried.
+    let staging = toolchains
+        .join(format!(".{}.partial", version))
+        .into_path_unlocked();
+    if staging.exists() {
+        paths::remove_dir_all(&staging)?;
+    }
+    paths::create_dir_all(&staging)?;
+
+    config
+        .shell()
+        .status("Installing", format!("toolchain `{}`", version))?;
+    run_provider(config, provider, ProviderAction::Install, version, &staging)?;
+    if !tool_path(&staging, "rustc").exists() {
+        bail!(
+            "toolchain provider `{}` did not install `bin/rustc` for toolchain `{}`",
+            provider.path.resolve_program(config).display(),
+            version
+        );
+    }
+    fs::rename(&staging, &dir).with_context(|| {
+        format!(
+            "failed to move `{}` to `{}`",
+            staging.display(),
+            dir.display()
+        )
+    })?;
+    Ok(dir)
+}
+
+/// Asks the provider in `toolchain.provider` to add a target or a component
+/// to the toolchain `version` in `dir`.
+pub fn add_to_toolchain(
+    config: &Config,
+    action: ProviderAction<'_>,
+    version: &str,
+    dir: &Path,
+) -> CargoResult<()> {
+    let toolchain: ToolchainConfig = config
+        .get::<Option<ToolchainConfig>>("toolchain")?
+        .unwrap_or_default();
+    let provider = match &toolchain.provider {
+        Some(provider) => provider,
+        None => bail!("no `toolchain.provider` is configured"),
+    };
+    if !provider.args.iter().any(|arg| arg.contains("{action}")) {
+        bail!(
+            "toolchain provider `{}` cannot be used to {}, \
+             the `toolchain.provider` configuration value must pass the \
+             `{{action}}` argument to support this",
+            provider.path.raw_value(),
+            action.describe(version)
+        );
+    }
+    if config.offline() {
+        bail!(
+            "can't {} when the network is disabled (--offline or --frozen)",
+            action.describe(version)
+        );
+    }
+    let kind = match action {
+        ProviderAction::AddTarget(_) => "target",
+        _ => "component",
+    };
+    config
+        .shell()
+        .status("Installing", format!("{} `{}`", kind, action.name()))?;
+    run_provider(config, provider, action, version, dir)
+}
+
+/// Runs the toolchain provider for `action` on the toolchain `version` in
+/// `dest`.
+fn run_provider(
+    config: &Config,
+    provider: &PathAndArgs,
+    action: ProviderAction<'_>,
+    version: &str,
+    dest: &Path,
+) -> CargoResult<()> {
+    let exe = provider.path.resolve_program(config);
+    let args: Vec<_> = provider
+        .args
+        .iter()
+        .map(|arg| {
+            arg.replace("{action}", action.as_str())
+                .replace("{version}", version)
+                .replace("{dest}", &dest.to_string_lossy())
+                .replace("{name}", action.name())
+        })
+        .collect();
+    let what = action.describe(version);
+    let mut cmd = Command::new(&exe);
+    cmd.args(&args)
+        .env("CARGO", config.cargo_exe()?)
+        .env("CARGO_TOOLCHAIN_ACTION", action.as_str())
+        .env("CARGO_TOOLCHAIN_VERSION", version)
+        .env("CARGO_TOOLCHAIN_DEST", dest)
+        .env("CARGO_TOOLCHAIN_NAME", action.name());
+    let output = cmd.output().with_context(|| {
+        format!(
+            "failed to execute toolchain provider `{}` to {}",
+            exe.display(),
+            what
+        )
+    })?;
+    if !output.status.success() {
+        let err = ProcessError::new(
+            &format!("toolchain provider `{}` failed to {}", exe.display(), what),
+            Some(output.status),
+            Some(&output),
+        );
+        return Err(err.into());
+    }
+    Ok(())
+}
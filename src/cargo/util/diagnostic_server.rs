@@ -1,7 +1,7 @@
 //! A small TCP server to handle collection of diagnostics information in a
 //! cross-platform way for the `cargo fix` command.
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::env;
 use std::io::{BufReader, Read, Write};
 use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
@@ -33,6 +33,7 @@ const PLEASE_REPORT_THIS_BUG: &str =
 #[derive(Deserialize, Serialize, Hash, Eq, PartialEq, Clone)]
 pub enum Message {
     Migrating {
+        package: Option<String>,
         file: String,
         from_edition: Edition,
         to_edition: Edition,
@@ -41,15 +42,39 @@ pub enum Message {
         file: String,
     },
     Fixed {
+        package: Option<String>,
         file: String,
         fixes: u32,
     },
+    /// The fixes of a file checked with `--dry-run`, which were not kept.
+    WouldFix {
+        package: Option<String>,
+        file: String,
+        fixes: Vec<SuggestedFix>,
+        /// The unified diff of the fixes, with `--diff`.
+        patch: Option<String>,
+    },
+    /// Suggestions replacing code that other suggestions applied to the file
+    /// replace too.
+    FixConflicts {
+        package: Option<String>,
+        file: String,
+        fixes: Vec<SuggestedFix>,
+    },
     FixFailed {
+        package: Option<String>,
         files: Vec<String>,
         krate: Option<String>,
         errors: Vec<String>,
         abnormal_exit: Option<String>,
     },
+    /// Edition migration warnings in code generated by macros, which have no
+    /// suggestion that can be applied.
+    MacroWarnings {
+        package: Option<String>,
+        file: String,
+        warnings: Vec<MacroWarning>,
+    },
     ReplaceFailed {
         file: String,
         message: String,
@@ -60,6 +85,27 @@ pub enum Message {
     },
 }
 
+/// A suggestion of rustc applied to a file.
+#[derive(Deserialize, Serialize, Hash, Eq, PartialEq, Ord, PartialOrd, Clone, Debug)]
+pub struct SuggestedFix {
+    /// The message of the diagnostic with the suggestion.
+    pub message: String,
+    /// The 1-based line and column of the replaced code.
+    pub line: usize,
+    pub column: usize,
+    pub original: String,
+    pub replacement: String,
+}
+
+/// A warning in code generated by a macro, reported where the macro is
+/// invoked.
+#[derive(Deserialize, Serialize, Hash, Eq, PartialEq, Ord, PartialOrd, Clone, Debug)]
+pub struct MacroWarning {
+    pub line: usize,
+    pub macro_name: String,
+    pub message: String,
+}
+
 impl Message {
     pub fn post(&self) -> Result<(), Error> {
         let addr =
@@ -103,6 +149,7 @@ impl<'a> DiagnosticPrinter<'a> {
                 file,
                 from_edition,
                 to_edition,
+                ..
             } => {
                 if !self.dedupe.insert(msg.clone()) {
                     return Ok(());
@@ -116,11 +163,58 @@ impl<'a> DiagnosticPrinter<'a> {
                 .config
                 .shell()
                 .verbose(|shell| shell.status("Fixing", file)),
-            Message::Fixed { file, fixes } => {
+            Message::Fixed { file, fixes, .. } => {
                 let msg = if *fixes == 1 { "fix" } else { "fixes" };
                 let msg = format!("{} ({} {})", file, fixes, msg);
                 self.config.shell().status("Fixed", msg)
             }
+            Message::WouldFix { file, fixes, .. } => {
+                if !self.dedupe.insert(msg.clone()) {
+                    return Ok(());
+                }
+                let msg = if fixes.len() == 1 { "fix" } else { "fixes" };
+                let msg = format!("{} ({} {})", file, fixes.len(), msg);
+                self.config.shell().status("Would fix", msg)
+            }
+            Message::FixConflicts { file, fixes, .. } => {
+                if !self.dedupe.insert(msg.clone()) {
+                    return Ok(());
+                }
+                self.config.shell().warn(format!(
+                    "some suggestions for `{}` overlap with other suggestions, \
+                     and were not applied:",
+                    file
+                ))?;
+                for fix in fixes {
+                    writeln!(
+                        self.config.shell().err(),
+                        "  * line {}: {}",
+                        fix.line,
+                        fix.message
+                    )?;
+                }
+                Ok(())
+            }
+            Message::MacroWarnings { file, warnings, .. } => {
+                if !self.dedupe.insert(msg.clone()) {
+                    return Ok(());
+                }
+                self.config.shell().note(format!(
+                    "`{}` has edition migration warnings in code generated by macros, \
+                     which `cargo fix` can't fix, they need to be fixed by hand:",
+                    file
+                ))?;
+                for warning in warnings {
+                    writeln!(
+                        self.config.shell().err(),
+                        "  * line {}, in `{}`: {}",
+                        warning.line,
+                        warning.macro_name,
+                        warning.message
+                    )?;
+                }
+                Ok(())
+            }
             Message::ReplaceFailed { file, message } => {
                 let msg = format!("error applying suggestions to `{}`\n", file);
                 self.config.shell().warn(&msg)?;
@@ -137,6 +231,7 @@ impl<'a> DiagnosticPrinter<'a> {
                 krate,
                 errors,
                 abnormal_exit,
+                ..
             } => {
                 if let Some(ref krate) = *krate {
                     self.config.shell().warn(&format!(
@@ -219,6 +314,104 @@ https://doc.rust-lang.org/edition-guide/editions/transitioning-an-existing-proje
     }
 }
 
+/// What `cargo fix` did to each package, collected from the messages for the
+/// summary printed with `-Z edition-migration`.
+#[derive(Default, Debug)]
+pub struct FixSummary {
+    pub packages: HashMap<String, PackageFixes>,
+}
+
+#[derive(Default, Debug)]
+pub struct PackageFixes {
+    /// The number of fixes applied to each file.
+    pub fixed: BTreeMap<String, u32>,
+    /// The fixes of each file checked with `--dry-run`.
+    pub previews: BTreeMap<String, BTreeSet<SuggestedFix>>,
+    /// The unified diff of each file with `--diff`, and its number of fixes.
+    pub patches: BTreeMap<String, (usize, String)>,
+    /// The warnings in code generated by macros, by file.
+    pub macro_warnings: BTreeMap<String, BTreeSet<MacroWarning>>,
+    /// Whether the fixes of one of the targets broke it.
+    pub failed: bool,
+}
+
+impl FixSummary {
+    pub fn record(&mut self, msg: &Message) {
+        match msg {
+            Message::Migrating {
+                package: Some(package),
+                ..
+            } => {
+                self.packages.entry(package.clone()).or_default();
+            }
+            Message::Fixed {
+                package: Some(package),
+                file,
+                fixes,
+            } => {
+                *self
+                    .packages
+                    .entry(package.clone())
+                    .or_default()
+                    .fixed
+                    .entry(file.clone())
+                    .or_default() += fixes;
+            }
+            // The targets of a package sharing a file check the same fixes.
+            Message::WouldFix {
+                package: Some(package),
+                file,
+                fixes,
+                patch,
+            } => {
+                let pkg = self.packages.entry(package.clone()).or_default();
+                pkg.previews
+                    .entry(file.clone())
+                    .or_default()
+                    .extend(fixes.iter().cloned());
+                // The patch with the most fixes has those of the other
+                // targets too.
+                if let Some(patch) = patch {
+                    let len = fixes.len();
+                    if pkg.patches.get(file).map_or(true, |(n, _)| *n < len) {
+                        pkg.patches.insert(file.clone(), (len, patch.clone()));
+                    }
+                }
+            }
+            Message::FixFailed {
+                package: Some(package),
+                ..
+            } => {
+                self.packages.entry(package.clone()).or_default().failed = true;
+            }
+            Message::MacroWarnings {
+                package: Some(package),
+                file,
+                warnings,
+            } => {
+                self.packages
+                    .entry(package.clone())
+                    .or_default()
+                    .macro_warnings
+                    .entry(file.clone())
+                    .or_default()
+                    .extend(warnings.iter().cloned());
+            }
+            _ => {}
+        }
+    }
+}
+
+impl PackageFixes {
+    /// The number of files and fixes, applied or checked.
+    pub fn counts(&self) -> (usize, usize) {
+        let files = self.fixed.len() + self.previews.len();
+        let fixes = self.fixed.values().map(|n| *n as usize).sum::<usize>()
+            + self.previews.values().map(|f| f.len()).sum::<usize>();
+        (files, fixes)
+    }
+}
+
 #[derive(Debug)]
 pub struct RustfixDiagnosticServer {
     listener: TcpListener,
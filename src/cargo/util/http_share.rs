@@ -0,0 +1,65 @@
+//! The connections shared by the HTTP handles of Cargo, with
+//! `http.share-connections`.
+//!
+//! Every curl handle normally keeps its own cache of connections, so the
+//! handle of the index and of the registry API, and the handles of the crate
+//! downloads, each connect to the same hosts again. Attaching the handles to
+//! a curl share object makes them use one cache of connections, along with
+//! the DNS cache and the TLS sessions.
+//!
+//! The share object has no lock callbacks: the handles of a `Config` must
+//! only be used on one thread at a time, as Cargo already does.
+
+use anyhow::bail;
+use curl::easy::Easy;
+
+use crate::util::CargoResult;
+
+#[derive(Debug)]
+pub struct HttpShare {
+    raw: *mut curl_sys::CURLSH,
+}
+
+impl HttpShare {
+    pub fn new() -> CargoResult<HttpShare> {
+        let raw = unsafe { curl_sys::curl_share_init() };
+        if raw.is_null() {
+            bail!("failed to create the shared connection cache of curl");
+        }
+        let share = HttpShare { raw };
+        for data in [
+            curl_sys::CURL_LOCK_DATA_CONNECT,
+            curl_sys::CURL_LOCK_DATA_DNS,
+            curl_sys::CURL_LOCK_DATA_SSL_SESSION,
+        ] {
+            let code = unsafe { curl_sys::curl_share_setopt(raw, curl_sys::CURLSHOPT_SHARE, data) };
+            if code != curl_sys::CURLSHE_OK {
+                bail!(
+                    "failed to share the connections between the HTTP handles \
+                     (curl share error {}), is curl too old?",
+                    code
+                );
+            }
+        }
+        Ok(share)
+    }
+
+    /// Makes `handle` use the shared connections.
+    pub fn attach(&self, handle: &mut Easy) -> CargoResult<()> {
+        let code =
+            unsafe { curl_sys::curl_easy_setopt(handle.raw(), curl_sys::CURLOPT_SHARE, self.raw) };
+        if code != curl_sys::CURLE_OK {
+            return Err(curl::Error::new(code).into());
+        }
+        Ok(())
+    }
+}
+
+impl Drop for HttpShare {
+    fn drop(&mut self) {
+        // This fails, leaking the share object, if a handle still uses it.
+        unsafe {
+            curl_sys::curl_share_cleanup(self.raw);
+        }
+    }
+}
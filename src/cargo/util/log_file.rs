@@ -0,0 +1,168 @@
+//! The logger of Cargo, with the structured log file of `CARGO_LOG_FILE`.
+//!
+//! The records of the `log` crate are printed to the terminal as filtered by
+//! `CARGO_LOG`. When `CARGO_LOG_FILE` is set, they are also appended to that
+//! file as JSON lines, as filtered by `CARGO_LOG_FILE_FILTER`, independently
+//! of `CARGO_LOG`. The default filter records the resolver decisions, the
+//! fingerprint invalidations and the network requests, among the other
+//! `debug` records of Cargo.
+//!
+//! Each line holds the time, the process, the level, the target and the
+//! message of a record, with the spans opened on its thread by
+//! [`profile::start`](super::profile::start). Closing a span writes a line
+//! with its duration. The file is opened in append mode, and each line is
+//! written at once, so that the processes started by Cargo, such as the
+//! `rustc` wrapper of `cargo fix`, can log to the same file.
+
+use std::cell::RefCell;
+use std::env;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime};
+
+use env_logger::filter::{Builder, Filter};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use serde_json::json;
+
+/// The filter of the log file when `CARGO_LOG_FILE_FILTER` isn't set.
+const DEFAULT_FILTER: &str = "cargo=debug,cargo::core::resolver=trace";
+
+/// The target of the lines written when a span is closed.
+const SPAN_TARGET: &str = "cargo::util::profile";
+
+lazy_static::lazy_static! {
+    static ref LOG_FILE: Option<LogFile> = LogFile::open();
+}
+
+thread_local!(static SPANS: RefCell<Vec<(String, Instant)>> = RefCell::new(Vec::new()));
+
+struct Logger {
+    terminal: Box<dyn Log>,
+}
+
+struct LogFile {
+    file: Mutex<File>,
+    filter: Filter,
+}
+
+/// Installs the logger of Cargo, printing to the terminal with `terminal`,
+/// which only logs up to `terminal_level`.
+pub fn init(terminal: Box<dyn Log>, terminal_level: LevelFilter) {
+    let max_level = match &*LOG_FILE {
+        Some(file) => terminal_level.max(file.filter.filter()),
+        None => terminal_level,
+    };
+    if log::set_boxed_logger(Box::new(Logger { terminal })).is_ok() {
+        log::set_max_level(max_level);
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        self.terminal.enabled(metadata)
+            || LOG_FILE
+                .as_ref()
+                .map_or(false, |file| file.filter.enabled(metadata))
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        self.terminal.log(record);
+        if let Some(file) = &*LOG_FILE {
+            if file.filter.matches(record) {
+                file.write(json!({
+                    "level": record.level().as_str(),
+                    "target": record.target(),
+                    "spans": spans(),
+                    "message": record.args().to_string(),
+                }));
+            }
+        }
+    }
+
+    fn flush(&self) {
+        self.terminal.flush();
+        if let Some(file) = &*LOG_FILE {
+            let _ = file.file.lock().unwrap().flush();
+        }
+    }
+}
+
+impl LogFile {
+    fn open() -> Option<LogFile> {
+        let path = env::var_os("CARGO_LOG_FILE")?;
+        let file = match OpenOptions::new().create(true).append(true).open(&path) {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!(
+                    "warning: failed to open the log file `{}` of `CARGO_LOG_FILE`: {}",
+                    path.to_string_lossy(),
+                    e
+                );
+                return None;
+            }
+        };
+        let spec = env::var("CARGO_LOG_FILE_FILTER");
+        Some(LogFile {
+            file: Mutex::new(file),
+            filter: Builder::new()
+                .parse(spec.as_deref().unwrap_or(DEFAULT_FILTER))
+                .build(),
+        })
+    }
+
+    fn write(&self, mut line: serde_json::Value) {
+        line["time"] = humantime::format_rfc3339_millis(SystemTime::now())
+            .to_string()
+            .into();
+        line["pid"] = std::process::id().into();
+        let mut line = line.to_string();
+        line.push('\n');
+        // Failing to log shouldn't fail Cargo.
+        let _ = self.file.lock().unwrap().write_all(line.as_bytes());
+    }
+}
+
+/// The names of the spans opened on this thread, the outermost first.
+fn spans() -> Vec<String> {
+    SPANS.with(|spans| {
+        spans
+            .borrow()
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect()
+    })
+}
+
+/// Opens the span `name` on this thread, if there is a log file.
+pub(crate) fn enter_span(name: &dyn fmt::Display) {
+    if LOG_FILE.is_some() {
+        SPANS.with(|spans| spans.borrow_mut().push((name.to_string(), Instant::now())));
+    }
+}
+
+/// Closes the innermost span of this thread, writing its duration.
+pub(crate) fn exit_span() {
+    let file = match &*LOG_FILE {
+        Some(file) => file,
+        None => return,
+    };
+    let (name, start) = match SPANS.with(|spans| spans.borrow_mut().pop()) {
+        Some(span) => span,
+        None => return,
+    };
+    let metadata = Metadata::builder()
+        .level(Level::Debug)
+        .target(SPAN_TARGET)
+        .build();
+    if file.filter.enabled(&metadata) {
+        file.write(json!({
+            "level": Level::Debug.as_str(),
+            "target": SPAN_TARGET,
+            "spans": spans(),
+            "span": name,
+            "elapsed_ms": start.elapsed().as_millis() as u64,
+        }));
+    }
+}
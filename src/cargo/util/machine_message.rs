@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
 use serde::ser;
@@ -5,6 +6,8 @@ use serde::Serialize;
 use serde_json::{self, json, value::RawValue};
 
 use crate::core::{compiler::CompileMode, PackageId, Target};
+use crate::ops;
+use crate::util::diagnostic_server::SuggestedFix;
 
 pub trait Message: ser::Serialize {
     fn reason(&self) -> &str;
@@ -49,6 +52,23 @@ impl<'a> Message for Artifact<'a> {
     }
 }
 
+#[derive(Serialize)]
+pub struct UniversalArtifact<'a> {
+    pub package_id: PackageId,
+    pub target: &'a Target,
+    /// The targets whose artifacts were merged.
+    pub targets: Vec<&'a str>,
+    pub filenames: Vec<PathBuf>,
+    /// The artifacts of each target, in the order of `targets`.
+    pub sources: Vec<&'a Path>,
+}
+
+impl<'a> Message for UniversalArtifact<'a> {
+    fn reason(&self) -> &str {
+        "universal-artifact"
+    }
+}
+
 /// This is different from the regular `Profile` to maintain backwards
 /// compatibility (in particular, `test` is no longer in `Profile`, but we
 /// still want it to be included here).
@@ -103,3 +123,124 @@ impl Message for BuildFinished {
         "build-finished"
     }
 }
+
+#[derive(Serialize)]
+pub struct FixPreview<'a> {
+    pub package_id: PackageId,
+    pub file: &'a str,
+    pub fixes: Vec<&'a SuggestedFix>,
+}
+
+impl<'a> Message for FixPreview<'a> {
+    fn reason(&self) -> &str {
+        "fix-preview"
+    }
+}
+
+#[derive(Serialize)]
+pub struct EditionReadiness<'a> {
+    pub package_id: PackageId,
+    pub edition: String,
+    /// `None` if the package is on the latest edition.
+    pub next_edition: Option<String>,
+    pub ready: bool,
+    pub fixes: usize,
+    pub files: usize,
+    pub manual: Vec<ManualFix<'a>>,
+    pub error: Option<String>,
+}
+
+/// A warning in code generated by a macro, which has to be fixed by hand.
+#[derive(Serialize)]
+pub struct ManualFix<'a> {
+    pub file: &'a str,
+    pub line: usize,
+    pub macro_name: &'a str,
+    pub message: &'a str,
+}
+
+impl<'a> Message for EditionReadiness<'a> {
+    fn reason(&self) -> &str {
+        "edition-readiness"
+    }
+}
+
+#[derive(Serialize)]
+pub struct ValidationFinding<'a> {
+    pub check: &'a str,
+    pub level: &'a str,
+    pub package_id: Option<PackageId>,
+    pub manifest_path: &'a Path,
+    pub message: &'a str,
+}
+
+impl<'a> Message for ValidationFinding<'a> {
+    fn reason(&self) -> &str {
+        "validation-finding"
+    }
+}
+
+#[derive(Serialize)]
+pub struct ValidationFinished {
+    pub success: bool,
+    pub errors: usize,
+    pub warnings: usize,
+}
+
+impl Message for ValidationFinished {
+    fn reason(&self) -> &str {
+        "validation-finished"
+    }
+}
+
+#[derive(Serialize)]
+pub struct DocCoverage<'a> {
+    pub package_id: PackageId,
+    pub manifest_path: &'a Path,
+    #[serde(flatten)]
+    pub coverage: ops::DocCoverage,
+    pub files: &'a BTreeMap<PathBuf, ops::DocCoverage>,
+}
+
+impl<'a> Message for DocCoverage<'a> {
+    fn reason(&self) -> &str {
+        "doc-coverage"
+    }
+}
+
+#[derive(Serialize)]
+pub struct DownloadRetry<'a> {
+    pub package_id: PackageId,
+    pub url: &'a str,
+    /// The attempt which failed, starting from 1.
+    pub attempt: u32,
+    pub error: String,
+    /// How long until the next attempt.
+    pub retry_in_ms: u64,
+    /// The number of bytes the next attempt resumes from.
+    pub resume_from: u64,
+}
+
+impl<'a> Message for DownloadRetry<'a> {
+    fn reason(&self) -> &str {
+        "download-retry"
+    }
+}
+
+#[derive(Serialize)]
+pub struct DownloadFinished<'a> {
+    pub package_id: PackageId,
+    pub url: &'a str,
+    pub bytes: u64,
+    /// The number of bytes received by the attempts which failed, and which
+    /// were resumed from.
+    pub resumed_bytes: u64,
+    pub attempts: u32,
+    pub duration_ms: u64,
+}
+
+impl<'a> Message for DownloadFinished<'a> {
+    fn reason(&self) -> &str {
+        "download-finished"
+    }
+}
@@ -28,6 +28,7 @@ pub use self::workspace::{
     print_available_examples, print_available_packages, print_available_tests,
 };
 
+pub mod base64;
 mod canonical_url;
 pub mod command_prelude;
 pub mod config;
@@ -40,6 +41,7 @@ mod flock;
 pub mod graph;
 mod hasher;
 pub mod hex;
+pub mod http_share;
 pub mod important_paths;
 pub mod interning;
 pub mod into_url;
@@ -47,17 +49,22 @@ mod into_url_with_base;
 pub mod job;
 pub mod lev_distance;
 mod lockserver;
+pub mod log_file;
 pub mod machine_message;
 pub mod network;
+pub mod network_log;
 pub mod profile;
 mod progress;
 mod queue;
 pub mod restricted_names;
 pub mod rustc;
 mod semver_ext;
+pub mod symbols;
+pub mod system_proxy;
 pub mod to_semver;
 pub mod toml;
 mod vcs;
+pub mod vcs_state;
 mod workspace;
 
 pub fn elapsed(duration: Duration) -> String {
@@ -0,0 +1,333 @@
+//! The network log of `net.record` and `net.replay`.
+//!
+//! When recording, every crate download, every other HTTP GET request of
+//! Cargo and every git fetch is appended to `network.jsonl` in the directory
+//! of `net.record`, with its URL, its result and its duration. The bodies of
+//! the successful HTTP responses are saved in the `responses` directory,
+//! named by their SHA-256 checksum, which is logged. Git fetches log the
+//! revisions the fetched references pointed to.
+//!
+//! When replaying the directory of `net.replay`, no request is made: the
+//! last response recorded for a URL is served instead, failures included,
+//! and the references of a git fetch are set to the recorded revisions,
+//! which must already be in the local git database. A request which wasn't
+//! recorded is an error.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use anyhow::{bail, Context as _};
+use cargo_util::{paths, Sha256};
+use serde::{Deserialize, Serialize};
+
+use crate::util::{CargoResult, Config};
+
+/// The name of the log in the directory of the network log.
+const LOG_FILE: &str = "network.jsonl";
+
+/// A request of the network log.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+enum Entry {
+    Http {
+        url: String,
+        /// The HTTP status code, if a response was received.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        status: Option<u32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        size: Option<u64>,
+        /// The checksum of the body, the name of its file in `responses`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sha256: Option<String>,
+        duration_ms: u64,
+    },
+    Git {
+        url: String,
+        refspecs: Vec<String>,
+        /// The revisions of the references updated by the fetch.
+        #[serde(default)]
+        refs: BTreeMap<String, String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+        duration_ms: u64,
+    },
+}
+
+impl Entry {
+    fn key(&self) -> String {
+        match self {
+            Entry::Http { url, .. } => url.clone(),
+            Entry::Git { url, refspecs, .. } => git_key(url, refspecs),
+        }
+    }
+}
+
+fn git_key(url: &str, refspecs: &[String]) -> String {
+    format!("git {} {}", url, refspecs.join(" "))
+}
+
+#[derive(Debug)]
+pub struct NetworkLog {
+    dir: PathBuf,
+    mode: Mode,
+}
+
+#[derive(Debug)]
+enum Mode {
+    Record(RefCell<File>),
+    /// The last entry recorded for each request.
+    Replay(HashMap<String, Entry>),
+}
+
+impl NetworkLog {
+    /// Opens the network log of `net.record` or `net.replay`, if any.
+    pub fn from_config(config: &Config) -> CargoResult<Option<NetworkLog>> {
+        let net = config.net_config()?;
+        let (key, path) = match (&net.record, &net.replay) {
+            (None, None) => return Ok(None),
+            (Some(_), Some(_)) => bail!("`net.record` and `net.replay` cannot be used together"),
+            (Some(path), None) => ("net.record", path),
+            (None, Some(path)) => ("net.replay", path),
+        };
+        if !config.cli_unstable().network_log {
+            bail!("`{}` requires `-Z network-log`", key);
+        }
+        let dir = path.resolve_path(config);
+        let log = dir.join(LOG_FILE);
+        let mode = if key == "net.record" {
+            paths::create_dir_all(dir.join("responses"))?;
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&log)
+                .with_context(|| format!("failed to open the network log `{}`", log.display()))?;
+            Mode::Record(RefCell::new(file))
+        } else {
+            let contents = paths::read(&log)
+                .with_context(|| format!("failed to read the network log `{}`", log.display()))?;
+            let mut entries = HashMap::new();
+            for (i, line) in contents.lines().enumerate() {
+                let entry: Entry = serde_json::from_str(line).with_context(|| {
+                    format!(
+                        "invalid line {} of the network log `{}`",
+                        i + 1,
+                        log.display()
+                    )
+                })?;
+                entries.insert(entry.key(), entry);
+            }
+            Mode::Replay(entries)
+        };
+        Ok(Some(NetworkLog { dir, mode }))
+    }
+
+    /// Whether requests are served from the log instead of the network.
+    pub fn is_replay(&self) -> bool {
+        matches!(self.mode, Mode::Replay(_))
+    }
+
+    fn log_path(&self) -> PathBuf {
+        self.dir.join(LOG_FILE)
+    }
+
+    fn response_path(&self, sha256: &str) -> PathBuf {
+        self.dir.join("responses").join(sha256)
+    }
+
+    fn write(&self, entry: &Entry) -> CargoResult<()> {
+        if let Mode::Record(file) = &self.mode {
+            let mut line = serde_json::to_string(entry)?;
+            line.push('\n');
+            file.borrow_mut()
+                .write_all(line.as_bytes())
+                .with_context(|| {
+                    format!(
+                        "failed to write the network log `{}`",
+                        self.log_path().display()
+                    )
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Records an HTTP request of `url` started at `start`, whose `body` was
+    /// received with `result`.
+    pub fn record_http(
+        &self,
+        url: &str,
+        status: Option<u32>,
+        result: &CargoResult<()>,
+        body: &[u8],
+        start: Instant,
+    ) -> CargoResult<()> {
+        let mut sha256 = None;
+        if result.is_ok() && !self.is_replay() {
+            let checksum = Sha256::new().update(body).finish_hex();
+            paths::write(self.response_path(&checksum), body)?;
+            sha256 = Some(checksum);
+        }
+        self.write(&Entry::Http {
+            url: url.to_string(),
+            status: status.filter(|&code| code != 0),
+            error: result.as_ref().err().map(|e| e.root_cause().to_string()),
+            size: sha256.as_ref().map(|_| body.len() as u64),
+            sha256,
+            duration_ms: start.elapsed().as_millis() as u64,
+        })
+    }
+
+    /// The file holding the body of the response recorded for `url`, or the
+    /// error it failed with.
+    pub fn replay_http(&self, url: &str) -> CargoResult<PathBuf> {
+        match self.recorded(url)? {
+            Entry::Http {
+                sha256: Some(sha256),
+                ..
+            } => {
+                let path = self.response_path(sha256);
+                let body = paths::read_bytes(&path)?;
+                if Sha256::new().update(&body).finish_hex() != *sha256 {
+                    bail!(
+                        "the response recorded for `{}` in `{}` doesn't match its checksum",
+                        url,
+                        path.display()
+                    );
+                }
+                Ok(path)
+            }
+            Entry::Http { error, .. } => bail!(
+                "{} (replayed from `{}`)",
+                error.as_deref().unwrap_or("the request failed"),
+                self.log_path().display()
+            ),
+            Entry::Git { .. } => unreachable!(),
+        }
+    }
+
+    /// Fetches `url` with a GET request, or replays its response.
+    pub fn http_get(
+        &self,
+        url: &str,
+        get: impl FnOnce() -> CargoResult<Vec<u8>>,
+    ) -> CargoResult<Vec<u8>> {
+        if self.is_replay() {
+            let path = self.replay_http(url)?;
+            return Ok(paths::read_bytes(&path)?);
+        }
+        let start = Instant::now();
+        let result = get();
+        match &result {
+            Ok(body) => self.record_http(url, None, &Ok(()), body, start)?,
+            Err(e) => {
+                let error = Err(anyhow::format_err!("{}", e.root_cause()));
+                self.record_http(url, None, &error, &[], start)?
+            }
+        }
+        result
+    }
+
+    /// Records a git fetch of `refspecs` from `url` into `repo`, started at
+    /// `start`, which finished with `result`.
+    pub fn record_git(
+        &self,
+        repo: &git2::Repository,
+        url: &str,
+        refspecs: &[String],
+        result: &CargoResult<()>,
+        start: Instant,
+    ) -> CargoResult<()> {
+        let mut refs = BTreeMap::new();
+        if result.is_ok() {
+            for refspec in refspecs {
+                let dst = refspec.rsplit(':').next().unwrap();
+                for reference in repo.references_glob(dst)? {
+                    let reference = reference?;
+                    if let (Some(name), Some(oid)) = (reference.name(), reference.target()) {
+                        refs.insert(name.to_string(), oid.to_string());
+                    }
+                }
+            }
+        }
+        self.write(&Entry::Git {
+            url: url.to_string(),
+            refspecs: refspecs.to_vec(),
+            refs,
+            error: result.as_ref().err().map(|e| e.root_cause().to_string()),
+            duration_ms: start.elapsed().as_millis() as u64,
+        })
+    }
+
+    /// Replays a git fetch of `refspecs` from `url`, setting the references
+    /// of `repo` to the recorded revisions.
+    pub fn replay_git(
+        &self,
+        repo: &git2::Repository,
+        url: &str,
+        refspecs: &[String],
+    ) -> CargoResult<()> {
+        let (refs, error) = match self.recorded(&git_key(url, refspecs))? {
+            Entry::Git { refs, error, .. } => (refs, error),
+            Entry::Http { .. } => unreachable!(),
+        };
+        if let Some(error) = error {
+            bail!("{} (replayed from `{}`)", error, self.log_path().display());
+        }
+        for (name, oid) in refs {
+            let oid = git2::Oid::from_str(oid)?;
+            if repo.find_object(oid, None).is_err() {
+                bail!(
+                    "the revision `{}` of `{}` recorded for `{}` isn't in the git \
+                     database `{}`, replaying needs the git databases of the recording",
+                    oid,
+                    url,
+                    name,
+                    repo.path().display()
+                );
+            }
+            repo.reference(name, oid, true, "cargo: replay the network log")?;
+        }
+        Ok(())
+    }
+
+    fn recorded(&self, key: &str) -> CargoResult<&Entry> {
+        let entries = match &self.mode {
+            Mode::Replay(entries) => entries,
+            Mode::Record(_) => unreachable!(),
+        };
+        match entries.get(key) {
+            Some(entry) => Ok(entry),
+            None => bail!(
+                "no request for `{}` was recorded in the network log `{}`",
+                key,
+                self.log_path().display()
+            ),
+        }
+    }
+}
+
+/// Replays a recorded git fetch, or records it while `fetch` performs it.
+pub fn fetch_git(
+    config: &Config,
+    repo: &mut git2::Repository,
+    url: &str,
+    refspecs: &[String],
+    fetch: impl FnOnce(&mut git2::Repository) -> CargoResult<()>,
+) -> CargoResult<()> {
+    let log = match config.network_log()? {
+        Some(log) => log,
+        None => return fetch(repo),
+    };
+    if log.is_replay() {
+        return log.replay_git(repo, url, refspecs);
+    }
+    let start = Instant::now();
+    let result = fetch(repo);
+    log.record_git(repo, url, refspecs, &result, start)?;
+    result
+}
@@ -6,6 +6,8 @@ use std::iter::repeat;
 use std::mem;
 use std::time;
 
+use super::log_file;
+
 thread_local!(static PROFILE_STACK: RefCell<Vec<time::Instant>> = RefCell::new(Vec::new()));
 thread_local!(static MESSAGES: RefCell<Vec<Message>> = RefCell::new(Vec::new()));
 
@@ -20,6 +22,7 @@ fn enabled_level() -> Option<usize> {
 }
 
 pub fn start<T: fmt::Display>(desc: T) -> Profiler {
+    log_file::enter_span(&desc);
     if enabled_level().is_none() {
         return Profiler {
             desc: String::new(),
@@ -35,6 +38,7 @@ pub fn start<T: fmt::Display>(desc: T) -> Profiler {
 
 impl Drop for Profiler {
     fn drop(&mut self) {
+        log_file::exit_span();
         let enabled = match enabled_level() {
             Some(i) => i,
             None => return,
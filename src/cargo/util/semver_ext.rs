@@ -5,6 +5,9 @@ use std::fmt::{self, Display};
 pub enum OptVersionReq {
     Any,
     Req(VersionReq),
+    /// A version requirement also matching the pre-release versions whose
+    /// release would match it, from `workspace.allow-prerelease`.
+    AllowPrerelease(VersionReq),
     /// The exact locked version and the original version requirement.
     Locked(Version, VersionReq),
 }
@@ -51,17 +54,26 @@ impl OptVersionReq {
                     cmp.op == Op::Exact && cmp.minor.is_some() && cmp.patch.is_some()
                 }
             }
+            OptVersionReq::AllowPrerelease(_) => false,
             OptVersionReq::Locked(..) => true,
         }
     }
 
+    /// Also matches the pre-release versions whose release matches the
+    /// requirement, like `1.0.0-rc.1` for `^1.0`.
+    pub fn allow_prerelease(&mut self) {
+        if let OptVersionReq::Req(req) = self {
+            *self = OptVersionReq::AllowPrerelease(req.clone());
+        }
+    }
+
     pub fn lock_to(&mut self, version: &Version) {
         assert!(self.matches(version), "cannot lock {} to {}", self, version);
         use OptVersionReq::*;
         let version = version.clone();
         *self = match self {
             Any => Locked(version, VersionReq::STAR),
-            Req(req) => Locked(version, req.clone()),
+            Req(req) | AllowPrerelease(req) => Locked(version, req.clone()),
             Locked(_, req) => Locked(version, req.clone()),
         };
     }
@@ -82,6 +94,11 @@ impl OptVersionReq {
         match self {
             OptVersionReq::Any => true,
             OptVersionReq::Req(req) => req.matches(version),
+            OptVersionReq::AllowPrerelease(req) => {
+                req.matches(version)
+                    || (version.is_prerelease()
+                        && req.matches(&Version::new(version.major, version.minor, version.patch)))
+            }
             OptVersionReq::Locked(v, _) => {
                 v.major == version.major
                     && v.minor == version.minor
@@ -97,6 +114,7 @@ impl Display for OptVersionReq {
         match self {
             OptVersionReq::Any => f.write_str("*"),
             OptVersionReq::Req(req) => Display::fmt(req, f),
+            OptVersionReq::AllowPrerelease(req) => Display::fmt(req, f),
             OptVersionReq::Locked(_, req) => Display::fmt(req, f),
         }
     }
@@ -143,4 +161,17 @@ mod tests {
         );
         test_versions("0.1.0+meta", &["0.1.0", "0.1.0+meta", "0.1.0+any"]);
     }
+
+    #[test]
+    fn allow_prerelease_matches_the_prereleases_of_matching_releases() {
+        let mut req = OptVersionReq::Req(VersionReq::parse("1.0").unwrap());
+        req.allow_prerelease();
+        let matches = |v: &str| req.matches(&Version::parse(v).unwrap());
+        assert!(matches("1.0.0"));
+        assert!(matches("1.0.0-rc.1"));
+        assert!(matches("1.2.0-beta"));
+        assert!(!matches("2.0.0-rc.1"));
+        assert!(!matches("0.9.0-rc.1"));
+        assert_eq!(req.to_string(), "^1.0");
+    }
 }
@@ -0,0 +1,758 @@
+//! Reading the symbols of the code of linked artifacts, for
+//! `cargo report bloat`, and the symbols defined by native libraries, for the
+//! packages sharing a `links` value with `[workspace.links]`.
+//!
+//! ELF files, 32 and 64-bit in either byte order, and 64-bit Mach-O files
+//! are understood. The symbols are the functions of the `.text` section, or
+//! `__TEXT,__text` for Mach-O, taken from the full symbol table, or from the
+//! dynamic one if the artifact is stripped. Mach-O symbols have no size, so
+//! it is the distance to the next symbol.
+//!
+//! Rust symbols are demangled to attribute them to their crate. Only the
+//! legacy mangling is demangled into a path; symbols with the `v0` mangling
+//! are attributed to their crate but keep their mangled name.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::str;
+
+use anyhow::{bail, format_err};
+
+use crate::util::CargoResult;
+
+/// The code of a linked artifact.
+pub struct TextSymbols {
+    /// The size of the `.text` section.
+    pub text_size: u64,
+    pub symbols: Vec<Symbol>,
+}
+
+/// A function of the `.text` section.
+pub struct Symbol {
+    pub name: String,
+    pub size: u64,
+}
+
+/// Reads the functions of the `.text` section of `data`, the contents of an
+/// ELF or Mach-O file.
+pub fn text_symbols(data: &[u8]) -> CargoResult<TextSymbols> {
+    if data.starts_with(b"\x7fELF") {
+        Elf::new(data)?.text_symbols()
+    } else if data.starts_with(&[0xcf, 0xfa, 0xed, 0xfe]) {
+        macho_text_symbols(data)
+    } else {
+        bail!("only ELF and 64-bit Mach-O files are supported")
+    }
+}
+
+/// The global symbols defined by `data`, the contents of an ELF or 64-bit
+/// Mach-O object or shared library, or of an archive of such objects. Weak
+/// symbols are left out, as they don't conflict with other definitions, and
+/// so are the symbols a shared library doesn't export.
+pub fn defined_symbols(data: &[u8]) -> CargoResult<BTreeSet<String>> {
+    let mut symbols = BTreeSet::new();
+    if data.starts_with(ARCHIVE_MAGIC) {
+        archive_defined_symbols(data, &mut symbols)?;
+    } else {
+        object_defined_symbols(data, &mut symbols)?;
+    }
+    Ok(symbols)
+}
+
+fn object_defined_symbols(data: &[u8], symbols: &mut BTreeSet<String>) -> CargoResult<()> {
+    if data.starts_with(b"\x7fELF") {
+        Elf::new(data)?.defined_symbols(symbols)
+    } else if data.starts_with(&[0xcf, 0xfa, 0xed, 0xfe]) {
+        macho_defined_symbols(data, symbols)
+    } else {
+        bail!("only ELF and 64-bit Mach-O files are supported")
+    }
+}
+
+/// Whether `data` starts like a file `text_symbols` can read.
+pub fn is_object(data: &[u8]) -> bool {
+    data.starts_with(b"\x7fELF") || data.starts_with(&[0xcf, 0xfa, 0xed, 0xfe])
+}
+
+/// A bounds-checked reader of the integers of a file.
+struct Reader<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl Reader<'_> {
+    fn bytes(&self, offset: u64, len: u64) -> CargoResult<&[u8]> {
+        let start = usize::try_from(offset)?;
+        let end = start
+            .checked_add(usize::try_from(len)?)
+            .filter(|end| *end <= self.data.len());
+        match end {
+            Some(end) => Ok(&self.data[start..end]),
+            None => bail!("the file is truncated at offset {}", offset),
+        }
+    }
+
+    fn uint(&self, offset: u64, len: u64) -> CargoResult<u64> {
+        let bytes = self.bytes(offset, len)?;
+        let mut value = 0u64;
+        for i in 0..bytes.len() {
+            let byte = if self.big_endian {
+                bytes[i]
+            } else {
+                bytes[bytes.len() - 1 - i]
+            };
+            value = (value << 8) | u64::from(byte);
+        }
+        Ok(value)
+    }
+
+    fn u8(&self, offset: u64) -> CargoResult<u8> {
+        Ok(self.uint(offset, 1)? as u8)
+    }
+
+    fn u16(&self, offset: u64) -> CargoResult<u64> {
+        self.uint(offset, 2)
+    }
+
+    fn u32(&self, offset: u64) -> CargoResult<u64> {
+        self.uint(offset, 4)
+    }
+
+    fn u64(&self, offset: u64) -> CargoResult<u64> {
+        self.uint(offset, 8)
+    }
+
+    /// The NUL-terminated string at `offset`.
+    fn str(&self, offset: u64) -> CargoResult<String> {
+        let rest = match self.data.get(usize::try_from(offset)?..) {
+            Some(rest) => rest,
+            None => bail!("the file is truncated at offset {}", offset),
+        };
+        let end = rest.iter().position(|b| *b == 0).unwrap_or(rest.len());
+        Ok(String::from_utf8_lossy(&rest[..end]).into_owned())
+    }
+}
+
+struct Elf<'a> {
+    r: Reader<'a>,
+    is_64: bool,
+}
+
+struct ElfSection {
+    name: u64,
+    kind: u64,
+    offset: u64,
+    size: u64,
+    link: u64,
+    entsize: u64,
+}
+
+const SHT_SYMTAB: u64 = 2;
+const SHT_DYNSYM: u64 = 11;
+const STT_FUNC: u8 = 2;
+const STB_GLOBAL: u8 = 1;
+const SHN_UNDEF: u64 = 0;
+const ET_DYN: u64 = 3;
+
+impl<'a> Elf<'a> {
+    fn new(data: &'a [u8]) -> CargoResult<Elf<'a>> {
+        let is_64 = match data.get(4) {
+            Some(1) => false,
+            Some(2) => true,
+            _ => bail!("unknown ELF class"),
+        };
+        let big_endian = match data.get(5) {
+            Some(1) => false,
+            Some(2) => true,
+            _ => bail!("unknown ELF byte order"),
+        };
+        Ok(Elf {
+            r: Reader { data, big_endian },
+            is_64,
+        })
+    }
+
+    /// Reads an address-sized field, at `offset64` in 64-bit files or
+    /// `offset32` in 32-bit ones.
+    fn addr(&self, base: u64, offset64: u64, offset32: u64) -> CargoResult<u64> {
+        if self.is_64 {
+            self.r.u64(base + offset64)
+        } else {
+            self.r.u32(base + offset32)
+        }
+    }
+
+    fn sections(&self) -> CargoResult<Vec<ElfSection>> {
+        let shoff = self.addr(0, 0x28, 0x20)?;
+        let (entsize, num) = if self.is_64 {
+            (self.r.u16(0x3a)?, self.r.u16(0x3c)?)
+        } else {
+            (self.r.u16(0x2e)?, self.r.u16(0x30)?)
+        };
+        (0..num)
+            .map(|i| {
+                let base = shoff + i * entsize;
+                Ok(ElfSection {
+                    name: self.r.u32(base)?,
+                    kind: self.r.u32(base + 4)?,
+                    offset: self.addr(base, 24, 16)?,
+                    size: self.addr(base, 32, 20)?,
+                    link: self.r.u32(base + if self.is_64 { 40 } else { 24 })?,
+                    entsize: self.addr(base, 56, 36)?,
+                })
+            })
+            .collect()
+    }
+
+    fn text_symbols(&self) -> CargoResult<TextSymbols> {
+        let sections = self.sections()?;
+        let shstrndx = self.r.u16(if self.is_64 { 0x3e } else { 0x32 })?;
+        let names = match sections.get(shstrndx as usize) {
+            Some(section) => section.offset,
+            None => bail!("the ELF file has no section names"),
+        };
+        let mut text = None;
+        for (i, section) in sections.iter().enumerate() {
+            if self.r.str(names + section.name)? == ".text" {
+                text = Some((i as u64, section.size));
+            }
+        }
+        let (text_index, text_size) = match text {
+            Some(text) => text,
+            None => bail!("the ELF file has no `.text` section"),
+        };
+        let symtab = sections
+            .iter()
+            .find(|s| s.kind == SHT_SYMTAB)
+            .or_else(|| sections.iter().find(|s| s.kind == SHT_DYNSYM));
+        let symtab = match symtab {
+            Some(symtab) if symtab.entsize > 0 => symtab,
+            _ => bail!("the ELF file has no symbols, it may be stripped"),
+        };
+        let strtab = match sections.get(symtab.link as usize) {
+            Some(strtab) => strtab.offset,
+            None => bail!("the ELF symbol table has no names"),
+        };
+
+        // Aliases of a function share its address, and are only counted once.
+        let mut by_addr = BTreeMap::new();
+        for i in 0..symtab.size / symtab.entsize {
+            let base = symtab.offset + i * symtab.entsize;
+            let (info, shndx, value, size) = if self.is_64 {
+                (
+                    self.r.u8(base + 4)?,
+                    self.r.u16(base + 6)?,
+                    self.r.u64(base + 8)?,
+                    self.r.u64(base + 16)?,
+                )
+            } else {
+                (
+                    self.r.u8(base + 12)?,
+                    self.r.u16(base + 14)?,
+                    self.r.u32(base + 4)?,
+                    self.r.u32(base + 8)?,
+                )
+            };
+            if info & 0xf != STT_FUNC || shndx != text_index || size == 0 {
+                continue;
+            }
+            let name = self.r.str(strtab + self.r.u32(base)?)?;
+            by_addr.entry(value).or_insert(Symbol { name, size });
+        }
+        Ok(TextSymbols {
+            text_size,
+            symbols: by_addr.into_values().collect(),
+        })
+    }
+
+    /// The global symbols defined by the file, from the dynamic symbol table
+    /// of a shared library, which has the exported ones, or from the full
+    /// symbol table of an object.
+    fn defined_symbols(&self, symbols: &mut BTreeSet<String>) -> CargoResult<()> {
+        let sections = self.sections()?;
+        let kind = if self.r.u16(0x10)? == ET_DYN {
+            SHT_DYNSYM
+        } else {
+            SHT_SYMTAB
+        };
+        let symtab = match sections.iter().find(|s| s.kind == kind) {
+            Some(symtab) if symtab.entsize > 0 => symtab,
+            _ => bail!("the ELF file has no symbols, it may be stripped"),
+        };
+        let strtab = match sections.get(symtab.link as usize) {
+            Some(strtab) => strtab.offset,
+            None => bail!("the ELF symbol table has no names"),
+        };
+        for i in 0..symtab.size / symtab.entsize {
+            let base = symtab.offset + i * symtab.entsize;
+            let (info, shndx) = if self.is_64 {
+                (self.r.u8(base + 4)?, self.r.u16(base + 6)?)
+            } else {
+                (self.r.u8(base + 12)?, self.r.u16(base + 14)?)
+            };
+            if info >> 4 != STB_GLOBAL || shndx == SHN_UNDEF {
+                continue;
+            }
+            symbols.insert(self.r.str(strtab + self.r.u32(base)?)?);
+        }
+        Ok(())
+    }
+}
+
+const LC_SEGMENT_64: u64 = 0x19;
+const LC_SYMTAB: u64 = 0x2;
+const N_STAB: u8 = 0xe0;
+const N_TYPE: u8 = 0x0e;
+const N_SECT: u8 = 0x0e;
+const N_ABS: u8 = 0x02;
+const N_EXT: u8 = 0x01;
+const N_WEAK_DEF: u64 = 0x80;
+
+fn macho_text_symbols(data: &[u8]) -> CargoResult<TextSymbols> {
+    let r = Reader {
+        data,
+        big_endian: false,
+    };
+    let ncmds = r.u32(16)?;
+    let mut offset = 32;
+    let mut section_index = 0;
+    let mut text = None;
+    let mut symtab = None;
+    for _ in 0..ncmds {
+        let cmd = r.u32(offset)?;
+        let cmdsize = r.u32(offset + 4)?;
+        if cmd == LC_SEGMENT_64 {
+            let nsects = r.u32(offset + 64)?;
+            for i in 0..nsects {
+                let section = offset + 72 + i * 80;
+                section_index += 1;
+                let sectname = r.bytes(section, 16)?;
+                let segname = r.bytes(section + 16, 16)?;
+                if sectname.starts_with(b"__text\0") && segname.starts_with(b"__TEXT\0") {
+                    text = Some((section_index, r.u64(section + 32)?, r.u64(section + 40)?));
+                }
+            }
+        } else if cmd == LC_SYMTAB {
+            symtab = Some((r.u32(offset + 8)?, r.u32(offset + 12)?, r.u32(offset + 16)?));
+        }
+        if cmdsize == 0 {
+            bail!("the Mach-O file has an invalid load command");
+        }
+        offset += cmdsize;
+    }
+    let (text_index, text_addr, text_size) = match text {
+        Some(text) => text,
+        None => bail!("the Mach-O file has no `__TEXT,__text` section"),
+    };
+    let (symoff, nsyms, stroff) = match symtab {
+        Some(symtab) => symtab,
+        None => bail!("the Mach-O file has no symbols, it may be stripped"),
+    };
+
+    let mut by_addr = BTreeMap::new();
+    for i in 0..nsyms {
+        let base = symoff + i * 16;
+        let n_type = r.u8(base + 4)?;
+        let n_sect = r.u8(base + 5)?;
+        if n_type & N_STAB != 0 || n_type & N_TYPE != N_SECT || u64::from(n_sect) != text_index {
+            continue;
+        }
+        let name = r.str(stroff + r.u32(base)?)?;
+        // Mach-O symbols have a leading underscore added to their name.
+        let name = name.strip_prefix('_').unwrap_or(&name).to_string();
+        by_addr.entry(r.u64(base + 8)?).or_insert(name);
+    }
+    let addrs: Vec<u64> = by_addr.keys().cloned().collect();
+    let symbols = by_addr
+        .into_iter()
+        .enumerate()
+        .map(|(i, (addr, name))| {
+            let end = addrs.get(i + 1).cloned().unwrap_or(text_addr + text_size);
+            Symbol {
+                name,
+                size: end.saturating_sub(addr),
+            }
+        })
+        .filter(|symbol| symbol.size > 0)
+        .collect();
+    Ok(TextSymbols { text_size, symbols })
+}
+
+fn macho_defined_symbols(data: &[u8], symbols: &mut BTreeSet<String>) -> CargoResult<()> {
+    let r = Reader {
+        data,
+        big_endian: false,
+    };
+    let ncmds = r.u32(16)?;
+    let mut offset = 32;
+    let mut symtab = None;
+    for _ in 0..ncmds {
+        let cmd = r.u32(offset)?;
+        let cmdsize = r.u32(offset + 4)?;
+        if cmd == LC_SYMTAB {
+            symtab = Some((r.u32(offset + 8)?, r.u32(offset + 12)?, r.u32(offset + 16)?));
+        }
+        if cmdsize == 0 {
+            bail!("the Mach-O file has an invalid load command");
+        }
+        offset += cmdsize;
+    }
+    let (symoff, nsyms, stroff) = match symtab {
+        Some(symtab) => symtab,
+        None => bail!("the Mach-O file has no symbols, it may be stripped"),
+    };
+    for i in 0..nsyms {
+        let base = symoff + i * 16;
+        let n_type = r.u8(base + 4)?;
+        let n_desc = r.u16(base + 6)?;
+        if n_type & N_STAB != 0
+            || n_type & N_EXT == 0
+            || !matches!(n_type & N_TYPE, N_SECT | N_ABS)
+            || n_desc & N_WEAK_DEF != 0
+        {
+            continue;
+        }
+        let name = r.str(stroff + r.u32(base)?)?;
+        // Mach-O symbols have a leading underscore added to their name.
+        symbols.insert(name.strip_prefix('_').unwrap_or(&name).to_string());
+    }
+    Ok(())
+}
+
+const ARCHIVE_MAGIC: &[u8] = b"!<arch>\n";
+const ARCHIVE_HEADER_SIZE: u64 = 60;
+
+/// Reads the symbols of the objects of a static library, in the common `ar`
+/// format or its BSD variant.
+fn archive_defined_symbols(data: &[u8], symbols: &mut BTreeSet<String>) -> CargoResult<()> {
+    let r = Reader {
+        data,
+        big_endian: false,
+    };
+    let mut offset = ARCHIVE_MAGIC.len() as u64;
+    while offset < data.len() as u64 {
+        let header = r.bytes(offset, ARCHIVE_HEADER_SIZE)?;
+        let size = str::from_utf8(&header[48..58])
+            .ok()
+            .and_then(|size| size.trim().parse::<u64>().ok())
+            .ok_or_else(|| format_err!("the archive has an invalid member at offset {}", offset))?;
+        let mut member = r.bytes(offset + ARCHIVE_HEADER_SIZE, size)?;
+        let mut name = String::from_utf8_lossy(&header[..16])
+            .trim_end()
+            .to_string();
+        // BSD archives put the long names at the start of the member.
+        if let Some(len) = name.strip_prefix("#1/") {
+            let len = len
+                .parse::<usize>()
+                .ok()
+                .filter(|len| *len <= member.len())
+                .ok_or_else(|| format_err!("the archive has an invalid member name `{}`", name))?;
+            name = String::from_utf8_lossy(&member[..len])
+                .trim_end_matches('\0')
+                .to_string();
+            member = &member[len..];
+        }
+        let is_index =
+            matches!(name.as_str(), "/" | "//" | "/SYM64/") || name.starts_with("__.SYMDEF");
+        if is_object(member) {
+            object_defined_symbols(member, symbols)
+                .map_err(|e| format_err!("in the archive member `{}`: {}", name, e))?;
+        } else if !is_index {
+            bail!(
+                "the archive member `{}` is not an ELF or 64-bit Mach-O object",
+                name
+            );
+        }
+        offset += ARCHIVE_HEADER_SIZE + size + size % 2;
+    }
+    Ok(())
+}
+
+/// The crate a symbol belongs to, if it is a Rust symbol.
+pub fn symbol_crate(name: &str) -> Option<String> {
+    if let Some(rest) = name.strip_prefix("_ZN") {
+        let (first, _) = legacy_components(rest).into_iter().next()?;
+        let first = first.strip_prefix('_').unwrap_or(first);
+        // `<impl Trait for Type>` and `<Type as Trait>` blocks start with
+        // the path of a type or a trait, its crate being the first segment.
+        let first = first.strip_prefix("$LT$").unwrap_or(first);
+        let first = first.strip_prefix("impl$u20$").unwrap_or(first);
+        let end = first.find(|c| c == '.' || c == '$').unwrap_or(first.len());
+        return Some(first[..end].to_string()).filter(|c| !c.is_empty());
+    }
+    if let Some(rest) = name.strip_prefix("_R") {
+        return v0_crate(rest);
+    }
+    None
+}
+
+/// The demangled path of a symbol with the legacy Rust mangling, without
+/// its hash, or the name itself for other symbols.
+pub fn demangle(name: &str) -> String {
+    let rest = match name.strip_prefix("_ZN") {
+        Some(rest) => rest,
+        None => return name.to_string(),
+    };
+    let mut components = legacy_components(rest);
+    if components.is_empty() {
+        return name.to_string();
+    }
+    if let Some((last, _)) = components.last() {
+        if last.len() == 17 && last.starts_with('h') {
+            components.pop();
+        }
+    }
+    components
+        .iter()
+        .map(|(c, _)| decode_legacy(c))
+        .collect::<Vec<_>>()
+        .join("::")
+}
+
+/// The length-prefixed components of a legacy mangled path, up to the final
+/// `E`.
+fn legacy_components(mut rest: &str) -> Vec<(&str, usize)> {
+    let mut components = Vec::new();
+    while !rest.starts_with('E') {
+        let digits = rest.bytes().take_while(|b| b.is_ascii_digit()).count();
+        let len: usize = match rest[..digits].parse() {
+            Ok(len) => len,
+            Err(_) => break,
+        };
+        match rest.get(digits..digits + len) {
+            Some(component) => components.push((component, len)),
+            None => break,
+        }
+        rest = &rest[digits + len..];
+    }
+    components
+}
+
+/// Decodes the `$` escapes and `..` separators of a legacy component.
+fn decode_legacy(component: &str) -> String {
+    let component = if component.starts_with("_$") {
+        &component[1..]
+    } else {
+        component
+    };
+    let mut out = String::new();
+    let mut rest = component;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("..") {
+            out.push_str("::");
+            rest = after;
+            continue;
+        }
+        if rest.starts_with('$') {
+            if let Some(end) = rest[1..].find('$') {
+                let escape = &rest[1..end + 1];
+                let decoded = match escape {
+                    "SP" => Some('@'),
+                    "BP" => Some('*'),
+                    "RF" => Some('&'),
+                    "LT" => Some('<'),
+                    "GT" => Some('>'),
+                    "LP" => Some('('),
+                    "RP" => Some(')'),
+                    "C" => Some(','),
+                    _ => escape
+                        .strip_prefix('u')
+                        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                        .and_then(char::from_u32),
+                };
+                if let Some(c) = decoded {
+                    out.push(c);
+                    rest = &rest[end + 2..];
+                    continue;
+                }
+            }
+        }
+        let c = rest.chars().next().unwrap();
+        out.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    out
+}
+
+/// The crate of a symbol with the `v0` mangling: the first crate root of its
+/// path, `C` followed by an optional disambiguator and the crate name.
+fn v0_crate(rest: &str) -> Option<String> {
+    let bytes = rest.as_bytes();
+    let mut i = rest.find('C')?;
+    loop {
+        let mut j = i + 1;
+        if bytes.get(j) == Some(&b's') {
+            j += 1;
+            while j < bytes.len() && bytes[j] != b'_' {
+                j += 1;
+            }
+            j += 1;
+        }
+        let digits = bytes[j.min(bytes.len())..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        if digits > 0 {
+            let len: usize = rest[j..j + digits].parse().ok()?;
+            let start = j + digits;
+            // An underscore separates a name starting with a digit or an
+            // underscore from its length.
+            let start = if bytes.get(start) == Some(&b'_') {
+                start + 1
+            } else {
+                start
+            };
+            return rest.get(start..start + len).map(|s| s.to_string());
+        }
+        i += 1 + rest[i + 1..].find('C')?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{defined_symbols, demangle, symbol_crate, text_symbols};
+
+    #[test]
+    fn legacy_symbols() {
+        let name = "_ZN3foo3bar17h0123456789abcdefE";
+        assert_eq!(symbol_crate(name).as_deref(), Some("foo"));
+        assert_eq!(demangle(name), "foo::bar");
+
+        let name = "_ZN4core3fmt3num52_$LT$impl$u20$core..fmt..Debug$u20$for$u20$usize$GT$3fmt17h0123456789abcdefE";
+        assert_eq!(symbol_crate(name).as_deref(), Some("core"));
+        assert_eq!(
+            demangle(name),
+            "core::fmt::num::<impl core::fmt::Debug for usize>::fmt"
+        );
+
+        let name = "_ZN70_$LT$alloc..vec..Vec$LT$T$C$A$GT$$u20$as$u20$core..ops..drop..Drop$GT$4drop17h0123456789abcdefE";
+        assert_eq!(symbol_crate(name).as_deref(), Some("alloc"));
+        assert_eq!(
+            demangle(name),
+            "<alloc::vec::Vec<T,A> as core::ops::drop::Drop>::drop"
+        );
+    }
+
+    #[test]
+    fn v0_symbols() {
+        assert_eq!(
+            symbol_crate("_RNvCs1234_7mycrate4main").as_deref(),
+            Some("mycrate")
+        );
+        assert_eq!(
+            symbol_crate("_RNvNtCs5678_3std2rt10lang_start").as_deref(),
+            Some("std")
+        );
+        assert_eq!(symbol_crate("main"), None);
+        assert_eq!(demangle("main"), "main");
+    }
+
+    #[test]
+    fn macho_symbols() {
+        let mut data = vec![0u8; 32];
+        data[..4].copy_from_slice(&[0xcf, 0xfa, 0xed, 0xfe]);
+        data[16..20].copy_from_slice(&2u32.to_le_bytes());
+        // A `__TEXT` segment with a `__text` section at 0x1000 of 0x30 bytes.
+        let mut segment = vec![0u8; 72 + 80];
+        segment[..4].copy_from_slice(&0x19u32.to_le_bytes());
+        segment[4..8].copy_from_slice(&152u32.to_le_bytes());
+        segment[64..68].copy_from_slice(&1u32.to_le_bytes());
+        segment[72..78].copy_from_slice(b"__text");
+        segment[88..94].copy_from_slice(b"__TEXT");
+        segment[104..112].copy_from_slice(&0x1000u64.to_le_bytes());
+        segment[112..120].copy_from_slice(&0x30u64.to_le_bytes());
+        data.extend(segment);
+        let symoff = data.len() as u32 + 24;
+        let stroff = symoff + 3 * 16;
+        let mut symtab = vec![0u8; 24];
+        symtab[..4].copy_from_slice(&0x2u32.to_le_bytes());
+        symtab[4..8].copy_from_slice(&24u32.to_le_bytes());
+        symtab[8..12].copy_from_slice(&symoff.to_le_bytes());
+        symtab[12..16].copy_from_slice(&3u32.to_le_bytes());
+        symtab[16..20].copy_from_slice(&stroff.to_le_bytes());
+        data.extend(symtab);
+        let strings = b"\0_first\0_second\0_data\0";
+        for (name, n_type, n_sect, addr) in [
+            (1u32, 0x0fu8, 1u8, 0x1000u64),
+            (8, 0x0f, 1, 0x1010),
+            (16, 0x0f, 2, 0x2000),
+        ] {
+            data.extend(name.to_le_bytes());
+            data.extend([n_type, n_sect, 0, 0]);
+            data.extend(addr.to_le_bytes());
+        }
+        data.extend(strings);
+
+        let text = text_symbols(&data).unwrap();
+        assert_eq!(text.text_size, 0x30);
+        let symbols: Vec<_> = text
+            .symbols
+            .iter()
+            .map(|s| (s.name.as_str(), s.size))
+            .collect();
+        assert_eq!(symbols, [("first", 0x10), ("second", 0x20)]);
+    }
+
+    #[test]
+    fn archive_symbols() {
+        // A Mach-O object with only a symbol table.
+        let mut object = vec![0u8; 32];
+        object[..4].copy_from_slice(&[0xcf, 0xfa, 0xed, 0xfe]);
+        object[16..20].copy_from_slice(&1u32.to_le_bytes());
+        let symoff = 32u32 + 24;
+        let stroff = symoff + 4 * 16;
+        let mut symtab = vec![0u8; 24];
+        symtab[..4].copy_from_slice(&0x2u32.to_le_bytes());
+        symtab[4..8].copy_from_slice(&24u32.to_le_bytes());
+        symtab[8..12].copy_from_slice(&symoff.to_le_bytes());
+        symtab[12..16].copy_from_slice(&4u32.to_le_bytes());
+        symtab[16..20].copy_from_slice(&stroff.to_le_bytes());
+        object.extend(symtab);
+        let strings = b"\0_shared\0_undefined\0_weak\0_local\0";
+        for (name, n_type, n_desc) in [
+            (1u32, 0x0fu8, 0u16),
+            (9, 0x01, 0),
+            (20, 0x0f, 0x80),
+            (26, 0x0e, 0),
+        ] {
+            object.extend(name.to_le_bytes());
+            object.extend([n_type, 1]);
+            object.extend(n_desc.to_le_bytes());
+            object.extend(0u64.to_le_bytes());
+        }
+        object.extend(strings);
+
+        let member = |name: &str, data: &[u8]| {
+            let mut member = format!(
+                "{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n",
+                name,
+                0,
+                0,
+                0,
+                644,
+                data.len()
+            )
+            .into_bytes();
+            member.extend(data);
+            if data.len() % 2 == 1 {
+                member.push(b'\n');
+            }
+            member
+        };
+        let mut archive = b"!<arch>\n".to_vec();
+        archive.extend(member("/", b"index"));
+        let mut long_name = b"long_name.o\0".to_vec();
+        long_name.extend(&object);
+        archive.extend(member("#1/12", &long_name));
+        archive.extend(member("z.o/", &object));
+
+        let symbols: Vec<_> = defined_symbols(&archive).unwrap().into_iter().collect();
+        assert_eq!(symbols, ["shared"]);
+
+        archive.extend(member("readme/", b"text"));
+        let err = defined_symbols(&archive).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the archive member `readme/` is not an ELF or 64-bit Mach-O object"
+        );
+    }
+}
@@ -0,0 +1,273 @@
+//! Detection of the proxy configured in the settings of the operating
+//! system, with `-Z proxy-config`.
+//!
+//! The settings are read from the output of `scutil --proxy` on macOS, of
+//! `reg query` on the internet settings of the current user on Windows, and
+//! of `gsettings` for the GNOME settings on the other platforms. A proxy
+//! auto-config (PAC) file is detected, but isn't evaluated, as that needs a
+//! JavaScript engine.
+
+use std::collections::HashMap;
+use std::process::Command;
+
+/// The key of the internet settings of the current user on Windows.
+const WINDOWS_INTERNET_SETTINGS: &str =
+    r"HKCU\Software\Microsoft\Windows\CurrentVersion\Internet Settings";
+
+/// The proxy settings of the operating system.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SystemProxy {
+    /// The proxy of the HTTPS requests, as a URL.
+    pub proxy: Option<String>,
+    /// The hosts and domains reached without the proxy, in the syntax of
+    /// `NO_PROXY`.
+    pub no_proxy: Vec<String>,
+    /// The URL of the proxy auto-config file.
+    pub pac_url: Option<String>,
+    /// The settings the proxy comes from.
+    pub source: &'static str,
+}
+
+/// Detects the proxy settings of the operating system, if any.
+pub fn detect() -> Option<SystemProxy> {
+    let proxy = if cfg!(target_os = "macos") {
+        parse_scutil(&output("scutil", &["--proxy"])?)
+    } else if cfg!(windows) {
+        parse_reg_query(&output("reg", &["query", WINDOWS_INTERNET_SETTINGS])?)
+    } else {
+        parse_gsettings(|schema, key| output("gsettings", &["get", schema, key]))
+    };
+    log::debug!("system proxy: {:?}", proxy);
+    proxy.filter(|proxy| proxy.proxy.is_some() || proxy.pac_url.is_some())
+}
+
+/// The standard output of a successful `program`.
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+/// The URL of the proxy `host:port`.
+fn proxy_url(server: &str) -> String {
+    if server.contains("://") {
+        server.to_string()
+    } else {
+        format!("http://{}", server)
+    }
+}
+
+/// Converts the exceptions of the settings, like `*.example.com`, to the
+/// `NO_PROXY` syntax, like `example.com`.
+fn no_proxy<'a>(exceptions: impl Iterator<Item = &'a str>) -> Vec<String> {
+    exceptions
+        .map(|host| host.trim().trim_start_matches('*').trim_start_matches('.'))
+        .filter(|host| !host.is_empty() && *host != "<local>")
+        .map(str::to_string)
+        .collect()
+}
+
+/// Parses the GNOME settings read by `get(schema, key)`.
+fn parse_gsettings(get: impl Fn(&str, &str) -> Option<String>) -> Option<SystemProxy> {
+    let get = |schema: &str, key: &str| {
+        get(schema, key).map(|value| value.trim().trim_matches('\'').to_string())
+    };
+    let mut proxy = SystemProxy {
+        proxy: None,
+        no_proxy: Vec::new(),
+        pac_url: None,
+        source: "GNOME proxy settings",
+    };
+    match get("org.gnome.system.proxy", "mode")?.as_str() {
+        "manual" => {
+            for schema in [
+                "org.gnome.system.proxy.https",
+                "org.gnome.system.proxy.http",
+            ] {
+                let host = get(schema, "host").unwrap_or_default();
+                let port = get(schema, "port").unwrap_or_default();
+                if !host.is_empty() && port != "0" {
+                    proxy.proxy = Some(proxy_url(&format!("{}:{}", host, port)));
+                    break;
+                }
+            }
+            let ignored = get("org.gnome.system.proxy", "ignore-hosts").unwrap_or_default();
+            let ignored = ignored.trim_start_matches('[').trim_end_matches(']');
+            proxy.no_proxy = no_proxy(
+                ignored
+                    .split(',')
+                    .map(|host| host.trim().trim_matches('\'')),
+            );
+        }
+        "auto" => {
+            proxy.pac_url =
+                get("org.gnome.system.proxy", "autoconfig-url").filter(|url| !url.is_empty());
+        }
+        _ => return None,
+    }
+    Some(proxy)
+}
+
+/// Parses the output of `scutil --proxy` on macOS.
+fn parse_scutil(output: &str) -> Option<SystemProxy> {
+    let mut values = HashMap::new();
+    let mut exceptions = Vec::new();
+    let mut in_exceptions = false;
+    for line in output.lines().map(str::trim) {
+        if line == "}" {
+            in_exceptions = false;
+            continue;
+        }
+        let (key, value) = match line.split_once(" : ") {
+            Some(pair) => pair,
+            None => continue,
+        };
+        if in_exceptions {
+            exceptions.push(value);
+        } else if key == "ExceptionsList" {
+            in_exceptions = true;
+        } else {
+            values.insert(key, value);
+        }
+    }
+    let enabled = |key: &str| values.get(key) == Some(&"1");
+    let mut proxy = SystemProxy {
+        proxy: None,
+        no_proxy: no_proxy(exceptions.into_iter()),
+        pac_url: None,
+        source: "macOS network settings",
+    };
+    for kind in ["HTTPS", "HTTP"] {
+        let host = values.get(format!("{}Proxy", kind).as_str());
+        let port = values.get(format!("{}Port", kind).as_str());
+        if let (true, Some(host), Some(port)) = (enabled(&format!("{}Enable", kind)), host, port) {
+            proxy.proxy = Some(proxy_url(&format!("{}:{}", host, port)));
+            break;
+        }
+    }
+    if enabled("ProxyAutoConfigEnable") {
+        proxy.pac_url = values
+            .get("ProxyAutoConfigURLString")
+            .map(|url| url.to_string());
+    }
+    Some(proxy)
+}
+
+/// Parses the output of `reg query` on the internet settings of Windows.
+fn parse_reg_query(output: &str) -> Option<SystemProxy> {
+    let values: HashMap<&str, &str> = output
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.trim().splitn(3, "    ");
+            let name = parts.next()?;
+            let _kind = parts.next()?;
+            Some((name, parts.next()?.trim()))
+        })
+        .collect();
+    let mut proxy = SystemProxy {
+        proxy: None,
+        no_proxy: no_proxy(values.get("ProxyOverride").unwrap_or(&"").split(';')),
+        pac_url: values.get("AutoConfigURL").map(|url| url.to_string()),
+        source: "Windows internet settings",
+    };
+    if values.get("ProxyEnable") == Some(&"0x1") {
+        // Either one proxy for all the protocols, or `http=host:port;https=host:port`.
+        proxy.proxy = values.get("ProxyServer").map(|server| {
+            let servers: HashMap<&str, &str> = server
+                .split(';')
+                .filter_map(|server| server.split_once('='))
+                .collect();
+            match servers.get("https").or_else(|| servers.get("http")) {
+                Some(server) => proxy_url(server),
+                None => proxy_url(server),
+            }
+        });
+    }
+    Some(proxy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gsettings_manual() {
+        let proxy = parse_gsettings(|schema, key| {
+            let value = match (schema, key) {
+                ("org.gnome.system.proxy", "mode") => "'manual'",
+                ("org.gnome.system.proxy", "ignore-hosts") => "['localhost', '*.corp.example']",
+                ("org.gnome.system.proxy.https", "host") => "''",
+                ("org.gnome.system.proxy.https", "port") => "0",
+                ("org.gnome.system.proxy.http", "host") => "'proxy.example'",
+                ("org.gnome.system.proxy.http", "port") => "3128",
+                _ => return None,
+            };
+            Some(format!("{}\n", value))
+        })
+        .unwrap();
+        assert_eq!(proxy.proxy.as_deref(), Some("http://proxy.example:3128"));
+        assert_eq!(proxy.no_proxy, ["localhost", "corp.example"]);
+        assert_eq!(proxy.pac_url, None);
+    }
+
+    #[test]
+    fn gsettings_auto_and_none() {
+        let proxy = parse_gsettings(|_, key| match key {
+            "mode" => Some("'auto'\n".to_string()),
+            "autoconfig-url" => Some("'http://wpad/wpad.dat'\n".to_string()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(proxy.proxy, None);
+        assert_eq!(proxy.pac_url.as_deref(), Some("http://wpad/wpad.dat"));
+
+        assert_eq!(parse_gsettings(|_, _| Some("'none'".to_string())), None);
+    }
+
+    #[test]
+    fn scutil() {
+        let proxy = parse_scutil(
+            "<dictionary> {
+  ExceptionsList : <array> {
+    0 : *.local
+    1 : 169.254/16
+  }
+  HTTPEnable : 1
+  HTTPPort : 8080
+  HTTPProxy : http.example
+  HTTPSEnable : 1
+  HTTPSPort : 3128
+  HTTPSProxy : proxy.example
+  ProxyAutoConfigEnable : 0
+  ProxyAutoConfigURLString : http://wpad/wpad.dat
+}
+",
+        )
+        .unwrap();
+        assert_eq!(proxy.proxy.as_deref(), Some("http://proxy.example:3128"));
+        assert_eq!(proxy.no_proxy, ["local", "169.254/16"]);
+        assert_eq!(proxy.pac_url, None);
+    }
+
+    #[test]
+    fn reg_query() {
+        let proxy = parse_reg_query(
+            "
+HKEY_CURRENT_USER\\Software\\Microsoft\\Windows\\CurrentVersion\\Internet Settings
+    ProxyEnable    REG_DWORD    0x1
+    ProxyServer    REG_SZ    http=http.example:8080;https=proxy.example:3128
+    ProxyOverride    REG_SZ    *.corp.example;<local>
+    AutoConfigURL    REG_SZ    http://wpad/wpad.dat
+",
+        )
+        .unwrap();
+        assert_eq!(proxy.proxy.as_deref(), Some("http://proxy.example:3128"));
+        assert_eq!(proxy.no_proxy, ["corp.example"]);
+        assert_eq!(proxy.pac_url.as_deref(), Some("http://wpad/wpad.dat"));
+
+        let proxy = parse_reg_query("    ProxyEnable    REG_DWORD    0x0\n").unwrap();
+        assert_eq!(proxy.proxy, None);
+    }
+}
@@ -4,9 +4,10 @@ use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::str;
+use std::time::Duration;
 
 use anyhow::{anyhow, bail, Context as _};
-use cargo_platform::Platform;
+use cargo_platform::{Cfg, Platform};
 use cargo_util::paths;
 use log::{debug, trace};
 use semver::{self, VersionReq};
@@ -17,11 +18,19 @@ use url::Url;
 
 use crate::core::compiler::{CompileKind, CompileTarget};
 use crate::core::dependency::DepKind;
-use crate::core::manifest::{ManifestMetadata, TargetSourcePath, Warnings};
+use crate::core::manifest::{
+    Asset, AssetCompression, BuildInfo, BuildTimestamp, ManifestMetadata, TargetSourcePath,
+    Warnings,
+};
+use crate::core::profiles::{Sanitizer, TrimPaths};
 use crate::core::resolver::ResolveBehavior;
-use crate::core::{Dependency, Manifest, PackageId, Summary, Target};
-use crate::core::{Edition, EitherManifest, Feature, Features, VirtualManifest, Workspace};
-use crate::core::{GitReference, PackageIdSpec, SourceId, WorkspaceConfig, WorkspaceRootConfig};
+use crate::core::RequireAudits;
+use crate::core::{ChangelogConfig, DeniedPackage, GitReference, LinksPolicy, PackageIdSpec};
+use crate::core::{Dependency, FeatureMap, Manifest, PackageId, Summary, Target};
+use crate::core::{Edition, EitherManifest, EnvValue, Feature, FeatureOverride, Features};
+use crate::core::{SourceId, WorkspacePolicy};
+use crate::core::{VirtualManifest, Workspace};
+use crate::core::{WorkspaceConfig, WorkspaceRootConfig};
 use crate::sources::{CRATES_IO_INDEX, CRATES_IO_REGISTRY};
 use crate::util::errors::{CargoResult, ManifestError};
 use crate::util::interning::InternedString;
@@ -29,6 +38,7 @@ use crate::util::{
     self, config::ConfigRelativePath, validate_package_name, Config, IntoUrl, VersionReqExt,
 };
 
+pub mod schema;
 mod targets;
 use self::targets::targets;
 
@@ -71,6 +81,7 @@ fn do_read_manifest(
             .unwrap_or(manifest_file);
         parse(contents, pretty_filename, config)?
     };
+    schema::check_manifest_version(&toml)?;
 
     // Provide a helpful error message for a common user error.
     if let Some(package) = toml.get("package").or_else(|| toml.get("project")) {
@@ -98,12 +109,26 @@ fn do_read_manifest(
         }
     };
 
+    // Strictness is only for the manifests of the user, the warnings of the
+    // other manifests can't be fixed by them.
+    let strict = source_id.is_path()
+        && (config.cli_unstable().strict_manifest
+            || manifest
+                .project
+                .as_ref()
+                .or_else(|| manifest.package.as_ref())
+                .and_then(|p| p.strict)
+                == Some(true));
+
     let manifest = Rc::new(manifest);
     return if manifest.project.is_some() || manifest.package.is_some() {
         let (mut manifest, paths) =
             TomlManifest::to_real_manifest(&manifest, source_id, package_root, config)?;
         add_unused(manifest.warnings_mut());
-        if manifest.targets().iter().all(|t| t.is_custom_build()) {
+        if strict {
+            check_strict(manifest.warnings())?;
+        }
+        if manifest.targets().iter().all(|t| t.is_build_program()) {
             bail!(
                 "no targets specified in the manifest\n\
                  either src/lib.rs, src/main.rs, a [lib] section, or \
@@ -115,6 +140,9 @@ fn do_read_manifest(
         let (mut m, paths) =
             TomlManifest::to_virtual_manifest(&manifest, source_id, package_root, config)?;
         add_unused(m.warnings_mut());
+        if strict {
+            check_strict(m.warnings())?;
+        }
         Ok((EitherManifest::Virtual(m), paths))
     };
 
@@ -144,6 +172,29 @@ fn do_read_manifest(
     }
 }
 
+/// Fails with the warnings of a strict manifest, which are errors for it.
+fn check_strict(warnings: &Warnings) -> CargoResult<()> {
+    let warnings: Vec<_> = warnings
+        .warnings()
+        .iter()
+        .filter(|w| !w.is_critical)
+        .map(|w| format!("- {}", w.message.replace('\n', "\n  ")))
+        .collect();
+    if !warnings.is_empty() {
+        bail!(
+            "the manifest is strict, and has {} {}:\n{}",
+            warnings.len(),
+            if warnings.len() == 1 {
+                "warning"
+            } else {
+                "warnings"
+            },
+            warnings.join("\n")
+        );
+    }
+    Ok(())
+}
+
 /// Attempts to parse a string into a [`toml::Value`]. This is not specific to any
 /// particular kind of TOML file.
 ///
@@ -161,6 +212,8 @@ type TomlBinTarget = TomlTarget;
 type TomlExampleTarget = TomlTarget;
 type TomlTestTarget = TomlTarget;
 type TomlBenchTarget = TomlTarget;
+type TomlFuzzTarget = TomlTarget;
+type TomlCodegenTarget = TomlTarget;
 
 #[derive(Clone, Debug, Serialize)]
 #[serde(untagged)]
@@ -282,6 +335,7 @@ impl<P> Default for DetailedTomlDependency<P> {
 #[serde(rename_all = "kebab-case")]
 pub struct TomlManifest {
     cargo_features: Option<Vec<String>>,
+    manifest_version: Option<String>,
     package: Option<Box<TomlProject>>,
     project: Option<Box<TomlProject>>,
     profile: Option<TomlProfiles>,
@@ -290,6 +344,8 @@ pub struct TomlManifest {
     example: Option<Vec<TomlExampleTarget>>,
     test: Option<Vec<TomlTestTarget>>,
     bench: Option<Vec<TomlTestTarget>>,
+    fuzz: Option<Vec<TomlFuzzTarget>>,
+    codegen: Option<Vec<TomlCodegenTarget>>,
     dependencies: Option<BTreeMap<String, TomlDependency>>,
     dev_dependencies: Option<BTreeMap<String, TomlDependency>>,
     #[serde(rename = "dev_dependencies")]
@@ -297,12 +353,345 @@ pub struct TomlManifest {
     build_dependencies: Option<BTreeMap<String, TomlDependency>>,
     #[serde(rename = "build_dependencies")]
     build_dependencies2: Option<BTreeMap<String, TomlDependency>>,
-    features: Option<BTreeMap<InternedString, Vec<InternedString>>>,
+    features: Option<TomlFeatures>,
     target: Option<BTreeMap<String, TomlPlatform>>,
     replace: Option<BTreeMap<String, TomlDependency>>,
     patch: Option<BTreeMap<String, BTreeMap<String, TomlDependency>>>,
     workspace: Option<TomlWorkspace>,
     badges: Option<BTreeMap<String, BTreeMap<String, String>>>,
+    feature_matrix: Option<TomlFeatureMatrix>,
+    feature_overrides: Option<BTreeMap<InternedString, TomlFeatureOverride>>,
+    config_flags: Option<BTreeMap<InternedString, Vec<String>>>,
+    env: Option<BTreeMap<String, TomlEnvValue>>,
+    lints: Option<TomlLints>,
+    headers: Option<TomlHeaders>,
+}
+
+/// The `[features]` table.
+///
+/// Besides the feature definitions, it may hold a `[features.metadata]` table
+/// describing the features. A feature can still be named `metadata`, as long
+/// as its value is a list.
+#[derive(Clone, Debug, Default)]
+pub struct TomlFeatures {
+    features: BTreeMap<InternedString, Vec<InternedString>>,
+    metadata: Option<BTreeMap<InternedString, TomlFeatureMetadata>>,
+}
+
+impl<'de> de::Deserialize<'de> for TomlFeatures {
+    fn deserialize<D>(d: D) -> Result<TomlFeatures, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let mut features = BTreeMap::new();
+        let mut metadata = None;
+        for (name, value) in BTreeMap::<InternedString, toml::Value>::deserialize(d)? {
+            if name == "metadata" && value.is_table() {
+                let value = value.try_into().map_err(|e| {
+                    de::Error::custom(format_args!("invalid `features.metadata` table: {}", e))
+                })?;
+                metadata = Some(value);
+            } else {
+                let value = value.try_into().map_err(|e| {
+                    de::Error::custom(format_args!("invalid feature `{}`: {}", name, e))
+                })?;
+                features.insert(name, value);
+            }
+        }
+        Ok(TomlFeatures { features, metadata })
+    }
+}
+
+impl ser::Serialize for TomlFeatures {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        use serde::ser::SerializeMap;
+
+        let mut map = s.serialize_map(None)?;
+        for (name, values) in &self.features {
+            map.serialize_entry(name, values)?;
+        }
+        // Tables have to come after plain values in TOML.
+        if let Some(metadata) = &self.metadata {
+            map.serialize_entry("metadata", metadata)?;
+        }
+        map.end()
+    }
+}
+
+/// An entry of the `[features.metadata]` table.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TomlFeatureMetadata {
+    /// A short description of what the feature does.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// How stable the feature is.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stability: Option<FeatureStability>,
+    /// Features which cannot be enabled together with this one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conflicts: Option<Vec<InternedString>>,
+}
+
+/// The stability of a feature, as declared in `[features.metadata]`.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum FeatureStability {
+    Stable,
+    Unstable,
+    Deprecated,
+}
+
+/// An entry of the `[feature-overrides]` table.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TomlFeatureOverride {
+    default_features: Option<bool>,
+    enable: Option<Vec<InternedString>>,
+    disable: Option<Vec<InternedString>>,
+}
+
+/// The `package.build-info` field.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(
+    untagged,
+    expecting = "expected a boolean or a table with a `timestamp`"
+)]
+pub enum TomlBuildInfo {
+    Bool(bool),
+    Detailed { timestamp: String },
+}
+
+/// An entry of the `[package.assets]` table.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(untagged, expecting = "expected a path or a table with a `path`")]
+pub enum TomlAsset {
+    Path(String),
+    Detailed(TomlAssetDetail),
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TomlAssetDetail {
+    path: String,
+    compress: Option<String>,
+}
+
+/// An entry of the `[workspace.links]` table.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TomlLinksPolicy {
+    packages: Vec<String>,
+    winner: Option<String>,
+}
+
+/// The `[workspace.changelog]` table.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TomlChangelog {
+    fragments: Option<String>,
+    file: Option<String>,
+    require_entry: Option<bool>,
+}
+
+/// The `[workspace.minimal-versions]` table.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TomlMinimalVersions {
+    exempt: Option<Vec<String>>,
+}
+
+impl TomlMinimalVersions {
+    /// The packages resolved to their maximal versions even with
+    /// `--minimal-versions`.
+    fn exempt(&self) -> BTreeSet<InternedString> {
+        self.exempt
+            .iter()
+            .flatten()
+            .map(|name| InternedString::new(name))
+            .collect()
+    }
+}
+
+/// The `[workspace.policy]` table.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TomlPolicy {
+    allowed_licenses: Option<Vec<String>>,
+    license_exceptions: Option<Vec<String>>,
+    deny: Option<Vec<TomlDeniedPackage>>,
+    max_duplicate_versions: Option<u32>,
+    allow_duplicates: Option<Vec<String>>,
+    allowed_registries: Option<Vec<String>>,
+    allowed_git: Option<Vec<String>>,
+    allowed_sources: Option<Vec<String>>,
+    build_scripts: Option<String>,
+    allow_build_scripts: Option<Vec<TomlDeniedPackage>>,
+    deny_build_scripts: Option<Vec<TomlDeniedPackage>>,
+    require_audits: Option<String>,
+}
+
+/// An entry of the `deny` list of `[workspace.policy]`, or of the build script
+/// lists.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(
+    untagged,
+    expecting = "expected a package name or a table with a `name`"
+)]
+pub enum TomlDeniedPackage {
+    Simple(String),
+    Detailed(TomlDeniedPackageDetail),
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TomlDeniedPackageDetail {
+    name: String,
+    version: Option<String>,
+    reason: Option<String>,
+}
+
+/// An entry of the `[env]` table.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(
+    untagged,
+    expecting = "expected a string or a table with a `value` or a `file`"
+)]
+pub enum TomlEnvValue {
+    Simple(String),
+    Detailed(TomlEnvDetail),
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TomlEnvDetail {
+    value: Option<String>,
+    file: Option<String>,
+    relative: Option<bool>,
+}
+
+/// The `[lints]` table, with the documentation coverage required by
+/// `cargo doc --report-coverage`, in percent, and how the warnings of the
+/// workspace are handled.
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TomlLints {
+    /// The share of the public items that must be documented.
+    pub doc_coverage: Option<f64>,
+    /// The share of the items that can have examples that must have one.
+    pub doc_example_coverage: Option<f64>,
+    /// The lints of rustc.
+    pub rust: Option<TomlRustLints>,
+    /// Denies the warnings of the workspace members, but not the ones of
+    /// their dependencies. Only read in the root manifest.
+    pub workspace_deny_warnings: Option<bool>,
+}
+
+impl TomlLints {
+    /// The `[lints.rust.unexpected_cfgs]` table.
+    pub fn unexpected_cfgs(&self) -> Option<&TomlUnexpectedCfgs> {
+        self.rust.as_ref()?.unexpected_cfgs.as_ref()
+    }
+}
+
+/// The `[lints.rust]` table, keyed by the names of rustc lints.
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct TomlRustLints {
+    pub unexpected_cfgs: Option<TomlUnexpectedCfgs>,
+}
+
+/// The `[lints.rust.unexpected_cfgs]` table, with the custom cfgs a package
+/// expects.
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TomlUnexpectedCfgs {
+    /// The level of the lint: `allow`, `warn`, `deny` or `forbid`.
+    pub level: Option<String>,
+    /// The `cfg(...)` specifications passed to `--check-cfg`.
+    pub check_cfg: Option<Vec<String>>,
+}
+
+/// The `[headers]` table, which generates a C header for the library when it
+/// is built as a `cdylib` or a `staticlib`.
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TomlHeaders {
+    /// The file name of the header, `<crate name>.h` by default.
+    pub name: Option<String>,
+    /// The program generating the header, `cbindgen` by default.
+    pub generator: Option<String>,
+    /// Extra arguments passed to the generator.
+    pub args: Option<Vec<String>>,
+}
+
+/// The `[package.pkg-config]` table, which describes the pkg-config file
+/// written for the library with `cargo build --emit-pc`.
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TomlPkgConfig {
+    /// The name of the file and of the library, the crate name by default.
+    pub name: Option<String>,
+    /// The description, the one of the package by default.
+    pub description: Option<String>,
+    /// The pkg-config packages the library depends on.
+    pub requires: Option<Vec<String>>,
+    /// The pkg-config packages only needed to link the library statically.
+    pub requires_private: Option<Vec<String>>,
+    /// Extra compiler flags, after the include directory of the headers.
+    pub cflags: Option<Vec<String>>,
+    /// Extra linker flags, after the library.
+    pub libs: Option<Vec<String>>,
+    /// Extra linker flags only needed to link the library statically.
+    pub libs_private: Option<Vec<String>>,
+}
+
+/// The `[package.component]` table, which describes how the `component`
+/// crate type of the library is made into a WebAssembly component.
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TomlComponent {
+    /// The WIT file or directory describing the component, embedded into the
+    /// core module before it is wrapped.
+    pub wit: Option<String>,
+    /// The world of the WIT package the component implements.
+    pub world: Option<String>,
+    /// Adapter modules, like the WASI preview 1 adapter, by the name of the
+    /// module whose imports they implement.
+    pub adapters: Option<BTreeMap<String, String>>,
+}
+
+/// The `[package.windows]` table, which describes the resources embedded
+/// into the binaries and `cdylib`s built for Windows targets.
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TomlWindows {
+    /// The icon of the executable, a `.ico` file.
+    pub icon: Option<String>,
+    /// The application manifest, an XML file.
+    pub manifest: Option<String>,
+    /// Strings of the version resource, like `LegalCopyright`, which also
+    /// override the ones derived from the package.
+    pub version_info: Option<BTreeMap<String, String>>,
+}
+
+/// The `[feature-matrix]` table, which tunes the feature combinations tried
+/// by `cargo test --feature-matrix`.
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct TomlFeatureMatrix {
+    /// Groups of features of which at most one may be enabled at a time.
+    pub exclusive: Option<Vec<Vec<InternedString>>>,
+    /// Features that are left out of the combinations.
+    pub skip: Option<Vec<InternedString>>,
+    /// Features that are enabled in every combination.
+    pub always: Option<Vec<InternedString>>,
+    /// The maximum number of varied features in a single combination, 2 by
+    /// default.
+    pub depth: Option<usize>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug, Default)]
@@ -381,6 +770,73 @@ impl ser::Serialize for TomlOptLevel {
     }
 }
 
+/// A duration such as `"300s"`, `"5m"` or `"1h"`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TomlDuration(pub Duration);
+
+impl str::FromStr for TomlDuration {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> CargoResult<TomlDuration> {
+        let (n, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
+        let n: u64 = match n.parse() {
+            Ok(n) => n,
+            Err(_) => bail!(
+                "invalid duration `{}`, expected a number followed by `ms`, `s`, `m` or `h`",
+                s
+            ),
+        };
+        let secs = match unit {
+            "ms" => return Ok(TomlDuration(Duration::from_millis(n))),
+            "s" => Some(n),
+            "m" => n.checked_mul(60),
+            "h" => n.checked_mul(60 * 60),
+            _ => bail!(
+                "invalid duration `{}`, expected a number followed by `ms`, `s`, `m` or `h`",
+                s
+            ),
+        };
+        match secs {
+            Some(secs) => Ok(TomlDuration(Duration::from_secs(secs))),
+            None => bail!("invalid duration `{}`, it is too long", s),
+        }
+    }
+}
+
+impl fmt::Display for TomlDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let millis = self.0.as_millis();
+        if millis % 1000 != 0 {
+            write!(f, "{}ms", millis)
+        } else if millis % (60 * 60 * 1000) == 0 {
+            write!(f, "{}h", millis / (60 * 60 * 1000))
+        } else if millis % (60 * 1000) == 0 {
+            write!(f, "{}m", millis / (60 * 1000))
+        } else {
+            write!(f, "{}s", millis / 1000)
+        }
+    }
+}
+
+impl<'de> de::Deserialize<'de> for TomlDuration {
+    fn deserialize<D>(d: D) -> Result<TomlDuration, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let s = String::deserialize(d)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+impl ser::Serialize for TomlDuration {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        self.to_string().serialize(serializer)
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
 #[serde(untagged, expecting = "expected a boolean or an integer")]
 pub enum U32OrBool {
@@ -405,6 +861,11 @@ pub struct TomlProfile {
     pub dir_name: Option<InternedString>,
     pub inherits: Option<InternedString>,
     pub strip: Option<StringOrBool>,
+    pub timeout: Option<TomlDuration>,
+    pub retries: Option<u32>,
+    pub sanitizer: Option<String>,
+    pub trim_paths: Option<TomlTrimPaths>,
+    pub signing: Option<TomlSigning>,
     // These two fields must be last because they are sub-tables, and TOML
     // requires all non-tables to be listed first.
     pub package: Option<BTreeMap<ProfilePackageSpec, TomlProfile>>,
@@ -414,9 +875,27 @@ pub struct TomlProfile {
 #[derive(Clone, Debug, PartialEq, Eq, Ord, PartialOrd, Hash)]
 pub enum ProfilePackageSpec {
     Spec(PackageIdSpec),
+    /// A pattern like `"my-org-*"`, matching the packages whose name starts
+    /// with the given prefix.
+    Glob(InternedString),
     All,
 }
 
+impl ProfilePackageSpec {
+    /// Parses a name pattern, which must end with its only `*`.
+    fn parse_glob(pattern: &str) -> CargoResult<ProfilePackageSpec> {
+        let prefix = match pattern.strip_suffix('*') {
+            Some(prefix) if !prefix.contains('*') => prefix,
+            _ => bail!(
+                "invalid package name pattern `{}`, a `*` may only end the pattern",
+                pattern
+            ),
+        };
+        validate_package_name(prefix, "package name pattern", "")?;
+        Ok(ProfilePackageSpec::Glob(InternedString::new(prefix)))
+    }
+}
+
 impl ser::Serialize for ProfilePackageSpec {
     fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
     where
@@ -424,6 +903,7 @@ impl ser::Serialize for ProfilePackageSpec {
     {
         match *self {
             ProfilePackageSpec::Spec(ref spec) => spec.serialize(s),
+            ProfilePackageSpec::Glob(prefix) => format!("{}*", prefix).serialize(s),
             ProfilePackageSpec::All => "*".serialize(s),
         }
     }
@@ -437,6 +917,8 @@ impl<'de> de::Deserialize<'de> for ProfilePackageSpec {
         let string = String::deserialize(d)?;
         if string == "*" {
             Ok(ProfilePackageSpec::All)
+        } else if string.contains('*') {
+            ProfilePackageSpec::parse_glob(&string).map_err(de::Error::custom)
         } else {
             PackageIdSpec::parse(&string)
                 .map_err(de::Error::custom)
@@ -458,6 +940,12 @@ impl TomlProfile {
         }
         if let Some(ref packages) = self.package {
             features.require(Feature::profile_overrides())?;
+            if packages
+                .keys()
+                .any(|spec| matches!(spec, ProfilePackageSpec::Glob(_)))
+            {
+                features.require(Feature::profile_package_globs())?;
+            }
             for profile in packages.values() {
                 profile.validate_override("package", features)?;
             }
@@ -526,6 +1014,26 @@ impl TomlProfile {
             features.require(Feature::strip())?;
         }
 
+        if self.timeout.is_some() || self.retries.is_some() {
+            features.require(Feature::test_timeouts())?;
+        }
+
+        if let Some(sanitizer) = &self.sanitizer {
+            features.require(Feature::sanitizers())?;
+            sanitizer
+                .parse::<Sanitizer>()
+                .with_context(|| format!("invalid `profile.{}.sanitizer`", name))?;
+        }
+
+        if let Some(trim_paths) = &self.trim_paths {
+            TrimPaths::from_toml(trim_paths)
+                .with_context(|| format!("invalid `profile.{}.trim-paths`", name))?;
+        }
+
+        if self.signing.is_some() {
+            features.require(Feature::code_signing())?;
+        }
+
         if let Some(codegen_backend) = &self.codegen_backend {
             features.require(Feature::codegen_backend())?;
             if codegen_backend.contains(|c: char| !c.is_ascii_alphanumeric() && c != '_') {
@@ -635,6 +1143,22 @@ impl TomlProfile {
         if self.rpath.is_some() {
             bail!("`rpath` may not be specified in a `{}` profile", which)
         }
+        if self.timeout.is_some() {
+            bail!("`timeout` may not be specified in a `{}` profile", which)
+        }
+        if self.retries.is_some() {
+            bail!("`retries` may not be specified in a `{}` profile", which)
+        }
+        if self.sanitizer.is_some() {
+            bail!("`sanitizer` may not be specified in a `{}` profile", which)
+        }
+        if self.signing.is_some() {
+            bail!("`signing` may not be specified in a `{}` profile", which)
+        }
+        if let Some(trim_paths) = &self.trim_paths {
+            TrimPaths::from_toml(trim_paths)
+                .with_context(|| format!("invalid `trim-paths` in a `{}` profile", which))?;
+        }
         if self.codegen_backend.is_some() {
             features.require(Feature::codegen_backend())?;
         }
@@ -721,6 +1245,26 @@ impl TomlProfile {
         if let Some(v) = &profile.strip {
             self.strip = Some(v.clone());
         }
+
+        if let Some(v) = profile.timeout {
+            self.timeout = Some(v);
+        }
+
+        if let Some(v) = profile.retries {
+            self.retries = Some(v);
+        }
+
+        if let Some(v) = &profile.sanitizer {
+            self.sanitizer = Some(v.clone());
+        }
+
+        if let Some(v) = &profile.trim_paths {
+            self.trim_paths = Some(v.clone());
+        }
+
+        if let Some(v) = &profile.signing {
+            self.signing = Some(v.clone());
+        }
     }
 }
 
@@ -776,6 +1320,35 @@ pub enum StringOrBool {
     Bool(bool),
 }
 
+/// The `signing` profile setting, how the binaries and `cdylib`s built for
+/// Apple and Windows targets are code signed after linking.
+#[derive(Clone, Debug, Deserialize, Serialize, Default, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TomlSigning {
+    /// The signing identity: a name or SHA-1 hash of a certificate, or a
+    /// `.pfx` file for Windows.
+    pub identity: Option<String>,
+    /// The entitlements file of Apple targets.
+    pub entitlements: Option<String>,
+    /// The URL of the timestamp server.
+    pub timestamp_server: Option<String>,
+    /// Whether the artifacts are signed for notarization by Apple.
+    pub notarize: Option<bool>,
+}
+
+/// The `trim-paths` profile setting: a boolean, or one or more of the kinds
+/// of output to trim the paths from.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(
+    untagged,
+    expecting = "expected a boolean, a string or an array of strings"
+)]
+pub enum TomlTrimPaths {
+    Bool(bool),
+    String(String),
+    Values(Vec<String>),
+}
+
 #[derive(PartialEq, Clone, Debug, Serialize)]
 #[serde(untagged)]
 pub enum VecStringOrBool {
@@ -872,6 +1445,7 @@ pub struct TomlProject {
     autoexamples: Option<bool>,
     autotests: Option<bool>,
     autobenches: Option<bool>,
+    autofuzz: Option<bool>,
     default_run: Option<String>,
 
     // Package metadata.
@@ -885,6 +1459,15 @@ pub struct TomlProject {
     license_file: Option<String>,
     repository: Option<String>,
     resolver: Option<String>,
+    build_info: Option<TomlBuildInfo>,
+    assets: Option<BTreeMap<String, TomlAsset>>,
+    autofix: Option<bool>,
+    strict: Option<bool>,
+    pkg_config: Option<TomlPkgConfig>,
+    artifact_name: Option<String>,
+    component: Option<TomlComponent>,
+    windows: Option<TomlWindows>,
+    expected_cfgs: Option<Vec<String>>,
 
     // Note that this field must come last due to the way toml serialization
     // works which requires tables to be emitted after all values.
@@ -898,6 +1481,14 @@ pub struct TomlWorkspace {
     default_members: Option<Vec<String>>,
     exclude: Option<Vec<String>>,
     resolver: Option<String>,
+    groups: Option<BTreeMap<String, Vec<String>>>,
+    links: Option<BTreeMap<String, TomlLinksPolicy>>,
+    changelog: Option<TomlChangelog>,
+    policy: Option<TomlPolicy>,
+    #[serde(rename = "minimal-versions")]
+    minimal_versions: Option<TomlMinimalVersions>,
+    #[serde(rename = "allow-prerelease")]
+    allow_prerelease: Option<Vec<String>>,
 
     // Note that this field must come last due to the way toml serialization
     // works which requires tables to be emitted after all values.
@@ -965,6 +1556,8 @@ impl TomlManifest {
             example: self.example.clone(),
             test: self.test.clone(),
             bench: self.bench.clone(),
+            fuzz: self.fuzz.clone(),
+            codegen: self.codegen.clone(),
             dependencies: map_deps(config, self.dependencies.as_ref(), all)?,
             dev_dependencies: map_deps(
                 config,
@@ -1018,9 +1611,16 @@ impl TomlManifest {
             },
             replace: None,
             patch: None,
+            feature_overrides: None,
+            config_flags: None,
+            env: self.env.clone(),
             workspace: None,
             badges: self.badges.clone(),
+            feature_matrix: self.feature_matrix.clone(),
+            lints: self.lints.clone(),
+            headers: self.headers.clone(),
             cargo_features: self.cargo_features.clone(),
+            manifest_version: self.manifest_version.clone(),
         });
 
         fn map_deps(
@@ -1141,6 +1741,74 @@ impl TomlManifest {
             features.require(Feature::metabuild())?;
         }
 
+        let build_info = match &project.build_info {
+            None | Some(TomlBuildInfo::Bool(false)) => None,
+            Some(TomlBuildInfo::Bool(true)) => Some(BuildTimestamp::Build),
+            Some(TomlBuildInfo::Detailed { timestamp }) => match timestamp.as_str() {
+                "build" => Some(BuildTimestamp::Build),
+                "commit" => Some(BuildTimestamp::Commit),
+                _ => bail!(
+                    "unknown `package.build-info.timestamp` value `{}`, \
+                     expected `build` or `commit`",
+                    timestamp
+                ),
+            },
+        };
+        if project.build_info.is_some() {
+            features.require(Feature::build_info())?;
+        }
+        let assets = match &project.assets {
+            Some(assets) => {
+                features.require(Feature::assets())?;
+                TomlManifest::assets(assets, package_root)?
+            }
+            None => BTreeMap::new(),
+        };
+        if project.autofix.is_some() {
+            features.require(Feature::autofix())?;
+        }
+        if project.strict.is_some() {
+            features.require(Feature::strict_manifest())?;
+        }
+        if let Some(pkg_config) = &project.pkg_config {
+            features.require(Feature::pkg_config())?;
+            if let Some(name) = &pkg_config.name {
+                if name.is_empty()
+                    || name.contains(|c: char| c == '/' || c == '\\' || c.is_whitespace())
+                {
+                    bail!(
+                        "`package.pkg-config.name` must be a file name without spaces, found `{}`",
+                        name
+                    );
+                }
+            }
+        }
+        if let Some(template) = &project.artifact_name {
+            features.require(Feature::artifact_name())?;
+            validate_artifact_name(template)?;
+        }
+        if let Some(component) = &project.component {
+            features.require(Feature::wasm_component())?;
+            if component.world.is_some() && component.wit.is_none() {
+                bail!("`package.component.world` requires `package.component.wit`");
+            }
+        }
+        if let Some(windows) = &project.windows {
+            features.require(Feature::windows_resources())?;
+            for name in windows.version_info.iter().flat_map(|info| info.keys()) {
+                if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric()) {
+                    bail!(
+                        "invalid name `{}` in `package.windows.version-info`, \
+                         the names may only contain ASCII letters and digits",
+                        name
+                    );
+                }
+            }
+        }
+        if me.manifest_version.is_some() {
+            features.require(Feature::manifest_version())?;
+        }
+
         if project.resolver.is_some()
             || me
                 .workspace
@@ -1267,6 +1935,17 @@ impl TomlManifest {
             replace = me.replace(&mut cx)?;
             patch = me.patch(&mut cx)?;
         }
+        let feature_overrides = me.feature_overrides(&features)?;
+        let config_flags = me.config_flags(&features)?;
+        let env = me.env(&features, package_root)?;
+        let deny_warnings = me.workspace_deny_warnings(&features)?;
+        let expected_cfgs = match &project.expected_cfgs {
+            Some(cfgs) => {
+                features.require(Feature::config_flags())?;
+                Some(parse_cfgs(cfgs, "package.expected-cfgs")?)
+            }
+            None => None,
+        };
 
         {
             let mut names_sources = BTreeMap::new();
@@ -1288,15 +1967,20 @@ impl TomlManifest {
         let include = project.include.clone().unwrap_or_default();
         let empty_features = BTreeMap::new();
 
-        let summary = Summary::new(
+        let mut summary = Summary::new(
             config,
             pkgid,
             deps,
-            me.features.as_ref().unwrap_or(&empty_features),
+            me.features().unwrap_or(&empty_features),
             project.links.as_deref(),
         )?;
+        summary.set_rust_version(rust_version.as_deref().map(InternedString::new));
         let unstable = config.cli_unstable();
         summary.unstable_gate(unstable.namespaced_features, unstable.weak_dep_features)?;
+        if let Some(metadata) = me.feature_metadata() {
+            features.require(Feature::feature_metadata())?;
+            validate_feature_metadata(metadata, summary.features())?;
+        }
 
         let metadata = ManifestMetadata {
             description: project.description.clone(),
@@ -1313,12 +1997,102 @@ impl TomlManifest {
             links: project.links.clone(),
         };
 
+        if me
+            .workspace
+            .as_ref()
+            .map_or(false, |ws| ws.groups.is_some())
+        {
+            features.require(Feature::workspace_groups())?;
+        }
+        let links_policies = match me.workspace.as_ref().and_then(|ws| ws.links.as_ref()) {
+            Some(links) => TomlManifest::links_policies(links, &features)?,
+            None => BTreeMap::new(),
+        };
+        let changelog = match me.workspace.as_ref().and_then(|ws| ws.changelog.as_ref()) {
+            Some(changelog) => Some(TomlManifest::changelog(changelog, &features)?),
+            None => None,
+        };
+        let allow_prerelease = match me
+            .workspace
+            .as_ref()
+            .and_then(|ws| ws.allow_prerelease.as_ref())
+        {
+            Some(names) => TomlManifest::allow_prerelease(names, &features)?,
+            None => BTreeSet::new(),
+        };
+        let policy = match me.workspace.as_ref().and_then(|ws| ws.policy.as_ref()) {
+            Some(policy) => Some(TomlManifest::policy(policy, &features)?),
+            None => None,
+        };
+        if me.feature_matrix.is_some() {
+            features.require(Feature::feature_matrix())?;
+        }
+        if let Some(headers) = &me.headers {
+            features.require(Feature::c_headers())?;
+            if let Some(name) = &headers.name {
+                if name.is_empty() || name.contains(['/', '\\']) {
+                    bail!("`headers.name` must be a file name, found `{}`", name);
+                }
+            }
+        }
+        if let Some(lints) = &me.lints {
+            if lints.doc_coverage.is_some() || lints.doc_example_coverage.is_some() {
+                features.require(Feature::doc_coverage())?;
+            }
+            if lints.rust.is_some() {
+                features.require(Feature::check_cfg())?;
+            }
+            if let Some(lint) = lints.unexpected_cfgs() {
+                if let Some(level) = &lint.level {
+                    if !["allow", "warn", "deny", "forbid"].contains(&level.as_str()) {
+                        bail!(
+                            "`lints.rust.unexpected_cfgs.level` must be one of \
+                             `allow`, `warn`, `deny` or `forbid`, found `{}`",
+                            level
+                        );
+                    }
+                }
+                for spec in lint.check_cfg.iter().flatten() {
+                    if !spec.starts_with("cfg(") || !spec.ends_with(')') {
+                        bail!(
+                            "`lints.rust.unexpected_cfgs.check-cfg` entries must be \
+                             like `cfg(name)` or `cfg(name, values(\"a\", \"b\"))`, found `{}`",
+                            spec
+                        );
+                    }
+                }
+            }
+            for (key, value) in [
+                ("doc-coverage", lints.doc_coverage),
+                ("doc-example-coverage", lints.doc_example_coverage),
+            ] {
+                if let Some(value) = value {
+                    if !(0.0..=100.0).contains(&value) {
+                        bail!(
+                            "`lints.{}` must be a percentage between 0 and 100, found `{}`",
+                            key,
+                            value
+                        );
+                    }
+                }
+            }
+        }
         let workspace_config = match (me.workspace.as_ref(), project.workspace.as_ref()) {
             (Some(config), None) => WorkspaceConfig::Root(WorkspaceRootConfig::new(
                 package_root,
                 &config.members,
                 &config.default_members,
                 &config.exclude,
+                &config.groups,
+                links_policies,
+                changelog,
+                policy,
+                config
+                    .minimal_versions
+                    .as_ref()
+                    .map(TomlMinimalVersions::exempt)
+                    .unwrap_or_default(),
+                allow_prerelease,
                 &config.metadata,
             )),
             (None, root) => WorkspaceConfig::Member {
@@ -1387,6 +2161,10 @@ impl TomlManifest {
             publish,
             replace,
             patch,
+            feature_overrides,
+            config_flags,
+            env,
+            deny_warnings,
             workspace_config,
             features,
             edition,
@@ -1395,6 +2173,10 @@ impl TomlManifest {
             project.default_run.clone(),
             Rc::clone(me),
             project.metabuild.clone().map(|sov| sov.0),
+            build_info.map(|timestamp| BuildInfo { timestamp }),
+            assets,
+            project.autofix.unwrap_or(true),
+            expected_cfgs,
             resolve_behavior,
         );
         if project.license_file.is_some() && project.license.is_some() {
@@ -1447,6 +2229,12 @@ impl TomlManifest {
         if me.bench.is_some() {
             bail!("this virtual manifest specifies a [[bench]] section, which is not allowed");
         }
+        if me.fuzz.is_some() {
+            bail!("this virtual manifest specifies a [[fuzz]] section, which is not allowed");
+        }
+        if me.codegen.is_some() {
+            bail!("this virtual manifest specifies a [[codegen]] section, which is not allowed");
+        }
         if me.dependencies.is_some() {
             bail!("this virtual manifest specifies a [dependencies] section, which is not allowed");
         }
@@ -1465,6 +2253,25 @@ impl TomlManifest {
         if me.badges.is_some() {
             bail!("this virtual manifest specifies a [badges] section, which is not allowed");
         }
+        if me.feature_matrix.is_some() {
+            bail!(
+                "this virtual manifest specifies a [feature-matrix] section, which is not allowed"
+            );
+        }
+        if me.headers.is_some() {
+            bail!("this virtual manifest specifies a [headers] section, which is not allowed");
+        }
+        if let Some(lints) = &me.lints {
+            if lints.doc_coverage.is_some()
+                || lints.doc_example_coverage.is_some()
+                || lints.rust.is_some()
+            {
+                bail!(
+                    "this virtual manifest specifies a [lints] section, which is not allowed, \
+                     except for `workspace-deny-warnings`"
+                );
+            }
+        }
 
         let mut nested_paths = Vec::new();
         let mut warnings = Vec::new();
@@ -1472,6 +2279,9 @@ impl TomlManifest {
         let empty = Vec::new();
         let cargo_features = me.cargo_features.as_ref().unwrap_or(&empty);
         let features = Features::new(cargo_features, config, &mut warnings, source_id.is_path())?;
+        if me.manifest_version.is_some() {
+            features.require(Feature::manifest_version())?;
+        }
 
         let (replace, patch) = {
             let mut cx = Context {
@@ -1486,6 +2296,10 @@ impl TomlManifest {
             };
             (me.replace(&mut cx)?, me.patch(&mut cx)?)
         };
+        let feature_overrides = me.feature_overrides(&features)?;
+        let config_flags = me.config_flags(&features)?;
+        let env = me.env(&features, root)?;
+        let deny_warnings = me.workspace_deny_warnings(&features)?;
         let profiles = me.profile.clone();
         if let Some(profiles) = &profiles {
             profiles.validate(&features, &mut warnings)?;
@@ -1503,12 +2317,41 @@ impl TomlManifest {
             .and_then(|ws| ws.resolver.as_deref())
             .map(|r| ResolveBehavior::from_manifest(r))
             .transpose()?;
+        if me
+            .workspace
+            .as_ref()
+            .map_or(false, |ws| ws.groups.is_some())
+        {
+            features.require(Feature::workspace_groups())?;
+        }
         let workspace_config = match me.workspace {
             Some(ref config) => WorkspaceConfig::Root(WorkspaceRootConfig::new(
                 root,
                 &config.members,
                 &config.default_members,
                 &config.exclude,
+                &config.groups,
+                match &config.links {
+                    Some(links) => TomlManifest::links_policies(links, &features)?,
+                    None => BTreeMap::new(),
+                },
+                match &config.changelog {
+                    Some(changelog) => Some(TomlManifest::changelog(changelog, &features)?),
+                    None => None,
+                },
+                match &config.policy {
+                    Some(policy) => Some(TomlManifest::policy(policy, &features)?),
+                    None => None,
+                },
+                config
+                    .minimal_versions
+                    .as_ref()
+                    .map(TomlMinimalVersions::exempt)
+                    .unwrap_or_default(),
+                match &config.allow_prerelease {
+                    Some(names) => TomlManifest::allow_prerelease(names, &features)?,
+                    None => BTreeSet::new(),
+                },
                 &config.metadata,
             )),
             None => {
@@ -1519,6 +2362,10 @@ impl TomlManifest {
             VirtualManifest::new(
                 replace,
                 patch,
+                feature_overrides,
+                config_flags,
+                env,
+                deny_warnings,
                 workspace_config,
                 profiles,
                 features,
@@ -1591,6 +2438,280 @@ impl TomlManifest {
         Ok(patch)
     }
 
+    fn feature_overrides(
+        &self,
+        features: &Features,
+    ) -> CargoResult<BTreeMap<InternedString, FeatureOverride>> {
+        let overrides = match &self.feature_overrides {
+            Some(overrides) => overrides,
+            None => return Ok(BTreeMap::new()),
+        };
+        features.require(Feature::feature_overrides())?;
+        let mut result = BTreeMap::new();
+        for (name, o) in overrides {
+            let enable = o.enable.clone().unwrap_or_default();
+            let disable = o.disable.clone().unwrap_or_default();
+            if let Some(feature) = enable.iter().find(|f| disable.contains(f)) {
+                bail!(
+                    "`[feature-overrides]` entry `{}` both enables and disables feature `{}`",
+                    name,
+                    feature
+                );
+            }
+            result.insert(
+                *name,
+                FeatureOverride {
+                    default_features: o.default_features,
+                    enable,
+                    disable,
+                },
+            );
+        }
+        Ok(result)
+    }
+
+    /// Whether `lints.workspace-deny-warnings` denies the warnings of the
+    /// workspace members.
+    fn workspace_deny_warnings(&self, features: &Features) -> CargoResult<bool> {
+        let deny = match self.lints.as_ref().and_then(|l| l.workspace_deny_warnings) {
+            Some(deny) => deny,
+            None => return Ok(false),
+        };
+        features.require(Feature::deny_warnings())?;
+        Ok(deny)
+    }
+
+    fn config_flags(&self, features: &Features) -> CargoResult<BTreeMap<InternedString, Vec<Cfg>>> {
+        let flags = match &self.config_flags {
+            Some(flags) => flags,
+            None => return Ok(BTreeMap::new()),
+        };
+        features.require(Feature::config_flags())?;
+        let mut result = BTreeMap::new();
+        for (name, cfgs) in flags {
+            result.insert(
+                *name,
+                parse_cfgs(cfgs, &format!("`[config-flags]` entry `{}`", name))?,
+            );
+        }
+        Ok(result)
+    }
+
+    fn assets(
+        assets: &BTreeMap<String, TomlAsset>,
+        root: &Path,
+    ) -> CargoResult<BTreeMap<String, Asset>> {
+        let mut result = BTreeMap::new();
+        for (name, asset) in assets {
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+                || name.starts_with(|c: char| c.is_ascii_digit())
+            {
+                bail!(
+                    "invalid asset name `{}`, names may only contain letters, digits, \
+                     `_` and `-`, and may not start with a digit",
+                    name
+                );
+            }
+            let (path, compress) = match asset {
+                TomlAsset::Path(path) => (path, None),
+                TomlAsset::Detailed(detail) => (&detail.path, detail.compress.as_deref()),
+            };
+            let compress = match compress {
+                None | Some("none") => None,
+                Some("gzip") => Some(AssetCompression::Gzip),
+                Some(other) => bail!(
+                    "unknown `compress` value `{}` for asset `{}`, expected `none` or `gzip`",
+                    other,
+                    name
+                ),
+            };
+            let is_glob = path.contains(|c| matches!(c, '*' | '?' | '['));
+            if is_glob {
+                glob::Pattern::new(path)
+                    .with_context(|| format!("invalid pattern `{}` for asset `{}`", path, name))?;
+            }
+            result.insert(
+                name.clone(),
+                Asset {
+                    path: root.join(path),
+                    is_glob,
+                    compress,
+                },
+            );
+        }
+        Ok(result)
+    }
+
+    fn links_policies(
+        links: &BTreeMap<String, TomlLinksPolicy>,
+        features: &Features,
+    ) -> CargoResult<BTreeMap<InternedString, LinksPolicy>> {
+        features.require(Feature::links_policy())?;
+        let mut result = BTreeMap::new();
+        for (links, policy) in links {
+            if policy.packages.is_empty() {
+                bail!("`workspace.links.{}.packages` must list a package", links);
+            }
+            if let Some(winner) = &policy.winner {
+                if !policy.packages.contains(winner) {
+                    bail!(
+                        "`workspace.links.{}.winner` is `{}`, which is not one of its `packages`",
+                        links,
+                        winner
+                    );
+                }
+            }
+            result.insert(
+                InternedString::new(links),
+                LinksPolicy {
+                    packages: policy
+                        .packages
+                        .iter()
+                        .map(|name| InternedString::new(name))
+                        .collect(),
+                    winner: policy.winner.as_deref().map(InternedString::new),
+                },
+            );
+        }
+        Ok(result)
+    }
+
+    fn changelog(changelog: &TomlChangelog, features: &Features) -> CargoResult<ChangelogConfig> {
+        features.require(Feature::changelog_fragments())?;
+        Ok(ChangelogConfig {
+            fragments: PathBuf::from(changelog.fragments.as_deref().unwrap_or("changes")),
+            file: PathBuf::from(changelog.file.as_deref().unwrap_or("CHANGELOG.md")),
+            require_entry: changelog.require_entry.unwrap_or(true),
+        })
+    }
+
+    fn allow_prerelease(
+        names: &[String],
+        features: &Features,
+    ) -> CargoResult<BTreeSet<InternedString>> {
+        features.require(Feature::allow_prerelease())?;
+        for name in names {
+            validate_package_name(name, "package name", "")?;
+        }
+        Ok(names.iter().map(|name| InternedString::new(name)).collect())
+    }
+
+    fn policy(policy: &TomlPolicy, features: &Features) -> CargoResult<WorkspacePolicy> {
+        features.require(Feature::workspace_policy())?;
+        let names = |names: &Option<Vec<String>>| {
+            names
+                .iter()
+                .flatten()
+                .map(|name| InternedString::new(name))
+                .collect::<Vec<_>>()
+        };
+        let packages = |list: &Option<Vec<TomlDeniedPackage>>, key: &str| {
+            let mut packages = Vec::new();
+            for package in list.iter().flatten() {
+                packages.push(match package {
+                    TomlDeniedPackage::Simple(name) => DeniedPackage {
+                        name: InternedString::new(name),
+                        version: None,
+                        reason: None,
+                    },
+                    TomlDeniedPackage::Detailed(detail) => DeniedPackage {
+                        name: InternedString::new(&detail.name),
+                        version: match &detail.version {
+                            Some(version) => Some(version.parse().with_context(|| {
+                                format!(
+                                    "invalid `version` of the package `{}` in \
+                                     `workspace.policy.{}`",
+                                    detail.name, key
+                                )
+                            })?),
+                            None => None,
+                        },
+                        reason: detail.reason.clone(),
+                    },
+                });
+            }
+            Ok::<_, anyhow::Error>(packages)
+        };
+        let deny_build_scripts_by_default = match policy.build_scripts.as_deref() {
+            None | Some("allow") => false,
+            Some("deny") => true,
+            Some(other) => bail!(
+                "`workspace.policy.build-scripts` must be `allow` or `deny`, found `{}`",
+                other
+            ),
+        };
+        if policy.max_duplicate_versions == Some(0) {
+            bail!("`workspace.policy.max-duplicate-versions` must be at least 1");
+        }
+        Ok(WorkspacePolicy {
+            allowed_licenses: policy.allowed_licenses.clone(),
+            license_exceptions: names(&policy.license_exceptions),
+            deny: packages(&policy.deny, "deny")?,
+            max_duplicate_versions: policy.max_duplicate_versions.map(|max| max as usize),
+            allow_duplicates: names(&policy.allow_duplicates),
+            allowed_registries: policy.allowed_registries.clone(),
+            allowed_git: policy.allowed_git.clone(),
+            allowed_sources: policy.allowed_sources.clone(),
+            deny_build_scripts_by_default,
+            allow_build_scripts: packages(&policy.allow_build_scripts, "allow-build-scripts")?,
+            deny_build_scripts: packages(&policy.deny_build_scripts, "deny-build-scripts")?,
+            require_audits: match policy.require_audits.as_deref() {
+                None => None,
+                Some("warn") => Some(RequireAudits::Warn),
+                Some("deny") => Some(RequireAudits::Deny),
+                Some(other) => bail!(
+                    "`workspace.policy.require-audits` must be `warn` or `deny`, found `{}`",
+                    other
+                ),
+            },
+        })
+    }
+
+    fn env(&self, features: &Features, root: &Path) -> CargoResult<BTreeMap<String, EnvValue>> {
+        let env = match &self.env {
+            Some(env) => env,
+            None => return Ok(BTreeMap::new()),
+        };
+        features.require(Feature::manifest_env())?;
+        let mut result = BTreeMap::new();
+        for (key, value) in env {
+            if key.is_empty() || key.contains('=') || key.contains('\0') {
+                bail!("invalid `[env]` variable name `{}`", key);
+            }
+            if key.starts_with("CARGO_") {
+                bail!(
+                    "`[env]` variable `{}` is reserved, \
+                     variables starting with `CARGO_` are set by Cargo",
+                    key
+                );
+            }
+            let value = match value {
+                TomlEnvValue::Simple(value) => EnvValue::Value(value.clone()),
+                TomlEnvValue::Detailed(detail) => match (&detail.value, &detail.file) {
+                    (Some(_), Some(_)) | (None, None) => bail!(
+                        "`[env]` variable `{}` must specify exactly one of `value` or `file`",
+                        key
+                    ),
+                    (Some(value), None) if detail.relative == Some(true) => {
+                        EnvValue::Value(root.join(value).display().to_string())
+                    }
+                    (Some(value), None) => EnvValue::Value(value.clone()),
+                    (None, Some(_)) if detail.relative.is_some() => bail!(
+                        "`[env]` variable `{}` can't be `relative`, files are always \
+                         relative to the manifest",
+                        key
+                    ),
+                    (None, Some(file)) => EnvValue::File(root.join(file)),
+                },
+            };
+            result.insert(key.clone(), value);
+        }
+        Ok(result)
+    }
+
     /// Returns the path to the build script if one exists for this crate.
     fn maybe_custom_build(
         &self,
@@ -1620,8 +2741,151 @@ impl TomlManifest {
     }
 
     pub fn features(&self) -> Option<&BTreeMap<InternedString, Vec<InternedString>>> {
-        self.features.as_ref()
+        self.features.as_ref().map(|f| &f.features)
+    }
+
+    pub fn feature_metadata(&self) -> Option<&BTreeMap<InternedString, TomlFeatureMetadata>> {
+        self.features.as_ref().and_then(|f| f.metadata.as_ref())
+    }
+
+    pub fn feature_matrix(&self) -> Option<&TomlFeatureMatrix> {
+        self.feature_matrix.as_ref()
+    }
+
+    pub fn lints(&self) -> Option<&TomlLints> {
+        self.lints.as_ref()
+    }
+
+    pub fn headers(&self) -> Option<&TomlHeaders> {
+        self.headers.as_ref()
+    }
+
+    pub fn pkg_config(&self) -> Option<&TomlPkgConfig> {
+        self.package
+            .as_ref()
+            .or_else(|| self.project.as_ref())
+            .and_then(|p| p.pkg_config.as_ref())
+    }
+
+    pub fn artifact_name(&self) -> Option<&str> {
+        self.package
+            .as_ref()
+            .or_else(|| self.project.as_ref())
+            .and_then(|p| p.artifact_name.as_deref())
+    }
+
+    pub fn component(&self) -> Option<&TomlComponent> {
+        self.package
+            .as_ref()
+            .or_else(|| self.project.as_ref())
+            .and_then(|p| p.component.as_ref())
+    }
+
+    pub fn windows(&self) -> Option<&TomlWindows> {
+        self.package
+            .as_ref()
+            .or_else(|| self.project.as_ref())
+            .and_then(|p| p.windows.as_ref())
+    }
+}
+
+/// The placeholders which may be used in `package.artifact-name`.
+pub const ARTIFACT_NAME_PLACEHOLDERS: &[&str] = &["name", "version", "target", "profile"];
+
+/// Checks that the `package.artifact-name` template only uses known
+/// placeholders, and can't name a file outside of the output directory.
+fn validate_artifact_name(template: &str) -> CargoResult<()> {
+    if template.is_empty() {
+        bail!("`package.artifact-name` cannot be empty");
+    }
+    if template.contains(|c: char| c == '/' || c == '\\') {
+        bail!(
+            "`package.artifact-name` must be a file name, found `{}`",
+            template
+        );
+    }
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => bail!("unclosed `{{` in `package.artifact-name` `{}`", template),
+        };
+        let placeholder = &rest[start + 1..end];
+        if !ARTIFACT_NAME_PLACEHOLDERS.contains(&placeholder) {
+            bail!(
+                "unknown placeholder `{{{}}}` in `package.artifact-name` `{}`, \
+                 the supported placeholders are {}",
+                placeholder,
+                template,
+                ARTIFACT_NAME_PLACEHOLDERS
+                    .iter()
+                    .map(|p| format!("`{{{}}}`", p))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+        rest = &rest[end + 1..];
     }
+    Ok(())
+}
+
+/// Parses the cfgs of `[config-flags]` and `package.expected-cfgs`, like
+/// `foo` or `foo = "bar"`. The cfgs set by Cargo and rustc can't be used.
+fn parse_cfgs(cfgs: &[String], what: &str) -> CargoResult<Vec<Cfg>> {
+    let mut result = Vec::new();
+    for cfg in cfgs {
+        let parsed: Cfg = cfg
+            .parse()
+            .with_context(|| format!("invalid cfg `{}` in {}", cfg, what))?;
+        let name = match &parsed {
+            Cfg::Name(name) | Cfg::KeyPair(name, _) => name.as_str(),
+        };
+        if matches!(
+            name,
+            "feature" | "test" | "debug_assertions" | "doc" | "doctest" | "proc_macro"
+        ) || name.starts_with("target_")
+            || name == "unix"
+            || name == "windows"
+        {
+            bail!(
+                "the cfg `{}` in {} is set by Cargo or rustc and can't be configured",
+                cfg,
+                what
+            );
+        }
+        if !result.contains(&parsed) {
+            result.push(parsed);
+        }
+    }
+    Ok(result)
+}
+
+/// Checks that `[features.metadata]` only refers to features of the package.
+fn validate_feature_metadata(
+    metadata: &BTreeMap<InternedString, TomlFeatureMetadata>,
+    feature_map: &FeatureMap,
+) -> CargoResult<()> {
+    for (name, meta) in metadata {
+        if !feature_map.contains_key(name) {
+            bail!(
+                "`[features.metadata]` describes `{}`, which is not a feature of this package",
+                name
+            );
+        }
+        for conflict in meta.conflicts.iter().flatten() {
+            if conflict == name {
+                bail!("feature `{}` cannot conflict with itself", name);
+            }
+            if !feature_map.contains_key(conflict) {
+                bail!(
+                    "feature `{}` conflicts with `{}`, which is not a feature of this package",
+                    name,
+                    conflict
+                );
+            }
+        }
+    }
+    Ok(())
 }
 
 /// Returns the name of the README file for a `TomlProject`.
@@ -0,0 +1,153 @@
+//! The versions of Cargo that introduced the parts of the manifest schema.
+//!
+//! A manifest may declare the version of Cargo whose schema it is written
+//! for with `manifest-version`. A Cargo older than that refuses the manifest
+//! up front, instead of failing to parse what it does not know, and
+//! `cargo validate` checks that the manifest only uses what that version
+//! supports.
+
+use anyhow::bail;
+use semver::Version;
+
+use crate::util::CargoResult;
+
+/// A part of the schema used by a manifest, and the version of Cargo that
+/// introduced it.
+pub struct Requirement {
+    pub version: Version,
+    pub what: String,
+}
+
+/// The version of this Cargo.
+pub fn current() -> Version {
+    let version = crate::version();
+    Version::new(
+        version.major as u64,
+        version.minor as u64,
+        version.patch as u64,
+    )
+}
+
+/// Parses a version of Cargo like `1.56` or `1.56.0`.
+pub fn parse_version(version: &str, what: &str) -> CargoResult<Version> {
+    let parts: Vec<_> = version.split('.').collect();
+    let valid = matches!(parts.len(), 2 | 3)
+        && parts
+            .iter()
+            .all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit()));
+    if !valid {
+        bail!(
+            "{} must be a value like \"1.56\", found `{}`",
+            what,
+            version
+        );
+    }
+    let number = |i: usize| parts.get(i).map_or(Ok(0), |p| p.parse::<u64>());
+    Ok(Version::new(number(0)?, number(1)?, number(2)?))
+}
+
+/// The `manifest-version` of the manifest `toml`, which is checked against
+/// the version of this Cargo before the rest of the manifest is parsed.
+pub fn check_manifest_version(toml: &toml::Value) -> CargoResult<Option<Version>> {
+    let version = match toml.get("manifest-version") {
+        Some(toml::Value::String(version)) => parse_version(version, "`manifest-version`")?,
+        Some(_) => bail!("`manifest-version` must be a string like \"1.56\""),
+        None => return Ok(None),
+    };
+    let current = current();
+    if version > current {
+        bail!(
+            "this manifest needs Cargo {} or newer for its `manifest-version`, \
+             but this is Cargo {}\n\
+             Update Cargo, or lower the `manifest-version` if the manifest only \
+             uses what this version of Cargo supports.",
+            display(&version),
+            current
+        );
+    }
+    Ok(Some(version))
+}
+
+/// The parts of the schema used by the manifest `toml` that not every
+/// version of Cargo supports.
+pub fn requirements(toml: &toml::Value) -> Vec<Requirement> {
+    let mut requirements = Vec::new();
+    let mut require = |version: &str, what: String| {
+        requirements.push(Requirement {
+            version: Version::parse(version).unwrap(),
+            what,
+        })
+    };
+
+    let package = toml.get("package").or_else(|| toml.get("project"));
+    if let Some(package) = package {
+        match package.get("edition").and_then(|e| e.as_str()) {
+            Some("2021") => require("1.56.0", "`package.edition = \"2021\"`".to_string()),
+            Some(_) => require("1.31.0", "`package.edition`".to_string()),
+            None => {}
+        }
+        if package.get("rust-version").is_some() {
+            require("1.56.0", "`package.rust-version`".to_string());
+        }
+        if package.get("default-run").is_some() {
+            require("1.37.0", "`package.default-run`".to_string());
+        }
+    }
+    let workspace = toml.get("workspace");
+    for (table, resolver) in [
+        ("package", package.and_then(|p| p.get("resolver"))),
+        ("workspace", workspace.and_then(|w| w.get("resolver"))),
+    ] {
+        if resolver.and_then(|r| r.as_str()) == Some("2") {
+            require("1.51.0", format!("`{}.resolver = \"2\"`", table));
+        }
+    }
+    if toml.get("patch").is_some() {
+        require("1.21.0", "`[patch]`".to_string());
+    }
+
+    let profiles = toml.get("profile").and_then(|p| p.as_table());
+    for (name, profile) in profiles.into_iter().flatten() {
+        if !["dev", "release", "test", "bench", "doc"].contains(&name.as_str()) {
+            require("1.57.0", format!("the custom profile `profile.{}`", name));
+        }
+        for key in ["package", "build-override"] {
+            if profile.get(key).is_some() {
+                require("1.41.0", format!("`profile.{}.{}`", name, key));
+            }
+        }
+    }
+
+    let mut tables = vec![("", toml)];
+    let targets = toml.get("target").and_then(|t| t.as_table());
+    for (target, table) in targets.into_iter().flatten() {
+        tables.push((target.as_str(), table));
+    }
+    for (target, table) in tables {
+        for kind in ["dependencies", "dev-dependencies", "build-dependencies"] {
+            let deps = table.get(kind).and_then(|d| d.as_table());
+            for (name, dep) in deps.into_iter().flatten() {
+                if dep.get("package").is_some() {
+                    let key = if target.is_empty() {
+                        format!("{}.{}", kind, name)
+                    } else {
+                        format!("target.{}.{}.{}", target, kind, name)
+                    };
+                    require("1.31.0", format!("the renamed dependency `{}`", key));
+                }
+            }
+        }
+    }
+
+    requirements
+}
+
+/// Displays a version of Cargo like `manifest-version` is written, without
+/// a patch version of 0.
+pub fn display(version: &Version) -> String {
+    if version.patch == 0 {
+        format!("{}.{}", version.major, version.minor)
+    } else {
+        version.to_string()
+    }
+}
@@ -6,6 +6,9 @@
 //!  * `examples/*.rs` are examples
 //!  * `tests/*.rs` are integration tests
 //!  * `benches/*.rs` are benchmarks
+//!  * `fuzz/*.rs` are fuzz targets, with the unstable `fuzz-targets` feature
+//!  * `codegen/*.rs` are the default paths of the `[[codegen]]` steps, which
+//!    are never inferred, with the unstable `codegen` feature
 //!
 //! It is a bit tricky because we need match explicit information from `Cargo.toml`
 //! with implicit info in directory layout.
@@ -15,8 +18,8 @@ use std::fs::{self, DirEntry};
 use std::path::{Path, PathBuf};
 
 use super::{
-    PathValue, StringOrBool, StringOrVec, TomlBenchTarget, TomlBinTarget, TomlExampleTarget,
-    TomlLibTarget, TomlManifest, TomlTarget, TomlTestTarget,
+    PathValue, StringOrBool, StringOrVec, TomlBenchTarget, TomlBinTarget, TomlCodegenTarget,
+    TomlExampleTarget, TomlFuzzTarget, TomlLibTarget, TomlManifest, TomlTarget, TomlTestTarget,
 };
 use crate::core::compiler::CrateType;
 use crate::core::{Edition, Feature, Features, Target};
@@ -102,6 +105,25 @@ pub fn targets(
         errors,
     )?);
 
+    targets.extend(clean_fuzz_targets(
+        features,
+        manifest.fuzz.as_ref(),
+        package_root,
+        edition,
+        package.autofuzz,
+        warnings,
+        errors,
+    )?);
+
+    targets.extend(clean_codegen_targets(
+        features,
+        manifest.codegen.as_ref(),
+        package_root,
+        edition,
+        warnings,
+        errors,
+    )?);
+
     // processing the custom build script
     if let Some(custom_build) = manifest.maybe_custom_build(custom_build, package_root) {
         if metabuild.is_some() {
@@ -236,6 +258,10 @@ fn clean_lib(
         (None, _, _) => vec![CrateType::Lib],
     };
 
+    if crate_types.contains(&CrateType::Component) {
+        features.require(Feature::wasm_component())?;
+    }
+
     let mut target = Target::lib_target(&lib.name(), crate_types, path, edition);
     configure(features, lib, &mut target)?;
     Ok(Some(target))
@@ -496,6 +522,94 @@ fn clean_benches(
     Ok(result)
 }
 
+fn clean_fuzz_targets(
+    features: &Features,
+    toml_fuzz_targets: Option<&Vec<TomlFuzzTarget>>,
+    package_root: &Path,
+    edition: Edition,
+    autodiscover: Option<bool>,
+    warnings: &mut Vec<String>,
+    errors: &mut Vec<String>,
+) -> CargoResult<Vec<Target>> {
+    if toml_fuzz_targets.is_some() {
+        features.require(Feature::fuzz_targets())?;
+    }
+    if !features.is_enabled(Feature::fuzz_targets()) {
+        // Without the feature, a `fuzz` directory is most likely a separate
+        // package for the fuzz targets.
+        return Ok(Vec::new());
+    }
+
+    let inferred = infer_from_directory(&package_root.join("fuzz"));
+
+    let targets = clean_targets(
+        "fuzz target",
+        "fuzz",
+        toml_fuzz_targets,
+        &inferred,
+        package_root,
+        edition,
+        autodiscover,
+        warnings,
+        errors,
+        "autofuzz",
+    )?;
+
+    let mut result = Vec::new();
+    for (path, toml) in targets {
+        let mut target =
+            Target::fuzz_target(&toml.name(), path, toml.required_features.clone(), edition);
+        configure(features, &toml, &mut target)?;
+        result.push(target);
+    }
+    Ok(result)
+}
+
+fn clean_codegen_targets(
+    features: &Features,
+    toml_codegen_targets: Option<&Vec<TomlCodegenTarget>>,
+    package_root: &Path,
+    edition: Edition,
+    warnings: &mut Vec<String>,
+    errors: &mut Vec<String>,
+) -> CargoResult<Vec<Target>> {
+    let toml_codegen_targets = match toml_codegen_targets {
+        Some(targets) => targets,
+        None => return Ok(Vec::new()),
+    };
+    features.require(Feature::codegen())?;
+
+    // The files in `codegen` are only used for the paths of the declared
+    // steps, as running a program by accident is worse than a missing target.
+    let inferred = infer_from_directory(&package_root.join("codegen"));
+
+    let targets = clean_targets(
+        "codegen",
+        "codegen",
+        Some(toml_codegen_targets),
+        &inferred,
+        package_root,
+        edition,
+        Some(false),
+        warnings,
+        errors,
+        "autocodegen",
+    )?;
+
+    let mut result = Vec::new();
+    for (path, toml) in targets {
+        if toml.required_features.is_some() {
+            anyhow::bail!(
+                "codegen `{}` can't have `required-features`, \
+                 the package always needs its generated code",
+                toml.name()
+            );
+        }
+        result.push(Target::codegen_target(&toml.name(), path, edition));
+    }
+    Ok(result)
+}
+
 fn clean_targets(
     target_kind_human: &str,
     target_kind: &str,
@@ -0,0 +1,190 @@
+//! The state of the git working tree of a package, shared by the commands
+//! that care about uncommitted changes: `cargo package`, `cargo publish`,
+//! `cargo fix` and `cargo release`.
+//!
+//! By default these commands refuse to work on a dirty working tree, unless
+//! `--allow-dirty` is passed. With `-Z vcs-policy`, the `[vcs.allow-dirty]`
+//! config table sets what each command does instead:
+//!
+//! ```toml
+//! [vcs.allow-dirty]
+//! package = "warn"   # "deny" (the default), "warn" or "allow"
+//! publish = "deny"
+//! fix = "allow"
+//! release = "deny"
+//! ```
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context as _};
+use serde::Deserialize;
+
+use crate::util::{CargoResult, Config};
+
+/// A command checking the state of the working tree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VcsCommand {
+    Package,
+    Publish,
+    Fix,
+    Release,
+}
+
+impl fmt::Display for VcsCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            VcsCommand::Package => "package",
+            VcsCommand::Publish => "publish",
+            VcsCommand::Fix => "fix",
+            VcsCommand::Release => "release",
+        })
+    }
+}
+
+/// What a command does with uncommitted changes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DirtyPolicy {
+    /// Fails, listing the changed files.
+    Deny,
+    /// Lists the changed files in a warning, and goes on.
+    Warn,
+    /// Goes on without checking.
+    Allow,
+}
+
+impl DirtyPolicy {
+    /// The policy of `command`, which is `Allow` with `--allow-dirty`.
+    pub fn for_command(
+        config: &Config,
+        command: VcsCommand,
+        allow_dirty: bool,
+    ) -> CargoResult<DirtyPolicy> {
+        if allow_dirty {
+            return Ok(DirtyPolicy::Allow);
+        }
+        if !config.cli_unstable().vcs_policy {
+            return Ok(DirtyPolicy::Deny);
+        }
+        let key = format!("vcs.allow-dirty.{}", command);
+        Ok(config
+            .get::<Option<DirtyPolicy>>(&key)?
+            .unwrap_or(DirtyPolicy::Deny))
+    }
+
+    /// Fails with `message` and `hint`, or warns with `message`.
+    pub fn report(
+        self,
+        config: &Config,
+        command: VcsCommand,
+        message: &str,
+        hint: &str,
+    ) -> CargoResult<()> {
+        match self {
+            DirtyPolicy::Allow => Ok(()),
+            DirtyPolicy::Warn => config.shell().warn(format!(
+                "{}\n\nproceeding as `vcs.allow-dirty.{}` is set to `warn`",
+                message.trim_end(),
+                command
+            )),
+            DirtyPolicy::Deny => bail!("{}{}", message, hint),
+        }
+    }
+}
+
+/// A file with uncommitted changes.
+#[derive(Debug)]
+pub struct ChangedFile {
+    /// The absolute path of the file, or of a directory of untracked files.
+    pub path: PathBuf,
+    /// Whether the changes are all in the index.
+    pub staged: bool,
+    /// Whether the file is ignored by git.
+    pub ignored: bool,
+}
+
+/// The git repository of the working tree at `path`, if any. The working
+/// tree can be a linked worktree or a submodule.
+pub fn discover(path: &Path) -> Option<git2::Repository> {
+    let repo = git2::Repository::discover(path).ok()?;
+    if repo.workdir().is_none() {
+        return None;
+    }
+    Some(repo)
+}
+
+/// The path of `path` relative to the working tree of `repo`, or `None` if
+/// it is outside of it.
+pub fn path_in_repo(repo: &git2::Repository, path: &Path) -> Option<PathBuf> {
+    let workdir = repo.workdir()?;
+    if let Ok(rel) = path.strip_prefix(workdir) {
+        return Some(rel.to_path_buf());
+    }
+    // The working tree of a worktree or a submodule is recorded in the
+    // repository, and may be spelled differently than `path`.
+    let workdir = workdir.canonicalize().ok()?;
+    let path = path.canonicalize().ok()?;
+    path.strip_prefix(&workdir).ok().map(Path::to_path_buf)
+}
+
+/// The files with uncommitted changes in the working tree of `repo`,
+/// including the files in its submodules. With `include_untracked`, the
+/// untracked and ignored files are included too.
+pub fn changed_files(
+    repo: &git2::Repository,
+    include_untracked: bool,
+) -> CargoResult<Vec<ChangedFile>> {
+    let mut files = Vec::new();
+    collect_changes(repo, include_untracked, &mut files)?;
+    return Ok(files);
+
+    fn collect_changes(
+        repo: &git2::Repository,
+        include_untracked: bool,
+        files: &mut Vec<ChangedFile>,
+    ) -> CargoResult<()> {
+        let mut status_opts = git2::StatusOptions::new();
+        // Exclude submodules, as they are recursed into, so that the changed
+        // files in them are listed.
+        status_opts
+            .exclude_submodules(true)
+            .include_ignored(include_untracked)
+            .include_untracked(include_untracked);
+        let statuses = repo.statuses(Some(&mut status_opts)).with_context(|| {
+            format!(
+                "failed to retrieve git status from repo {}",
+                repo.path().display()
+            )
+        })?;
+        let workdir = repo.workdir().unwrap();
+        let index_changes = git2::Status::INDEX_NEW
+            | git2::Status::INDEX_MODIFIED
+            | git2::Status::INDEX_DELETED
+            | git2::Status::INDEX_RENAMED
+            | git2::Status::INDEX_TYPECHANGE;
+        for entry in statuses.iter() {
+            let status = entry.status();
+            if status == git2::Status::CURRENT {
+                continue;
+            }
+            let path = entry.path().expect("valid utf-8 path");
+            files.push(ChangedFile {
+                // Use an absolute path, so that comparing paths is easier
+                // (particularly with submodules).
+                path: workdir.join(path),
+                staged: index_changes.contains(status),
+                ignored: status == git2::Status::IGNORED,
+            });
+        }
+
+        for submodule in repo.submodules()? {
+            // Ignore submodules that don't open, they are probably not
+            // initialized.
+            if let Ok(sub_repo) = submodule.open() {
+                collect_changes(&sub_repo, include_untracked, files)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,228 @@
+//! Tests for `cargo add`.
+
+use cargo_test_support::registry::Package;
+use cargo_test_support::{basic_lib_manifest, project};
+
+fn licensed(name: &str, vers: &str, license: &str, deps: &[(&str, &str)]) {
+    let mut pkg = Package::new(name, vers);
+    let mut manifest = format!(
+        r#"
+            [package]
+            name = "{}"
+            version = "{}"
+            license = "{}"
+
+            [dependencies]
+        "#,
+        name, vers, license
+    );
+    for (dep, req) in deps {
+        manifest.push_str(&format!("{} = \"{}\"\n", dep, req));
+        pkg.dep(dep, req);
+    }
+    pkg.file("Cargo.toml", &manifest)
+        .file("src/lib.rs", "")
+        .publish();
+}
+
+#[cargo_test]
+fn add_requires_z_flag() {
+    let p = project().file("src/lib.rs", "").build();
+
+    // Without the flag, it is left to an external `cargo-add`.
+    p.cargo("add bar")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[ERROR] no such subcommand: `add`")
+        .run();
+}
+
+#[cargo_test]
+fn add_latest_version() {
+    Package::new("bar", "0.1.0").publish();
+    Package::new("bar", "1.2.0").publish();
+    Package::new("bar", "2.0.0-alpha.1").publish();
+    Package::new("baz", "0.1.0").feature("a", &[]).publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                # Logging.
+                log = "0.1"
+
+                [features]
+                default = []
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+    Package::new("log", "0.1.0").publish();
+
+    p.cargo("-Zadd add bar")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `dummy-registry` index
+[ADDING] bar v1.2.0 to dependencies of foo
+",
+        )
+        .run();
+    p.cargo("-Zadd add baz@0.1 --dev --features a --no-default-features")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[ADDING] baz v0.1 to dev-dependencies of foo")
+        .run();
+    assert_eq!(
+        p.read_file("Cargo.toml"),
+        r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                # Logging.
+                log = "0.1"
+bar = "1.2.0"
+
+                [features]
+                default = []
+            
+
+[dev-dependencies]
+baz = { version = "0.1", features = ["a"], default-features = false }
+"#
+    );
+    p.cargo("check").run();
+}
+
+#[cargo_test]
+fn add_path() {
+    let p = project()
+        .file("Cargo.toml", &basic_lib_manifest("foo"))
+        .file("src/lib.rs", "")
+        .file("crates/bar/Cargo.toml", &basic_lib_manifest("bar"))
+        .file("crates/bar/src/lib.rs", "")
+        .build();
+
+    p.cargo("-Zadd add bar --path ../crates/bar --optional")
+        .cwd("src")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[ADDING] bar ([ROOT]/foo/crates/bar) to dependencies of foo")
+        .run();
+    assert!(p
+        .read_file("Cargo.toml")
+        .ends_with("\n[dependencies]\nbar = { path = \"crates/bar\", optional = true }\n"));
+    p.cargo("check --features bar").run();
+}
+
+#[cargo_test]
+fn add_existing() {
+    Package::new("bar", "1.0.0").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("-Zadd add bar@1.0")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to add `bar` to `foo`
+
+Caused by:
+  `bar` is already in `[dependencies]`
+",
+        )
+        .run();
+
+    // A dependency which doesn't resolve leaves the manifest as it was.
+    let manifest = p.read_file("Cargo.toml");
+    p.cargo("-Zadd add baz@1.0 --analyze")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[ERROR] no matching package named `baz` found")
+        .run();
+    assert_eq!(p.read_file("Cargo.toml"), manifest);
+}
+
+#[cargo_test]
+fn add_analyze() {
+    licensed("log", "0.1.0", "MIT", &[]);
+    licensed("log", "0.2.0", "MIT", &[]);
+    licensed("baz", "1.0.0", "Apache-2.0", &[]);
+    licensed("bar", "1.0.0", "MIT", &[("log", "0.2"), ("baz", "1.0")]);
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                log = "0.1"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("generate-lockfile").run();
+    let manifest = p.read_file("Cargo.toml");
+    let lockfile = p.read_file("Cargo.lock");
+
+    p.cargo("-Zadd add bar --analyze --dry-run")
+        .masquerade_as_nightly_cargo()
+        .with_stdout(
+            "\
+bar v1.0.0: 3 packages, 3 new to the workspace
+  New packages: bar v1.0.0, baz v1.0.0, log v0.2.0
+  Duplicate versions: log v0.1.0 and v0.2.0
+  License: MIT
+  All licenses: Apache-2.0 (1), MIT (2)
+  Build time: unknown, none of the new packages was built with `-Z build-stats`
+",
+        )
+        .with_stderr_contains("[ADDING] bar v1.0.0 to dependencies of foo")
+        .with_stderr_contains("[WARNING] not writing the manifest because of `--dry-run`")
+        .run();
+    assert_eq!(p.read_file("Cargo.toml"), manifest);
+    assert_eq!(p.read_file("Cargo.lock"), lockfile);
+
+    // The build times of the new packages are the ones recorded with
+    // `-Z build-stats`.
+    let unit = |name: &str, duration: f64| {
+        format!(
+            r#"{{"package":"{} 1.0.0 (registry+https://github.com/rust-lang/crates.io-index)","target":"lib","mode":"build","kind":"host","duration":{},"fresh":false,"size":null}}"#,
+            name, duration
+        )
+    };
+    p.change_file(
+        "target/.stats/builds.jsonl",
+        &format!(
+            r#"{{"time":0,"profile":"dev","success":true,"duration":5.0,"peak_memory":null,"units":[{},{}]}}"#,
+            unit("bar", 1.5),
+            unit("baz", 3.0)
+        ),
+    );
+    p.cargo("-Zadd add bar@1.0 --analyze")
+        .masquerade_as_nightly_cargo()
+        .with_stdout_contains("  Build time: about 4.5s, measured for 2 of 3 new packages")
+        .run();
+    assert!(p
+        .read_file("Cargo.toml")
+        .contains("log = \"0.1\"\nbar = \"1.0\"\n"));
+}
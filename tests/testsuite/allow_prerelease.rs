@@ -0,0 +1,113 @@
+//! Tests for the `workspace.allow-prerelease` opt-in.
+
+use cargo_test_support::project;
+use cargo_test_support::registry::Package;
+
+#[cargo_test]
+fn allow_prerelease_requires_feature() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [workspace]
+                allow-prerelease = ["bar"]
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("  feature `allow-prerelease` is required")
+        .run();
+}
+
+#[cargo_test]
+fn transitive_prerelease() {
+    Package::new("bar", "1.0.0-rc.1").publish();
+    Package::new("bar", "2.0.0-rc.1").publish();
+    Package::new("baz", "1.0.0").dep("bar", "1.0").publish();
+
+    // Without the opt-in, the pre-release doesn't match `bar = "1.0"`.
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["allow-prerelease"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                baz = "1.0"
+
+                [workspace]
+                allow-prerelease = []
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("generate-lockfile")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[ERROR] no matching package named `bar` found")
+        .run();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["allow-prerelease"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                baz = "1.0"
+
+                [workspace]
+                allow-prerelease = ["bar"]
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[DOWNLOADED] bar v1.0.0-rc.1 (registry `dummy-registry`)")
+        .run();
+    let lock = p.read_lockfile();
+    assert!(
+        lock.contains(
+            "# bar 1.0.0-rc.1 is a pre-release, allowed by `workspace.allow-prerelease`\n\
+             [[package]]\n\
+             name = \"bar\"\n\
+             version = \"1.0.0-rc.1\"\n"
+        ),
+        "{}",
+        lock
+    );
+    assert!(!lock.contains("# baz"), "{}", lock);
+
+    // The locked pre-release is kept.
+    Package::new("bar", "1.0.0-rc.2").publish();
+    p.cargo("check --locked")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_does_not_contain("[..]rc.2[..]")
+        .run();
+
+    // The release is preferred once published.
+    Package::new("bar", "1.0.0").publish();
+    p.cargo("update -p bar")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[UPDATING] bar v1.0.0-rc.1 -> v1.0.0")
+        .run();
+    let lock = p.read_lockfile();
+    assert!(!lock.contains("allow-prerelease"), "{}", lock);
+}
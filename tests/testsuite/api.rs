@@ -0,0 +1,205 @@
+//! Tests for the stable library API in `cargo::api`.
+
+use cargo::api::{Context, DependencyKind, MetadataOptions, Workspace};
+
+use cargo_test_support::install::cargo_home;
+use cargo_test_support::{basic_manifest, project};
+
+#[cargo_test]
+fn load_workspace() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["foo", "bar"]
+            "#,
+        )
+        .file(
+            "foo/Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                edition = "2018"
+
+                [dependencies]
+                bar = { path = "../bar", optional = true }
+
+                [build-dependencies]
+                baz = { path = "../baz" }
+
+                [features]
+                default = ["bar"]
+            "#,
+        )
+        .file("foo/src/lib.rs", "")
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.2.0"))
+        .file("bar/src/lib.rs", "")
+        .file("baz/Cargo.toml", &basic_manifest("baz", "0.3.0"))
+        .file("baz/src/lib.rs", "")
+        .build();
+    let ctx = Context::with_paths(&p.root(), &cargo_home());
+    let ws = Workspace::load(&ctx, "foo/Cargo.toml".as_ref()).unwrap();
+    assert_eq!(ws.root(), p.root());
+    assert_eq!(ws.root_manifest(), p.root().join("Cargo.toml"));
+    assert_eq!(ws.target_dir(), p.root().join("target"));
+
+    let members = ws.members();
+    let names: Vec<_> = members.iter().map(|m| m.name()).collect();
+    // `baz` is a member as a path dependency in the workspace.
+    assert_eq!(names, ["foo", "bar", "baz"]);
+    let foo = &members[0];
+    assert_eq!(foo.version().to_string(), "0.1.0");
+    assert_eq!(foo.edition(), "2018");
+    assert_eq!(foo.manifest_path(), p.root().join("foo/Cargo.toml"));
+    assert_eq!(foo.features()["default"], ["bar"]);
+    assert_eq!(foo.features()["bar"], ["dep:bar"]);
+    let deps = foo.dependencies();
+    assert_eq!(deps.len(), 2);
+    assert_eq!(deps[0].name(), "bar");
+    assert_eq!(deps[0].kind(), DependencyKind::Normal);
+    assert!(deps[0].is_optional());
+    assert_eq!(deps[1].name(), "baz");
+    assert_eq!(deps[1].kind(), DependencyKind::Build);
+    assert_eq!(deps[1].version_req(), "*");
+    assert!(deps[1].source().starts_with("path+file://"));
+
+    let mut opts = MetadataOptions::default();
+    opts.no_deps = true;
+    let metadata = ws.metadata(&opts).unwrap();
+    assert_eq!(metadata["version"], 1);
+    assert_eq!(metadata["packages"].as_array().unwrap().len(), 3);
+    assert!(metadata["resolve"].is_null());
+
+    let err = Workspace::load(&ctx, "missing/Cargo.toml".as_ref())
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("failed to read"), "{:?}", err);
+}
+
+#[cargo_test]
+fn resolve_and_lockfile() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["foo", "bar"]
+            "#,
+        )
+        .file(
+            "foo/Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                edition = "2018"
+
+                [dependencies]
+                bar = { path = "../bar", optional = true }
+
+                [build-dependencies]
+                baz = { path = "../baz" }
+
+                [features]
+                default = ["bar"]
+            "#,
+        )
+        .file("foo/src/lib.rs", "")
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.2.0"))
+        .file("bar/src/lib.rs", "")
+        .file("baz/Cargo.toml", &basic_manifest("baz", "0.3.0"))
+        .file("baz/src/lib.rs", "")
+        .build();
+    let ctx = Context::with_paths(&p.root(), &cargo_home());
+    let ws = Workspace::load(&ctx, &p.root().join("Cargo.toml")).unwrap();
+    assert!(ws.read_lockfile().unwrap().is_none());
+
+    let resolve = ws.resolve().unwrap();
+    let packages = resolve.packages();
+    let names: Vec<_> = packages.iter().map(|id| id.name()).collect();
+    assert_eq!(names, ["bar", "baz", "foo"]);
+    let foo = resolve.query("foo").unwrap();
+    assert_eq!(foo, ws.members()[0].id());
+    let deps: Vec<_> = resolve
+        .dependencies(foo)
+        .iter()
+        .map(|id| id.to_string())
+        .collect();
+    assert_eq!(deps.len(), 2);
+    assert!(deps[0].starts_with("bar 0.2.0 (path+file://"));
+    assert!(deps[1].starts_with("baz 0.3.0 (path+file://"));
+    assert_eq!(resolve.features(foo), ["bar", "default"]);
+    assert!(resolve
+        .query("nope")
+        .unwrap_err()
+        .to_string()
+        .contains("did not match any packages"));
+
+    // Resolving writes the lock file.
+    let lock = p.read_lockfile();
+    assert!(lock.contains("name = \"baz\""));
+    let mut read = ws.read_lockfile().unwrap().unwrap();
+    assert_eq!(read.packages(), resolve.packages());
+
+    p.change_file("Cargo.lock", "");
+    ws.write_lockfile(&mut read).unwrap();
+    assert_eq!(p.read_lockfile(), lock);
+}
+
+#[cargo_test]
+fn package_ids() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["foo", "bar"]
+            "#,
+        )
+        .file(
+            "foo/Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                edition = "2018"
+
+                [dependencies]
+                bar = { path = "../bar", optional = true }
+
+                [build-dependencies]
+                baz = { path = "../baz" }
+
+                [features]
+                default = ["bar"]
+            "#,
+        )
+        .file("foo/src/lib.rs", "")
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.2.0"))
+        .file("bar/src/lib.rs", "")
+        .file("baz/Cargo.toml", &basic_manifest("baz", "0.3.0"))
+        .file("baz/src/lib.rs", "")
+        .build();
+    let ctx = Context::with_paths(&p.root(), &cargo_home());
+    let ws = Workspace::load(&ctx, "Cargo.toml".as_ref()).unwrap();
+    let id = ws.members()[1].id();
+
+    assert_eq!(id.name(), "bar");
+    assert_eq!(id.version().to_string(), "0.2.0");
+    assert!(id.is_path());
+    assert_eq!(id.to_string(), format!("bar 0.2.0 ({})", id.source()),);
+    // The ids are the same as those of the metadata.
+    let metadata = ws.metadata(&MetadataOptions::default()).unwrap();
+    assert!(metadata["workspace_members"]
+        .as_array()
+        .unwrap()
+        .contains(&id.to_string().into()));
+
+    assert!(id.matches("bar").unwrap());
+    assert!(id.matches("bar:0.2.0").unwrap());
+    assert!(!id.matches("bar:0.1.0").unwrap());
+    assert!(!id.matches("foo").unwrap());
+    assert!(id.matches("bar:nope").is_err());
+}
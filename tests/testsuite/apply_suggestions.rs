@@ -0,0 +1,197 @@
+//! Tests for `cargo fix --apply-suggestions`, `--diff` and `package.autofix`.
+
+use std::fs;
+
+use cargo_test_support::{basic_manifest, project};
+use filetime::FileTime;
+
+#[cargo_test]
+fn apply_suggestions_requires_flag() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("fix --allow-no-vcs --apply-suggestions unused_qualifications")
+        .with_status(101)
+        .with_stderr("[ERROR] Usage of `--apply-suggestions` requires `-Z apply-suggestions`")
+        .run();
+
+    p.cargo("fix --allow-no-vcs --diff")
+        .with_status(101)
+        .with_stderr("[ERROR] Usage of `--diff` requires `-Z apply-suggestions`")
+        .run();
+}
+
+#[cargo_test]
+fn only_chosen_lints() {
+    let p = project()
+        .file(
+            "src/lib.rs",
+            r#"
+                pub fn foo() -> String {
+                    let mut x = std::string::String::new();
+                    x
+                }
+            "#,
+        )
+        .build();
+
+    // `unused_qualifications` is allowed by default, and `unused_mut` isn't
+    // chosen.
+    p.cargo("fix --allow-no-vcs -Zapply-suggestions --apply-suggestions unused-qualifications")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[FIXED] src/lib.rs (1 fix)")
+        .with_stderr_contains("[WARNING] variable does not need to be mutable")
+        .run();
+
+    let src = p.read_file("src/lib.rs");
+    assert!(src.contains("let mut x = String::new();"));
+}
+
+#[cargo_test]
+fn overlapping_suggestions() {
+    let source = r#"
+        pub fn foo<T>() {}
+        pub struct Bar;
+
+        pub mod m {
+            pub fn baz() {
+                ::foo::<::Bar>();
+            }
+        }
+    "#;
+    let p = project().file("src/lib.rs", source).build();
+
+    // Without another run of rustc, the second suggestion is left out.
+    p.cargo(
+        "fix --allow-no-vcs --lib -Zapply-suggestions \
+         --apply-suggestions absolute_paths_not_starting_with_crate",
+    )
+    .env("CARGO_FIX_MAX_RETRIES", "1")
+    .masquerade_as_nightly_cargo()
+    .with_stderr_contains(
+        "\
+[WARNING] some suggestions for `src/lib.rs` overlap with other suggestions, and were not applied:
+  * line 7: absolute paths must start with [..]
+",
+    )
+    .with_stderr_does_not_contain("[..]error applying suggestions[..]")
+    .run();
+    assert!(p.read_file("src/lib.rs").contains("crate::foo::<::Bar>();"));
+
+    // It is applied by the next run.
+    p.change_file("src/lib.rs", source);
+    p.cargo(
+        "fix --allow-no-vcs --lib -Zapply-suggestions \
+         --apply-suggestions absolute_paths_not_starting_with_crate",
+    )
+    .masquerade_as_nightly_cargo()
+    .with_stderr(
+        "\
+[CHECKING] foo v0.0.1 ([..])
+[FIXED] src/lib.rs (2 fixes)
+[FINISHED] [..]
+",
+    )
+    .run();
+    assert!(p
+        .read_file("src/lib.rs")
+        .contains("crate::foo::<crate::Bar>();"));
+}
+
+#[cargo_test]
+fn diff() {
+    let source = "pub fn foo() -> String {\n    std::string::String::new()\n}\n";
+    let p = project().file("src/lib.rs", source).build();
+
+    // The files aren't changed, so there is no need for a clean VCS. They
+    // are not even written to give them their code back.
+    let lib = p.root().join("src/lib.rs");
+    let mtime = FileTime::from_unix_time(1_000_000_000, 0);
+    filetime::set_file_mtime(&lib, mtime).unwrap();
+    p.cargo("fix --lib --diff -Zapply-suggestions --apply-suggestions unused_qualifications")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[CHECKING] foo v0.0.1 ([..])
+[WOULD_FIX] src/lib.rs (1 fix)
+[FINISHED] [..]
+",
+        )
+        .with_stdout(
+            "\
+diff --git a/src/lib.rs b/src/lib.rs
+index [..]
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,3 +1,3 @@
+ pub fn foo() -> String {
+-    std::string::String::new()
++    String::new()
+ }
+",
+        )
+        .run();
+
+    assert_eq!(p.read_file("src/lib.rs"), source);
+    let modified = FileTime::from_last_modification_time(&fs::metadata(&lib).unwrap());
+    assert_eq!(modified, mtime);
+}
+
+#[cargo_test]
+fn autofix_requires_cargo_feature() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                autofix = false
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("  feature `autofix` is required")
+        .run();
+}
+
+#[cargo_test]
+fn autofix_opt_out() {
+    let source = "pub fn foo() -> u32 { let mut x = 3; x }\n";
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["a", "b"]
+            "#,
+        )
+        .file(
+            "a/Cargo.toml",
+            r#"
+                cargo-features = ["autofix"]
+
+                [package]
+                name = "a"
+                version = "0.1.0"
+                autofix = false
+            "#,
+        )
+        .file("a/src/lib.rs", source)
+        .file("b/Cargo.toml", &basic_manifest("b", "0.1.0"))
+        .file("b/src/lib.rs", source)
+        .build();
+
+    p.cargo("fix --workspace --lib --allow-no-vcs")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[SKIPPING] fixes of `a`, which sets `package.autofix = false`")
+        .with_stderr_contains("[FIXED] b/src/lib.rs (1 fix)")
+        .with_stderr_does_not_contain("[FIXED] a/src/lib.rs[..]")
+        .run();
+
+    assert_eq!(p.read_file("a/src/lib.rs"), source);
+    assert_ne!(p.read_file("b/src/lib.rs"), source);
+}
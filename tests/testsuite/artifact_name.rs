@@ -0,0 +1,207 @@
+//! Tests for the `package.artifact-name` template.
+
+use cargo_test_support::install::{assert_has_installed_exe, cargo_home};
+use cargo_test_support::{project, rustc_host};
+use std::env::consts::{DLL_PREFIX, DLL_SUFFIX, EXE_SUFFIX};
+
+#[cargo_test]
+fn gated() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                artifact-name = "{name}-{version}"
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[..]feature `artifact-name` is required")
+        .run();
+}
+
+#[cargo_test]
+fn binary_and_cdylib_are_named() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["artifact-name"]
+
+                [package]
+                name = "foo-bar"
+                version = "0.1.0"
+                artifact-name = "{name}-{version}-{target}-{profile}"
+
+                [lib]
+                crate-type = ["cdylib", "rlib"]
+            "#,
+        )
+        .file("src/lib.rs", "#[no_mangle] pub extern \"C\" fn foo() {}")
+        .file("src/main.rs", "fn main() { println!(\"hello\"); }")
+        .build();
+
+    p.cargo("build --release")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    let host = rustc_host();
+    let bin = p.root().join(format!(
+        "target/release/foo-bar-0.1.0-{}-release{}",
+        host, EXE_SUFFIX
+    ));
+    assert!(bin.is_file(), "{:?}", bin);
+    assert!(!p.release_bin("foo-bar").exists());
+    let cdylib = p.root().join(format!(
+        "target/release/{}foo_bar-0.1.0-{}-release{}",
+        DLL_PREFIX, host, DLL_SUFFIX
+    ));
+    assert!(cdylib.is_file(), "{:?}", cdylib);
+    // The rlib is only used by Cargo, and keeps its name.
+    assert!(p.root().join("target/release/libfoo_bar.rlib").is_file());
+
+    p.cargo("run --release")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("hello")
+        .run();
+}
+
+#[cargo_test]
+fn examples_are_not_named() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["artifact-name"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                artifact-name = "{name}-{version}"
+            "#,
+        )
+        .file("src/main.rs", "fn main() { println!(\"hello\"); }")
+        .build();
+    p.change_file("examples/ex.rs", "fn main() {}");
+    p.cargo("build --examples")
+        .masquerade_as_nightly_cargo()
+        .run();
+    assert!(p
+        .root()
+        .join(format!("target/debug/examples/ex{}", EXE_SUFFIX))
+        .is_file());
+}
+
+#[cargo_test]
+fn unknown_placeholder() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["artifact-name"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                artifact-name = "{name}-{commit}"
+            "#,
+        )
+        .file("src/main.rs", "fn main() { println!(\"hello\"); }")
+        .build();
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse manifest at `[..]/foo/Cargo.toml`
+
+Caused by:
+  unknown placeholder `{commit}` in `package.artifact-name` `{name}-{commit}`, \
+  the supported placeholders are `{name}`, `{version}`, `{target}`, `{profile}`
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn path_separator() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["artifact-name"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                artifact-name = "bin/{name}"
+            "#,
+        )
+        .file("src/main.rs", "fn main() { println!(\"hello\"); }")
+        .build();
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("  `package.artifact-name` must be a file name, found `bin/{name}`")
+        .run();
+}
+
+#[cargo_test]
+fn collision() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["artifact-name"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                artifact-name = "app-{version}"
+            "#,
+        )
+        .file("src/main.rs", "fn main() { println!(\"hello\"); }")
+        .build();
+    p.change_file("src/bin/other.rs", "fn main() {}");
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] artifact name collision
+The bin target `other` in package `foo v0.1.0 ([..])` has the same output filename \
+as the bin target `foo` in package `foo v0.1.0 ([..])`.
+Colliding filename is: [..]/target/debug/app-0.1.0[EXE]
+The `package.artifact-name` template `app-{version}` must give each artifact a unique \
+name, consider adding the `{name}`, `{version}`, `{target}` or `{profile}` placeholders.
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn install_keeps_binary_name() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["artifact-name"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                artifact-name = "{name}-{version}"
+            "#,
+        )
+        .file("src/main.rs", "fn main() { println!(\"hello\"); }")
+        .build();
+    p.cargo("install --path .")
+        .masquerade_as_nightly_cargo()
+        .run();
+    assert_has_installed_exe(cargo_home(), "foo");
+}
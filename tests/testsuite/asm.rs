@@ -0,0 +1,145 @@
+//! Tests for `cargo asm`.
+
+use cargo_test_support::paths::CargoPathExt;
+use cargo_test_support::project;
+
+#[cargo_test]
+fn asm_requires_z_flag() {
+    let p = project()
+        .file(
+            "src/lib.rs",
+            r#"
+                pub mod math {
+                    #[inline(never)]
+                    pub fn add(a: u32, b: u32) -> u32 { a.wrapping_add(b) }
+                }
+
+                #[inline(never)]
+                pub fn sub(a: u32, b: u32) -> u32 { a.wrapping_sub(b) }
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    // Without the flag, it is left to an external `cargo-asm`.
+    p.cargo("asm --lib")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[ERROR] no such subcommand: `asm`")
+        .run();
+}
+
+#[cargo_test]
+fn asm_function() {
+    let p = project()
+        .file(
+            "src/lib.rs",
+            r#"
+                pub mod math {
+                    #[inline(never)]
+                    pub fn add(a: u32, b: u32) -> u32 { a.wrapping_add(b) }
+                }
+
+                #[inline(never)]
+                pub fn sub(a: u32, b: u32) -> u32 { a.wrapping_sub(b) }
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("-Zasm asm --lib math::add")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+",
+        )
+        .with_stdout_contains("_ZN3foo4math3add[..]:")
+        .with_stdout_does_not_contain("_ZN3foo3sub[..]")
+        .run();
+
+    p.cargo("-Zasm asm --lib --llvm-ir add")
+        .masquerade_as_nightly_cargo()
+        .with_stdout_contains("define [..]@_ZN3foo4math3add[..](i32 [..], i32 [..])[..]{")
+        .with_stdout_does_not_contain("define [..]@_ZN3foo3sub[..]")
+        .run();
+
+    p.cargo("-Zasm asm --lib --mir sub")
+        .masquerade_as_nightly_cargo()
+        .with_stdout_contains("fn sub(_1: u32, _2: u32) -> u32 {")
+        .with_stdout_does_not_contain("fn math::add[..]")
+        .run();
+
+    p.cargo("-Zasm asm --lib mul")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[ERROR] no function matching `mul` in the assembly of lib `foo`")
+        .run();
+}
+
+#[cargo_test]
+fn asm_single_target() {
+    let p = project()
+        .file(
+            "src/lib.rs",
+            r#"
+                pub mod math {
+                    #[inline(never)]
+                    pub fn add(a: u32, b: u32) -> u32 { a.wrapping_add(b) }
+                }
+
+                #[inline(never)]
+                pub fn sub(a: u32, b: u32) -> u32 { a.wrapping_sub(b) }
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("-Zasm asm add")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] `cargo asm` prints the assembly of a single target, \
+             select one with `--lib`, `--bin`, `--example`, `--test` or `--bench`",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn asm_fresh() {
+    let p = project()
+        .file(
+            "src/lib.rs",
+            r#"
+                pub mod math {
+                    #[inline(never)]
+                    pub fn add(a: u32, b: u32) -> u32 { a.wrapping_add(b) }
+                }
+
+                #[inline(never)]
+                pub fn sub(a: u32, b: u32) -> u32 { a.wrapping_sub(b) }
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("-Zasm asm --lib add")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[COMPILING] foo v0.0.1 ([CWD])")
+        .run();
+
+    p.cargo("-Zasm asm --lib add")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]")
+        .with_stdout_contains("_ZN3foo4math3add[..]:")
+        .run();
+
+    // The output is emitted again if it was removed.
+    p.root().join("target/asm").rm_rf();
+    p.cargo("-Zasm asm --lib add")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[COMPILING] foo v0.0.1 ([CWD])")
+        .with_stdout_contains("_ZN3foo4math3add[..]:")
+        .run();
+}
@@ -0,0 +1,231 @@
+//! Tests for `[package.assets]`.
+
+use cargo_test_support::{project, sleep_ms};
+
+#[cargo_test]
+fn assets_require_cargo_feature() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [package.assets]
+                logo = "logo.txt"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("  feature `assets` is required")
+        .run();
+}
+
+#[cargo_test]
+fn embed_assets() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["assets"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [package.assets]
+                logo = "assets/logo.txt"
+                shaders = "shaders/**/*.wgsl"
+                index-page = { path = "web/index.html", compress = "gzip" }
+            "#,
+        )
+        .file("assets/logo.txt", "LOGO")
+        .file("shaders/blur.wgsl", "blur")
+        .file("shaders/post/fade.wgsl", "fade")
+        .file("shaders/README.md", "not a shader")
+        .file("web/index.html", "<html></html>")
+        .file(
+            "src/main.rs",
+            r#"
+                include!(env!("CARGO_ASSETS"));
+
+                fn main() {
+                    println!("{}", std::str::from_utf8(LOGO).unwrap());
+                    for (name, contents) in SHADERS {
+                        println!("{} {}", name, std::str::from_utf8(contents).unwrap());
+                    }
+                    // The gzip magic number.
+                    assert_eq!(&INDEX_PAGE[..2], &[0x1f, 0x8b]);
+                    assert!(env!("CARGO_ASSET_LOGO").ends_with("logo.txt"));
+                    assert!(env!("CARGO_ASSET_SHADERS").ends_with("shaders"));
+                    assert!(env!("CARGO_ASSET_INDEX_PAGE").ends_with("index.html.gz"));
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("run")
+        .masquerade_as_nightly_cargo()
+        .with_stdout(
+            "\
+LOGO
+blur.wgsl blur
+post/fade.wgsl fade
+",
+        )
+        .run();
+    assert!(p
+        .root()
+        .join("target/assets")
+        .read_dir()
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path()
+        .join("index-page/index.html.gz")
+        .is_file());
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[FINISHED] [..]")
+        .run();
+
+    // Changing an asset, or adding one to a pattern, rebuilds.
+    sleep_ms(1000);
+    p.change_file("assets/logo.txt", "NEW LOGO");
+    p.change_file("shaders/sharpen.wgsl", "sharpen");
+    p.cargo("run")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[COMPILING] foo v0.1.0 ([CWD])")
+        .with_stdout(
+            "\
+NEW LOGO
+blur.wgsl blur
+post/fade.wgsl fade
+sharpen.wgsl sharpen
+",
+        )
+        .run();
+
+    sleep_ms(1000);
+    p.change_file("web/index.html", "<html>new</html>");
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[COMPILING] foo v0.1.0 ([CWD])")
+        .run();
+}
+
+#[cargo_test]
+fn missing_assets() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["assets"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [package.assets]
+                logo = "logo.png"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to prepare asset `logo` of package `foo v0.1.0 ([CWD])`
+
+Caused by:
+  `[CWD]/logo.png` is not a file
+",
+        )
+        .run();
+
+    p.change_file(
+        "Cargo.toml",
+        r#"
+            cargo-features = ["assets"]
+
+            [package]
+            name = "foo"
+            version = "0.1.0"
+
+            [package.assets]
+            shaders = "shaders/*.wgsl"
+        "#,
+    );
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to prepare asset `shaders` of package `foo v0.1.0 ([CWD])`
+
+Caused by:
+  `[CWD]/shaders/*.wgsl` matched no files
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn invalid_assets() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["assets"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [package.assets]
+                "3d-model" = "model.obj"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "  invalid asset name `3d-model`, names may only contain letters, digits, \
+             `_` and `-`, and may not start with a digit",
+        )
+        .run();
+
+    p.change_file(
+        "Cargo.toml",
+        r#"
+            cargo-features = ["assets"]
+
+            [package]
+            name = "foo"
+            version = "0.1.0"
+
+            [package.assets]
+            model = { path = "model.obj", compress = "zstd" }
+        "#,
+    );
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "  unknown `compress` value `zstd` for asset `model`, expected `none` or `gzip`",
+        )
+        .run();
+}
@@ -0,0 +1,257 @@
+//! Tests for the audit store, `cargo audits` and the `require-audits` rule of
+//! `[workspace.policy]`.
+
+use cargo_test_support::registry::Package;
+use cargo_test_support::{basic_manifest, paths, project};
+
+#[cargo_test]
+fn audits_require_z_flag() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("audits check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] the `cargo audits` command is unstable, pass `-Z audits` to enable it",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn require_audits_deny() {
+    Package::new("bar", "1.0.0").publish();
+    Package::new("baz", "0.1.0").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["workspace-policy"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+                baz = "0.1"
+                local = { path = "local" }
+
+                [workspace.policy]
+                require-audits = "deny"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("local/Cargo.toml", &basic_manifest("local", "0.1.0"))
+        .file("local/src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+[ERROR] 2 violations of the workspace policy in `[CWD]/Cargo.toml`:
+
+  bar v1.0.0 is not audited in `supply-chain/audits.toml`
+    dependency path: foo v0.1.0 ([CWD]) -> bar v1.0.0
+    help: review it, then record the audit with `cargo audits certify bar 1.0.0`, \
+or exempt it with `cargo audits exempt`
+
+  baz v0.1.0 is not audited in `supply-chain/audits.toml`
+    dependency path: foo v0.1.0 ([CWD]) -> baz v0.1.0
+    help: review it, then record the audit with `cargo audits certify baz 0.1.0`, \
+or exempt it with `cargo audits exempt`
+",
+        )
+        .run();
+
+    // The versions in the lock file are exempted, and new ones need an audit.
+    p.cargo("audits init -Z audits")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("    Exempted 2 unaudited dependencies in `supply-chain/audits.toml`")
+        .run();
+    p.cargo("check").masquerade_as_nightly_cargo().run();
+
+    Package::new("bar", "1.0.1").publish();
+    p.cargo("update -p bar").masquerade_as_nightly_cargo().run();
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("  bar v1.0.1 is not audited in `supply-chain/audits.toml`")
+        .run();
+    p.cargo("audits certify bar 1.0.1 --who Alice -Z audits")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("   Certified bar v1.0.1")
+        .run();
+    p.cargo("check").masquerade_as_nightly_cargo().run();
+
+    // A newer version can be audited from the audited one.
+    Package::new("bar", "1.0.2").publish();
+    p.cargo("update -p bar").masquerade_as_nightly_cargo().run();
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "  \
+bar v1.0.2 is not audited in `supply-chain/audits.toml`, version 1.0.1 is
+    dependency path: foo v0.1.0 ([CWD]) -> bar v1.0.2
+    help: review the changes since 1.0.1, then record the audit with \
+`cargo audits certify bar 1.0.2 --delta 1.0.1`, or exempt it with `cargo audits exempt`",
+        )
+        .run();
+    p.cargo("audits certify bar 1.0.2 --delta 1.0.1 -Z audits")
+        .masquerade_as_nightly_cargo()
+        .run();
+    p.cargo("check").masquerade_as_nightly_cargo().run();
+
+    assert_eq!(
+        p.read_file("supply-chain/audits.toml"),
+        r#"[[audits]]
+name = "bar"
+version = "1.0.1"
+who = "Alice"
+
+[[audits]]
+name = "bar"
+delta = "1.0.1 -> 1.0.2"
+
+[[exemptions]]
+name = "bar"
+version = "1.0.0"
+
+[[exemptions]]
+name = "baz"
+version = "0.1.0"
+"#
+    );
+}
+
+#[cargo_test]
+fn require_audits_warn() {
+    Package::new("bar", "1.0.0").publish();
+    Package::new("baz", "0.1.0").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["workspace-policy"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+                baz = "0.1"
+                local = { path = "local" }
+
+                [workspace.policy]
+                require-audits = "warn"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("local/Cargo.toml", &basic_manifest("local", "0.1.0"))
+        .file("local/src/lib.rs", "")
+        .build();
+
+    p.cargo("audits exempt baz 0.1.0 --reason internal -Z audits")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("    Exempted baz v0.1.0")
+        .run();
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "\
+[WARNING] bar v1.0.0 is not audited in `supply-chain/audits.toml`
+help: review it, then record the audit with `cargo audits certify bar 1.0.0`, \
+or exempt it with `cargo audits exempt`",
+        )
+        .with_stderr_contains("[FINISHED] [..]")
+        .run();
+
+    p.cargo("audits check -Z audits")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stdout(
+            "\
+bar v1.0.0: not audited
+baz v0.1.0: exempted
+",
+        )
+        .with_stderr_contains("[ERROR] 1 dependency is not audited in `supply-chain/audits.toml`")
+        .run();
+}
+
+#[cargo_test]
+fn imported_audits() {
+    Package::new("bar", "1.0.0").publish();
+    Package::new("baz", "0.1.0").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["workspace-policy"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+                baz = "0.1"
+                local = { path = "local" }
+
+                [workspace.policy]
+                require-audits = "deny"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("local/Cargo.toml", &basic_manifest("local", "0.1.0"))
+        .file("local/src/lib.rs", "")
+        .build();
+    let feed = paths::root().join("feed.toml");
+    std::fs::write(
+        &feed,
+        r#"
+            [[audits]]
+            name = "bar"
+            version = "1.0.0"
+
+            [[audits]]
+            name = "baz"
+            version = "0.1.0"
+        "#,
+    )
+    .unwrap();
+    let url = url::Url::from_file_path(&feed).unwrap();
+
+    p.cargo(&format!("audits import other {} -Z audits", url))
+        .masquerade_as_nightly_cargo()
+        .with_stderr("    Fetching audits of `other`")
+        .run();
+    assert!(p
+        .read_file("supply-chain/audits.toml")
+        .contains(&format!("[imports]\nother = \"{}\"", url)));
+    p.cargo("check").masquerade_as_nightly_cargo().run();
+    p.cargo("audits check -Z audits")
+        .masquerade_as_nightly_cargo()
+        .with_stdout(
+            "\
+bar v1.0.0: audited by `other`
+baz v0.1.0: audited by `other`
+",
+        )
+        .run();
+
+    // The audits are read from `imports.lock` until they are fetched again.
+    std::fs::write(&feed, "").unwrap();
+    p.cargo("check").masquerade_as_nightly_cargo().run();
+    p.cargo("audits fetch -Z audits")
+        .masquerade_as_nightly_cargo()
+        .run();
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[ERROR] 2 violations of the workspace policy in [..]")
+        .run();
+}
@@ -0,0 +1,375 @@
+//! Tests for `--auto-features`, which enables the required features of the
+//! selected targets.
+
+use cargo_test_support::{project, Project};
+
+#[cargo_test]
+fn auto_features_requires_flag() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [features]
+                default = ["base"]
+                base = []
+                cli = []
+                color = []
+
+                [[bin]]
+                name = "tool"
+                required-features = ["cli", "color"]
+
+                [[bin]]
+                name = "plain"
+                required-features = ["base"]
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            "src/bin/tool.rs",
+            r#"fn main() { println!("tool {}", cfg!(feature = "cli")); }"#,
+        )
+        .file("src/bin/plain.rs", "fn main() {}")
+        .build();
+
+    p.cargo("run --bin tool --auto-features")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] Usage of `--auto-features` requires `-Z auto-features`")
+        .run();
+}
+
+#[cargo_test]
+fn run_enables_missing_features() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [features]
+                default = ["base"]
+                base = []
+                cli = []
+                color = []
+
+                [[bin]]
+                name = "tool"
+                required-features = ["cli", "color"]
+
+                [[bin]]
+                name = "plain"
+                required-features = ["base"]
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            "src/bin/tool.rs",
+            r#"fn main() { println!("tool {}", cfg!(feature = "cli")); }"#,
+        )
+        .file("src/bin/plain.rs", "fn main() {}")
+        .build();
+
+    p.cargo("run -Z auto-features --bin tool --auto-features")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("tool true")
+        .with_stderr(
+            "\
+[ENABLING] features `cli`, `color` required by bin \"tool\" of package `foo`
+[COMPILING] foo v0.1.0 ([..])
+[FINISHED] [..]
+[RUNNING] `target/debug/tool[EXE]`
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn only_missing_features_are_enabled() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [features]
+                default = ["base"]
+                base = []
+                cli = []
+                color = []
+
+                [[bin]]
+                name = "tool"
+                required-features = ["cli", "color"]
+
+                [[bin]]
+                name = "plain"
+                required-features = ["base"]
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            "src/bin/tool.rs",
+            r#"fn main() { println!("tool {}", cfg!(feature = "cli")); }"#,
+        )
+        .file("src/bin/plain.rs", "fn main() {}")
+        .build();
+
+    p.cargo("build -Z auto-features --bin tool --bin plain --features cli --auto-features")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[ENABLING] features `color` required by bin \"tool\" of package `foo`
+[COMPILING] foo v0.1.0 ([..])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn default_targets_are_not_affected() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [features]
+                default = ["base"]
+                base = []
+                cli = []
+                color = []
+
+                [[bin]]
+                name = "tool"
+                required-features = ["cli", "color"]
+
+                [[bin]]
+                name = "plain"
+                required-features = ["base"]
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            "src/bin/tool.rs",
+            r#"fn main() { println!("tool {}", cfg!(feature = "cli")); }"#,
+        )
+        .file("src/bin/plain.rs", "fn main() {}")
+        .build();
+
+    p.cargo("build -Z auto-features --auto-features")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.1.0 ([..])
+[FINISHED] [..]
+",
+        )
+        .run();
+    assert!(!p.bin("tool").is_file());
+}
+
+#[cargo_test]
+fn auto_features_from_config() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [features]
+                default = ["base"]
+                base = []
+                cli = []
+                color = []
+
+                [[bin]]
+                name = "tool"
+                required-features = ["cli", "color"]
+
+                [[bin]]
+                name = "plain"
+                required-features = ["base"]
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            "src/bin/tool.rs",
+            r#"fn main() { println!("tool {}", cfg!(feature = "cli")); }"#,
+        )
+        .file("src/bin/plain.rs", "fn main() {}")
+        .build();
+    p.change_file(
+        ".cargo/config.toml",
+        "
+        [build]
+        auto-features = true
+        ",
+    );
+
+    p.cargo("build -Z auto-features --bin tool")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[ENABLING] features `cli`, `color` required by bin \"tool\" of package `foo`
+[COMPILING] foo v0.1.0 ([..])
+[FINISHED] [..]
+",
+        )
+        .run();
+    assert!(p.bin("tool").is_file());
+}
+
+#[cargo_test]
+fn without_auto_features_still_errors() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [features]
+                default = ["base"]
+                base = []
+                cli = []
+                color = []
+
+                [[bin]]
+                name = "tool"
+                required-features = ["cli", "color"]
+
+                [[bin]]
+                name = "plain"
+                required-features = ["base"]
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            "src/bin/tool.rs",
+            r#"fn main() { println!("tool {}", cfg!(feature = "cli")); }"#,
+        )
+        .file("src/bin/plain.rs", "fn main() {}")
+        .build();
+
+    p.cargo("build --bin tool")
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] target `tool` in package `foo` requires the features: `cli`, `color`
+Consider enabling them by passing, e.g., `--features=\"cli color\"`
+",
+        )
+        .run();
+}
+
+/// A virtual workspace whose member `a` has a bin requiring a feature of its
+/// dependency `dep`, and whose member `b` depends on `dep` too, optionally if
+/// `b_optional`.
+fn dep_feature_workspace(b_optional: bool) -> Project {
+    project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["a", "b"]
+            "#,
+        )
+        .file(
+            "a/Cargo.toml",
+            r#"
+                [package]
+                name = "a"
+                version = "0.1.0"
+
+                [dependencies]
+                dep = { path = "../dep" }
+
+                [[bin]]
+                name = "tool"
+                required-features = ["dep/extra"]
+            "#,
+        )
+        .file("a/src/bin/tool.rs", "fn main() { dep::extra(); }")
+        .file(
+            "b/Cargo.toml",
+            &format!(
+                r#"
+                    [package]
+                    name = "b"
+                    version = "0.1.0"
+
+                    [dependencies]
+                    dep = {{ path = "../dep", optional = {} }}
+                "#,
+                b_optional
+            ),
+        )
+        .file("b/src/lib.rs", "")
+        .file(
+            "dep/Cargo.toml",
+            r#"
+                [package]
+                name = "dep"
+                version = "0.1.0"
+
+                [features]
+                extra = []
+            "#,
+        )
+        .file(
+            "dep/src/lib.rs",
+            r#"#[cfg(feature = "extra")] pub fn extra() {}"#,
+        )
+        .build()
+}
+
+#[cargo_test]
+fn dep_features_with_several_packages() {
+    let p = dep_feature_workspace(false);
+
+    p.cargo("build -Z auto-features -p a -p b --bin tool --auto-features")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "[ENABLING] features `dep/extra` required by bin \"tool\" of package `a`",
+        )
+        .with_stderr_contains("[FINISHED] [..]")
+        .run();
+}
+
+#[cargo_test]
+fn dep_features_enabling_optional_deps_of_other_packages() {
+    // `dep/extra` would enable the optional `dep` of `b` too.
+    let p = dep_feature_workspace(true);
+
+    p.cargo("build -Z auto-features -p a -p b --bin tool --auto-features")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "[WARNING] feature `dep/extra` required by bin \"tool\" of package `a` \
+             can't be enabled for `a` alone while several packages are selected, \
+             select only `a` with `-p`",
+        )
+        .run();
+
+    p.cargo("build -Z auto-features -p a --bin tool --auto-features")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "[ENABLING] features `dep/extra` required by bin \"tool\" of package `a`",
+        )
+        .run();
+}
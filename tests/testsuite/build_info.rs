@@ -0,0 +1,150 @@
+//! Tests for `package.build-info`.
+
+use cargo_test_support::{git, project, sleep_ms};
+
+const MAIN: &str = r#"
+    fn main() {
+        println!(
+            "{} {} {}",
+            env!("CARGO_GIT_SHA"),
+            env!("CARGO_GIT_DIRTY"),
+            env!("CARGO_BUILD_TIMESTAMP"),
+        );
+    }
+"#;
+
+fn manifest(build_info: &str) -> String {
+    format!(
+        r#"
+            cargo-features = ["build-info"]
+
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            build-info = {}
+        "#,
+        build_info
+    )
+}
+
+#[cargo_test]
+fn build_info_requires_cargo_feature() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                build-info = true
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("  feature `build-info` is required")
+        .run();
+}
+
+#[cargo_test]
+fn git_commit_and_build_time() {
+    let (p, repo) = git::new_repo("foo", |p| {
+        p.file("Cargo.toml", &manifest("true"))
+            .file("src/main.rs", MAIN)
+    });
+    let head = repo.head().unwrap().target().unwrap().to_string();
+
+    p.cargo("run")
+        .masquerade_as_nightly_cargo()
+        .with_stdout(&format!("{} false [..]", head))
+        .run();
+
+    // The time of the build doesn't rebuild by itself.
+    sleep_ms(1000);
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[FINISHED] [..]")
+        .run();
+
+    // Uncommitted changes are reported.
+    p.change_file("src/main.rs", &format!("{}\n// changed", MAIN));
+    p.cargo("run")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[COMPILING] foo v0.1.0 ([CWD])")
+        .with_stdout(&format!("{} true [..]", head))
+        .run();
+
+    // A new commit rebuilds.
+    git::add(&repo);
+    let head = git::commit(&repo).to_string();
+    p.cargo("run")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[COMPILING] foo v0.1.0 ([CWD])")
+        .with_stdout(&format!("{} false [..]", head))
+        .run();
+}
+
+#[cargo_test]
+fn reproducible_timestamp() {
+    let (p, repo) = git::new_repo("foo", |p| {
+        p.file("Cargo.toml", &manifest(r#"{ timestamp = "commit" }"#))
+            .file("src/main.rs", MAIN)
+    });
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+
+    p.cargo("run")
+        .masquerade_as_nightly_cargo()
+        .with_stdout(&format!("{} false {}", head.id(), head.time().seconds()))
+        .run();
+
+    p.cargo("run")
+        .masquerade_as_nightly_cargo()
+        .env("SOURCE_DATE_EPOCH", "1234")
+        .with_stderr_contains("[COMPILING] foo v0.1.0 ([CWD])")
+        .with_stdout(&format!("{} false 1234", head.id()))
+        .run();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .env("SOURCE_DATE_EPOCH", "1234")
+        .with_stderr("[FINISHED] [..]")
+        .run();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .env("SOURCE_DATE_EPOCH", "yesterday")
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] `SOURCE_DATE_EPOCH` is not a number of seconds: `yesterday`
+
+Caused by:
+  invalid digit found in string
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn invalid_timestamp() {
+    let p = project()
+        .file("Cargo.toml", &manifest(r#"{ timestamp = "now" }"#))
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  unknown `package.build-info.timestamp` value `now`, expected `build` or `commit`
+",
+        )
+        .run();
+}
@@ -411,12 +411,7 @@ fn custom_build_env_var_rustc_linker_host_target() {
 
     // no crate type set => linker never called => build succeeds if and
     // only if build.rs succeeds, despite linker binary not existing.
-    if cargo_test_support::is_nightly() {
-        p.cargo("build -Z target-applies-to-host --target")
-            .arg(&target)
-            .masquerade_as_nightly_cargo()
-            .run();
-    }
+    p.cargo("build --target").arg(&target).run();
 }
 
 #[cargo_test]
@@ -448,17 +443,14 @@ fn custom_build_env_var_rustc_linker_host_target_env() {
 
     // no crate type set => linker never called => build succeeds if and
     // only if build.rs succeeds, despite linker binary not existing.
-    if cargo_test_support::is_nightly() {
-        p.cargo("build -Z target-applies-to-host --target")
-            .env("CARGO_TARGET_APPLIES_TO_HOST", "false")
-            .arg(&target)
-            .masquerade_as_nightly_cargo()
-            .run();
-    }
+    p.cargo("build --target")
+        .env("CARGO_TARGET_APPLIES_TO_HOST", "false")
+        .arg(&target)
+        .run();
 }
 
 #[cargo_test]
-fn custom_build_invalid_host_config_feature_flag() {
+fn custom_build_env_var_rustc_linker_host_target_default() {
     let target = rustc_host();
     let p = project()
         .file(
@@ -484,19 +476,12 @@ fn custom_build_invalid_host_config_feature_flag() {
         .file("src/lib.rs", "")
         .build();
 
-    // build.rs should fail due to -Zhost-config being set without -Ztarget-applies-to-host
-    if cargo_test_support::is_nightly() {
-        p.cargo("build -Z host-config --target")
-            .arg(&target)
-            .masquerade_as_nightly_cargo()
-            .with_status(101)
-            .with_stderr_contains(
-                "\
-error: the -Zhost-config flag requires the -Ztarget-applies-to-host flag to be set
-",
-            )
-            .run();
-    }
+    // The `[target]` table doesn't apply to build scripts when cross
+    // compiling, so the linker isn't passed to build.rs.
+    p.cargo("build --verbose --target")
+        .arg(&target)
+        .with_stderr_does_not_contain("[..]build_script_build[..]/path/to/linker[..]")
+        .run();
 }
 
 #[cargo_test]
@@ -530,20 +515,17 @@ fn custom_build_env_var_rustc_linker_host_target_with_bad_host_config() {
         .build();
 
     // build.rs should fail due to bad target linker being set
-    if cargo_test_support::is_nightly() {
-        p.cargo("build -Z target-applies-to-host -Z host-config --verbose --target")
-            .arg(&target)
-            .masquerade_as_nightly_cargo()
-            .with_status(101)
-            .with_stderr_contains(
-                "\
+    p.cargo("build --verbose --target")
+        .arg(&target)
+        .with_status(101)
+        .with_stderr_contains(
+            "\
 [COMPILING] foo v0.0.1 ([CWD])
 [RUNNING] `rustc --crate-name build_script_build build.rs [..]--crate-type bin [..]-C linker=[..]/path/to/target/linker [..]`
 [ERROR] linker `[..]/path/to/target/linker` not found
 "
-            )
-            .run();
-    }
+        )
+        .run();
 }
 
 #[cargo_test]
@@ -576,20 +558,17 @@ fn custom_build_env_var_rustc_linker_bad_host() {
         .build();
 
     // build.rs should fail due to bad host linker being set
-    if cargo_test_support::is_nightly() {
-        p.cargo("build -Z target-applies-to-host -Z host-config --verbose --target")
-            .arg(&target)
-            .masquerade_as_nightly_cargo()
-            .with_status(101)
-            .with_stderr_contains(
-                "\
+    p.cargo("build --verbose --target")
+        .arg(&target)
+        .with_status(101)
+        .with_stderr_contains(
+            "\
 [COMPILING] foo v0.0.1 ([CWD])
 [RUNNING] `rustc --crate-name build_script_build build.rs [..]--crate-type bin [..]-C linker=[..]/path/to/host/linker [..]`
 [ERROR] linker `[..]/path/to/host/linker` not found
 "
-            )
-            .run();
-    }
+        )
+        .run();
 }
 
 #[cargo_test]
@@ -624,20 +603,17 @@ fn custom_build_env_var_rustc_linker_bad_host_with_arch() {
         .build();
 
     // build.rs should fail due to bad host linker being set
-    if cargo_test_support::is_nightly() {
-        p.cargo("build -Z target-applies-to-host -Z host-config --verbose --target")
-            .arg(&target)
-            .masquerade_as_nightly_cargo()
-            .with_status(101)
-            .with_stderr_contains(
-                "\
+    p.cargo("build --verbose --target")
+        .arg(&target)
+        .with_status(101)
+        .with_stderr_contains(
+            "\
 [COMPILING] foo v0.0.1 ([CWD])
 [RUNNING] `rustc --crate-name build_script_build build.rs [..]--crate-type bin [..]-C linker=[..]/path/to/host/arch/linker [..]`
 [ERROR] linker `[..]/path/to/host/arch/linker` not found
 "
-            )
-            .run();
-    }
+        )
+        .run();
 }
 
 #[cargo_test]
@@ -671,12 +647,7 @@ fn custom_build_env_var_rustc_linker_cross_arch_host() {
         .build();
 
     // build.rs should fail due to bad host linker being set
-    if cargo_test_support::is_nightly() {
-        p.cargo("build -Z target-applies-to-host -Z host-config --verbose --target")
-            .arg(&target)
-            .masquerade_as_nightly_cargo()
-            .run();
-    }
+    p.cargo("build --verbose --target").arg(&target).run();
 }
 
 #[cargo_test]
@@ -712,20 +683,17 @@ fn custom_build_env_var_rustc_linker_bad_cross_arch_host() {
         .build();
 
     // build.rs should fail due to bad host linker being set
-    if cargo_test_support::is_nightly() {
-        p.cargo("build -Z target-applies-to-host -Z host-config --verbose --target")
-            .arg(&target)
-            .masquerade_as_nightly_cargo()
-            .with_status(101)
-            .with_stderr_contains(
-                "\
+    p.cargo("build --verbose --target")
+        .arg(&target)
+        .with_status(101)
+        .with_stderr_contains(
+            "\
 [COMPILING] foo v0.0.1 ([CWD])
 [RUNNING] `rustc --crate-name build_script_build build.rs [..]--crate-type bin [..]-C linker=[..]/path/to/host/linker [..]`
 [ERROR] linker `[..]/path/to/host/linker` not found
 "
-            )
-            .run();
-    }
+        )
+        .run();
 }
 
 #[cargo_test]
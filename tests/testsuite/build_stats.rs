@@ -0,0 +1,186 @@
+//! Tests for `-Z build-stats` and `cargo report build-stats`.
+
+use cargo_test_support::{basic_manifest, project, Project};
+use serde_json::Value;
+
+fn builds(p: &Project) -> Vec<Value> {
+    p.read_file("target/.stats/builds.jsonl")
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[cargo_test]
+fn not_recorded_without_flag() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = { path = "bar" }
+            "#,
+        )
+        .file("src/main.rs", "fn main() { bar::bar() }")
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.1.0"))
+        .file("bar/src/lib.rs", "pub fn bar() {}")
+        .build();
+    p.cargo("build").run();
+    assert!(!p.root().join("target/.stats").exists());
+
+    p.cargo("report build-stats")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] no build statistics found in `[CWD]/target/.stats/builds.jsonl`, \
+             build with `-Z build-stats` to record them",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn records_builds() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = { path = "bar" }
+            "#,
+        )
+        .file("src/main.rs", "fn main() { bar::bar() }")
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.1.0"))
+        .file("bar/src/lib.rs", "pub fn bar() {}")
+        .build();
+    p.cargo("build -Zbuild-stats")
+        .masquerade_as_nightly_cargo()
+        .run();
+    p.cargo("build -Zbuild-stats")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    let builds = builds(&p);
+    assert_eq!(builds.len(), 2);
+    for (build, fresh) in builds.iter().zip([false, true]) {
+        assert_eq!(build["profile"], "dev");
+        assert_eq!(build["success"], true);
+        assert!(build["duration"].as_f64().unwrap() > 0.0);
+        if cfg!(unix) && !fresh {
+            assert!(build["peak_memory"].as_u64().unwrap() > 0);
+        }
+        let units = build["units"].as_array().unwrap();
+        let targets: Vec<_> = units
+            .iter()
+            .map(|unit| {
+                (
+                    unit["package"].as_str().unwrap().split(' ').next().unwrap(),
+                    unit["target"].as_str().unwrap(),
+                    unit["mode"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            targets,
+            [("bar", "lib", "build"), ("foo", "bin \"foo\"", "build")]
+        );
+        for unit in units {
+            assert_eq!(unit["fresh"], fresh);
+            assert!(unit["size"].as_u64().unwrap() > 0);
+        }
+    }
+}
+
+#[cargo_test]
+fn keeps_last_builds() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = { path = "bar" }
+            "#,
+        )
+        .file("src/main.rs", "fn main() { bar::bar() }")
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.1.0"))
+        .file("bar/src/lib.rs", "pub fn bar() {}")
+        .build();
+    let old =
+        r#"{"time":0,"profile":"old","success":true,"duration":1.0,"peak_memory":null,"units":[]}"#;
+    p.change_file(
+        "target/.stats/builds.jsonl",
+        &format!("{}\n", old).repeat(100),
+    );
+    p.cargo("build -Zbuild-stats")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    let builds = builds(&p);
+    assert_eq!(builds.len(), 100);
+    assert_eq!(builds[0]["profile"], "old");
+    assert_eq!(builds[99]["profile"], "dev");
+}
+
+#[cargo_test]
+fn report() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = { path = "bar" }
+            "#,
+        )
+        .file("src/main.rs", "fn main() { bar::bar() }")
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.1.0"))
+        .file("bar/src/lib.rs", "pub fn bar() {}")
+        .build();
+    p.cargo("build -Zbuild-stats")
+        .masquerade_as_nightly_cargo()
+        .run();
+    p.cargo("build -Zbuild-stats")
+        .masquerade_as_nightly_cargo()
+        .run();
+    p.change_file("bar/src/lib.rs", "pub fn bar() { println!(); }");
+    p.cargo("build -Zbuild-stats")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    p.cargo("report build-stats --top 1 --builds 2")
+        .masquerade_as_nightly_cargo()
+        .with_stdout(
+            "\
+Last 2 of 3 recorded builds:
+    2  dev      [..]s  0 compiled, 2 fresh[..]
+    3  dev      [..]s  2 compiled, 0 fresh[..]
+
+Slowest units, by average compile time:
+[..]s  [..] v0.1.0 ([..]) [..], last [..]s over [..] builds[..], [..]B
+",
+        )
+        .run();
+
+    p.cargo("report build-stats")
+        .masquerade_as_nightly_cargo()
+        .with_stdout_contains(
+            "[..]s  bar v0.1.0 ([CWD]/bar) lib, last [..]s over 2 builds ([..]%), [..]B",
+        )
+        .with_stdout_contains(
+            "[..]s  foo v0.1.0 ([CWD]) bin \"foo\", last [..]s over 2 builds ([..]%), [..]B",
+        )
+        .run();
+}
@@ -0,0 +1,415 @@
+//! Tests for the `cargo cache` command.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use cargo_test_support::registry::Package;
+use cargo_test_support::{basic_manifest, git, paths, project, Project};
+use filetime::FileTime;
+
+/// A project depending on `bar` from the registry and `dep` from git, built
+/// so that both are cached.
+fn cached_project() -> Project {
+    Package::new("bar", "1.0.0").publish();
+    let dep = git::new("dep", |p| {
+        p.file("Cargo.toml", &basic_manifest("dep", "0.5.0"))
+            .file("src/lib.rs", "")
+    });
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    [package]
+                    name = "foo"
+                    version = "0.1.0"
+
+                    [dependencies]
+                    bar = "1.0"
+                    dep = {{ git = '{}' }}
+                "#,
+                dep.url()
+            ),
+        )
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("build").run();
+    p
+}
+
+/// The only directory in `dir` of the Cargo home, named after the registry
+/// or git repository.
+fn only_dir(dir: &str) -> PathBuf {
+    let dir = paths::home().join(".cargo").join(dir);
+    let mut entries = fs::read_dir(&dir).unwrap();
+    let entry = entries.next().unwrap().unwrap().path();
+    assert!(entries.next().is_none());
+    entry
+}
+
+/// The total size of the files under `path`.
+fn size(path: &Path) -> u64 {
+    if path.is_dir() {
+        fs::read_dir(path)
+            .unwrap()
+            .map(|entry| size(&entry.unwrap().path()))
+            .sum()
+    } else {
+        fs::metadata(path).unwrap().len()
+    }
+}
+
+/// Pretends that `path` was last modified `days` ago.
+fn age(path: &PathBuf, days: u64) {
+    let time = SystemTime::now() - Duration::from_secs(days * 24 * 60 * 60);
+    filetime::set_file_mtime(path, FileTime::from_system_time(time)).unwrap();
+}
+
+#[cargo_test]
+fn cache_requires_z_flag() {
+    cargo_test_support::cargo_process("cache info")
+        .with_status(101)
+        .with_stderr("[ERROR] the `cargo cache` command is unstable, pass `-Z cache` to enable it")
+        .run();
+}
+
+#[cargo_test]
+fn info() {
+    let _p = cached_project();
+
+    cargo_test_support::cargo_process("cache info -Z cache")
+        .masquerade_as_nightly_cargo()
+        .with_stdout(
+            "\
+registry `-[..]`: [..]
+    index: [..]
+    cache: [..] in 1 crates
+    src: [..] in 1 crates
+git `dep-[..]`: [..]
+    db: [..]
+    checkouts: [..] in 1 revisions
+total: [..]
+",
+        )
+        .run();
+
+    cargo_test_support::cargo_process("cache info -Z cache --json")
+        .masquerade_as_nightly_cargo()
+        .with_json(
+            r#"
+                {
+                    "registries": [
+                        {
+                            "name": "-[..]",
+                            "index": "{...}",
+                            "cache": "{...}",
+                            "crates": 1,
+                            "src": "{...}",
+                            "sources": 1
+                        }
+                    ],
+                    "git": [
+                        {
+                            "name": "dep-[..]",
+                            "db": "{...}",
+                            "checkouts": "{...}",
+                            "revisions": 1
+                        }
+                    ],
+                    "total": "{...}"
+                }
+            "#,
+        )
+        .run();
+}
+
+#[cargo_test]
+fn prune_unused() {
+    let p = cached_project();
+    let crate_file = only_dir("registry/cache").join("bar-1.0.0.crate");
+    let src = only_dir("registry/src").join("bar-1.0.0");
+    let checkout = only_dir(&format!(
+        "git/checkouts/{}",
+        only_dir("git/checkouts")
+            .file_name()
+            .unwrap()
+            .to_str()
+            .unwrap()
+    ));
+    age(&src.join(".cargo-ok"), 60);
+
+    cargo_test_support::cargo_process("cache prune -Z cache --unused-for 30d --dry-run")
+        .masquerade_as_nightly_cargo()
+        .with_stdout(
+            "\
+[..]bar-1.0.0.crate
+[..]bar-1.0.0
+",
+        )
+        .with_stderr(
+            "\
+[SUMMARY] 2 entries, [..] total
+[WARNING] no files deleted due to --dry-run
+",
+        )
+        .run();
+    assert!(crate_file.exists());
+
+    cargo_test_support::cargo_process("cache prune -Z cache --unused-for 30d")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("     Removed 2 entries, [..] total")
+        .run();
+    assert!(!crate_file.exists());
+    assert!(!src.exists());
+    assert!(checkout.exists());
+
+    // Using the checkout counts as a use, even if nothing is built.
+    age(&checkout.join(".cargo-ok"), 60);
+    p.cargo("build").run();
+    cargo_test_support::cargo_process("cache prune -Z cache --unused-for 30d --json")
+        .masquerade_as_nightly_cargo()
+        .with_json(r#"{"removed": [], "bytes": 0, "dry_run": false}"#)
+        .run();
+    assert!(checkout.exists());
+    assert!(crate_file.exists());
+}
+
+#[cargo_test]
+fn prune_max_age() {
+    let _p = cached_project();
+    let checkout = only_dir(&format!(
+        "git/checkouts/{}",
+        only_dir("git/checkouts")
+            .file_name()
+            .unwrap()
+            .to_str()
+            .unwrap()
+    ));
+    age(&checkout, 10);
+
+    cargo_test_support::cargo_process("cache prune -Z cache --max-age 1w --json")
+        .masquerade_as_nightly_cargo()
+        .with_json(
+            r#"
+                {
+                    "removed": [
+                        {"kind": "checkout", "path": "[..]", "bytes": "{...}"}
+                    ],
+                    "bytes": "{...}",
+                    "dry_run": false
+                }
+            "#,
+        )
+        .run();
+    assert!(!checkout.exists());
+    assert!(only_dir("registry/src").join("bar-1.0.0").exists());
+}
+
+#[cargo_test]
+fn prune_max_size() {
+    let _p = cached_project();
+    let src = only_dir("registry/src").join("bar-1.0.0");
+    let crate_file = only_dir("registry/cache").join("bar-1.0.0.crate");
+    age(&src.join(".cargo-ok"), 2);
+
+    // The least recently used entries go first, until the rest fits.
+    let size = size(&only_dir("git/db")) + size(&only_dir("git/checkouts"));
+    cargo_test_support::cargo_process(&format!("cache prune -Z cache --max-size {}", size))
+        .masquerade_as_nightly_cargo()
+        .with_stderr("     Removed 2 entries, [..] total")
+        .run();
+    assert!(!src.exists());
+    assert!(!crate_file.exists());
+    assert!(only_dir("git/db").exists());
+    assert!(only_dir("git/checkouts").exists());
+}
+
+#[cargo_test]
+fn prune_arguments() {
+    cargo_test_support::cargo_process("cache prune -Z cache")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] nothing to prune, pass `--max-age`, `--unused-for` or `--max-size` \
+             to choose what to remove",
+        )
+        .run();
+    cargo_test_support::cargo_process("cache prune -Z cache --max-age soon")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "[ERROR] invalid duration `soon`, expected a number with a unit like `30d`, `12h` or `2w`",
+        )
+        .run();
+    cargo_test_support::cargo_process("cache prune -Z cache --max-size lots")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] invalid size `lots`, expected a number of bytes like `500MB` or `5GB`",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn verify() {
+    let p = cached_project();
+
+    p.cargo("cache verify -Z cache")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[..]Verified 1 cached crates, 0 not downloaded")
+        .run();
+
+    let crate_file = only_dir("registry/cache").join("bar-1.0.0.crate");
+    fs::write(&crate_file, "corrupted").unwrap();
+    p.cargo("cache verify -Z cache")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] the cached `.crate` files of 1 packages don't match the checksums of `Cargo.lock`:
+  bar v1.0.0 at `[..]bar-1.0.0.crate`
+remove them to download them again
+",
+        )
+        .run();
+
+    fs::remove_file(&crate_file).unwrap();
+    p.cargo("cache verify -Z cache --json")
+        .masquerade_as_nightly_cargo()
+        .with_json(
+            r#"
+                {
+                    "crates": [
+                        {
+                            "package_id": "bar 1.0.0 (registry+[..])",
+                            "path": "[..]bar-1.0.0.crate",
+                            "status": "missing"
+                        }
+                    ]
+                }
+            "#,
+        )
+        .run();
+}
+
+#[cargo_test]
+fn auto_gc() {
+    let p = cached_project();
+    let src = only_dir("registry/src").join("bar-1.0.0");
+    let last_gc = paths::home().join(".cargo/.last-gc");
+    age(&src.join(".cargo-ok"), 200);
+
+    // Nothing is cleaned up without `-Z gc`.
+    p.cargo("locate-project").run();
+    assert!(src.exists());
+    assert!(!last_gc.exists());
+
+    // Nor offline, where it couldn't be downloaded again.
+    p.cargo("locate-project -Z gc --offline")
+        .masquerade_as_nightly_cargo()
+        .run();
+    assert!(src.exists());
+
+    p.cargo("locate-project -Z gc -v")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[REMOVING] [..]bar-1.0.0.crate
+[REMOVING] [..]bar-1.0.0
+     Cleaned 2 entries, [..] total from the Cargo home
+",
+        )
+        .run();
+    assert!(!src.exists());
+    assert!(last_gc.exists());
+
+    // The cleanup runs once a day by default.
+    let checkout = only_dir(&format!(
+        "git/checkouts/{}",
+        only_dir("git/checkouts")
+            .file_name()
+            .unwrap()
+            .to_str()
+            .unwrap()
+    ));
+    age(&checkout.join(".cargo-ok"), 200);
+    p.cargo("locate-project -Z gc")
+        .masquerade_as_nightly_cargo()
+        .run();
+    assert!(checkout.exists());
+    p.cargo("locate-project -Z gc")
+        .masquerade_as_nightly_cargo()
+        .env("CARGO_GC_AUTO_FREQUENCY", "always")
+        .env("CARGO_GC_AUTO_MAX_UNUSED", "1 year")
+        .run();
+    assert!(checkout.exists());
+    p.cargo("locate-project -Z gc")
+        .masquerade_as_nightly_cargo()
+        .env("CARGO_GC_AUTO_FREQUENCY", "always")
+        .run();
+    assert!(!checkout.exists());
+}
+
+#[cargo_test]
+fn auto_gc_config_error() {
+    let p = cached_project();
+
+    p.cargo("locate-project -Z gc")
+        .masquerade_as_nightly_cargo()
+        .env("CARGO_GC_AUTO_MAX_SIZE", "huge")
+        .with_stderr(
+            "\
+[WARNING] failed to clean up the caches of the Cargo home
+
+`gc.auto.max-size` must be a number of bytes like `5GB`, found `huge`
+",
+        )
+        .run();
+    p.cargo("cache gc -Z cache")
+        .masquerade_as_nightly_cargo()
+        .env("CARGO_GC_AUTO_MAX_UNUSED", "forever")
+        .with_status(101)
+        .with_stderr_contains(
+            "[ERROR] `gc.auto.max-unused` must be a duration like `30 days`, found `forever`",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn gc_dry_run() {
+    let _p = cached_project();
+    let src = only_dir("registry/src").join("bar-1.0.0");
+
+    // With the default policy, everything was used recently.
+    cargo_test_support::cargo_process("cache gc -Z cache --dry-run")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("")
+        .with_stderr(
+            "\
+[SUMMARY] 0 entries, 0 B total
+[WARNING] no files deleted due to --dry-run
+",
+        )
+        .run();
+
+    cargo_test_support::cargo_process("cache gc -Z cache --dry-run")
+        .masquerade_as_nightly_cargo()
+        .env("CARGO_GC_AUTO_MAX_SIZE", "1B")
+        .with_stdout_contains("[..]bar-1.0.0.crate")
+        .with_stdout_contains("[..]bar-1.0.0")
+        .with_stdout_contains("[..]git/db/dep-[..]")
+        .with_stdout_contains("[..]git/checkouts/dep-[..]")
+        .with_stderr_contains("[SUMMARY] 4 entries, [..] total")
+        .run();
+    assert!(src.exists());
+    assert!(!paths::home().join(".cargo/.last-gc").exists());
+
+    cargo_test_support::cargo_process("cache gc -Z cache")
+        .masquerade_as_nightly_cargo()
+        .env("CARGO_GC_AUTO_MAX_SIZE", "1B")
+        .with_stderr("     Removed 4 entries, [..] total")
+        .run();
+    assert!(!src.exists());
+    assert!(paths::home().join(".cargo/.last-gc").exists());
+}
@@ -0,0 +1,157 @@
+//! Tests for the `--changed-since` package selection flag.
+
+use cargo_test_support::{basic_manifest, git, project, Project};
+
+fn changed_project() -> (Project, git2::Repository) {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["core", "app", "tools"]
+            "#,
+        )
+        .file("core/Cargo.toml", &basic_manifest("core", "0.1.0"))
+        .file("core/src/lib.rs", "")
+        .file(
+            "app/Cargo.toml",
+            r#"
+                [package]
+                name = "app"
+                version = "0.1.0"
+
+                [dependencies]
+                core = { path = "../core" }
+            "#,
+        )
+        .file("app/src/lib.rs", "")
+        .file("tools/Cargo.toml", &basic_manifest("tools", "0.1.0"))
+        .file("tools/src/lib.rs", "")
+        .file(".gitignore", "target\nCargo.lock\n")
+        .build();
+    let repo = git::init(&p.root());
+    git::add(&repo);
+    git::commit(&repo);
+    (p, repo)
+}
+
+#[cargo_test]
+fn changed_since_requires_flag() {
+    let (p, _repo) = changed_project();
+
+    p.cargo("build --changed-since HEAD")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] Usage of `--changed-since` requires `-Z changed-since`")
+        .run();
+}
+
+#[cargo_test]
+fn changed_member_and_reverse_deps() {
+    let (p, _repo) = changed_project();
+    p.change_file("core/src/lib.rs", "pub fn f() {}");
+
+    p.cargo("check -Z changed-since --changed-since HEAD")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_unordered(
+            "\
+[CHECKING] core v0.1.0 ([..])
+[CHECKING] app v0.1.0 ([..])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn changed_leaf_member() {
+    let (p, _repo) = changed_project();
+    p.change_file("tools/src/new.rs", "");
+
+    p.cargo("check -Z changed-since --changed-since HEAD")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[CHECKING] tools v0.1.0 ([..])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn nothing_changed() {
+    let (p, _repo) = changed_project();
+
+    p.cargo("check -Z changed-since --changed-since HEAD")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[NOTE] no workspace members changed since `HEAD`
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn unknown_revision() {
+    let (p, _repo) = changed_project();
+
+    p.cargo("check -Z changed-since --changed-since no-such-rev")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[ERROR] failed to find git revision `no-such-rev`")
+        .run();
+}
+
+#[cargo_test]
+fn conflicts_with_package() {
+    let (p, _repo) = changed_project();
+
+    p.cargo("check -Z changed-since --changed-since HEAD -p core")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] --changed-since cannot be used together with \
+             --workspace, --package, --exclude or --group",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn workspace_files_affect_all_members() {
+    let (p, repo) = changed_project();
+    p.change_file(
+        "Cargo.toml",
+        r#"
+            [workspace]
+            members = ["core", "app", "tools"]
+
+            [profile.dev]
+            debug = 1
+        "#,
+    );
+
+    let all_members = "\
+[CHECKING] core v0.1.0 ([..])
+[CHECKING] app v0.1.0 ([..])
+[CHECKING] tools v0.1.0 ([..])
+[FINISHED] [..]
+";
+    p.cargo("check -Z changed-since --changed-since HEAD")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_unordered(all_members)
+        .run();
+
+    git::add(&repo);
+    git::commit(&repo);
+    p.change_file(
+        ".cargo/config.toml",
+        "[build]\nrustflags = [\"--cfg=changed\"]\n",
+    );
+    p.cargo("check -Z changed-since --changed-since HEAD")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_unordered(all_members)
+        .run();
+}
@@ -0,0 +1,281 @@
+//! Tests for the `[workspace.changelog]` changelog fragments.
+
+use std::fs::File;
+
+use cargo_test_support::project;
+use cargo_test_support::publish::validate_crate_contents;
+
+#[cargo_test]
+fn changelog_requires_feature() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [workspace.changelog]
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("package --no-verify")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  feature `changelog-fragments` is required
+
+  The package requires the Cargo feature called `changelog-fragments`, \
+  but that feature is not stabilized in this version of Cargo (1.[..]).
+  Consider adding `cargo-features = [\"changelog-fragments\"]` to the top of Cargo.toml \
+  (above the [package] table) to tell Cargo you are opting in to use this unstable feature.
+  See https://doc.rust-lang.org/nightly/cargo/reference/unstable.html#changelog-fragments \
+  for more information about the status of this feature.
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn missing_entry() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["changelog-fragments"]
+
+                [package]
+                name = "foo"
+                version = "0.2.0"
+                license = "MIT"
+                description = "foo"
+                homepage = "foo"
+
+                [workspace.changelog]
+
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            "CHANGELOG.md",
+            "# Changelog\n\n## [0.1.0] - 2021-01-01\n\n- Initial release.\n",
+        )
+        .build();
+
+    p.cargo("package --no-verify")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] no changelog entry for foo v0.2.0, \
+             add a fragment to `changes` or a section for 0.2.0 to `CHANGELOG.md`",
+        )
+        .run();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["changelog-fragments"]
+
+                [package]
+                name = "foo"
+                version = "0.2.0"
+                license = "MIT"
+                description = "foo"
+                homepage = "foo"
+
+                [workspace.changelog]
+                require-entry = false
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            "CHANGELOG.md",
+            "# Changelog\n\n## [0.1.0] - 2021-01-01\n\n- Initial release.\n",
+        )
+        .build();
+    p.cargo("package --no-verify")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[WARNING] no changelog entry for foo v0.2.0, \
+add a fragment to `changes` or a section for 0.2.0 to `CHANGELOG.md`
+[PACKAGING] foo v0.2.0 ([CWD])
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn existing_entry() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["changelog-fragments"]
+
+                [package]
+                name = "foo"
+                version = "0.2.0"
+                license = "MIT"
+                description = "foo"
+                homepage = "foo"
+
+                [workspace.changelog]
+
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            "CHANGELOG.md",
+            "# Changelog\n\n## [0.1.0] - 2021-01-01\n\n- Initial release.\n",
+        )
+        .build();
+    p.change_file(
+        "CHANGELOG.md",
+        "# Changelog\n\n## 0.2.0\n\n- Better.\n\n## [0.1.0] - 2021-01-01\n\n- Initial release.\n",
+    );
+    p.change_file("changes/1.fixed.md", "Not assembled.");
+
+    p.cargo("package --no-verify")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[PACKAGING] foo v0.2.0 ([CWD])")
+        .run();
+
+    let f = File::open(&p.root().join("target/package/foo-0.2.0.crate")).unwrap();
+    validate_crate_contents(
+        f,
+        "foo-0.2.0.crate",
+        &[
+            "CHANGELOG.md",
+            "Cargo.toml",
+            "Cargo.toml.orig",
+            "changes/1.fixed.md",
+            "src/lib.rs",
+        ],
+        &[],
+    );
+}
+
+#[cargo_test]
+fn fragments_are_assembled() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["changelog-fragments"]
+
+                [package]
+                name = "foo"
+                version = "0.2.0"
+                license = "MIT"
+                description = "foo"
+                homepage = "foo"
+
+                [workspace.changelog]
+                fragments = "news"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            "CHANGELOG.md",
+            "# Changelog\n\n## [0.1.0] - 2021-01-01\n\n- Initial release.\n",
+        )
+        .build();
+    p.change_file("news/README.md", "One file per change.");
+    p.change_file("news/12.fixed.md", "Fix the frobnicator.");
+    p.change_file("news/3.added.md", "Add `foo::bar`.\n\nIt bars.\n");
+    p.change_file("news/4.added.md", "- Add `foo::baz`.");
+
+    p.cargo("package --no-verify")
+        .masquerade_as_nightly_cargo()
+        .env("SOURCE_DATE_EPOCH", "0")
+        .with_stderr(
+            "\
+[ASSEMBLING] 3 changelog fragments into `CHANGELOG.md`
+[PACKAGING] foo v0.2.0 ([CWD])
+",
+        )
+        .run();
+
+    let f = File::open(&p.root().join("target/package/foo-0.2.0.crate")).unwrap();
+    validate_crate_contents(
+        f,
+        "foo-0.2.0.crate",
+        &[
+            "CHANGELOG.md",
+            "Cargo.toml",
+            "Cargo.toml.orig",
+            "news/README.md",
+            "src/lib.rs",
+        ],
+        &[(
+            "CHANGELOG.md",
+            "\
+# Changelog
+
+## [0.2.0] - 1970-01-01
+
+### Added
+
+- Add `foo::bar`.
+  It bars.
+- Add `foo::baz`.
+
+### Fixed
+
+- Fix the frobnicator.
+
+## [0.1.0] - 2021-01-01
+
+- Initial release.
+",
+        )],
+    );
+    // The working tree is left alone.
+    assert!(p.root().join("news/12.fixed.md").exists());
+    assert!(!p.read_file("CHANGELOG.md").contains("0.2.0"));
+}
+
+#[cargo_test]
+fn unknown_fragment_kind() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["changelog-fragments"]
+
+                [package]
+                name = "foo"
+                version = "0.2.0"
+                license = "MIT"
+                description = "foo"
+                homepage = "foo"
+
+                [workspace.changelog]
+
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            "CHANGELOG.md",
+            "# Changelog\n\n## [0.1.0] - 2021-01-01\n\n- Initial release.\n",
+        )
+        .build();
+    p.change_file("changes/5.improved.md", "Improve it.");
+
+    p.cargo("package --no-verify")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] unknown kind `improved` of the changelog fragment `[CWD]/changes/5.improved.md`, \
+             expected one of: added, changed, deprecated, removed, fixed, security",
+        )
+        .run();
+}
@@ -0,0 +1,240 @@
+//! Tests for the cfgs checked with `--check-cfg`: `-Z check-cfg`,
+//! `[lints.rust.unexpected_cfgs]`, `cargo:rustc-check-cfg` and
+//! `cargo report check-cfg`.
+
+use cargo_test_support::{basic_manifest, project};
+
+#[cargo_test]
+fn lints_rust_require_cargo_feature() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [lints.rust.unexpected_cfgs]
+                check-cfg = ["cfg(has_foo)"]
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("  feature `check-cfg` is required")
+        .run();
+}
+
+#[cargo_test]
+fn lints_rust_invalid() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["check-cfg"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [lints.rust.unexpected_cfgs]
+                level = "loud"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "  `lints.rust.unexpected_cfgs.level` must be one of `allow`, `warn`, `deny` \
+             or `forbid`, found `loud`",
+        )
+        .run();
+
+    p.change_file(
+        "Cargo.toml",
+        r#"
+            cargo-features = ["check-cfg"]
+
+            [package]
+            name = "foo"
+            version = "0.1.0"
+
+            [lints.rust.unexpected_cfgs]
+            check-cfg = ["has_foo"]
+        "#,
+    );
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "  `lints.rust.unexpected_cfgs.check-cfg` entries must be like `cfg(name)` \
+             or `cfg(name, values(\"a\", \"b\"))`, found `has_foo`",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn lints_rust_check_cfg() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["check-cfg"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [features]
+                extra = []
+
+                [lints.rust.unexpected_cfgs]
+                level = "deny"
+                check-cfg = ["cfg(has_foo)"]
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            r#"
+                #[cfg(has_foo)]
+                pub fn foo() {}
+
+                #[cfg(feature = "extra")]
+                pub fn extra() {}
+            "#,
+        )
+        .build();
+
+    p.cargo("check -v")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "[RUNNING] `rustc --crate-name foo [..]--deny unexpected_cfgs \
+             --check-cfg 'cfg(feature, values(\"extra\"))' --check-cfg 'cfg(has_foo)' [..]",
+        )
+        .with_stderr_does_not_contain("[WARNING][..]")
+        .run();
+
+    p.change_file("src/lib.rs", "#[cfg(has_bar)] pub fn bar() {}");
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("error: unexpected `cfg` condition name: `has_bar`")
+        .run();
+}
+
+#[cargo_test]
+fn build_script_check_cfg() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file(
+            "build.rs",
+            r#"
+                fn main() {
+                    println!("cargo:rustc-cfg=has_foo");
+                    println!("cargo:rustc-check-cfg=cfg(has_foo)");
+                }
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            r#"
+                #[cfg(has_foo)]
+                pub fn foo() {}
+
+                #[cfg(has_bar)]
+                pub fn bar() {}
+            "#,
+        )
+        .build();
+
+    p.cargo("check -v")
+        .with_stderr_contains(
+            "[RUNNING] `rustc --crate-name foo [..]--cfg has_foo \
+             --check-cfg 'cfg(feature, values())' --check-cfg 'cfg(has_foo)'[..]",
+        )
+        .with_stderr_contains("[..]unexpected `cfg` condition name: `has_bar`")
+        .with_stderr_does_not_contain("[..]condition name: `has_foo`")
+        .run();
+}
+
+#[cargo_test]
+fn z_check_cfg() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("src/lib.rs", "#[cfg(has_bar)] pub fn bar() {}")
+        .build();
+
+    p.cargo("check")
+        .with_stderr_does_not_contain("[..]has_bar[..]")
+        .run();
+
+    // The cfgs are checked from now on, so the package is rebuilt.
+    p.cargo("check -Z check-cfg")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[CHECKING] foo v0.1.0 ([..])")
+        .with_stderr_contains("[..]unexpected `cfg` condition name: `has_bar`")
+        .run();
+}
+
+#[cargo_test]
+fn report_check_cfg() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["check-cfg", "config-flags"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                expected-cfgs = ["foo_fast"]
+
+                [dependencies]
+                bar = { path = "bar" }
+
+                [features]
+                extra = []
+
+                [lints.rust.unexpected_cfgs]
+                check-cfg = ['cfg(foo_mode, values("simd"))']
+            "#,
+        )
+        .file(
+            "build.rs",
+            r#"
+                fn main() {
+                    println!("cargo:rustc-check-cfg=cfg(has_foo)");
+                }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.1.0"))
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    p.cargo("report check-cfg")
+        .masquerade_as_nightly_cargo()
+        .with_stdout(
+            "\
+bar v0.1.0 ([CWD]/bar) (not checked)
+  cfg(feature, values())  features
+foo v0.1.0 ([CWD])
+  cfg(feature, values(\"extra\"))  features
+  cfg(foo_fast)                  package.expected-cfgs
+  cfg(foo_mode, values(\"simd\"))  lints.rust.unexpected_cfgs
+  cfg(has_foo)                   build script
+",
+        )
+        .run();
+
+    p.cargo("report check-cfg -Z check-cfg")
+        .masquerade_as_nightly_cargo()
+        .with_stdout_contains("bar v0.1.0 ([CWD]/bar)")
+        .run();
+}
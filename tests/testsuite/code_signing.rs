@@ -0,0 +1,330 @@
+//! Tests for the `signing` profile setting and `[target.<triple>.signing]`.
+//!
+//! Signing is tested with fake signing tools and linkers, and only when the
+//! standard library of the target is installed.
+
+use cargo_test_support::{basic_manifest, project, Project};
+use std::path::Path;
+use std::process::Command;
+
+const WINDOWS: &str = "x86_64-pc-windows-gnu";
+const APPLE: &str = "x86_64-apple-darwin";
+
+/// A signing tool or linker which logs its arguments to `<name>.log` next to
+/// the file after `-o`, or its last argument, and writes an empty file after
+/// `-o`.
+fn fake_tool(name: &str) -> Project {
+    let p = project()
+        .at(name)
+        .file("Cargo.toml", &basic_manifest(name, "1.0.0"))
+        .file(
+            "src/main.rs",
+            &format!(
+                r#"
+                    use std::io::Write;
+                    use std::path::Path;
+
+                    fn main() {{
+                        let args: Vec<String> = std::env::args().skip(1).collect();
+                        let output = match args.iter().position(|a| a == "-o") {{
+                            Some(i) => {{
+                                std::fs::write(&args[i + 1], "").unwrap();
+                                &args[i + 1]
+                            }}
+                            None => args.last().unwrap(),
+                        }};
+                        let log = Path::new(output).with_file_name("{}.log");
+                        let mut log = std::fs::OpenOptions::new()
+                            .create(true)
+                            .append(true)
+                            .open(log)
+                            .unwrap();
+                        writeln!(log, "{{}}", args.join(" ")).unwrap();
+                    }}
+                "#,
+                name
+            ),
+        )
+        .build();
+    p.cargo("build").run();
+    p
+}
+
+fn target_installed(target: &str) -> bool {
+    let output = Command::new("rustc")
+        .args(&["--print", "sysroot"])
+        .output()
+        .unwrap();
+    let sysroot = String::from_utf8(output.stdout).unwrap();
+    Path::new(sysroot.trim())
+        .join("lib/rustlib")
+        .join(target)
+        .join("lib")
+        .is_dir()
+}
+
+fn linker_env(target: &str) -> String {
+    format!(
+        "CARGO_TARGET_{}_LINKER",
+        target.to_uppercase().replace('-', "_")
+    )
+}
+
+#[cargo_test]
+fn profile_gated() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [profile.release.signing]
+                identity = "Foo"
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[..]feature `code-signing` is required")
+        .run();
+}
+
+#[cargo_test]
+fn not_in_overrides() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["code-signing"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [profile.dev.build-override.signing]
+                identity = "Foo"
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("  `signing` may not be specified in a `build-override` profile")
+        .run();
+}
+
+#[cargo_test]
+fn ignored_for_other_targets() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["code-signing"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [profile.dev.signing]
+                identity = "Foo"
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .file("cert.pfx", "certificate")
+        .file("foo.entitlements", "<plist/>")
+        .build();
+    p.cargo("build -v")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_does_not_contain("[..]codesign[..]")
+        .with_stderr_does_not_contain("[..]signtool[..]")
+        .run();
+}
+
+#[cargo_test]
+fn sign_windows() {
+    if !target_installed(WINDOWS) {
+        return;
+    }
+    let signtool = fake_tool("signtool");
+    let linker = fake_tool("linker");
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                    cargo-features = ["code-signing"]
+
+                    [package]
+                    name = "foo"
+                    version = "0.1.0"
+
+                    [profile.dev.signing]
+
+                identity = "cert.pfx"
+                timestamp-server = "http://timestamp.example.com"
+
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .file("cert.pfx", "certificate")
+        .file("foo.entitlements", "<plist/>")
+        .build();
+    let build = |p: &Project| {
+        let mut build = p.cargo(&format!("build -v --target {}", WINDOWS));
+        build
+            .masquerade_as_nightly_cargo()
+            .env("SIGNTOOL", signtool.bin("signtool"))
+            .env(&linker_env(WINDOWS), linker.bin("linker"));
+        build
+    };
+
+    build(&p)
+        .with_stderr(&format!(
+            "\
+[COMPILING] foo v0.1.0 ([..])
+[RUNNING] `rustc --crate-name foo [..]`
+[RUNNING] `[..]signtool[EXE] sign /fd SHA256 /f [..]/foo/cert.pfx \
+/tr [..]http://timestamp.example.com[..] /td SHA256 [..]/target/{target}/debug/deps/foo-[..].exe`
+[FINISHED] [..]
+",
+            target = WINDOWS
+        ))
+        .run();
+
+    build(&p)
+        .with_stderr("[FRESH] foo v0.1.0 ([..])\n[FINISHED] [..]")
+        .run();
+
+    // A new certificate signs the binary again.
+    p.change_file("cert.pfx", "new certificate");
+    build(&p)
+        .with_stderr_contains("[RUNNING] `[..]signtool[EXE] sign /fd SHA256 /f [..]`")
+        .run();
+
+    // The config of the target takes precedence over the profile.
+    p.change_file(
+        ".cargo/config.toml",
+        &format!(
+            r#"
+                [target.{}.signing]
+                identity = "0123456789abcdef0123456789abcdef01234567"
+            "#,
+            WINDOWS
+        ),
+    );
+    build(&p)
+        .with_status(101)
+        .with_stderr(&format!(
+            "[ERROR] `target.{}.signing` requires `-Z code-signing`",
+            WINDOWS
+        ))
+        .run();
+    build(&p)
+        .arg("-Zcode-signing")
+        .with_stderr_contains(
+            "[RUNNING] `[..]signtool[EXE] sign /fd SHA256 \
+             /sha1 0123456789abcdef0123456789abcdef01234567 \
+             /tr [..]http://timestamp.example.com[..] /td SHA256 [..].exe`",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn sign_apple() {
+    if !target_installed(APPLE) {
+        return;
+    }
+    let codesign = fake_tool("codesign");
+    let linker = fake_tool("linker");
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                    cargo-features = ["code-signing"]
+
+                    [package]
+                    name = "foo"
+                    version = "0.1.0"
+
+                    [profile.dev.signing]
+
+                identity = "Developer ID Application: Foo"
+                entitlements = "foo.entitlements"
+
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .file("cert.pfx", "certificate")
+        .file("foo.entitlements", "<plist/>")
+        .build();
+    let build = |p: &Project| {
+        let mut build = p.cargo(&format!("build -v --target {}", APPLE));
+        build
+            .masquerade_as_nightly_cargo()
+            .env("CODESIGN", codesign.bin("codesign"))
+            .env(&linker_env(APPLE), linker.bin("linker"));
+        build
+    };
+
+    // Development builds don't ask for a timestamp.
+    build(&p)
+        .with_stderr_contains(
+            "[RUNNING] `[..]codesign[EXE] --force --sign 'Developer ID Application: Foo' \
+             --entitlements [..]/foo/foo.entitlements --timestamp=none \
+             [..]/target/x86_64-apple-darwin/debug/deps/foo-[..]`",
+        )
+        .run();
+
+    p.change_file("foo.entitlements", "<plist></plist>");
+    build(&p)
+        .with_stderr_contains("[RUNNING] `[..]codesign[EXE] --force [..]`")
+        .run();
+
+    // Notarization needs the hardened runtime and a secure timestamp.
+    p.change_file(
+        "Cargo.toml",
+        r#"
+            cargo-features = ["code-signing"]
+
+            [package]
+            name = "foo"
+            version = "0.1.0"
+
+            [profile.dev.signing]
+            identity = "Developer ID Application: Foo"
+            notarize = true
+        "#,
+    );
+    build(&p)
+        .with_stderr_contains(
+            "[RUNNING] `[..]codesign[EXE] --force --sign 'Developer ID Application: Foo' \
+             --options runtime --timestamp [..]`",
+        )
+        .run();
+
+    p.change_file(
+        "Cargo.toml",
+        r#"
+            cargo-features = ["code-signing"]
+
+            [package]
+            name = "foo"
+            version = "0.1.0"
+
+            [profile.dev.signing]
+            identity = "-"
+            notarize = true
+        "#,
+    );
+    build(&p)
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] the ad-hoc signing identity `-` of `x86_64-apple-darwin` cannot be \
+             notarized, a Developer ID certificate is required",
+        )
+        .run();
+}
@@ -0,0 +1,268 @@
+//! Tests for `[[codegen]]` steps.
+
+use cargo_test_support::{basic_lib_manifest, project, sleep_ms};
+
+#[cargo_test]
+fn codegen_requires_cargo_feature() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [[codegen]]
+                name = "protos"
+            "#,
+        )
+        .file("codegen/protos.rs", "fn main() {}")
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("  feature `codegen` is required")
+        .run();
+}
+
+#[cargo_test]
+fn codegen_runs_before_crates() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["codegen"]
+
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [build-dependencies]
+                gen = { path = "gen" }
+
+                [[codegen]]
+                name = "protos"
+            "#,
+        )
+        .file("gen/Cargo.toml", &basic_lib_manifest("gen"))
+        .file(
+            "gen/src/lib.rs",
+            r#"
+                pub fn generate(message: &str) -> String {
+                    format!("pub fn message() -> &'static str {{ {:?} }}", message)
+                }
+            "#,
+        )
+        .file(
+            "codegen/protos.rs",
+            r#"
+                fn main() {
+                    println!("cargo:rerun-if-changed=protos/message.txt");
+                    let message = std::fs::read_to_string("protos/message.txt").unwrap();
+                    let out_dir = std::env::var("OUT_DIR").unwrap();
+                    std::fs::write(
+                        format!("{}/protos.rs", out_dir),
+                        gen::generate(message.trim()),
+                    )
+                    .unwrap();
+                }
+            "#,
+        )
+        .file("protos/message.txt", "hello")
+        .file(
+            "src/lib.rs",
+            r#"
+                include!(concat!(env!("CARGO_CODEGEN_DIR_PROTOS"), "/protos.rs"));
+            "#,
+        )
+        .file(
+            "src/main.rs",
+            "fn main() { println!(\"{}\", foo::message()); }",
+        )
+        .build();
+
+    p.cargo("run")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] gen v0.5.0 ([CWD]/gen)
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+[RUNNING] `target/debug/foo[EXE]`
+",
+        )
+        .with_stdout("hello")
+        .run();
+
+    // Only the declared steps run, even with other files in `codegen`.
+    p.change_file("codegen/other.rs", "fn main() { panic!(); }");
+    p.cargo("check").masquerade_as_nightly_cargo().run();
+}
+
+#[cargo_test]
+fn codegen_output_is_cached() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["codegen"]
+
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [build-dependencies]
+                gen = { path = "gen" }
+
+                [[codegen]]
+                name = "protos"
+            "#,
+        )
+        .file("gen/Cargo.toml", &basic_lib_manifest("gen"))
+        .file(
+            "gen/src/lib.rs",
+            r#"
+                pub fn generate(message: &str) -> String {
+                    format!("pub fn message() -> &'static str {{ {:?} }}", message)
+                }
+            "#,
+        )
+        .file(
+            "codegen/protos.rs",
+            r#"
+                fn main() {
+                    println!("cargo:rerun-if-changed=protos/message.txt");
+                    let message = std::fs::read_to_string("protos/message.txt").unwrap();
+                    let out_dir = std::env::var("OUT_DIR").unwrap();
+                    std::fs::write(
+                        format!("{}/protos.rs", out_dir),
+                        gen::generate(message.trim()),
+                    )
+                    .unwrap();
+                }
+            "#,
+        )
+        .file("protos/message.txt", "hello")
+        .file(
+            "src/lib.rs",
+            r#"
+                include!(concat!(env!("CARGO_CODEGEN_DIR_PROTOS"), "/protos.rs"));
+            "#,
+        )
+        .file(
+            "src/main.rs",
+            "fn main() { println!(\"{}\", foo::message()); }",
+        )
+        .build();
+
+    p.cargo("build").masquerade_as_nightly_cargo().run();
+    p.cargo("build -v")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[FRESH] gen v0.5.0 ([CWD]/gen)
+[FRESH] foo v0.0.1 ([CWD])
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+",
+        )
+        .run();
+
+    // Changing the crate doesn't run the step again.
+    sleep_ms(1000);
+    p.change_file(
+        "src/main.rs",
+        "fn main() { println!(\"{}!\", foo::message()); }",
+    );
+    p.cargo("build -v")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_does_not_contain("[RUNNING] `[..]/build/foo-[..]/protos`")
+        .with_stderr_contains("[RUNNING] `rustc --crate-name foo src/main.rs [..]")
+        .run();
+
+    // Changing its input does.
+    p.change_file("protos/message.txt", "bye");
+    p.cargo("run -v")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[RUNNING] `[..]/build/foo-[..]/protos`")
+        .with_stdout("bye!")
+        .run();
+}
+
+#[cargo_test]
+fn codegen_with_build_script() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["codegen"]
+
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [[codegen]]
+                name = "bindings"
+                path = "gen/bindings.rs"
+
+                [[codegen]]
+                name = "fail"
+            "#,
+        )
+        .file(
+            "build.rs",
+            r#"
+                fn main() {
+                    let out_dir = std::env::var("OUT_DIR").unwrap();
+                    std::fs::write(format!("{}/script.rs", out_dir), "pub const SCRIPT: u32 = 1;")
+                        .unwrap();
+                }
+            "#,
+        )
+        .file(
+            "gen/bindings.rs",
+            r#"
+                fn main() {
+                    let out_dir = std::env::var("OUT_DIR").unwrap();
+                    std::fs::write(format!("{}/bindings.rs", out_dir), "pub const BINDINGS: u32 = 2;")
+                        .unwrap();
+                }
+            "#,
+        )
+        .file(
+            "codegen/fail.rs",
+            r#"
+                fn main() {
+                    if std::env::var_os("FAIL").is_some() {
+                        panic!("generation failed");
+                    }
+                }
+            "#,
+        )
+        .file(
+            "src/main.rs",
+            r#"
+                include!(concat!(env!("OUT_DIR"), "/script.rs"));
+                include!(concat!(env!("CARGO_CODEGEN_DIR_BINDINGS"), "/bindings.rs"));
+
+                fn main() {
+                    assert_ne!(env!("OUT_DIR"), env!("CARGO_CODEGEN_DIR_BINDINGS"));
+                    println!("{}", SCRIPT + BINDINGS);
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("run")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("3")
+        .run();
+
+    p.change_file("codegen/fail.rs", "fn main() { std::process::exit(1); }");
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[ERROR] failed to run codegen `fail` for `foo v0.0.1 ([CWD])`")
+        .run();
+}
@@ -0,0 +1,290 @@
+//! Tests for the `cargo completions` command.
+
+use cargo_test_support::{basic_manifest, cargo_process, project};
+
+#[cargo_test]
+fn gated() {
+    cargo_process("completions bash")
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] the `cargo completions` command is unstable, \
+             pass `-Z completions` to enable it",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn query_packages() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["foo", "bar"]
+            "#,
+        )
+        .file(
+            "foo/Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [features]
+                default = ["fast"]
+                fast = []
+            "#,
+        )
+        .file("foo/src/lib.rs", "")
+        .file("foo/src/main.rs", "fn main() {}")
+        .file("foo/src/bin/foo-cli.rs", "fn main() {}")
+        .file("foo/examples/demo.rs", "fn main() {}")
+        .file("foo/tests/it.rs", "")
+        .file(
+            "bar/Cargo.toml",
+            r#"
+                [package]
+                name = "bar"
+                version = "0.1.0"
+
+                [dependencies]
+                baz = { path = "../baz", optional = true }
+
+                [features]
+                extra = []
+            "#,
+        )
+        .file("bar/src/lib.rs", "")
+        .file("baz/Cargo.toml", &basic_manifest("baz", "0.1.0"))
+        .file("baz/src/lib.rs", "")
+        .build();
+    p.cargo("completions -Zcompletions --query packages")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("bar\nbaz\nfoo")
+        .run();
+    // Selecting a package doesn't restrict the packages.
+    p.cargo("completions -Zcompletions --query packages -p foo")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("bar\nbaz\nfoo")
+        .run();
+}
+
+#[cargo_test]
+fn query_targets() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["foo", "bar"]
+            "#,
+        )
+        .file(
+            "foo/Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [features]
+                default = ["fast"]
+                fast = []
+            "#,
+        )
+        .file("foo/src/lib.rs", "")
+        .file("foo/src/main.rs", "fn main() {}")
+        .file("foo/src/bin/foo-cli.rs", "fn main() {}")
+        .file("foo/examples/demo.rs", "fn main() {}")
+        .file("foo/tests/it.rs", "")
+        .file(
+            "bar/Cargo.toml",
+            r#"
+                [package]
+                name = "bar"
+                version = "0.1.0"
+
+                [dependencies]
+                baz = { path = "../baz", optional = true }
+
+                [features]
+                extra = []
+            "#,
+        )
+        .file("bar/src/lib.rs", "")
+        .file("baz/Cargo.toml", &basic_manifest("baz", "0.1.0"))
+        .file("baz/src/lib.rs", "")
+        .build();
+    p.cargo("completions -Zcompletions --query bins")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("foo\nfoo-cli")
+        .run();
+    p.cargo("completions -Zcompletions --query examples -p foo")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("demo")
+        .run();
+    p.cargo("completions -Zcompletions --query tests -p foo")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("it")
+        .run();
+    p.cargo("completions -Zcompletions --query bins -p bar")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("")
+        .run();
+}
+
+#[cargo_test]
+fn query_features() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["foo", "bar"]
+            "#,
+        )
+        .file(
+            "foo/Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [features]
+                default = ["fast"]
+                fast = []
+            "#,
+        )
+        .file("foo/src/lib.rs", "")
+        .file("foo/src/main.rs", "fn main() {}")
+        .file("foo/src/bin/foo-cli.rs", "fn main() {}")
+        .file("foo/examples/demo.rs", "fn main() {}")
+        .file("foo/tests/it.rs", "")
+        .file(
+            "bar/Cargo.toml",
+            r#"
+                [package]
+                name = "bar"
+                version = "0.1.0"
+
+                [dependencies]
+                baz = { path = "../baz", optional = true }
+
+                [features]
+                extra = []
+            "#,
+        )
+        .file("bar/src/lib.rs", "")
+        .file("baz/Cargo.toml", &basic_manifest("baz", "0.1.0"))
+        .file("baz/src/lib.rs", "")
+        .build();
+    p.cargo("completions -Zcompletions --query features -p bar")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("baz\nextra")
+        .run();
+    p.cargo("completions -Zcompletions --query features")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("bar/baz\nbar/extra\nfoo/default\nfoo/fast")
+        .run();
+    p.cargo("completions -Zcompletions --query features")
+        .cwd("foo")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("default\nfast")
+        .run();
+}
+
+#[cargo_test]
+fn query_errors() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["foo", "bar"]
+            "#,
+        )
+        .file(
+            "foo/Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [features]
+                default = ["fast"]
+                fast = []
+            "#,
+        )
+        .file("foo/src/lib.rs", "")
+        .file("foo/src/main.rs", "fn main() {}")
+        .file("foo/src/bin/foo-cli.rs", "fn main() {}")
+        .file("foo/examples/demo.rs", "fn main() {}")
+        .file("foo/tests/it.rs", "")
+        .file(
+            "bar/Cargo.toml",
+            r#"
+                [package]
+                name = "bar"
+                version = "0.1.0"
+
+                [dependencies]
+                baz = { path = "../baz", optional = true }
+
+                [features]
+                extra = []
+            "#,
+        )
+        .file("bar/src/lib.rs", "")
+        .file("baz/Cargo.toml", &basic_manifest("baz", "0.1.0"))
+        .file("baz/src/lib.rs", "")
+        .build();
+    p.cargo("completions -Zcompletions --query nothing")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] unknown completion query `nothing`, expected one of: \
+             packages, bins, examples, tests, benches, features",
+        )
+        .run();
+    cargo_process("completions -Zcompletions powershell")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] unsupported shell `powershell`, expected one of: bash, fish, zsh")
+        .run();
+}
+
+#[cargo_test]
+fn scripts() {
+    cargo_process("completions -Zcompletions bash")
+        .masquerade_as_nightly_cargo()
+        .with_stdout_contains("complete -F _cargo cargo")
+        .with_stdout_contains("<tab><tab>\"build\")")
+        .with_stdout_contains(
+            "<tab><tab><tab><tab>-p|--package|--exclude) _cargo_complete packages; return 0 ;;",
+        )
+        .with_stdout_contains(
+            "<tab><tab><tab><tab>--features) _cargo_complete features; return 0 ;;",
+        )
+        .with_stdout_contains("<tab><tab><tab>opts=\"[..] --release [..]\"")
+        .with_stdout_contains("<tab><tab><tab>opts=\"get [..]\"")
+        .with_stdout_contains("<tab><tab>\"b\")")
+        .run();
+    cargo_process("completions -Zcompletions zsh")
+        .masquerade_as_nightly_cargo()
+        .with_stdout_contains("#compdef cargo")
+        .with_stdout_contains("    ('build')")
+        .with_stdout_contains("        (--bin) _cargo_complete bins; return ;;")
+        .run();
+    cargo_process("completions -Zcompletions fish")
+        .masquerade_as_nightly_cargo()
+        .with_stdout_contains(
+            "complete -c cargo -n __fish_use_subcommand -a build -d 'Compile a local package and all of its dependencies'",
+        )
+        .with_stdout_contains(
+            "complete -c cargo -n '__fish_seen_subcommand_from build' -s p -l package -x -a '(__cargo_query packages)' -d [..]",
+        )
+        .with_stdout_contains("complete -c cargo -n '__fish_seen_subcommand_from config' -a get")
+        .with_stdout_contains(
+            "complete -c cargo -n '__fish_seen_subcommand_from build' -l manifest-path -r -F -d 'Path to Cargo.toml'",
+        )
+        .run();
+}
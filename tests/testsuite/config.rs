@@ -1482,6 +1482,19 @@ fn all_profile_options() {
         dir_name: Some(InternedString::new("dir_name")),
         inherits: Some(InternedString::new("debug")),
         strip: Some(toml::StringOrBool::String("symbols".to_string())),
+        timeout: Some(toml::TomlDuration(std::time::Duration::from_secs(300))),
+        retries: Some(2),
+        sanitizer: Some("address".to_string()),
+        trim_paths: Some(toml::TomlTrimPaths::Values(vec![
+            "macro".to_string(),
+            "object".to_string(),
+        ])),
+        signing: Some(toml::TomlSigning {
+            identity: Some("Developer ID Application: Foo".to_string()),
+            entitlements: Some("foo.entitlements".to_string()),
+            timestamp_server: Some("http://timestamp.example.com".to_string()),
+            notarize: Some(true),
+        }),
         package: None,
         build_override: None,
     };
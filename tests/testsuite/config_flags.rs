@@ -0,0 +1,304 @@
+//! Tests for the `[config-flags]` table and `package.expected-cfgs`.
+
+use cargo_test_support::{basic_lib_manifest, project};
+
+#[cargo_test]
+fn config_flags_require_cargo_feature() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [config-flags]
+                foo = ["foo_fast"]
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("  feature `config-flags` is required")
+        .run();
+}
+
+#[cargo_test]
+fn sets_cfgs_of_dependency() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["config-flags"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = { path = "bar" }
+
+                [config-flags]
+                bar = ["bar_fast", 'bar_mode="simd"']
+            "#,
+        )
+        .file("src/main.rs", "fn main() { bar::fast(); bar::simd(); }")
+        .file(
+            "bar/Cargo.toml",
+            r#"
+                cargo-features = ["config-flags"]
+
+                [package]
+                name = "bar"
+                version = "0.1.0"
+                expected-cfgs = ["bar_fast", 'bar_mode = "simd"', 'bar_mode = "scalar"']
+
+                [features]
+                extra = []
+            "#,
+        )
+        .file(
+            "bar/src/lib.rs",
+            r#"
+                #[cfg(bar_fast)]
+                pub fn fast() {}
+
+                #[cfg(bar_mode = "simd")]
+                pub fn simd() {}
+
+                #[cfg(feature = "extra")]
+                pub fn extra() {}
+            "#,
+        )
+        .build();
+
+    p.cargo("build -v")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "[RUNNING] `rustc --crate-name bar [..]--cfg bar_fast --cfg 'bar_mode=\"simd\"' \
+             --check-cfg 'cfg(feature, values(\"extra\"))' --check-cfg 'cfg(bar_fast)' \
+             --check-cfg 'cfg(bar_mode, values(\"simd\", \"scalar\"))' [..]",
+        )
+        .with_stderr_does_not_contain("[..]--crate-name foo [..]--cfg bar_fast[..]")
+        .with_stderr_does_not_contain("[WARNING][..]")
+        .run();
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[FINISHED] [..]")
+        .run();
+
+    // Changing the cfgs rebuilds the package.
+    p.change_file(
+        "Cargo.toml",
+        &p.read_file("Cargo.toml").replace("\"bar_fast\", ", ""),
+    );
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[COMPILING] bar v0.1.0 ([..])")
+        .with_stderr_contains("[..]cannot find function `fast` in crate `bar`[..]")
+        .run();
+}
+
+#[cargo_test]
+fn unexpected_cfg() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["config-flags"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = { path = "bar" }
+
+                [config-flags]
+                bar = ["bar_fsat"]
+            "#,
+        )
+        .file("src/main.rs", "fn main() { bar::fast(); bar::simd(); }")
+        .file(
+            "bar/Cargo.toml",
+            r#"
+                cargo-features = ["config-flags"]
+
+                [package]
+                name = "bar"
+                version = "0.1.0"
+                expected-cfgs = ["bar_fast", 'bar_mode = "simd"', 'bar_mode = "scalar"']
+
+                [features]
+                extra = []
+            "#,
+        )
+        .file(
+            "bar/src/lib.rs",
+            r#"
+                #[cfg(bar_fast)]
+                pub fn fast() {}
+
+                #[cfg(bar_mode = "simd")]
+                pub fn simd() {}
+
+                #[cfg(feature = "extra")]
+                pub fn extra() {}
+            "#,
+        )
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] package `bar v0.1.0 ([CWD]/bar)` doesn't expect the cfg `bar_fsat` set for it in \
+`[config-flags]`, the cfgs it expects are: bar_fast, bar_mode = \"simd\", bar_mode = \"scalar\"
+
+<tab>Did you mean `bar_fast`?
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn reserved_cfg() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["config-flags"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = { path = "bar" }
+
+                [config-flags]
+                bar = ['feature = "extra"']
+            "#,
+        )
+        .file("src/main.rs", "fn main() { bar::fast(); bar::simd(); }")
+        .file(
+            "bar/Cargo.toml",
+            r#"
+                cargo-features = ["config-flags"]
+
+                [package]
+                name = "bar"
+                version = "0.1.0"
+                expected-cfgs = ["bar_fast", 'bar_mode = "simd"', 'bar_mode = "scalar"']
+
+                [features]
+                extra = []
+            "#,
+        )
+        .file(
+            "bar/src/lib.rs",
+            r#"
+                #[cfg(bar_fast)]
+                pub fn fast() {}
+
+                #[cfg(bar_mode = "simd")]
+                pub fn simd() {}
+
+                #[cfg(feature = "extra")]
+                pub fn extra() {}
+            "#,
+        )
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse manifest at `[CWD]/Cargo.toml`
+
+Caused by:
+  the cfg `feature = \"extra\"` in `[config-flags]` entry `bar` is set by Cargo or rustc \
+and can't be configured
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn unchecked_packages() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["config-flags"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = { path = "bar" }
+
+                [config-flags]
+                bar = ["bar_fast"]
+                baz = ["baz_fast"]
+            "#,
+        )
+        .file("src/lib.rs", "pub fn foo() { bar::fast() }")
+        .file("bar/Cargo.toml", &basic_lib_manifest("bar"))
+        .file("bar/src/lib.rs", "#[cfg(bar_fast)] pub fn fast() {}")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[WARNING] `[config-flags]` sets cfgs for `baz`, which is not in the dependency graph
+[WARNING] package `bar v0.5.0 ([CWD]/bar)` doesn't declare `package.expected-cfgs`, \
+the cfgs set for it in `[config-flags]` are not checked
+[CHECKING] bar v0.5.0 ([CWD]/bar)
+[CHECKING] foo v0.1.0 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn checks_cfgs_of_package() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["config-flags"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                expected-cfgs = ["foo_fast"]
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            r#"
+                #[cfg(foo_fast)]
+                pub fn fast() {}
+
+                #[cfg(foo_fsat)]
+                pub fn typo() {}
+            "#,
+        )
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[WARNING] unexpected `cfg` condition name: `foo_fsat`")
+        .with_stderr_contains("[WARNING] `foo` (lib) generated 1 warning")
+        .run();
+}
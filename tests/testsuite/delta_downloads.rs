@@ -0,0 +1,153 @@
+//! Tests for `-Z delta-downloads`.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+use cargo_test_support::registry::{self, Package};
+use cargo_test_support::{git, project, Project};
+
+fn has_zstd() -> bool {
+    Command::new("zstd").arg("--version").output().is_ok()
+}
+
+/// Configures the registry with a `delta` template, and builds a project
+/// which caches `bar v1.0.0`.
+fn setup() -> Project {
+    Package::new("bar", "1.0.0")
+        .file("src/lib.rs", "")
+        .publish();
+    let repo = git2::Repository::open(registry::registry_path()).unwrap();
+    fs::write(
+        registry::registry_path().join("config.json"),
+        format!(
+            r#"{{"dl": "{0}", "api": "{1}", "delta": "{0}/{{crate}}/{{from-version}}/{{version}}/delta"}}"#,
+            registry::dl_url(),
+            registry::api_url()
+        ),
+    )
+    .unwrap();
+    git::add(&repo);
+    git::commit(&repo);
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("build -Z delta-downloads")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[DOWNLOADED] bar v1.0.0 (registry `dummy-registry`)")
+        .run();
+    p
+}
+
+fn archive(version: &str) -> PathBuf {
+    registry::dl_path()
+        .join("bar")
+        .join(version)
+        .join("download")
+}
+
+/// Publishes the delta of `bar` from `from` to `to`, which is a patch of the
+/// `.crate` file of `base` giving the `.crate` file of `contents`, or with
+/// `base` set to `None`, a frame giving it from any file.
+fn publish_delta(from: &str, to: &str, base: Option<&str>, contents: &str) {
+    let dst = registry::dl_path()
+        .join("bar")
+        .join(from)
+        .join(to)
+        .join("delta");
+    fs::create_dir_all(dst.parent().unwrap()).unwrap();
+    let mut cmd = Command::new("zstd");
+    cmd.arg("-q");
+    if let Some(base) = base {
+        cmd.arg(format!("--patch-from={}", archive(base).display()));
+    }
+    let status = cmd
+        .arg(archive(contents))
+        .arg("-o")
+        .arg(&dst)
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+#[cargo_test]
+fn delta_download() {
+    if !has_zstd() {
+        return;
+    }
+    let p = setup();
+
+    Package::new("bar", "1.0.1")
+        .file("src/lib.rs", "pub fn new() {}")
+        .publish();
+    publish_delta("1.0.0", "1.0.1", Some("1.0.0"), "1.0.1");
+    // The full download is gone, so only the patch can be used.
+    fs::remove_file(archive("1.0.1")).unwrap();
+
+    p.cargo("update").masquerade_as_nightly_cargo().run();
+    p.cargo("build -Z delta-downloads")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[DOWNLOADED] bar v1.0.1 (registry `dummy-registry`) (delta from v1.0.0)
+[COMPILING] bar v1.0.1
+[COMPILING] foo v0.1.0 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn delta_download_fallback() {
+    if !has_zstd() {
+        return;
+    }
+    let p = setup();
+
+    // Without a patch, the crate is downloaded in full.
+    Package::new("bar", "1.0.1")
+        .file("src/lib.rs", "pub fn new() {}")
+        .publish();
+    p.cargo("update").masquerade_as_nightly_cargo().run();
+    p.cargo("fetch -Z delta-downloads")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[DOWNLOADING] crates ...
+[DOWNLOADED] bar v1.0.1 (registry `dummy-registry`)
+",
+        )
+        .run();
+
+    // A patch which doesn't give the checksum of the index, here because it
+    // gives another version, is not used.
+    Package::new("bar", "1.0.2")
+        .file("src/lib.rs", "pub fn newer() {}")
+        .publish();
+    publish_delta("1.0.1", "1.0.2", None, "1.0.1");
+    p.cargo("update").masquerade_as_nightly_cargo().run();
+    p.cargo("fetch -Z delta-downloads")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[WARNING] the delta of `bar v1.0.2 (registry `dummy-registry`)` from v1.0.1 does not match \
+its checksum, downloading it in full
+[DOWNLOADING] crates ...
+[DOWNLOADED] bar v1.0.2 (registry `dummy-registry`)
+",
+        )
+        .run();
+}
@@ -0,0 +1,191 @@
+//! Tests for `lints.workspace-deny-warnings` and `--deny-warnings`.
+
+use cargo_test_support::registry::Package;
+use cargo_test_support::{basic_manifest, project, Project};
+
+const WARNING: &str = "pub fn f() { let x = 1; }";
+
+/// A workspace whose member `a` depends on the member `b`, on the path
+/// dependency `dep` outside of the workspace and on the registry package
+/// `bar`, which all have a warning.
+fn workspace(root: &str) -> Project {
+    Package::new("bar", "1.0.0")
+        .file("src/lib.rs", WARNING)
+        .publish();
+    project()
+        .at("dep")
+        .file("Cargo.toml", &basic_manifest("dep", "0.1.0"))
+        .file("src/lib.rs", WARNING)
+        .build();
+    project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    cargo-features = ["deny-warnings"]
+
+                    [workspace]
+                    members = ["a", "b"]
+
+                    {}
+                "#,
+                root
+            ),
+        )
+        .file(
+            "a/Cargo.toml",
+            r#"
+                [package]
+                name = "a"
+                version = "0.1.0"
+
+                [dependencies]
+                b = { path = "../b" }
+                dep = { path = "../../dep" }
+                bar = "1.0"
+            "#,
+        )
+        .file("a/src/lib.rs", WARNING)
+        .file("b/Cargo.toml", &basic_manifest("b", "0.1.0"))
+        .file("b/src/lib.rs", WARNING)
+        .build()
+}
+
+#[cargo_test]
+fn manifest_gated() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [lints]
+                workspace-deny-warnings = true
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[..]feature `deny-warnings` is required")
+        .run();
+}
+
+#[cargo_test]
+fn other_lints_in_virtual_manifest() {
+    let p = workspace(
+        r#"
+            [lints]
+            doc-coverage = 50
+        "#,
+    );
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "  this virtual manifest specifies a [lints] section, which is not allowed, \
+             except for `workspace-deny-warnings`",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn workspace_members() {
+    let p = workspace(
+        r#"
+            [lints]
+            workspace-deny-warnings = true
+        "#,
+    );
+    p.cargo("check -v -p b")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[RUNNING] `rustc --crate-name b [..]-D warnings[..]`")
+        .with_stderr_contains("[ERROR] unused variable: `x`")
+        .run();
+
+    // The dependencies outside of the workspace only warn.
+    p.change_file("b/src/lib.rs", "");
+    p.cargo("check -v -p a")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[RUNNING] `rustc --crate-name bar [..]--cap-lints allow[..]`")
+        .with_stderr_does_not_contain("[RUNNING] `rustc --crate-name dep [..]-D warnings[..]`")
+        .with_stderr_contains("[WARNING] `dep` (lib) generated 1 warning")
+        .with_stderr_contains("[RUNNING] `rustc --crate-name a [..]-D warnings[..]`")
+        .with_stderr_contains("[ERROR] could not compile `a` due to previous error")
+        .run();
+
+    p.change_file("a/src/lib.rs", "");
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[FINISHED] [..]")
+        .run();
+}
+
+#[cargo_test]
+fn not_in_members() {
+    let p = workspace("");
+    p.change_file(
+        "b/Cargo.toml",
+        r#"
+            cargo-features = ["deny-warnings"]
+
+            [package]
+            name = "b"
+            version = "0.1.0"
+
+            [lints]
+            workspace-deny-warnings = true
+        "#,
+    );
+    p.cargo("check -p b")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "[WARNING] lints.workspace-deny-warnings for the non root package will be ignored, \
+             specify lints.workspace-deny-warnings at the workspace root:",
+        )
+        .with_stderr_contains("[WARNING] unused variable: `x`")
+        .with_stderr_contains("[FINISHED] [..]")
+        .run();
+}
+
+#[cargo_test]
+fn cli_gated() {
+    let p = workspace("");
+    p.cargo("check --deny-warnings")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] Usage of `--deny-warnings` requires `-Z deny-warnings`")
+        .run();
+}
+
+#[cargo_test]
+fn cli_selected_packages() {
+    let p = workspace("");
+    p.change_file("a/src/lib.rs", "");
+    p.cargo("check -v -p a -Zdeny-warnings --deny-warnings")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_does_not_contain("[RUNNING] `rustc --crate-name b [..]-D warnings[..]`")
+        .with_stderr_contains("[WARNING] `b` (lib) generated 1 warning")
+        .with_stderr_contains("[RUNNING] `rustc --crate-name a [..]-D warnings[..]`")
+        .with_stderr_contains("[FINISHED] [..]")
+        .run();
+
+    // Only the selected package is checked again without the flag.
+    p.cargo("check -p a")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_does_not_contain("[CHECKING] b [..]")
+        .with_stderr_contains("[CHECKING] a v0.1.0 ([..])")
+        .run();
+
+    p.cargo("check -v -p b -Zdeny-warnings --deny-warnings")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[RUNNING] `rustc --crate-name b [..]-D warnings[..]`")
+        .with_stderr_contains("[ERROR] unused variable: `x`")
+        .run();
+}
@@ -0,0 +1,362 @@
+//! Tests for `cargo doc --report-coverage`.
+
+use cargo_test_support::{basic_lib_manifest, basic_manifest, project};
+
+#[cargo_test]
+fn gated() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["foo", "bar", "baz"]
+            "#,
+        )
+        .file(
+            "foo/Cargo.toml",
+            r#"
+                cargo-features = ["doc-coverage"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [lints]
+
+            "#,
+        )
+        .file(
+            "foo/src/lib.rs",
+            r#"
+                //! The foo crate.
+
+                /// Documented.
+                pub fn documented() {}
+
+                pub fn undocumented() {}
+
+                /// ```
+                /// foo::S;
+                /// ```
+                pub struct S;
+            "#,
+        )
+        .file("bar/Cargo.toml", &basic_lib_manifest("bar"))
+        .file(
+            "bar/src/lib.rs",
+            r#"
+                //! The bar crate.
+
+                /// Documented.
+                pub fn documented() {}
+            "#,
+        )
+        .file("baz/Cargo.toml", &basic_manifest("baz", "0.3.0"))
+        .file("baz/src/main.rs", "fn main() {}")
+        .build();
+    p.cargo("doc --report-coverage")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] Usage of `--report-coverage` requires `-Z doc-coverage`")
+        .run();
+}
+
+#[cargo_test]
+fn lints_require_cargo_feature() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [lints]
+                doc-coverage = 50
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("doc")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("  feature `doc-coverage` is required")
+        .run();
+}
+
+#[cargo_test]
+fn invalid_threshold() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["foo", "bar", "baz"]
+            "#,
+        )
+        .file(
+            "foo/Cargo.toml",
+            r#"
+                cargo-features = ["doc-coverage"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [lints]
+                doc-coverage = 150
+            "#,
+        )
+        .file(
+            "foo/src/lib.rs",
+            r#"
+                //! The foo crate.
+
+                /// Documented.
+                pub fn documented() {}
+
+                pub fn undocumented() {}
+
+                /// ```
+                /// foo::S;
+                /// ```
+                pub struct S;
+            "#,
+        )
+        .file("bar/Cargo.toml", &basic_lib_manifest("bar"))
+        .file(
+            "bar/src/lib.rs",
+            r#"
+                //! The bar crate.
+
+                /// Documented.
+                pub fn documented() {}
+            "#,
+        )
+        .file("baz/Cargo.toml", &basic_manifest("baz", "0.3.0"))
+        .file("baz/src/main.rs", "fn main() {}")
+        .build();
+    p.cargo("doc")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "  `lints.doc-coverage` must be a percentage between 0 and 100, found `150`",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn report() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["foo", "bar", "baz"]
+            "#,
+        )
+        .file(
+            "foo/Cargo.toml",
+            r#"
+                cargo-features = ["doc-coverage"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [lints]
+                doc-coverage = 50
+            "#,
+        )
+        .file(
+            "foo/src/lib.rs",
+            r#"
+                //! The foo crate.
+
+                /// Documented.
+                pub fn documented() {}
+
+                pub fn undocumented() {}
+
+                /// ```
+                /// foo::S;
+                /// ```
+                pub struct S;
+            "#,
+        )
+        .file("bar/Cargo.toml", &basic_lib_manifest("bar"))
+        .file(
+            "bar/src/lib.rs",
+            r#"
+                //! The bar crate.
+
+                /// Documented.
+                pub fn documented() {}
+            "#,
+        )
+        .file("baz/Cargo.toml", &basic_manifest("baz", "0.3.0"))
+        .file("baz/src/main.rs", "fn main() {}")
+        .build();
+    p.cargo("doc -Zdoc-coverage --report-coverage")
+        .masquerade_as_nightly_cargo()
+        .env("RUSTC_BOOTSTRAP", "1")
+        .with_stdout(
+            "\
+package             documented            examples
+bar v0.5.0        2/2 (100.0%)          0/1 (0.0%)
+foo v0.1.0         3/4 (75.0%)         1/3 (33.3%)
+total              5/6 (83.3%)         1/4 (25.0%)
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn below_threshold() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["foo", "bar", "baz"]
+            "#,
+        )
+        .file(
+            "foo/Cargo.toml",
+            r#"
+                                cargo-features = ["doc-coverage"]
+
+                                [package]
+                                name = "foo"
+                                version = "0.1.0"
+
+                                [lints]
+                                doc-coverage = 80
+                doc-example-coverage = 30
+            "#,
+        )
+        .file(
+            "foo/src/lib.rs",
+            r#"
+                //! The foo crate.
+
+                /// Documented.
+                pub fn documented() {}
+
+                pub fn undocumented() {}
+
+                /// ```
+                /// foo::S;
+                /// ```
+                pub struct S;
+            "#,
+        )
+        .file("bar/Cargo.toml", &basic_lib_manifest("bar"))
+        .file(
+            "bar/src/lib.rs",
+            r#"
+                //! The bar crate.
+
+                /// Documented.
+                pub fn documented() {}
+            "#,
+        )
+        .file("baz/Cargo.toml", &basic_manifest("baz", "0.3.0"))
+        .file("baz/src/main.rs", "fn main() {}")
+        .build();
+    p.cargo("doc -Zdoc-coverage --report-coverage -p foo")
+        .masquerade_as_nightly_cargo()
+        .env("RUSTC_BOOTSTRAP", "1")
+        .with_status(101)
+        .with_stdout(
+            "\
+package             documented            examples
+foo v0.1.0         3/4 (75.0%)         1/3 (33.3%)
+",
+        )
+        .with_stderr_contains(
+            "\
+[ERROR] the documentation coverage is below the thresholds of `[lints]`:
+  `foo`: doc-coverage is 75.0%, below the required 80%",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn json() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["foo", "bar", "baz"]
+            "#,
+        )
+        .file(
+            "foo/Cargo.toml",
+            r#"
+                cargo-features = ["doc-coverage"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [lints]
+
+            "#,
+        )
+        .file(
+            "foo/src/lib.rs",
+            r#"
+                //! The foo crate.
+
+                /// Documented.
+                pub fn documented() {}
+
+                pub fn undocumented() {}
+
+                /// ```
+                /// foo::S;
+                /// ```
+                pub struct S;
+            "#,
+        )
+        .file("bar/Cargo.toml", &basic_lib_manifest("bar"))
+        .file(
+            "bar/src/lib.rs",
+            r#"
+                //! The bar crate.
+
+                /// Documented.
+                pub fn documented() {}
+            "#,
+        )
+        .file("baz/Cargo.toml", &basic_manifest("baz", "0.3.0"))
+        .file("baz/src/main.rs", "fn main() {}")
+        .build();
+    p.cargo("doc -Zdoc-coverage --report-coverage --message-format json -p bar")
+        .masquerade_as_nightly_cargo()
+        .env("RUSTC_BOOTSTRAP", "1")
+        .with_json_contains_unordered(
+            r#"
+                {
+                    "reason": "doc-coverage",
+                    "package_id": "bar 0.5.0 ([..])",
+                    "manifest_path": "[..]/bar/Cargo.toml",
+                    "total": 2,
+                    "with_docs": 2,
+                    "total_examples": 1,
+                    "with_examples": 0,
+                    "files": {
+                        "src/lib.rs": {
+                            "total": 2,
+                            "with_docs": 2,
+                            "total_examples": 1,
+                            "with_examples": 0
+                        }
+                    }
+                }
+            "#,
+        )
+        .run();
+}
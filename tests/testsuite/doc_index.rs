@@ -0,0 +1,192 @@
+//! Tests for `-Z doc-index`.
+
+use cargo_test_support::registry::Package;
+use cargo_test_support::{basic_manifest, project, tools};
+
+#[cargo_test]
+fn not_recorded_without_flag() {
+    Package::new("dep", "1.0.0")
+        .file("src/lib.rs", "pub fn dep() {}")
+        .publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["foo", "bar"]
+            "#,
+        )
+        .file(
+            "foo/Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                description = "The <foo> crate"
+
+                [dependencies]
+                dep = "1.0"
+            "#,
+        )
+        .file("foo/src/lib.rs", "//! Uses [dep::dep].")
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.2.0"))
+        .file("bar/src/main.rs", "fn main() {}")
+        .build();
+    p.cargo("doc").run();
+    assert!(!p.root().join("target/doc/doc-index.json").exists());
+    assert!(!p.root().join("target/doc/index.html").exists());
+}
+
+#[cargo_test]
+fn merged_across_invocations() {
+    Package::new("dep", "1.0.0")
+        .file("src/lib.rs", "pub fn dep() {}")
+        .publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["foo", "bar"]
+            "#,
+        )
+        .file(
+            "foo/Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                description = "The <foo> crate"
+
+                [dependencies]
+                dep = "1.0"
+            "#,
+        )
+        .file("foo/src/lib.rs", "//! Uses [dep::dep].")
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.2.0"))
+        .file("bar/src/main.rs", "fn main() {}")
+        .build();
+    p.cargo("doc -Zdoc-index -p foo")
+        .masquerade_as_nightly_cargo()
+        .run();
+    p.cargo("doc -Zdoc-index -p bar")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    let index = p.read_file("target/doc/doc-index.json");
+    let index: serde_json::Value = serde_json::from_str(&index).unwrap();
+    let crates: Vec<_> = index["crates"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| {
+            (
+                c["name"].as_str().unwrap(),
+                c["kind"].as_str().unwrap(),
+                c["member"].as_bool().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        crates,
+        [
+            ("bar", "bin", true),
+            ("foo", "lib", true),
+            ("dep", "lib", false)
+        ]
+    );
+
+    let page = p.read_file("target/doc/index.html");
+    assert!(page.contains(
+        "<h2>Workspace crates</h2>\n<ul>\n\
+         <li><a href=\"bar/index.html\">bar</a> 0.2.0 (bin)</li>\n\
+         <li><a href=\"foo/index.html\">foo</a> 0.1.0 &mdash; The &lt;foo&gt; crate</li>\n\
+         </ul>\n\
+         <h2>Dependencies</h2>\n<ul>\n\
+         <li><a href=\"dep/index.html\">dep</a> 1.0.0</li>\n\
+         </ul>\n"
+    ));
+}
+
+#[cargo_test]
+fn removed_docs_are_dropped() {
+    Package::new("dep", "1.0.0")
+        .file("src/lib.rs", "pub fn dep() {}")
+        .publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["foo", "bar"]
+            "#,
+        )
+        .file(
+            "foo/Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                description = "The <foo> crate"
+
+                [dependencies]
+                dep = "1.0"
+            "#,
+        )
+        .file("foo/src/lib.rs", "//! Uses [dep::dep].")
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.2.0"))
+        .file("bar/src/main.rs", "fn main() {}")
+        .build();
+    p.cargo("doc -Zdoc-index")
+        .masquerade_as_nightly_cargo()
+        .run();
+    std::fs::remove_dir_all(p.root().join("target/doc/dep")).unwrap();
+    p.cargo("doc -Zdoc-index -p bar")
+        .masquerade_as_nightly_cargo()
+        .run();
+    let page = p.read_file("target/doc/index.html");
+    assert!(page.contains("bar/index.html"));
+    assert!(page.contains("foo/index.html"));
+    assert!(!page.contains("dep/index.html"));
+}
+
+#[cargo_test]
+fn open_landing_page() {
+    Package::new("dep", "1.0.0")
+        .file("src/lib.rs", "pub fn dep() {}")
+        .publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["foo", "bar"]
+            "#,
+        )
+        .file(
+            "foo/Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                description = "The <foo> crate"
+
+                [dependencies]
+                dep = "1.0"
+            "#,
+        )
+        .file("foo/src/lib.rs", "//! Uses [dep::dep].")
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.2.0"))
+        .file("bar/src/main.rs", "fn main() {}")
+        .build();
+    p.cargo("doc -Zdoc-index --open")
+        .masquerade_as_nightly_cargo()
+        .env("BROWSER", tools::echo())
+        .with_stdout("[CWD]/target/doc/index.html")
+        .run();
+    p.cargo("doc -Zdoc-index --open -p foo")
+        .masquerade_as_nightly_cargo()
+        .env("BROWSER", tools::echo())
+        .with_stdout("[CWD]/target/doc/foo/index.html")
+        .run();
+}
@@ -0,0 +1,141 @@
+//! Tests for `cargo doc --offline-first`.
+
+use cargo_test_support::registry::Package;
+use cargo_test_support::{basic_manifest, project, tools};
+
+#[cargo_test]
+fn gated() {
+    let p = project().file("src/lib.rs", "").build();
+    p.cargo("doc --offline-first -p foo")
+        .with_status(101)
+        .with_stderr("[ERROR] Usage of `--offline-first` requires `-Z doc-offline-first`")
+        .run();
+}
+
+#[cargo_test]
+fn shared_and_reused() {
+    Package::new("baz", "1.0.0").publish();
+    Package::new("bar", "1.0.0")
+        .dep("baz", "1.0")
+        .file("src/lib.rs", "pub fn bar() {}")
+        .publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("doc -Zdoc-offline-first --offline-first --open -p bar")
+        .masquerade_as_nightly_cargo()
+        .env("BROWSER", tools::echo())
+        .with_stderr_unordered(
+            "\
+[UPDATING] `[..]` index
+[DOWNLOADING] crates ...
+[DOWNLOADED] ba[..] v1.0.0 (registry `dummy-registry`)
+[DOWNLOADED] ba[..] v1.0.0 (registry `dummy-registry`)
+[CHECKING] baz v1.0.0
+[DOCUMENTING] baz v1.0.0
+[DOCUMENTING] bar v1.0.0
+[FINISHED] [..]
+[..] Opening [..]/home/.cargo/doc-cache/doc/bar/index.html
+",
+        )
+        .run();
+    assert!(!p.root().join("target/doc").exists());
+
+    // Another workspace reuses the docs.
+    let p2 = project()
+        .at("other")
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "other"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+    p2.cargo("doc -Zdoc-offline-first --offline-first --open -p bar")
+        .masquerade_as_nightly_cargo()
+        .env("BROWSER", tools::echo())
+        .with_stderr(
+            "\
+[UPDATING] `dummy-registry` index
+[..] Fresh bar v1.0.0
+[..] Opening [..]/home/.cargo/doc-cache/doc/bar/index.html
+",
+        )
+        .run();
+
+    // Another version is documented again.
+    Package::new("bar", "1.1.0").publish();
+    p2.cargo("update").run();
+    p2.cargo("doc -Zdoc-offline-first --offline-first --open -p bar")
+        .masquerade_as_nightly_cargo()
+        .env("BROWSER", tools::echo())
+        .with_stderr_contains("[DOCUMENTING] bar v1.1.0")
+        .with_stderr_contains("[..] Opening [..]/home/.cargo/doc-cache/doc/bar/index.html")
+        .run();
+}
+
+#[cargo_test]
+fn docs_rs_fallback() {
+    Package::new("bar", "1.0.0")
+        .file("src/lib.rs", "pub fn bar() -> {}")
+        .publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("doc -Zdoc-offline-first --offline-first --open -p bar")
+        .masquerade_as_nightly_cargo()
+        .env("BROWSER", tools::echo())
+        .with_stdout("https://docs.rs/bar/1.0.0")
+        .with_stderr_contains("[WARNING] failed to document `bar v1.0.0`, opening docs.rs instead")
+        .with_stderr_contains("[..] Opening https://docs.rs/bar/1.0.0")
+        .run();
+}
+
+#[cargo_test]
+fn members_are_documented_as_usual() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("doc -Zdoc-offline-first --offline-first --open -p foo")
+        .masquerade_as_nightly_cargo()
+        .env("BROWSER", tools::echo())
+        .with_stderr(
+            "\
+[DOCUMENTING] foo v0.1.0 ([CWD])
+[FINISHED] [..]
+[..] Opening [CWD]/target/doc/foo/index.html
+",
+        )
+        .run();
+}
@@ -0,0 +1,273 @@
+//! Tests for resuming downloads, and for `-Z downloads`.
+
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+use cargo_test_support::registry::{self, Package};
+use cargo_test_support::{git, paths, project};
+
+/// How the server answers a request for a `.crate` file.
+enum Reply {
+    /// Sends the first half of the file, and closes the connection.
+    Half,
+    /// Sends the file, or the range requested.
+    Full,
+    /// Sends the whole file, even if a range is requested.
+    IgnoreRange,
+}
+
+/// Serves the `.crate` files of the registry over HTTP, answering the
+/// requests with `replies` in order, and points the registry at it. Returns
+/// the `Range` header of each request.
+fn serve(replies: Vec<Reply>) -> thread::JoinHandle<Vec<Option<String>>> {
+    let server = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = server.local_addr().unwrap();
+    let repo = git2::Repository::open(registry::registry_path()).unwrap();
+    fs::write(
+        registry::registry_path().join("config.json"),
+        format!(
+            r#"{{"dl": "http://{}/{{crate}}/{{version}}/download", "api": "{}"}}"#,
+            addr,
+            registry::api_url()
+        ),
+    )
+    .unwrap();
+    git::add(&repo);
+    git::commit(&repo);
+
+    let dl_path = registry::dl_path();
+    thread::spawn(move || {
+        let mut ranges = Vec::new();
+        for reply in replies {
+            let (conn, _) = server.accept().unwrap();
+            let mut conn = BufReader::new(conn);
+            let headers: Vec<String> = (&mut conn)
+                .lines()
+                .map(|line| line.unwrap())
+                .take_while(|line| !line.is_empty())
+                .collect();
+            let path = headers[0].split(' ').nth(1).unwrap();
+            let data = fs::read(dl_path.join(&path[1..])).unwrap();
+            let range = headers
+                .iter()
+                .find_map(|h| h.strip_prefix("Range: bytes="))
+                .map(|r| r.trim_end_matches('-').to_string());
+            respond(conn.get_mut(), &data, range.as_deref(), reply);
+            ranges.push(range);
+        }
+        ranges
+    })
+}
+
+fn respond(stream: &mut TcpStream, data: &[u8], range: Option<&str>, reply: Reply) {
+    match (reply, range) {
+        (Reply::Half, _) => {
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n",
+                data.len()
+            )
+            .unwrap();
+            stream.write_all(&data[..data.len() / 2]).unwrap();
+        }
+        (Reply::Full, Some(range)) => {
+            let start: usize = range.parse().unwrap();
+            write!(
+                stream,
+                "HTTP/1.1 206 Partial Content\r\n\
+                 Content-Range: bytes {}-{}/{}\r\n\
+                 Content-Length: {}\r\n\r\n",
+                start,
+                data.len() - 1,
+                data.len(),
+                data.len() - start
+            )
+            .unwrap();
+            stream.write_all(&data[start..]).unwrap();
+        }
+        (Reply::Full, None) | (Reply::IgnoreRange, _) => {
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n",
+                data.len()
+            )
+            .unwrap();
+            stream.write_all(data).unwrap();
+        }
+    }
+}
+
+fn publish_bar() -> usize {
+    // Enough contents that half of the file is not nothing.
+    Package::new("bar", "1.0.0")
+        .file("src/lib.rs", &"// padding\n".repeat(200))
+        .publish();
+    fs::read(registry::dl_path().join("bar/1.0.0/download"))
+        .unwrap()
+        .len()
+}
+
+#[cargo_test]
+fn resume_after_network_error() {
+    let len = publish_bar();
+    let server = serve(vec![Reply::Half, Reply::Full]);
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("fetch")
+        .with_stderr_contains("[WARNING] spurious network error (2 tries remaining): [..]")
+        .with_stderr_contains("[DOWNLOADED] bar v1.0.0 (registry `dummy-registry`)")
+        .run();
+
+    let ranges = server.join().unwrap();
+    assert_eq!(ranges, vec![None, Some((len / 2).to_string())]);
+    p.cargo("build").run();
+}
+
+#[cargo_test]
+fn resume_unsupported() {
+    publish_bar();
+    let server = serve(vec![Reply::Half, Reply::IgnoreRange, Reply::Full]);
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    // The download starts over, without using up a retry.
+    p.cargo("fetch")
+        .with_stderr_contains("[WARNING] spurious network error (2 tries remaining): [..]")
+        .with_stderr_does_not_contain("[..]1 tries remaining[..]")
+        .with_stderr_contains("[DOWNLOADED] bar v1.0.0 (registry `dummy-registry`)")
+        .run();
+
+    let ranges = server.join().unwrap();
+    assert_eq!(ranges.len(), 3);
+    assert_eq!(ranges[2], None);
+}
+
+#[cargo_test]
+fn download_messages() {
+    let len = publish_bar();
+    let server = serve(vec![Reply::Half, Reply::Full]);
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build -Z downloads --message-format json")
+        .masquerade_as_nightly_cargo()
+        .with_json_contains_unordered(&format!(
+            r#"
+                {{
+                    "reason": "download-retry",
+                    "package_id": "bar 1.0.0 [..]",
+                    "url": "http://[..]/bar/1.0.0/download",
+                    "attempt": 1,
+                    "error": "{{...}}",
+                    "retry_in_ms": 500,
+                    "resume_from": {half}
+                }}
+
+                {{
+                    "reason": "download-finished",
+                    "package_id": "bar 1.0.0 [..]",
+                    "url": "http://[..]/bar/1.0.0/download",
+                    "bytes": {len},
+                    "resumed_bytes": {half},
+                    "attempts": 2,
+                    "duration_ms": "{{...}}"
+                }}
+            "#,
+            half = len / 2,
+            len = len
+        ))
+        .run();
+    server.join().unwrap();
+}
+
+#[cargo_test]
+fn download_limits() {
+    Package::new("bar", "1.0.0").publish();
+    Package::new("baz", "1.0.0").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+                baz = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    // The settings are ignored without `-Z downloads`.
+    p.cargo("fetch")
+        .env("CARGO_NET_MAX_DOWNLOAD_RATE", "fast")
+        .run();
+    p.cargo("clean").run();
+    fs::remove_dir_all(paths::home().join(".cargo/registry/cache")).unwrap();
+    fs::remove_dir_all(paths::home().join(".cargo/registry/src")).unwrap();
+
+    p.cargo("fetch -Z downloads")
+        .masquerade_as_nightly_cargo()
+        .env("CARGO_NET_MAX_DOWNLOAD_RATE", "fast")
+        .with_status(101)
+        .with_stderr_contains(
+            "[ERROR] `net.max-download-rate` must be a number of bytes per second \
+             like `2MB`, found `fast`",
+        )
+        .run();
+    p.cargo("fetch -Z downloads")
+        .masquerade_as_nightly_cargo()
+        .env("CARGO_NET_MAX_CONCURRENT_DOWNLOADS", "0")
+        .with_status(101)
+        .with_stderr_contains("[ERROR] `net.max-concurrent-downloads` must be at least 1")
+        .run();
+
+    p.cargo("fetch -Z downloads")
+        .masquerade_as_nightly_cargo()
+        .env("CARGO_NET_MAX_DOWNLOAD_RATE", "10MB")
+        .env("CARGO_NET_MAX_CONCURRENT_DOWNLOADS", "1")
+        .with_stderr_contains("[DOWNLOADED] bar v1.0.0 (registry `dummy-registry`)")
+        .with_stderr_contains("[DOWNLOADED] baz v1.0.0 (registry `dummy-registry`)")
+        .run();
+}
@@ -0,0 +1,305 @@
+//! Tests for `cargo fix` with `-Z edition-migration`.
+
+use std::fs;
+
+use cargo_test_support::{basic_manifest, project};
+use filetime::FileTime;
+
+#[cargo_test]
+fn dry_run_requires_flag() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("fix --edition --dry-run")
+        .with_status(101)
+        .with_stderr("[ERROR] Usage of `--dry-run` requires `-Z edition-migration`")
+        .run();
+
+    p.cargo("fix --edition --json -Zedition-migration")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] `--json` can only be used with `--dry-run`")
+        .run();
+}
+
+#[cargo_test]
+fn workspace_summary_in_dependency_order() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["a", "b"]
+            "#,
+        )
+        .file("a/Cargo.toml", &basic_manifest("a", "0.1.0"))
+        .file(
+            "a/src/lib.rs",
+            r#"
+                mod foo {
+                    pub const FOO: u32 = 1;
+                }
+
+                pub fn a() -> u32 {
+                    ::foo::FOO
+                }
+            "#,
+        )
+        .file(
+            "b/Cargo.toml",
+            r#"
+                [package]
+                name = "b"
+                version = "0.1.0"
+
+                [dependencies]
+                renamed = { path = "../a", package = "a" }
+            "#,
+        )
+        .file(
+            "b/src/lib.rs",
+            r#"
+                extern crate renamed;
+
+                mod foo {
+                    pub fn f() -> u32 { 2 }
+                }
+
+                pub fn b() -> u32 {
+                    ::foo::f() + ::foo::f() + renamed::a()
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("fix --edition --workspace --allow-no-vcs -Zedition-migration")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "\
+[SUMMARY] applied 3 fixes to 2 files of 2 packages
+  a: 1 fix in 1 file
+  b: 2 fixes in 1 file
+",
+        )
+        .with_stdout("")
+        .run();
+
+    assert!(p.read_file("a/src/lib.rs").contains("crate::foo::FOO"));
+    assert!(p
+        .read_file("b/src/lib.rs")
+        .contains("crate::foo::f() + crate::foo::f() + renamed::a()"));
+}
+
+#[cargo_test]
+fn dry_run_json() {
+    let source = r#"
+        mod foo {
+            pub const FOO: u32 = 1;
+        }
+
+        pub fn foo() -> u32 {
+            ::foo::FOO
+        }
+    "#;
+    let p = project().file("src/lib.rs", source).build();
+
+    // The files aren't changed, so there is no need for a clean VCS. They
+    // are not even written to give them their code back.
+    let lib = p.root().join("src/lib.rs");
+    let mtime = FileTime::from_unix_time(1_000_000_000, 0);
+    filetime::set_file_mtime(&lib, mtime).unwrap();
+    p.cargo("fix --edition --dry-run --json -Zedition-migration")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[CHECKING] foo v0.0.1 ([..])
+[MIGRATING] src/lib.rs from 2015 edition to 2018
+[WOULD_FIX] src/lib.rs (1 fix)
+[FINISHED] [..]
+[SUMMARY] would apply 1 fix to 1 file of 1 package
+  foo: 1 fix in 1 file
+",
+        )
+        .with_json(
+            r#"
+                {
+                    "reason": "fix-preview",
+                    "package_id": "foo 0.0.1 [..]",
+                    "file": "src/lib.rs",
+                    "fixes": [
+                        {
+                            "message": "{...}",
+                            "line": 7,
+                            "column": 13,
+                            "original": "::foo::FOO",
+                            "replacement": "crate::foo::FOO"
+                        }
+                    ]
+                }
+            "#,
+        )
+        .run();
+
+    assert_eq!(p.read_file("src/lib.rs"), source);
+    let modified = FileTime::from_last_modification_time(&fs::metadata(&lib).unwrap());
+    assert_eq!(modified, mtime);
+}
+
+#[cargo_test]
+fn macro_warnings() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                edition = "2018"
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            r#"
+                pub trait Tr {}
+
+                macro_rules! make {
+                    ($t:ident) => {
+                        pub fn f(_x: &$t) {}
+                    };
+                }
+
+                make!(Tr);
+
+                pub fn g(_x: &Tr) {}
+            "#,
+        )
+        .build();
+
+    p.cargo("fix --edition --allow-no-vcs -Zedition-migration")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "\
+[NOTE] `src/lib.rs` has edition migration warnings in code generated by macros, \
+which `cargo fix` can't fix, they need to be fixed by hand:
+  * line 10, in `make!`: trait objects without an explicit `dyn` are deprecated
+",
+        )
+        .with_stderr_contains(
+            "  foo: 1 fix in 1 file, 1 warning in code generated by macros to fix by hand",
+        )
+        .run();
+
+    assert!(p
+        .read_file("src/lib.rs")
+        .contains("pub fn g(_x: &dyn Tr) {}"));
+}
+
+#[cargo_test]
+fn edition_readiness_requires_flag() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("check --edition-readiness")
+        .with_status(101)
+        .with_stderr("[ERROR] Usage of `--edition-readiness` requires `-Z edition-readiness`")
+        .run();
+}
+
+#[cargo_test]
+fn edition_readiness_report() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["a", "b", "c", "d"]
+            "#,
+        )
+        .file("a/Cargo.toml", &basic_manifest("a", "0.1.0"))
+        .file("a/src/lib.rs", "pub fn a() {}")
+        .file("b/Cargo.toml", &basic_manifest("b", "0.1.0"))
+        .file(
+            "b/src/lib.rs",
+            r#"
+                mod foo {
+                    pub const FOO: u32 = 1;
+                }
+
+                pub fn b() -> u32 {
+                    ::foo::FOO
+                }
+            "#,
+        )
+        .file("c/Cargo.toml", &basic_manifest("c", "0.1.0"))
+        .file("c/src/lib.rs", "pub fn c() -> u32 { \"not a number\" }")
+        .file(
+            "d/Cargo.toml",
+            r#"
+                [package]
+                name = "d"
+                version = "0.1.0"
+                edition = "2021"
+            "#,
+        )
+        .file("d/src/lib.rs", "")
+        .build();
+    let b = p.read_file("b/src/lib.rs");
+
+    // The files aren't changed, so there is no need for a clean VCS.
+    p.cargo("check --workspace --edition-readiness -Zedition-readiness")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+[READINESS] 2 of 4 packages are ready for the next edition
+  a (2015 -> 2018): ready
+  b (2015 -> 2018): 1 fix in 1 file to apply with `cargo fix --edition`
+  c (2015 -> 2018): doesn't build: could not compile `c`[..]
+  d (2021): on the latest edition
+[ERROR] 1 package didn't build, see the errors above
+",
+        )
+        .run();
+    assert_eq!(p.read_file("b/src/lib.rs"), b);
+
+    p.cargo("check -p a -p b -p d --edition-readiness -Zedition-readiness --message-format json")
+        .masquerade_as_nightly_cargo()
+        .with_json_contains_unordered(
+            r#"
+            {
+                "reason": "edition-readiness",
+                "package_id": "a 0.1.0 [..]",
+                "edition": "2015",
+                "next_edition": "2018",
+                "ready": true,
+                "fixes": 0,
+                "files": 0,
+                "manual": [],
+                "error": null
+            }
+
+            {
+                "reason": "edition-readiness",
+                "package_id": "b 0.1.0 [..]",
+                "edition": "2015",
+                "next_edition": "2018",
+                "ready": false,
+                "fixes": 1,
+                "files": 1,
+                "manual": [],
+                "error": null
+            }
+
+            {
+                "reason": "edition-readiness",
+                "package_id": "d 0.1.0 [..]",
+                "edition": "2021",
+                "next_edition": null,
+                "ready": true,
+                "fixes": 0,
+                "files": 0,
+                "manual": [],
+                "error": null
+            }
+        "#,
+        )
+        .run();
+}
@@ -0,0 +1,191 @@
+//! Tests for `cargo expand`.
+
+use cargo_test_support::{project, sleep_ms};
+
+#[cargo_test]
+fn expand_requires_z_flag() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [features]
+                extra = []
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            r#"
+                macro_rules! two {
+                    () => { 1 + 1 };
+                }
+
+                pub fn two() -> i32 { two!() }
+
+                #[cfg(feature = "extra")]
+                pub fn extra() {}
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    // Without the flag, it is left to an external `cargo-expand`.
+    p.cargo("expand --lib")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[ERROR] no such subcommand: `expand`")
+        .run();
+}
+
+#[cargo_test]
+fn expand_lib() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [features]
+                extra = []
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            r#"
+                macro_rules! two {
+                    () => { 1 + 1 };
+                }
+
+                pub fn two() -> i32 { two!() }
+
+                #[cfg(feature = "extra")]
+                pub fn extra() {}
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("-Zexpand expand --lib")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[CHECKING] foo v0.0.1 ([CWD])
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+",
+        )
+        .with_stdout_contains("pub fn two() -> i32 { 1 + 1 }")
+        .with_stdout_does_not_contain("[..]fn extra()[..]")
+        .run();
+
+    p.cargo("-Zexpand expand --lib --features extra")
+        .masquerade_as_nightly_cargo()
+        .with_stdout_contains("pub fn extra() {}")
+        .run();
+}
+
+#[cargo_test]
+fn expand_single_target() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [features]
+                extra = []
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            r#"
+                macro_rules! two {
+                    () => { 1 + 1 };
+                }
+
+                pub fn two() -> i32 { two!() }
+
+                #[cfg(feature = "extra")]
+                pub fn extra() {}
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("-Zexpand expand")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "[ERROR] extra arguments to `rustc` can only be passed to one target, [..]",
+        )
+        .run();
+
+    p.cargo("-Zexpand expand --bin foo")
+        .masquerade_as_nightly_cargo()
+        .with_stdout_contains("fn main() {}")
+        .run();
+}
+
+#[cargo_test]
+fn expansion_is_cached() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [features]
+                extra = []
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            r#"
+                macro_rules! two {
+                    () => { 1 + 1 };
+                }
+
+                pub fn two() -> i32 { two!() }
+
+                #[cfg(feature = "extra")]
+                pub fn extra() {}
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("-Zexpand expand --lib")
+        .masquerade_as_nightly_cargo()
+        .run();
+    let expansion = p
+        .root()
+        .join("target/expand")
+        .read_dir()
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.extension().unwrap() == "rs")
+        .unwrap();
+    let mtime = expansion.metadata().unwrap().modified().unwrap();
+
+    sleep_ms(1000);
+    p.cargo("-Zexpand expand --lib")
+        .masquerade_as_nightly_cargo()
+        .with_stdout_contains("pub fn two() -> i32 { 1 + 1 }")
+        .run();
+    assert_eq!(expansion.metadata().unwrap().modified().unwrap(), mtime);
+
+    p.change_file("src/lib.rs", "pub fn three() -> i32 { 3 }");
+    p.cargo("-Zexpand expand --lib")
+        .masquerade_as_nightly_cargo()
+        .with_stdout_contains("pub fn three() -> i32 { 3 }")
+        .run();
+    assert_ne!(expansion.metadata().unwrap().modified().unwrap(), mtime);
+}
@@ -0,0 +1,225 @@
+//! Tests for `cargo feature explain`.
+
+use cargo_test_support::project;
+use cargo_test_support::registry::{Dependency, Package};
+
+#[cargo_test]
+fn explain_requires_flag() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("feature explain foo/std")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] the `cargo feature` command is unstable, \
+             pass `-Z feature-explain` to enable it",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn command_line_and_defaults() {
+    Package::new("log", "1.0.0")
+        .feature("default", &["std"])
+        .feature("std", &[])
+        .publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                log = "1.0"
+
+                [features]
+                default = ["fast"]
+                fast = []
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("feature explain -Z feature-explain foo/fast --features fast")
+        .masquerade_as_nightly_cargo()
+        .with_stdout(
+            "\
+feature `fast` of `foo v0.1.0 ([..])` is activated by:
+  - the command line
+  - feature `default` of `foo v0.1.0 ([..])`
+note: features are unified between host and target dependencies, \
+see `resolver = \"2\"` to keep them separate
+",
+        )
+        .run();
+
+    p.cargo("feature explain -Z feature-explain log/std")
+        .masquerade_as_nightly_cargo()
+        .with_stdout(
+            "\
+feature `std` of `log v1.0.0` is activated by:
+  - feature `default` of `log v1.0.0`
+note: [..]
+",
+        )
+        .run();
+
+    p.cargo("feature explain -Z feature-explain log/default")
+        .masquerade_as_nightly_cargo()
+        .with_stdout(
+            "\
+feature `default` of `log v1.0.0` is activated by:
+  - the default features of the dependency in `foo v0.1.0 ([..])`
+note: [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn dependency_features() {
+    Package::new("log", "1.0.0").feature("std", &[]).publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                log = { version = "1.0", features = ["std"] }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("feature explain -Z feature-explain log/std")
+        .masquerade_as_nightly_cargo()
+        .with_stdout(
+            "\
+feature `std` of `log v1.0.0` is activated by:
+  - the dependency declaration in `foo v0.1.0 ([..])`
+note: [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn weak_dependency_feature() {
+    Package::new("log", "1.0.0").feature("std", &[]).publish();
+    Package::new("bar", "1.0.0")
+        .add_dep(Dependency::new("log", "1.0").optional(true))
+        .feature("std", &["log?/std"])
+        .feature("logging", &["log"])
+        .publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = { version = "1.0", features = ["std", "logging"] }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("feature explain -Z feature-explain -Z weak-dep-features log/std")
+        .masquerade_as_nightly_cargo()
+        .with_stdout(
+            "\
+feature `std` of `log v1.0.0` is activated by:
+  - feature `std` of `bar v1.0.0` (weak dependency feature)
+note: [..]
+",
+        )
+        .run();
+
+    // Without `logging`, `log` isn't a dependency, so the weak feature does nothing.
+    p.change_file(
+        "Cargo.toml",
+        r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+
+            [dependencies]
+            bar = { version = "1.0", features = ["std"] }
+        "#,
+    );
+    p.cargo("feature explain -Z feature-explain -Z weak-dep-features bar/logging")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("feature `logging` of `bar v1.0.0` is not activated")
+        .run();
+}
+
+#[cargo_test]
+fn host_and_target() {
+    Package::new("common", "1.0.0")
+        .feature("std", &[])
+        .feature("alloc", &[])
+        .publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                resolver = "2"
+
+                [dependencies]
+                common = { version = "1.0", features = ["alloc"] }
+
+                [build-dependencies]
+                common = { version = "1.0", features = ["std"] }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("build.rs", "fn main() {}")
+        .build();
+
+    p.cargo("feature explain -Z feature-explain common/std")
+        .masquerade_as_nightly_cargo()
+        .with_stdout(
+            "\
+feature `std` of `common v1.0.0` is activated for the host by:
+  - the dependency declaration in `foo v0.1.0 ([..])`
+",
+        )
+        .run();
+
+    p.cargo("feature explain -Z feature-explain common/alloc")
+        .masquerade_as_nightly_cargo()
+        .with_stdout(
+            "\
+feature `alloc` of `common v1.0.0` is activated for the target by:
+  - the dependency declaration in `foo v0.1.0 ([..])`
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn unknown_feature() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("feature explain -Z feature-explain foo/nope")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] package `foo v0.0.1 ([..])` does not have the feature `nope`")
+        .run();
+
+    p.cargo("feature explain -Z feature-explain foo")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] invalid feature `foo`, expected the form `<package>/<feature>`")
+        .run();
+}
@@ -0,0 +1,358 @@
+//! Tests for `cargo test --feature-matrix`.
+
+use cargo_test_support::{basic_manifest, project};
+
+#[cargo_test]
+fn feature_matrix_requires_flag() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [features]
+                default = ["a"]
+                a = []
+                b = ["a"]
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            r#"
+                #[test]
+                fn not_b() {
+                    assert!(!cfg!(feature = "b"));
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("test --feature-matrix")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] Usage of `--feature-matrix` requires `-Z feature-matrix`")
+        .run();
+}
+
+#[cargo_test]
+fn conflicts_with_feature_flags() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [features]
+                default = ["a"]
+                a = []
+                b = ["a"]
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            r#"
+                #[test]
+                fn not_b() {
+                    assert!(!cfg!(feature = "b"));
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("test -Z feature-matrix --feature-matrix --features b")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] --feature-matrix cannot be used together with \
+             --features, --all-features or --no-default-features",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn equivalent_combinations_are_skipped() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [features]
+                default = ["a"]
+                a = []
+                b = ["a"]
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            r#"
+                #[test]
+                fn not_b() {
+                    assert!(!cfg!(feature = "b"));
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("test -Z feature-matrix --feature-matrix --no-run")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[TESTING] foo with no features
+[COMPILING] foo v0.1.0 ([..])
+[FINISHED] [..]
+[TESTING] foo with features `a`
+[COMPILING] foo v0.1.0 ([..])
+[FINISHED] [..]
+[TESTING] foo with features `b`
+[COMPILING] foo v0.1.0 ([..])
+[FINISHED] [..]
+[SUMMARY] feature matrix ran 3 combinations, 0 failed
+[..]ok foo with no features
+[..]ok foo with features `a`
+[..]ok foo with features `b`
+",
+        )
+        .run();
+
+    // Every combination keeps its own artifacts.
+    p.cargo("test -Z feature-matrix --feature-matrix --no-run")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_does_not_contain("[COMPILING] [..]")
+        .run();
+}
+
+#[cargo_test]
+fn failures_are_summarized() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [features]
+                default = ["a"]
+                a = []
+                b = ["a"]
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            r#"
+                #[test]
+                fn not_b() {
+                    assert!(!cfg!(feature = "b"));
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("test -Z feature-matrix --feature-matrix --no-fail-fast")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[SUMMARY] feature matrix ran 3 combinations, 1 failed")
+        .with_stderr_contains("[..]ok foo with features `a`")
+        .with_stderr_contains("[..]FAILED foo with features `b`")
+        .with_stderr_contains("[ERROR] 1 of 3 feature combinations failed")
+        .run();
+}
+
+#[cargo_test]
+fn stops_at_first_failure() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [features]
+                default = ["a"]
+                a = []
+                b = ["a"]
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            r#"
+                #[test]
+                fn not_b() {
+                    assert!(!cfg!(feature = "b"));
+                }
+            "#,
+        )
+        .build();
+    p.change_file("src/lib.rs", "#[test] fn fails() { panic!() }");
+
+    p.cargo("test -Z feature-matrix --feature-matrix")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[SUMMARY] feature matrix ran 1 combination, 1 failed")
+        .with_stderr_does_not_contain("[TESTING] foo with features `a`")
+        .run();
+}
+
+#[cargo_test]
+fn default_depth() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [features]
+                a = []
+                b = []
+                c = []
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    // At most two features are varied at a time without a `depth`.
+    p.cargo("test -Z feature-matrix --feature-matrix --no-run")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[SUMMARY] feature matrix ran 7 combinations, 0 failed")
+        .with_stderr_contains("[..]ok foo with features `b`, `c`")
+        .with_stderr_does_not_contain("[..]`a`, `b`, `c`")
+        .run();
+}
+
+#[cargo_test]
+fn manifest_table() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["feature-matrix"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [features]
+                std = []
+                json = []
+                nightly = []
+                tls-native = []
+                tls-rustls = []
+
+                [feature-matrix]
+                always = ["std"]
+                skip = ["nightly"]
+                exclusive = [["tls-native", "tls-rustls"]]
+                depth = 2
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("test -Z feature-matrix --feature-matrix --no-run")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[SUMMARY] feature matrix ran 6 combinations, 0 failed")
+        .with_stderr_contains("[..]ok foo with features `std`")
+        .with_stderr_contains("[..]ok foo with features `std`, `json`, `tls-native`")
+        .with_stderr_contains("[..]ok foo with features `std`, `json`, `tls-rustls`")
+        .with_stderr_does_not_contain("[..]`tls-native`, `tls-rustls`")
+        .with_stderr_does_not_contain("[..]`nightly`[..]")
+        .run();
+}
+
+#[cargo_test]
+fn manifest_table_requires_cargo_feature() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [feature-matrix]
+                depth = 1
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("test -Z feature-matrix --feature-matrix")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("  feature `feature-matrix` is required")
+        .run();
+}
+
+#[cargo_test]
+fn unknown_feature_in_table() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["feature-matrix"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [feature-matrix]
+                skip = ["missing"]
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("test -Z feature-matrix --feature-matrix")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] feature `missing` listed in `[feature-matrix]` \
+             is not a feature of package `foo`",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn workspace_members() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["api", "web"]
+            "#,
+        )
+        .file(
+            "api/Cargo.toml",
+            r#"
+                [package]
+                name = "api"
+                version = "0.1.0"
+
+                [features]
+                extra = []
+            "#,
+        )
+        .file("api/src/lib.rs", "")
+        .file("web/Cargo.toml", &basic_manifest("web", "0.1.0"))
+        .file("web/src/lib.rs", "")
+        .build();
+
+    p.cargo("test -Z feature-matrix --feature-matrix --workspace --no-run")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[SUMMARY] feature matrix ran 3 combinations, 0 failed")
+        .with_stderr_contains("[..]ok api with no features")
+        .with_stderr_contains("[..]ok api with features `extra`")
+        .with_stderr_contains("[..]ok web with no features")
+        .run();
+}
@@ -0,0 +1,335 @@
+//! Tests for the `[features.metadata]` table.
+
+use cargo_test_support::{basic_manifest, project};
+
+#[cargo_test]
+fn metadata_requires_cargo_feature() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [features]
+                json = []
+
+                [features.metadata]
+                json = { description = "JSON support" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("  feature `feature-metadata` is required")
+        .run();
+}
+
+#[cargo_test]
+fn cli_conflict() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["feature-metadata"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [features]
+                json = []
+                tls-native = []
+                tls-rustls = []
+
+                [features.metadata]
+                json = { description = "JSON support", stability = "stable" }
+                tls-rustls = { conflicts = ["tls-native"], stability = "unstable" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check --features tls-native,tls-rustls")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] package `foo v0.1.0 ([..])` has conflicting features `tls-rustls` and `tls-native` enabled
+At most one of them may be enabled, as declared in the `[features.metadata]` table of `[..]Cargo.toml`.
+",
+        )
+        .run();
+
+    p.cargo("check --features tls-rustls,json")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[CHECKING] foo v0.1.0 ([..])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn dependency_conflict() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "app"
+                version = "0.1.0"
+
+                [dependencies]
+                foo = { path = "foo", features = ["tls-native"] }
+                other = { path = "other" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            "foo/Cargo.toml",
+            r#"
+                cargo-features = ["feature-metadata"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [features]
+                tls-native = []
+                tls-rustls = []
+
+                [features.metadata]
+                tls-rustls = { conflicts = ["tls-native"] }
+            "#,
+        )
+        .file("foo/src/lib.rs", "compile_error!(\"not reached\");")
+        .file(
+            "other/Cargo.toml",
+            r#"
+                [package]
+                name = "other"
+                version = "0.1.0"
+
+                [dependencies]
+                foo = { path = "../foo", features = ["tls-rustls"] }
+            "#,
+        )
+        .file("other/src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "[ERROR] package `foo v0.1.0 ([..])` has conflicting features \
+             `tls-rustls` and `tls-native` enabled",
+        )
+        .with_stderr_does_not_contain("[CHECKING] [..]")
+        .run();
+}
+
+#[cargo_test]
+fn surfaced_in_cargo_metadata() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["feature-metadata"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [features]
+                json = []
+                tls-native = []
+                tls-rustls = []
+
+                [features.metadata]
+                json = { description = "JSON support", stability = "stable" }
+                tls-rustls = { conflicts = ["tls-native"], stability = "unstable" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("metadata --no-deps --format-version 1")
+        .masquerade_as_nightly_cargo()
+        .with_stdout_contains(
+            "[..]\"feature_metadata\":{\
+             \"json\":{\"description\":\"JSON support\",\"stability\":\"stable\"},\
+             \"tls-rustls\":{\"stability\":\"unstable\",\"conflicts\":[\"tls-native\"]}\
+             }[..]",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn omitted_from_cargo_metadata_without_table() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("metadata --no-deps --format-version 1")
+        .with_stdout_does_not_contain("[..]feature_metadata[..]")
+        .run();
+}
+
+#[cargo_test]
+fn unknown_feature_described() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["feature-metadata"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [features]
+                json = []
+
+                [features.metadata]
+                yaml = { description = "YAML support" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  `[features.metadata]` describes `yaml`, which is not a feature of this package
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn unknown_conflict() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["feature-metadata"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [features]
+                json = []
+
+                [features.metadata]
+                json = { conflicts = ["yaml"] }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "  feature `json` conflicts with `yaml`, which is not a feature of this package",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn invalid_stability() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["feature-metadata"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [features]
+                json = []
+
+                [features.metadata]
+                json = { stability = "beta" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[..]invalid `features.metadata` table: unknown variant `beta`[..]")
+        .run();
+}
+
+#[cargo_test]
+fn feature_named_metadata() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [features]
+                metadata = []
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            "#[cfg(not(feature = \"metadata\"))] compile_error!(\"missing\");",
+        )
+        .build();
+
+    p.cargo("check --features metadata").run();
+}
+
+#[cargo_test]
+fn feature_matrix_skips_conflicts() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["feature-metadata"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [features]
+                json = []
+                tls-native = []
+                tls-rustls = []
+
+                [features.metadata]
+                json = { description = "JSON support", stability = "stable" }
+                tls-rustls = { conflicts = ["tls-native"], stability = "unstable" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("test -Z feature-matrix --feature-matrix --no-run")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[SUMMARY] feature matrix ran 6 combinations, 0 failed")
+        .with_stderr_does_not_contain("[..]`tls-native`, `tls-rustls`")
+        .run();
+}
@@ -0,0 +1,279 @@
+//! Tests for the `[feature-overrides]` table.
+
+use cargo_test_support::project;
+use cargo_test_support::registry::Package;
+
+/// Publishes `base`, whose lib only compiles when `expr` holds, and `mid`,
+/// which depends on `base` with its default features plus `fs`.
+fn publish_deps(expr: &str) {
+    Package::new("base", "1.0.0")
+        .feature("default", &["fs"])
+        .feature("fs", &[])
+        .feature("rt", &[])
+        .file(
+            "src/lib.rs",
+            &format!("#[cfg(not({}))] compile_error!(\"bad features\");", expr),
+        )
+        .publish();
+    Package::new("mid", "1.0.0")
+        .feature_dep("base", "1.0", &["fs"])
+        .publish();
+}
+
+#[cargo_test]
+fn overrides_require_cargo_feature() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [feature-overrides]
+                base = { default-features = false }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("  feature `feature-overrides` is required")
+        .run();
+}
+
+#[cargo_test]
+fn disable_transitive_feature() {
+    publish_deps("not(feature = \"fs\")");
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["feature-overrides"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                mid = "1.0"
+
+                [feature-overrides]
+                base = { disable = ["fs"] }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_unordered(
+            "\
+[UPDATING] [..]
+[WARNING] `[feature-overrides]` changes the features of `base`: feature `fs` disabled
+This may break packages that depend on `base`.
+[DOWNLOADING] crates ...
+[DOWNLOADED] base v1.0.0 ([..])
+[DOWNLOADED] mid v1.0.0 ([..])
+[CHECKING] base v1.0.0
+[CHECKING] mid v1.0.0
+[CHECKING] foo v0.1.0 ([..])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn disable_default_features() {
+    publish_deps("all(feature = \"fs\", not(feature = \"default\"))");
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["feature-overrides"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                mid = "1.0"
+
+                [feature-overrides]
+                base = { default-features = false }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "[WARNING] `[feature-overrides]` changes the features of `base`: \
+             default features disabled",
+        )
+        .with_stderr_contains("[CHECKING] base v1.0.0")
+        .run();
+}
+
+#[cargo_test]
+fn enable_transitive_feature() {
+    publish_deps("feature = \"rt\"");
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["feature-overrides"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                mid = "1.0"
+
+                [feature-overrides]
+                base = { enable = ["rt"] }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "[WARNING] `[feature-overrides]` changes the features of `base`: feature `rt` enabled",
+        )
+        .with_stderr_contains("[CHECKING] base v1.0.0")
+        .run();
+}
+
+#[cargo_test]
+fn without_overrides_features_are_unchanged() {
+    publish_deps("not(feature = \"fs\")");
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                mid = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .with_status(101)
+        .with_stderr_contains("[..]bad features[..]")
+        .run();
+}
+
+#[cargo_test]
+fn unmatched_override_warns() {
+    publish_deps("feature = \"fs\"");
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["feature-overrides"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                mid = "1.0"
+
+                [feature-overrides]
+                missing = { default-features = false }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "[WARNING] `[feature-overrides]` entry `missing` does not match \
+             any package in the dependency graph",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn enable_and_disable_same_feature() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["feature-overrides"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                mid = "1.0"
+
+                [feature-overrides]
+                base = { enable = ["fs"], disable = ["fs"] }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "  `[feature-overrides]` entry `base` both enables and disables feature `fs`",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn non_root_overrides_are_ignored() {
+    publish_deps("feature = \"fs\"");
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["member"]
+            "#,
+        )
+        .file(
+            "member/Cargo.toml",
+            r#"
+                cargo-features = ["feature-overrides"]
+
+                [package]
+                name = "member"
+                version = "0.1.0"
+
+                [dependencies]
+                mid = "1.0"
+
+                [feature-overrides]
+                base = { disable = ["fs"] }
+            "#,
+        )
+        .file("member/src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "[WARNING] feature-overrides for the non root package will be ignored, \
+             specify feature-overrides at the workspace root:",
+        )
+        .with_stderr_contains("[CHECKING] base v1.0.0")
+        .run();
+}
@@ -0,0 +1,124 @@
+//! Tests for `cargo fix -Z fix-backups`, `--undo` and `--vcs-snapshot`.
+
+use cargo_test_support::{git, project};
+
+const UNUSED_MUT: &str = r#"
+    pub fn foo() -> u32 {
+        let mut x = 3;
+        x
+    }
+"#;
+
+#[cargo_test]
+fn fix_backups_requires_flag() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("fix --undo")
+        .with_status(101)
+        .with_stderr("[ERROR] Usage of `--undo` requires `-Z fix-backups`")
+        .run();
+
+    p.cargo("fix --allow-no-vcs --vcs-snapshot")
+        .with_status(101)
+        .with_stderr("[ERROR] Usage of `--vcs-snapshot` requires `-Z fix-backups`")
+        .run();
+}
+
+#[cargo_test]
+fn undo_restores_original_code() {
+    let p = project().file("src/lib.rs", UNUSED_MUT).build();
+
+    p.cargo("fix --allow-no-vcs -Zfix-backups")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[FIXED] src/lib.rs (1 fix)")
+        .with_stderr_contains(
+            "[BACKED_UP] 1 file in `[..]/target/fix-backups/1`, \
+             undo the fixes with `cargo fix --undo`",
+        )
+        .run();
+    assert!(p.read_file("src/lib.rs").contains("let x = 3;"));
+    assert!(p.root().join("target/fix-backups/1/journal.json").is_file());
+
+    // Nothing left to fix, so there is nothing to back up.
+    p.cargo("fix --allow-no-vcs -Zfix-backups")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_does_not_contain("[BACKED_UP][..]")
+        .run();
+    assert!(!p.root().join("target/fix-backups/2").exists());
+
+    p.cargo("fix --undo -Zfix-backups")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[RESTORED] 1 file changed by `cargo fix`")
+        .run();
+    assert_eq!(p.read_file("src/lib.rs"), UNUSED_MUT);
+
+    p.cargo("fix --undo -Zfix-backups")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] no fixes to undo in `[..]/target/fix-backups`")
+        .run();
+}
+
+#[cargo_test]
+fn undo_refuses_changed_files() {
+    let p = project().file("src/lib.rs", UNUSED_MUT).build();
+
+    p.cargo("fix --allow-no-vcs -Zfix-backups")
+        .masquerade_as_nightly_cargo()
+        .run();
+    p.change_file("src/lib.rs", "pub fn foo() {}");
+
+    p.cargo("fix --undo -Zfix-backups")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] files fixed by `cargo fix` were changed since:
+  [..]/foo/src/lib.rs
+Their original code is in `[..]/target/fix-backups/1/journal.json`.
+",
+        )
+        .run();
+    assert_eq!(p.read_file("src/lib.rs"), "pub fn foo() {}");
+}
+
+#[cargo_test]
+fn vcs_snapshot_in_stash() {
+    let (p, mut repo) = git::new_repo("foo", |p| p.file("src/lib.rs", "pub fn foo() {}"));
+    p.change_file("src/lib.rs", UNUSED_MUT);
+
+    p.cargo("fix --allow-dirty -Zfix-backups --vcs-snapshot")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "[SAVED] the working tree in the git stash as [..], \
+             bring it back with `git stash apply`",
+        )
+        .with_stderr_contains("[BACKED_UP] 1 file in `[..]`, [..]")
+        .run();
+    assert!(p.read_file("src/lib.rs").contains("let x = 3;"));
+
+    let mut stashes = Vec::new();
+    repo.stash_foreach(|_, message, _| {
+        stashes.push(message.to_string());
+        true
+    })
+    .unwrap();
+    assert_eq!(stashes, ["On master: cargo fix backup 1"]);
+    // The stash has the code before fixing, and the index is untouched.
+    let stash = repo.refname_to_id("refs/stash").unwrap();
+    let tree = repo.find_commit(stash).unwrap().tree().unwrap();
+    let blob = tree.get_path("src/lib.rs".as_ref()).unwrap().id();
+    let code = repo.find_blob(blob).unwrap().content().to_vec();
+    assert_eq!(String::from_utf8(code).unwrap(), UNUSED_MUT);
+    let staged = repo.index().unwrap();
+    let entry = staged.get_path("src/lib.rs".as_ref(), 0).unwrap();
+    let blob = repo.find_blob(entry.id).unwrap();
+    assert_eq!(blob.content(), b"pub fn foo() {}");
+
+    p.cargo("fix --undo -Zfix-backups")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[RESTORED] 1 file changed by `cargo fix`")
+        .run();
+    assert_eq!(p.read_file("src/lib.rs"), UNUSED_MUT);
+    assert!(repo.refname_to_id("refs/stash").is_err());
+}
@@ -0,0 +1,363 @@
+//! Tests for `[[fuzz]]` targets and `cargo fuzz-run`.
+
+use cargo_test_support::{basic_lib_manifest, project, rustc_host};
+
+/// A stand-in for libFuzzer, defining the symbols the coverage
+/// instrumentation calls.
+const STUBS: &str = r#"
+    macro_rules! stubs {
+        ($($name:ident),*) => { $(#[no_mangle] pub extern "C" fn $name() {})* }
+    }
+    stubs!(
+        __sanitizer_cov_trace_pc_indir,
+        __sanitizer_cov_trace_cmp1,
+        __sanitizer_cov_trace_cmp2,
+        __sanitizer_cov_trace_cmp4,
+        __sanitizer_cov_trace_cmp8,
+        __sanitizer_cov_trace_const_cmp1,
+        __sanitizer_cov_trace_const_cmp2,
+        __sanitizer_cov_trace_const_cmp4,
+        __sanitizer_cov_trace_const_cmp8,
+        __sanitizer_cov_trace_switch,
+        __sanitizer_cov_8bit_counters_init,
+        __sanitizer_cov_pcs_init
+    );
+"#;
+
+const PRINT_ARGS: &str = r#"
+    use stubs as _;
+
+    fn main() {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        println!("args: {}", args.join(" "));
+        println!("parse: {}", foo::parse("ab"));
+    }
+"#;
+
+#[cargo_test]
+fn fuzz_targets_require_feature() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [[fuzz]]
+                name = "parse"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("fuzz/parse.rs", "fn main() {}")
+        .build();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  feature `fuzz-targets` is required
+
+  The package requires the Cargo feature called `fuzz-targets`, [..]
+  Consider adding `cargo-features = [\"fuzz-targets\"]` to the top of Cargo.toml [..]
+  See https://doc.rust-lang.org/nightly/cargo/reference/unstable.html#fuzz-targets \
+  for more information about the status of this feature.
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn fuzz_dir_is_ignored_without_feature() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file("fuzz/parse.rs", "this is not rust")
+        .build();
+
+    p.cargo("build --all-targets").run();
+}
+
+#[cargo_test]
+fn fuzz_targets_are_not_built_by_default() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["fuzz-targets"]
+
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                edition = "2018"
+
+                [dev-dependencies]
+                stubs = { path = "stubs" }
+            "#,
+        )
+        .file("build.rs", "fn main() {}")
+        .file(
+            "src/lib.rs",
+            r#"
+                pub fn parse(input: &str) -> bool {
+                    if cfg!(fuzzing) { input.len() > 1 } else { false }
+                }
+            "#,
+        )
+        .file("fuzz/parse.rs", "this is not rust")
+        .file("stubs/Cargo.toml", &basic_lib_manifest("stubs"))
+        .file("stubs/src/lib.rs", STUBS)
+        .build();
+
+    p.cargo("build --all-targets")
+        .masquerade_as_nightly_cargo()
+        .run();
+    p.cargo("test")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_does_not_contain("[..]fuzz/parse.rs[..]")
+        .run();
+}
+
+#[cargo_test]
+fn fuzz_target_metadata() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["fuzz-targets"]
+
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                edition = "2018"
+
+                [dev-dependencies]
+                stubs = { path = "stubs" }
+            "#,
+        )
+        .file("build.rs", "fn main() {}")
+        .file(
+            "src/lib.rs",
+            r#"
+                pub fn parse(input: &str) -> bool {
+                    if cfg!(fuzzing) { input.len() > 1 } else { false }
+                }
+            "#,
+        )
+        .file("fuzz/parse.rs", PRINT_ARGS)
+        .file("stubs/Cargo.toml", &basic_lib_manifest("stubs"))
+        .file("stubs/src/lib.rs", STUBS)
+        .build();
+
+    p.cargo("metadata --format-version=1 --no-deps")
+        .masquerade_as_nightly_cargo()
+        .with_stdout_contains(r#"[..]{"kind":["fuzz"],"crate_types":["bin"],"name":"parse","src_path":"[..]/fuzz/parse.rs",[..]"#)
+        .run();
+}
+
+#[cargo_test]
+fn fuzz_run() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["fuzz-targets"]
+
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                edition = "2018"
+
+                [dev-dependencies]
+                stubs = { path = "stubs" }
+            "#,
+        )
+        .file("build.rs", "fn main() {}")
+        .file(
+            "src/lib.rs",
+            r#"
+                pub fn parse(input: &str) -> bool {
+                    if cfg!(fuzzing) { input.len() > 1 } else { false }
+                }
+            "#,
+        )
+        .file("fuzz/parse.rs", PRINT_ARGS)
+        .file("stubs/Cargo.toml", &basic_lib_manifest("stubs"))
+        .file("stubs/src/lib.rs", STUBS)
+        .build();
+    let host = rustc_host();
+
+    p.cargo("fuzz-run parse -v -- -runs=10")
+        .masquerade_as_nightly_cargo()
+        .with_stdout(
+            "\
+args: -artifact_prefix=fuzz/artifacts/parse/ -runs=10 fuzz/corpus/parse
+parse: true
+",
+        )
+        .with_stderr_contains("[RUNNING] `rustc --crate-name build_script_build [..]`")
+        .with_stderr_line_without(
+            &["[RUNNING] `rustc --crate-name build_script_build"],
+            &["sancov"],
+        )
+        .with_stderr_contains(&format!(
+            "[RUNNING] `rustc --crate-name foo [..]src/lib.rs [..]-Cpasses=sancov-module \
+             [..]--cfg fuzzing -C debug-assertions=on [..]--target {} [..]`",
+            host
+        ))
+        .with_stderr_contains(
+            "[RUNNING] `rustc --crate-name parse [..]fuzz/parse.rs [..]-Cpasses=sancov-module [..]`",
+        )
+        .with_stderr_contains(&format!(
+            "[RUNNING] `[CWD]/target/{}/fuzz/parse -artifact_prefix=[..]`",
+            host
+        ))
+        .run();
+
+    assert!(p.root().join("fuzz/corpus/parse").is_dir());
+    assert!(p.root().join("fuzz/artifacts/parse").is_dir());
+}
+
+#[cargo_test]
+fn fuzz_run_corpus_option() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["fuzz-targets"]
+
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                edition = "2018"
+
+                [dev-dependencies]
+                stubs = { path = "stubs" }
+            "#,
+        )
+        .file("build.rs", "fn main() {}")
+        .file(
+            "src/lib.rs",
+            r#"
+                pub fn parse(input: &str) -> bool {
+                    if cfg!(fuzzing) { input.len() > 1 } else { false }
+                }
+            "#,
+        )
+        .file("fuzz/parse.rs", PRINT_ARGS)
+        .file("stubs/Cargo.toml", &basic_lib_manifest("stubs"))
+        .file("stubs/src/lib.rs", STUBS)
+        .build();
+
+    p.cargo("fuzz-run parse --corpus seeds")
+        .masquerade_as_nightly_cargo()
+        .with_stdout_contains("args: -artifact_prefix=fuzz/artifacts/parse/ seeds")
+        .run();
+
+    assert!(p.root().join("seeds").is_dir());
+    assert!(!p.root().join("fuzz/corpus").exists());
+}
+
+#[cargo_test]
+fn fuzz_run_forwards_exit_code() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["fuzz-targets"]
+
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                edition = "2018"
+
+                [dev-dependencies]
+                stubs = { path = "stubs" }
+            "#,
+        )
+        .file("build.rs", "fn main() {}")
+        .file(
+            "src/lib.rs",
+            r#"
+                pub fn parse(input: &str) -> bool {
+                    if cfg!(fuzzing) { input.len() > 1 } else { false }
+                }
+            "#,
+        )
+        .file(
+            "fuzz/parse.rs",
+            r#"
+                use stubs as _;
+
+                fn main() {
+                    eprintln!("==1== ERROR: libFuzzer: deadly signal");
+                    std::process::exit(77);
+                }
+            "#,
+        )
+        .file("stubs/Cargo.toml", &basic_lib_manifest("stubs"))
+        .file("stubs/src/lib.rs", STUBS)
+        .build();
+
+    p.cargo("fuzz-run parse")
+        .masquerade_as_nightly_cargo()
+        .with_status(77)
+        .with_stderr_contains("==1== ERROR: libFuzzer: deadly signal")
+        .run();
+}
+
+#[cargo_test]
+fn fuzz_run_unknown_target() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["fuzz-targets"]
+
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                edition = "2018"
+
+                [dev-dependencies]
+                stubs = { path = "stubs" }
+            "#,
+        )
+        .file("build.rs", "fn main() {}")
+        .file(
+            "src/lib.rs",
+            r#"
+                pub fn parse(input: &str) -> bool {
+                    if cfg!(fuzzing) { input.len() > 1 } else { false }
+                }
+            "#,
+        )
+        .file("fuzz/parse.rs", PRINT_ARGS)
+        .file("stubs/Cargo.toml", &basic_lib_manifest("stubs"))
+        .file("stubs/src/lib.rs", STUBS)
+        .build();
+
+    p.cargo("fuzz-run pars")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[ERROR] no fuzz target named `pars`[..]")
+        .run();
+}
+
+#[cargo_test]
+fn fuzz_run_requires_feature() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("fuzz-run parse")
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] `cargo fuzz-run` requires the `fuzz-targets` feature
+Consider adding `cargo-features = [\"fuzz-targets\"]` to the top of [..]Cargo.toml
+",
+        )
+        .run();
+}
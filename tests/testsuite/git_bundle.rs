@@ -0,0 +1,75 @@
+//! Tests for the `cargo git-bundle` command.
+
+use std::fs;
+
+use cargo_test_support::{basic_lib_manifest, git, paths, project};
+
+#[cargo_test]
+fn gated() {
+    let p = project().file("src/lib.rs", "").build();
+    p.cargo("git-bundle")
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] the `cargo git-bundle` command is unstable, pass `-Z git-bundle` to enable it",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn bundle_and_build_from_bundles() {
+    let bar = git::new("bar", |p| {
+        p.file("Cargo.toml", &basic_lib_manifest("bar"))
+            .file("src/lib.rs", "pub fn bar() {}")
+    });
+    let (baz, baz_repo) = git::new_repo("baz", |p| {
+        p.file("Cargo.toml", &basic_lib_manifest("baz"))
+            .file("src/lib.rs", "pub fn baz() {}")
+    });
+    git::tag(&baz_repo, "v1");
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    [package]
+                    name = "foo"
+                    version = "0.1.0"
+
+                    [dependencies]
+                    bar = {{ git = "{}" }}
+                    baz = {{ git = "{}", tag = "v1" }}
+                "#,
+                bar.url(),
+                baz.url()
+            ),
+        )
+        .file("src/lib.rs", "pub fn foo() { bar::bar(); baz::baz(); }")
+        .build();
+
+    let output = p
+        .cargo("git-bundle -Zgit-bundle")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[BUNDLING] [..]/bar to [..]/foo/git-bundles/bar-[..].bundle")
+        .with_stderr_contains("[BUNDLING] [..]/baz to [..]/foo/git-bundles/baz-[..].bundle")
+        .with_stderr_contains(
+            "To use the git bundles, add this to your .cargo/config.toml for this project:",
+        )
+        .exec_with_output()
+        .unwrap();
+    let bundle_config = String::from_utf8(output.stdout).unwrap();
+    assert!(bundle_config.contains(&format!("[source.\"{}?tag=v1\"]", baz.url())));
+    assert!(bundle_config.contains("replace-with = \"git-bundle-baz-"));
+    assert!(bundle_config.contains("tag = \"v1\""));
+
+    // The dependencies are fetched from the bundles, without the original
+    // repositories.
+    p.change_file(".cargo/config", &bundle_config);
+    fs::remove_dir_all(bar.root()).unwrap();
+    fs::remove_dir_all(baz.root()).unwrap();
+    fs::remove_dir_all(paths::home().join(".cargo/git")).unwrap();
+    p.cargo("build")
+        .with_stderr_contains("[UPDATING] git repository `file://[..]/git-bundles/bar-[..].bundle`")
+        .with_stderr_contains("[UPDATING] git repository `file://[..]/git-bundles/baz-[..].bundle`")
+        .with_stderr_contains("[FINISHED] [..]")
+        .run();
+}
@@ -0,0 +1,266 @@
+//! Tests for the `[headers]` table of the `c-headers` feature.
+
+use std::path::PathBuf;
+
+use cargo_test_support::paths::CargoPathExt;
+use cargo_test_support::{basic_bin_manifest, project, sleep_ms};
+
+/// Builds `gen-header`, which writes the crate name and its arguments to
+/// `CARGO_HEADER_OUTPUT`, or fails with the argument `fail`.
+fn generator() -> PathBuf {
+    let p = project()
+        .at("gen-header")
+        .file("Cargo.toml", &basic_bin_manifest("gen-header"))
+        .file(
+            "src/main.rs",
+            r#"
+                use std::env;
+
+                fn main() {
+                    let args: Vec<String> = env::args().skip(1).collect();
+                    if args.iter().any(|arg| arg == "fail") {
+                        eprintln!("generator failed");
+                        std::process::exit(1);
+                    }
+                    let header = format!(
+                        "/* {} {} */\n",
+                        env::var("CARGO_CRATE_NAME").unwrap(),
+                        args.join(" ")
+                    );
+                    std::fs::write(env::var("CARGO_HEADER_OUTPUT").unwrap(), header).unwrap();
+                }
+            "#,
+        )
+        .build();
+    p.cargo("build").run();
+    p.bin("gen-header")
+}
+
+fn generator_keys(extra: &str) -> String {
+    format!(
+        "generator = '{}'\n{}",
+        generator().display().to_string().replace('\\', "/"),
+        extra
+    )
+}
+
+#[cargo_test]
+fn feature_required() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [headers]
+                name = "foo.h"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("  feature `c-headers` is required")
+        .run();
+}
+
+#[cargo_test]
+fn invalid_name() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["c-headers"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [lib]
+                crate-type = ["cdylib"]
+
+                [headers]
+                name = "include/foo.h"
+            "#,
+        )
+        .file("src/lib.rs", "#[no_mangle] pub extern \"C\" fn foo() {}")
+        .build();
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("  `headers.name` must be a file name, found `include/foo.h`")
+        .run();
+}
+
+#[cargo_test]
+fn generated_and_tracked() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    cargo-features = ["c-headers"]
+
+                    [package]
+                    name = "foo"
+                    version = "0.1.0"
+
+                    [lib]
+                    crate-type = ["cdylib"]
+
+                    [headers]
+                    {}
+                "#,
+                generator_keys(r#"args = ["--lang", "c"]"#)
+            ),
+        )
+        .file("src/lib.rs", "#[no_mangle] pub extern \"C\" fn foo() {}")
+        .build();
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.1.0 ([CWD])
+[FINISHED] [..]
+[..]Generating foo.h for foo v0.1.0 ([CWD])
+",
+        )
+        .run();
+    assert_eq!(
+        p.read_file("target/debug/include/foo.h"),
+        "/* foo --lang c */\n"
+    );
+
+    // Nothing changed.
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[FINISHED] [..]")
+        .run();
+
+    // The library is rebuilt.
+    sleep_ms(1000);
+    p.change_file("src/lib.rs", "#[no_mangle] pub extern \"C\" fn bar() {}");
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[..]Generating foo.h for foo v0.1.0 ([CWD])")
+        .run();
+
+    // The header was removed.
+    p.root().join("target/debug/include/foo.h").rm_rf();
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[..]Generating foo.h for foo v0.1.0 ([CWD])")
+        .run();
+}
+
+#[cargo_test]
+fn staticlib_with_name_and_out_dir() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    cargo-features = ["c-headers"]
+
+                    [package]
+                    name = "foo"
+                    version = "0.1.0"
+
+                    [lib]
+                    crate-type = ["staticlib"]
+
+                    [headers]
+                    {}
+                "#,
+                generator_keys(r#"name = "bindings.h""#)
+            ),
+        )
+        .file("src/lib.rs", "#[no_mangle] pub extern \"C\" fn foo() {}")
+        .build();
+    p.cargo("build -Z unstable-options --out-dir out")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[..]Generating bindings.h for foo v0.1.0 ([CWD])")
+        .run();
+    assert_eq!(
+        p.read_file("target/debug/include/bindings.h"),
+        "/* foo  */\n"
+    );
+    assert_eq!(p.read_file("out/bindings.h"), "/* foo  */\n");
+}
+
+#[cargo_test]
+fn only_for_cdylib_and_staticlib() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    cargo-features = ["c-headers"]
+
+                    [package]
+                    name = "foo"
+                    version = "0.1.0"
+
+                    [lib]
+                    crate-type = ["rlib"]
+
+                    [headers]
+                    {}
+                "#,
+                generator_keys("")
+            ),
+        )
+        .file("src/lib.rs", "#[no_mangle] pub extern \"C\" fn foo() {}")
+        .build();
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_does_not_contain("[..]Generating [..]")
+        .run();
+    assert!(!p.root().join("target/debug/include").exists());
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_does_not_contain("[..]Generating [..]")
+        .run();
+
+    // The crate types may be overridden for one build.
+    p.cargo("rustc --lib --crate-type cdylib -Z unstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[..]Generating foo.h for foo v0.1.0 ([CWD])")
+        .run();
+    assert!(p.root().join("target/debug/include/foo.h").is_file());
+}
+
+#[cargo_test]
+fn generator_fails() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    cargo-features = ["c-headers"]
+
+                    [package]
+                    name = "foo"
+                    version = "0.1.0"
+
+                    [lib]
+                    crate-type = ["cdylib"]
+
+                    [headers]
+                    {}
+                "#,
+                generator_keys(r#"args = ["fail"]"#)
+            ),
+        )
+        .file("src/lib.rs", "#[no_mangle] pub extern \"C\" fn foo() {}")
+        .build();
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[ERROR] failed to generate the header `foo.h` of `foo`")
+        .with_stderr_contains("  generator failed")
+        .run();
+}
@@ -0,0 +1,255 @@
+//! Tests for the HTTP settings of `-Z http-transport`.
+
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::thread;
+
+use cargo_test_support::registry::{self, Package};
+use cargo_test_support::{git, project};
+
+/// A request received by the server, with the index of its connection.
+type Request = (usize, Vec<String>);
+
+/// Serves the `.crate` files of the registry over HTTP/1.1, keeping the
+/// connections alive, until `count` requests are answered, and points the
+/// registry at it. With `proxy_auth`, it acts as a proxy requiring the basic
+/// authentication.
+fn serve(count: usize, proxy_auth: bool) -> (SocketAddr, thread::JoinHandle<Vec<Request>>) {
+    let server = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = server.local_addr().unwrap();
+    let repo = git2::Repository::open(registry::registry_path()).unwrap();
+    fs::write(
+        registry::registry_path().join("config.json"),
+        format!(
+            r#"{{"dl": "http://{}/{{crate}}/{{version}}/download", "api": "{}"}}"#,
+            addr,
+            registry::api_url()
+        ),
+    )
+    .unwrap();
+    git::add(&repo);
+    git::commit(&repo);
+
+    let dl_path = registry::dl_path();
+    let handle = thread::spawn(move || {
+        let mut requests = Vec::new();
+        for (index, conn) in server.incoming().enumerate() {
+            let mut conn = BufReader::new(conn.unwrap());
+            loop {
+                let headers: Vec<String> = (&mut conn)
+                    .lines()
+                    .map(|line| line.unwrap())
+                    .take_while(|line| !line.is_empty())
+                    .collect();
+                if headers.is_empty() {
+                    break;
+                }
+                let authorized = headers
+                    .iter()
+                    .any(|h| h.starts_with("Proxy-Authorization: Basic "));
+                let stream = conn.get_mut();
+                if proxy_auth && !authorized {
+                    write!(
+                        stream,
+                        "HTTP/1.1 407 Proxy Authentication Required\r\n\
+                         Proxy-Authenticate: Basic realm=\"cargo\"\r\n\
+                         Content-Length: 0\r\n\r\n"
+                    )
+                    .unwrap();
+                } else {
+                    // The target of a request to a proxy is the whole URL.
+                    let target = headers[0].split(' ').nth(1).unwrap();
+                    let path = match target.strip_prefix("http://") {
+                        Some(url) => &url[url.find('/').unwrap()..],
+                        None => target,
+                    };
+                    let data = fs::read(dl_path.join(&path[1..])).unwrap();
+                    write!(
+                        stream,
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n",
+                        data.len()
+                    )
+                    .unwrap();
+                    stream.write_all(&data).unwrap();
+                }
+                requests.push((index, headers));
+                if requests.len() == count {
+                    return requests;
+                }
+            }
+        }
+        unreachable!()
+    });
+    (addr, handle)
+}
+
+#[cargo_test]
+fn gated() {
+    Package::new("bar", "1.0.0").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            r#"
+                [http]
+                version = "1.1"
+            "#,
+        )
+        .build();
+    p.cargo("fetch")
+        .with_status(101)
+        .with_stderr_contains("[..]`http.version` requires `-Z http-transport`")
+        .run();
+}
+
+#[cargo_test]
+fn invalid_settings() {
+    Package::new("bar", "1.0.0").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            r#"
+                [http]
+                version = "4"
+            "#,
+        )
+        .build();
+    p.cargo("fetch -Zhttp-transport")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[..]invalid `http.version` `4`, choose from '1.1', '2', '3'")
+        .run();
+
+    p.change_file(
+        ".cargo/config",
+        r#"
+            [http]
+            proxy-auth = ["basic", "kerberos"]
+        "#,
+    );
+    p.cargo("fetch -Zhttp-transport")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "[..]invalid `http.proxy-auth` method `kerberos`, \
+             choose from 'basic', 'digest', 'negotiate', 'ntlm'",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn share_connections() {
+    Package::new("bar", "1.0.0").publish();
+    Package::new("baz", "1.0.0").publish();
+    let (_, server) = serve(2, false);
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                    [package]
+                    name = "foo"
+                    version = "0.1.0"
+
+                    [dependencies]
+
+                bar = "1.0"
+                baz = "1.0"
+
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            r#"
+                [http]
+                version = "1.1"
+                share-connections = true
+
+                [net]
+                max-concurrent-downloads = 1
+            "#,
+        )
+        .build();
+    p.cargo("fetch -Zhttp-transport -Zdownloads")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[DOWNLOADED] bar v1.0.0 (registry `dummy-registry`)")
+        .with_stderr_contains("[DOWNLOADED] baz v1.0.0 (registry `dummy-registry`)")
+        .run();
+
+    // Both crates are downloaded over the first connection.
+    let requests = server.join().unwrap();
+    assert!(requests.iter().all(|(index, _)| *index == 0));
+    assert!(requests
+        .iter()
+        .all(|(_, headers)| headers[0].ends_with(" HTTP/1.1")));
+}
+
+#[cargo_test]
+fn proxy_auth() {
+    Package::new("bar", "1.0.0").publish();
+    let (addr, server) = serve(2, true);
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            &format!(
+                r#"
+                    [http]
+                    proxy = "http://user:pass@{}"
+                    proxy-auth = ["digest", "basic"]
+                "#,
+                addr
+            ),
+        )
+        .build();
+    p.cargo("fetch -Zhttp-transport")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[DOWNLOADED] bar v1.0.0 (registry `dummy-registry`)")
+        .run();
+
+    // With several methods, curl waits for the proxy to pick one.
+    let requests = server.join().unwrap();
+    assert!(!requests[0]
+        .1
+        .iter()
+        .any(|h| h.starts_with("Proxy-Authorization:")));
+    assert!(requests[1]
+        .1
+        .iter()
+        .any(|h| h == "Proxy-Authorization: Basic dXNlcjpwYXNz"));
+}
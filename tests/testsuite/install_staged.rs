@@ -0,0 +1,117 @@
+//! Tests for the staged installs of `cargo install --destdir`.
+
+use cargo_test_support::cargo_process;
+use cargo_test_support::install::{assert_has_not_installed_exe, cargo_home, exe};
+use cargo_test_support::paths;
+use cargo_test_support::registry::Package;
+
+fn pkg(name: &str, vers: &str) {
+    Package::new(name, vers)
+        .file("src/main.rs", "fn main() {}")
+        .publish();
+}
+
+#[cargo_test]
+fn gated() {
+    pkg("foo", "0.0.1");
+    cargo_process("install foo --destdir stage")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] Usage of `--destdir` requires `-Z staged-install`")
+        .run();
+    cargo_process("install foo --prefix /usr")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] Usage of `--prefix` requires `-Z staged-install`")
+        .run();
+}
+
+#[cargo_test]
+fn staged_into_destdir() {
+    pkg("foo", "0.0.1");
+    cargo_process(
+        "install foo -Zstaged-install --destdir stage --prefix /usr \
+         --install-manifest files.txt",
+    )
+    .masquerade_as_nightly_cargo()
+    .with_stderr(
+        "\
+[UPDATING] `[..]` index
+[DOWNLOADING] crates ...
+[DOWNLOADED] foo v0.0.1 (registry [..])
+[INSTALLING] foo v0.0.1
+[COMPILING] foo v0.0.1
+[FINISHED] release [optimized] target(s) in [..]
+[INSTALLING] [CWD]/stage/usr/bin/foo[EXE]
+[INSTALLED] package `foo v0.0.1` (executable `foo[EXE]`)
+",
+    )
+    .run();
+
+    let stage = paths::root().join("stage/usr");
+    assert!(stage.join("bin").join(exe("foo")).is_file());
+    // Staged installs aren't tracked.
+    assert!(!stage.join(".crates.toml").exists());
+    assert!(!stage.join(".crates2.json").exists());
+    assert!(!cargo_home().join(".crates.toml").exists());
+    assert_has_not_installed_exe(cargo_home(), "foo");
+
+    assert_eq!(
+        std::fs::read_to_string(paths::root().join("files.txt")).unwrap(),
+        format!("/usr/bin/{}\n", exe("foo"))
+    );
+}
+
+#[cargo_test]
+fn destdir_from_config() {
+    pkg("foo", "0.0.1");
+    pkg("bar", "0.0.2");
+    cargo_process("install foo bar -Zstaged-install --install-manifest files.txt")
+        .masquerade_as_nightly_cargo()
+        .env("CARGO_INSTALL_DESTDIR", paths::root().join("stage"))
+        .run();
+
+    // The installation root is the default one, staged.
+    let home = cargo_home();
+    let relative = home.strip_prefix("/").unwrap_or(&home);
+    let staged_bin = paths::root().join("stage").join(relative).join("bin");
+    assert!(staged_bin.join(exe("foo")).is_file());
+    assert!(staged_bin.join(exe("bar")).is_file());
+    assert_has_not_installed_exe(cargo_home(), "foo");
+
+    assert_eq!(
+        std::fs::read_to_string(paths::root().join("files.txt")).unwrap(),
+        format!(
+            "{}\n{}\n",
+            home.join("bin").join(exe("bar")).display(),
+            home.join("bin").join(exe("foo")).display()
+        )
+    );
+}
+
+#[cargo_test]
+fn manifest_without_destdir() {
+    pkg("foo", "0.0.1");
+    cargo_process("install foo -Zstaged-install --install-manifest out/files.txt")
+        .masquerade_as_nightly_cargo()
+        .run();
+    assert_eq!(
+        std::fs::read_to_string(paths::root().join("out/files.txt")).unwrap(),
+        format!("{}\n", cargo_home().join("bin").join(exe("foo")).display())
+    );
+    // Without a staging directory, the install is tracked as usual.
+    assert!(cargo_home().join(".crates.toml").is_file());
+}
+
+#[cargo_test]
+fn relative_prefix() {
+    pkg("foo", "0.0.1");
+    cargo_process("install foo -Zstaged-install --destdir stage --prefix usr")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] the installation root `usr` must be an absolute path \
+             to be staged into `[CWD]/stage`",
+        )
+        .run();
+}
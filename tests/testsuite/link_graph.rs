@@ -0,0 +1,184 @@
+//! Tests for `-Z link-graph` and `cargo report link-graph`.
+
+use cargo_test_support::{basic_manifest, project};
+
+#[cargo_test]
+fn link_graph_requires_z_flag() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [dependencies]
+                bar = { path = "bar" }
+            "#,
+        )
+        .file("src/main.rs", "fn main() { bar::bar() }")
+        .file(
+            "bar/Cargo.toml",
+            r#"
+                [package]
+                name = "bar"
+                version = "0.0.1"
+                links = "native"
+            "#,
+        )
+        .file("bar/src/lib.rs", "pub fn bar() {}")
+        .file(
+            "bar/build.rs",
+            r#"
+                fn main() {
+                    println!("cargo:rustc-link-search=native=/nonexistent");
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("build").run();
+    p.cargo("report link-graph")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] no link graph found in `[CWD]/target/debug`, \
+             build with `-Z link-graph` to record it",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn link_graph_native_libs() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [dependencies]
+                bar = { path = "bar" }
+            "#,
+        )
+        .file("src/main.rs", "fn main() { bar::bar() }")
+        .file(
+            "bar/Cargo.toml",
+            r#"
+                [package]
+                name = "bar"
+                version = "0.0.1"
+                links = "native"
+            "#,
+        )
+        .file("bar/src/lib.rs", "pub fn bar() {}")
+        .file(
+            "bar/build.rs",
+            r#"
+                fn main() {
+                    println!("cargo:rustc-link-search=native=/nonexistent");
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("build -Zlink-graph")
+        .masquerade_as_nightly_cargo()
+        .run();
+    p.cargo("report link-graph")
+        .masquerade_as_nightly_cargo()
+        .with_json(
+            r#"
+                {
+                    "target": "[..]",
+                    "profile": "dev",
+                    "artifacts": [
+                        {
+                            "package": "foo 0.0.1 ([..])",
+                            "name": "foo",
+                            "kind": "bin",
+                            "test": false,
+                            "path": "[CWD]/target/debug/foo[EXE]",
+                            "crates": [
+                                {
+                                    "package": "bar 0.0.1 ([..])",
+                                    "name": "bar",
+                                    "path": "[CWD]/target/debug/deps/libbar-[..].rlib"
+                                }
+                            ],
+                            "native_libs": [
+                                {
+                                    "package": "bar 0.0.1 ([..])",
+                                    "links": "native",
+                                    "libs": [],
+                                    "search_paths": ["native=/nonexistent"]
+                                }
+                            ]
+                        }
+                    ]
+                }
+            "#,
+        )
+        .run();
+}
+
+#[cargo_test]
+fn link_graph_keeps_other_artifacts() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.0.1"))
+        .file("src/lib.rs", "")
+        .file("src/bin/a.rs", "fn main() {}")
+        .file("src/bin/b.rs", "fn main() {}")
+        .build();
+
+    p.cargo("build -Zlink-graph --bin a")
+        .masquerade_as_nightly_cargo()
+        .run();
+    p.cargo("build -Zlink-graph --bin b")
+        .masquerade_as_nightly_cargo()
+        .run();
+    p.cargo("report link-graph")
+        .masquerade_as_nightly_cargo()
+        .with_json(
+            r#"
+                {
+                    "target": "[..]",
+                    "profile": "dev",
+                    "artifacts": [
+                        {
+                            "package": "foo 0.0.1 ([..])",
+                            "name": "a",
+                            "kind": "bin",
+                            "test": false,
+                            "path": "[CWD]/target/debug/a[EXE]",
+                            "crates": [
+                                {
+                                    "package": "foo 0.0.1 ([..])",
+                                    "name": "foo",
+                                    "path": "[CWD]/target/debug/deps/libfoo-[..].rlib"
+                                }
+                            ],
+                            "native_libs": []
+                        },
+                        {
+                            "package": "foo 0.0.1 ([..])",
+                            "name": "b",
+                            "kind": "bin",
+                            "test": false,
+                            "path": "[CWD]/target/debug/b[EXE]",
+                            "crates": [
+                                {
+                                    "package": "foo 0.0.1 ([..])",
+                                    "name": "foo",
+                                    "path": "[CWD]/target/debug/deps/libfoo-[..].rlib"
+                                }
+                            ],
+                            "native_libs": []
+                        }
+                    ]
+                }
+            "#,
+        )
+        .run();
+}
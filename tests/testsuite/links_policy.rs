@@ -0,0 +1,458 @@
+//! Tests for the `[workspace.links]` table.
+
+use cargo_test_support::{project, Project};
+
+#[cargo_test]
+fn links_policy_requires_feature() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [workspace.links.z]
+                packages = ["a-sys", "b-sys"]
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("  feature `links-policy` is required")
+        .run();
+}
+
+#[cargo_test]
+fn links_policy_coexist() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["links-policy"]
+
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [dependencies]
+                a-sys = { path = "a-sys" }
+                b-sys = { path = "b-sys" }
+
+                [workspace.links.z]
+                packages = ["a-sys", "b-sys"]
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .file(
+            "a-sys/Cargo.toml",
+            r#"
+                [package]
+                name = "a-sys"
+                version = "0.0.1"
+                links = "z"
+            "#,
+        )
+        .file("a-sys/src/lib.rs", "")
+        .file(
+            "a-sys/build.rs",
+            r#"fn main() { println!("cargo:rustc-link-search=native=/a-sys-libs"); }"#,
+        )
+        .file(
+            "b-sys/Cargo.toml",
+            r#"
+                [package]
+                name = "b-sys"
+                version = "0.0.1"
+                links = "z"
+            "#,
+        )
+        .file("b-sys/src/lib.rs", "")
+        .file(
+            "b-sys/build.rs",
+            r#"fn main() { println!("cargo:rustc-link-search=native=/b-sys-libs"); }"#,
+        )
+        .build();
+
+    p.cargo("build -v")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "\
+[WARNING] packages `a-sys v0.0.1 ([..])`, `b-sys v0.0.1 ([..])` link to native library `z`, \
+as allowed by `workspace.links.z`
+the native libraries of all of them are linked, once checked not to define the same symbols",
+        )
+        .with_stderr_contains(
+            "[RUNNING] `rustc --crate-name foo [..]-L native=/a-sys-libs -L native=/b-sys-libs`",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn links_policy_winner() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["links-policy"]
+
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [dependencies]
+                a-sys = { path = "a-sys" }
+                b-sys = { path = "b-sys" }
+
+                [workspace.links.z]
+                packages = ["a-sys", "b-sys"]
+                winner = "a-sys"
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .file(
+            "a-sys/Cargo.toml",
+            r#"
+                [package]
+                name = "a-sys"
+                version = "0.0.1"
+                links = "z"
+            "#,
+        )
+        .file("a-sys/src/lib.rs", "")
+        .file(
+            "a-sys/build.rs",
+            r#"fn main() { println!("cargo:rustc-link-search=native=/a-sys-libs"); }"#,
+        )
+        .file(
+            "b-sys/Cargo.toml",
+            r#"
+                [package]
+                name = "b-sys"
+                version = "0.0.1"
+                links = "z"
+            "#,
+        )
+        .file("b-sys/src/lib.rs", "")
+        .file(
+            "b-sys/build.rs",
+            r#"fn main() { println!("cargo:rustc-link-search=native=/b-sys-libs"); }"#,
+        )
+        .build();
+
+    p.cargo("build -v")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "\
+[WARNING] packages `a-sys v0.0.1 ([..])`, `b-sys v0.0.1 ([..])` link to native library `z`, \
+as allowed by `workspace.links.z`
+only the native libraries of `a-sys` are linked, the build scripts of the others can't link anything",
+        )
+        .with_stderr_contains("[RUNNING] `rustc --crate-name foo [..]-L native=/a-sys-libs`")
+        .with_stderr_does_not_contain("[..]/b-sys-libs[..]")
+        .run();
+
+    // Without a winner, the other package links its libraries again.
+    p.change_file(
+        "Cargo.toml",
+        &p.read_file("Cargo.toml").replace("winner = \"a-sys\"", ""),
+    );
+    p.cargo("build -v")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "[RUNNING] `rustc --crate-name foo [..]-L native=/a-sys-libs -L native=/b-sys-libs`",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn links_policy_unlisted_package() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["links-policy"]
+
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [dependencies]
+                a-sys = { path = "a-sys" }
+                b-sys = { path = "b-sys" }
+
+                [workspace.links.z]
+                packages = ["a-sys"]
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .file(
+            "a-sys/Cargo.toml",
+            r#"
+                [package]
+                name = "a-sys"
+                version = "0.0.1"
+                links = "z"
+            "#,
+        )
+        .file("a-sys/src/lib.rs", "")
+        .file(
+            "a-sys/build.rs",
+            r#"fn main() { println!("cargo:rustc-link-search=native=/a-sys-libs"); }"#,
+        )
+        .file(
+            "b-sys/Cargo.toml",
+            r#"
+                [package]
+                name = "b-sys"
+                version = "0.0.1"
+                links = "z"
+            "#,
+        )
+        .file("b-sys/src/lib.rs", "")
+        .file(
+            "b-sys/build.rs",
+            r#"fn main() { println!("cargo:rustc-link-search=native=/b-sys-libs"); }"#,
+        )
+        .build();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "[ERROR] Attempting to resolve a dependency with more than one crate with links=z.",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn links_policy_invalid_winner() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["links-policy"]
+
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [dependencies]
+                a-sys = { path = "a-sys" }
+                b-sys = { path = "b-sys" }
+
+                [workspace.links.z]
+                packages = ["a-sys", "b-sys"]
+                winner = "c-sys"
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .file(
+            "a-sys/Cargo.toml",
+            r#"
+                [package]
+                name = "a-sys"
+                version = "0.0.1"
+                links = "z"
+            "#,
+        )
+        .file("a-sys/src/lib.rs", "")
+        .file(
+            "a-sys/build.rs",
+            r#"fn main() { println!("cargo:rustc-link-search=native=/a-sys-libs"); }"#,
+        )
+        .file(
+            "b-sys/Cargo.toml",
+            r#"
+                [package]
+                name = "b-sys"
+                version = "0.0.1"
+                links = "z"
+            "#,
+        )
+        .file("b-sys/src/lib.rs", "")
+        .file(
+            "b-sys/build.rs",
+            r#"fn main() { println!("cargo:rustc-link-search=native=/b-sys-libs"); }"#,
+        )
+        .build();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  `workspace.links.z.winner` is `c-sys`, which is not one of its `packages`
+",
+        )
+        .run();
+}
+
+/// A project whose `a-sys` and `b-sys` dependencies share `links = "z"`, and
+/// whose build scripts build a static library defining the given symbols.
+fn symbols_project(a_symbols: &[&str], b_symbols: &[&str]) -> Project {
+    project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["links-policy"]
+
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [dependencies]
+                a-sys = { path = "a-sys" }
+                b-sys = { path = "b-sys" }
+
+                [workspace.links.z]
+                packages = ["a-sys", "b-sys"]
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .file(
+            "a-sys/Cargo.toml",
+            r#"
+                [package]
+                name = "a-sys"
+                version = "0.0.1"
+                links = "z"
+            "#,
+        )
+        .file("a-sys/src/lib.rs", "")
+        .file("a-sys/build.rs", &static_lib_build_script("za", a_symbols))
+        .file(
+            "b-sys/Cargo.toml",
+            r#"
+                [package]
+                name = "b-sys"
+                version = "0.0.1"
+                links = "z"
+            "#,
+        )
+        .file("b-sys/src/lib.rs", "")
+        .file("b-sys/build.rs", &static_lib_build_script("zb", b_symbols))
+        .build()
+}
+
+/// A build script compiling the functions `symbols` to an object with
+/// `$RUSTC`, and linking an archive of it as the static library `name`.
+fn static_lib_build_script(name: &str, symbols: &[&str]) -> String {
+    let functions: String = symbols
+        .iter()
+        .map(|symbol| format!(r#"#[no_mangle] pub extern \"C\" fn {}() {{}}\n"#, symbol))
+        .collect();
+    format!(
+        r##"
+            use std::env;
+            use std::fs;
+            use std::path::PathBuf;
+            use std::process::Command;
+
+            fn main() {{
+                let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+                fs::write(out_dir.join("{name}.rs"), "#![no_std]\n{functions}").unwrap();
+                let status = Command::new(env::var("RUSTC").unwrap())
+                    .args(&["--crate-type", "lib", "--emit", "obj", "-C", "panic=abort"])
+                    .args(&["-C", "codegen-units=1", "-o"])
+                    .arg(out_dir.join("{name}.o"))
+                    .arg(out_dir.join("{name}.rs"))
+                    .status()
+                    .unwrap();
+                assert!(status.success());
+
+                let object = fs::read(out_dir.join("{name}.o")).unwrap();
+                let mut archive = b"!<arch>\n".to_vec();
+                archive.extend(
+                    format!(
+                        "{{:<16}}{{:<12}}{{:<6}}{{:<6}}{{:<8}}{{:<10}}`\n",
+                        "{name}.o/", 0, 0, 0, 644, object.len()
+                    )
+                    .into_bytes(),
+                );
+                archive.extend(&object);
+                if object.len() % 2 == 1 {{
+                    archive.push(b'\n');
+                }}
+                fs::write(out_dir.join("lib{name}.a"), archive).unwrap();
+                println!("cargo:rustc-link-search=native={{}}", out_dir.display());
+                println!("cargo:rustc-link-lib=static={name}");
+            }}
+        "##,
+        name = name,
+        functions = functions,
+    )
+}
+
+#[cargo_test]
+fn links_policy_distinct_symbols() {
+    let p = symbols_project(&["z_a"], &["z_b"]);
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "[WARNING] packages `a-sys v0.0.1 ([..])`, `b-sys v0.0.1 ([..])` link to native library `z`, [..]",
+        )
+        .with_stderr_contains("[FINISHED] [..]")
+        .run();
+
+    // The fresh build scripts are checked again.
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[FINISHED] [..]")
+        .run();
+}
+
+#[cargo_test]
+fn links_policy_same_symbols() {
+    let p = symbols_project(&["z_a", "z_shared"], &["z_b", "z_shared"]);
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+[ERROR] the native libraries of `[..]-sys v0.0.1 ([..])` and `[..]-sys v0.0.1 ([..])` both define `z_shared`
+they share `links = \"z\"`, so all of them are linked; \
+set `workspace.links.z.winner` to link the libraries of only one package",
+        )
+        .run();
+
+    // A winner drops the libraries of the other package.
+    p.change_file(
+        "Cargo.toml",
+        &p.read_file("Cargo.toml")
+            .replace("packages = [", "winner = \"a-sys\"\npackages = ["),
+    );
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[FINISHED] [..]")
+        .run();
+}
+
+#[cargo_test]
+fn links_policy_unknown_symbols() {
+    let p = symbols_project(&["z_a"], &[]);
+    p.change_file(
+        "b-sys/build.rs",
+        r#"fn main() { println!("cargo:rustc-link-lib=z"); }"#,
+    );
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+[ERROR] failed to determine the symbols of native library `z` of `b-sys v0.0.1 ([..])`, \
+which shares `links = \"z\"` with other packages
+set `workspace.links.z.winner` to link the libraries of only one package",
+        )
+        .with_stderr_contains("  it is not in any of the search paths printed by the build script")
+        .run();
+}
@@ -836,3 +836,152 @@ source = "git+{url}#{sha}"
 
     assert_eq!(p.read_file("Cargo.lock"), lockfile);
 }
+
+fn v5_project() -> cargo_test_support::Project {
+    project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                resolver = "2"
+
+                [dependencies]
+                bar = "0.1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build()
+}
+
+#[cargo_test]
+fn v5_format() {
+    Package::new("bar", "0.1.0").publish();
+    let p = v5_project();
+
+    p.cargo("build -Z lockfile-v5")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    let expected = "\
+# This file is automatically @generated by Cargo.
+# It is not intended for manual editing.
+version = 5
+resolver = \"2\"
+
+[[package]]
+name = \"bar\"
+version = \"0.1.0\"
+source = \"registry+https://github.com/rust-lang/crates.io-index\"
+checksum = \"[..]\"
+mirror = \"dummy-registry\"
+
+[[package]]
+name = \"foo\"
+version = \"0.0.1\"
+dependencies = [
+ \"bar\",
+]
+";
+    assert_match_exact(expected, &p.read_lockfile());
+
+    // The format is kept without the flag.
+    p.cargo("build").run();
+    assert_match_exact(expected, &p.read_lockfile());
+}
+
+#[cargo_test]
+fn v5_signature() {
+    Package::new("bar", "0.1.0").publish();
+    let p = v5_project();
+
+    // The checksum is checked by `-Z sumdb` when the package is locked, so the
+    // signature is recorded by the command writing the lock file.
+    p.cargo("generate-lockfile -Z lockfile-v5 -Z sumdb")
+        .masquerade_as_nightly_cargo()
+        .run();
+    let lock = p.read_lockfile();
+    assert!(lock.contains("mirror = \"dummy-registry\"\nsignature = \"checksum-db\"\n"));
+    p.cargo("build --locked -Z sumdb")
+        .masquerade_as_nightly_cargo()
+        .run();
+    assert_match_exact(&lock, &p.read_lockfile());
+
+    // What was recorded is kept, even without the checksum database.
+    let sumdb = cargo_test_support::paths::home().join(".cargo/registry/sumdb");
+    std::fs::remove_dir_all(&sumdb).unwrap();
+    p.cargo("build").run();
+    assert_match_exact(&lock, &p.read_lockfile());
+
+    // A package locked without the checksum database doesn't get a signature
+    // from a later build.
+    std::fs::remove_file(p.root().join("Cargo.lock")).unwrap();
+    p.cargo("generate-lockfile -Z lockfile-v5")
+        .masquerade_as_nightly_cargo()
+        .run();
+    let lock = p.read_lockfile();
+    assert!(lock.contains("mirror = \"dummy-registry\"\n"));
+    assert!(!lock.contains("signature"));
+    p.cargo("build --locked -Z sumdb")
+        .masquerade_as_nightly_cargo()
+        .run();
+    assert!(sumdb.exists());
+    p.cargo("build --locked").run();
+    assert_match_exact(&lock, &p.read_lockfile());
+
+    // Until the package changes.
+    Package::new("bar", "0.1.1").publish();
+    p.cargo("update").run();
+    let lock = p.read_lockfile();
+    assert!(lock.contains("version = \"0.1.1\""));
+    assert!(!lock.contains("signature"));
+}
+
+#[cargo_test]
+fn v5_migrate() {
+    Package::new("bar", "0.1.0").publish();
+    let p = v5_project();
+    p.cargo("generate-lockfile").run();
+    let v3 = p.read_lockfile();
+    assert!(v3.contains("version = 3\n"));
+
+    p.cargo("lockfile migrate")
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] the `cargo lockfile` command is unstable, pass `-Z lockfile-v5` to enable it",
+        )
+        .run();
+
+    p.cargo("lockfile migrate -Z lockfile-v5")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("    Migrated `Cargo.lock` from version 3 to version 5")
+        .run();
+    let lock = p.read_lockfile();
+    assert!(lock.contains("version = 5\nresolver = \"2\"\n"));
+    assert!(lock.contains("mirror = \"dummy-registry\"\n"));
+
+    // A downgrade isn't undone by the flag, since nothing else changes.
+    p.cargo("lockfile migrate -Z lockfile-v5 --to 3")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("    Migrated `Cargo.lock` from version 5 to version 3")
+        .run();
+    assert_match_exact(&v3, &p.read_lockfile());
+    p.cargo("build -Z lockfile-v5")
+        .masquerade_as_nightly_cargo()
+        .run();
+    assert_match_exact(&v3, &p.read_lockfile());
+
+    p.cargo("lockfile migrate -Z lockfile-v5 --to 4")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] lock file version `4` is not supported, the versions are 1, 2, 3 and 5",
+        )
+        .run();
+    p.cargo("lockfile migrate -Z lockfile-v5 --locked")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[ERROR] the lock file [..] needs to be updated but --locked [..]")
+        .run();
+}
@@ -0,0 +1,121 @@
+//! Tests for the structured log of `CARGO_LOG_FILE`.
+
+use cargo_test_support::registry::Package;
+use cargo_test_support::{paths, project};
+use serde_json::Value;
+use std::path::Path;
+
+fn read_log(path: &Path) -> Vec<Value> {
+    std::fs::read_to_string(path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[cargo_test]
+fn log_file() {
+    Package::new("bar", "1.0.0").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+    let log = paths::root().join("cargo.log");
+
+    // The terminal output isn't changed.
+    p.cargo("build")
+        .env("CARGO_LOG_FILE", &log)
+        .with_stderr(
+            "\
+[UPDATING] [..]
+[DOWNLOADING] crates ...
+[DOWNLOADED] bar v1.0.0 ([..])
+[COMPILING] bar v1.0.0
+[COMPILING] foo v0.1.0 ([..])
+[FINISHED] [..]
+",
+        )
+        .run();
+
+    let lines = read_log(&log);
+    for line in &lines {
+        assert!(line["time"].is_string(), "{}", line);
+        assert!(line["pid"].is_u64(), "{}", line);
+        assert!(line["level"].is_string(), "{}", line);
+        assert!(line["spans"].is_array(), "{}", line);
+    }
+    let has = |target: &str, message: &str| {
+        lines.iter().any(|line| {
+            line["target"].as_str().unwrap().starts_with(target)
+                && line["message"]
+                    .as_str()
+                    .map_or(false, |m| m.contains(message))
+        })
+    };
+    assert!(has("cargo::core::resolver", "activating bar v1.0.0"));
+    assert!(has("cargo::core::package", "downloading bar v1.0.0"));
+    let span = lines
+        .iter()
+        .find(|line| line["span"] == "compiling")
+        .expect("the span of the compilation");
+    assert!(span["elapsed_ms"].is_u64());
+    assert!(lines.iter().any(|line| {
+        line["spans"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|span| span == "compiling")
+    }));
+
+    // The fingerprint invalidations are logged.
+    p.change_file("src/lib.rs", "pub fn f() {}");
+    std::fs::remove_file(&log).unwrap();
+    p.cargo("build").env("CARGO_LOG_FILE", &log).run();
+    assert!(read_log(&log).iter().any(|line| {
+        line["target"] == "cargo::core::compiler::fingerprint"
+            && line["message"]
+                .as_str()
+                .unwrap()
+                .starts_with("stale: changed")
+    }));
+}
+
+#[cargo_test]
+fn log_file_filter() {
+    let p = project().file("src/lib.rs", "").build();
+    let log = paths::root().join("cargo.log");
+    p.cargo("build")
+        .env("CARGO_LOG_FILE", &log)
+        .env(
+            "CARGO_LOG_FILE_FILTER",
+            "cargo::core::compiler::fingerprint=debug",
+        )
+        .run();
+    let lines = read_log(&log);
+    assert!(!lines.is_empty());
+    assert!(lines
+        .iter()
+        .all(|line| line["target"] == "cargo::core::compiler::fingerprint"));
+}
+
+#[cargo_test]
+fn log_file_error() {
+    let p = project().file("src/lib.rs", "").build();
+    p.cargo("build")
+        .env("CARGO_LOG_FILE", p.root().join("missing/cargo.log"))
+        .with_stderr_contains(
+            "warning: failed to open the log file `[..]/missing/cargo.log` of `CARGO_LOG_FILE`: [..]",
+        )
+        .with_stderr_contains("[FINISHED] [..]")
+        .run();
+}
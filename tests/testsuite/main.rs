@@ -8,17 +8,29 @@
 #[macro_use]
 extern crate cargo_test_macro;
 
+mod add;
 mod advanced_env;
+mod allow_prerelease;
 mod alt_registry;
+mod api;
+mod apply_suggestions;
+mod artifact_name;
+mod asm;
+mod assets;
+mod audits;
+mod auto_features;
 mod bad_config;
 mod bad_manifest_path;
 mod bench;
 mod binary_name;
 mod build;
+mod build_info;
 mod build_plan;
 mod build_script;
 mod build_script_env;
 mod build_script_extra_link_arg;
+mod build_stats;
+mod cache;
 mod cache_messages;
 mod cargo_alias_config;
 mod cargo_command;
@@ -27,12 +39,20 @@ mod cargo_env_config;
 mod cargo_features;
 mod cargo_targets;
 mod cfg;
+mod changed_since;
+mod changelog_fragments;
 mod check;
+mod check_cfg;
 mod clean;
+mod client_certs;
+mod code_signing;
+mod codegen;
 mod collisions;
+mod completions;
 mod concurrent;
 mod config;
 mod config_cli;
+mod config_flags;
 mod config_include;
 mod corrupt_git;
 mod credential_process;
@@ -40,55 +60,87 @@ mod cross_compile;
 mod cross_publish;
 mod custom_target;
 mod death;
+mod delta_downloads;
+mod deny_warnings;
 mod dep_info;
 mod directory;
 mod doc;
+mod doc_coverage;
+mod doc_index;
+mod doc_offline_first;
+mod downloads;
 mod edition;
+mod edition_migration;
 mod error;
+mod expand;
+mod feature_explain;
+mod feature_matrix;
+mod feature_metadata;
+mod feature_overrides;
 mod features;
 mod features2;
 mod features_namespaced;
 mod fetch;
 mod fix;
+mod fix_backups;
 mod freshness;
 mod future_incompat_report;
+mod fuzz_targets;
 mod generate_lockfile;
 mod git;
 mod git_auth;
+mod git_bundle;
 mod git_gc;
 mod glob_targets;
+mod headers;
 mod help;
+mod http_transport;
 mod init;
 mod install;
+mod install_staged;
 mod install_upgrade;
 mod jobserver;
+mod link_graph;
+mod links_policy;
 mod list_availables;
 mod local_registry;
 mod locate_project;
 mod lockfile_compat;
+mod log_file;
 mod login;
 mod logout;
 mod lto;
+mod manifest_env;
+mod manifest_version;
 mod member_discovery;
 mod member_errors;
 mod message_format;
 mod messages;
 mod metabuild;
 mod metadata;
+mod metadata_watch;
 mod minimal_versions;
 mod multitarget;
+mod named_lockfiles;
 mod net_config;
+mod net_ssh;
+mod network_log;
 mod new;
 mod offline;
 mod old_cargos;
 mod out_dir;
 mod owner;
 mod package;
+mod package_binary;
 mod package_features;
+mod package_hooks;
 mod patch;
+mod patch_command;
 mod path;
 mod paths;
+mod pkg_config;
 mod pkgid;
+mod plugin;
 mod plugins;
 mod proc_macro;
 mod profile_config;
@@ -97,37 +149,72 @@ mod profile_overrides;
 mod profile_targets;
 mod profiles;
 mod progress;
+mod proxy_config;
 mod pub_priv;
 mod publish;
 mod publish_lockfile;
 mod read_manifest;
 mod registry;
+mod registry_dedup;
+mod release;
+mod remote_runner;
 mod rename_deps;
 mod replace;
+mod report_bloat;
+mod report_licenses;
 mod required_features;
 mod run;
 mod rust_version;
 mod rustc;
 mod rustc_info_cache;
+mod rustc_namespace;
 mod rustdoc;
 mod rustdoc_extern_html;
 mod rustdocflags;
 mod rustflags;
+mod sanitizers;
 mod search;
+mod serve_api;
 mod shell_quoting;
+mod source_modes;
 mod standard_lib;
+mod staticlib_bundle;
+mod strict_manifest;
+mod subcommand_context;
+mod sumdb;
+mod target_expr;
+mod temporary_overrides;
 mod test;
+mod test_affected;
+mod test_cache;
+mod test_coverage;
+mod test_logs;
+mod test_miri;
+mod test_timeouts;
 mod timings;
 mod tool_paths;
+mod toolchain_config;
+mod toolchain_file;
 mod tree;
 mod tree_graph_features;
+mod trim_paths;
 mod unit_graph;
+mod universal_binaries;
 mod update;
+mod validate;
+mod vcs_policy;
 mod vendor;
 mod verify_project;
 mod version;
+mod version_bump;
 mod warn_on_failure;
+mod warning_summary;
+mod wasm_component;
+mod wasm_runner;
 mod weak_dep_features;
+mod windows_resources;
+mod workspace_groups;
+mod workspace_policy;
 mod workspaces;
 mod yank;
 
@@ -0,0 +1,245 @@
+//! Tests for the `[env]` table of `Cargo.toml`.
+
+use cargo_test_support::{project, sleep_ms};
+
+#[cargo_test]
+fn env_requires_cargo_feature() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [env]
+                CHANNEL = "stable"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("  feature `manifest-env` is required")
+        .run();
+}
+
+#[cargo_test]
+fn env_values_and_files() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["manifest-env"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [env]
+                CHANNEL = "stable"
+                BUILD_VERSION = { file = "VERSION" }
+                ASSETS = { value = "assets", relative = true }
+            "#,
+        )
+        .file("VERSION", "1.2.3-abcdef\n")
+        .file(
+            "src/main.rs",
+            r#"
+                fn main() {
+                    println!("{} {}", env!("CHANNEL"), env!("BUILD_VERSION"));
+                    assert!(env!("ASSETS").ends_with("foo/assets"));
+                    // Only set for the compilation, not when running.
+                    assert_ne!(std::env::var("CHANNEL").ok().as_deref(), Some("stable"));
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("run")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("stable 1.2.3-abcdef")
+        .run();
+
+    // The manifest wins over the environment, which doesn't cause rebuilds.
+    p.cargo("run")
+        .masquerade_as_nightly_cargo()
+        .env("CHANNEL", "nightly")
+        .with_stderr(
+            "\
+[FINISHED] [..]
+[RUNNING] `target/debug/foo[EXE]`
+",
+        )
+        .with_stdout("stable 1.2.3-abcdef")
+        .run();
+
+    // Changing the file rebuilds.
+    sleep_ms(1000);
+    p.change_file("VERSION", "1.2.4-012345\n");
+    p.cargo("run")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.1.0 ([CWD])
+[FINISHED] [..]
+[RUNNING] `target/debug/foo[EXE]`
+",
+        )
+        .with_stdout("stable 1.2.4-012345")
+        .run();
+}
+
+#[cargo_test]
+fn workspace_env() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["manifest-env"]
+
+                [workspace]
+                members = ["a", "b"]
+
+                [env]
+                CHANNEL = "stable"
+                VENDOR = "acme"
+            "#,
+        )
+        .file(
+            "a/Cargo.toml",
+            r#"
+                [package]
+                name = "a"
+                version = "0.1.0"
+            "#,
+        )
+        .file(
+            "a/src/lib.rs",
+            r#"
+                pub fn f() {
+                    assert_eq!(env!("CHANNEL"), "stable");
+                    assert_eq!(env!("VENDOR"), "acme");
+                }
+            "#,
+        )
+        .file(
+            "b/Cargo.toml",
+            r#"
+                cargo-features = ["manifest-env"]
+
+                [package]
+                name = "b"
+                version = "0.1.0"
+
+                [env]
+                CHANNEL = "beta"
+            "#,
+        )
+        .file(
+            "b/src/lib.rs",
+            r#"
+                pub fn f() {
+                    assert_eq!(env!("CHANNEL"), "beta");
+                    assert_eq!(env!("VENDOR"), "acme");
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_unordered(
+            "\
+[CHECKING] a v0.1.0 ([CWD]/a)
+[CHECKING] b v0.1.0 ([CWD]/b)
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn invalid_env() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["manifest-env"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [env]
+                CARGO_PKG_VERSION = "1.0.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  `[env]` variable `CARGO_PKG_VERSION` is reserved, variables starting with `CARGO_` are set by Cargo
+",
+        )
+        .run();
+
+    p.change_file(
+        "Cargo.toml",
+        r#"
+            cargo-features = ["manifest-env"]
+
+            [package]
+            name = "foo"
+            version = "0.1.0"
+
+            [env]
+            VERSION = { value = "1.0.0", file = "VERSION" }
+        "#,
+    );
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "  `[env]` variable `VERSION` must specify exactly one of `value` or `file`",
+        )
+        .run();
+
+    p.change_file(
+        "Cargo.toml",
+        r#"
+            cargo-features = ["manifest-env"]
+
+            [package]
+            name = "foo"
+            version = "0.1.0"
+
+            [env]
+            VERSION = { file = "VERSION" }
+        "#,
+    );
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to read `[env]` variable `VERSION` of package `foo v0.1.0 ([CWD])`
+
+Caused by:
+  failed to read `[CWD]/VERSION`
+
+Caused by:
+  No such file or directory (os error 2)
+",
+        )
+        .run();
+}
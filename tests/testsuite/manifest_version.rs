@@ -0,0 +1,153 @@
+//! Tests for the `manifest-version` field.
+
+use cargo_test_support::{basic_manifest, project};
+
+#[cargo_test]
+fn manifest_version_requires_feature() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                manifest-version = "1.50"
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("  feature `manifest-version` is required")
+        .run();
+}
+
+#[cargo_test]
+fn manifest_version_newer_than_cargo() {
+    // The version is checked before anything else, so that the manifest can
+    // use what this Cargo does not know.
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                manifest-version = "1.99"
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                from-the-future = { x = 1 }
+
+                [dependencies]
+                bar = { version = "1.0", future = true }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse manifest at `[..]/foo/Cargo.toml`
+
+Caused by:
+  this manifest needs Cargo 1.99 or newer for its `manifest-version`, but this is Cargo [..]
+  Update Cargo, or lower the `manifest-version` if the manifest only uses what this version of Cargo supports.
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn invalid_manifest_version() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["manifest-version"]
+                manifest-version = "1.x"
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse manifest at `[..]/foo/Cargo.toml`
+
+Caused by:
+  `manifest-version` must be a value like \"1.56\", found `1.x`
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn validate_against_manifest_version() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["manifest-version"]
+                manifest-version = "1.50"
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                edition = "2021"
+                rust-version = "1.56"
+                description = "foo"
+                license = "MIT"
+                repository = "https://example.com/foo"
+
+                [dependencies]
+                baz = { path = "bar", package = "bar", version = "0.1.0" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.1.0"))
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    p.cargo("check").masquerade_as_nightly_cargo().run();
+    p.cargo("validate -Zvalidate")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] foo v0.1.0: `package.edition = \"2021\"` needs Cargo 1.56 or newer, but the `manifest-version` is 1.50 [schema]
+[ERROR] foo v0.1.0: `package.rust-version` needs Cargo 1.56 or newer, but the `manifest-version` is 1.50 [schema]
+[ERROR] validation failed due to 2 previous errors
+",
+        )
+        .run();
+
+    p.cargo("validate -Zvalidate --cargo-version 1.30")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] foo v0.1.0: the `manifest-version` 1.50 is newer than Cargo 1.30 [schema]
+[ERROR] foo v0.1.0: `package.edition = \"2021\"` needs Cargo 1.56 or newer, but the manifest is checked against Cargo 1.30 [schema]
+[ERROR] foo v0.1.0: `package.rust-version` needs Cargo 1.56 or newer, but the manifest is checked against Cargo 1.30 [schema]
+[ERROR] foo v0.1.0: the renamed dependency `dependencies.baz` needs Cargo 1.31 or newer, but the manifest is checked against Cargo 1.30 [schema]
+[ERROR] validation failed due to 4 previous errors
+",
+        )
+        .run();
+
+    p.cargo("validate -Zvalidate --cargo-version 1.56")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[VALIDATED] 1 package, 0 warnings")
+        .run();
+}
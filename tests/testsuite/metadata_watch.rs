@@ -0,0 +1,143 @@
+//! Tests for `cargo metadata --watch`.
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::Stdio;
+
+use cargo_test_support::{basic_manifest, project};
+use serde_json::Value;
+
+#[cargo_test]
+fn watch_requires_z_flag() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("metadata --format-version 1 --watch")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] Usage of `--watch` requires `-Z metadata-watch`")
+        .run();
+}
+
+#[cargo_test]
+fn watch_stops_when_stdin_is_closed() {
+    // The test harness runs Cargo with an empty stdin.
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("metadata --format-version 1 --no-deps --watch -Zmetadata-watch")
+        .masquerade_as_nightly_cargo()
+        .with_stdout_contains(r#"{"type":"hello","version":1}"#)
+        .with_stdout_contains(
+            r#"{"type":"metadata","fingerprint":"[..]","metadata":{[..]"name":"foo"[..]}}"#,
+        )
+        .run();
+}
+
+#[cargo_test]
+fn watch_sends_deltas() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["foo", "bar"]
+            "#,
+        )
+        .file("foo/Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("foo/src/lib.rs", "")
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.1.0"))
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    let mut child = p
+        .cargo("metadata --format-version 1 --watch -Zmetadata-watch")
+        .masquerade_as_nightly_cargo()
+        .build_command()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut recv = || -> Value {
+        let mut line = String::new();
+        stdout.read_line(&mut line).unwrap();
+        serde_json::from_str(&line).unwrap()
+    };
+
+    assert_eq!(recv(), serde_json::json!({"type": "hello", "version": 1}));
+    let full = recv();
+    assert_eq!(full["type"], "metadata");
+    assert_eq!(full["metadata"]["packages"].as_array().unwrap().len(), 2);
+    let fingerprint = full["fingerprint"].as_str().unwrap().to_string();
+
+    // Nothing changed.
+    writeln!(stdin, r#"{{"type":"refresh"}}"#).unwrap();
+    let delta = recv();
+    assert_eq!(delta["type"], "delta");
+    assert_eq!(delta["since"], fingerprint);
+    assert_eq!(delta["fingerprint"], fingerprint);
+    assert_eq!(delta["packages"], serde_json::json!([]));
+    assert_eq!(delta["fields"], serde_json::json!({}));
+
+    p.change_file(
+        "foo/Cargo.toml",
+        r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+
+            [dependencies]
+            bar = { path = "../bar" }
+        "#,
+    );
+    // The change may be seen before the refresh, which is then answered with
+    // an empty delta.
+    writeln!(stdin, r#"{{"type":"refresh"}}"#).unwrap();
+    let delta = loop {
+        let delta = recv();
+        assert_eq!(delta["type"], "delta");
+        if delta["fingerprint"] != fingerprint {
+            break delta;
+        }
+    };
+    assert_eq!(delta["since"], fingerprint);
+    let packages = delta["packages"].as_array().unwrap();
+    assert_eq!(packages.len(), 1);
+    assert_eq!(packages[0]["name"], "foo");
+    assert_eq!(packages[0]["dependencies"][0]["name"], "bar");
+    let nodes = delta["resolve_nodes"].as_array().unwrap();
+    assert_eq!(nodes.len(), 1);
+    assert!(nodes[0]["id"].as_str().unwrap().starts_with("foo 0.1.0"));
+    assert_eq!(delta["removed_packages"], serde_json::json!([]));
+    assert_eq!(delta["fields"], serde_json::json!({}));
+    let fingerprint = delta["fingerprint"].clone();
+
+    writeln!(stdin, r#"{{"type":"full"}}"#).unwrap();
+    let full = loop {
+        let message = recv();
+        if message["type"] == "metadata" {
+            break message;
+        }
+    };
+    assert_eq!(full["fingerprint"], fingerprint);
+
+    p.change_file("bar/Cargo.toml", "[package]\nname = \"bar\"\n");
+    writeln!(stdin, r#"{{"type":"refresh"}}"#).unwrap();
+    let error = recv();
+    assert_eq!(error["type"], "error");
+    assert!(error["message"]
+        .as_str()
+        .unwrap()
+        .contains("failed to parse manifest"));
+
+    writeln!(stdin, "bogus").unwrap();
+    let error = recv();
+    assert_eq!(error["type"], "error");
+    assert!(error["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("invalid request `bogus`"));
+
+    drop(stdin);
+    assert!(child.wait().unwrap().success());
+}
@@ -36,3 +36,161 @@ fn minimal_version_cli() {
 
     assert!(!lock.contains("1.1.0"));
 }
+
+// Ensure that "-Z direct-minimal-versions" only picks the minimal versions of
+// the direct dependencies, and the maximal versions of the rest.
+#[cargo_test]
+fn direct_minimal_versions() {
+    Package::new("indirect", "1.0.0").publish();
+    Package::new("indirect", "1.1.0").publish();
+    Package::new("dep", "1.0.0")
+        .dep("indirect", "1.0")
+        .publish();
+    Package::new("dep", "1.1.0")
+        .dep("indirect", "1.0")
+        .publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                authors = []
+                version = "0.0.1"
+
+                [dependencies]
+                dep = "1.0"
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("generate-lockfile -Zdirect-minimal-versions")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    let lock = p.read_lockfile();
+
+    assert!(lock.contains("name = \"dep\"\nversion = \"1.0.0\""));
+    assert!(lock.contains("name = \"indirect\"\nversion = \"1.1.0\""));
+}
+
+#[cargo_test]
+fn stable_flags() {
+    Package::new("indirect", "1.0.0").publish();
+    Package::new("indirect", "1.1.0").publish();
+    Package::new("dep", "1.0.0")
+        .dep("indirect", "1.0")
+        .publish();
+    Package::new("dep", "1.1.0")
+        .dep("indirect", "1.0")
+        .publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [dependencies]
+                dep = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("generate-lockfile --minimal-versions").run();
+    let lock = p.read_lockfile();
+    assert!(lock.contains("name = \"dep\"\nversion = \"1.0.0\""));
+    assert!(lock.contains("name = \"indirect\"\nversion = \"1.0.0\""));
+
+    p.cargo("update --direct-minimal-versions")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[UPDATING] indirect v1.0.0 -> v1.1.0
+",
+        )
+        .run();
+
+    p.cargo("update --minimal-versions --direct-minimal-versions")
+        .with_status(1)
+        .with_stderr_contains("[..]cannot be used with[..]")
+        .run();
+}
+
+#[cargo_test]
+fn exempt_dependencies() {
+    Package::new("dep", "1.0.0").publish();
+    Package::new("dep", "1.1.0").publish();
+    Package::new("broken", "1.0.0").publish();
+    Package::new("broken", "1.1.0").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [dependencies]
+                dep = "1.0"
+                broken = "1.0"
+
+                [workspace.minimal-versions]
+                exempt = ["broken"]
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("generate-lockfile --minimal-versions").run();
+    let lock = p.read_lockfile();
+    assert!(lock.contains("name = \"dep\"\nversion = \"1.0.0\""));
+    assert!(lock.contains("name = \"broken\"\nversion = \"1.1.0\""));
+}
+
+#[cargo_test]
+fn report_lower_bounds_that_dont_build() {
+    Package::new("dep", "1.0.0")
+        .file("src/lib.rs", "")
+        .publish();
+    Package::new("dep", "1.1.0")
+        .file("src/lib.rs", "pub fn f() {}")
+        .publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [dependencies]
+                dep = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "pub fn g() { dep::f() }")
+        .build();
+
+    p.cargo("check").run();
+    let lock = p.read_lockfile();
+
+    p.cargo("check --minimal-versions")
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+[NOTE] the build may have failed on the lower bounds of these requirements, \
+to which the dependencies were resolved:
+  `dep = \"^1.0\"` of foo v0.0.1 ([..]), resolved to v1.0.0
+Raise the lower bounds, or exempt the dependencies with the `exempt` list of \
+`[workspace.minimal-versions]`.",
+        )
+        .run();
+    assert_eq!(p.read_lockfile(), lock);
+}
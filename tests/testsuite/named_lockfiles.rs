@@ -0,0 +1,143 @@
+//! Tests for selecting another lock file of the workspace with `--lockfile`.
+
+use cargo_test_support::project;
+use cargo_test_support::registry::Package;
+
+#[cargo_test]
+fn requires_z_flag() {
+    Package::new("dep", "1.0.0").publish();
+    Package::new("dep", "1.1.0").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [dependencies]
+                dep = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("build --lockfile Cargo.minimal.lock")
+        .with_status(101)
+        .with_stderr("[ERROR] Usage of `--lockfile` requires `-Z named-lockfiles`")
+        .run();
+}
+
+#[cargo_test]
+fn invalid_name() {
+    Package::new("dep", "1.0.0").publish();
+    Package::new("dep", "1.1.0").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [dependencies]
+                dep = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("build --lockfile ../Cargo.lock -Z named-lockfiles")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] invalid lock file name `../Cargo.lock`, \
+             expected a file name like `Cargo.minimal.lock`",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn minimal_lockfile() {
+    Package::new("dep", "1.0.0").publish();
+    Package::new("dep", "1.1.0").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [dependencies]
+                dep = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("generate-lockfile").run();
+    p.cargo("update --lockfile Cargo.minimal.lock -Z named-lockfiles -Z direct-minimal-versions")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[UPDATING] `[..]` index")
+        .run();
+
+    assert!(p.read_lockfile().contains("version = \"1.1.0\""));
+    let minimal = p.read_file("Cargo.minimal.lock");
+    assert!(minimal.contains("version = \"1.0.0\""));
+    assert!(!minimal.contains("version = \"1.1.0\""));
+
+    // The lock files are used and kept independently.
+    p.cargo("build --locked --lockfile Cargo.minimal.lock -Z named-lockfiles")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[DOWNLOADING] crates ...
+[DOWNLOADED] dep v1.0.0 ([..])
+[COMPILING] dep v1.0.0
+[COMPILING] foo v0.0.1 ([..])
+[FINISHED] [..]
+",
+        )
+        .run();
+    p.cargo("build --locked")
+        .with_stderr(
+            "\
+[DOWNLOADING] crates ...
+[DOWNLOADED] dep v1.1.0 ([..])
+[COMPILING] dep v1.1.0
+[COMPILING] foo v0.0.1 ([..])
+[FINISHED] [..]
+",
+        )
+        .run();
+    assert_eq!(p.read_file("Cargo.minimal.lock"), minimal);
+}
+
+#[cargo_test]
+fn missing_lockfile_is_generated() {
+    Package::new("dep", "1.0.0").publish();
+    Package::new("dep", "1.1.0").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [dependencies]
+                dep = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("-Z named-lockfiles --lockfile Cargo.msrv.lock generate-lockfile")
+        .masquerade_as_nightly_cargo()
+        .run();
+    assert!(p.root().join("Cargo.msrv.lock").is_file());
+    assert!(!p.root().join("Cargo.lock").exists());
+
+    p.cargo("build --locked --lockfile Cargo.msrv.lock -Z named-lockfiles")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[COMPILING] foo v0.0.1 ([..])")
+        .run();
+    assert!(!p.root().join("Cargo.lock").exists());
+}
@@ -0,0 +1,111 @@
+//! Tests for the SSH settings of the git sources, `-Z net-ssh`.
+//!
+//! There is no SSH server in the tests, so the authentication and the host
+//! key verification are covered by the unit tests of `sources::git::ssh`.
+
+use cargo_test_support::project;
+
+#[cargo_test]
+fn gated() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.0"
+
+                [dependencies]
+                bar = { git = "ssh://git@git.invalid/bar" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("keys/ci_key", "")
+        .file(
+            ".cargo/config",
+            r#"
+                [net.ssh]
+                key-path = "keys/ci_key"
+            "#,
+        )
+        .build();
+    p.cargo("fetch")
+        .with_status(101)
+        .with_stderr_contains("[..]`net.ssh` requires `-Z net-ssh`")
+        .run();
+}
+
+#[cargo_test]
+fn missing_key() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.0"
+
+                [dependencies]
+                bar = { git = "ssh://git@git.invalid/bar" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("keys/ci_key", "")
+        .file(
+            ".cargo/config",
+            r#"
+                [net.ssh]
+                key-path = "keys/ci_key"
+
+                [net.ssh.hosts."git.invalid"]
+                key-path = "keys/missing_key"
+            "#,
+        )
+        .build();
+    p.cargo("fetch -Znet-ssh")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "  the SSH key `[..]keys/missing_key` of \
+             `net.ssh.hosts.git.invalid.key-path` doesn't exist",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn invalid_known_host() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.0"
+
+                [dependencies]
+                bar = { git = "ssh://git@git.invalid/bar" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("keys/ci_key", "")
+        .file(
+            ".cargo/config",
+            r#"
+                [net.ssh]
+                known-hosts = [
+                    "git.invalid SHA256:+DiY3wvvV6TuJJhbpZisF/zLDA0zPMSvHdkr4UvCOqU",
+                    "git.invalid SHA1:abcdef",
+                ]
+            "#,
+        )
+        .build();
+    p.cargo("fetch -Znet-ssh")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "  invalid `net.ssh.known-hosts` entry `git.invalid SHA1:abcdef`, \
+             expected `<host> SHA256:<fingerprint>`, or a line of a `known_hosts` \
+             file",
+        )
+        .run();
+}
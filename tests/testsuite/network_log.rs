@@ -0,0 +1,245 @@
+//! Tests for `net.record` and `net.replay`.
+
+use cargo_test_support::registry::{dl_path, registry_url, Package};
+use cargo_test_support::{basic_manifest, git, paths, project, Project};
+use serde_json::Value;
+
+fn read_log(p: &Project) -> Vec<Value> {
+    std::fs::read_to_string(p.root().join("net/network.jsonl"))
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+/// Removes the downloaded crates, so that they are downloaded again.
+fn clear_downloads() {
+    let registry = paths::home().join(".cargo/registry");
+    for dir in ["cache", "src"] {
+        let _ = std::fs::remove_dir_all(registry.join(dir));
+    }
+}
+
+#[cargo_test]
+fn gated() {
+    Package::new("bar", "1.0.0").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("fetch")
+        .env("CARGO_NET_RECORD", "net")
+        .with_status(101)
+        .with_stderr_contains("  `net.record` requires `-Z network-log`")
+        .run();
+}
+
+#[cargo_test]
+fn record_and_replay() {
+    Package::new("bar", "1.0.0").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("fetch -Znetwork-log")
+        .masquerade_as_nightly_cargo()
+        .env("CARGO_NET_RECORD", "net")
+        .run();
+
+    let lines = read_log(&p);
+    assert_eq!(lines.len(), 2, "{:?}", lines);
+    assert_eq!(lines[0]["kind"], "git");
+    assert_eq!(lines[0]["url"], registry_url().as_str());
+    assert!(lines[0]["refs"]["refs/remotes/origin/HEAD"].is_string());
+    assert!(lines[0]["duration_ms"].is_u64());
+    assert_eq!(lines[1]["kind"], "http");
+    assert!(lines[1]["url"]
+        .as_str()
+        .unwrap()
+        .ends_with("/dl/bar/1.0.0/download"));
+    let sha256 = lines[1]["sha256"].as_str().unwrap();
+    assert!(p.root().join("net/responses").join(sha256).is_file());
+
+    // The network isn't used: the crate isn't served anymore, and the new
+    // version published in the index isn't seen.
+    std::fs::remove_dir_all(dl_path()).unwrap();
+    Package::new("bar", "1.0.1").publish();
+    clear_downloads();
+    std::fs::remove_file(p.root().join("Cargo.lock")).unwrap();
+    p.cargo("fetch -Znetwork-log")
+        .masquerade_as_nightly_cargo()
+        .env("CARGO_NET_REPLAY", "net")
+        .with_stderr(
+            "\
+[UPDATING] `dummy-registry` index
+[DOWNLOADING] crates ...
+[DOWNLOADED] bar v1.0.0 (registry `dummy-registry`)
+",
+        )
+        .run();
+    assert!(p.read_lockfile().contains("1.0.0"));
+    assert!(!p.read_lockfile().contains("1.0.1"));
+
+    // Without replaying, the download fails.
+    clear_downloads();
+    p.cargo("fetch")
+        .with_status(101)
+        .with_stderr_contains("[..]failed to download from `[..]/dl/bar/1.0.0/download`")
+        .run();
+}
+
+#[cargo_test]
+fn replay_failure() {
+    Package::new("bar", "1.0.0").publish();
+    std::fs::remove_dir_all(dl_path()).unwrap();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("fetch -Znetwork-log")
+        .masquerade_as_nightly_cargo()
+        .env("CARGO_NET_RECORD", "net")
+        .with_status(101)
+        .with_stderr_contains("[..]failed to download from `[..]/dl/bar/1.0.0/download`")
+        .run();
+    let lines = read_log(&p);
+    let error = lines[1]["error"].as_str().unwrap().to_string();
+
+    p.cargo("fetch -Znetwork-log")
+        .masquerade_as_nightly_cargo()
+        .env("CARGO_NET_REPLAY", "net")
+        .with_status(101)
+        .with_stderr_contains(&format!(
+            "  {} (replayed from `[..]/net/network.jsonl`)",
+            error
+        ))
+        .run();
+}
+
+#[cargo_test]
+fn replay_unrecorded() {
+    Package::new("bar", "1.0.0").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+    p.change_file("net/network.jsonl", "");
+    p.cargo("fetch -Znetwork-log")
+        .masquerade_as_nightly_cargo()
+        .env("CARGO_NET_REPLAY", "net")
+        .with_status(101)
+        .with_stderr_contains(
+            "  no request for `git [..]/registry +HEAD:refs/remotes/origin/HEAD` was recorded \
+             in the network log `[..]/net/network.jsonl`",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn record_and_replay_together() {
+    Package::new("bar", "1.0.0").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("fetch -Znetwork-log")
+        .masquerade_as_nightly_cargo()
+        .env("CARGO_NET_RECORD", "net")
+        .env("CARGO_NET_REPLAY", "net")
+        .with_status(101)
+        .with_stderr_contains("  `net.record` and `net.replay` cannot be used together")
+        .run();
+}
+
+#[cargo_test]
+fn replay_git_dependency() {
+    let (dep, repo) = git::new_repo("dep", |p| {
+        p.file("Cargo.toml", &basic_manifest("dep", "0.1.0"))
+            .file("src/lib.rs", "")
+    });
+    let first = repo.head().unwrap().target().unwrap();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    [package]
+                    name = "foo"
+                    version = "0.1.0"
+
+                    [dependencies]
+                    dep = {{ git = "{}" }}
+                "#,
+                dep.url()
+            ),
+        )
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("generate-lockfile -Znetwork-log")
+        .masquerade_as_nightly_cargo()
+        .env("CARGO_NET_RECORD", "net")
+        .run();
+    assert!(read_log(&p)[0]["refs"]["refs/remotes/origin/HEAD"] == first.to_string());
+
+    // The new commit is fetched without replaying, but not when replaying.
+    dep.change_file("src/lib.rs", "pub fn f() {}");
+    git::add(&repo);
+    git::commit(&repo);
+    p.cargo("update").run();
+    assert!(!p.read_lockfile().contains(&first.to_string()));
+    p.cargo("update -Znetwork-log")
+        .masquerade_as_nightly_cargo()
+        .env("CARGO_NET_REPLAY", "net")
+        .run();
+    assert!(p.read_lockfile().contains(&first.to_string()));
+}
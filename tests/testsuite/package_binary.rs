@@ -0,0 +1,310 @@
+//! Tests for the binary archives of `cargo package --binary`.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+
+use cargo_test_support::registry::Package;
+use cargo_test_support::{basic_bin_manifest, basic_lib_manifest, project, rustc_host, Project};
+use cargo_util::Sha256;
+use flate2::read::{DeflateDecoder, GzDecoder};
+use tar::Archive;
+
+/// The files of a `.tar.gz` archive with their contents and modes.
+fn tar_gz_files(p: &Project, archive: &str) -> BTreeMap<String, (Vec<u8>, u32)> {
+    let f = File::open(p.root().join("target/package").join(archive)).unwrap();
+    let mut archive = Archive::new(GzDecoder::new(f));
+    let mut files = BTreeMap::new();
+    for entry in archive.entries().unwrap() {
+        let mut entry = entry.unwrap();
+        let path = entry.path().unwrap().to_str().unwrap().to_string();
+        let mode = entry.header().mode().unwrap();
+        let mut contents = Vec::new();
+        entry.read_to_end(&mut contents).unwrap();
+        files.insert(path, (contents, mode));
+    }
+    files
+}
+
+/// The files of a `.zip` archive with their contents and modes, read from
+/// its central directory.
+fn zip_files(p: &Project, archive: &str) -> BTreeMap<String, (Vec<u8>, u32)> {
+    let data = std::fs::read(p.root().join("target/package").join(archive)).unwrap();
+    let u16_at = |i: usize| u16::from_le_bytes([data[i], data[i + 1]]) as usize;
+    let u32_at = |i: usize| u32::from_le_bytes(data[i..i + 4].try_into().unwrap()) as usize;
+    let end = data.len() - 22;
+    assert_eq!(u32_at(end), 0x06054b50);
+    let mut files = BTreeMap::new();
+    let mut pos = u32_at(end + 16);
+    for _ in 0..u16_at(end + 10) {
+        assert_eq!(u32_at(pos), 0x02014b50);
+        let compressed_size = u32_at(pos + 20);
+        let name_len = u16_at(pos + 28);
+        let mode = (u32_at(pos + 38) >> 16) as u32 & 0o777;
+        let offset = u32_at(pos + 42);
+        let name = String::from_utf8(data[pos + 46..pos + 46 + name_len].to_vec()).unwrap();
+
+        assert_eq!(u32_at(offset), 0x04034b50);
+        let start = offset + 30 + u16_at(offset + 26) + u16_at(offset + 28);
+        let mut contents = Vec::new();
+        DeflateDecoder::new(&data[start..start + compressed_size])
+            .read_to_end(&mut contents)
+            .unwrap();
+        files.insert(name, (contents, mode));
+        pos += 46 + name_len;
+    }
+    files
+}
+
+#[cargo_test]
+fn gated() {
+    let p = project()
+        .file("Cargo.toml", &basic_bin_manifest("foo"))
+        .file("src/main.rs", "fn main() {}")
+        .build();
+    p.cargo("package --binary")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] Usage of `--binary` requires `-Z binary-package`")
+        .run();
+}
+
+#[cargo_test]
+fn tar_gz_archive() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                license = "MIT"
+                readme = "README.md"
+
+                [dependencies]
+                bar = { path = "bar" }
+
+                [build-dependencies]
+                build-only = { path = "build-only" }
+            "#,
+        )
+        .file("README.md", "# foo")
+        .file("LICENSE", "foo license")
+        .file("src/main.rs", "fn main() { bar::f(); }")
+        .file("build.rs", "fn main() { build_only::f(); }")
+        .file("completions/foo.bash", "complete -F _foo foo")
+        .file("man/foo.1", ".TH FOO 1")
+        .file(
+            "bar/Cargo.toml",
+            r#"
+                [package]
+                name = "bar"
+                version = "0.2.0"
+                license = "MIT OR Apache-2.0"
+            "#,
+        )
+        .file("bar/src/lib.rs", "pub fn f() {}")
+        .file("bar/LICENSE-MIT", "bar MIT license")
+        .file("bar/LICENSE-APACHE", "bar Apache license")
+        .file(
+            "build-only/Cargo.toml",
+            r#"
+                [package]
+                name = "build-only"
+                version = "0.1.0"
+                license = "MIT"
+            "#,
+        )
+        .file("build-only/src/lib.rs", "pub fn f() {}")
+        .file("build-only/LICENSE", "build-only license")
+        .build();
+    let host = rustc_host();
+    p.cargo("package -Zbinary-package --binary --archive-format tar.gz --include completions --include man/foo.1")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_unordered(&format!(
+            "\
+[COMPILING] bar v0.2.0 ([CWD]/bar)
+[COMPILING] build-only v0.1.0 ([CWD]/build-only)
+[COMPILING] foo v0.1.0 ([CWD])
+[FINISHED] release [optimized] target(s) in [..]
+[PACKAGING] foo v0.1.0 ([CWD]) ({})
+",
+            host
+        ))
+        .run();
+
+    let base = format!("foo-0.1.0-{}", host);
+    let archive = format!("{}.tar.gz", base);
+    let files = tar_gz_files(&p, &archive);
+    let names: Vec<_> = files
+        .keys()
+        .map(|name| name.strip_prefix(&format!("{}/", base)).unwrap())
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "README.md",
+            "completions/foo.bash",
+            "foo",
+            "licenses/README.txt",
+            "licenses/bar-0.2.0/LICENSE-APACHE",
+            "licenses/bar-0.2.0/LICENSE-MIT",
+            "licenses/foo-0.1.0/LICENSE",
+            "man/foo.1",
+        ]
+    );
+    let file = |name: &str| &files[&format!("{}/{}", base, name)];
+    assert_eq!(file("foo").1, 0o755);
+    assert_eq!(file("README.md").1, 0o644);
+    assert_eq!(file("licenses/bar-0.2.0/LICENSE-MIT").0, b"bar MIT license");
+    assert_eq!(
+        std::str::from_utf8(&file("licenses/README.txt").0).unwrap(),
+        "\
+The licenses of the packages linked into the binaries of foo v0.1.0.
+
+bar 0.2.0: MIT OR Apache-2.0
+foo 0.1.0: MIT
+"
+    );
+
+    let checksum = Sha256::new()
+        .update_path(p.root().join("target/package").join(&archive))
+        .unwrap()
+        .finish_hex();
+    assert_eq!(
+        p.read_file(&format!("target/package/{}.sha256", archive)),
+        format!("{}  {}\n", checksum, archive)
+    );
+}
+
+#[cargo_test]
+fn zip_archive() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                license = "MIT"
+                readme = "README.md"
+
+                [dependencies]
+                bar = { path = "bar" }
+
+                [build-dependencies]
+                build-only = { path = "build-only" }
+            "#,
+        )
+        .file("README.md", "# foo")
+        .file("LICENSE", "foo license")
+        .file("src/main.rs", "fn main() { bar::f(); }")
+        .file("build.rs", "fn main() { build_only::f(); }")
+        .file("completions/foo.bash", "complete -F _foo foo")
+        .file("man/foo.1", ".TH FOO 1")
+        .file(
+            "bar/Cargo.toml",
+            r#"
+                [package]
+                name = "bar"
+                version = "0.2.0"
+                license = "MIT OR Apache-2.0"
+            "#,
+        )
+        .file("bar/src/lib.rs", "pub fn f() {}")
+        .file("bar/LICENSE-MIT", "bar MIT license")
+        .file("bar/LICENSE-APACHE", "bar Apache license")
+        .file(
+            "build-only/Cargo.toml",
+            r#"
+                [package]
+                name = "build-only"
+                version = "0.1.0"
+                license = "MIT"
+            "#,
+        )
+        .file("build-only/src/lib.rs", "pub fn f() {}")
+        .file("build-only/LICENSE", "build-only license")
+        .build();
+    p.cargo("package -Zbinary-package --binary --archive-format zip")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    let base = format!("foo-0.1.0-{}", rustc_host());
+    let archive = format!("{}.zip", base);
+    let files = zip_files(&p, &archive);
+    assert_eq!(files.len(), 6);
+    let file = |name: &str| &files[&format!("{}/{}", base, name)];
+    assert_eq!(file("foo").1, 0o755);
+    assert_eq!(file("README.md"), &(b"# foo".to_vec(), 0o644));
+    assert_eq!(
+        file("licenses/bar-0.2.0/LICENSE-APACHE").0,
+        b"bar Apache license"
+    );
+    assert!(p
+        .root()
+        .join(format!("target/package/{}.sha256", archive))
+        .is_file());
+}
+
+#[cargo_test]
+fn dependency_without_license() {
+    Package::new("unlicensed", "0.1.0")
+        .file("src/lib.rs", "pub fn f() {}")
+        .publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                license = "MIT"
+
+                [dependencies]
+                unlicensed = "0.1"
+            "#,
+        )
+        .file("src/main.rs", "fn main() { unlicensed::f(); }")
+        .build();
+    p.cargo("package -Zbinary-package --binary")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "[WARNING] package `unlicensed v0.1.0` linked into the binaries of `foo` \
+             has no license information",
+        )
+        .run();
+
+    let base = format!("foo-0.1.0-{}", rustc_host());
+    let files = tar_gz_files(&p, &format!("{}.tar.gz", base));
+    let readme = &files[&format!("{}/licenses/README.txt", base)].0;
+    assert!(std::str::from_utf8(readme)
+        .unwrap()
+        .ends_with("foo 0.1.0: MIT\nunlicensed 0.1.0: unknown\n"));
+}
+
+#[cargo_test]
+fn missing_include() {
+    let p = project()
+        .file("Cargo.toml", &basic_bin_manifest("foo"))
+        .file("src/main.rs", "fn main() {}")
+        .build();
+    p.cargo("package -Zbinary-package --binary --include man")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[ERROR] the included path `man` doesn't exist in `[CWD]`")
+        .run();
+}
+
+#[cargo_test]
+fn no_binaries() {
+    let p = project()
+        .file("Cargo.toml", &basic_lib_manifest("foo"))
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("package -Zbinary-package --binary")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[ERROR] no binaries are available to package[..]")
+        .run();
+}
@@ -0,0 +1,219 @@
+//! Tests for `-Z package-hooks`.
+
+use cargo_test_support::{basic_manifest, paths, project, rustc_host};
+use std::path::PathBuf;
+
+/// Builds an executable standing in for a packaging tool.
+///
+/// It "packages" each binary it is given by copying it to `<path>.pkg`, and
+/// fails if `FAKE_HOOK_FAIL` is set.
+fn fake_hook() -> PathBuf {
+    let p = project()
+        .at(paths::global_root().join("fake-hook"))
+        .file("Cargo.toml", &basic_manifest("fake-hook", "1.0.0"))
+        .file(
+            "src/main.rs",
+            r##"
+                fn main() {
+                    if std::env::var_os("FAKE_HOOK_FAIL").is_some() {
+                        eprintln!("signing failed");
+                        std::process::exit(1);
+                    }
+                    let file = std::env::var("CARGO_ARTIFACTS").unwrap();
+                    let artifacts = std::fs::read_to_string(file).unwrap();
+                    eprintln!("assembling {}", std::env::args().nth(1).unwrap());
+                    for artifact in artifacts.split("},{") {
+                        if !artifact.contains(r#""kind":"bin""#) {
+                            continue;
+                        }
+                        let path = artifact.split(r#""path":""#).nth(1).unwrap();
+                        let path = &path[..path.find('"').unwrap()];
+                        let package = format!("{}.pkg", path);
+                        std::fs::copy(path, &package).unwrap();
+                        println!(r#"{{"artifact":"{}","package":"{}"}}"#, path, package);
+                    }
+                }
+            "##,
+        )
+        .build();
+    p.cargo("build").run();
+    p.bin("fake-hook")
+}
+
+#[cargo_test]
+fn package_hook_requires_z_flag() {
+    let hook = fake_hook();
+    let p = project()
+        .file("src/main.rs", r#"fn main() { println!("running"); }"#)
+        .file(
+            "src/lib.rs",
+            r#"
+                #[test]
+                fn unit() {}
+            "#,
+        )
+        .file(
+            ".cargo/config",
+            &format!(
+                r#"
+                    [target.{}]
+                    package-hook = ['{}', 'apk']
+                "#,
+                rustc_host(),
+                hook.display()
+            ),
+        )
+        .build();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(&format!(
+            "[ERROR] `target.{}.package-hook` requires `-Z package-hooks`",
+            rustc_host()
+        ))
+        .run();
+}
+
+#[cargo_test]
+fn package_after_build() {
+    let hook = fake_hook();
+    let p = project()
+        .file("src/main.rs", r#"fn main() { println!("running"); }"#)
+        .file(
+            "src/lib.rs",
+            r#"
+                #[test]
+                fn unit() {}
+            "#,
+        )
+        .file(
+            ".cargo/config",
+            &format!(
+                r#"
+                    [target.{}]
+                    package-hook = ['{}', 'apk']
+                "#,
+                rustc_host(),
+                hook.display()
+            ),
+        )
+        .build();
+
+    p.cargo("build -Zpackage-hooks")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(&format!(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+[PACKAGING] foo for {}
+assembling apk
+",
+            rustc_host()
+        ))
+        .run();
+
+    let artifacts = p.read_file("target/debug/package-hook.json");
+    assert!(artifacts.starts_with(&format!(
+        r#"{{"target":"{}","profile":"dev","artifacts":[{{"package":"foo","name":"foo","kind":"bin","test":false,"path":"#,
+        rustc_host()
+    )));
+    assert!(p.root().join("target/debug/foo.pkg").is_file());
+
+    // Nothing is packaged when there is nothing to package.
+    p.cargo("check -Zpackage-hooks")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[CHECKING] foo v0.0.1 ([CWD])
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn run_packaged() {
+    let hook = fake_hook();
+    let p = project()
+        .file("src/main.rs", r#"fn main() { println!("running"); }"#)
+        .file(
+            "src/lib.rs",
+            r#"
+                #[test]
+                fn unit() {}
+            "#,
+        )
+        .file(
+            ".cargo/config",
+            &format!(
+                r#"
+                    [target.{}]
+                    package-hook = ['{}', 'apk']
+                "#,
+                rustc_host(),
+                hook.display()
+            ),
+        )
+        .build();
+
+    p.cargo("run -Zpackage-hooks")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[PACKAGING] foo for [..]")
+        .with_stderr_contains("[RUNNING] `target/debug/foo.pkg`")
+        .with_stdout("running")
+        .run();
+
+    // Test executables are handed to the hook too, which leaves them alone.
+    p.cargo("test -Zpackage-hooks --lib")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[PACKAGING] foo for [..]")
+        .with_stderr_contains("[RUNNING] [..] (target/debug/deps/foo-[..][EXE])")
+        .with_stdout_contains("test unit ... ok")
+        .run();
+}
+
+#[cargo_test]
+fn package_hook_fails() {
+    let hook = fake_hook();
+    let p = project()
+        .file("src/main.rs", r#"fn main() { println!("running"); }"#)
+        .file(
+            "src/lib.rs",
+            r#"
+                #[test]
+                fn unit() {}
+            "#,
+        )
+        .file(
+            ".cargo/config",
+            &format!(
+                r#"
+                    [target.{}]
+                    package-hook = ['{}', 'apk']
+                "#,
+                rustc_host(),
+                hook.display()
+            ),
+        )
+        .build();
+
+    p.cargo("build -Zpackage-hooks")
+        .masquerade_as_nightly_cargo()
+        .env("FAKE_HOOK_FAIL", "1")
+        .with_status(101)
+        .with_stderr(&format!(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+[PACKAGING] foo for {0}
+signing failed
+[ERROR] package hook for `{0}` failed, set in `target.{0}.package-hook`
+
+Caused by:
+  process didn't exit successfully: `[..]fake-hook[EXE] apk` (exit status: 1)
+",
+            rustc_host()
+        ))
+        .run();
+}
@@ -0,0 +1,193 @@
+//! Tests for `cargo patch`.
+
+use cargo_test_support::registry::Package;
+use cargo_test_support::{basic_manifest, project};
+
+#[cargo_test]
+fn patch_requires_z_flag() {
+    let p = project().file("src/lib.rs", "").build();
+
+    // Without the flag, it is left to an external `cargo-patch`.
+    p.cargo("patch explain")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[ERROR] no such subcommand: `patch`")
+        .run();
+}
+
+#[cargo_test]
+fn add_path_patch() {
+    Package::new("bar", "1.0.0").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("bar-fork/Cargo.toml", &basic_manifest("bar", "1.0.1"))
+        .file("bar-fork/src/lib.rs", "")
+        .file("bar-next/Cargo.toml", &basic_manifest("bar", "2.0.0"))
+        .file("bar-next/src/lib.rs", "")
+        .build();
+    p.cargo("generate-lockfile").run();
+
+    p.cargo("-Zpatch patch add bar --path bar-fork")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[ADDING] bar v1.0.1 ([ROOT]/foo/bar-fork) to patch.crates-io
+[UPDATING] `dummy-registry` index
+[UPDATING] bar v1.0.0 -> bar v1.0.1 ([ROOT]/foo/bar-fork)
+",
+        )
+        .run();
+    assert!(p
+        .read_file("Cargo.toml")
+        .ends_with("\n[patch.crates-io]\nbar = { path = \"bar-fork\" }\n"));
+    assert!(p.read_file("Cargo.lock").contains(
+        r#"
+[[package]]
+name = "bar"
+version = "1.0.1"
+"#
+    ));
+
+    p.cargo("-Zpatch patch add bar --path bar-fork")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to add the patch of `bar`
+
+Caused by:
+  `bar` is already in `[patch.crates-io]`
+",
+        )
+        .run();
+
+    p.cargo("-Zpatch patch explain")
+        .masquerade_as_nightly_cargo()
+        .with_stdout(
+            "\
+bar v1.0.1 ([ROOT]/foo/bar-fork) patches `bar` of crates-io: applied
+  used by `foo v0.1.0 ([ROOT]/foo)`, which requires `bar ^1.0`
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn add_unapplied_patch() {
+    Package::new("bar", "1.0.0").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("bar-fork/Cargo.toml", &basic_manifest("bar", "1.0.1"))
+        .file("bar-fork/src/lib.rs", "")
+        .file("bar-next/Cargo.toml", &basic_manifest("bar", "2.0.0"))
+        .file("bar-next/src/lib.rs", "")
+        .build();
+    let manifest = p.read_file("Cargo.toml");
+
+    p.cargo("-Zpatch patch add bar --path bar-next")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+[ERROR] the patch of `bar` isn't applied
+  `foo v0.1.0 ([ROOT]/foo)` requires `bar ^1.0`, which v2.0.0 of the patch doesn't match
+",
+        )
+        .run();
+    assert_eq!(p.read_file("Cargo.toml"), manifest);
+
+    p.cargo("-Zpatch patch add baz --path bar-fork")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] the package at `[ROOT]/foo/bar-fork` is `bar`, not `baz`")
+        .run();
+    assert_eq!(p.read_file("Cargo.toml"), manifest);
+}
+
+#[cargo_test]
+fn explain_unapplied_patches() {
+    Package::new("bar", "1.0.0").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("bar-fork/Cargo.toml", &basic_manifest("bar", "1.0.1"))
+        .file("bar-fork/src/lib.rs", "")
+        .file("bar-next/Cargo.toml", &basic_manifest("bar", "2.0.0"))
+        .file("bar-next/src/lib.rs", "")
+        .build();
+    p.change_file(
+        "Cargo.toml",
+        r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+
+            [dependencies]
+            bar = "1.0"
+
+            [patch.crates-io]
+            bar = { path = "bar-next" }
+            baz = { path = "baz" }
+        "#,
+    );
+    p.change_file("baz/Cargo.toml", &basic_manifest("baz", "1.0.0"));
+    p.change_file("baz/src/lib.rs", "");
+
+    p.cargo("-Zpatch patch explain")
+        .masquerade_as_nightly_cargo()
+        .with_stdout(
+            "\
+bar v2.0.0 ([ROOT]/foo/bar-next) patches `bar` of crates-io: not applied
+  `foo v0.1.0 ([ROOT]/foo)` requires `bar ^1.0`, which v2.0.0 of the patch doesn't match
+baz v1.0.0 ([ROOT]/foo/baz) patches `baz` of crates-io: not applied
+  no package of the workspace depends on `baz` from the patched source
+",
+        )
+        .run();
+    p.cargo("-Zpatch patch explain baz")
+        .masquerade_as_nightly_cargo()
+        .with_stdout(
+            "\
+baz v1.0.0 ([ROOT]/foo/baz) patches `baz` of crates-io: not applied
+  no package of the workspace depends on `baz` from the patched source
+",
+        )
+        .run();
+    p.cargo("-Zpatch patch explain qux")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[ERROR] the workspace has no patch for `qux`")
+        .run();
+}
@@ -0,0 +1,231 @@
+//! Tests for `cargo build --emit-pc` and the `[package.pkg-config]` table.
+
+use cargo_test_support::{basic_lib_manifest, project};
+
+#[cargo_test]
+fn gated() {
+    let p = project()
+        .file("Cargo.toml", &basic_lib_manifest("foo"))
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("build --emit-pc")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] Usage of `--emit-pc` requires `-Z pkg-config`")
+        .run();
+}
+
+#[cargo_test]
+fn table_requires_cargo_feature() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [package.pkg-config]
+                name = "foo"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("  feature `pkg-config` is required")
+        .run();
+}
+
+#[cargo_test]
+fn invalid_name() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["pkg-config"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                description = "The foo library"
+
+                [package.pkg-config]
+                name = "lib/foo"
+
+                [lib]
+                crate-type = ["cdylib"]
+            "#,
+        )
+        .file("src/lib.rs", "#[no_mangle] pub extern \"C\" fn foo() {}")
+        .build();
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "  `package.pkg-config.name` must be a file name without spaces, found `lib/foo`",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn cdylib() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["pkg-config"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                description = "The foo library"
+
+                [package.pkg-config]
+
+
+                [lib]
+                crate-type = ["cdylib"]
+            "#,
+        )
+        .file("src/lib.rs", "#[no_mangle] pub extern \"C\" fn foo() {}")
+        .build();
+    p.cargo("build -Zpkg-config")
+        .masquerade_as_nightly_cargo()
+        .run();
+    assert!(!p.root().join("target/debug/pkgconfig").exists());
+
+    p.cargo("build -Zpkg-config --emit-pc")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[FINISHED] [..]")
+        .run();
+    assert_eq!(
+        p.read_file("target/debug/pkgconfig/foo.pc"),
+        "\
+prefix=${pcfiledir}/..
+libdir=${prefix}
+includedir=${prefix}/include
+
+Name: foo
+Description: The foo library
+Version: 0.1.0
+Libs: -L${libdir} -lfoo
+"
+    );
+}
+
+#[cargo_test]
+fn configured_fields() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                    cargo-features = ["pkg-config"]
+
+                    [package]
+                    name = "foo"
+                    version = "0.1.0"
+                    description = "The foo library"
+
+                    [package.pkg-config]
+
+                name = "foo-1"
+                description = "Foo for C"
+                requires = ["zlib >= 1.2"]
+                requires-private = ["bar"]
+                cflags = ["-DFOO"]
+                libs = ["-lextra"]
+                libs-private = ["-lprivate"]
+
+
+                    [lib]
+                    crate-type = ["cdylib", "staticlib"]
+            "#,
+        )
+        .file("src/lib.rs", "#[no_mangle] pub extern \"C\" fn foo() {}")
+        .build();
+    p.cargo("build -Zpkg-config --emit-pc")
+        .masquerade_as_nightly_cargo()
+        .run();
+    let pc = p.read_file("target/debug/pkgconfig/foo-1.pc");
+    let lines: Vec<_> = pc.lines().collect();
+    assert_eq!(
+        lines[4..9],
+        [
+            "Name: foo-1",
+            "Description: Foo for C",
+            "Version: 0.1.0",
+            "Requires: zlib >= 1.2",
+            "Requires.private: bar",
+        ]
+    );
+    assert_eq!(lines[9], "Libs: -L${libdir} -lfoo -lextra");
+    // The native libraries of the staticlib printed by rustc.
+    assert!(
+        lines[10].starts_with("Libs.private: -lprivate -"),
+        "{}",
+        lines[10]
+    );
+    assert_eq!(lines[11], "Cflags: -DFOO");
+    assert_eq!(lines.len(), 12);
+}
+
+#[cargo_test]
+fn staticlib_native_libs() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["pkg-config"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                description = "The foo library"
+
+                [package.pkg-config]
+
+
+                [lib]
+                crate-type = ["staticlib"]
+            "#,
+        )
+        .file("src/lib.rs", "#[no_mangle] pub extern \"C\" fn foo() {}")
+        .build();
+    p.cargo("build -Zpkg-config --emit-pc")
+        .masquerade_as_nightly_cargo()
+        .run();
+    let pc = p.read_file("target/debug/pkgconfig/foo.pc");
+    let libs = pc.lines().find(|l| l.starts_with("Libs")).unwrap();
+    assert!(libs.starts_with("Libs: -L${libdir} -lfoo -"), "{}", libs);
+    assert!(!pc.contains("Libs.private"));
+}
+
+#[cargo_test]
+fn not_for_rlib() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["pkg-config"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                description = "The foo library"
+
+                [package.pkg-config]
+
+
+                [lib]
+                crate-type = ["rlib"]
+            "#,
+        )
+        .file("src/lib.rs", "#[no_mangle] pub extern \"C\" fn foo() {}")
+        .build();
+    p.cargo("build -Zpkg-config --emit-pc")
+        .masquerade_as_nightly_cargo()
+        .run();
+    assert!(!p.root().join("target/debug/pkgconfig").exists());
+}
@@ -0,0 +1,194 @@
+//! Tests for the `cargo plugin` command.
+
+use cargo_test_support::cargo_process;
+use cargo_test_support::install::{assert_has_installed_exe, cargo_home};
+use cargo_test_support::registry::{self, Package};
+
+/// Publishes the plugin `cargo-foo`, whose `cargo foo` prints the registry
+/// token and permissions it is run with.
+fn publish_plugin(vers: &str, plugin: &str) {
+    Package::new("cargo-foo", vers)
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    [package]
+                    name = "cargo-foo"
+                    version = "{}"
+
+                    {}
+                "#,
+                vers, plugin
+            ),
+        )
+        .file(
+            "src/main.rs",
+            &format!(
+                r#"
+                    fn main() {{
+                        println!("v{}");
+                        println!("token: {{:?}}", std::env::var("CARGO_REGISTRY_TOKEN").ok());
+                        println!("permissions: {{:?}}", std::env::var("CARGO_PLUGIN_PERMISSIONS").ok());
+                    }}
+                "#,
+                vers
+            ),
+        )
+        .publish();
+}
+
+const PLUGIN: &str = "[package.metadata.cargo-plugin]";
+
+#[cargo_test]
+fn requires_z_flag() {
+    cargo_process("plugin list")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] the `cargo plugin` command is unstable, pass `-Z plugin` to enable it",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn install_and_run() {
+    registry::init();
+    publish_plugin("1.0.0", PLUGIN);
+
+    cargo_process("plugin install cargo-foo -Zplugin")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "[INSTALLED] package `cargo-foo v1.0.0` (executable `cargo-foo[EXE]`)",
+        )
+        .run();
+    assert_has_installed_exe(cargo_home(), "cargo-foo");
+
+    cargo_process("plugin list -Zplugin")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("cargo-foo v1.0.0: cargo foo")
+        .run();
+
+    // The plugin is not given the registry token.
+    cargo_process("foo")
+        .env("CARGO_REGISTRY_TOKEN", "sekrit")
+        .with_stdout(
+            "\
+v1.0.0
+token: None
+permissions: Some(\"\")
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn not_a_plugin() {
+    registry::init();
+    publish_plugin("1.0.0", "");
+
+    cargo_process("plugin install cargo-foo -Zplugin")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "[ERROR] `cargo-foo` is not a Cargo plugin, its manifest has no \
+             `[package.metadata.cargo-plugin]` table",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn incompatible_cargo_version() {
+    registry::init();
+    publish_plugin("1.0.0", &format!("{}\ncargo-version = \">=99\"", PLUGIN));
+
+    cargo_process("plugin install cargo-foo -Zplugin")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[ERROR] `cargo-foo v1.0.0` needs Cargo >=99, but this is Cargo [..]")
+        .run();
+}
+
+#[cargo_test]
+fn permissions() {
+    registry::init();
+    publish_plugin(
+        "1.0.0",
+        &format!("{}\npermissions = [\"credentials\"]", PLUGIN),
+    );
+
+    cargo_process("plugin install cargo-foo -Zplugin")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+[ERROR] `cargo-foo` needs the permission `credentials` to receive the registry tokens of the environment
+Pass `--allow credentials` to grant it.",
+        )
+        .run();
+
+    cargo_process("plugin install cargo-foo -Zplugin --allow credentials")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    cargo_process("plugin list -Zplugin")
+        .masquerade_as_nightly_cargo()
+        .with_stdout(
+            "\
+cargo-foo v1.0.0: cargo foo
+    permissions: credentials
+",
+        )
+        .run();
+
+    cargo_process("foo")
+        .env("CARGO_REGISTRY_TOKEN", "sekrit")
+        .with_stdout(
+            "\
+v1.0.0
+token: Some(\"sekrit\")
+permissions: Some(\"credentials\")
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn update() {
+    registry::init();
+    publish_plugin("1.0.0", PLUGIN);
+
+    cargo_process("plugin install cargo-foo -Zplugin")
+        .masquerade_as_nightly_cargo()
+        .run();
+    cargo_process("plugin update -Zplugin")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[FRESH] cargo-foo v1.0.0 is up to date")
+        .run();
+
+    publish_plugin("1.1.0", PLUGIN);
+    cargo_process("plugin update cargo-foo -Zplugin")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[REPLACED] package `cargo-foo v1.0.0` with `cargo-foo v1.1.0` [..]")
+        .run();
+    cargo_process("foo").with_stdout_contains("v1.1.0").run();
+
+    // A version that doesn't work with this Cargo is skipped.
+    publish_plugin("2.0.0", &format!("{}\ncargo-version = \">=99\"", PLUGIN));
+    cargo_process("plugin update -Zplugin")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "[WARNING] not updating cargo-foo v1.1.0 to v2.0.0: \
+             `cargo-foo v2.0.0` needs Cargo >=99, but this is Cargo [..]",
+        )
+        .run();
+    cargo_process("plugin list -Zplugin")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("cargo-foo v1.1.0: cargo foo")
+        .run();
+
+    cargo_process("plugin update cargo-bar -Zplugin")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] the plugin `cargo-bar` is not installed")
+        .run();
+}
@@ -348,6 +348,56 @@ fn overrides_with_custom() {
         .run();
 }
 
+#[cargo_test]
+fn multi_level_inherits() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                authors = []
+
+                [dependencies]
+                xxx = {path = "xxx"}
+
+                [profile.release.package.xxx]
+                codegen-units = 5
+
+                [profile.base]
+                inherits = "release"
+                debug = 1
+
+                [profile.middle]
+                inherits = "base"
+                codegen-units = 3
+
+                [profile.leaf]
+                inherits = "middle"
+                opt-level = 2
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("xxx/Cargo.toml", &basic_lib_manifest("xxx"))
+        .file("xxx/src/lib.rs", "")
+        .build();
+
+    // Settings and package overrides are merged along the whole chain.
+    p.cargo("build --profile=leaf -v")
+        .with_stderr_unordered(
+            "\
+[COMPILING] xxx [..]
+[COMPILING] foo [..]
+[RUNNING] `rustc --crate-name xxx [..] -C opt-level=2 [..]-C codegen-units=5 -C debuginfo=1 [..]`
+[RUNNING] `rustc --crate-name foo [..] -C opt-level=2 [..]-C codegen-units=3 -C debuginfo=1 [..]`
+[FINISHED] leaf [optimized + debuginfo] target(s) in [..]
+",
+        )
+        .run();
+    assert!(p.root().join("target/leaf").is_dir());
+}
+
 #[cargo_test]
 fn conflicting_usage() {
     let p = project()
@@ -513,3 +513,144 @@ fn build_override_shared() {
 
     p.cargo("run").run();
 }
+
+#[cargo_test]
+fn profile_override_glob_requires_feature() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [profile.dev.package."my-org-*"]
+                opt-level = 3
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[..]feature `profile-package-globs` is required[..]")
+        .run();
+}
+
+#[cargo_test]
+fn profile_override_glob() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["profile-package-globs"]
+
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [dependencies]
+                my-org-a = {path = "a"}
+                my-org-core-b = {path = "b"}
+                my-org-core-c = {path = "c"}
+                other = {path = "other"}
+
+                [profile.dev.package."*"]
+                opt-level = 1
+
+                [profile.dev.package."my-org-*"]
+                opt-level = 2
+                codegen-units = 3
+
+                [profile.dev.package."my-org-core-*"]
+                opt-level = 3
+
+                [profile.dev.package.my-org-core-c]
+                opt-level = "s"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("a/Cargo.toml", &basic_manifest("my-org-a", "0.5.0"))
+        .file("a/src/lib.rs", "")
+        .file("b/Cargo.toml", &basic_manifest("my-org-core-b", "0.5.0"))
+        .file("b/src/lib.rs", "")
+        .file("c/Cargo.toml", &basic_manifest("my-org-core-c", "0.5.0"))
+        .file("c/src/lib.rs", "")
+        .file("other/Cargo.toml", &basic_lib_manifest("other"))
+        .file("other/src/lib.rs", "")
+        .build();
+
+    // The most specific pattern wins, and a named package beats any pattern.
+    p.cargo("build -v")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[RUNNING] `rustc --crate-name my_org_a [..] -C opt-level=2 [..]-C codegen-units=3 [..]`")
+        .with_stderr_contains("[RUNNING] `rustc --crate-name my_org_core_b [..] -C opt-level=3 [..]`")
+        .with_stderr_line_without(
+            &["[RUNNING] `rustc --crate-name my_org_core_b"],
+            &["codegen-units"],
+        )
+        .with_stderr_contains("[RUNNING] `rustc --crate-name my_org_core_c [..] -C opt-level=s [..]`")
+        .with_stderr_contains("[RUNNING] `rustc --crate-name other [..] -C opt-level=1 [..]`")
+        .with_stderr_line_without(&["[RUNNING] `rustc --crate-name foo"], &["opt-level"])
+        .run();
+}
+
+#[cargo_test]
+fn profile_override_glob_invalid() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["profile-package-globs"]
+
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [profile.dev.package."my-*-org"]
+                opt-level = 3
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "  invalid package name pattern `my-*-org`, a `*` may only end the pattern[..]",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn profile_override_glob_unmatched() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["profile-package-globs"]
+
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [profile.dev.package."my-org-*"]
+                opt-level = 3
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[WARNING] profile package pattern `my-org-*` in profile `dev` did not match any packages
+[COMPILING] foo [..]
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+",
+        )
+        .run();
+}
@@ -214,6 +214,8 @@ fn top_level_overrides_deps() {
         -C debuginfo=2 \
         -C metadata=[..] \
         --out-dir [CWD]/target/release/deps \
+        --remap-path-scope=object \
+        --remap-path-prefix=[CWD]= \
         -L dependency=[CWD]/target/release/deps`
 [COMPILING] test v0.0.0 ([CWD])
 [RUNNING] `rustc --crate-name test src/lib.rs [..]--crate-type lib \
@@ -222,6 +224,8 @@ fn top_level_overrides_deps() {
         -C debuginfo=2 \
         -C metadata=[..] \
         --out-dir [..] \
+        --remap-path-scope=object \
+        --remap-path-prefix=[CWD]= \
         -L dependency=[CWD]/target/release/deps \
         --extern foo=[CWD]/target/release/deps/\
                      {prefix}foo[..]{suffix} \
@@ -505,6 +509,78 @@ fn strip_works() {
         .run();
 }
 
+#[cargo_test]
+fn strip_separate() {
+    if !cfg!(target_os = "linux") {
+        // The debuginfo is only separated with objcopy on ELF targets.
+        return;
+    }
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["strip"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [profile.release]
+                strip = "separate"
+            "#,
+        )
+        .file("src/main.rs", "fn main() { println!(\"hello\"); }")
+        .build();
+
+    p.cargo("build --release --message-format=json")
+        .masquerade_as_nightly_cargo()
+        .with_json_contains_unordered(
+            r#"
+                {
+                    "reason": "compiler-artifact",
+                    "package_id": "foo 0.1.0 [..]",
+                    "manifest_path": "[..]",
+                    "target": "{...}",
+                    "profile": {
+                        "opt_level": "3",
+                        "debuginfo": 2,
+                        "debug_assertions": false,
+                        "overflow_checks": false,
+                        "test": false
+                    },
+                    "features": [],
+                    "filenames": [
+                        "[..]/target/release/foo",
+                        "[..]/target/release/foo.debug"
+                    ],
+                    "executable": "[..]/target/release/foo",
+                    "fresh": false
+                }
+
+                {"reason": "build-finished", "success": true}
+            "#,
+        )
+        .run();
+
+    // The debuginfo of std alone is much larger than the stripped binary.
+    let len = |path: &str| p.root().join(path).metadata().unwrap().len();
+    assert!(len("target/release/foo.debug") > len("target/release/foo"));
+    p.process(p.release_bin("foo")).with_stdout("hello").run();
+
+    // A fresh build reports the same files.
+    p.cargo("build --release -v")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[FRESH] foo [..]
+[FINISHED] [..]
+",
+        )
+        .run();
+    assert!(p.root().join("target/release/foo.debug").is_file());
+}
+
 #[cargo_test]
 fn strip_requires_cargo_feature() {
     if !is_nightly() {
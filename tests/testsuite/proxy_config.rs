@@ -0,0 +1,290 @@
+//! Tests for the proxy settings of `-Z proxy-config`.
+
+use std::env;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::path::PathBuf;
+use std::thread;
+
+use cargo_test_support::registry::{self, Package};
+use cargo_test_support::{git, paths, project, Execs};
+
+const PROXY_ENV_VARS: [&str; 4] = ["http_proxy", "HTTP_PROXY", "https_proxy", "HTTPS_PROXY"];
+
+/// Serves the `.crate` files of the registry over HTTP, directly or as a
+/// proxy, answering `count` requests, and points the registry at it.
+/// Returns the request line of each request.
+fn serve(count: usize) -> (SocketAddr, thread::JoinHandle<Vec<String>>) {
+    let server = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = server.local_addr().unwrap();
+    let repo = git2::Repository::open(registry::registry_path()).unwrap();
+    fs::write(
+        registry::registry_path().join("config.json"),
+        format!(
+            r#"{{"dl": "http://{}/{{crate}}/{{version}}/download", "api": "{}"}}"#,
+            addr,
+            registry::api_url()
+        ),
+    )
+    .unwrap();
+    git::add(&repo);
+    git::commit(&repo);
+
+    let dl_path = registry::dl_path();
+    let handle = thread::spawn(move || {
+        let mut requests = Vec::new();
+        for _ in 0..count {
+            let (conn, _) = server.accept().unwrap();
+            let mut conn = BufReader::new(conn);
+            let headers: Vec<String> = (&mut conn)
+                .lines()
+                .map(|line| line.unwrap())
+                .take_while(|line| !line.is_empty())
+                .collect();
+            let target = headers[0].split(' ').nth(1).unwrap();
+            let path = match target.strip_prefix("http://") {
+                Some(url) => &url[url.find('/').unwrap()..],
+                None => target,
+            };
+            let data = fs::read(dl_path.join(&path[1..])).unwrap();
+            let stream = conn.get_mut();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                data.len()
+            )
+            .unwrap();
+            stream.write_all(&data).unwrap();
+            requests.push(headers[0].clone());
+        }
+        requests
+    });
+    (addr, handle)
+}
+
+/// Installs a fake `gsettings` answering `settings`, pairs of `schema key`
+/// and value, and returns the `PATH` running it.
+fn fake_gsettings(settings: &[(&str, &str)]) -> PathBuf {
+    let dir = paths::root().join("fake-gsettings");
+    fs::create_dir_all(&dir).unwrap();
+    let mut script = String::from("#!/bin/sh\ncase \"$2 $3\" in\n");
+    for (key, value) in settings {
+        script.push_str(&format!("  \"{}\") echo \"{}\" ;;\n", key, value));
+    }
+    script.push_str("  *) exit 1 ;;\nesac\n");
+    let path = dir.join("gsettings");
+    fs::write(&path, script).unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    let mut paths = vec![dir];
+    paths.extend(env::split_paths(&env::var_os("PATH").unwrap_or_default()));
+    PathBuf::from(env::join_paths(paths).unwrap())
+}
+
+fn without_proxy_env(execs: &mut Execs) -> &mut Execs {
+    for var in PROXY_ENV_VARS {
+        execs.env_remove(var);
+    }
+    execs
+}
+
+#[cargo_test]
+fn gated() {
+    Package::new("bar", "1.0.0").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            r#"
+                [registry]
+                proxy = ""
+            "#,
+        )
+        .build();
+    without_proxy_env(&mut p.cargo("fetch"))
+        .with_status(101)
+        .with_stderr_contains("[..]`registry.proxy` requires `-Z proxy-config`")
+        .run();
+
+    p.change_file(
+        ".cargo/config",
+        r#"
+            [http]
+            system-proxy = false
+        "#,
+    );
+    without_proxy_env(&mut p.cargo("fetch"))
+        .with_status(101)
+        .with_stderr_contains("[..]`http.system-proxy` requires `-Z proxy-config`")
+        .run();
+}
+
+// The fake `gsettings` is a shell script, and the GNOME settings are only
+// read on these platforms.
+#[cfg(all(unix, not(target_os = "macos")))]
+#[cargo_test]
+fn system_proxy_and_registry_override() {
+    Package::new("bar", "1.0.0").publish();
+    let (addr, server) = serve(2);
+    let port = addr.port().to_string();
+    let path = fake_gsettings(&[
+        ("org.gnome.system.proxy mode", "'manual'"),
+        (
+            "org.gnome.system.proxy ignore-hosts",
+            "['localhost', '*.example']",
+        ),
+        ("org.gnome.system.proxy.https host", "'127.0.0.1'"),
+        ("org.gnome.system.proxy.https port", &port),
+    ]);
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(".cargo/config", "")
+        .build();
+
+    // The crate is downloaded through the proxy of the system.
+    without_proxy_env(&mut p.cargo("fetch -Zproxy-config"))
+        .masquerade_as_nightly_cargo()
+        .env("PATH", &path)
+        .with_stderr_contains("[DOWNLOADED] bar v1.0.0 (registry `dummy-registry`)")
+        .run();
+
+    without_proxy_env(&mut p.cargo("config show-proxy -Zunstable-options -Zproxy-config"))
+        .masquerade_as_nightly_cargo()
+        .env("PATH", &path)
+        .with_stdout(&format!(
+            "\
+crates-io: http://127.0.0.1:{port} (GNOME proxy settings)
+system proxy: http://127.0.0.1:{port} (GNOME proxy settings)
+system proxy exceptions: localhost, example
+",
+            port = port
+        ))
+        .run();
+
+    // The registry doesn't use a proxy.
+    p.change_file(
+        ".cargo/config",
+        r#"
+            [registry]
+            proxy = ""
+        "#,
+    );
+    fs::remove_dir_all(paths::home().join(".cargo/registry/cache")).unwrap();
+    without_proxy_env(&mut p.cargo("fetch -Zproxy-config"))
+        .masquerade_as_nightly_cargo()
+        .env("PATH", &path)
+        .with_stderr_contains("[DOWNLOADED] bar v1.0.0 (registry `dummy-registry`)")
+        .run();
+
+    let requests = server.join().unwrap();
+    assert_eq!(
+        requests[0],
+        format!("GET http://{}/bar/1.0.0/download HTTP/1.1", addr)
+    );
+    assert_eq!(requests[1], "GET /bar/1.0.0/download HTTP/1.1");
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+#[cargo_test]
+fn system_proxy_auto_config() {
+    let path = fake_gsettings(&[
+        ("org.gnome.system.proxy mode", "'auto'"),
+        (
+            "org.gnome.system.proxy autoconfig-url",
+            "'http://wpad.example/wpad.dat'",
+        ),
+    ]);
+    let p = project().file("src/lib.rs", "").build();
+    without_proxy_env(&mut p.cargo("config show-proxy -Zunstable-options -Zproxy-config"))
+        .masquerade_as_nightly_cargo()
+        .env("PATH", &path)
+        .with_stdout(
+            "\
+crates-io: no proxy
+system proxy: none (GNOME proxy settings)
+system proxy auto-config: http://wpad.example/wpad.dat (not evaluated)
+",
+        )
+        .with_stderr(
+            "\
+[WARNING] the GNOME proxy settings use the proxy auto-config file \
+`http://wpad.example/wpad.dat`, which Cargo can't evaluate, so no proxy is used
+Set `http.proxy` to the proxy to use, or set `http.system-proxy` to `false` \
+to disable the detection.
+",
+        )
+        .run();
+
+    // The detection is disabled.
+    p.change_file(
+        ".cargo/config",
+        r#"
+            [http]
+            system-proxy = false
+        "#,
+    );
+    without_proxy_env(&mut p.cargo("config show-proxy -Zunstable-options -Zproxy-config"))
+        .masquerade_as_nightly_cargo()
+        .env("PATH", &path)
+        .with_stdout(
+            "\
+crates-io: no proxy
+",
+        )
+        .with_stderr("")
+        .run();
+}
+
+#[cargo_test]
+fn show_proxy() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            r#"
+                [http]
+                proxy = "http://proxy.example:3128"
+                system-proxy = false
+
+                [registries.internal]
+                index = "https://internal.example/index"
+                proxy = ""
+            "#,
+        )
+        .build();
+    without_proxy_env(&mut p.cargo("config show-proxy -Zunstable-options -Zproxy-config"))
+        .masquerade_as_nightly_cargo()
+        .with_stdout(
+            "\
+crates-io: http://proxy.example:3128 (`http.proxy` in [..]/foo/.cargo/config)
+internal: no proxy (`registries.internal.proxy` in [..]/foo/.cargo/config)
+",
+        )
+        .run();
+}
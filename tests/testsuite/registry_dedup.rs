@@ -0,0 +1,140 @@
+//! Tests for `-Z registry-dedup`, which relies on hard links.
+
+#![cfg(unix)]
+
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::PathBuf;
+
+use cargo_test_support::registry::Package;
+use cargo_test_support::{paths, project, Project};
+
+fn src(package: &str, file: &str) -> PathBuf {
+    let src = paths::home().join(".cargo/registry/src");
+    let registry = std::fs::read_dir(&src).unwrap().next().unwrap().unwrap();
+    registry.path().join(package).join(file)
+}
+
+fn inode(package: &str, file: &str) -> u64 {
+    std::fs::metadata(src(package, file)).unwrap().ino()
+}
+
+fn depend_on(p: &Project, req: &str) {
+    p.change_file(
+        "Cargo.toml",
+        &format!(
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "={}"
+            "#,
+            req
+        ),
+    );
+}
+
+#[cargo_test]
+fn dedup_between_versions() {
+    for (version, changed) in [("1.0.0", "one"), ("1.0.1", "two")] {
+        Package::new("bar", version)
+            .file("src/lib.rs", "pub mod data;")
+            .file(
+                "src/data.rs",
+                &format!("pub const V: &str = \"{}\";", changed),
+            )
+            .file("README.md", "The same in every version.")
+            .file_with_mode("run.sh", 0o755, "echo same")
+            .file_with_mode("notes.sh", 0o644, "echo same")
+            .publish();
+    }
+    let p = project().file("src/lib.rs", "").build();
+
+    depend_on(&p, "1.0.0");
+    p.cargo("build -Z registry-dedup")
+        .masquerade_as_nightly_cargo()
+        .run();
+    depend_on(&p, "1.0.1");
+    p.cargo("build -Z registry-dedup")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    assert_eq!(
+        inode("bar-1.0.0", "README.md"),
+        inode("bar-1.0.1", "README.md")
+    );
+    assert_eq!(
+        inode("bar-1.0.0", "src/lib.rs"),
+        inode("bar-1.0.1", "src/lib.rs")
+    );
+    assert_ne!(
+        inode("bar-1.0.0", "src/data.rs"),
+        inode("bar-1.0.1", "src/data.rs")
+    );
+    assert_eq!(inode("bar-1.0.0", "run.sh"), inode("bar-1.0.1", "run.sh"));
+    // Hard links share their permissions, so executables are stored apart.
+    assert_ne!(inode("bar-1.0.0", "run.sh"), inode("bar-1.0.0", "notes.sh"));
+    assert_eq!(
+        std::fs::metadata(src("bar-1.0.1", "run.sh"))
+            .unwrap()
+            .mode()
+            & 0o111,
+        0o111
+    );
+    assert!(paths::home().join(".cargo/registry/cas").is_dir());
+}
+
+#[cargo_test]
+fn changed_store_files_are_replaced() {
+    for version in ["1.0.0", "1.0.1"] {
+        Package::new("bar", version)
+            .file("src/lib.rs", "pub fn bar() {}")
+            .publish();
+    }
+    let p = project().file("src/lib.rs", "").build();
+
+    depend_on(&p, "1.0.0");
+    p.cargo("build -Z registry-dedup")
+        .masquerade_as_nightly_cargo()
+        .run();
+    let lib = src("bar-1.0.0", "src/lib.rs");
+    let mut permissions = std::fs::metadata(&lib).unwrap().permissions();
+    assert!(permissions.readonly());
+
+    // The file is changed anyway, through the store.
+    permissions.set_mode(0o644);
+    std::fs::set_permissions(&lib, permissions).unwrap();
+    std::fs::write(&lib, "compile_error!(\"changed\");").unwrap();
+
+    depend_on(&p, "1.0.1");
+    p.cargo("build -Z registry-dedup")
+        .masquerade_as_nightly_cargo()
+        .run();
+    assert_eq!(
+        std::fs::read_to_string(src("bar-1.0.1", "src/lib.rs")).unwrap(),
+        "pub fn bar() {}"
+    );
+    assert_ne!(
+        inode("bar-1.0.0", "src/lib.rs"),
+        inode("bar-1.0.1", "src/lib.rs")
+    );
+}
+
+#[cargo_test]
+fn no_dedup_without_flag() {
+    Package::new("bar", "1.0.0").publish();
+    Package::new("bar", "1.0.1").publish();
+    let p = project().file("src/lib.rs", "").build();
+
+    depend_on(&p, "1.0.0");
+    p.cargo("build").run();
+    depend_on(&p, "1.0.1");
+    p.cargo("build").run();
+
+    assert_ne!(
+        inode("bar-1.0.0", "src/lib.rs"),
+        inode("bar-1.0.1", "src/lib.rs")
+    );
+    assert!(!paths::home().join(".cargo/registry/cas").exists());
+}
@@ -0,0 +1,328 @@
+//! Tests for the `cargo release` command.
+
+use cargo_test_support::registry::{self, alt_api_path, api_path};
+use cargo_test_support::{git, paths, Project};
+
+fn workspace(version: &str) -> (Project, git2::Repository) {
+    let manifest = |name: &str, deps: &str| {
+        format!(
+            r#"
+                [package]
+                name = "{}"
+                version = "{}"
+                license = "MIT"
+                description = "{}"
+                homepage = "{}"
+
+                [dependencies]
+                {}
+            "#,
+            name, version, name, name, deps
+        )
+    };
+    git::new_repo("ws", |p| {
+        p.file("Cargo.toml", "[workspace]\nmembers = [\"foo\", \"bar\"]\n")
+            .file("foo/Cargo.toml", &manifest("foo", ""))
+            .file("foo/src/lib.rs", "")
+            .file(
+                "bar/Cargo.toml",
+                &manifest(
+                    "bar",
+                    &format!(r#"foo = {{ path = "../foo", version = "{}" }}"#, version),
+                ),
+            )
+            .file("bar/src/lib.rs", "")
+    })
+}
+
+fn commit(repo: &git2::Repository, message: &str) {
+    git::add(repo);
+    let tree = repo
+        .find_tree(repo.index().unwrap().write_tree().unwrap())
+        .unwrap();
+    let sig = repo.signature().unwrap();
+    let parent = repo.head().unwrap().peel_to_commit().unwrap();
+    repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &[&parent])
+        .unwrap();
+}
+
+fn head_message(repo: &git2::Repository) -> String {
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    head.message().unwrap().to_string()
+}
+
+#[cargo_test]
+fn release_requires_flag() {
+    let (p, _repo) = workspace("0.1.0");
+
+    // Without the flag, it is left to an external `cargo-release`.
+    p.cargo("release minor")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[ERROR] no such subcommand: `release`")
+        .run();
+}
+
+#[cargo_test]
+fn dry_run_changes_nothing() {
+    let (p, _repo) = workspace("0.1.0");
+    let bar_manifest = p.read_file("bar/Cargo.toml");
+
+    p.cargo("-Zrelease release minor -p foo --dry-run")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[RELEASING] foo v0.1.0 -> v0.2.0
+[UPDATING] bar's requirement on foo from `0.1.0` to `0.2.0`
+[WARNING] aborting release due to dry run
+",
+        )
+        .run();
+    assert_eq!(p.read_file("bar/Cargo.toml"), bar_manifest);
+    assert!(p
+        .read_file("foo/Cargo.toml")
+        .contains("version = \"0.1.0\""));
+    assert!(!p.root().join("target/cargo-release/state.json").exists());
+}
+
+#[cargo_test]
+fn release_commits_and_tags() {
+    let (p, repo) = workspace("0.1.0");
+
+    p.cargo("-Zrelease release minor -p foo --no-publish")
+        .masquerade_as_nightly_cargo()
+        .env("SOURCE_DATE_EPOCH", "0")
+        .with_stderr(
+            "\
+[RELEASING] foo v0.1.0 -> v0.2.0
+[UPDATING] bar's requirement on foo from `0.1.0` to `0.2.0`
+[COMMITTING] chore: release foo v0.2.0
+[TAGGING] foo-v0.2.0
+[RELEASED] foo v0.2.0
+",
+        )
+        .run();
+
+    assert!(p
+        .read_file("foo/Cargo.toml")
+        .contains("version = \"0.2.0\""));
+    assert!(p
+        .read_file("bar/Cargo.toml")
+        .contains(r#"foo = { path = "../foo", version = "0.2.0" }"#));
+    assert!(p
+        .read_file("bar/Cargo.toml")
+        .contains("version = \"0.1.0\""));
+    assert_eq!(
+        p.read_file("foo/CHANGELOG.md"),
+        "# Changelog\n\n## [0.2.0] - 1970-01-01\n\n- test\n"
+    );
+    assert!(!p.root().join("bar/CHANGELOG.md").exists());
+
+    assert_eq!(head_message(&repo), "chore: release foo v0.2.0");
+    let tag = repo.revparse_single("refs/tags/foo-v0.2.0").unwrap();
+    let head = repo.head().unwrap().target().unwrap();
+    assert_eq!(tag.peel_to_commit().unwrap().id(), head);
+    let statuses = repo.statuses(None).unwrap();
+    assert!(statuses.iter().all(|s| s.status() == git2::Status::WT_NEW));
+    assert!(!p.root().join("target/cargo-release/state.json").exists());
+}
+
+#[cargo_test]
+fn bump_from_conventional_commits() {
+    let (p, repo) = workspace("1.0.0");
+    p.change_file("foo/src/lib.rs", "pub fn foo() {}");
+    commit(&repo, "feat(foo): add foo");
+    p.change_file("bar/src/lib.rs", "pub fn bar() {}");
+    commit(&repo, "fix: make bar public");
+    p.change_file("README.md", "");
+    commit(&repo, "docs: add a readme");
+
+    p.cargo("-Zrelease release --no-publish --no-changelog")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[RELEASING] foo v1.0.0 -> v1.1.0
+[RELEASING] bar v1.0.0 -> v1.0.1
+[UPDATING] bar's requirement on foo from `1.0.0` to `1.1.0`
+[COMMITTING] chore: release foo v1.1.0, bar v1.0.1
+[TAGGING] foo-v1.1.0
+[TAGGING] bar-v1.0.1
+[RELEASED] foo v1.1.0, bar v1.0.1
+",
+        )
+        .run();
+
+    // Nothing changed since the release tags.
+    p.cargo("-Zrelease release --dry-run")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[SKIPPING] foo v1.1.0, it has no releasable commits since its last release
+[SKIPPING] bar v1.0.1, it has no releasable commits since its last release
+[WARNING] nothing to release
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn breaking_changes_below_1_0_0() {
+    let (p, repo) = workspace("0.1.0");
+    p.change_file("foo/src/lib.rs", "pub fn foo() {}");
+    commit(&repo, "refactor!: rename everything");
+    p.change_file("bar/src/lib.rs", "pub fn bar() {}");
+    commit(&repo, "feat: add bar\n\nBREAKING CHANGE: bar is new");
+
+    p.cargo("-Zrelease release --dry-run")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[RELEASING] foo v0.1.0 -> v0.2.0
+[RELEASING] bar v0.1.0 -> v0.2.0
+[UPDATING] bar's requirement on foo from `0.1.0` to `0.2.0`
+[WARNING] aborting release due to dry run
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn refuses_dirty_working_tree() {
+    let (p, _repo) = workspace("0.1.0");
+    p.change_file("foo/src/lib.rs", "pub fn foo() {}");
+
+    p.cargo("-Zrelease release patch")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] 1 files in the working directory contain changes that were not yet committed into git:
+
+foo/src/lib.rs
+
+to proceed despite this and include the uncommitted changes in the release, pass the `--allow-dirty` flag
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn requires_git_identity() {
+    let (p, repo) = workspace("0.1.0");
+    let mut config = repo.config().unwrap();
+    config.remove("user.name").unwrap();
+    config.remove("user.email").unwrap();
+
+    p.cargo("-Zrelease release minor -p foo --no-publish")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "[ERROR] `cargo release` commits and tags as the git user, \
+             but no identity is configured: [..]",
+        )
+        .with_stderr_contains(
+            "set it with `git config user.name \"Your Name\"` and \
+             `git config user.email you@example.com`",
+        )
+        .run();
+    assert!(p
+        .read_file("foo/Cargo.toml")
+        .contains("version = \"0.1.0\""));
+    assert!(!p.root().join("target/cargo-release/state.json").exists());
+}
+
+#[cargo_test]
+fn resume_after_failed_publish() {
+    registry::init();
+    let (p, repo) = workspace("0.1.0");
+    // `bar` can't be published until the alternative registry is set up.
+    let manifest = p.read_file("bar/Cargo.toml");
+    p.change_file(
+        "bar/Cargo.toml",
+        &manifest.replace(
+            "[dependencies]",
+            "publish = [\"alternative\"]\n\n[dependencies]",
+        ),
+    );
+    commit(&repo, "chore: publish bar to the alternative registry");
+
+    p.cargo("-Zrelease release minor --no-verify")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[UPLOADING] foo v0.2.0 ([CWD]/foo)")
+        .with_stderr_contains(
+            "[ERROR] failed to publish bar v0.2.0, once the problem is solved, \
+             continue the release with `cargo release --resume`",
+        )
+        .run();
+    assert!(api_path().join("api/v1/crates/new").exists());
+    assert_eq!(head_message(&repo), "chore: release foo v0.2.0, bar v0.2.0");
+
+    p.cargo("-Zrelease release patch")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] a release is already in progress, continue it with `cargo release --resume`, \
+             or remove `[CWD]/target/cargo-release/state.json` to start over",
+        )
+        .run();
+
+    let config = paths::home().join(".cargo/config");
+    let mut contents = std::fs::read_to_string(&config).unwrap();
+    contents.push_str(&format!(
+        "\n[registries.alternative]\nindex = '{}'\n",
+        registry::alt_registry_url()
+    ));
+    std::fs::write(&config, contents).unwrap();
+    registry::init_registry(
+        registry::alt_registry_path(),
+        registry::alt_dl_url(),
+        registry::alt_api_url(),
+        alt_api_path(),
+    );
+
+    p.cargo("-Zrelease release --resume --no-verify")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[RELEASING] bar v0.1.0 -> v0.2.0")
+        .with_stderr_contains("[UPLOADING] bar v0.2.0 ([CWD]/bar)")
+        .with_stderr_does_not_contain("[UPLOADING] foo[..]")
+        .with_stderr_does_not_contain("[COMMITTING][..]")
+        .with_stderr_contains("[RELEASED] foo v0.2.0, bar v0.2.0")
+        .run();
+    assert!(alt_api_path().join("api/v1/crates/new").exists());
+    assert!(!p.root().join("target/cargo-release/state.json").exists());
+}
+
+#[cargo_test]
+fn release_assembles_changelog_fragments() {
+    let (p, repo) = workspace("0.1.0");
+    p.change_file(
+        "Cargo.toml",
+        r#"
+            cargo-features = ["changelog-fragments"]
+
+            [workspace]
+            members = ["foo", "bar"]
+
+            [workspace.changelog]
+        "#,
+    );
+    p.change_file("foo/changes/1.added.md", "Add `foo::foo`.");
+    p.change_file("foo/changes/2.fixed.md", "Fix everything.");
+    commit(&repo, "feat: add foo");
+
+    p.cargo("-Zrelease release minor -p foo --no-publish")
+        .masquerade_as_nightly_cargo()
+        .env("SOURCE_DATE_EPOCH", "0")
+        .with_stderr_contains("[COMMITTING] chore: release foo v0.2.0")
+        .run();
+
+    assert_eq!(
+        p.read_file("foo/CHANGELOG.md"),
+        "# Changelog\n\n## [0.2.0] - 1970-01-01\n\n\
+         ### Added\n\n- Add `foo::foo`.\n\n### Fixed\n\n- Fix everything.\n"
+    );
+    assert!(!p.root().join("foo/changes/1.added.md").exists());
+    let statuses = repo.statuses(None).unwrap();
+    assert!(statuses.iter().all(|s| s.status() == git2::Status::WT_NEW));
+}
@@ -0,0 +1,291 @@
+//! Tests for `-Z remote-runner`.
+
+use cargo_test_support::{basic_manifest, paths, project, rustc_host, Project};
+use std::path::PathBuf;
+
+/// Builds an executable standing in for a remote runner.
+///
+/// It "uploads" programs by copying them into the `remote` directory next to
+/// it, runs them locally, and logs what it was asked to do to `agent.log`.
+fn fake_agent() -> PathBuf {
+    let p = project()
+        .at(paths::global_root().join("fake-agent"))
+        .file("Cargo.toml", &basic_manifest("fake-agent", "1.0.0"))
+        .file(
+            "src/main.rs",
+            r##"
+                use std::fs::{self, OpenOptions};
+                use std::io::{BufRead, Write};
+                use std::path::PathBuf;
+                use std::process::Command;
+
+                /// The strings in `msg` following `"<field>":`.
+                fn strings(msg: &str, field: &str) -> Vec<String> {
+                    let start = msg.find(&format!("\"{}\":", field)).unwrap() + field.len() + 3;
+                    let mut rest = msg[start..].chars();
+                    let (mut out, mut cur, mut in_str, mut depth) = (Vec::new(), None, false, 0);
+                    while let Some(c) = rest.next() {
+                        match c {
+                            '\\' if in_str => {
+                                let c = rest.next().unwrap();
+                                cur.as_mut().map(|s: &mut String| s.push(c));
+                            }
+                            '"' if in_str => {
+                                out.push(cur.take().unwrap());
+                                in_str = false;
+                            }
+                            '"' => {
+                                cur = Some(String::new());
+                                in_str = true;
+                            }
+                            _ if in_str => cur.as_mut().unwrap().push(c),
+                            '[' | '{' => depth += 1,
+                            ']' | '}' => depth -= 1,
+                            _ => {}
+                        }
+                        if depth == 0 && !in_str && (c == '"' || c == ']' || c == '}') {
+                            break;
+                        }
+                    }
+                    out
+                }
+
+                fn escape(s: &str) -> String {
+                    s.replace('\\', "\\\\").replace('"', "\\\"")
+                }
+
+                fn main() {
+                    let dir = std::env::current_exe().unwrap().parent().unwrap().to_path_buf();
+                    let remote = dir.join("remote");
+                    fs::create_dir_all(&remote).unwrap();
+                    let mut log = OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(dir.join("agent.log"))
+                        .unwrap();
+                    writeln!(log, "start").unwrap();
+                    println!(r#"{{"type":"hello","version":1}}"#);
+                    let stdin = std::io::stdin();
+                    for msg in stdin.lock().lines() {
+                        let msg = msg.unwrap();
+                        let id: u64 = msg[msg.find("\"id\":").unwrap() + 5..]
+                            .split(',')
+                            .next()
+                            .unwrap()
+                            .parse()
+                            .unwrap();
+                        if msg.contains(r#""type":"upload""#) {
+                            let path = PathBuf::from(&strings(&msg, "path")[0]);
+                            writeln!(log, "upload {}", path.file_name().unwrap().to_str().unwrap()).unwrap();
+                            let dst = remote.join(path.file_name().unwrap());
+                            fs::copy(&path, &dst).unwrap();
+                            println!(
+                                r#"{{"type":"uploaded","id":{},"path":"{}"}}"#,
+                                id,
+                                escape(dst.to_str().unwrap())
+                            );
+                        } else {
+                            let program = strings(&msg, "program").remove(0);
+                            writeln!(log, "run {}", program).unwrap();
+                            let env = strings(&msg, "env");
+                            let output = Command::new(&program)
+                                .args(strings(&msg, "args"))
+                                .envs(env.chunks(2).map(|kv| (&kv[0], &kv[1])))
+                                .current_dir(&remote)
+                                .output()
+                                .unwrap();
+                            for (stream, out) in [("stdout", output.stdout), ("stderr", output.stderr)] {
+                                for line in String::from_utf8(out).unwrap().lines() {
+                                    println!(
+                                        r#"{{"type":"{}","id":{},"line":"{}"}}"#,
+                                        stream,
+                                        id,
+                                        escape(line)
+                                    );
+                                }
+                            }
+                            println!(
+                                r#"{{"type":"exit","id":{},"code":{}}}"#,
+                                id,
+                                output.status.code().unwrap()
+                            );
+                        }
+                    }
+                    writeln!(log, "stop").unwrap();
+                }
+            "##,
+        )
+        .build();
+    p.cargo("build").run();
+    p.bin("fake-agent")
+}
+
+fn agent_log() -> String {
+    std::fs::read_to_string(paths::global_root().join("fake-agent/target/debug/agent.log"))
+        .unwrap_or_default()
+}
+
+fn remote_project(config: &str) -> Project {
+    let agent = fake_agent();
+    project()
+        .file(
+            "src/lib.rs",
+            r#"
+                #[test]
+                fn unit() {
+                    let exe = std::env::current_exe().unwrap();
+                    assert!(exe.parent().unwrap().ends_with("remote"));
+                    assert_eq!(std::env::var("CARGO_PKG_NAME").unwrap(), "foo");
+                }
+            "#,
+        )
+        .file(
+            "tests/it.rs",
+            r#"
+                #[test]
+                fn threads() {
+                    println!("threads={:?}", std::env::var("RUST_TEST_THREADS"));
+                }
+            "#,
+        )
+        .file(
+            ".cargo/config",
+            &format!(
+                r#"
+                    [target.{}]
+                    runner = '{}'
+                    {}
+                "#,
+                rustc_host(),
+                agent.display(),
+                config
+            ),
+        )
+        .build()
+}
+
+#[cargo_test]
+fn remote_runner_requires_z_flag() {
+    let p = remote_project(r#"runner-protocol = "remote""#);
+
+    p.cargo("test")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(&format!(
+            "[ERROR] `target.{}.runner-protocol` requires `-Z remote-runner`",
+            rustc_host()
+        ))
+        .run();
+}
+
+#[cargo_test]
+fn invalid_runner_protocol() {
+    let p = remote_project(r#"runner-protocol = "telnet""#);
+
+    p.cargo("test -Zremote-runner")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(&format!(
+            "[ERROR] unknown runner protocol `telnet`, expected `exec` or `remote`, \
+             set in `target.{}.runner-protocol`",
+            rustc_host()
+        ))
+        .run();
+
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            &format!(
+                r#"
+                    [target.{}]
+                    runner-protocol = "remote"
+                "#,
+                rustc_host()
+            ),
+        )
+        .build();
+    p.cargo("test -Zremote-runner")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(&format!(
+            "[ERROR] `target.{0}.runner-protocol` is `remote`, \
+             but no `target.{0}.runner` is configured",
+            rustc_host()
+        ))
+        .run();
+}
+
+#[cargo_test]
+fn test_on_remote() {
+    let p = remote_project(
+        r#"
+            runner-protocol = "remote"
+            runner-jobs = 1
+        "#,
+    );
+
+    p.cargo("test -Zremote-runner -- --nocapture")
+        .masquerade_as_nightly_cargo()
+        .with_stdout_contains("test unit ... ok")
+        .with_stdout_contains(r#"test threads ... threads=Ok("1")"#)
+        .run();
+
+    // The agent ran both tests, and stopped afterwards.
+    let log = agent_log();
+    let lines: Vec<_> = log.lines().collect();
+    assert_eq!(lines.len(), 6, "{}", log);
+    assert_eq!(lines[0], "start");
+    assert!(lines[1].starts_with("upload foo-"), "{}", log);
+    assert!(lines[2].starts_with("run "), "{}", log);
+    assert!(lines[3].starts_with("upload it-"), "{}", log);
+    assert!(lines[4].starts_with("run "), "{}", log);
+    assert_eq!(lines[5], "stop");
+}
+
+#[cargo_test]
+fn remote_failure() {
+    let p = remote_project(r#"runner-protocol = "remote""#);
+    p.change_file("tests/it.rs", "#[test] fn fails() { panic!(\"nope\"); }");
+
+    p.cargo("test -Zremote-runner --test it")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stdout_contains("test fails ... FAILED")
+        .with_stderr_contains("[ERROR] test failed, to rerun pass '--test it'")
+        .run();
+}
+
+#[cargo_test]
+fn run_on_remote() {
+    let agent = fake_agent();
+    let p = project()
+        .file(
+            "src/main.rs",
+            r#"
+                fn main() {
+                    println!("hello from {:?}", std::env::args().nth(1));
+                    std::process::exit(2);
+                }
+            "#,
+        )
+        .file(
+            ".cargo/config",
+            &format!(
+                r#"
+                    [target.{}]
+                    runner = '{}'
+                    runner-protocol = "remote"
+                "#,
+                rustc_host(),
+                agent.display()
+            ),
+        )
+        .build();
+
+    p.cargo("run -Zremote-runner -- arg")
+        .masquerade_as_nightly_cargo()
+        .with_status(2)
+        .with_stdout(r#"hello from Some("arg")"#)
+        .run();
+}
@@ -0,0 +1,193 @@
+//! Tests for `cargo report bloat`.
+
+use cargo_test_support::{basic_bin_manifest, project, Project};
+use serde_json::Value;
+
+fn report(p: &Project, args: &str) -> Value {
+    let output = p
+        .cargo(&format!("report bloat --json {}", args))
+        .masquerade_as_nightly_cargo()
+        .exec_with_output()
+        .unwrap();
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[cargo_test]
+fn not_built() {
+    let p = project()
+        .file("Cargo.toml", &basic_bin_manifest("foo"))
+        .file(
+            "src/main.rs",
+            r#"
+                #[inline(never)]
+                fn greet(name: &str) {
+                    println!("hello {}", name);
+                }
+
+                fn main() {
+                    greet("world");
+                }
+            "#,
+        )
+        .build();
+    p.cargo("report bloat")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] no artifacts found in `[CWD]/target/debug`, \
+             build them before analyzing them",
+        )
+        .run();
+
+    p.cargo("build").run();
+    p.cargo("report bloat bar")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] no artifacts named `bar` found")
+        .run();
+}
+
+#[cfg_attr(not(any(target_os = "linux", target_os = "macos")), ignore)]
+#[cargo_test]
+fn breakdown() {
+    let p = project()
+        .file("Cargo.toml", &basic_bin_manifest("foo"))
+        .file(
+            "src/main.rs",
+            r#"
+                #[inline(never)]
+                fn greet(name: &str) {
+                    println!("hello {}", name);
+                }
+
+                fn main() {
+                    greet("world");
+                }
+            "#,
+        )
+        .build();
+    p.cargo("build").run();
+
+    p.cargo("report bloat")
+        .masquerade_as_nightly_cargo()
+        .with_stdout_contains("foo: [..]B, .text [..]B")
+        .with_stdout_contains("  Crates:")
+        .with_stdout_contains("[..]%[..]B  foo")
+        .with_stdout_contains("  Functions:")
+        .run();
+
+    let bloat = report(&p, "foo");
+    assert_eq!(bloat["artifact"], "foo");
+    assert!(bloat["file_size"].as_u64().unwrap() > bloat["text_size"].as_u64().unwrap());
+    let crates = bloat["crates"].as_array().unwrap();
+    let krate = |name: &str| crates.iter().find(|c| c["name"] == name).cloned();
+    assert!(krate("foo").unwrap()["size"].as_u64().unwrap() > 0);
+    assert!(krate("std").is_some());
+    let symbols = bloat["symbols"].as_array().unwrap();
+    assert_eq!(symbols.len(), 20);
+    assert!(report(&p, "foo --top 100000")["symbols"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|s| s["crate"] == "foo" && s["name"].as_str().unwrap() == "foo::greet"));
+}
+
+#[cfg_attr(not(any(target_os = "linux", target_os = "macos")), ignore)]
+#[cargo_test]
+fn compares_with_last_report() {
+    let p = project()
+        .file("Cargo.toml", &basic_bin_manifest("foo"))
+        .file(
+            "src/main.rs",
+            r#"
+                #[inline(never)]
+                fn greet(name: &str) {
+                    println!("hello {}", name);
+                }
+
+                fn main() {
+                    greet("world");
+                }
+            "#,
+        )
+        .build();
+    p.cargo("build").run();
+    let before = report(&p, "foo");
+    assert!(before.get("text_change").is_none());
+
+    p.change_file(
+        "src/main.rs",
+        r#"
+            #[inline(never)]
+            fn greet(name: &str) {
+                for i in 0..10 {
+                    println!("hello {} {} {:?}", name, i, std::env::args().collect::<Vec<_>>());
+                }
+            }
+
+            fn main() {
+                greet("world");
+            }
+        "#,
+    );
+    p.cargo("build").run();
+    let after = report(&p, "foo");
+    assert!(after["text_change"].as_i64().unwrap() > 0);
+    let foo = after["crates"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|c| c["name"] == "foo")
+        .unwrap();
+    assert!(foo["change"].as_i64().unwrap() > 0);
+
+    p.cargo("report bloat foo")
+        .masquerade_as_nightly_cargo()
+        .with_stdout_contains("foo: [..]B, .text [..]B")
+        .with_stdout_does_not_contain("[..](+[..]")
+        .run();
+}
+
+#[cfg_attr(not(any(target_os = "linux", target_os = "macos")), ignore)]
+#[cargo_test]
+fn max_growth() {
+    let p = project()
+        .file("Cargo.toml", &basic_bin_manifest("foo"))
+        .file(
+            "src/main.rs",
+            r#"
+                #[inline(never)]
+                fn greet(name: &str) {
+                    println!("hello {}", name);
+                }
+
+                fn main() {
+                    greet("world");
+                }
+            "#,
+        )
+        .build();
+    p.cargo("build").run();
+    let mut baseline = report(&p, "foo");
+    let text_size = baseline["text_size"].as_u64().unwrap();
+    baseline["text_size"] = (text_size / 2).into();
+    p.change_file("baseline.json", &baseline.to_string());
+
+    p.cargo("report bloat foo --baseline baseline.json --max-growth 150%")
+        .masquerade_as_nightly_cargo()
+        .with_stdout_contains("foo: [..]B, .text [..]B (+[..]B)")
+        .run();
+    p.cargo("report bloat foo --baseline baseline.json --max-growth 5")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] the code of `foo` grew by [..]%, from [..]B to [..]B, \
+             more than the 5% allowed by `--max-growth`",
+        )
+        .run();
+    p.cargo("report bloat --max-growth lots")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] `--max-growth` must be a percentage, like `5`, found `lots`")
+        .run();
+}
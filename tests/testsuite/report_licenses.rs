@@ -0,0 +1,197 @@
+//! Tests for `cargo report licenses`.
+
+use cargo_test_support::project;
+use cargo_test_support::registry::Package;
+
+fn dep(name: &str, license: &str, files: &[&str]) {
+    let mut pkg = Package::new(name, "1.0.0");
+    pkg.file(
+        "Cargo.toml",
+        &format!(
+            r#"
+                [package]
+                name = "{}"
+                version = "1.0.0"
+                repository = "https://example.com/{}"
+                {}
+            "#,
+            name, name, license
+        ),
+    )
+    .file("src/lib.rs", "");
+    for file in files {
+        pkg.file(file, &format!("{} of {}\n", file, name));
+    }
+    pkg.publish();
+}
+
+#[cargo_test]
+fn list() {
+    dep(
+        "bar",
+        r#"license = "MIT OR Apache-2.0""#,
+        &["LICENSE-MIT", "LICENSE-APACHE"],
+    );
+    dep("baz", r#"license-file = "COPYING""#, &["COPYING"]);
+    dep("dev", "", &[]);
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+
+                [build-dependencies]
+                baz = "1.0"
+
+                [dev-dependencies]
+                dev = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("report licenses")
+        .masquerade_as_nightly_cargo()
+        .with_stdout(
+            "\
+bar v1.0.0: MIT OR Apache-2.0 (LICENSE-APACHE, LICENSE-MIT)
+baz v1.0.0: no license expression (COPYING)
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn bundle() {
+    dep(
+        "bar",
+        r#"license = "MIT OR Apache-2.0""#,
+        &["LICENSE-MIT", "LICENSE-APACHE"],
+    );
+    dep("baz", r#"license-file = "COPYING""#, &["COPYING"]);
+    dep("dev", "", &[]);
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+
+                [build-dependencies]
+                baz = "1.0"
+
+                [dev-dependencies]
+                dev = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("report licenses --bundle")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "[..]Bundled the licenses of 2 dependencies into `[CWD]/target/licenses`",
+        )
+        .run();
+
+    let separator = "-".repeat(80);
+    assert_eq!(
+        p.read_file("target/licenses/THIRD-PARTY-NOTICES"),
+        format!(
+            "\
+THIRD-PARTY SOFTWARE NOTICES
+
+The licenses of the third-party packages used by foo.
+
+{sep}
+
+bar 1.0.0
+License: MIT OR Apache-2.0
+Repository: https://example.com/bar
+
+LICENSE-APACHE:
+
+LICENSE-APACHE of bar
+
+LICENSE-MIT:
+
+LICENSE-MIT of bar
+
+{sep}
+
+baz 1.0.0
+Repository: https://example.com/baz
+
+COPYING:
+
+COPYING of baz
+",
+            sep = separator
+        )
+    );
+
+    let summary: serde_json::Value =
+        serde_json::from_str(&p.read_file("target/licenses/licenses.json")).unwrap();
+    let packages = summary["packages"].as_array().unwrap();
+    assert_eq!(packages.len(), 2);
+    assert_eq!(packages[0]["name"], "bar");
+    assert_eq!(packages[0]["version"], "1.0.0");
+    assert_eq!(packages[0]["license"], "MIT OR Apache-2.0");
+    assert_eq!(
+        packages[0]["license_files"],
+        serde_json::json!(["LICENSE-APACHE", "LICENSE-MIT"])
+    );
+    assert_eq!(packages[1]["name"], "baz");
+    assert_eq!(packages[1]["license"], serde_json::Value::Null);
+    assert_eq!(packages[1]["license_files"], serde_json::json!(["COPYING"]));
+}
+
+#[cargo_test]
+fn bundle_fails_on_missing_licenses() {
+    dep("bar", r#"license = "MIT OR""#, &["LICENSE"]);
+    dep("baz", r#"license = "MIT""#, &[]);
+    dep("qux", "", &["NOTICE"]);
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+                baz = "1.0"
+                qux = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("report licenses")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[WARNING] baz v1.0.0 has no license files")
+        .run();
+
+    p.cargo("report licenses --bundle")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+[ERROR] the licenses of 3 dependencies can't be bundled:
+  bar v1.0.0 has the license `MIT OR`, which is not a valid SPDX expression: \
+expected a license at the end of the expression
+  baz v1.0.0 has no license files
+  qux v1.0.0 has no license expression
+",
+        )
+        .run();
+    assert!(!p.root().join("target/licenses").exists());
+}
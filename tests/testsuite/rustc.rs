@@ -28,6 +28,132 @@ fn build_lib_for_foo() {
         .run();
 }
 
+#[cargo_test]
+fn build_lib_for_foo_with_crate_type() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("rustc -v --crate-type lib,cdylib -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[RUNNING] `rustc --crate-name foo src/lib.rs [..]--crate-type lib --crate-type cdylib[..]
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn build_example_lib_with_crate_type() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [[example]]
+                name = "ex"
+                crate-type = ["rlib"]
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("examples/ex.rs", "")
+        .build();
+
+    p.cargo("rustc -v --example ex --crate-type staticlib -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "[RUNNING] `rustc --crate-name ex examples/ex.rs [..]--crate-type staticlib [..]",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn crate_type_requires_unstable_options() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("rustc --crate-type cdylib")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] the `crate-type` flag is unstable, pass `-Z unstable-options` to enable it
+See https://github.com/rust-lang/cargo/issues/10083 for more information about the `crate-type` flag.
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn fails_with_crate_type_to_binary() {
+    let p = project().file("src/bin/foo.rs", "fn main() {}").build();
+
+    p.cargo("rustc --crate-type lib -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] crate types can only be specified for libraries and example libraries.
+Binaries, tests, and benchmarks are always the `bin` crate type
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn fails_with_crate_type_to_multiple_targets() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("rustc --crate-type cdylib -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] crate types to rustc can only be passed to one target, consider filtering
+the package by passing, e.g., `--lib` or `--example` to specify a single target
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn crate_type_fingerprint_is_separate() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("rustc --lib").run();
+    p.cargo("rustc --lib --crate-type cdylib -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+    assert!(p
+        .root()
+        .join("target/debug")
+        .join(format!(
+            "{}foo{}",
+            std::env::consts::DLL_PREFIX,
+            std::env::consts::DLL_SUFFIX
+        ))
+        .is_file());
+
+    // Neither build invalidates the other.
+    p.cargo("rustc --lib").with_stderr("[FINISHED] [..]").run();
+    p.cargo("rustc --lib --crate-type cdylib -Zunstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[FINISHED] [..]")
+        .run();
+}
+
 #[cargo_test]
 fn lib() {
     let p = project()
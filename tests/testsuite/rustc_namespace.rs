@@ -0,0 +1,153 @@
+//! Tests for `-Z rustc-namespace`.
+
+use cargo_test_support::{basic_manifest, project, Project};
+use std::fs;
+
+/// Builds a `rustc` wrapper which reports a different commit hash, so that
+/// it looks like another version of rustc.
+fn other_rustc() -> Project {
+    let p = project()
+        .at("other-rustc")
+        .file("Cargo.toml", &basic_manifest("other-rustc", "1.0.0"))
+        .file(
+            "src/main.rs",
+            r#"
+                use std::process::{exit, Command, Stdio};
+
+                fn main() {
+                    let args: Vec<String> = std::env::args().skip(1).collect();
+                    let output = Command::new("rustc")
+                        .args(&args)
+                        .stderr(Stdio::inherit())
+                        .output()
+                        .unwrap();
+                    let stdout = String::from_utf8(output.stdout).unwrap();
+                    print!("{}", stdout.replace("commit-hash: ", "commit-hash: other-"));
+                    exit(output.status.code().unwrap_or(1));
+                }
+            "#,
+        )
+        .build();
+    p.cargo("build").run();
+    p
+}
+
+#[cargo_test]
+fn intermediate_artifacts_are_namespaced() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [lib]
+                crate-type = ["cdylib"]
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build -Z rustc-namespace")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    let namespaces: Vec<_> = fs::read_dir(p.target_debug_dir().join(".rustc"))
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect();
+    assert_eq!(namespaces.len(), 1);
+    let namespace = &namespaces[0];
+    assert!(namespace.join(".fingerprint").is_dir());
+    assert!(namespace.join("deps").is_dir());
+    assert!(!p.target_debug_dir().join("deps").exists());
+    assert!(!p.target_debug_dir().join(".fingerprint").exists());
+    // The final artifacts are still in `target/debug`.
+    assert!(p
+        .target_debug_dir()
+        .join(format!(
+            "{}foo{}",
+            std::env::consts::DLL_PREFIX,
+            std::env::consts::DLL_SUFFIX
+        ))
+        .is_file());
+}
+
+#[cargo_test]
+fn toolchains_coexist() {
+    let other = other_rustc();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [lib]
+                crate-type = ["cdylib"]
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build -Z rustc-namespace -Z separate-nightlies")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.1.0 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+
+    p.cargo("build -Z rustc-namespace -Z separate-nightlies")
+        .masquerade_as_nightly_cargo()
+        .env("RUSTC", other.bin("other-rustc"))
+        .with_stderr(
+            "\
+[COMPILING] foo v0.1.0 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+    assert_eq!(
+        fs::read_dir(p.target_debug_dir().join(".rustc"))
+            .unwrap()
+            .count(),
+        2
+    );
+
+    // Switching back doesn't rebuild anything.
+    p.cargo("build -Z rustc-namespace -Z separate-nightlies")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[FINISHED] [..]")
+        .run();
+    p.cargo("build -Z rustc-namespace -Z separate-nightlies")
+        .masquerade_as_nightly_cargo()
+        .env("RUSTC", other.bin("other-rustc"))
+        .with_stderr("[FINISHED] [..]")
+        .run();
+}
+
+#[cargo_test]
+fn clean_package() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("build -Z rustc-namespace")
+        .masquerade_as_nightly_cargo()
+        .run();
+    p.cargo("clean -p foo -Z rustc-namespace")
+        .masquerade_as_nightly_cargo()
+        .run();
+    p.cargo("build -Z rustc-namespace")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
@@ -729,6 +729,53 @@ fn build_rustflags_build_script_with_target() {
     p.cargo("build --target").arg(host).run();
 }
 
+#[cargo_test]
+fn host_rustflags_build_script_with_target() {
+    // The `[host]` rustflags are passed to rustc for build scripts
+    // when --target is specified, and not for the library.
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                build = "build.rs"
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            r#"
+                #[cfg(foo)]
+                compile_error!("host rustflags applied to the library");
+            "#,
+        )
+        .file(
+            "build.rs",
+            r#"
+                #[cfg(foo)]
+                fn main() { }
+            "#,
+        )
+        .file(
+            ".cargo/config",
+            r#"
+            [host]
+            rustflags = ["--cfg", "foo"]
+            "#,
+        )
+        .build();
+
+    let host = rustc_host();
+    p.cargo("build --target").arg(host).run();
+
+    // Without --target, the build script is built like the library.
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr_contains("[..]main[..]")
+        .run();
+}
+
 #[cargo_test]
 fn build_rustflags_build_script_dep_with_target() {
     // RUSTFLAGS should not be passed to rustc for build scripts
@@ -0,0 +1,284 @@
+//! Tests for sanitizer profiles and `cargo test --sanitizer`.
+
+use cargo_test_support::registry::Package;
+use cargo_test_support::{basic_manifest, project};
+
+#[cargo_test]
+fn sanitizer_requires_z_flag() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("test --sanitizer address")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] Usage of `--sanitizer` requires `-Z sanitizers`")
+        .run();
+}
+
+#[cargo_test]
+fn profile_sanitizer_requires_feature() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [profile.dev]
+                sanitizer = "address"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[..]feature `sanitizers` is required[..]")
+        .run();
+}
+
+#[cargo_test]
+fn unknown_sanitizer() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("test -Zsanitizers --sanitizer bogus")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] unknown sanitizer `bogus`, \
+             expected one of `address`, `thread`, `memory` or `leak`",
+        )
+        .run();
+
+    p.change_file(
+        "Cargo.toml",
+        r#"
+            cargo-features = ["sanitizers"]
+
+            [package]
+            name = "foo"
+            version = "0.0.1"
+
+            [profile.dev]
+            sanitizer = "bogus"
+        "#,
+    );
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  invalid `profile.dev.sanitizer`
+
+Caused by:
+  unknown sanitizer `bogus`, expected one of `address`, `thread`, `memory` or `leak`
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn sanitizer_not_allowed_in_overrides() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["sanitizers"]
+
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [profile.dev.build-override]
+                sanitizer = "address"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  `sanitizer` may not be specified in a `build-override` profile
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn test_with_address_sanitizer() {
+    Package::new("bar", "1.0.0")
+        .file("src/lib.rs", "pub fn id(x: u32) -> u32 { x }")
+        .publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                build = "build.rs"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("build.rs", "fn main() {}")
+        .file(
+            "src/lib.rs",
+            r#"
+                pub fn add(a: u32, b: u32) -> u32 { bar::id(a) + b }
+
+                #[test]
+                fn unit() { assert_eq!(add(1, 2), 3); }
+            "#,
+        )
+        .build();
+
+    p.cargo("test -v -Zsanitizers --sanitizer address")
+        .masquerade_as_nightly_cargo()
+        .env("RUSTC_BOOTSTRAP", "1")
+        .with_stderr_contains("[RUNNING] `rustc --crate-name build_script_build build.rs [..]`")
+        .with_stderr_line_without(
+            &["[RUNNING] `rustc --crate-name build_script_build"],
+            &["-Z sanitizer"],
+        )
+        .with_stderr_contains("[RUNNING] `rustc --crate-name bar [..]-Z sanitizer=address [..]`")
+        .with_stderr_contains(
+            "[RUNNING] `rustc --crate-name foo src/lib.rs [..]-Z sanitizer=address [..]`",
+        )
+        .with_stderr_contains("[RUNNING] `[CWD]/target/debug-asan/deps/foo-[..][EXE]`")
+        .with_stderr_contains("[RUNNING] `rustdoc [..]-Z sanitizer=address[..]`")
+        .with_stdout_contains("test unit ... ok")
+        .run();
+
+    assert!(p.root().join("target/debug-asan").is_dir());
+    assert!(!p.root().join("target/debug").exists());
+}
+
+#[cargo_test]
+fn address_sanitizer_reports_errors() {
+    let p = project()
+        .file(
+            "src/lib.rs",
+            r#"
+                #[test]
+                fn overflow() {
+                    let v = vec![1u8; 4];
+                    let x = unsafe { *v.as_ptr().add(4) };
+                    assert_eq!(x, x);
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("test --lib -Zsanitizers --sanitizer asan")
+        .masquerade_as_nightly_cargo()
+        .env("RUSTC_BOOTSTRAP", "1")
+        .with_status(1)
+        .with_stderr_contains("[..]ERROR: AddressSanitizer: heap-buffer-overflow[..]")
+        .run();
+}
+
+#[cargo_test]
+fn profile_sanitizer() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["sanitizers"]
+
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [profile.dev]
+                sanitizer = "leak"
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("build -v")
+        .masquerade_as_nightly_cargo()
+        .env("RUSTC_BOOTSTRAP", "1")
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[RUNNING] `rustc --crate-name foo [..]-Z sanitizer=leak [..]`
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+",
+        )
+        .run();
+    assert!(p.root().join("target/debug-lsan/foo").is_file());
+
+    p.cargo("build --release")
+        .masquerade_as_nightly_cargo()
+        .env("RUSTC_BOOTSTRAP", "1")
+        .run();
+    assert!(p.root().join("target/release/foo").is_file());
+}
+
+#[cargo_test]
+fn unsupported_target() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.0.1"))
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("test -Zsanitizers --sanitizer address --target wasm32-unknown-unknown")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] the `address` sanitizer is not supported for the target `wasm32-unknown-unknown`
+No sanitizer runtime was found in `[..]`.
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn memory_sanitizer_rebuilds_std() {
+    let p = project().file("src/lib.rs", "").build();
+
+    // The standard library sources are not available in the test
+    // environment, so resolving the std workspace fails.
+    p.cargo("test -Zsanitizers --sanitizer memory")
+        .masquerade_as_nightly_cargo()
+        .env("__CARGO_TESTS_ONLY_SRC_ROOT", p.root().join("no-std-here"))
+        .with_status(101)
+        .with_stderr_contains("[..]no-std-here[..]")
+        .run();
+
+    p.change_file(
+        "Cargo.toml",
+        r#"
+            cargo-features = ["sanitizers"]
+
+            [package]
+            name = "foo"
+            version = "0.0.1"
+
+            [profile.dev]
+            sanitizer = "thread"
+        "#,
+    );
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] the `thread` sanitizer requires rebuilding the standard library, \
+             which requires --target",
+        )
+        .run();
+}
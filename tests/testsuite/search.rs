@@ -3,7 +3,7 @@
 use cargo_test_support::cargo_process;
 use cargo_test_support::git::repo;
 use cargo_test_support::paths;
-use cargo_test_support::registry::{api_path, registry_path, registry_url};
+use cargo_test_support::registry::{self, api_path, registry_path, registry_url, Package};
 use std::collections::HashSet;
 use std::fs;
 use std::path::Path;
@@ -244,3 +244,193 @@ fn multiple_query_params() {
         .with_stdout_contains(SEARCH_RESULTS)
         .run();
 }
+
+/// Sets up a registry like `setup`, where the crates of the search results
+/// have a license, and are published with or without a `rust-version`.
+fn setup_with_packages() {
+    registry::init();
+    // `init` creates the directory of the API of the crates, which is a file
+    // for the search results here.
+    let base = api_path().join("api/v1/crates");
+    fs::remove_dir(&base).unwrap();
+    write_crates(&base);
+    let mut crates: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&base).unwrap()).unwrap();
+    crates["crates"][0]["license"] = "MIT/Apache-2.0".into();
+    crates["crates"][1]["license"] = "GPL-3.0-only".into();
+    fs::write(&base, crates.to_string()).unwrap();
+
+    Package::new("hoare", "0.1.1")
+        .rust_version("1.60")
+        .publish();
+    Package::new("postgres", "0.17.3").publish();
+}
+
+#[cargo_test]
+fn filters_gated() {
+    setup();
+
+    cargo_process("search postgres --json --index")
+        .arg(registry_url().to_string())
+        .with_status(101)
+        .with_stderr("[ERROR] Usage of `--json` requires `-Z search-filters`")
+        .run();
+}
+
+#[cargo_test]
+fn json() {
+    setup();
+
+    cargo_process("search postgres -Zsearch-filters --json --index")
+        .arg(registry_url().to_string())
+        .masquerade_as_nightly_cargo()
+        .with_json(
+            r#"
+            {
+              "crates": [
+                {
+                  "name": "hoare",
+                  "version": "0.1.1",
+                  "description": "Design by contract style assertions for Rust",
+                  "downloads": 2,
+                  "recent_downloads": null,
+                  "created_at": "2014-11-16T20:17:35Z",
+                  "updated_at": "2014-11-20T21:49:21Z",
+                  "homepage": null,
+                  "documentation": null,
+                  "repository": "https://github.com/nick29581/libhoare",
+                  "license": null,
+                  "rust_version": null
+                },
+                {
+                  "name": "postgres",
+                  "version": "0.17.3",
+                  "description": "A native, synchronous PostgreSQL client",
+                  "downloads": 535491,
+                  "recent_downloads": 88321,
+                  "created_at": "2014-11-24T02:34:44.756689+00:00",
+                  "updated_at": "2020-05-01T23:17:54.335921+00:00",
+                  "homepage": null,
+                  "documentation": null,
+                  "repository": "https://github.com/sfackler/rust-postgres",
+                  "license": null,
+                  "rust_version": null
+                }
+              ],
+              "total": 2
+            }
+            "#,
+        )
+        .run();
+}
+
+#[cargo_test]
+fn sort() {
+    setup();
+
+    cargo_process("search postgres -Zsearch-filters --sort downloads --index")
+        .arg(registry_url().to_string())
+        .masquerade_as_nightly_cargo()
+        .with_stdout(
+            "\
+postgres = \"0.17.3\"    # A native, synchronous PostgreSQL client
+hoare = \"0.1.1\"        # Design by contract style assertions for Rust
+",
+        )
+        .run();
+    cargo_process("search postgres -Zsearch-filters --sort alpha --index")
+        .arg(registry_url().to_string())
+        .masquerade_as_nightly_cargo()
+        .with_stdout(SEARCH_RESULTS)
+        .run();
+}
+
+#[cargo_test]
+fn license_and_rust_version() {
+    setup_with_packages();
+
+    cargo_process("search postgres -Zsearch-filters --license mit --index")
+        .arg(registry_url().to_string())
+        .masquerade_as_nightly_cargo()
+        .with_stdout("hoare = \"0.1.1\"    # Design by contract style assertions for Rust")
+        .run();
+    cargo_process("search postgres -Zsearch-filters --rust-version 1.56 --index")
+        .arg(registry_url().to_string())
+        .masquerade_as_nightly_cargo()
+        .with_stdout("postgres = \"0.17.3\"    # A native, synchronous PostgreSQL client")
+        .run();
+    cargo_process("search postgres -Zsearch-filters --rust-version 1.60 --json --index")
+        .arg(registry_url().to_string())
+        .masquerade_as_nightly_cargo()
+        .with_json(
+            r#"
+            {
+              "crates": [
+                {
+                  "name": "hoare",
+                  "version": "{...}",
+                  "description": "{...}",
+                  "downloads": "{...}",
+                  "recent_downloads": "{...}",
+                  "created_at": "{...}",
+                  "updated_at": "{...}",
+                  "homepage": "{...}",
+                  "documentation": "{...}",
+                  "repository": "{...}",
+                  "license": "MIT/Apache-2.0",
+                  "rust_version": "1.60"
+                },
+                {
+                  "name": "postgres",
+                  "version": "{...}",
+                  "description": "{...}",
+                  "downloads": "{...}",
+                  "recent_downloads": "{...}",
+                  "created_at": "{...}",
+                  "updated_at": "{...}",
+                  "homepage": "{...}",
+                  "documentation": "{...}",
+                  "repository": "{...}",
+                  "license": "GPL-3.0-only",
+                  "rust_version": null
+                }
+              ],
+              "total": 2
+            }
+            "#,
+        )
+        .run();
+}
+
+#[cargo_test]
+fn filters_before_limit() {
+    setup_with_packages();
+
+    cargo_process("search postgres -Zsearch-filters --rust-version 1.56 --limit 1 --index")
+        .arg(registry_url().to_string())
+        .masquerade_as_nightly_cargo()
+        .with_stdout("postgres = \"0.17.3\"    # A native, synchronous PostgreSQL client")
+        .run();
+    cargo_process("search postgres -Zsearch-filters --rust-version 1.60 --limit 1 --index")
+        .arg(registry_url().to_string())
+        .masquerade_as_nightly_cargo()
+        .with_stdout("hoare = \"0.1.1\"    # Design by contract style assertions for Rust")
+        .run();
+    // The filters only need the index, the crates aren't downloaded.
+    assert!(!paths::home().join(".cargo/registry/cache").exists());
+}
+
+#[cargo_test]
+fn invalid_filters() {
+    setup();
+
+    cargo_process("search postgres -Zsearch-filters --rust-version latest --index")
+        .arg(registry_url().to_string())
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] invalid value `latest` for `--rust-version`, \
+             expected a version like `1.56`",
+        )
+        .run();
+}
@@ -0,0 +1,301 @@
+//! Tests for the `cargo serve-api` command.
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Stdio};
+
+use cargo_test_support::{basic_manifest, project, Project};
+use serde_json::{json, Value};
+
+/// A client of `cargo serve-api`.
+struct Client {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Client {
+    fn start(p: &Project) -> Client {
+        let mut child = p
+            .cargo("serve-api -Zserve-api")
+            .masquerade_as_nightly_cargo()
+            // Backtraces must not leak into the error messages.
+            .env("RUST_BACKTRACE", "1")
+            .build_command()
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+        Client {
+            stdin: child.stdin.take(),
+            stdout: BufReader::new(child.stdout.take().unwrap()),
+            child,
+        }
+    }
+
+    fn send(&mut self, message: &str) {
+        let stdin = self.stdin.as_mut().unwrap();
+        writeln!(stdin, "{}", message).unwrap();
+    }
+
+    fn request(&mut self, id: u64, method: &str, params: Value) {
+        let request = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+        self.send(&request.to_string());
+    }
+
+    fn recv(&mut self) -> Value {
+        let mut line = String::new();
+        self.stdout.read_line(&mut line).unwrap();
+        serde_json::from_str(&line).unwrap()
+    }
+
+    /// Receives the notifications until the answer to the request `id`.
+    fn answer(&mut self, id: u64) -> (Value, Vec<Value>) {
+        let mut notifications = Vec::new();
+        loop {
+            let message = self.recv();
+            if message.get("id") == Some(&json!(id)) {
+                return (message, notifications);
+            }
+            assert!(message.get("method").is_some(), "{}", message);
+            notifications.push(message);
+        }
+    }
+
+    fn call(&mut self, id: u64, method: &str, params: Value) -> Value {
+        self.request(id, method, params);
+        self.answer(id).0
+    }
+
+    fn stop(mut self) {
+        self.stdin = None;
+        assert!(self.child.wait().unwrap().success());
+    }
+}
+
+#[cargo_test]
+fn requires_z_flag() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("serve-api")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] the `cargo serve-api` command is unstable, pass `-Z serve-api` to enable it",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn metadata_and_resolve() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = { path = "bar" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.1.0"))
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    let mut client = Client::start(&p);
+    let init = client.call(1, "initialize", Value::Null);
+    assert_eq!(init["result"]["protocol_version"], 1);
+    assert_eq!(
+        init["result"]["workspace_root"].as_str().unwrap(),
+        p.root().to_str().unwrap()
+    );
+
+    let metadata = client.call(2, "metadata", json!({"no_deps": true}));
+    let packages = metadata["result"]["packages"].as_array().unwrap();
+    assert_eq!(packages.len(), 1);
+    assert_eq!(packages[0]["name"], "foo");
+    assert!(metadata["result"]["resolve"].is_null());
+
+    let resolve = client.call(3, "resolve", Value::Null);
+    let nodes = resolve["result"]["nodes"].as_array().unwrap();
+    assert_eq!(nodes.len(), 2);
+    assert!(resolve["result"]["root"]
+        .as_str()
+        .unwrap()
+        .starts_with("foo 0.1.0"));
+
+    // The workspace is loaded again when a manifest changes.
+    p.change_file(
+        "Cargo.toml",
+        r#"
+            [package]
+            name = "foo"
+            version = "0.2.0"
+        "#,
+    );
+    let resolve = client.call(4, "resolve", Value::Null);
+    let nodes = resolve["result"]["nodes"].as_array().unwrap();
+    assert_eq!(nodes.len(), 1);
+    assert!(nodes[0]["id"].as_str().unwrap().starts_with("foo 0.2.0"));
+
+    let shutdown = client.call(5, "shutdown", Value::Null);
+    assert_eq!(shutdown, json!({"jsonrpc": "2.0", "id": 5, "result": null}));
+    assert!(client.child.wait().unwrap().success());
+}
+
+#[cargo_test]
+fn invalid_requests() {
+    let p = project().file("src/lib.rs", "").build();
+
+    let mut client = Client::start(&p);
+    client.send("not json");
+    let answer = client.recv();
+    assert_eq!(answer["id"], Value::Null);
+    assert_eq!(answer["error"]["code"], -32700);
+
+    client.send(r#"{"jsonrpc":"1.0","id":1,"method":"metadata"}"#);
+    assert_eq!(client.recv()["error"]["code"], -32600);
+
+    let answer = client.call(2, "frobnicate", Value::Null);
+    assert_eq!(answer["error"]["code"], -32601);
+    assert_eq!(answer["error"]["message"], "unknown method `frobnicate`");
+
+    let answer = client.call(3, "check", json!({"packages": "foo"}));
+    assert_eq!(answer["error"]["code"], -32602);
+
+    let answer = client.call(4, "cancel", json!({"id": 99}));
+    assert_eq!(answer["error"]["code"], -32602);
+    assert_eq!(
+        answer["error"]["message"],
+        "there is no `check` or `build` with the id 99"
+    );
+
+    let answer = client.call(5, "check", json!({"packages": ["nope"]}));
+    assert_eq!(answer["error"]["code"], -32000);
+    let message = answer["error"]["message"].as_str().unwrap();
+    assert!(message.contains("package ID specification `nope` did not match any packages"));
+    assert!(!message.contains("backtrace"));
+
+    client.stop();
+}
+
+#[cargo_test]
+fn check_and_build() {
+    let p = project()
+        .file("src/lib.rs", "pub fn f() {}")
+        .file("src/main.rs", "fn main() { let x = 1; }")
+        .build();
+
+    let mut client = Client::start(&p);
+    client.request(1, "check", Value::Null);
+    let (answer, notifications) = client.answer(1);
+    assert_eq!(answer["result"], Value::Null);
+    assert!(notifications.iter().all(|n| n["params"]["request"] == 1));
+    let reasons: Vec<_> = notifications
+        .iter()
+        .filter(|n| n["method"] == "build/message")
+        .map(|n| n["params"]["message"]["reason"].as_str().unwrap())
+        .collect();
+    assert!(reasons.contains(&"compiler-artifact"));
+    assert!(reasons.contains(&"compiler-message"));
+    assert_eq!(reasons.last(), Some(&"build-finished"));
+    assert!(notifications.iter().any(|n| n["method"] == "log"
+        && n["params"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("Checking foo v0.0.1")));
+    assert!(!p.bin("foo").is_file());
+
+    client.request(2, "build", json!({"release": true}));
+    let (answer, _) = client.answer(2);
+    assert_eq!(answer["result"], Value::Null);
+    assert!(p.release_bin("foo").is_file());
+
+    p.change_file("src/lib.rs", "pub fn f() { error }");
+    client.request(3, "build", Value::Null);
+    let (answer, notifications) = client.answer(3);
+    assert_eq!(answer["error"]["code"], -32000);
+    assert!(answer["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("could not compile `foo`"));
+    assert!(notifications.iter().any(|n| n["method"] == "build/message"
+        && n["params"]["message"]["reason"] == "compiler-message"
+        && n["params"]["message"]["message"]["level"] == "error"));
+
+    client.stop();
+}
+
+#[cargo_test]
+fn cancel_build() {
+    // The build script waits for the test to let it finish.
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                build = "build.rs"
+            "#,
+        )
+        .file(
+            "build.rs",
+            r#"
+                fn main() {
+                    let go = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("go");
+                    while !go.exists() {
+                        std::thread::sleep(std::time::Duration::from_millis(50));
+                    }
+                }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    let mut client = Client::start(&p);
+    client.request(1, "build", Value::Null);
+    loop {
+        let message = client.recv();
+        assert!(message.get("id").is_none(), "{}", message);
+        if message["method"] == "log"
+            && message["params"]["message"]
+                .as_str()
+                .unwrap()
+                .contains("Compiling foo v0.1.0")
+        {
+            break;
+        }
+    }
+    // The build script runs after it is built.
+    loop {
+        let message = client.recv();
+        if message["method"] == "build/message"
+            && message["params"]["message"]["reason"] == "compiler-artifact"
+        {
+            break;
+        }
+    }
+
+    let cancel = client.call(2, "cancel", json!({"id": 1}));
+    assert_eq!(cancel["result"], Value::Null);
+    p.change_file("go", "");
+    let (answer, _) = client.answer(1);
+    assert_eq!(
+        answer["error"],
+        json!({"code": -32800, "message": "the build was cancelled"})
+    );
+    // The library was not built after the build script ran.
+    let lib = p.target_debug_dir().join("libfoo.rlib");
+    assert!(!lib.exists());
+
+    // It is built by the next build.
+    let answer = client.call(3, "build", Value::Null);
+    assert_eq!(answer["result"], Value::Null);
+    assert!(lib.exists());
+
+    client.stop();
+}
@@ -0,0 +1,267 @@
+//! Tests for the source replacements per mode of `-Z source-modes`.
+
+use cargo_test_support::git;
+use cargo_test_support::paths;
+use cargo_test_support::registry::Package;
+use cargo_test_support::{basic_manifest, project};
+use std::fs;
+
+#[cargo_test]
+fn gated() {
+    Package::new("bar", "1.0.0")
+        .file(
+            "src/lib.rs",
+            "pub fn origin() -> &'static str { \"registry\" }",
+        )
+        .publish();
+    let bar_git = git::repo(&paths::root().join("bar-git"))
+        .file("Cargo.toml", &basic_manifest("bar", "1.0.0"))
+        .file("src/lib.rs", "pub fn origin() -> &'static str { \"git\" }")
+        .build();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file(
+            "src/main.rs",
+            r#"fn main() { println!("bar from {}", bar::origin()); }"#,
+        )
+        .file(
+            ".cargo/config",
+            &format!(
+                r#"
+                    [source.crates-io]
+                    replace-when = {{ dev = "bar-git" }}
+
+                    [source.bar-git]
+                    git = "{}"
+                "#,
+                bar_git.url()
+            ),
+        )
+        .build();
+    p.cargo("check")
+        .with_status(101)
+        .with_stderr_contains("[..]`source.crates-io.replace-when` requires `-Z source-modes`[..]")
+        .run();
+    p.cargo("check")
+        .env("CARGO_BUILD_SOURCE_MODE", "ci")
+        .with_status(101)
+        .with_stderr_contains("[..]`build.source-mode` requires `-Z source-modes`")
+        .run();
+}
+
+#[cargo_test]
+fn replace_per_profile() {
+    Package::new("bar", "1.0.0")
+        .file(
+            "src/lib.rs",
+            "pub fn origin() -> &'static str { \"registry\" }",
+        )
+        .publish();
+    let bar_git = git::repo(&paths::root().join("bar-git"))
+        .file("Cargo.toml", &basic_manifest("bar", "1.0.0"))
+        .file("src/lib.rs", "pub fn origin() -> &'static str { \"git\" }")
+        .build();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file(
+            "src/main.rs",
+            r#"fn main() { println!("bar from {}", bar::origin()); }"#,
+        )
+        .file(
+            ".cargo/config",
+            &format!(
+                r#"
+                    [source.crates-io]
+                    replace-when = {{ dev = "bar-git" }}
+
+                    [source.bar-git]
+                    git = "{}"
+                "#,
+                bar_git.url()
+            ),
+        )
+        .build();
+
+    // The `dev` profile uses the git repository, with its own lock file.
+    p.cargo("run -Zsource-modes")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("bar from git")
+        .with_stderr_contains("[UPDATING] git repository `[..]bar-git`")
+        .run();
+    assert!(!p.root().join("Cargo.lock").exists());
+    let dev_lock = p.read_file("Cargo.dev.lock");
+    assert!(dev_lock.contains("name = \"bar\""));
+    assert!(!dev_lock.contains("checksum"));
+
+    // The `release` profile uses the registry, and `Cargo.lock`.
+    p.cargo("run -Zsource-modes --release")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("bar from registry")
+        .with_stderr_contains("[DOWNLOADED] bar v1.0.0 (registry `dummy-registry`)")
+        .run();
+    assert!(p.read_file("Cargo.lock").contains("checksum"));
+    assert_eq!(p.read_file("Cargo.dev.lock"), dev_lock);
+
+    // Each mode keeps using its lock file.
+    p.cargo("run -Zsource-modes")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("bar from git")
+        .run();
+}
+
+#[cargo_test]
+fn replace_per_named_mode() {
+    Package::new("bar", "1.0.0")
+        .file(
+            "src/lib.rs",
+            "pub fn origin() -> &'static str { \"registry\" }",
+        )
+        .publish();
+    let bar_git = git::repo(&paths::root().join("bar-git"))
+        .file("Cargo.toml", &basic_manifest("bar", "1.0.0"))
+        .file("src/lib.rs", "pub fn origin() -> &'static str { \"git\" }")
+        .build();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file(
+            "src/main.rs",
+            r#"fn main() { println!("bar from {}", bar::origin()); }"#,
+        )
+        .file(
+            ".cargo/config",
+            &format!(
+                r#"
+                    [source.crates-io]
+                    replace-when = {{ local-dev = "bar-git" }}
+
+                    [source.bar-git]
+                    git = "{}"
+                "#,
+                bar_git.url()
+            ),
+        )
+        .build();
+
+    p.cargo("run -Zsource-modes")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("bar from registry")
+        .run();
+    assert!(p.root().join("Cargo.lock").exists());
+
+    p.cargo("run -Zsource-modes")
+        .env("CARGO_BUILD_SOURCE_MODE", "local-dev")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("bar from git")
+        .run();
+    assert!(p.root().join("Cargo.local-dev.lock").exists());
+
+    p.cargo("check -Zsource-modes")
+        .env("CARGO_BUILD_SOURCE_MODE", "a/b")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "[..]invalid `build.source-mode` `a/b`, expected a name made of letters, digits, `-` and `_`",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn replacing_git_revision_is_locked() {
+    Package::new("bar", "1.0.0").publish();
+    let bar_git = git::repo(&paths::root().join("bar-git"))
+        .file("Cargo.toml", &basic_manifest("bar", "1.0.0"))
+        .file("src/lib.rs", "pub fn origin() -> &'static str { \"old\" }")
+        .build();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file(
+            "src/main.rs",
+            r#"fn main() { println!("bar from {}", bar::origin()); }"#,
+        )
+        .file(
+            ".cargo/config",
+            &format!(
+                r#"
+                    [source.crates-io]
+                    replace-when = {{ dev = "bar-git" }}
+
+                    [source.bar-git]
+                    git = "{}"
+                "#,
+                bar_git.url()
+            ),
+        )
+        .build();
+
+    p.cargo("run -Zsource-modes")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("bar from old")
+        .run();
+    let old_rev = bar_git.revparse_head();
+    let dev_lock = p.read_file("Cargo.dev.lock");
+    assert!(dev_lock.contains(&old_rev));
+
+    // A new commit on the branch isn't picked up until `cargo update`.
+    fs::write(
+        bar_git.root().join("src/lib.rs"),
+        "pub fn origin() -> &'static str { \"new\" }",
+    )
+    .unwrap();
+    let repo = git2::Repository::open(&bar_git.root()).unwrap();
+    git::add(&repo);
+    let new_rev = git::commit(&repo).to_string();
+    p.cargo("run -Zsource-modes")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("bar from old")
+        .run();
+    assert_eq!(p.read_file("Cargo.dev.lock"), dev_lock);
+
+    p.cargo("update -Zsource-modes -p bar")
+        .masquerade_as_nightly_cargo()
+        .run();
+    assert!(p.read_file("Cargo.dev.lock").contains(&new_rev));
+    p.cargo("run -Zsource-modes")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("bar from new")
+        .run();
+}
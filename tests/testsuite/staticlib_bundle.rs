@@ -0,0 +1,259 @@
+//! Tests for `-Z staticlib-bundle`.
+
+use cargo_test_support::paths::CargoPathExt;
+use cargo_test_support::{basic_manifest, project, Project};
+
+fn archive_contains(p: &Project, member: &str) -> bool {
+    let archive = std::fs::read(p.root().join("target/debug/libfoo.a")).unwrap();
+    archive
+        .windows(member.len())
+        .any(|window| window == member.as_bytes())
+}
+
+fn pc_file(p: &Project) -> String {
+    p.read_file("target/debug/pkgconfig/foo.pc")
+}
+
+#[cargo_test]
+fn not_bundled_without_flag() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                description = "The foo library"
+
+                [lib]
+                crate-type = ["staticlib"]
+
+                [dependencies]
+                nat = { path = "nat" }
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            "extern crate nat; #[no_mangle] pub extern \"C\" fn foo() {}",
+        )
+        .file("nat/Cargo.toml", &basic_manifest("nat", "0.1.0"))
+        .file("nat/src/lib.rs", "")
+        .file(
+            "nat/build.rs",
+            r#"
+                fn main() {
+                    let out = std::env::var("OUT_DIR").unwrap();
+                    std::fs::write(
+                        format!("{}/libnative.a", out),
+                        "!<arch>\nnative.o/       0           0     0     644     6         `\nhello\n",
+                    )
+                    .unwrap();
+                    println!("cargo:rustc-link-search=native={}", out);
+                    println!("cargo:rustc-link-lib=static:-bundle=native");
+                    println!("cargo:rustc-link-search=native=/opt/system");
+                    println!("cargo:rustc-link-lib=dylib=system");
+                }
+            "#,
+        )
+        .build();
+    p.cargo("build").run();
+    assert!(!archive_contains(&p, "native.o"));
+    assert!(!p.root().join("target/debug/pkgconfig").exists());
+}
+
+#[cargo_test]
+fn merges_unbundled_static_libs() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                description = "The foo library"
+
+                [lib]
+                crate-type = ["staticlib"]
+
+                [dependencies]
+                nat = { path = "nat" }
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            "extern crate nat; #[no_mangle] pub extern \"C\" fn foo() {}",
+        )
+        .file("nat/Cargo.toml", &basic_manifest("nat", "0.1.0"))
+        .file("nat/src/lib.rs", "")
+        .file(
+            "nat/build.rs",
+            r#"
+                fn main() {
+                    let out = std::env::var("OUT_DIR").unwrap();
+                    std::fs::write(
+                        format!("{}/libnative.a", out),
+                        "!<arch>\nnative.o/       0           0     0     644     6         `\nhello\n",
+                    )
+                    .unwrap();
+                    println!("cargo:rustc-link-search=native={}", out);
+                    println!("cargo:rustc-link-lib=static:-bundle=native");
+                    println!("cargo:rustc-link-search=native=/opt/system");
+                    println!("cargo:rustc-link-lib=dylib=system");
+                }
+            "#,
+        )
+        .build();
+    p.cargo("build -Zstaticlib-bundle")
+        .masquerade_as_nightly_cargo()
+        .run();
+    assert!(archive_contains(&p, "native.o"));
+
+    let pc = pc_file(&p);
+    let mut lines = pc.lines();
+    assert_eq!(lines.next(), Some("prefix=${pcfiledir}/.."));
+    assert_eq!(lines.next(), Some("libdir=${prefix}"));
+    assert_eq!(lines.next(), Some("includedir=${prefix}/include"));
+    assert_eq!(lines.next(), Some(""));
+    assert_eq!(lines.next(), Some("Name: foo"));
+    assert_eq!(lines.next(), Some("Description: The foo library"));
+    assert_eq!(lines.next(), Some("Version: 0.1.0"));
+    let libs = lines.next().unwrap();
+    assert!(libs.starts_with("Libs: -L${libdir} -lfoo -L"), "{}", libs);
+    assert!(libs.contains(" -L/opt/system "), "{}", libs);
+    assert!(libs.contains(" -lsystem"), "{}", libs);
+    assert!(!libs.contains("-lnative"), "{}", libs);
+    assert_eq!(lines.next(), None);
+}
+
+#[cargo_test]
+fn pkg_config_written_when_fresh() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                description = "The foo library"
+
+                [lib]
+                crate-type = ["staticlib"]
+
+                [dependencies]
+                nat = { path = "nat" }
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            "extern crate nat; #[no_mangle] pub extern \"C\" fn foo() {}",
+        )
+        .file("nat/Cargo.toml", &basic_manifest("nat", "0.1.0"))
+        .file("nat/src/lib.rs", "")
+        .file(
+            "nat/build.rs",
+            r#"
+                fn main() {
+                    let out = std::env::var("OUT_DIR").unwrap();
+                    std::fs::write(
+                        format!("{}/libnative.a", out),
+                        "!<arch>\nnative.o/       0           0     0     644     6         `\nhello\n",
+                    )
+                    .unwrap();
+                    println!("cargo:rustc-link-search=native={}", out);
+                    println!("cargo:rustc-link-lib=static:-bundle=native");
+                    println!("cargo:rustc-link-search=native=/opt/system");
+                    println!("cargo:rustc-link-lib=dylib=system");
+                }
+            "#,
+        )
+        .build();
+    p.cargo("build -Zstaticlib-bundle")
+        .masquerade_as_nightly_cargo()
+        .run();
+    let pc = pc_file(&p);
+
+    p.root().join("target/debug/pkgconfig").rm_rf();
+    p.cargo("build -Zstaticlib-bundle")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[FINISHED] [..]")
+        .run();
+    assert_eq!(pc_file(&p), pc);
+}
+
+#[cargo_test]
+fn rebuilt_when_enabled() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                description = "The foo library"
+
+                [lib]
+                crate-type = ["staticlib"]
+
+                [dependencies]
+                nat = { path = "nat" }
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            "extern crate nat; #[no_mangle] pub extern \"C\" fn foo() {}",
+        )
+        .file("nat/Cargo.toml", &basic_manifest("nat", "0.1.0"))
+        .file("nat/src/lib.rs", "")
+        .file(
+            "nat/build.rs",
+            r#"
+                fn main() {
+                    let out = std::env::var("OUT_DIR").unwrap();
+                    std::fs::write(
+                        format!("{}/libnative.a", out),
+                        "!<arch>\nnative.o/       0           0     0     644     6         `\nhello\n",
+                    )
+                    .unwrap();
+                    println!("cargo:rustc-link-search=native={}", out);
+                    println!("cargo:rustc-link-lib=static:-bundle=native");
+                    println!("cargo:rustc-link-search=native=/opt/system");
+                    println!("cargo:rustc-link-lib=dylib=system");
+                }
+            "#,
+        )
+        .build();
+    p.cargo("build").run();
+    p.cargo("build -Zstaticlib-bundle")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.1.0 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+    assert!(archive_contains(&p, "native.o"));
+    assert!(p.root().join("target/debug/pkgconfig/foo.pc").is_file());
+}
+
+#[cargo_test]
+fn only_for_staticlib() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [lib]
+                crate-type = ["rlib", "cdylib"]
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("build -Zstaticlib-bundle")
+        .masquerade_as_nightly_cargo()
+        .run();
+    assert!(!p.root().join("target/debug/pkgconfig").exists());
+}
@@ -0,0 +1,170 @@
+//! Tests for strict manifests.
+
+use cargo_test_support::registry::Package;
+use cargo_test_support::{basic_manifest, project};
+
+#[cargo_test]
+fn strict_requires_feature() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                strict = true
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("  feature `strict-manifest` is required")
+        .run();
+}
+
+#[cargo_test]
+fn strict_package_rejects_warnings() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["strict-manifest"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                strict = true
+                license = "MIT"
+                license-file = "LICENSE"
+                unknown = true
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse manifest at `[..]/foo/Cargo.toml`
+
+Caused by:
+  the manifest is strict, and has 2 warnings:
+  - only one of `license` or `license-file` is necessary
+    `license` should be used if the package license can be expressed with a standard SPDX expression.
+    `license-file` should be used if the package uses a non-standard license.
+    See https://doc.rust-lang.org/cargo/reference/manifest.html#the-license-and-license-file-fields for more information.
+  - unused manifest key: package.unknown
+",
+        )
+        .run();
+
+    p.change_file(
+        "Cargo.toml",
+        r#"
+            cargo-features = ["strict-manifest"]
+
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            strict = true
+        "#,
+    );
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[CHECKING] foo v0.1.0 ([..])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn strict_manifest_flag() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["foo"]
+                unknown = true
+            "#,
+        )
+        .file("foo/Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("foo/src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .with_stderr(
+            "\
+[WARNING] [..]/Cargo.toml: unused manifest key: workspace.unknown
+[CHECKING] foo v0.1.0 ([..])
+[FINISHED] [..]
+",
+        )
+        .run();
+
+    p.cargo("check -Zstrict-manifest")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse manifest at `[..]/Cargo.toml`
+
+Caused by:
+  the manifest is strict, and has 1 warning:
+  - unused manifest key: workspace.unknown
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn dependencies_from_registries_are_not_strict() {
+    Package::new("bar", "0.1.0")
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "bar"
+                version = "0.1.0"
+                unknown = true
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "0.1"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check -Zstrict-manifest")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] [..]
+[DOWNLOADING] crates ...
+[DOWNLOADED] bar v0.1.0 ([..])
+[CHECKING] bar v0.1.0
+[CHECKING] foo v0.1.0 ([..])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
@@ -0,0 +1,223 @@
+//! Tests for `-Z subcommand-context`.
+
+use std::env;
+use std::ffi::OsString;
+
+use cargo_test_support::{basic_bin_manifest, basic_manifest, project};
+
+/// Builds `cargo-ctx`, which sends the queries given as arguments to the
+/// context and prints the answers, and returns the `PATH` it is found in.
+/// With the argument `fail`, it exits with code 3 instead. A first argument
+/// `stall` leaves another connection idle, and `wrong-secret` presents a
+/// wrong secret.
+fn ctx_subcommand() -> OsString {
+    let p = project()
+        .at("cargo-ctx")
+        .file("Cargo.toml", &basic_bin_manifest("cargo-ctx"))
+        .file(
+            "src/main.rs",
+            r#"
+                use std::env;
+                use std::io::{BufRead, BufReader, Write};
+                use std::net::TcpStream;
+
+                fn main() {
+                    let context = match env::var("CARGO_SUBCOMMAND_CONTEXT") {
+                        Ok(context) => context,
+                        Err(_) => {
+                            println!("no context");
+                            return;
+                        }
+                    };
+                    let (secret, addr) = context.split_once('@').unwrap();
+                    let mut args: Vec<_> = env::args().skip(2).collect();
+                    let _idle = if args.first().map(|a| a == "stall") == Some(true) {
+                        args.remove(0);
+                        Some(TcpStream::connect(addr).unwrap())
+                    } else {
+                        None
+                    };
+                    let secret = if args.first().map(|a| a == "wrong-secret") == Some(true) {
+                        args.remove(0);
+                        "wrong"
+                    } else {
+                        secret
+                    };
+                    let stream = TcpStream::connect(addr).unwrap();
+                    let mut out = stream.try_clone().unwrap();
+                    writeln!(out, "{}", secret).unwrap();
+                    let mut lines = BufReader::new(stream).lines();
+                    match lines.next() {
+                        Some(Ok(line)) => println!("{}", line),
+                        _ => {
+                            println!("closed");
+                            return;
+                        }
+                    }
+                    for query in args {
+                        if query == "fail" {
+                            std::process::exit(3);
+                        }
+                        writeln!(out, "{}", query).unwrap();
+                        println!("{}", lines.next().unwrap().unwrap());
+                    }
+                }
+            "#,
+        )
+        .build();
+    p.cargo("build").run();
+    let mut path: Vec<_> = env::split_paths(&env::var_os("PATH").unwrap_or_default()).collect();
+    path.push(p.target_debug_dir());
+    env::join_paths(path).unwrap()
+}
+
+#[cargo_test]
+fn gated() {
+    let path = ctx_subcommand();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["foo"]
+            "#,
+        )
+        .file("foo/Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("foo/src/lib.rs", "")
+        .file(
+            ".cargo/config.toml",
+            r#"
+                [build]
+                jobs = 2
+
+                [registry]
+                token = "secret"
+            "#,
+        )
+        .build();
+    p.cargo("ctx")
+        .env("PATH", &path)
+        .with_stdout("no context")
+        .run();
+}
+
+#[cargo_test]
+fn queries() {
+    let path = ctx_subcommand();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["foo"]
+            "#,
+        )
+        .file("foo/Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("foo/src/lib.rs", "")
+        .file(
+            ".cargo/config.toml",
+            r#"
+                [build]
+                jobs = 2
+
+                [registry]
+                token = "secret"
+            "#,
+        )
+        .build();
+    p.cargo("-Zsubcommand-context ctx")
+        .arg(r#"{"query":"config"}"#)
+        .arg(r#"{"query":"workspace"}"#)
+        .arg(r#"{"query":"resolve"}"#)
+        .arg(r#"{"query":"config"}"#)
+        .masquerade_as_nightly_cargo()
+        .env("PATH", &path)
+        .with_stdout_contains(r#"{"version":1}"#)
+        .with_stdout_contains(r#"{"query":"config","value":{"cwd":"[CWD]","home":"[..]","values":{"build":{"jobs":2},"registry":{}}}}"#)
+        .with_stdout_contains(r#"{"query":"workspace","value":{"default_members":["foo 0.1.0 (path+file://[..]/foo)"],"members":["foo 0.1.0 (path+file://[..]/foo)"],"root":"[CWD]","root_manifest":"[CWD]/Cargo.toml","target_dir":"[CWD]/target"}}"#)
+        .with_stdout_contains(r#"{"query":"resolve","value":{"nodes":[{"dependencies":[],"deps":[],"features":[],"id":"foo 0.1.0 (path+file://[..]/foo)"}],"root":null}}"#)
+        .with_stdout_does_not_contain("[..]secret[..]")
+        .run();
+}
+
+#[cargo_test]
+fn query_errors() {
+    let path = ctx_subcommand();
+    let p = project()
+        .file("Cargo.toml", "[package]\nname = \"foo\"")
+        .build();
+    p.cargo("-Zsubcommand-context ctx")
+        .arg(r#"{"query":"workspace"}"#)
+        .arg(r#"{"query":"nothing"}"#)
+        .arg("nothing")
+        .masquerade_as_nightly_cargo()
+        .env("PATH", &path)
+        .with_stdout_contains(r#"{"error":"failed to parse manifest at `[CWD]/Cargo.toml`: missing field `version` for key `package`","query":"workspace"}"#)
+        .with_stdout_contains(r#"{"error":"unknown query `nothing`, expected one of: config, workspace, resolve","query":"nothing"}"#)
+        .with_stdout_contains(r#"{"error":"invalid query `nothing`: [..]"}"#)
+        .run();
+}
+
+#[cargo_test]
+fn exit_code() {
+    let path = ctx_subcommand();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["foo"]
+            "#,
+        )
+        .file("foo/Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("foo/src/lib.rs", "")
+        .file(
+            ".cargo/config.toml",
+            r#"
+                [build]
+                jobs = 2
+
+                [registry]
+                token = "secret"
+            "#,
+        )
+        .build();
+    p.cargo("-Zsubcommand-context ctx fail")
+        .masquerade_as_nightly_cargo()
+        .env("PATH", &path)
+        .with_status(3)
+        .with_stdout(r#"{"version":1}"#)
+        .run();
+}
+
+#[cargo_test]
+fn wrong_secret() {
+    let path = ctx_subcommand();
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("-Zsubcommand-context ctx wrong-secret")
+        .arg(r#"{"query":"config"}"#)
+        .masquerade_as_nightly_cargo()
+        .env("PATH", &path)
+        .with_stdout("closed")
+        .run();
+}
+
+#[cargo_test]
+fn idle_connection() {
+    // A connection that sends nothing doesn't hold up the others.
+    let path = ctx_subcommand();
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("-Zsubcommand-context ctx stall")
+        .arg(r#"{"query":"workspace"}"#)
+        .masquerade_as_nightly_cargo()
+        .env("PATH", &path)
+        .with_stdout_contains(r#"{"version":1}"#)
+        .with_stdout_contains(r#"{"query":"workspace","value":{[..]"root":"[CWD]"[..]}}"#)
+        .run();
+}
@@ -0,0 +1,564 @@
+//! Tests for the `-Z sumdb` checksum database.
+
+use std::fs;
+use std::path::Path;
+
+use cargo::util::base64;
+use cargo_test_support::registry::{self, Package};
+use cargo_test_support::{path2url, paths, project, Project};
+use cargo_util::Sha256;
+use ed25519_compact::{KeyPair, Seed};
+use serde_json::json;
+
+type Hash = [u8; 32];
+
+/// The name of the test log, and the origin of its tree heads.
+const LOG_NAME: &str = "sumdb.example.com";
+
+fn log_key(seed: u8) -> KeyPair {
+    KeyPair::from_seed(Seed::new([seed; 32]))
+}
+
+/// The verifier key of `key` for `sumdb-key`.
+fn verifier_key(key: &KeyPair) -> String {
+    let mut encoded = vec![1];
+    encoded.extend_from_slice(&key.pk[..]);
+    let hash = Sha256::new()
+        .update(LOG_NAME.as_bytes())
+        .update(b"\n")
+        .update(&encoded)
+        .finish();
+    format!(
+        "{}+{}+{}",
+        LOG_NAME,
+        hex::encode(&hash[..4]),
+        base64::encode(&encoded)
+    )
+}
+
+/// The tree head of a tree of `size` leaves with the root `root`, as a note
+/// signed with `key`.
+fn tree_head(key: &KeyPair, size: usize, root: &Hash) -> String {
+    let text = format!("{}\n{}\n{}\n", LOG_NAME, size, base64::encode(root));
+    let verifier = verifier_key(key);
+    let mut signature = hex::decode(verifier.split('+').nth(1).unwrap()).unwrap();
+    signature.extend_from_slice(&key.sk.sign(&text, None)[..]);
+    format!(
+        "{}\n\u{2014} {} {}\n",
+        text,
+        LOG_NAME,
+        base64::encode(&signature)
+    )
+}
+
+fn leaf_hash(leaf: &str) -> Hash {
+    Sha256::new().update(&[0]).update(leaf.as_bytes()).finish()
+}
+
+fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    Sha256::new()
+        .update(&[1])
+        .update(left)
+        .update(right)
+        .finish()
+}
+
+/// The largest power of two smaller than `n`.
+fn split(n: usize) -> usize {
+    let mut k = 1;
+    while k * 2 < n {
+        k *= 2;
+    }
+    k
+}
+
+/// The Merkle tree hash of RFC 6962.
+fn root(leaves: &[Hash]) -> Hash {
+    if leaves.len() == 1 {
+        return leaves[0];
+    }
+    let k = split(leaves.len());
+    node_hash(&root(&leaves[..k]), &root(&leaves[k..]))
+}
+
+fn inclusion_proof(m: usize, leaves: &[Hash]) -> Vec<Hash> {
+    if leaves.len() == 1 {
+        return Vec::new();
+    }
+    let k = split(leaves.len());
+    if m < k {
+        let mut proof = inclusion_proof(m, &leaves[..k]);
+        proof.push(root(&leaves[k..]));
+        proof
+    } else {
+        let mut proof = inclusion_proof(m - k, &leaves[k..]);
+        proof.push(root(&leaves[..k]));
+        proof
+    }
+}
+
+fn consistency_proof(m: usize, leaves: &[Hash], complete: bool) -> Vec<Hash> {
+    if m == leaves.len() {
+        return if complete {
+            Vec::new()
+        } else {
+            vec![root(leaves)]
+        };
+    }
+    let k = split(leaves.len());
+    if m <= k {
+        let mut proof = consistency_proof(m, &leaves[..k], complete);
+        proof.push(root(&leaves[k..]));
+        proof
+    } else {
+        let mut proof = consistency_proof(m - k, &leaves[k..], false);
+        proof.push(root(&leaves[..k]));
+        proof
+    }
+}
+
+fn hex_list(hashes: &[Hash]) -> Vec<String> {
+    hashes.iter().map(hex::encode).collect()
+}
+
+/// Writes a transparency log of the crates `entries` to `dir`, signed with
+/// `log_key(1)`, with the consistency proofs from its trees of `sizes` leaves.
+fn write_log(dir: &Path, entries: &[(&str, &str, &str)], sizes: &[usize]) {
+    let _ = fs::remove_dir_all(dir);
+    let leaves: Vec<Hash> = entries
+        .iter()
+        .map(|(name, version, cksum)| leaf_hash(&format!("{} {} {}\n", name, version, cksum)))
+        .collect();
+    for (i, (name, version, cksum)) in entries.iter().enumerate() {
+        let path = dir.join("lookup").join(name).join(version);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(
+            path,
+            json!({
+                "checksum": cksum,
+                "leaf-index": i,
+                "tree-head": tree_head(&log_key(1), leaves.len(), &root(&leaves)),
+                "inclusion-proof": hex_list(&inclusion_proof(i, &leaves)),
+            })
+            .to_string(),
+        )
+        .unwrap();
+    }
+    for &size in sizes {
+        let path = dir
+            .join("consistency")
+            .join(size.to_string())
+            .join(leaves.len().to_string());
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(
+            path,
+            json!({ "proof": hex_list(&consistency_proof(size, &leaves, true)) }).to_string(),
+        )
+        .unwrap();
+    }
+}
+
+/// Configures the log in `log` for the alternative registry.
+fn use_log(p: &Project, log: &Path) {
+    p.change_file(
+        ".cargo/config",
+        &format!(
+            "[registries.alternative]\nsumdb = '{}'\nsumdb-key = '{}'\n",
+            path2url(log),
+            verifier_key(&log_key(1))
+        ),
+    );
+}
+
+fn sumdb_dir() -> std::path::PathBuf {
+    let dirs: Vec<_> = fs::read_dir(paths::home().join(".cargo/registry/sumdb"))
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect();
+    assert_eq!(dirs.len(), 1);
+    dirs.into_iter().next().unwrap()
+}
+
+#[cargo_test]
+fn records_and_checks_checksums() {
+    registry::init();
+    let cksum = Package::new("bar", "1.0.0").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    // Without the flag, nothing is recorded.
+    p.cargo("check").run();
+    assert!(!paths::home().join(".cargo/registry/sumdb").exists());
+
+    p.cargo("check -Zsumdb").masquerade_as_nightly_cargo().run();
+    let sums = sumdb_dir().join("sums");
+    assert_eq!(
+        fs::read_to_string(&sums).unwrap(),
+        format!("bar 1.0.0 {}\n", cksum)
+    );
+
+    // The recorded checksum no longer matches the index.
+    fs::write(&sums, format!("bar 1.0.0 {}\n", "0".repeat(64))).unwrap();
+    p.cargo("check -Zsumdb")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(&format!(
+            "\
+Caused by:
+  the checksum of bar v1.0.0 (registry `dummy-registry`) changed since it was recorded \
+in the checksum database
+  recorded: {}
+  index:    {}
+  the index may have been tampered with, or the crate republished with other contents, \
+the checksum database is in `[..]/sumdb/[..]`
+",
+            "0".repeat(64),
+            cksum
+        ))
+        .run();
+}
+
+#[cargo_test]
+fn verifies_against_transparency_log() {
+    registry::alt_init();
+    let bar = Package::new("bar", "1.0.0").alternative(true).publish();
+    let baz = Package::new("baz", "1.0.0").alternative(true).publish();
+    let log = paths::root().join("log");
+
+    write_log(
+        &log,
+        &[("qux", "0.1.0", &"1".repeat(64)), ("bar", "1.0.0", &bar)],
+        &[],
+    );
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = { version = "1.0", registry = "alternative" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+    use_log(&p, &log);
+    p.cargo("check -Zsumdb").masquerade_as_nightly_cargo().run();
+    assert_eq!(
+        fs::read_to_string(sumdb_dir().join("tree")).unwrap(),
+        format!(
+            "2 {}\n",
+            hex::encode(root(&[
+                leaf_hash(&format!("qux 0.1.0 {}\n", "1".repeat(64))),
+                leaf_hash(&format!("bar 1.0.0 {}\n", bar))
+            ]))
+        )
+    );
+
+    // The log grew, consistently with the tree verified before.
+    write_log(
+        &log,
+        &[
+            ("qux", "0.1.0", &"1".repeat(64)),
+            ("bar", "1.0.0", &bar),
+            ("qux", "0.2.0", &"2".repeat(64)),
+            ("baz", "1.0.0", &baz),
+            ("qux", "0.3.0", &"3".repeat(64)),
+        ],
+        &[2],
+    );
+    p.change_file(
+        "Cargo.toml",
+        &p.read_file("Cargo.toml").replace(
+            "[dependencies]",
+            "[dependencies]\nbaz = { version = \"1.0\", registry = \"alternative\" }",
+        ),
+    );
+    p.cargo("check -Zsumdb").masquerade_as_nightly_cargo().run();
+    assert!(fs::read_to_string(sumdb_dir().join("tree"))
+        .unwrap()
+        .starts_with("5 "));
+}
+
+#[cargo_test]
+fn rejects_mismatches_with_the_log() {
+    registry::alt_init();
+    let bar = Package::new("bar", "1.0.0").alternative(true).publish();
+    let baz = Package::new("baz", "1.0.0").alternative(true).publish();
+    let log = paths::root().join("log");
+
+    // The log has another checksum for `bar`.
+    write_log(&log, &[("bar", "1.0.0", &"0".repeat(64))], &[]);
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = { version = "1.0", registry = "alternative" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+    use_log(&p, &log);
+    p.cargo("check -Zsumdb")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(&format!(
+            "\
+Caused by:
+  failed to verify bar v1.0.0 (registry `alternative`) in the transparency log
+
+Caused by:
+  the checksum of bar v1.0.0 (registry `alternative`) in the index differs from the one in the transparency log
+  log:   {}
+  index: {}
+  the index may have been tampered with
+",
+            "0".repeat(64),
+            bar
+        ))
+        .run();
+    assert!(!paths::home().join(".cargo/registry/sumdb").exists());
+
+    write_log(
+        &log,
+        &[("bar", "1.0.0", &bar), ("qux", "0.1.0", &"1".repeat(64))],
+        &[],
+    );
+    p.cargo("check -Zsumdb").masquerade_as_nightly_cargo().run();
+
+    // The log rewrote its history, `qux 0.1.0` is gone.
+    write_log(
+        &log,
+        &[
+            ("bar", "1.0.0", &bar),
+            ("qux", "0.1.1", &"1".repeat(64)),
+            ("baz", "1.0.0", &baz),
+        ],
+        &[2],
+    );
+    p.change_file(
+        "Cargo.toml",
+        &p.read_file("Cargo.toml").replace(
+            "[dependencies]",
+            "[dependencies]\nbaz = { version = \"1.0\", registry = \"alternative\" }",
+        ),
+    );
+    p.cargo("check -Zsumdb")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+Caused by:
+  failed to verify baz v1.0.0 (registry `alternative`) in the transparency log
+
+Caused by:
+  the transparency log `file://[..]/log` is not consistent with the tree of 2 entries \
+verified before, its history may have been rewritten
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn sums_recorded_before_the_log() {
+    registry::alt_init();
+    let bar = Package::new("bar", "1.0.0").alternative(true).publish();
+    let log = paths::root().join("log");
+    write_log(&log, &[("bar", "1.0.0", &bar)], &[]);
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = { version = "1.0", registry = "alternative" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("generate-lockfile -Zsumdb -Zlockfile-v5")
+        .masquerade_as_nightly_cargo()
+        .run();
+    assert!(p.read_lockfile().contains("signature = \"checksum-db\"\n"));
+
+    // The checksum recorded before the log was configured wasn't verified
+    // against it.
+    use_log(&p, &log);
+    fs::remove_file(p.root().join("Cargo.lock")).unwrap();
+    p.cargo("generate-lockfile -Zlockfile-v5")
+        .masquerade_as_nightly_cargo()
+        .run();
+    assert!(p.read_lockfile().contains("signature = \"checksum-db\"\n"));
+
+    // Until it is checked again.
+    fs::remove_file(p.root().join("Cargo.lock")).unwrap();
+    p.cargo("generate-lockfile -Zsumdb -Zlockfile-v5")
+        .masquerade_as_nightly_cargo()
+        .run();
+    assert!(p
+        .read_lockfile()
+        .contains("signature = \"transparency-log\"\n"));
+    assert_eq!(
+        fs::read_to_string(sumdb_dir().join("sums")).unwrap(),
+        format!("bar 1.0.0 {0}\nbar 1.0.0 {0} log\n", bar)
+    );
+}
+
+#[cargo_test]
+fn log_without_key() {
+    registry::alt_init();
+    let bar = Package::new("bar", "1.0.0").alternative(true).publish();
+    let log = paths::root().join("log");
+    write_log(&log, &[("bar", "1.0.0", &bar)], &[]);
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = { version = "1.0", registry = "alternative" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+    p.change_file(
+        ".cargo/config",
+        &format!("[registries.alternative]\nsumdb = '{}'\n", path2url(&log)),
+    );
+    p.cargo("check -Zsumdb")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "  the transparency log `file://[..]/log` has no `registries.alternative.sumdb-key`, \
+the public key of the log is needed to verify its tree heads",
+        )
+        .run();
+
+    p.change_file(
+        ".cargo/config",
+        &format!(
+            "[registries.alternative]\nsumdb = '{}'\nsumdb-key = '{}+00000000+AQ'\n",
+            path2url(&log),
+            LOG_NAME
+        ),
+    );
+    p.cargo("check -Zsumdb")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("  invalid `registries.alternative.sumdb-key` in [..]config")
+        .run();
+    assert!(!paths::home().join(".cargo/registry/sumdb").exists());
+}
+
+#[cargo_test]
+fn rejects_tree_heads_not_signed_by_the_log() {
+    registry::alt_init();
+    let bar = Package::new("bar", "1.0.0").alternative(true).publish();
+    let log = paths::root().join("log");
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = { version = "1.0", registry = "alternative" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+    use_log(&p, &log);
+
+    // A tree head of `bar` alone, signed with another key.
+    write_log(&log, &[("bar", "1.0.0", &bar)], &[]);
+    let leaf = leaf_hash(&format!("bar 1.0.0 {}\n", bar));
+    let lookup = log.join("lookup/bar/1.0.0");
+    fs::write(
+        &lookup,
+        json!({
+            "checksum": bar,
+            "leaf-index": 0,
+            "tree-head": tree_head(&log_key(2), 1, &leaf),
+            "inclusion-proof": [],
+        })
+        .to_string(),
+    )
+    .unwrap();
+    p.cargo("check -Zsumdb")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+Caused by:
+  failed to verify bar v1.0.0 (registry `alternative`) in the transparency log
+
+Caused by:
+  invalid tree head from `file://[..]/log/lookup/bar/1.0.0`
+
+Caused by:
+  the tree head isn't signed by the key `sumdb.example.com`
+",
+        )
+        .run();
+
+    // The signed text is changed.
+    let head = tree_head(&log_key(1), 1, &leaf);
+    fs::write(
+        &lookup,
+        json!({
+            "checksum": bar,
+            "leaf-index": 0,
+            "tree-head": head.replacen("\n1\n", "\n2\n", 1),
+            "inclusion-proof": [],
+        })
+        .to_string(),
+    )
+    .unwrap();
+    p.cargo("check -Zsumdb")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("  the tree head isn't signed by the key `sumdb.example.com`")
+        .run();
+    assert!(!paths::home().join(".cargo/registry/sumdb").exists());
+
+    fs::write(
+        &lookup,
+        json!({
+            "checksum": bar,
+            "leaf-index": 0,
+            "tree-head": head,
+            "inclusion-proof": [],
+        })
+        .to_string(),
+    )
+    .unwrap();
+    p.cargo("check -Zsumdb").masquerade_as_nightly_cargo().run();
+}
@@ -0,0 +1,270 @@
+//! Tests for selecting targets with `--targets` expressions.
+
+use cargo_test_support::{basic_manifest, project};
+
+#[cargo_test]
+fn targets_requires_flag() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                edition = "2018"
+
+                [features]
+                cli = []
+
+                [[bin]]
+                name = "tool"
+                required-features = ["cli"]
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("src/main.rs", "fn main() {}")
+        .file("src/bin/tool.rs", "fn main() {}")
+        .file("src/bin/legacy-a.rs", "fn main() {}")
+        .file("src/bin/legacy-b.rs", "fn main() {}")
+        .file("examples/demo.rs", "fn main() {}")
+        .file("tests/it.rs", "")
+        .build();
+
+    p.cargo("build --targets kind(bin)")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] Usage of `--targets` requires `-Z target-expr`")
+        .run();
+}
+
+#[cargo_test]
+fn bins_except_legacy() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                edition = "2018"
+
+                [features]
+                cli = []
+
+                [[bin]]
+                name = "tool"
+                required-features = ["cli"]
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("src/main.rs", "fn main() {}")
+        .file("src/bin/tool.rs", "fn main() {}")
+        .file("src/bin/legacy-a.rs", "fn main() {}")
+        .file("src/bin/legacy-b.rs", "fn main() {}")
+        .file("examples/demo.rs", "fn main() {}")
+        .file("tests/it.rs", "")
+        .build();
+
+    p.cargo("build -Z target-expr -v")
+        .arg("--targets")
+        .arg("kind(bin) & !name(legacy*)")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[RUNNING] `rustc --crate-name foo --edition=2018 src/main.rs [..]")
+        .with_stderr_does_not_contain("[RUNNING] `rustc --crate-name legacy[..]")
+        .with_stderr_does_not_contain("[RUNNING] `rustc --crate-name demo[..]")
+        .with_stderr_does_not_contain("[RUNNING] `rustc --crate-name it[..]")
+        .run();
+
+    assert!(p.bin("foo").is_file());
+    assert!(!p.bin("legacy-a").is_file());
+    assert!(!p.bin("tool").is_file());
+}
+
+#[cargo_test]
+fn required_features_predicate() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                edition = "2018"
+
+                [features]
+                cli = []
+
+                [[bin]]
+                name = "tool"
+                required-features = ["cli"]
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("src/main.rs", "fn main() {}")
+        .file("src/bin/tool.rs", "fn main() {}")
+        .file("src/bin/legacy-a.rs", "fn main() {}")
+        .file("src/bin/legacy-b.rs", "fn main() {}")
+        .file("examples/demo.rs", "fn main() {}")
+        .file("tests/it.rs", "")
+        .build();
+
+    p.cargo("build -Z target-expr --features cli")
+        .arg("--targets")
+        .arg("required-features(cli)")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    assert!(p.bin("tool").is_file());
+    assert!(!p.bin("foo").is_file());
+}
+
+#[cargo_test]
+fn combined_with_filter_flags() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                edition = "2018"
+
+                [features]
+                cli = []
+
+                [[bin]]
+                name = "tool"
+                required-features = ["cli"]
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("src/main.rs", "fn main() {}")
+        .file("src/bin/tool.rs", "fn main() {}")
+        .file("src/bin/legacy-a.rs", "fn main() {}")
+        .file("src/bin/legacy-b.rs", "fn main() {}")
+        .file("examples/demo.rs", "fn main() {}")
+        .file("tests/it.rs", "")
+        .build();
+
+    p.cargo("build -Z target-expr --bins -v")
+        .arg("--targets")
+        .arg("name(legacy-a) | name(demo)")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[RUNNING] `rustc --crate-name legacy_a [..]")
+        .with_stderr_does_not_contain("[RUNNING] `rustc --crate-name demo[..]")
+        .run();
+}
+
+#[cargo_test]
+fn package_predicate() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["api", "web"]
+            "#,
+        )
+        .file("api/Cargo.toml", &basic_manifest("api", "0.1.0"))
+        .file("api/src/lib.rs", "")
+        .file("web/Cargo.toml", &basic_manifest("web", "0.1.0"))
+        .file("web/src/lib.rs", "")
+        .build();
+
+    p.cargo("check -Z target-expr")
+        .arg("--targets")
+        .arg("package(a*) & kind(lib)")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[CHECKING] api v0.1.0 ([..])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn no_match_warns() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                edition = "2018"
+
+                [features]
+                cli = []
+
+                [[bin]]
+                name = "tool"
+                required-features = ["cli"]
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("src/main.rs", "fn main() {}")
+        .file("src/bin/tool.rs", "fn main() {}")
+        .file("src/bin/legacy-a.rs", "fn main() {}")
+        .file("src/bin/legacy-b.rs", "fn main() {}")
+        .file("examples/demo.rs", "fn main() {}")
+        .file("tests/it.rs", "")
+        .build();
+
+    p.cargo("build -Z target-expr")
+        .arg("--targets")
+        .arg("kind(bench)")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[WARNING] target expression `kind(bench)` did not match any targets
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn invalid_expression() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                edition = "2018"
+
+                [features]
+                cli = []
+
+                [[bin]]
+                name = "tool"
+                required-features = ["cli"]
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("src/main.rs", "fn main() {}")
+        .file("src/bin/tool.rs", "fn main() {}")
+        .file("src/bin/legacy-a.rs", "fn main() {}")
+        .file("src/bin/legacy-b.rs", "fn main() {}")
+        .file("examples/demo.rs", "fn main() {}")
+        .file("tests/it.rs", "")
+        .build();
+
+    p.cargo("build -Z target-expr")
+        .arg("--targets")
+        .arg("kind(binary)")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse target expression `kind(binary)`
+
+Caused by:
+  unknown target kind `binary`, expected one of `lib`, `proc-macro`, `bin`, `example`, `test` or `bench`
+",
+        )
+        .run();
+}
@@ -0,0 +1,193 @@
+//! Tests for the temporary overrides of `-Z temporary-overrides`.
+
+use cargo_test_support::registry::Package;
+use cargo_test_support::{basic_manifest, project};
+
+#[cargo_test]
+fn gated() {
+    Package::new("bar", "1.0.0")
+        .file("src/lib.rs", "pub fn bar() -> u32 { 1 }")
+        .publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "pub fn foo() -> u32 { bar::bar() }")
+        .file("bar-fork/Cargo.toml", &basic_manifest("bar", "1.1.0-dev"))
+        .file("bar-fork/src/lib.rs", "pub fn bar() -> u32 { 2 }")
+        .build();
+    p.cargo("check --override bar=bar-fork")
+        .with_status(101)
+        .with_stderr("[ERROR] Usage of `--override` requires `-Z temporary-overrides`")
+        .run();
+
+    p.change_file(
+        "Cargo.override.toml",
+        r#"
+            [override]
+            bar = { path = "bar-fork" }
+        "#,
+    );
+    p.cargo("check")
+        .with_stderr(
+            "\
+[WARNING] ignoring `[ROOT]/foo/Cargo.override.toml`, which requires `-Z temporary-overrides`
+[UPDATING] [..]
+[DOWNLOADING] crates ...
+[DOWNLOADED] bar v1.0.0 [..]
+[CHECKING] bar v1.0.0
+[CHECKING] foo v0.1.0 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn cli_override() {
+    Package::new("bar", "1.0.0")
+        .file("src/lib.rs", "pub fn bar() -> u32 { 1 }")
+        .publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "pub fn foo() -> u32 { bar::bar() }")
+        .file("bar-fork/Cargo.toml", &basic_manifest("bar", "1.1.0-dev"))
+        .file("bar-fork/src/lib.rs", "pub fn bar() -> u32 { 2 }")
+        .build();
+    p.cargo("generate-lockfile").run();
+    let lockfile = p.read_file("Cargo.lock");
+
+    p.cargo("check -Ztemporary-overrides --override bar=bar-fork")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[OVERRIDING] bar v1.0.0 with bar v1.1.0-dev ([ROOT]/foo/bar-fork) \
+(override active, not written to Cargo.lock)
+[CHECKING] bar v1.1.0-dev ([ROOT]/foo/bar-fork)
+[CHECKING] foo v0.1.0 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+    assert_eq!(p.read_file("Cargo.lock"), lockfile);
+
+    // Without the override, the locked package is used again.
+    p.cargo("check")
+        .with_stderr(
+            "\
+[DOWNLOADING] crates ...
+[DOWNLOADED] bar v1.0.0 [..]
+[CHECKING] bar v1.0.0
+[CHECKING] foo v0.1.0 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+    assert_eq!(p.read_file("Cargo.lock"), lockfile);
+}
+
+#[cargo_test]
+fn override_file() {
+    Package::new("bar", "1.0.0")
+        .file("src/lib.rs", "pub fn bar() -> u32 { 1 }")
+        .publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "pub fn foo() -> u32 { bar::bar() }")
+        .file("bar-fork/Cargo.toml", &basic_manifest("bar", "1.1.0-dev"))
+        .file("bar-fork/src/lib.rs", "pub fn bar() -> u32 { 2 }")
+        .build();
+    p.change_file(
+        "Cargo.override.toml",
+        r#"
+            [override]
+            bar = { path = "bar-fork" }
+        "#,
+    );
+    p.cargo("check -Ztemporary-overrides")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] [..]
+[OVERRIDING] bar v1.0.0 with bar v1.1.0-dev ([ROOT]/foo/bar-fork) \
+(override active, not written to Cargo.lock)
+[CHECKING] bar v1.1.0-dev ([ROOT]/foo/bar-fork)
+[CHECKING] foo v0.1.0 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+    assert!(!p.read_file("Cargo.lock").contains("bar-fork"));
+}
+
+#[cargo_test]
+fn invalid_overrides() {
+    Package::new("bar", "1.0.0")
+        .file("src/lib.rs", "pub fn bar() -> u32 { 1 }")
+        .publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "pub fn foo() -> u32 { bar::bar() }")
+        .file("bar-fork/Cargo.toml", &basic_manifest("bar", "1.1.0-dev"))
+        .file("bar-fork/src/lib.rs", "pub fn bar() -> u32 { 2 }")
+        .build();
+    p.cargo("check -Ztemporary-overrides --override bar")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] invalid override `bar`, expected `NAME=PATH`, like `bar=../bar-fork`")
+        .run();
+    p.cargo("check -Ztemporary-overrides --override baz=bar-fork")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "[ERROR] the override of `baz` is the package `bar` at `[ROOT]/foo/bar-fork`",
+        )
+        .run();
+    p.change_file("baz/Cargo.toml", &basic_manifest("baz", "1.0.0"));
+    p.change_file("baz/src/lib.rs", "");
+    p.cargo("check -Ztemporary-overrides --override baz=baz")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "[WARNING] the override of `baz` by `[ROOT]/foo/baz` is not used, \
+             no package of the workspace depends on `baz`",
+        )
+        .run();
+}
@@ -0,0 +1,215 @@
+//! Tests for `cargo test --affected`.
+
+use cargo_test_support::project;
+
+#[cargo_test]
+fn affected_requires_flag() {
+    let p = project()
+        .file(
+            "src/lib.rs",
+            "pub fn answer() -> u32 { 42 } #[test] fn unit() {}",
+        )
+        .file(
+            "tests/math.rs",
+            "#[test] fn math() { assert_eq!(foo::answer(), 42); }",
+        )
+        .file("tests/other.rs", "#[test] fn other() {}")
+        .build();
+
+    p.cargo("test --affected")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] Usage of `--affected` requires `-Z test-affected`")
+        .run();
+}
+
+#[cargo_test]
+fn skips_unchanged_tests() {
+    let p = project()
+        .file(
+            "src/lib.rs",
+            "pub fn answer() -> u32 { 42 } #[test] fn unit() {}",
+        )
+        .file(
+            "tests/math.rs",
+            "#[test] fn math() { assert_eq!(foo::answer(), 42); }",
+        )
+        .file("tests/other.rs", "#[test] fn other() {}")
+        .build();
+
+    p.cargo("test -Z test-affected --affected --tests")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+[RUNNING] unittests (target/debug/deps/foo-[..][EXE])
+[RUNNING] tests/math.rs (target/debug/deps/math-[..][EXE])
+[RUNNING] tests/other.rs (target/debug/deps/other-[..][EXE])
+",
+        )
+        .run();
+
+    p.cargo("test -Z test-affected --affected --tests")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[FINISHED] [..]
+[SKIPPING] unittests (target/debug/deps/foo-[..][EXE]) (unchanged since it last passed)
+[SKIPPING] tests/math.rs (target/debug/deps/math-[..][EXE]) (unchanged since it last passed)
+[SKIPPING] tests/other.rs (target/debug/deps/other-[..][EXE]) (unchanged since it last passed)
+",
+        )
+        .run();
+
+    // Without `--affected` everything runs as usual.
+    p.cargo("test --tests")
+        .with_stderr(
+            "\
+[FINISHED] [..]
+[RUNNING] unittests (target/debug/deps/foo-[..][EXE])
+[RUNNING] tests/math.rs (target/debug/deps/math-[..][EXE])
+[RUNNING] tests/other.rs (target/debug/deps/other-[..][EXE])
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn reruns_changed_tests() {
+    let p = project()
+        .file(
+            "src/lib.rs",
+            "pub fn answer() -> u32 { 42 } #[test] fn unit() {}",
+        )
+        .file(
+            "tests/math.rs",
+            "#[test] fn math() { assert_eq!(foo::answer(), 42); }",
+        )
+        .file("tests/other.rs", "#[test] fn other() {}")
+        .build();
+
+    p.cargo("test -Z test-affected --affected --tests")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    p.change_file(
+        "tests/other.rs",
+        "#[test] fn other() {} #[test] fn more() {}",
+    );
+    p.cargo("test -Z test-affected --affected --tests")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+[SKIPPING] unittests (target/debug/deps/foo-[..][EXE]) (unchanged since it last passed)
+[SKIPPING] tests/math.rs (target/debug/deps/math-[..][EXE]) (unchanged since it last passed)
+[RUNNING] tests/other.rs (target/debug/deps/other-[..][EXE])
+",
+        )
+        .run();
+
+    // Integration tests are relinked when the library changes.
+    p.change_file(
+        "src/lib.rs",
+        "pub fn answer() -> u32 { 40 + 2 } #[test] fn unit() {}",
+    );
+    p.cargo("test -Z test-affected --affected --tests")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+[RUNNING] unittests (target/debug/deps/foo-[..][EXE])
+[RUNNING] tests/math.rs (target/debug/deps/math-[..][EXE])
+[RUNNING] tests/other.rs (target/debug/deps/other-[..][EXE])
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn reruns_failed_tests() {
+    let p = project()
+        .file(
+            "src/lib.rs",
+            "pub fn answer() -> u32 { 42 } #[test] fn unit() {}",
+        )
+        .file(
+            "tests/math.rs",
+            "#[test] fn math() { assert_eq!(foo::answer(), 42); }",
+        )
+        .file("tests/other.rs", "#[test] fn other() {}")
+        .build();
+    p.change_file("tests/other.rs", "#[test] fn other() { panic!() }");
+
+    p.cargo("test -Z test-affected --affected --tests --no-fail-fast")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[ERROR] test failed, to rerun pass '--test other'")
+        .run();
+
+    p.cargo("test -Z test-affected --affected --tests --no-fail-fast")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "[SKIPPING] tests/math.rs (target/debug/deps/math-[..][EXE]) (unchanged since it last passed)",
+        )
+        .with_stderr_contains("[RUNNING] tests/other.rs (target/debug/deps/other-[..][EXE])")
+        .run();
+}
+
+#[cargo_test]
+fn reruns_with_different_args() {
+    let p = project()
+        .file(
+            "src/lib.rs",
+            "pub fn answer() -> u32 { 42 } #[test] fn unit() {}",
+        )
+        .file(
+            "tests/math.rs",
+            "#[test] fn math() { assert_eq!(foo::answer(), 42); }",
+        )
+        .file("tests/other.rs", "#[test] fn other() {}")
+        .build();
+
+    p.cargo("test -Z test-affected --affected --test math")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    p.cargo("test -Z test-affected --affected --test math -- --test-threads=1")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[FINISHED] [..]
+[RUNNING] tests/math.rs (target/debug/deps/math-[..][EXE])
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn doc_tests_always_run() {
+    let p = project()
+        .file(
+            "src/lib.rs",
+            "/// ```\n/// foo::f();\n/// ```\npub fn f() {}",
+        )
+        .build();
+
+    p.cargo("test -Z test-affected --affected")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    p.cargo("test -Z test-affected --affected")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[FINISHED] [..]
+[SKIPPING] unittests (target/debug/deps/foo-[..][EXE]) (unchanged since it last passed)
+[DOCTEST] foo
+",
+        )
+        .run();
+}
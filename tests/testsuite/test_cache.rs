@@ -0,0 +1,198 @@
+//! Tests for caching test results with `-Z test-cache`.
+
+use cargo_test_support::project;
+
+#[cargo_test]
+fn no_cache_requires_flag() {
+    let p = project()
+        .file("src/lib.rs", "#[test] fn unit() {}")
+        .file("tests/it.rs", "#[test] fn it() {}")
+        .build();
+
+    p.cargo("test --no-cache")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] Usage of `--no-cache` requires `-Z test-cache`")
+        .run();
+}
+
+#[cargo_test]
+fn passed_tests_are_cached() {
+    let p = project()
+        .file("src/lib.rs", "#[test] fn unit() {}")
+        .file("tests/it.rs", "#[test] fn it() {}")
+        .build();
+
+    p.cargo("test -Z test-cache --tests")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+[RUNNING] unittests (target/debug/deps/foo-[..][EXE])
+[RUNNING] tests/it.rs (target/debug/deps/it-[..][EXE])
+",
+        )
+        .run();
+
+    p.cargo("test -Z test-cache --tests")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("")
+        .with_stderr(
+            "\
+[FINISHED] [..]
+[CACHED] unittests (target/debug/deps/foo-[..][EXE]) (passed previously)
+[CACHED] tests/it.rs (target/debug/deps/it-[..][EXE]) (passed previously)
+",
+        )
+        .run();
+
+    p.cargo("test -Z test-cache --tests --no-cache")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[FINISHED] [..]
+[RUNNING] unittests (target/debug/deps/foo-[..][EXE])
+[RUNNING] tests/it.rs (target/debug/deps/it-[..][EXE])
+",
+        )
+        .run();
+
+    // The cache is not used without `-Z test-cache`.
+    p.cargo("test --tests")
+        .with_stderr(
+            "\
+[FINISHED] [..]
+[RUNNING] unittests (target/debug/deps/foo-[..][EXE])
+[RUNNING] tests/it.rs (target/debug/deps/it-[..][EXE])
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn rebuild_invalidates_cache() {
+    let p = project()
+        .file("src/lib.rs", "#[test] fn unit() {}")
+        .file("tests/it.rs", "#[test] fn it() {}")
+        .build();
+
+    p.cargo("test -Z test-cache --tests")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    p.change_file("tests/it.rs", "#[test] fn it() {} #[test] fn more() {}");
+    p.cargo("test -Z test-cache --tests")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+[CACHED] unittests (target/debug/deps/foo-[..][EXE]) (passed previously)
+[RUNNING] tests/it.rs (target/debug/deps/it-[..][EXE])
+",
+        )
+        .run();
+
+    // Changing the library rebuilds the integration test as well.
+    p.change_file("src/lib.rs", "#[test] fn unit() {} pub fn f() {}");
+    p.cargo("test -Z test-cache --tests")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+[RUNNING] unittests (target/debug/deps/foo-[..][EXE])
+[RUNNING] tests/it.rs (target/debug/deps/it-[..][EXE])
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn different_args_are_not_cached() {
+    let p = project()
+        .file("src/lib.rs", "#[test] fn unit() {}")
+        .file("tests/it.rs", "#[test] fn it() {}")
+        .build();
+
+    p.cargo("test -Z test-cache --test it")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    p.cargo("test -Z test-cache --test it -- --test-threads=1")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[FINISHED] [..]
+[RUNNING] tests/it.rs (target/debug/deps/it-[..][EXE])
+",
+        )
+        .run();
+
+    p.cargo("test -Z test-cache --test it -- --test-threads=1")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[FINISHED] [..]
+[CACHED] tests/it.rs (target/debug/deps/it-[..][EXE]) (passed previously)
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn different_env_is_not_cached() {
+    let p = project()
+        .file("src/lib.rs", "#[test] fn unit() {}")
+        .file("tests/it.rs", "#[test] fn it() {}")
+        .build();
+
+    p.cargo("test -Z test-cache --test it")
+        .masquerade_as_nightly_cargo()
+        .env("TEST_CACHE_VAR", "1")
+        .run();
+
+    // Variables inherited from Cargo's environment are part of the key.
+    p.cargo("test -Z test-cache --test it")
+        .masquerade_as_nightly_cargo()
+        .env("TEST_CACHE_VAR", "2")
+        .with_stderr(
+            "\
+[FINISHED] [..]
+[RUNNING] tests/it.rs (target/debug/deps/it-[..][EXE])
+",
+        )
+        .run();
+
+    p.cargo("test -Z test-cache --test it")
+        .masquerade_as_nightly_cargo()
+        .env("TEST_CACHE_VAR", "2")
+        .with_stderr(
+            "\
+[FINISHED] [..]
+[CACHED] tests/it.rs (target/debug/deps/it-[..][EXE]) (passed previously)
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn failures_are_not_cached() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file("tests/it.rs", "#[test] fn it() { panic!() }")
+        .build();
+
+    p.cargo("test -Z test-cache --test it")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[RUNNING] tests/it.rs (target/debug/deps/it-[..][EXE])")
+        .run();
+
+    p.cargo("test -Z test-cache --test it")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[RUNNING] tests/it.rs (target/debug/deps/it-[..][EXE])")
+        .run();
+}
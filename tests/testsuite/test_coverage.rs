@@ -0,0 +1,253 @@
+//! Tests for `cargo test --coverage`.
+
+use cargo_test_support::registry::Package;
+use cargo_test_support::{basic_manifest, is_nightly, paths, project};
+use std::path::PathBuf;
+
+/// Builds an executable standing in for both `llvm-profdata` and `llvm-cov`.
+///
+/// It records its command line in the file named by `FAKE_LLVM_LOG`, and
+/// writes the outputs the real tools would.
+fn fake_llvm_tool() -> PathBuf {
+    let p = project()
+        .at(paths::global_root().join("fake-llvm-tool"))
+        .file("Cargo.toml", &basic_manifest("fake-llvm-tool", "1.0.0"))
+        .file(
+            "src/main.rs",
+            r#"
+                use std::io::Write;
+
+                fn main() {
+                    let args: Vec<String> = std::env::args().skip(1).collect();
+                    let log = std::env::var("FAKE_LLVM_LOG").unwrap();
+                    let mut log = std::fs::OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(log)
+                        .unwrap();
+                    writeln!(log, "{}", args.join(" ")).unwrap();
+                    match args.iter().position(|a| a == "merge" || a == "export" || a == "show") {
+                        Some(i) if args[i] == "merge" => {
+                            let out = args.iter().position(|a| a == "-o").unwrap();
+                            std::fs::write(&args[out + 1], "profdata").unwrap();
+                        }
+                        Some(i) if args[i] == "export" => println!("SF:src/lib.rs"),
+                        _ => {
+                            let dir = args
+                                .iter()
+                                .find_map(|a| a.strip_prefix("-output-dir="))
+                                .unwrap();
+                            std::fs::create_dir_all(dir).unwrap();
+                            std::fs::write(format!("{}/index.html", dir), "html").unwrap();
+                        }
+                    }
+                }
+            "#,
+        )
+        .build();
+    p.cargo("build").run();
+    p.bin("fake-llvm-tool")
+}
+
+#[cargo_test]
+fn coverage_requires_flag() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("test --coverage")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] Usage of `--coverage` requires `-Z test-coverage`")
+        .run();
+}
+
+#[cargo_test]
+fn coverage_rejects_doc_and_affected() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("test -Z test-coverage --coverage --doc")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] Can't collect coverage of doc tests with --coverage")
+        .run();
+
+    p.cargo("test -Z test-coverage -Z test-affected --coverage --affected")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] Can't mix --coverage with --affected")
+        .run();
+}
+
+#[cargo_test]
+fn only_local_code_is_instrumented() {
+    Package::new("bar", "1.0.0")
+        .file("src/lib.rs", "pub fn id(x: u32) -> u32 { x }")
+        .publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                build = "build.rs"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("build.rs", "fn main() {}")
+        .file(
+            "src/lib.rs",
+            r#"
+                pub fn add(a: u32, b: u32) -> u32 { bar::id(a) + b }
+
+                #[test]
+                fn unit() { assert_eq!(add(1, 2), 3); }
+            "#,
+        )
+        .file(
+            "tests/it.rs",
+            r#"#[test] fn it() { assert_eq!(foo::add(2, 2), 4); }"#,
+        )
+        .build();
+
+    p.cargo("test -Z test-coverage --coverage --no-run -v")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "[RUNNING] `rustc --crate-name foo [..]-C instrument-coverage --test [..]`",
+        )
+        .with_stderr_contains(
+            "[RUNNING] `rustc --crate-name it [..]-C instrument-coverage --test [..]`",
+        )
+        .with_stderr_line_without(
+            &["[RUNNING] `rustc --crate-name bar "],
+            &["instrument-coverage"],
+        )
+        .with_stderr_line_without(
+            &["[RUNNING] `rustc --crate-name build_script_build "],
+            &["instrument-coverage"],
+        )
+        .run();
+}
+
+#[cargo_test]
+fn reports_are_written() {
+    Package::new("bar", "1.0.0")
+        .file("src/lib.rs", "pub fn id(x: u32) -> u32 { x }")
+        .publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                build = "build.rs"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("build.rs", "fn main() {}")
+        .file(
+            "src/lib.rs",
+            r#"
+                pub fn add(a: u32, b: u32) -> u32 { bar::id(a) + b }
+
+                #[test]
+                fn unit() { assert_eq!(add(1, 2), 3); }
+            "#,
+        )
+        .file(
+            "tests/it.rs",
+            r#"#[test] fn it() { assert_eq!(foo::add(2, 2), 4); }"#,
+        )
+        .build();
+    let tool = fake_llvm_tool();
+    let log = paths::root().join("llvm.log");
+
+    p.cargo("test -Z test-coverage --coverage")
+        .masquerade_as_nightly_cargo()
+        .env("LLVM_PROFDATA", &tool)
+        .env("LLVM_COV", &tool)
+        .env("FAKE_LLVM_LOG", &log)
+        .with_stderr_contains(
+            "[COVERAGE] report written to `target/coverage/lcov.info` and \
+             `target/coverage/html/index.html`",
+        )
+        .run();
+
+    let coverage = p.root().join("target/coverage");
+    let profraws = std::fs::read_dir(coverage.join("profraw"))
+        .unwrap()
+        .filter(|e| e.as_ref().unwrap().path().extension().unwrap() == "profraw")
+        .count();
+    assert_eq!(profraws, 2);
+    assert_eq!(
+        std::fs::read_to_string(coverage.join("lcov.info")).unwrap(),
+        "SF:src/lib.rs\n"
+    );
+    assert!(coverage.join("html/index.html").exists());
+
+    let log = std::fs::read_to_string(&log).unwrap();
+    let lines: Vec<_> = log.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("merge -sparse "));
+    assert!(lines[0].ends_with("coverage.profdata"));
+    assert!(lines[1].contains("-object"));
+    assert!(lines[1].contains("-ignore-filename-regex=^/rustc/|"));
+    assert!(lines[1].ends_with("export -format=lcov"));
+    assert!(lines[2].contains("show -format=html -output-dir="));
+}
+
+#[cargo_test]
+fn missing_tool() {
+    let p = project().file("src/lib.rs", "#[test] fn t() {}").build();
+
+    p.cargo("test -Z test-coverage --coverage")
+        .masquerade_as_nightly_cargo()
+        .env_remove("LLVM_PROFDATA")
+        .with_status(101)
+        .with_stderr_contains("[ERROR] could not find `llvm-profdata`[..]")
+        .run();
+}
+
+#[cargo_test]
+fn doc_tests() {
+    let p = project()
+        .file(
+            "src/lib.rs",
+            r#"
+                /// ```
+                /// assert_eq!(foo::add(1, 2), 3);
+                /// ```
+                pub fn add(a: u32, b: u32) -> u32 { a + b }
+            "#,
+        )
+        .build();
+    let tool = fake_llvm_tool();
+    let log = paths::root().join("llvm.log");
+
+    let mut execs = p.cargo("test -Z test-coverage --coverage");
+    execs
+        .masquerade_as_nightly_cargo()
+        .env("LLVM_PROFDATA", &tool)
+        .env("LLVM_COV", &tool)
+        .env("FAKE_LLVM_LOG", &log)
+        .with_stderr_contains("[COVERAGE] report written to [..]");
+    // Instrumenting doc tests needs unstable rustdoc flags.
+    if is_nightly() {
+        execs
+            .with_stderr_does_not_contain("[NOTE] doc tests are not instrumented[..]")
+            .run();
+        let log = std::fs::read_to_string(&log).unwrap();
+        assert!(log.contains("target/coverage/doctests/foo/"));
+    } else {
+        execs
+            .with_stderr_contains(
+                "[NOTE] doc tests are not instrumented for coverage without a nightly \
+                 toolchain, so they don't count towards the coverage",
+            )
+            .run();
+    }
+}
@@ -0,0 +1,240 @@
+//! Tests for `cargo test --no-capture-streaming`.
+
+use cargo_test_support::project;
+
+#[cargo_test]
+fn logs_require_flag() {
+    let p = project()
+        .file(
+            "src/lib.rs",
+            r#"#[test] fn unit() { println!("hello from unit"); }"#,
+        )
+        .file(
+            "tests/it.rs",
+            r#"#[test] fn it() { eprintln!("hello from it"); }"#,
+        )
+        .build();
+
+    p.cargo("test --no-capture-streaming")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] Usage of `--no-capture-streaming` requires `-Z test-logs`")
+        .run();
+}
+
+#[cargo_test]
+fn output_is_routed_to_logs() {
+    let p = project()
+        .file(
+            "src/lib.rs",
+            r#"#[test] fn unit() { println!("hello from unit"); }"#,
+        )
+        .file(
+            "tests/it.rs",
+            r#"#[test] fn it() { eprintln!("hello from it"); }"#,
+        )
+        .build();
+
+    p.cargo("test -Z test-logs --no-capture-streaming --tests -- --nocapture")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("")
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+[RUNNING] unittests (target/debug/deps/foo-[..][EXE])
+[RUNNING] tests/it.rs (target/debug/deps/it-[..][EXE])
+",
+        )
+        .run();
+
+    let logs = p.build_dir().join("test-logs");
+    let mut names: Vec<_> = std::fs::read_dir(&logs)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+    assert_eq!(names.len(), 3);
+    assert!(names[0].starts_with("foo-") && names[0].ends_with(".log"));
+    assert_eq!(names[1], "index.json");
+    assert!(names[2].starts_with("it-") && names[2].ends_with(".log"));
+
+    let unit_log = std::fs::read_to_string(logs.join(&names[0])).unwrap();
+    assert!(unit_log.contains("hello from unit"));
+    assert!(unit_log.contains("test result: ok. 1 passed"));
+    let it_log = std::fs::read_to_string(logs.join(&names[2])).unwrap();
+    assert!(it_log.contains("hello from it"));
+
+    let index: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(logs.join("index.json")).unwrap()).unwrap();
+    let tests = index["tests"].as_array().unwrap();
+    assert_eq!(tests.len(), 2);
+    assert_eq!(tests[0]["target"], "foo");
+    assert_eq!(tests[0]["kind"], "lib");
+    assert_eq!(tests[0]["success"], true);
+    assert_eq!(tests[0]["attempts"], 1);
+    assert_eq!(tests[1]["target"], "it");
+    assert_eq!(tests[1]["kind"], "integration-test");
+    assert!(tests[1]["log"].as_str().unwrap().ends_with(&names[2]));
+}
+
+#[cargo_test]
+fn failures_are_streamed() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            "tests/it.rs",
+            r#"
+                #[test] fn good() { println!("quiet"); }
+                #[test] fn bad() { panic!("oh no"); }
+            "#,
+        )
+        .build();
+
+    p.cargo("test -Z test-logs --no-capture-streaming --test it")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stdout_contains("test bad ... FAILED")
+        .with_stdout_contains("thread 'bad' [..]panicked at [..]")
+        .with_stdout_contains("test result: FAILED. 1 passed; 1 failed[..]")
+        .with_stdout_does_not_contain("test good ... ok")
+        .with_stderr_contains(
+            "[NOTE] the output of tests/it.rs (target/debug/deps/it-[..][EXE]) \
+             is in `[..]/target/test-logs/it-[..].log`",
+        )
+        .run();
+
+    let index = std::fs::read_to_string(p.build_dir().join("test-logs/index.json")).unwrap();
+    let index: serde_json::Value = serde_json::from_str(&index).unwrap();
+    assert_eq!(index["tests"][0]["success"], false);
+}
+
+#[cargo_test]
+fn non_utf8_output_is_drained() {
+    let p = project()
+        .file(
+            "src/lib.rs",
+            r#"
+                #[test]
+                fn bytes() {
+                    use std::io::Write;
+                    let mut out = std::io::stdout();
+                    out.write_all(b"bad \xff line\n").unwrap();
+                    for i in 0..20000 {
+                        writeln!(out, "after {}", i).unwrap();
+                    }
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("test -Z test-logs --no-capture-streaming --lib -- --nocapture")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    let logs = p.build_dir().join("test-logs");
+    let log = std::fs::read_dir(&logs)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .find(|path| path.extension().unwrap() == "log")
+        .unwrap();
+    let log = std::fs::read_to_string(log).unwrap();
+    assert!(log.contains("bad \u{FFFD} line\n"));
+    assert!(log.contains("after 19999\n"));
+}
+
+// The test spawns a `sleep` which inherits the pipes of the test binary.
+#[cfg(unix)]
+#[cargo_test]
+fn pipes_held_by_a_grandchild() {
+    let p = project()
+        .file(
+            "src/lib.rs",
+            r#"
+                #[test]
+                fn spawn() {
+                    std::process::Command::new("sleep").arg("60").spawn().unwrap();
+                    println!("spawned");
+                }
+            "#,
+        )
+        .build();
+
+    let start = std::time::Instant::now();
+    p.cargo("test -Z test-logs --no-capture-streaming --lib -- --nocapture")
+        .masquerade_as_nightly_cargo()
+        .run();
+    assert!(start.elapsed() < std::time::Duration::from_secs(30));
+}
+
+#[cargo_test]
+fn test_binaries_run_concurrently() {
+    // Each test waits for the other one to start.
+    let rendezvous = |me: &str, other: &str| {
+        format!(
+            r#"
+                #[test]
+                fn wait() {{
+                    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
+                    std::fs::write(dir.join("{}-started"), "").unwrap();
+                    let start = std::time::Instant::now();
+                    while !dir.join("{}-started").exists() {{
+                        assert!(start.elapsed().as_secs() < 60, "not run concurrently");
+                        std::thread::sleep(std::time::Duration::from_millis(10));
+                    }}
+                }}
+            "#,
+            me, other
+        )
+    };
+    let p = project()
+        .file("src/lib.rs", "")
+        .file("tests/a.rs", &rendezvous("a", "b"))
+        .file("tests/b.rs", &rendezvous("b", "a"))
+        .build();
+
+    p.cargo("test -Z test-logs --no-capture-streaming -j 2 --tests")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[RUNNING] tests/a.rs (target/debug/deps/a-[..][EXE])")
+        .with_stderr_contains("[RUNNING] tests/b.rs (target/debug/deps/b-[..][EXE])")
+        .run();
+}
+
+#[cargo_test]
+fn doc_tests_are_routed_to_logs() {
+    let p = project()
+        .file(
+            "src/lib.rs",
+            r#"
+                /// ```
+                /// assert_eq!(foo::answer(), 42);
+                /// ```
+                pub fn answer() -> u32 { 42 }
+
+                /// ```
+                /// assert_eq!(foo::answer(), 0);
+                /// ```
+                pub fn wrong() {}
+            "#,
+        )
+        .build();
+
+    p.cargo("test -Z test-logs --no-capture-streaming --doc")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stdout_contains("test result: FAILED. 1 passed; 1 failed[..]")
+        .with_stdout_does_not_contain("[..]answer[..] ... ok")
+        .with_stderr_contains("[DOCTEST] foo")
+        .with_stderr_contains(
+            "[NOTE] the output of doctests (foo) is in `[..]/target/test-logs/foo-doctests.log`",
+        )
+        .run();
+
+    let logs = p.build_dir().join("test-logs");
+    let log = std::fs::read_to_string(logs.join("foo-doctests.log")).unwrap();
+    assert!(log.contains("src/lib.rs - answer (line 2) ... ok"));
+    let index = std::fs::read_to_string(logs.join("index.json")).unwrap();
+    let index: serde_json::Value = serde_json::from_str(&index).unwrap();
+    assert_eq!(index["tests"][0]["kind"], "doc-test");
+    assert_eq!(index["tests"][0]["success"], false);
+}
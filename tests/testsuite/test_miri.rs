@@ -0,0 +1,233 @@
+//! Tests for `cargo test --miri`.
+
+use cargo_test_support::registry::Package;
+use cargo_test_support::{basic_manifest, paths, project, rustc_host, Execs, Project};
+use std::path::PathBuf;
+
+/// Builds an executable standing in for `miri`.
+///
+/// When acting as rustc it runs rustc, otherwise it prints what it was asked
+/// to interpret, and fails if `FAKE_MIRI_UB` is set.
+fn fake_miri() -> PathBuf {
+    let p = project()
+        .at(paths::global_root().join("fake-miri"))
+        .file("Cargo.toml", &basic_manifest("fake-miri", "1.0.0"))
+        .file(
+            "src/main.rs",
+            r#"
+                use std::process::{exit, Command};
+
+                fn main() {
+                    let args: Vec<String> = std::env::args().skip(1).collect();
+                    if std::env::var("MIRI_BE_RUSTC").as_deref() == Ok("target") {
+                        let status = Command::new("rustc").args(&args).status().unwrap();
+                        exit(status.code().unwrap_or(1));
+                    }
+                    let name = args.iter().position(|a| a == "--crate-name").unwrap() + 1;
+                    let sep = args.iter().position(|a| a == "--").unwrap();
+                    println!("interpreting {} with {:?}", args[name], &args[sep + 1..]);
+                    if std::env::var_os("FAKE_MIRI_UB").is_some() {
+                        println!("error: Undefined Behavior: fake");
+                        exit(1);
+                    }
+                }
+            "#,
+        )
+        .build();
+    p.cargo("build").run();
+    p.bin("fake-miri")
+}
+
+fn sysroot() -> String {
+    let output = std::process::Command::new("rustc")
+        .arg("--print=sysroot")
+        .output()
+        .unwrap();
+    String::from_utf8(output.stdout).unwrap().trim().to_string()
+}
+
+fn cargo_miri(p: &Project, args: &str) -> Execs {
+    let mut execs = p.cargo(args);
+    execs
+        .masquerade_as_nightly_cargo()
+        .env("MIRI", fake_miri())
+        .env("MIRI_SYSROOT", sysroot());
+    execs
+}
+
+#[cargo_test]
+fn miri_requires_z_flag() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("test --miri")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] Usage of `--miri` requires `-Z test-miri`")
+        .run();
+}
+
+#[cargo_test]
+fn miri_doc_tests() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("test -Ztest-miri --miri --doc")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] Can't run doc tests with --miri")
+        .run();
+}
+
+#[cargo_test]
+fn miri_not_found() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("test -Ztest-miri --miri")
+        .masquerade_as_nightly_cargo()
+        .env_remove("MIRI")
+        .env("MIRI_SYSROOT", sysroot())
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] `cargo test --miri` requires `miri`, which was not found in `[..]`
+Install it with `rustup component add miri`, or set the `MIRI` environment variable to its path.
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn test_in_miri() {
+    Package::new("bar", "1.0.0")
+        .file("src/lib.rs", "pub fn id(x: u32) -> u32 { x }")
+        .publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                build = "build.rs"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("build.rs", "fn main() {}")
+        .file(
+            "src/lib.rs",
+            r#"
+                /// ```
+                /// assert_eq!(foo::add(1, 1), 2);
+                /// ```
+                pub fn add(a: u32, b: u32) -> u32 { bar::id(a) + b }
+
+                #[test]
+                fn unit() { assert_eq!(add(1, 2), 3); }
+            "#,
+        )
+        .file(
+            "tests/it.rs",
+            r#"#[test] fn it() { assert_eq!(foo::add(2, 2), 4); }"#,
+        )
+        .build();
+
+    cargo_miri(&p, "test -v -Ztest-miri --miri -- --nocapture")
+        .with_stderr_contains("[RUNNING] `rustc --crate-name build_script_build build.rs [..]`")
+        .with_stderr_line_without(
+            &["[RUNNING] `rustc --crate-name build_script_build"],
+            &["--cfg miri"],
+        )
+        .with_stderr_contains(
+            "[RUNNING] `[..]fake-miri[EXE] --crate-name bar [..]--cfg miri --sysroot [..]`",
+        )
+        .with_stderr_contains(
+            "[RUNNING] `[..]fake-miri[EXE] --crate-name foo src/lib.rs [..]--test [..]`",
+        )
+        .with_stderr_contains(
+            "[RUNNING] `[..]fake-miri[EXE] --crate-name foo src/lib.rs [..]-- --nocapture`",
+        )
+        .with_stderr_contains(
+            "[RUNNING] `[..]fake-miri[EXE] --crate-name it tests/it.rs [..]-- --nocapture`",
+        )
+        .with_stderr_contains("[NOTE] doc tests are not run in Miri, skipping them")
+        .with_stdout_contains(r#"interpreting foo with ["--nocapture"]"#)
+        .with_stdout_contains(r#"interpreting it with ["--nocapture"]"#)
+        .run();
+
+    // The artifacts of rustc and Miri don't mix.
+    let target = rustc_host();
+    cargo_miri(&p, &format!("test --target {} --lib", target))
+        .with_stderr(&format!(
+            "\
+[COMPILING] bar v1.0.0
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] test [unoptimized + debuginfo] target(s) in [..]
+[RUNNING] [..] (target/{}/debug/deps/foo-[..][EXE])
+",
+            target
+        ))
+        .with_stdout_contains("test unit ... ok")
+        .run();
+}
+
+#[cargo_test]
+fn undefined_behavior_fails_the_test() {
+    Package::new("bar", "1.0.0")
+        .file("src/lib.rs", "pub fn id(x: u32) -> u32 { x }")
+        .publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                build = "build.rs"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("build.rs", "fn main() {}")
+        .file(
+            "src/lib.rs",
+            r#"
+                /// ```
+                /// assert_eq!(foo::add(1, 1), 2);
+                /// ```
+                pub fn add(a: u32, b: u32) -> u32 { bar::id(a) + b }
+
+                #[test]
+                fn unit() { assert_eq!(add(1, 2), 3); }
+            "#,
+        )
+        .file(
+            "tests/it.rs",
+            r#"#[test] fn it() { assert_eq!(foo::add(2, 2), 4); }"#,
+        )
+        .build();
+
+    cargo_miri(&p, "test -Ztest-miri --miri --lib")
+        .env("FAKE_MIRI_UB", "1")
+        .with_status(1)
+        .with_stdout_contains("error: Undefined Behavior: fake")
+        .with_stderr_contains("[ERROR] test failed, to rerun pass '--lib'")
+        .run();
+}
+
+#[cargo_test]
+fn miri_builds_std_without_sysroot() {
+    let p = project().file("src/lib.rs", "").build();
+
+    // Without `MIRI_SYSROOT`, the standard library is built with Miri, but
+    // its sources are not available in the test environment.
+    p.cargo("test -Ztest-miri --miri")
+        .masquerade_as_nightly_cargo()
+        .env("MIRI", fake_miri())
+        .env_remove("MIRI_SYSROOT")
+        .env("__CARGO_TESTS_ONLY_SRC_ROOT", p.root().join("no-std-here"))
+        .with_status(101)
+        .with_stderr_contains("[..]no-std-here[..]")
+        .run();
+}
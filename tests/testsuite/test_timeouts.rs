@@ -0,0 +1,255 @@
+//! Tests for the `timeout` and `retries` profile settings.
+
+use cargo_test_support::project;
+
+#[cargo_test]
+fn timeouts_require_feature() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [profile.test]
+                timeout = "300s"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("test")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  feature `test-timeouts` is required
+
+  The package requires the Cargo feature called `test-timeouts`, \
+  but that feature is not stabilized in this version of Cargo (1.[..]).
+  Consider adding `cargo-features = [\"test-timeouts\"]` to the top of Cargo.toml \
+  (above the [package] table) to tell Cargo you are opting in to use this unstable feature.
+  See https://doc.rust-lang.org/nightly/cargo/reference/unstable.html#test-timeouts \
+  for more information about the status of this feature.
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn hung_test_is_killed() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["test-timeouts"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [profile.test]
+                timeout = "1s"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            "tests/hang.rs",
+            r#"
+                #[test]
+                fn hang() {
+                    std::thread::sleep(std::time::Duration::from_secs(600));
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("test --test hang")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[COMPILING] foo v0.1.0 ([CWD])
+[FINISHED] [..]
+[RUNNING] tests/hang.rs (target/debug/deps/hang-[..][EXE])
+[ERROR] test failed, to rerun pass '--test hang'
+
+Caused by:
+  process didn't exit successfully: `[..]hang-[..]` (timed out after 1s)
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn flaky_test_is_retried() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["test-timeouts"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [profile.test]
+                retries = 2
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            "tests/flaky.rs",
+            r#"
+                #[test]
+                fn flaky() {
+                    // Fails the first time it runs.
+                    let marker = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("ran");
+                    if !marker.exists() {
+                        std::fs::write(&marker, "").unwrap();
+                        panic!("first run");
+                    }
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("test --test flaky")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.1.0 ([CWD])
+[FINISHED] [..]
+[RUNNING] tests/flaky.rs (target/debug/deps/flaky-[..][EXE])
+[RETRYING] tests/flaky.rs (target/debug/deps/flaky-[..][EXE]) (attempt 2 of 3)
+[RETRIED] tests/flaky.rs (target/debug/deps/flaky-[..][EXE]), passed on attempt 2
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn retries_exhausted() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["test-timeouts"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [profile.test]
+                retries = 1
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("tests/broken.rs", "#[test] fn broken() { panic!() }")
+        .build();
+
+    p.cargo("test --test broken")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[COMPILING] foo v0.1.0 ([CWD])
+[FINISHED] [..]
+[RUNNING] tests/broken.rs (target/debug/deps/broken-[..][EXE])
+[RETRYING] tests/broken.rs (target/debug/deps/broken-[..][EXE]) (attempt 2 of 2)
+[RETRIED] tests/broken.rs (target/debug/deps/broken-[..][EXE]), failed all 2 attempts
+[ERROR] test failed, to rerun pass '--test broken'
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn invalid_timeout() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["test-timeouts"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [profile.test]
+                timeout = "5 minutes"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("test")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  invalid duration `5 minutes`, expected a number followed by `ms`, `s`, `m` or `h` \
+  for key `profile.test.timeout`
+",
+        )
+        .run();
+
+    // Durations that don't fit are errors rather than overflows.
+    p.change_file(
+        "Cargo.toml",
+        &p.read_file("Cargo.toml")
+            .replace("5 minutes", "18446744073709551615h"),
+    );
+    p.cargo("test")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  invalid duration `18446744073709551615h`, it is too long for key `profile.test.timeout`
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn timeout_not_allowed_in_package_profile() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["test-timeouts"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [profile.test.package."*"]
+                timeout = "5m"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("test")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  `timeout` may not be specified in a `package` profile
+",
+        )
+        .run();
+}
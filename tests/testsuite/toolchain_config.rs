@@ -0,0 +1,211 @@
+//! Tests for the `[toolchain]` config table.
+
+use cargo_test_support::{basic_manifest, paths, project, Project};
+use std::fs;
+use std::path::Path;
+
+/// Builds an executable which is both a toolchain provider and the `rustc`
+/// of the toolchains it installs.
+///
+/// With `install <dest>` it copies itself to `<dest>/bin/rustc`, otherwise it
+/// prints `TOOLCHAIN RUSTC` to stderr and runs `rustc` from `PATH`.
+fn fake_toolchain() -> Project {
+    let p = project()
+        .at("fake-toolchain")
+        .file("Cargo.toml", &basic_manifest("fake-toolchain", "1.0.0"))
+        .file(
+            "src/main.rs",
+            r#"
+                use std::path::Path;
+                use std::process::{exit, Command};
+
+                fn main() {
+                    let args: Vec<String> = std::env::args().collect();
+                    if args.get(1).map(String::as_str) == Some("install") {
+                        if std::env::var("CARGO_TOOLCHAIN_VERSION").unwrap() == "broken" {
+                            eprintln!("no such toolchain");
+                            exit(1);
+                        }
+                        let bin = Path::new(&args[2]).join("bin");
+                        std::fs::create_dir_all(&bin).unwrap();
+                        let rustc = bin.join("rustc").with_extension(std::env::consts::EXE_EXTENSION);
+                        std::fs::copy(&args[0], rustc).unwrap();
+                        return;
+                    }
+                    eprintln!("TOOLCHAIN RUSTC");
+                    let status = Command::new("rustc").args(&args[1..]).status().unwrap();
+                    exit(status.code().unwrap_or(1));
+                }
+            "#,
+        )
+        .build();
+    p.cargo("build").run();
+    p
+}
+
+fn toml_path(path: &Path) -> String {
+    path.display().to_string().replace('\\', "\\\\")
+}
+
+#[cargo_test]
+fn path_toolchain() {
+    let toolchain = fake_toolchain();
+    let dir = paths::root().join("toolchain");
+    fs::create_dir_all(dir.join("bin")).unwrap();
+    fs::copy(
+        toolchain.bin("fake-toolchain"),
+        dir.join("bin")
+            .join("rustc")
+            .with_extension(std::env::consts::EXE_EXTENSION),
+    )
+    .unwrap();
+
+    let p = project()
+        .file(
+            ".cargo/config.toml",
+            &format!(
+                r#"
+                    [toolchain]
+                    path = "{}"
+                "#,
+                toml_path(&dir)
+            ),
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check -Z toolchain-config")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("TOOLCHAIN RUSTC")
+        .run();
+
+    // `build.rustc` takes precedence over the toolchain.
+    p.change_file("src/lib.rs", "pub fn f() {}");
+    p.cargo("check -Z toolchain-config")
+        .masquerade_as_nightly_cargo()
+        .env("CARGO_BUILD_RUSTC", "rustc")
+        .with_stderr_does_not_contain("TOOLCHAIN RUSTC")
+        .run();
+
+    // Without the flag the table is ignored.
+    p.change_file("src/lib.rs", "");
+    p.cargo("check")
+        .with_stderr_does_not_contain("TOOLCHAIN RUSTC")
+        .run();
+}
+
+#[cargo_test]
+fn path_without_rustc() {
+    let p = project()
+        .file(
+            ".cargo/config.toml",
+            r#"
+                [toolchain]
+                path = "missing"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check -Z toolchain-config")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] the toolchain in `[..]missing` (from `toolchain.path` in [..]config.toml) \
+does not contain `bin/rustc`
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn provider_installs_version() {
+    let toolchain = fake_toolchain();
+    let p = project()
+        .file(
+            ".cargo/config.toml",
+            &format!(
+                r#"
+                    [toolchain]
+                    version = "1.0.0-test"
+                    provider = ["{}", "install", "{{dest}}"]
+                "#,
+                toml_path(&toolchain.bin("fake-toolchain"))
+            ),
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check -Z toolchain-config")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[INSTALLING] toolchain `1.0.0-test`")
+        .with_stderr_contains("TOOLCHAIN RUSTC")
+        .run();
+    assert!(paths::home()
+        .join(".cargo/toolchains/1.0.0-test/bin")
+        .is_dir());
+
+    // The installed toolchain is reused, also offline.
+    p.cargo("build --offline -Z toolchain-config")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_does_not_contain("[INSTALLING] [..]")
+        .with_stderr_contains("TOOLCHAIN RUSTC")
+        .run();
+}
+
+#[cargo_test]
+fn provider_errors() {
+    let toolchain = fake_toolchain();
+    let p = project()
+        .file(
+            ".cargo/config.toml",
+            r#"
+                [toolchain]
+                version = "1.0.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check -Z toolchain-config")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] `toolchain.version` requires a `toolchain.provider` to install toolchain `1.0.0`",
+        )
+        .run();
+
+    p.change_file(
+        ".cargo/config.toml",
+        &format!(
+            r#"
+                [toolchain]
+                version = "broken"
+                provider = ["{}", "install", "{{dest}}"]
+            "#,
+            toml_path(&toolchain.bin("fake-toolchain"))
+        ),
+    );
+
+    p.cargo("check -Z toolchain-config")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[INSTALLING] toolchain `broken`")
+        .with_stderr_contains(
+            "[ERROR] toolchain provider `[..]fake-toolchain[..]` failed to install toolchain `broken` (exit [..])",
+        )
+        .with_stderr_contains("no such toolchain")
+        .run();
+
+    p.cargo("check --offline -Z toolchain-config")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] toolchain `broken` is not installed, and can't be installed \
+when the network is disabled (--offline or --frozen)
+",
+        )
+        .run();
+}
@@ -0,0 +1,313 @@
+//! Tests for `-Z toolchain-file`, which checks the toolchain against
+//! `rust-toolchain.toml`.
+
+use cargo_test_support::{basic_manifest, project, rustc_host, Project};
+
+/// Builds an executable which is both a toolchain provider and a `rustc`.
+///
+/// As a provider, it is called with `<action> <dest> <name>`. As a `rustc`,
+/// it runs `rustc` from `PATH`, and reports the toolchain directory it is in
+/// as its sysroot, so that targets and components can be added to it.
+fn fake_toolchain() -> Project {
+    let p = project()
+        .at("fake-toolchain")
+        .file("Cargo.toml", &basic_manifest("fake-toolchain", "1.0.0"))
+        .file(
+            "src/main.rs",
+            r#"
+                use std::fs;
+                use std::path::Path;
+                use std::process::{exit, Command, Stdio};
+
+                fn main() {
+                    let args: Vec<String> = std::env::args().collect();
+                    let exe = std::env::consts::EXE_EXTENSION;
+                    match args.get(1).map(String::as_str) {
+                        Some("install") => {
+                            let bin = Path::new(&args[2]).join("bin");
+                            fs::create_dir_all(&bin).unwrap();
+                            fs::copy(&args[0], bin.join("rustc").with_extension(exe)).unwrap();
+                            return;
+                        }
+                        Some("add-target") => {
+                            let lib = Path::new(&args[2]).join("lib/rustlib").join(&args[3]).join("lib");
+                            fs::create_dir_all(lib).unwrap();
+                            return;
+                        }
+                        Some("add-component") => {
+                            assert_eq!(args[3], "rustfmt");
+                            let rustfmt = Path::new(&args[2]).join("bin/rustfmt").with_extension(exe);
+                            fs::write(rustfmt, "").unwrap();
+                            return;
+                        }
+                        _ => {}
+                    }
+                    let sysroot = Command::new("rustc").args(&["--print", "sysroot"]).output().unwrap();
+                    let sysroot = String::from_utf8(sysroot.stdout).unwrap();
+                    let own = std::env::current_exe().unwrap();
+                    let own = own.parent().unwrap().parent().unwrap();
+                    let output = Command::new("rustc")
+                        .args(&args[1..])
+                        .stderr(Stdio::inherit())
+                        .output()
+                        .unwrap();
+                    let stdout = String::from_utf8(output.stdout).unwrap();
+                    print!("{}", stdout.replace(sysroot.trim(), own.to_str().unwrap()));
+                    exit(output.status.code().unwrap_or(1));
+                }
+            "#,
+        )
+        .build();
+    p.cargo("build").run();
+    p
+}
+
+fn toml_bin(p: &Project, name: &str) -> String {
+    p.bin(name).display().to_string().replace('\\', "\\\\")
+}
+
+#[cargo_test]
+fn mismatch_warning() {
+    let p = project()
+        .file(
+            "rust-toolchain.toml",
+            r#"
+                [toolchain]
+                channel = "1.0.0"
+                components = ["miri"]
+                targets = ["thumbv7m-none-eabi"]
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    // Without the flag the file is left to rustup.
+    p.cargo("check")
+        .with_stderr_does_not_contain("[..]rust-toolchain.toml[..]")
+        .run();
+
+    p.cargo("check -Z toolchain-file")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[WARNING] the toolchain does not match `[..]rust-toolchain.toml`:
+  - channel `1.0.0` is required, but rustc is [..]
+  - component `miri` is not installed
+  - target `thumbv7m-none-eabi` is not installed
+rustc `rustc` has its sysroot in `[..]`
+
+Set `toolchain.auto-install` and `toolchain.provider` in the Cargo config \
+to install the missing targets and components automatically.
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn missing_target_is_error() {
+    let p = project()
+        .file(
+            "rust-toolchain.toml",
+            r#"
+                [toolchain]
+                targets = ["thumbv7m-none-eabi"]
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check -Z toolchain-file --target thumbv7m-none-eabi")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] the toolchain does not match `[..]rust-toolchain.toml`:
+  - target `thumbv7m-none-eabi` is not installed
+rustc `rustc` has its sysroot in `[..]`
+
+Set `toolchain.auto-install` and `toolchain.provider` in the Cargo config \
+to install the missing targets and components automatically.
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn legacy_file() {
+    let p = project()
+        .file("rust-toolchain", "1.0\n")
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check -Z toolchain-file")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("  - channel `1.0` is required, but rustc is [..]")
+        .run();
+}
+
+#[cargo_test]
+fn auto_install() {
+    let toolchain = fake_toolchain();
+    let p = project()
+        .file(
+            "rust-toolchain.toml",
+            r#"
+                [toolchain]
+                channel = "1.0.0-test"
+                components = ["rustfmt"]
+                targets = ["thumbv7m-none-eabi"]
+            "#,
+        )
+        .file(
+            ".cargo/config.toml",
+            &format!(
+                r#"
+                    [toolchain]
+                    provider = ["{}", "{{action}}", "{{dest}}", "{{name}}"]
+                    auto-install = true
+                "#,
+                toml_bin(&toolchain, "fake-toolchain")
+            ),
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    // The toolchain is the channel of `rust-toolchain.toml`.
+    p.cargo("check -Z toolchain-config -Z toolchain-file")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[INSTALLING] toolchain `1.0.0-test`
+[INSTALLING] component `rustfmt`
+[INSTALLING] target `thumbv7m-none-eabi`
+[CHECKING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+
+    p.cargo("check -Z toolchain-config -Z toolchain-file")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[FINISHED] [..]")
+        .run();
+}
+
+#[cargo_test]
+fn provider_without_action() {
+    let toolchain = fake_toolchain();
+    let p = project()
+        .file(
+            "rust-toolchain.toml",
+            r#"
+                [toolchain]
+                components = ["miri"]
+            "#,
+        )
+        .file(
+            ".cargo/config.toml",
+            &format!(
+                r#"
+                    [toolchain]
+                    provider = ["{}", "install", "{{dest}}"]
+                    auto-install = true
+                "#,
+                toml_bin(&toolchain, "fake-toolchain")
+            ),
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check -Z toolchain-file")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] toolchain provider `[..]fake-toolchain[..]` cannot be used to add component `miri`, \
+the `toolchain.provider` configuration value must pass the `{action}` argument to support this
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn auto_install_targets_gated() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("check --auto-install-targets")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] Usage of `--auto-install-targets` requires `-Z auto-install-targets`")
+        .run();
+}
+
+#[cargo_test]
+fn missing_requested_target() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("check -Z auto-install-targets --target thumbv7m-none-eabi")
+        .masquerade_as_nightly_cargo()
+        .env_remove("RUSTUP_TOOLCHAIN")
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] the standard library for target `thumbv7m-none-eabi` is not installed
+
+rustc `rustc` looks for it in `[..]thumbv7m-none-eabi[..]lib`.
+Configure `toolchain.provider` and pass `--auto-install-targets` to install it.
+To build the standard library from source instead, pass `-Z build-std`.
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn auto_install_requested_target() {
+    let toolchain = fake_toolchain();
+    let p = project()
+        .file(
+            ".cargo/config.toml",
+            &format!(
+                r#"
+                    [toolchain]
+                    version = "test"
+                    provider = ["{}", "{{action}}", "{{dest}}", "{{name}}"]
+                "#,
+                toml_bin(&toolchain, "fake-toolchain")
+            ),
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    // The fake toolchain reports its own directory as the sysroot, which
+    // doesn't have the standard library of the host yet.
+    p.cargo("check -Z toolchain-config -Z auto-install-targets --target")
+        .arg(rustc_host())
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(&format!(
+            "\
+[INSTALLING] toolchain `test`
+[ERROR] the standard library for target `{host}` is not installed
+
+rustc `[..]rustc[..]` looks for it in `[..]toolchains[..]test[..]{host}[..]lib`.
+Pass `--auto-install-targets` to install it with `toolchain.provider`.
+To build the standard library from source instead, pass `-Z build-std`.
+",
+            host = rustc_host()
+        ))
+        .run();
+
+    p.cargo("check -Z toolchain-config -Z auto-install-targets --auto-install-targets --target")
+        .arg(rustc_host())
+        .masquerade_as_nightly_cargo()
+        .with_stderr(&format!(
+            "\
+[INSTALLING] target `{}`
+[CHECKING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+            rustc_host()
+        ))
+        .run();
+}
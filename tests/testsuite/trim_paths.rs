@@ -0,0 +1,203 @@
+//! Tests for the `trim-paths` profile setting and `cargo report paths`.
+
+use cargo_test_support::registry::Package;
+use cargo_test_support::{basic_manifest, project};
+
+#[cargo_test]
+fn release_trims_object_paths() {
+    Package::new("bar", "0.0.1")
+        .file("src/lib.rs", "")
+        .publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [dependencies]
+                bar = "0.0.1"
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("build --release -v")
+        .with_stderr_contains(
+            "[RUNNING] `rustc --crate-name bar [..]--remap-path-scope=object \
+             --remap-path-prefix=[CWD]= \
+             --remap-path-prefix=[..]/bar-0.0.1=bar-0.0.1 [..]",
+        )
+        .with_stderr_contains(
+            "[RUNNING] `rustc --crate-name foo [..]--remap-path-scope=object \
+             --remap-path-prefix=[CWD]= [..]",
+        )
+        .with_stderr_line_without(&["[RUNNING] `rustc --crate-name foo"], &["bar-0.0.1="])
+        .run();
+
+    p.cargo("build -v")
+        .with_stderr_does_not_contain("[..]remap-path[..]")
+        .run();
+}
+
+#[cargo_test]
+fn trim_paths_scopes() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [profile.dev]
+                trim-paths = ["macro", "diagnostics"]
+
+                [profile.release]
+                trim-paths = false
+
+                [profile.custom]
+                inherits = "release"
+                trim-paths = true
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build -v")
+        .with_stderr_contains("[RUNNING] `rustc [..]--remap-path-scope=macro,diagnostics [..]")
+        .run();
+    p.cargo("build -v --release")
+        .with_stderr_does_not_contain("[..]remap-path[..]")
+        .run();
+    p.cargo("build -v --profile custom")
+        .with_stderr_contains("[RUNNING] `rustc [..]--remap-path-scope=all [..]")
+        .run();
+}
+
+#[cargo_test]
+fn invalid_trim_paths() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [profile.dev]
+                trim-paths = ["object", "everything"]
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  invalid `profile.dev.trim-paths`
+
+Caused by:
+  unknown `trim-paths` value `everything`, expected one of \
+`none`, `all`, `macro`, `diagnostics` or `object`
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn invalid_trim_paths_in_override() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [profile.dev.package."*"]
+                trim-paths = "everything"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  invalid `trim-paths` in a `package` profile
+
+Caused by:
+  unknown `trim-paths` value `everything`, expected one of \
+`none`, `all`, `macro`, `diagnostics` or `object`
+",
+        )
+        .run();
+
+    p.change_file(
+        "Cargo.toml",
+        r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+
+            [profile.dev.build-override]
+            trim-paths = ["everything"]
+        "#,
+    );
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr_contains("  invalid `trim-paths` in a `build-override` profile")
+        .run();
+}
+
+#[cargo_test]
+fn report_paths() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.0.1"))
+        .file(
+            "src/main.rs",
+            r#"fn main() { println!("{}", env!("CARGO_MANIFEST_DIR")); }"#,
+        )
+        .build();
+
+    p.cargo("report paths")
+        .with_status(101)
+        .with_stderr("[ERROR] `cargo report` can only be used on the nightly channel")
+        .run();
+
+    p.cargo("build --release").run();
+    p.cargo("report paths --release")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stdout_contains("target/release/foo[EXE]:")
+        .with_stdout_contains("    [CWD]")
+        .with_stderr(
+            "[ERROR] found absolute paths in 1 of 1 artifacts, \
+             see the `trim-paths` profile setting to remove them",
+        )
+        .run();
+
+    p.change_file("src/main.rs", r#"fn main() { println!("hello"); }"#);
+    p.cargo("build --release").run();
+    p.cargo("report paths --release")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("")
+        .with_stderr("[CHECKED] 1 artifacts, found no absolute paths")
+        .run();
+
+    p.cargo("report paths")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] no artifacts found in `[CWD]/target/debug`, [..]")
+        .run();
+}
@@ -0,0 +1,209 @@
+//! Tests for `cargo build --lipo`.
+//!
+//! Linking for Apple targets needs their SDK, so the merge is tested with a
+//! `staticlib`, and only when the standard library of both macOS targets is
+//! installed.
+
+use cargo_test_support::{basic_manifest, project, Project};
+use std::path::Path;
+use std::process::Command;
+
+/// A `lipo` which writes the paths of the files it merges.
+fn fake_lipo() -> Project {
+    let p = project()
+        .at("lipo")
+        .file("Cargo.toml", &basic_manifest("lipo", "1.0.0"))
+        .file(
+            "src/main.rs",
+            r#"
+                fn main() {
+                    let args: Vec<String> = std::env::args().skip(1).collect();
+                    assert_eq!(args[0], "-create");
+                    assert_eq!(args[1], "-output");
+                    std::fs::write(&args[2], args[3..].join("\n")).unwrap();
+                }
+            "#,
+        )
+        .build();
+    p.cargo("build").run();
+    p
+}
+
+fn apple_targets_installed() -> bool {
+    let output = Command::new("rustc")
+        .args(&["--print", "sysroot"])
+        .output()
+        .unwrap();
+    let sysroot = String::from_utf8(output.stdout).unwrap();
+    ["aarch64-apple-darwin", "x86_64-apple-darwin"]
+        .iter()
+        .all(|target| {
+            Path::new(sysroot.trim())
+                .join("lib/rustlib")
+                .join(target)
+                .join("lib")
+                .is_dir()
+        })
+}
+
+#[cargo_test]
+fn gated() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [lib]
+                crate-type = ["staticlib"]
+            "#,
+        )
+        .file("src/lib.rs", "#[no_mangle] pub extern \"C\" fn foo() {}")
+        .build();
+    p.cargo("build --lipo")
+        .with_status(101)
+        .with_stderr("[ERROR] Usage of `--lipo` requires `-Z universal-binaries`")
+        .run();
+}
+
+#[cargo_test]
+fn requires_several_targets() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [lib]
+                crate-type = ["staticlib"]
+            "#,
+        )
+        .file("src/lib.rs", "#[no_mangle] pub extern \"C\" fn foo() {}")
+        .build();
+    p.cargo("build -Zuniversal-binaries --lipo --target x86_64-apple-darwin")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] `--lipo` requires at least two `--target` flags")
+        .run();
+}
+
+#[cargo_test]
+fn requires_apple_targets() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [lib]
+                crate-type = ["staticlib"]
+            "#,
+        )
+        .file("src/lib.rs", "#[no_mangle] pub extern \"C\" fn foo() {}")
+        .build();
+    p.cargo(
+        "build -Zmultitarget -Zuniversal-binaries --lipo \
+         --target x86_64-apple-darwin --target x86_64-unknown-linux-gnu",
+    )
+    .masquerade_as_nightly_cargo()
+    .with_status(101)
+    .with_stderr(
+        "[ERROR] `--lipo` only supports Apple targets, \
+         but `x86_64-unknown-linux-gnu` is not one",
+    )
+    .run();
+
+    p.cargo(
+        "build -Zmultitarget -Zuniversal-binaries --lipo \
+         --target x86_64-apple-darwin --target aarch64-apple-ios",
+    )
+    .masquerade_as_nightly_cargo()
+    .with_status(101)
+    .with_stderr(
+        "[ERROR] `--lipo` requires targets of the same platform, \
+         but `aarch64-apple-ios` and `x86_64-apple-darwin` are for different platforms",
+    )
+    .run();
+}
+
+#[cargo_test]
+fn merge_staticlib() {
+    if !apple_targets_installed() {
+        return;
+    }
+    let lipo = fake_lipo();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [lib]
+                crate-type = ["staticlib"]
+            "#,
+        )
+        .file("src/lib.rs", "#[no_mangle] pub extern \"C\" fn foo() {}")
+        .build();
+
+    p.cargo(
+        "build -Zmultitarget -Zunstable-options -Zuniversal-binaries --lipo \
+         --target aarch64-apple-darwin --target x86_64-apple-darwin --out-dir out",
+    )
+    .masquerade_as_nightly_cargo()
+    .env("LIPO", lipo.bin("lipo"))
+    .with_stderr(
+        "\
+[COMPILING] foo v0.1.0 ([..])
+[FINISHED] [..]
+[MERGING] debug/libfoo.a for aarch64-apple-darwin, x86_64-apple-darwin
+",
+    )
+    .run();
+
+    let merged = p.read_file("target/universal-apple-darwin/debug/libfoo.a");
+    let root = p.root();
+    assert_eq!(
+        merged,
+        format!(
+            "{root}/target/aarch64-apple-darwin/debug/libfoo.a\n\
+             {root}/target/x86_64-apple-darwin/debug/libfoo.a",
+            root = root.display()
+        )
+    );
+    // Only the universal library is copied to `--out-dir`.
+    assert_eq!(p.read_file("out/libfoo.a"), merged);
+    assert_eq!(std::fs::read_dir(root.join("out")).unwrap().count(), 1);
+
+    // Fresh builds report the universal library without merging again.
+    p.cargo(
+        "build -Zmultitarget -Zuniversal-binaries --lipo \
+         --target aarch64-apple-darwin --target x86_64-apple-darwin --message-format=json",
+    )
+    .masquerade_as_nightly_cargo()
+    .env("LIPO", lipo.bin("lipo"))
+    .with_stderr("[FINISHED] [..]")
+    .with_json_contains_unordered(
+        r#"
+            {
+                "reason": "universal-artifact",
+                "package_id": "foo 0.1.0 [..]",
+                "target": "{...}",
+                "targets": ["aarch64-apple-darwin", "x86_64-apple-darwin"],
+                "filenames": ["[..]/target/universal-apple-darwin/debug/libfoo.a"],
+                "sources": [
+                    "[..]/target/aarch64-apple-darwin/debug/libfoo.a",
+                    "[..]/target/x86_64-apple-darwin/debug/libfoo.a"
+                ]
+            }
+        "#,
+    )
+    .run();
+}
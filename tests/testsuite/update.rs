@@ -758,3 +758,128 @@ required by package `foo v0.1.0 ([ROOT]/foo)`
         )
         .run();
 }
+
+/// A project with two sides of a merge of `Cargo.lock`: ours updated `foo`
+/// to 1.0.1, theirs added `bar` with `foo` at 1.0.2. The manifest is the
+/// merged one, with both dependencies.
+fn conflicting_lockfiles() -> (cargo_test_support::Project, String, String) {
+    Package::new("foo", "1.0.0").publish();
+    Package::new("foo", "1.0.1").publish();
+    Package::new("foo", "1.0.2").publish();
+    Package::new("bar", "1.0.0").publish();
+
+    let manifest = |deps: &str| {
+        format!(
+            r#"
+                [package]
+                name = "a"
+                version = "0.0.1"
+
+                [dependencies]
+                {}
+            "#,
+            deps
+        )
+    };
+    let p = project()
+        .file("Cargo.toml", &manifest("foo = \"1.0\""))
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("generate-lockfile").run();
+    p.cargo("update -p foo --precise 1.0.1").run();
+    let ours = p.read_lockfile();
+
+    p.change_file("Cargo.toml", &manifest("foo = \"1.0\"\nbar = \"1.0\""));
+    p.cargo("generate-lockfile").run();
+    let theirs = p.read_lockfile();
+    (p, ours, theirs)
+}
+
+#[cargo_test]
+fn merge_conflicts() {
+    let (p, ours, theirs) = conflicting_lockfiles();
+    p.change_file(
+        "Cargo.lock",
+        &format!("<<<<<<< HEAD\n{}=======\n{}>>>>>>> theirs\n", ours, theirs),
+    );
+
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+[ERROR] failed to parse lock file at: [..]Cargo.lock
+
+Caused by:
+  the lock file has merge conflicts, resolve them with `cargo update --merge -Z lockfile-merge`",
+        )
+        .run();
+
+    p.cargo("update --merge -Z lockfile-merge")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[ADDING] bar v1.0.0
+[UPDATING] foo v1.0.1 -> v1.0.2
+",
+        )
+        .run();
+    assert_eq!(p.read_lockfile(), theirs);
+    p.cargo("build --locked").run();
+}
+
+#[cargo_test]
+fn merge_keeps_locked_versions() {
+    let (p, ours, _theirs) = conflicting_lockfiles();
+    // Their side is the base, ours is a clean update of it.
+    p.cargo("update -p foo --precise 1.0.0").run();
+    let theirs = p.read_lockfile();
+    p.change_file(
+        "Cargo.lock",
+        &format!(
+            "<<<<<<< HEAD\n{}||||||| base\n{}=======\n{}>>>>>>> theirs\n",
+            ours, theirs, theirs
+        ),
+    );
+
+    p.cargo("update --merge -Z lockfile-merge")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[ADDING] bar v1.0.0
+",
+        )
+        .run();
+    let lock = p.read_lockfile();
+    assert!(lock.contains("name = \"foo\"\nversion = \"1.0.1\""));
+    assert!(!lock.contains("name = \"foo\"\nversion = \"1.0.0\""));
+}
+
+#[cargo_test]
+fn merge_requires_z_flag() {
+    let p = project().file("src/lib.rs", "").build();
+    p.cargo("update --merge")
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] the `--merge` flag is unstable, pass `-Z lockfile-merge` to enable it",
+        )
+        .run();
+    p.cargo("update --merge -p foo -Z lockfile-merge")
+        .masquerade_as_nightly_cargo()
+        .with_status(1)
+        .with_stderr_contains("[..]cannot be used with[..]")
+        .run();
+}
+
+#[cargo_test]
+fn merge_driver() {
+    let (p, ours, theirs) = conflicting_lockfiles();
+    p.change_file("ours.lock", &ours);
+    p.change_file("theirs.lock", &theirs);
+
+    p.cargo("lockfile merge-driver base.lock ours.lock theirs.lock -Z lockfile-merge")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("")
+        .run();
+    assert_eq!(p.read_file("ours.lock"), theirs);
+    assert_eq!(p.read_file("theirs.lock"), theirs);
+}
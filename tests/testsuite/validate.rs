@@ -0,0 +1,273 @@
+//! Tests for the `cargo validate` command.
+
+use cargo_test_support::{basic_manifest, project};
+
+const METADATA: &str = r#"
+    description = "foo"
+    license = "MIT"
+    repository = "https://example.com/foo"
+"#;
+
+#[cargo_test]
+fn requires_z_flag() {
+    let p = project().build();
+
+    p.cargo("validate")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] the `cargo validate` command is unstable, pass `-Z validate` to enable it",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn valid_package() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    [package]
+                    name = "foo"
+                    version = "0.1.0"
+                    {}
+                "#,
+                METADATA
+            ),
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("generate-lockfile").run();
+    p.cargo("validate -Zvalidate")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[VALIDATED] 1 package, 0 warnings")
+        .run();
+}
+
+#[cargo_test]
+fn reports_findings() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                readme = "README.md"
+                unknown = true
+
+                [dependencies]
+                bar = { path = "bar" }
+
+                [features]
+                a = ["b", "c"]
+                b = ["a"]
+                c = []
+                d = ["c", "c"]
+
+                [[bin]]
+                name = "tool"
+                path = "src/tool.rs"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.1.0"))
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    p.cargo("validate -Zvalidate")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[WARNING] foo v0.1.0: unused manifest key: package.unknown [manifest]
+[WARNING] foo v0.1.0: feature `d` lists `c` more than once [features]
+[WARNING] foo v0.1.0: features `a`, `b` enable each other in a cycle [features]
+[ERROR] foo v0.1.0: the source file `src/tool.rs` of the bin \"tool\" target does not exist [targets]
+[WARNING] foo v0.1.0: manifest has no description, license, license-file, documentation, homepage or repository [publish]
+[WARNING] foo v0.1.0: the `readme` file `README.md` does not exist [publish]
+[WARNING] foo v0.1.0: the path dependency `bar` does not specify a version, which is required to publish [publish]
+[ERROR] validation failed due to 1 previous error
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn configurable_severity() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [[bin]]
+                name = "tool"
+                path = "src/tool.rs"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config.toml",
+            r#"
+                [validate]
+                targets = "warn"
+                publish = "deny"
+            "#,
+        )
+        .build();
+
+    p.cargo("validate -Zvalidate")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[WARNING] foo v0.1.0: the source file `src/tool.rs` of the bin \"tool\" target does not exist [targets]
+[ERROR] foo v0.1.0: manifest has no description, license, license-file, documentation, homepage or repository [publish]
+[ERROR] validation failed due to 1 previous error
+",
+        )
+        .run();
+
+    // The command line overrides the configuration.
+    p.cargo("validate -Zvalidate --allow publish --allow targets")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[VALIDATED] 1 package, 0 warnings")
+        .run();
+
+    p.cargo("validate -Zvalidate --deny unknown")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] unknown check `unknown`, expected one of: \
+             manifest, schema, features, targets, lockfile, workspace, publish",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn lockfile_out_of_date() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["foo"]
+            "#,
+        )
+        .file("foo/Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("foo/src/lib.rs", "")
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.1.0"))
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    p.cargo("generate-lockfile").run();
+    p.change_file(
+        "foo/Cargo.toml",
+        r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+
+            [dependencies]
+            bar = { path = "../bar", version = "0.1.0" }
+        "#,
+    );
+    let lock = p.read_lockfile();
+
+    p.cargo("validate -Zvalidate --allow publish")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] [..]/Cargo.toml: `Cargo.lock` does not match the manifests, and needs to be updated [lockfile]
+[ERROR] validation failed due to 1 previous error
+",
+        )
+        .run();
+    // The lock file is only checked.
+    assert_eq!(p.read_lockfile(), lock);
+}
+
+#[cargo_test]
+fn json_output() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("validate -Zvalidate --message-format json")
+        .masquerade_as_nightly_cargo()
+        .with_json(
+            r#"
+                {
+                    "reason": "validation-finding",
+                    "check": "publish",
+                    "level": "warning",
+                    "package_id": "foo 0.1.0 ([..])",
+                    "manifest_path": "[..]/foo/Cargo.toml",
+                    "message": "manifest has no description, license, license-file, documentation, homepage or repository"
+                }
+
+                {
+                    "reason": "validation-finished",
+                    "success": true,
+                    "errors": 0,
+                    "warnings": 1
+                }
+            "#,
+        )
+        .with_stderr("[VALIDATED] 1 package, 1 warning")
+        .run();
+}
+
+#[cargo_test]
+fn path_dependency_outside_of_workspace() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["foo"]
+                exclude = ["bar"]
+            "#,
+        )
+        .file(
+            "foo/Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = { path = "../bar", version = "0.1.0" }
+            "#,
+        )
+        .file("foo/src/lib.rs", "")
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.1.0"))
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    p.cargo("validate -Zvalidate --allow publish")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[WARNING] foo v0.1.0: the path dependency `bar` at `[..]/bar` is not a member of the \
+workspace, and does not share its profiles, `[patch]` and `Cargo.lock` when it is built \
+on its own [workspace]
+[VALIDATED] 1 package, 1 warning
+",
+        )
+        .run();
+}
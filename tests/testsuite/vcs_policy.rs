@@ -0,0 +1,217 @@
+//! Tests for `-Z vcs-policy`, and for the VCS info of packages in worktrees
+//! and submodules.
+
+use std::fs::File;
+use std::path::Path;
+
+use cargo_test_support::paths::{self, CargoPathExt};
+use cargo_test_support::publish::validate_crate_contents;
+use cargo_test_support::registry;
+use cargo_test_support::{basic_manifest, cargo_process, git, path2url};
+
+fn vcs_info(sha1: &str, dirty: bool, path_in_vcs: &str) -> String {
+    format!(
+        r#"{{
+  "git": {{
+    "sha1": "{}"{}
+  }},
+  "path_in_vcs": "{}"
+}}
+"#,
+        sha1,
+        if dirty { ",\n    \"dirty\": true" } else { "" },
+        path_in_vcs
+    )
+}
+
+fn write_policy(policy: &str) {
+    let config = paths::root().join(".cargo/config.toml");
+    config.parent().unwrap().mkdir_p();
+    std::fs::write(config, format!("[vcs.allow-dirty]\n{}\n", policy)).unwrap();
+}
+
+#[cargo_test]
+fn package_warns_with_policy() {
+    let repo = git::repo(&paths::root().join("foo"))
+        .file("Cargo.toml", &basic_manifest("foo", "0.0.1"))
+        .file("src/lib.rs", "")
+        .build();
+    std::fs::write(repo.root().join("src/lib.rs"), "pub fn foo() {}").unwrap();
+    write_policy("package = \"warn\"");
+
+    // The policy is only read with `-Z vcs-policy`.
+    cargo_process("package --no-verify")
+        .cwd(repo.root())
+        .with_status(101)
+        .with_stderr_contains("[ERROR] 1 files in the working directory contain changes[..]")
+        .run();
+
+    cargo_process("package --no-verify -Zvcs-policy")
+        .cwd(repo.root())
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "\
+[WARNING] 1 files in the working directory contain changes that were not yet committed into git:
+
+src/lib.rs
+
+proceeding as `vcs.allow-dirty.package` is set to `warn`
+",
+        )
+        .run();
+
+    let f = File::open(repo.root().join("target/package/foo-0.0.1.crate")).unwrap();
+    validate_crate_contents(
+        f,
+        "foo-0.0.1.crate",
+        &[
+            "Cargo.toml",
+            "Cargo.toml.orig",
+            "src/lib.rs",
+            ".cargo_vcs_info.json",
+        ],
+        &[(
+            ".cargo_vcs_info.json",
+            &vcs_info(&repo.revparse_head(), true, ""),
+        )],
+    );
+}
+
+#[cargo_test]
+fn publish_has_its_own_policy() {
+    registry::init();
+    let repo = git::repo(&paths::root().join("foo"))
+        .file(
+            "Cargo.toml",
+            r#"
+                [project]
+                name = "foo"
+                version = "0.0.1"
+                license = "MIT"
+                description = "foo"
+                homepage = "foo"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+    std::fs::write(repo.root().join("src/lib.rs"), "pub fn foo() {}").unwrap();
+    write_policy("package = \"allow\"");
+
+    cargo_process("publish --dry-run --token sekrit -Zvcs-policy")
+        .cwd(repo.root())
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[ERROR] 1 files in the working directory contain changes that were not yet committed into git:
+
+src/lib.rs
+
+to proceed despite this and include the uncommitted changes, pass the `--allow-dirty` flag
+",
+        )
+        .run();
+
+    cargo_process("package --no-verify -Zvcs-policy")
+        .cwd(repo.root())
+        .masquerade_as_nightly_cargo()
+        .with_stderr_does_not_contain("[..]uncommitted[..]")
+        .run();
+}
+
+#[cargo_test]
+fn fix_warns_with_policy() {
+    let (p, repo) = git::new_repo("foo", |p| p.file("src/lib.rs", "pub fn foo() {}"));
+    p.change_file("src/lib.rs", "pub fn foo() -> u32 { let mut x = 3; x }");
+    write_policy("fix = \"warn\"");
+
+    p.cargo("fix -Zvcs-policy")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "\
+[WARNING] the working directory of this package has uncommitted changes to these files:
+
+  * src/lib.rs (dirty)
+
+proceeding as `vcs.allow-dirty.fix` is set to `warn`
+",
+        )
+        .with_stderr_contains("[FIXED] src/lib.rs (1 fix)")
+        .run();
+
+    // Staged changes are still refused without `--allow-staged`.
+    git::add(&repo);
+    p.cargo("fix -Zvcs-policy")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("  * src/lib.rs (staged)")
+        .run();
+}
+
+#[cargo_test]
+fn vcs_info_in_worktree() {
+    let repo = git::repo(&paths::root().join("foo"))
+        .file("Cargo.toml", &basic_manifest("foo", "0.0.1"))
+        .file("src/lib.rs", "")
+        .file("a/Cargo.toml", &basic_manifest("a", "0.0.1"))
+        .file("a/src/lib.rs", "")
+        .build();
+    let git_repo = git2::Repository::open(repo.root()).unwrap();
+    let worktree = paths::root().join("wt");
+    git_repo.worktree("wt", &worktree, None).unwrap();
+
+    cargo_process("package --no-verify")
+        .cwd(worktree.join("a"))
+        .run();
+
+    let f = File::open(worktree.join("a/target/package/a-0.0.1.crate")).unwrap();
+    validate_crate_contents(
+        f,
+        "a-0.0.1.crate",
+        &[
+            "Cargo.toml",
+            "Cargo.toml.orig",
+            "src/lib.rs",
+            ".cargo_vcs_info.json",
+        ],
+        &[(
+            ".cargo_vcs_info.json",
+            &vcs_info(&repo.revparse_head(), false, "a"),
+        )],
+    );
+}
+
+#[cargo_test]
+fn vcs_info_in_submodule() {
+    let sub = git::repo(&paths::root().join("sub"))
+        .file("Cargo.toml", &basic_manifest("sub", "0.0.1"))
+        .file("src/lib.rs", "")
+        .build();
+    let superproject = git::repo(&paths::root().join("super"))
+        .file("README.md", "")
+        .build();
+    let super_repo = git2::Repository::open(superproject.root()).unwrap();
+    let url = path2url(sub.root()).to_string();
+    git::add_submodule(&super_repo, &url, Path::new("sub"));
+    git::commit(&super_repo);
+    let sub_root = superproject.root().join("sub");
+
+    cargo_process("package --no-verify").cwd(&sub_root).run();
+
+    let f = File::open(sub_root.join("target/package/sub-0.0.1.crate")).unwrap();
+    validate_crate_contents(
+        f,
+        "sub-0.0.1.crate",
+        &[
+            "Cargo.toml",
+            "Cargo.toml.orig",
+            "src/lib.rs",
+            ".cargo_vcs_info.json",
+        ],
+        &[(
+            ".cargo_vcs_info.json",
+            &vcs_info(&sub.revparse_head(), false, ""),
+        )],
+    );
+}
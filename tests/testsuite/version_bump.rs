@@ -0,0 +1,258 @@
+//! Tests for the `cargo version bump` command.
+
+use cargo_test_support::{basic_manifest, project};
+
+#[cargo_test]
+fn version_bump_requires_flag() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["foo", "bar", "baz"]
+
+                [workspace.dependencies]
+                foo = { path = "foo", version = "0.1" } # shared
+            "#,
+        )
+        .file("foo/Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("foo/src/lib.rs", "")
+        .file(
+            "bar/Cargo.toml",
+            r#"
+                [package]
+                name = "bar"
+                version = "0.1.0"
+
+                [dependencies]
+                foo = { path = "../foo", version = "0.1.0" }
+            "#,
+        )
+        .file("bar/src/lib.rs", "")
+        .file(
+            "baz/Cargo.toml",
+            r#"
+                [package]
+                name = "baz"
+                version = "0.3.0"
+
+                [dev-dependencies.foo]
+                path = "../foo"
+                # Exactly this one.
+                version = "=0.1.0"
+            "#,
+        )
+        .file("baz/src/lib.rs", "")
+        .build();
+
+    p.cargo("version bump -p foo --minor")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] the `cargo version bump` command is unstable, \
+             pass `-Z version-bump` to enable it",
+        )
+        .run();
+
+    // `cargo version` still prints the version.
+    p.cargo("version").with_stdout_contains("cargo [..]").run();
+}
+
+#[cargo_test]
+fn bump_updates_requirements() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["foo", "bar", "baz"]
+
+                [workspace.dependencies]
+                foo = { path = "foo", version = "0.1" } # shared
+            "#,
+        )
+        .file("foo/Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("foo/src/lib.rs", "")
+        .file(
+            "bar/Cargo.toml",
+            r#"
+                [package]
+                name = "bar"
+                version = "0.1.0"
+
+                [dependencies]
+                foo = { path = "../foo", version = "0.1.0" }
+            "#,
+        )
+        .file("bar/src/lib.rs", "")
+        .file(
+            "baz/Cargo.toml",
+            r#"
+                [package]
+                name = "baz"
+                version = "0.3.0"
+
+                [dev-dependencies.foo]
+                path = "../foo"
+                # Exactly this one.
+                version = "=0.1.0"
+            "#,
+        )
+        .file("baz/src/lib.rs", "")
+        .build();
+
+    p.cargo("version bump -p foo --minor -Zversion-bump")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_unordered(
+            "\
+[BUMPING] foo v0.1.0 -> v0.2.0
+[UPDATING] bar's requirement on foo from `0.1.0` to `0.2.0`
+[UPDATING] baz's requirement on foo from `=0.1.0` to `=0.2.0`
+[UPDATING] workspace's requirement on foo from `0.1` to `0.2`
+",
+        )
+        .run();
+
+    assert_eq!(
+        p.read_file("foo/Cargo.toml"),
+        basic_manifest("foo", "0.2.0")
+    );
+    assert!(p
+        .read_file("bar/Cargo.toml")
+        .contains(r#"foo = { path = "../foo", version = "0.2.0" }"#));
+    assert!(p
+        .read_file("baz/Cargo.toml")
+        .contains("# Exactly this one.\n                version = \"=0.2.0\"\n"));
+    assert!(p
+        .read_file("Cargo.toml")
+        .contains(r#"foo = { path = "foo", version = "0.2" } # shared"#));
+
+    p.cargo("build").run();
+}
+
+#[cargo_test]
+fn dry_run_prints_diff() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["foo", "bar", "baz"]
+
+                [workspace.dependencies]
+                foo = { path = "foo", version = "0.1" } # shared
+            "#,
+        )
+        .file("foo/Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("foo/src/lib.rs", "")
+        .file(
+            "bar/Cargo.toml",
+            r#"
+                [package]
+                name = "bar"
+                version = "0.1.0"
+
+                [dependencies]
+                foo = { path = "../foo", version = "0.1.0" }
+            "#,
+        )
+        .file("bar/src/lib.rs", "")
+        .file(
+            "baz/Cargo.toml",
+            r#"
+                [package]
+                name = "baz"
+                version = "0.3.0"
+
+                [dev-dependencies.foo]
+                path = "../foo"
+                # Exactly this one.
+                version = "=0.1.0"
+            "#,
+        )
+        .file("baz/src/lib.rs", "")
+        .build();
+    let bar_manifest = p.read_file("bar/Cargo.toml");
+
+    p.cargo("version bump -p foo --to 1.0.0 --dry-run -Zversion-bump")
+        .masquerade_as_nightly_cargo()
+        .with_stdout_contains("--- a/bar/Cargo.toml")
+        .with_stdout_contains(r#"-                foo = { path = "../foo", version = "0.1.0" }"#)
+        .with_stdout_contains(r#"+                foo = { path = "../foo", version = "1.0.0" }"#)
+        .with_stdout_contains("+++ b/foo/Cargo.toml")
+        .with_stdout_contains(r#"+        version = "1.0.0""#)
+        .with_stdout_contains(
+            r#"+                foo = { path = "foo", version = "1.0" } # shared"#,
+        )
+        .with_stderr_contains("[WARNING] aborting version bump due to dry run")
+        .run();
+    assert_eq!(p.read_file("bar/Cargo.toml"), bar_manifest);
+    assert_eq!(
+        p.read_file("foo/Cargo.toml"),
+        basic_manifest("foo", "0.1.0")
+    );
+}
+
+#[cargo_test]
+fn bump_current_package() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["foo", "bar", "baz"]
+
+                [workspace.dependencies]
+                foo = { path = "foo", version = "0.1" } # shared
+            "#,
+        )
+        .file("foo/Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("foo/src/lib.rs", "")
+        .file(
+            "bar/Cargo.toml",
+            r#"
+                [package]
+                name = "bar"
+                version = "0.1.0"
+
+                [dependencies]
+                foo = { path = "../foo", version = "0.1.0" }
+            "#,
+        )
+        .file("bar/src/lib.rs", "")
+        .file(
+            "baz/Cargo.toml",
+            r#"
+                [package]
+                name = "baz"
+                version = "0.3.0"
+
+                [dev-dependencies.foo]
+                path = "../foo"
+                # Exactly this one.
+                version = "=0.1.0"
+            "#,
+        )
+        .file("baz/src/lib.rs", "")
+        .build();
+    p.cargo("generate-lockfile").run();
+
+    p.cargo("version bump --patch -Zversion-bump")
+        .cwd("baz")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[BUMPING] baz v0.3.0 -> v0.3.1")
+        .run();
+    assert!(p
+        .read_file("baz/Cargo.toml")
+        .contains("version = \"0.3.1\""));
+    assert!(p
+        .read_file("Cargo.lock")
+        .contains("name = \"baz\"\nversion = \"0.3.1\""));
+
+    p.cargo("version bump --to 0.2.0 -Zversion-bump")
+        .cwd("baz")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] cannot bump baz v0.3.1 to v0.2.0, as it is not newer")
+        .run();
+}
@@ -0,0 +1,213 @@
+//! Tests for `--warnings`.
+
+use cargo_test_support::{basic_manifest, project};
+
+#[cargo_test]
+fn gated() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["a", "b"]
+            "#,
+        )
+        .file("a/Cargo.toml", &basic_manifest("a", "0.1.0"))
+        .file(
+            "a/src/lib.rs",
+            r#"
+                fn unused() {}
+                pub fn a() {
+                    let x = 1;
+                    let y = 2;
+                }
+            "#,
+        )
+        .file("b/Cargo.toml", &basic_manifest("b", "0.1.0"))
+        .file(
+            "b/src/lib.rs",
+            r#"
+                fn unused() {}
+                pub fn b() {
+                    let x = 1;
+                }
+            "#,
+        )
+        .build();
+    p.cargo("check --warnings=summary")
+        .with_status(101)
+        .with_stderr("[ERROR] Usage of `--warnings` requires `-Z warnings`")
+        .run();
+}
+
+#[cargo_test]
+fn invalid_mode() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["a", "b"]
+            "#,
+        )
+        .file("a/Cargo.toml", &basic_manifest("a", "0.1.0"))
+        .file(
+            "a/src/lib.rs",
+            r#"
+                fn unused() {}
+                pub fn a() {
+                    let x = 1;
+                    let y = 2;
+                }
+            "#,
+        )
+        .file("b/Cargo.toml", &basic_manifest("b", "0.1.0"))
+        .file(
+            "b/src/lib.rs",
+            r#"
+                fn unused() {}
+                pub fn b() {
+                    let x = 1;
+                }
+            "#,
+        )
+        .build();
+    p.cargo("check -Zwarnings --warnings=none")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] invalid `--warnings` value `none`, expected `full`, `summary` or `deny`",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn summary() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["a", "b"]
+            "#,
+        )
+        .file("a/Cargo.toml", &basic_manifest("a", "0.1.0"))
+        .file(
+            "a/src/lib.rs",
+            r#"
+                fn unused() {}
+                pub fn a() {
+                    let x = 1;
+                    let y = 2;
+                }
+            "#,
+        )
+        .file("b/Cargo.toml", &basic_manifest("b", "0.1.0"))
+        .file(
+            "b/src/lib.rs",
+            r#"
+                fn unused() {}
+                pub fn b() {
+                    let x = 1;
+                }
+            "#,
+        )
+        .build();
+    let summary = "\
+[WARNING] 5 warnings in 2 packages
+  unused_variables: 3 (`a`: 2, `b`: 1)
+  dead_code: 2 (`a`: 1, `b`: 1)
+[NOTE] pass `--warnings=full` to see each warning
+[FINISHED] [..]
+";
+    p.cargo("check -Zwarnings --warnings=summary")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_unordered(&format!(
+            "[CHECKING] a v0.1.0 ([..])\n[CHECKING] b v0.1.0 ([..])\n{}",
+            summary
+        ))
+        .run();
+
+    // The warnings of fresh units are replayed.
+    p.cargo("check -Zwarnings --warnings=summary")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_unordered(summary)
+        .run();
+
+    p.cargo("check")
+        .with_stderr_contains("[WARNING] `a` (lib) generated 3 warnings")
+        .with_stderr_contains("[WARNING] `b` (lib) generated 2 warnings")
+        .run();
+}
+
+#[cargo_test]
+fn summary_duplicates() {
+    let p = project().file("src/lib.rs", "fn unused() {}").build();
+    p.cargo("check --lib --tests -Zwarnings --warnings=summary")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[CHECKING] foo v0.0.1 ([..])
+[WARNING] 1 warning in 1 package (1 duplicate)
+  dead_code: 1 (`foo`: 1)
+[NOTE] pass `--warnings=full` to see each warning
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn deny() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["a", "b"]
+            "#,
+        )
+        .file("a/Cargo.toml", &basic_manifest("a", "0.1.0"))
+        .file(
+            "a/src/lib.rs",
+            r#"
+                fn unused() {}
+                pub fn a() {
+                    let x = 1;
+                    let y = 2;
+                }
+            "#,
+        )
+        .file("b/Cargo.toml", &basic_manifest("b", "0.1.0"))
+        .file(
+            "b/src/lib.rs",
+            r#"
+                fn unused() {}
+                pub fn b() {
+                    let x = 1;
+                }
+            "#,
+        )
+        .build();
+    p.cargo("check -Zwarnings --warnings=deny")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[WARNING] unused variable: `x`")
+        .with_stderr_contains("[WARNING] `a` (lib) generated 3 warnings")
+        .with_stderr_contains("[ERROR] 5 warnings were denied by `--warnings=deny`")
+        .with_stderr_does_not_contain("[FINISHED] [..]")
+        .run();
+
+    // The warnings are denied without rebuilding.
+    p.cargo("check -Zwarnings --warnings=deny")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_does_not_contain("[CHECKING] [..]")
+        .with_stderr_contains("[ERROR] 5 warnings were denied by `--warnings=deny`")
+        .run();
+
+    p.cargo("check")
+        .with_stderr_does_not_contain("[CHECKING] [..]")
+        .with_stderr_contains("[FINISHED] [..]")
+        .run();
+}
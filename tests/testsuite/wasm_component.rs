@@ -0,0 +1,262 @@
+//! Tests for the `component` crate type.
+//!
+//! The WebAssembly targets are usually not installed, so building a component
+//! is only tested when the standard library of `wasm32-unknown-unknown` is.
+
+use cargo_test_support::{basic_manifest, project, rustc_host, Project};
+use std::path::Path;
+use std::process::Command;
+
+/// A `wasm-tools` which logs its arguments to `wasm-tools.log` next to its
+/// output, and copies its input module to its output.
+fn fake_wasm_tools() -> Project {
+    let p = project()
+        .at("wasm-tools")
+        .file("Cargo.toml", &basic_manifest("wasm-tools", "1.0.0"))
+        .file(
+            "src/main.rs",
+            r#"
+                use std::io::Write;
+                use std::path::Path;
+
+                fn main() {
+                    let args: Vec<String> = std::env::args().skip(1).collect();
+                    let output = &args[args.iter().position(|a| a == "-o").unwrap() + 1];
+                    let input = if args[1] == "embed" { &args[3] } else { &args[2] };
+                    std::fs::copy(input, output).unwrap();
+                    let log = Path::new(output).with_file_name("wasm-tools.log");
+                    let mut log = std::fs::OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(log)
+                        .unwrap();
+                    writeln!(log, "{}", args.join(" ")).unwrap();
+                }
+            "#,
+        )
+        .build();
+    p.cargo("build").run();
+    p
+}
+
+fn wasm_target_installed() -> bool {
+    let output = Command::new("rustc")
+        .args(&["--print", "sysroot"])
+        .output()
+        .unwrap();
+    let sysroot = String::from_utf8(output.stdout).unwrap();
+    Path::new(sysroot.trim())
+        .join("lib/rustlib/wasm32-unknown-unknown/lib")
+        .is_dir()
+}
+
+#[cargo_test]
+fn crate_type_gated() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [lib]
+                crate-type = ["component"]
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[..]feature `wasm-component` is required")
+        .run();
+}
+
+#[cargo_test]
+fn table_gated() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [package.component]
+                wit = "wit"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[..]feature `wasm-component` is required")
+        .run();
+}
+
+#[cargo_test]
+fn world_requires_wit() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                    cargo-features = ["wasm-component"]
+
+                    [package]
+                    name = "foo"
+                    version = "0.1.0"
+
+                    [lib]
+                    crate-type = ["component"]
+
+
+                [package.component]
+                world = "foo"
+
+            "#,
+        )
+        .file("src/lib.rs", "#[no_mangle] pub extern \"C\" fn foo() {}")
+        .build();
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("  `package.component.world` requires `package.component.wit`")
+        .run();
+}
+
+#[cargo_test]
+fn not_a_wasm_target() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["wasm-component"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [lib]
+                crate-type = ["component"]
+
+
+            "#,
+        )
+        .file("src/lib.rs", "#[no_mangle] pub extern \"C\" fn foo() {}")
+        .build();
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(&format!(
+            "[ERROR] cannot produce component for `foo v0.1.0 ([..])` as the target `{}` \
+             does not support these crate types",
+            rustc_host()
+        ))
+        .run();
+
+    // The other crate types are still built.
+    p.change_file(
+        "Cargo.toml",
+        r#"
+            cargo-features = ["wasm-component"]
+
+            [package]
+            name = "foo"
+            version = "0.1.0"
+
+            [lib]
+            crate-type = ["rlib", "component"]
+        "#,
+    );
+    p.cargo("build -v")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.1.0 ([..])
+[RUNNING] `rustc [..]--crate-type rlib --crate-type cdylib [..]`
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn build_component() {
+    if !wasm_target_installed() {
+        return;
+    }
+    let wasm_tools = fake_wasm_tools();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                    cargo-features = ["wasm-component"]
+
+                    [package]
+                    name = "foo"
+                    version = "0.1.0"
+
+                    [lib]
+                    crate-type = ["component"]
+
+
+                [package.component]
+                wit = "wit"
+                world = "foo"
+                adapters = { wasi_snapshot_preview1 = "adapter.wasm" }
+
+            "#,
+        )
+        .file("src/lib.rs", "#[no_mangle] pub extern \"C\" fn foo() {}")
+        .build();
+
+    p.cargo("build --target wasm32-unknown-unknown --message-format=json")
+        .masquerade_as_nightly_cargo()
+        .env("WASM_TOOLS", wasm_tools.bin("wasm-tools"))
+        .with_json_contains_unordered(
+            r#"
+                {
+                    "reason": "compiler-artifact",
+                    "package_id": "foo 0.1.0 [..]",
+                    "manifest_path": "[..]",
+                    "target": {
+                        "kind": ["component"],
+                        "crate_types": ["component"],
+                        "name": "foo",
+                        "src_path": "[..]",
+                        "edition": "2015",
+                        "doc": true,
+                        "doctest": false,
+                        "test": true
+                    },
+                    "profile": "{...}",
+                    "features": [],
+                    "filenames": [
+                        "[..]/target/wasm32-unknown-unknown/debug/foo.component.wasm",
+                        "[..]/target/wasm32-unknown-unknown/debug/foo.wasm"
+                    ],
+                    "executable": null,
+                    "fresh": false
+                }
+            "#,
+        )
+        .run();
+
+    let deps = p.root().join("target/wasm32-unknown-unknown/debug/deps");
+    let log = std::fs::read_to_string(deps.join("wasm-tools.log")).unwrap();
+    let root = p.root();
+    assert_eq!(
+        log,
+        format!(
+            "component embed {root}/wit {deps}/foo.wasm -o {deps}/foo.component.wasm.embed \
+             --world foo\n\
+             component new {deps}/foo.component.wasm.embed -o {deps}/foo.component.wasm \
+             --adapt wasi_snapshot_preview1={root}/adapter.wasm\n",
+            root = root.display(),
+            deps = deps.display()
+        )
+    );
+    assert!(!deps.join("foo.component.wasm.embed").exists());
+}
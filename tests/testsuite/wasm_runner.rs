@@ -0,0 +1,104 @@
+//! Tests for `-Z wasm-runner`.
+//!
+//! The WebAssembly targets and runtimes are usually not installed, so these
+//! only cover the configuration. How the runtimes are invoked is covered by
+//! the unit tests of `wasm_runner.rs`.
+
+use cargo_test_support::{project, rustc_host};
+use cargo_util::paths;
+use std::path::Path;
+
+#[cargo_test]
+fn configured_runtime_not_found() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            r#"
+                [target.wasm32-wasip1]
+                wasm-runtime = "wasmer"
+            "#,
+        )
+        .build();
+
+    p.cargo("test -Zwasm-runner --target wasm32-wasip1")
+        .masquerade_as_nightly_cargo()
+        .env(
+            "RUSTC",
+            paths::resolve_executable(Path::new("rustc")).unwrap(),
+        )
+        .env("PATH", p.root().join("empty-path"))
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] `wasmer` was not found in PATH, set in `target.wasm32-wasip1.wasm-runtime`",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn configured_runtime_wrong_target() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            r#"
+                [target.wasm32-wasip1]
+                wasm-runtime = "node"
+            "#,
+        )
+        .build();
+
+    p.cargo("test -Zwasm-runner --target wasm32-wasip1")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] `node` can't run the executables of the target `wasm32-wasip1`, \
+             set in `target.wasm32-wasip1.wasm-runtime`",
+        )
+        .run();
+
+    p.change_file(
+        ".cargo/config",
+        r#"
+            [target.wasm32-wasip1]
+            wasm-runtime = "v8"
+        "#,
+    );
+    p.cargo("test -Zwasm-runner --target wasm32-wasip1")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] unknown WebAssembly runtime `v8`, \
+             expected one of `wasmtime`, `wasmer` or `node`",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn wasm_runtime_is_not_a_links_override() {
+    let p = project()
+        .file("src/main.rs", "fn main() {}")
+        .file(
+            ".cargo/config",
+            &format!(
+                r#"
+                    [target.{}]
+                    wasm-runtime = "wasmtime"
+                "#,
+                rustc_host()
+            ),
+        )
+        .build();
+
+    // Not a WebAssembly target, so the setting is ignored.
+    p.cargo("run -Zwasm-runner")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+[RUNNING] `target/debug/foo[EXE]`
+",
+        )
+        .run();
+}
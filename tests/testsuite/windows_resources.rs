@@ -0,0 +1,258 @@
+//! Tests for the `[package.windows]` table.
+//!
+//! Embedding the resources is tested with a fake resource compiler and
+//! linker, and only when the standard library of `x86_64-pc-windows-gnu` is
+//! installed.
+
+use cargo_test_support::{basic_manifest, project, Project};
+use std::path::Path;
+use std::process::Command;
+
+const TARGET: &str = "x86_64-pc-windows-gnu";
+
+/// A tool which logs its arguments to `<name>.log` next to the file after
+/// `out_flag`, and copies the file after `in_flag` to it, or writes an empty
+/// one.
+fn fake_tool(name: &str, in_flag: &str, out_flag: &str) -> Project {
+    let p = project()
+        .at(name)
+        .file("Cargo.toml", &basic_manifest(name, "1.0.0"))
+        .file(
+            "src/main.rs",
+            &format!(
+                r#"
+                    use std::io::Write;
+                    use std::path::Path;
+
+                    fn main() {{
+                        let args: Vec<String> = std::env::args().skip(1).collect();
+                        let after = |flag| args.iter().position(|a| a == flag).map(|i| &args[i + 1]);
+                        let output = after("{out_flag}").unwrap();
+                        match after("{in_flag}") {{
+                            Some(input) => {{ std::fs::copy(input, output).unwrap(); }}
+                            None => std::fs::write(output, "").unwrap(),
+                        }}
+                        let log = Path::new(output).with_file_name("{name}.log");
+                        let mut log = std::fs::OpenOptions::new()
+                            .create(true)
+                            .append(true)
+                            .open(log)
+                            .unwrap();
+                        writeln!(log, "{{}}", args.join(" ")).unwrap();
+                    }}
+                "#,
+                name = name,
+                in_flag = in_flag,
+                out_flag = out_flag
+            ),
+        )
+        .build();
+    p.cargo("build").run();
+    p
+}
+
+fn windows_target_installed() -> bool {
+    let output = Command::new("rustc")
+        .args(&["--print", "sysroot"])
+        .output()
+        .unwrap();
+    let sysroot = String::from_utf8(output.stdout).unwrap();
+    Path::new(sysroot.trim())
+        .join("lib/rustlib")
+        .join(TARGET)
+        .join("lib")
+        .is_dir()
+}
+
+#[cargo_test]
+fn gated() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [package.windows]
+                icon = "foo.ico"
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[..]feature `windows-resources` is required")
+        .run();
+}
+
+#[cargo_test]
+fn invalid_version_info_name() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["windows-resources"]
+
+                [package]
+                name = "foo"
+                version = "1.2.3"
+                description = "A \"foo\" tool"
+
+                [package.windows]
+                version-info = { "Legal Copyright" = "Me" }
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .file("foo.ico", "icon")
+        .file("foo.manifest", "<assembly/>")
+        .build();
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "  invalid name `Legal Copyright` in `package.windows.version-info`, \
+             the names may only contain ASCII letters and digits",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn ignored_for_other_targets() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["windows-resources"]
+
+                [package]
+                name = "foo"
+                version = "1.2.3"
+                description = "A \"foo\" tool"
+
+                [package.windows]
+                icon = "foo.ico"
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .file("foo.ico", "icon")
+        .file("foo.manifest", "<assembly/>")
+        .build();
+    p.cargo("build -v")
+        .masquerade_as_nightly_cargo()
+        .env("RC", "does-not-exist")
+        .with_stderr_does_not_contain("[..]link-arg[..]")
+        .run();
+}
+
+#[cargo_test]
+fn embed_resources() {
+    if !windows_target_installed() {
+        return;
+    }
+    let rc = fake_tool("rc", "--input", "--output");
+    let linker = fake_tool("linker", "--no-input", "-o");
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                    cargo-features = ["windows-resources"]
+
+                    [package]
+                    name = "foo"
+                    version = "1.2.3"
+                    description = "A \"foo\" tool"
+
+                    [package.windows]
+
+                icon = "foo.ico"
+                manifest = "foo.manifest"
+                version-info = { LegalCopyright = "Copyright (c) Foo", ProductName = "Foo" }
+
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .file("foo.ico", "icon")
+        .file("foo.manifest", "<assembly/>")
+        .build();
+    let build = |p: &Project| {
+        let mut build = p.cargo(&format!("build -v --target {}", TARGET));
+        build
+            .masquerade_as_nightly_cargo()
+            .env("RC", rc.bin("rc"))
+            .env(
+                "CARGO_TARGET_X86_64_PC_WINDOWS_GNU_LINKER",
+                linker.bin("linker"),
+            );
+        build
+    };
+
+    build(&p)
+        .with_stderr(&format!(
+            "\
+[COMPILING] foo v1.2.3 ([..])
+[RUNNING] `rustc --crate-name foo [..]-C link-arg=[..]/target/{target}/debug/deps/foo-[..].res.o[..]`
+[FINISHED] [..]
+",
+            target = TARGET
+        ))
+        .run();
+
+    let deps = p.root().join("target").join(TARGET).join("debug/deps");
+    let rc_log = std::fs::read_to_string(deps.join("rc.log")).unwrap();
+    let script = Path::new(rc_log.split(' ').nth(1).unwrap());
+    let root = p.root();
+    assert_eq!(
+        std::fs::read_to_string(script).unwrap(),
+        format!(
+            r#"#pragma code_page(65001)
+1 ICON "{root}/foo.ico"
+1 24 "{root}/foo.manifest"
+1 VERSIONINFO
+FILEVERSION 1,2,3,0
+PRODUCTVERSION 1,2,3,0
+FILEOS 0x40004
+FILETYPE 0x1
+BEGIN
+  BLOCK "StringFileInfo"
+  BEGIN
+    BLOCK "040904B0"
+    BEGIN
+      VALUE "FileDescription", "A ""foo"" tool"
+      VALUE "FileVersion", "1.2.3"
+      VALUE "InternalName", "foo"
+      VALUE "LegalCopyright", "Copyright (c) Foo"
+      VALUE "OriginalFilename", "foo.exe"
+      VALUE "ProductName", "Foo"
+      VALUE "ProductVersion", "1.2.3"
+    END
+  END
+  BLOCK "VarFileInfo"
+  BEGIN
+    VALUE "Translation", 0x409, 1200
+  END
+END
+"#,
+            root = root.display()
+        )
+    );
+    let linker_log = std::fs::read_to_string(deps.join("linker.log")).unwrap();
+    assert!(linker_log.contains(".res.o"));
+
+    build(&p)
+        .with_stderr("[FRESH] foo v1.2.3 ([..])\n[FINISHED] [..]")
+        .run();
+
+    // The icon is an input of the build.
+    p.change_file("foo.ico", "new icon");
+    build(&p)
+        .with_stderr(
+            "\
+[COMPILING] foo v1.2.3 ([..])
+[RUNNING] `rustc [..]`
+[FINISHED] [..]
+",
+        )
+        .run();
+}
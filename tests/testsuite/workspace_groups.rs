@@ -0,0 +1,161 @@
+//! Tests for `[workspace.groups]` and the `--group` flag.
+
+use cargo_test_support::{basic_manifest, project};
+
+fn groups_project() -> cargo_test_support::Project {
+    project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["workspace-groups"]
+
+                [workspace]
+                members = ["crates/*"]
+
+                [workspace.groups]
+                backend = ["crates/api*", "!crates/api-legacy", "crates/db"]
+                frontend = ["crates/web"]
+            "#,
+        )
+        .file("crates/api/Cargo.toml", &basic_manifest("api", "0.1.0"))
+        .file("crates/api/src/lib.rs", "")
+        .file(
+            "crates/api-legacy/Cargo.toml",
+            &basic_manifest("api-legacy", "0.1.0"),
+        )
+        .file("crates/api-legacy/src/lib.rs", "")
+        .file("crates/db/Cargo.toml", &basic_manifest("db", "0.1.0"))
+        .file("crates/db/src/lib.rs", "")
+        .file("crates/web/Cargo.toml", &basic_manifest("web", "0.1.0"))
+        .file("crates/web/src/lib.rs", "")
+        .build()
+}
+
+#[cargo_test]
+fn build_group() {
+    let p = groups_project();
+
+    p.cargo("build --group backend")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_unordered(
+            "\
+[COMPILING] api v0.1.0 ([..])
+[COMPILING] db v0.1.0 ([..])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn multiple_groups() {
+    let p = groups_project();
+
+    p.cargo("check --group backend --group frontend")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_unordered(
+            "\
+[CHECKING] api v0.1.0 ([..])
+[CHECKING] db v0.1.0 ([..])
+[CHECKING] web v0.1.0 ([..])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn unknown_group() {
+    let p = groups_project();
+
+    p.cargo("build --group backedn")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] workspace group `backedn` not found in workspace `[..]`
+
+<tab>Did you mean `backend`?
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn group_conflicts_with_package() {
+    let p = groups_project();
+
+    p.cargo("build --group backend -p web")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] --group cannot be used together with --workspace or --package")
+        .run();
+}
+
+#[cargo_test]
+fn group_member_must_be_member() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["workspace-groups"]
+
+                [workspace]
+                members = ["a"]
+
+                [workspace.groups]
+                extra = ["b"]
+            "#,
+        )
+        .file("a/Cargo.toml", &basic_manifest("a", "0.1.0"))
+        .file("a/src/lib.rs", "")
+        .file("b/Cargo.toml", &basic_manifest("b", "0.1.0"))
+        .file("b/src/lib.rs", "")
+        .build();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] package `[..]b` is listed in workspace group `extra` but is not a member.",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn groups_require_feature() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["a"]
+
+                [workspace.groups]
+                all = ["a"]
+            "#,
+        )
+        .file("a/Cargo.toml", &basic_manifest("a", "0.1.0"))
+        .file("a/src/lib.rs", "")
+        .build();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  feature `workspace-groups` is required
+
+  The package requires the Cargo feature called `workspace-groups`, \
+  but that feature is not stabilized in this version of Cargo (1.[..]).
+  Consider adding `cargo-features = [\"workspace-groups\"]` to the top of Cargo.toml \
+  (above the [package] table) to tell Cargo you are opting in to use this unstable feature.
+  See https://doc.rust-lang.org/nightly/cargo/reference/unstable.html#workspace-groups \
+  for more information about the status of this feature.
+",
+        )
+        .run();
+}
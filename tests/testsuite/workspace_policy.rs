@@ -0,0 +1,527 @@
+//! Tests for the `[workspace.policy]` rules.
+
+use cargo_test_support::registry::{self, Package};
+use cargo_test_support::{basic_lib_manifest, git, project};
+
+/// Publishes `name` with the license `license` and the dependencies `deps`.
+fn publish(name: &str, version: &str, license: &str, deps: &[(&str, &str)]) {
+    let mut manifest = format!(
+        "[package]\nname = \"{}\"\nversion = \"{}\"\nlicense = \"{}\"\n\n[dependencies]\n",
+        name, version, license
+    );
+    let mut pkg = Package::new(name, version);
+    for (dep, req) in deps {
+        manifest.push_str(&format!("{} = \"{}\"\n", dep, req));
+        pkg.dep(dep, req);
+    }
+    pkg.file("Cargo.toml", &manifest)
+        .file("src/lib.rs", "")
+        .publish();
+}
+
+#[cargo_test]
+fn policy_requires_feature() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [workspace.policy]
+                allowed-licenses = ["MIT"]
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("  feature `workspace-policy` is required")
+        .run();
+}
+
+#[cargo_test]
+fn disallowed_licenses() {
+    publish("baz", "1.0.0", "GPL-3.0-only", &[]);
+    publish("bar", "1.0.0", "MIT OR Apache-2.0", &[("baz", "1.0")]);
+    publish("qux", "1.0.0", "MIT AND (Zlib OR GPL-3.0-only)", &[]);
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                     cargo-features = ["workspace-policy"]
+
+                     [package]
+                     name = "foo"
+                     version = "0.1.0"
+
+                     [dependencies]
+                     bar = "1.0"
+                qux = "1.0"
+
+                     [workspace.policy]
+                     allowed-licenses = ["MIT", "Apache-2.0"]
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[UPDATING] [..]
+[DOWNLOADING] crates ...
+[DOWNLOADED] [..]
+[DOWNLOADED] [..]
+[DOWNLOADED] [..]
+[ERROR] 2 violations of the workspace policy in `[CWD]/Cargo.toml`:
+
+  baz v1.0.0 has the license `GPL-3.0-only`, which is not allowed by `allowed-licenses`
+    dependency path: foo v0.1.0 ([CWD]) -> bar v1.0.0 -> baz v1.0.0
+    help: allow one of its licenses in `allowed-licenses`, \
+or add `baz` to `license-exceptions` once its license is reviewed
+
+  qux v1.0.0 has the license `MIT AND (Zlib OR GPL-3.0-only)`, \
+which is not allowed by `allowed-licenses`
+    dependency path: foo v0.1.0 ([CWD]) -> qux v1.0.0
+    help: allow one of its licenses in `allowed-licenses`, \
+or add `qux` to `license-exceptions` once its license is reviewed
+",
+        )
+        .run();
+
+    p.change_file(
+        "Cargo.toml",
+        &p.read_file("Cargo.toml").replace(
+            r#"allowed-licenses = ["MIT", "Apache-2.0"]"#,
+            r#"allowed-licenses = ["MIT", "Apache-2.0", "Zlib"]
+               license-exceptions = ["baz"]"#,
+        ),
+    );
+    p.cargo("check").masquerade_as_nightly_cargo().run();
+}
+
+#[cargo_test]
+fn denied_and_duplicate_packages() {
+    publish("log", "0.3.0", "MIT", &[]);
+    publish("log", "0.4.0", "MIT", &[]);
+    publish("bar", "1.0.0", "MIT", &[("log", "0.3")]);
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                     cargo-features = ["workspace-policy"]
+
+                     [package]
+                     name = "foo"
+                     version = "0.1.0"
+
+                     [dependencies]
+                     bar = "1.0"
+                log = "0.4"
+
+                     [workspace.policy]
+                     deny = [
+                    "openssl",
+                    { name = "bar", version = "<2", reason = "bar 1 is unmaintained, use bar 2" },
+                ]
+                max-duplicate-versions = 1
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("generate-lockfile")
+        .masquerade_as_nightly_cargo()
+        .run();
+    p.cargo("tree")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+[ERROR] 2 violations of the workspace policy in `[CWD]/Cargo.toml`:
+
+  bar v1.0.0 is denied: bar 1 is unmaintained, use bar 2
+    dependency path: foo v0.1.0 ([CWD]) -> bar v1.0.0
+    help: remove the dependencies on `bar`
+
+  `log` is resolved to 2 versions (0.3.0, 0.4.0), \
+more than the 1 allowed by `max-duplicate-versions`
+    dependency path: foo v0.1.0 ([CWD]) -> bar v1.0.0 -> log v0.3.0
+    dependency path: foo v0.1.0 ([CWD]) -> log v0.4.0
+    help: update the dependencies so they agree on a version, \
+or add `log` to `allow-duplicates`
+",
+        )
+        .run();
+
+    p.change_file(
+        "Cargo.toml",
+        &p.read_file("Cargo.toml")
+            .replace("version = \"<2\"", "version = \">=2\"")
+            .replace(
+                "max-duplicate-versions = 1",
+                "max-duplicate-versions = 1\nallow-duplicates = [\"log\"]",
+            ),
+    );
+    p.cargo("tree")
+        .masquerade_as_nightly_cargo()
+        .with_stdout_contains("└── log v0.4.0")
+        .run();
+}
+
+#[cargo_test]
+fn disallowed_sources() {
+    registry::alt_init();
+    Package::new("bar", "1.0.0").alternative(true).publish();
+    let git_project = git::new("baz", |p| {
+        p.file("Cargo.toml", &basic_lib_manifest("baz"))
+            .file("src/lib.rs", "")
+    });
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    cargo-features = ["workspace-policy"]
+
+                    [package]
+                    name = "foo"
+                    version = "0.1.0"
+
+                    [dependencies]
+                    bar = {{ version = "1.0", registry = "alternative" }}
+                    baz = {{ git = "{}" }}
+
+                    [workspace.policy]
+                    allowed-registries = ["crates-io"]
+                    allowed-git = ["https://github.com/rust-lang/"]
+                "#,
+                git_project.url()
+            ),
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+[ERROR] 2 violations of the workspace policy in `[CWD]/Cargo.toml`:
+
+  bar v1.0.0 (registry `alternative`) comes from the registry `alternative`, \
+which is not in `allowed-registries`
+    dependency path: foo v0.1.0 ([CWD]) -> bar v1.0.0 (registry `alternative`)
+    help: depend on it from an allowed registry, or add its registry to `allowed-registries`
+
+  baz v0.5.0 (file://[..]/baz#[..]) comes from the git repository `file://[..]/baz`, \
+which is not in `allowed-git`
+    dependency path: foo v0.1.0 ([CWD]) -> baz v0.5.0 (file://[..]/baz#[..])
+    help: depend on it from an allowed source, or add its repository to `allowed-git`
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn allowed_git_matches_whole_path_segments() {
+    let bar = git::new("bar-fork", |p| {
+        p.file("Cargo.toml", &basic_lib_manifest("bar"))
+            .file("src/lib.rs", "")
+    });
+    // `.../bar` doesn't allow the sibling repository `.../bar-fork`.
+    let prefix = bar.url().as_str().trim_end_matches("-fork").to_string();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    cargo-features = ["workspace-policy"]
+
+                    [package]
+                    name = "foo"
+                    version = "0.1.0"
+
+                    [dependencies]
+                    bar = {{ git = "{}" }}
+
+                    [workspace.policy]
+                    allowed-git = ["{}"]
+                "#,
+                bar.url(),
+                prefix
+            ),
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "  bar v0.5.0 (file://[..]/bar-fork#[..]) comes from the git repository \
+`file://[..]/bar-fork`, which is not in `allowed-git`",
+        )
+        .run();
+
+    p.change_file(
+        "Cargo.toml",
+        &p.read_file("Cargo.toml")
+            .replace(&format!(r#"["{}"]"#, prefix), r#"["file:///"]"#),
+    );
+    p.cargo("check").masquerade_as_nightly_cargo().run();
+}
+
+#[cargo_test]
+fn allowed_sources_enforced_by_resolver() {
+    registry::alt_init();
+    Package::new("qux", "1.0.0").alternative(true).publish();
+    let baz = git::new("baz", |p| {
+        p.file("Cargo.toml", &basic_lib_manifest("baz"))
+            .file("src/lib.rs", "")
+    });
+    let bar = git::new("bar", |p| {
+        p.file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    [package]
+                    name = "bar"
+                    version = "0.5.0"
+
+                    [dependencies]
+                    baz = {{ git = "{}" }}
+                "#,
+                baz.url()
+            ),
+        )
+        .file("src/lib.rs", "")
+    });
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    cargo-features = ["workspace-policy"]
+
+                    [package]
+                    name = "foo"
+                    version = "0.1.0"
+
+                    [dependencies]
+                    bar = {{ git = "{0}" }}
+
+                    [workspace.policy]
+                    allowed-sources = ["crates-io", "{0}"]
+                "#,
+                bar.url()
+            ),
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[UPDATING] git repository `file://[..]/bar`
+[ERROR] dependency `baz` comes from the git repository `file://[..]/baz`, \
+which is not one of the `allowed-sources` of the workspace policy
+required by package `bar v0.5.0 (file://[..]/bar#[..])`
+    ... which satisfies git dependency `bar` of package `foo v0.1.0 ([CWD])`
+",
+        )
+        .run();
+
+    p.change_file(
+        "Cargo.toml",
+        &p.read_file("Cargo.toml").replace(
+            r#"allowed-sources = ["crates-io", "#,
+            &format!(r#"allowed-sources = ["crates-io", "{}", "#, baz.url()),
+        ),
+    );
+    p.cargo("check").masquerade_as_nightly_cargo().run();
+
+    p.change_file(
+        "Cargo.toml",
+        &p.read_file("Cargo.toml").replace(
+            "[dependencies]",
+            "[dependencies]\nqux = { version = \"1.0\", registry = \"alternative\" }",
+        ),
+    );
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] dependency `qux` comes from the registry `alternative`, \
+which is not one of the `allowed-sources` of the workspace policy
+required by package `foo v0.1.0 ([CWD])`
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn allowed_sources_match_whole_path_segments() {
+    let bar = git::new("bar-fork", |p| {
+        p.file("Cargo.toml", &basic_lib_manifest("bar"))
+            .file("src/lib.rs", "")
+    });
+    // `.../bar` doesn't allow the sibling repository `.../bar-fork`.
+    let prefix = bar.url().as_str().trim_end_matches("-fork").to_string();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    cargo-features = ["workspace-policy"]
+
+                    [package]
+                    name = "foo"
+                    version = "0.1.0"
+
+                    [dependencies]
+                    bar = {{ git = "{}" }}
+
+                    [workspace.policy]
+                    allowed-sources = ["{}"]
+                "#,
+                bar.url(),
+                prefix
+            ),
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] dependency `bar` comes from the git repository `file://[..]/bar-fork`, \
+which is not one of the `allowed-sources` of the workspace policy
+required by package `foo v0.1.0 ([CWD])`
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn build_scripts() {
+    Package::new("sys", "1.0.0")
+        .file("build.rs", "fn main() {}")
+        .file("src/lib.rs", "")
+        .publish();
+    Package::new("sketchy", "0.1.0")
+        .file("build.rs", "fn main() {}")
+        .file("src/lib.rs", "")
+        .publish();
+    Package::new("plain", "1.0.0").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                     cargo-features = ["workspace-policy"]
+
+                     [package]
+                     name = "foo"
+                     version = "0.1.0"
+
+                     [dependencies]
+                     sys = "1.0"
+                sketchy = "0.1"
+                plain = "1.0"
+
+                     [workspace.policy]
+                     build-scripts = "deny"
+                deny-build-scripts = [{ name = "sketchy", reason = "it downloads binaries" }]
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+[ERROR] 2 violations of the workspace policy in `[CWD]/Cargo.toml`:
+
+  sketchy v0.1.0 has a build script, which is denied by `deny-build-scripts`: \
+it downloads binaries
+    dependency path: foo v0.1.0 ([CWD]) -> sketchy v0.1.0
+    help: remove the dependencies on `sketchy`
+
+  sys v1.0.0 has a build script, which is not in `allow-build-scripts`
+    dependency path: foo v0.1.0 ([CWD]) -> sys v1.0.0
+    help: review its build script at `[..]/sys-1.0.0/build.rs`, \
+then add `{ name = \"sys\", version = \"=1.0.0\" }` to `allow-build-scripts`
+",
+        )
+        .with_stderr_does_not_contain("[COMPILING] [..]")
+        .run();
+
+    // A newer version needs to be reviewed again.
+    p.change_file(
+        "Cargo.toml",
+        &p.read_file("Cargo.toml")
+            .replace("sketchy = \"0.1\"", "")
+            .replace(
+                "build-scripts = \"deny\"",
+                "build-scripts = \"deny\"\nallow-build-scripts = [{ name = \"sys\", version = \"=1.0.0\" }]",
+            ),
+    );
+    p.cargo("check").masquerade_as_nightly_cargo().run();
+
+    Package::new("sys", "1.0.1")
+        .file("build.rs", "fn main() {}")
+        .file("src/lib.rs", "")
+        .publish();
+    p.cargo("update").masquerade_as_nightly_cargo().run();
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "  sys v1.0.1 has a build script, which is not in `allow-build-scripts`",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn invalid_build_scripts_rule() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["workspace-policy"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+
+
+                [workspace.policy]
+                build-scripts = "review"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "  `workspace.policy.build-scripts` must be `allow` or `deny`, found `review`",
+        )
+        .run();
+}